@@ -31,43 +31,35 @@ use tracing::info;
 async fn main() -> anyhow::Result<()> {
     // init logger
     rocketmq_common::log::init_logger();
-    let (broker_config, message_store_config) = parse_config_file();
+    let (broker_config, message_store_config, config_file) = parse_config_file();
     // boot strap broker
     Builder::new()
         .set_broker_config(broker_config)
         .set_message_store_config(message_store_config)
+        .set_config_file(config_file)
         .build()
         .boot()
         .await;
     Ok(())
 }
 
-fn parse_config_file() -> (BrokerConfig, MessageStoreConfig) {
+fn parse_config_file() -> (BrokerConfig, MessageStoreConfig, PathBuf) {
     let args = Args::parse();
     let home = EnvUtils::get_rocketmq_home();
-    let config = if let Some(ref config_file) = args.config_file {
-        let config_file = PathBuf::from(config_file);
-        (
-            ParseConfigFile::parse_config_file::<BrokerConfig>(config_file.clone())
-                .ok()
-                .unwrap(),
-            ParseConfigFile::parse_config_file::<MessageStoreConfig>(config_file)
-                .ok()
-                .unwrap(),
-        )
+    let config_file = if let Some(ref config_file) = args.config_file {
+        PathBuf::from(config_file)
     } else {
-        let path_buf = PathBuf::from(home.as_str())
+        PathBuf::from(home.as_str())
             .join("conf")
-            .join("broker.toml");
-        (
-            ParseConfigFile::parse_config_file::<BrokerConfig>(path_buf.clone())
-                .ok()
-                .unwrap(),
-            ParseConfigFile::parse_config_file::<MessageStoreConfig>(path_buf)
-                .ok()
-                .unwrap(),
-        )
+            .join("broker.toml")
     };
+    let broker_config = ParseConfigFile::parse_config_file::<BrokerConfig>(config_file.clone())
+        .ok()
+        .unwrap();
+    let message_store_config =
+        ParseConfigFile::parse_config_file::<MessageStoreConfig>(config_file.clone())
+            .ok()
+            .unwrap();
     info!("Rocketmq(Rust) home: {}", home);
-    config
+    (broker_config, message_store_config, config_file)
 }
@@ -0,0 +1,54 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use clap::Parser;
+use rocketmq_broker::command::Args;
+use rocketmq_broker::BrokerContainer;
+use rocketmq_broker::BrokerContainerConfig;
+use rocketmq_common::EnvUtils::EnvUtils;
+use rocketmq_common::ParseConfigFile;
+use rocketmq_rust::rocketmq;
+use rocketmq_rust::wait_for_signal;
+use tracing::info;
+
+#[rocketmq::main]
+async fn main() -> anyhow::Result<()> {
+    // init logger
+    rocketmq_common::log::init_logger();
+    let broker_container = Arc::new(BrokerContainer::new(parse_config_file()));
+    broker_container.start().await;
+    wait_for_signal().await;
+    info!("BrokerContainer Received signal, initiating shutdown...");
+    broker_container.shutdown().await;
+    Ok(())
+}
+
+fn parse_config_file() -> BrokerContainerConfig {
+    let args = Args::parse();
+    let home = EnvUtils::get_rocketmq_home();
+    let config_file = args.config_file.unwrap_or_else(|| {
+        PathBuf::from(home.as_str())
+            .join("conf")
+            .join("container.toml")
+    });
+    info!("Rocketmq(Rust) home: {}", home);
+    ParseConfigFile::parse_config_file::<BrokerContainerConfig>(config_file)
+        .ok()
+        .unwrap()
+}
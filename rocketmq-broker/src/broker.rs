@@ -15,5 +15,9 @@
  * limitations under the License.
  */
 
+pub(crate) mod broker_config_watcher;
 pub mod broker_hook;
+pub(crate) mod broker_persist_scheduler;
 pub mod broker_pre_online_service;
+pub(crate) mod broker_preflight;
+pub(crate) mod dledger_role_change_handler;
@@ -0,0 +1,250 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use cheetah_string::CheetahString;
+use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::ParseConfigFile;
+use tracing::warn;
+
+/// Polls the toml config file the broker was started from and reports the properties an edit
+/// of it changed.
+///
+/// The content found when the watcher is created is the baseline, only later edits are
+/// reported. A file that can not be read or parsed is skipped until it is fixed.
+pub(crate) struct BrokerConfigWatcher {
+    path: PathBuf,
+    content: String,
+    properties: HashMap<CheetahString, CheetahString>,
+}
+
+impl BrokerConfigWatcher {
+    pub(crate) fn new(path: PathBuf) -> Self {
+        let content = fs::read_to_string(&path).unwrap_or_default();
+        let properties = ParseConfigFile::parse_config_properties(&path).unwrap_or_default();
+        Self {
+            path,
+            content,
+            properties,
+        }
+    }
+
+    pub(crate) fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// Properties added or changed since the last poll, `None` when the file did not change.
+    pub(crate) fn poll(&mut self) -> Option<HashMap<CheetahString, CheetahString>> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) => {
+                warn!("read broker config file {:?} failed: {}", self.path, e);
+                return None;
+            }
+        };
+        if content == self.content {
+            return None;
+        }
+        self.content = content;
+        let properties = match ParseConfigFile::parse_config_properties(&self.path) {
+            Ok(properties) => properties,
+            Err(e) => {
+                warn!(
+                    "broker config file {:?} can not be parsed: {}",
+                    self.path, e
+                );
+                return None;
+            }
+        };
+        let changed = properties
+            .iter()
+            .filter(|(key, value)| self.properties.get(*key) != Some(*value))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        self.properties = properties;
+        Some(changed)
+    }
+}
+
+/// How the properties changed in the config file were taken up.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct ConfigReload {
+    /// Applied to the running broker config.
+    pub(crate) applied: Vec<CheetahString>,
+    /// Only take effect after a restart, the running value is kept.
+    pub(crate) restart_required: Vec<CheetahString>,
+    /// Unknown, blacklisted or invalid.
+    pub(crate) ignored: Vec<CheetahString>,
+}
+
+impl ConfigReload {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.applied.is_empty() && self.restart_required.is_empty() && self.ignored.is_empty()
+    }
+}
+
+/// Applies the `changed` properties that can change at runtime to `broker_config`, and the
+/// message store ones to the running store through `update_store`.
+///
+/// Broker properties already holding the running value are skipped, so an edit written by the
+/// update config admin request is a no-op here. `update_store` returns `false` for the store
+/// properties that are only read when the store is created.
+pub(crate) fn reload_broker_config(
+    broker_config: &mut BrokerConfig,
+    store_properties: &HashMap<CheetahString, CheetahString>,
+    update_store: impl Fn(&str, &str) -> Result<bool, String>,
+    changed: &HashMap<CheetahString, CheetahString>,
+) -> ConfigReload {
+    let running = broker_config.get_properties();
+    let black_list = broker_config.get_config_blacklist();
+    let mut keys = changed.keys().collect::<Vec<_>>();
+    keys.sort();
+    let mut reload = ConfigReload::default();
+    for key in keys {
+        let value = &changed[key];
+        if black_list.contains(key) {
+            reload.ignored.push(key.clone());
+        } else if broker_config.contains_key(key) {
+            if running.get(key) == Some(value) {
+                continue;
+            }
+            if BrokerConfig::requires_restart(key) {
+                reload.restart_required.push(key.clone());
+                continue;
+            }
+            // one at a time, so that an invalid value does not hold back the others
+            let property = HashMap::from([(key.clone(), value.clone())]);
+            match broker_config.update(&property) {
+                Ok(()) => reload.applied.push(key.clone()),
+                Err(e) => {
+                    warn!("ignore broker config {}={}: {}", key, value, e);
+                    reload.ignored.push(key.clone());
+                }
+            }
+        } else if let Some(configured) = store_properties.get(key) {
+            match update_store(key, value) {
+                Ok(true) => reload.applied.push(key.clone()),
+                Ok(false) if configured != value => reload.restart_required.push(key.clone()),
+                Ok(false) => {}
+                Err(e) => {
+                    warn!("ignore message store config {}={}: {}", key, value, e);
+                    reload.ignored.push(key.clone());
+                }
+            }
+        } else {
+            reload.ignored.push(key.clone());
+        }
+    }
+    reload
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn properties(pairs: &[(&str, &str)]) -> HashMap<CheetahString, CheetahString> {
+        pairs
+            .iter()
+            .map(|(key, value)| (CheetahString::from(*key), CheetahString::from(*value)))
+            .collect()
+    }
+
+    fn keys(keys: &[CheetahString]) -> Vec<&str> {
+        keys.iter().map(CheetahString::as_str).collect()
+    }
+
+    #[test]
+    fn reports_only_edited_properties() {
+        let path = std::env::temp_dir().join(format!("config-watch-{}.toml", std::process::id()));
+        fs::write(&path, "a = 1\nb = \"2\"\n").unwrap();
+        let mut watcher = BrokerConfigWatcher::new(path.clone());
+        assert_eq!(watcher.poll(), None);
+
+        fs::write(&path, "# edited\na = 1\nb = \"3\"\nc = true\n").unwrap();
+        assert_eq!(
+            watcher.poll(),
+            Some(properties(&[("b", "3"), ("c", "true")]))
+        );
+        assert_eq!(watcher.poll(), None);
+
+        fs::write(&path, "a = \n").unwrap();
+        assert_eq!(watcher.poll(), None);
+        fs::write(&path, "a = 2\nb = \"3\"\nc = true\n").unwrap();
+        assert_eq!(watcher.poll(), Some(properties(&[("a", "2")])));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn applies_reload_safe_fields_only() {
+        let mut broker_config = BrokerConfig::default();
+        let store_properties = properties(&[
+            ("flushIntervalCommitLog", "500"),
+            ("mappedFileSizeCommitLog", "1073741824"),
+        ]);
+        let store_updates = std::cell::RefCell::new(Vec::new());
+        let update_store = |key: &str, value: &str| {
+            if key != "flushIntervalCommitLog" {
+                return Ok(false);
+            }
+            let millis = value.parse::<u64>().map_err(|e| e.to_string())?;
+            store_updates.borrow_mut().push(millis);
+            Ok(true)
+        };
+        let changed = properties(&[
+            ("flushConsumerOffsetInterval", "1000"),
+            ("listenPort", "20911"),
+            ("flushIntervalCommitLog", "200"),
+            ("mappedFileSizeCommitLog", "1024"),
+            ("commercialBaseCount", "not a number"),
+            ("noSuchKey", "1"),
+        ]);
+        let reload = reload_broker_config(
+            &mut broker_config,
+            &store_properties,
+            update_store,
+            &changed,
+        );
+        assert_eq!(
+            keys(&reload.applied),
+            ["flushConsumerOffsetInterval", "flushIntervalCommitLog"]
+        );
+        assert_eq!(
+            keys(&reload.restart_required),
+            ["listenPort", "mappedFileSizeCommitLog"]
+        );
+        assert_eq!(keys(&reload.ignored), ["commercialBaseCount", "noSuchKey"]);
+        assert_eq!(broker_config.flush_consumer_offset_interval, 1000);
+        assert_ne!(broker_config.listen_port, 20911);
+        assert_eq!(*store_updates.borrow(), [200]);
+
+        let invalid = properties(&[("flushIntervalCommitLog", "soon")]);
+        let reload = reload_broker_config(
+            &mut broker_config,
+            &store_properties,
+            update_store,
+            &invalid,
+        );
+        assert_eq!(keys(&reload.ignored), ["flushIntervalCommitLog"]);
+
+        let again = properties(&[("flushConsumerOffsetInterval", "1000")]);
+        let reload =
+            reload_broker_config(&mut broker_config, &store_properties, update_store, &again);
+        assert!(reload.is_empty());
+    }
+}
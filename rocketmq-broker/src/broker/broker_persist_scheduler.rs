@@ -0,0 +1,127 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::time::Duration;
+use std::time::Instant;
+
+use rocketmq_common::common::broker::broker_config::BrokerConfig;
+
+/// Broker managers whose state is persisted by the broker periodically and on shutdown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PersistTarget {
+    ConsumerOffset,
+    TopicConfig,
+    SubscriptionGroup,
+    ConsumerFilter,
+    ConsumerOrderInfo,
+    MessageRequestMode,
+}
+
+impl PersistTarget {
+    pub(crate) const ALL: [PersistTarget; 6] = [
+        PersistTarget::ConsumerOffset,
+        PersistTarget::TopicConfig,
+        PersistTarget::SubscriptionGroup,
+        PersistTarget::ConsumerFilter,
+        PersistTarget::ConsumerOrderInfo,
+        PersistTarget::MessageRequestMode,
+    ];
+
+    /// Persist interval in ms configured for the target, 0 when it is only persisted on change
+    /// and on shutdown.
+    pub(crate) fn interval_millis(self, broker_config: &BrokerConfig) -> u64 {
+        match self {
+            PersistTarget::ConsumerOffset => broker_config.flush_consumer_offset_interval,
+            PersistTarget::TopicConfig => broker_config.flush_topic_config_interval,
+            PersistTarget::SubscriptionGroup => broker_config.flush_subscription_group_interval,
+            PersistTarget::ConsumerFilter => broker_config.flush_consumer_filter_interval,
+            PersistTarget::ConsumerOrderInfo => broker_config.flush_consumer_order_info_interval,
+            PersistTarget::MessageRequestMode => broker_config.flush_message_request_mode_interval,
+        }
+    }
+}
+
+/// Tracks when every [`PersistTarget`] was persisted last.
+///
+/// The intervals are passed on every check rather than kept, so that an interval updated at
+/// runtime takes effect with the next check.
+pub(crate) struct PersistSchedule {
+    last_persisted: [Instant; PersistTarget::ALL.len()],
+}
+
+impl PersistSchedule {
+    /// Nothing is due before one interval has passed since `now`.
+    pub(crate) fn new(now: Instant) -> Self {
+        Self {
+            last_persisted: [now; PersistTarget::ALL.len()],
+        }
+    }
+
+    /// Targets whose interval has passed at `now`, they count as persisted at `now`.
+    pub(crate) fn take_due(
+        &mut self,
+        now: Instant,
+        interval_millis: impl Fn(PersistTarget) -> u64,
+    ) -> Vec<PersistTarget> {
+        let mut due = Vec::new();
+        for (target, last_persisted) in PersistTarget::ALL.into_iter().zip(&mut self.last_persisted)
+        {
+            let interval = interval_millis(target);
+            if interval > 0
+                && now.saturating_duration_since(*last_persisted) >= Duration::from_millis(interval)
+            {
+                *last_persisted = now;
+                due.push(target);
+            }
+        }
+        due
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn persists_every_target_at_its_own_interval() {
+        let start = Instant::now();
+        let mut schedule = PersistSchedule::new(start);
+        let intervals = |target| match target {
+            PersistTarget::ConsumerOffset => 5_000,
+            PersistTarget::TopicConfig => 0,
+            _ => 10_000,
+        };
+        let at = |millis| start + Duration::from_millis(millis);
+
+        assert!(schedule.take_due(at(4_999), intervals).is_empty());
+        assert_eq!(
+            schedule.take_due(at(5_000), intervals),
+            [PersistTarget::ConsumerOffset]
+        );
+        assert!(schedule.take_due(at(9_000), intervals).is_empty());
+        assert_eq!(
+            schedule.take_due(at(10_000), intervals),
+            [
+                PersistTarget::ConsumerOffset,
+                PersistTarget::SubscriptionGroup,
+                PersistTarget::ConsumerFilter,
+                PersistTarget::ConsumerOrderInfo,
+                PersistTarget::MessageRequestMode,
+            ]
+        );
+        assert!(schedule.take_due(at(100_000), |_| 0).is_empty());
+    }
+}
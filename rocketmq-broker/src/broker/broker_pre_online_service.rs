@@ -14,16 +14,435 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use cheetah_string::CheetahString;
+use rocketmq_common::common::config_manager::ConfigManager;
+use rocketmq_common::common::mix_all::MASTER_ID;
+use rocketmq_remoting::protocol::body::broker_body::broker_member_group::BrokerMemberGroup;
+use rocketmq_remoting::protocol::header::exchange_ha_info_response_header::ExchangeHAInfoResponseHeader;
+use rocketmq_rust::ArcMut;
+use rocketmq_store::log_file::MessageStore;
+use tokio::sync::Notify;
+use tracing::error;
+use tracing::info;
 use tracing::warn;
 
-pub struct BrokerPreOnlineService;
+use crate::broker_runtime::BrokerRuntimeInner;
+
+/// Keeps a recovering broker away from the name server until it has caught up with the broker
+/// that served its role while it was down.
+///
+/// A master taking over again first pulls the consumer, delay, timer and transaction check
+/// progress of the acting master and waits until its commit log has replicated everything the
+/// acting master stored. Registering earlier would let clients read stale offsets and miss
+/// messages written during the failover.
+pub struct BrokerPreOnlineService {
+    shutdown: Arc<Notify>,
+}
+
+impl Default for BrokerPreOnlineService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl BrokerPreOnlineService {
-    pub fn start(&mut self) {
-        warn!("BrokerPreOnlineService started not implemented");
+    pub fn new() -> Self {
+        Self {
+            shutdown: Arc::new(Notify::new()),
+        }
     }
 
-    pub fn shutdown(&mut self) {
-        warn!("BrokerPreOnlineService shutdown not implemented");
+    pub fn start<MS: MessageStore>(&self, broker_runtime_inner: ArcMut<BrokerRuntimeInner<MS>>) {
+        let shutdown = Arc::clone(&self.shutdown);
+        tokio::spawn(async move {
+            info!("BrokerPreOnlineService service started");
+            loop {
+                if !broker_runtime_inner.is_isolated().load(Ordering::Acquire) {
+                    info!(
+                        "broker {} is online",
+                        broker_runtime_inner.get_broker_addr()
+                    );
+                    break;
+                }
+                if prepare_for_broker_online(&broker_runtime_inner).await {
+                    break;
+                }
+                tokio::select! {
+                    _ = tokio::time::sleep(Duration::from_secs(1)) => {}
+                    _ = shutdown.notified() => break,
+                }
+            }
+            info!("BrokerPreOnlineService service end");
+        });
+    }
+
+    pub fn shutdown(&self) {
+        self.shutdown.notify_waiters();
+    }
+}
+
+/// Returns `true` once the broker has been brought online.
+async fn prepare_for_broker_online<MS: MessageStore>(
+    broker_runtime_inner: &ArcMut<BrokerRuntimeInner<MS>>,
+) -> bool {
+    let broker_identity = &broker_runtime_inner.broker_config().broker_identity;
+    let broker_member_group = match broker_runtime_inner
+        .broker_outer_api()
+        .sync_broker_member_group(
+            &broker_identity.broker_cluster_name,
+            &broker_identity.broker_name,
+        )
+        .await
+    {
+        Ok(broker_member_group) => broker_member_group,
+        Err(e) => {
+            error!(
+                "syncBrokerMemberGroup from namesrv error, start service failed, will try later, \
+                 {}",
+                e
+            );
+            return false;
+        }
+    };
+
+    let broker_id = broker_identity.broker_id;
+    let Some(acting_master_addr) = broker_member_group
+        .as_ref()
+        .and_then(|group| acting_master_addr(group, broker_id))
+    else {
+        info!("no other broker online, will start service directly");
+        start_service(broker_runtime_inner).await;
+        return true;
+    };
+
+    if broker_id == MASTER_ID {
+        if !prepare_master_online(broker_runtime_inner, &acting_master_addr).await {
+            return false;
+        }
+    } else {
+        let slave_synchronize = broker_runtime_inner.slave_synchronize();
+        slave_synchronize.set_master_addr(Some(acting_master_addr));
+        slave_synchronize.sync_all(broker_runtime_inner).await;
+    }
+    start_service(broker_runtime_inner).await;
+    true
+}
+
+/// Address of the broker with the smallest id other than `broker_id`, which is the master while
+/// the real master is away.
+fn acting_master_addr(
+    broker_member_group: &BrokerMemberGroup,
+    broker_id: u64,
+) -> Option<CheetahString> {
+    broker_member_group
+        .broker_addrs
+        .iter()
+        .filter(|(id, _)| **id != broker_id)
+        .min_by_key(|(id, _)| **id)
+        .map(|(_, addr)| addr.clone())
+}
+
+async fn prepare_master_online<MS: MessageStore>(
+    broker_runtime_inner: &ArcMut<BrokerRuntimeInner<MS>>,
+    acting_master_addr: &CheetahString,
+) -> bool {
+    let broker_outer_api = broker_runtime_inner.broker_outer_api();
+
+    let ha_runtime_info = match broker_outer_api
+        .get_broker_ha_status(acting_master_addr)
+        .await
+    {
+        Ok(ha_runtime_info) => ha_runtime_info,
+        Err(e) => {
+            error!(
+                "get broker ha status from {} failed, {}",
+                acting_master_addr, e
+            );
+            return false;
+        }
+    };
+    let local_max_offset = broker_runtime_inner
+        .message_store()
+        .as_ref()
+        .map_or(0, |message_store| message_store.get_max_phy_offset());
+    if ha_runtime_info.master_commit_log_max_offset as i64 > local_max_offset {
+        info!(
+            "wait for commit log to catch up with {}, master max offset {}, local max offset {}",
+            acting_master_addr, ha_runtime_info.master_commit_log_max_offset, local_max_offset
+        );
+        replicate_from_acting_master(broker_runtime_inner, acting_master_addr).await;
+        return false;
+    }
+    // Caught up, stop replicating before taking over the writes.
+    if let Some(message_store) = broker_runtime_inner.message_store() {
+        message_store.update_ha_master_address(&CheetahString::empty());
+    }
+
+    // The consumer offsets also carry the check progress of the transaction half and op topics.
+    match broker_outer_api
+        .get_all_consumer_offset(acting_master_addr)
+        .await
+    {
+        Ok(content) => {
+            let consumer_offset_manager = broker_runtime_inner.consumer_offset_manager();
+            consumer_offset_manager.decode(content.as_str());
+            consumer_offset_manager.persist();
+        }
+        Err(e) => {
+            error!(
+                "sync consumer offset from {} failed, {}",
+                acting_master_addr, e
+            );
+            return false;
+        }
+    }
+
+    match broker_outer_api
+        .get_all_delay_offset(acting_master_addr)
+        .await
+    {
+        Ok(content) => {
+            let schedule_message_service = broker_runtime_inner.schedule_message_service();
+            schedule_message_service.decode(content.as_str());
+            schedule_message_service.persist();
+        }
+        Err(e) => {
+            error!(
+                "sync delay offset from {} failed, {}",
+                acting_master_addr, e
+            );
+            return false;
+        }
+    }
+
+    if let Some(timer_checkpoint) = broker_runtime_inner
+        .timer_message_store()
+        .as_ref()
+        .and_then(|timer_message_store| timer_message_store.timer_checkpoint())
+    {
+        match broker_outer_api
+            .get_timer_check_point(acting_master_addr)
+            .await
+        {
+            Ok(encoded) => {
+                if !timer_checkpoint.apply_master_progress(&encoded) {
+                    warn!(
+                        "ignore malformed timer checkpoint of {}, {} bytes",
+                        acting_master_addr,
+                        encoded.len()
+                    );
+                }
+            }
+            Err(e) => {
+                error!(
+                    "sync timer checkpoint from {} failed, {}",
+                    acting_master_addr, e
+                );
+                return false;
+            }
+        }
+    }
+    info!(
+        "master synced metadata from acting master {}",
+        acting_master_addr
+    );
+    true
+}
+
+/// Points the replication of the commit log at the HA service of the acting master.
+async fn replicate_from_acting_master<MS: MessageStore>(
+    broker_runtime_inner: &ArcMut<BrokerRuntimeInner<MS>>,
+    acting_master_addr: &CheetahString,
+) {
+    let Some(message_store) = broker_runtime_inner.message_store() else {
+        return;
+    };
+    match broker_runtime_inner
+        .broker_outer_api()
+        .retrieve_broker_ha_info(acting_master_addr)
+        .await
+    {
+        Ok(ExchangeHAInfoResponseHeader {
+            master_ha_address: Some(master_ha_address),
+            ..
+        }) if !master_ha_address.is_empty() => {
+            message_store.update_ha_master_address(&master_ha_address);
+        }
+        Ok(_) => warn!("acting master {} has no HA address", acting_master_addr),
+        Err(e) => error!("retrieve ha info from {} failed, {}", acting_master_addr, e),
+    }
+}
+
+async fn start_service<MS: MessageStore>(broker_runtime_inner: &ArcMut<BrokerRuntimeInner<MS>>) {
+    broker_runtime_inner
+        .start_service_without_condition(broker_runtime_inner.clone())
+        .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use rocketmq_common::common::broker::broker_config::BrokerConfig;
+    use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+    use rocketmq_common::common::message::MessageTrait;
+    use rocketmq_common::common::server::config::ServerConfig;
+    use rocketmq_remoting::remoting_server::server::RocketMQServer;
+    use rocketmq_store::base::message_status_enum::PutMessageStatus;
+    use rocketmq_store::config::message_store_config::MessageStoreConfig;
+    use rocketmq_store::message_store::boxed_message_store::BoxedMessageStore;
+
+    use super::*;
+    use crate::broker_runtime::BrokerRuntime;
+
+    fn free_port() -> u32 {
+        std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port() as u32
+    }
+
+    fn started_broker(
+        runtime: &tokio::runtime::Runtime,
+        store_dir: &tempfile::TempDir,
+        listen_port: u32,
+    ) -> BrokerRuntime {
+        let store_path_root_dir = CheetahString::from(store_dir.path().to_string_lossy().as_ref());
+        let mut broker = BrokerRuntime::new(
+            BrokerConfig {
+                broker_ip1: "127.0.0.1".into(),
+                store_path_root_dir: store_path_root_dir.clone(),
+                ..Default::default()
+            },
+            MessageStoreConfig {
+                store_path_root_dir,
+                mapped_file_size_commit_log: 1024 * 1024,
+                mapped_file_size_consume_queue: 20 * 1024,
+                ha_listen_port: free_port() as usize,
+                ..Default::default()
+            },
+            ServerConfig {
+                listen_port,
+                bind_address: "127.0.0.1".to_string(),
+            },
+        );
+        assert!(runtime.block_on(broker.initialize()));
+        broker
+            .inner()
+            .message_store()
+            .as_ref()
+            .unwrap()
+            .mut_from_ref()
+            .start()
+            .unwrap();
+        broker
+    }
+
+    fn max_phy_offset(broker_runtime_inner: &ArcMut<BrokerRuntimeInner<BoxedMessageStore>>) -> i64 {
+        broker_runtime_inner
+            .message_store()
+            .as_ref()
+            .unwrap()
+            .get_max_phy_offset()
+    }
+
+    #[test]
+    fn returning_master_replicates_the_commit_log_of_the_acting_master() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let _guard = runtime.enter();
+        let acting_dir = tempfile::tempdir().unwrap();
+        let returning_dir = tempfile::tempdir().unwrap();
+        let acting_port = free_port();
+        let mut acting = started_broker(&runtime, &acting_dir, acting_port);
+        let returning = started_broker(&runtime, &returning_dir, free_port());
+        let acting_inner = acting.inner().clone();
+        let returning_inner = returning.inner().clone();
+
+        let request_processor = acting.init_processor();
+        let server = RocketMQServer::new(Arc::new(acting_inner.server_config().clone()));
+        runtime.spawn(async move { server.run(request_processor).await });
+        runtime.block_on(returning_inner.broker_outer_api().start());
+
+        for body in [b"first".as_slice(), b"second".as_slice()] {
+            let mut message = MessageExtBrokerInner::default();
+            message.set_topic("PreOnlineTopic".into());
+            message.set_body(Bytes::copy_from_slice(body));
+            message.message_ext_inner.born_host = acting_inner.store_host();
+            message.message_ext_inner.store_host = acting_inner.store_host();
+            let result = runtime.block_on(
+                acting_inner
+                    .message_store()
+                    .as_ref()
+                    .unwrap()
+                    .mut_from_ref()
+                    .put_message(message),
+            );
+            assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+        }
+        let acting_master_addr = CheetahString::from(format!("127.0.0.1:{}", acting_port));
+        let ha_info = runtime.block_on(async {
+            // the remoting server binds in the background
+            for _ in 0..50 {
+                if let Ok(ha_info) = returning_inner
+                    .broker_outer_api()
+                    .retrieve_broker_ha_info(&acting_master_addr)
+                    .await
+                {
+                    return Some(ha_info);
+                }
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            None
+        });
+        let ha_info = ha_info.expect("the acting master answers with its ha info");
+        assert_eq!(
+            ha_info.master_ha_address,
+            Some(acting_inner.get_ha_server_addr())
+        );
+        assert_eq!(
+            ha_info.master_flush_offset,
+            Some(max_phy_offset(&acting_inner))
+        );
+
+        runtime.block_on(replicate_from_acting_master(
+            &returning_inner,
+            &acting_master_addr,
+        ));
+        let caught_up = runtime.block_on(async {
+            for _ in 0..100 {
+                if max_phy_offset(&returning_inner) == max_phy_offset(&acting_inner) {
+                    return true;
+                }
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+            false
+        });
+        assert!(caught_up, "the returning master replicated the commit log");
+    }
+
+    #[test]
+    fn picks_the_smallest_other_broker_as_acting_master() {
+        let mut group = BrokerMemberGroup::new("cluster".into(), "broker-a".into());
+        assert_eq!(acting_master_addr(&group, MASTER_ID), None);
+
+        group
+            .broker_addrs
+            .insert(MASTER_ID, "127.0.0.1:10911".into());
+        assert_eq!(acting_master_addr(&group, MASTER_ID), None);
+
+        group.broker_addrs.insert(2, "127.0.0.1:10931".into());
+        group.broker_addrs.insert(1, "127.0.0.1:10921".into());
+        assert_eq!(
+            acting_master_addr(&group, MASTER_ID),
+            Some("127.0.0.1:10921".into())
+        );
+        assert_eq!(
+            acting_master_addr(&group, 2),
+            Some("127.0.0.1:10911".into())
+        );
     }
 }
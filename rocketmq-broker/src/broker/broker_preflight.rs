@@ -0,0 +1,303 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::fs;
+use std::net::TcpListener;
+use std::path::Path;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use rocketmq_common::common::mix_all::MULTI_PATH_SPLITTER;
+use rocketmq_common::common::server::config::ServerConfig;
+use rocketmq_store::config::message_store_config::MessageStoreConfig;
+use rocketmq_store::dledger::dledger_server::get_store_path_dledger_data;
+use rocketmq_store::dledger::member_state::DLedgerPeers;
+use rocketmq_store::store_path_config_helper::get_store_checkpoint;
+use sysinfo::Disks;
+
+const MB: u64 = 1024 * 1024;
+/// 2020-01-01T00:00:00Z, any clock reading before it is certainly wrong.
+const MIN_SANE_TIME_MILLIS: u64 = 1_577_836_800_000;
+/// How far the clock may lag behind the last store write before it is worth a warning.
+const MAX_CLOCK_BEHIND_MILLIS: u64 = 60_000;
+/// Open files the broker wants at least, one queue alone holds a few mapped files open.
+const MIN_OPEN_FILES: u64 = 65_535;
+
+/// Problems found before the broker starts. Failures stop the start, warnings are only logged.
+#[derive(Debug, Default)]
+pub(crate) struct PreflightReport {
+    pub(crate) failures: Vec<String>,
+    pub(crate) warnings: Vec<String>,
+}
+
+/// Checks the environment the broker is about to start in, so that a broken store directory, a
+/// full disk or a taken port is reported up front instead of failing somewhere mid-start.
+pub(crate) fn run_preflight_checks(
+    message_store_config: &MessageStoreConfig,
+    server_config: &ServerConfig,
+) -> PreflightReport {
+    let mut report = PreflightReport::default();
+    let store_root = message_store_config.store_path_root_dir.as_str();
+    let store_commit_log = if message_store_config.enable_dledger_commit_log {
+        get_store_path_dledger_data(message_store_config)
+    } else {
+        message_store_config.get_store_path_commit_log()
+    };
+    let commit_log_dirs: Vec<&str> = store_commit_log
+        .split(MULTI_PATH_SPLITTER.as_str())
+        .map(str::trim)
+        .filter(|dir| !dir.is_empty())
+        .collect();
+    for dir in std::iter::once(store_root).chain(commit_log_dirs.iter().copied()) {
+        if let Err(failure) = check_writable_dir(dir) {
+            report.failures.push(failure);
+        }
+    }
+    let mut disk_report = PreflightReport::default();
+    let mut full_dirs = 0;
+    for dir in commit_log_dirs.iter() {
+        let failures = disk_report.failures.len();
+        check_disk_space(
+            dir,
+            message_store_config.mapped_file_size_commit_log as u64,
+            message_store_config.disk_max_used_space_ratio as f64 / 100.0,
+            &mut disk_report,
+        );
+        if disk_report.failures.len() > failures {
+            full_dirs += 1;
+        }
+    }
+    // new files go to the other directories as long as one of them has space left
+    if full_dirs < commit_log_dirs.len() {
+        disk_report.warnings.append(&mut disk_report.failures);
+    }
+    report.failures.append(&mut disk_report.failures);
+    report.warnings.append(&mut disk_report.warnings);
+
+    let listen_port = server_config.listen_port;
+    let mut ports = vec![listen_port, listen_port.saturating_sub(2)];
+    if message_store_config.enable_dledger_commit_log {
+        // the HA service is replaced by the DLedger member, which listens on its peer port
+        match DLedgerPeers::parse(
+            message_store_config
+                .dledger_group
+                .as_deref()
+                .unwrap_or_default(),
+            message_store_config
+                .dledger_peers
+                .as_deref()
+                .unwrap_or_default(),
+            message_store_config
+                .dledger_self_id
+                .as_deref()
+                .unwrap_or_default(),
+        ) {
+            Ok(peers) => ports.push(peers.self_port() as u32),
+            Err(e) => report
+                .failures
+                .push(format!("illegal dledger config: {}", e)),
+        }
+    } else if message_store_config.ha_listen_port > 0 {
+        ports.push(message_store_config.ha_listen_port as u32);
+    }
+    for port in ports {
+        if let Err(failure) = check_port(&server_config.bind_address, port) {
+            report.failures.push(failure);
+        }
+    }
+
+    check_limits(message_store_config, &mut report);
+    check_clock(store_root, &mut report);
+    report
+}
+
+fn check_writable_dir(dir: &str) -> Result<(), String> {
+    let probe = Path::new(dir).join(".preflight");
+    fs::create_dir_all(dir)
+        .and_then(|_| fs::write(&probe, b""))
+        .and_then(|_| fs::remove_file(&probe))
+        .map_err(|e| {
+            format!(
+                "store directory {} is not writable: {}, check that it exists and belongs to the \
+                 user running the broker",
+                dir, e
+            )
+        })
+}
+
+fn check_disk_space(dir: &str, required: u64, max_used_ratio: f64, report: &mut PreflightReport) {
+    let Ok(dir) = fs::canonicalize(dir) else {
+        return;
+    };
+    let disks = Disks::new_with_refreshed_list();
+    let Some(disk) = disks
+        .iter()
+        .filter(|disk| dir.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().as_os_str().len())
+    else {
+        return;
+    };
+    let (available, total) = (disk.available_space(), disk.total_space());
+    if available < required {
+        report.failures.push(format!(
+            "only {} MB are free on {}, one commit log file needs {} MB, free up space or lower \
+             mappedFileSizeCommitLog",
+            available / MB,
+            disk.mount_point().display(),
+            required / MB
+        ));
+    } else if total > 0 && 1.0 - available as f64 / total as f64 > max_used_ratio {
+        report.warnings.push(format!(
+            "{} is more than {:.0}% used, expired commit log files will be deleted right away",
+            disk.mount_point().display(),
+            max_used_ratio * 100.0
+        ));
+    }
+}
+
+fn check_port(bind_address: &str, port: u32) -> Result<(), String> {
+    let Ok(port) = u16::try_from(port) else {
+        return Err(format!("port {} is out of range", port));
+    };
+    TcpListener::bind((bind_address, port))
+        .map(drop)
+        .map_err(|e| {
+            format!(
+                "port {} on {} is not available: {}, stop the process holding it or change the \
+                 listen port",
+                port, bind_address, e
+            )
+        })
+}
+
+fn check_limits(message_store_config: &MessageStoreConfig, report: &mut PreflightReport) {
+    let Some(limits) = read_limits() else {
+        return;
+    };
+    if let Some(open_files) = parse_soft_limit(&limits, "Max open files") {
+        if open_files < MIN_OPEN_FILES {
+            report.warnings.push(format!(
+                "open files are limited to {}, raise `ulimit -n` to at least {}",
+                open_files, MIN_OPEN_FILES
+            ));
+        }
+    }
+    let file_size = message_store_config.mapped_file_size_commit_log as u64;
+    let locked = if message_store_config.is_transient_store_pool_enable() {
+        message_store_config.transient_store_pool_size as u64 * file_size
+    } else if message_store_config.warm_mapped_file_enable {
+        file_size
+    } else {
+        0
+    };
+    if locked == 0 {
+        return;
+    }
+    if let Some(memlock) = parse_soft_limit(&limits, "Max locked memory") {
+        if memlock < locked {
+            report.warnings.push(format!(
+                "locked memory is limited to {} MB but {} MB of mapped files are locked, raise \
+                 `ulimit -l`",
+                memlock / MB,
+                locked / MB
+            ));
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn read_limits() -> Option<String> {
+    fs::read_to_string("/proc/self/limits").ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_limits() -> Option<String> {
+    None
+}
+
+/// Soft limit of `name` in the `/proc/self/limits` layout, [`u64::MAX`] when unlimited.
+fn parse_soft_limit(limits: &str, name: &str) -> Option<u64> {
+    let value = limits
+        .lines()
+        .find_map(|line| line.strip_prefix(name))?
+        .split_whitespace()
+        .next()?;
+    if value == "unlimited" {
+        return Some(u64::MAX);
+    }
+    value.parse().ok()
+}
+
+fn check_clock(store_root: &str, report: &mut PreflightReport) {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_millis() as u64)
+        .unwrap_or_default();
+    if now < MIN_SANE_TIME_MILLIS {
+        report.failures.push(format!(
+            "system clock reads {} ms since the epoch, which is before 2020, fix the clock",
+            now
+        ));
+        return;
+    }
+    let last_write = fs::metadata(get_store_checkpoint(store_root))
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|modified| modified.as_millis() as u64);
+    if let Some(last_write) = last_write {
+        if last_write > now + MAX_CLOCK_BEHIND_MILLIS {
+            report.warnings.push(format!(
+                "system clock is {} s behind the last store write, store timestamps will go \
+                 backwards, check the time synchronization",
+                (last_write - now) / 1000
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMITS: &str = "Limit              Soft Limit   Hard Limit   Units\nMax open files     \
+                          1024         1048576      files\nMax locked memory  unlimited    \
+                          unlimited    bytes\n";
+
+    #[test]
+    fn parses_soft_limits() {
+        assert_eq!(parse_soft_limit(LIMITS, "Max open files"), Some(1024));
+        assert_eq!(
+            parse_soft_limit(LIMITS, "Max locked memory"),
+            Some(u64::MAX)
+        );
+        assert_eq!(parse_soft_limit(LIMITS, "Max processes"), None);
+    }
+
+    #[test]
+    fn reports_taken_port_and_unwritable_dir() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port() as u32;
+        assert!(check_port("127.0.0.1", port).is_err());
+        drop(listener);
+        assert!(check_port("127.0.0.1", port).is_ok());
+
+        let file = std::env::temp_dir().join(format!("preflight-{}", std::process::id()));
+        fs::write(&file, b"").unwrap();
+        assert!(check_writable_dir(file.join("store").to_str().unwrap()).is_err());
+        fs::remove_file(file).unwrap();
+    }
+}
@@ -0,0 +1,81 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use rocketmq_common::common::broker::broker_role::BrokerRole;
+use rocketmq_common::common::mix_all;
+use rocketmq_rust::ArcMut;
+use rocketmq_store::dledger::dledger_server::DLedgerRoleChangeHandler;
+use rocketmq_store::dledger::member_state::MemberRole;
+use rocketmq_store::log_file::MessageStore;
+use tracing::info;
+
+use crate::broker_runtime::BrokerRuntimeInner;
+
+/// Registers the broker as master of its group while its DLedger member leads, and as slave
+/// with `slave_broker_id` otherwise, so that producers follow the leader of the group.
+pub(crate) struct BrokerDLedgerRoleChangeHandler<MS> {
+    broker_runtime_inner: ArcMut<BrokerRuntimeInner<MS>>,
+    slave_broker_id: u64,
+}
+
+impl<MS> BrokerDLedgerRoleChangeHandler<MS> {
+    pub(crate) fn new(
+        broker_runtime_inner: ArcMut<BrokerRuntimeInner<MS>>,
+        slave_broker_id: u64,
+    ) -> Self {
+        Self {
+            broker_runtime_inner,
+            slave_broker_id,
+        }
+    }
+}
+
+impl<MS: MessageStore> DLedgerRoleChangeHandler for BrokerDLedgerRoleChangeHandler<MS> {
+    fn handle(&self, term: i64, role: MemberRole) {
+        let (broker_role, broker_id) = match role {
+            MemberRole::Leader => (BrokerRole::SyncMaster, mix_all::MASTER_ID),
+            MemberRole::Candidate | MemberRole::Follower => {
+                (BrokerRole::Slave, self.slave_broker_id)
+            }
+        };
+        let mut inner = self.broker_runtime_inner.clone();
+        if inner.message_store_config().broker_role == broker_role
+            && inner.broker_config().broker_identity.broker_id == broker_id
+        {
+            return;
+        }
+        info!(
+            "dledger role changed to {} in term {}, broker changes to {} with id {}",
+            role,
+            term,
+            broker_role.get_broker_role(),
+            broker_id
+        );
+        inner.message_store_config_mut().broker_role = broker_role;
+        inner
+            .shared_broker_config()
+            .modify(|broker_config| broker_config.broker_identity.broker_id = broker_id);
+        if broker_role != BrokerRole::Slave {
+            inner.slave_synchronize().set_master_addr(None);
+        }
+        tokio::spawn(async move {
+            let this = inner.clone();
+            inner
+                .register_broker_all_inner(this, true, false, true)
+                .await;
+        });
+    }
+}
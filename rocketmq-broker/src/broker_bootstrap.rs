@@ -14,6 +14,7 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::path::PathBuf;
 
 use rocketmq_common::common::broker::broker_config::BrokerConfig;
 use rocketmq_common::common::server::config::ServerConfig;
@@ -63,6 +64,7 @@ pub struct Builder {
     broker_config: BrokerConfig,
     message_store_config: MessageStoreConfig,
     server_config: ServerConfig,
+    config_file: Option<PathBuf>,
 }
 
 impl Builder {
@@ -71,6 +73,7 @@ impl Builder {
             broker_config: Default::default(),
             message_store_config: MessageStoreConfig::default(),
             server_config: Default::default(),
+            config_file: None,
         }
     }
 
@@ -89,14 +92,23 @@ impl Builder {
         self
     }
 
+    /// The file the configs were read from, watched for edits when
+    /// `enable_config_file_watch` is set.
+    pub fn set_config_file(mut self, config_file: PathBuf) -> Self {
+        self.config_file = Some(config_file);
+        self
+    }
+
     pub fn build(self) -> BrokerBootstrap {
-        BrokerBootstrap {
-            broker_runtime: BrokerRuntime::new(
-                self.broker_config,
-                self.message_store_config,
-                self.server_config,
-            ),
+        let mut broker_runtime = BrokerRuntime::new(
+            self.broker_config,
+            self.message_store_config,
+            self.server_config,
+        );
+        if let Some(config_file) = self.config_file {
+            broker_runtime.set_config_file(config_file);
         }
+        BrokerBootstrap { broker_runtime }
     }
 }
 
@@ -26,6 +26,24 @@ pub fn get_broker_config_path() -> String {
     path.to_string_lossy().into_owned()
 }
 
+// Configured broker config path, the default one when none is configured
+pub fn resolve_broker_config_path(configured: &str) -> String {
+    if configured.is_empty() {
+        get_broker_config_path()
+    } else {
+        configured.to_string()
+    }
+}
+
+// Slow request log path
+pub fn get_slow_request_log_path() -> String {
+    let mut path = dirs::home_dir().unwrap();
+    path.push("logs");
+    path.push("rocketmqlogs");
+    path.push("slow.log");
+    path.to_string_lossy().into_owned()
+}
+
 // Topic config path
 pub fn get_topic_config_path(root_dir: &str) -> String {
     PathBuf::from(root_dir)
@@ -80,6 +98,33 @@ pub fn get_subscription_group_path(root_dir: &str) -> String {
         .into_owned()
 }
 
+// Topic RocksDB path
+pub fn get_topic_config_rocksdb_path(root_dir: &str) -> String {
+    PathBuf::from(root_dir)
+        .join("config")
+        .join("topics")
+        .to_string_lossy()
+        .into_owned()
+}
+
+// Subscription group RocksDB path
+pub fn get_subscription_group_rocksdb_path(root_dir: &str) -> String {
+    PathBuf::from(root_dir)
+        .join("config")
+        .join("subscriptionGroups")
+        .to_string_lossy()
+        .into_owned()
+}
+
+// Consumer offset RocksDB path
+pub fn get_consumer_offset_rocksdb_path(root_dir: &str) -> String {
+    PathBuf::from(root_dir)
+        .join("config")
+        .join("consumerOffsets")
+        .to_string_lossy()
+        .into_owned()
+}
+
 // Timer check path
 pub fn get_timer_check_path(root_dir: &str) -> String {
     PathBuf::from(root_dir)
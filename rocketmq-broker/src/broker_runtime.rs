@@ -16,6 +16,7 @@
  */
 use std::collections::HashMap;
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::AtomicU64;
 use std::sync::atomic::Ordering;
@@ -24,9 +25,12 @@ use std::time::Duration;
 
 use cheetah_string::CheetahString;
 use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::common::broker::broker_role::BrokerRole;
+use rocketmq_common::common::broker::shared_broker_config::SharedBrokerConfig;
 use rocketmq_common::common::config::TopicConfig;
 use rocketmq_common::common::config_manager::ConfigManager;
 use rocketmq_common::common::constant::PermName;
+use rocketmq_common::common::mix_all;
 use rocketmq_common::common::server::config::ServerConfig;
 use rocketmq_common::common::statistics::state_getter::StateGetter;
 use rocketmq_common::TimeUtils::get_current_millis;
@@ -41,18 +45,29 @@ use rocketmq_remoting::remoting_server::server::RocketMQServer;
 use rocketmq_remoting::runtime::config::client_config::TokioClientConfig;
 use rocketmq_runtime::RocketMQRuntime;
 use rocketmq_rust::ArcMut;
-use rocketmq_store::base::store_enum::StoreType;
+use rocketmq_store::base::dispatch_subscription::DispatchSubscriptionManager;
 use rocketmq_store::config::message_store_config::MessageStoreConfig;
+use rocketmq_store::dledger::member_state::DLedgerPeers;
 use rocketmq_store::log_file::MessageStore;
-use rocketmq_store::message_store::default_message_store::DefaultMessageStore;
+use rocketmq_store::message_store::boxed_message_store::BoxedMessageStore;
+use rocketmq_store::message_store::message_store_factory::MessageStoreContext;
+use rocketmq_store::message_store::message_store_factory::MessageStoreFactory;
 use rocketmq_store::stats::broker_stats::BrokerStats;
 use rocketmq_store::stats::broker_stats_manager::BrokerStatsManager;
 use rocketmq_store::timer::timer_message_store::TimerMessageStore;
+use tokio::task::JoinHandle;
+use tracing::error;
 use tracing::info;
 use tracing::warn;
 
+use crate::broker::broker_config_watcher::reload_broker_config;
+use crate::broker::broker_config_watcher::BrokerConfigWatcher;
 use crate::broker::broker_hook::BrokerShutdownHook;
+use crate::broker::broker_persist_scheduler::PersistSchedule;
+use crate::broker::broker_persist_scheduler::PersistTarget;
 use crate::broker::broker_pre_online_service::BrokerPreOnlineService;
+use crate::broker::broker_preflight::run_preflight_checks;
+use crate::broker::dledger_role_change_handler::BrokerDLedgerRoleChangeHandler;
 use crate::client::consumer_ids_change_listener::ConsumerIdsChangeListener;
 use crate::client::default_consumer_ids_change_listener::DefaultConsumerIdsChangeListener;
 use crate::client::manager::consumer_manager::ConsumerManager;
@@ -63,12 +78,21 @@ use crate::coldctr::cold_data_cg_ctr_service::ColdDataCgCtrService;
 use crate::coldctr::cold_data_pull_request_hold_service::ColdDataPullRequestHoldService;
 use crate::controller::replicas_manager::ReplicasManager;
 use crate::failover::escape_bridge::EscapeBridge;
+use crate::filter::commit_log_dispatcher_calc_bit_map::CommitLogDispatcherCalcBitMap;
 use crate::filter::manager::consumer_filter_manager::ConsumerFilterManager;
 use crate::hook::batch_check_before_put_message::BatchCheckBeforePutMessageHook;
 use crate::hook::check_before_put_message::CheckBeforePutMessageHook;
+use crate::hook::schedule_message_hook::ScheduleMessageHook;
 use crate::latency::broker_fast_failure::BrokerFastFailure;
+use crate::latency::overload_shedder::OverloadShedder;
+use crate::latency::slow_request_log::SlowRequestLog;
+use crate::load_balance::message_request_mode_manager::MessageRequestModeManager;
 use crate::long_polling::long_polling_service::pull_request_hold_service::PullRequestHoldService;
 use crate::long_polling::notify_message_arriving_listener::NotifyMessageArrivingListener;
+use crate::mqtrace::broker_trace_dispatcher::BrokerTraceDispatcher;
+use crate::mqtrace::broker_trace_hook::BrokerTraceConsumeMessageHook;
+use crate::mqtrace::broker_trace_hook::BrokerTraceSendMessageHook;
+use crate::mqtrace::consume_message_hook::ConsumeMessageHook;
 use crate::offset::manager::broadcast_offset_manager::BroadcastOffsetManager;
 use crate::offset::manager::consumer_offset_manager::ConsumerOffsetManager;
 use crate::offset::manager::consumer_order_info_manager::ConsumerOrderInfoManager;
@@ -88,9 +112,11 @@ use crate::processor::pull_message_result_handler::PullMessageResultHandler;
 use crate::processor::query_assignment_processor::QueryAssignmentProcessor;
 use crate::processor::query_message_processor::QueryMessageProcessor;
 use crate::processor::reply_message_processor::ReplyMessageProcessor;
+use crate::processor::request_executor::RequestExecutors;
 use crate::processor::send_message_processor::SendMessageProcessor;
 use crate::processor::BrokerRequestProcessor;
 use crate::schedule::schedule_message_service::ScheduleMessageService;
+use crate::slave::slave_synchronize::SlaveSynchronize;
 use crate::subscription::manager::subscription_group_manager::SubscriptionGroupManager;
 use crate::topic::manager::topic_config_manager::TopicConfigManager;
 use crate::topic::manager::topic_queue_mapping_manager::TopicQueueMappingManager;
@@ -101,6 +127,7 @@ use crate::transaction::queue::default_transactional_message_service::DefaultTra
 use crate::transaction::queue::transactional_message_bridge::TransactionalMessageBridge;
 use crate::transaction::transaction_metrics_flush_service::TransactionMetricsFlushService;
 use crate::transaction::transactional_message_check_service::TransactionalMessageCheckService;
+use crate::util::ordering_checker::OrderingChecker;
 
 pub(crate) struct BrokerRuntime {
     /*    store_host: SocketAddr,
@@ -151,15 +178,19 @@ pub(crate) struct BrokerRuntime {
     escape_bridge: ArcMut<EscapeBridge<DefaultMessageStore>>,
     pop_inflight_message_counter: Arc<PopInflightMessageCounter>,*/
     #[cfg(feature = "local_file_store")]
-    inner: ArcMut<BrokerRuntimeInner<DefaultMessageStore>>,
+    inner: ArcMut<BrokerRuntimeInner<BoxedMessageStore>>,
     #[cfg(feature = "local_file_store")]
     transactional_message_service:
-        Option<ArcMut<DefaultTransactionalMessageService<DefaultMessageStore>>>,
+        Option<ArcMut<DefaultTransactionalMessageService<BoxedMessageStore>>>,
     broker_runtime: Option<RocketMQRuntime>,
     shutdown_hook: Option<BrokerShutdownHook>,
     consumer_ids_change_listener: Arc<Box<dyn ConsumerIdsChangeListener + Send + Sync + 'static>>,
     topic_queue_mapping_clean_service: TopicQueueMappingCleanService,
     broker_pre_online_service: BrokerPreOnlineService,
+    message_store_factory: MessageStoreFactory,
+    remoting_server_handles: Vec<JoinHandle<()>>,
+    // toml file the broker was started from, watched when enable_config_file_watch is set
+    config_file: Option<PathBuf>,
     // receiver for shutdown signal
     pub(crate) shutdown_rx: Option<tokio::sync::broadcast::Receiver<()>>,
 }
@@ -177,8 +208,9 @@ impl BrokerRuntime {
         let runtime = RocketMQRuntime::new_multi(10, "broker-thread");
         let broker_outer_api = BrokerOuterAPI::new(Arc::new(TokioClientConfig::default()));
 
+        let shared_broker_config = SharedBrokerConfig::new(broker_config.clone());
         let topic_queue_mapping_manager =
-            TopicQueueMappingManager::new(Arc::new(broker_config.clone()));
+            TopicQueueMappingManager::new(shared_broker_config.clone());
         let mut broker_member_group = BrokerMemberGroup::new(
             broker_config.broker_identity.broker_cluster_name.clone(),
             broker_config.broker_identity.broker_name.clone(),
@@ -190,7 +222,9 @@ impl BrokerRuntime {
         let producer_manager = ProducerManager::new();
         let consumer_ids_change_listener: Arc<
             Box<dyn ConsumerIdsChangeListener + Send + Sync + 'static>,
-        > = Arc::new(Box::new(DefaultConsumerIdsChangeListener {}));
+        > = Arc::new(Box::new(DefaultConsumerIdsChangeListener::new(
+            &broker_config,
+        )));
         let consumer_manager = ConsumerManager::new_with_broker_stats(
             consumer_ids_change_listener.clone(),
             Arc::new(broker_config.clone()),
@@ -200,22 +234,39 @@ impl BrokerRuntime {
         let pop_inflight_message_counter =
             PopInflightMessageCounter::new(should_start_time.clone());
 
-        let mut inner = ArcMut::new(BrokerRuntimeInner::<DefaultMessageStore> {
+        let request_executors = RequestExecutors::new(&broker_config);
+        let broker_trace_dispatcher = Arc::new(BrokerTraceDispatcher::new(
+            broker_config.broker_trace_queue_size,
+        ));
+        let schedule_message_service = ScheduleMessageService::new(&message_store_config);
+        let consumer_filter_manager = ConsumerFilterManager::new(shared_broker_config.clone());
+        let message_request_mode_manager =
+            MessageRequestModeManager::new(Arc::new(message_store_config.clone()));
+        let consumer_offset_manager =
+            ConsumerOffsetManager::new(shared_broker_config.clone(), None);
+        #[cfg(feature = "rocksdb")]
+        let consumer_offset_manager = consumer_offset_manager
+            .with_rocksdb_store(message_store_config.is_enable_rocksdb_store());
+        let cold_data_cg_ctr_service = ColdDataCgCtrService::new(
+            shared_broker_config.clone(),
+            Arc::new(message_store_config.clone()),
+        );
+        let mut inner = ArcMut::new(BrokerRuntimeInner::<BoxedMessageStore> {
             shutdown: Arc::new(AtomicBool::new(false)),
             store_host,
             broker_addr: CheetahString::from(broker_address),
-            broker_config,
+            broker_config: shared_broker_config,
             message_store_config,
             server_config,
             topic_config_manager: None,
             topic_queue_mapping_manager,
-            consumer_offset_manager: Default::default(),
+            consumer_offset_manager,
             subscription_group_manager: None,
-            consumer_filter_manager: Some(Default::default()),
+            consumer_filter_manager: Some(consumer_filter_manager),
             consumer_order_info_manager: None,
             message_store: None,
             broker_stats: None,
-            schedule_message_service: Default::default(),
+            schedule_message_service,
             timer_message_store: None,
             broker_outer_api,
             producer_manager,
@@ -236,14 +287,22 @@ impl BrokerRuntime {
             escape_bridge: None,
             pop_inflight_message_counter,
             replicas_manager: None,
-            broker_fast_failure: BrokerFastFailure,
+            broker_fast_failure: BrokerFastFailure::default(),
+            request_executors,
+            broker_trace_dispatcher,
+            overload_shedder: OverloadShedder::default(),
+            slow_request_log: SlowRequestLog::default(),
             cold_data_pull_request_hold_service: None,
-            cold_data_cg_ctr_service: None,
+            cold_data_cg_ctr_service: Some(cold_data_cg_ctr_service),
+            message_request_mode_manager,
+            slave_synchronize: SlaveSynchronize::default(),
+            dispatch_subscription_manager: DispatchSubscriptionManager::new(),
+            ordering_checker: OrderingChecker::default(),
             pop_message_processor: None,
             ack_message_processor: None,
             notification_processor: None,
         });
-        let mut stats_manager = BrokerStatsManager::new(Arc::new(inner.broker_config.clone()));
+        let mut stats_manager = BrokerStatsManager::new(inner.broker_config.clone());
         stats_manager.set_producer_state_getter(Arc::new(ProducerStateGetter {
             broker_runtime_inner: inner.clone(),
         }));
@@ -265,12 +324,25 @@ impl BrokerRuntime {
             shutdown_hook: None,
             consumer_ids_change_listener,
             topic_queue_mapping_clean_service: TopicQueueMappingCleanService,
-            broker_pre_online_service: BrokerPreOnlineService,
+            broker_pre_online_service: BrokerPreOnlineService::new(),
+            message_store_factory: MessageStoreFactory::default(),
+            remoting_server_handles: Vec::new(),
+            config_file: None,
             shutdown_rx: None,
         }
     }
 
-    pub(crate) fn broker_config(&self) -> &BrokerConfig {
+    pub(crate) fn set_config_file(&mut self, config_file: PathBuf) {
+        self.config_file = Some(config_file);
+    }
+
+    /// The factory the message store is built with, register other store types with it before
+    /// the broker is initialized.
+    pub(crate) fn message_store_factory_mut(&mut self) -> &mut MessageStoreFactory {
+        &mut self.message_store_factory
+    }
+
+    pub(crate) fn broker_config(&self) -> Arc<BrokerConfig> {
         self.inner.broker_config()
     }
 
@@ -278,6 +350,11 @@ impl BrokerRuntime {
         self.inner.message_store_config()
     }
 
+    #[cfg(test)]
+    pub(crate) fn inner(&self) -> &ArcMut<BrokerRuntimeInner<BoxedMessageStore>> {
+        &self.inner
+    }
+
     pub async fn shutdown(&mut self) {
         self.shutdown_basic_service().await;
 
@@ -286,6 +363,10 @@ impl BrokerRuntime {
         if let Some(runtime) = self.broker_runtime.take() {
             runtime.shutdown();
         }
+        // frees the listen ports, a broker of a container may be added again later
+        for handle in self.remoting_server_handles.drain(..) {
+            handle.abort();
+        }
         /* if let Some(message_store) = &mut self.inner.message_store {
             message_store.shutdown()
         }
@@ -307,10 +388,14 @@ impl BrokerRuntime {
         self.inner
             .broker_outer_api
             .unregister_broker_all(
-                &self.inner.broker_config.broker_identity.broker_cluster_name,
-                &self.inner.broker_config.broker_identity.broker_name,
+                &self
+                    .inner
+                    .broker_config()
+                    .broker_identity
+                    .broker_cluster_name,
+                &self.inner.broker_config().broker_identity.broker_name,
                 self.inner.get_broker_addr(),
-                self.inner.broker_config.broker_identity.broker_id,
+                self.inner.broker_config().broker_identity.broker_id,
             )
             .await;
     }
@@ -364,13 +449,8 @@ impl BrokerRuntime {
         }
 
         self.inner.broker_fast_failure.shutdown();
-
-        if let Some(consumer_filter_manager) = self.inner.consumer_filter_manager.as_ref() {
-            consumer_filter_manager.persist();
-        }
-        if let Some(consumer_order_info_manager) = self.inner.consumer_order_info_manager.as_ref() {
-            consumer_order_info_manager.persist();
-        }
+        self.inner.slow_request_log.shutdown();
+        self.inner.broker_trace_dispatcher.shutdown();
 
         self.inner.schedule_message_service.persist();
         self.inner.schedule_message_service.shutdown();
@@ -394,6 +474,7 @@ impl BrokerRuntime {
         if let Some(topic_route_info_manager) = self.inner.topic_route_info_manager.as_mut() {
             topic_route_info_manager.shutdown();
         }
+        self.broker_pre_online_service.shutdown();
 
         if let Some(cold_data_pull_request_hold_service) =
             self.inner.cold_data_pull_request_hold_service.as_mut()
@@ -405,17 +486,13 @@ impl BrokerRuntime {
             cold_data_cg_ctr_service.shutdown();
         }
 
+        self.inner.persist_all();
         if let Some(topic_config_manager) = self.inner.topic_config_manager.as_mut() {
-            topic_config_manager.persist();
             topic_config_manager.stop();
         }
-
         if let Some(subscription_group_manager) = self.inner.subscription_group_manager.as_mut() {
-            subscription_group_manager.persist();
             subscription_group_manager.stop();
         }
-
-        self.inner.consumer_offset_manager.persist();
         self.inner.consumer_offset_manager.stop();
     }
 }
@@ -434,6 +511,17 @@ impl BrokerRuntime {
 
 impl BrokerRuntime {
     pub(crate) async fn initialize(&mut self) -> bool {
+        let report =
+            run_preflight_checks(&self.inner.message_store_config, &self.inner.server_config);
+        for warning in &report.warnings {
+            warn!("Preflight check: {}", warning);
+        }
+        if !report.failures.is_empty() {
+            for failure in &report.failures {
+                error!("Preflight check failed: {}", failure);
+            }
+            return false;
+        }
         let mut result = self.initialize_metadata();
         if !result {
             warn!("Initialize metadata failed");
@@ -460,34 +548,61 @@ impl BrokerRuntime {
     }
 
     async fn initialize_message_store(&mut self) -> bool {
-        if self.inner.message_store_config.store_type == StoreType::LocalFile {
-            info!("Use local file as message store");
-            let mut message_store = ArcMut::new(DefaultMessageStore::new(
-                Arc::new(self.inner.message_store_config.clone()),
-                Arc::new(self.inner.broker_config.clone()),
-                self.inner.topic_config_manager().topic_config_table(),
-                self.inner.broker_stats_manager.clone(),
-                false,
-            ));
-            let message_store_clone = message_store.clone();
-            message_store.set_message_store_arc(Some(message_store_clone));
-            if self.inner.message_store_config.is_timer_wheel_enable() {
-                let time_message_store = TimerMessageStore::new(Some(message_store.clone()));
-                message_store.set_timer_message_store(Arc::new(time_message_store));
-            }
-            //Maybe need to set message store to other components
-            /*self.consumer_offset_manager
-                .set_message_store(Some(message_store.clone()));
-            self.topic_config_manager
-                .set_message_store(Some(message_store.clone()));*/
-            self.inner.broker_stats = Some(BrokerStats::new(message_store.clone()));
-            self.inner.message_store = Some(message_store);
-        } else if self.inner.message_store_config.store_type == StoreType::RocksDB {
-            info!("Use RocksDB as message store");
-        } else {
-            warn!("Unknown store type");
+        let store_type = self.inner.message_store_config.store_type;
+        let context = MessageStoreContext {
+            message_store_config: Arc::new(self.inner.message_store_config.clone()),
+            broker_config: self.inner.broker_config.clone(),
+            topic_config_table: self.inner.topic_config_manager().topic_config_table(),
+            broker_stats_manager: self.inner.broker_stats_manager.clone(),
+        };
+        let Some(message_store) = self.message_store_factory.build(&context) else {
+            warn!(
+                "No message store is registered for store type {}",
+                store_type.get_store_type()
+            );
             return false;
+        };
+        info!("Use {} as message store", store_type.get_store_type());
+        let message_store = ArcMut::new(message_store);
+        message_store.add_first_dispatcher(Box::new(CommitLogDispatcherCalcBitMap::new(
+            self.inner.broker_config.clone(),
+            self.inner.consumer_filter_manager().clone(),
+        )));
+        message_store.add_dispatcher(Box::new(self.inner.dispatch_subscription_manager.clone()));
+        if self.inner.broker_config().enable_ordering_check {
+            info!("ordering check is enabled, queue offsets going backwards are logged");
+            message_store.add_dispatcher(Box::new(self.inner.ordering_checker.clone()));
+        }
+        if self.inner.message_store_config.enable_dledger_commit_log {
+            // the preflight checks rejected an illegal dledger config already
+            let slave_broker_id = DLedgerPeers::parse(
+                self.inner
+                    .message_store_config
+                    .dledger_group
+                    .as_deref()
+                    .unwrap_or_default(),
+                self.inner
+                    .message_store_config
+                    .dledger_peers
+                    .as_deref()
+                    .unwrap_or_default(),
+                self.inner
+                    .message_store_config
+                    .dledger_self_id
+                    .as_deref()
+                    .unwrap_or_default(),
+            )
+            .map_or(1, |peers| peers.slave_broker_id());
+            message_store.set_dledger_role_change_handler(Arc::new(
+                BrokerDLedgerRoleChangeHandler::new(self.inner.clone(), slave_broker_id),
+            ));
+        }
+        if self.inner.message_store_config.is_timer_wheel_enable() {
+            self.inner.timer_message_store =
+                Some(message_store.get_timer_message_store().as_ref().clone());
         }
+        self.inner.broker_stats = Some(BrokerStats::new(message_store.clone()));
+        self.inner.message_store = Some(message_store);
         true
     }
 
@@ -495,21 +610,22 @@ impl BrokerRuntime {
         let mut result: bool = true;
 
         if self.inner.broker_config().enable_controller_mode {
-            info!("Start controller mode(Support for future versions)");
-            unimplemented!()
+            if self.inner.broker_config().broker_identity.broker_id == mix_all::MASTER_ID {
+                error!(
+                    "the broker id identifies the broker toward the controller, it must be > 0 in \
+                     controller mode"
+                );
+                return false;
+            }
+            self.inner.replicas_manager = Some(ReplicasManager::new(self.inner.clone()));
         }
         if self.inner.message_store.is_some() {
             self.register_message_store_hook();
             self.inner.message_store.as_mut().unwrap().load().await;
         }
 
-        if self
-            .inner
-            .broker_config
-            .timer_wheel_config
-            .timer_wheel_enable
-        {
-            result &= self.inner.timer_message_store.as_mut().unwrap().load();
+        if let Some(timer_message_store) = self.inner.timer_message_store.as_mut() {
+            result &= timer_message_store.load();
         }
         result &= self.inner.schedule_message_service.load();
 
@@ -528,12 +644,19 @@ impl BrokerRuntime {
     pub fn register_message_store_hook(&mut self) {
         let config = self.inner.message_store_config.clone();
         let arc = self.inner.topic_config_manager().topic_config_table();
+        let schedule_message_service = self.inner.schedule_message_service.clone();
         if let Some(ref mut message_store) = self.inner.message_store {
+            let config = Arc::new(config);
             message_store.set_put_message_hook(Box::new(CheckBeforePutMessageHook::new(
                 message_store.clone(),
-                Arc::new(config),
+                config.clone(),
             )));
             message_store.set_put_message_hook(Box::new(BatchCheckBeforePutMessageHook::new(arc)));
+            message_store.set_put_message_hook(Box::new(ScheduleMessageHook::new(
+                message_store.clone(),
+                schedule_message_service,
+                config,
+            )));
         }
     }
 
@@ -546,13 +669,13 @@ impl BrokerRuntime {
         self.inner.topic_queue_mapping_clean_service = Some(TopicQueueMappingCleanService);
     }
 
-    fn init_processor(
+    pub(crate) fn init_processor(
         &mut self,
     ) -> BrokerRequestProcessor<
-        DefaultMessageStore,
-        DefaultTransactionalMessageService<DefaultMessageStore>,
+        BoxedMessageStore,
+        DefaultTransactionalMessageService<BoxedMessageStore>,
     > {
-        let send_message_processor = SendMessageProcessor::new(
+        let mut send_message_processor = SendMessageProcessor::new(
             /*self.topic_queue_mapping_manager.clone(),
             self.subscription_group_manager.clone(),
             self.topic_config_manager.clone(),
@@ -578,6 +701,20 @@ impl BrokerRuntime {
             self.transactional_message_service.as_ref().unwrap().clone(),
             self.inner.clone(),
         );
+        let mut consume_message_hooks: Vec<Box<dyn ConsumeMessageHook>> = Vec::new();
+        if self.inner.broker_config().broker_trace_enable {
+            let broker_trace_dispatcher = self.inner.broker_trace_dispatcher.clone();
+            send_message_processor.register_send_message_hook(Box::new(
+                BrokerTraceSendMessageHook::new(
+                    broker_trace_dispatcher.clone(),
+                    self.inner.broker_config().msg_trace_topic_name.clone(),
+                ),
+            ));
+            consume_message_hooks.push(Box::new(BrokerTraceConsumeMessageHook::new(
+                broker_trace_dispatcher,
+                self.inner.broker_config().region_id.clone(),
+            )));
+        }
         let mut pull_message_result_handler =
             ArcMut::new(Box::new(DefaultPullMessageResultHandler::new(
                 /*self.message_store_config.clone(),
@@ -588,7 +725,7 @@ impl BrokerRuntime {
                 self.broker_stats_manager.clone(),
                 self.broker_config.clone(),
                 Arc::new(Default::default()),*/
-                Arc::new(Default::default()), //optimize
+                Arc::new(consume_message_hooks),
                 self.inner.clone(),
             )) as Box<dyn PullMessageResultHandler>);
         //let message_store = self.message_store.clone().unwrap();
@@ -623,11 +760,14 @@ impl BrokerRuntime {
             /* self.broker_config.clone(), */
             self.inner.clone(),
         ));
+        self.inner.cold_data_pull_request_hold_service = Some(ColdDataPullRequestHoldService::new(
+            pull_message_processor.clone(),
+        ));
 
         let pull_message_result_handler = pull_message_result_handler.as_mut().as_mut();
         pull_message_result_handler
             .as_any_mut()
-            .downcast_mut::<DefaultPullMessageResultHandler<DefaultMessageStore>>()
+            .downcast_mut::<DefaultPullMessageResultHandler<BoxedMessageStore>>()
             .expect("downcast DefaultPullMessageResultHandler failed")
             .set_pull_request_hold_service(self.inner.clone());
 
@@ -680,7 +820,7 @@ impl BrokerRuntime {
         ));
         self.inner.ack_message_processor = Some(ack_message_processor.clone());
 
-        let notification_processor = ArcMut::new(NotificationProcessor::default());
+        let notification_processor = NotificationProcessor::new_arc_mut(self.inner.clone());
         self.inner.notification_processor = Some(notification_processor.clone());
         BrokerRequestProcessor {
             send_message_processor: ArcMut::new(send_message_processor),
@@ -729,6 +869,7 @@ impl BrokerRuntime {
                 self.transactional_message_service.as_ref().unwrap().clone(),
                 self.inner.clone(),
             )),
+            broker_runtime_inner: self.inner.clone(),
         }
     }
 
@@ -754,51 +895,56 @@ impl BrokerRuntime {
                 }
             });
 
-        //need to optimize
-        let consumer_offset_manager_inner = self.inner.clone();
-        let flush_consumer_offset_interval =
-            self.inner.broker_config.flush_consumer_offset_interval;
+        let persist_inner = self.inner.clone();
         self.broker_runtime
             .as_ref()
             .unwrap()
             .get_handle()
             .spawn(async move {
-                info!("Consumer offset manager Start scheduled task");
-                tokio::time::sleep(Duration::from_millis(1000 * 10)).await;
+                info!("Persist managers Start scheduled task");
+                tokio::time::sleep(Duration::from_secs(10)).await;
+                let mut schedule = PersistSchedule::new(std::time::Instant::now());
+                let mut interval = tokio::time::interval(Duration::from_secs(1));
                 loop {
-                    let current_execution_time = tokio::time::Instant::now();
-                    consumer_offset_manager_inner
-                        .consumer_offset_manager
-                        .persist();
-                    let next_execution_time = current_execution_time
-                        + Duration::from_millis(flush_consumer_offset_interval);
-                    let delay =
-                        next_execution_time.saturating_duration_since(tokio::time::Instant::now());
-                    tokio::time::sleep(delay).await;
+                    interval.tick().await;
+                    if persist_inner.shutdown.load(Ordering::Acquire) {
+                        break;
+                    }
+                    let due = schedule.take_due(std::time::Instant::now(), |target| {
+                        target.interval_millis(&persist_inner.broker_config())
+                    });
+                    for target in due {
+                        persist_inner.persist(target);
+                    }
                 }
             });
 
-        //need to optimize
-        let mut _inner = self.inner.clone();
-        //let mut  consumer_order_info_manager = self.inner.consumer_order_info_manager.clone();
+        let client_housekeeping = self.inner.clone();
         self.broker_runtime
             .as_ref()
             .unwrap()
             .get_handle()
             .spawn(async move {
-                info!("consumer filter manager Start scheduled task");
-                info!("consumer order info manager Start scheduled task");
-                tokio::time::sleep(Duration::from_millis(1000 * 10)).await;
+                info!("Client housekeeping Start scheduled task");
+                tokio::time::sleep(Duration::from_secs(10)).await;
                 loop {
                     let current_execution_time = tokio::time::Instant::now();
-                    _inner.consumer_filter_manager.as_mut().unwrap().persist();
-                    _inner
-                        .consumer_order_info_manager
-                        .as_mut()
-                        .unwrap()
-                        .persist();
-                    let next_execution_time =
-                        current_execution_time + Duration::from_millis(1000 * 10);
+                    let channel_expired_timeout =
+                        client_housekeeping.broker_config().channel_expired_timeout;
+                    let mut expired = client_housekeeping
+                        .producer_manager()
+                        .scan_not_active_channel(channel_expired_timeout);
+                    expired.extend(
+                        client_housekeeping
+                            .consumer_manager()
+                            .scan_not_active_channel(),
+                    );
+                    for client_channel_info in expired {
+                        let mut channel = client_channel_info.channel().clone();
+                        // the channel may be shared by a producer and a consumer of one client
+                        let _ = channel.connection_mut().close().await;
+                    }
+                    let next_execution_time = current_execution_time + Duration::from_secs(10);
                     let delay =
                         next_execution_time.saturating_duration_since(tokio::time::Instant::now());
                     tokio::time::sleep(delay).await;
@@ -845,11 +991,91 @@ impl BrokerRuntime {
                 }
             });
 
-        if self.inner.broker_config.enable_controller_mode {
+        if let Some(config_file) = self
+            .config_file
+            .clone()
+            .filter(|_| self.inner.broker_config().enable_config_file_watch)
+        {
+            let broker_runtime = self.inner.clone();
+            let mut watcher = BrokerConfigWatcher::new(config_file);
+            let period = Duration::from_millis(
+                self.inner
+                    .broker_config()
+                    .config_file_watch_interval_millis
+                    .max(1000),
+            );
+            self.broker_runtime
+                .as_ref()
+                .unwrap()
+                .get_handle()
+                .spawn(async move {
+                    info!("Broker config file watch Start scheduled task");
+                    loop {
+                        tokio::time::sleep(period).await;
+                        let Some(changed) = watcher.poll() else {
+                            continue;
+                        };
+                        let store_properties = broker_runtime.message_store_config.get_properties();
+                        let Some(message_store) = broker_runtime.message_store().clone() else {
+                            continue;
+                        };
+                        let reload =
+                            broker_runtime
+                                .shared_broker_config()
+                                .modify(|broker_config| {
+                                    reload_broker_config(
+                                        broker_config,
+                                        &store_properties,
+                                        |key, value| {
+                                            message_store.update_runtime_config(key, value)
+                                        },
+                                        &changed,
+                                    )
+                                });
+                        if !reload.is_empty() {
+                            info!(
+                                "reload broker config from {:?}, applied: {:?}, restart required: \
+                                 {:?}, ignored: {:?}",
+                                watcher.path(),
+                                reload.applied,
+                                reload.restart_required,
+                                reload.ignored
+                            );
+                        }
+                    }
+                });
+        }
+
+        if self.inner.broker_config().enable_controller_mode {
             self.inner.update_master_haserver_addr_periodically = true;
         }
 
-        if let Some(ref namesrv_address) = self.inner.broker_config.namesrv_addr.clone() {
+        if self.inner.message_store_config.broker_role == BrokerRole::Slave {
+            let broker_runtime = self.inner.clone();
+            self.broker_runtime
+                .as_ref()
+                .unwrap()
+                .get_handle()
+                .spawn(async move {
+                    info!("SlaveSynchronize Start scheduled task");
+                    tokio::time::sleep(Duration::from_secs(3)).await;
+                    loop {
+                        let current_execution_time = tokio::time::Instant::now();
+                        if broker_runtime.message_store_config.broker_role == BrokerRole::Slave {
+                            broker_runtime
+                                .slave_synchronize
+                                .sync_all(&broker_runtime)
+                                .await;
+                        }
+                        let next_execution_time = current_execution_time + Duration::from_secs(10);
+                        let delay = next_execution_time
+                            .saturating_duration_since(tokio::time::Instant::now());
+                        tokio::time::sleep(delay).await;
+                    }
+                });
+        }
+
+        if let Some(ref namesrv_address) = self.inner.broker_config().namesrv_addr.clone() {
             self.update_namesrv_addr().await;
             info!(
                 "Set user specified name remoting_server address: {}",
@@ -899,7 +1125,8 @@ impl BrokerRuntime {
                 self.message_store.as_ref().cloned().unwrap(),*/
                 self.inner.clone(),
             ));
-        self.inner.transactional_message_check_service = Some(TransactionalMessageCheckService);
+        self.inner.transactional_message_check_service =
+            Some(TransactionalMessageCheckService::new());
         self.inner.transaction_metrics_flush_service = Some(TransactionMetricsFlushService);
     }
 
@@ -930,12 +1157,16 @@ impl BrokerRuntime {
 
         let server = RocketMQServer::new(Arc::new(self.inner.server_config.clone()));
         //start nomarl broker remoting_server
-        tokio::spawn(async move { server.run(request_processor).await });
+        self.remoting_server_handles.push(tokio::spawn(async move {
+            server.run(request_processor).await
+        }));
         //start fast broker remoting_server
         let mut fast_server_config = self.inner.server_config.clone();
         fast_server_config.listen_port = self.inner.server_config.listen_port - 2;
         let fast_server = RocketMQServer::new(Arc::new(fast_server_config));
-        tokio::spawn(async move { fast_server.run(fast_request_processor).await });
+        self.remoting_server_handles.push(tokio::spawn(async move {
+            fast_server.run(fast_request_processor).await
+        }));
 
         if let Some(pop_message_processor) = self.inner.pop_message_processor.as_mut() {
             pop_message_processor.start();
@@ -943,11 +1174,30 @@ impl BrokerRuntime {
         if let Some(ack_message_processor) = self.inner.ack_message_processor.as_mut() {
             ack_message_processor.start();
         }
+        if let Some(transactional_message_service) = self.transactional_message_service.as_ref() {
+            transactional_message_service.start();
+            if let (Some(check_service), Some(listener)) = (
+                self.inner.transactional_message_check_service.as_ref(),
+                self.inner.transactional_message_check_listener.as_ref(),
+            ) {
+                check_service.start(
+                    transactional_message_service.clone(),
+                    listener.clone(),
+                    self.inner.clone(),
+                );
+            }
+        }
 
         if let Some(notification_processor) = self.inner.notification_processor.as_mut() {
             notification_processor.start();
         }
 
+        if self.inner.message_store_config.broker_role != BrokerRole::Slave {
+            self.inner
+                .schedule_message_service
+                .start(self.inner.clone());
+        }
+
         if let Some(topic_queue_mapping_clean_service) =
             self.inner.topic_queue_mapping_clean_service.as_mut()
         {
@@ -963,7 +1213,15 @@ impl BrokerRuntime {
             broker_stats_manager.start();
         }
 
-        self.inner.broker_fast_failure.start();
+        let inner = self.inner.clone();
+        self.inner.broker_fast_failure.start(inner);
+        let inner = self.inner.clone();
+        self.inner.slow_request_log.start(inner);
+
+        if self.inner.broker_config().broker_trace_enable {
+            let inner = self.inner.clone();
+            self.inner.broker_trace_dispatcher.start(inner);
+        }
 
         self.inner.broadcast_offset_manager.start();
 
@@ -973,12 +1231,15 @@ impl BrokerRuntime {
         if let Some(topic_route_info_manager) = self.inner.topic_route_info_manager.as_mut() {
             topic_route_info_manager.start();
         }
-        self.broker_pre_online_service.start();
+        if !self.inner.broker_config().skip_pre_online {
+            self.broker_pre_online_service.start(self.inner.clone());
+        }
 
+        let inner = self.inner.clone();
         if let Some(cold_data_pull_request_hold_service) =
             self.inner.cold_data_pull_request_hold_service.as_mut()
         {
-            cold_data_pull_request_hold_service.start();
+            cold_data_pull_request_hold_service.start(inner);
         }
         if let Some(cold_data_cg_ctr_service) = self.inner.cold_data_cg_ctr_service.as_mut() {
             cold_data_cg_ctr_service.start();
@@ -996,7 +1257,7 @@ impl BrokerRuntime {
             Ordering::Release,
         );
         if self.inner.message_store_config.total_replicas > 1
-            && self.inner.broker_config.enable_slave_acting_master
+            && self.inner.broker_config().enable_slave_acting_master
         {
             self.inner.is_isolated.store(true, Ordering::Release);
         }
@@ -1006,7 +1267,7 @@ impl BrokerRuntime {
 
         if !self.inner.is_isolated.load(Ordering::Acquire)
             && !self.inner.message_store_config.enable_dledger_commit_log
-            && !self.inner.broker_config.duplication_enable
+            && !self.inner.broker_config().duplication_enable
         {
             self.register_broker_all(true, false, true).await;
         }
@@ -1022,7 +1283,7 @@ impl BrokerRuntime {
                     10000.max(
                         60000.min(
                             broker_runtime_inner
-                                .broker_config
+                                .broker_config()
                                 .register_name_server_period,
                         ),
                     ),
@@ -1030,29 +1291,27 @@ impl BrokerRuntime {
                 let initial_delay = Duration::from_secs(10);
                 tokio::time::sleep(initial_delay).await;
                 loop {
+                    // record current execution time
+                    let current_execution_time = tokio::time::Instant::now();
+                    // execute task
                     let start_time = broker_runtime_inner
                         .should_start_time
                         .load(Ordering::Relaxed);
                     if get_current_millis() < start_time {
                         info!("Register to namesrv after {}", start_time);
-                        continue;
-                    }
-                    if broker_runtime_inner.is_isolated.load(Ordering::Relaxed) {
+                    } else if broker_runtime_inner.is_isolated.load(Ordering::Relaxed) {
                         info!("Skip register for broker is isolated");
-                        continue;
+                    } else {
+                        let this = broker_runtime_inner.clone();
+                        broker_runtime_inner
+                            .register_broker_all_inner(
+                                this,
+                                true,
+                                false,
+                                broker_runtime_inner.broker_config().force_register,
+                            )
+                            .await;
                     }
-                    // record current execution time
-                    let current_execution_time = tokio::time::Instant::now();
-                    // execute task
-                    let this = broker_runtime_inner.clone();
-                    broker_runtime_inner
-                        .register_broker_all_inner(
-                            this,
-                            true,
-                            false,
-                            broker_runtime_inner.broker_config.force_register,
-                        )
-                        .await;
                     // Calculate the time of the next execution
                     let next_execution_time = current_execution_time + period;
 
@@ -1063,26 +1322,26 @@ impl BrokerRuntime {
                 }
             });
 
-        if self.inner.broker_config.enable_slave_acting_master {
+        if self.inner.broker_config().enable_slave_acting_master {
             self.schedule_send_heartbeat();
-            let broker_runtime_inner = self.inner.clone();
+            let mut broker_runtime_inner = self.inner.clone();
             self.broker_runtime
                 .as_ref()
                 .unwrap()
                 .get_handle()
                 .spawn(async move {
-                    let period = Duration::from_secs(1);
-                    let initial_delay = Duration::from_millis(
+                    let period = Duration::from_millis(
                         broker_runtime_inner
-                            .broker_config
+                            .broker_config()
                             .sync_broker_member_group_period,
                     );
+                    let initial_delay = Duration::from_secs(1);
                     tokio::time::sleep(initial_delay).await;
                     loop {
                         // record current execution time
                         let current_execution_time = tokio::time::Instant::now();
                         // execute task
-                        broker_runtime_inner.sync_broker_member_group();
+                        broker_runtime_inner.sync_broker_member_group().await;
                         // Calculate the time of the next execution
                         let next_execution_time = current_execution_time + period;
 
@@ -1094,12 +1353,12 @@ impl BrokerRuntime {
                 });
         }
 
-        if self.inner.broker_config.enable_controller_mode {
+        if self.inner.broker_config().enable_controller_mode {
             self.schedule_send_heartbeat();
         }
 
-        if self.inner.broker_config.skip_pre_online {
-            self.start_service_without_condition();
+        if self.inner.broker_config().skip_pre_online {
+            self.start_service_without_condition().await;
         }
 
         let broker_out_api_inner = self.inner.clone();
@@ -1127,7 +1386,7 @@ impl BrokerRuntime {
             });
         info!(
             "Rocketmq Broker({} ----Rust) start success",
-            self.inner.broker_config.broker_identity.broker_name
+            self.inner.broker_config().broker_identity.broker_name
         );
         tokio::select! {
             _ = self.shutdown_rx.as_mut().unwrap().recv() => {
@@ -1140,7 +1399,10 @@ impl BrokerRuntime {
 
     pub(crate) fn schedule_send_heartbeat(&mut self) {}
 
-    pub(crate) fn start_service_without_condition(&mut self) {}
+    pub(crate) async fn start_service_without_condition(&mut self) {
+        let this = self.inner.clone();
+        self.inner.start_service_without_condition(this).await;
+    }
 
     /// Register broker to name remoting_server
     pub(crate) async fn register_broker_all(
@@ -1161,57 +1423,31 @@ impl BrokerRuntime {
 
     async fn do_register_broker_all(
         &mut self,
-        _check_order_config: bool,
+        check_order_config: bool,
         oneway: bool,
         topic_config_wrapper: TopicConfigAndMappingSerializeWrapper,
     ) {
-        let cluster_name = self
-            .inner
-            .broker_config
-            .broker_identity
-            .broker_cluster_name
-            .clone();
-        let broker_name = self.inner.broker_config.broker_identity.broker_name.clone();
-        let broker_addr = CheetahString::from_string(format!(
-            "{}:{}",
-            self.inner.broker_config.broker_ip1, self.inner.server_config.listen_port
-        ));
-        let broker_id = self.inner.broker_config.broker_identity.broker_id;
-        //  let weak = Arc::downgrade(&self.inner.broker_outer_api);
-
-        self.inner
-            .broker_outer_api
-            .register_broker_all(
-                cluster_name,
-                broker_addr.clone(),
-                broker_name,
-                broker_id,
-                broker_addr,
-                topic_config_wrapper,
-                vec![],
-                oneway,
-                10000,
-                false,
-                false,
-                None,
-                Default::default(),
-                self.inner.clone(),
-            )
-            .await;
+        BrokerRuntimeInner::<BoxedMessageStore>::do_register_broker_all(
+            self.inner.clone(),
+            check_order_config,
+            oneway,
+            topic_config_wrapper,
+        )
+        .await;
     }
 }
 
 impl<MS: MessageStore> BrokerRuntimeInner<MS> {
     pub async fn register_single_topic_all(&self, topic_config: TopicConfig) {
         let mut topic_config = topic_config;
-        if !PermName::is_writeable(self.broker_config.broker_permission)
-            || !PermName::is_readable(self.broker_config.broker_permission)
+        if !PermName::is_writeable(self.broker_config().broker_permission)
+            || !PermName::is_readable(self.broker_config().broker_permission)
         {
-            topic_config.perm &= self.broker_config.broker_permission;
+            topic_config.perm &= self.broker_config().broker_permission;
         }
         self.broker_outer_api
             .register_single_topic_all(
-                self.broker_config
+                self.broker_config()
                     .broker_identity
                     .broker_cluster_name
                     .clone(),
@@ -1273,7 +1509,7 @@ impl<MS: MessageStore> BrokerRuntimeInner<MS> {
 
     async fn do_register_broker_all(
         this: ArcMut<BrokerRuntimeInner<MS>>,
-        _check_order_config: bool,
+        check_order_config: bool,
         oneway: bool,
         topic_config_wrapper: TopicConfigAndMappingSerializeWrapper,
     ) {
@@ -1286,37 +1522,64 @@ impl<MS: MessageStore> BrokerRuntimeInner<MS> {
         }
 
         let cluster_name = this
-            .broker_config
+            .broker_config()
             .broker_identity
             .broker_cluster_name
             .clone();
-        let broker_name = this.broker_config.broker_identity.broker_name.clone();
+        let broker_name = this.broker_config().broker_identity.broker_name.clone();
         let broker_addr = CheetahString::from_string(format!(
             "{}:{}",
-            this.broker_config.broker_ip1, this.server_config.listen_port
+            this.broker_config().broker_ip1,
+            this.server_config.listen_port
         ));
-        let broker_id = this.broker_config.broker_identity.broker_id;
+        let broker_id = this.broker_config().broker_identity.broker_id;
+        let ha_server_addr = this.get_ha_server_addr();
         //let weak = Arc::downgrade(&self.broker_out_api);
-        this.broker_outer_api
+        let register_broker_result_list = this
+            .broker_outer_api
             .register_broker_all(
                 cluster_name,
-                broker_addr.clone(),
+                broker_addr,
                 broker_name,
                 broker_id,
-                broker_addr,
+                ha_server_addr,
                 topic_config_wrapper,
                 vec![],
                 oneway,
-                this.broker_config.register_broker_timeout_mills as u64,
-                this.broker_config.enable_slave_acting_master,
-                this.broker_config.compressed_register,
-                this.broker_config
+                this.broker_config().register_broker_timeout_mills as u64,
+                this.broker_config().enable_slave_acting_master,
+                this.broker_config().compressed_register,
+                this.broker_config()
                     .enable_slave_acting_master
-                    .then_some(this.broker_config.broker_not_active_timeout_millis),
+                    .then_some(this.broker_config().broker_not_active_timeout_millis),
                 Default::default(), //optimize
-                this.clone(),
             )
             .await;
+        if check_order_config {
+            if let Some(register_broker_result) = register_broker_result_list.first() {
+                this.topic_config_manager()
+                    .update_order_topic_config(&register_broker_result.kv_table);
+            }
+        }
+        if this.message_store_config.broker_role == BrokerRole::Slave {
+            if let Some(register_broker_result) = register_broker_result_list.first() {
+                let master_addr = &register_broker_result.master_addr;
+                this.slave_synchronize
+                    .set_master_addr((!master_addr.is_empty()).then(|| master_addr.clone()));
+                // a master address configured for the store is kept
+                let ha_server_addr = &register_broker_result.ha_server_addr;
+                let ha_master_configured = this
+                    .message_store_config
+                    .ha_master_address
+                    .as_ref()
+                    .is_some_and(|address| !address.is_empty());
+                if !ha_master_configured && !ha_server_addr.is_empty() {
+                    if let Some(message_store) = this.message_store.as_ref() {
+                        message_store.update_ha_master_address(ha_server_addr);
+                    }
+                }
+            }
+        }
     }
 }
 
@@ -1397,7 +1660,7 @@ pub(crate) struct BrokerRuntimeInner<MS> {
     shutdown: Arc<AtomicBool>,
     store_host: SocketAddr,
     broker_addr: CheetahString,
-    broker_config: BrokerConfig,
+    broker_config: SharedBrokerConfig,
     message_store_config: MessageStoreConfig,
     server_config: ServerConfig,
     topic_config_manager: Option<TopicConfigManager<MS>>,
@@ -1428,15 +1691,23 @@ pub(crate) struct BrokerRuntimeInner<MS> {
     topic_route_info_manager: Option<TopicRouteInfoManager<MS>>,
     escape_bridge: Option<EscapeBridge<MS>>,
     pop_inflight_message_counter: PopInflightMessageCounter,
-    replicas_manager: Option<ReplicasManager>,
+    replicas_manager: Option<ReplicasManager<MS>>,
     broker_fast_failure: BrokerFastFailure,
-    cold_data_pull_request_hold_service: Option<ColdDataPullRequestHoldService>,
+    request_executors: RequestExecutors,
+    broker_trace_dispatcher: Arc<BrokerTraceDispatcher>,
+    overload_shedder: OverloadShedder,
+    slow_request_log: SlowRequestLog,
+    cold_data_pull_request_hold_service: Option<ColdDataPullRequestHoldService<MS>>,
     cold_data_cg_ctr_service: Option<ColdDataCgCtrService>,
+    message_request_mode_manager: MessageRequestModeManager,
+    slave_synchronize: SlaveSynchronize,
+    dispatch_subscription_manager: DispatchSubscriptionManager,
+    ordering_checker: OrderingChecker,
 
     //Processor
     pop_message_processor: Option<ArcMut<PopMessageProcessor<MS>>>,
     ack_message_processor: Option<ArcMut<AckMessageProcessor<MS>>>,
-    notification_processor: Option<ArcMut<NotificationProcessor>>,
+    notification_processor: Option<ArcMut<NotificationProcessor<MS>>>,
 }
 
 impl<MS: MessageStore> BrokerRuntimeInner<MS> {
@@ -1445,11 +1716,6 @@ impl<MS: MessageStore> BrokerRuntimeInner<MS> {
         &mut self.store_host
     }
 
-    #[inline]
-    pub fn broker_config_mut(&mut self) -> &mut BrokerConfig {
-        &mut self.broker_config
-    }
-
     #[inline]
     pub fn message_store_config_mut(&mut self) -> &mut MessageStoreConfig {
         &mut self.message_store_config
@@ -1621,8 +1887,15 @@ impl<MS: MessageStore> BrokerRuntimeInner<MS> {
         self.store_host
     }
 
+    /// The current broker config, an update published later is not seen by the snapshot.
     #[inline]
-    pub fn broker_config(&self) -> &BrokerConfig {
+    pub fn broker_config(&self) -> Arc<BrokerConfig> {
+        self.broker_config.get()
+    }
+
+    /// Handle to update the broker config of every component of this broker.
+    #[inline]
+    pub fn shared_broker_config(&self) -> &SharedBrokerConfig {
         &self.broker_config
     }
 
@@ -1636,6 +1909,43 @@ impl<MS: MessageStore> BrokerRuntimeInner<MS> {
         &self.server_config
     }
 
+    /// Persists `target`, skipped when the manager was not created.
+    pub(crate) fn persist(&self, target: PersistTarget) {
+        match target {
+            PersistTarget::ConsumerOffset => self.consumer_offset_manager.persist(),
+            PersistTarget::TopicConfig => {
+                if let Some(topic_config_manager) = self.topic_config_manager.as_ref() {
+                    topic_config_manager.persist();
+                }
+            }
+            PersistTarget::SubscriptionGroup => {
+                if let Some(subscription_group_manager) = self.subscription_group_manager.as_ref() {
+                    subscription_group_manager.persist();
+                }
+            }
+            PersistTarget::ConsumerFilter => {
+                if let Some(consumer_filter_manager) = self.consumer_filter_manager.as_ref() {
+                    consumer_filter_manager.persist();
+                }
+            }
+            PersistTarget::ConsumerOrderInfo => {
+                if let Some(consumer_order_info_manager) = self.consumer_order_info_manager.as_ref()
+                {
+                    consumer_order_info_manager.persist();
+                }
+            }
+            PersistTarget::MessageRequestMode => self.message_request_mode_manager.persist(),
+        }
+    }
+
+    /// Persists every [`PersistTarget`], called on shutdown.
+    pub(crate) fn persist_all(&self) {
+        for target in PersistTarget::ALL {
+            self.persist(target);
+        }
+        info!("[Broker shutdown] broker managers persisted");
+    }
+
     #[inline]
     pub fn topic_config_manager(&self) -> &TopicConfigManager<MS> {
         self.topic_config_manager.as_ref().unwrap()
@@ -1676,6 +1986,30 @@ impl<MS: MessageStore> BrokerRuntimeInner<MS> {
         &self.broker_stats
     }
 
+    #[inline]
+    pub fn message_request_mode_manager(&self) -> &MessageRequestModeManager {
+        &self.message_request_mode_manager
+    }
+
+    #[inline]
+    pub fn replicas_manager(&self) -> Option<&ReplicasManager<MS>> {
+        self.replicas_manager.as_ref()
+    }
+
+    pub fn slave_synchronize(&self) -> &SlaveSynchronize {
+        &self.slave_synchronize
+    }
+
+    #[inline]
+    pub fn dispatch_subscription_manager(&self) -> &DispatchSubscriptionManager {
+        &self.dispatch_subscription_manager
+    }
+
+    #[inline]
+    pub(crate) fn ordering_checker(&self) -> &OrderingChecker {
+        &self.ordering_checker
+    }
+
     #[inline]
     pub fn schedule_message_service(&self) -> &ScheduleMessageService {
         &self.schedule_message_service
@@ -1736,6 +2070,28 @@ impl<MS: MessageStore> BrokerRuntimeInner<MS> {
         &self.pull_request_hold_service
     }
 
+    #[inline]
+    pub fn cold_data_pull_request_hold_service(
+        &self,
+    ) -> &Option<ColdDataPullRequestHoldService<MS>> {
+        &self.cold_data_pull_request_hold_service
+    }
+
+    #[inline]
+    pub fn cold_data_cg_ctr_service(&self) -> &Option<ColdDataCgCtrService> {
+        &self.cold_data_cg_ctr_service
+    }
+
+    #[inline]
+    pub fn pop_message_processor(&self) -> &Option<ArcMut<PopMessageProcessor<MS>>> {
+        &self.pop_message_processor
+    }
+
+    #[inline]
+    pub fn notification_processor(&self) -> &Option<ArcMut<NotificationProcessor<MS>>> {
+        &self.notification_processor
+    }
+
     #[inline]
     pub fn rebalance_lock_manager(&self) -> &RebalanceLockManager {
         &self.rebalance_lock_manager
@@ -1783,6 +2139,26 @@ impl<MS: MessageStore> BrokerRuntimeInner<MS> {
         &self.pop_inflight_message_counter
     }
 
+    #[inline]
+    pub(crate) fn overload_shedder(&self) -> &OverloadShedder {
+        &self.overload_shedder
+    }
+
+    #[inline]
+    pub(crate) fn slow_request_log(&self) -> &SlowRequestLog {
+        &self.slow_request_log
+    }
+
+    #[inline]
+    pub(crate) fn request_executors(&self) -> &RequestExecutors {
+        &self.request_executors
+    }
+
+    #[inline]
+    pub(crate) fn broker_trace_dispatcher(&self) -> &Arc<BrokerTraceDispatcher> {
+        &self.broker_trace_dispatcher
+    }
+
     #[inline]
     pub fn set_store_host(&mut self, store_host: SocketAddr) {
         self.store_host = store_host;
@@ -1790,7 +2166,8 @@ impl<MS: MessageStore> BrokerRuntimeInner<MS> {
 
     #[inline]
     pub fn set_broker_config(&mut self, broker_config: BrokerConfig) {
-        self.broker_config = broker_config;
+        self.broker_config
+            .modify(|current| *current = broker_config);
     }
 
     #[inline]
@@ -1993,13 +2370,13 @@ impl<MS: MessageStore> BrokerRuntimeInner<MS> {
     fn protect_broker(&mut self) {}
 
     async fn update_namesrv_addr_inner(&mut self) {
-        if self.broker_config.fetch_name_srv_addr_by_dns_lookup {
-            if let Some(namesrv_addr) = &self.broker_config.namesrv_addr {
+        if self.broker_config().fetch_name_srv_addr_by_dns_lookup {
+            if let Some(namesrv_addr) = &self.broker_config().namesrv_addr {
                 self.broker_outer_api
                     .update_name_server_address_list_by_dns_lookup(namesrv_addr.clone())
                     .await;
             }
-        } else if let Some(namesrv_addr) = &self.broker_config.namesrv_addr {
+        } else if let Some(namesrv_addr) = &self.broker_config().namesrv_addr {
             self.broker_outer_api
                 .update_name_server_address_list(namesrv_addr.clone())
                 .await;
@@ -2017,19 +2394,20 @@ impl<MS: MessageStore> BrokerRuntimeInner<MS> {
         let mut topic_config_table = HashMap::new();
         let table = self.topic_config_manager().topic_config_table();
         for topic_config in table.lock().values() {
-            let new_topic_config = if !PermName::is_writeable(self.broker_config.broker_permission)
-                || !PermName::is_readable(self.broker_config.broker_permission)
-            {
-                TopicConfig {
-                    topic_name: topic_config.topic_name.clone(),
-                    read_queue_nums: topic_config.read_queue_nums,
-                    write_queue_nums: topic_config.write_queue_nums,
-                    perm: topic_config.perm & self.broker_config.broker_permission,
-                    ..TopicConfig::default()
-                }
-            } else {
-                topic_config.clone()
-            };
+            let new_topic_config =
+                if !PermName::is_writeable(self.broker_config().broker_permission)
+                    || !PermName::is_readable(self.broker_config().broker_permission)
+                {
+                    TopicConfig {
+                        topic_name: topic_config.topic_name.clone(),
+                        read_queue_nums: topic_config.read_queue_nums,
+                        write_queue_nums: topic_config.write_queue_nums,
+                        perm: topic_config.perm & self.broker_config().broker_permission,
+                        ..TopicConfig::default()
+                    }
+                } else {
+                    topic_config.clone()
+                };
             topic_config_table.insert(
                 new_topic_config.topic_name.as_ref().unwrap().clone(),
                 new_topic_config,
@@ -2037,8 +2415,8 @@ impl<MS: MessageStore> BrokerRuntimeInner<MS> {
         }
 
         // Handle split registration logic
-        if self.broker_config.enable_split_registration
-            && topic_config_table.len() as i32 >= self.broker_config.split_registration_size
+        if self.broker_config().enable_split_registration
+            && topic_config_table.len() as i32 >= self.broker_config().split_registration_size
         {
             let topic_config_wrapper = this
                 .topic_config_manager()
@@ -2074,24 +2452,9 @@ impl<MS: MessageStore> BrokerRuntimeInner<MS> {
                 topic_queue_mapping_info_map,
             );
 
-        if self.broker_config.enable_split_registration
+        if self.broker_config().enable_split_registration
             || force_register
-            || need_register(
-                self.broker_config
-                    .broker_identity
-                    .broker_cluster_name
-                    .clone()
-                    .as_str(),
-                self.broker_config.broker_ip1.clone().as_str(),
-                self.broker_config
-                    .broker_identity
-                    .broker_name
-                    .clone()
-                    .as_str(),
-                self.broker_config.broker_identity.broker_id,
-                self.broker_config.register_broker_timeout_mills,
-                self.broker_config.is_in_broker_container,
-            )
+            || self.need_register(&topic_config_wrapper).await
         {
             BrokerRuntimeInner::<MS>::do_register_broker_all(
                 this,
@@ -2103,60 +2466,99 @@ impl<MS: MessageStore> BrokerRuntimeInner<MS> {
         }
     }
 
-    async fn do_register_broker_all_inner(
-        this: ArcMut<BrokerRuntimeInner<MS>>,
-        _check_order_config: bool,
-        oneway: bool,
-        topic_config_wrapper: TopicConfigAndMappingSerializeWrapper,
-    ) {
-        let cluster_name = this
-            .broker_config
-            .broker_identity
-            .broker_cluster_name
-            .clone();
-        let broker_name = this.broker_config.broker_identity.broker_name.clone();
-        let broker_addr = CheetahString::from_string(format!(
+    pub fn get_broker_addr(&self) -> &CheetahString {
+        &self.broker_addr
+    }
+
+    /// Address the HA service of this broker listens on for replicating brokers.
+    pub fn get_ha_server_addr(&self) -> CheetahString {
+        CheetahString::from_string(format!(
             "{}:{}",
-            this.broker_config.broker_ip1, this.server_config.listen_port
-        ));
-        let broker_id = this.broker_config.broker_identity.broker_id;
-        //  let weak = Arc::downgrade(&self.inner.broker_outer_api);
-        let this_ = this.clone();
-        this.broker_outer_api
-            .register_broker_all(
-                cluster_name,
-                broker_addr.clone(),
-                broker_name,
-                broker_id,
-                broker_addr,
+            self.broker_config()
+                .broker_ip2
+                .as_ref()
+                .unwrap_or(&self.broker_config().broker_ip1),
+            self.message_store_config.ha_listen_port
+        ))
+    }
+
+    /// Whether any name server holds an older topic config of this broker than
+    /// `topic_config_wrapper`.
+    async fn need_register(
+        &self,
+        topic_config_wrapper: &TopicConfigAndMappingSerializeWrapper,
+    ) -> bool {
+        let broker_identity = &self.broker_config().broker_identity;
+        self.broker_outer_api
+            .need_register(
+                &broker_identity.broker_cluster_name,
+                self.get_broker_addr(),
+                &broker_identity.broker_name,
+                broker_identity.broker_id,
                 topic_config_wrapper,
-                vec![],
-                oneway,
-                10000,
-                false,
-                false,
-                None,
-                Default::default(),
-                this_,
+                self.broker_config().register_broker_timeout_mills as u64,
             )
-            .await;
+            .await
+            .into_iter()
+            .any(|changed| changed)
+    }
+    /// Refreshes the brokers sharing this broker's name from the name servers and the number of
+    /// alive replicas of the message store with it.
+    pub async fn sync_broker_member_group(&mut self) {
+        let broker_identity = &self.broker_config().broker_identity;
+        let broker_member_group = match self
+            .broker_outer_api
+            .sync_broker_member_group(
+                &broker_identity.broker_cluster_name,
+                &broker_identity.broker_name,
+            )
+            .await
+        {
+            Ok(broker_member_group) => broker_member_group,
+            Err(e) => {
+                error!("syncBrokerMemberGroup from namesrv failed, {}", e);
+                return;
+            }
+        };
+        let Some(mut broker_member_group) =
+            broker_member_group.filter(|group| !group.broker_addrs.is_empty())
+        else {
+            warn!(
+                "Couldn't find any broker member from namesrv in {}/{}",
+                broker_identity.broker_cluster_name, broker_identity.broker_name
+            );
+            return;
+        };
+        // this broker is alive even when the name servers have not seen it yet
+        broker_member_group
+            .broker_addrs
+            .entry(broker_identity.broker_id)
+            .or_insert_with(|| self.broker_config().get_broker_addr().into());
+        if let Some(message_store) = self.message_store.as_mut() {
+            message_store
+                .set_alive_replica_num_in_group(broker_member_group.broker_addrs.len() as i32);
+        }
+        if broker_member_group != self.broker_member_group {
+            info!(
+                "broker member group of {} changed to {:?}",
+                broker_member_group.broker_name, broker_member_group.broker_addrs
+            );
+            self.broker_member_group = broker_member_group;
+        }
     }
 
-    pub fn get_broker_addr(&self) -> &CheetahString {
-        &self.broker_addr
-    }
-    pub fn sync_broker_member_group(&self) {
-        warn!("sync_broker_member_group not implemented");
+    /// Takes the broker out of isolation and announces it to the name servers.
+    pub(crate) async fn start_service_without_condition(
+        &self,
+        this: ArcMut<BrokerRuntimeInner<MS>>,
+    ) {
+        info!(
+            "{}-{} start service",
+            self.broker_config().broker_identity.broker_name,
+            self.broker_config().broker_identity.broker_id
+        );
+        self.is_isolated.store(false, Ordering::Release);
+        self.register_broker_all_inner(this, true, false, true)
+            .await;
     }
 }
-
-fn need_register(
-    _cluster_name: &str,
-    _broker_addr: &str,
-    _broker_name: &str,
-    _broker_id: u64,
-    _register_timeout_mills: i32,
-    _in_broker_container: bool,
-) -> bool {
-    unimplemented!()
-}
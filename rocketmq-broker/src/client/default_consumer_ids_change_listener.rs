@@ -15,19 +15,159 @@
  * limitations under the License.
  */
 use std::any::Any;
+use std::sync::Arc;
+use std::time::Duration;
 
+use cheetah_string::CheetahString;
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::TimeUtils::get_current_millis;
+use rocketmq_remoting::net::channel::Channel;
 use tracing::warn;
 
 use crate::client::consumer_group_event::ConsumerGroupEvent;
 use crate::client::consumer_ids_change_listener::ConsumerIdsChangeListener;
+use crate::client::net::broker_to_client::Broker2Client;
 
+/// Pushes `NOTIFY_CONSUMER_IDS_CHANGED` to the clients of a group on every
+/// [`ConsumerGroupEvent::Change`], so that they rebalance without waiting for their next
+/// rebalance round.
+///
+/// A group is notified at most once per `notify_consumer_ids_changed_min_interval_millis`,
+/// changes within that window are coalesced into one notify at its end that goes to the
+/// channels of the latest change.
 #[derive(Default)]
-pub struct DefaultConsumerIdsChangeListener {}
+pub struct DefaultConsumerIdsChangeListener {
+    notify_enable: bool,
+    min_interval_millis: u64,
+    broker2client: Broker2Client,
+    last_notified: Arc<DashMap<CheetahString, u64>>,
+    pending: Arc<DashMap<CheetahString, Vec<Channel>>>,
+}
+
+impl DefaultConsumerIdsChangeListener {
+    pub fn new(broker_config: &BrokerConfig) -> Self {
+        Self {
+            notify_enable: broker_config.notify_consumer_ids_changed_enable,
+            min_interval_millis: broker_config.notify_consumer_ids_changed_min_interval_millis,
+            ..Default::default()
+        }
+    }
+
+    fn notify_consumer_ids_changed(&self, group: &str, channels: Vec<Channel>) {
+        let group = CheetahString::from(group);
+        let now = get_current_millis();
+        let next_allowed = self
+            .last_notified
+            .get(&group)
+            .map(|last| *last + self.min_interval_millis)
+            .unwrap_or(now);
+        match self.pending.entry(group.clone()) {
+            // the scheduled notify goes to the channels of the latest change
+            Entry::Occupied(mut pending) => {
+                pending.insert(channels);
+            }
+            Entry::Vacant(_) if next_allowed <= now => {
+                self.last_notified.insert(group.clone(), now);
+                let broker2client = self.broker2client.clone();
+                tokio::spawn(async move {
+                    notify_channels(&broker2client, &group, channels).await;
+                });
+            }
+            Entry::Vacant(pending) => {
+                pending.insert(channels);
+                let broker2client = self.broker2client.clone();
+                let last_notified = self.last_notified.clone();
+                let pending = self.pending.clone();
+                tokio::spawn(async move {
+                    tokio::time::sleep(Duration::from_millis(next_allowed - now)).await;
+                    if let Some((_, channels)) = pending.remove(&group) {
+                        last_notified.insert(group.clone(), get_current_millis());
+                        notify_channels(&broker2client, &group, channels).await;
+                    }
+                });
+            }
+        }
+    }
+}
+
+async fn notify_channels(
+    broker2client: &Broker2Client,
+    group: &CheetahString,
+    channels: Vec<Channel>,
+) {
+    for mut channel in channels {
+        if let Err(e) = broker2client
+            .notify_consumer_ids_changed(&mut channel, group)
+            .await
+        {
+            warn!(
+                "notify consumer ids changed of group {} to {} failed: {}",
+                group,
+                channel.remote_address(),
+                e
+            );
+        }
+    }
+}
 
 impl ConsumerIdsChangeListener for DefaultConsumerIdsChangeListener {
-    fn handle(&self, _event: ConsumerGroupEvent, _group: &str, _args: &[&dyn Any]) {}
+    fn handle(&self, event: ConsumerGroupEvent, group: &str, args: &[&dyn Any]) {
+        match event {
+            ConsumerGroupEvent::Change => {
+                if !self.notify_enable {
+                    return;
+                }
+                if let Some(channels) = args
+                    .first()
+                    .and_then(|channels| channels.downcast_ref::<Vec<Channel>>())
+                {
+                    self.notify_consumer_ids_changed(group, channels.clone());
+                }
+            }
+            ConsumerGroupEvent::Unregister => {
+                self.last_notified.remove(group);
+                self.pending.remove(group);
+            }
+            _ => {}
+        }
+    }
 
     fn shutdown(&self) {
-        warn!("DefaultConsumerIdsChangeListener shutdown not implemented");
+        self.pending.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listener(min_interval_millis: u64) -> DefaultConsumerIdsChangeListener {
+        DefaultConsumerIdsChangeListener::new(&BrokerConfig {
+            notify_consumer_ids_changed_min_interval_millis: min_interval_millis,
+            ..Default::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn coalesces_changes_within_the_interval() {
+        let listener = listener(50);
+        let channels: Vec<Channel> = Vec::new();
+        listener.handle(ConsumerGroupEvent::Change, "group", &[&channels]);
+        assert!(listener.last_notified.contains_key("group"));
+        assert!(listener.pending.is_empty());
+
+        listener.handle(ConsumerGroupEvent::Change, "group", &[&channels]);
+        listener.handle(ConsumerGroupEvent::Change, "group", &[&channels]);
+        assert_eq!(listener.pending.len(), 1);
+        listener.handle(ConsumerGroupEvent::Change, "other", &[&channels]);
+        assert_eq!(listener.pending.len(), 1);
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(listener.pending.is_empty());
+
+        listener.handle(ConsumerGroupEvent::Unregister, "group", &[]);
+        assert!(!listener.last_notified.contains_key("group"));
     }
 }
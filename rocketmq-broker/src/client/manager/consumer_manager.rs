@@ -24,10 +24,13 @@ use cheetah_string::CheetahString;
 use parking_lot::RwLock;
 use rocketmq_common::common::broker::broker_config::BrokerConfig;
 use rocketmq_common::common::consumer::consume_from_where::ConsumeFromWhere;
+use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_remoting::protocol::heartbeat::consume_type::ConsumeType;
 use rocketmq_remoting::protocol::heartbeat::message_model::MessageModel;
 use rocketmq_remoting::protocol::heartbeat::subscription_data::SubscriptionData;
 use rocketmq_store::stats::broker_stats_manager::BrokerStatsManager;
+use tracing::info;
+use tracing::warn;
 
 use crate::client::client_channel_info::ClientChannelInfo;
 use crate::client::consumer_group_event::ConsumerGroupEvent;
@@ -247,6 +250,147 @@ impl ConsumerManager {
         r1 || r2
     }
 
+    /// Removes `client_channel_info` from `group` on the client's request.
+    ///
+    /// Fires [`ConsumerGroupEvent::ClientUnregister`] for the client, and
+    /// [`ConsumerGroupEvent::Unregister`] when the group is left without clients. With
+    /// `is_notify_consumer_ids_changed_enable` the remaining clients are notified through
+    /// [`ConsumerGroupEvent::Change`].
+    pub fn unregister_consumer(
+        &self,
+        group: &CheetahString,
+        client_channel_info: &ClientChannelInfo,
+        is_notify_consumer_ids_changed_enable: bool,
+    ) {
+        let (topics, channels, group_removed) = {
+            let mut write_guard = self.consumer_table.write();
+            let Some(consumer_group_info) = write_guard.get(group) else {
+                return;
+            };
+            if !consumer_group_info.unregister_channel(client_channel_info) {
+                return;
+            }
+            let topics = consumer_group_info.get_subscribe_topics();
+            let channels = consumer_group_info.get_all_channels();
+            let group_removed = consumer_group_info.get_channel_info_table().is_empty();
+            if group_removed {
+                info!(
+                    "unregister consumer ok, no any connection, and remove consumer group, {}",
+                    group
+                );
+                write_guard.remove(group);
+            }
+            (topics, channels, group_removed)
+        };
+
+        self.call_consumer_ids_change_listener(
+            ConsumerGroupEvent::ClientUnregister,
+            group,
+            &[client_channel_info as &dyn Any, &topics as &dyn Any],
+        );
+        if group_removed {
+            self.call_consumer_ids_change_listener(ConsumerGroupEvent::Unregister, group, &[]);
+        }
+        if is_notify_consumer_ids_changed_enable {
+            self.call_consumer_ids_change_listener(
+                ConsumerGroupEvent::Change,
+                group,
+                &[&channels as &dyn Any],
+            );
+        }
+    }
+
+    /// Removes the consumers whose channel has not sent a heartbeat within the channel expired
+    /// timeout and returns them, so that their channels can be closed.
+    ///
+    /// Every removed client fires [`ConsumerGroupEvent::ClientUnregister`]. A group left without
+    /// clients is removed and fires [`ConsumerGroupEvent::Unregister`], a group that still has
+    /// clients fires [`ConsumerGroupEvent::Change`] with the remaining channels. Compensated
+    /// subscriptions not refreshed within the subscription expired timeout are dropped as well.
+    pub fn scan_not_active_channel(&self) -> Vec<ClientChannelInfo> {
+        let now = get_current_millis();
+        let mut removed = Vec::new();
+        let mut changed_groups = Vec::new();
+        let mut unregistered_groups = Vec::new();
+        self.consumer_table
+            .write()
+            .retain(|group, consumer_group_info| {
+                let expired = consumer_group_info
+                    .get_channel_info_table()
+                    .iter()
+                    .filter(|info| {
+                        now.saturating_sub(info.last_update_timestamp())
+                            > self.channel_expired_timeout
+                    })
+                    .map(|info| info.value().clone())
+                    .collect::<Vec<_>>();
+                if expired.is_empty() {
+                    return true;
+                }
+                for info in &expired {
+                    warn!(
+                        "ConsumerManager#scanNotActiveChannel: remove expired channel[{}] from \
+                         consumer group {}",
+                        info.channel().remote_address(),
+                        group
+                    );
+                    consumer_group_info.unregister_channel(info);
+                }
+                let topics = consumer_group_info.get_subscribe_topics();
+                removed.extend(
+                    expired
+                        .into_iter()
+                        .map(|info| (group.clone(), info, topics.clone())),
+                );
+                if consumer_group_info.get_channel_info_table().is_empty() {
+                    warn!(
+                        "ConsumerManager#scanNotActiveChannel: remove consumer group {} without \
+                         channels",
+                        group
+                    );
+                    unregistered_groups.push(group.clone());
+                    false
+                } else {
+                    changed_groups.push((group.clone(), consumer_group_info.get_all_channels()));
+                    true
+                }
+            });
+        self.consumer_compensation_table
+            .write()
+            .retain(|group, consumer_group_info| {
+                let idle = now.saturating_sub(consumer_group_info.get_last_update_timestamp());
+                if idle > self.subscription_expired_timeout {
+                    info!(
+                        "ConsumerManager#scanNotActiveChannel: remove expired compensated \
+                         subscriptions of consumer group {}",
+                        group
+                    );
+                    return false;
+                }
+                true
+            });
+
+        // the listeners may look the groups up again, so they are called without the lock
+        for (group, info, topics) in &removed {
+            self.call_consumer_ids_change_listener(
+                ConsumerGroupEvent::ClientUnregister,
+                group,
+                &[info as &dyn Any, topics as &dyn Any],
+            );
+        }
+        for group in &unregistered_groups {
+            self.call_consumer_ids_change_listener(ConsumerGroupEvent::Unregister, group, &[]);
+        }
+        for (group, channels) in &changed_groups {
+            self.call_consumer_ids_change_listener(
+                ConsumerGroupEvent::Change,
+                group,
+                &[channels as &dyn Any],
+            );
+        }
+        removed.into_iter().map(|(_, info, _)| info).collect()
+    }
+
     pub fn call_consumer_ids_change_listener(
         &self,
         event: ConsumerGroupEvent,
@@ -268,3 +412,92 @@ impl ConsumerManager {
         groups
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rocketmq_remoting::connection::Connection;
+    use rocketmq_remoting::net::channel::Channel;
+    use rocketmq_remoting::protocol::LanguageCode;
+    use rocketmq_rust::ArcMut;
+    use tokio::net::TcpListener;
+    use tokio::net::TcpStream;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct RecordingListener {
+        events: Arc<parking_lot::Mutex<Vec<String>>>,
+    }
+
+    impl ConsumerIdsChangeListener for RecordingListener {
+        fn handle(&self, event: ConsumerGroupEvent, group: &str, _args: &[&dyn Any]) {
+            self.events.lock().push(format!("{:?} {}", event, group));
+        }
+
+        fn shutdown(&self) {}
+    }
+
+    async fn client_channel_info(client_id: &str, last_update_timestamp: u64) -> ClientChannelInfo {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let channel = Channel::new(
+            stream.local_addr().unwrap(),
+            stream.peer_addr().unwrap(),
+            Connection::new(stream),
+            ArcMut::new(HashMap::new()),
+        );
+        let mut info = ClientChannelInfo::new(channel, client_id.into(), LanguageCode::RUST, 1);
+        info.set_last_update_timestamp(last_update_timestamp);
+        info
+    }
+
+    #[tokio::test]
+    async fn scan_not_active_channel_removes_expired_consumers_and_fires_events() {
+        let listener = RecordingListener::default();
+        let events = listener.events.clone();
+        let manager = ConsumerManager::new(Arc::new(Box::new(listener)), 5_000);
+        let (group_a, group_b) = ("GroupA".into(), "GroupB".into());
+        let now = get_current_millis();
+        for (group, client_id, last_update_timestamp) in [
+            (&group_a, "active", now),
+            (&group_a, "expired_a", now - 10_000),
+            (&group_b, "expired_b", now - 10_000),
+        ] {
+            manager.register_consumer(
+                group,
+                client_channel_info(client_id, last_update_timestamp).await,
+                ConsumeType::ConsumePassively,
+                MessageModel::Clustering,
+                ConsumeFromWhere::ConsumeFromLastOffset,
+                HashSet::new(),
+                false,
+            );
+        }
+        events.lock().clear();
+
+        let mut removed = manager
+            .scan_not_active_channel()
+            .iter()
+            .map(|info| info.client_id().to_string())
+            .collect::<Vec<_>>();
+        removed.sort();
+        assert_eq!(removed, ["expired_a", "expired_b"]);
+        let group_a_info = manager.get_consumer_group_info(&group_a).unwrap();
+        assert_eq!(group_a_info.get_all_client_ids(), ["active"]);
+        assert!(manager.get_consumer_group_info(&group_b).is_none());
+
+        let mut events = events.lock().clone();
+        events.sort();
+        assert_eq!(
+            events,
+            [
+                "Change GroupA",
+                "ClientUnregister GroupA",
+                "ClientUnregister GroupB",
+                "Unregister GroupB",
+            ]
+        );
+    }
+}
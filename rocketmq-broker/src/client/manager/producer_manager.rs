@@ -22,8 +22,11 @@ use std::sync::Arc;
 use cheetah_string::CheetahString;
 use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_remoting::net::channel::Channel;
+use rocketmq_remoting::protocol::body::producer_info::ProducerInfo;
+use rocketmq_remoting::protocol::body::producer_table_info::ProducerTableInfo;
 use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
 use tracing::info;
+use tracing::warn;
 
 use crate::client::client_channel_info::ClientChannelInfo;
 
@@ -115,6 +118,76 @@ impl ProducerManager {
         );
     }
 
+    /// Removes the producers whose channel has not sent a heartbeat within
+    /// `channel_expired_timeout` ms and returns them, so that their channels can be closed.
+    #[allow(clippy::mutable_key_type)]
+    pub fn scan_not_active_channel(&self, channel_expired_timeout: u64) -> Vec<ClientChannelInfo> {
+        let now = get_current_millis();
+        let mut removed = Vec::new();
+        self.group_channel_table
+            .lock()
+            .retain(|group, channel_table| {
+                channel_table.retain(|channel, info| {
+                    let idle = now.saturating_sub(info.last_update_timestamp());
+                    if idle <= channel_expired_timeout {
+                        return true;
+                    }
+                    warn!(
+                        "ProducerManager#scanNotActiveChannel: remove expired channel[{}] from \
+                         producer group {}, idle {} ms",
+                        channel.remote_address(),
+                        group,
+                        idle
+                    );
+                    removed.push(info.clone());
+                    false
+                });
+                if channel_table.is_empty() {
+                    info!(
+                        "ProducerManager#scanNotActiveChannel: remove producer group[{}] without \
+                         channels",
+                        group
+                    );
+                }
+                !channel_table.is_empty()
+            });
+        let mut client_channel_table = self.client_channel_table.lock();
+        for info in &removed {
+            if client_channel_table.get(info.client_id()) == Some(info.channel()) {
+                client_channel_table.remove(info.client_id());
+            }
+        }
+        removed
+    }
+
+    pub fn get_group_channel_infos(&self, group: &str) -> Option<Vec<ClientChannelInfo>> {
+        self.group_channel_table
+            .lock()
+            .get(group)
+            .map(|channel_table| channel_table.values().cloned().collect())
+    }
+
+    pub fn get_producer_table(&self) -> ProducerTableInfo {
+        let group_channel_table = self.group_channel_table.lock();
+        let mut data = HashMap::with_capacity(group_channel_table.len());
+        for (group, channel_table) in group_channel_table.iter() {
+            let producer_infos = channel_table
+                .values()
+                .map(|info| {
+                    ProducerInfo::new(
+                        info.client_id().clone(),
+                        info.channel().remote_address().to_string().into(),
+                        info.language(),
+                        info.version(),
+                        info.last_update_timestamp(),
+                    )
+                })
+                .collect();
+            data.insert(group.clone(), producer_infos);
+        }
+        ProducerTableInfo::new(data)
+    }
+
     pub fn find_channel(&self, client_id: &str) -> Option<Channel> {
         self.client_channel_table.lock().get(client_id).cloned()
     }
@@ -138,3 +211,56 @@ impl ProducerManager {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rocketmq_remoting::connection::Connection;
+    use rocketmq_remoting::protocol::LanguageCode;
+    use rocketmq_rust::ArcMut;
+    use tokio::net::TcpListener;
+    use tokio::net::TcpStream;
+
+    use super::*;
+
+    async fn client_channel_info(client_id: &str, last_update_timestamp: u64) -> ClientChannelInfo {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let channel = Channel::new(
+            stream.local_addr().unwrap(),
+            stream.peer_addr().unwrap(),
+            Connection::new(stream),
+            ArcMut::new(HashMap::new()),
+        );
+        let mut info = ClientChannelInfo::new(channel, client_id.into(), LanguageCode::RUST, 1);
+        info.set_last_update_timestamp(last_update_timestamp);
+        info
+    }
+
+    #[tokio::test]
+    async fn scan_not_active_channel_removes_expired_producers() {
+        let manager = ProducerManager::new();
+        let (group_a, group_b) = ("GroupA".into(), "GroupB".into());
+        let now = get_current_millis();
+        let active = client_channel_info("active", now).await;
+        let expired_a = client_channel_info("expired_a", now - 10_000).await;
+        let expired_b = client_channel_info("expired_b", now - 10_000).await;
+        manager.register_producer(&group_a, &active);
+        manager.register_producer(&group_a, &expired_a);
+        manager.register_producer(&group_b, &expired_b);
+
+        let mut removed = manager.scan_not_active_channel(5_000);
+        removed.sort_by(|a, b| a.client_id().cmp(b.client_id()));
+        assert_eq!(removed, [expired_a, expired_b]);
+        assert!(manager.group_online("GroupA".to_string()));
+        assert!(!manager.group_online("GroupB".to_string()));
+        let client_channel_table = manager.client_channel_table.lock();
+        assert!(client_channel_table.contains_key(&CheetahString::from("active")));
+        assert!(!client_channel_table.contains_key(&CheetahString::from("expired_a")));
+        assert!(!client_channel_table.contains_key(&CheetahString::from("expired_b")));
+        drop(client_channel_table);
+
+        assert!(manager.scan_not_active_channel(5_000).is_empty());
+    }
+}
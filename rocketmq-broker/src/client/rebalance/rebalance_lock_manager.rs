@@ -58,6 +58,17 @@ impl RebalanceLockManager {
         true
     }
 
+    /// Tries to lock a single message queue for `client_id`, renewing the lock if the client
+    /// already holds it and taking it over if the previous holder's lock has expired.
+    pub fn try_lock(&self, group: &str, mq: &MessageQueue, client_id: &str) -> bool {
+        if self.is_locked(group, mq, client_id) {
+            return true;
+        }
+        let mut mqs = HashSet::with_capacity(1);
+        mqs.insert(mq.clone());
+        !self.try_lock_batch(group, &mqs, client_id).is_empty()
+    }
+
     pub fn try_lock_batch(
         &self,
         group: &str,
@@ -290,6 +301,33 @@ mod rebalance_lock_manager_tests {
         assert!(manager.is_locked("test_group", &mq, "client_1"));
     }
 
+    #[test]
+    fn try_lock_renews_own_lock_and_rejects_other_clients() {
+        let manager = RebalanceLockManager::default();
+        let mq = MessageQueue::default();
+        assert!(manager.try_lock("test_group", &mq, "client_1"));
+        assert!(manager.try_lock("test_group", &mq, "client_1"));
+        assert!(!manager.try_lock("test_group", &mq, "client_2"));
+    }
+
+    #[test]
+    fn try_lock_takes_over_expired_lock() {
+        let manager = RebalanceLockManager::default();
+        let mq = MessageQueue::default();
+        assert!(manager.try_lock("test_group", &mq, "client_1"));
+        manager
+            .mq_lock_table
+            .read()
+            .get("test_group")
+            .and_then(|group| group.get(&mq))
+            .unwrap()
+            .last_update_timestamp
+            .store(0, std::sync::atomic::Ordering::Relaxed);
+        assert!(manager.is_lock_all_expired("test_group"));
+        assert!(manager.try_lock("test_group", &mq, "client_2"));
+        assert!(!manager.try_lock("test_group", &mq, "client_1"));
+    }
+
     #[test]
     fn is_locked_returns_false_for_unlocked_message_queue() {
         let manager = RebalanceLockManager::default();
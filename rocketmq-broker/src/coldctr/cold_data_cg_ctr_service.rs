@@ -14,22 +14,227 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::collections::HashMap;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicI64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
 
-use tracing::warn;
+use cheetah_string::CheetahString;
+use parking_lot::Mutex;
+use parking_lot::RwLock;
+use rocketmq_common::common::broker::shared_broker_config::SharedBrokerConfig;
+use rocketmq_common::common::mix_all::is_sys_consumer_group_for_no_cold_read_limit;
+use rocketmq_common::TimeUtils::get_current_millis;
+use rocketmq_store::config::message_store_config::MessageStoreConfig;
+use tokio::sync::Notify;
+use tracing::info;
 
-#[derive(Default)]
-pub struct ColdDataCgCtrService;
+/// Length of a cold read accounting window, the thresholds are bytes per window.
+const COLD_ACC_WINDOW_MILLIS: u64 = 5_000;
+/// Groups that read no cold data for this long are forgotten.
+const CG_COLD_ACC_RESIDE_TIMEOUT_MILLIS: u64 = 60_000;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct ColdAcc {
+    cold_acc: i64,
+    last_cold_read_time_millis: u64,
+}
+
+/// Accounts the cold (not page cached) commit log bytes read by every consumer group and decides
+/// whose pulls of cold data are flow controlled.
+///
+/// A group is limited once it read more than its threshold in the current window while the cold
+/// reads of all groups together load the disk beyond the global threshold, so warm traffic
+/// sharing the disk keeps its latency and cold reads are only slowed down when they compete.
+pub struct ColdDataCgCtrService {
+    broker_config: SharedBrokerConfig,
+    message_store_config: Arc<MessageStoreConfig>,
+    cg_cold_acc: Arc<Mutex<HashMap<CheetahString, ColdAcc>>>,
+    /// Per group thresholds overriding `cg_cold_read_threshold`.
+    cg_cold_threshold_config: RwLock<HashMap<CheetahString, i64>>,
+    global_acc: Arc<AtomicI64>,
+    /// Whether the last window ended beyond the global threshold.
+    global_cold_ctr: Arc<AtomicBool>,
+    shutdown: Arc<Notify>,
+}
 
 impl ColdDataCgCtrService {
+    pub fn new(
+        broker_config: SharedBrokerConfig,
+        message_store_config: Arc<MessageStoreConfig>,
+    ) -> Self {
+        Self {
+            broker_config,
+            message_store_config,
+            cg_cold_acc: Arc::new(Mutex::new(HashMap::new())),
+            cg_cold_threshold_config: RwLock::new(HashMap::new()),
+            global_acc: Arc::new(AtomicI64::new(0)),
+            global_cold_ctr: Arc::new(AtomicBool::new(false)),
+            shutdown: Arc::new(Notify::new()),
+        }
+    }
+
     pub fn start(&mut self) {
-        warn!("ColdDataCgCtrService started not implemented");
+        if !self.message_store_config.cold_data_flow_control_enable {
+            return;
+        }
+        let cg_cold_acc = self.cg_cold_acc.clone();
+        let global_acc = self.global_acc.clone();
+        let global_cold_ctr = self.global_cold_ctr.clone();
+        let global_threshold = self.broker_config.get().global_cold_read_threshold;
+        let shutdown = self.shutdown.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_millis(COLD_ACC_WINDOW_MILLIS));
+            interval.tick().await;
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = shutdown.notified() => {
+                        info!("ColdDataCgCtrService shutdown");
+                        break;
+                    }
+                }
+                let global = global_acc.swap(0, Ordering::Relaxed);
+                let cold = global > global_threshold;
+                if cold != global_cold_ctr.swap(cold, Ordering::Relaxed) {
+                    info!(
+                        "cold data flow control {}, {} bytes of cold data read in the last {}ms",
+                        if cold { "on" } else { "off" },
+                        global,
+                        COLD_ACC_WINDOW_MILLIS
+                    );
+                }
+                clear_cg_cold_acc(&mut cg_cold_acc.lock(), get_current_millis());
+            }
+        });
+    }
+
+    /// Adds `cold_data_to_acc` bytes of cold data read by `consumer_group`.
+    pub fn cold_acc(&self, consumer_group: &CheetahString, cold_data_to_acc: i64) {
+        if cold_data_to_acc <= 0 {
+            return;
+        }
+        self.global_acc
+            .fetch_add(cold_data_to_acc, Ordering::Relaxed);
+        let mut cg_cold_acc = self.cg_cold_acc.lock();
+        let acc = cg_cold_acc.entry(consumer_group.clone()).or_default();
+        acc.cold_acc += cold_data_to_acc;
+        acc.last_cold_read_time_millis = get_current_millis();
     }
 
-    pub fn is_cg_need_cold_data_flow_ctr(&self, _consumer_group: &str) -> bool {
-        false
+    /// Whether the cold reads of all groups are beyond `global_cold_read_threshold`.
+    pub fn is_global_cold_ctr(&self) -> bool {
+        self.global_cold_ctr.load(Ordering::Relaxed)
+            || self.global_acc.load(Ordering::Relaxed)
+                > self.broker_config.get().global_cold_read_threshold
+    }
+
+    pub fn is_cg_need_cold_data_flow_ctr(&self, consumer_group: &str) -> bool {
+        if !self.message_store_config.cold_data_flow_control_enable
+            || is_sys_consumer_group_for_no_cold_read_limit(consumer_group)
+            || !self.is_global_cold_ctr()
+        {
+            return false;
+        }
+        let cold_acc = match self.cg_cold_acc.lock().get(consumer_group) {
+            Some(acc) => acc.cold_acc,
+            None => return false,
+        };
+        cold_acc >= self.threshold(consumer_group)
+    }
+
+    pub fn add_or_update_group_config(&self, consumer_group: CheetahString, threshold: i64) {
+        self.cg_cold_threshold_config
+            .write()
+            .insert(consumer_group, threshold);
+    }
+
+    pub fn remove_group_config(&self, consumer_group: &str) {
+        self.cg_cold_threshold_config.write().remove(consumer_group);
+    }
+
+    fn threshold(&self, consumer_group: &str) -> i64 {
+        self.cg_cold_threshold_config
+            .read()
+            .get(consumer_group)
+            .copied()
+            .unwrap_or(self.broker_config.get().cg_cold_read_threshold)
     }
 
     pub fn shutdown(&mut self) {
-        warn!("ColdDataCgCtrService shutdown not implemented");
+        self.shutdown.notify_waiters();
+    }
+}
+
+/// Starts a new window for every group, forgetting the groups that stopped reading cold data.
+fn clear_cg_cold_acc(cg_cold_acc: &mut HashMap<CheetahString, ColdAcc>, now: u64) {
+    cg_cold_acc.retain(|_, acc| {
+        acc.cold_acc = 0;
+        now.saturating_sub(acc.last_cold_read_time_millis) < CG_COLD_ACC_RESIDE_TIMEOUT_MILLIS
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use rocketmq_common::common::broker::broker_config::BrokerConfig;
+
+    use super::*;
+
+    fn service(global_cold_read_threshold: i64) -> ColdDataCgCtrService {
+        let broker_config = BrokerConfig {
+            cg_cold_read_threshold: 100,
+            global_cold_read_threshold,
+            ..Default::default()
+        };
+        let message_store_config = MessageStoreConfig {
+            cold_data_flow_control_enable: true,
+            ..Default::default()
+        };
+        ColdDataCgCtrService::new(
+            SharedBrokerConfig::new(broker_config),
+            Arc::new(message_store_config),
+        )
+    }
+
+    #[test]
+    fn limits_groups_beyond_their_threshold_under_global_pressure() {
+        let service = service(150);
+        let group = CheetahString::from("group");
+        service.cold_acc(&group, 100);
+        // the disk still has room for cold reads
+        assert!(!service.is_cg_need_cold_data_flow_ctr("group"));
+
+        service.cold_acc(&CheetahString::from("other"), 60);
+        assert!(service.is_cg_need_cold_data_flow_ctr("group"));
+        assert!(!service.is_cg_need_cold_data_flow_ctr("other"));
+
+        service.add_or_update_group_config(group.clone(), 200);
+        assert!(!service.is_cg_need_cold_data_flow_ctr("group"));
+        service.remove_group_config("group");
+        assert!(service.is_cg_need_cold_data_flow_ctr("group"));
+    }
+
+    #[test]
+    fn clears_accumulated_cold_reads() {
+        let mut cg_cold_acc = HashMap::new();
+        cg_cold_acc.insert(
+            CheetahString::from("active"),
+            ColdAcc {
+                cold_acc: 10,
+                last_cold_read_time_millis: 100_000,
+            },
+        );
+        cg_cold_acc.insert(
+            CheetahString::from("idle"),
+            ColdAcc {
+                cold_acc: 10,
+                last_cold_read_time_millis: 1_000,
+            },
+        );
+        clear_cg_cold_acc(&mut cg_cold_acc, 110_000);
+        assert_eq!(cg_cold_acc.len(), 1);
+        assert_eq!(cg_cold_acc[&CheetahString::from("active")].cold_acc, 0);
     }
 }
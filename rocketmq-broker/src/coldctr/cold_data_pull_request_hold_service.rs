@@ -14,17 +14,115 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
-use tracing::warn;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use rocketmq_common::TimeUtils::get_current_millis;
+use rocketmq_rust::ArcMut;
+use rocketmq_store::log_file::MessageStore;
+use tokio::sync::Notify;
+use tracing::info;
+
+use crate::broker_runtime::BrokerRuntimeInner;
+use crate::long_polling::pull_request::PullRequest;
+use crate::processor::pull_message_processor::PullMessageProcessor;
 
 pub const NO_SUSPEND_KEY: &str = "_noSuspend_";
-#[derive(Default)]
-pub struct ColdDataPullRequestHoldService {}
+/// How often the held requests are checked for the end of their hold.
+const COLD_HOLD_CHECK_INTERVAL_MILLIS: u64 = 100;
+
+/// Holds pulls of cold data of flow controlled consumer groups for `cold_hold_timeout_millis`
+/// before serving them, so their disk reads wait instead of slowing down warm pulls.
+///
+/// A released request is marked with [`NO_SUSPEND_KEY`] and is served even if its group is still
+/// flow controlled, a request is held at most once.
+pub struct ColdDataPullRequestHoldService<MS> {
+    pull_request_cold_hold_queue: Mutex<VecDeque<PullRequest>>,
+    pull_message_processor: ArcMut<PullMessageProcessor<MS>>,
+    shutdown: Notify,
+}
 
-impl ColdDataPullRequestHoldService {
-    pub fn start(&mut self) {
-        warn!("ColdDataPullRequestHoldService started not implemented");
+impl<MS> ColdDataPullRequestHoldService<MS>
+where
+    MS: MessageStore + Send + Sync + 'static,
+{
+    pub fn new(pull_message_processor: ArcMut<PullMessageProcessor<MS>>) -> Self {
+        Self {
+            pull_request_cold_hold_queue: Mutex::new(VecDeque::new()),
+            pull_message_processor,
+            shutdown: Notify::new(),
+        }
     }
+
+    pub fn start(&mut self, this: ArcMut<BrokerRuntimeInner<MS>>) {
+        if !this.message_store_config().cold_data_flow_control_enable {
+            return;
+        }
+        tokio::spawn(async move {
+            let Some(service) = this.cold_data_pull_request_hold_service().as_ref() else {
+                return;
+            };
+            loop {
+                tokio::select! {
+                    _ = tokio::time::sleep(Duration::from_millis(COLD_HOLD_CHECK_INTERVAL_MILLIS)) => {}
+                    _ = service.shutdown.notified() => {
+                        info!("ColdDataPullRequestHoldService shutdown");
+                        break;
+                    }
+                }
+                service.check_cold_data_pull_request(
+                    get_current_millis(),
+                    this.broker_config().cold_hold_timeout_millis,
+                );
+            }
+        });
+    }
+
     pub fn shutdown(&mut self) {
-        warn!("ColdDataPullRequestHoldService shutdown not implemented");
+        self.shutdown.notify_waiters();
     }
+
+    /// Holds `pull_request`, it is served once its hold is over.
+    pub fn suspend_cold_data_read_request(&self, pull_request: PullRequest) {
+        self.pull_request_cold_hold_queue
+            .lock()
+            .push_back(pull_request);
+    }
+
+    pub fn hold_request_num(&self) -> usize {
+        self.pull_request_cold_hold_queue.lock().len()
+    }
+
+    fn check_cold_data_pull_request(&self, now: u64, cold_hold_timeout_millis: u64) {
+        let expired = take_expired(
+            &mut self.pull_request_cold_hold_queue.lock(),
+            now,
+            cold_hold_timeout_millis,
+        );
+        for mut pull_request in expired {
+            pull_request
+                .request_command_mut()
+                .add_ext_field(NO_SUSPEND_KEY, "1");
+            self.pull_message_processor.execute_request_when_wakeup(
+                self.pull_message_processor.clone(),
+                pull_request.client_channel().clone(),
+                pull_request.connection_handler_context().clone(),
+                pull_request.request_command().clone(),
+            );
+        }
+    }
+}
+
+/// Removes the requests whose hold is over, requests are held in the order they arrived.
+fn take_expired(
+    queue: &mut VecDeque<PullRequest>,
+    now: u64,
+    cold_hold_timeout_millis: u64,
+) -> Vec<PullRequest> {
+    let expired = queue
+        .iter()
+        .take_while(|request| request.suspend_timestamp() + cold_hold_timeout_millis <= now)
+        .count();
+    queue.drain(..expired).collect()
 }
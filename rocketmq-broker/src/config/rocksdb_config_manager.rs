@@ -0,0 +1,371 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::OnceLock;
+
+use cheetah_string::CheetahString;
+use rocketmq_common::common::config_manager::ConfigManager;
+use rocketmq_remoting::protocol::DataVersion;
+use rocksdb::ColumnFamilyDescriptor;
+use rocksdb::Error;
+use rocksdb::IteratorMode;
+use rocksdb::Options;
+use rocksdb::WriteBatch;
+use rocksdb::WriteOptions;
+use rocksdb::DB;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
+
+pub(crate) const DEFAULT_CF: &str = "default";
+
+const DATA_VERSION_CF: &str = "kvDataVersion";
+const DATA_VERSION_KEY: &[u8] = b"kvDataVersion";
+
+/// Where a config manager keeps its RocksDB once it is loaded, left empty when the JSON file is
+/// used.
+pub(crate) type RocksDBConfigSlot = Arc<OnceLock<RocksDBConfigManager>>;
+
+/// The tables of a config manager as key values, so that they are kept in RocksDB one entry per
+/// key instead of in one JSON file rewritten on every persist.
+pub(crate) trait KvConfig: ConfigManager {
+    /// Column families of the tables.
+    const COLUMN_FAMILIES: &'static [&'static str] = &[DEFAULT_CF];
+
+    /// Whether the tables are kept in RocksDB, that is storeType is RocksDB.
+    fn rocksdb_enabled(&self) -> bool;
+
+    fn rocksdb_config_path(&self) -> String;
+
+    /// Every entry of every table, by column family.
+    fn encode_kv(&self) -> Vec<(&'static str, HashMap<CheetahString, Vec<u8>>)>;
+
+    fn decode_kv(&self, column_family: &str, key: &str, value: &[u8]);
+
+    fn kv_data_version(&self) -> DataVersion;
+
+    fn set_kv_data_version(&self, data_version: &DataVersion);
+}
+
+/// The RocksDB of one config manager.
+///
+/// A persist writes the entries that changed since the previous one and deletes the removed
+/// ones, together with the data version, in one write batch, so that RocksDB holds either the
+/// tables before or after it.
+#[derive(Clone)]
+pub(crate) struct RocksDBConfigManager {
+    db: Arc<DB>,
+    // hashes of the values last written, by column family and key
+    written: Arc<parking_lot::Mutex<HashMap<(&'static str, CheetahString), u64>>>,
+}
+
+fn value_hash(value: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl RocksDBConfigManager {
+    pub(crate) fn open(store_path: &str, column_families: &[&str]) -> Result<Self, Error> {
+        if let Err(e) = fs::create_dir_all(store_path) {
+            warn!("create RocksDB config dir {} failed, {}", store_path, e);
+        }
+        let mut options = Options::default();
+        options.create_if_missing(true);
+        options.create_missing_column_families(true);
+        let descriptors = column_families
+            .iter()
+            .chain(std::iter::once(&DATA_VERSION_CF))
+            .map(|name| ColumnFamilyDescriptor::new(*name, Options::default()));
+        let db = DB::open_cf_descriptors(&options, store_path, descriptors)?;
+        Ok(Self {
+            db: Arc::new(db),
+            written: Default::default(),
+        })
+    }
+
+    /// The data version of the last persist, `None` when nothing was persisted yet.
+    pub(crate) fn data_version(&self) -> Result<Option<DataVersion>, Error> {
+        let cf = self
+            .db
+            .cf_handle(DATA_VERSION_CF)
+            .expect("data version column family");
+        Ok(self
+            .db
+            .get_cf(cf, DATA_VERSION_KEY)?
+            .and_then(|value| serde_json::from_slice(&value).ok()))
+    }
+
+    /// Decodes every entry into `manager`, returns how many there were.
+    pub(crate) fn load_into<M: KvConfig>(&self, manager: &M) -> Result<usize, Error> {
+        let mut written = self.written.lock();
+        let mut loaded = 0;
+        for column_family in M::COLUMN_FAMILIES {
+            let cf = self
+                .db
+                .cf_handle(column_family)
+                .expect("config column family");
+            for item in self.db.iterator_cf(cf, IteratorMode::Start) {
+                let (key, value) = item?;
+                let key = String::from_utf8_lossy(&key);
+                manager.decode_kv(column_family, &key, &value);
+                written.insert(
+                    (*column_family, CheetahString::from(key.as_ref())),
+                    value_hash(&value),
+                );
+                loaded += 1;
+            }
+        }
+        if let Some(data_version) = self.data_version()? {
+            manager.set_kv_data_version(&data_version);
+        }
+        Ok(loaded)
+    }
+
+    /// Writes what changed in `manager` since the previous persist, returns how many entries
+    /// were put or deleted.
+    pub(crate) fn persist<M: KvConfig>(&self, manager: &M) -> Result<usize, Error> {
+        let mut written = self.written.lock();
+        let mut batch = WriteBatch::default();
+        let mut next_written = HashMap::with_capacity(written.len());
+        let mut changed = 0;
+        for (column_family, entries) in manager.encode_kv() {
+            let cf = self
+                .db
+                .cf_handle(column_family)
+                .expect("config column family");
+            for (key, value) in entries {
+                let hash = value_hash(&value);
+                let written_key = (column_family, key);
+                if written.remove(&written_key) != Some(hash) {
+                    batch.put_cf(cf, written_key.1.as_bytes(), &value);
+                    changed += 1;
+                }
+                next_written.insert(written_key, hash);
+            }
+        }
+        // what is left was removed from the tables
+        for (column_family, key) in written.keys() {
+            let cf = self
+                .db
+                .cf_handle(column_family)
+                .expect("config column family");
+            batch.delete_cf(cf, key.as_bytes());
+            changed += 1;
+        }
+        let data_version = serde_json::to_vec(&manager.kv_data_version()).unwrap_or_default();
+        let cf = self
+            .db
+            .cf_handle(DATA_VERSION_CF)
+            .expect("data version column family");
+        batch.put_cf(cf, DATA_VERSION_KEY, data_version);
+        let mut write_options = WriteOptions::default();
+        write_options.set_sync(true);
+        self.db.write_opt(batch, &write_options)?;
+        *written = next_written;
+        Ok(changed)
+    }
+}
+
+/// Loads `manager` from RocksDB when it is enabled, from the JSON file otherwise.
+///
+/// The first start with RocksDB imports the JSON file into it, and the first start without
+/// RocksDB after it was used exports it to the JSON file, so that the store type can be switched
+/// either way.
+pub(crate) fn load_kv<M: KvConfig>(manager: &M, slot: &RocksDBConfigSlot) -> bool {
+    let rocksdb_path = manager.rocksdb_config_path();
+    if !manager.rocksdb_enabled() {
+        let json_path = manager.config_file_path();
+        if Path::new(&json_path).exists() || !Path::new(&rocksdb_path).exists() {
+            return manager.load_json();
+        }
+        return match RocksDBConfigManager::open(&rocksdb_path, M::COLUMN_FAMILIES)
+            .and_then(|rocksdb| rocksdb.load_into(manager))
+        {
+            Ok(loaded) => {
+                manager.persist_json();
+                info!(
+                    "exported {} entries of {} to {}",
+                    loaded, rocksdb_path, json_path
+                );
+                true
+            }
+            Err(e) => {
+                error!("export {} to JSON failed, {}", rocksdb_path, e);
+                false
+            }
+        };
+    }
+    let rocksdb = match RocksDBConfigManager::open(&rocksdb_path, M::COLUMN_FAMILIES) {
+        Ok(rocksdb) => rocksdb,
+        Err(e) => {
+            error!("open RocksDB config {} failed, {}", rocksdb_path, e);
+            return false;
+        }
+    };
+    let result = match rocksdb.data_version() {
+        Ok(Some(_)) => rocksdb.load_into(manager).map(|loaded| {
+            info!("load {} entries from {} OK", loaded, rocksdb_path);
+        }),
+        Ok(None) if manager.load_json() => rocksdb.persist(manager).map(|imported| {
+            info!(
+                "imported {} entries of {} to {}",
+                imported,
+                manager.config_file_path(),
+                rocksdb_path
+            );
+        }),
+        Ok(None) => return false,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        error!("load RocksDB config {} failed, {}", rocksdb_path, e);
+        return false;
+    }
+    let _ = slot.set(rocksdb);
+    true
+}
+
+/// Persists `manager` to its RocksDB once it is loaded, to the JSON file otherwise.
+pub(crate) fn persist_kv<M: KvConfig>(manager: &M, slot: &RocksDBConfigSlot) {
+    match slot.get() {
+        Some(rocksdb) => {
+            if let Err(e) = rocksdb.persist(manager) {
+                error!(
+                    "persist RocksDB config {} failed, {}",
+                    manager.rocksdb_config_path(),
+                    e
+                );
+            }
+        }
+        None => manager.persist_json(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    struct TestConfig {
+        dir: PathBuf,
+        rocksdb_enabled: bool,
+        table: parking_lot::Mutex<HashMap<CheetahString, i64>>,
+        data_version: parking_lot::Mutex<DataVersion>,
+    }
+
+    impl TestConfig {
+        fn new(dir: &Path, rocksdb_enabled: bool) -> Self {
+            Self {
+                dir: dir.to_path_buf(),
+                rocksdb_enabled,
+                table: Default::default(),
+                data_version: Default::default(),
+            }
+        }
+    }
+
+    impl ConfigManager for TestConfig {
+        fn config_file_path(&self) -> String {
+            self.dir.join("test.json").to_string_lossy().into_owned()
+        }
+
+        fn encode_pretty(&self, _pretty_format: bool) -> String {
+            serde_json::to_string(&*self.table.lock()).unwrap()
+        }
+
+        fn decode(&self, json_string: &str) {
+            if let Ok(table) = serde_json::from_str(json_string) {
+                *self.table.lock() = table;
+            }
+        }
+    }
+
+    impl KvConfig for TestConfig {
+        fn rocksdb_enabled(&self) -> bool {
+            self.rocksdb_enabled
+        }
+
+        fn rocksdb_config_path(&self) -> String {
+            self.dir.join("test").to_string_lossy().into_owned()
+        }
+
+        fn encode_kv(&self) -> Vec<(&'static str, HashMap<CheetahString, Vec<u8>>)> {
+            let entries = self
+                .table
+                .lock()
+                .iter()
+                .map(|(key, value)| (key.clone(), value.to_string().into_bytes()))
+                .collect();
+            vec![(DEFAULT_CF, entries)]
+        }
+
+        fn decode_kv(&self, _column_family: &str, key: &str, value: &[u8]) {
+            let value = String::from_utf8_lossy(value).parse().unwrap();
+            self.table.lock().insert(key.into(), value);
+        }
+
+        fn kv_data_version(&self) -> DataVersion {
+            self.data_version.lock().clone()
+        }
+
+        fn set_kv_data_version(&self, data_version: &DataVersion) {
+            self.data_version.lock().assign_new_one(data_version);
+        }
+    }
+
+    #[test]
+    fn imports_persists_changes_and_exports() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(temp_dir.path().join("test.json"), r#"{"a":1,"b":2}"#).unwrap();
+
+        // the first start with RocksDB imports the JSON file
+        let config = TestConfig::new(temp_dir.path(), true);
+        let slot = RocksDBConfigSlot::default();
+        assert!(load_kv(&config, &slot));
+        let rocksdb = slot.get().unwrap().clone();
+        assert_eq!(rocksdb.persist(&config).unwrap(), 0);
+
+        config.table.lock().insert("a".into(), 10);
+        config.table.lock().remove("b");
+        config.table.lock().insert("c".into(), 3);
+        assert_eq!(rocksdb.persist(&config).unwrap(), 3);
+        drop(rocksdb);
+        drop(slot);
+
+        let reloaded = TestConfig::new(temp_dir.path(), true);
+        let slot = RocksDBConfigSlot::default();
+        assert!(load_kv(&reloaded, &slot));
+        assert_eq!(*reloaded.table.lock(), *config.table.lock());
+        drop(slot);
+
+        // switched back to files, the JSON file is exported once
+        fs::remove_file(temp_dir.path().join("test.json")).unwrap();
+        let exported = TestConfig::new(temp_dir.path(), false);
+        assert!(load_kv(&exported, &RocksDBConfigSlot::default()));
+        let json = fs::read_to_string(temp_dir.path().join("test.json")).unwrap();
+        let table: HashMap<CheetahString, i64> = serde_json::from_str(&json).unwrap();
+        assert_eq!(table, *config.table.lock());
+    }
+}
@@ -0,0 +1,505 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use cheetah_string::CheetahString;
+use parking_lot::Mutex;
+use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::common::broker::broker_role::BrokerRole;
+use rocketmq_common::common::mix_all;
+use rocketmq_common::common::server::config::ServerConfig;
+use rocketmq_common::ParseConfigFile;
+use rocketmq_remoting::remoting_server::server::RocketMQServer;
+use rocketmq_store::config::message_store_config::MessageStoreConfig;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
+
+use crate::broker_error::BrokerError;
+use crate::broker_runtime::BrokerRuntime;
+use crate::container::broker_container_config::BrokerContainerConfig;
+use crate::container::broker_container_processor::BrokerContainerProcessor;
+use crate::Result;
+
+/// Cluster name, broker name and broker id of a broker in the container.
+type BrokerKey = (CheetahString, CheetahString, u64);
+
+struct InnerBroker {
+    broker_config: BrokerConfig,
+    store_paths: [String; 2],
+    shutdown_tx: broadcast::Sender<()>,
+    handle: JoinHandle<()>,
+}
+
+/// Hosts several brokers, masters and slaves of different broker groups, in one process.
+///
+/// The brokers share the process runtime and keep their own ports. Brokers are added from the
+/// config files of [`BrokerContainerConfig::broker_config_paths`] on start and by the add and
+/// remove broker requests served on the container's own port later.
+pub struct BrokerContainer {
+    container_config: BrokerContainerConfig,
+    brokers: tokio::sync::Mutex<HashMap<BrokerKey, InnerBroker>>,
+    server_handle: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl BrokerContainer {
+    pub fn new(container_config: BrokerContainerConfig) -> Self {
+        Self {
+            container_config,
+            brokers: tokio::sync::Mutex::new(HashMap::new()),
+            server_handle: Mutex::new(None),
+        }
+    }
+
+    pub fn container_config(&self) -> &BrokerContainerConfig {
+        &self.container_config
+    }
+
+    /// Starts the container's remoting server and the brokers of the configured config files.
+    pub async fn start(self: &Arc<Self>) {
+        let server = RocketMQServer::new(Arc::new(ServerConfig {
+            listen_port: self.container_config.listen_port,
+            bind_address: self.container_config.bind_address.clone(),
+        }));
+        let processor = BrokerContainerProcessor::new(self.clone());
+        *self.server_handle.lock() = Some(tokio::spawn(async move { server.run(processor).await }));
+
+        for config_path in &self.container_config.broker_config_paths {
+            if let Err(e) = self.add_broker_from_config_file(config_path).await {
+                error!(
+                    "add broker of {} to the container failed: {}",
+                    config_path, e
+                );
+            }
+        }
+        info!(
+            "Rocketmq BrokerContainer(----Rust) start success, listen port: {}",
+            self.container_config.listen_port
+        );
+    }
+
+    /// Shuts every broker down, then the container's remoting server.
+    pub async fn shutdown(&self) {
+        let brokers = std::mem::take(&mut *self.brokers.lock().await);
+        for (key, broker) in brokers {
+            shutdown_broker(&key, broker).await;
+        }
+        if let Some(handle) = self.server_handle.lock().take() {
+            handle.abort();
+        }
+        info!("Rocketmq BrokerContainer(----Rust) shutdown");
+    }
+
+    /// Adds the broker configured in the file at `config_path`, which holds both the broker
+    /// and the message store config as for a standalone broker.
+    pub async fn add_broker_from_config_file(&self, config_path: &str) -> Result<()> {
+        let path = PathBuf::from(config_path);
+        let mut broker_config = ParseConfigFile::parse_config_file::<BrokerConfig>(path.clone())
+            .map_err(|e| {
+                BrokerError::IllegalArgumentError(format!(
+                    "parse broker config {} failed: {}",
+                    config_path, e
+                ))
+            })?;
+        let message_store_config = ParseConfigFile::parse_config_file::<MessageStoreConfig>(path)
+            .map_err(|e| {
+            BrokerError::IllegalArgumentError(format!(
+                "parse message store config {} failed: {}",
+                config_path, e
+            ))
+        })?;
+        broker_config.broker_config_path = config_path.into();
+        self.add_broker(broker_config, message_store_config).await
+    }
+
+    /// Adds the broker configured by `properties`, each applied to the broker config, the
+    /// message store config or both, whichever knows the key.
+    pub async fn add_broker_from_properties(
+        &self,
+        properties: &HashMap<CheetahString, CheetahString>,
+    ) -> Result<()> {
+        let (broker_config, message_store_config) =
+            parse_broker_properties(properties).map_err(BrokerError::IllegalArgumentError)?;
+        self.add_broker(broker_config, message_store_config).await
+    }
+
+    /// Initializes and starts a broker in the container.
+    ///
+    /// Fails when a broker with the same cluster, name and id is in the container already, or
+    /// when the ports or the store paths of the broker collide with those of another broker.
+    pub async fn add_broker(
+        &self,
+        mut broker_config: BrokerConfig,
+        mut message_store_config: MessageStoreConfig,
+    ) -> Result<()> {
+        self.prepare_broker_config(&mut broker_config, &mut message_store_config)
+            .map_err(BrokerError::IllegalArgumentError)?;
+        let key = broker_key(&broker_config);
+        let store_paths = store_paths(&message_store_config);
+        // adds are serialized, a broker is only visible once it started
+        let mut brokers = self.brokers.lock().await;
+        check_conflicts(
+            &brokers,
+            &key,
+            &broker_config,
+            &store_paths,
+            self.container_config.listen_port,
+        )
+        .map_err(BrokerError::IllegalArgumentError)?;
+
+        let server_config = ServerConfig {
+            listen_port: broker_config.listen_port,
+            bind_address: self.container_config.bind_address.clone(),
+        };
+        let mut broker_runtime =
+            BrokerRuntime::new(broker_config.clone(), message_store_config, server_config);
+        if !broker_runtime.initialize().await {
+            broker_runtime.shutdown().await;
+            return Err(BrokerError::IllegalArgumentError(format!(
+                "initialize broker {}/{}/{} failed",
+                key.0, key.1, key.2
+            )));
+        }
+        let (shutdown_tx, shutdown_rx) = broadcast::channel(1);
+        broker_runtime.shutdown_rx = Some(shutdown_rx);
+        let handle = tokio::spawn(async move { broker_runtime.start().await });
+        info!(
+            "add broker {}/{}/{} to the container, listen port: {}",
+            key.0, key.1, key.2, broker_config.listen_port
+        );
+        brokers.insert(
+            key,
+            InnerBroker {
+                broker_config,
+                store_paths,
+                shutdown_tx,
+                handle,
+            },
+        );
+        Ok(())
+    }
+
+    /// Shuts the broker down and removes it from the container, returning its config.
+    pub async fn remove_broker(
+        &self,
+        broker_cluster_name: &CheetahString,
+        broker_name: &CheetahString,
+        broker_id: u64,
+    ) -> Result<BrokerConfig> {
+        let key = (broker_cluster_name.clone(), broker_name.clone(), broker_id);
+        let Some(broker) = self.brokers.lock().await.remove(&key) else {
+            return Err(BrokerError::IllegalArgumentError(format!(
+                "broker {}/{}/{} is not in the container",
+                key.0, key.1, key.2
+            )));
+        };
+        let broker_config = broker.broker_config.clone();
+        shutdown_broker(&key, broker).await;
+        Ok(broker_config)
+    }
+
+    /// Configs of the brokers in the container, the masters first.
+    pub async fn broker_configs(&self) -> Vec<BrokerConfig> {
+        let mut broker_configs = self
+            .brokers
+            .lock()
+            .await
+            .values()
+            .map(|broker| broker.broker_config.clone())
+            .collect::<Vec<_>>();
+        broker_configs.sort_by_key(|broker_config| broker_config.broker_identity.broker_id);
+        broker_configs
+    }
+
+    fn prepare_broker_config(
+        &self,
+        broker_config: &mut BrokerConfig,
+        message_store_config: &mut MessageStoreConfig,
+    ) -> std::result::Result<(), String> {
+        if !message_store_config.enable_dledger_commit_log && !broker_config.enable_controller_mode
+        {
+            match message_store_config.broker_role {
+                BrokerRole::AsyncMaster | BrokerRole::SyncMaster => {
+                    broker_config.broker_identity.broker_id = mix_all::MASTER_ID;
+                }
+                BrokerRole::Slave
+                    if broker_config.broker_identity.broker_id == mix_all::MASTER_ID =>
+                {
+                    return Err("slave broker id must be > 0".to_string());
+                }
+                BrokerRole::Slave => {}
+            }
+            if message_store_config.total_replicas < message_store_config.in_sync_replicas as usize
+            {
+                return Err("invalid replicas number, inSyncReplicas must not exceed \
+                            totalReplicas"
+                    .to_string());
+            }
+        }
+        message_store_config.ha_listen_port = broker_config.listen_port as usize + 1;
+        broker_config.is_in_broker_container = true;
+        broker_config.broker_identity.is_in_broker_container = true;
+        if let Some(namesrv_addr) = &self.container_config.namesrv_addr {
+            broker_config.namesrv_addr = Some(namesrv_addr.clone());
+        }
+        Ok(())
+    }
+}
+
+fn broker_key(broker_config: &BrokerConfig) -> BrokerKey {
+    let broker_identity = &broker_config.broker_identity;
+    (
+        broker_identity.broker_cluster_name.clone(),
+        broker_identity.broker_name.clone(),
+        broker_identity.broker_id,
+    )
+}
+
+/// Ports a broker listens on: the remoting port, the fast remoting port and the HA port.
+fn broker_ports(listen_port: u32) -> [u32; 3] {
+    [listen_port, listen_port.saturating_sub(2), listen_port + 1]
+}
+
+/// Store root dir and commit log dir of a broker, no two brokers may share one.
+fn store_paths(message_store_config: &MessageStoreConfig) -> [String; 2] {
+    [
+        message_store_config.store_path_root_dir.to_string(),
+        message_store_config.get_store_path_commit_log(),
+    ]
+}
+
+fn check_conflicts(
+    brokers: &HashMap<BrokerKey, InnerBroker>,
+    key: &BrokerKey,
+    broker_config: &BrokerConfig,
+    store_paths: &[String; 2],
+    container_port: u32,
+) -> std::result::Result<(), String> {
+    if brokers.contains_key(key) {
+        return Err(format!("duplicate broker {}/{}/{}", key.0, key.1, key.2));
+    }
+    let ports = broker_ports(broker_config.listen_port);
+    if ports.contains(&container_port) {
+        return Err(format!(
+            "listen port {} of the broker collides with the container port {}",
+            broker_config.listen_port, container_port
+        ));
+    }
+    if let Some(other) = brokers.values().find(|other| {
+        broker_ports(other.broker_config.listen_port)
+            .iter()
+            .any(|port| ports.contains(port))
+    }) {
+        return Err(format!(
+            "listen port {} of the broker collides with broker {} on port {}",
+            broker_config.listen_port,
+            other.broker_config.broker_identity.broker_name,
+            other.broker_config.listen_port
+        ));
+    }
+    for other in brokers.values() {
+        if let Some(path) = store_paths
+            .iter()
+            .find(|path| other.store_paths.contains(path))
+        {
+            return Err(format!(
+                "store path {} of the broker is used by broker {}/{}",
+                path,
+                other.broker_config.broker_identity.broker_name,
+                other.broker_config.broker_identity.broker_id
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Splits `properties` into a broker config and a message store config, a key known to both is
+/// applied to both.
+fn parse_broker_properties(
+    properties: &HashMap<CheetahString, CheetahString>,
+) -> std::result::Result<(BrokerConfig, MessageStoreConfig), String> {
+    let mut broker_config = BrokerConfig::default();
+    let mut message_store_config = MessageStoreConfig::default();
+    let mut broker_properties = HashMap::new();
+    let mut store_properties = HashMap::new();
+    for (key, value) in properties {
+        let broker_key = broker_config.contains_key(key);
+        let store_key = message_store_config.contains_key(key);
+        if !broker_key && !store_key {
+            return Err(format!("unknown broker config key '{}'", key));
+        }
+        if broker_key {
+            broker_properties.insert(key.clone(), value.clone());
+        }
+        if store_key {
+            store_properties.insert(key.clone(), value.clone());
+        }
+    }
+    broker_config.update(&broker_properties)?;
+    message_store_config.update(&store_properties)?;
+    Ok((broker_config, message_store_config))
+}
+
+async fn shutdown_broker(key: &BrokerKey, broker: InnerBroker) {
+    let _ = broker.shutdown_tx.send(());
+    if let Err(e) = broker.handle.await {
+        warn!(
+            "broker {}/{}/{} did not shut down cleanly: {}",
+            key.0, key.1, key.2, e
+        );
+    }
+    info!(
+        "remove broker {}/{}/{} from the container",
+        key.0, key.1, key.2
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn broker_config(broker_name: &str, broker_id: u64, listen_port: u32) -> BrokerConfig {
+        let mut broker_config = BrokerConfig {
+            listen_port,
+            ..Default::default()
+        };
+        broker_config.broker_identity.broker_name = broker_name.into();
+        broker_config.broker_identity.broker_id = broker_id;
+        broker_config
+    }
+
+    fn inner_broker(broker_config: BrokerConfig) -> InnerBroker {
+        let store_path_root_dir = format!("/store/{}", broker_config.listen_port);
+        InnerBroker {
+            store_paths: [
+                store_path_root_dir.clone(),
+                format!("{}/commitlog", store_path_root_dir),
+            ],
+            broker_config,
+            shutdown_tx: broadcast::channel(1).0,
+            handle: tokio::spawn(async {}),
+        }
+    }
+
+    fn store_paths_of(store_path_root_dir: &str) -> [String; 2] {
+        store_paths(&MessageStoreConfig {
+            store_path_root_dir: store_path_root_dir.into(),
+            ..Default::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn rejects_duplicate_brokers_and_colliding_ports() {
+        let master = broker_config("broker-a", 0, 10911);
+        let mut brokers = HashMap::new();
+        brokers.insert(broker_key(&master), inner_broker(master.clone()));
+        let paths = store_paths_of("/store/b");
+
+        assert!(check_conflicts(&brokers, &broker_key(&master), &master, &paths, 10811).is_err());
+        let slave = broker_config("broker-a", 1, 10912);
+        assert!(check_conflicts(&brokers, &broker_key(&slave), &slave, &paths, 10811).is_err());
+        let slave = broker_config("broker-a", 1, 10921);
+        assert!(check_conflicts(&brokers, &broker_key(&slave), &slave, &paths, 10811).is_ok());
+        assert!(check_conflicts(&brokers, &broker_key(&slave), &slave, &paths, 10919).is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_brokers_sharing_a_store_path() {
+        let master = broker_config("broker-a", 0, 10911);
+        let mut brokers = HashMap::new();
+        brokers.insert(broker_key(&master), inner_broker(master.clone()));
+        let slave = broker_config("broker-a", 1, 10921);
+
+        let err = check_conflicts(
+            &brokers,
+            &broker_key(&slave),
+            &slave,
+            &store_paths_of("/store/10911"),
+            10811,
+        )
+        .unwrap_err();
+        assert!(err.contains("/store/10911"));
+
+        // a root dir of its own, but the commit log of the master
+        let mut paths = store_paths_of("/store/10921");
+        paths[1] = "/store/10911/commitlog".to_string();
+        assert!(check_conflicts(&brokers, &broker_key(&slave), &slave, &paths, 10811).is_err());
+
+        let paths = store_paths_of("/store/10921");
+        assert!(check_conflicts(&brokers, &broker_key(&slave), &slave, &paths, 10811).is_ok());
+    }
+
+    #[test]
+    fn parses_the_broker_and_the_message_store_config_from_properties() {
+        let properties = [
+            ("brokerName", "broker-b"),
+            ("listenPort", "10931"),
+            ("storePathRootDir", "/store/broker-b"),
+            ("brokerRole", "SLAVE"),
+            ("flushDiskType", "SYNC_FLUSH"),
+        ]
+        .into_iter()
+        .map(|(key, value)| (CheetahString::from(key), CheetahString::from(value)))
+        .collect::<HashMap<_, _>>();
+        let (broker_config, message_store_config) = parse_broker_properties(&properties).unwrap();
+        assert_eq!(broker_config.broker_identity.broker_name, "broker-b");
+        assert_eq!(broker_config.listen_port, 10931);
+        assert_eq!(broker_config.store_path_root_dir, "/store/broker-b");
+        assert_eq!(message_store_config.store_path_root_dir, "/store/broker-b");
+        assert_eq!(message_store_config.broker_role, BrokerRole::Slave);
+        assert_eq!(
+            message_store_config.get_store_path_commit_log(),
+            "/store/broker-b/commitlog"
+        );
+
+        let mut properties = properties;
+        properties.insert("noSuchKey".into(), "1".into());
+        assert!(parse_broker_properties(&properties)
+            .unwrap_err()
+            .contains("noSuchKey"));
+    }
+
+    #[test]
+    fn prepares_the_broker_for_the_container() {
+        let container = BrokerContainer::new(BrokerContainerConfig {
+            namesrv_addr: Some("127.0.0.1:9876".into()),
+            ..Default::default()
+        });
+        let mut master = broker_config("broker-a", 3, 10911);
+        let mut message_store_config = MessageStoreConfig::default();
+        container
+            .prepare_broker_config(&mut master, &mut message_store_config)
+            .unwrap();
+        assert_eq!(master.broker_identity.broker_id, mix_all::MASTER_ID);
+        assert!(master.is_in_broker_container);
+        assert_eq!(master.namesrv_addr, Some("127.0.0.1:9876".into()));
+        assert_eq!(message_store_config.ha_listen_port, 10912);
+
+        let mut slave = broker_config("broker-a", 0, 10921);
+        let mut message_store_config = MessageStoreConfig {
+            broker_role: BrokerRole::Slave,
+            ..Default::default()
+        };
+        assert!(container
+            .prepare_broker_config(&mut slave, &mut message_store_config)
+            .is_err());
+    }
+}
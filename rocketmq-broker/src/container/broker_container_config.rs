@@ -0,0 +1,45 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use cheetah_string::CheetahString;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Config of a [`BrokerContainer`](crate::BrokerContainer), the process that hosts several
+/// brokers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BrokerContainerConfig {
+    /// Port of the container's own remoting server, which serves the add and remove broker
+    /// requests. The brokers keep listening on their own ports.
+    pub listen_port: u32,
+    pub bind_address: String,
+    /// Name servers of every broker in the container, in place of the broker's own setting.
+    pub namesrv_addr: Option<CheetahString>,
+    /// Config files of the brokers added when the container starts.
+    pub broker_config_paths: Vec<String>,
+}
+
+impl Default for BrokerContainerConfig {
+    fn default() -> Self {
+        Self {
+            listen_port: 10811,
+            bind_address: "0.0.0.0".to_string(),
+            namesrv_addr: None,
+            broker_config_paths: Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,125 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::sync::Arc;
+
+use rocketmq_common::common::mix_all;
+use rocketmq_remoting::code::request_code::RequestCode;
+use rocketmq_remoting::code::response_code::ResponseCode;
+use rocketmq_remoting::net::channel::Channel;
+use rocketmq_remoting::protocol::header::broker::add_broker_request_header::AddBrokerRequestHeader;
+use rocketmq_remoting::protocol::header::broker::remove_broker_request_header::RemoveBrokerRequestHeader;
+use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
+use rocketmq_remoting::runtime::processor::RequestProcessor;
+use rocketmq_remoting::Result;
+use tracing::info;
+
+use crate::container::broker_container::BrokerContainer;
+
+/// Serves the admin requests sent to the port of a [`BrokerContainer`].
+#[derive(Clone)]
+pub(crate) struct BrokerContainerProcessor {
+    broker_container: Arc<BrokerContainer>,
+}
+
+impl BrokerContainerProcessor {
+    pub(crate) fn new(broker_container: Arc<BrokerContainer>) -> Self {
+        Self { broker_container }
+    }
+
+    async fn add_broker(
+        &self,
+        channel: &Channel,
+        request: RemotingCommand,
+    ) -> Result<Option<RemotingCommand>> {
+        let request_header = request.decode_command_custom_header::<AddBrokerRequestHeader>()?;
+        info!("addBroker called by {}", channel.remote_address());
+        let result = match request_header
+            .config_path
+            .filter(|config_path| !config_path.is_empty())
+        {
+            Some(config_path) => {
+                self.broker_container
+                    .add_broker_from_config_file(config_path.as_str())
+                    .await
+            }
+            None => {
+                let Some(properties) = request
+                    .body()
+                    .as_ref()
+                    .and_then(|body| std::str::from_utf8(body).ok())
+                    .and_then(mix_all::string_to_properties)
+                    .filter(|properties| !properties.is_empty())
+                else {
+                    return Ok(Some(RemotingCommand::create_error_response(
+                        ResponseCode::SystemError,
+                        "addBroker properties empty",
+                    )));
+                };
+                self.broker_container
+                    .add_broker_from_properties(&properties)
+                    .await
+            }
+        };
+        Ok(Some(match result {
+            Ok(()) => RemotingCommand::create_response_command(),
+            Err(e) => RemotingCommand::create_error_response(ResponseCode::SystemError, e),
+        }))
+    }
+
+    async fn remove_broker(
+        &self,
+        channel: &Channel,
+        request: RemotingCommand,
+    ) -> Result<Option<RemotingCommand>> {
+        let request_header = request.decode_command_custom_header::<RemoveBrokerRequestHeader>()?;
+        info!("removeBroker called by {}", channel.remote_address());
+        let result = self
+            .broker_container
+            .remove_broker(
+                &request_header.broker_cluster_name,
+                &request_header.broker_name,
+                request_header.broker_id,
+            )
+            .await;
+        Ok(Some(match result {
+            Ok(_) => RemotingCommand::create_response_command(),
+            Err(e) => RemotingCommand::create_error_response(ResponseCode::SystemError, e),
+        }))
+    }
+}
+
+impl RequestProcessor for BrokerContainerProcessor {
+    async fn process_request(
+        &mut self,
+        channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        request: RemotingCommand,
+    ) -> Result<Option<RemotingCommand>> {
+        match RequestCode::from(request.code()) {
+            RequestCode::AddBroker => self.add_broker(&channel, request).await,
+            RequestCode::RemoveBroker => self.remove_broker(&channel, request).await,
+            _ => Ok(Some(RemotingCommand::create_error_response(
+                ResponseCode::RequestCodeNotSupported,
+                format!(
+                    "request code {} is not supported by the broker container",
+                    request.code()
+                ),
+            ))),
+        }
+    }
+}
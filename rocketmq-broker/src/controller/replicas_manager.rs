@@ -14,17 +14,321 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::collections::HashSet;
+use std::sync::atomic::AtomicI32;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use cheetah_string::CheetahString;
+use rocketmq_common::common::broker::broker_role::BrokerRole;
+use rocketmq_common::common::mix_all;
+use rocketmq_rust::ArcMut;
+use rocketmq_store::ha::auto_switch_ha_service::AutoSwitchHAService;
+use rocketmq_store::log_file::MessageStore;
+use tracing::info;
 use tracing::warn;
 
-#[derive(Default)]
-pub struct ReplicasManager {}
+use crate::broker_runtime::BrokerRuntimeInner;
+
+/// Switches the role of a broker in controller mode to the one the controller elected.
+///
+/// The broker id of the config identifies the broker in its group toward the controller, the
+/// broker registers with the master id while it is master and with its own id otherwise. Until
+/// the controller notifies a role, a broker configured as master serves as master of the last
+/// epoch of its epoch file.
+pub struct ReplicasManager<MS> {
+    broker_runtime_inner: ArcMut<BrokerRuntimeInner<MS>>,
+    local_broker_id: u64,
+    master_epoch: Arc<AtomicI32>,
+    sync_state_set_epoch: Arc<AtomicI32>,
+}
+
+impl<MS> Clone for ReplicasManager<MS> {
+    fn clone(&self) -> Self {
+        Self {
+            broker_runtime_inner: self.broker_runtime_inner.clone(),
+            local_broker_id: self.local_broker_id,
+            master_epoch: self.master_epoch.clone(),
+            sync_state_set_epoch: self.sync_state_set_epoch.clone(),
+        }
+    }
+}
+
+impl<MS: MessageStore> ReplicasManager<MS> {
+    pub fn new(broker_runtime_inner: ArcMut<BrokerRuntimeInner<MS>>) -> Self {
+        let local_broker_id = broker_runtime_inner
+            .broker_config()
+            .broker_identity
+            .broker_id;
+        Self {
+            broker_runtime_inner,
+            local_broker_id,
+            master_epoch: Arc::new(AtomicI32::new(0)),
+            sync_state_set_epoch: Arc::new(AtomicI32::new(0)),
+        }
+    }
 
-impl ReplicasManager {
     pub fn start(&mut self) {
-        warn!("ReplicasManager started not implemented");
+        if self.broker_runtime_inner.message_store_config().broker_role == BrokerRole::Slave {
+            return;
+        }
+        let Some(ha_service) = self.auto_switch_ha_service() else {
+            return;
+        };
+        let this = self.clone();
+        tokio::spawn(async move {
+            let master_epoch = ha_service.get_last_epoch();
+            this.change_to_master(master_epoch, 0, HashSet::from([this.local_broker_id]))
+                .await;
+        });
+    }
+
+    pub fn shutdown(&mut self) {}
+
+    pub fn master_epoch(&self) -> i32 {
+        self.master_epoch.load(Ordering::Acquire)
+    }
+
+    /// Takes the role the controller elected in `new_master_epoch`, ignored when the broker
+    /// already took the role of that epoch or a later one. A master also takes the sync state
+    /// set of a later `sync_state_set_epoch`.
+    pub async fn change_broker_role(
+        &self,
+        new_master_broker_id: u64,
+        new_master_address: Option<CheetahString>,
+        new_master_epoch: i32,
+        sync_state_set_epoch: i32,
+        sync_state_set: HashSet<u64>,
+    ) -> bool {
+        let master_epoch = self.master_epoch();
+        if new_master_epoch < master_epoch {
+            warn!(
+                "ignore the role change of epoch {}, the broker is in epoch {} already",
+                new_master_epoch, master_epoch
+            );
+            return false;
+        }
+        let is_master = new_master_broker_id == self.local_broker_id;
+        if new_master_epoch > master_epoch {
+            return if is_master {
+                self.change_to_master(new_master_epoch, sync_state_set_epoch, sync_state_set)
+                    .await
+            } else {
+                self.change_to_slave(new_master_address, new_master_epoch, new_master_broker_id)
+                    .await
+            };
+        }
+        if is_master && sync_state_set_epoch > self.sync_state_set_epoch.load(Ordering::Acquire) {
+            self.change_sync_state_set(sync_state_set, sync_state_set_epoch);
+        }
+        true
+    }
+
+    async fn change_to_master(
+        &self,
+        new_master_epoch: i32,
+        sync_state_set_epoch: i32,
+        sync_state_set: HashSet<u64>,
+    ) -> bool {
+        let Some(ha_service) = self.auto_switch_ha_service() else {
+            return false;
+        };
+        if !ha_service.change_to_master(new_master_epoch).await {
+            return false;
+        }
+        self.master_epoch.store(new_master_epoch, Ordering::Release);
+        self.change_sync_state_set(sync_state_set, sync_state_set_epoch);
+        info!(
+            "broker {} changes to master in epoch {}",
+            self.local_broker_id, new_master_epoch
+        );
+        self.change_broker_config(BrokerRole::SyncMaster, mix_all::MASTER_ID, None);
+        true
+    }
+
+    async fn change_to_slave(
+        &self,
+        new_master_address: Option<CheetahString>,
+        new_master_epoch: i32,
+        new_master_broker_id: u64,
+    ) -> bool {
+        let Some(ha_service) = self.auto_switch_ha_service() else {
+            return false;
+        };
+        // the HA address of the new master comes with the registration to the name servers
+        if !ha_service
+            .change_to_slave(None, new_master_epoch, Some(self.local_broker_id))
+            .await
+        {
+            return false;
+        }
+        self.master_epoch.store(new_master_epoch, Ordering::Release);
+        info!(
+            "broker {} changes to slave of broker {} at {:?} in epoch {}",
+            self.local_broker_id, new_master_broker_id, new_master_address, new_master_epoch
+        );
+        self.change_broker_config(BrokerRole::Slave, self.local_broker_id, new_master_address);
+        true
+    }
+
+    fn change_sync_state_set(&self, mut sync_state_set: HashSet<u64>, sync_state_set_epoch: i32) {
+        let Some(ha_service) = self.auto_switch_ha_service() else {
+            return;
+        };
+        // the master holds its own commit log, whatever the controller still knows
+        sync_state_set.insert(self.local_broker_id);
+        info!(
+            "sync state set changes to {:?} in epoch {}",
+            sync_state_set, sync_state_set_epoch
+        );
+        ha_service.set_sync_state_set(sync_state_set);
+        self.sync_state_set_epoch
+            .store(sync_state_set_epoch, Ordering::Release);
+    }
+
+    /// Registers the broker with the new role, so that producers and consumers follow.
+    fn change_broker_config(
+        &self,
+        broker_role: BrokerRole,
+        broker_id: u64,
+        master_address: Option<CheetahString>,
+    ) {
+        let mut inner = self.broker_runtime_inner.clone();
+        inner.message_store_config_mut().broker_role = broker_role;
+        inner
+            .shared_broker_config()
+            .modify(|broker_config| broker_config.broker_identity.broker_id = broker_id);
+        inner.slave_synchronize().set_master_addr(master_address);
+        tokio::spawn(async move {
+            let this = inner.clone();
+            inner
+                .register_broker_all_inner(this, true, false, true)
+                .await;
+        });
+    }
+
+    fn auto_switch_ha_service(&self) -> Option<Arc<AutoSwitchHAService>> {
+        let ha_service = self
+            .broker_runtime_inner
+            .message_store()
+            .as_ref()
+            .and_then(|message_store| message_store.get_auto_switch_ha_service());
+        if ha_service.is_none() {
+            warn!("the message store has no auto switch HA service, the broker role is kept");
+        }
+        ha_service
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocketmq_common::common::broker::broker_config::BrokerConfig;
+    use rocketmq_common::common::server::config::ServerConfig;
+    use rocketmq_store::config::message_store_config::MessageStoreConfig;
+
+    use super::*;
+    use crate::broker_runtime::BrokerRuntime;
+
+    fn controller_mode_broker(store_dir: &tempfile::TempDir, broker_id: u64) -> BrokerRuntime {
+        let store_path_root_dir = CheetahString::from(store_dir.path().to_string_lossy().as_ref());
+        let mut broker_config = BrokerConfig {
+            enable_controller_mode: true,
+            store_path_root_dir: store_path_root_dir.clone(),
+            ..Default::default()
+        };
+        broker_config.broker_identity.broker_id = broker_id;
+        BrokerRuntime::new(
+            broker_config,
+            MessageStoreConfig {
+                store_path_root_dir,
+                ..Default::default()
+            },
+            ServerConfig::default(),
+        )
+    }
+
+    #[test]
+    fn broker_takes_the_role_the_controller_elected() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let _guard = runtime.enter();
+        let store_dir = tempfile::tempdir().unwrap();
+        let mut broker = controller_mode_broker(&store_dir, 2);
+        assert!(runtime.block_on(broker.initialize()));
+        let inner = broker.inner().clone();
+        let replicas_manager = inner.replicas_manager().unwrap();
+
+        assert!(runtime.block_on(replicas_manager.change_broker_role(
+            2,
+            None,
+            1,
+            1,
+            HashSet::from([2, 3]),
+        )));
+        assert_eq!(
+            inner.message_store_config().broker_role,
+            BrokerRole::SyncMaster
+        );
+        assert_eq!(
+            inner.broker_config().broker_identity.broker_id,
+            mix_all::MASTER_ID
+        );
+        let ha_service = replicas_manager.auto_switch_ha_service().unwrap();
+        assert!(ha_service.is_master());
+        assert_eq!(ha_service.get_sync_state_set(), HashSet::from([2, 3]));
+
+        // a later sync state set of the same epoch
+        assert!(runtime.block_on(replicas_manager.change_broker_role(
+            2,
+            None,
+            1,
+            2,
+            HashSet::from([3]),
+        )));
+        assert_eq!(ha_service.get_sync_state_set(), HashSet::from([2, 3]));
+
+        assert!(runtime.block_on(replicas_manager.change_broker_role(
+            3,
+            Some("127.0.0.1:10911".into()),
+            2,
+            2,
+            HashSet::from([3]),
+        )));
+        assert_eq!(inner.message_store_config().broker_role, BrokerRole::Slave);
+        assert_eq!(inner.broker_config().broker_identity.broker_id, 2);
+        assert_eq!(
+            inner.slave_synchronize().master_addr(),
+            Some("127.0.0.1:10911".into())
+        );
+        assert!(!ha_service.is_master());
+        assert_eq!(replicas_manager.master_epoch(), 2);
+
+        // stale notifications are ignored
+        assert!(!runtime.block_on(replicas_manager.change_broker_role(
+            2,
+            None,
+            1,
+            3,
+            HashSet::from([2]),
+        )));
+        assert_eq!(inner.message_store_config().broker_role, BrokerRole::Slave);
+        // the epochs of the empty commit log start at the same offset, the last one is kept
+        assert_eq!(
+            ha_service
+                .get_epoch_entries()
+                .iter()
+                .map(|entry| entry.epoch)
+                .collect::<Vec<_>>(),
+            vec![2]
+        );
     }
 
-    pub fn shutdown(&mut self) {
-        warn!("ReplicasManager shutdown not implemented");
+    #[test]
+    fn controller_mode_needs_a_broker_id() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let _guard = runtime.enter();
+        let store_dir = tempfile::tempdir().unwrap();
+        let mut broker = controller_mode_broker(&store_dir, mix_all::MASTER_ID);
+        assert!(!runtime.block_on(broker.initialize()));
+        assert!(broker.inner().replicas_manager().is_none());
     }
 }
@@ -28,7 +28,6 @@ use rocketmq_common::common::message::message_decoder;
 use rocketmq_common::common::message::message_ext::MessageExt;
 use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
 use rocketmq_common::common::message::message_queue::MessageQueue;
-use rocketmq_common::common::message::MessageConst;
 use rocketmq_common::common::message::MessageTrait;
 use rocketmq_common::common::mix_all;
 use rocketmq_runtime::RocketMQRuntime;
@@ -42,11 +41,11 @@ use tracing::error;
 use tracing::warn;
 
 use crate::broker_runtime::BrokerRuntimeInner;
+use crate::failover::inner_producer::InnerProducer;
 use crate::transaction::queue::transactional_message_util::TransactionalMessageUtil;
 
-const SEND_TIMEOUT: u64 = 3_000;
 const DEFAULT_PULL_TIMEOUT_MILLIS: u64 = 10_000;
-type FutureResult = Pin<Box<dyn Future<Output = (Option<MessageExt>, String, bool)>>>;
+type FutureResult = Pin<Box<dyn Future<Output = (Option<MessageExt>, String, bool)> + Send>>;
 
 ///### RocketMQ's EscapeBridge for Dead Letter Queue (DLQ) Mechanism
 ///
@@ -88,7 +87,7 @@ type FutureResult = Pin<Box<dyn Future<Output = (Option<MessageExt>, String, boo
 /// **Note:** The specific configuration and usage methods may vary depending on the version of
 /// RocketMQ. Please refer to the official documentation for the most accurate information.
 pub(crate) struct EscapeBridge<MS> {
-    inner_producer_group_name: CheetahString,
+    inner_producer: InnerProducer<MS>,
     inner_consumer_group_name: CheetahString,
     escape_bridge_runtime: Option<RocketMQRuntime>,
     message_store: Option<ArcMut<MS>>,
@@ -115,7 +114,10 @@ impl<MS: MessageStore> EscapeBridge<MS> {
         ));
 
         Self {
-            inner_producer_group_name,
+            inner_producer: InnerProducer::new(
+                inner_producer_group_name,
+                broker_runtime_inner.clone(),
+            ),
             inner_consumer_group_name,
             escape_bridge_runtime: None,
             message_store: None,
@@ -123,7 +125,8 @@ impl<MS: MessageStore> EscapeBridge<MS> {
         }
     }
 
-    pub fn start(&mut self /* message_store: Option<ArcMut<MS>> */) {
+    pub fn start(&mut self) {
+        self.message_store = self.broker_runtime_inner.message_store().clone();
         if self
             .broker_runtime_inner
             .broker_config()
@@ -137,7 +140,6 @@ impl<MS: MessageStore> EscapeBridge<MS> {
                 num_cpus::get(),
                 "AsyncEscapeBridgeExecutor",
             ));
-            //self.message_store = message_store;
         }
     }
 
@@ -200,106 +202,65 @@ where
     pub async fn put_message_to_remote_broker(
         &mut self,
         message_ext: MessageExtBrokerInner,
-        mut broker_name_to_send: Option<CheetahString>,
+        broker_name_to_send: Option<CheetahString>,
     ) -> crate::Result<Option<SendResult>> {
-        let broker_name = self
-            .broker_runtime_inner
-            .broker_config()
-            .broker_identity
-            .broker_name
-            .as_str();
-        if broker_name.is_empty()
-            || broker_name
-                == broker_name_to_send
-                    .as_ref()
-                    .map_or("", |value| value.as_str())
-        {
+        if broker_name_to_send.as_ref().is_some_and(|broker_name| {
+            broker_name
+                == &self
+                    .broker_runtime_inner
+                    .broker_config()
+                    .broker_identity
+                    .broker_name
+        }) {
             // not remote broker
             return Ok(None);
         }
         let is_trans_half_message =
             TransactionalMessageUtil::build_half_topic() == message_ext.get_topic();
-        let mut message_to_put = if is_trans_half_message {
+        let message_to_put = if is_trans_half_message {
             TransactionalMessageUtil::build_transactional_message_from_half_message(
                 &message_ext.message_ext_inner,
             )
         } else {
             message_ext
         };
-        let topic_publish_info = self
-            .broker_runtime_inner
-            .topic_route_info_manager()
-            .try_to_find_topic_publish_info(message_to_put.get_topic())
-            .await;
-        if !topic_publish_info.as_ref().is_some_and(|value| value.ok()) {
-            warn!(
-                "putMessageToRemoteBroker: no route info of topic {} when escaping message, \
-                 msgId={}",
-                message_to_put.get_topic(),
-                message_to_put.message_ext_inner.msg_id
-            );
-            return Ok(None);
-        }
-        let topic_publish_info = topic_publish_info.unwrap();
-        let _mq_selected = if !broker_name_to_send
+        let selected = match broker_name_to_send
             .as_ref()
-            .is_some_and(|value| !value.is_empty())
+            .filter(|value| !value.is_empty())
         {
-            let mq = topic_publish_info
-                .select_one_message_queue_by_broker(broker_name_to_send.as_ref())
-                .unwrap();
-            message_to_put.message_ext_inner.queue_id = mq.get_queue_id();
-            broker_name_to_send = Some(mq.get_broker_name().clone());
-            if self
-                .broker_runtime_inner
-                .broker_config()
-                .broker_identity
-                .broker_name
-                .as_str()
-                == mq.get_broker_name().as_str()
-            {
-                warn!(
-                    "putMessageToRemoteBroker failed, remote broker not found. Topic: {}, MsgId: \
-                     {}, Broker: {}",
-                    message_to_put.get_topic(),
-                    message_to_put.message_ext_inner.msg_id,
-                    mq.get_broker_name()
-                );
-                return Ok(None);
+            Some(broker_name) => self
+                .inner_producer
+                .find_broker_addr(message_to_put.get_topic(), broker_name)
+                .await
+                .map(|broker_addr| {
+                    (
+                        MessageQueue::from_parts(
+                            message_to_put.get_topic(),
+                            broker_name.clone(),
+                            message_to_put.queue_id(),
+                        ),
+                        broker_addr,
+                    )
+                }),
+            None => {
+                self.inner_producer
+                    .select_remote_queue(message_to_put.get_topic())
+                    .await
             }
-            mq
-        } else {
-            MessageQueue::from_parts(
-                message_to_put.get_topic(),
-                broker_name_to_send.clone().unwrap(),
-                message_to_put.queue_id(),
-            )
         };
-        let broker_addr_to_send = self
-            .broker_runtime_inner
-            .topic_route_info_manager()
-            .find_broker_address_in_publish(broker_name_to_send.as_ref());
-        if broker_addr_to_send.is_none() {
+        let Some((message_queue, broker_addr)) = selected else {
             warn!(
-                "putMessageToRemoteBroker failed, remote broker not found. Topic: {}, MsgId:  {}, \
-                 Broker: {}",
+                "putMessageToRemoteBroker failed, remote broker not found. Topic: {}, MsgId: {}, \
+                 Broker: {:?}",
                 message_to_put.get_topic(),
                 message_to_put.message_ext_inner.msg_id,
-                broker_name_to_send.as_ref().unwrap()
+                broker_name_to_send
             );
             return Ok(None);
-        }
-        let producer_group = self.get_producer_group(&message_to_put);
+        };
         let result = self
-            .broker_runtime_inner
-            .broker_outer_api()
-            .send_message_to_specific_broker(
-                broker_addr_to_send.as_ref().unwrap(),
-                broker_name_to_send.as_ref().unwrap(),
-                message_to_put.message_ext_inner,
-                producer_group,
-                SEND_TIMEOUT,
-            )
+            .inner_producer
+            .send(message_to_put, &message_queue, &broker_addr)
             .await?;
         if result.send_status == SendStatus::SendOk {
             return Ok(Some(result));
@@ -307,120 +268,77 @@ where
         Ok(None)
     }
 
-    fn get_producer_group(&self, message_ext: &MessageExtBrokerInner) -> CheetahString {
-        let producer_group = message_ext.get_property(&CheetahString::from_static_str(
-            MessageConst::PROPERTY_PRODUCER_GROUP,
-        ));
-        match producer_group {
-            None => self.inner_producer_group_name.clone(),
-            Some(value) => value,
-        }
-    }
-
-    pub async fn async_put_message(
-        &mut self,
+    /// Stores `message_ext` locally, or on another broker while this one is a slave acting as
+    /// master.
+    ///
+    /// The returned future borrows nothing from the bridge, so callers can issue several puts
+    /// and await them together.
+    pub fn async_put_message(
+        &self,
         mut message_ext: MessageExtBrokerInner,
-    ) -> PutMessageResult {
-        if self
-            .broker_runtime_inner
-            .broker_config()
-            .broker_identity
-            .broker_id
-            == mix_all::MASTER_ID
-        {
-            self.message_store
-                .as_mut()
-                .unwrap()
-                .put_message(message_ext)
-                .await
-        } else if self
-            .broker_runtime_inner
-            .broker_config()
-            .enable_slave_acting_master
-            && self
-                .broker_runtime_inner
-                .broker_config()
-                .enable_remote_escape
-        {
-            message_ext.set_wait_store_msg_ok(false);
-            let topic_publish_info = self
-                .broker_runtime_inner
-                .topic_route_info_manager()
-                .try_to_find_topic_publish_info(message_ext.get_topic())
-                .await;
-            if topic_publish_info.is_none() {
-                return PutMessageResult::new_default(PutMessageStatus::ServiceNotAvailable);
+    ) -> impl Future<Output = PutMessageResult> + 'static {
+        let is_master = self.is_master();
+        let can_escape = self.can_escape();
+        let message_store = self.message_store();
+        let inner_producer = self.inner_producer.clone();
+        async move {
+            if is_master {
+                return put_message_to_local_store(message_store, message_ext).await;
             }
-            let topic_publish_info = topic_publish_info.unwrap();
-            let mq_selected = topic_publish_info.select_one_message_queue();
-            if mq_selected.is_none() {
+            if !can_escape {
                 return PutMessageResult::new_default(PutMessageStatus::ServiceNotAvailable);
             }
-            let message_queue = mq_selected.unwrap();
-            message_ext.message_ext_inner.queue_id = message_queue.get_queue_id();
-            let broker_name_to_send = message_queue.get_broker_name();
-            let broker_addr_to_send = self
-                .broker_runtime_inner
-                .topic_route_info_manager()
-                .find_broker_address_in_publish(Some(broker_name_to_send));
-            let producer_group = self.get_producer_group(&message_ext);
-            let result = self
-                .broker_runtime_inner
-                .broker_outer_api()
-                .send_message_to_specific_broker(
-                    broker_addr_to_send.as_ref().unwrap(),
-                    broker_name_to_send,
-                    message_ext.message_ext_inner,
-                    producer_group,
-                    SEND_TIMEOUT,
-                )
-                .await;
-            transform_send_result2put_result(result.ok())
-        } else {
-            PutMessageResult::new_default(PutMessageStatus::ServiceNotAvailable)
+            message_ext.set_wait_store_msg_ok(false);
+            let Some((message_queue, broker_addr)) = inner_producer
+                .select_remote_queue(message_ext.get_topic())
+                .await
+            else {
+                return PutMessageResult::new_default(PutMessageStatus::ServiceNotAvailable);
+            };
+            transform_send_result2put_result(
+                send_to_remote_queue(&inner_producer, message_ext, &message_queue, &broker_addr)
+                    .await,
+            )
         }
     }
 
-    pub async fn put_message_to_specific_queue(
-        &mut self,
+    /// Stores `message_ext` locally, or on the queue of another broker its topic and store host
+    /// hash to while this one is a slave acting as master, so that messages of one broker keep
+    /// landing on the same remote queue.
+    pub fn put_message_to_specific_queue(
+        &self,
         mut message_ext: MessageExtBrokerInner,
-    ) -> PutMessageResult {
-        if self
-            .broker_runtime_inner
-            .broker_config()
-            .broker_identity
-            .broker_id
-            == mix_all::MASTER_ID
-        {
-            self.message_store
-                .as_mut()
-                .unwrap()
-                .put_message(message_ext)
-                .await
-        } else if self
-            .broker_runtime_inner
-            .broker_config()
-            .enable_slave_acting_master
-            && self
-                .broker_runtime_inner
-                .broker_config()
-                .enable_remote_escape
-        {
+    ) -> impl Future<Output = PutMessageResult> + 'static {
+        let is_master = self.is_master();
+        let can_escape = self.can_escape();
+        let message_store = self.message_store();
+        let inner_producer = self.inner_producer.clone();
+        let broker_runtime_inner = self.broker_runtime_inner.clone();
+        async move {
+            if is_master {
+                return put_message_to_local_store(message_store, message_ext).await;
+            }
+            if !can_escape {
+                warn!(
+                    "Put message failed, enableSlaveActingMaster={}, enableRemoteEscape={}.",
+                    broker_runtime_inner
+                        .broker_config()
+                        .enable_slave_acting_master,
+                    broker_runtime_inner.broker_config().enable_remote_escape
+                );
+                return PutMessageResult::new_default(PutMessageStatus::ServiceNotAvailable);
+            }
             message_ext.set_wait_store_msg_ok(false);
-            let topic_publish_info = self
-                .broker_runtime_inner
+            let Some(topic_publish_info) = broker_runtime_inner
                 .topic_route_info_manager()
                 .try_to_find_topic_publish_info(message_ext.get_topic())
-                .await;
-            if topic_publish_info.is_none() {
+                .await
+            else {
                 return PutMessageResult::new(PutMessageStatus::PutToRemoteBrokerFail, None, true);
-            }
-            let topic_publish_info = topic_publish_info.unwrap();
-
+            };
             if topic_publish_info.message_queue_list.is_empty() {
                 return PutMessageResult::new(PutMessageStatus::PutToRemoteBrokerFail, None, true);
             }
-            //let message_queue = mq_selected.unwrap();
             let id = format!(
                 "{}{}",
                 message_ext.get_topic(),
@@ -429,45 +347,50 @@ where
             let code = JavaStringHasher::new().hash_str(id.as_str());
             let index = code as usize % topic_publish_info.message_queue_list.len();
             let message_queue = topic_publish_info.message_queue_list[index].clone();
-            message_ext.message_ext_inner.queue_id = message_queue.get_queue_id();
-            let broker_name_to_send = message_queue.get_broker_name();
-            let broker_addr_to_send = self
-                .broker_runtime_inner
+            let Some(broker_addr) = broker_runtime_inner
                 .topic_route_info_manager()
-                .find_broker_address_in_publish(Some(broker_name_to_send));
-            let producer_group = self.get_producer_group(&message_ext);
-            match self
-                .broker_runtime_inner
-                .broker_outer_api()
-                .send_message_to_specific_broker(
-                    broker_addr_to_send.as_ref().unwrap(),
-                    broker_name_to_send,
-                    message_ext.message_ext_inner,
-                    producer_group,
-                    SEND_TIMEOUT,
-                )
-                .await
-            {
-                Ok(result) => transform_send_result2put_result(Some(result)),
-                Err(e) => {
-                    error!("sendMessageInFailover to remote failed, {}", e);
-                    PutMessageResult::new(PutMessageStatus::PutToRemoteBrokerFail, None, true)
-                }
-            }
-        } else {
-            warn!(
-                "Put message failed, enableSlaveActingMaster={}, enableRemoteEscape={}.",
-                self.broker_runtime_inner
-                    .broker_config()
-                    .enable_slave_acting_master,
-                self.broker_runtime_inner
-                    .broker_config()
-                    .enable_remote_escape
-            );
-            PutMessageResult::new_default(PutMessageStatus::ServiceNotAvailable)
+                .find_broker_address_in_publish(Some(message_queue.get_broker_name()))
+            else {
+                warn!(
+                    "putMessageToSpecificQueue failed, address of broker {} not found",
+                    message_queue.get_broker_name()
+                );
+                return PutMessageResult::new(PutMessageStatus::PutToRemoteBrokerFail, None, true);
+            };
+            transform_send_result2put_result(
+                send_to_remote_queue(&inner_producer, message_ext, &message_queue, &broker_addr)
+                    .await,
+            )
         }
     }
 
+    #[inline]
+    fn is_master(&self) -> bool {
+        self.broker_runtime_inner
+            .broker_config()
+            .broker_identity
+            .broker_id
+            == mix_all::MASTER_ID
+    }
+
+    /// Whether messages may be sent to other brokers while this one is not the master.
+    #[inline]
+    fn can_escape(&self) -> bool {
+        let broker_config = self.broker_runtime_inner.broker_config();
+        broker_config.enable_slave_acting_master && broker_config.enable_remote_escape
+    }
+
+    fn message_store(&self) -> Option<ArcMut<MS>> {
+        self.message_store
+            .clone()
+            .or_else(|| self.broker_runtime_inner.message_store().clone())
+    }
+
+    /// Reads the message at `offset` of a queue, pulling it from the broker named
+    /// `broker_name` when that is not this broker.
+    ///
+    /// Returns the message if found, otherwise the reason and whether the read is worth
+    /// retrying.
     pub fn get_message_async(
         &self,
         topic: &CheetahString,
@@ -476,7 +399,9 @@ where
         broker_name: &CheetahString,
         de_compress_body: bool,
     ) -> FutureResult {
-        let message_store = self.message_store.clone().unwrap();
+        let Some(message_store) = self.message_store() else {
+            return Box::pin(async { (None, "message store is not ready".to_string(), true) });
+        };
         let inner_consumer_group_name = self.inner_consumer_group_name.clone();
         let topic = topic.clone();
         let broker_name = broker_name.clone();
@@ -531,6 +456,26 @@ where
         }
     }
 
+    /// Pulls the message at `offset` of a queue of this broker from the master of its group, for
+    /// a slave acting as master which has not replicated the message yet.
+    pub fn get_message_from_master_async(
+        &self,
+        topic: &CheetahString,
+        offset: i64,
+        queue_id: i32,
+    ) -> FutureResult {
+        if self.is_master() || !self.can_escape() {
+            return Box::pin(async { (None, "not a slave acting master".to_string(), false) });
+        }
+        let broker_name = self
+            .broker_runtime_inner
+            .broker_config()
+            .broker_identity
+            .broker_name
+            .clone();
+        self.get_message_from_remote_async(topic, offset, queue_id, &broker_name)
+    }
+
     fn get_message_from_remote_async(
         &self,
         topic: &CheetahString,
@@ -579,39 +524,65 @@ where
                     queue_id,
                     offset,
                     1,
-                    10000,
+                    DEFAULT_PULL_TIMEOUT_MILLIS,
                 )
                 .await
             {
-                Ok(pull_result) => {
-                    if let Some(result) = pull_result.0 {
-                        if *result.pull_status() == PullStatus::Found
-                            && result
-                                .msg_found_list()
-                                .as_ref()
-                                .is_some_and(|value| !value.is_empty())
-                        {
-                            return (
-                                Some(
-                                    result.msg_found_list().clone().unwrap()[0]
-                                        .clone()
-                                        .deref()
-                                        .clone(),
-                                ),
-                                "".to_string(),
-                                false,
-                            );
-                        }
-                    }
+                Ok((Some(result), _, _))
+                    if *result.pull_status() == PullStatus::Found
+                        && result
+                            .msg_found_list()
+                            .as_ref()
+                            .is_some_and(|value| !value.is_empty()) =>
+                {
+                    (
+                        Some(result.msg_found_list().as_ref().unwrap()[0].deref().clone()),
+                        "".to_string(),
+                        false,
+                    )
+                }
+                Ok((_, info, need_retry)) => (None, info, need_retry),
+                Err(e) => {
+                    warn!(
+                        "pull message from remote broker failed, broker {}, topic {}, queueId {}, \
+                         offset {}, {}",
+                        broker_name, topic, queue_id, offset, e
+                    );
+                    (None, e.to_string(), true)
                 }
-                Err(_e) => {}
             }
-
-            (None, "Get message from remote failed".to_string(), true)
         })
     }
 }
 
+async fn put_message_to_local_store<MS: MessageStore>(
+    message_store: Option<ArcMut<MS>>,
+    message_ext: MessageExtBrokerInner,
+) -> PutMessageResult {
+    match message_store {
+        Some(mut message_store) => message_store.put_message(message_ext).await,
+        None => PutMessageResult::new_default(PutMessageStatus::ServiceNotAvailable),
+    }
+}
+
+async fn send_to_remote_queue<MS: MessageStore>(
+    inner_producer: &InnerProducer<MS>,
+    message_ext: MessageExtBrokerInner,
+    message_queue: &MessageQueue,
+    broker_addr: &CheetahString,
+) -> Option<SendResult> {
+    match inner_producer
+        .send(message_ext, message_queue, broker_addr)
+        .await
+    {
+        Ok(result) => Some(result),
+        Err(e) => {
+            error!("sendMessageInFailover to remote failed, {}", e);
+            None
+        }
+    }
+}
+
 fn decode_msg_list(
     get_message_result: GetMessageResult,
     de_compress_body: bool,
@@ -651,10 +622,171 @@ fn transform_send_result2put_result(send_result: Option<SendResult>) -> PutMessa
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+
+    use rocketmq_client_rust::producer::producer_impl::topic_publish_info::TopicPublishInfo;
     use rocketmq_client_rust::producer::send_result::SendResult;
     use rocketmq_client_rust::producer::send_status::SendStatus;
+    use rocketmq_common::common::broker::broker_config::BrokerConfig;
+    use rocketmq_common::common::server::config::ServerConfig;
+    use rocketmq_store::config::message_store_config::MessageStoreConfig;
+    use rocketmq_store::message_store::boxed_message_store::BoxedMessageStore;
 
     use super::*;
+    use crate::broker_runtime::BrokerRuntime;
+
+    const TOPIC: &str = "EscapeTopic";
+
+    fn slave_broker(can_escape: bool) -> BrokerRuntime {
+        let mut broker_config = BrokerConfig {
+            enable_slave_acting_master: can_escape,
+            enable_remote_escape: can_escape,
+            ..Default::default()
+        };
+        broker_config.broker_identity.broker_name = "broker-a".into();
+        broker_config.broker_identity.broker_id = 1;
+        BrokerRuntime::new(
+            broker_config,
+            MessageStoreConfig::default(),
+            ServerConfig::default(),
+        )
+    }
+
+    /// Routes `TOPIC` to two queues of `broker_name`, reachable at `broker_addr` if given.
+    fn add_route(
+        broker_runtime_inner: &ArcMut<BrokerRuntimeInner<BoxedMessageStore>>,
+        broker_name: &str,
+        broker_addr: Option<&str>,
+    ) {
+        let topic_route_info_manager = broker_runtime_inner.topic_route_info_manager();
+        let topic_publish_info = TopicPublishInfo {
+            have_topic_router_info: true,
+            message_queue_list: (0..2)
+                .map(|queue_id| MessageQueue::from_parts(TOPIC, broker_name, queue_id))
+                .collect(),
+            ..TopicPublishInfo::new()
+        };
+        topic_route_info_manager
+            .topic_publish_info_table
+            .mut_from_ref()
+            .insert(TOPIC.into(), topic_publish_info);
+        if let Some(broker_addr) = broker_addr {
+            topic_route_info_manager
+                .broker_addr_table
+                .mut_from_ref()
+                .insert(
+                    broker_name.into(),
+                    HashMap::from([(mix_all::MASTER_ID, broker_addr.into())]),
+                );
+        }
+    }
+
+    fn message() -> MessageExtBrokerInner {
+        let mut message_ext = MessageExtBrokerInner::default();
+        message_ext.set_topic(TOPIC.into());
+        message_ext.set_body(Bytes::from_static(b"escape"));
+        message_ext
+    }
+
+    #[test]
+    fn slave_refuses_puts_unless_it_may_escape() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let _guard = runtime.enter();
+        let broker = slave_broker(false);
+        add_route(broker.inner(), "broker-b", Some("127.0.0.1:1"));
+        let escape_bridge = broker.inner().escape_bridge();
+
+        let result = runtime.block_on(escape_bridge.async_put_message(message()));
+        assert_eq!(
+            result.put_message_status(),
+            PutMessageStatus::ServiceNotAvailable
+        );
+        let result = runtime.block_on(escape_bridge.put_message_to_specific_queue(message()));
+        assert_eq!(
+            result.put_message_status(),
+            PutMessageStatus::ServiceNotAvailable
+        );
+    }
+
+    #[test]
+    fn async_put_message_needs_a_queue_on_another_broker() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let _guard = runtime.enter();
+        let broker = slave_broker(true);
+        add_route(broker.inner(), "broker-a", Some("127.0.0.1:1"));
+
+        let result = runtime.block_on(broker.inner().escape_bridge().async_put_message(message()));
+        assert_eq!(
+            result.put_message_status(),
+            PutMessageStatus::ServiceNotAvailable
+        );
+    }
+
+    #[test]
+    fn put_message_to_specific_queue_fails_without_the_broker_address() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let _guard = runtime.enter();
+        let broker = slave_broker(true);
+        add_route(broker.inner(), "broker-b", None);
+
+        let result = runtime.block_on(
+            broker
+                .inner()
+                .escape_bridge()
+                .put_message_to_specific_queue(message()),
+        );
+        assert_eq!(
+            result.put_message_status(),
+            PutMessageStatus::PutToRemoteBrokerFail
+        );
+    }
+
+    #[test]
+    fn failed_remote_sends_report_put_to_remote_broker_fail() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let _guard = runtime.enter();
+        let broker = slave_broker(true);
+        add_route(broker.inner(), "broker-b", Some("127.0.0.1:1"));
+        let escape_bridge = broker.inner().escape_bridge();
+
+        let result = runtime.block_on(escape_bridge.async_put_message(message()));
+        assert_eq!(
+            result.put_message_status(),
+            PutMessageStatus::PutToRemoteBrokerFail
+        );
+        let result = runtime.block_on(escape_bridge.put_message_to_specific_queue(message()));
+        assert_eq!(
+            result.put_message_status(),
+            PutMessageStatus::PutToRemoteBrokerFail
+        );
+    }
+
+    #[test]
+    fn only_a_slave_acting_master_reads_from_the_master() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let _guard = runtime.enter();
+        let broker = slave_broker(false);
+        add_route(broker.inner(), "broker-a", Some("127.0.0.1:1"));
+        let (message, _, need_retry) = runtime.block_on(
+            broker
+                .inner()
+                .escape_bridge()
+                .get_message_from_master_async(&TOPIC.into(), 0, 0),
+        );
+        assert!(message.is_none());
+        assert!(!need_retry);
+
+        let broker = slave_broker(true);
+        add_route(broker.inner(), "broker-a", Some("127.0.0.1:1"));
+        let (message, _, need_retry) = runtime.block_on(
+            broker
+                .inner()
+                .escape_bridge()
+                .get_message_from_master_async(&TOPIC.into(), 0, 0),
+        );
+        assert!(message.is_none());
+        assert!(need_retry);
+    }
 
     #[test]
     fn transform_send_result2put_result_handles_none() {
@@ -0,0 +1,186 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use cheetah_string::CheetahString;
+use rocketmq_client_rust::producer::send_result::SendResult;
+use rocketmq_common::common::message::message_decoder;
+use rocketmq_common::common::message::message_ext::MessageExt;
+use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+use rocketmq_common::common::message::message_queue::MessageQueue;
+use rocketmq_common::common::message::MessageConst;
+use rocketmq_common::common::message::MessageTrait;
+use rocketmq_rust::ArcMut;
+use rocketmq_store::log_file::MessageStore;
+
+use crate::broker_error::BrokerError;
+use crate::broker_runtime::BrokerRuntimeInner;
+
+/// Timeout of a message sent to another broker of the cluster.
+pub(crate) const SEND_TIMEOUT: u64 = 3_000;
+
+/// Producer the broker sends with when it cannot store a message itself, e.g. a slave acting as
+/// master that has to hand retry and revive messages over to a writable broker.
+pub(crate) struct InnerProducer<MS> {
+    producer_group: CheetahString,
+    broker_runtime_inner: ArcMut<BrokerRuntimeInner<MS>>,
+}
+
+impl<MS> Clone for InnerProducer<MS> {
+    fn clone(&self) -> Self {
+        Self {
+            producer_group: self.producer_group.clone(),
+            broker_runtime_inner: self.broker_runtime_inner.clone(),
+        }
+    }
+}
+
+impl<MS: MessageStore> InnerProducer<MS> {
+    pub(crate) fn new(
+        producer_group: CheetahString,
+        broker_runtime_inner: ArcMut<BrokerRuntimeInner<MS>>,
+    ) -> Self {
+        Self {
+            producer_group,
+            broker_runtime_inner,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn producer_group(&self) -> &CheetahString {
+        &self.producer_group
+    }
+
+    /// Picks a queue of `topic` on another broker whose address is known.
+    pub(crate) async fn select_remote_queue(
+        &self,
+        topic: &CheetahString,
+    ) -> Option<(MessageQueue, CheetahString)> {
+        let topic_route_info_manager = self.broker_runtime_inner.topic_route_info_manager();
+        let topic_publish_info = topic_route_info_manager
+            .try_to_find_topic_publish_info(topic)
+            .await?;
+        let message_queue_list = &topic_publish_info.message_queue_list;
+        if !topic_publish_info.ok() || message_queue_list.is_empty() {
+            return None;
+        }
+        let local_broker_name = &self
+            .broker_runtime_inner
+            .broker_config()
+            .broker_identity
+            .broker_name;
+        let start = topic_publish_info.send_which_queue.increment_and_get() as usize;
+        (0..message_queue_list.len())
+            .map(|index| &message_queue_list[(start + index) % message_queue_list.len()])
+            .filter(|mq| mq.get_broker_name() != local_broker_name)
+            .find_map(|mq| {
+                topic_route_info_manager
+                    .find_broker_address_in_publish(Some(mq.get_broker_name()))
+                    .map(|broker_addr| (mq.clone(), broker_addr))
+            })
+    }
+
+    /// Address of `broker_name`, loading the route of `topic` first if it is not known yet.
+    pub(crate) async fn find_broker_addr(
+        &self,
+        topic: &CheetahString,
+        broker_name: &CheetahString,
+    ) -> Option<CheetahString> {
+        let topic_route_info_manager = self.broker_runtime_inner.topic_route_info_manager();
+        topic_route_info_manager
+            .try_to_find_topic_publish_info(topic)
+            .await?;
+        topic_route_info_manager.find_broker_address_in_publish(Some(broker_name))
+    }
+
+    /// Sends `msg` to `message_queue` on the broker at `broker_addr`.
+    pub(crate) async fn send(
+        &self,
+        msg: MessageExtBrokerInner,
+        message_queue: &MessageQueue,
+        broker_addr: &CheetahString,
+    ) -> crate::Result<SendResult> {
+        let mut msg = to_sendable_message(msg);
+        if msg.get_topic() != message_queue.get_topic() {
+            return Err(BrokerError::IllegalArgumentError(format!(
+                "message of topic {} can not be sent to a queue of topic {}",
+                msg.get_topic(),
+                message_queue.get_topic()
+            )));
+        }
+        msg.queue_id = message_queue.get_queue_id();
+        let producer_group = msg
+            .get_property(&CheetahString::from_static_str(
+                MessageConst::PROPERTY_PRODUCER_GROUP,
+            ))
+            .unwrap_or_else(|| self.producer_group.clone());
+        self.broker_runtime_inner
+            .broker_outer_api()
+            .send_message_to_specific_broker(
+                broker_addr,
+                message_queue.get_broker_name(),
+                msg,
+                producer_group,
+                SEND_TIMEOUT,
+            )
+            .await
+    }
+}
+
+/// Turns a message of the store into one that can be sent: the properties the store keeps as
+/// a string are restored, and the born host travels as a property because the receiving broker
+/// records the sending broker as born host.
+pub(crate) fn to_sendable_message(msg: MessageExtBrokerInner) -> MessageExt {
+    let MessageExtBrokerInner {
+        message_ext_inner: mut msg_ext,
+        properties_string,
+        ..
+    } = msg;
+    if msg_ext.get_properties().is_empty() && !properties_string.is_empty() {
+        msg_ext.set_properties(message_decoder::string_to_message_properties(Some(
+            &properties_string,
+        )));
+    }
+    let born_host = CheetahString::from_static_str(MessageConst::PROPERTY_BORN_HOST);
+    if msg_ext.get_property(&born_host).is_none() {
+        let value = CheetahString::from_string(msg_ext.born_host.to_string());
+        msg_ext.put_property(born_host, value);
+    }
+    msg_ext
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restores_properties_and_born_host() {
+        let mut msg = MessageExtBrokerInner::default();
+        msg.set_topic("topic".into());
+        msg.message_ext_inner.born_host = "10.0.0.1:5000".parse().unwrap();
+        msg.message_ext_inner.store_host = "10.0.0.2:10911".parse().unwrap();
+        msg.properties_string = message_decoder::message_properties_to_string(
+            &[("k".into(), "v".into())].into_iter().collect(),
+        );
+
+        let msg_ext = to_sendable_message(msg);
+        assert_eq!(msg_ext.get_property(&"k".into()), Some("v".into()));
+        assert_eq!(
+            msg_ext.get_property(&MessageConst::PROPERTY_BORN_HOST.into()),
+            Some("10.0.0.1:5000".into())
+        );
+        assert_eq!(msg_ext.store_host.to_string(), "10.0.0.2:10911");
+    }
+}
@@ -0,0 +1,147 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::time::Instant;
+
+use rocketmq_common::common::broker::shared_broker_config::SharedBrokerConfig;
+use rocketmq_store::base::commit_log_dispatcher::CommitLogDispatcher;
+use rocketmq_store::base::dispatch_request::DispatchRequest;
+use tracing::error;
+use tracing::warn;
+
+use crate::filter::manager::consumer_filter_manager::ConsumerFilterManager;
+use crate::filter::message_evaluation_context::MessageEvaluationContext;
+
+/// Evaluates the SQL92 filters of the topic against every dispatched message and hashes the
+/// filters it matches into the bit map saved in the consume queue ext.
+pub(crate) struct CommitLogDispatcherCalcBitMap {
+    broker_config: SharedBrokerConfig,
+    consumer_filter_manager: ConsumerFilterManager,
+}
+
+impl CommitLogDispatcherCalcBitMap {
+    pub(crate) fn new(
+        broker_config: SharedBrokerConfig,
+        consumer_filter_manager: ConsumerFilterManager,
+    ) -> Self {
+        Self {
+            broker_config,
+            consumer_filter_manager,
+        }
+    }
+}
+
+impl CommitLogDispatcher for CommitLogDispatcherCalcBitMap {
+    fn dispatch(&self, dispatch_request: &mut DispatchRequest) {
+        if !self.broker_config.get().enable_calc_filter_bit_map {
+            return;
+        }
+        let Some(bloom_filter) = self.consumer_filter_manager.get_bloom_filter() else {
+            return;
+        };
+        let start = Instant::now();
+        let context = MessageEvaluationContext::new(dispatch_request.properties_map.as_ref());
+        let mut filter_bit_map = vec![0u8; bloom_filter.m() as usize / 8];
+        let filter_num = self.consumer_filter_manager.for_each_filter_data(
+            &dispatch_request.topic,
+            |filter_data| {
+                let Some(compiled_expression) = filter_data.compiled_expression() else {
+                    error!(
+                        "[BUG] Consumer in filter manager has no compiled expression! {}",
+                        filter_data.consumer_group()
+                    );
+                    return;
+                };
+                let Some(bloom_filter_data) = filter_data.bloom_filter_data() else {
+                    error!(
+                        "[BUG] Consumer in filter manager has no bloom data! {}",
+                        filter_data.consumer_group()
+                    );
+                    return;
+                };
+                match compiled_expression.evaluate(&context) {
+                    Ok(result) if result.downcast_ref::<bool>() == Some(&true) => {
+                        bloom_filter.hash_to(bloom_filter_data, &mut filter_bit_map);
+                    }
+                    Ok(_) => {}
+                    Err(e) => error!(
+                        "Calc filter bit map error!commitLogOffset={}, consumer={}, {}",
+                        dispatch_request.commit_log_offset,
+                        filter_data.consumer_group(),
+                        e
+                    ),
+                }
+            },
+        );
+        if filter_num == 0 {
+            return;
+        }
+        dispatch_request.bit_map = Some(filter_bit_map);
+
+        let elapsed = start.elapsed().as_millis();
+        if elapsed >= 1 {
+            warn!(
+                "Spend {} ms to calc bit map, consumerNum={}, topic={}",
+                elapsed, filter_num, dispatch_request.topic
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use cheetah_string::CheetahString;
+    use rocketmq_common::common::broker::broker_config::BrokerConfig;
+    use rocketmq_common::common::filter::expression_type::ExpressionType;
+
+    use super::*;
+
+    #[test]
+    fn hashes_matched_filters_into_bit_map() {
+        let broker_config = SharedBrokerConfig::new(BrokerConfig {
+            enable_calc_filter_bit_map: true,
+            ..Default::default()
+        });
+        let manager = ConsumerFilterManager::new(broker_config.clone());
+        let sql92 = ExpressionType::SQL92.into();
+        let topic = CheetahString::from("topic");
+        manager.register(&topic, &"g1".into(), &"a = 1".into(), &sql92, 1);
+        manager.register(&topic, &"g2".into(), &"a = 2".into(), &sql92, 1);
+        let dispatcher = CommitLogDispatcherCalcBitMap::new(broker_config, manager.clone());
+
+        let mut dispatch_request = DispatchRequest {
+            topic: topic.clone(),
+            properties_map: Some(HashMap::from([("a".into(), "1".into())])),
+            ..Default::default()
+        };
+        dispatcher.dispatch(&mut dispatch_request);
+        let bit_map = dispatch_request.bit_map.unwrap();
+        let bloom_filter = manager.get_bloom_filter().unwrap();
+        let g1 = manager
+            .get_consumer_filter_data(&topic, &"g1".into())
+            .unwrap();
+        assert!(bloom_filter.is_hit(g1.bloom_filter_data().unwrap(), &bit_map));
+
+        let mut dispatch_request = DispatchRequest {
+            topic: "other".into(),
+            ..Default::default()
+        };
+        dispatcher.dispatch(&mut dispatch_request);
+        assert!(dispatch_request.bit_map.is_none());
+    }
+}
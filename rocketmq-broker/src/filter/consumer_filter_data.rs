@@ -17,6 +17,7 @@
 use std::sync::Arc;
 
 use cheetah_string::CheetahString;
+use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_filter::expression::Expression;
 use rocketmq_filter::utils::bloom_filter_data::BloomFilterData;
 use serde::Deserialize;
@@ -70,6 +71,30 @@ impl ConsumerFilterData {
         self.client_version
     }
 
+    pub fn compiled_expression(&self) -> Option<&Arc<Box<dyn Expression + Send + Sync + 'static>>> {
+        self.compiled_expression.as_ref()
+    }
+
+    /// Whether every consumer of the group stopped subscribing to the topic.
+    pub fn is_dead(&self) -> bool {
+        self.dead_time >= self.born_time
+    }
+
+    /// Whether the message was stored after the filter was built, only the bit maps of such
+    /// messages were calculated with the filter.
+    pub fn is_msg_in_live(&self, msg_store_time: u64) -> bool {
+        msg_store_time > self.born_time
+    }
+
+    /// Milliseconds since the filter died, `None` while it is alive.
+    pub fn how_long_after_death(&self) -> Option<u64> {
+        if self.is_dead() {
+            Some(get_current_millis().saturating_sub(self.dead_time))
+        } else {
+            None
+        }
+    }
+
     pub fn set_consumer_group(&mut self, consumer_group: CheetahString) {
         self.consumer_group = consumer_group;
     }
@@ -101,4 +126,11 @@ impl ConsumerFilterData {
     pub fn set_client_version(&mut self, client_version: u64) {
         self.client_version = client_version;
     }
+
+    pub fn set_compiled_expression(
+        &mut self,
+        compiled_expression: Option<Arc<Box<dyn Expression + Send + Sync + 'static>>>,
+    ) {
+        self.compiled_expression = compiled_expression;
+    }
 }
@@ -15,21 +15,51 @@
  * limitations under the License.
  */
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use cheetah_string::CheetahString;
+use rocketmq_common::common::message::message_decoder;
+use rocketmq_common::common::message::MessageConst;
+use rocketmq_common::common::mix_all::RETRY_GROUP_TOPIC_PREFIX;
+use rocketmq_remoting::protocol::heartbeat::subscription_data::SubscriptionData;
 use rocketmq_store::consume_queue::consume_queue_ext::CqExtUnit;
 use rocketmq_store::filter::MessageFilter;
 
-pub struct ExpressionForRetryMessageFilter;
+use crate::filter::consumer_filter_data::ConsumerFilterData;
+use crate::filter::expression_message_filter;
+use crate::filter::expression_message_filter::ExpressionMessageFilter;
+use crate::filter::manager::consumer_filter_manager::ConsumerFilterManager;
+
+/// Filters the messages of a retry topic with the SQL92 expression the group subscribes their
+/// original topic with, the messages of other topics as [`ExpressionMessageFilter`] does.
+pub struct ExpressionForRetryMessageFilter {
+    inner: ExpressionMessageFilter,
+}
+
+impl ExpressionForRetryMessageFilter {
+    pub fn new(
+        subscription_data: Option<SubscriptionData>,
+        consumer_filter_data: Option<ConsumerFilterData>,
+        consumer_filter_manager: Arc<ConsumerFilterManager>,
+    ) -> Self {
+        ExpressionForRetryMessageFilter {
+            inner: ExpressionMessageFilter::new(
+                subscription_data,
+                consumer_filter_data,
+                consumer_filter_manager,
+            ),
+        }
+    }
+}
 
-#[allow(unused_variables)]
 impl MessageFilter for ExpressionForRetryMessageFilter {
     fn is_matched_by_consume_queue(
         &self,
         tags_code: Option<i64>,
         cq_ext_unit: Option<&CqExtUnit>,
     ) -> bool {
-        true
+        self.inner
+            .is_matched_by_consume_queue(tags_code, cq_ext_unit)
     }
 
     fn is_matched_by_commit_log(
@@ -37,6 +67,83 @@ impl MessageFilter for ExpressionForRetryMessageFilter {
         msg_buffer: Option<&[u8]>,
         properties: Option<&HashMap<CheetahString, CheetahString>>,
     ) -> bool {
-        true
+        let Some(subscription_data) = self.inner.subscription_data.as_ref() else {
+            return true;
+        };
+        if subscription_data.class_filter_mode {
+            return true;
+        }
+        let Some(group) = subscription_data
+            .topic
+            .as_str()
+            .strip_prefix(RETRY_GROUP_TOPIC_PREFIX)
+        else {
+            return self.inner.is_matched_by_commit_log(msg_buffer, properties);
+        };
+
+        // the filter of a retry topic is the one of the topic the message was sent to, which
+        // is only known from the properties of the message
+        let decoded_properties;
+        let properties = match properties {
+            Some(properties) => Some(properties),
+            None => {
+                decoded_properties = msg_buffer.and_then(message_decoder::decode_properties);
+                decoded_properties.as_ref()
+            }
+        };
+        let Some(real_topic) =
+            properties.and_then(|properties| properties.get(MessageConst::PROPERTY_RETRY_TOPIC))
+        else {
+            return true;
+        };
+        let Some(real_filter_data) = self
+            .inner
+            .consumer_filter_manager
+            .get_consumer_filter_data(real_topic, &CheetahString::from(group))
+        else {
+            return true;
+        };
+        expression_message_filter::evaluate(&real_filter_data, msg_buffer, properties)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocketmq_common::common::filter::expression_type::ExpressionType;
+
+    use super::*;
+
+    #[test]
+    fn retry_messages_are_filtered_by_the_filter_of_their_topic() {
+        let manager = Arc::new(ConsumerFilterManager::default());
+        assert!(manager.register(
+            &"topic".into(),
+            &"group".into(),
+            &"a = 1".into(),
+            &ExpressionType::SQL92.into(),
+            1,
+        ));
+        let subscription_data = SubscriptionData {
+            topic: format!("{}group", RETRY_GROUP_TOPIC_PREFIX).into(),
+            sub_string: SubscriptionData::SUB_ALL.into(),
+            expression_type: ExpressionType::TAG.into(),
+            ..Default::default()
+        };
+        let filter = ExpressionForRetryMessageFilter::new(Some(subscription_data), None, manager);
+
+        let properties = |a: &str| {
+            HashMap::from([
+                (MessageConst::PROPERTY_RETRY_TOPIC.into(), "topic".into()),
+                ("a".into(), a.into()),
+            ])
+        };
+        assert!(filter.is_matched_by_consume_queue(Some(1), None));
+        assert!(filter.is_matched_by_commit_log(None, Some(&properties("1"))));
+        assert!(!filter.is_matched_by_commit_log(None, Some(&properties("2"))));
+        let sent_to_another_topic = HashMap::from([
+            (MessageConst::PROPERTY_RETRY_TOPIC.into(), "other".into()),
+            ("a".into(), "2".into()),
+        ]);
+        assert!(filter.is_matched_by_commit_log(None, Some(&sent_to_another_topic)));
     }
 }
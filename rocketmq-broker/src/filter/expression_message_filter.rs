@@ -19,17 +19,20 @@ use std::sync::Arc;
 
 use cheetah_string::CheetahString;
 use rocketmq_common::common::filter::expression_type::ExpressionType;
+use rocketmq_common::common::message::message_decoder;
 use rocketmq_remoting::protocol::heartbeat::subscription_data::SubscriptionData;
 use rocketmq_store::consume_queue::consume_queue_ext::CqExtUnit;
 use rocketmq_store::filter::MessageFilter;
+use tracing::error;
 
 use crate::filter::consumer_filter_data::ConsumerFilterData;
 use crate::filter::manager::consumer_filter_manager::ConsumerFilterManager;
+use crate::filter::message_evaluation_context::MessageEvaluationContext;
 
 pub struct ExpressionMessageFilter {
-    subscription_data: Option<SubscriptionData>,
+    pub(crate) subscription_data: Option<SubscriptionData>,
     consumer_filter_data: Option<ConsumerFilterData>,
-    consumer_filter_manager: Arc<ConsumerFilterManager>,
+    pub(crate) consumer_filter_manager: Arc<ConsumerFilterManager>,
     bloom_data_valid: bool,
 }
 
@@ -56,7 +59,6 @@ impl ExpressionMessageFilter {
     }
 }
 
-#[allow(unused_variables)]
 impl MessageFilter for ExpressionMessageFilter {
     fn is_matched_by_consume_queue(
         &self,
@@ -81,7 +83,33 @@ impl MessageFilter for ExpressionMessageFilter {
                 .code_set
                 .contains(&(tags_code.unwrap() as i32))
         } else {
-            unimplemented!("SQL92 expression type is not supported yet.")
+            let Some(real_filter_data) = self.consumer_filter_data.as_ref() else {
+                return true;
+            };
+            let Some(bloom_filter_data) = real_filter_data.bloom_filter_data() else {
+                return true;
+            };
+            if real_filter_data.compiled_expression().is_none() {
+                return true;
+            }
+            // the bit map of a message stored before the filter was built lacks its bits
+            let Some(cq_ext_unit) = cq_ext_unit.filter(|cq_ext_unit| {
+                real_filter_data.is_msg_in_live(cq_ext_unit.msg_store_time() as u64)
+            }) else {
+                return true;
+            };
+            let Some(filter_bit_map) = cq_ext_unit.filter_bit_map() else {
+                return true;
+            };
+            if !self.bloom_data_valid
+                || filter_bit_map.len() * 8 != bloom_filter_data.bit_num() as usize
+            {
+                return true;
+            }
+            match self.consumer_filter_manager.get_bloom_filter() {
+                Some(bloom_filter) => bloom_filter.is_hit(bloom_filter_data, filter_bit_map),
+                None => true,
+            }
         }
     }
 
@@ -104,9 +132,117 @@ impl MessageFilter for ExpressionMessageFilter {
             return true;
         }
         let real_filter_data = self.consumer_filter_data.as_ref().unwrap();
-        if real_filter_data.expression().is_none() || real_filter_data.expression_type().is_none() {
-            return true;
+        evaluate(real_filter_data, msg_buffer, properties)
+    }
+}
+
+/// Evaluates the SQL92 expression of `real_filter_data` against the properties of a message,
+/// decoded from `msg_buffer` when `properties` is `None`. Matches when there is no expression.
+pub(crate) fn evaluate(
+    real_filter_data: &ConsumerFilterData,
+    msg_buffer: Option<&[u8]>,
+    properties: Option<&HashMap<CheetahString, CheetahString>>,
+) -> bool {
+    if real_filter_data.expression().is_none() || real_filter_data.expression_type().is_none() {
+        return true;
+    }
+    let Some(compiled_expression) = real_filter_data.compiled_expression() else {
+        return true;
+    };
+
+    let decoded_properties;
+    let properties = match properties {
+        Some(properties) => Some(properties),
+        None => {
+            decoded_properties = msg_buffer.and_then(message_decoder::decode_properties);
+            decoded_properties.as_ref()
+        }
+    };
+    let context = MessageEvaluationContext::new(properties);
+    match compiled_expression.evaluate(&context) {
+        Ok(result) => result.downcast_ref::<bool>().copied().unwrap_or(false),
+        Err(e) => {
+            error!(
+                "Message Filter error, group: {}, topic: {}, error: {}",
+                real_filter_data.consumer_group(),
+                real_filter_data.topic(),
+                e
+            );
+            false
         }
-        unimplemented!("SQL92 expression type is not supported yet.")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocketmq_common::common::broker::shared_broker_config::SharedBrokerConfig;
+    use rocketmq_common::TimeUtils::get_current_millis;
+
+    use super::*;
+
+    #[test]
+    fn sql92_filter_matches_message_properties() {
+        let subscription_data = SubscriptionData {
+            topic: "topic".into(),
+            sub_string: "a BETWEEN 1 AND 3 AND b = 'x'".into(),
+            expression_type: ExpressionType::SQL92.into(),
+            ..Default::default()
+        };
+        let consumer_filter_data = ConsumerFilterManager::build(
+            "topic".into(),
+            "group".into(),
+            Some(subscription_data.sub_string.clone()),
+            Some(subscription_data.expression_type.clone()),
+            1,
+        );
+        let filter = ExpressionMessageFilter::new(
+            Some(subscription_data),
+            consumer_filter_data,
+            Arc::new(ConsumerFilterManager::default()),
+        );
+        let matched = HashMap::from([("a".into(), "2".into()), ("b".into(), "x".into())]);
+        let not_matched = HashMap::from([("a".into(), "4".into()), ("b".into(), "x".into())]);
+        assert!(filter.is_matched_by_consume_queue(Some(1), None));
+        assert!(filter.is_matched_by_commit_log(None, Some(&matched)));
+        assert!(!filter.is_matched_by_commit_log(None, Some(&not_matched)));
+        assert!(!filter.is_matched_by_commit_log(None, None));
+    }
+
+    #[test]
+    fn sql92_filter_skips_messages_by_bit_map() {
+        let manager = Arc::new(ConsumerFilterManager::new(SharedBrokerConfig::default()));
+        let bloom_filter = *manager.get_bloom_filter().unwrap();
+        let bloom_filter_data = bloom_filter.generate("group#topic");
+        let mut consumer_filter_data = ConsumerFilterManager::build(
+            "topic".into(),
+            "group".into(),
+            Some("a = 1".into()),
+            Some(ExpressionType::SQL92.into()),
+            1,
+        )
+        .unwrap();
+        consumer_filter_data.set_bloom_filter_data(Some(bloom_filter_data.clone()));
+        let subscription_data = SubscriptionData {
+            topic: "topic".into(),
+            sub_string: "a = 1".into(),
+            expression_type: ExpressionType::SQL92.into(),
+            ..Default::default()
+        };
+        let filter = ExpressionMessageFilter::new(
+            Some(subscription_data),
+            Some(consumer_filter_data),
+            manager,
+        );
+
+        let store_time = get_current_millis() as i64 + 1000;
+        let mut bits = vec![0u8; bloom_filter.m() as usize / 8];
+        let missed = CqExtUnit::new(0, store_time, Some(bits.clone()));
+        assert!(bloom_filter.hash_to(&bloom_filter_data, &mut bits));
+        let hit = CqExtUnit::new(0, store_time, Some(bits));
+        assert!(!filter.is_matched_by_consume_queue(None, Some(&missed)));
+        assert!(filter.is_matched_by_consume_queue(None, Some(&hit)));
+        let stored_before_filter = CqExtUnit::new(0, 0, missed.filter_bit_map().clone());
+        assert!(filter.is_matched_by_consume_queue(None, Some(&stored_before_filter)));
+        assert!(filter.is_matched_by_consume_queue(None, None));
     }
 }
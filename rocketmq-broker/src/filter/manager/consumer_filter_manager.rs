@@ -19,11 +19,17 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use cheetah_string::CheetahString;
-use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::common::broker::shared_broker_config::SharedBrokerConfig;
 use rocketmq_common::common::config_manager::ConfigManager;
 use rocketmq_common::common::filter::expression_type::ExpressionType;
+use rocketmq_common::utils::serde_json_utils::SerdeJsonUtils;
 use rocketmq_common::TimeUtils::get_current_millis;
+use rocketmq_filter::expression::Expression;
+use rocketmq_filter::filter::filter_factory::FilterFactory;
 use rocketmq_filter::utils::bloom_filter::BloomFilter;
+use rocketmq_remoting::protocol::heartbeat::subscription_data::SubscriptionData;
+use tracing::error;
+use tracing::info;
 
 use crate::broker_path_config_helper::get_consumer_filter_path;
 use crate::filter::consumer_filter_data::ConsumerFilterData;
@@ -33,22 +39,23 @@ const MS_24_HOUR: u64 = Duration::from_hours(24).as_millis() as u64;
 
 #[derive(Default, Clone)]
 pub(crate) struct ConsumerFilterManager {
-    broker_config: Arc<BrokerConfig>,
+    broker_config: SharedBrokerConfig,
     consumer_filter_wrapper: Arc<parking_lot::RwLock<ConsumerFilterWrapper>>,
     bloom_filter: Option<BloomFilter>,
 }
 
 impl ConsumerFilterManager {
-    pub fn new(mut broker_config: Arc<BrokerConfig>) -> Self {
+    pub fn new(broker_config: SharedBrokerConfig) -> Self {
         let consumer_filter_wrapper =
             Arc::new(parking_lot::RwLock::new(ConsumerFilterWrapper::default()));
         let bloom_filter = BloomFilter::new(
-            broker_config.max_error_rate_of_bloom_filter,
-            broker_config.expect_consumer_num_use_filter,
+            broker_config.get().max_error_rate_of_bloom_filter,
+            broker_config.get().expect_consumer_num_use_filter,
         )
         .unwrap();
-        let broker_config_mut = Arc::make_mut(&mut broker_config);
-        broker_config_mut.bit_map_length_consume_queue_ext = bloom_filter.m();
+        broker_config.modify(|broker_config| {
+            broker_config.bit_map_length_consume_queue_ext = bloom_filter.m()
+        });
         ConsumerFilterManager {
             broker_config,
             consumer_filter_wrapper,
@@ -57,33 +64,62 @@ impl ConsumerFilterManager {
     }
 }
 
-//Fully implemented will be removed
-#[allow(unused_variables)]
 impl ConfigManager for ConsumerFilterManager {
-    fn decode0(&mut self, key: &[u8], body: &[u8]) {
-        todo!()
-    }
-
-    fn stop(&mut self) -> bool {
-        todo!()
-    }
-
     fn config_file_path(&self) -> String {
-        get_consumer_filter_path(self.broker_config.store_path_root_dir.as_str())
-    }
-
-    fn encode(&mut self) -> String {
-        todo!()
+        get_consumer_filter_path(self.broker_config.get().store_path_root_dir.as_str())
     }
 
     fn encode_pretty(&self, pretty_format: bool) -> String {
-        "".to_string()
+        let mut consumer_filter_wrapper = self.consumer_filter_wrapper.write();
+        consumer_filter_wrapper.clean(MS_24_HOUR);
+        if pretty_format {
+            SerdeJsonUtils::to_json_pretty(&*consumer_filter_wrapper).expect("encode failed")
+        } else {
+            SerdeJsonUtils::to_json(&*consumer_filter_wrapper).expect("encode failed")
+        }
     }
 
-    fn decode(&self, json_string: &str) {}
+    fn decode(&self, json_string: &str) {
+        if json_string.is_empty() {
+            return;
+        }
+        let mut consumer_filter_wrapper: ConsumerFilterWrapper =
+            match SerdeJsonUtils::from_json_str(json_string) {
+                Ok(consumer_filter_wrapper) => consumer_filter_wrapper,
+                Err(e) => {
+                    error!("decode consumer filter data failed: {}", e);
+                    return;
+                }
+            };
+        for filter_data in consumer_filter_wrapper.filter_data_iter_mut() {
+            let compiled_expression = filter_data.expression_type().and_then(|type_| {
+                compile(
+                    type_,
+                    filter_data.expression()?,
+                    filter_data.topic(),
+                    filter_data.consumer_group(),
+                )
+            });
+            filter_data.set_compiled_expression(compiled_expression);
+            // bit maps calculated with another bloom filter can not be used anymore
+            if let (Some(bloom_filter), Some(bloom_filter_data)) =
+                (self.bloom_filter.as_ref(), filter_data.bloom_filter_data())
+            {
+                if !bloom_filter.is_valid(Some(bloom_filter_data)) {
+                    info!("Bloom filter is changed! So ignore all filter data persisted!");
+                    return;
+                }
+            }
+            if filter_data.dead_time() == 0 {
+                // every consumer is considered dead until its next heartbeat
+                let dead_time = get_current_millis().saturating_sub(30 * 1000);
+                filter_data.set_dead_time(dead_time.max(filter_data.born_time()));
+            }
+        }
+        *self.consumer_filter_wrapper.write() = consumer_filter_wrapper;
+    }
 }
 
-#[allow(unused_variables)]
 impl ConsumerFilterManager {
     pub fn build(
         topic: CheetahString,
@@ -95,6 +131,12 @@ impl ConsumerFilterManager {
         if ExpressionType::is_tag_type(type_.as_deref()) {
             return None;
         }
+        let compiled_expression = compile(
+            type_.as_ref()?,
+            expression.as_ref()?,
+            &topic,
+            &consumer_group,
+        )?;
 
         let mut consumer_filter_data = ConsumerFilterData::default();
         consumer_filter_data.set_topic(topic);
@@ -104,33 +146,227 @@ impl ConsumerFilterManager {
         consumer_filter_data.set_expression(expression);
         consumer_filter_data.set_expression_type(type_);
         consumer_filter_data.set_client_version(client_version);
-
-        /*        let filter_factory = FilterFactory;
-                match filter_factory.get(&type_).compile(&expression) {
-                    Ok(compiled_expression) => {
-                        consumer_filter_data.set_compiled_expression(compiled_expression);
-                    }
-                    Err(e) => {
-                        eprintln!(
-                            "parse error: expr={}, topic={}, group={}, error={}",
-                            expression, topic, consumer_group, e
-                        );
-                        return None;
-                    }
-                }
-        */
+        consumer_filter_data.set_compiled_expression(Some(compiled_expression));
         Some(consumer_filter_data)
     }
 
+    /// Registers the filter of a subscription, returns whether the registered filter changed.
+    pub fn register(
+        &self,
+        topic: &CheetahString,
+        consumer_group: &CheetahString,
+        expression: &CheetahString,
+        type_: &CheetahString,
+        client_version: u64,
+    ) -> bool {
+        if ExpressionType::is_tag_type(Some(type_.as_str())) || expression.is_empty() {
+            return false;
+        }
+        let bloom_filter_data = self
+            .bloom_filter
+            .as_ref()
+            .map(|bloom_filter| bloom_filter.generate(&format!("{}#{}", consumer_group, topic)));
+        self.consumer_filter_wrapper.write().register(
+            topic,
+            consumer_group,
+            expression,
+            type_,
+            bloom_filter_data,
+            client_version,
+        )
+    }
+
+    /// Registers the filters of every subscription of a group, the filters of topics the group
+    /// no longer subscribes to die.
+    pub fn register_group<'a>(
+        &self,
+        consumer_group: &CheetahString,
+        subscriptions: impl IntoIterator<Item = &'a SubscriptionData>,
+    ) {
+        let mut topics = Vec::new();
+        for subscription_data in subscriptions {
+            self.register(
+                &subscription_data.topic,
+                consumer_group,
+                &subscription_data.sub_string,
+                &subscription_data.expression_type,
+                subscription_data.sub_version as u64,
+            );
+            topics.push(subscription_data.topic.as_str());
+        }
+        self.consumer_filter_wrapper
+            .write()
+            .retain_group_topics(consumer_group.as_str(), &topics);
+    }
+
+    pub fn unregister(&self, consumer_group: &CheetahString) {
+        self.consumer_filter_wrapper
+            .write()
+            .unregister(consumer_group.as_str());
+    }
+
     pub fn get_consumer_filter_data(
         &self,
         topic: &CheetahString,
         consumer_group: &CheetahString,
     ) -> Option<ConsumerFilterData> {
-        None
+        self.consumer_filter_wrapper
+            .read()
+            .get(topic.as_str(), consumer_group.as_str())
+            .cloned()
+    }
+
+    /// Calls `f` with the filter of every group subscribed to `topic`, returns how many there are.
+    pub fn for_each_filter_data(
+        &self,
+        topic: &CheetahString,
+        mut f: impl FnMut(&ConsumerFilterData),
+    ) -> usize {
+        let consumer_filter_wrapper = self.consumer_filter_wrapper.read();
+        let mut count = 0;
+        for filter_data in consumer_filter_wrapper.topic_filter_data(topic.as_str()) {
+            f(filter_data);
+            count += 1;
+        }
+        count
     }
 
     pub fn get_bloom_filter(&self) -> Option<&BloomFilter> {
         self.bloom_filter.as_ref()
     }
+
+    pub fn unregister_topic(&self, topic: &CheetahString) {
+        if self
+            .consumer_filter_wrapper
+            .write()
+            .remove_topic(topic.as_str())
+        {
+            info!("Unregister consumer filters of deleted topic {}", topic);
+        }
+    }
+}
+
+fn compile(
+    type_: &CheetahString,
+    expression: &CheetahString,
+    topic: &CheetahString,
+    consumer_group: &CheetahString,
+) -> Option<Arc<Box<dyn Expression + Send + Sync + 'static>>> {
+    match FilterFactory::instance().compile(type_.as_str(), expression.as_str()) {
+        Ok(compiled_expression) => Some(Arc::new(compiled_expression)),
+        Err(e) => {
+            error!(
+                "parse error: expr={}, topic={}, group={}, error={}",
+                expression, topic, consumer_group, e
+            );
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manager() -> ConsumerFilterManager {
+        ConsumerFilterManager::new(SharedBrokerConfig::default())
+    }
+
+    fn subscription(topic: &str, expression: &str) -> SubscriptionData {
+        SubscriptionData {
+            topic: topic.into(),
+            sub_string: expression.into(),
+            expression_type: ExpressionType::SQL92.into(),
+            sub_version: 1,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn register_keeps_the_latest_version() {
+        let manager = manager();
+        let (topic, group, sql92) = ("topic".into(), "group".into(), ExpressionType::SQL92.into());
+        assert!(manager.register(&topic, &group, &"a > 1".into(), &sql92, 2));
+        assert!(!manager.register(&topic, &group, &"a > 2".into(), &sql92, 1));
+        assert!(!manager.register(&topic, &group, &"a >".into(), &sql92, 2));
+        let filter_data = manager.get_consumer_filter_data(&topic, &group).unwrap();
+        assert_eq!(filter_data.expression().unwrap().as_str(), "a > 1");
+        assert!(filter_data.compiled_expression().is_some());
+
+        assert!(manager.register(&topic, &group, &"a > 2".into(), &sql92, 3));
+        let filter_data = manager.get_consumer_filter_data(&topic, &group).unwrap();
+        assert_eq!(filter_data.expression().unwrap().as_str(), "a > 2");
+        assert_eq!(filter_data.client_version(), 3);
+
+        assert!(!manager.register(
+            &topic,
+            &"tag_group".into(),
+            &"*".into(),
+            &ExpressionType::TAG.into(),
+            1
+        ));
+    }
+
+    #[test]
+    fn unregister_topic_drops_the_filters_of_the_topic() {
+        let manager = manager();
+        let group = CheetahString::from("group");
+        manager.register_group(
+            &group,
+            &[subscription("t1", "a = 1"), subscription("t2", "a = 2")],
+        );
+        manager.unregister_topic(&"t1".into());
+        assert!(manager
+            .get_consumer_filter_data(&"t1".into(), &group)
+            .is_none());
+        assert!(manager
+            .get_consumer_filter_data(&"t2".into(), &group)
+            .is_some());
+    }
+
+    #[test]
+    fn topics_no_longer_subscribed_die() {
+        let manager = manager();
+        let group = CheetahString::from("group");
+        manager.register_group(
+            &group,
+            &[subscription("t1", "a = 1"), subscription("t2", "a = 2")],
+        );
+        manager.register_group(&group, &[subscription("t1", "a = 1")]);
+        let t1 = manager
+            .get_consumer_filter_data(&"t1".into(), &group)
+            .unwrap();
+        let t2 = manager
+            .get_consumer_filter_data(&"t2".into(), &group)
+            .unwrap();
+        assert!(!t1.is_dead());
+        assert!(t2.is_dead());
+    }
+
+    #[test]
+    fn decode_compiles_persisted_filters() {
+        let manager = manager();
+        let group = CheetahString::from("group");
+        manager.register_group(&group, &[subscription("topic", "a IN ('x', 'y')")]);
+        let json = manager.encode_pretty(false);
+
+        let loaded = ConsumerFilterManager::new(SharedBrokerConfig::default());
+        loaded.decode(&json);
+        let filter_data = loaded
+            .get_consumer_filter_data(&"topic".into(), &group)
+            .unwrap();
+        assert!(filter_data.compiled_expression().is_some());
+        assert!(filter_data.is_dead());
+        assert!(loaded.register(
+            &"topic".into(),
+            &group,
+            &"a IN ('x', 'y')".into(),
+            &ExpressionType::SQL92.into(),
+            1
+        ));
+        let filter_data = loaded
+            .get_consumer_filter_data(&"topic".into(), &group)
+            .unwrap();
+        assert!(!filter_data.is_dead());
+    }
 }
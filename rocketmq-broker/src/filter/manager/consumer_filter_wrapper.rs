@@ -16,10 +16,16 @@
  */
 use std::collections::HashMap;
 
+use cheetah_string::CheetahString;
+use rocketmq_common::TimeUtils::get_current_millis;
+use rocketmq_filter::utils::bloom_filter_data::BloomFilterData;
 use serde::Deserialize;
 use serde::Serialize;
+use tracing::info;
+use tracing::warn;
 
 use crate::filter::consumer_filter_data::ConsumerFilterData;
+use crate::filter::manager::consumer_filter_manager::ConsumerFilterManager;
 
 #[derive(Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
@@ -30,6 +36,222 @@ pub struct ConsumerFilterWrapper {
 #[derive(Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct FilterDataMapByTopic {
+    #[serde(rename = "groupFilterData")]
     filter_data_map: HashMap<String /* consumer group */, ConsumerFilterData>,
     topic: String,
 }
+
+impl ConsumerFilterWrapper {
+    /// Removes the filters of every group subscribed to `topic`.
+    pub fn remove_topic(&mut self, topic: &str) -> bool {
+        self.filter_data_by_topic.remove(topic).is_some()
+    }
+
+    pub fn get(&self, topic: &str, consumer_group: &str) -> Option<&ConsumerFilterData> {
+        self.filter_data_by_topic
+            .get(topic)?
+            .filter_data_map
+            .get(consumer_group)
+    }
+
+    /// Filters of every group subscribed to `topic`.
+    pub fn topic_filter_data(&self, topic: &str) -> impl Iterator<Item = &ConsumerFilterData> {
+        self.filter_data_by_topic
+            .get(topic)
+            .into_iter()
+            .flat_map(|filter_data_map| filter_data_map.filter_data_map.values())
+    }
+
+    pub fn register(
+        &mut self,
+        topic: &CheetahString,
+        consumer_group: &CheetahString,
+        expression: &CheetahString,
+        type_: &CheetahString,
+        bloom_filter_data: Option<BloomFilterData>,
+        client_version: u64,
+    ) -> bool {
+        self.filter_data_by_topic
+            .entry(topic.to_string())
+            .or_insert_with(|| FilterDataMapByTopic::new(topic.to_string()))
+            .register(
+                consumer_group,
+                expression,
+                type_,
+                bloom_filter_data,
+                client_version,
+            )
+    }
+
+    /// Marks the filters of `consumer_group` on topics other than `topics` as dead.
+    pub fn retain_group_topics(&mut self, consumer_group: &str, topics: &[&str]) {
+        for (topic, filter_data_map) in self.filter_data_by_topic.iter_mut() {
+            if topics.contains(&topic.as_str()) {
+                continue;
+            }
+            if let Some(filter_data) = filter_data_map.filter_data_map.get_mut(consumer_group) {
+                if !filter_data.is_dead() {
+                    filter_data.set_dead_time(get_current_millis());
+                    info!(
+                        "Consumer's filter data is dead, group {} no longer subscribes to topic {}",
+                        consumer_group, topic
+                    );
+                }
+            }
+        }
+    }
+
+    pub fn unregister(&mut self, consumer_group: &str) {
+        for filter_data_map in self.filter_data_by_topic.values_mut() {
+            filter_data_map.unregister(consumer_group);
+        }
+    }
+
+    /// Drops the filters dead for longer than `clean_time_span` and the topics left without any.
+    pub fn clean(&mut self, clean_time_span: u64) {
+        self.filter_data_by_topic.retain(|topic, filter_data_map| {
+            filter_data_map
+                .filter_data_map
+                .retain(|consumer_group, filter_data| {
+                    let died_too_long = filter_data
+                        .how_long_after_death()
+                        .is_some_and(|time| time >= clean_time_span);
+                    if died_too_long {
+                        info!(
+                            "Remove filter of consumer {} on topic {}, died too long!",
+                            consumer_group, topic
+                        );
+                    }
+                    !died_too_long
+                });
+            if filter_data_map.filter_data_map.is_empty() {
+                info!("Topic has no consumer, remove it! {}", topic);
+                return false;
+            }
+            true
+        });
+    }
+
+    pub fn filter_data_iter_mut(&mut self) -> impl Iterator<Item = &mut ConsumerFilterData> {
+        self.filter_data_by_topic
+            .values_mut()
+            .flat_map(|filter_data_map| filter_data_map.filter_data_map.values_mut())
+    }
+}
+
+impl FilterDataMapByTopic {
+    pub fn new(topic: String) -> Self {
+        Self {
+            filter_data_map: HashMap::new(),
+            topic,
+        }
+    }
+
+    /// Registers the filter a consumer of the group subscribes with, a filter of an older
+    /// subscription version than the registered one is ignored.
+    pub fn register(
+        &mut self,
+        consumer_group: &CheetahString,
+        expression: &CheetahString,
+        type_: &CheetahString,
+        bloom_filter_data: Option<BloomFilterData>,
+        client_version: u64,
+    ) -> bool {
+        let Some(old) = self.filter_data_map.get_mut(consumer_group.as_str()) else {
+            let Some(mut consumer_filter_data) = ConsumerFilterManager::build(
+                CheetahString::from_slice(&self.topic),
+                consumer_group.clone(),
+                Some(expression.clone()),
+                Some(type_.clone()),
+                client_version,
+            ) else {
+                return false;
+            };
+            info!(
+                "New consumer filter registered, group: {}, topic: {}, expression: {}",
+                consumer_group, self.topic, expression
+            );
+            consumer_filter_data.set_bloom_filter_data(bloom_filter_data);
+            self.filter_data_map
+                .insert(consumer_group.to_string(), consumer_filter_data);
+            return true;
+        };
+
+        let changed = old.expression() != Some(expression) || old.expression_type() != Some(type_);
+        if client_version <= old.client_version() {
+            if changed {
+                warn!(
+                    "Ignore consumer({} : {}) filter, because of version {} <= {}, but maybe info \
+                     changed!old={:?}:{:?}, ignored={}:{}",
+                    consumer_group,
+                    self.topic,
+                    client_version,
+                    old.client_version(),
+                    old.expression_type(),
+                    old.expression(),
+                    type_,
+                    expression
+                );
+            }
+            if client_version == old.client_version() && old.is_dead() {
+                re_alive(old);
+                return true;
+            }
+            return false;
+        }
+
+        if !changed {
+            old.set_client_version(client_version);
+            if old.is_dead() {
+                re_alive(old);
+            }
+            return true;
+        }
+        match ConsumerFilterManager::build(
+            CheetahString::from_slice(&self.topic),
+            consumer_group.clone(),
+            Some(expression.clone()),
+            Some(type_.clone()),
+            client_version,
+        ) {
+            Some(mut consumer_filter_data) => {
+                info!(
+                    "Consumer filter info change, group: {}, topic: {}, expression: {}",
+                    consumer_group, self.topic, expression
+                );
+                consumer_filter_data.set_bloom_filter_data(bloom_filter_data);
+                self.filter_data_map
+                    .insert(consumer_group.to_string(), consumer_filter_data);
+                true
+            }
+            None => {
+                // the new expression does not compile, let the client report the error
+                self.filter_data_map.remove(consumer_group.as_str());
+                false
+            }
+        }
+    }
+
+    pub fn unregister(&mut self, consumer_group: &str) {
+        if let Some(filter_data) = self.filter_data_map.get_mut(consumer_group) {
+            if !filter_data.is_dead() {
+                filter_data.set_dead_time(get_current_millis());
+                info!(
+                    "Unregister consumer filter, group: {}, topic: {}",
+                    consumer_group, self.topic
+                );
+            }
+        }
+    }
+}
+
+fn re_alive(filter_data: &mut ConsumerFilterData) {
+    let old_dead_time = filter_data.dead_time();
+    filter_data.set_dead_time(0);
+    info!(
+        "Re alive consumer filter, group: {}, topic: {}, dead time: {}",
+        filter_data.consumer_group(),
+        filter_data.topic(),
+        old_dead_time
+    );
+}
@@ -18,6 +18,7 @@ use std::ops::Deref;
 use std::sync::Arc;
 
 use rocketmq_common::common::message::message_ext::MessageExt;
+use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
 use rocketmq_rust::ArcMut;
 use rocketmq_store::base::message_result::PutMessageResult;
 use rocketmq_store::config::message_store_config::MessageStoreConfig;
@@ -52,4 +53,12 @@ impl<MS: MessageStore> PutMessageHook for CheckBeforePutMessageHook<MS> {
             msg,
         )
     }
+
+    fn execute_before_put_message_mut(
+        &self,
+        msg: &mut MessageExtBrokerInner,
+    ) -> Option<PutMessageResult> {
+        self.execute_before_put_message(&msg.message_ext_inner)
+            .or_else(|| HookUtils::check_properties_size(msg))
+    }
 }
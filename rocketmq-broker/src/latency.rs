@@ -15,3 +15,5 @@
  * limitations under the License.
  */
 pub(crate) mod broker_fast_failure;
+pub(crate) mod overload_shedder;
+pub(crate) mod slow_request_log;
@@ -14,16 +14,167 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
-use tracing::warn;
+use std::sync::Arc;
+use std::time::Duration;
 
-pub struct BrokerFastFailure;
+use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::TimeUtils::get_current_millis;
+use rocketmq_rust::ArcMut;
+use rocketmq_store::log_file::MessageStore;
+use tokio::sync::Notify;
+use tracing::info;
+
+use crate::broker_runtime::BrokerRuntimeInner;
+use crate::processor::request_executor::RequestExecutors;
+use crate::processor::request_executor::RequestQueueKind;
+
+/// Responds `SYSTEM_BUSY` to requests which waited too long in their [`RequestQueueKind`] queue
+/// instead of letting them time out on the client, and drains the send queue while the page
+/// cache of the store is busy.
+#[derive(Default)]
+pub struct BrokerFastFailure {
+    shutdown: Arc<Notify>,
+}
 
 impl BrokerFastFailure {
-    pub fn start(&mut self) {
-        warn!("BrokerFastFailure started not implemented");
+    pub fn start<MS: MessageStore>(
+        &mut self,
+        broker_runtime_inner: ArcMut<BrokerRuntimeInner<MS>>,
+    ) {
+        let shutdown = Arc::clone(&self.shutdown);
+        tokio::spawn(async move {
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_millis(1000)) => {}
+                _ = shutdown.notified() => return,
+            }
+            let mut interval = tokio::time::interval(Duration::from_millis(10));
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = shutdown.notified() => {
+                        info!("BrokerFastFailure: shutdown..........");
+                        break;
+                    }
+                }
+                let broker_config = broker_runtime_inner.broker_config();
+                if !broker_config.broker_fast_failure_enable {
+                    continue;
+                }
+                let page_cache_busy = broker_runtime_inner
+                    .message_store()
+                    .as_ref()
+                    .is_some_and(|store| store.is_os_page_cache_busy());
+                Self::clean_expired_request(
+                    broker_runtime_inner.request_executors(),
+                    &broker_config,
+                    page_cache_busy,
+                );
+            }
+        });
+    }
+
+    fn clean_expired_request(
+        request_executors: &RequestExecutors,
+        broker_config: &BrokerConfig,
+        page_cache_busy: bool,
+    ) {
+        let now = get_current_millis();
+        if page_cache_busy {
+            while request_executors.fail_oldest(
+                RequestQueueKind::Send,
+                "PCBUSY_CLEAN_QUEUE",
+                now,
+                0,
+            ) {}
+        }
+        for kind in RequestQueueKind::ALL {
+            let Some(max_wait_time_mills) = kind.max_wait_time_mills(broker_config) else {
+                continue;
+            };
+            while request_executors.fail_oldest(
+                kind,
+                "TIMEOUT_CLEAN_QUEUE",
+                now,
+                max_wait_time_mills,
+            ) {}
+        }
     }
 
     pub fn shutdown(&mut self) {
-        warn!("BrokerFastFailure shutdown not implemented");
+        self.shutdown.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocketmq_remoting::code::request_code::RequestCode;
+
+    use super::*;
+
+    async fn queue_one_send(
+        config: &BrokerConfig,
+    ) -> (
+        Arc<RequestExecutors>,
+        tokio::sync::OwnedSemaphorePermit,
+        tokio::task::JoinHandle<Result<(), String>>,
+    ) {
+        let executors = Arc::new(RequestExecutors::new(config));
+        let held = executors
+            .enter(config, RequestCode::SendMessage)
+            .await
+            .unwrap();
+        let waiter = {
+            let executors = Arc::clone(&executors);
+            let config = config.clone();
+            tokio::spawn(async move {
+                executors
+                    .enter(&config, RequestCode::SendMessage)
+                    .await
+                    .map(|_| ())
+            })
+        };
+        while executors.queue_size(RequestQueueKind::Send) == 0 {
+            tokio::task::yield_now().await;
+        }
+        (executors, held, waiter)
+    }
+
+    #[tokio::test]
+    async fn expired_request_is_failed() {
+        let config = BrokerConfig {
+            send_message_thread_pool_nums: 1,
+            wait_time_mills_in_send_queue: 0,
+            ..BrokerConfig::default()
+        };
+        let (executors, _held, waiter) = queue_one_send(&config).await;
+        BrokerFastFailure::clean_expired_request(&executors, &config, false);
+        let remark = waiter.await.unwrap().unwrap_err();
+        assert!(remark.starts_with("[TIMEOUT_CLEAN_QUEUE]broker busy"));
+    }
+
+    #[tokio::test]
+    async fn page_cache_busy_drains_send_queue() {
+        let config = BrokerConfig {
+            send_message_thread_pool_nums: 1,
+            ..BrokerConfig::default()
+        };
+        let (executors, _held, waiter) = queue_one_send(&config).await;
+        BrokerFastFailure::clean_expired_request(&executors, &config, true);
+        let remark = waiter.await.unwrap().unwrap_err();
+        assert!(remark.starts_with("[PCBUSY_CLEAN_QUEUE]broker busy"));
+    }
+
+    #[tokio::test]
+    async fn fresh_request_is_kept() {
+        let config = BrokerConfig {
+            send_message_thread_pool_nums: 1,
+            wait_time_mills_in_send_queue: 60_000,
+            ..BrokerConfig::default()
+        };
+        let (executors, held, waiter) = queue_one_send(&config).await;
+        BrokerFastFailure::clean_expired_request(&executors, &config, false);
+        assert_eq!(executors.queue_size(RequestQueueKind::Send), 1);
+        drop(held);
+        assert!(waiter.await.unwrap().is_ok());
     }
 }
@@ -0,0 +1,266 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_remoting::code::request_code::RequestCode;
+
+/// Priority classes of broker requests, from the most to the least important one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RequestPriority {
+    /// Heartbeats, client registration and admin requests, never shed.
+    Critical,
+    /// Acks and offset commits, shedding them causes redelivery.
+    Ack,
+    /// Pull, pop and related consume requests.
+    Consume,
+    /// Produce requests.
+    Send,
+}
+
+impl RequestPriority {
+    pub(crate) fn of(request_code: RequestCode) -> Self {
+        match request_code {
+            RequestCode::AckMessage
+            | RequestCode::BatchAckMessage
+            | RequestCode::ChangeMessageInvisibleTime
+            | RequestCode::UpdateConsumerOffset => RequestPriority::Ack,
+            RequestCode::PullMessage
+            | RequestCode::LitePullMessage
+            | RequestCode::PopMessage
+            | RequestCode::PeekMessage
+            | RequestCode::Notification
+            | RequestCode::PollingInfo => RequestPriority::Consume,
+            RequestCode::SendMessage
+            | RequestCode::SendMessageV2
+            | RequestCode::SendBatchMessage
+            | RequestCode::ConsumerSendMsgBack
+            | RequestCode::SendReplyMessage
+            | RequestCode::SendReplyMessageV2
+            | RequestCode::EndTransaction => RequestPriority::Send,
+            _ => RequestPriority::Critical,
+        }
+    }
+
+    /// Position in the shedding order, `0` is shed last.
+    fn rank(self, shed_consume_before_send: bool) -> usize {
+        match (self, shed_consume_before_send) {
+            (RequestPriority::Critical, _) => 0,
+            (RequestPriority::Ack, _) => 1,
+            (RequestPriority::Consume, false) | (RequestPriority::Send, true) => 2,
+            (RequestPriority::Send, false) | (RequestPriority::Consume, true) => 3,
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            RequestPriority::Critical => 0,
+            RequestPriority::Ack => 1,
+            RequestPriority::Consume => 2,
+            RequestPriority::Send => 3,
+        }
+    }
+}
+
+/// Admits requests according to their [`RequestPriority`] while the broker is saturated.
+///
+/// The number of requests being processed is compared with `overload_max_in_flight_requests`:
+/// the lowest class is rejected from 80% of the limit on, the next one from 90% and acks once the
+/// limit is reached. Critical requests are always admitted so clients keep their registration.
+#[derive(Default)]
+pub(crate) struct OverloadShedder {
+    in_flight: Arc<AtomicUsize>,
+    shed: [AtomicU64; 4],
+}
+
+impl OverloadShedder {
+    pub(crate) fn try_acquire(
+        &self,
+        broker_config: &BrokerConfig,
+        request_code: RequestCode,
+    ) -> Result<InFlightPermit, RequestPriority> {
+        let priority = RequestPriority::of(request_code);
+        let in_flight = self.in_flight.fetch_add(1, Ordering::AcqRel);
+        let permit = InFlightPermit {
+            in_flight: Arc::clone(&self.in_flight),
+        };
+        if !broker_config.enable_overload_shedding {
+            return Ok(permit);
+        }
+        let limit = broker_config.overload_max_in_flight_requests;
+        if in_flight
+            >= admit_below(
+                priority.rank(broker_config.overload_shed_consume_before_send),
+                limit,
+            )
+        {
+            drop(permit);
+            self.shed[priority.index()].fetch_add(1, Ordering::Relaxed);
+            return Err(priority);
+        }
+        Ok(permit)
+    }
+
+    pub(crate) fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn shed_count(&self, priority: RequestPriority) -> u64 {
+        self.shed[priority.index()].load(Ordering::Relaxed)
+    }
+}
+
+/// The in flight count from which requests of `rank` are shed, a class below acks is still
+/// admitted while nothing else is in flight however small `limit` is.
+fn admit_below(rank: usize, limit: usize) -> usize {
+    match rank {
+        0 => usize::MAX,
+        1 => limit,
+        2 => (limit.saturating_mul(9) / 10).max(1),
+        _ => (limit.saturating_mul(8) / 10).max(1),
+    }
+}
+
+/// Counts a request as in flight until dropped.
+pub(crate) struct InFlightPermit {
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl Drop for InFlightPermit {
+    fn drop(&mut self) {
+        self.in_flight.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(limit: usize, shed_consume_before_send: bool) -> BrokerConfig {
+        BrokerConfig {
+            enable_overload_shedding: true,
+            overload_max_in_flight_requests: limit,
+            overload_shed_consume_before_send: shed_consume_before_send,
+            ..BrokerConfig::default()
+        }
+    }
+
+    #[test]
+    fn classifies_request_codes() {
+        assert_eq!(
+            RequestPriority::of(RequestCode::HeartBeat),
+            RequestPriority::Critical
+        );
+        assert_eq!(
+            RequestPriority::of(RequestCode::AckMessage),
+            RequestPriority::Ack
+        );
+        assert_eq!(
+            RequestPriority::of(RequestCode::PopMessage),
+            RequestPriority::Consume
+        );
+        assert_eq!(
+            RequestPriority::of(RequestCode::SendMessageV2),
+            RequestPriority::Send
+        );
+    }
+
+    #[test]
+    fn sheds_lowest_class_first() {
+        let shedder = OverloadShedder::default();
+        let config = config(10, false);
+        let held = (0..8)
+            .map(|_| {
+                shedder
+                    .try_acquire(&config, RequestCode::HeartBeat)
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            shedder.try_acquire(&config, RequestCode::SendMessage).err(),
+            Some(RequestPriority::Send)
+        );
+        assert!(shedder
+            .try_acquire(&config, RequestCode::PullMessage)
+            .is_ok());
+        assert!(shedder
+            .try_acquire(&config, RequestCode::AckMessage)
+            .is_ok());
+        assert_eq!(shedder.shed_count(RequestPriority::Send), 1);
+        assert_eq!(shedder.in_flight(), 8);
+        drop(held);
+        assert_eq!(shedder.in_flight(), 0);
+    }
+
+    #[test]
+    fn order_of_send_and_consume_is_configurable() {
+        let shedder = OverloadShedder::default();
+        let config = config(10, true);
+        let _held = (0..8)
+            .map(|_| {
+                shedder
+                    .try_acquire(&config, RequestCode::HeartBeat)
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert!(shedder
+            .try_acquire(&config, RequestCode::SendMessage)
+            .is_ok());
+        assert_eq!(
+            shedder.try_acquire(&config, RequestCode::PopMessage).err(),
+            Some(RequestPriority::Consume)
+        );
+    }
+
+    #[test]
+    fn small_limits_keep_every_class_admitted_while_idle() {
+        assert_eq!(admit_below(2, 5), 4);
+        assert_eq!(admit_below(3, 5), 4);
+        assert_eq!(admit_below(2, 1), 1);
+        assert_eq!(admit_below(3, 1), 1);
+
+        let shedder = OverloadShedder::default();
+        let config = config(5, false);
+        let held = shedder
+            .try_acquire(&config, RequestCode::SendMessage)
+            .unwrap();
+        assert!(shedder
+            .try_acquire(&config, RequestCode::PullMessage)
+            .is_ok());
+        drop(held);
+    }
+
+    #[test]
+    fn critical_requests_are_never_shed() {
+        let shedder = OverloadShedder::default();
+        let config = config(1, false);
+        let _held = (0..4)
+            .map(|_| {
+                shedder
+                    .try_acquire(&config, RequestCode::HeartBeat)
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert!(shedder.try_acquire(&config, RequestCode::HeartBeat).is_ok());
+        assert!(shedder
+            .try_acquire(&config, RequestCode::AckMessage)
+            .is_err());
+    }
+}
@@ -0,0 +1,281 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::fmt::Write as _;
+use std::fs;
+use std::fs::OpenOptions;
+use std::io::Write as _;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use cheetah_string::CheetahString;
+use parking_lot::Mutex;
+use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::UtilAll::time_millis_to_human_string2;
+use rocketmq_remoting::code::request_code::RequestCode;
+use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+use rocketmq_rust::ArcMut;
+use rocketmq_store::log_file::MessageStore;
+use tokio::sync::Notify;
+use tracing::info;
+use tracing::warn;
+
+use crate::broker_path_config_helper::get_slow_request_log_path;
+use crate::broker_runtime::BrokerRuntimeInner;
+
+/// How often the sampled requests are written to the slow log.
+const SLOW_REQUEST_LOG_INTERVAL_MILLIS: u64 = 60_000;
+
+/// A request as recorded by the [`SlowRequestLog`].
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SlowRequest {
+    /// When the broker received the request.
+    pub(crate) timestamp: u64,
+    pub(crate) code: i32,
+    pub(crate) topic: Option<CheetahString>,
+    pub(crate) group: Option<CheetahString>,
+    pub(crate) remote_address: SocketAddr,
+    pub(crate) request_body_size: usize,
+    pub(crate) response_body_size: usize,
+    pub(crate) response_code: Option<i32>,
+    /// Time spent waiting for a processing slot.
+    pub(crate) wait_millis: u64,
+    /// Time spent in the processor.
+    pub(crate) process_millis: u64,
+}
+
+impl SlowRequest {
+    pub(crate) fn new(
+        request: &RemotingCommand,
+        remote_address: SocketAddr,
+        timestamp: u64,
+    ) -> Self {
+        let code = request.code();
+        // SendMessageV2 abbreviates the header fields, `a` is the producer group and `b` the topic
+        let (topic_key, group_keys): (&str, &[&str]) = match RequestCode::from(code) {
+            RequestCode::SendMessageV2 | RequestCode::SendBatchMessage => ("b", &["a"]),
+            _ => ("topic", &["consumerGroup", "producerGroup", "group"]),
+        };
+        let ext_fields = request.ext_fields();
+        let field = |key: &str| ext_fields.and_then(|fields| fields.get(key)).cloned();
+        Self {
+            timestamp,
+            code,
+            topic: field(topic_key),
+            group: group_keys.iter().find_map(|key| field(key)),
+            remote_address,
+            request_body_size: request.get_body().map_or(0, |body| body.len()),
+            response_body_size: 0,
+            response_code: None,
+            wait_millis: 0,
+            process_millis: 0,
+        }
+    }
+
+    pub(crate) fn total_millis(&self) -> u64 {
+        self.wait_millis + self.process_millis
+    }
+
+    fn to_log_line(&self) -> String {
+        let mut line = format!(
+            "{} code={}({:?}) remote={} topic={} group={} requestSize={} responseSize={}",
+            time_millis_to_human_string2(self.timestamp as i64),
+            self.code,
+            RequestCode::from(self.code),
+            self.remote_address,
+            self.topic.as_deref().unwrap_or("-"),
+            self.group.as_deref().unwrap_or("-"),
+            self.request_body_size,
+            self.response_body_size,
+        );
+        match self.response_code {
+            Some(response_code) => {
+                let _ = write!(line, " responseCode={}", response_code);
+            }
+            None => line.push_str(" responseCode=-"),
+        }
+        let _ = write!(
+            line,
+            " waitMs={} processMs={} totalMs={}",
+            self.wait_millis,
+            self.process_millis,
+            self.total_millis()
+        );
+        line
+    }
+}
+
+/// Keeps the slowest requests of every minute and writes them to `slow.log`, so that a latency
+/// spike can be traced back to the requests, clients and stages behind it.
+///
+/// Only the `slow_request_log_sample_count` slowest requests above
+/// `slow_request_log_threshold_millis` of a minute are kept, recording is a lock and a compare for
+/// every other request.
+#[derive(Default)]
+pub struct SlowRequestLog {
+    samples: Arc<Mutex<Vec<SlowRequest>>>,
+    shutdown: Arc<Notify>,
+}
+
+impl SlowRequestLog {
+    pub fn start<MS: MessageStore>(
+        &mut self,
+        broker_runtime_inner: ArcMut<BrokerRuntimeInner<MS>>,
+    ) {
+        let samples = Arc::clone(&self.samples);
+        let shutdown = Arc::clone(&self.shutdown);
+        tokio::spawn(async move {
+            let mut interval =
+                tokio::time::interval(Duration::from_millis(SLOW_REQUEST_LOG_INTERVAL_MILLIS));
+            interval.tick().await;
+            loop {
+                let stop = tokio::select! {
+                    _ = interval.tick() => false,
+                    _ = shutdown.notified() => true,
+                };
+                let taken = take_slowest_first(&mut samples.lock());
+                if !taken.is_empty() {
+                    let path = slow_request_log_path(&broker_runtime_inner.broker_config());
+                    if let Err(e) = append_lines(&path, &taken) {
+                        warn!("write slow request log {} failed, {}", path, e);
+                    }
+                }
+                if stop {
+                    info!("SlowRequestLog: shutdown..........");
+                    break;
+                }
+            }
+        });
+    }
+
+    /// Keeps `request` if it is among the slowest requests of the current minute.
+    pub(crate) fn record(&self, broker_config: &BrokerConfig, request: SlowRequest) {
+        if !broker_config.slow_request_log_enable
+            || request.total_millis() < broker_config.slow_request_log_threshold_millis
+        {
+            return;
+        }
+        keep_slowest(
+            &mut self.samples.lock(),
+            broker_config.slow_request_log_sample_count,
+            request,
+        );
+    }
+
+    pub fn shutdown(&mut self) {
+        self.shutdown.notify_waiters();
+    }
+}
+
+fn slow_request_log_path(broker_config: &BrokerConfig) -> String {
+    if broker_config.slow_request_log_path.is_empty() {
+        get_slow_request_log_path()
+    } else {
+        broker_config.slow_request_log_path.to_string()
+    }
+}
+
+fn keep_slowest(samples: &mut Vec<SlowRequest>, max_samples: usize, request: SlowRequest) {
+    if samples.len() < max_samples {
+        samples.push(request);
+        return;
+    }
+    let fastest = samples
+        .iter_mut()
+        .min_by_key(|sample| sample.total_millis());
+    if let Some(fastest) = fastest {
+        if fastest.total_millis() < request.total_millis() {
+            *fastest = request;
+        }
+    }
+}
+
+fn take_slowest_first(samples: &mut Vec<SlowRequest>) -> Vec<SlowRequest> {
+    let mut taken = std::mem::take(samples);
+    taken.sort_by_key(|sample| std::cmp::Reverse(sample.total_millis()));
+    taken
+}
+
+fn append_lines(path: &str, requests: &[SlowRequest]) -> std::io::Result<()> {
+    if let Some(parent) = Path::new(path).parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut content = String::new();
+    for request in requests {
+        content.push_str(&request.to_log_line());
+        content.push('\n');
+    }
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(content.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(total_millis: u64) -> SlowRequest {
+        let mut request = SlowRequest::new(
+            &RemotingCommand::create_remoting_command(RequestCode::PullMessage).set_ext_fields(
+                [
+                    ("topic".into(), "topic".into()),
+                    ("consumerGroup".into(), "group".into()),
+                ]
+                .into(),
+            ),
+            "127.0.0.1:10911".parse().unwrap(),
+            0,
+        );
+        request.process_millis = total_millis;
+        request
+    }
+
+    #[test]
+    fn keeps_the_slowest_requests() {
+        let mut samples = Vec::new();
+        for total_millis in [30, 10, 50, 20, 40] {
+            keep_slowest(&mut samples, 3, request(total_millis));
+        }
+        let taken = take_slowest_first(&mut samples);
+        assert!(samples.is_empty());
+        assert_eq!(
+            taken
+                .iter()
+                .map(SlowRequest::total_millis)
+                .collect::<Vec<_>>(),
+            vec![50, 40, 30]
+        );
+    }
+
+    #[test]
+    fn logs_the_request_context() {
+        let mut request = request(120);
+        request.wait_millis = 5;
+        request.response_code = Some(0);
+        let line = request.to_log_line();
+        assert!(line.contains("code=11(PullMessage)"), "{}", line);
+        assert!(line.contains("topic=topic group=group"), "{}", line);
+        assert!(
+            line.contains("responseCode=0 waitMs=5 processMs=120 totalMs=125"),
+            "{}",
+            line
+        );
+    }
+}
@@ -21,6 +21,8 @@
 
 pub use broker_bootstrap::BrokerBootstrap;
 pub use broker_bootstrap::Builder;
+pub use container::broker_container::BrokerContainer;
+pub use container::broker_container_config::BrokerContainerConfig;
 
 use crate::broker_error::BrokerError;
 
@@ -33,6 +35,9 @@ pub(crate) mod broker_path_config_helper;
 pub(crate) mod broker_runtime;
 pub(crate) mod client;
 pub(crate) mod coldctr;
+#[cfg(feature = "rocksdb")]
+pub(crate) mod config;
+pub(crate) mod container;
 pub(crate) mod controller;
 pub(crate) mod failover;
 pub(crate) mod filter;
@@ -45,6 +50,7 @@ pub(crate) mod offset;
 pub(crate) mod out_api;
 pub(crate) mod processor;
 pub(crate) mod schedule;
+pub(crate) mod slave;
 pub(crate) mod subscription;
 pub(crate) mod topic;
 mod transaction;
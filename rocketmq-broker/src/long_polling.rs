@@ -15,6 +15,7 @@
  * limitations under the License.
  */
 
+pub(crate) mod adaptive_suspend;
 pub(crate) mod long_polling_service;
 pub(crate) mod many_pull_request;
 pub(crate) mod notify_message_arriving_listener;
@@ -0,0 +1,111 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use rocketmq_common::common::broker::broker_config::BrokerConfig;
+
+/// Computes the suspend time of a long polling request according to the number of requests
+/// currently held by the broker.
+///
+/// While the hold queue is below `adaptive_polling_hold_threshold` the timeout requested by the
+/// client is honored. Above it, the timeout shrinks linearly and reaches
+/// `adaptive_polling_min_suspend_millis` once `adaptive_polling_hold_limit` requests are held,
+/// so that a huge number of parked consumers does not pin broker memory for the full timeout.
+pub(crate) fn adaptive_suspend_millis(
+    broker_config: &BrokerConfig,
+    requested_millis: u64,
+    hold_num: usize,
+) -> u64 {
+    if !broker_config.adaptive_polling_enable {
+        return requested_millis;
+    }
+    compute_suspend_millis(
+        requested_millis,
+        hold_num,
+        broker_config.adaptive_polling_min_suspend_millis,
+        broker_config.adaptive_polling_hold_threshold,
+        broker_config.adaptive_polling_hold_limit,
+    )
+}
+
+fn compute_suspend_millis(
+    requested_millis: u64,
+    hold_num: usize,
+    min_millis: u64,
+    threshold: usize,
+    limit: usize,
+) -> u64 {
+    if requested_millis <= min_millis || hold_num <= threshold {
+        return requested_millis;
+    }
+    if hold_num >= limit || limit <= threshold {
+        return min_millis;
+    }
+    let range = (requested_millis - min_millis) as u128;
+    let over = (hold_num - threshold) as u128;
+    let span = (limit - threshold) as u128;
+    requested_millis - (range * over / span) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_requested_timeout_below_threshold() {
+        assert_eq!(
+            compute_suspend_millis(15_000, 100, 1_000, 1_000, 10_000),
+            15_000
+        );
+        assert_eq!(
+            compute_suspend_millis(15_000, 1_000, 1_000, 1_000, 10_000),
+            15_000
+        );
+    }
+
+    #[test]
+    fn shrinks_linearly_between_threshold_and_limit() {
+        assert_eq!(
+            compute_suspend_millis(11_000, 5_500, 1_000, 1_000, 10_000),
+            6_000
+        );
+    }
+
+    #[test]
+    fn uses_min_at_or_above_limit() {
+        assert_eq!(
+            compute_suspend_millis(15_000, 10_000, 1_000, 1_000, 10_000),
+            1_000
+        );
+        assert_eq!(
+            compute_suspend_millis(15_000, 50_000, 1_000, 1_000, 10_000),
+            1_000
+        );
+    }
+
+    #[test]
+    fn never_extends_short_requests() {
+        assert_eq!(
+            compute_suspend_millis(500, 50_000, 1_000, 1_000, 10_000),
+            500
+        );
+    }
+
+    #[test]
+    fn disabled_by_default() {
+        let config = BrokerConfig::default();
+        assert_eq!(adaptive_suspend_millis(&config, 15_000, usize::MAX), 15_000);
+    }
+}
@@ -40,6 +40,7 @@ use tracing::error;
 use tracing::warn;
 
 use crate::broker_runtime::BrokerRuntimeInner;
+use crate::long_polling::adaptive_suspend::adaptive_suspend_millis;
 use crate::long_polling::polling_header::PollingHeader;
 use crate::long_polling::polling_result::PollingResult;
 use crate::long_polling::pop_request::PopRequest;
@@ -121,6 +122,50 @@ impl<MS: MessageStore, RP: RequestProcessor + Sync + 'static> PopLongPollingServ
         warn!("clean_unused_resource start");
     }
 
+    /// Wakes the pop requests of every consumer group polling `topic`, a message arriving on a
+    /// pop retry topic wakes the requests polling its normal topic.
+    pub fn notify_message_arriving_with_retry_topic(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        tags_code: Option<i64>,
+        msg_store_time: i64,
+        filter_bit_map: Option<Vec<u8>>,
+        properties: Option<&HashMap<CheetahString, CheetahString>>,
+    ) {
+        let notify_topic = if KeyBuilder::is_pop_retry_topic_v2(topic) {
+            CheetahString::from_string(KeyBuilder::parse_normal_topic_default(topic))
+        } else {
+            topic.clone()
+        };
+        let Some(cids) = self.topic_cid_map.get(&notify_topic) else {
+            return;
+        };
+        for cid in cids.iter() {
+            // requests popping from all queues wait on queue -1
+            if queue_id >= 0 {
+                self.notify_message_arriving(
+                    &notify_topic,
+                    -1,
+                    cid.key(),
+                    tags_code,
+                    msg_store_time,
+                    filter_bit_map.clone(),
+                    properties,
+                );
+            }
+            self.notify_message_arriving(
+                &notify_topic,
+                queue_id,
+                cid.key(),
+                tags_code,
+                msg_store_time,
+                filter_bit_map.clone(),
+                properties,
+            );
+        }
+    }
+
     pub fn notify_message_arriving(
         &self,
         topic: &CheetahString,
@@ -144,14 +189,11 @@ impl<MS: MessageStore, RP: RequestProcessor + Sync + 'static> PopLongPollingServ
                     pop_request.get_subscription_data(),
                 );
 
-                let mut match_result = message_filter.is_matched_by_consume_queue(
-                    tags_code,
-                    Some(&CqExtUnit::new(
-                        tags_code.unwrap(),
-                        msg_store_time,
-                        filter_bit_map,
-                    )),
-                );
+                // without a tags code, as on the notify after a pop, tag filters let it through
+                let cq_ext_unit = tags_code
+                    .map(|tags_code| CqExtUnit::new(tags_code, msg_store_time, filter_bit_map));
+                let mut match_result =
+                    message_filter.is_matched_by_consume_queue(tags_code, cq_ext_unit.as_ref());
                 if match_result {
                     if let Some(props) = properties {
                         match_result = message_filter.is_matched_by_commit_log(None, Some(props));
@@ -159,6 +201,7 @@ impl<MS: MessageStore, RP: RequestProcessor + Sync + 'static> PopLongPollingServ
                 }
                 if !match_result {
                     remoting_commands.value().insert(pop_request);
+                    self.total_polling_num.fetch_add(1, Ordering::AcqRel);
                     return false;
                 }
 
@@ -189,7 +232,12 @@ impl<MS: MessageStore, RP: RequestProcessor + Sync + 'static> PopLongPollingServ
         cids.entry(request_header.get_consumer_group().clone())
             .or_insert(u8::MIN);
 
-        let expired = request_header.get_born_time() + request_header.get_poll_time();
+        let poll_time = adaptive_suspend_millis(
+            &self.broker_runtime_inner.broker_config(),
+            request_header.get_poll_time() as u64,
+            self.total_polling_num.load(Ordering::Relaxed) as usize,
+        );
+        let expired = request_header.get_born_time() + poll_time as i64;
         let request = Arc::new(PopRequest::new(
             remoting_command.clone(),
             ctx.clone(),
@@ -269,27 +317,15 @@ impl<MS: MessageStore, RP: RequestProcessor + Sync + 'static> PopLongPollingServ
             return None;
         }
 
-        let mut pop_request: Option<Arc<PopRequest>>;
-
-        //maybe need to optimize
-        loop {
-            if self.notify_last {
-                pop_request = remoting_commands
-                    .pop_back()
-                    .map(|entry| entry.value().clone());
-            } else {
-                pop_request = remoting_commands
-                    .pop_front()
-                    .map(|entry| entry.value().clone());
-            }
-
-            if let Some(ref request) = pop_request {
-                self.total_polling_num.fetch_sub(1, Ordering::AcqRel);
-            } else {
-                break;
-            }
-        }
-        pop_request
+        let pop_request = if self.notify_last {
+            remoting_commands.pop_back()
+        } else {
+            remoting_commands.pop_front()
+        };
+        pop_request.map(|entry| {
+            self.total_polling_num.fetch_sub(1, Ordering::AcqRel);
+            entry.value().clone()
+        })
     }
 
     pub fn set_processor(&mut self, processor: ArcMut<RP>) {
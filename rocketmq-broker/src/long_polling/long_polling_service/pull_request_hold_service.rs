@@ -16,6 +16,8 @@
  */
 
 use std::collections::HashMap;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
 use cheetah_string::CheetahString;
@@ -37,6 +39,7 @@ const TOPIC_QUEUE_ID_SEPARATOR: &str = "@";
 
 pub struct PullRequestHoldService<MS> {
     pull_request_table: Arc<parking_lot::RwLock<HashMap<String, ManyPullRequest>>>,
+    hold_request_num: Arc<AtomicUsize>,
     pull_message_processor: ArcMut<PullMessageProcessor<MS>>,
     //message_store: ArcMut<MS>,
     // broker_config: Arc<BrokerConfig>,
@@ -57,6 +60,7 @@ where
     ) -> Self {
         PullRequestHoldService {
             pull_request_table: Arc::new(parking_lot::RwLock::new(HashMap::new())),
+            hold_request_num: Arc::new(AtomicUsize::new(0)),
             pull_message_processor,
             /* message_store,
             broker_config,*/
@@ -113,6 +117,13 @@ where
         let mpr = table.entry(key).or_insert_with(ManyPullRequest::new);
         pull_request.request_command_mut().set_suspended_ref(true);
         mpr.add_pull_request(pull_request);
+        self.hold_request_num.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Number of pull requests currently suspended by this service.
+    #[inline]
+    pub fn hold_request_num(&self) -> usize {
+        self.hold_request_num.load(Ordering::Relaxed)
     }
 
     fn check_hold_request(&self) {
@@ -193,6 +204,7 @@ where
                         }
 
                         if match_by_commit_log {
+                            self.hold_request_num.fetch_sub(1, Ordering::Relaxed);
                             let pull_message_this = self.pull_message_processor.clone();
                             self.pull_message_processor.execute_request_when_wakeup(
                                 pull_message_this,
@@ -207,6 +219,7 @@ where
                     if get_current_millis()
                         >= (request.suspend_timestamp() + request.timeout_millis())
                     {
+                        self.hold_request_num.fetch_sub(1, Ordering::Relaxed);
                         let pull_message_this = self.pull_message_processor.clone();
                         self.pull_message_processor.execute_request_when_wakeup(
                             pull_message_this,
@@ -232,6 +245,8 @@ where
     pub async fn notify_master_online(&self) {
         for (_, mpr) in self.pull_request_table.read().iter() {
             if let Some(request_list) = mpr.clone_list_and_clear() {
+                self.hold_request_num
+                    .fetch_sub(request_list.len(), Ordering::Relaxed);
                 for request in request_list {
                     info!(
                         "notify master online, wakeup {} {}",
@@ -23,8 +23,14 @@ use rocketmq_store::log_file::MessageStore;
 
 use crate::broker_runtime::BrokerRuntimeInner;
 
+/// The message arriving listener of the store, the store calls it once for every message it
+/// dispatches to a consume queue.
+///
+/// It fans the message out to everything holding requests for new messages: the pull request
+/// hold service, the pop long polling and the notification processor. The tags code, filter
+/// bit map and properties of the message go along, so that each of them matches the message
+/// against the filters of its held requests and only wakes the requests it can satisfy.
 pub struct NotifyMessageArrivingListener<MS> {
-    //pull_request_hold_service: ArcMut<PullRequestHoldService<MS>>,
     broker_runtime_inner: ArcMut<BrokerRuntimeInner<MS>>,
 }
 
@@ -39,7 +45,6 @@ where
     }
 }
 
-#[allow(unused_variables)]
 impl<MS> MessageArrivingListener for NotifyMessageArrivingListener<MS>
 where
     MS: MessageStore + Send + Sync,
@@ -54,18 +59,31 @@ where
         filter_bit_map: Option<Vec<u8>>,
         properties: Option<&HashMap<CheetahString, CheetahString>>,
     ) {
-        self.broker_runtime_inner
-            .pull_request_hold_service()
-            .as_ref()
-            .unwrap()
-            .notify_message_arriving_ext(
+        if let Some(pull_request_hold_service) =
+            self.broker_runtime_inner.pull_request_hold_service()
+        {
+            pull_request_hold_service.notify_message_arriving_ext(
                 topic,
                 queue_id,
                 logic_offset,
                 tags_code,
                 msg_store_time,
+                filter_bit_map.clone(),
+                properties,
+            );
+        }
+        if let Some(pop_message_processor) = self.broker_runtime_inner.pop_message_processor() {
+            pop_message_processor.notify_message_arriving_with_retry_topic(
+                topic,
+                queue_id,
+                tags_code,
+                msg_store_time,
                 filter_bit_map,
                 properties,
             );
+        }
+        if let Some(notification_processor) = self.broker_runtime_inner.notification_processor() {
+            notification_processor.notify_message_arriving(topic, queue_id);
+        }
     }
 }
@@ -124,6 +124,7 @@ mod tests {
             attempt_id: None,
             poll_time: 1234567890,
             invisible_time: 0,
+            accept_compression: None,
             topic_request_header: None,
         };
 
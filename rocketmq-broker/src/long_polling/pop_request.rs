@@ -83,7 +83,7 @@ impl PopRequest {
     pub fn complete(&self) -> bool {
         self.complete
             .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
-            .unwrap_or_default()
+            .is_ok()
     }
 
     pub fn get_expired(&self) -> u64 {
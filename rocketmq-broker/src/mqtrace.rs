@@ -15,6 +15,8 @@
  * limitations under the License.
  */
 
+pub(crate) mod broker_trace_dispatcher;
+pub(crate) mod broker_trace_hook;
 pub(crate) mod consume_message_context;
 pub(crate) mod consume_message_hook;
 pub(crate) mod send_message_context;
@@ -0,0 +1,360 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::BTreeSet;
+use std::fmt::Display;
+use std::fmt::Write;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use cheetah_string::CheetahString;
+use rocketmq_common::common::message::message_decoder;
+use rocketmq_common::common::message::message_enum::MessageType;
+use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+use rocketmq_common::common::message::MessageConst;
+use rocketmq_common::common::message::MessageTrait;
+use rocketmq_common::TimeUtils::get_current_millis;
+use rocketmq_rust::ArcMut;
+use rocketmq_store::base::message_status_enum::PutMessageStatus;
+use rocketmq_store::log_file::MessageStore;
+use tokio::sync::mpsc;
+use tokio::sync::Notify;
+use tracing::info;
+use tracing::warn;
+
+use crate::broker_runtime::BrokerRuntimeInner;
+
+const CONTENT_SPLITOR: char = '\u{0001}';
+const FIELD_SPLITOR: char = '\u{0002}';
+
+/// Records written into one trace message at most.
+const MAX_BATCH_NUM: usize = 100;
+/// Size of the body of one trace message at most.
+const MAX_BATCH_SIZE: usize = 128 * 1024;
+
+/// Trace types understood by the trace query of the admin tools and dashboards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BrokerTraceType {
+    Pub,
+    SubBefore,
+    SubAfter,
+}
+
+impl Display for BrokerTraceType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BrokerTraceType::Pub => write!(f, "Pub"),
+            BrokerTraceType::SubBefore => write!(f, "SubBefore"),
+            BrokerTraceType::SubAfter => write!(f, "SubAfter"),
+        }
+    }
+}
+
+/// One produce or consume event of a message, as seen by the broker.
+#[derive(Debug, Clone)]
+pub(crate) struct BrokerTraceRecord {
+    pub(crate) trace_type: BrokerTraceType,
+    pub(crate) timestamp: i64,
+    pub(crate) region_id: CheetahString,
+    pub(crate) group: CheetahString,
+    pub(crate) topic: CheetahString,
+    pub(crate) msg_id: CheetahString,
+    pub(crate) offset_msg_id: CheetahString,
+    pub(crate) tags: CheetahString,
+    pub(crate) keys: CheetahString,
+    pub(crate) store_host: CheetahString,
+    pub(crate) body_length: i32,
+    pub(crate) cost_time: i64,
+    pub(crate) msg_type: MessageType,
+    pub(crate) success: bool,
+    pub(crate) request_id: CheetahString,
+    pub(crate) retry_times: i32,
+}
+
+impl BrokerTraceRecord {
+    pub(crate) fn new(trace_type: BrokerTraceType) -> Self {
+        Self {
+            trace_type,
+            timestamp: get_current_millis() as i64,
+            region_id: CheetahString::empty(),
+            group: CheetahString::empty(),
+            topic: CheetahString::empty(),
+            msg_id: CheetahString::empty(),
+            offset_msg_id: CheetahString::empty(),
+            tags: CheetahString::empty(),
+            keys: CheetahString::empty(),
+            store_host: CheetahString::empty(),
+            body_length: 0,
+            cost_time: 0,
+            msg_type: MessageType::NormalMsg,
+            success: true,
+            request_id: CheetahString::empty(),
+            retry_times: 0,
+        }
+    }
+
+    /// Appends the record in the layout of the client trace encoder, so that broker and client
+    /// traces can be queried the same way.
+    pub(crate) fn encode(&self, buf: &mut String) {
+        let msg_type = self.msg_type as i32;
+        let fields: Vec<&dyn Display> = match self.trace_type {
+            BrokerTraceType::Pub => vec![
+                &self.trace_type,
+                &self.timestamp,
+                &self.region_id,
+                &self.group,
+                &self.topic,
+                &self.msg_id,
+                &self.tags,
+                &self.keys,
+                &self.store_host,
+                &self.body_length,
+                &self.cost_time,
+                &msg_type,
+                &self.offset_msg_id,
+                &self.success,
+            ],
+            BrokerTraceType::SubBefore => vec![
+                &self.trace_type,
+                &self.timestamp,
+                &self.region_id,
+                &self.group,
+                &self.request_id,
+                &self.msg_id,
+                &self.retry_times,
+                &self.keys,
+            ],
+            BrokerTraceType::SubAfter => vec![
+                &self.trace_type,
+                &self.request_id,
+                &self.msg_id,
+                &self.cost_time,
+                &self.success,
+                &self.keys,
+                &0,
+                &self.timestamp,
+                &self.group,
+            ],
+        };
+        for (index, field) in fields.iter().enumerate() {
+            if index > 0 {
+                buf.push(CONTENT_SPLITOR);
+            }
+            let _ = write!(buf, "{}", field);
+        }
+        buf.push(FIELD_SPLITOR);
+    }
+
+    /// Keys of the trace message, which the trace query looks the message up by.
+    fn trace_keys(&self, keys: &mut BTreeSet<String>) {
+        if !self.msg_id.is_empty() {
+            keys.insert(self.msg_id.to_string());
+        }
+        keys.extend(
+            self.keys
+                .split(MessageConst::KEY_SEPARATOR)
+                .filter(|key| !key.is_empty())
+                .map(str::to_string),
+        );
+    }
+}
+
+/// Collects trace records from the hooks and writes them in batches into the trace topic, so
+/// message trajectories can be queried for clients that do not trace themselves.
+///
+/// Records are dropped instead of slowing down the request path once the queue is full.
+pub(crate) struct BrokerTraceDispatcher {
+    sender: mpsc::Sender<BrokerTraceRecord>,
+    receiver: parking_lot::Mutex<Option<mpsc::Receiver<BrokerTraceRecord>>>,
+    discard_count: AtomicU64,
+    shutdown: Arc<Notify>,
+}
+
+impl BrokerTraceDispatcher {
+    pub(crate) fn new(queue_size: usize) -> Self {
+        let (sender, receiver) = mpsc::channel(queue_size.max(1));
+        Self {
+            sender,
+            receiver: parking_lot::Mutex::new(Some(receiver)),
+            discard_count: AtomicU64::new(0),
+            shutdown: Arc::new(Notify::new()),
+        }
+    }
+
+    pub(crate) fn dispatch(&self, record: BrokerTraceRecord) -> bool {
+        if self.sender.try_send(record).is_err() {
+            let discard_count = self.discard_count.fetch_add(1, Ordering::Relaxed) + 1;
+            if discard_count % 1000 == 1 {
+                warn!(
+                    "broker trace queue is full, {} records discarded",
+                    discard_count
+                );
+            }
+            return false;
+        }
+        true
+    }
+
+    pub(crate) fn discard_count(&self) -> u64 {
+        self.discard_count.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn start<MS: MessageStore>(
+        &self,
+        broker_runtime_inner: ArcMut<BrokerRuntimeInner<MS>>,
+    ) {
+        let Some(mut receiver) = self.receiver.lock().take() else {
+            return;
+        };
+        let shutdown = Arc::clone(&self.shutdown);
+        tokio::spawn(async move {
+            let mut batch = Vec::with_capacity(MAX_BATCH_NUM);
+            loop {
+                let record = tokio::select! {
+                    record = receiver.recv() => record,
+                    _ = shutdown.notified() => {
+                        info!("BrokerTraceDispatcher: shutdown..........");
+                        break;
+                    }
+                };
+                let Some(record) = record else {
+                    break;
+                };
+                batch.push(record);
+                while batch.len() < MAX_BATCH_NUM {
+                    match receiver.try_recv() {
+                        Ok(record) => batch.push(record),
+                        Err(_) => break,
+                    }
+                }
+                for mut msg in build_trace_messages(
+                    &mut batch,
+                    &broker_runtime_inner.broker_config().msg_trace_topic_name,
+                ) {
+                    let store_host = broker_runtime_inner.store_host();
+                    msg.message_ext_inner.born_host = store_host;
+                    msg.message_ext_inner.store_host = store_host;
+                    let put_message_result = broker_runtime_inner
+                        .escape_bridge()
+                        .async_put_message(msg)
+                        .await;
+                    if put_message_result.put_message_status() != PutMessageStatus::PutOk {
+                        warn!(
+                            "write broker trace failed, {:?}",
+                            put_message_result.put_message_status()
+                        );
+                    }
+                }
+            }
+        });
+    }
+
+    pub(crate) fn shutdown(&self) {
+        self.shutdown.notify_waiters();
+    }
+}
+
+/// Drains `batch` into trace messages of at most [`MAX_BATCH_SIZE`] bytes each.
+fn build_trace_messages(
+    batch: &mut Vec<BrokerTraceRecord>,
+    trace_topic: &CheetahString,
+) -> Vec<MessageExtBrokerInner> {
+    let mut messages = Vec::new();
+    let mut body = String::new();
+    let mut keys = BTreeSet::new();
+    for record in batch.drain(..) {
+        record.encode(&mut body);
+        record.trace_keys(&mut keys);
+        if body.len() >= MAX_BATCH_SIZE {
+            messages.push(build_trace_message(
+                trace_topic,
+                std::mem::take(&mut body),
+                std::mem::take(&mut keys),
+            ));
+        }
+    }
+    if !body.is_empty() {
+        messages.push(build_trace_message(trace_topic, body, keys));
+    }
+    messages
+}
+
+fn build_trace_message(
+    trace_topic: &CheetahString,
+    body: String,
+    keys: BTreeSet<String>,
+) -> MessageExtBrokerInner {
+    let mut msg = MessageExtBrokerInner::default();
+    msg.set_topic(trace_topic.clone());
+    msg.set_body(Bytes::from(body));
+    msg.message_ext_inner.queue_id = 0;
+    msg.message_ext_inner.born_timestamp = get_current_millis() as i64;
+    msg.set_keys(CheetahString::from_string(
+        keys.into_iter()
+            .collect::<Vec<_>>()
+            .join(MessageConst::KEY_SEPARATOR),
+    ));
+    msg.properties_string = message_decoder::message_properties_to_string(msg.get_properties());
+    msg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pub_record() -> BrokerTraceRecord {
+        let mut record = BrokerTraceRecord::new(BrokerTraceType::Pub);
+        record.timestamp = 1;
+        record.group = "group".into();
+        record.topic = "topic".into();
+        record.msg_id = "id".into();
+        record.keys = "k1 k2".into();
+        record
+    }
+
+    #[test]
+    fn encodes_like_the_client_trace_encoder() {
+        let mut buf = String::new();
+        pub_record().encode(&mut buf);
+        let fields: Vec<&str> = buf
+            .trim_end_matches(FIELD_SPLITOR)
+            .split(CONTENT_SPLITOR)
+            .collect();
+        assert_eq!(fields.len(), 14);
+        assert_eq!(fields[0], "Pub");
+        assert_eq!(fields[4], "topic");
+        assert_eq!(fields[13], "true");
+    }
+
+    #[test]
+    fn batches_records_into_trace_messages() {
+        let mut batch = vec![pub_record(), pub_record()];
+        let messages = build_trace_messages(&mut batch, &"RMQ_SYS_TRACE_TOPIC".into());
+        assert!(batch.is_empty());
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].get_keys().unwrap().as_str(), "id k1 k2");
+    }
+
+    #[test]
+    fn drops_records_when_full() {
+        let dispatcher = BrokerTraceDispatcher::new(1);
+        assert!(dispatcher.dispatch(pub_record()));
+        assert!(!dispatcher.dispatch(pub_record()));
+        assert_eq!(dispatcher.discard_count(), 1);
+    }
+}
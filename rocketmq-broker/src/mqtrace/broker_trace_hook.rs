@@ -0,0 +1,141 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::sync::Arc;
+
+use cheetah_string::CheetahString;
+use rocketmq_common::common::message::message_decoder;
+use rocketmq_common::common::message::MessageConst;
+use rocketmq_common::TimeUtils::get_current_millis;
+
+use crate::mqtrace::broker_trace_dispatcher::BrokerTraceDispatcher;
+use crate::mqtrace::broker_trace_dispatcher::BrokerTraceRecord;
+use crate::mqtrace::broker_trace_dispatcher::BrokerTraceType;
+use crate::mqtrace::consume_message_context::ConsumeMessageContext;
+use crate::mqtrace::consume_message_hook::ConsumeMessageHook;
+use crate::mqtrace::send_message_context::SendMessageContext;
+use crate::mqtrace::send_message_hook::SendMessageHook;
+
+/// Records a `Pub` trace for every message stored by the broker.
+pub(crate) struct BrokerTraceSendMessageHook {
+    dispatcher: Arc<BrokerTraceDispatcher>,
+    trace_topic: CheetahString,
+}
+
+impl BrokerTraceSendMessageHook {
+    pub(crate) fn new(dispatcher: Arc<BrokerTraceDispatcher>, trace_topic: CheetahString) -> Self {
+        Self {
+            dispatcher,
+            trace_topic,
+        }
+    }
+}
+
+impl SendMessageHook for BrokerTraceSendMessageHook {
+    fn hook_name(&self) -> &str {
+        "BrokerTraceSendMessageHook"
+    }
+
+    fn send_message_before(&self, _context: &SendMessageContext) {}
+
+    fn send_message_after(&self, context: &SendMessageContext) {
+        if context.topic == self.trace_topic {
+            return;
+        }
+        let properties = message_decoder::string_to_message_properties(Some(&context.msg_props));
+        let mut record = BrokerTraceRecord::new(BrokerTraceType::Pub);
+        record.region_id.clone_from(&context.broker_region_id);
+        record.group.clone_from(&context.producer_group);
+        record.topic.clone_from(&context.topic);
+        record.msg_id = if context.msg_unique_key.is_empty() {
+            context.msg_id.clone()
+        } else {
+            context.msg_unique_key.clone()
+        };
+        record.offset_msg_id.clone_from(&context.msg_id);
+        if let Some(tags) = properties.get(MessageConst::PROPERTY_TAGS) {
+            record.tags.clone_from(tags);
+        }
+        if let Some(keys) = properties.get(MessageConst::PROPERTY_KEYS) {
+            record.keys.clone_from(keys);
+        }
+        record.store_host.clone_from(&context.broker_addr);
+        record.body_length = context.body_length;
+        record.cost_time = get_current_millis() as i64 - context.request_time_stamp;
+        record.msg_type = context.msg_type;
+        record.success = context.is_success;
+        self.dispatcher.dispatch(record);
+    }
+}
+
+/// Records a `SubBefore` trace for every message delivered by a pull.
+pub(crate) struct BrokerTraceConsumeMessageHook {
+    dispatcher: Arc<BrokerTraceDispatcher>,
+    region_id: CheetahString,
+}
+
+impl BrokerTraceConsumeMessageHook {
+    pub(crate) fn new(dispatcher: Arc<BrokerTraceDispatcher>, region_id: CheetahString) -> Self {
+        Self {
+            dispatcher,
+            region_id,
+        }
+    }
+}
+
+impl ConsumeMessageHook for BrokerTraceConsumeMessageHook {
+    fn hook_name(&self) -> &str {
+        "BrokerTraceConsumeMessageHook"
+    }
+
+    fn consume_message_before(&self, context: &mut ConsumeMessageContext) {
+        for msg_id in context.message_ids.keys() {
+            let mut record = BrokerTraceRecord::new(BrokerTraceType::SubBefore);
+            record.region_id.clone_from(&self.region_id);
+            record.group.clone_from(&context.consumer_group);
+            record.topic.clone_from(&context.topic);
+            record.msg_id = CheetahString::from_slice(msg_id);
+            record.store_host.clone_from(&context.store_host);
+            record.request_id.clone_from(&context.client_host);
+            self.dispatcher.dispatch(record);
+        }
+    }
+
+    fn consume_message_after(&self, _context: &mut ConsumeMessageContext) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_messages_of_the_trace_topic() {
+        let dispatcher = Arc::new(BrokerTraceDispatcher::new(1));
+        let hook = BrokerTraceSendMessageHook::new(dispatcher.clone(), "trace".into());
+        let context = SendMessageContext {
+            topic: "trace".into(),
+            ..Default::default()
+        };
+        hook.send_message_after(&context);
+        let context = SendMessageContext {
+            topic: "topic".into(),
+            ..Default::default()
+        };
+        hook.send_message_after(&context);
+        hook.send_message_after(&context);
+        assert_eq!(dispatcher.discard_count(), 1);
+    }
+}
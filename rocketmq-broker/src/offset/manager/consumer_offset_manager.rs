@@ -20,13 +20,15 @@ use std::collections::HashSet;
 use std::fmt;
 use std::net::SocketAddr;
 use std::sync::atomic::AtomicI64;
+use std::sync::atomic::AtomicU64;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
 use cheetah_string::CheetahString;
-use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::common::broker::shared_broker_config::SharedBrokerConfig;
 use rocketmq_common::common::config_manager::ConfigManager;
 use rocketmq_common::utils::serde_json_utils::SerdeJsonUtils;
+use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_remoting::protocol::DataVersion;
 use rocketmq_remoting::protocol::RemotingSerializable;
 use rocketmq_rust::ArcMut;
@@ -43,19 +45,51 @@ use serde::Serializer;
 use tracing::warn;
 
 use crate::broker_path_config_helper::get_consumer_offset_path;
+#[cfg(feature = "rocksdb")]
+use crate::broker_path_config_helper::get_consumer_offset_rocksdb_path;
+#[cfg(feature = "rocksdb")]
+use crate::config::rocksdb_config_manager::load_kv;
+#[cfg(feature = "rocksdb")]
+use crate::config::rocksdb_config_manager::persist_kv;
+#[cfg(feature = "rocksdb")]
+use crate::config::rocksdb_config_manager::KvConfig;
+#[cfg(feature = "rocksdb")]
+use crate::config::rocksdb_config_manager::RocksDBConfigSlot;
+#[cfg(feature = "rocksdb")]
+use crate::config::rocksdb_config_manager::DEFAULT_CF;
 
 pub const TOPIC_GROUP_SEPARATOR: &str = "@";
 
 #[derive(Default, Clone)]
 pub(crate) struct ConsumerOffsetManager {
-    pub(crate) broker_config: Arc<BrokerConfig>,
+    pub(crate) broker_config: SharedBrokerConfig,
     consumer_offset_wrapper: ConsumerOffsetWrapper,
     message_store: Option<ArcMut<DefaultMessageStore>>,
+    rejected_rollback_count: Arc<AtomicU64>,
+    /// When each queue was last pulled, not persisted.
+    pull_timestamp_table: Arc<parking_lot::RwLock<HashMap<CheetahString, HashMap<i32, u64>>>>,
+    /// Whether the offsets are kept in RocksDB, that is storeType is RocksDB.
+    #[cfg(feature = "rocksdb")]
+    enable_rocksdb_store: bool,
+    #[cfg(feature = "rocksdb")]
+    rocksdb_config: RocksDBConfigSlot,
+}
+
+/// How far a group is behind on one queue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct QueueLag {
+    pub(crate) broker_offset: i64,
+    pub(crate) consumer_offset: i64,
+    pub(crate) pull_offset: i64,
+    /// Messages the group has not committed yet.
+    pub(crate) lag: i64,
+    /// Millis since the queue was last pulled by the group, `None` if it was not since startup.
+    pub(crate) pull_staleness_millis: Option<u64>,
 }
 
 impl ConsumerOffsetManager {
     pub fn new(
-        broker_config: Arc<BrokerConfig>,
+        broker_config: SharedBrokerConfig,
         message_store: Option<ArcMut<DefaultMessageStore>>,
     ) -> Self {
         ConsumerOffsetManager {
@@ -68,29 +102,29 @@ impl ConsumerOffsetManager {
                 version_change_counter: Arc::new(AtomicI64::new(0)),
             },
             message_store,
+            rejected_rollback_count: Arc::new(AtomicU64::new(0)),
+            pull_timestamp_table: Arc::new(parking_lot::RwLock::new(HashMap::new())),
+            #[cfg(feature = "rocksdb")]
+            enable_rocksdb_store: false,
+            #[cfg(feature = "rocksdb")]
+            rocksdb_config: Default::default(),
         }
     }
+
+    #[cfg(feature = "rocksdb")]
+    pub fn with_rocksdb_store(mut self, enable_rocksdb_store: bool) -> Self {
+        self.enable_rocksdb_store = enable_rocksdb_store;
+        self
+    }
     pub fn set_message_store(&mut self, message_store: Option<ArcMut<DefaultMessageStore>>) {
         self.message_store = message_store;
     }
 }
 
 impl ConsumerOffsetManager {
+    /// Removes the committed, reset and pull offsets every group has on `topic`.
     pub fn clean_offset_by_topic(&self, topic: &CheetahString) {
-        let mut offset_table = self.consumer_offset_wrapper.offset_table.write();
-        let mut keys_to_remove = Vec::new();
-
-        for (topic_at_group, _) in offset_table.iter() {
-            if topic_at_group.contains(topic.as_str()) {
-                let arrays: Vec<&str> = topic_at_group.split(TOPIC_GROUP_SEPARATOR).collect();
-                if arrays.len() == 2 && arrays[0] == topic {
-                    keys_to_remove.push(topic_at_group.clone());
-                }
-            }
-        }
-        for key in keys_to_remove {
-            offset_table.remove(&key);
-        }
+        self.retain_offsets(|topic_of_key, _| topic_of_key != topic.as_str());
     }
 
     pub fn which_group_by_topic(&self, topic: &str) -> HashSet<CheetahString> {
@@ -137,7 +171,7 @@ impl ConsumerOffsetManager {
             .consumer_offset_wrapper
             .version_change_counter
             .load(Ordering::Acquire)
-            % self.broker_config.consumer_offset_update_version_step
+            % self.broker_config.get().consumer_offset_update_version_step
             == 0
         {
             let state_machine_version = if let Some(ref message_store) = self.message_store {
@@ -152,6 +186,54 @@ impl ConsumerOffsetManager {
         }
     }
 
+    /// Commits an offset reported by a client.
+    ///
+    /// With rollback protection enabled, a commit moving the offset back further than
+    /// `offset_rollback_protect_window` is rejected unless the queue offset was reset on the
+    /// server side. Returns whether the offset was committed.
+    pub fn commit_client_offset(
+        &self,
+        client_host: CheetahString,
+        group: &CheetahString,
+        topic: &CheetahString,
+        queue_id: i32,
+        offset: i64,
+    ) -> bool {
+        if self.broker_config.get().enable_offset_rollback_protection
+            && !self.has_offset_reset(group, topic, queue_id)
+        {
+            let store_offset = self
+                .query_offset_table(group, topic)
+                .get(&queue_id)
+                .copied();
+            if let Some(store_offset) = store_offset {
+                if offset
+                    < store_offset
+                        - self
+                            .broker_config
+                            .get()
+                            .offset_rollback_protect_window
+                            .max(0)
+                {
+                    self.rejected_rollback_count.fetch_add(1, Ordering::Relaxed);
+                    warn!(
+                        "reject consumer offset rollback. clientHost={}, group={}, topic={}, \
+                         queueId={}, requestOffset={}, storeOffset={}",
+                        client_host, group, topic, queue_id, offset, store_offset
+                    );
+                    return false;
+                }
+            }
+        }
+        self.commit_offset(client_host, group, topic, queue_id, offset);
+        true
+    }
+
+    /// Number of client commits rejected by the rollback protection since startup.
+    pub fn rejected_rollback_count(&self) -> u64 {
+        self.rejected_rollback_count.load(Ordering::Relaxed)
+    }
+
     pub fn has_offset_reset(&self, group: &str, topic: &str, queue_id: i32) -> bool {
         let key = format!("{}{}{}", topic, TOPIC_GROUP_SEPARATOR, group);
         match self
@@ -167,7 +249,7 @@ impl ConsumerOffsetManager {
 
     pub fn query_offset(&self, group: &CheetahString, topic: &CheetahString, queue_id: i32) -> i64 {
         let key = format!("{}{}{}", topic, TOPIC_GROUP_SEPARATOR, group);
-        if self.broker_config.use_server_side_reset_offset {
+        if self.broker_config.get().use_server_side_reset_offset {
             if let Some(value) = self
                 .consumer_offset_wrapper
                 .reset_offset_table
@@ -192,6 +274,199 @@ impl ConsumerOffsetManager {
         -1
     }
 
+    pub fn query_pull_offset(
+        &self,
+        group: &CheetahString,
+        topic: &CheetahString,
+        queue_id: i32,
+    ) -> i64 {
+        let key = format!("{}{}{}", topic, TOPIC_GROUP_SEPARATOR, group);
+        if let Some(value) = self
+            .consumer_offset_wrapper
+            .pull_offset_table
+            .read()
+            .get(key.as_str())
+        {
+            if let Some(offset) = value.get(&queue_id) {
+                return *offset;
+            }
+        }
+        self.query_offset(group, topic, queue_id)
+    }
+
+    /// All committed offsets of `group` on `topic`, keyed by queue id.
+    pub fn query_offset_table(
+        &self,
+        group: &CheetahString,
+        topic: &CheetahString,
+    ) -> HashMap<i32, i64> {
+        let key = format!("{}{}{}", topic, TOPIC_GROUP_SEPARATOR, group);
+        self.consumer_offset_wrapper
+            .offset_table
+            .read()
+            .get(key.as_str())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Copies the offsets `src_group` committed on `topic` to `dest_group`.
+    pub fn clone_offset(
+        &self,
+        src_group: &CheetahString,
+        dest_group: &CheetahString,
+        topic: &CheetahString,
+    ) {
+        let src_key = format!("{}{}{}", topic, TOPIC_GROUP_SEPARATOR, src_group);
+        let mut write_guard = self.consumer_offset_wrapper.offset_table.write();
+        if let Some(offsets) = write_guard.get(src_key.as_str()).cloned() {
+            let dest_key = CheetahString::from_string(format!(
+                "{}{}{}",
+                topic, TOPIC_GROUP_SEPARATOR, dest_group
+            ));
+            write_guard.insert(dest_key, offsets);
+        }
+    }
+
+    /// Smallest offset per queue of `topic` over all groups except `filter_groups` (comma
+    /// separated). Offsets already behind the queue's min offset are ignored.
+    pub fn query_min_offset_in_all_group(
+        &self,
+        topic: &CheetahString,
+        filter_groups: Option<&str>,
+    ) -> HashMap<i32, i64> {
+        let filtered = filter_groups
+            .map(|groups| {
+                groups
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|group| !group.is_empty())
+                    .collect::<HashSet<_>>()
+            })
+            .unwrap_or_default();
+        let mut queue_min_offset = HashMap::new();
+        let read_guard = self.consumer_offset_wrapper.offset_table.read();
+        for (topic_at_group, offsets) in read_guard.iter() {
+            let arr: Vec<&str> = topic_at_group.split(TOPIC_GROUP_SEPARATOR).collect();
+            if arr.len() != 2 || arr[0] != topic.as_str() || filtered.contains(arr[1]) {
+                continue;
+            }
+            for (queue_id, offset) in offsets {
+                let min_offset = self
+                    .message_store
+                    .as_ref()
+                    .map_or(0, |store| store.get_min_offset_in_queue(topic, *queue_id));
+                if *offset >= min_offset {
+                    queue_min_offset
+                        .entry(*queue_id)
+                        .and_modify(|min: &mut i64| *min = (*min).min(*offset))
+                        .or_insert(*offset);
+                }
+            }
+        }
+        queue_min_offset
+    }
+
+    /// Removes the committed, reset and pull offsets of `group` on every topic.
+    pub fn remove_offset(&self, group: &CheetahString) {
+        self.retain_offsets(|_, group_of_key| group_of_key != group.as_str());
+    }
+
+    /// Keeps the offsets of the `topic@group` keys `keep` returns true for.
+    fn retain_offsets(&self, keep: impl Fn(&str, &str) -> bool) {
+        let keep_key = |topic_at_group: &CheetahString| {
+            let arrays: Vec<&str> = topic_at_group.split(TOPIC_GROUP_SEPARATOR).collect();
+            arrays.len() != 2 || keep(arrays[0], arrays[1])
+        };
+        for table in [
+            &self.consumer_offset_wrapper.offset_table,
+            &self.consumer_offset_wrapper.reset_offset_table,
+            &self.consumer_offset_wrapper.pull_offset_table,
+        ] {
+            table.write().retain(|topic_at_group, _| {
+                let keep = keep_key(topic_at_group);
+                if !keep {
+                    warn!("Clean group's offset, {}", topic_at_group);
+                }
+                keep
+            });
+        }
+        self.pull_timestamp_table
+            .write()
+            .retain(|topic_at_group, _| keep_key(topic_at_group));
+    }
+
+    /// Lag of `group` on `queue_id` of `topic`, whose max offset is `broker_offset`.
+    ///
+    /// A queue the group never committed counts as consumed from its start, except for static
+    /// topics where the offset lives on another broker and is left at `-1`.
+    pub(crate) fn queue_lag(
+        &self,
+        group: &CheetahString,
+        topic: &CheetahString,
+        queue_id: i32,
+        broker_offset: i64,
+        static_topic: bool,
+    ) -> QueueLag {
+        let broker_offset = broker_offset.max(0);
+        let mut consumer_offset = self.query_offset(group, topic, queue_id);
+        if !static_topic && consumer_offset < 0 {
+            consumer_offset = 0;
+        }
+        let pull_offset = self
+            .query_pull_offset(group, topic, queue_id)
+            .max(consumer_offset);
+        let key = format!("{}{}{}", topic, TOPIC_GROUP_SEPARATOR, group);
+        let pull_staleness_millis = self
+            .pull_timestamp_table
+            .read()
+            .get(key.as_str())
+            .and_then(|queues| queues.get(&queue_id).copied())
+            .map(|pulled| get_current_millis().saturating_sub(pulled));
+        QueueLag {
+            broker_offset,
+            consumer_offset,
+            pull_offset,
+            lag: (broker_offset - consumer_offset.max(0)).max(0),
+            pull_staleness_millis,
+        }
+    }
+
+    /// Total lag of `group` over the queues it committed offsets on, `max_offset` gives the max
+    /// offset of a topic queue.
+    pub(crate) fn group_lag(
+        &self,
+        group: &CheetahString,
+        max_offset: impl Fn(&CheetahString, i32) -> i64,
+    ) -> i64 {
+        self.which_topic_by_consumer(group)
+            .iter()
+            .map(|topic| {
+                self.query_offset_table(group, topic)
+                    .keys()
+                    .map(|&queue_id| {
+                        self.queue_lag(group, topic, queue_id, max_offset(topic, queue_id), false)
+                            .lag
+                    })
+                    .sum::<i64>()
+            })
+            .sum()
+    }
+
+    /// Every group that committed an offset, with its total lag.
+    pub(crate) fn all_group_lag(
+        &self,
+        max_offset: impl Fn(&CheetahString, i32) -> i64,
+    ) -> HashMap<CheetahString, i64> {
+        self.consumer_offset_wrapper
+            .get_group_topic_map()
+            .into_keys()
+            .map(|group| {
+                let lag = self.group_lag(&group, &max_offset);
+                (group, lag)
+            })
+            .collect()
+    }
+
     pub fn which_topic_by_consumer(&self, group: &CheetahString) -> HashSet<CheetahString> {
         let read_guard = self.consumer_offset_wrapper.offset_table.read();
         let mut topics = HashSet::new();
@@ -207,8 +482,18 @@ impl ConsumerOffsetManager {
 }
 
 impl ConfigManager for ConsumerOffsetManager {
+    #[cfg(feature = "rocksdb")]
+    fn load(&self) -> bool {
+        load_kv(self, &self.rocksdb_config)
+    }
+
+    #[cfg(feature = "rocksdb")]
+    fn persist(&self) {
+        persist_kv(self, &self.rocksdb_config)
+    }
+
     fn config_file_path(&self) -> String {
-        get_consumer_offset_path(self.broker_config.store_path_root_dir.as_str())
+        get_consumer_offset_path(self.broker_config.get().store_path_root_dir.as_str())
     }
 
     fn encode_pretty(&self, pretty_format: bool) -> String {
@@ -239,6 +524,51 @@ impl ConfigManager for ConsumerOffsetManager {
     }
 }
 
+#[cfg(feature = "rocksdb")]
+impl KvConfig for ConsumerOffsetManager {
+    fn rocksdb_enabled(&self) -> bool {
+        self.enable_rocksdb_store
+    }
+
+    fn rocksdb_config_path(&self) -> String {
+        get_consumer_offset_rocksdb_path(self.broker_config.store_path_root_dir.as_str())
+    }
+
+    fn encode_kv(&self) -> Vec<(&'static str, HashMap<CheetahString, Vec<u8>>)> {
+        let entries = self
+            .consumer_offset_wrapper
+            .offset_table
+            .read()
+            .iter()
+            .map(|(key, offsets)| (key.clone(), serde_json::to_vec(offsets).unwrap_or_default()))
+            .collect();
+        vec![(DEFAULT_CF, entries)]
+    }
+
+    fn decode_kv(&self, _column_family: &str, key: &str, value: &[u8]) {
+        match serde_json::from_slice::<HashMap<i32, i64>>(value) {
+            Ok(offsets) => {
+                self.consumer_offset_wrapper
+                    .offset_table
+                    .write()
+                    .insert(key.into(), offsets);
+            }
+            Err(e) => warn!("decode consumer offsets of {} failed, {}", key, e),
+        }
+    }
+
+    fn kv_data_version(&self) -> DataVersion {
+        self.consumer_offset_wrapper.data_version.as_ref().clone()
+    }
+
+    fn set_kv_data_version(&self, data_version: &DataVersion) {
+        self.consumer_offset_wrapper
+            .data_version
+            .mut_from_ref()
+            .assign_new_one(data_version);
+    }
+}
+
 #[allow(unused_variables)]
 impl ConsumerOffsetManager {
     pub fn commit_pull_offset(
@@ -251,6 +581,11 @@ impl ConsumerOffsetManager {
     ) {
         let key =
             CheetahString::from_string(format!("{}{}{}", topic, TOPIC_GROUP_SEPARATOR, group));
+        self.pull_timestamp_table
+            .write()
+            .entry(key.clone())
+            .or_default()
+            .insert(queue_id, get_current_millis());
         self.consumer_offset_wrapper
             .pull_offset_table
             .write()
@@ -259,6 +594,38 @@ impl ConsumerOffsetManager {
             .insert(queue_id, offset);
     }
 
+    pub fn assign_reset_offset(
+        &self,
+        topic: &CheetahString,
+        group: &CheetahString,
+        queue_id: i32,
+        offset: i64,
+    ) {
+        if topic.is_empty() || group.is_empty() || queue_id < 0 || offset < 0 {
+            warn!(
+                "Illegal arguments when assigning reset offset. Topic={}, group={}, queueId={}, \
+                 offset={}",
+                topic, group, queue_id, offset
+            );
+            return;
+        }
+        let key =
+            CheetahString::from_string(format!("{}{}{}", topic, TOPIC_GROUP_SEPARATOR, group));
+        self.consumer_offset_wrapper
+            .reset_offset_table
+            .write()
+            .entry(key.clone())
+            .or_default()
+            .insert(queue_id, offset);
+        // the client may override it right away, but it still takes effect for offline clients
+        self.consumer_offset_wrapper
+            .offset_table
+            .write()
+            .entry(key)
+            .or_default()
+            .insert(queue_id, offset);
+    }
+
     pub fn query_then_erase_reset_offset(
         &self,
         topic: &CheetahString,
@@ -435,3 +802,125 @@ impl<'de> Deserialize<'de> for ConsumerOffsetWrapper {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rocketmq_common::common::broker::broker_config::BrokerConfig;
+
+    use super::*;
+
+    fn manager() -> ConsumerOffsetManager {
+        ConsumerOffsetManager::new(SharedBrokerConfig::default(), None)
+    }
+
+    #[test]
+    fn commit_client_offset_rejects_rollback_beyond_window() {
+        let manager = ConsumerOffsetManager::new(
+            SharedBrokerConfig::new(BrokerConfig {
+                enable_offset_rollback_protection: true,
+                offset_rollback_protect_window: 10,
+                ..BrokerConfig::default()
+            }),
+            None,
+        );
+        let topic = CheetahString::from_static_str("topic");
+        let group = CheetahString::from_static_str("group");
+        assert!(manager.commit_client_offset("127.0.0.1".into(), &group, &topic, 0, 100));
+        assert!(manager.commit_client_offset("127.0.0.1".into(), &group, &topic, 0, 90));
+        assert!(!manager.commit_client_offset("127.0.0.1".into(), &group, &topic, 0, 50));
+        assert_eq!(manager.query_offset(&group, &topic, 0), 90);
+        assert_eq!(manager.rejected_rollback_count(), 1);
+
+        manager.assign_reset_offset(&topic, &group, 0, 20);
+        assert!(manager.commit_client_offset("127.0.0.1".into(), &group, &topic, 0, 20));
+    }
+
+    #[test]
+    fn remove_offset_only_drops_the_group() {
+        let manager = manager();
+        let topic = CheetahString::from_static_str("topic");
+        let group = CheetahString::from_static_str("group");
+        let other = CheetahString::from_static_str("other");
+        manager.commit_offset("127.0.0.1".into(), &group, &topic, 0, 10);
+        manager.commit_offset("127.0.0.1".into(), &other, &topic, 0, 20);
+        manager.remove_offset(&group);
+        assert_eq!(manager.query_offset(&group, &topic, 0), -1);
+        assert_eq!(manager.query_offset(&other, &topic, 0), 20);
+    }
+
+    #[test]
+    fn clean_offset_by_topic_drops_every_table() {
+        let manager = manager();
+        let topic = CheetahString::from_static_str("topic");
+        let other = CheetahString::from_static_str("other");
+        let group = CheetahString::from_static_str("group");
+        manager.commit_offset("127.0.0.1".into(), &group, &topic, 0, 10);
+        manager.commit_offset("127.0.0.1".into(), &group, &other, 0, 20);
+        manager.commit_pull_offset("127.0.0.1:0".parse().unwrap(), &group, &topic, 0, 12);
+        manager.clean_offset_by_topic(&topic);
+        assert_eq!(manager.query_pull_offset(&group, &topic, 0), -1);
+        assert_eq!(
+            manager
+                .queue_lag(&group, &topic, 0, 0, false)
+                .pull_staleness_millis,
+            None
+        );
+        assert_eq!(manager.query_offset(&group, &other, 0), 20);
+    }
+
+    #[test]
+    fn lag_is_broker_offset_minus_committed_offset() {
+        let manager = manager();
+        let topic = CheetahString::from_static_str("topic");
+        let group = CheetahString::from_static_str("group");
+        manager.commit_offset("127.0.0.1".into(), &group, &topic, 0, 10);
+        manager.commit_offset("127.0.0.1".into(), &group, &topic, 1, 30);
+        manager.commit_pull_offset("127.0.0.1:0".parse().unwrap(), &group, &topic, 0, 15);
+
+        let lag = manager.queue_lag(&group, &topic, 0, 25, false);
+        assert_eq!(lag.lag, 15);
+        assert_eq!(lag.pull_offset, 15);
+        assert!(lag.pull_staleness_millis.is_some());
+        assert_eq!(manager.queue_lag(&group, &topic, 2, 5, false).lag, 5);
+        assert_eq!(
+            manager
+                .queue_lag(&group, &topic, 2, 5, true)
+                .consumer_offset,
+            -1
+        );
+
+        assert_eq!(manager.group_lag(&group, |_, _| 40), 30 + 10);
+        let all = manager.all_group_lag(|_, _| 40);
+        assert_eq!(all.get(&group), Some(&40));
+    }
+
+    #[test]
+    fn clone_offset_copies_source_group() {
+        let manager = manager();
+        let topic = CheetahString::from_static_str("topic");
+        let src = CheetahString::from_static_str("src");
+        let dest = CheetahString::from_static_str("dest");
+        manager.commit_offset("127.0.0.1".into(), &src, &topic, 0, 10);
+        manager.commit_offset("127.0.0.1".into(), &src, &topic, 1, 20);
+        manager.clone_offset(&src, &dest, &topic);
+        assert_eq!(manager.query_offset(&dest, &topic, 0), 10);
+        assert_eq!(manager.query_offset(&dest, &topic, 1), 20);
+        assert_eq!(manager.query_offset_table(&dest, &topic).len(), 2);
+    }
+
+    #[test]
+    fn query_min_offset_in_all_group_skips_filtered_groups() {
+        let manager = manager();
+        let topic = CheetahString::from_static_str("topic");
+        let a = CheetahString::from_static_str("a");
+        let b = CheetahString::from_static_str("b");
+        let c = CheetahString::from_static_str("c");
+        manager.commit_offset("127.0.0.1".into(), &a, &topic, 0, 10);
+        manager.commit_offset("127.0.0.1".into(), &b, &topic, 0, 5);
+        manager.commit_offset("127.0.0.1".into(), &c, &topic, 0, 1);
+        let min = manager.query_min_offset_in_all_group(&topic, Some("c"));
+        assert_eq!(min.get(&0), Some(&5));
+        let min = manager.query_min_offset_in_all_group(&topic, None);
+        assert_eq!(min.get(&0), Some(&1));
+    }
+}
@@ -108,6 +108,24 @@ impl<MS: MessageStore> ConsumerOrderInfoManager<MS> {
         unimplemented!()
     }
 
+    /// Drops the order info of every group consuming `topic`.
+    pub fn remove_topic(&self, topic: &CheetahString) {
+        let prefix = format!("{}{}", topic, TOPIC_GROUP_SEPARATOR);
+        let mut consumer_order_info_wrapper = self.consumer_order_info_wrapper.lock();
+        consumer_order_info_wrapper
+            .table
+            .retain(|topic_at_group, _| !topic_at_group.starts_with(prefix.as_str()));
+    }
+
+    /// Drops the order info of `group` on every topic.
+    pub fn remove_group(&self, group: &CheetahString) {
+        let suffix = format!("{}{}", TOPIC_GROUP_SEPARATOR, group);
+        let mut consumer_order_info_wrapper = self.consumer_order_info_wrapper.lock();
+        consumer_order_info_wrapper
+            .table
+            .retain(|topic_at_group, _| !topic_at_group.ends_with(suffix.as_str()));
+    }
+
     pub fn auto_clean(&self) {
         let mut consumer_order_info_wrapper = self.consumer_order_info_wrapper.lock();
         let table = &mut consumer_order_info_wrapper.table;
@@ -550,7 +568,46 @@ impl OrderInfo {
 mod tests {
     use std::collections::HashMap;
 
+    use rocketmq_common::common::broker::broker_config::BrokerConfig;
+    use rocketmq_common::common::server::config::ServerConfig;
+    use rocketmq_store::config::message_store_config::MessageStoreConfig;
+
     use super::*;
+    use crate::broker_runtime::BrokerRuntime;
+
+    #[test]
+    fn remove_topic_keeps_the_order_info_of_other_topics() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let _guard = runtime.enter();
+        let broker = BrokerRuntime::new(
+            BrokerConfig::default(),
+            MessageStoreConfig::default(),
+            ServerConfig::default(),
+        );
+        let manager = broker.inner().consumer_order_info_manager();
+        for topic_at_group in [
+            "TopicA@GroupA",
+            "TopicA@GroupB",
+            "TopicAB@GroupA",
+            "TopicB@GroupA",
+        ] {
+            manager
+                .consumer_order_info_wrapper
+                .lock()
+                .table
+                .insert(topic_at_group.into(), HashMap::new());
+        }
+
+        manager.remove_topic(&"TopicA".into());
+        let wrapper = manager.consumer_order_info_wrapper.lock();
+        let mut remaining = wrapper
+            .table
+            .keys()
+            .map(|key| key.as_str())
+            .collect::<Vec<_>>();
+        remaining.sort();
+        assert_eq!(remaining, ["TopicAB@GroupA", "TopicB@GroupA"]);
+    }
 
     #[test]
     fn build_offset_list_with_single_element() {
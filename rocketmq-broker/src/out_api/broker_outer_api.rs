@@ -16,9 +16,11 @@
  */
 use std::collections::HashSet;
 use std::sync::Arc;
+use std::time::Duration;
 
 use cheetah_string::CheetahString;
 use dns_lookup::lookup_host;
+use futures::future::join_all;
 use rocketmq_client_rust::consumer::pull_result::PullResult;
 use rocketmq_client_rust::consumer::pull_status::PullStatus;
 use rocketmq_client_rust::producer::send_result::SendResult;
@@ -44,16 +46,24 @@ use rocketmq_remoting::clients::rocketmq_default_impl::RocketmqDefaultClient;
 use rocketmq_remoting::clients::RemotingClient;
 use rocketmq_remoting::code::request_code::RequestCode;
 use rocketmq_remoting::code::response_code::ResponseCode;
+use rocketmq_remoting::protocol::body::broker_body::broker_member_group::BrokerMemberGroup;
+use rocketmq_remoting::protocol::body::broker_body::broker_member_group::GetBrokerMemberGroupResponseBody;
 use rocketmq_remoting::protocol::body::broker_body::register_broker_body::RegisterBrokerBody;
+use rocketmq_remoting::protocol::body::ha_runtime_info::HARuntimeInfo;
 use rocketmq_remoting::protocol::body::kv_table::KVTable;
 use rocketmq_remoting::protocol::body::response::lock_batch_response_body::LockBatchResponseBody;
 use rocketmq_remoting::protocol::body::topic_info_wrapper::topic_config_wrapper::TopicConfigAndMappingSerializeWrapper;
 use rocketmq_remoting::protocol::header::client_request_header::GetRouteInfoRequestHeader;
+use rocketmq_remoting::protocol::header::exchange_ha_info_request_header::ExchangeHAInfoRequestHeader;
+use rocketmq_remoting::protocol::header::exchange_ha_info_response_header::ExchangeHAInfoResponseHeader;
 use rocketmq_remoting::protocol::header::lock_batch_mq_request_header::LockBatchMqRequestHeader;
 use rocketmq_remoting::protocol::header::message_operation_header::send_message_request_header::SendMessageRequestHeader;
 use rocketmq_remoting::protocol::header::message_operation_header::send_message_request_header_v2::SendMessageRequestHeaderV2;
 use rocketmq_remoting::protocol::header::message_operation_header::send_message_response_header::SendMessageResponseHeader;
+use rocketmq_remoting::protocol::header::namesrv::broker_request::GetBrokerMemberGroupRequestHeader;
 use rocketmq_remoting::protocol::header::namesrv::broker_request::UnRegisterBrokerRequestHeader;
+use rocketmq_remoting::protocol::header::namesrv::query_data_version_header::QueryDataVersionRequestHeader;
+use rocketmq_remoting::protocol::header::namesrv::query_data_version_header::QueryDataVersionResponseHeader;
 use rocketmq_remoting::protocol::header::namesrv::register_broker_header::RegisterBrokerRequestHeader;
 use rocketmq_remoting::protocol::header::namesrv::register_broker_header::RegisterBrokerResponseHeader;
 use rocketmq_remoting::protocol::header::namesrv::topic_operation_header::RegisterTopicRequestHeader;
@@ -66,6 +76,7 @@ use rocketmq_remoting::protocol::namesrv::RegisterBrokerResult;
 use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
 use rocketmq_remoting::protocol::route::route_data_view::QueueData;
 use rocketmq_remoting::protocol::route::topic_route_data::TopicRouteData;
+use rocketmq_remoting::protocol::DataVersion;
 use rocketmq_remoting::protocol::RemotingDeserializable;
 use rocketmq_remoting::protocol::RemotingSerializable;
 use rocketmq_remoting::remoting::RemotingService;
@@ -76,7 +87,6 @@ use rocketmq_remoting::rpc::rpc_request_header::RpcRequestHeader;
 use rocketmq_remoting::runtime::config::client_config::TokioClientConfig;
 use rocketmq_remoting::runtime::RPCHook;
 use rocketmq_rust::ArcMut;
-use rocketmq_store::log_file::MessageStore;
 use tracing::debug;
 use tracing::error;
 use tracing::info;
@@ -84,7 +94,7 @@ use tracing::warn;
 
 use crate::broker_error::BrokerError;
 use crate::broker_error::BrokerError::BrokerRemotingError;
-use crate::broker_runtime::BrokerRuntimeInner;
+use crate::subscription::manager::subscription_group_manager::SubscriptionGroupWrapper;
 use crate::Result;
 
 pub struct BrokerOuterAPI {
@@ -175,7 +185,9 @@ impl BrokerOuterAPI {
             .await;
     }
 
-    pub async fn register_broker_all<MS: MessageStore>(
+    /// Registers the broker to every available name server at once, and returns the results of
+    /// the name servers that answered within `timeout_mills`.
+    pub async fn register_broker_all(
         &self,
         cluster_name: CheetahString,
         broker_addr: CheetahString,
@@ -190,66 +202,135 @@ impl BrokerOuterAPI {
         compressed: bool,
         heartbeat_timeout_millis: Option<i64>,
         _broker_identity: BrokerIdentity,
-        broker_runtime_inner: ArcMut<BrokerRuntimeInner<MS>>,
     ) -> Vec<RegisterBrokerResult> {
         let name_server_address_list = self.remoting_client.get_available_name_srv_list();
-        let mut register_broker_result_list = Vec::new();
-        if !name_server_address_list.is_empty() {
-            let mut request_header = RegisterBrokerRequestHeader {
-                broker_addr,
-                broker_id,
-                broker_name,
-                cluster_name,
-                ha_server_addr,
-                enable_acting_master: Some(enable_acting_master),
-                compressed: false,
-                heartbeat_timeout_millis,
-                body_crc32: 0,
-            };
-
-            //build request body
-            let request_body = RegisterBrokerBody {
-                topic_config_serialize_wrapper: topic_config_wrapper,
-                filter_server_list,
-            };
-            let body = request_body.encode(compressed);
-            let body_crc32 = crc32_utils::crc32(body.as_ref());
-            request_header.body_crc32 = body_crc32;
-
-            let mut handle_vec = Vec::with_capacity(name_server_address_list.len());
-            for namesrv_addr in name_server_address_list.iter() {
-                let cloned_body = body.clone();
-                let cloned_header = request_header.clone();
-                let addr = namesrv_addr.clone();
-                let broker_runtime_inner_ = broker_runtime_inner.clone();
-                let join_handle = tokio::spawn(async move {
-                    broker_runtime_inner_
-                        .broker_outer_api()
-                        .register_broker(&addr, oneway, timeout_mills, cloned_header, cloned_body)
-                        .await
-                });
-                /*let handle =
-                self.register_broker(addr, oneway, timeout_mills, cloned_header, cloned_body);*/
-                handle_vec.push(join_handle);
+        if name_server_address_list.is_empty() {
+            return Vec::new();
+        }
+        let mut request_header = RegisterBrokerRequestHeader {
+            broker_addr,
+            broker_id,
+            broker_name,
+            cluster_name,
+            ha_server_addr,
+            enable_acting_master: Some(enable_acting_master),
+            compressed: false,
+            heartbeat_timeout_millis,
+            body_crc32: 0,
+        };
+
+        //build request body
+        let request_body = RegisterBrokerBody {
+            topic_config_serialize_wrapper: topic_config_wrapper,
+            filter_server_list,
+        };
+        let body = request_body.encode(compressed);
+        let body_crc32 = crc32_utils::crc32(body.as_ref());
+        request_header.body_crc32 = body_crc32;
+
+        let timeout = Duration::from_millis(timeout_mills);
+        let results = join_all(name_server_address_list.iter().map(|namesrv_addr| {
+            tokio::time::timeout(
+                timeout,
+                self.register_broker(
+                    namesrv_addr,
+                    oneway,
+                    timeout_mills,
+                    request_header.clone(),
+                    body.clone(),
+                ),
+            )
+        }))
+        .await;
+        let mut register_broker_result_list = Vec::with_capacity(results.len());
+        for (namesrv_addr, result) in name_server_address_list.iter().zip(results) {
+            match result {
+                Ok(Some(register_broker_result)) => {
+                    register_broker_result_list.push(register_broker_result)
+                }
+                Ok(None) => {}
+                Err(_) => error!(
+                    "Register broker to name remoting_server timeout, namesrv_addr={}, \
+                     timeout_mills={}",
+                    namesrv_addr, timeout_mills
+                ),
             }
-            while let Some(handle) = handle_vec.pop() {
-                let result = tokio::join!(handle);
-                match result.0 {
-                    Ok(value) => {
-                        if let Some(v) = value {
-                            register_broker_result_list.push(v);
-                        } else {
-                            error!("Register broker to name remoting_server error");
-                        }
-                    }
-                    Err(e) => {
-                        error!("Register broker to name remoting_server error, error={}", e);
+        }
+        register_broker_result_list
+    }
+
+    /// Asks every available name server whether the topic config it holds for the broker is
+    /// older than `topic_config_wrapper`. The query also refreshes the liveness of the broker
+    /// on the name server, so it serves as a lightweight heartbeat while nothing has changed.
+    pub async fn need_register(
+        &self,
+        cluster_name: &CheetahString,
+        broker_addr: &CheetahString,
+        broker_name: &CheetahString,
+        broker_id: u64,
+        topic_config_wrapper: &TopicConfigAndMappingSerializeWrapper,
+        timeout_mills: u64,
+    ) -> Vec<bool> {
+        let data_version = topic_config_wrapper
+            .topic_config_serialize_wrapper()
+            .data_version()
+            .clone();
+        let body = match data_version.encode() {
+            Ok(body) => body,
+            Err(e) => {
+                error!("encode data version failed, {}", e);
+                return vec![true];
+            }
+        };
+        let request_header = QueryDataVersionRequestHeader::new(
+            broker_name.clone(),
+            broker_addr.clone(),
+            cluster_name.clone(),
+            broker_id,
+        );
+        let name_server_address_list = self.remoting_client.get_available_name_srv_list();
+        let results = join_all(name_server_address_list.iter().map(|namesrv_addr| {
+            let request = RemotingCommand::create_request_command(
+                RequestCode::QueryDataVersion,
+                request_header.clone(),
+            )
+            .set_body(body.clone());
+            self.remoting_client
+                .invoke_async(Some(namesrv_addr), request, timeout_mills)
+        }))
+        .await;
+        let mut changed_list = Vec::with_capacity(results.len());
+        for (namesrv_addr, result) in name_server_address_list.iter().zip(results) {
+            match result {
+                Ok(response) if ResponseCode::from(response.code()) == ResponseCode::Success => {
+                    let mut changed = response
+                        .decode_command_custom_header::<QueryDataVersionResponseHeader>()
+                        .is_ok_and(|header| header.changed());
+                    if !changed {
+                        changed = !response.body().as_ref().is_some_and(|body| {
+                            DataVersion::decode(body).is_ok_and(|name_server_data_version| {
+                                name_server_data_version == data_version
+                            })
+                        });
                     }
+                    debug!(
+                        "Query data version from name server {} OK, changed {}",
+                        namesrv_addr, changed
+                    );
+                    changed_list.push(changed);
                 }
+                Ok(response) => warn!(
+                    "Query data version from name server {} failed, code {}",
+                    namesrv_addr,
+                    response.code()
+                ),
+                Err(e) => error!(
+                    "Query data version from name server {} exception, {}",
+                    namesrv_addr, e
+                ),
             }
         }
-
-        register_broker_result_list
+        changed_list
     }
 
     async fn register_broker(
@@ -306,7 +387,16 @@ impl BrokerOuterAPI {
                     }
                     Some(result)
                 }
-                _ => None,
+                _ => {
+                    warn!(
+                        "Register broker to name remoting_server failed, namesrv_addr={}, \
+                         code={}, remark={:?}",
+                        namesrv_addr,
+                        response.code(),
+                        response.remark()
+                    );
+                    None
+                }
             },
             Err(err) => {
                 error!(
@@ -428,6 +518,186 @@ impl BrokerOuterAPI {
         }
     }
 
+    /// Fetches the topic configs and static topic mappings of the master at `addr`.
+    pub async fn get_all_topic_config(
+        &self,
+        addr: &CheetahString,
+    ) -> Result<TopicConfigAndMappingSerializeWrapper> {
+        let content = self
+            .get_all_config(addr, RequestCode::GetAllTopicConfig)
+            .await?;
+        Ok(SerdeJsonUtils::from_json_str::<
+            TopicConfigAndMappingSerializeWrapper,
+        >(content.as_str())?)
+    }
+
+    /// Fetches the consumer offsets of the master at `addr`, in the layout of the offset file.
+    pub async fn get_all_consumer_offset(&self, addr: &CheetahString) -> Result<String> {
+        self.get_all_config(addr, RequestCode::GetAllConsumerOffset)
+            .await
+    }
+
+    /// Fetches the schedule progress of the delay levels of the master at `addr`, in the layout
+    /// of the delay offset file.
+    pub async fn get_all_delay_offset(&self, addr: &CheetahString) -> Result<String> {
+        self.get_all_config(addr, RequestCode::GetAllDelayOffset)
+            .await
+    }
+
+    /// Fetches the subscription groups of the master at `addr`.
+    pub async fn get_all_subscription_group_config(
+        &self,
+        addr: &CheetahString,
+    ) -> Result<SubscriptionGroupWrapper> {
+        let content = self
+            .get_all_config(addr, RequestCode::GetAllSubscriptionGroupConfig)
+            .await?;
+        Ok(SerdeJsonUtils::from_json_str::<SubscriptionGroupWrapper>(
+            content.as_str(),
+        )?)
+    }
+
+    /// Fetches the message request modes of the master at `addr`, in the layout of the message
+    /// request mode file.
+    pub async fn get_all_message_request_mode(&self, addr: &CheetahString) -> Result<String> {
+        self.get_all_config(addr, RequestCode::GetAllMessageRequestMode)
+            .await
+    }
+
+    /// Fetches the addresses of the brokers sharing `broker_name` from the first name server
+    /// that answers.
+    pub async fn sync_broker_member_group(
+        &self,
+        cluster_name: &CheetahString,
+        broker_name: &CheetahString,
+    ) -> Result<Option<BrokerMemberGroup>> {
+        let header =
+            GetBrokerMemberGroupRequestHeader::new(cluster_name.clone(), broker_name.clone());
+        let mut last_error = None;
+        for namesrv_addr in self.remoting_client.get_available_name_srv_list() {
+            let request = RemotingCommand::create_request_command(
+                RequestCode::GetBrokerMemberGroup,
+                header.clone(),
+            );
+            match self
+                .remoting_client
+                .invoke_async(Some(&namesrv_addr), request, 3000)
+                .await
+            {
+                Ok(response) if ResponseCode::from(response.code()) == ResponseCode::Success => {
+                    let Some(body) = response.body() else {
+                        return Ok(None);
+                    };
+                    return Ok(GetBrokerMemberGroupResponseBody::decode(body)?.broker_member_group);
+                }
+                Ok(response) => {
+                    last_error = Some(BrokerError::MQBrokerError(
+                        response.code(),
+                        response
+                            .remark()
+                            .cloned()
+                            .unwrap_or(CheetahString::empty())
+                            .to_string(),
+                        namesrv_addr.to_string(),
+                    ));
+                }
+                Err(e) => last_error = Some(BrokerRemotingError(e)),
+            }
+        }
+        match last_error {
+            Some(e) => Err(e),
+            None => Ok(None),
+        }
+    }
+
+    /// Fetches the encoded timer checkpoint of the broker at `addr`.
+    pub async fn get_timer_check_point(&self, addr: &CheetahString) -> Result<bytes::Bytes> {
+        let request = RemotingCommand::create_remoting_command(RequestCode::GetTimerCheckPoint);
+        let response = self
+            .remoting_client
+            .invoke_async(Some(addr), request, 3000)
+            .await?;
+        if ResponseCode::from(response.code()) != ResponseCode::Success {
+            return Err(BrokerError::MQBrokerError(
+                response.code(),
+                response
+                    .remark()
+                    .cloned()
+                    .unwrap_or(CheetahString::empty())
+                    .to_string(),
+                addr.to_string(),
+            ));
+        }
+        Ok(response.body().clone().unwrap_or_default())
+    }
+
+    /// Fetches the replication state of the broker at `addr`.
+    pub async fn get_broker_ha_status(&self, addr: &CheetahString) -> Result<HARuntimeInfo> {
+        let content = self
+            .get_all_config(addr, RequestCode::GetBrokerHaStatus)
+            .await?;
+        Ok(SerdeJsonUtils::from_json_str::<HARuntimeInfo>(
+            content.as_str(),
+        )?)
+    }
+
+    /// Fetches the HA address and flushed commit log offset of the broker at `addr`.
+    pub async fn retrieve_broker_ha_info(
+        &self,
+        addr: &CheetahString,
+    ) -> Result<ExchangeHAInfoResponseHeader> {
+        let request = RemotingCommand::create_request_command(
+            RequestCode::ExchangeBrokerHaInfo,
+            ExchangeHAInfoRequestHeader::default(),
+        );
+        let response = self
+            .remoting_client
+            .invoke_async(Some(addr), request, 3000)
+            .await?;
+        if ResponseCode::from(response.code()) != ResponseCode::Success {
+            return Err(BrokerError::MQBrokerError(
+                response.code(),
+                response
+                    .remark()
+                    .cloned()
+                    .unwrap_or(CheetahString::empty())
+                    .to_string(),
+                addr.to_string(),
+            ));
+        }
+        response
+            .decode_command_custom_header::<ExchangeHAInfoResponseHeader>()
+            .map_err(BrokerRemotingError)
+    }
+
+    async fn get_all_config(
+        &self,
+        addr: &CheetahString,
+        request_code: RequestCode,
+    ) -> Result<String> {
+        let request = RemotingCommand::create_remoting_command(request_code);
+        let response = self
+            .remoting_client
+            .invoke_async(Some(addr), request, 3000)
+            .await?;
+        if ResponseCode::from(response.code()) != ResponseCode::Success {
+            return Err(BrokerError::MQBrokerError(
+                response.code(),
+                response
+                    .remark()
+                    .cloned()
+                    .unwrap_or(CheetahString::empty())
+                    .to_string(),
+                addr.to_string(),
+            ));
+        }
+        Ok(response
+            .body()
+            .as_ref()
+            .map(|body| String::from_utf8_lossy(body).into_owned())
+            .unwrap_or_default())
+    }
+
     pub async fn get_topic_route_info_from_name_server(
         &self,
         topic: &CheetahString,
@@ -560,17 +830,18 @@ impl BrokerOuterAPI {
         broker_id: u64,
     ) {
         let name_server_address_list = self.remoting_client.get_name_server_address_list();
-        for namesrv_addr in name_server_address_list.iter() {
-            match self
-                .unregister_broker(
-                    namesrv_addr,
-                    cluster_name,
-                    broker_addr,
-                    broker_name,
-                    broker_id,
-                )
-                .await
-            {
+        let results = join_all(name_server_address_list.iter().map(|namesrv_addr| {
+            self.unregister_broker(
+                namesrv_addr,
+                cluster_name,
+                broker_addr,
+                broker_name,
+                broker_id,
+            )
+        }))
+        .await;
+        for (namesrv_addr, result) in name_server_address_list.iter().zip(results) {
+            match result {
                 Ok(_) => {
                     info!(
                         "Unregister broker from name remoting_server success, namesrv_addr={}",
@@ -587,6 +858,7 @@ impl BrokerOuterAPI {
             }
         }
     }
+
     pub async fn unregister_broker(
         &self,
         namesrv_addr: &CheetahString,
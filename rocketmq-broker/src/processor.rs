@@ -14,7 +14,11 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::time::Instant;
+
+use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_remoting::code::request_code::RequestCode;
+use rocketmq_remoting::code::response_code::ResponseCode;
 use rocketmq_remoting::net::channel::Channel;
 use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
 use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
@@ -23,8 +27,11 @@ use rocketmq_remoting::Result;
 use rocketmq_rust::ArcMut;
 use rocketmq_store::log_file::MessageStore;
 use tracing::info;
+use tracing::warn;
 
 use self::client_manage_processor::ClientManageProcessor;
+use crate::broker_runtime::BrokerRuntimeInner;
+use crate::latency::slow_request_log::SlowRequest;
 use crate::processor::ack_message_processor::AckMessageProcessor;
 use crate::processor::admin_broker_processor::AdminBrokerProcessor;
 use crate::processor::change_invisible_time_processor::ChangeInvisibleTimeProcessor;
@@ -59,6 +66,7 @@ pub(crate) mod pull_message_result_handler;
 pub(crate) mod query_assignment_processor;
 pub(crate) mod query_message_processor;
 pub(crate) mod reply_message_processor;
+pub(crate) mod request_executor;
 pub(crate) mod send_message_processor;
 
 pub struct BrokerRequestProcessor<MS, TS> {
@@ -68,7 +76,7 @@ pub struct BrokerRequestProcessor<MS, TS> {
     pub(crate) pop_message_processor: ArcMut<PopMessageProcessor<MS>>,
     pub(crate) ack_message_processor: ArcMut<AckMessageProcessor<MS>>,
     pub(crate) change_invisible_time_processor: ArcMut<ChangeInvisibleTimeProcessor<MS>>,
-    pub(crate) notification_processor: ArcMut<NotificationProcessor>,
+    pub(crate) notification_processor: ArcMut<NotificationProcessor<MS>>,
     pub(crate) polling_info_processor: ArcMut<PollingInfoProcessor>,
     pub(crate) reply_message_processor: ArcMut<ReplyMessageProcessor<MS, TS>>,
     pub(crate) query_message_processor: ArcMut<QueryMessageProcessor<MS>>,
@@ -77,6 +85,7 @@ pub struct BrokerRequestProcessor<MS, TS> {
     pub(crate) query_assignment_processor: ArcMut<QueryAssignmentProcessor<MS>>,
     pub(crate) end_transaction_processor: ArcMut<EndTransactionProcessor<TS, MS>>,
     pub(crate) admin_broker_processor: ArcMut<AdminBrokerProcessor<MS>>,
+    pub(crate) broker_runtime_inner: ArcMut<BrokerRuntimeInner<MS>>,
 }
 impl<MS, TS> Clone for BrokerRequestProcessor<MS, TS> {
     fn clone(&self) -> Self {
@@ -96,6 +105,7 @@ impl<MS, TS> Clone for BrokerRequestProcessor<MS, TS> {
             query_assignment_processor: self.query_assignment_processor.clone(),
             query_message_processor: self.query_message_processor.clone(),
             end_transaction_processor: self.end_transaction_processor.clone(),
+            broker_runtime_inner: self.broker_runtime_inner.clone(),
         }
     }
 }
@@ -111,8 +121,130 @@ where
         ctx: ConnectionHandlerContext,
         request: RemotingCommand,
     ) -> Result<Option<RemotingCommand>> {
+        let begin = Instant::now();
+        let begin_timestamp = get_current_millis();
         let request_code = RequestCode::from(request.code());
         info!("process_request: {:?}", request_code);
+        let broker_runtime_inner = self.broker_runtime_inner.clone();
+        let in_flight = match broker_runtime_inner
+            .overload_shedder()
+            .try_acquire(&broker_runtime_inner.broker_config(), request_code)
+        {
+            Ok(permit) => permit,
+            Err(priority) => {
+                warn!(
+                    "broker overloaded, shed {:?} request {:?}, in flight: {}",
+                    priority,
+                    request_code,
+                    broker_runtime_inner.overload_shedder().in_flight()
+                );
+                return Ok(Some(RemotingCommand::create_error_response(
+                    ResponseCode::SystemBusy,
+                    format!(
+                        "broker overloaded, {:?} requests are rejected for a while",
+                        priority
+                    ),
+                )));
+            }
+        };
+        let slow_request = broker_runtime_inner
+            .broker_config()
+            .slow_request_log_enable
+            .then(|| SlowRequest::new(&request, channel.remote_address(), begin_timestamp));
+        let opaque = request.opaque();
+        let oneway_rpc = request.is_oneway_rpc();
+        let mut processor = self.clone();
+        // The request runs on the pool of its kind and hands its response back to the connection,
+        // which runs the rpc hooks and writes the responses one after the other. The connection
+        // goes on reading the next request meanwhile.
+        let executed = broker_runtime_inner.request_executors().execute(
+            &broker_runtime_inner.broker_config(),
+            request_code,
+            move |admitted| async move {
+                let _in_flight = in_flight;
+                let response = match admitted {
+                    Ok(()) => {
+                        processor
+                            .process_admitted_request(
+                                channel,
+                                ctx.clone(),
+                                request_code,
+                                request,
+                                begin,
+                                slow_request,
+                            )
+                            .await
+                    }
+                    Err(remark) => Some(RemotingCommand::create_response_command_with_code_remark(
+                        ResponseCode::SystemBusy,
+                        remark,
+                    )),
+                };
+                if let Some(response) = response {
+                    if !oneway_rpc {
+                        let mut ctx = ctx;
+                        ctx.write(response.set_opaque(opaque)).await;
+                    }
+                }
+            },
+        );
+        match executed {
+            Ok(()) => Ok(None),
+            Err(remark) => Ok(Some(
+                RemotingCommand::create_response_command_with_code_remark(
+                    ResponseCode::SystemBusy,
+                    remark,
+                ),
+            )),
+        }
+    }
+}
+
+impl<MS, TS> BrokerRequestProcessor<MS, TS>
+where
+    MS: MessageStore + Send + Sync + 'static,
+    TS: TransactionalMessageService,
+{
+    async fn process_admitted_request(
+        &mut self,
+        channel: Channel,
+        ctx: ConnectionHandlerContext,
+        request_code: RequestCode,
+        request: RemotingCommand,
+        begin: Instant,
+        slow_request: Option<SlowRequest>,
+    ) -> Option<RemotingCommand> {
+        let wait_millis = begin.elapsed().as_millis() as u64;
+        let result = self
+            .dispatch_request(channel, ctx, request_code, request)
+            .await;
+        if let Some(mut slow_request) = slow_request {
+            slow_request.wait_millis = wait_millis;
+            slow_request.process_millis =
+                (begin.elapsed().as_millis() as u64).saturating_sub(wait_millis);
+            if let Ok(Some(response)) = result.as_ref() {
+                slow_request.response_code = Some(response.code());
+                slow_request.response_body_size = response.get_body().map_or(0, |body| body.len());
+            }
+            let broker_runtime_inner = &self.broker_runtime_inner;
+            broker_runtime_inner
+                .slow_request_log()
+                .record(&broker_runtime_inner.broker_config(), slow_request);
+        }
+        result.unwrap_or_else(|_| {
+            Some(RemotingCommand::create_response_command_with_code(
+                ResponseCode::SystemError,
+            ))
+        })
+    }
+
+    async fn dispatch_request(
+        &mut self,
+        channel: Channel,
+        ctx: ConnectionHandlerContext,
+        request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Result<Option<RemotingCommand>> {
         let result = match request_code {
             RequestCode::SendMessage
             | RequestCode::SendMessageV2
@@ -198,6 +330,13 @@ where
                     .process_request(channel, ctx, request)
                     .await;
             }
+
+            RequestCode::Notification => {
+                return self
+                    .notification_processor
+                    .process_request(channel, ctx, request)
+                    .await;
+            }
             _ => {
                 self.admin_broker_processor
                     .process_request(channel, ctx, request_code, request)
@@ -27,6 +27,7 @@ use rocketmq_common::common::message::MessageConst;
 use rocketmq_common::common::message::MessageTrait;
 use rocketmq_common::common::pop_ack_constants::PopAckConstants;
 use rocketmq_common::common::FAQUrl;
+use rocketmq_common::utils::message_utils;
 use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_remoting::code::request_code::RequestCode;
 use rocketmq_remoting::code::response_code::ResponseCode;
@@ -51,6 +52,8 @@ use tracing::warn;
 use crate::broker_error::BrokerError::BrokerCommonError;
 use crate::broker_error::BrokerError::BrokerRemotingError;
 use crate::broker_runtime::BrokerRuntimeInner;
+use crate::mqtrace::broker_trace_dispatcher::BrokerTraceRecord;
+use crate::mqtrace::broker_trace_dispatcher::BrokerTraceType;
 use crate::processor::pop_message_processor::PopMessageProcessor;
 use crate::processor::processor_service::pop_revive_service::PopReviveService;
 
@@ -196,11 +199,64 @@ where
             ));
         }
         let mut response = RemotingCommand::create_response_command();
+        let trace_record = if self
+            .broker_runtime_inner
+            .broker_config()
+            .broker_trace_enable
+        {
+            Some(self.build_ack_trace_record(&request_header, &channel))
+        } else {
+            None
+        };
         self.append_ack(Some(request_header), &mut response, None, &channel, None)
             .await;
+        if let Some(mut trace_record) = trace_record {
+            trace_record.success = response.code() == ResponseCode::Success as i32;
+            self.broker_runtime_inner
+                .broker_trace_dispatcher()
+                .dispatch(trace_record);
+        }
         Ok(Some(response))
     }
 
+    /// Builds the `SubAfter` trace of an acked message, identified by its unique key when the
+    /// message can still be read, by its offset message id otherwise.
+    fn build_ack_trace_record(
+        &self,
+        request_header: &AckMessageRequestHeader,
+        channel: &Channel,
+    ) -> BrokerTraceRecord {
+        let mut record = BrokerTraceRecord::new(BrokerTraceType::SubAfter);
+        record
+            .region_id
+            .clone_from(&self.broker_runtime_inner.broker_config().region_id);
+        record.group.clone_from(&request_header.consumer_group);
+        record.topic.clone_from(&request_header.topic);
+        record.request_id = CheetahString::from_string(channel.remote_address().to_string());
+        record
+            .store_host
+            .clone_from(self.broker_runtime_inner.get_broker_addr());
+        let message_store = self.broker_runtime_inner.message_store().as_ref().unwrap();
+        let Some(cq_unit) = message_store
+            .find_consume_queue(&request_header.topic, request_header.queue_id)
+            .and_then(|consume_queue| consume_queue.get(request_header.offset))
+        else {
+            return record;
+        };
+        record.msg_id = match message_store.look_message_by_offset(cq_unit.pos) {
+            Some(msg) => msg
+                .get_property(&CheetahString::from_static_str(
+                    MessageConst::PROPERTY_UNIQ_CLIENT_MESSAGE_ID_KEYIDX,
+                ))
+                .unwrap_or_else(|| msg.msg_id().clone()),
+            None => CheetahString::from_string(message_utils::build_message_id(
+                self.broker_runtime_inner.store_host(),
+                cq_unit.pos,
+            )),
+        };
+        record
+    }
+
     async fn process_batch_ack(
         &mut self,
         _channel: Channel,
@@ -437,7 +493,7 @@ where
             message_decoder::message_properties_to_string(inner.get_properties());
         let put_message_result = self
             .broker_runtime_inner
-            .escape_bridge_mut()
+            .escape_bridge()
             .put_message_to_specific_queue(inner)
             .await;
         match put_message_result.put_message_status() {
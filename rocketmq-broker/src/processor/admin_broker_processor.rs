@@ -18,10 +18,14 @@
 use rocketmq_remoting::code::request_code::RequestCode;
 use rocketmq_remoting::code::response_code::ResponseCode;
 use rocketmq_remoting::net::channel::Channel;
+use rocketmq_remoting::protocol::body::sync_state_set::SyncStateSet;
+use rocketmq_remoting::protocol::header::notify_broker_role_changed_request_header::NotifyBrokerRoleChangedRequestHeader;
 use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+use rocketmq_remoting::protocol::RemotingDeserializable;
 use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
 use rocketmq_rust::ArcMut;
 use rocketmq_store::log_file::MessageStore;
+use tracing::info;
 use tracing::warn;
 
 use crate::broker_runtime::BrokerRuntimeInner;
@@ -29,12 +33,16 @@ use crate::processor::admin_broker_processor::batch_mq_handler::BatchMqHandler;
 use crate::processor::admin_broker_processor::broker_config_request_handler::BrokerConfigRequestHandler;
 use crate::processor::admin_broker_processor::consumer_request_handler::ConsumerRequestHandler;
 use crate::processor::admin_broker_processor::offset_request_handler::OffsetRequestHandler;
+use crate::processor::admin_broker_processor::producer_request_handler::ProducerRequestHandler;
+use crate::processor::admin_broker_processor::subscription_group_handler::SubscriptionGroupHandler;
 use crate::processor::admin_broker_processor::topic_request_handler::TopicRequestHandler;
 
 mod batch_mq_handler;
 mod broker_config_request_handler;
 mod consumer_request_handler;
 mod offset_request_handler;
+mod producer_request_handler;
+mod subscription_group_handler;
 mod topic_request_handler;
 
 pub struct AdminBrokerProcessor<MS> {
@@ -42,7 +50,9 @@ pub struct AdminBrokerProcessor<MS> {
     broker_config_request_handler: BrokerConfigRequestHandler<MS>,
     consumer_request_handler: ConsumerRequestHandler<MS>,
     offset_request_handler: OffsetRequestHandler<MS>,
+    producer_request_handler: ProducerRequestHandler<MS>,
     batch_mq_handler: BatchMqHandler<MS>,
+    subscription_group_handler: SubscriptionGroupHandler<MS>,
     broker_runtime_inner: ArcMut<BrokerRuntimeInner<MS>>,
 }
 
@@ -70,13 +80,18 @@ impl<MS: MessageStore> AdminBrokerProcessor<MS> {
             BrokerConfigRequestHandler::new(broker_runtime_inner.clone());
         let consumer_request_handler = ConsumerRequestHandler::new(broker_runtime_inner.clone());
         let offset_request_handler = OffsetRequestHandler::new(broker_runtime_inner.clone());
+        let producer_request_handler = ProducerRequestHandler::new(broker_runtime_inner.clone());
         let batch_mq_handler = BatchMqHandler::new(broker_runtime_inner.clone());
+        let subscription_group_handler =
+            SubscriptionGroupHandler::new(broker_runtime_inner.clone());
         AdminBrokerProcessor {
             topic_request_handler,
             broker_config_request_handler,
             consumer_request_handler,
             offset_request_handler,
+            producer_request_handler,
             batch_mq_handler,
+            subscription_group_handler,
             broker_runtime_inner,
         }
     }
@@ -101,6 +116,11 @@ impl<MS: MessageStore> AdminBrokerProcessor<MS> {
                     .update_and_create_topic_list(channel, ctx, request_code, request)
                     .await
             }
+            RequestCode::UpdateAndCreateStaticTopic => {
+                self.topic_request_handler
+                    .update_and_create_static_topic(channel, ctx, request_code, request)
+                    .await
+            }
             RequestCode::DeleteTopicInBroker => {
                 self.topic_request_handler
                     .delete_topic(channel, ctx, request_code, request)
@@ -136,6 +156,16 @@ impl<MS: MessageStore> AdminBrokerProcessor<MS> {
                     .get_consumer_connection_list(channel, ctx, request_code, request)
                     .await
             }
+            RequestCode::GetProducerConnectionList => {
+                self.producer_request_handler
+                    .get_producer_connection_list(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::GetAllProducerInfo => {
+                self.producer_request_handler
+                    .get_all_producer_info(channel, ctx, request_code, request)
+                    .await
+            }
             RequestCode::GetConsumeStats => {
                 self.consumer_request_handler
                     .get_consume_stats(channel, ctx, request_code, request)
@@ -146,6 +176,36 @@ impl<MS: MessageStore> AdminBrokerProcessor<MS> {
                     .get_all_consumer_offset(channel, ctx, request_code, request)
                     .await
             }
+            RequestCode::GetAllDelayOffset => {
+                self.consumer_request_handler
+                    .get_all_delay_offset(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::GetAllMessageRequestMode => {
+                self.consumer_request_handler
+                    .get_all_message_request_mode(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::UpdateAndCreateSubscriptionGroup => {
+                self.subscription_group_handler
+                    .update_and_create_subscription_group(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::GetAllSubscriptionGroupConfig => {
+                self.subscription_group_handler
+                    .get_all_subscription_group_config(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::QueryConsumeTimeSpan => {
+                self.consumer_request_handler
+                    .query_consume_time_span(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::QueryConsumeQueue => {
+                self.consumer_request_handler
+                    .query_consume_queue(channel, ctx, request_code, request)
+                    .await
+            }
             RequestCode::GetTopicConfig => {
                 self.topic_request_handler
                     .get_topic_config(channel, ctx, request_code, request)
@@ -156,6 +216,26 @@ impl<MS: MessageStore> AdminBrokerProcessor<MS> {
                     .get_broker_runtime_info(channel, ctx, request_code, request)
                     .await
             }
+            RequestCode::GetTimerCheckPoint => {
+                self.broker_config_request_handler
+                    .get_timer_check_point(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::GetBrokerHaStatus => {
+                self.broker_config_request_handler
+                    .get_broker_ha_status(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::ExchangeBrokerHaInfo => {
+                self.broker_config_request_handler
+                    .exchange_ha_info(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::ViewBrokerStatsData => {
+                self.broker_config_request_handler
+                    .view_broker_stats_data(channel, ctx, request_code, request)
+                    .await
+            }
             RequestCode::QueryTopicConsumeByWho => {
                 self.topic_request_handler
                     .query_topic_consume_by_who(channel, ctx, request_code, request)
@@ -176,10 +256,31 @@ impl<MS: MessageStore> AdminBrokerProcessor<MS> {
                     .get_min_offset(channel, ctx, request_code, request)
                     .await
             }
+            RequestCode::SearchOffsetByTimestamp => {
+                self.offset_request_handler
+                    .search_offset_by_timestamp(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::CloneGroupOffset => {
+                self.offset_request_handler
+                    .clone_group_offset(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::QueryCorrectionOffset => {
+                self.offset_request_handler
+                    .query_correction_offset(channel, ctx, request_code, request)
+                    .await
+            }
+
+            RequestCode::InvokeBrokerToResetOffset => {
+                self.offset_request_handler
+                    .reset_offset(channel, ctx, request_code, request)
+                    .await
+            }
 
             RequestCode::LockBatchMq => {
                 self.batch_mq_handler
-                    .lock_natch_mq(channel, ctx, request_code, request)
+                    .lock_batch_mq(channel, ctx, request_code, request)
                     .await
             }
 
@@ -188,11 +289,78 @@ impl<MS: MessageStore> AdminBrokerProcessor<MS> {
                     .unlock_batch_mq(channel, ctx, request_code, request)
                     .await
             }
+            RequestCode::UpdateAndGetGroupForbidden => {
+                self.subscription_group_handler
+                    .update_and_get_group_forbidden(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::DeleteSubscriptionGroup => {
+                self.subscription_group_handler
+                    .delete_subscription_group(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::NotifyBrokerRoleChanged => self.notify_broker_role_changed(request).await,
             _ => Some(get_unknown_cmd_response(request_code)),
         }
     }
 }
 
+impl<MS: MessageStore> AdminBrokerProcessor<MS> {
+    /// Takes the role the controller elected, the body carries the sync state set of the group.
+    async fn notify_broker_role_changed(
+        &mut self,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let request_header =
+            match request.decode_command_custom_header::<NotifyBrokerRoleChangedRequestHeader>() {
+                Ok(request_header) => request_header,
+                Err(e) => {
+                    return Some(RemotingCommand::create_response_command_with_code_remark(
+                        ResponseCode::SystemError,
+                        e.to_string(),
+                    ))
+                }
+            };
+        let sync_state_set = request
+            .body()
+            .as_ref()
+            .and_then(|body| SyncStateSet::decode(body).ok())
+            .unwrap_or_default();
+        info!(
+            "receive notify broker role changed, new master broker id {}, epoch {}, sync state \
+             set {:?}",
+            request_header.master_broker_id,
+            request_header.master_epoch,
+            sync_state_set.sync_state_set
+        );
+        let Some(replicas_manager) = self.broker_runtime_inner.replicas_manager() else {
+            return Some(RemotingCommand::create_response_command_with_code_remark(
+                ResponseCode::SystemError,
+                "the broker is not in controller mode",
+            ));
+        };
+        if !replicas_manager
+            .change_broker_role(
+                request_header.master_broker_id,
+                request_header.master_address,
+                request_header.master_epoch,
+                request_header.sync_state_set_epoch,
+                sync_state_set.sync_state_set,
+            )
+            .await
+        {
+            return Some(RemotingCommand::create_response_command_with_code_remark(
+                ResponseCode::SystemError,
+                format!(
+                    "change the broker role to that of epoch {} failed",
+                    request_header.master_epoch
+                ),
+            ));
+        }
+        Some(RemotingCommand::create_response_command())
+    }
+}
+
 fn get_unknown_cmd_response(request_code: RequestCode) -> RemotingCommand {
     warn!(
         "request type {:?}-{} not supported",
@@ -21,6 +21,7 @@ use std::time::Duration;
 
 use bytes::Bytes;
 use rocketmq_remoting::code::request_code::RequestCode;
+use rocketmq_remoting::code::response_code::ResponseCode;
 use rocketmq_remoting::net::channel::Channel;
 use rocketmq_remoting::protocol::body::request::lock_batch_request_body::LockBatchRequestBody;
 use rocketmq_remoting::protocol::body::response::lock_batch_response_body::LockBatchResponseBody;
@@ -49,23 +50,30 @@ impl<MS: MessageStore> BatchMqHandler<MS> {
         }
     }
 
-    pub async fn lock_natch_mq(
+    pub async fn lock_batch_mq(
         &mut self,
         _channel: Channel,
         _ctx: ConnectionHandlerContext,
         _request_code: RequestCode,
         request: RemotingCommand,
     ) -> Option<RemotingCommand> {
-        let mut request_body = LockBatchRequestBody::decode(request.get_body().unwrap()).unwrap();
+        let Some(mut request_body) = request
+            .get_body()
+            .and_then(|body| LockBatchRequestBody::decode(body).ok())
+        else {
+            return Some(invalid_body_response("lockBatchMQ"));
+        };
+        let (Some(consumer_group), Some(client_id)) = (
+            request_body.consumer_group.as_ref(),
+            request_body.client_id.as_ref(),
+        ) else {
+            return Some(missing_identity_response("lockBatchMQ"));
+        };
         let mut lock_ok_mqset = HashSet::new();
         let self_lock_okmqset = self
             .broker_runtime_inner
             .rebalance_lock_manager()
-            .try_lock_batch(
-                request_body.consumer_group.as_ref().unwrap(),
-                &request_body.mq_set,
-                request_body.client_id.as_ref().unwrap(),
-            );
+            .try_lock_batch(consumer_group, &request_body.mq_set, client_id);
         if request_body.only_this_broker
             || !self
                 .broker_runtime_inner
@@ -156,7 +164,18 @@ impl<MS: MessageStore> BatchMqHandler<MS> {
         _request_code: RequestCode,
         request: RemotingCommand,
     ) -> Option<RemotingCommand> {
-        let mut request_body = UnlockBatchRequestBody::decode(request.get_body().unwrap()).unwrap();
+        let Some(mut request_body) = request
+            .get_body()
+            .and_then(|body| UnlockBatchRequestBody::decode(body).ok())
+        else {
+            return Some(invalid_body_response("unlockBatchMQ"));
+        };
+        let (Some(consumer_group), Some(client_id)) = (
+            request_body.consumer_group.as_ref(),
+            request_body.client_id.as_ref(),
+        ) else {
+            return Some(missing_identity_response("unlockBatchMQ"));
+        };
         if request_body.only_this_broker
             || !self
                 .broker_runtime_inner
@@ -165,11 +184,7 @@ impl<MS: MessageStore> BatchMqHandler<MS> {
         {
             self.broker_runtime_inner
                 .rebalance_lock_manager()
-                .unlock_batch(
-                    request_body.consumer_group.as_ref().unwrap(),
-                    &request_body.mq_set,
-                    request_body.client_id.as_ref().unwrap(),
-                );
+                .unlock_batch(consumer_group, &request_body.mq_set, client_id);
         } else {
             request_body.only_this_broker = true;
             let request_body =
@@ -196,3 +211,20 @@ impl<MS: MessageStore> BatchMqHandler<MS> {
         Some(RemotingCommand::create_response_command())
     }
 }
+
+fn invalid_body_response(operation: &str) -> RemotingCommand {
+    RemotingCommand::create_response_command_with_code_remark(
+        ResponseCode::SystemError,
+        format!("{} request body is missing or malformed", operation),
+    )
+}
+
+fn missing_identity_response(operation: &str) -> RemotingCommand {
+    RemotingCommand::create_response_command_with_code_remark(
+        ResponseCode::SystemError,
+        format!(
+            "{} request requires both consumerGroup and clientId",
+            operation
+        ),
+    )
+}
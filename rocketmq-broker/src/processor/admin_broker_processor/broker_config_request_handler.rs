@@ -18,18 +18,39 @@
 use std::collections::HashMap;
 
 use cheetah_string::CheetahString;
+use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::common::broker::broker_role::BrokerRole;
 use rocketmq_common::common::mix_all;
 use rocketmq_common::common::mq_version::RocketMqVersion;
+use rocketmq_common::common::stats::stats_snapshot::StatsSnapshot;
+use rocketmq_common::utils::file_utils;
 use rocketmq_remoting::code::request_code::RequestCode;
+use rocketmq_remoting::code::response_code::ResponseCode;
 use rocketmq_remoting::net::channel::Channel;
+use rocketmq_remoting::protocol::body::broker_item::BrokerStatsItem;
+use rocketmq_remoting::protocol::body::ha_runtime_info::HARuntimeInfo;
 use rocketmq_remoting::protocol::body::kv_table::KVTable;
+use rocketmq_remoting::protocol::header::exchange_ha_info_request_header::ExchangeHAInfoRequestHeader;
+use rocketmq_remoting::protocol::header::exchange_ha_info_response_header::ExchangeHAInfoResponseHeader;
+use rocketmq_remoting::protocol::header::view_broker_stats_data_request_header::ViewBrokerStatsDataRequestHeader;
 use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+use rocketmq_remoting::protocol::subscription::broker_stats_data::BrokerStatsData;
+use rocketmq_remoting::protocol::RemotingSerializable;
 use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
 use rocketmq_rust::ArcMut;
 use rocketmq_store::log_file::MessageStore;
 use sysinfo::Disks;
+use tracing::error;
+use tracing::info;
 
+use crate::broker_path_config_helper;
 use crate::broker_runtime::BrokerRuntimeInner;
+use crate::processor::request_executor::RequestQueueKind;
+use crate::schedule::schedule_message_service::ScheduleMessageService;
+use crate::util::process_metrics;
+
+/// The one message store setting that is applied at runtime, by the schedule message service.
+const MESSAGE_DELAY_LEVEL: &str = "messageDelayLevel";
 
 #[derive(Clone)]
 pub(super) struct BrokerConfigRequestHandler<MS> {
@@ -49,9 +70,156 @@ impl<MS: MessageStore> BrokerConfigRequestHandler<MS> {
         _channel: Channel,
         _ctx: ConnectionHandlerContext,
         _request_code: RequestCode,
-        _request: RemotingCommand,
+        request: RemotingCommand,
     ) -> Option<RemotingCommand> {
-        todo!()
+        let Some(body) = request.body() else {
+            return Some(
+                RemotingCommand::create_response_command().set_remark(CheetahString::empty()),
+            );
+        };
+        let body_str = match std::str::from_utf8(body) {
+            Ok(body_str) => body_str,
+            Err(e) => {
+                return Some(
+                    RemotingCommand::create_response_command_with_code(ResponseCode::SystemError)
+                        .set_remark(format!("UnsupportedEncodingException {:?}", e)),
+                );
+            }
+        };
+        let Some(properties) = mix_all::string_to_properties(body_str) else {
+            return Some(
+                RemotingCommand::create_response_command_with_code(ResponseCode::SystemError)
+                    .set_remark("string_to_properties error"),
+            );
+        };
+        Some(self.update_config_properties(properties))
+    }
+
+    /// Applies the runtime changeable `properties` to the broker config shared by all the
+    /// components and persists all of them, the others are reported as restart required.
+    fn update_config_properties(
+        &mut self,
+        properties: HashMap<CheetahString, CheetahString>,
+    ) -> RemotingCommand {
+        let black_list = self
+            .broker_runtime_inner
+            .broker_config()
+            .get_config_blacklist();
+        if properties.keys().any(|key| black_list.contains(key)) {
+            return RemotingCommand::create_response_command_with_code(ResponseCode::NoPermission)
+                .set_remark("Cannot update config in blacklist.");
+        }
+
+        // Message store settings are read when the store is created, so apart from the delay
+        // levels they are only persisted and reported as restart required.
+        let store_keys = self
+            .broker_runtime_inner
+            .message_store_config()
+            .get_properties();
+        let broker_config = self.broker_runtime_inner.broker_config();
+        let mut broker_properties = HashMap::new();
+        let mut restart_required = Vec::new();
+        let mut message_delay_level = None;
+        for (key, value) in &properties {
+            if broker_config.contains_key(key) {
+                if BrokerConfig::requires_restart(key) {
+                    restart_required.push(key.clone());
+                }
+                broker_properties.insert(key.clone(), value.clone());
+            } else if key.as_str() == MESSAGE_DELAY_LEVEL {
+                if ScheduleMessageService::parse_delay_level(value).is_none() {
+                    return RemotingCommand::create_response_command_with_code(
+                        ResponseCode::SystemError,
+                    )
+                    .set_remark(format!("Invalid {}: {}", MESSAGE_DELAY_LEVEL, value));
+                }
+                message_delay_level = Some(value.clone());
+            } else if store_keys.contains_key(key) {
+                restart_required.push(key.clone());
+            } else {
+                return RemotingCommand::create_response_command_with_code(
+                    ResponseCode::SystemError,
+                )
+                .set_remark(format!("Unknown config key: {}", key));
+            }
+        }
+
+        let mut updated = BrokerConfig::clone(&broker_config);
+        if let Err(e) = updated.update(&broker_properties) {
+            return RemotingCommand::create_response_command_with_code(ResponseCode::SystemError)
+                .set_remark(format!("Update error {}", e));
+        }
+        if let Err(e) = self.persist(&properties) {
+            error!("persist broker config failed: {}", e);
+            return RemotingCommand::create_response_command_with_code(ResponseCode::SystemError)
+                .set_remark(format!("Persist error {}", e));
+        }
+        // Keys that need a restart keep their running value, the persisted file carries them over.
+        broker_properties.retain(|key, _| !restart_required.contains(key));
+        restart_required.sort();
+        if let Err(e) = self
+            .broker_runtime_inner
+            .shared_broker_config()
+            .update(|broker_config| broker_config.update(&broker_properties))
+        {
+            return RemotingCommand::create_response_command_with_code(ResponseCode::SystemError)
+                .set_remark(format!("Update error {}", e));
+        }
+        if let Some(message_delay_level) = message_delay_level {
+            let broker_runtime_inner = self.broker_runtime_inner.clone();
+            if let Err(e) = self
+                .broker_runtime_inner
+                .schedule_message_service()
+                .update_delay_level_table(message_delay_level.as_str(), &broker_runtime_inner)
+            {
+                return RemotingCommand::create_response_command_with_code(
+                    ResponseCode::SystemError,
+                )
+                .set_remark(format!("Update error {}", e));
+            }
+            self.broker_runtime_inner
+                .message_store_config_mut()
+                .message_delay_level = message_delay_level.to_string();
+        }
+        info!(
+            "update broker config, keys: {:?}, restart required: {:?}",
+            properties.keys(),
+            restart_required
+        );
+
+        let remark = if restart_required.is_empty() {
+            CheetahString::empty()
+        } else {
+            CheetahString::from(format!(
+                "restartRequired={}",
+                restart_required
+                    .iter()
+                    .map(CheetahString::as_str)
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ))
+        };
+        RemotingCommand::create_response_command().set_remark(remark)
+    }
+
+    /// Merges `properties` into the broker config overlay file so that updates survive a
+    /// restart.
+    fn persist(&self, properties: &HashMap<CheetahString, CheetahString>) -> std::io::Result<()> {
+        let path = broker_path_config_helper::resolve_broker_config_path(
+            &self.broker_runtime_inner.broker_config().broker_config_path,
+        );
+        let mut persisted = match file_utils::file_to_string(&path) {
+            Ok(content) => mix_all::string_to_properties(&content).unwrap_or_default(),
+            Err(_) => HashMap::new(),
+        };
+        persisted.extend(properties.iter().map(|(k, v)| (k.clone(), v.clone())));
+        let mut keys = persisted.keys().collect::<Vec<_>>();
+        keys.sort();
+        let mut content = String::new();
+        for key in keys {
+            content.push_str(&format!("{}={}\n", key, persisted[key]));
+        }
+        file_utils::string_to_file(&content, &path)
     }
 
     pub async fn get_broker_config(
@@ -64,7 +232,7 @@ impl<MS: MessageStore> BrokerConfigRequestHandler<MS> {
         let mut response = RemotingCommand::create_response_command();
         // broker config => broker config
         // default message store config => message store config
-        let broker_config = self.broker_runtime_inner.broker_config().clone();
+        let broker_config = self.broker_runtime_inner.broker_config();
         let message_store_config = self
             .broker_runtime_inner
             .message_store()
@@ -80,7 +248,7 @@ impl<MS: MessageStore> BrokerConfigRequestHandler<MS> {
             .collect::<HashMap<_, _>>();
         let mut body = String::new();
         for (key, value) in combine_map {
-            body.push_str(&format!("{}:{}\n", key, value));
+            body.push_str(&format!("{}={}\n", key, value));
         }
         if !body.is_empty() {
             response.set_body_mut_ref(body);
@@ -104,6 +272,107 @@ impl<MS: MessageStore> BrokerConfigRequestHandler<MS> {
         Some(response)
     }
 
+    pub async fn get_timer_check_point(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        _request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let response = RemotingCommand::create_response_command();
+        match self
+            .broker_runtime_inner
+            .timer_message_store()
+            .as_ref()
+            .and_then(|timer_message_store| timer_message_store.timer_checkpoint())
+        {
+            Some(timer_checkpoint) => Some(response.set_body(timer_checkpoint.encode())),
+            None => Some(
+                response
+                    .set_code(ResponseCode::SystemError)
+                    .set_remark("The checkpoint is null"),
+            ),
+        }
+    }
+
+    pub async fn get_broker_ha_status(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        _request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let response = RemotingCommand::create_response_command();
+        let Some(message_store) = self.broker_runtime_inner.message_store() else {
+            return Some(
+                response
+                    .set_code(ResponseCode::SystemError)
+                    .set_remark("The message store is not ready"),
+            );
+        };
+        let ha_runtime_info = HARuntimeInfo {
+            master: self.broker_runtime_inner.message_store_config().broker_role
+                != BrokerRole::Slave,
+            master_commit_log_max_offset: message_store.get_max_phy_offset().max(0) as u64,
+            ..Default::default()
+        };
+        Some(
+            response.set_body(
+                ha_runtime_info
+                    .encode()
+                    .expect("encode HARuntimeInfo failed"),
+            ),
+        )
+    }
+
+    /// Points the replication of this broker at the HA address in the request, or answers with
+    /// the HA address and flushed offset of this broker when the request carries none.
+    pub async fn exchange_ha_info(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let response = RemotingCommand::create_response_command();
+        let request_header =
+            match request.decode_command_custom_header::<ExchangeHAInfoRequestHeader>() {
+                Ok(request_header) => request_header,
+                Err(e) => {
+                    return Some(
+                        response
+                            .set_code(ResponseCode::SystemError)
+                            .set_remark(e.to_string()),
+                    )
+                }
+            };
+        let Some(message_store) = self.broker_runtime_inner.message_store() else {
+            return Some(
+                response
+                    .set_code(ResponseCode::SystemError)
+                    .set_remark("The message store is not ready"),
+            );
+        };
+        match request_header.master_ha_address {
+            Some(master_ha_address) if !master_ha_address.is_empty() => {
+                info!(
+                    "replicate the commit log from {} on request",
+                    master_ha_address
+                );
+                message_store.update_ha_master_address(&master_ha_address);
+                Some(response)
+            }
+            _ => {
+                let response_header = ExchangeHAInfoResponseHeader {
+                    master_ha_address: Some(self.broker_runtime_inner.get_ha_server_addr()),
+                    master_flush_offset: Some(message_store.get_max_phy_offset()),
+                    master_address: Some(self.broker_runtime_inner.get_broker_addr().clone()),
+                };
+                Some(response.set_command_custom_header(response_header))
+            }
+        }
+    }
+
     fn prepare_runtime_info(&self) -> HashMap<CheetahString, CheetahString> {
         let mut runtime_info = self
             .broker_runtime_inner
@@ -113,11 +382,25 @@ impl<MS: MessageStore> BrokerConfigRequestHandler<MS> {
             .get_runtime_info();
         self.broker_runtime_inner
             .schedule_message_service()
-            .build_running_stats(&mut runtime_info);
+            .build_running_stats(
+                self.broker_runtime_inner
+                    .message_store()
+                    .as_ref()
+                    .unwrap()
+                    .as_ref(),
+                &mut runtime_info,
+            );
         runtime_info.insert(
             "brokerActive".to_string(),
             self.is_special_service_running().to_string(),
         );
+        runtime_info.insert(
+            "rejectedOffsetRollbackCount".to_string(),
+            self.broker_runtime_inner
+                .consumer_offset_manager()
+                .rejected_rollback_count()
+                .to_string(),
+        );
         let version = RocketMqVersion::CURRENT_VERSION;
         runtime_info.insert("brokerVersionDesc".to_string(), version.to_string());
         runtime_info.insert("brokerVersion".to_string(), version.to_string());
@@ -190,6 +473,29 @@ impl<MS: MessageStore> BrokerConfigRequestHandler<MS> {
                 .lock_time_mills()
                 .to_string(),
         );
+        let request_executors = self.broker_runtime_inner.request_executors();
+        for (name, kind) in [
+            ("send", RequestQueueKind::Send),
+            ("pull", RequestQueueKind::Pull),
+            ("litePull", RequestQueueKind::LitePull),
+            ("pop", RequestQueueKind::Pop),
+            ("ack", RequestQueueKind::Ack),
+            ("query", RequestQueueKind::Query),
+            ("adminBroker", RequestQueueKind::Admin),
+            ("clientManager", RequestQueueKind::ClientManage),
+            ("consumerManager", RequestQueueKind::ConsumerManage),
+            ("heartbeat", RequestQueueKind::Heartbeat),
+            ("endTransaction", RequestQueueKind::Transaction),
+        ] {
+            runtime_info.insert(
+                format!("{}ThreadPoolQueueSize", name),
+                request_executors.queue_size(kind).to_string(),
+            );
+            runtime_info.insert(
+                format!("{}ThreadPoolQueueHeadWaitTimeMills", name),
+                request_executors.head_wait_time_mills(kind).to_string(),
+            );
+        }
         runtime_info.insert(
             "earliestMessageTimeStamp".to_string(),
             self.broker_runtime_inner
@@ -277,7 +583,7 @@ impl<MS: MessageStore> BrokerConfigRequestHandler<MS> {
         );
         if default_message_store
             .get_message_store_config()
-            .transient_store_pool_enable
+            .is_transient_store_pool_enable()
         {
             runtime_info.insert(
                 "remainHowManyDataToCommit".to_string(),
@@ -294,6 +600,21 @@ impl<MS: MessageStore> BrokerConfigRequestHandler<MS> {
                 false,
             ),
         );
+        let group_lag = self
+            .broker_runtime_inner
+            .consumer_offset_manager()
+            .all_group_lag(|topic, queue_id| {
+                default_message_store.get_max_offset_in_queue(topic, queue_id)
+            });
+        runtime_info.insert(
+            "consumerLagTotal".to_string(),
+            group_lag.values().sum::<i64>().to_string(),
+        );
+        runtime_info.insert(
+            "consumerLagMax".to_string(),
+            group_lag.values().max().copied().unwrap_or(0).to_string(),
+        );
+        process_metrics::build_process_metrics(&mut runtime_info);
         let store_path_root_dir = &self
             .broker_runtime_inner
             .message_store_config()
@@ -323,7 +644,120 @@ impl<MS: MessageStore> BrokerConfigRequestHandler<MS> {
             .map(|(k, v)| (CheetahString::from_string(k), CheetahString::from_string(v)))
             .collect()
     }
+    pub async fn view_broker_stats_data(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let request_header = match request
+            .decode_command_custom_header::<ViewBrokerStatsDataRequestHeader>()
+        {
+            Ok(header) => header,
+            Err(e) => {
+                return Some(
+                    RemotingCommand::create_response_command_with_code(ResponseCode::SystemError)
+                        .set_remark(format!("decode request header failed: {}", e)),
+                )
+            }
+        };
+        let Some(stats_item) = self
+            .broker_runtime_inner
+            .broker_stats_manager()
+            .get_stats_item(
+                request_header.stats_name.as_str(),
+                request_header.stats_key.as_str(),
+            )
+        else {
+            return Some(
+                RemotingCommand::create_response_command_with_code(ResponseCode::SystemError)
+                    .set_remark(format!(
+                        "The stats <{}> <{}> not exist",
+                        request_header.stats_name, request_header.stats_key
+                    )),
+            );
+        };
+        let to_broker_stats_item =
+            |ss: StatsSnapshot| BrokerStatsItem::new(ss.get_sum(), ss.get_tps(), ss.get_avgpt());
+        let broker_stats_data = BrokerStatsData::new(
+            to_broker_stats_item(stats_item.get_stats_data_in_minute()),
+            to_broker_stats_item(stats_item.get_stats_data_in_hour()),
+            to_broker_stats_item(stats_item.get_stats_data_in_day()),
+        );
+        Some(
+            RemotingCommand::create_response_command().set_body(
+                broker_stats_data
+                    .encode()
+                    .expect("encode BrokerStatsData failed"),
+            ),
+        )
+    }
+
     fn is_special_service_running(&self) -> bool {
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rocketmq_common::common::server::config::ServerConfig;
+    use rocketmq_store::config::message_store_config::MessageStoreConfig;
+
+    use super::*;
+    use crate::broker_runtime::BrokerRuntime;
+
+    #[test]
+    fn update_is_seen_by_the_components_of_the_broker() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let _guard = runtime.enter();
+        let store_dir = tempfile::tempdir().unwrap();
+        let config_path = store_dir.path().join("broker.properties");
+        let broker = BrokerRuntime::new(
+            BrokerConfig {
+                broker_config_path: config_path.to_string_lossy().as_ref().into(),
+                store_path_root_dir: store_dir.path().to_string_lossy().as_ref().into(),
+                ..Default::default()
+            },
+            MessageStoreConfig::default(),
+            ServerConfig::default(),
+        );
+        let inner = broker.inner().clone();
+        let listen_port = inner.broker_config().listen_port;
+        let mut handler = BrokerConfigRequestHandler::new(inner.clone());
+
+        let response = handler.update_config_properties(HashMap::from([
+            ("flushConsumerOffsetInterval".into(), "1000".into()),
+            ("listenPort".into(), "20911".into()),
+        ]));
+        assert_eq!(ResponseCode::from(response.code()), ResponseCode::Success);
+        assert_eq!(
+            response.remark(),
+            Some(&CheetahString::from("restartRequired=listenPort"))
+        );
+        assert_eq!(inner.broker_config().flush_consumer_offset_interval, 1000);
+        assert_eq!(
+            inner
+                .consumer_offset_manager()
+                .broker_config
+                .get()
+                .flush_consumer_offset_interval,
+            1000
+        );
+        assert_eq!(inner.broker_config().listen_port, listen_port);
+        let persisted = std::fs::read_to_string(&config_path).unwrap();
+        assert!(persisted.contains("flushConsumerOffsetInterval=1000"));
+        assert!(persisted.contains("listenPort=20911"));
+
+        // nothing is applied when one of the values is invalid
+        let response = handler.update_config_properties(HashMap::from([
+            ("flushConsumerOffsetInterval".into(), "2000".into()),
+            ("commercialBaseCount".into(), "not a number".into()),
+        ]));
+        assert_eq!(
+            ResponseCode::from(response.code()),
+            ResponseCode::SystemError
+        );
+        assert_eq!(inner.broker_config().flush_consumer_offset_interval, 1000);
+    }
+}
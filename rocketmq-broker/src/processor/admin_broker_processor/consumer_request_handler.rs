@@ -16,26 +16,38 @@
  */
 
 use std::collections::HashSet;
+use std::sync::Arc;
 
+use cheetah_string::CheetahString;
 use rocketmq_common::common::config_manager::ConfigManager;
 use rocketmq_common::common::message::message_queue::MessageQueue;
+use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_remoting::code::request_code::RequestCode;
 use rocketmq_remoting::code::response_code::ResponseCode;
 use rocketmq_remoting::net::channel::Channel;
 use rocketmq_remoting::protocol::admin::consume_stats::ConsumeStats;
 use rocketmq_remoting::protocol::admin::offset_wrapper::OffsetWrapper;
 use rocketmq_remoting::protocol::body::connection::Connection;
+use rocketmq_remoting::protocol::body::consume_queue_data::ConsumeQueueData;
 use rocketmq_remoting::protocol::body::consumer_connection::ConsumerConnection;
+use rocketmq_remoting::protocol::body::query_consume_queue_response_body::QueryConsumeQueueResponseBody;
+use rocketmq_remoting::protocol::body::query_consume_time_span_body::QueryConsumeTimeSpanBody;
+use rocketmq_remoting::protocol::body::queue_time_span::QueueTimeSpan;
 use rocketmq_remoting::protocol::header::get_consume_stats_request_header::GetConsumeStatsRequestHeader;
 use rocketmq_remoting::protocol::header::get_consumer_connection_list_request_header::GetConsumerConnectionListRequestHeader;
+use rocketmq_remoting::protocol::header::query_consume_queue_request_header::QueryConsumeQueueRequestHeader;
+use rocketmq_remoting::protocol::header::query_consume_time_span_request_header::QueryConsumeTimeSpanRequestHeader;
 use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
 use rocketmq_remoting::protocol::RemotingSerializable;
 use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
 use rocketmq_rust::ArcMut;
+use rocketmq_store::consume_queue::consume_queue_ext;
+use rocketmq_store::filter::MessageFilter;
 use rocketmq_store::log_file::MessageStore;
 use tracing::warn;
 
 use crate::broker_runtime::BrokerRuntimeInner;
+use crate::filter::expression_message_filter::ExpressionMessageFilter;
 
 #[derive(Clone)]
 pub(super) struct ConsumerRequestHandler<MS> {
@@ -177,35 +189,28 @@ impl<MS: MessageStore> ConsumerRequestHandler<MS> {
 
                 let mut offset_wrapper = OffsetWrapper::new();
 
-                let mut broker_offset = self
+                let broker_offset = self
                     .broker_runtime_inner
                     .message_store()
                     .as_ref()
                     .unwrap()
                     .get_max_offset_in_queue(topic, i as i32);
-                if broker_offset < 0 {
-                    broker_offset = 0;
-                }
-
-                let mut consumer_offset = self
-                    .broker_runtime_inner
-                    .consumer_offset_manager()
-                    .query_offset(request_header.get_consumer_group(), topic, i as i32);
-
-                if mapping_detail.is_none() && consumer_offset < 0 {
-                    consumer_offset = 0;
-                }
-
-                let pull_offset = self
+                let queue_lag = self
                     .broker_runtime_inner
                     .consumer_offset_manager()
-                    .query_offset(request_header.get_consumer_group(), topic, i as i32);
+                    .queue_lag(
+                        request_header.get_consumer_group(),
+                        topic,
+                        i as i32,
+                        broker_offset,
+                        mapping_detail.is_some(),
+                    );
 
-                offset_wrapper.set_broker_offset(broker_offset);
-                offset_wrapper.set_consumer_offset(consumer_offset);
-                offset_wrapper.set_pull_offset(std::cmp::max(consumer_offset, pull_offset));
+                offset_wrapper.set_broker_offset(queue_lag.broker_offset);
+                offset_wrapper.set_consumer_offset(queue_lag.consumer_offset);
+                offset_wrapper.set_pull_offset(queue_lag.pull_offset);
 
-                let time_offset = consumer_offset - 1;
+                let time_offset = queue_lag.consumer_offset - 1;
                 if time_offset >= 0 {
                     let last_timestamp = self
                         .broker_runtime_inner
@@ -218,7 +223,9 @@ impl<MS: MessageStore> ConsumerRequestHandler<MS> {
                     }
                 }
 
-                consume_stats.get_offset_table().insert(mq, offset_wrapper);
+                consume_stats
+                    .get_offset_table_mut()
+                    .insert(mq, offset_wrapper);
             }
 
             let consume_tps = self
@@ -257,4 +264,267 @@ impl<MS: MessageStore> ConsumerRequestHandler<MS> {
             )
         }
     }
+
+    pub async fn get_all_delay_offset(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        _request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let mut response = RemotingCommand::create_response_command();
+        let content = self
+            .broker_runtime_inner
+            .schedule_message_service()
+            .encode_pretty(false);
+        if !content.is_empty() {
+            response.set_body_mut_ref(content);
+            Some(response)
+        } else {
+            Some(
+                response
+                    .set_code(ResponseCode::SystemError)
+                    .set_remark("No delay offset in this broker"),
+            )
+        }
+    }
+
+    pub async fn get_all_message_request_mode(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        _request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let mut response = RemotingCommand::create_response_command();
+        let content = self
+            .broker_runtime_inner
+            .message_request_mode_manager()
+            .encode_pretty(false);
+        if !content.is_empty() {
+            response.set_body_mut_ref(content);
+            Some(response)
+        } else {
+            Some(
+                response
+                    .set_code(ResponseCode::SystemError)
+                    .set_remark("No message request mode in this broker"),
+            )
+        }
+    }
+
+    pub async fn query_consume_time_span(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let request_header =
+            match request.decode_command_custom_header::<QueryConsumeTimeSpanRequestHeader>() {
+                Ok(header) => header,
+                Err(e) => {
+                    return Some(RemotingCommand::create_response_command_with_code_remark(
+                        ResponseCode::SystemError,
+                        format!("decode QueryConsumeTimeSpanRequestHeader failed: {}", e),
+                    ));
+                }
+            };
+        let topic = &request_header.topic;
+        let group = &request_header.group;
+        let Some(topic_config) = self
+            .broker_runtime_inner
+            .topic_config_manager()
+            .select_topic_config(topic)
+        else {
+            return Some(
+                RemotingCommand::create_response_command_with_code(ResponseCode::TopicNotExist)
+                    .set_remark(format!("topic[{}] not exist", topic)),
+            );
+        };
+
+        let message_store = self.broker_runtime_inner.message_store().as_ref().unwrap();
+        let mut time_spans = Vec::with_capacity(topic_config.write_queue_nums as usize);
+        for i in 0..topic_config.write_queue_nums as i32 {
+            let mq = MessageQueue::from_parts(
+                topic.as_str(),
+                self.broker_runtime_inner
+                    .broker_config()
+                    .broker_name
+                    .as_str(),
+                i,
+            );
+            let min_offset = message_store.get_min_offset_in_queue(topic, i);
+            let max_offset = message_store.get_max_offset_in_queue(topic, i);
+            let min_time = message_store.get_message_store_timestamp(topic, i, min_offset);
+            let max_time =
+                message_store.get_message_store_timestamp(topic, i, (max_offset - 1).max(0));
+
+            let consumer_offset = self
+                .broker_runtime_inner
+                .consumer_offset_manager()
+                .query_offset(group, topic, i);
+            let consume_time = if consumer_offset > 0 {
+                message_store.get_message_store_timestamp(topic, i, consumer_offset - 1)
+            } else {
+                min_time
+            };
+            let delay_time = if consumer_offset >= 0 && consumer_offset < max_offset {
+                let next_time =
+                    message_store.get_message_store_timestamp(topic, i, consumer_offset);
+                (get_current_millis() as i64 - next_time).max(0)
+            } else {
+                0
+            };
+
+            time_spans.push(QueueTimeSpan {
+                message_queue: Some(mq),
+                min_time_stamp: min_time,
+                max_time_stamp: max_time,
+                consume_time_stamp: consume_time,
+                delay_time,
+            });
+        }
+
+        let body = QueryConsumeTimeSpanBody {
+            consume_time_span_set: time_spans,
+        };
+        Some(
+            RemotingCommand::create_response_command()
+                .set_body(body.encode().expect("consume time span body encode failed")),
+        )
+    }
+
+    pub async fn query_consume_queue(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let request_header =
+            match request.decode_command_custom_header::<QueryConsumeQueueRequestHeader>() {
+                Ok(header) => header,
+                Err(e) => {
+                    return Some(RemotingCommand::create_response_command_with_code_remark(
+                        ResponseCode::SystemError,
+                        format!("decode QueryConsumeQueueRequestHeader failed: {}", e),
+                    ));
+                }
+            };
+        let topic = &request_header.topic;
+        let queue_id = request_header.queue_id;
+        let Some(consume_queue) = self
+            .broker_runtime_inner
+            .message_store()
+            .as_ref()
+            .unwrap()
+            .find_consume_queue(topic, queue_id)
+        else {
+            return Some(RemotingCommand::create_response_command_with_code_remark(
+                ResponseCode::SystemError,
+                format!("{}@{} is not exist!", queue_id, topic),
+            ));
+        };
+
+        let mut body = QueryConsumeQueueResponseBody {
+            max_queue_index: consume_queue.get_max_offset_in_queue(),
+            min_queue_index: consume_queue.get_min_offset_in_queue(),
+            ..Default::default()
+        };
+
+        let mut message_filter: Option<ExpressionMessageFilter> = None;
+        if let Some(consumer_group) = request_header.consumer_group.as_ref() {
+            match self
+                .broker_runtime_inner
+                .consumer_manager()
+                .find_subscription_data(consumer_group, topic)
+            {
+                Some(subscription_data) => {
+                    body.subscription_data = subscription_data.clone();
+                    let filter_data = self
+                        .broker_runtime_inner
+                        .consumer_filter_manager()
+                        .get_consumer_filter_data(topic, consumer_group);
+                    message_filter = Some(ExpressionMessageFilter::new(
+                        Some(subscription_data),
+                        filter_data,
+                        Arc::new(self.broker_runtime_inner.consumer_filter_manager().clone()),
+                    ));
+                }
+                None => {
+                    body.filter_data =
+                        CheetahString::from(format!("{}@{} is not online!", consumer_group, topic));
+                }
+            }
+        }
+
+        let index = request_header.index;
+        let Some(iter) = consume_queue.iterate_from(index) else {
+            return Some(
+                RemotingCommand::create_response_command()
+                    .set_remark(format!(
+                        "Index {} of {}@{} is not exist!",
+                        index, queue_id, topic
+                    ))
+                    .set_body(
+                        body.encode()
+                            .expect("query consume queue body encode failed"),
+                    ),
+            );
+        };
+
+        for cq_unit in iter {
+            if cq_unit.queue_offset - index >= request_header.count as i64 {
+                break;
+            }
+            let mut queue_data = ConsumeQueueData {
+                physic_offset: cq_unit.pos,
+                physic_size: cq_unit.size,
+                tags_code: cq_unit.tags_code,
+                ..Default::default()
+            };
+            match cq_unit.cq_ext_unit.as_ref() {
+                Some(ext) => {
+                    queue_data.extend_data_json = Some(CheetahString::from(format!(
+                        "{{\"tagsCode\":{},\"msgStoreTime\":{},\"bitMapSize\":{}}}",
+                        ext.tags_code(),
+                        ext.msg_store_time(),
+                        ext.bit_map_size()
+                    )));
+                    if let Some(bit_map) = ext.filter_bit_map() {
+                        queue_data.bit_map = Some(CheetahString::from(
+                            bit_map
+                                .iter()
+                                .map(|byte| format!("{:08b}", byte))
+                                .collect::<String>(),
+                        ));
+                    }
+                    if let Some(filter) = message_filter.as_ref() {
+                        queue_data.eval =
+                            filter.is_matched_by_consume_queue(Some(ext.tags_code()), Some(ext));
+                    }
+                }
+                None => {
+                    if consume_queue_ext::is_ext_addr(cq_unit.tags_code) {
+                        queue_data.msg = Some(CheetahString::from(format!(
+                            "Cq extend not exist!addr: {}",
+                            cq_unit.tags_code
+                        )));
+                    } else if let Some(filter) = message_filter.as_ref() {
+                        queue_data.eval =
+                            filter.is_matched_by_consume_queue(Some(cq_unit.tags_code), None);
+                    }
+                }
+            }
+            body.queue_data.push(queue_data);
+        }
+
+        Some(
+            RemotingCommand::create_response_command().set_body(
+                body.encode()
+                    .expect("query consume queue body encode failed"),
+            ),
+        )
+    }
 }
@@ -14,22 +14,40 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use cheetah_string::CheetahString;
+use rocketmq_common::common::boundary_type::BoundaryType;
+use rocketmq_common::common::broker::broker_role::BrokerRole;
+use rocketmq_common::common::message::message_queue::MessageQueue;
 use rocketmq_remoting::code::request_code::RequestCode;
 use rocketmq_remoting::code::response_code::ResponseCode;
 use rocketmq_remoting::net::channel::Channel;
+use rocketmq_remoting::protocol::body::query_correction_offset_body::QueryCorrectionOffsetBody;
+use rocketmq_remoting::protocol::body::reset_offset_body::ResetOffsetBody;
+use rocketmq_remoting::protocol::header::clone_group_offset_request_header::CloneGroupOffsetRequestHeader;
 use rocketmq_remoting::protocol::header::get_max_offset_request_header::GetMaxOffsetRequestHeader;
 use rocketmq_remoting::protocol::header::get_max_offset_response_header::GetMaxOffsetResponseHeader;
 use rocketmq_remoting::protocol::header::get_min_offset_request_header::GetMinOffsetRequestHeader;
 use rocketmq_remoting::protocol::header::get_min_offset_response_header::GetMinOffsetResponseHeader;
 use rocketmq_remoting::protocol::header::message_operation_header::TopicRequestHeaderTrait;
+use rocketmq_remoting::protocol::header::query_correction_offset_header::QueryCorrectionOffsetHeader;
+use rocketmq_remoting::protocol::header::reset_offset_request_header::ResetOffsetRequestHeader;
+use rocketmq_remoting::protocol::header::search_offset_request_header::SearchOffsetRequestHeader;
+use rocketmq_remoting::protocol::header::search_offset_response_header::SearchOffsetResponseHeader;
 use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
 use rocketmq_remoting::protocol::static_topic::topic_queue_mapping_context::TopicQueueMappingContext;
 use rocketmq_remoting::protocol::static_topic::topic_queue_mapping_utils::TopicQueueMappingUtils;
+use rocketmq_remoting::protocol::RemotingSerializable;
 use rocketmq_remoting::rpc::rpc_client::RpcClient;
 use rocketmq_remoting::rpc::rpc_request::RpcRequest;
 use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
 use rocketmq_rust::ArcMut;
 use rocketmq_store::log_file::MessageStore;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
 
 use crate::broker_runtime::BrokerRuntimeInner;
 
@@ -117,6 +135,440 @@ impl<MS: MessageStore> OffsetRequestHandler<MS> {
             response_header,
         ))
     }
+    /// Answers the offset of the first message of a queue stored at the requested timestamp, or
+    /// of the last one when the boundary type is upper.
+    pub async fn search_offset_by_timestamp(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let request_header =
+            match request.decode_command_custom_header::<SearchOffsetRequestHeader>() {
+                Ok(header) => header,
+                Err(e) => {
+                    return Some(RemotingCommand::create_response_command_with_code_remark(
+                        ResponseCode::SystemError,
+                        format!("decode SearchOffsetRequestHeader failed: {}", e),
+                    ));
+                }
+            };
+        let boundary_type = request_header
+            .boundary_type
+            .as_ref()
+            .and_then(|boundary_type| BoundaryType::get_type(boundary_type))
+            .unwrap_or(BoundaryType::Lower);
+        let Some(message_store) = self.broker_runtime_inner.message_store() else {
+            return Some(RemotingCommand::create_response_command_with_code_remark(
+                ResponseCode::SystemError,
+                "the message store is not started",
+            ));
+        };
+        let offset = message_store.get_offset_in_queue_by_time_with_boundary(
+            &request_header.topic,
+            request_header.queue_id,
+            request_header.timestamp,
+            boundary_type,
+        );
+        Some(RemotingCommand::create_response_command_with_header(
+            SearchOffsetResponseHeader { offset },
+        ))
+    }
+
+    pub async fn reset_offset(
+        &mut self,
+        channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let request_header = match request
+            .decode_command_custom_header::<ResetOffsetRequestHeader>()
+        {
+            Ok(header) => header,
+            Err(e) => {
+                return Some(
+                    RemotingCommand::create_response_command_with_code(ResponseCode::SystemError)
+                        .set_remark(format!("decode request header failed: {}", e)),
+                )
+            }
+        };
+        info!(
+            "[reset-offset] reset offset started by {}. topic={}, group={}, timestamp={}, \
+             isForce={}",
+            channel.remote_address(),
+            request_header.topic,
+            request_header.group,
+            request_header.timestamp,
+            request_header.is_force
+        );
+        if self
+            .broker_runtime_inner
+            .broker_config()
+            .use_server_side_reset_offset
+        {
+            return Some(self.reset_offset_inner(&request_header));
+        }
+        Some(self.reset_offset_by_client(&request_header).await)
+    }
+
+    pub async fn clone_group_offset(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let request_header = match request
+            .decode_command_custom_header::<CloneGroupOffsetRequestHeader>()
+        {
+            Ok(header) => header,
+            Err(e) => {
+                return Some(
+                    RemotingCommand::create_response_command_with_code(ResponseCode::SystemError)
+                        .set_remark(format!(
+                            "decode CloneGroupOffsetRequestHeader failed: {}",
+                            e
+                        )),
+                );
+            }
+        };
+        let topics = match request_header
+            .topic
+            .as_ref()
+            .filter(|topic| !topic.is_empty())
+        {
+            Some(topic) => HashSet::from([topic.clone()]),
+            None => self
+                .broker_runtime_inner
+                .consumer_offset_manager()
+                .which_topic_by_consumer(&request_header.src_group),
+        };
+        for topic in &topics {
+            if self
+                .broker_runtime_inner
+                .topic_config_manager()
+                .select_topic_config(topic)
+                .is_none()
+            {
+                warn!("[cloneGroupOffset], topic config not exist, {}", topic);
+                continue;
+            }
+            if !request_header.offline {
+                let consumer_manager = self.broker_runtime_inner.consumer_manager();
+                // the source group is online but no longer subscribes this topic
+                if consumer_manager.find_subscription_data_count(&request_header.src_group) > 0
+                    && consumer_manager
+                        .find_subscription_data(&request_header.src_group, topic)
+                        .is_none()
+                {
+                    warn!(
+                        "[cloneGroupOffset], the consumer group[{}], topic[{}] not exist",
+                        request_header.src_group, topic
+                    );
+                    continue;
+                }
+            }
+            self.broker_runtime_inner
+                .consumer_offset_manager()
+                .clone_offset(&request_header.src_group, &request_header.dest_group, topic);
+        }
+        info!(
+            "clone group offset from {} to {}, topics: {:?}",
+            request_header.src_group, request_header.dest_group, topics
+        );
+        Some(RemotingCommand::create_response_command())
+    }
+
+    pub async fn query_correction_offset(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let request_header = match request
+            .decode_command_custom_header::<QueryCorrectionOffsetHeader>()
+        {
+            Ok(header) => header,
+            Err(e) => {
+                return Some(
+                    RemotingCommand::create_response_command_with_code(ResponseCode::SystemError)
+                        .set_remark(format!("decode QueryCorrectionOffsetHeader failed: {}", e)),
+                );
+            }
+        };
+        let consumer_offset_manager = self.broker_runtime_inner.consumer_offset_manager();
+        let mut correction_offsets = consumer_offset_manager.query_min_offset_in_all_group(
+            &request_header.topic,
+            request_header.filter_groups.as_deref(),
+        );
+        let compare_offsets = consumer_offset_manager
+            .query_offset_table(&request_header.compare_group, &request_header.topic);
+        // queues where the compare group is behind every other group need no correction
+        for (queue_id, compare_offset) in compare_offsets {
+            if let Some(offset) = correction_offsets.get_mut(&queue_id) {
+                if *offset > compare_offset {
+                    *offset = i64::MAX;
+                }
+            }
+        }
+        let body = QueryCorrectionOffsetBody { correction_offsets };
+        Some(
+            RemotingCommand::create_response_command().set_body(
+                body.encode()
+                    .expect("encode QueryCorrectionOffsetBody failed"),
+            ),
+        )
+    }
+
+    fn reset_offset_inner(&self, request_header: &ResetOffsetRequestHeader) -> RemotingCommand {
+        let topic = &request_header.topic;
+        let group = &request_header.group;
+        if self.broker_runtime_inner.message_store_config().broker_role == BrokerRole::Slave {
+            return RemotingCommand::create_response_command_with_code(ResponseCode::SystemError)
+                .set_remark("Can not reset offset in slave broker");
+        }
+        let topic_config = match self
+            .broker_runtime_inner
+            .topic_config_manager()
+            .select_topic_config(topic)
+        {
+            Some(topic_config) => topic_config,
+            None => {
+                warn!("[reset-offset] topic {} does not exist", topic);
+                return RemotingCommand::create_response_command_with_code(
+                    ResponseCode::TopicNotExist,
+                )
+                .set_remark(format!("Topic {} does not exist", topic));
+            }
+        };
+        if !self
+            .broker_runtime_inner
+            .subscription_group_manager()
+            .contains_subscription_group(group)
+        {
+            return RemotingCommand::create_response_command_with_code(
+                ResponseCode::SubscriptionGroupNotExist,
+            )
+            .set_remark(format!("Group {} does not exist", group));
+        }
+        let message_store = self.broker_runtime_inner.message_store().as_ref().unwrap();
+        let mut queue_offset_map = HashMap::new();
+        if request_header.queue_id >= 0 {
+            let queue_id = request_header.queue_id;
+            let offset = match request_header.offset {
+                Some(offset) if offset != -1 => {
+                    let min = message_store.get_min_offset_in_queue(topic, queue_id);
+                    let max = message_store.get_max_offset_in_queue(topic, queue_id);
+                    if (min >= 0 && offset < min) || offset > max + 1 {
+                        return RemotingCommand::create_response_command_with_code(
+                            ResponseCode::SystemError,
+                        )
+                        .set_remark(format!(
+                            "Target offset {} not in consume queue range [{}-{}]",
+                            offset, min, max
+                        ));
+                    }
+                    offset
+                }
+                _ => message_store.get_offset_in_queue_by_time(
+                    topic,
+                    queue_id,
+                    request_header.timestamp,
+                ),
+            };
+            queue_offset_map.insert(queue_id, offset);
+        } else {
+            for queue_id in 0..topic_config.read_queue_nums as i32 {
+                let offset = message_store.get_offset_in_queue_by_time(
+                    topic,
+                    queue_id,
+                    request_header.timestamp,
+                );
+                queue_offset_map.insert(queue_id, offset);
+            }
+        }
+        if queue_offset_map.is_empty() {
+            return RemotingCommand::create_response_command_with_code(ResponseCode::SystemError)
+                .set_remark("No queues to reset.");
+        }
+        let broker_name = &self.broker_runtime_inner.broker_config().broker_name;
+        let mut body = ResetOffsetBody::default();
+        for (queue_id, offset) in queue_offset_map {
+            self.broker_runtime_inner
+                .consumer_offset_manager()
+                .assign_reset_offset(topic, group, queue_id, offset);
+            self.broker_runtime_inner
+                .pop_inflight_message_counter()
+                .clear_in_flight_message_num(topic, group, queue_id);
+            body.offset_table.insert(
+                MessageQueue::from_parts(topic.clone(), broker_name.clone(), queue_id),
+                offset,
+            );
+        }
+        info!(
+            "[reset-offset] reset offset in broker success. topic={}, group={}, offsets={:?}",
+            topic, group, body.offset_table
+        );
+        RemotingCommand::create_response_command()
+            .set_body(body.encode().expect("reset offset body encode failed"))
+    }
+
+    /// Computes the target offsets, commits them and pushes
+    /// `RESET_CONSUMER_CLIENT_OFFSET` to every online client of the group.
+    async fn reset_offset_by_client(
+        &self,
+        request_header: &ResetOffsetRequestHeader,
+    ) -> RemotingCommand {
+        let topic = &request_header.topic;
+        let group = &request_header.group;
+        let timestamp = request_header.timestamp;
+        let topic_config = match self
+            .broker_runtime_inner
+            .topic_config_manager()
+            .select_topic_config(topic)
+        {
+            Some(topic_config) => topic_config,
+            None => {
+                error!(
+                    "[reset-offset] reset offset failed, no topic in this broker. topic={}",
+                    topic
+                );
+                return RemotingCommand::create_response_command_with_code(
+                    ResponseCode::TopicNotExist,
+                )
+                .set_remark(format!(
+                    "[reset-offset] reset offset failed, no topic in this broker. topic={}",
+                    topic
+                ));
+            }
+        };
+        let offset_table = match self.compute_reset_offset_table(
+            topic,
+            group,
+            timestamp,
+            request_header.is_force,
+            topic_config.write_queue_nums as i32,
+        ) {
+            Ok(offset_table) => offset_table,
+            Err(remark) => {
+                return RemotingCommand::create_response_command_with_code(
+                    ResponseCode::SystemError,
+                )
+                .set_remark(remark)
+            }
+        };
+
+        let consumer_group_info = self
+            .broker_runtime_inner
+            .consumer_manager()
+            .get_consumer_group_info(group);
+        let channel_infos = match consumer_group_info {
+            Some(info) if !info.get_all_channels().is_empty() => info.get_channel_info_table(),
+            _ => {
+                let error_info = format!(
+                    "Consumer not online, so can not reset offset, Group: {} Topic: {} Timestamp: \
+                     {}",
+                    group, topic, timestamp
+                );
+                error!("{}", error_info);
+                return RemotingCommand::create_response_command_with_code(
+                    ResponseCode::ConsumerNotOnline,
+                )
+                .set_remark(error_info);
+            }
+        };
+
+        for (mq, offset) in offset_table.iter() {
+            self.broker_runtime_inner
+                .consumer_offset_manager()
+                .commit_offset(
+                    CheetahString::from_static_str("ResetOffsetByTime"),
+                    group,
+                    topic,
+                    mq.get_queue_id(),
+                    *offset,
+                );
+        }
+
+        let body = ResetOffsetBody::new(offset_table)
+            .encode()
+            .expect("reset offset body encode failed");
+        let client_request_header = ResetOffsetRequestHeader {
+            topic: topic.clone(),
+            group: group.clone(),
+            timestamp,
+            ..Default::default()
+        };
+        let request = RemotingCommand::create_request_command(
+            RequestCode::ResetConsumerClientOffset,
+            client_request_header,
+        )
+        .set_body(body.clone());
+        for entry in channel_infos.iter() {
+            match entry.key().send_one_way(request.clone(), 5000).await {
+                Ok(_) => info!(
+                    "[reset-offset] reset offset success. topic={}, group={}, clientId={}",
+                    topic,
+                    group,
+                    entry.value().client_id()
+                ),
+                Err(e) => error!(
+                    "[reset-offset] reset offset exception. topic={}, group={}, clientId={}, {}",
+                    topic,
+                    group,
+                    entry.value().client_id(),
+                    e
+                ),
+            }
+        }
+        RemotingCommand::create_response_command().set_body(body)
+    }
+
+    fn compute_reset_offset_table(
+        &self,
+        topic: &CheetahString,
+        group: &CheetahString,
+        timestamp: i64,
+        is_force: bool,
+        queue_nums: i32,
+    ) -> Result<HashMap<MessageQueue, i64>, String> {
+        let message_store = self.broker_runtime_inner.message_store().as_ref().unwrap();
+        let consumer_offset_manager = self.broker_runtime_inner.consumer_offset_manager();
+        let broker_name = &self.broker_runtime_inner.broker_config().broker_name;
+        let mut offset_table = HashMap::new();
+        for queue_id in 0..queue_nums {
+            let consumer_offset = consumer_offset_manager.query_offset(group, topic, queue_id);
+            if consumer_offset == -1 {
+                return Err(format!("THe consumer group <{}> not exist", group));
+            }
+            let mut timestamp_offset = if timestamp == -1 {
+                message_store.get_max_offset_in_queue(topic, queue_id)
+            } else {
+                message_store.get_offset_in_queue_by_time(topic, queue_id, timestamp)
+            };
+            if timestamp_offset < 0 {
+                warn!(
+                    "reset offset is invalid. topic={}, queueId={}, timeStampOffset={}",
+                    topic, queue_id, timestamp_offset
+                );
+                timestamp_offset = 0;
+            }
+            let offset = if is_force || timestamp_offset < consumer_offset {
+                timestamp_offset
+            } else {
+                consumer_offset
+            };
+            offset_table.insert(
+                MessageQueue::from_parts(topic.clone(), broker_name.clone(), queue_id),
+                offset,
+            );
+        }
+        Ok(offset_table)
+    }
+
     /*
     async fn handle_get_min_offset(
         &mut self,
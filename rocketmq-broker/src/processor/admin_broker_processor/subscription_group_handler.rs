@@ -0,0 +1,347 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use cheetah_string::CheetahString;
+use rocketmq_common::common::config_manager::ConfigManager;
+use rocketmq_common::common::constant::PermName;
+use rocketmq_common::common::mix_all;
+use rocketmq_common::common::topic::TopicValidator;
+use rocketmq_remoting::code::request_code::RequestCode;
+use rocketmq_remoting::code::response_code::ResponseCode;
+use rocketmq_remoting::net::channel::Channel;
+use rocketmq_remoting::protocol::header::delete_subscription_group_request_header::DeleteSubscriptionGroupRequestHeader;
+use rocketmq_remoting::protocol::header::update_group_forbidden_request_header::UpdateGroupForbiddenRequestHeader;
+use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+use rocketmq_remoting::protocol::subscription::group_forbidden::GroupForbidden;
+use rocketmq_remoting::protocol::subscription::subscription_group_config::SubscriptionGroupConfig;
+use rocketmq_remoting::protocol::RemotingDeserializable;
+use rocketmq_remoting::protocol::RemotingSerializable;
+use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
+use rocketmq_rust::ArcMut;
+use rocketmq_store::log_file::MessageStore;
+use tracing::info;
+
+use crate::broker_runtime::BrokerRuntimeInner;
+use crate::subscription::manager::subscription_group_manager::CHARACTER_MAX_LENGTH;
+
+#[derive(Clone)]
+pub(super) struct SubscriptionGroupHandler<MS> {
+    broker_runtime_inner: ArcMut<BrokerRuntimeInner<MS>>,
+}
+
+impl<MS: MessageStore> SubscriptionGroupHandler<MS> {
+    pub(super) fn new(broker_runtime_inner: ArcMut<BrokerRuntimeInner<MS>>) -> Self {
+        Self {
+            broker_runtime_inner,
+        }
+    }
+
+    /// Creates or replaces the subscription group sent in the body, the admin tool sends it to
+    /// every broker of the cluster.
+    pub async fn update_and_create_subscription_group(
+        &mut self,
+        channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        info!(
+            "AdminBrokerProcessor#updateAndCreateSubscriptionGroup called by {}",
+            channel.remote_address()
+        );
+        let response = RemotingCommand::create_response_command();
+        let config = match request
+            .body()
+            .as_ref()
+            .map(|body| SubscriptionGroupConfig::decode(body.as_ref()))
+        {
+            Some(Ok(config)) => config,
+            _ => {
+                return Some(
+                    response
+                        .set_code(ResponseCode::SystemError)
+                        .set_remark("The subscription group config is missing or malformed."),
+                );
+            }
+        };
+        let group = config.group_name();
+        if group.is_empty()
+            || group.len() > CHARACTER_MAX_LENGTH
+            || TopicValidator::is_topic_or_group_illegal(group)
+        {
+            return Some(
+                response
+                    .set_code(ResponseCode::SystemError)
+                    .set_remark(format!("The group name[{}] is illegal.", group)),
+            );
+        }
+        self.broker_runtime_inner
+            .subscription_group_manager()
+            .update_subscription_group_config(config);
+        Some(response)
+    }
+
+    pub async fn get_all_subscription_group_config(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        _request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let mut response = RemotingCommand::create_response_command();
+        let content = self
+            .broker_runtime_inner
+            .subscription_group_manager()
+            .encode_pretty(false);
+        if !content.is_empty() {
+            response.set_body_mut_ref(content);
+            Some(response)
+        } else {
+            Some(
+                response
+                    .set_code(ResponseCode::SystemError)
+                    .set_remark("No subscription group in this broker"),
+            )
+        }
+    }
+
+    /// Pauses or resumes consumption of `topic` by `group` without touching its subscription.
+    pub async fn update_and_get_group_forbidden(
+        &mut self,
+        channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let response = RemotingCommand::create_response_command();
+        let request_header =
+            match request.decode_command_custom_header::<UpdateGroupForbiddenRequestHeader>() {
+                Ok(header) => header,
+                Err(e) => {
+                    return Some(
+                        response
+                            .set_code(ResponseCode::SystemError)
+                            .set_remark(format!("decode request header failed: {}", e)),
+                    )
+                }
+            };
+        info!(
+            "updateAndGetGroupForbidden called by {}, {:?}",
+            channel.remote_address(),
+            request_header
+        );
+        let group = &request_header.group;
+        let topic = &request_header.topic;
+        let subscription_group_manager = self.broker_runtime_inner.subscription_group_manager();
+        if let Some(readable) = request_header.readable {
+            subscription_group_manager.update_forbidden(
+                group,
+                topic,
+                PermName::INDEX_PERM_READ as i32,
+                !readable,
+            );
+        }
+        let readable = !subscription_group_manager.get_forbidden(
+            group.as_str(),
+            topic.as_str(),
+            PermName::INDEX_PERM_READ as i32,
+        );
+        let group_forbidden = GroupForbidden::new(topic.clone(), group.clone(), Some(readable));
+        let body = group_forbidden
+            .encode()
+            .expect("group forbidden encode failed");
+        Some(response.set_body(body))
+    }
+
+    /// Deletes the subscription of a group. With `clean_offset` the offsets, in flight counters
+    /// and order info of the group are dropped as well, together with its retry and DLQ topics.
+    pub async fn delete_subscription_group(
+        &mut self,
+        channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let request_header = match request
+            .decode_command_custom_header::<DeleteSubscriptionGroupRequestHeader>()
+        {
+            Ok(header) => header,
+            Err(e) => {
+                return Some(
+                    RemotingCommand::create_response_command_with_code(ResponseCode::SystemError)
+                        .set_remark(format!("decode request header failed: {}", e)),
+                )
+            }
+        };
+        info!(
+            "AdminBrokerProcessor#deleteSubscriptionGroup, caller={}, {:?}",
+            channel.remote_address(),
+            request_header
+        );
+        let group = &request_header.group_name;
+        self.broker_runtime_inner
+            .subscription_group_manager()
+            .delete_subscription_group_config(group);
+
+        if request_header.clean_offset {
+            self.broker_runtime_inner
+                .consumer_offset_manager()
+                .remove_offset(group);
+            self.broker_runtime_inner
+                .pop_inflight_message_counter()
+                .clear_in_flight_message_num_by_group_name(group);
+            self.broker_runtime_inner
+                .consumer_filter_manager()
+                .unregister(group);
+            self.broker_runtime_inner
+                .consumer_order_info_manager()
+                .remove_group(group);
+            let retry_topic = CheetahString::from_string(mix_all::get_retry_topic(group));
+            let dlq_topic = CheetahString::from_string(mix_all::get_dlq_topic(group));
+            for topic in [&retry_topic, &dlq_topic] {
+                self.broker_runtime_inner
+                    .topic_config_manager()
+                    .delete_topic_config(topic);
+            }
+            if let Some(message_store) = self.broker_runtime_inner.message_store_mut() {
+                message_store.delete_topics(vec![&retry_topic, &dlq_topic]);
+            }
+        }
+
+        if self
+            .broker_runtime_inner
+            .broker_config()
+            .auto_delete_unused_stats
+        {
+            self.broker_runtime_inner
+                .broker_stats_manager()
+                .on_group_deleted(group);
+        }
+
+        Some(RemotingCommand::create_response_command())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use rocketmq_common::common::broker::broker_config::BrokerConfig;
+    use rocketmq_common::common::filter::expression_type::ExpressionType;
+    use rocketmq_common::common::server::config::ServerConfig;
+    use rocketmq_remoting::connection::Connection;
+    use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContextWrapper;
+    use rocketmq_store::config::message_store_config::MessageStoreConfig;
+    use tokio::net::TcpListener;
+    use tokio::net::TcpStream;
+
+    use super::*;
+    use crate::broker_runtime::BrokerRuntime;
+
+    const TOPIC: &str = "DeleteGroupTopic";
+    const GROUP: &str = "DeleteGroup";
+
+    #[test]
+    fn deleting_a_group_with_clean_offset_removes_its_offsets_and_filters() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let _guard = runtime.enter();
+        let store_dir = tempfile::tempdir().unwrap();
+        let store_path_root_dir = CheetahString::from(store_dir.path().to_string_lossy().as_ref());
+        let ha_listen_port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port() as usize;
+        let mut broker = BrokerRuntime::new(
+            BrokerConfig {
+                store_path_root_dir: store_path_root_dir.clone(),
+                ..Default::default()
+            },
+            MessageStoreConfig {
+                store_path_root_dir,
+                ha_listen_port,
+                ..Default::default()
+            },
+            ServerConfig::default(),
+        );
+        assert!(runtime.block_on(broker.initialize()));
+        let inner = broker.inner().clone();
+        let group = CheetahString::from_static_str(GROUP);
+        let topic = CheetahString::from_static_str(TOPIC);
+        inner
+            .subscription_group_manager()
+            .update_subscription_group_config(SubscriptionGroupConfig::new(group.clone()));
+        inner
+            .consumer_offset_manager()
+            .commit_offset("127.0.0.1".into(), &group, &topic, 0, 42);
+        assert!(inner.consumer_filter_manager().register(
+            &topic,
+            &group,
+            &CheetahString::from_static_str("a > 1"),
+            &CheetahString::from_static_str(ExpressionType::SQL92),
+            1,
+        ));
+
+        runtime.block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let stream = TcpStream::connect(listener.local_addr().unwrap())
+                .await
+                .unwrap();
+            let channel = Channel::new(
+                stream.local_addr().unwrap(),
+                stream.peer_addr().unwrap(),
+                Connection::new(stream),
+                ArcMut::new(HashMap::new()),
+            );
+            let ctx = ArcMut::new(ConnectionHandlerContextWrapper::new(channel.clone()));
+            let mut request = RemotingCommand::create_request_command(
+                RequestCode::DeleteSubscriptionGroup,
+                DeleteSubscriptionGroupRequestHeader {
+                    group_name: group.clone(),
+                    clean_offset: true,
+                    rpc_request_header: None,
+                },
+            );
+            request.make_custom_header_to_net();
+            let response = SubscriptionGroupHandler::new(inner.clone())
+                .delete_subscription_group(
+                    channel,
+                    ctx,
+                    RequestCode::DeleteSubscriptionGroup,
+                    request,
+                )
+                .await
+                .unwrap();
+            assert_eq!(ResponseCode::from(response.code()), ResponseCode::Success);
+        });
+
+        assert!(!inner
+            .subscription_group_manager()
+            .contains_subscription_group(&group));
+        assert_eq!(
+            inner
+                .consumer_offset_manager()
+                .query_offset(&group, &topic, 0),
+            -1
+        );
+        // the filter is dead, it is cleaned up by the filter manager later
+        assert!(inner
+            .consumer_filter_manager()
+            .get_consumer_filter_data(&topic, &group)
+            .unwrap()
+            .is_dead());
+    }
+}
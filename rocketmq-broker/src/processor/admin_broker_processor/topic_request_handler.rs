@@ -25,6 +25,7 @@ use rocketmq_common::common::key_builder::KeyBuilder;
 use rocketmq_common::common::message::message_queue::MessageQueue;
 use rocketmq_common::common::topic::TopicValidator;
 use rocketmq_common::common::TopicFilterType;
+use rocketmq_common::TopicAttributes::TOPIC_MESSAGE_TYPE_ATTRIBUTE;
 use rocketmq_remoting::code::request_code::RequestCode;
 use rocketmq_remoting::code::response_code::ResponseCode;
 use rocketmq_remoting::net::channel::Channel;
@@ -49,6 +50,7 @@ use rocketmq_remoting::protocol::RemotingSerializable;
 use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
 use rocketmq_rust::ArcMut;
 use rocketmq_store::log_file::MessageStore;
+use tracing::error;
 use tracing::info;
 
 use crate::broker_runtime::BrokerRuntimeInner;
@@ -67,6 +69,34 @@ impl<MS> TopicRequestHandler<MS> {
 }
 
 impl<MS: MessageStore> TopicRequestHandler<MS> {
+    /// Checks the queue nums and the requested message type of a topic to create or update,
+    /// returns the reason it is rejected.
+    fn check_topic_config(&self, topic_config: &TopicConfig) -> Option<String> {
+        // negative queue nums of the request header wrap around
+        let valid_queue_nums = 1..=i32::MAX as u32;
+        if !valid_queue_nums.contains(&topic_config.read_queue_nums)
+            || !valid_queue_nums.contains(&topic_config.write_queue_nums)
+        {
+            return Some(format!(
+                "The read and write queue nums of topic[{}] must be positive.",
+                topic_config.topic_name.clone().unwrap_or_default()
+            ));
+        }
+        let message_type = topic_config
+            .attributes
+            .get(format!("+{}", TOPIC_MESSAGE_TYPE_ATTRIBUTE.get_name()).as_str())
+            .map(|message_type| TopicMessageType::from(message_type.to_string()));
+        if message_type == Some(TopicMessageType::Mixed)
+            && !self
+                .broker_runtime_inner
+                .broker_config()
+                .enable_mixed_message_type
+        {
+            return Some("MIXED message type is not supported.".to_string());
+        }
+        None
+    }
+
     pub async fn update_and_create_topic(
         &mut self,
         channel: Channel,
@@ -138,16 +168,11 @@ impl<MS: MessageStore> TopicRequestHandler<MS> {
             order: request_header.order,
             attributes,
         };
-        if topic_config.get_topic_message_type() == TopicMessageType::Mixed
-            && !self
-                .broker_runtime_inner
-                .broker_config()
-                .enable_mixed_message_type
-        {
+        if let Some(remark) = self.check_topic_config(&topic_config) {
             return Some(
                 response
                     .set_code(ResponseCode::SystemError)
-                    .set_remark("MIXED message type is not supported."),
+                    .set_remark(remark),
             );
         }
 
@@ -167,9 +192,14 @@ impl<MS: MessageStore> TopicRequestHandler<MS> {
             );
             return Some(response.set_code(ResponseCode::Success));
         }
-        self.broker_runtime_inner
+        if let Err(e) = self
+            .broker_runtime_inner
             .topic_config_manager_mut()
-            .update_topic_config(&mut topic_config);
+            .update_topic_config(&mut topic_config)
+        {
+            error!("Update topic failed for [{}], {}", topic, e);
+            return Some(response.set_code(ResponseCode::SystemError).set_remark(e));
+        }
 
         if self
             .broker_runtime_inner
@@ -200,6 +230,112 @@ impl<MS: MessageStore> TopicRequestHandler<MS> {
         Some(response.set_code(ResponseCode::Success))
     }
 
+    pub async fn update_and_create_static_topic(
+        &mut self,
+        channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let response = RemotingCommand::create_response_command();
+        let request_header = request
+            .decode_command_custom_header::<CreateTopicRequestHeader>()
+            .unwrap();
+        info!(
+            "Broker receive request to update or create static topic={}, caller address={}",
+            request_header.topic,
+            channel.remote_address()
+        );
+        let topic_queue_mapping_detail = match request
+            .body()
+            .as_ref()
+            .map(|body| TopicQueueMappingDetail::decode(body.as_ref()))
+        {
+            Some(Ok(value)) => value,
+            _ => {
+                return Some(
+                    response
+                        .set_code(ResponseCode::SystemError)
+                        .set_remark("The static topic mapping is missing or malformed."),
+                );
+            }
+        };
+        let topic = request_header.topic.clone();
+        let result = TopicValidator::validate_topic(topic.as_str());
+        if !result.valid() {
+            return Some(
+                response
+                    .set_code(ResponseCode::SystemError)
+                    .set_remark(result.remark().clone()),
+            );
+        }
+        if self
+            .broker_runtime_inner
+            .broker_config()
+            .validate_system_topic_when_update_topic
+            && TopicValidator::is_system_topic(topic.as_str())
+        {
+            return Some(
+                response
+                    .set_code(ResponseCode::SystemError)
+                    .set_remark(format!(
+                        "The topic[{}] is conflict with system topic.",
+                        topic.as_str()
+                    )),
+            );
+        }
+        let force = request_header.force.unwrap_or(false);
+
+        let mut topic_config = TopicConfig {
+            topic_name: Some(topic.clone()),
+            read_queue_nums: request_header.read_queue_nums as u32,
+            write_queue_nums: request_header.write_queue_nums as u32,
+            perm: request_header.perm as u32,
+            topic_filter_type: TopicFilterType::from(request_header.topic_filter_type.as_str()),
+            topic_sys_flag: request_header.topic_sys_flag.unwrap_or(0) as u32,
+            order: request_header.order,
+            ..TopicConfig::default()
+        };
+        if let Some(remark) = self.check_topic_config(&topic_config) {
+            return Some(
+                response
+                    .set_code(ResponseCode::SystemError)
+                    .set_remark(remark),
+            );
+        }
+        if let Err(e) = self
+            .broker_runtime_inner
+            .topic_config_manager_mut()
+            .update_topic_config(&mut topic_config)
+        {
+            error!("Update static topic failed for [{}], {}", topic, e);
+            return Some(response.set_code(ResponseCode::SystemError).set_remark(e));
+        }
+        if let Err(e) = self
+            .broker_runtime_inner
+            .topic_queue_mapping_manager()
+            .update_topic_queue_mapping(topic_queue_mapping_detail, force, false, true)
+        {
+            error!("Update static topic failed for [{}], {}", topic, e);
+            return Some(
+                response
+                    .set_code(ResponseCode::SystemError)
+                    .set_remark(e.to_string()),
+            );
+        }
+        BrokerRuntimeInner::<MS>::register_increment_broker_data(
+            self.broker_runtime_inner.clone(),
+            vec![topic_config],
+            self.broker_runtime_inner
+                .topic_config_manager()
+                .data_version()
+                .as_ref()
+                .clone(),
+        )
+        .await;
+        Some(response.set_code(ResponseCode::Success))
+    }
+
     pub async fn update_and_create_topic_list(
         &mut self,
         channel: Channel,
@@ -245,16 +381,11 @@ impl<MS: MessageStore> TopicRequestHandler<MS> {
                         )),
                 );
             }
-            if topic_config.get_topic_message_type() == TopicMessageType::Mixed
-                && !self
-                    .broker_runtime_inner
-                    .broker_config()
-                    .enable_mixed_message_type
-            {
+            if let Some(remark) = self.check_topic_config(topic_config) {
                 return Some(
                     response
                         .set_code(ResponseCode::SystemError)
-                        .set_remark("MIXED message type is not supported.".to_string()),
+                        .set_remark(remark),
                 );
             }
             let topic_config_origin = self
@@ -276,9 +407,14 @@ impl<MS: MessageStore> TopicRequestHandler<MS> {
             }
         }
 
-        self.broker_runtime_inner
+        if let Err(e) = self
+            .broker_runtime_inner
             .topic_config_manager_mut()
-            .update_topic_config_list(request_body.topic_config_list.as_mut_slice());
+            .update_topic_config_list(request_body.topic_config_list.as_mut_slice())
+        {
+            error!("Update topic list failed for [{}], {}", topic_names, e);
+            return Some(response.set_code(ResponseCode::SystemError).set_remark(e));
+        }
         if self
             .broker_runtime_inner
             .broker_config()
@@ -375,8 +511,8 @@ impl<MS: MessageStore> TopicRequestHandler<MS> {
             {
                 self.delete_topic_in_broker(pop_retry_topic_v1.as_ref());
             }
-            self.delete_topic_in_broker(topic);
         }
+        self.delete_topic_in_broker(topic);
         Some(response.set_code(ResponseCode::Success))
     }
 
@@ -451,9 +587,17 @@ impl<MS: MessageStore> TopicRequestHandler<MS> {
         request: RemotingCommand,
     ) -> Option<RemotingCommand> {
         let mut response = RemotingCommand::create_response_command();
-        let request_header = request
-            .decode_command_custom_header::<GetTopicStatsRequestHeader>()
-            .unwrap();
+        let request_header =
+            match request.decode_command_custom_header::<GetTopicStatsRequestHeader>() {
+                Ok(header) => header,
+                Err(e) => {
+                    return Some(
+                        response
+                            .set_code(ResponseCode::SystemError)
+                            .set_remark(format!("decode GetTopicStatsRequestHeader failed: {}", e)),
+                    );
+                }
+            };
         let topic = request_header.topic.as_ref();
         let topic_config = self
             .broker_runtime_inner
@@ -470,6 +614,7 @@ impl<MS: MessageStore> TopicRequestHandler<MS> {
         let max_queue_nums = topic_config
             .write_queue_nums
             .max(topic_config.read_queue_nums);
+        let message_store = self.broker_runtime_inner.message_store().as_ref().unwrap();
         let mut topic_stats_table = TopicStatsTable::new();
         let mut map = HashMap::new();
         for i in 0..max_queue_nums {
@@ -483,31 +628,15 @@ impl<MS: MessageStore> TopicRequestHandler<MS> {
             );
             message_queue.set_queue_id(i as i32);
             let mut topic_offset = TopicOffset::new();
-            let min = std::cmp::max(
-                self.broker_runtime_inner
-                    .message_store()
-                    .as_ref()
-                    .unwrap()
-                    .get_min_offset_in_queue(topic, i as i32),
-                0,
-            );
-            let max = std::cmp::max(
-                self.broker_runtime_inner
-                    .message_store()
-                    .as_ref()
-                    .unwrap()
-                    .get_max_offset_in_queue(topic, i as i32),
-                0,
-            );
-            let mut timestamp = 0;
-            if max > 0 {
-                timestamp = self
-                    .broker_runtime_inner
-                    .message_store()
-                    .as_ref()
-                    .unwrap()
-                    .get_message_store_timestamp(topic, i as i32, max - 1);
-            }
+            let min = message_store
+                .get_min_offset_in_queue(topic, i as i32)
+                .max(0);
+            let max = message_store
+                .get_max_offset_in_queue(topic, i as i32)
+                .max(0);
+            let timestamp = message_store
+                .get_latest_message_time_in_queue(topic, i as i32)
+                .max(0);
             topic_offset.set_min_offset(min);
             topic_offset.set_max_offset(max);
             topic_offset.set_last_update_timestamp(timestamp);
@@ -636,6 +765,17 @@ impl<MS: MessageStore> TopicRequestHandler<MS> {
         self.broker_runtime_inner
             .pop_inflight_message_counter()
             .clear_in_flight_message_num_by_topic_name(topic);
+        self.broker_runtime_inner
+            .consumer_filter_manager()
+            .unregister_topic(topic);
+        self.broker_runtime_inner
+            .consumer_order_info_manager()
+            .remove_topic(topic);
+        if let Some(pop_message_processor) = self.broker_runtime_inner.pop_message_processor() {
+            pop_message_processor
+                .pop_buffer_merge_service()
+                .clear_topic(topic);
+        }
         self.broker_runtime_inner
             .message_store_mut()
             .as_mut()
@@ -313,7 +313,7 @@ where
             message_decoder::message_properties_to_string(inner.get_properties());
         let result = self
             .broker_runtime_inner
-            .escape_bridge_mut()
+            .escape_bridge()
             .put_message_to_specific_queue(inner)
             .await;
         match result.put_message_status() {
@@ -381,7 +381,7 @@ where
             message_decoder::message_properties_to_string(inner.get_properties());
         let put_message_result = self
             .broker_runtime_inner
-            .escape_bridge_mut()
+            .escape_bridge()
             .put_message_to_specific_queue(inner)
             .await;
         if self.broker_runtime_inner.broker_config().enable_pop_log {
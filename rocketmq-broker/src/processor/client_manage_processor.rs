@@ -123,8 +123,20 @@ where
                 .unregister_producer(group, &client_channel_info, &ctx);
         }
 
-        if let Some(ref _group) = request_header.consumer_group {
-            unimplemented!()
+        if let Some(ref group) = request_header.consumer_group {
+            let is_notify_consumer_ids_changed_enable = self
+                .broker_runtime_inner
+                .subscription_group_manager()
+                .find_subscription_group_config(group)
+                .map(|config| config.notify_consumer_ids_changed_enable())
+                .unwrap_or(true);
+            self.broker_runtime_inner
+                .consumer_manager()
+                .unregister_consumer(
+                    group,
+                    &client_channel_info,
+                    is_notify_consumer_ids_changed_enable,
+                );
         }
 
         Some(RemotingCommand::create_response_command())
@@ -214,6 +226,12 @@ where
                     consumer_data.subscription_data_set.clone(),
                     is_notify_consumer_ids_changed_enable,
                 );
+            self.broker_runtime_inner
+                .consumer_filter_manager()
+                .register_group(
+                    &consumer_data.group_name,
+                    &consumer_data.subscription_data_set,
+                );
             if changed {
                 info!(
                     "ClientManageProcessor: registerConsumer info changed, SDK address={}, \
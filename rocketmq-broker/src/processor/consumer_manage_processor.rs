@@ -226,7 +226,7 @@ where
             && self
                 .broker_runtime_inner
                 .consumer_offset_manager()
-                .has_offset_reset(topic, group, queue_id)
+                .has_offset_reset(group, topic, queue_id)
         {
             info!(
                 "Update consumer offset is rejected because of previous offset-reset. \
@@ -235,15 +235,25 @@ where
             );
             return Some(response.set_remark("Offset has been previously reset"));
         }
-        self.broker_runtime_inner
+        if !self
+            .broker_runtime_inner
             .consumer_offset_manager()
-            .commit_offset(
+            .commit_client_offset(
                 channel.remote_address().to_string().into(),
                 group,
                 topic,
                 queue_id,
                 offset,
-            );
+            )
+        {
+            return Some(RemotingCommand::create_error_response(
+                ResponseCode::SystemError,
+                format!(
+                    "offset {} of queue {} rejected because it rolls the committed offset back",
+                    offset, queue_id
+                ),
+            ));
+        }
         Some(response)
     }
 
@@ -284,7 +294,13 @@ where
                 .as_ref()
                 .unwrap()
                 .get_min_offset_in_queue(request_header.topic.as_ref(), request_header.queue_id);
-            if let Some(value) = request_header.set_zero_if_not_found {
+            if self
+                .broker_runtime_inner
+                .subscription_group_manager()
+                .consume_from_min_enable(&request_header.consumer_group)
+            {
+                response_header.offset = Some(min_offset);
+            } else if let Some(value) = request_header.set_zero_if_not_found {
                 if !value {
                     response = response
                         .set_code(ResponseCode::QueryNotFound)
@@ -23,7 +23,10 @@ use bytes::BytesMut;
 use cheetah_string::CheetahString;
 use rocketmq_common::common::broker::broker_config::BrokerConfig;
 use rocketmq_common::common::broker::broker_role::BrokerRole;
+use rocketmq_common::common::message::message_decoder;
 use rocketmq_common::common::message::message_queue::MessageQueue;
+use rocketmq_common::common::message::MessageConst;
+use rocketmq_common::common::message::MessageTrait;
 use rocketmq_common::common::mix_all::MASTER_ID;
 use rocketmq_common::common::sys_flag::pull_sys_flag::PullSysFlag;
 use rocketmq_common::TimeUtils::get_current_millis;
@@ -45,7 +48,6 @@ use rocketmq_store::base::get_message_result::GetMessageResult;
 use rocketmq_store::base::message_status_enum::GetMessageStatus;
 use rocketmq_store::filter::MessageFilter;
 use rocketmq_store::log_file::MessageStore;
-use rocketmq_store::message_store::default_message_store::DefaultMessageStore;
 use rocketmq_store::stats::broker_stats_manager::BrokerStatsManager;
 use rocketmq_store::stats::stats_type::StatsType;
 use tracing::debug;
@@ -53,12 +55,14 @@ use tracing::info;
 use tracing::warn;
 
 use crate::broker_runtime::BrokerRuntimeInner;
+use crate::long_polling::adaptive_suspend::adaptive_suspend_millis;
 use crate::long_polling::pull_request::PullRequest;
 use crate::mqtrace::consume_message_context::ConsumeMessageContext;
 use crate::mqtrace::consume_message_hook::ConsumeMessageHook;
 use crate::processor::pull_message_processor::is_broadcast;
 use crate::processor::pull_message_processor::rewrite_response_for_static_topic;
 use crate::processor::pull_message_result_handler::PullMessageResultHandler;
+use crate::util::response_compression::compress_response_body;
 
 pub struct DefaultPullMessageResultHandler<MS> {
     /*    topic_config_manager: Arc<TopicConfigManager>,
@@ -103,7 +107,7 @@ impl<MS> DefaultPullMessageResultHandler<MS> {
     pub fn set_pull_request_hold_service(
         &mut self,
         //pull_request_hold_service: Option<ArcMut<PullRequestHoldService<DefaultMessageStore>>>,
-        _inner: ArcMut<BrokerRuntimeInner<DefaultMessageStore>>,
+        _inner: ArcMut<BrokerRuntimeInner<MS>>,
     ) {
         //self.pull_request_hold_service = pull_request_hold_service;
     }
@@ -131,13 +135,17 @@ impl<MS: MessageStore> PullMessageResultHandler for DefaultPullMessageResultHand
             .topic_config_manager()
             .select_topic_config(request_header.topic.as_ref());
         Self::compose_response_header(
-            &Arc::new(self.broker_runtime_inner.broker_config().clone()), //need optimization
+            &self.broker_runtime_inner.broker_config(),
             &request_header,
             &get_message_result,
             topic_config.as_ref().unwrap().topic_sys_flag as i32,
             &subscription_group_config,
             &mut response,
             client_address.as_str(),
+            self.broker_runtime_inner
+                .message_store()
+                .as_ref()
+                .map_or(0, |message_store| message_store.dispatch_behind_bytes()),
         );
         let code = From::from(response.code());
         self.execute_consume_message_hook_before(
@@ -146,6 +154,7 @@ impl<MS: MessageStore> PullMessageResultHandler for DefaultPullMessageResultHand
             &get_message_result,
             broker_allow_suspend,
             code,
+            client_address.as_str(),
         );
         {
             let response_header = response
@@ -199,19 +208,54 @@ impl<MS: MessageStore> PullMessageResultHandler for DefaultPullMessageResultHand
                         request_header.topic.as_str(),
                         get_message_result.message_count(),
                     );
+                self.broker_runtime_inner
+                    .broker_stats_manager()
+                    .inc_queue_get_nums(
+                        request_header.consumer_group.as_str(),
+                        request_header.topic.as_str(),
+                        request_header.queue_id,
+                        get_message_result.message_count(),
+                    );
+                self.broker_runtime_inner
+                    .broker_stats_manager()
+                    .inc_queue_get_size(
+                        request_header.consumer_group.as_str(),
+                        request_header.topic.as_str(),
+                        request_header.queue_id,
+                        get_message_result.buffer_total_size(),
+                    );
 
                 if self
                     .broker_runtime_inner
                     .broker_config()
                     .transfer_msg_by_heap
                 {
+                    let begin_time_millis = get_current_millis();
                     let body = self.read_get_message_result(
                         &get_message_result,
                         request_header.consumer_group.as_str(),
                         request_header.topic.as_str(),
                         request_header.queue_id,
                     );
+                    self.broker_runtime_inner
+                        .broker_stats_manager()
+                        .inc_group_get_latency(
+                            request_header.consumer_group.as_str(),
+                            request_header.topic.as_str(),
+                            request_header.queue_id,
+                            (get_current_millis() - begin_time_millis) as i32,
+                        );
                     if let Some(body) = body {
+                        let (body, body_compression_type) = compress_response_body(
+                            &self.broker_runtime_inner.broker_config(),
+                            request_header.accept_compression,
+                            body,
+                        );
+                        if let Some(response_header) =
+                            response.read_custom_header_mut::<PullMessageResponseHeader>()
+                        {
+                            response_header.body_compression_type = body_compression_type;
+                        }
                         response.set_body_mut_ref(body);
                     }
                     Some(response)
@@ -228,17 +272,21 @@ impl<MS: MessageStore> PullMessageResultHandler for DefaultPullMessageResultHand
                     0
                 };
                 if broker_allow_suspend && has_suspend_flag {
-                    let mut polling_time_mills = suspend_timeout_millis_long;
-                    if !self
-                        .broker_runtime_inner
-                        .broker_config()
-                        .long_polling_enable
-                    {
-                        polling_time_mills = self
+                    let broker_config = self.broker_runtime_inner.broker_config();
+                    let polling_time_mills = if broker_config.long_polling_enable {
+                        let hold_request_num = self
                             .broker_runtime_inner
-                            .broker_config()
-                            .short_polling_time_mills;
-                    }
+                            .pull_request_hold_service()
+                            .as_ref()
+                            .map_or(0, |service| service.hold_request_num());
+                        adaptive_suspend_millis(
+                            &broker_config,
+                            suspend_timeout_millis_long,
+                            hold_request_num,
+                        )
+                    } else {
+                        broker_config.short_polling_time_mills
+                    };
                     let topic = request_header.topic.as_str();
                     let queue_id = request_header.queue_id;
                     let offset = request_header.queue_offset;
@@ -339,9 +387,7 @@ impl<MS: MessageStore> DefaultPullMessageResultHandler<MS> {
         let mut bytes_mut =
             BytesMut::with_capacity(get_message_result.buffer_total_size() as usize);
         for msg in get_message_result.message_mapped_list() {
-            let data = &msg.mapped_file.as_ref().unwrap().get_mapped_file()
-                [msg.start_offset as usize..(msg.start_offset + msg.size as u64) as usize];
-            bytes_mut.extend_from_slice(data);
+            bytes_mut.extend_from_slice(msg.get_buffer());
         }
         Some(bytes_mut.freeze())
     }
@@ -353,6 +399,7 @@ impl<MS: MessageStore> DefaultPullMessageResultHandler<MS> {
         get_message_result: &GetMessageResult,
         broker_allow_suspend: bool,
         response_code: ResponseCode,
+        client_address: &str,
     ) {
         if self.has_consume_message_hook() {
             let ext_fields = request.get_ext_fields().unwrap();
@@ -381,9 +428,33 @@ impl<MS: MessageStore> DefaultPullMessageResultHandler<MS> {
             context.namespace = CheetahString::from_string(
                 NamespaceUtil::get_namespace_from_resource(&request_header.topic),
             );
+            context.client_host = CheetahString::from_slice(client_address);
+            context.store_host = self.broker_runtime_inner.get_broker_addr().clone();
 
             match response_code {
                 ResponseCode::Success => {
+                    for (result, queue_offset) in get_message_result
+                        .message_mapped_list()
+                        .iter()
+                        .zip(get_message_result.message_queue_offset())
+                    {
+                        let Some(mut bytes) = result.get_bytes() else {
+                            continue;
+                        };
+                        let Some(msg) =
+                            message_decoder::decode(&mut bytes, false, false, false, false, false)
+                        else {
+                            continue;
+                        };
+                        let msg_id = msg
+                            .get_property(&CheetahString::from_static_str(
+                                MessageConst::PROPERTY_UNIQ_CLIENT_MESSAGE_ID_KEYIDX,
+                            ))
+                            .unwrap_or_else(|| msg.msg_id().clone());
+                        context
+                            .message_ids
+                            .insert(msg_id.to_string(), *queue_offset as i64);
+                    }
                     let commercial_base_count = self
                         .broker_runtime_inner
                         .broker_config()
@@ -446,6 +517,7 @@ impl<MS: MessageStore> DefaultPullMessageResultHandler<MS> {
         subscription_group_config: &SubscriptionGroupConfig,
         response: &mut RemotingCommand,
         client_address: &str,
+        dispatch_behind_bytes: i64,
     ) {
         let mut response_header = PullMessageResponseHeader::default();
         response.set_remark_mut(format!("{:?}", get_message_result.status()));
@@ -513,29 +585,27 @@ impl<MS: MessageStore> DefaultPullMessageResultHandler<MS> {
             }
         }
 
-        if broker_config.slave_read_enable && !broker_config.is_in_broker_container {
-            if get_message_result.suggest_pulling_from_slave() {
-                response_header.suggest_which_broker_id =
-                    subscription_group_config.which_broker_when_consume_slowly();
-            } else {
-                response_header.suggest_which_broker_id = subscription_group_config.broker_id();
-            }
-        } else {
-            response_header.suggest_which_broker_id = MASTER_ID;
-        }
+        response_header.suggest_which_broker_id = suggest_which_broker_id(
+            broker_config,
+            subscription_group_config,
+            get_message_result.suggest_pulling_from_slave(),
+        );
 
-        if broker_config.broker_identity.broker_id != MASTER_ID
-            && !get_message_result.suggest_pulling_from_slave()
-        {
+        if should_redirect_to_master(
+            broker_config,
+            get_message_result.suggest_pulling_from_slave(),
+            dispatch_behind_bytes,
+        ) {
             debug!(
                 "slave redirect pullRequest to master, topic: {}, queueId: {}, consumer group: \
-                 {}, next: {}, min: {}, max: {}",
+                 {}, next: {}, min: {}, max: {}, dispatchBehindBytes: {}",
                 request_header.topic,
                 request_header.queue_id,
                 request_header.consumer_group,
                 response_header.next_begin_offset,
                 response_header.min_offset,
-                response_header.max_offset
+                response_header.max_offset,
+                dispatch_behind_bytes
             );
             response_header.suggest_which_broker_id = MASTER_ID;
             if get_message_result.status() != Some(GetMessageStatus::Found) {
@@ -569,7 +639,7 @@ impl<MS: MessageStore> DefaultPullMessageResultHandler<MS> {
         if store_offset_enable {
             self.broker_runtime_inner
                 .consumer_offset_manager()
-                .commit_offset(
+                .commit_client_offset(
                     client_address.to_string().into(),
                     request_header.consumer_group.as_ref(),
                     request_header.topic.as_ref(),
@@ -641,3 +711,80 @@ impl<MS: MessageStore> DefaultPullMessageResultHandler<MS> {
         }
     }
 }
+
+/// Broker the client should pull the queue from next: the configured slave when the consumer
+/// reads messages that are no longer in memory, the broker of the group otherwise.
+fn suggest_which_broker_id(
+    broker_config: &BrokerConfig,
+    subscription_group_config: &SubscriptionGroupConfig,
+    suggest_pulling_from_slave: bool,
+) -> u64 {
+    if !broker_config.slave_read_enable || broker_config.is_in_broker_container {
+        return MASTER_ID;
+    }
+    if suggest_pulling_from_slave {
+        subscription_group_config.which_broker_when_consume_slowly()
+    } else {
+        subscription_group_config.broker_id()
+    }
+}
+
+/// Whether a slave should send the consumer back to its master, either because the consumer
+/// caught up with the hot data or because the slave is too far behind in dispatching the
+/// replicated commit log to its consume queues.
+fn should_redirect_to_master(
+    broker_config: &BrokerConfig,
+    suggest_pulling_from_slave: bool,
+    dispatch_behind_bytes: i64,
+) -> bool {
+    if broker_config.broker_identity.broker_id == MASTER_ID {
+        return false;
+    }
+    !suggest_pulling_from_slave
+        || dispatch_behind_bytes > broker_config.slave_read_max_dispatch_behind_bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slave_config() -> BrokerConfig {
+        let mut broker_config = BrokerConfig {
+            slave_read_enable: true,
+            ..BrokerConfig::default()
+        };
+        broker_config.broker_identity.broker_id = 1;
+        broker_config
+    }
+
+    #[test]
+    fn suggests_master_unless_slave_read_is_enabled() {
+        let subscription_group_config = SubscriptionGroupConfig::default();
+        assert_eq!(
+            suggest_which_broker_id(&BrokerConfig::default(), &subscription_group_config, true),
+            MASTER_ID
+        );
+        assert_eq!(
+            suggest_which_broker_id(&slave_config(), &subscription_group_config, true),
+            subscription_group_config.which_broker_when_consume_slowly()
+        );
+        assert_eq!(
+            suggest_which_broker_id(&slave_config(), &subscription_group_config, false),
+            subscription_group_config.broker_id()
+        );
+    }
+
+    #[test]
+    fn slave_redirects_caught_up_or_lagging_reads() {
+        let broker_config = slave_config();
+        let limit = broker_config.slave_read_max_dispatch_behind_bytes;
+        assert!(!should_redirect_to_master(&broker_config, true, limit));
+        assert!(should_redirect_to_master(&broker_config, false, 0));
+        assert!(should_redirect_to_master(&broker_config, true, limit + 1));
+        assert!(!should_redirect_to_master(
+            &BrokerConfig::default(),
+            false,
+            i64::MAX
+        ));
+    }
+}
@@ -115,7 +115,8 @@ where
         {
             let result = self
                 .transactional_message_service
-                .commit_message(&request_header);
+                .commit_message(&request_header)
+                .await;
             if result.response_code == ResponseCode::Success {
                 if self.reject_commit_or_rollback(
                     request_header.from_transaction_check,
@@ -132,7 +133,7 @@ where
                 }
                 let res =
                     self.check_prepare_message(result.prepare_message.as_ref(), &request_header);
-                if ResponseCode::from(res.code()) != ResponseCode::Success {
+                if ResponseCode::from(res.code()) == ResponseCode::Success {
                     let mut msg_inner =
                         end_message_transaction(result.prepare_message.as_ref().unwrap());
                     msg_inner.message_ext_inner.sys_flag = MessageSysFlag::reset_transaction_value(
@@ -159,13 +160,13 @@ where
                     return Some(send_result);
                 }
                 return Some(res);
-            } else {
-                OperationResult::default()
             }
+            result
         } else if MessageSysFlag::TRANSACTION_ROLLBACK_TYPE == request_header.commit_or_rollback {
             let result = self
                 .transactional_message_service
-                .rollback_message(&request_header);
+                .rollback_message(&request_header)
+                .await;
             if result.response_code == ResponseCode::Success {
                 if self.reject_commit_or_rollback(
                     request_header.from_transaction_check,
@@ -15,26 +15,472 @@
  * limitations under the License.
  */
 
+use std::sync::Arc;
+
+use cheetah_string::CheetahString;
+use rand::Rng;
+use rocketmq_common::common::config::TopicConfig;
+use rocketmq_common::common::constant::PermName;
+use rocketmq_common::common::key_builder::KeyBuilder;
+use rocketmq_common::common::FAQUrl;
+use rocketmq_remoting::code::response_code::ResponseCode;
+use rocketmq_remoting::net::channel::Channel;
+use rocketmq_remoting::protocol::header::notification_request_header::NotificationRequestHeader;
+use rocketmq_remoting::protocol::header::notification_response_header::NotificationResponseHeader;
+use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
 use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
-use tracing::warn;
+use rocketmq_remoting::runtime::processor::RequestProcessor;
+use rocketmq_rust::ArcMut;
+use rocketmq_store::filter::MessageFilter;
+use rocketmq_store::log_file::MessageStore;
+
+use crate::broker_runtime::BrokerRuntimeInner;
+use crate::filter::expression_message_filter::ExpressionMessageFilter;
+use crate::long_polling::long_polling_service::pop_long_polling_service::PopLongPollingService;
+use crate::long_polling::polling_header::PollingHeader;
+use crate::long_polling::polling_result::PollingResult;
+
+/// Answers whether a consumer group has messages to pop from a topic, holding the request until
+/// a message arrives or the poll time runs out when there is none.
+pub struct NotificationProcessor<MS> {
+    pop_long_polling_service: ArcMut<PopLongPollingService<MS, NotificationProcessor<MS>>>,
+    broker_runtime_inner: ArcMut<BrokerRuntimeInner<MS>>,
+}
 
-#[derive(Default)]
-pub struct NotificationProcessor {}
+impl<MS: MessageStore> NotificationProcessor<MS> {
+    pub fn new_arc_mut(broker_runtime_inner: ArcMut<BrokerRuntimeInner<MS>>) -> ArcMut<Self> {
+        let processor = NotificationProcessor {
+            pop_long_polling_service: ArcMut::new(PopLongPollingService::new(
+                broker_runtime_inner.clone(),
+                true,
+            )),
+            broker_runtime_inner,
+        };
+        let mut processor = ArcMut::new(processor);
+        let cloned = processor.clone();
+        processor.pop_long_polling_service.set_processor(cloned);
+        processor
+    }
 
-impl NotificationProcessor {
     pub fn start(&mut self) {
-        warn!("NotificationProcessor started unimplemented, need to implement it");
+        PopLongPollingService::start(self.pop_long_polling_service.clone());
     }
 
-    fn process_request(
+    pub fn shutdown(&mut self) {
+        self.pop_long_polling_service.shutdown();
+    }
+
+    /// Wakes the notification requests waiting for messages of `topic`.
+    pub fn notify_message_arriving(&self, topic: &CheetahString, queue_id: i32) {
+        self.pop_long_polling_service
+            .notify_message_arriving_with_retry_topic(topic, queue_id, None, 0, None, None);
+    }
+
+    fn process_notification(
         &self,
-        _ctx: ConnectionHandlerContext,
-        _request: rocketmq_remoting::protocol::remoting_command::RemotingCommand,
-    ) -> rocketmq_remoting::protocol::remoting_command::RemotingCommand {
-        todo!()
+        channel: &Channel,
+        ctx: ConnectionHandlerContext,
+        request: RemotingCommand,
+    ) -> rocketmq_remoting::Result<Option<RemotingCommand>> {
+        let request_header = request.decode_command_custom_header::<NotificationRequestHeader>()?;
+        let broker_config = self.broker_runtime_inner.broker_config();
+        if !PermName::is_readable(broker_config.broker_permission) {
+            return Ok(Some(
+                RemotingCommand::create_response_command_with_code_remark(
+                    ResponseCode::NoPermission,
+                    format!(
+                        "the broker[{}] peeking message is forbidden",
+                        broker_config.broker_ip1
+                    ),
+                ),
+            ));
+        }
+        let Some(topic_config) = self
+            .broker_runtime_inner
+            .topic_config_manager()
+            .select_topic_config(&request_header.topic)
+        else {
+            return Ok(Some(
+                RemotingCommand::create_response_command_with_code_remark(
+                    ResponseCode::TopicNotExist,
+                    format!(
+                        "topic[{}] not exist, apply first please! {}",
+                        request_header.topic,
+                        FAQUrl::suggest_todo(FAQUrl::APPLY_TOPIC_URL)
+                    ),
+                ),
+            ));
+        };
+        if !PermName::is_readable(topic_config.perm) {
+            return Ok(Some(
+                RemotingCommand::create_response_command_with_code_remark(
+                    ResponseCode::NoPermission,
+                    format!(
+                        "the topic[{}] peeking message is forbidden",
+                        request_header.topic
+                    ),
+                ),
+            ));
+        }
+        if request_header.queue_id >= topic_config.read_queue_nums as i32 {
+            return Ok(Some(
+                RemotingCommand::create_response_command_with_code_remark(
+                    ResponseCode::SystemError,
+                    format!(
+                        "queueId[{}] is illegal, topic:[{}] topicConfig.readQueueNums:[{}] \
+                         consumer:[{}]",
+                        request_header.queue_id,
+                        request_header.topic,
+                        topic_config.read_queue_nums,
+                        channel.remote_address()
+                    ),
+                ),
+            ));
+        }
+        let Some(subscription_group_config) = self
+            .broker_runtime_inner
+            .subscription_group_manager()
+            .find_subscription_group_config(&request_header.consumer_group)
+        else {
+            return Ok(Some(
+                RemotingCommand::create_response_command_with_code_remark(
+                    ResponseCode::SubscriptionGroupNotExist,
+                    format!(
+                        "subscription group [{}] does not exist, {}",
+                        request_header.consumer_group,
+                        FAQUrl::suggest_todo(FAQUrl::SUBSCRIPTION_GROUP_NOT_EXIST)
+                    ),
+                ),
+            ));
+        };
+        if !subscription_group_config.consume_enable() {
+            return Ok(Some(
+                RemotingCommand::create_response_command_with_code_remark(
+                    ResponseCode::NoPermission,
+                    format!(
+                        "subscription group no permission, {}",
+                        request_header.consumer_group
+                    ),
+                ),
+            ));
+        }
+
+        let random_q = rand::thread_rng().gen_range(0..100);
+        let need_retry = random_q % 5 == 0;
+        let retry_topic_config = self
+            .broker_runtime_inner
+            .topic_config_manager()
+            .select_topic_config(&CheetahString::from_string(
+                KeyBuilder::build_pop_retry_topic(
+                    &request_header.topic,
+                    &request_header.consumer_group,
+                    broker_config.enable_retry_topic_v2,
+                ),
+            ));
+        let mut has_msg = false;
+        if need_retry {
+            if let Some(retry_topic_config) = retry_topic_config.as_ref() {
+                has_msg = self.has_msg_from_topic(retry_topic_config, random_q, &request_header);
+            }
+        }
+        if !has_msg {
+            has_msg = if request_header.queue_id < 0 {
+                self.has_msg_from_topic(&topic_config, random_q, &request_header)
+            } else {
+                self.has_msg_from_queue(
+                    &request_header.topic,
+                    &request_header,
+                    request_header.queue_id,
+                )
+            };
+            if !has_msg && !need_retry {
+                if let Some(retry_topic_config) = retry_topic_config.as_ref() {
+                    has_msg =
+                        self.has_msg_from_topic(retry_topic_config, random_q, &request_header);
+                }
+            }
+        }
+
+        let mut response_header = NotificationResponseHeader {
+            has_msg,
+            polling_full: false,
+        };
+        if !has_msg {
+            // a notification takes every message, the filter only satisfies the polling service
+            let message_filter: Box<dyn MessageFilter> = Box::new(ExpressionMessageFilter::new(
+                None,
+                None,
+                Arc::new(self.broker_runtime_inner.consumer_filter_manager().clone()),
+            ));
+            match self.pop_long_polling_service.polling(
+                ctx,
+                request,
+                PollingHeader::new_from_notification_request_header(&request_header),
+                Default::default(),
+                Some(Arc::new(message_filter)),
+            ) {
+                PollingResult::PollingSuc => return Ok(None),
+                PollingResult::PollingFull => response_header.polling_full = true,
+                _ => {}
+            }
+        }
+        Ok(Some(
+            RemotingCommand::create_response_command().set_command_custom_header(response_header),
+        ))
     }
 
-    pub fn shutdown(&mut self) {
-        warn!("NotificationProcessor shutdown unimplemented, need to implement it");
+    fn has_msg_from_topic(
+        &self,
+        topic_config: &TopicConfig,
+        random_q: i32,
+        request_header: &NotificationRequestHeader,
+    ) -> bool {
+        let Some(topic_name) = topic_config.topic_name.as_ref() else {
+            return false;
+        };
+        let read_queue_nums = topic_config.read_queue_nums as i32;
+        (0..read_queue_nums).any(|i| {
+            self.has_msg_from_queue(topic_name, request_header, (random_q + i) % read_queue_nums)
+        })
+    }
+
+    fn has_msg_from_queue(
+        &self,
+        target_topic: &CheetahString,
+        request_header: &NotificationRequestHeader,
+        queue_id: i32,
+    ) -> bool {
+        let Some(message_store) = self.broker_runtime_inner.message_store() else {
+            return false;
+        };
+        let offset = self.get_pop_offset(
+            message_store,
+            target_topic,
+            &request_header.consumer_group,
+            queue_id,
+        );
+        message_store.get_max_offset_in_queue(target_topic, queue_id) - offset > 0
+    }
+
+    fn get_pop_offset(
+        &self,
+        message_store: &MS,
+        topic: &CheetahString,
+        group: &CheetahString,
+        queue_id: i32,
+    ) -> i64 {
+        let mut offset = self
+            .broker_runtime_inner
+            .consumer_offset_manager()
+            .query_offset(group, topic, queue_id);
+        if offset < 0 {
+            offset = message_store.get_min_offset_in_queue(topic, queue_id);
+        }
+        let buffer_offset = self
+            .broker_runtime_inner
+            .pop_message_processor()
+            .as_ref()
+            .map_or(-1, |pop_message_processor| {
+                pop_message_processor
+                    .pop_buffer_merge_service()
+                    .get_latest_offset_full(topic, group, queue_id)
+            });
+        offset.max(buffer_offset)
+    }
+}
+
+impl<MS: MessageStore> RequestProcessor for NotificationProcessor<MS> {
+    async fn process_request(
+        &mut self,
+        channel: Channel,
+        ctx: ConnectionHandlerContext,
+        request: RemotingCommand,
+    ) -> rocketmq_remoting::Result<Option<RemotingCommand>> {
+        self.process_notification(&channel, ctx, request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    use bytes::Bytes;
+    use rocketmq_common::common::broker::broker_config::BrokerConfig;
+    use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+    use rocketmq_common::common::message::MessageTrait;
+    use rocketmq_common::common::server::config::ServerConfig;
+    use rocketmq_common::TimeUtils::get_current_millis;
+    use rocketmq_remoting::code::request_code::RequestCode;
+    use rocketmq_remoting::connection::Connection;
+    use rocketmq_remoting::protocol::subscription::subscription_group_config::SubscriptionGroupConfig;
+    use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContextWrapper;
+    use rocketmq_store::base::message_status_enum::PutMessageStatus;
+    use rocketmq_store::config::message_store_config::MessageStoreConfig;
+    use rocketmq_store::message_store::boxed_message_store::BoxedMessageStore;
+    use tokio::net::TcpListener;
+    use tokio::net::TcpStream;
+
+    use super::*;
+    use crate::broker_runtime::BrokerRuntime;
+
+    const TOPIC: &str = "NotificationTopic";
+    const GROUP: &str = "NotificationGroup";
+
+    fn started_broker(
+        runtime: &tokio::runtime::Runtime,
+        store_dir: &tempfile::TempDir,
+    ) -> ArcMut<BrokerRuntimeInner<BoxedMessageStore>> {
+        let store_path_root_dir = CheetahString::from(store_dir.path().to_string_lossy().as_ref());
+        let ha_listen_port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port() as usize;
+        let mut broker = BrokerRuntime::new(
+            BrokerConfig {
+                store_path_root_dir: store_path_root_dir.clone(),
+                ..Default::default()
+            },
+            MessageStoreConfig {
+                store_path_root_dir,
+                mapped_file_size_commit_log: 1024 * 1024,
+                mapped_file_size_consume_queue: 20 * 1024,
+                ha_listen_port,
+                ..Default::default()
+            },
+            ServerConfig::default(),
+        );
+        assert!(runtime.block_on(broker.initialize()));
+        // sets up the notification processor and the message arriving listener
+        broker.init_processor();
+        let mut inner = broker.inner().clone();
+        inner
+            .message_store()
+            .as_ref()
+            .unwrap()
+            .mut_from_ref()
+            .start()
+            .unwrap();
+        inner
+            .topic_config_manager_mut()
+            .update_topic_config(&mut TopicConfig::with_queues(TOPIC, 1, 1))
+            .unwrap();
+        inner
+            .subscription_group_manager()
+            .update_subscription_group_config(SubscriptionGroupConfig::new(GROUP.into()));
+        inner
+    }
+
+    fn notification_request(poll_time: i64) -> RemotingCommand {
+        let mut request = RemotingCommand::create_request_command(
+            RequestCode::Notification,
+            NotificationRequestHeader {
+                consumer_group: GROUP.into(),
+                topic: TOPIC.into(),
+                queue_id: 0,
+                poll_time,
+                born_time: get_current_millis() as i64,
+                order: false,
+                attempt_id: None,
+                topic_request_header: None,
+            },
+        )
+        .set_opaque(7);
+        request.make_custom_header_to_net();
+        request
+    }
+
+    #[test]
+    fn a_waiting_notification_is_woken_by_an_arriving_message() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let _guard = runtime.enter();
+        let store_dir = tempfile::tempdir().unwrap();
+        let inner = started_broker(&runtime, &store_dir);
+
+        runtime.block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let stream = TcpStream::connect(listener.local_addr().unwrap())
+                .await
+                .unwrap();
+            let (peer, _) = listener.accept().await.unwrap();
+            let channel = Channel::new(
+                stream.local_addr().unwrap(),
+                stream.peer_addr().unwrap(),
+                Connection::new(stream),
+                ArcMut::new(HashMap::new()),
+            );
+            let ctx = ArcMut::new(ConnectionHandlerContextWrapper::new(channel.clone()));
+            let mut processor = inner.notification_processor().clone().unwrap();
+
+            // nothing to pop yet, the request is held
+            let response = processor
+                .process_request(channel, ctx, notification_request(10_000))
+                .await
+                .unwrap();
+            assert!(response.is_none());
+
+            let mut message = MessageExtBrokerInner::default();
+            message.set_topic(CheetahString::from_static_str(TOPIC));
+            message.set_body(Bytes::from_static(b"arrived"));
+            message.message_ext_inner.born_host = inner.store_host();
+            message.message_ext_inner.store_host = inner.store_host();
+            let result = inner
+                .message_store()
+                .as_ref()
+                .unwrap()
+                .mut_from_ref()
+                .put_message(message)
+                .await;
+            assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+
+            let mut peer = Connection::new(peer);
+            let response = tokio::time::timeout(Duration::from_secs(10), peer.receive_command())
+                .await
+                .expect("the held notification was not woken")
+                .unwrap()
+                .unwrap();
+            assert_eq!(response.opaque(), 7);
+            let response_header = response
+                .decode_command_custom_header::<NotificationResponseHeader>()
+                .unwrap();
+            assert!(response_header.has_msg);
+        });
+    }
+
+    #[test]
+    fn a_notification_is_answered_at_once_when_messages_are_there() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let _guard = runtime.enter();
+        let store_dir = tempfile::tempdir().unwrap();
+        let inner = started_broker(&runtime, &store_dir);
+
+        runtime.block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let stream = TcpStream::connect(listener.local_addr().unwrap())
+                .await
+                .unwrap();
+            let channel = Channel::new(
+                stream.local_addr().unwrap(),
+                stream.peer_addr().unwrap(),
+                Connection::new(stream),
+                ArcMut::new(HashMap::new()),
+            );
+            let ctx = ArcMut::new(ConnectionHandlerContextWrapper::new(channel.clone()));
+            let mut processor = inner.notification_processor().clone().unwrap();
+
+            // without a poll time the request is answered at once
+            let mut response = processor
+                .process_request(channel, ctx, notification_request(0))
+                .await
+                .unwrap()
+                .unwrap();
+            response.make_custom_header_to_net();
+            let response_header = response
+                .decode_command_custom_header::<NotificationResponseHeader>()
+                .unwrap();
+            assert!(!response_header.has_msg);
+            assert!(!response_header.polling_full);
+        });
     }
 }
@@ -185,6 +185,18 @@ impl PopInflightMessageCounter {
         0
     }
 
+    /// Sums the in-flight message number of `group` over all the topics and queues it pops from.
+    pub fn get_group_pop_in_flight_message_total(&self, group: &CheetahString) -> i64 {
+        let map = self.topic_in_flight_message_num.lock();
+        map.iter()
+            .filter(|(key, _)| {
+                Self::split_key(key).is_some_and(|(_, group_name)| &group_name == group)
+            })
+            .flat_map(|(_, queue_counter)| queue_counter.values())
+            .map(|counter| counter.load(Ordering::SeqCst).max(0))
+            .sum()
+    }
+
     fn split_key(key: &CheetahString) -> Option<(CheetahString, CheetahString)> {
         let parts: Vec<&str> = key.split(Self::TOPIC_GROUP_SEPARATOR).collect();
         if parts.len() == 2 {
@@ -301,4 +313,26 @@ mod tests {
             4
         );
     }
+
+    #[test]
+    fn get_group_pop_in_flight_message_total_sums_topics_and_queues() {
+        let counter = setup_counter();
+        let topic_a = CheetahString::from("topic_a");
+        let topic_b = CheetahString::from("topic_b");
+        let group = CheetahString::from("test_group");
+        let other_group = CheetahString::from("other_group");
+        counter.increment_in_flight_message_num(&topic_a, &group, 0, 3);
+        counter.increment_in_flight_message_num(&topic_a, &group, 1, 4);
+        counter.increment_in_flight_message_num(&topic_b, &group, 0, 5);
+        counter.increment_in_flight_message_num(&topic_a, &other_group, 0, 7);
+        assert_eq!(counter.get_group_pop_in_flight_message_total(&group), 12);
+        assert_eq!(
+            counter.get_group_pop_in_flight_message_total(&other_group),
+            7
+        );
+        assert_eq!(
+            counter.get_group_pop_in_flight_message_total(&CheetahString::from("none")),
+            0
+        );
+    }
 }
@@ -17,12 +17,14 @@
 #![allow(unused_variables)]
 
 use std::collections::HashMap;
+use std::collections::VecDeque;
 use std::net::SocketAddr;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::AtomicI64;
 use std::sync::atomic::AtomicU64;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::time::Duration;
 
 use bytes::Bytes;
 use bytes::BytesMut;
@@ -66,6 +68,7 @@ use rocketmq_store::log_file::MessageStore;
 use rocketmq_store::pop::batch_ack_msg::BatchAckMsg;
 use rocketmq_store::pop::pop_check_point::PopCheckPoint;
 use rocketmq_store::pop::AckMessage;
+use tokio::sync::oneshot;
 use tokio::sync::Mutex;
 use tracing::info;
 use tracing::warn;
@@ -78,6 +81,7 @@ use crate::long_polling::long_polling_service::pop_long_polling_service::PopLong
 use crate::long_polling::polling_header::PollingHeader;
 use crate::long_polling::polling_result::PollingResult;
 use crate::processor::processor_service::pop_buffer_merge_service::PopBufferMergeService;
+use crate::util::response_compression::compress_response_body;
 
 const BORN_TIME: &str = "bornTime";
 
@@ -217,15 +221,13 @@ where
             );
 
         if request_header.is_timeout_too_much() {
-            return Ok(Some(
-                RemotingCommand::create_response_command_with_code_remark(
-                    ResponseCode::PollingTimeout,
-                    format!(
-                        "the broker[{}] pop message is timeout too much",
-                        self.broker_runtime_inner.broker_config().broker_ip1
-                    ),
+            return Ok(Some(RemotingCommand::create_error_response(
+                ResponseCode::PollingTimeout,
+                format!(
+                    "the broker[{}] pop message is timeout too much",
+                    self.broker_runtime_inner.broker_config().broker_ip1
                 ),
-            ));
+            )));
         }
 
         if !PermName::is_readable(self.broker_runtime_inner.broker_config().broker_permission) {
@@ -338,6 +340,34 @@ where
                 ),
             ));
         }
+        if self
+            .broker_runtime_inner
+            .subscription_group_manager()
+            .get_forbidden(
+                subscription_group_config.group_name(),
+                request_header.topic.as_str(),
+                PermName::INDEX_PERM_READ as i32,
+            )
+        {
+            return Ok(Some(
+                RemotingCommand::create_response_command_with_code_remark(
+                    ResponseCode::NoPermission,
+                    format!(
+                        "the consumer group[{}] is forbidden for topic[{}]",
+                        request_header.consumer_group, request_header.topic
+                    ),
+                ),
+            ));
+        }
+        if self.is_group_inflight_full(&request_header.consumer_group) {
+            return Ok(Some(RemotingCommand::create_error_response(
+                ResponseCode::PollingFull,
+                format!(
+                    "the consumer group[{}] has too many inflight messages, ack them first",
+                    request_header.consumer_group
+                ),
+            )));
+        }
 
         let exp = request_header.exp.as_ref();
 
@@ -702,9 +732,14 @@ where
                 }
                 PollingResult::PollingFull => {
                     final_response.set_code_ref(ResponseCode::PollingFull);
+                    final_response.set_remark_mut(
+                        ResponseCode::PollingFull.remark("too many pop requests are held"),
+                    );
                 }
                 _ => {
                     final_response.set_code_ref(ResponseCode::PollingTimeout);
+                    final_response
+                        .set_remark_mut(ResponseCode::PollingTimeout.remark("no new message"));
                 }
             }
             get_message_result.set_status(Some(GetMessageStatus::NoMessageInQueue));
@@ -717,8 +752,10 @@ where
             start_offset_info: Some(CheetahString::from_string(start_offset_info)),
             msg_offset_info: Some(CheetahString::from_string(msg_offset_info)),
             order_count_info: Some(CheetahString::from_string(order_count_info)),
+            body_compression_type: None,
         };
         final_response.set_remark_mut(get_message_result.status().unwrap().to_string());
+        final_response.set_command_custom_header_ref(response_header);
 
         match ResponseCode::from(final_response.code()) {
             ResponseCode::Success => {
@@ -733,6 +770,16 @@ where
                         &request_header.topic,
                         request_header.queue_id,
                     ) {
+                        let (bytes, body_compression_type) = compress_response_body(
+                            &self.broker_runtime_inner.broker_config(),
+                            request_header.accept_compression,
+                            bytes,
+                        );
+                        if let Some(response_header) =
+                            final_response.read_custom_header_mut::<PopMessageResponseHeader>()
+                        {
+                            response_header.body_compression_type = body_compression_type;
+                        }
                         final_response.set_body_mut_ref(bytes);
                     }
                     Ok(Some(final_response))
@@ -761,11 +808,33 @@ where
         random_q: i32,
         mut rest_num: i64,
     ) -> i64 {
-        for index in 0..topic_config.read_queue_nums {
-            let queue_id = (random_q + index as i32) % topic_config.read_queue_nums as i32;
+        let topic = topic_config.topic_name.clone().unwrap_or_default();
+        let queue_ids = (0..topic_config.read_queue_nums)
+            .map(|index| (random_q + index as i32) % topic_config.read_queue_nums as i32)
+            .collect::<Vec<_>>();
+        let mut readahead = if queue_ids.len() > 1
+            && self
+                .broker_runtime_inner
+                .broker_config()
+                .enable_pop_readahead
+        {
+            self.readahead_queues(
+                &topic,
+                request_header,
+                &queue_ids,
+                request_header.max_msg_nums as i32
+                    - get_message_result.message_mapped_list().len() as i32,
+                message_filter.clone(),
+            )
+            .await
+        } else {
+            Vec::new()
+        };
+        for (index, queue_id) in queue_ids.into_iter().enumerate() {
+            let mut prefetched = readahead.get_mut(index).and_then(Option::take);
             rest_num = self
                 .pop_msg_from_queue(
-                    &topic_config.topic_name.clone().unwrap_or_default(),
+                    &topic,
                     &request_header.consumer_group,
                     is_retry,
                     get_message_result.clone(),
@@ -779,12 +848,94 @@ where
                     start_offset_info,
                     msg_offset_info,
                     order_count_info,
+                    &mut prefetched,
                 )
                 .await;
+            if let Some((_, unused)) = prefetched {
+                release_message_result(unused);
+            }
         }
         rest_num
     }
 
+    /// Reads `queue_ids` of `topic` concurrently from the offsets the pop would start at.
+    ///
+    /// The results are kept in queue order until together they hold `max_msg_nums` messages or
+    /// `popReadaheadMaxBytes` bytes, the reads after that are released right away and their queues
+    /// are read again when the pop reaches them.
+    async fn readahead_queues(
+        &self,
+        topic: &CheetahString,
+        request_header: &PopMessageRequestHeader,
+        queue_ids: &[i32],
+        max_msg_nums: i32,
+        message_filter: Option<Arc<Box<dyn MessageFilter>>>,
+    ) -> Vec<Option<(i64, GetMessageResult)>> {
+        if max_msg_nums <= 0 {
+            return Vec::new();
+        }
+        let message_store = self.broker_runtime_inner.message_store().as_ref().unwrap();
+        let reads = queue_ids.iter().map(|&queue_id| {
+            let lock_key = CheetahString::from_string(format!(
+                "{}{}{}{}{}",
+                topic,
+                PopAckConstants::SPLIT,
+                request_header.consumer_group,
+                PopAckConstants::SPLIT,
+                queue_id
+            ));
+            let offset = self.get_pop_offset(
+                topic,
+                &request_header.consumer_group,
+                queue_id,
+                request_header.init_mode,
+                false,
+                &lock_key,
+                false,
+            );
+            let message_filter = message_filter.clone();
+            async move {
+                message_store
+                    .get_message(
+                        &request_header.consumer_group,
+                        topic,
+                        queue_id,
+                        offset,
+                        max_msg_nums,
+                        message_filter,
+                    )
+                    .await
+                    .map(|result| (offset, result))
+            }
+        });
+        let mut results = futures::future::join_all(reads).await;
+
+        let sizes = results
+            .iter()
+            .map(|slot| {
+                slot.as_ref().map(|(_, result)| {
+                    (
+                        result.message_mapped_list().len() as i64,
+                        result.buffer_total_size() as i64,
+                    )
+                })
+            })
+            .collect::<Vec<_>>();
+        let kept = readahead_reads_kept(
+            &sizes,
+            max_msg_nums as i64,
+            self.broker_runtime_inner
+                .broker_config()
+                .pop_readahead_max_bytes,
+        );
+        for slot in results.iter_mut().skip(kept) {
+            if let Some((_, result)) = slot.take() {
+                release_message_result(result);
+            }
+        }
+        results
+    }
+
     async fn pop_msg_from_topic_by_name(
         &self,
         topic: &CheetahString,
@@ -842,6 +993,7 @@ where
         start_offset_info: &mut String,
         msg_offset_info: &mut String,
         order_count_info: &mut str,
+        readahead: &mut Option<(i64, GetMessageResult)>,
     ) -> i64 {
         let lock_key = CheetahString::from_string(format!(
             "{}{}{}{}{}",
@@ -862,7 +1014,12 @@ where
         );
         if !self
             .queue_lock_manager()
-            .try_lock_with_key(lock_key.clone())
+            .lock_with_key_timeout(
+                lock_key.clone(),
+                self.broker_runtime_inner
+                    .broker_config()
+                    .pop_queue_lock_wait_millis,
+            )
             .await
         {
             return self
@@ -925,21 +1082,35 @@ where
                 - offset
                 + rest_num;
         }
-        let get_message_result_inner = self
-            .broker_runtime_inner
-            .message_store()
-            .as_ref()
-            .unwrap()
-            .get_message(
-                &request_header.consumer_group,
-                topic,
-                queue_id,
-                offset,
-                request_header.max_msg_nums as i32
-                    - get_message_result.message_mapped_list().len() as i32,
-                message_filter.clone(),
-            )
-            .await;
+        let rest_count =
+            request_header.max_msg_nums as usize - get_message_result.message_mapped_list().len();
+        let get_message_result_inner = match readahead.take() {
+            // the read ahead is only usable if nobody moved the offset since and it still fits
+            Some((readahead_offset, result))
+                if readahead_offset == offset
+                    && result.message_mapped_list().len() <= rest_count =>
+            {
+                Some(result)
+            }
+            stale => {
+                if let Some((_, result)) = stale {
+                    release_message_result(result);
+                }
+                self.broker_runtime_inner
+                    .message_store()
+                    .as_ref()
+                    .unwrap()
+                    .get_message(
+                        &request_header.consumer_group,
+                        topic,
+                        queue_id,
+                        offset,
+                        rest_count as i32,
+                        message_filter.clone(),
+                    )
+                    .await
+            }
+        };
         let atomic_rest_num = AtomicI64::new(rest_num);
         let atomic_offset = AtomicI64::new(offset);
         let final_offset = offset;
@@ -1009,6 +1180,20 @@ where
                         _ => result,
                     }
                 };
+                if self
+                    .broker_runtime_inner
+                    .broker_config()
+                    .enable_ordering_check
+                {
+                    self.broker_runtime_inner.ordering_checker().check_delivery(
+                        "pop",
+                        &request_header.consumer_group,
+                        topic,
+                        queue_id,
+                        offset,
+                        &result_inner,
+                    );
+                }
                 if !result_inner.message_mapped_list().is_empty() {
                     if is_order {
                         self.broker_runtime_inner
@@ -1102,11 +1287,8 @@ where
                     result_inner.max_offset() - result_inner.next_begin_offset(),
                     Ordering::AcqRel,
                 );
-                let broker_name = self
-                    .broker_runtime_inner
-                    .broker_config()
-                    .broker_name
-                    .as_str();
+                let broker_config = self.broker_runtime_inner.broker_config();
+                let broker_name = broker_config.broker_name.as_str();
                 let message_count = result_inner.message_count();
                 for mut maped_buffer in result_inner.message_mapped_vec() {
                     if self
@@ -1181,6 +1363,16 @@ where
         unimplemented!()
     }
 
+    fn is_group_inflight_full(&self, group: &CheetahString) -> bool {
+        let broker_config = self.broker_runtime_inner.broker_config();
+        broker_config.enable_pop_group_inflight_limit
+            && self
+                .broker_runtime_inner
+                .pop_inflight_message_counter()
+                .get_group_pop_in_flight_message_total(group)
+                >= broker_config.pop_group_inflight_message_limit
+    }
+
     fn is_pop_should_stop(
         &self,
         topic: &CheetahString,
@@ -1237,7 +1429,12 @@ where
         init: bool,
     ) -> i64 {
         let mut offset;
-        if init_mode == ConsumeInitMode::MIN || topic.starts_with(mix_all::RETRY_GROUP_TOPIC_PREFIX)
+        if init_mode == ConsumeInitMode::MIN
+            || topic.starts_with(mix_all::RETRY_GROUP_TOPIC_PREFIX)
+            || self
+                .broker_runtime_inner
+                .subscription_group_manager()
+                .consume_from_min_enable(group)
         {
             offset = self
                 .broker_runtime_inner
@@ -1326,11 +1523,29 @@ where
         queue_id: i32,
         cid: &CheetahString,
     ) {
-        info!(
-            "notifyMessageArriving topic={} queueId={} cid={}",
-            topic, queue_id, cid
-        );
-        unimplemented!("PopMessageProcessor notify_message_arriving")
+        self.pop_long_polling_service
+            .notify_message_arriving(topic, queue_id, cid, None, 0, None, None);
+    }
+
+    /// Wakes the pop requests a message stored to `topic` can satisfy.
+    pub fn notify_message_arriving_with_retry_topic(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        tags_code: Option<i64>,
+        msg_store_time: i64,
+        filter_bit_map: Option<Vec<u8>>,
+        properties: Option<&HashMap<CheetahString, CheetahString>>,
+    ) {
+        self.pop_long_polling_service
+            .notify_message_arriving_with_retry_topic(
+                topic,
+                queue_id,
+                tags_code,
+                msg_store_time,
+                filter_bit_map,
+                properties,
+            );
     }
 
     fn read_get_message_result(
@@ -1445,9 +1660,34 @@ impl<MS> PopMessageProcessor<MS> {
     }
 }
 
+/// Number of leading reads, given as their message count and bytes, kept by the readahead of a
+/// pop: reads are kept until together they hold `max_msg_nums` messages or `max_bytes` bytes.
+fn readahead_reads_kept(reads: &[Option<(i64, i64)>], max_msg_nums: i64, max_bytes: i64) -> usize {
+    let mut rest_count = max_msg_nums;
+    let mut rest_bytes = max_bytes;
+    for (index, read) in reads.iter().enumerate() {
+        if rest_count <= 0 || rest_bytes <= 0 {
+            return index;
+        }
+        if let Some((count, bytes)) = read {
+            rest_count -= count;
+            rest_bytes -= bytes;
+        }
+    }
+    reads.len()
+}
+
+/// Gives back the mapped files held by a read that is not going to be returned.
+fn release_message_result(result: GetMessageResult) {
+    for mut buffer in result.message_mapped_vec() {
+        buffer.release();
+    }
+}
+
 struct TimedLock {
     lock: AtomicBool,
     lock_time: AtomicU64,
+    waiters: VecDeque<oneshot::Sender<()>>,
 }
 
 impl TimedLock {
@@ -1455,6 +1695,7 @@ impl TimedLock {
         TimedLock {
             lock: AtomicBool::new(false),
             lock_time: AtomicU64::new(get_current_millis()),
+            waiters: VecDeque::new(),
         }
     }
 
@@ -1476,6 +1717,18 @@ impl TimedLock {
         self.lock.store(false, Ordering::Release);
     }
 
+    /// Hands the lock over to the oldest waiter still waiting, or releases it when there is none.
+    pub fn unlock_fair(&mut self) {
+        while let Some(waiter) = self.waiters.pop_front() {
+            if waiter.send(()).is_ok() {
+                self.lock_time
+                    .store(get_current_millis(), Ordering::Relaxed);
+                return;
+            }
+        }
+        self.unlock();
+    }
+
     pub fn is_locked(&self) -> bool {
         self.lock.load(Ordering::Acquire)
     }
@@ -1483,6 +1736,10 @@ impl TimedLock {
     pub fn get_lock_time(&self) -> u64 {
         self.lock_time.load(Ordering::Relaxed)
     }
+
+    pub fn has_waiters(&self) -> bool {
+        self.waiters.iter().any(|waiter| !waiter.is_closed())
+    }
 }
 
 #[derive(Clone)]
@@ -1526,7 +1783,47 @@ impl QueueLockManager {
     pub async fn try_lock_with_key(&self, key: CheetahString) -> bool {
         let mut cache = self.expired_local_cache.lock().await;
         let lock = cache.entry(key).or_insert(TimedLock::new());
-        lock.try_lock()
+        // Do not barge in front of requests already queued for this key.
+        !lock.has_waiters() && lock.try_lock()
+    }
+
+    pub async fn lock_with_timeout(
+        &self,
+        topic: &CheetahString,
+        consumer_group: &CheetahString,
+        queue_id: i32,
+        timeout_millis: u64,
+    ) -> bool {
+        let key = Self::build_lock_key(topic, consumer_group, queue_id);
+        self.lock_with_key_timeout(CheetahString::from_string(key), timeout_millis)
+            .await
+    }
+
+    /// Acquires the lock of `key`, waiting up to `timeout_millis` behind the requests already
+    /// queued for it. Waiters are served in FIFO order; a timeout of 0 behaves like
+    /// [`try_lock_with_key`](Self::try_lock_with_key).
+    pub async fn lock_with_key_timeout(&self, key: CheetahString, timeout_millis: u64) -> bool {
+        let mut receiver = {
+            let mut cache = self.expired_local_cache.lock().await;
+            let lock = cache.entry(key).or_insert(TimedLock::new());
+            if !lock.has_waiters() && lock.try_lock() {
+                return true;
+            }
+            if timeout_millis == 0 {
+                return false;
+            }
+            let (sender, receiver) = oneshot::channel();
+            lock.waiters.push_back(sender);
+            receiver
+        };
+        match tokio::time::timeout(Duration::from_millis(timeout_millis), &mut receiver).await {
+            Ok(result) => result.is_ok(),
+            Err(_) => {
+                // The lock may have been handed over right when the timeout fired.
+                receiver.close();
+                receiver.try_recv().is_ok()
+            }
+        }
     }
 
     pub async fn unlock(
@@ -1540,16 +1837,20 @@ impl QueueLockManager {
     }
 
     pub async fn unlock_with_key(&self, key: CheetahString) {
-        let cache = self.expired_local_cache.lock().await;
-        if let Some(lock) = cache.get(&key) {
-            lock.unlock();
+        let mut cache = self.expired_local_cache.lock().await;
+        if let Some(lock) = cache.get_mut(&key) {
+            lock.unlock_fair();
         }
     }
 
     pub async fn clean_unused_locks(&self, used_expire_millis: u64) -> usize {
         let mut cache = self.expired_local_cache.lock().await;
         let count = cache.len();
-        cache.retain(|_, lock| get_current_millis() - lock.get_lock_time() <= used_expire_millis);
+        cache.retain(|_, lock| {
+            lock.waiters.retain(|waiter| !waiter.is_closed());
+            !lock.waiters.is_empty()
+                || get_current_millis() - lock.get_lock_time() <= used_expire_millis
+        });
         count
     }
 
@@ -1573,6 +1874,17 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn readahead_keeps_the_reads_within_the_count_and_byte_budget() {
+        let reads = [Some((3, 300)), None, Some((2, 200)), Some((4, 400))];
+        assert_eq!(readahead_reads_kept(&reads, 10, 4096), 4);
+        // the read reaching the budget is kept, the ones after it are not
+        assert_eq!(readahead_reads_kept(&reads, 4, 4096), 3);
+        assert_eq!(readahead_reads_kept(&reads, 10, 300), 1);
+        assert_eq!(readahead_reads_kept(&reads, 0, 4096), 0);
+        assert_eq!(readahead_reads_kept(&[], 10, 4096), 0);
+    }
+
     #[test]
     fn gen_ack_unique_id_formats_correctly() {
         let ack_msg = AckMsg {
@@ -1715,6 +2027,60 @@ mod tests {
         assert!(manager.try_lock(&topic, &consumer_group, queue_id).await);
     }
 
+    #[tokio::test]
+    async fn try_lock_does_not_barge_waiters() {
+        let manager = QueueLockManager::new();
+        let key = CheetahString::from_static_str("test_topic@test_group@1");
+        assert!(manager.try_lock_with_key(key.clone()).await);
+        let waiter = {
+            let manager = manager.clone();
+            let key = key.clone();
+            tokio::spawn(async move { manager.lock_with_key_timeout(key, 1000).await })
+        };
+        tokio::time::sleep(tokio::time::Duration::from_millis(20)).await;
+        manager.unlock_with_key(key.clone()).await;
+        assert!(!manager.try_lock_with_key(key.clone()).await);
+        assert!(waiter.await.unwrap());
+        manager.unlock_with_key(key.clone()).await;
+        assert!(manager.try_lock_with_key(key).await);
+    }
+
+    #[tokio::test]
+    async fn lock_with_timeout_serves_waiters_in_order() {
+        let manager = QueueLockManager::new();
+        let key = CheetahString::from_static_str("test_topic@test_group@1");
+        assert!(manager.try_lock_with_key(key.clone()).await);
+        let order = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut handles = Vec::new();
+        for i in 0..3 {
+            let manager = manager.clone();
+            let key = key.clone();
+            let order = order.clone();
+            handles.push(tokio::spawn(async move {
+                assert!(manager.lock_with_key_timeout(key.clone(), 1000).await);
+                order.lock().unwrap().push(i);
+                manager.unlock_with_key(key).await;
+            }));
+            tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+        }
+        manager.unlock_with_key(key).await;
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), vec![0, 1, 2]);
+    }
+
+    #[tokio::test]
+    async fn lock_with_timeout_gives_up_after_timeout() {
+        let manager = QueueLockManager::new();
+        let key = CheetahString::from_static_str("test_topic@test_group@1");
+        assert!(manager.try_lock_with_key(key.clone()).await);
+        assert!(!manager.lock_with_key_timeout(key.clone(), 0).await);
+        assert!(!manager.lock_with_key_timeout(key.clone(), 10).await);
+        manager.unlock_with_key(key.clone()).await;
+        assert!(manager.try_lock_with_key(key).await);
+    }
+
     #[tokio::test]
     async fn clean_unused_locks_removes_expired_locks() {
         let manager = QueueLockManager::new();
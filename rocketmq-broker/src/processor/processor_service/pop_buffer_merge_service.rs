@@ -232,6 +232,16 @@ impl<MS: MessageStore> PopBufferMergeService<MS> {
         self.commit_offsets.remove(lock_key);
     }
 
+    /// Drops the buffered checkpoints and offsets of `topic`, they would otherwise be revived or
+    /// committed against a topic which no longer exists.
+    pub fn clear_topic(&self, topic: &CheetahString) {
+        let prefix = format!("{}{}", topic, PopAckConstants::SPLIT);
+        self.buffer
+            .retain(|_, point_wrapper| point_wrapper.get_ck().topic != *topic);
+        self.commit_offsets
+            .retain(|lock_key, _| !lock_key.starts_with(prefix.as_str()));
+    }
+
     fn mark_bit_cas(set_bits: &AtomicI32, index: usize) {
         loop {
             let bits = set_bits.load(Ordering::Relaxed);
@@ -569,8 +579,7 @@ impl<MS: MessageStore> PopBufferMergeService<MS> {
         );
         let put_message_result = self
             .broker_runtime_inner
-            .mut_from_ref()
-            .escape_bridge_mut()
+            .escape_bridge()
             .put_message_to_specific_queue(msg_inner)
             .await;
         match put_message_result.put_message_status() {
@@ -639,8 +648,7 @@ impl<MS: MessageStore> PopBufferMergeService<MS> {
 
         let put_message_result = self
             .broker_runtime_inner
-            .mut_from_ref()
-            .escape_bridge_mut()
+            .escape_bridge()
             .put_message_to_specific_queue(msg)
             .await;
         matches!(
@@ -679,8 +687,7 @@ impl<MS: MessageStore> PopBufferMergeService<MS> {
 
         let put_message_result = self
             .broker_runtime_inner
-            .mut_from_ref()
-            .escape_bridge_mut()
+            .escape_bridge()
             .put_message_to_specific_queue(msg)
             .await;
         matches!(
@@ -901,9 +908,55 @@ impl std::fmt::Display for PopCheckPointWrapper {
 mod tests {
     use std::sync::Arc;
 
+    use rocketmq_common::common::broker::broker_config::BrokerConfig;
+    use rocketmq_common::common::server::config::ServerConfig;
+    use rocketmq_store::config::message_store_config::MessageStoreConfig;
     use rocketmq_store::pop::pop_check_point::PopCheckPoint;
 
     use super::*;
+    use crate::broker_runtime::BrokerRuntime;
+
+    #[test]
+    fn clear_topic_drops_the_checkpoints_and_offsets_of_the_topic() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let _guard = runtime.enter();
+        let broker = BrokerRuntime::new(
+            BrokerConfig::default(),
+            MessageStoreConfig::default(),
+            ServerConfig::default(),
+        );
+        let service = PopBufferMergeService::new(
+            "rmq_sys_REVIVE_LOG_DefaultCluster".into(),
+            QueueLockManager::new(),
+            broker.inner().clone(),
+        );
+        for topic in ["TopicA", "TopicAB"] {
+            let ck = Arc::new(PopCheckPoint {
+                topic: topic.into(),
+                cid: "GroupA".into(),
+                ..Default::default()
+            });
+            let wrapper = PopCheckPointWrapper::new(0, 0, ck, 0);
+            service
+                .commit_offsets
+                .insert(wrapper.get_lock_key().clone(), QueueWithTime::new());
+            service
+                .buffer
+                .insert(wrapper.get_merge_key().into(), wrapper);
+        }
+
+        service.clear_topic(&"TopicA".into());
+        assert_eq!(service.buffer.len(), 1);
+        assert!(service
+            .buffer
+            .iter()
+            .all(|entry| entry.value().get_ck().topic == "TopicAB"));
+        assert_eq!(service.commit_offsets.len(), 1);
+        assert!(service
+            .commit_offsets
+            .iter()
+            .all(|entry| entry.key().starts_with("TopicAB")));
+    }
 
     #[test]
     fn new_creates_instance_correctly() {
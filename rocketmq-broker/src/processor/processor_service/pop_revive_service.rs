@@ -48,6 +48,7 @@ use rocketmq_store::pop::pop_check_point::PopCheckPoint;
 use rocketmq_store::pop::AckMessage;
 use tracing::error;
 use tracing::info;
+use tracing::warn;
 
 use crate::broker_runtime::BrokerRuntimeInner;
 use crate::processor::pop_message_processor::PopMessageProcessor;
@@ -93,7 +94,7 @@ impl<MS: MessageStore> PopReviveService<MS> {
         message_ext: &MessageExt,
     ) -> bool {
         let mut msg_inner = MessageExtBrokerInner::default();
-        if pop_check_point
+        if !pop_check_point
             .topic
             .starts_with(mix_all::RETRY_GROUP_TOPIC_PREFIX)
         {
@@ -142,7 +143,7 @@ impl<MS: MessageStore> PopReviveService<MS> {
         self.add_retry_topic_if_not_exist(msg_inner.get_topic(), &pop_check_point.cid);
         let put_message_result = self
             .broker_runtime_inner
-            .escape_bridge_mut()
+            .escape_bridge()
             .put_message_to_specific_queue(msg_inner)
             .await;
         if put_message_result.append_message_result().is_none()
@@ -172,9 +173,14 @@ impl<MS: MessageStore> PopReviveService<MS> {
         topic_config.topic_filter_type = TopicFilterType::SingleTag;
         topic_config.perm = 6;
         topic_config.topic_sys_flag = 0;
-        self.broker_runtime_inner
+        if let Err(e) = self
+            .broker_runtime_inner
             .topic_config_manager_mut()
-            .update_topic_config(&mut topic_config);
+            .update_topic_config(&mut topic_config)
+        {
+            error!("create retry topic {} failed: {}", topic, e);
+            return;
+        }
         self.init_pop_retry_offset(topic, consumer_group);
     }
 
@@ -383,7 +389,7 @@ impl<MS: MessageStore> PopReviveService<MS> {
     async fn consume_revive_message(&self, consume_revive_obj: &mut ConsumeReviveObj) {
         let map = &mut consume_revive_obj.map;
         let mut mock_point_map = HashMap::new();
-        let _start_scan_time = get_current_millis();
+        let start_scan_time = get_current_millis();
         let mut end_time = 0;
         let consume_offset = self
             .broker_runtime_inner
@@ -441,7 +447,7 @@ impl<MS: MessageStore> PopReviveService<MS> {
                      endTime {}, timerDelay={}, commitLogDelay={}",
                     self.queue_id, offset, old, end_time, timer_delay, commit_log_delay
                 );
-                if end_time - first_rt
+                if end_time.saturating_sub(first_rt)
                     > (PopAckConstants::ACK_TIME_INTERVAL + PopAckConstants::SECOND) as u64
                 {
                     break;
@@ -457,7 +463,9 @@ impl<MS: MessageStore> PopReviveService<MS> {
                 no_msg_count = 0;
             }
 
-            if get_current_millis() > self.broker_runtime_inner.broker_config().revive_scan_time {
+            if get_current_millis().saturating_sub(start_scan_time)
+                > self.broker_runtime_inner.broker_config().revive_scan_time
+            {
                 info!("reviveQueueId={}, scan timeout", self.queue_id);
                 break;
             }
@@ -475,6 +483,7 @@ impl<MS: MessageStore> PopReviveService<MS> {
                     if point.topic.is_empty() || point.cid.is_empty() {
                         continue;
                     }
+                    point.revive_offset = message_ext.queue_offset;
                     map.insert(
                         format!(
                             "{}{}{}{}{}{}",
@@ -489,7 +498,6 @@ impl<MS: MessageStore> PopReviveService<MS> {
                         point.clone(),
                     );
                     //  PopMetricsManager::inc_pop_revive_ck_get_count(&point, self.queue_id);
-                    point.revive_offset = message_ext.queue_offset;
                     if first_rt == 0 {
                         first_rt = point.get_revive_time() as u64;
                     }
@@ -596,15 +604,38 @@ impl<MS: MessageStore> PopReviveService<MS> {
         consume_revive_obj.map.extend(mock_point_map);
         consume_revive_obj.end_time = end_time as i64;
     }
+    /// Stands in for the checkpoint of an ack which has waited longer than
+    /// `revive_ack_wait_ms`, so that the revive offset can move past it.
     fn mock_ck_for_ack(
         &self,
-        _message_ext: &MessageExt,
-        _ack_msg: &dyn AckMessage,
-        _merge_key: &CheetahString,
-        _mock_point_map: &mut HashMap<CheetahString, PopCheckPoint>,
+        message_ext: &MessageExt,
+        ack_msg: &dyn AckMessage,
+        merge_key: &CheetahString,
+        mock_point_map: &mut HashMap<CheetahString, PopCheckPoint>,
     ) -> bool {
-        // Implement the logic to mock checkpoint for ack
-        unimplemented!()
+        let ack_wait_time = get_current_millis().saturating_sub(message_ext.get_deliver_time_ms());
+        let revive_ack_wait_ms = self.broker_runtime_inner.broker_config().revive_ack_wait_ms;
+        if ack_wait_time <= revive_ack_wait_ms {
+            return false;
+        }
+        let mock_point = PopCheckPoint {
+            start_offset: ack_msg.start_offset(),
+            pop_time: ack_msg.pop_time(),
+            queue_id: ack_msg.queue_id(),
+            cid: ack_msg.consumer_group().clone(),
+            topic: ack_msg.topic().clone(),
+            num: 0,
+            bit_map: 0,
+            revive_offset: message_ext.queue_offset,
+            broker_name: Some(ack_msg.broker_name().clone()),
+            ..Default::default()
+        };
+        warn!(
+            "reviveQueueId={}, ack waited {}ms over {}ms, mock ck {}",
+            self.queue_id, ack_wait_time, revive_ack_wait_ms, mock_point
+        );
+        mock_point_map.insert(merge_key.clone(), mock_point);
+        true
     }
 
     async fn merge_and_revive(
@@ -698,18 +729,19 @@ impl<MS: MessageStore> PopReviveService<MS> {
                     );
                 }
             }
-            let mut inflight_revive_request_map = self.inflight_revive_request_map.lock().await;
-            for ck in remove {
-                inflight_revive_request_map.remove(&ck);
+            {
+                let mut inflight_revive_request_map = self.inflight_revive_request_map.lock().await;
+                for ck in remove {
+                    inflight_revive_request_map.remove(&ck);
+                }
             }
-            // may be need to optimize
-            self.revive_msg_from_ck(pop_check_point);
+            self.revive_msg_from_ck(pop_check_point).await;
 
             new_offset = pop_check_point.revive_offset;
         }
         if new_offset > consume_revive_obj.old_offset {
             if !self.should_run_pop_revive {
-                println!(
+                info!(
                     "slave skip commit, revive topic={}, reviveQueueId={}",
                     self.revive_topic, self.queue_id
                 );
@@ -795,9 +827,101 @@ impl<MS: MessageStore> PopReviveService<MS> {
             .await;
     }
 
-    fn revive_msg_from_ck(&self, _pop_check_point: &PopCheckPoint) {
-        // Implement the logic to revive message from checkpoint
-        unimplemented!()
+    /// Reads the popped message a checkpoint refers to, which lives on another broker while this
+    /// one is acting master for it.
+    async fn get_biz_message(
+        &self,
+        pop_check_point: &PopCheckPoint,
+        offset: i64,
+    ) -> (Option<MessageExt>, String, bool) {
+        let broker_name = pop_check_point
+            .get_broker_name()
+            .cloned()
+            .unwrap_or_else(|| {
+                self.broker_runtime_inner
+                    .broker_config()
+                    .broker_name
+                    .clone()
+            });
+        self.broker_runtime_inner
+            .escape_bridge()
+            .get_message_async(
+                pop_check_point.get_topic(),
+                offset,
+                pop_check_point.get_queue_id(),
+                &broker_name,
+                false,
+            )
+            .await
+    }
+
+    /// Sends the messages of `pop_check_point` which were not acked to the retry topic, and
+    /// writes the checkpoint again for those which could not be revived yet. The revive offset
+    /// is committed up to the first checkpoint still in flight.
+    async fn revive_msg_from_ck(&mut self, pop_check_point: &PopCheckPoint) {
+        if !self.should_run_pop_revive {
+            info!(
+                "slave skip revive msg from ck, revive topic={}, reviveQueueId={}",
+                self.revive_topic, self.queue_id
+            );
+            return;
+        }
+        self.inflight_revive_request_map.lock().await.insert(
+            pop_check_point.clone(),
+            (get_current_millis() as i64, false),
+        );
+        let mut results = Vec::with_capacity(pop_check_point.get_num() as usize);
+        for index in 0..pop_check_point.get_num() {
+            if DataConverter::get_bit(pop_check_point.get_bit_map(), index as usize) {
+                continue;
+            }
+            let msg_offset = pop_check_point.ack_offset_by_index(index);
+            let (message, info, need_retry) =
+                self.get_biz_message(pop_check_point, msg_offset).await;
+            let revived = match message {
+                Some(message) => self.revive_retry(pop_check_point, &message).await,
+                None => {
+                    info!(
+                        "reviveQueueId={}, can not get biz msg, topic:{}, qid:{}, offset:{}, \
+                         brokerName:{:?}, info:{}, retry:{}, then continue",
+                        self.queue_id,
+                        pop_check_point.get_topic(),
+                        pop_check_point.get_queue_id(),
+                        msg_offset,
+                        pop_check_point.get_broker_name(),
+                        info,
+                        need_retry
+                    );
+                    !need_retry
+                }
+            };
+            results.push((msg_offset, revived));
+        }
+        for pair in &results {
+            if !pair.1 {
+                self.re_put_ck(pop_check_point, pair).await;
+            }
+        }
+
+        let mut inflight_revive_request_map = self.inflight_revive_request_map.lock().await;
+        if let Some(pair) = inflight_revive_request_map.get_mut(pop_check_point) {
+            pair.1 = true;
+        }
+        while let Some(entry) = inflight_revive_request_map.first_entry() {
+            if !entry.get().1 {
+                break;
+            }
+            let (old_ck, _) = entry.remove_entry();
+            self.broker_runtime_inner
+                .consumer_offset_manager()
+                .commit_offset(
+                    CheetahString::from_static_str(PopAckConstants::LOCAL_HOST),
+                    &CheetahString::from_static_str(PopAckConstants::REVIVE_GROUP),
+                    &self.revive_topic,
+                    self.queue_id,
+                    old_ck.revive_offset,
+                );
+        }
     }
 }
 
@@ -856,10 +980,213 @@ impl ConsumeReviveObj {
 
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
     use cheetah_string::CheetahString;
+    use rocketmq_common::common::broker::broker_config::BrokerConfig;
+    use rocketmq_common::common::server::config::ServerConfig;
+    use rocketmq_store::base::message_status_enum::PutMessageStatus;
+    use rocketmq_store::config::message_store_config::MessageStoreConfig;
+    use rocketmq_store::message_store::boxed_message_store::BoxedMessageStore;
     use rocketmq_store::pop::pop_check_point::PopCheckPoint;
 
     use super::*;
+    use crate::broker_runtime::BrokerRuntime;
+
+    const TOPIC: &str = "ReviveTopic";
+    const GROUP: &str = "ReviveGroup";
+
+    fn started_broker(
+        runtime: &tokio::runtime::Runtime,
+        store_dir: &tempfile::TempDir,
+    ) -> ArcMut<BrokerRuntimeInner<BoxedMessageStore>> {
+        let store_path_root_dir = CheetahString::from(store_dir.path().to_string_lossy().as_ref());
+        let ha_listen_port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port() as usize;
+        let mut broker = BrokerRuntime::new(
+            BrokerConfig {
+                store_path_root_dir: store_path_root_dir.clone(),
+                ..Default::default()
+            },
+            MessageStoreConfig {
+                store_path_root_dir,
+                mapped_file_size_commit_log: 1024 * 1024,
+                mapped_file_size_consume_queue: 20 * 1024,
+                ha_listen_port,
+                ..Default::default()
+            },
+            ServerConfig::default(),
+        );
+        assert!(runtime.block_on(broker.initialize()));
+        let inner = broker.inner().clone();
+        inner
+            .message_store()
+            .as_ref()
+            .unwrap()
+            .mut_from_ref()
+            .start()
+            .unwrap();
+        inner
+    }
+
+    async fn put(
+        inner: &ArcMut<BrokerRuntimeInner<BoxedMessageStore>>,
+        topic: &str,
+        body: &'static [u8],
+    ) {
+        let mut message = MessageExtBrokerInner::default();
+        message.set_topic(CheetahString::from_slice(topic));
+        message.set_body(Bytes::from_static(body));
+        message.message_ext_inner.born_host = inner.store_host();
+        message.message_ext_inner.store_host = inner.store_host();
+        let result = inner
+            .message_store()
+            .as_ref()
+            .unwrap()
+            .mut_from_ref()
+            .put_message(message)
+            .await;
+        assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+    }
+
+    async fn wait_for_queue(
+        inner: &ArcMut<BrokerRuntimeInner<BoxedMessageStore>>,
+        topic: &str,
+        max_offset: i64,
+    ) -> bool {
+        let topic = CheetahString::from_slice(topic);
+        for _ in 0..100 {
+            if inner
+                .message_store()
+                .as_ref()
+                .unwrap()
+                .get_max_offset_in_queue(&topic, 0)
+                >= max_offset
+            {
+                return true;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        false
+    }
+
+    fn check_point(bit_map: i32) -> PopCheckPoint {
+        PopCheckPoint {
+            start_offset: 0,
+            pop_time: 1,
+            invisible_time: 1,
+            bit_map,
+            num: 2,
+            queue_id: 0,
+            topic: TOPIC.into(),
+            cid: GROUP.into(),
+            revive_offset: 7,
+            queue_offset_diff: vec![0, 1],
+            ..Default::default()
+        }
+    }
+
+    fn revive_offset(inner: &ArcMut<BrokerRuntimeInner<BoxedMessageStore>>) -> i64 {
+        inner.consumer_offset_manager().query_offset(
+            &CheetahString::from_static_str(PopAckConstants::REVIVE_GROUP),
+            &CheetahString::from_static_str("rmq_sys_REVIVE_LOG_test"),
+            0,
+        )
+    }
+
+    #[test]
+    fn revives_the_messages_which_were_not_acked() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let _guard = runtime.enter();
+        let store_dir = tempfile::tempdir().unwrap();
+        let inner = started_broker(&runtime, &store_dir);
+        let mut service = PopReviveService::new(
+            CheetahString::from_static_str("rmq_sys_REVIVE_LOG_test"),
+            0,
+            inner.clone(),
+        );
+        service.should_run_pop_revive = true;
+
+        runtime.block_on(async {
+            put(&inner, TOPIC, b"acked").await;
+            put(&inner, TOPIC, b"not acked").await;
+            assert!(wait_for_queue(&inner, TOPIC, 2).await);
+
+            // the first message was acked
+            service.revive_msg_from_ck(&check_point(0b01)).await;
+
+            let retry_topic = KeyBuilder::build_pop_retry_topic(
+                TOPIC,
+                GROUP,
+                inner.broker_config().enable_retry_topic_v2,
+            );
+            assert!(wait_for_queue(&inner, &retry_topic, 1).await);
+            let (message, _, _) = inner
+                .escape_bridge()
+                .get_message_async(
+                    &CheetahString::from_string(retry_topic.clone()),
+                    0,
+                    0,
+                    &inner.broker_config().broker_name,
+                    false,
+                )
+                .await;
+            let message = message.unwrap();
+            assert_eq!(message.get_body().unwrap().as_ref(), b"not acked");
+            assert_eq!(message.reconsume_times(), 1);
+            assert!(inner
+                .topic_config_manager()
+                .select_topic_config(&CheetahString::from_string(retry_topic.clone()))
+                .is_some());
+            // only one message was revived
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            assert_eq!(
+                inner
+                    .message_store()
+                    .as_ref()
+                    .unwrap()
+                    .get_max_offset_in_queue(&CheetahString::from_string(retry_topic), 0),
+                1
+            );
+        });
+        assert_eq!(revive_offset(&inner), 7);
+        assert!(runtime
+            .block_on(service.inflight_revive_request_map.lock())
+            .is_empty());
+    }
+
+    #[test]
+    fn skips_the_messages_of_an_acked_check_point() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let _guard = runtime.enter();
+        let store_dir = tempfile::tempdir().unwrap();
+        let inner = started_broker(&runtime, &store_dir);
+        let mut service = PopReviveService::new(
+            CheetahString::from_static_str("rmq_sys_REVIVE_LOG_test"),
+            0,
+            inner.clone(),
+        );
+
+        // a slave does not revive
+        runtime.block_on(service.revive_msg_from_ck(&check_point(0)));
+        assert_eq!(revive_offset(&inner), -1);
+
+        service.should_run_pop_revive = true;
+        runtime.block_on(service.revive_msg_from_ck(&check_point(0b11)));
+        assert_eq!(revive_offset(&inner), 7);
+        let retry_topic = KeyBuilder::build_pop_retry_topic(
+            TOPIC,
+            GROUP,
+            inner.broker_config().enable_retry_topic_v2,
+        );
+        assert!(inner
+            .topic_config_manager()
+            .select_topic_config(&CheetahString::from_string(retry_topic))
+            .is_none());
+    }
 
     #[test]
     fn new_initializes_correctly() {
@@ -54,17 +54,16 @@ use tracing::warn;
 
 use crate::broker_runtime::BrokerRuntimeInner;
 use crate::client::consumer_group_info::ConsumerGroupInfo;
-use crate::coldctr::cold_data_cg_ctr_service::ColdDataCgCtrService;
 use crate::coldctr::cold_data_pull_request_hold_service::NO_SUSPEND_KEY;
 use crate::filter::expression_for_retry_message_filter::ExpressionForRetryMessageFilter;
 use crate::filter::expression_message_filter::ExpressionMessageFilter;
 use crate::filter::manager::consumer_filter_manager::ConsumerFilterManager;
+use crate::long_polling::pull_request::PullRequest;
 use crate::processor::pull_message_result_handler::PullMessageResultHandler;
 
 pub struct PullMessageProcessor<MS> {
     pull_message_result_handler: ArcMut<Box<dyn PullMessageResultHandler>>,
     // write message to consume client runtime
-    cold_data_cg_ctr_service: Arc<ColdDataCgCtrService>,
     write_message_runtime: Arc<RocketMQRuntime>,
     // write message to consume client lock
     write_message_lock: Arc<Mutex<()>>,
@@ -79,7 +78,6 @@ impl<MS: MessageStore> PullMessageProcessor<MS> {
         let cpus = num_cpus::get();
         Self {
             pull_message_result_handler,
-            cold_data_cg_ctr_service: Arc::new(Default::default()),
             write_message_runtime: Arc::new(RocketMQRuntime::new_multi(
                 cpus,
                 "write_consumer_message_runtime",
@@ -676,7 +674,11 @@ where
             .broker_config()
             .filter_support_retry
         {
-            Arc::new(Box::new(ExpressionForRetryMessageFilter))
+            Arc::new(Box::new(ExpressionForRetryMessageFilter::new(
+                Some(subscription_data.clone()),
+                consumer_filter_data,
+                Arc::new(self.broker_runtime_inner.consumer_filter_manager().clone()),
+            )))
         } else {
             Arc::new(Box::new(ExpressionMessageFilter::new(
                 Some(subscription_data.clone()),
@@ -685,14 +687,56 @@ where
             )))
         };
 
-        //ColdDataFlow not implement
-
-        cfg_if::cfg_if! {
-            if #[cfg(feature = "local_file_store")] {
-                if self.cold_data_cg_ctr_service.is_cg_need_cold_data_flow_ctr(request_header.consumer_group.as_str()) {
-                    unimplemented!("ColdDataFlow not implement")
+        let cg_need_cold_data_flow_ctr = self
+            .broker_runtime_inner
+            .cold_data_cg_ctr_service()
+            .as_ref()
+            .is_some_and(|service| {
+                service.is_cg_need_cold_data_flow_ctr(request_header.consumer_group.as_str())
+            });
+        if cg_need_cold_data_flow_ctr
+            && self
+                .broker_runtime_inner
+                .message_store()
+                .as_ref()
+                .unwrap()
+                .is_msg_in_cold_area(
+                    &request_header.consumer_group,
+                    &request_header.topic,
+                    request_header.queue_id,
+                    request_header.queue_offset,
+                )
+        {
+            let consume_type = self
+                .broker_runtime_inner
+                .consumer_manager()
+                .get_consumer_group_info(&request_header.consumer_group)
+                .map(|consumer_group_info| consumer_group_info.get_consume_type());
+            if consume_type == Some(ConsumeType::ConsumePassively) {
+                return Some(response.set_code(ResponseCode::SystemBusy).set_remark(
+                    "This consumer group is reading cold data. It has been flow control",
+                ));
+            }
+            if broker_allow_flow_ctr_suspend {
+                if let Some(hold_service) = self
+                    .broker_runtime_inner
+                    .cold_data_pull_request_hold_service()
+                    .as_ref()
+                {
+                    hold_service.suspend_cold_data_read_request(PullRequest::new(
+                        request,
+                        channel,
+                        ctx,
+                        request_header.suspend_timeout_millis,
+                        get_current_millis(),
+                        request_header.queue_offset,
+                        subscription_data,
+                        message_filter,
+                    ));
+                    return None;
                 }
             }
+            request_header.max_msg_nums = 1;
         }
 
         let use_reset_offset_feature = self
@@ -762,6 +806,30 @@ where
                             .set_remark("store getMessage return None"),
                     );
                 }
+                if let Some(cold_data_cg_ctr_service) =
+                    self.broker_runtime_inner.cold_data_cg_ctr_service()
+                {
+                    cold_data_cg_ctr_service.cold_acc(
+                        group,
+                        result.as_ref().map_or(0, |result| result.cold_data_sum()),
+                    );
+                }
+                if self
+                    .broker_runtime_inner
+                    .broker_config()
+                    .enable_ordering_check
+                {
+                    if let Some(result) = result.as_ref() {
+                        self.broker_runtime_inner.ordering_checker().check_delivery(
+                            "pull",
+                            group,
+                            topic,
+                            queue_id,
+                            request_header.queue_offset,
+                            result,
+                        );
+                    }
+                }
                 result
             }
         };
@@ -70,9 +70,7 @@ impl<MS: MessageStore> QueryAssignmentProcessor<MS> {
             CheetahString::from_static_str(allocate_message_queue_averagely_by_circle.get_name()),
             allocate_message_queue_averagely_by_circle,
         );
-        let manager = MessageRequestModeManager::new(Arc::new(
-            broker_runtime_inner.message_store_config().clone(),
-        ));
+        let manager = broker_runtime_inner.message_request_mode_manager().clone();
         let _ = manager.load();
         Self {
             message_request_mode_manager: manager,
@@ -0,0 +1,502 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::TimeUtils::get_current_millis;
+use rocketmq_remoting::code::request_code::RequestCode;
+use tokio::sync::oneshot;
+use tokio::sync::OwnedSemaphorePermit;
+use tokio::sync::Semaphore;
+
+/// Groups of request codes processed in isolation from each other, like the thread pools of the
+/// Java broker, so that a flood of one kind of request can not starve the others.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RequestQueueKind {
+    Send,
+    Pull,
+    LitePull,
+    Pop,
+    Ack,
+    Query,
+    Admin,
+    ClientManage,
+    ConsumerManage,
+    Heartbeat,
+    Transaction,
+}
+
+impl RequestQueueKind {
+    pub(crate) const ALL: [RequestQueueKind; 11] = [
+        RequestQueueKind::Send,
+        RequestQueueKind::Pull,
+        RequestQueueKind::LitePull,
+        RequestQueueKind::Pop,
+        RequestQueueKind::Ack,
+        RequestQueueKind::Query,
+        RequestQueueKind::Admin,
+        RequestQueueKind::ClientManage,
+        RequestQueueKind::ConsumerManage,
+        RequestQueueKind::Heartbeat,
+        RequestQueueKind::Transaction,
+    ];
+
+    pub(crate) fn of(request_code: RequestCode) -> Self {
+        match request_code {
+            RequestCode::SendMessage
+            | RequestCode::SendMessageV2
+            | RequestCode::SendBatchMessage
+            | RequestCode::ConsumerSendMsgBack
+            | RequestCode::SendReplyMessage
+            | RequestCode::SendReplyMessageV2 => RequestQueueKind::Send,
+            RequestCode::PullMessage => RequestQueueKind::Pull,
+            RequestCode::LitePullMessage => RequestQueueKind::LitePull,
+            RequestCode::PopMessage
+            | RequestCode::PeekMessage
+            | RequestCode::PollingInfo
+            | RequestCode::Notification => RequestQueueKind::Pop,
+            RequestCode::AckMessage
+            | RequestCode::BatchAckMessage
+            | RequestCode::ChangeMessageInvisibleTime => RequestQueueKind::Ack,
+            RequestCode::QueryMessage | RequestCode::ViewMessageById => RequestQueueKind::Query,
+            RequestCode::UnregisterClient | RequestCode::CheckClientConfig => {
+                RequestQueueKind::ClientManage
+            }
+            RequestCode::GetConsumerListByGroup
+            | RequestCode::UpdateConsumerOffset
+            | RequestCode::QueryConsumerOffset => RequestQueueKind::ConsumerManage,
+            RequestCode::HeartBeat => RequestQueueKind::Heartbeat,
+            RequestCode::EndTransaction => RequestQueueKind::Transaction,
+            _ => RequestQueueKind::Admin,
+        }
+    }
+
+    fn concurrency(self, broker_config: &BrokerConfig) -> usize {
+        let nums = match self {
+            RequestQueueKind::Send => broker_config.send_message_thread_pool_nums,
+            RequestQueueKind::Pull => broker_config.pull_message_thread_pool_nums,
+            RequestQueueKind::LitePull => broker_config.lite_pull_message_thread_pool_nums,
+            RequestQueueKind::Pop => broker_config.pop_message_thread_pool_nums,
+            RequestQueueKind::Ack => broker_config.ack_message_thread_pool_nums,
+            RequestQueueKind::Query => broker_config.query_message_thread_pool_nums,
+            RequestQueueKind::Admin => broker_config.admin_broker_thread_pool_nums,
+            RequestQueueKind::ClientManage => broker_config.client_manage_thread_pool_nums,
+            RequestQueueKind::ConsumerManage => broker_config.consumer_manage_thread_pool_nums,
+            RequestQueueKind::Heartbeat => broker_config.heartbeat_thread_pool_nums,
+            RequestQueueKind::Transaction => broker_config.end_transaction_thread_pool_nums,
+        };
+        nums.max(1)
+    }
+
+    fn queue_capacity(self, broker_config: &BrokerConfig) -> usize {
+        match self {
+            RequestQueueKind::Send => broker_config.send_thread_pool_queue_capacity,
+            RequestQueueKind::Pull => broker_config.pull_thread_pool_queue_capacity,
+            RequestQueueKind::LitePull => broker_config.lite_pull_thread_pool_queue_capacity,
+            RequestQueueKind::Pop => broker_config.pop_thread_pool_queue_capacity,
+            RequestQueueKind::Ack => broker_config.ack_thread_pool_queue_capacity,
+            RequestQueueKind::Query => broker_config.query_thread_pool_queue_capacity,
+            RequestQueueKind::Admin => broker_config.admin_broker_thread_pool_queue_capacity,
+            RequestQueueKind::ClientManage => {
+                broker_config.client_manager_thread_pool_queue_capacity
+            }
+            RequestQueueKind::ConsumerManage => {
+                broker_config.consumer_manager_thread_pool_queue_capacity
+            }
+            RequestQueueKind::Heartbeat => broker_config.heartbeat_thread_pool_queue_capacity,
+            RequestQueueKind::Transaction => {
+                broker_config.end_transaction_thread_pool_queue_capacity
+            }
+        }
+    }
+
+    /// How long a request may wait before the fast failure service rejects it, `None` for
+    /// queues which are not cleaned.
+    pub(crate) fn max_wait_time_mills(self, broker_config: &BrokerConfig) -> Option<u64> {
+        match self {
+            RequestQueueKind::Send => Some(broker_config.wait_time_mills_in_send_queue),
+            RequestQueueKind::Pull => Some(broker_config.wait_time_mills_in_pull_queue),
+            RequestQueueKind::LitePull => Some(broker_config.wait_time_mills_in_lite_pull_queue),
+            RequestQueueKind::Heartbeat => Some(broker_config.wait_time_mills_in_heartbeat_queue),
+            RequestQueueKind::Transaction => {
+                Some(broker_config.wait_time_mills_in_transaction_queue)
+            }
+            RequestQueueKind::Ack => Some(broker_config.wait_time_mills_in_ack_queue),
+            _ => None,
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+struct QueuedRequest {
+    id: u64,
+    create_timestamp: u64,
+    expire: oneshot::Sender<String>,
+}
+
+struct RequestQueue {
+    permits: Arc<Semaphore>,
+    waiting: Mutex<VecDeque<QueuedRequest>>,
+    next_id: AtomicU64,
+}
+
+impl RequestQueue {
+    fn new(concurrency: usize) -> Self {
+        RequestQueue {
+            permits: Arc::new(Semaphore::new(concurrency)),
+            waiting: Mutex::new(VecDeque::new()),
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    /// Takes a free slot or queues the request, fails when `capacity` requests are waiting.
+    fn reserve(self: &Arc<Self>, capacity: usize) -> Result<Admission, String> {
+        if let Ok(permit) = Arc::clone(&self.permits).try_acquire_owned() {
+            return Ok(Admission::Admitted(permit));
+        }
+        let (expire, expired) = oneshot::channel();
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut waiting = self.waiting.lock();
+        if waiting.len() >= capacity {
+            return Err("[OVERLOAD]system busy, start flow control for a while".to_string());
+        }
+        waiting.push_back(QueuedRequest {
+            id,
+            create_timestamp: get_current_millis(),
+            expire,
+        });
+        Ok(Admission::Queued {
+            queue: Arc::clone(self),
+            id,
+            expired,
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.waiting.lock().len()
+    }
+
+    fn head_wait_time_mills(&self, now: u64) -> u64 {
+        self.waiting
+            .lock()
+            .front()
+            .map_or(0, |request| now.saturating_sub(request.create_timestamp))
+    }
+
+    fn fail_oldest(&self, reason: &str, now: u64, min_wait_time_mills: u64) -> bool {
+        let mut waiting = self.waiting.lock();
+        let expired = waiting.front().is_some_and(|request| {
+            now.saturating_sub(request.create_timestamp) >= min_wait_time_mills
+        });
+        if !expired {
+            return false;
+        }
+        let Some(request) = waiting.pop_front() else {
+            return false;
+        };
+        let _ = request.expire.send(format!(
+            "[{}]broker busy, start flow control for a while, period in queue: {}ms, size of \
+             queue: {}",
+            reason,
+            now.saturating_sub(request.create_timestamp),
+            waiting.len()
+        ));
+        true
+    }
+}
+
+/// A request accepted by a [`RequestQueue`], either holding a slot or waiting for one.
+enum Admission {
+    Admitted(OwnedSemaphorePermit),
+    Queued {
+        queue: Arc<RequestQueue>,
+        id: u64,
+        expired: oneshot::Receiver<String>,
+    },
+}
+
+impl Admission {
+    async fn wait(self) -> Result<OwnedSemaphorePermit, String> {
+        let (queue, id, mut expired) = match self {
+            Admission::Admitted(permit) => return Ok(permit),
+            Admission::Queued { queue, id, expired } => (queue, id, expired),
+        };
+        let result = tokio::select! {
+            Ok(remark) = &mut expired => Err(remark),
+            permit = Arc::clone(&queue.permits).acquire_owned() => {
+                Ok(permit.expect("request queue semaphore closed"))
+            }
+        };
+        if result.is_ok() {
+            queue.waiting.lock().retain(|request| request.id != id);
+        }
+        result
+    }
+}
+
+/// Broker wide pools running a configurable number of requests of each [`RequestQueueKind`] at
+/// a time. Requests beyond the concurrency wait in order, and are rejected once the queue holds
+/// its capacity.
+pub(crate) struct RequestExecutors {
+    queues: [Arc<RequestQueue>; 11],
+}
+
+impl RequestExecutors {
+    pub(crate) fn new(broker_config: &BrokerConfig) -> Self {
+        RequestExecutors {
+            queues: RequestQueueKind::ALL
+                .map(|kind| Arc::new(RequestQueue::new(kind.concurrency(broker_config)))),
+        }
+    }
+
+    /// Runs `task` on the pool of the kind `request_code` belongs to once the pool has a free
+    /// slot, without blocking the caller, so the connection keeps reading requests of other kinds
+    /// meanwhile.
+    ///
+    /// `task` is given the remark of the `SYSTEM_BUSY` response instead of a slot when the request
+    /// is failed while waiting. `Err` holds that remark when the queue is full, `task` is not run
+    /// then.
+    pub(crate) fn execute<F, Fut>(
+        &self,
+        broker_config: &BrokerConfig,
+        request_code: RequestCode,
+        task: F,
+    ) -> Result<(), String>
+    where
+        F: FnOnce(Result<(), String>) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let kind = RequestQueueKind::of(request_code);
+        let admission = self.queues[kind.index()].reserve(kind.queue_capacity(broker_config))?;
+        tokio::spawn(async move {
+            match admission.wait().await {
+                Ok(permit) => {
+                    task(Ok(())).await;
+                    drop(permit);
+                }
+                Err(remark) => task(Err(remark)).await,
+            }
+        });
+        Ok(())
+    }
+
+    /// Waits for a slot of the pool `request_code` belongs to in the calling task.
+    #[cfg(test)]
+    pub(crate) async fn enter(
+        &self,
+        broker_config: &BrokerConfig,
+        request_code: RequestCode,
+    ) -> Result<OwnedSemaphorePermit, String> {
+        let kind = RequestQueueKind::of(request_code);
+        self.queues[kind.index()]
+            .reserve(kind.queue_capacity(broker_config))?
+            .wait()
+            .await
+    }
+
+    pub(crate) fn queue_size(&self, kind: RequestQueueKind) -> usize {
+        self.queues[kind.index()].len()
+    }
+
+    /// How long the oldest request of the queue has been waiting, `0` if it is empty.
+    pub(crate) fn head_wait_time_mills(&self, kind: RequestQueueKind) -> u64 {
+        self.queues[kind.index()].head_wait_time_mills(get_current_millis())
+    }
+
+    /// Fails the oldest waiting request of the queue if it has been waiting for at least
+    /// `min_wait_time_mills`, returns `false` when no request was failed.
+    pub(crate) fn fail_oldest(
+        &self,
+        kind: RequestQueueKind,
+        reason: &str,
+        now: u64,
+        min_wait_time_mills: u64,
+    ) -> bool {
+        self.queues[kind.index()].fail_oldest(reason, now, min_wait_time_mills)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(send_concurrency: usize, send_capacity: usize) -> BrokerConfig {
+        BrokerConfig {
+            send_message_thread_pool_nums: send_concurrency,
+            send_thread_pool_queue_capacity: send_capacity,
+            ..BrokerConfig::default()
+        }
+    }
+
+    #[test]
+    fn classifies_request_codes() {
+        assert_eq!(
+            RequestQueueKind::of(RequestCode::SendBatchMessage),
+            RequestQueueKind::Send
+        );
+        assert_eq!(
+            RequestQueueKind::of(RequestCode::PopMessage),
+            RequestQueueKind::Pop
+        );
+        assert_eq!(
+            RequestQueueKind::of(RequestCode::UpdateConsumerOffset),
+            RequestQueueKind::ConsumerManage
+        );
+        assert_eq!(
+            RequestQueueKind::of(RequestCode::GetBrokerRuntimeInfo),
+            RequestQueueKind::Admin
+        );
+    }
+
+    #[tokio::test]
+    async fn kinds_do_not_share_slots() {
+        let config = config(1, 10);
+        let executors = RequestExecutors::new(&config);
+        let _send = executors
+            .enter(&config, RequestCode::SendMessage)
+            .await
+            .unwrap();
+        assert!(executors
+            .enter(&config, RequestCode::PullMessage)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn waiting_request_gets_slot_once_released() {
+        let config = config(1, 10);
+        let executors = Arc::new(RequestExecutors::new(&config));
+        let held = executors
+            .enter(&config, RequestCode::SendMessage)
+            .await
+            .unwrap();
+
+        let waiter = {
+            let executors = Arc::clone(&executors);
+            let config = config.clone();
+            tokio::spawn(async move {
+                executors
+                    .enter(&config, RequestCode::SendMessageV2)
+                    .await
+                    .is_ok()
+            })
+        };
+        while executors.queue_size(RequestQueueKind::Send) == 0 {
+            tokio::task::yield_now().await;
+        }
+        drop(held);
+        assert!(waiter.await.unwrap());
+        assert_eq!(executors.queue_size(RequestQueueKind::Send), 0);
+    }
+
+    #[tokio::test]
+    async fn full_queue_rejects_request() {
+        let config = config(1, 0);
+        let executors = RequestExecutors::new(&config);
+        let _held = executors
+            .enter(&config, RequestCode::SendMessage)
+            .await
+            .unwrap();
+        let remark = executors
+            .enter(&config, RequestCode::SendMessage)
+            .await
+            .unwrap_err();
+        assert!(remark.starts_with("[OVERLOAD]system busy"));
+    }
+
+    #[tokio::test]
+    async fn busy_kind_does_not_hold_up_other_kinds() {
+        let config = config(1, 10);
+        let executors = RequestExecutors::new(&config);
+        let held = executors
+            .enter(&config, RequestCode::SendMessage)
+            .await
+            .unwrap();
+
+        let (send_done, send_ran) = oneshot::channel();
+        executors
+            .execute(
+                &config,
+                RequestCode::SendMessage,
+                move |admitted| async move {
+                    let _ = send_done.send(admitted);
+                },
+            )
+            .unwrap();
+        let (heartbeat_done, heartbeat_ran) = oneshot::channel();
+        executors
+            .execute(
+                &config,
+                RequestCode::HeartBeat,
+                move |admitted| async move {
+                    let _ = heartbeat_done.send(admitted);
+                },
+            )
+            .unwrap();
+
+        assert_eq!(heartbeat_ran.await.unwrap(), Ok(()));
+        assert_eq!(executors.queue_size(RequestQueueKind::Send), 1);
+        drop(held);
+        assert_eq!(send_ran.await.unwrap(), Ok(()));
+    }
+
+    #[tokio::test]
+    async fn failed_request_runs_with_remark() {
+        let config = config(1, 10);
+        let executors = RequestExecutors::new(&config);
+        let _held = executors
+            .enter(&config, RequestCode::SendMessage)
+            .await
+            .unwrap();
+
+        let (done, ran) = oneshot::channel();
+        executors
+            .execute(
+                &config,
+                RequestCode::SendMessage,
+                move |admitted| async move {
+                    let _ = done.send(admitted);
+                },
+            )
+            .unwrap();
+        assert!(executors.fail_oldest(RequestQueueKind::Send, "TEST", get_current_millis(), 0));
+        let remark = ran.await.unwrap().unwrap_err();
+        assert!(remark.starts_with("[TEST]broker busy"));
+    }
+
+    #[tokio::test]
+    async fn full_queue_rejects_task() {
+        let config = config(1, 0);
+        let executors = RequestExecutors::new(&config);
+        let _held = executors
+            .enter(&config, RequestCode::SendMessage)
+            .await
+            .unwrap();
+        let remark = executors
+            .execute(&config, RequestCode::SendMessage, |_| async {})
+            .unwrap_err();
+        assert!(remark.starts_with("[OVERLOAD]system busy"));
+    }
+}
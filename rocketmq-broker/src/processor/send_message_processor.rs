@@ -22,6 +22,8 @@ use std::time::Instant;
 use cheetah_string::CheetahString;
 use rand::Rng;
 use rocketmq_common::common::attribute::cleanup_policy::CleanupPolicy;
+use rocketmq_common::common::attribute::topic_message_type::TopicMessageType;
+use rocketmq_common::common::config::TopicConfig;
 use rocketmq_common::common::constant::PermName;
 use rocketmq_common::common::key_builder::KeyBuilder;
 use rocketmq_common::common::message::message_batch::MessageExtBatch;
@@ -34,6 +36,7 @@ use rocketmq_common::common::message::MessageTrait;
 use rocketmq_common::common::mix_all;
 use rocketmq_common::common::mix_all::RETRY_GROUP_TOPIC_PREFIX;
 use rocketmq_common::common::mq_version::RocketMqVersion;
+use rocketmq_common::common::stats::Stats;
 use rocketmq_common::common::sys_flag::message_sys_flag::MessageSysFlag;
 use rocketmq_common::common::topic::TopicValidator;
 use rocketmq_common::common::FAQUrl;
@@ -94,7 +97,11 @@ where
     TS: TransactionalMessageService,
 {
     pub fn has_send_message_hook(&self) -> bool {
-        self.inner.send_message_hook_vec.is_empty()
+        !self.inner.send_message_hook_vec.is_empty()
+    }
+
+    pub fn register_send_message_hook(&mut self, hook: Box<dyn SendMessageHook>) {
+        self.inner.send_message_hook_vec.push(hook);
     }
 
     fn clear_reserved_properties(request_header: &mut SendMessageRequestHeader) {
@@ -180,6 +187,39 @@ where
     MS: MessageStore,
     TS: TransactionalMessageService,
 {
+    /// Checks the type of a sent message against the `message.type` attribute of its topic as
+    /// RocketMQ 5.x does, returns the reason the message is rejected.
+    ///
+    /// `MIXED` topics take every type. Retry and system topics are not checked, they take the
+    /// messages the broker sends back and schedules itself.
+    fn check_topic_message_type(
+        &self,
+        topic_config: &TopicConfig,
+        properties: &HashMap<CheetahString, CheetahString>,
+    ) -> Option<String> {
+        if !self
+            .inner
+            .broker_runtime_inner
+            .broker_config()
+            .enable_topic_message_type_check
+        {
+            return None;
+        }
+        let topic = topic_config.topic_name.clone().unwrap_or_default();
+        if topic.starts_with(RETRY_GROUP_TOPIC_PREFIX) || TopicValidator::is_system_topic(&topic) {
+            return None;
+        }
+        let topic_message_type = topic_config.get_topic_message_type();
+        let message_type = TopicMessageType::parse_from_message_property(properties);
+        if topic_message_type == TopicMessageType::Mixed || message_type == topic_message_type {
+            return None;
+        }
+        Some(format!(
+            "The message type {} does not match the message type {} of topic[{}]",
+            message_type, topic_message_type, topic
+        ))
+    }
+
     pub fn new(
         /*topic_queue_mapping_manager: Arc<TopicQueueMappingManager>,
         subscription_group_manager: Arc<SubscriptionGroupManager<MS>>,
@@ -271,6 +311,14 @@ where
                     )),
             ));
         }
+        let properties = string_to_message_properties(request_header.properties.as_ref());
+        if let Some(remark) = self.check_topic_message_type(&topic_config, &properties) {
+            return Ok(Some(
+                response
+                    .set_code(ResponseCode::MessageIllegal)
+                    .set_remark(remark),
+            ));
+        }
         let mut message_ext = MessageExtBrokerInner::default();
         message_ext.message_ext_inner.message.topic = request_header.topic().clone();
         message_ext.message_ext_inner.queue_id = queue_id;
@@ -283,9 +331,7 @@ where
         message_ext
             .message_ext_inner
             .message
-            .set_properties(string_to_message_properties(
-                request_header.properties.as_ref(),
-            ));
+            .set_properties(properties);
         message_ext
             .message_ext_inner
             .message
@@ -498,6 +544,13 @@ where
         ) {
             return Ok(Some(response));
         }
+        if let Some(remark) = self.check_topic_message_type(&topic_config, &ori_props) {
+            return Ok(Some(
+                response
+                    .set_code(ResponseCode::MessageIllegal)
+                    .set_remark(remark),
+            ));
+        }
         message_ext
             .message_ext_inner
             .message
@@ -587,6 +640,13 @@ where
             false
         };
 
+        if message_ext
+            .topic()
+            .starts_with(mix_all::DLQ_GROUP_TOPIC_PREFIX)
+        {
+            self.inner.put_audit_dlq_message(&message_ext);
+        }
+
         let start = Instant::now();
         let topic = message_ext.topic().clone();
         let transaction_id =
@@ -764,6 +824,7 @@ where
             .read_custom_header_mut::<SendMessageResponseHeader>()
             .unwrap();
         if send_ok {
+            let store_rt = begin_time_millis.elapsed().as_millis() as i64;
             if TopicValidator::RMQ_SYS_SCHEDULE_TOPIC == topic {
                 self.inner
                     .broker_runtime_inner
@@ -814,11 +875,7 @@ where
             self.inner
                 .broker_runtime_inner
                 .broker_stats_manager()
-                .inc_topic_put_latency(
-                    topic,
-                    queue_id_int,
-                    begin_time_millis.elapsed().as_millis() as i32,
-                );
+                .inc_topic_put_latency(topic, queue_id_int, store_rt as i32);
 
             response_header.set_msg_id(
                 put_message_result
@@ -835,6 +892,12 @@ where
                     .logics_offset,
             );
             response_header.set_transaction_id(transaction_id);
+            response_header.set_store_rt(Some(store_rt));
+            response_header.set_page_cache_rt(
+                put_message_result
+                    .append_message_result()
+                    .map(|append_message_result| append_message_result.page_cache_rt),
+            );
 
             let rewrite_result =
                 rewrite_response_for_static_topic(response_header, mapping_context);
@@ -847,6 +910,33 @@ where
 
             ctx.write(response.set_opaque(request.opaque())).await;
 
+            if let Some(owner) = owner.as_ref().filter(|owner| !owner.is_empty()) {
+                let broker_config = self.inner.broker_runtime_inner.broker_config();
+                let wrote_size = put_message_result
+                    .append_message_result()
+                    .unwrap()
+                    .wrote_bytes;
+                let commercial_msg_num =
+                    (wrote_size as f64 / commercial_size_per_msg as f64).ceil() as i32;
+                let broker_stats_manager = self.inner.broker_runtime_inner.broker_stats_manager();
+                broker_stats_manager.inc_commercial_value(
+                    Stats::COMMERCIAL_SEND_TIMES,
+                    owner,
+                    "",
+                    topic,
+                    StatsType::SendSuccess,
+                    commercial_msg_num * broker_config.commercial_base_count,
+                );
+                broker_stats_manager.inc_commercial_value(
+                    Stats::COMMERCIAL_SEND_SIZE,
+                    owner,
+                    "",
+                    topic,
+                    StatsType::SendSuccess,
+                    wrote_size,
+                );
+            }
+
             if self.has_send_message_hook() {
                 send_message_context.msg_id = CheetahString::from_string(msg_id);
                 send_message_context.queue_id = queue_id;
@@ -876,6 +966,10 @@ where
                 send_message_context.account_owner_self = owner_self.unwrap_or_default();
                 send_message_context.send_msg_size = wrote_size;
                 send_message_context.send_msg_num = msg_num;
+                send_message_context.code = RemotingSysResponseCode::Success as i32;
+                send_message_context.is_success = true;
+                self.inner
+                    .execute_send_message_hook_after(None, send_message_context);
             }
             None
         } else {
@@ -900,6 +994,11 @@ where
                 send_message_context.account_owner_self = owner_self.unwrap_or_default();
                 send_message_context.send_msg_size = wrote_size;
                 send_message_context.send_msg_num = msg_num;
+                send_message_context.code = response.code();
+                send_message_context.error_msg = response.remark().cloned().unwrap_or_default();
+                send_message_context.is_success = false;
+                self.inner
+                    .execute_send_message_hook_after(None, send_message_context);
             }
             Some(response)
         }
@@ -963,7 +1062,7 @@ where
         response: &mut RemotingCommand,
         request: &RemotingCommand,
         msg: &mut MessageExt,
-        topic_config: &mut rocketmq_common::common::config::TopicConfig,
+        topic_config: &mut TopicConfig,
         properties: &mut HashMap<CheetahString, CheetahString>,
     ) -> bool {
         let mut new_topic = request_header.topic();
@@ -1015,32 +1114,34 @@ where
                     CheetahString::from_static_str(MessageConst::PROPERTY_DELAY_TIME_LEVEL),
                     CheetahString::from_string("-1".to_string()),
                 );
+                let origin_topic = properties
+                    .get(MessageConst::PROPERTY_RETRY_TOPIC)
+                    .unwrap_or(new_topic)
+                    .clone();
+                let origin_msg_id = properties
+                    .get(MessageConst::PROPERTY_ORIGIN_MESSAGE_ID)
+                    .or_else(|| {
+                        properties.get(MessageConst::PROPERTY_UNIQ_CLIENT_MESSAGE_ID_KEYIDX)
+                    })
+                    .cloned();
+                mark_dead_letter(properties, &origin_topic, origin_msg_id.as_ref());
                 let topic_ =
                     CheetahString::from_string(mix_all::get_dlq_topic(group_name.as_str()));
                 new_topic = &topic_;
-                let queue_id_int = self.inner.random_queue_id(DLQ_NUMS_PER_GROUP) as i32;
-                let new_topic_config = self
-                    .inner
-                    .broker_runtime_inner
-                    .topic_config_manager_mut()
-                    .create_topic_in_send_message_back_method(
-                        new_topic,
-                        DLQ_NUMS_PER_GROUP as i32,
-                        PermName::PERM_WRITE | PermName::PERM_READ,
-                        false,
-                        0,
-                    );
-                // can optimize
-                msg.message.topic = CheetahString::from_string(new_topic.to_string());
-                msg.queue_id = queue_id_int;
-                msg.message.set_delay_time_level(0);
-                if new_topic_config.is_none() {
+                let Some(new_topic_config) = self.inner.create_dlq_topic(group_name.as_str())
+                else {
                     response
                         .with_code(ResponseCode::SystemError)
                         .with_remark(format!("topic {} not exist, apply DLQ failed", new_topic));
                     return false;
-                }
-                *topic_config = new_topic_config.unwrap();
+                };
+                msg.message.topic = new_topic.clone();
+                msg.queue_id = self
+                    .inner
+                    .random_queue_id(new_topic_config.write_queue_nums)
+                    as i32;
+                msg.message.set_delay_time_level(0);
+                *topic_config = new_topic_config;
             }
         }
 
@@ -1053,7 +1154,28 @@ where
     }
 }
 
-const DLQ_NUMS_PER_GROUP: u32 = 1;
+const MAX_DLQ_QUEUE_NUMS: i32 = 16;
+
+/// Records the topic and the id a dead letter was originally sent with, a message dead lettered
+/// again keeps the ones of its first time.
+fn mark_dead_letter(
+    properties: &mut HashMap<CheetahString, CheetahString>,
+    origin_topic: &CheetahString,
+    origin_msg_id: Option<&CheetahString>,
+) {
+    properties
+        .entry(CheetahString::from_static_str(
+            MessageConst::PROPERTY_DLQ_ORIGIN_TOPIC,
+        ))
+        .or_insert_with(|| origin_topic.clone());
+    if let Some(origin_msg_id) = origin_msg_id {
+        properties
+            .entry(CheetahString::from_static_str(
+                MessageConst::PROPERTY_DLQ_ORIGIN_MESSAGE_ID,
+            ))
+            .or_insert_with(|| origin_msg_id.clone());
+    }
+}
 
 pub(crate) struct Inner<MS, TS> {
     /*pub(crate) topic_config_manager: TopicConfigManager,
@@ -1083,7 +1205,7 @@ where
 {
     #[inline]
     pub fn has_send_message_hook(&self) -> bool {
-        self.send_message_hook_vec.is_empty()
+        !self.send_message_hook_vec.is_empty()
     }
 
     #[inline]
@@ -1267,25 +1389,15 @@ where
 
         let is_dlq = if msg_ext.reconsume_times >= max_reconsume_times || delay_level < 0 {
             new_topic = CheetahString::from_string(mix_all::get_dlq_topic(&request_header.group));
-            queue_id_int = 0;
-            let topic_config_inner = self
-                .broker_runtime_inner
-                .topic_config_manager_mut()
-                .create_topic_in_send_message_back_method(
-                    &new_topic,
-                    DLQ_NUMS_PER_GROUP as i32,
-                    PermName::PERM_WRITE | PermName::PERM_READ,
-                    false,
-                    0,
-                );
-            if topic_config_inner.is_none() {
+            let Some(dlq_topic_config) = self.create_dlq_topic(&request_header.group) else {
                 return Ok(Some(
                     RemotingCommand::create_response_command_with_code_remark(
                         ResponseCode::SystemError,
                         format!("topic {} not exist", new_topic),
                     ),
                 ));
-            }
+            };
+            queue_id_int = self.random_queue_id(dlq_topic_config.write_queue_nums) as i32;
             msg_ext.set_delay_time_level(0);
             true
         } else {
@@ -1318,8 +1430,24 @@ where
         } else {
             msg_ext.msg_id.clone()
         };
+        if is_dlq {
+            let origin_topic = msg_ext
+                .get_property(&CheetahString::from_static_str(
+                    MessageConst::PROPERTY_RETRY_TOPIC,
+                ))
+                .unwrap_or_else(|| msg_ext.get_topic().clone());
+            mark_dead_letter(
+                &mut msg_inner.message_ext_inner.message.properties,
+                &origin_topic,
+                Some(&origin_msg_id),
+            );
+        }
         MessageAccessor::set_origin_message_id(&mut msg_inner, origin_msg_id);
-        msg_inner.properties_string = message_properties_to_string(msg_ext.get_properties());
+        msg_inner.properties_string =
+            message_properties_to_string(&msg_inner.message_ext_inner.message.properties);
+        if is_dlq {
+            self.put_audit_dlq_message(&msg_inner);
+        }
 
         let inner_topic = msg_inner.get_topic().clone();
         let put_message_result = self
@@ -1334,20 +1462,32 @@ where
             .and_then(|value| value.get(BrokerStatsManager::COMMERCIAL_OWNER).cloned());
         let (response, succeeded) = match put_message_result.put_message_status() {
             PutMessageStatus::PutOk => {
-                let mut _back_topic = msg_ext.get_topic().clone();
+                let mut back_topic = msg_ext.get_topic().clone();
                 let correct_topic = msg_ext.get_property(&CheetahString::from_static_str(
                     MessageConst::PROPERTY_RETRY_TOPIC,
                 ));
                 if let Some(topic) = correct_topic {
-                    _back_topic = topic;
+                    back_topic = topic;
                 }
 
                 if TopicValidator::RMQ_SYS_SCHEDULE_TOPIC == inner_topic {
                     //TODO: implement this
                 }
+                self.broker_runtime_inner
+                    .broker_stats_manager()
+                    .inc_send_back_nums(request_header.group.as_str(), back_topic.as_str());
 
                 if is_dlq {
-                    // TODO: implement this
+                    let broker_stats_manager = self.broker_runtime_inner.broker_stats_manager();
+                    broker_stats_manager
+                        .inc_dlq_put_nums(request_header.group.as_str(), back_topic.as_str());
+                    broker_stats_manager.inc_dlq_stat_value(
+                        commercial_owner.as_deref().unwrap_or_default(),
+                        request_header.group.as_str(),
+                        back_topic.as_str(),
+                        StatsType::SendBackToDlq,
+                        1,
+                    );
                 }
                 (RemotingCommand::create_response_command(), true)
             }
@@ -1576,8 +1716,17 @@ where
             }
         }
 
-        let queue_id_int = request_header.queue_id;
         let topic_config_inner = topic_config.as_ref().unwrap();
+        if !PermName::is_writeable(topic_config_inner.perm) {
+            response.with_code(ResponseCode::NoPermission);
+            response.with_remark(format!(
+                "the topic[{}] sending message is forbidden",
+                request_header.topic.as_str()
+            ));
+            return;
+        }
+
+        let queue_id_int = request_header.queue_id;
         let id_valid = topic_config_inner
             .write_queue_nums
             .max(topic_config_inner.read_queue_nums);
@@ -1592,6 +1741,68 @@ where
         }
     }
 
+    /// Creates the DLQ topic of `group` on its first dead letter, with the perm and the queue
+    /// nums configured for DLQ topics.
+    pub(crate) fn create_dlq_topic(&mut self, group: &str) -> Option<TopicConfig> {
+        let dlq_topic = CheetahString::from_string(mix_all::get_dlq_topic(group));
+        self.create_dead_letter_topic(&dlq_topic)
+    }
+
+    fn create_dead_letter_topic(&mut self, topic: &CheetahString) -> Option<TopicConfig> {
+        let broker_config = self.broker_runtime_inner.broker_config();
+        let queue_nums = broker_config.dlq_queue_nums.clamp(1, MAX_DLQ_QUEUE_NUMS);
+        let perm = broker_config.dlq_topic_perm;
+        self.broker_runtime_inner
+            .topic_config_manager_mut()
+            .create_topic_in_send_message_back_method(topic, queue_nums, perm, false, 0)
+    }
+
+    /// Puts a copy of the dead letter `msg` to the audit DLQ topic, if enabled.
+    ///
+    /// The copy is put in the background, a failure is only logged and does not fail the put
+    /// of the dead letter itself.
+    pub(crate) fn put_audit_dlq_message(&mut self, msg: &MessageExtBrokerInner) {
+        if !self.broker_runtime_inner.broker_config().enable_audit_dlq {
+            return;
+        }
+        let audit_topic = self
+            .broker_runtime_inner
+            .broker_config()
+            .audit_dlq_topic
+            .clone();
+        let Some(mut message_store) = self.broker_runtime_inner.message_store().clone() else {
+            warn!(
+                "put dead letter copy to audit DLQ topic {} failed: no message store",
+                audit_topic
+            );
+            return;
+        };
+        let Some(topic_config) = self.create_dead_letter_topic(&audit_topic) else {
+            warn!("create audit DLQ topic {} failed", audit_topic);
+            return;
+        };
+        let mut copy = MessageExtBrokerInner {
+            message_ext_inner: msg.message_ext_inner.clone(),
+            properties_string: msg.properties_string.clone(),
+            tags_code: msg.tags_code,
+            ..Default::default()
+        };
+        copy.message_ext_inner.message.topic = audit_topic;
+        copy.message_ext_inner.queue_id =
+            self.random_queue_id(topic_config.write_queue_nums) as i32;
+        tokio::spawn(async move {
+            let topic = copy.topic().clone();
+            let put_message_result = message_store.put_message(copy).await;
+            if put_message_result.put_message_status() != PutMessageStatus::PutOk {
+                warn!(
+                    "put dead letter copy to audit DLQ topic {} failed: {}",
+                    topic,
+                    put_message_result.put_message_status()
+                );
+            }
+        });
+    }
+
     pub(crate) fn random_queue_id(&self, write_queue_nums: u32) -> u32 {
         rand::thread_rng().gen_range(0..=99999999) % write_queue_nums
     }
@@ -1624,3 +1835,60 @@ fn rewrite_response_for_static_topic(
     response_header.set_queue_offset(static_logic_offset);
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mark_dead_letter_records_the_origin() {
+        let mut properties = HashMap::new();
+        mark_dead_letter(
+            &mut properties,
+            &CheetahString::from("topic"),
+            Some(&CheetahString::from("msg-id")),
+        );
+        assert_eq!(
+            properties.get(MessageConst::PROPERTY_DLQ_ORIGIN_TOPIC),
+            Some(&CheetahString::from("topic"))
+        );
+        assert_eq!(
+            properties.get(MessageConst::PROPERTY_DLQ_ORIGIN_MESSAGE_ID),
+            Some(&CheetahString::from("msg-id"))
+        );
+    }
+
+    #[test]
+    fn mark_dead_letter_without_message_id_only_records_the_topic() {
+        let mut properties = HashMap::new();
+        mark_dead_letter(&mut properties, &CheetahString::from("topic"), None);
+        assert_eq!(
+            properties.get(MessageConst::PROPERTY_DLQ_ORIGIN_TOPIC),
+            Some(&CheetahString::from("topic"))
+        );
+        assert!(!properties.contains_key(MessageConst::PROPERTY_DLQ_ORIGIN_MESSAGE_ID));
+    }
+
+    #[test]
+    fn mark_dead_letter_again_keeps_the_first_origin() {
+        let mut properties = HashMap::new();
+        mark_dead_letter(
+            &mut properties,
+            &CheetahString::from("topic"),
+            Some(&CheetahString::from("msg-id")),
+        );
+        mark_dead_letter(
+            &mut properties,
+            &CheetahString::from("%RETRY%group"),
+            Some(&CheetahString::from("retry-msg-id")),
+        );
+        assert_eq!(
+            properties.get(MessageConst::PROPERTY_DLQ_ORIGIN_TOPIC),
+            Some(&CheetahString::from("topic"))
+        );
+        assert_eq!(
+            properties.get(MessageConst::PROPERTY_DLQ_ORIGIN_MESSAGE_ID),
+            Some(&CheetahString::from("msg-id"))
+        );
+    }
+}
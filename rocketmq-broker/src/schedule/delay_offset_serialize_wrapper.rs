@@ -24,14 +24,33 @@ use serde::Serialize;
 #[serde(rename_all = "camelCase")]
 pub struct DelayOffsetSerializeWrapper {
     offset_table: HashMap<i32 /* level */, i64 /* offset */>,
+    /// Delay of every level with an offset, files written before it was added have none.
+    #[serde(default)]
+    delay_level_table: HashMap<i32 /* level */, i64 /* delay millis */>,
     data_version: DataVersion,
 }
 
 impl DelayOffsetSerializeWrapper {
+    pub fn new(
+        offset_table: HashMap<i32, i64>,
+        delay_level_table: HashMap<i32, i64>,
+        data_version: DataVersion,
+    ) -> Self {
+        Self {
+            offset_table,
+            delay_level_table,
+            data_version,
+        }
+    }
+
     pub fn offset_table(&self) -> &HashMap<i32, i64> {
         &self.offset_table
     }
 
+    pub fn delay_level_table(&self) -> &HashMap<i32, i64> {
+        &self.delay_level_table
+    }
+
     pub fn data_version(&self) -> &DataVersion {
         &self.data_version
     }
@@ -14,59 +14,594 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
-
+use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::time::Duration;
 
-use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use cheetah_string::CheetahString;
+use parking_lot::Mutex;
+use parking_lot::RwLock;
 use rocketmq_common::common::config_manager::ConfigManager;
+use rocketmq_common::common::message::message_decoder;
+use rocketmq_common::common::message::message_ext::MessageExt;
+use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+use rocketmq_common::common::message::MessageConst;
+use rocketmq_common::common::message::MessageTrait;
+use rocketmq_common::common::topic::TopicValidator;
+use rocketmq_common::utils::serde_json_utils::SerdeJsonUtils;
+use rocketmq_common::MessageAccessor::MessageAccessor;
+use rocketmq_common::TimeUtils::get_current_millis;
+use rocketmq_remoting::protocol::DataVersion;
+use rocketmq_rust::ArcMut;
+use rocketmq_store::base::message_iterator::MessageIterator;
+use rocketmq_store::base::message_status_enum::PutMessageStatus;
+use rocketmq_store::config::message_store_config::MessageStoreConfig;
+use rocketmq_store::log_file::MessageStore;
 use rocketmq_store::store_path_config_helper::get_delay_offset_store_path;
+use tokio::sync::Notify;
+use tracing::error;
+use tracing::info;
 use tracing::warn;
 
-#[derive(Default, Clone)]
+use crate::broker_runtime::BrokerRuntimeInner;
+use crate::schedule::delay_offset_serialize_wrapper::DelayOffsetSerializeWrapper;
+
+/// Wait before the first delivery of every level after start.
+const FIRST_DELAY_TIME: u64 = 1000;
+/// Wait before looking at a level again once its queue is drained.
+const DELAY_FOR_A_WHILE: u64 = 100;
+/// Wait before retrying a level whose redelivery failed.
+const DELAY_FOR_A_PERIOD: u64 = 10_000;
+/// Messages read from the queue of a level at once.
+const DELIVER_BATCH_SIZE: i32 = 32;
+
+/// Delivers the messages sent with a delay level.
+///
+/// Such messages are parked in the queue of their level of `SCHEDULE_TOPIC_XXXX` when stored.
+/// One timer per level walks that queue and puts every message whose delay is over back to its
+/// real topic. The position of every timer is persisted to `delayOffset.json`, together with
+/// the delay of every level.
+///
+/// The level table can be replaced at runtime. Levels dropped from it keep being delivered with
+/// their last delay until their queue is drained, so that no parked message is lost.
+#[derive(Clone)]
 pub struct ScheduleMessageService {
-    pub(crate) broker_config: Arc<BrokerConfig>,
+    store_path_root_dir: CheetahString,
+    flush_delay_offset_interval: u64,
+    delay_level_table: Arc<RwLock<BTreeMap<i32 /* level */, i64 /* delay millis */>>>,
+    /// Levels dropped from the level table whose queue still holds messages.
+    retired_level_table: Arc<RwLock<BTreeMap<i32 /* level */, i64 /* delay millis */>>>,
+    /// Levels with a running delivery timer.
+    running_levels: Arc<Mutex<HashSet<i32>>>,
+    offset_table: Arc<RwLock<HashMap<i32 /* level */, i64 /* offset */>>>,
+    data_version: Arc<RwLock<DataVersion>>,
+    started: Arc<AtomicBool>,
+    notify: Arc<Notify>,
 }
 
 impl ScheduleMessageService {
+    pub fn new(message_store_config: &MessageStoreConfig) -> Self {
+        let delay_level_table = Self::parse_delay_level(&message_store_config.message_delay_level)
+            .unwrap_or_else(|| {
+                error!(
+                    "parse messageDelayLevel {} failed, delay messages can not be delivered",
+                    message_store_config.message_delay_level
+                );
+                BTreeMap::new()
+            });
+        Self {
+            store_path_root_dir: message_store_config.store_path_root_dir.clone(),
+            flush_delay_offset_interval: message_store_config.flush_delay_offset_interval as u64,
+            delay_level_table: Arc::new(RwLock::new(delay_level_table)),
+            retired_level_table: Arc::new(RwLock::new(BTreeMap::new())),
+            running_levels: Arc::new(Mutex::new(HashSet::new())),
+            offset_table: Arc::new(RwLock::new(HashMap::new())),
+            data_version: Arc::new(RwLock::new(DataVersion::new())),
+            started: Arc::new(AtomicBool::new(false)),
+            notify: Arc::new(Notify::new()),
+        }
+    }
+
+    /// Parses a level table like `1s 5s 10s 1m 2h 1d`, level `1` being the first entry.
+    pub fn parse_delay_level(level_string: &str) -> Option<BTreeMap<i32, i64>> {
+        let mut delay_level_table = BTreeMap::new();
+        for (index, value) in level_string.split_whitespace().enumerate() {
+            let unit = value.chars().last()?;
+            let unit_millis = match unit {
+                's' => 1000,
+                'm' => 1000 * 60,
+                'h' => 1000 * 60 * 60,
+                'd' => 1000 * 60 * 60 * 24,
+                _ => return None,
+            };
+            let num = value[..value.len() - unit.len_utf8()].parse::<i64>().ok()?;
+            delay_level_table.insert(index as i32 + 1, num * unit_millis);
+        }
+        Some(delay_level_table)
+    }
+
     pub fn delay_level2queue_id(delay_level: i32) -> i32 {
         delay_level - 1
     }
 
-    pub fn build_running_stats(&self, _stats: &mut HashMap<String, String>) {
-        //TODO
+    pub fn queue_id2delay_level(queue_id: i32) -> i32 {
+        queue_id + 1
     }
 
     pub fn get_max_delay_level(&self) -> i32 {
-        0
+        self.delay_level_table
+            .read()
+            .keys()
+            .next_back()
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Delay of `delay_level`, also for a level that was dropped but is not drained yet.
+    fn level_delay(&self, delay_level: i32) -> Option<i64> {
+        self.delay_level_table
+            .read()
+            .get(&delay_level)
+            .or(self.retired_level_table.read().get(&delay_level))
+            .copied()
+    }
+
+    pub fn compute_deliver_timestamp(&self, delay_level: i32, store_timestamp: i64) -> i64 {
+        match self.level_delay(delay_level) {
+            Some(delay) => store_timestamp + delay,
+            None => store_timestamp + 1000,
+        }
+    }
+
+    /// Delivers at once the messages whose deliver time lies further ahead than their delay, as
+    /// happens when the clock of the broker was set back.
+    fn correct_deliver_timestamp(&self, now: i64, deliver_timestamp: i64, delay_level: i32) -> i64 {
+        let max_timestamp = now + self.level_delay(delay_level).unwrap_or_default();
+        if deliver_timestamp > max_timestamp {
+            now
+        } else {
+            deliver_timestamp
+        }
+    }
+
+    fn delay_offset(&self, delay_level: i32) -> i64 {
+        self.offset_table
+            .read()
+            .get(&delay_level)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn update_offset(&self, delay_level: i32, offset: i64) {
+        self.offset_table.write().insert(delay_level, offset);
+        self.data_version.write().next_version();
+    }
+
+    pub fn is_started(&self) -> bool {
+        self.started.load(Ordering::Acquire)
+    }
+
+    /// Starts one delivery timer per level, and the periodic persistence of their offsets.
+    pub fn start<MS: MessageStore>(&self, broker_runtime_inner: ArcMut<BrokerRuntimeInner<MS>>) {
+        if self.started.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        info!(
+            "ScheduleMessageService start, {} delay levels",
+            self.delay_level_table.read().len()
+        );
+        self.start_level_timers(&broker_runtime_inner);
+        if self.flush_delay_offset_interval > 0 {
+            let this = self.clone();
+            let interval = Duration::from_millis(self.flush_delay_offset_interval);
+            tokio::spawn(async move {
+                loop {
+                    tokio::select! {
+                        _ = tokio::time::sleep(interval) => {}
+                        _ = this.notify.notified() => {}
+                    }
+                    if !this.is_started() {
+                        break;
+                    }
+                    this.persist();
+                }
+            });
+        }
+    }
+
+    /// Starts the delivery timer of every level, active or retired, that has none.
+    fn start_level_timers<MS: MessageStore>(
+        &self,
+        broker_runtime_inner: &ArcMut<BrokerRuntimeInner<MS>>,
+    ) {
+        let levels = self
+            .delay_level_table
+            .read()
+            .keys()
+            .chain(self.retired_level_table.read().keys())
+            .copied()
+            .collect::<Vec<_>>();
+        let mut running_levels = self.running_levels.lock();
+        for delay_level in levels {
+            if !running_levels.insert(delay_level) {
+                continue;
+            }
+            let this = self.clone();
+            let mut broker_runtime_inner = broker_runtime_inner.clone();
+            tokio::spawn(async move {
+                let mut wait = FIRST_DELAY_TIME;
+                loop {
+                    tokio::select! {
+                        _ = tokio::time::sleep(Duration::from_millis(wait)) => {}
+                        _ = this.notify.notified() => {}
+                    }
+                    if !this.keep_level_timer(delay_level) {
+                        break;
+                    }
+                    wait = this.deliver(delay_level, &mut broker_runtime_inner).await;
+                }
+            });
+        }
+    }
+
+    /// Whether the timer of `delay_level` goes on, a timer that stops deregisters itself.
+    fn keep_level_timer(&self, delay_level: i32) -> bool {
+        // checked under the lock, so that a level added again meanwhile gets a new timer
+        let mut running_levels = self.running_levels.lock();
+        if self.is_started() && self.level_delay(delay_level).is_some() {
+            return true;
+        }
+        running_levels.remove(&delay_level);
+        false
+    }
+
+    /// Replaces the level table with the one parsed from `level_string`.
+    ///
+    /// New levels get a timer at once, levels whose delay changed are delivered with the new
+    /// delay from the next round on, and dropped levels are retired once their queue is drained.
+    pub fn update_delay_level_table<MS: MessageStore>(
+        &self,
+        level_string: &str,
+        broker_runtime_inner: &ArcMut<BrokerRuntimeInner<MS>>,
+    ) -> Result<(), String> {
+        let new_table = Self::parse_delay_level(level_string)
+            .ok_or_else(|| format!("invalid messageDelayLevel {}", level_string))?;
+        self.replace_delay_level_table(new_table);
+        if self.is_started() {
+            self.start_level_timers(broker_runtime_inner);
+        }
+        info!(
+            "ScheduleMessageService update messageDelayLevel to {}, retired levels: {:?}",
+            level_string,
+            self.retired_level_table.read().keys()
+        );
+        self.persist();
+        Ok(())
+    }
+
+    fn replace_delay_level_table(&self, new_table: BTreeMap<i32, i64>) {
+        {
+            let mut delay_level_table = self.delay_level_table.write();
+            let mut retired_level_table = self.retired_level_table.write();
+            for (&delay_level, &delay) in delay_level_table.iter() {
+                if !new_table.contains_key(&delay_level) {
+                    retired_level_table.insert(delay_level, delay);
+                }
+            }
+            retired_level_table.retain(|delay_level, _| !new_table.contains_key(delay_level));
+            *delay_level_table = new_table;
+        }
+        self.data_version.write().next_version();
+    }
+
+    /// Puts the messages of `delay_level` whose delay is over back to their real topic, and
+    /// returns how long to wait before the next round.
+    async fn deliver<MS: MessageStore>(
+        &self,
+        delay_level: i32,
+        broker_runtime_inner: &mut ArcMut<BrokerRuntimeInner<MS>>,
+    ) -> u64 {
+        let topic = CheetahString::from_static_str(TopicValidator::RMQ_SYS_SCHEDULE_TOPIC);
+        let queue_id = Self::delay_level2queue_id(delay_level);
+        let offset = self.delay_offset(delay_level);
+        let Some(message_store) = broker_runtime_inner.message_store().clone() else {
+            return DELAY_FOR_A_WHILE;
+        };
+        let mut message_iterator =
+            MessageIterator::new(&*message_store, topic.clone(), topic, queue_id, offset)
+                .with_batch_size(DELIVER_BATCH_SIZE);
+        let messages = message_iterator.next_batch().await;
+        let next_begin_offset = message_iterator.next_offset();
+        if messages.is_empty() {
+            if next_begin_offset != offset {
+                warn!(
+                    "ScheduleMessageService, offset {} of delay level {} is illegal, correct it \
+                     to {}",
+                    offset, delay_level, next_begin_offset
+                );
+                self.update_offset(delay_level, next_begin_offset);
+            }
+            // no message is parked for a dropped level any more once its queue is drained
+            if self
+                .retired_level_table
+                .write()
+                .remove(&delay_level)
+                .is_some()
+            {
+                info!(
+                    "ScheduleMessageService, retired delay level {} is drained",
+                    delay_level
+                );
+                self.data_version.write().next_version();
+            }
+            return DELAY_FOR_A_WHILE;
+        }
+        let now = get_current_millis() as i64;
+        // The iterator may have moved over illegal offsets before reading the batch.
+        let mut next_offset = messages[0].queue_offset;
+        for msg_ext in &messages {
+            let deliver_timestamp = self.correct_deliver_timestamp(
+                now,
+                self.compute_deliver_timestamp(delay_level, msg_ext.store_timestamp),
+                delay_level,
+            );
+            let countdown = deliver_timestamp - now;
+            if countdown > 0 {
+                self.update_offset(delay_level, next_offset);
+                return countdown as u64;
+            }
+            let msg_inner = message_time_up(msg_ext);
+            let put_message_result = broker_runtime_inner
+                .escape_bridge()
+                .async_put_message(msg_inner)
+                .await;
+            if put_message_result.put_message_status() != PutMessageStatus::PutOk {
+                error!(
+                    "ScheduleMessageService, a message time up, but reput it failed, topic: {} \
+                     msgId {}, status {:?}",
+                    msg_ext.topic(),
+                    msg_ext.msg_id(),
+                    put_message_result.put_message_status()
+                );
+                self.update_offset(delay_level, next_offset);
+                return DELAY_FOR_A_PERIOD;
+            }
+            next_offset = msg_ext.queue_offset + 1;
+        }
+        self.update_offset(delay_level, next_offset.max(next_begin_offset));
+        if messages.len() as i32 >= DELIVER_BATCH_SIZE {
+            0
+        } else {
+            DELAY_FOR_A_WHILE
+        }
+    }
+
+    /// Adds the delivery progress of every level, as `delayOffset,maxOffset`.
+    pub fn build_running_stats<MS: MessageStore>(
+        &self,
+        message_store: &MS,
+        stats: &mut HashMap<String, String>,
+    ) {
+        let topic = CheetahString::from_static_str(TopicValidator::RMQ_SYS_SCHEDULE_TOPIC);
+        for delay_level in self.all_levels().into_keys() {
+            let max_offset = message_store
+                .get_max_offset_in_queue(&topic, Self::delay_level2queue_id(delay_level));
+            stats.insert(
+                format!("scheduleMessageOffset_{}", delay_level),
+                format!("{},{}", self.delay_offset(delay_level), max_offset),
+            );
+        }
+    }
+
+    /// Delays of the active and the retired levels.
+    fn all_levels(&self) -> HashMap<i32, i64> {
+        let mut levels = self
+            .retired_level_table
+            .read()
+            .iter()
+            .map(|(&delay_level, &delay)| (delay_level, delay))
+            .collect::<HashMap<_, _>>();
+        levels.extend(self.delay_level_table.read().iter());
+        levels
     }
 
     pub fn shutdown(&mut self) {
-        warn!("ScheduleMessageService shutdown not implemented");
+        if self.started.swap(false, Ordering::AcqRel) {
+            self.notify.notify_waiters();
+            info!("ScheduleMessageService shutdown");
+        }
+    }
+}
+
+/// Restores the real topic and queue of a message whose delay is over.
+fn message_time_up(msg_ext: &MessageExt) -> MessageExtBrokerInner {
+    let mut msg_inner = MessageExtBrokerInner {
+        message_ext_inner: msg_ext.clone(),
+        tags_code: MessageExtBrokerInner::tags_string_to_tags_code(
+            msg_ext.get_tags().unwrap_or_default().as_str(),
+        ),
+        ..MessageExtBrokerInner::default()
+    };
+    msg_inner.set_wait_store_msg_ok(false);
+    MessageAccessor::clear_property(&mut msg_inner, MessageConst::PROPERTY_DELAY_TIME_LEVEL);
+    if let Some(real_topic) = msg_ext.get_property(&CheetahString::from_static_str(
+        MessageConst::PROPERTY_REAL_TOPIC,
+    )) {
+        msg_inner.set_topic(real_topic);
+    }
+    if let Some(real_queue_id) = msg_ext
+        .get_property(&CheetahString::from_static_str(
+            MessageConst::PROPERTY_REAL_QUEUE_ID,
+        ))
+        .and_then(|queue_id| queue_id.parse::<i32>().ok())
+    {
+        msg_inner.message_ext_inner.queue_id = real_queue_id;
     }
+    msg_inner.properties_string =
+        message_decoder::message_properties_to_string(msg_inner.get_properties());
+    msg_inner
 }
 
 impl ConfigManager for ScheduleMessageService {
-    fn decode0(&mut self, _key: &[u8], _body: &[u8]) {
-        todo!()
+    fn config_file_path(&self) -> String {
+        get_delay_offset_store_path(self.store_path_root_dir.as_str())
     }
 
-    fn stop(&mut self) -> bool {
-        todo!()
+    fn encode_pretty(&self, pretty_format: bool) -> String {
+        let wrapper = DelayOffsetSerializeWrapper::new(
+            self.offset_table.read().clone(),
+            self.all_levels(),
+            self.data_version.read().clone(),
+        );
+        let json = if pretty_format {
+            SerdeJsonUtils::to_json_pretty(&wrapper)
+        } else {
+            SerdeJsonUtils::to_json(&wrapper)
+        };
+        json.unwrap_or_else(|e| {
+            error!("encode delay offset failed: {}", e);
+            String::new()
+        })
     }
 
-    fn config_file_path(&self) -> String {
-        get_delay_offset_store_path(self.broker_config.store_path_root_dir.as_str())
+    fn decode(&self, json_string: &str) {
+        if json_string.is_empty() {
+            return;
+        }
+        match SerdeJsonUtils::from_json_str::<DelayOffsetSerializeWrapper>(json_string) {
+            Ok(wrapper) => {
+                let delay_level_table = self.delay_level_table.read();
+                let mut retired_level_table = self.retired_level_table.write();
+                // levels dropped from messageDelayLevel while messages were parked for them
+                for (&delay_level, &delay) in wrapper.delay_level_table() {
+                    if !delay_level_table.contains_key(&delay_level) {
+                        retired_level_table.insert(delay_level, delay);
+                    }
+                }
+                let mut offset_table = self.offset_table.write();
+                for (&delay_level, &offset) in wrapper.offset_table() {
+                    if delay_level_table.contains_key(&delay_level)
+                        || retired_level_table.contains_key(&delay_level)
+                    {
+                        offset_table.insert(delay_level, offset);
+                    }
+                }
+                self.data_version
+                    .write()
+                    .assign_new_one(wrapper.data_version());
+            }
+            Err(e) => error!("decode delay offset failed: {}", e),
+        }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    fn encode(&mut self) -> String {
-        todo!()
+    fn new_service() -> ScheduleMessageService {
+        ScheduleMessageService::new(&MessageStoreConfig {
+            message_delay_level: "1s 5s 1m 2h 1d".to_string(),
+            ..MessageStoreConfig::default()
+        })
     }
 
-    fn encode_pretty(&self, _pretty_format: bool) -> String {
-        warn!("ScheduleMessageService encode_pretty not implemented");
-        "".to_string()
+    #[test]
+    fn parses_delay_level_table() {
+        let table = ScheduleMessageService::parse_delay_level("1s 5s 1m 2h 1d").unwrap();
+        assert_eq!(table.len(), 5);
+        assert_eq!(table[&1], 1000);
+        assert_eq!(table[&3], 60_000);
+        assert_eq!(table[&4], 2 * 3_600_000);
+        assert_eq!(table[&5], 86_400_000);
+        assert!(ScheduleMessageService::parse_delay_level("1s 5x").is_none());
+        assert!(ScheduleMessageService::parse_delay_level("s").is_none());
     }
 
-    fn decode(&self, _json_string: &str) {}
+    #[test]
+    fn computes_deliver_timestamp() {
+        let service = new_service();
+        assert_eq!(service.get_max_delay_level(), 5);
+        assert_eq!(service.compute_deliver_timestamp(2, 100), 5100);
+        assert_eq!(service.correct_deliver_timestamp(0, 5000, 2), 5000);
+        assert_eq!(service.correct_deliver_timestamp(0, 6000, 2), 0);
+    }
+
+    #[test]
+    fn restores_real_topic_when_time_up() {
+        let mut msg_ext = MessageExt::default();
+        msg_ext.set_topic(CheetahString::from_static_str(
+            TopicValidator::RMQ_SYS_SCHEDULE_TOPIC,
+        ));
+        msg_ext.queue_id = 1;
+        for (name, value) in [
+            (MessageConst::PROPERTY_REAL_TOPIC, "topic"),
+            (MessageConst::PROPERTY_REAL_QUEUE_ID, "3"),
+            (MessageConst::PROPERTY_DELAY_TIME_LEVEL, "2"),
+        ] {
+            MessageAccessor::put_property(
+                &mut msg_ext,
+                CheetahString::from_static_str(name),
+                CheetahString::from_static_str(value),
+            );
+        }
+        let msg_inner = message_time_up(&msg_ext);
+        assert_eq!(msg_inner.get_topic().as_str(), "topic");
+        assert_eq!(msg_inner.message_ext_inner.queue_id, 3);
+        assert!(msg_inner
+            .get_property(&CheetahString::from_static_str(
+                MessageConst::PROPERTY_DELAY_TIME_LEVEL
+            ))
+            .is_none());
+    }
+
+    #[test]
+    fn persists_offsets_of_known_levels() {
+        let service = new_service();
+        service.update_offset(1, 10);
+        let json = service.encode_pretty(false);
+        let restored = new_service();
+        restored.decode(&json.replace(
+            "\"offsetTable\":{\"1\":10}",
+            "\"offsetTable\":{\"1\":10,\"9\":3}",
+        ));
+        assert_eq!(restored.delay_offset(1), 10);
+        assert!(!restored.offset_table.read().contains_key(&9));
+    }
+
+    #[test]
+    fn retires_dropped_levels_until_drained() {
+        let service = new_service();
+        service.update_offset(5, 7);
+        service.replace_delay_level_table(
+            ScheduleMessageService::parse_delay_level("1s 5s 1m").unwrap(),
+        );
+        assert_eq!(service.get_max_delay_level(), 3);
+        assert_eq!(service.compute_deliver_timestamp(5, 0), 86_400_000);
+
+        service.replace_delay_level_table(
+            ScheduleMessageService::parse_delay_level("1s 5s 1m 3h").unwrap(),
+        );
+        assert_eq!(service.compute_deliver_timestamp(4, 0), 3 * 3_600_000);
+        assert_eq!(
+            service
+                .retired_level_table
+                .read()
+                .keys()
+                .collect::<Vec<_>>(),
+            [&5]
+        );
+
+        let restored = ScheduleMessageService::new(&MessageStoreConfig {
+            message_delay_level: "1s 5s 1m 3h".to_string(),
+            ..MessageStoreConfig::default()
+        });
+        restored.decode(&service.encode_pretty(false));
+        assert_eq!(restored.delay_offset(5), 7);
+        assert_eq!(restored.compute_deliver_timestamp(5, 0), 86_400_000);
+    }
 }
@@ -0,0 +1,231 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::sync::Arc;
+
+use cheetah_string::CheetahString;
+use rocketmq_common::common::config_manager::ConfigManager;
+use rocketmq_rust::ArcMut;
+use rocketmq_store::log_file::MessageStore;
+use tracing::error;
+use tracing::info;
+
+use crate::broker_runtime::BrokerRuntimeInner;
+
+/// Pulls the metadata of the master into a slave, so that the slave serves consumers with the
+/// same topics, offsets and groups once it is promoted or reads are redirected to it.
+#[derive(Clone, Default)]
+pub(crate) struct SlaveSynchronize {
+    master_addr: Arc<parking_lot::RwLock<Option<CheetahString>>>,
+}
+
+impl SlaveSynchronize {
+    pub fn master_addr(&self) -> Option<CheetahString> {
+        self.master_addr.read().clone()
+    }
+
+    pub fn set_master_addr(&self, master_addr: Option<CheetahString>) {
+        let mut current = self.master_addr.write();
+        if *current != master_addr {
+            info!(
+                "Update master address from {:?} to {:?}",
+                *current, master_addr
+            );
+            *current = master_addr;
+        }
+    }
+
+    pub async fn sync_all<MS: MessageStore>(
+        &self,
+        broker_runtime_inner: &ArcMut<BrokerRuntimeInner<MS>>,
+    ) {
+        let Some(master_addr) = self.master_addr() else {
+            return;
+        };
+        if master_addr.is_empty() || &master_addr == broker_runtime_inner.get_broker_addr() {
+            return;
+        }
+        self.sync_topic_config(broker_runtime_inner, &master_addr)
+            .await;
+        self.sync_consumer_offset(broker_runtime_inner, &master_addr)
+            .await;
+        self.sync_delay_offset(broker_runtime_inner, &master_addr)
+            .await;
+        self.sync_subscription_group_config(broker_runtime_inner, &master_addr)
+            .await;
+        self.sync_message_request_mode(broker_runtime_inner, &master_addr)
+            .await;
+    }
+
+    async fn sync_topic_config<MS: MessageStore>(
+        &self,
+        broker_runtime_inner: &ArcMut<BrokerRuntimeInner<MS>>,
+        master_addr: &CheetahString,
+    ) {
+        let wrapper = match broker_runtime_inner
+            .broker_outer_api()
+            .get_all_topic_config(master_addr)
+            .await
+        {
+            Ok(wrapper) => wrapper,
+            Err(e) => {
+                error!("SyncTopicConfig Exception, {}, {}", master_addr, e);
+                return;
+            }
+        };
+        let topic_config_manager = broker_runtime_inner.topic_config_manager();
+        let topic_config_wrapper = wrapper.topic_config_serialize_wrapper();
+        if topic_config_manager.data_version().as_ref() != topic_config_wrapper.data_version() {
+            topic_config_manager
+                .data_version()
+                .mut_from_ref()
+                .assign_new_one(topic_config_wrapper.data_version());
+            *topic_config_manager.topic_config_table().lock() =
+                topic_config_wrapper.topic_config_table().clone();
+            topic_config_manager.persist();
+        }
+
+        let topic_queue_mapping_manager = broker_runtime_inner.topic_queue_mapping_manager();
+        if *topic_queue_mapping_manager.data_version.lock() != *wrapper.mapping_data_version() {
+            topic_queue_mapping_manager
+                .data_version
+                .lock()
+                .assign_new_one(wrapper.mapping_data_version());
+            *topic_queue_mapping_manager.topic_queue_mapping_table.lock() =
+                wrapper.topic_queue_mapping_detail_map().clone();
+            topic_queue_mapping_manager.persist();
+        }
+        info!("Update slave topic config from master, {}", master_addr);
+    }
+
+    async fn sync_consumer_offset<MS: MessageStore>(
+        &self,
+        broker_runtime_inner: &ArcMut<BrokerRuntimeInner<MS>>,
+        master_addr: &CheetahString,
+    ) {
+        match broker_runtime_inner
+            .broker_outer_api()
+            .get_all_consumer_offset(master_addr)
+            .await
+        {
+            Ok(content) => {
+                let consumer_offset_manager = broker_runtime_inner.consumer_offset_manager();
+                consumer_offset_manager.decode(content.as_str());
+                consumer_offset_manager.persist();
+                info!("Update slave consumer offset from master, {}", master_addr);
+            }
+            Err(e) => error!("SyncConsumerOffset Exception, {}, {}", master_addr, e),
+        }
+    }
+
+    async fn sync_delay_offset<MS: MessageStore>(
+        &self,
+        broker_runtime_inner: &ArcMut<BrokerRuntimeInner<MS>>,
+        master_addr: &CheetahString,
+    ) {
+        match broker_runtime_inner
+            .broker_outer_api()
+            .get_all_delay_offset(master_addr)
+            .await
+        {
+            Ok(content) => {
+                let schedule_message_service = broker_runtime_inner.schedule_message_service();
+                schedule_message_service.decode(content.as_str());
+                schedule_message_service.persist();
+                info!("Update slave delay offset from master, {}", master_addr);
+            }
+            Err(e) => error!("SyncDelayOffset Exception, {}, {}", master_addr, e),
+        }
+    }
+
+    async fn sync_subscription_group_config<MS: MessageStore>(
+        &self,
+        broker_runtime_inner: &ArcMut<BrokerRuntimeInner<MS>>,
+        master_addr: &CheetahString,
+    ) {
+        let wrapper = match broker_runtime_inner
+            .broker_outer_api()
+            .get_all_subscription_group_config(master_addr)
+            .await
+        {
+            Ok(wrapper) => wrapper,
+            Err(e) => {
+                error!("SyncSubscriptionGroup Exception, {}, {}", master_addr, e);
+                return;
+            }
+        };
+        let subscription_group_manager = broker_runtime_inner.subscription_group_manager();
+        {
+            let mut current = subscription_group_manager
+                .subscription_group_wrapper()
+                .lock();
+            if current.data_version() == wrapper.data_version() {
+                return;
+            }
+            *current = wrapper;
+        }
+        subscription_group_manager.persist();
+        info!(
+            "Update slave Subscription Group from master, {}",
+            master_addr
+        );
+    }
+
+    async fn sync_message_request_mode<MS: MessageStore>(
+        &self,
+        broker_runtime_inner: &ArcMut<BrokerRuntimeInner<MS>>,
+        master_addr: &CheetahString,
+    ) {
+        match broker_runtime_inner
+            .broker_outer_api()
+            .get_all_message_request_mode(master_addr)
+            .await
+        {
+            Ok(content) => {
+                let message_request_mode_manager =
+                    broker_runtime_inner.message_request_mode_manager();
+                message_request_mode_manager.decode(content.as_str());
+                message_request_mode_manager.persist();
+                info!(
+                    "Update slave Message Request Mode from master, {}",
+                    master_addr
+                );
+            }
+            Err(e) => error!("SyncMessageRequestMode Exception, {}, {}", master_addr, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn master_addr_is_shared_by_clones() {
+        let slave_synchronize = SlaveSynchronize::default();
+        let clone = slave_synchronize.clone();
+        assert!(clone.master_addr().is_none());
+
+        slave_synchronize.set_master_addr(Some("127.0.0.1:10911".into()));
+        assert_eq!(
+            clone.master_addr(),
+            Some(CheetahString::from("127.0.0.1:10911"))
+        );
+
+        clone.set_master_addr(None);
+        assert!(slave_synchronize.master_addr().is_none());
+    }
+}
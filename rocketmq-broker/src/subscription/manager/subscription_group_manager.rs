@@ -20,6 +20,7 @@ use std::sync::Arc;
 
 use cheetah_string::CheetahString;
 use rocketmq_common::common::config_manager::ConfigManager;
+use rocketmq_common::common::mix_all;
 use rocketmq_common::common::mix_all::is_sys_consumer_group;
 use rocketmq_common::common::topic::TopicValidator;
 use rocketmq_remoting::protocol::subscription::subscription_group_config::SubscriptionGroupConfig;
@@ -30,27 +31,66 @@ use rocketmq_store::log_file::MessageStore;
 use serde::Deserialize;
 use serde::Serialize;
 use tracing::info;
+use tracing::warn;
 
 use crate::broker_path_config_helper::get_subscription_group_path;
+#[cfg(feature = "rocksdb")]
+use crate::broker_path_config_helper::get_subscription_group_rocksdb_path;
 use crate::broker_runtime::BrokerRuntimeInner;
+#[cfg(feature = "rocksdb")]
+use crate::config::rocksdb_config_manager::load_kv;
+#[cfg(feature = "rocksdb")]
+use crate::config::rocksdb_config_manager::persist_kv;
+#[cfg(feature = "rocksdb")]
+use crate::config::rocksdb_config_manager::KvConfig;
+#[cfg(feature = "rocksdb")]
+use crate::config::rocksdb_config_manager::RocksDBConfigSlot;
+#[cfg(feature = "rocksdb")]
+use crate::config::rocksdb_config_manager::DEFAULT_CF;
 
 pub const CHARACTER_MAX_LENGTH: usize = 255;
 pub const TOPIC_MAX_LENGTH: usize = 127;
 
+/// Groups every broker knows without them being created, with their broadcast flag.
+const SYSTEM_GROUPS: [(&str, bool); 8] = [
+    (mix_all::TOOLS_CONSUMER_GROUP, false),
+    (mix_all::FILTERSRV_CONSUMER_GROUP, false),
+    (mix_all::SELF_TEST_CONSUMER_GROUP, false),
+    (mix_all::ONS_HTTP_PROXY_GROUP, true),
+    (mix_all::CID_ONSAPI_PULL_GROUP, true),
+    (mix_all::CID_ONSAPI_PERMISSION_GROUP, true),
+    (mix_all::CID_ONSAPI_OWNER_GROUP, true),
+    (mix_all::CID_SYS_RMQ_TRANS, true),
+];
+
 pub(crate) struct SubscriptionGroupManager<MS> {
     pub(crate) subscription_group_wrapper: Arc<parking_lot::Mutex<SubscriptionGroupWrapper>>,
     broker_runtime_inner: ArcMut<BrokerRuntimeInner<MS>>,
+    #[cfg(feature = "rocksdb")]
+    rocksdb_config: RocksDBConfigSlot,
 }
 
+/// Column family of the forbidden table, the subscription groups are in the default one.
+#[cfg(feature = "rocksdb")]
+const FORBIDDEN_CF: &str = "forbidden";
+
 impl<MS> SubscriptionGroupManager<MS> {
     pub fn new(
         broker_runtime_inner: ArcMut<BrokerRuntimeInner<MS>>,
     ) -> SubscriptionGroupManager<MS> {
+        let mut wrapper = SubscriptionGroupWrapper::default();
+        for (group, consume_broadcast_enable) in SYSTEM_GROUPS {
+            let mut config = SubscriptionGroupConfig::new(CheetahString::from_static_str(group));
+            config.set_consume_broadcast_enable(consume_broadcast_enable);
+            wrapper
+                .subscription_group_table
+                .insert(CheetahString::from_static_str(group), config);
+        }
         Self {
-            subscription_group_wrapper: Arc::new(parking_lot::Mutex::new(
-                SubscriptionGroupWrapper::default(),
-            )),
+            subscription_group_wrapper: Arc::new(parking_lot::Mutex::new(wrapper)),
             broker_runtime_inner,
+            #[cfg(feature = "rocksdb")]
+            rocksdb_config: Default::default(),
         }
     }
 
@@ -60,6 +100,16 @@ impl<MS> SubscriptionGroupManager<MS> {
 }
 
 impl<MS: MessageStore> ConfigManager for SubscriptionGroupManager<MS> {
+    #[cfg(feature = "rocksdb")]
+    fn load(&self) -> bool {
+        load_kv(self, &self.rocksdb_config)
+    }
+
+    #[cfg(feature = "rocksdb")]
+    fn persist(&self) {
+        persist_kv(self, &self.rocksdb_config)
+    }
+
     fn config_file_path(&self) -> String {
         get_subscription_group_path(
             self.broker_runtime_inner
@@ -111,6 +161,81 @@ impl<MS: MessageStore> ConfigManager for SubscriptionGroupManager<MS> {
     }
 }
 
+#[cfg(feature = "rocksdb")]
+impl<MS: MessageStore> KvConfig for SubscriptionGroupManager<MS> {
+    const COLUMN_FAMILIES: &'static [&'static str] = &[DEFAULT_CF, FORBIDDEN_CF];
+
+    fn rocksdb_enabled(&self) -> bool {
+        self.broker_runtime_inner
+            .message_store_config()
+            .is_enable_rocksdb_store()
+    }
+
+    fn rocksdb_config_path(&self) -> String {
+        get_subscription_group_rocksdb_path(
+            self.broker_runtime_inner
+                .broker_config()
+                .store_path_root_dir
+                .as_str(),
+        )
+    }
+
+    fn encode_kv(&self) -> Vec<(&'static str, HashMap<CheetahString, Vec<u8>>)> {
+        let wrapper = self.subscription_group_wrapper.lock();
+        let groups = wrapper
+            .subscription_group_table
+            .iter()
+            .map(|(group, config)| {
+                (
+                    group.clone(),
+                    serde_json::to_vec(config).unwrap_or_default(),
+                )
+            })
+            .collect();
+        let forbidden = wrapper
+            .forbidden_table
+            .iter()
+            .map(|(group, topics)| {
+                (
+                    group.clone(),
+                    serde_json::to_vec(topics).unwrap_or_default(),
+                )
+            })
+            .collect();
+        vec![(DEFAULT_CF, groups), (FORBIDDEN_CF, forbidden)]
+    }
+
+    fn decode_kv(&self, column_family: &str, key: &str, value: &[u8]) {
+        let mut wrapper = self.subscription_group_wrapper.lock();
+        let decoded = if column_family == FORBIDDEN_CF {
+            serde_json::from_slice(value).map(|topics| {
+                wrapper.forbidden_table.insert(key.into(), topics);
+            })
+        } else {
+            serde_json::from_slice(value).map(|config| {
+                wrapper.subscription_group_table.insert(key.into(), config);
+            })
+        };
+        if let Err(e) = decoded {
+            warn!(
+                "decode subscription group {} of {} failed, {}",
+                key, column_family, e
+            );
+        }
+    }
+
+    fn kv_data_version(&self) -> DataVersion {
+        self.subscription_group_wrapper.lock().data_version.clone()
+    }
+
+    fn set_kv_data_version(&self, data_version: &DataVersion) {
+        self.subscription_group_wrapper
+            .lock()
+            .data_version
+            .assign_new_one(data_version);
+    }
+}
+
 impl<MS> SubscriptionGroupManager<MS>
 where
     MS: MessageStore,
@@ -155,16 +280,7 @@ where
                     subscription_group_config_new
                 );
             }
-            let state_machine_version =
-                if let Some(ref store) = self.broker_runtime_inner.message_store() {
-                    store.get_state_machine_version()
-                } else {
-                    0
-                };
-            self.subscription_group_wrapper
-                .lock()
-                .data_version
-                .next_version_with(state_machine_version);
+            self.update_data_version();
             self.persist();
             subscription_group_config = Some(subscription_group_config_new);
         }
@@ -182,6 +298,141 @@ where
             .cloned()
     }
 
+    /// Creates `config`, or replaces the config of its group, and persists the table.
+    pub fn update_subscription_group_config(&self, config: SubscriptionGroupConfig) {
+        self.put_subscription_group_config(config);
+        self.update_data_version();
+        self.persist();
+    }
+
+    fn put_subscription_group_config(&self, config: SubscriptionGroupConfig) {
+        let group = CheetahString::from_slice(config.group_name());
+        let old = self
+            .subscription_group_wrapper
+            .lock()
+            .subscription_group_table
+            .insert(group, config.clone());
+        match old {
+            Some(old) => info!(
+                "update subscription group config, old: {:?} new: {:?}",
+                old, config
+            ),
+            None => info!("create new subscription group, {:?}", config),
+        }
+    }
+
+    fn update_data_version(&self) {
+        let state_machine_version =
+            if let Some(ref store) = self.broker_runtime_inner.message_store() {
+                store.get_state_machine_version()
+            } else {
+                0
+            };
+        self.subscription_group_wrapper
+            .lock()
+            .data_version
+            .next_version_with(state_machine_version);
+    }
+
+    /// Whether a group without a committed offset starts consuming from the min offset of the
+    /// queue instead of where the client asks for.
+    pub fn consume_from_min_enable(&self, group: &CheetahString) -> bool {
+        self.subscription_group_wrapper
+            .lock()
+            .subscription_group_table
+            .get(group)
+            .is_some_and(|config| config.consume_from_min_enable())
+    }
+
+    pub fn update_forbidden(
+        &self,
+        group: &CheetahString,
+        topic: &CheetahString,
+        forbidden_index: i32,
+        set_or_clear: bool,
+    ) {
+        if set_or_clear {
+            self.set_forbidden(group, topic, forbidden_index);
+        } else {
+            self.clear_forbidden(group, topic, forbidden_index);
+        }
+    }
+
+    pub fn set_forbidden(
+        &self,
+        group: &CheetahString,
+        topic: &CheetahString,
+        forbidden_index: i32,
+    ) {
+        let topic_forbidden =
+            self.get_forbidden_internal(group.as_str(), topic.as_str()) | (1 << forbidden_index);
+        self.update_forbidden_value(group, topic, topic_forbidden);
+    }
+
+    pub fn clear_forbidden(
+        &self,
+        group: &CheetahString,
+        topic: &CheetahString,
+        forbidden_index: i32,
+    ) {
+        let topic_forbidden =
+            self.get_forbidden_internal(group.as_str(), topic.as_str()) & !(1 << forbidden_index);
+        self.update_forbidden_value(group, topic, topic_forbidden);
+    }
+
+    pub fn update_forbidden_value(
+        &self,
+        group: &CheetahString,
+        topic: &CheetahString,
+        forbidden: i32,
+    ) {
+        {
+            let mut wrapper = self.subscription_group_wrapper.lock();
+            if forbidden <= 0 {
+                if let Some(topic_forbiddens) = wrapper.forbidden_table.get_mut(group) {
+                    topic_forbiddens.remove(topic);
+                    if topic_forbiddens.is_empty() {
+                        wrapper.forbidden_table.remove(group);
+                    }
+                }
+                info!("clear group forbidden, {}@{}", group, topic);
+            } else {
+                let old = wrapper
+                    .forbidden_table
+                    .entry(group.clone())
+                    .or_default()
+                    .insert(topic.clone(), forbidden);
+                info!(
+                    "set group forbidden, {}@{} old: {:?} new: {}",
+                    group, topic, old, forbidden
+                );
+            }
+        }
+        self.update_data_version();
+        self.persist();
+    }
+
+    pub fn delete_subscription_group_config(&self, group: &CheetahString) {
+        let old = {
+            let mut wrapper = self.subscription_group_wrapper.lock();
+            wrapper.forbidden_table.remove(group);
+            wrapper.subscription_group_table.remove(group)
+        };
+        match old {
+            Some(old) => {
+                info!("delete subscription group OK, subscription group:{:?}", old);
+                self.update_data_version();
+                self.persist();
+            }
+            None => {
+                warn!(
+                    "delete subscription group failed, subscription groupName: {} not exist",
+                    group
+                );
+            }
+        }
+    }
+
     pub fn get_forbidden(&self, group: &str, topic: &str, forbidden_index: i32) -> bool {
         let topic_forbidden = self.get_forbidden_internal(group, topic);
         let bit_forbidden = 1 << forbidden_index;
@@ -225,4 +476,103 @@ impl SubscriptionGroupWrapper {
     pub fn forbidden_table(&self) -> &HashMap<CheetahString, HashMap<CheetahString, i32>> {
         &self.forbidden_table
     }
+
+    pub fn data_version(&self) -> &DataVersion {
+        &self.data_version
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocketmq_common::common::broker::broker_config::BrokerConfig;
+    use rocketmq_common::common::constant::PermName;
+    use rocketmq_common::common::server::config::ServerConfig;
+    use rocketmq_store::config::message_store_config::MessageStoreConfig;
+
+    use super::*;
+    use crate::broker_runtime::BrokerRuntime;
+
+    const READ: i32 = PermName::INDEX_PERM_READ as i32;
+    const WRITE: i32 = PermName::INDEX_PERM_WRITE as i32;
+
+    fn new_broker(store_dir: &tempfile::TempDir) -> BrokerRuntime {
+        let store_path_root_dir = CheetahString::from(store_dir.path().to_string_lossy().as_ref());
+        BrokerRuntime::new(
+            BrokerConfig {
+                store_path_root_dir: store_path_root_dir.clone(),
+                ..Default::default()
+            },
+            MessageStoreConfig {
+                store_path_root_dir,
+                ..Default::default()
+            },
+            ServerConfig::default(),
+        )
+    }
+
+    #[test]
+    fn updated_groups_replace_the_old_config_and_survive_a_restart() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let _guard = runtime.enter();
+        let store_dir = tempfile::tempdir().unwrap();
+        let broker = new_broker(&store_dir);
+        let manager = broker.inner().subscription_group_manager();
+        let group = CheetahString::from_static_str("GroupA");
+        assert!(manager.contains_subscription_group(&mix_all::TOOLS_CONSUMER_GROUP.into()));
+        assert!(!manager.consume_from_min_enable(&group));
+
+        let mut config = SubscriptionGroupConfig::new(group.clone());
+        config.set_retry_queue_nums(2);
+        manager.update_subscription_group_config(config.clone());
+        config.set_consume_from_min_enable(true);
+        manager.update_subscription_group_config(config);
+        assert!(manager.consume_from_min_enable(&group));
+
+        let reloaded = new_broker(&store_dir);
+        let reloaded_manager = reloaded.inner().subscription_group_manager();
+        assert!(reloaded_manager.load());
+        let reloaded_config = reloaded_manager
+            .find_subscription_group_config_inner(&group)
+            .unwrap();
+        assert_eq!(reloaded_config.retry_queue_nums(), 2);
+        assert!(reloaded_config.consume_from_min_enable());
+    }
+
+    #[test]
+    fn forbidden_flags_are_kept_per_topic() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let _guard = runtime.enter();
+        let store_dir = tempfile::tempdir().unwrap();
+        let broker = new_broker(&store_dir);
+        let manager = broker.inner().subscription_group_manager();
+        let group = CheetahString::from_static_str("GroupA");
+        let topic = CheetahString::from_static_str("TopicA");
+
+        manager.update_forbidden(&group, &topic, READ, true);
+        manager.set_forbidden(&group, &topic, WRITE);
+        assert!(manager.get_forbidden("GroupA", "TopicA", READ));
+        assert!(manager.get_forbidden("GroupA", "TopicA", WRITE));
+        assert!(!manager.get_forbidden("GroupA", "TopicB", READ));
+        assert!(!manager.get_forbidden("GroupB", "TopicA", READ));
+
+        manager.update_forbidden(&group, &topic, READ, false);
+        assert!(!manager.get_forbidden("GroupA", "TopicA", READ));
+        assert!(manager.get_forbidden("GroupA", "TopicA", WRITE));
+
+        // the flags survive a restart
+        let reloaded = new_broker(&store_dir);
+        let reloaded_manager = reloaded.inner().subscription_group_manager();
+        assert!(reloaded_manager.load());
+        assert!(!reloaded_manager.get_forbidden("GroupA", "TopicA", READ));
+        assert!(reloaded_manager.get_forbidden("GroupA", "TopicA", WRITE));
+
+        // a group without any flag left is dropped from the table
+        manager.clear_forbidden(&group, &topic, WRITE);
+        assert!(!manager.get_forbidden("GroupA", "TopicA", WRITE));
+        assert!(!manager
+            .subscription_group_wrapper
+            .lock()
+            .forbidden_table
+            .contains_key(&group));
+    }
 }
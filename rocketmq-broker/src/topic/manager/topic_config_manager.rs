@@ -29,6 +29,7 @@ use rocketmq_common::common::mix_all;
 use rocketmq_common::common::topic::TopicValidator;
 use rocketmq_common::utils::serde_json_utils::SerdeJsonUtils;
 use rocketmq_common::TopicAttributes::ALL;
+use rocketmq_remoting::protocol::body::kv_table::KVTable;
 use rocketmq_remoting::protocol::body::topic_info_wrapper::topic_config_wrapper::TopicConfigAndMappingSerializeWrapper;
 use rocketmq_remoting::protocol::body::topic_info_wrapper::TopicConfigSerializeWrapper;
 use rocketmq_remoting::protocol::static_topic::topic_queue_info::TopicQueueMappingInfo;
@@ -40,13 +41,27 @@ use tracing::info;
 use tracing::warn;
 
 use crate::broker_path_config_helper::get_topic_config_path;
+#[cfg(feature = "rocksdb")]
+use crate::broker_path_config_helper::get_topic_config_rocksdb_path;
 use crate::broker_runtime::BrokerRuntimeInner;
+#[cfg(feature = "rocksdb")]
+use crate::config::rocksdb_config_manager::load_kv;
+#[cfg(feature = "rocksdb")]
+use crate::config::rocksdb_config_manager::persist_kv;
+#[cfg(feature = "rocksdb")]
+use crate::config::rocksdb_config_manager::KvConfig;
+#[cfg(feature = "rocksdb")]
+use crate::config::rocksdb_config_manager::RocksDBConfigSlot;
+#[cfg(feature = "rocksdb")]
+use crate::config::rocksdb_config_manager::DEFAULT_CF;
 
 pub(crate) struct TopicConfigManager<MS> {
     topic_config_table: Arc<parking_lot::Mutex<HashMap<CheetahString, TopicConfig>>>,
     data_version: ArcMut<DataVersion>,
     topic_config_table_lock: Arc<parking_lot::ReentrantMutex<()>>,
     broker_runtime_inner: ArcMut<BrokerRuntimeInner<MS>>,
+    #[cfg(feature = "rocksdb")]
+    rocksdb_config: RocksDBConfigSlot,
 }
 
 /*impl Clone for TopicConfigManager {
@@ -71,6 +86,8 @@ impl<MS: MessageStore> TopicConfigManager<MS> {
             data_version: ArcMut::new(DataVersion::default()),
             topic_config_table_lock: Default::default(),
             broker_runtime_inner,
+            #[cfg(feature = "rocksdb")]
+            rocksdb_config: Default::default(),
         };
         manager.init();
         manager
@@ -170,7 +187,8 @@ impl<MS: MessageStore> TopicConfigManager<MS> {
         }
 
         {
-            if self.broker_runtime_inner.broker_config().trace_topic_enable {
+            let broker_config = self.broker_runtime_inner.broker_config();
+            if broker_config.trace_topic_enable || broker_config.broker_trace_enable {
                 let topic = self
                     .broker_runtime_inner
                     .broker_config()
@@ -306,6 +324,15 @@ impl<MS: MessageStore> TopicConfigManager<MS> {
                 return Some(topic_config);
             }
 
+            if TopicValidator::is_system_topic(topic) {
+                warn!(
+                    "Create new topic failed, because the topic[{}] is a system topic \
+                     producer:[{}]",
+                    topic, remote_address
+                );
+                return None;
+            }
+
             if let Some(mut default_topic_config) = self.get_topic_config(default_topic) {
                 if default_topic == TopicValidator::AUTO_CREATE_TOPIC_KEY_TOPIC
                     && !self
@@ -372,7 +399,11 @@ impl<MS: MessageStore> TopicConfigManager<MS> {
         if let Some(ref mut config) = self.get_topic_config(topic) {
             if is_order != config.order {
                 config.order = is_order;
-                self.update_topic_config(config);
+                // keep the current attributes
+                config.attributes.clear();
+                if let Err(e) = self.update_topic_config(config) {
+                    warn!("update order of topic {} failed: {}", topic, e);
+                }
             }
             return Some(config.clone());
         }
@@ -413,7 +444,7 @@ impl<MS: MessageStore> TopicConfigManager<MS> {
     }
 
     fn register_broker_data(&mut self, topic_config: &TopicConfig) {
-        let broker_config = self.broker_runtime_inner.broker_config().clone();
+        let broker_config = self.broker_runtime_inner.broker_config();
         let broker_runtime_inner = self.broker_runtime_inner.clone();
         let topic_config_clone = topic_config.clone();
         tokio::spawn(async move {
@@ -427,10 +458,53 @@ impl<MS: MessageStore> TopicConfigManager<MS> {
         });
     }
 
-    pub fn update_topic_config_list(&mut self, topic_config_list: &mut [TopicConfig]) {
-        for topic_config in topic_config_list {
-            self.update_topic_config(topic_config);
+    /// Marks the topics the name server keeps an order config for as order topics, and clears
+    /// the order flag of the topics whose order config was removed from the name server.
+    pub fn update_order_topic_config(&self, order_kv_table: &KVTable) {
+        let mut changed = false;
+        {
+            let mut topic_config_table = self.topic_config_table.lock();
+            for (topic, topic_config) in topic_config_table.iter_mut() {
+                let order = order_kv_table.table.contains_key(topic);
+                if topic_config.order != order {
+                    topic_config.order = order;
+                    changed = true;
+                    info!(
+                        "update order topic config, topic={}, order={}",
+                        topic, order
+                    );
+                }
+            }
+        }
+        if changed {
+            let state_machine_version =
+                if let Some(message_store) = self.broker_runtime_inner.message_store().as_ref() {
+                    message_store.get_state_machine_version()
+                } else {
+                    0
+                };
+            self.data_version
+                .mut_from_ref()
+                .next_version_with(state_machine_version);
+            self.persist();
+        }
+    }
+
+    /// Creates or updates every topic of `topic_config_list`, or none of them when the
+    /// modifications of any topic are rejected.
+    pub fn update_topic_config_list(
+        &mut self,
+        topic_config_list: &mut [TopicConfig],
+    ) -> Result<(), String> {
+        let final_attributes = topic_config_list
+            .iter()
+            .map(|topic_config| self.final_attributes(topic_config))
+            .collect::<Result<Vec<_>, String>>()?;
+        for (topic_config, attributes) in topic_config_list.iter_mut().zip(final_attributes) {
+            topic_config.attributes = attributes;
+            self.apply_topic_config(topic_config);
         }
+        Ok(())
     }
 
     #[inline]
@@ -457,7 +531,23 @@ impl<MS: MessageStore> TopicConfigManager<MS> {
         }
     }
 
-    pub fn update_topic_config(&mut self, topic_config: &mut TopicConfig) {
+    /// Creates or updates `topic_config` and persists it.
+    ///
+    /// The attributes of `topic_config` are the modifications requested for the topic,
+    /// `+key=value` to add or update an attribute and `-key` to delete one. They are replaced
+    /// with the attributes of the topic after the modifications. Nothing is changed when the
+    /// modifications are rejected.
+    pub fn update_topic_config(&mut self, topic_config: &mut TopicConfig) -> Result<(), String> {
+        topic_config.attributes = self.final_attributes(topic_config)?;
+        self.apply_topic_config(topic_config);
+        Ok(())
+    }
+
+    /// Returns the attributes of the topic of `topic_config` once its modifications are applied.
+    fn final_attributes(
+        &self,
+        topic_config: &TopicConfig,
+    ) -> Result<HashMap<CheetahString, CheetahString>, String> {
         let new_attributes = Self::request(topic_config);
         let current_attributes = self.current(topic_config.topic_name.as_ref().unwrap().as_str());
         let create = self
@@ -466,16 +556,17 @@ impl<MS: MessageStore> TopicConfigManager<MS> {
             .get(topic_config.topic_name.as_ref().unwrap().as_str())
             .is_none();
 
-        let final_attributes = alter_current_attributes(
+        alter_current_attributes(
             create,
-            ALL.clone()
-                .into_iter()
-                .map(|(k, v)| (k.into(), v))
+            ALL.iter()
+                .map(|(k, v)| (k.as_str().into(), v.clone()))
                 .collect(),
-            new_attributes,
             current_attributes,
-        );
-        topic_config.attributes = final_attributes;
+            new_attributes,
+        )
+    }
+
+    fn apply_topic_config(&mut self, topic_config: &TopicConfig) {
         match self.put_topic_config(topic_config.clone()) {
             None => {
                 info!("create new topic [{:?}]", topic_config)
@@ -588,6 +679,16 @@ impl<MS: MessageStore> TopicConfigManager<MS> {
 }
 
 impl<MS: MessageStore> ConfigManager for TopicConfigManager<MS> {
+    #[cfg(feature = "rocksdb")]
+    fn load(&self) -> bool {
+        load_kv(self, &self.rocksdb_config)
+    }
+
+    #[cfg(feature = "rocksdb")]
+    fn persist(&self) {
+        persist_kv(self, &self.rocksdb_config)
+    }
+
     fn config_file_path(&self) -> String {
         get_topic_config_path(
             self.broker_runtime_inner
@@ -629,3 +730,141 @@ impl<MS: MessageStore> ConfigManager for TopicConfigManager<MS> {
         }
     }
 }
+
+#[cfg(feature = "rocksdb")]
+impl<MS: MessageStore> KvConfig for TopicConfigManager<MS> {
+    fn rocksdb_enabled(&self) -> bool {
+        self.broker_runtime_inner
+            .message_store_config()
+            .is_enable_rocksdb_store()
+    }
+
+    fn rocksdb_config_path(&self) -> String {
+        get_topic_config_rocksdb_path(
+            self.broker_runtime_inner
+                .broker_config()
+                .store_path_root_dir
+                .as_str(),
+        )
+    }
+
+    fn encode_kv(&self) -> Vec<(&'static str, HashMap<CheetahString, Vec<u8>>)> {
+        let entries = self
+            .topic_config_table
+            .lock()
+            .iter()
+            .map(|(topic, config)| {
+                (
+                    topic.clone(),
+                    serde_json::to_vec(config).unwrap_or_default(),
+                )
+            })
+            .collect();
+        vec![(DEFAULT_CF, entries)]
+    }
+
+    fn decode_kv(&self, _column_family: &str, key: &str, value: &[u8]) {
+        match serde_json::from_slice::<TopicConfig>(value) {
+            Ok(config) => {
+                self.topic_config_table.lock().insert(key.into(), config);
+            }
+            Err(e) => warn!("decode topic config {} failed, {}", key, e),
+        }
+    }
+
+    fn kv_data_version(&self) -> DataVersion {
+        self.data_version.as_ref().clone()
+    }
+
+    fn set_kv_data_version(&self, data_version: &DataVersion) {
+        self.data_version
+            .mut_from_ref()
+            .assign_new_one(data_version);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocketmq_common::common::broker::broker_config::BrokerConfig;
+    use rocketmq_common::common::server::config::ServerConfig;
+    use rocketmq_store::config::message_store_config::MessageStoreConfig;
+
+    use super::*;
+    use crate::broker_runtime::BrokerRuntime;
+
+    fn topic_config(topic: &str, attribute: (&str, &str)) -> TopicConfig {
+        let mut topic_config = TopicConfig::new(topic);
+        topic_config
+            .attributes
+            .insert(attribute.0.into(), attribute.1.into());
+        topic_config
+    }
+
+    #[test]
+    fn order_flags_follow_the_order_config_of_the_name_server() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let _guard = runtime.enter();
+        let store_dir = tempfile::tempdir().unwrap();
+        let store_path_root_dir = CheetahString::from(store_dir.path().to_string_lossy().as_ref());
+        let broker = BrokerRuntime::new(
+            BrokerConfig {
+                store_path_root_dir: store_path_root_dir.clone(),
+                ..Default::default()
+            },
+            MessageStoreConfig {
+                store_path_root_dir,
+                ..Default::default()
+            },
+            ServerConfig::default(),
+        );
+        let topic_config_manager = broker.inner().topic_config_manager();
+        topic_config_manager.put_topic_config(TopicConfig::new("TopicA"));
+        topic_config_manager.put_topic_config(TopicConfig::new("TopicB"));
+        let is_order = |topic: &str| {
+            topic_config_manager
+                .topic_config_table()
+                .lock()
+                .get(topic)
+                .unwrap()
+                .order
+        };
+
+        let mut order_kv_table = KVTable::default();
+        order_kv_table
+            .table
+            .insert("TopicA".into(), "broker-a:4".into());
+        topic_config_manager.update_order_topic_config(&order_kv_table);
+        assert!(is_order("TopicA"));
+        assert!(!is_order("TopicB"));
+
+        topic_config_manager.update_order_topic_config(&KVTable::default());
+        assert!(!is_order("TopicA"));
+        assert!(!is_order("TopicB"));
+    }
+
+    #[test]
+    fn update_topic_config_list_applies_nothing_when_a_topic_is_rejected() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let _guard = runtime.enter();
+        let broker = BrokerRuntime::new(
+            BrokerConfig::default(),
+            MessageStoreConfig::default(),
+            ServerConfig::default(),
+        );
+        let mut topic_config_list = [
+            topic_config("TopicA", ("+queue.type", "SimpleCQ")),
+            topic_config("TopicB", ("+unknown.attribute", "1")),
+        ];
+
+        let topic_config_manager = broker.inner().mut_from_ref().topic_config_manager_mut();
+        assert!(topic_config_manager
+            .final_attributes(&topic_config_list[0])
+            .is_ok());
+
+        let result = topic_config_manager.update_topic_config_list(&mut topic_config_list);
+        assert!(result.is_err());
+        let topic_config_table = broker.inner().topic_config_manager().topic_config_table();
+        assert!(!topic_config_table.lock().contains_key("TopicA"));
+        assert!(!topic_config_table.lock().contains_key("TopicB"));
+    }
+}
@@ -16,10 +16,9 @@
  */
 
 use std::collections::HashMap;
-use std::sync::Arc;
 
 use cheetah_string::CheetahString;
-use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::common::broker::shared_broker_config::SharedBrokerConfig;
 use rocketmq_common::common::config_manager::ConfigManager;
 use rocketmq_remoting::code::response_code::ResponseCode;
 use rocketmq_remoting::protocol::body::topic_info_wrapper::topic_queue_wrapper::TopicQueueMappingSerializeWrapper;
@@ -27,11 +26,13 @@ use rocketmq_remoting::protocol::header::message_operation_header::TopicRequestH
 use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
 use rocketmq_remoting::protocol::static_topic::topic_queue_mapping_context::TopicQueueMappingContext;
 use rocketmq_remoting::protocol::static_topic::topic_queue_mapping_detail::TopicQueueMappingDetail;
+use rocketmq_remoting::protocol::static_topic::topic_queue_mapping_utils::TopicQueueMappingUtils;
 use rocketmq_remoting::protocol::DataVersion;
 use rocketmq_remoting::protocol::RemotingSerializable;
 use tracing::info;
 use tracing::warn;
 
+use crate::broker_error::BrokerError;
 use crate::broker_path_config_helper::get_topic_queue_mapping_path;
 
 #[derive(Default)]
@@ -39,11 +40,11 @@ pub(crate) struct TopicQueueMappingManager {
     pub(crate) data_version: parking_lot::Mutex<DataVersion>,
     pub(crate) topic_queue_mapping_table:
         parking_lot::Mutex<HashMap<CheetahString /* topic */, TopicQueueMappingDetail>>,
-    pub(crate) broker_config: Arc<BrokerConfig>,
+    pub(crate) broker_config: SharedBrokerConfig,
 }
 
 impl TopicQueueMappingManager {
-    pub(crate) fn new(broker_config: Arc<BrokerConfig>) -> Self {
+    pub(crate) fn new(broker_config: SharedBrokerConfig) -> Self {
         Self {
             broker_config,
             ..Default::default()
@@ -120,7 +121,7 @@ impl TopicQueueMappingManager {
                 .bname
                 .clone()
                 .unwrap(),
-            self.broker_config.broker_name
+            self.broker_config.get().broker_name
         );
 
         // if global_id.is_none() {
@@ -194,6 +195,86 @@ impl TopicQueueMappingManager {
         self.topic_queue_mapping_table.lock().get(topic).cloned()
     }
 
+    /// Stores the mapping of a static topic sent by the admin tool.
+    ///
+    /// Unless `force` is set, a mapping with an older epoch or another scope is refused, and the
+    /// items of the logic queues already hosted here must stay the same. Queues missing from
+    /// `new_detail` keep their old items, so remapping a queue never loses its history.
+    pub(crate) fn update_topic_queue_mapping(
+        &self,
+        mut new_detail: TopicQueueMappingDetail,
+        force: bool,
+        is_clean: bool,
+        flush: bool,
+    ) -> Result<(), Box<BrokerError>> {
+        let info = &new_detail.topic_queue_mapping_info;
+        if info.bname.as_ref() != Some(&self.broker_config.get().broker_name) {
+            return Err(Box::new(BrokerError::IllegalArgumentError(format!(
+                "Can't accept data with unmatched broker name {:?} != {}",
+                info.bname,
+                self.broker_config.get().broker_name
+            ))));
+        }
+        let Some(topic) = info.topic.clone() else {
+            return Err(Box::new(BrokerError::IllegalArgumentError(
+                "Can't accept data without topic".to_string(),
+            )));
+        };
+        {
+            let mut table = self.topic_queue_mapping_table.lock();
+            if let Some(old_detail) = table.get(&topic) {
+                let old_hosted = old_detail.hosted_queues.clone().unwrap_or_default();
+                let new_hosted = new_detail.hosted_queues.get_or_insert_with(HashMap::new);
+                if force {
+                    for (global_id, items) in old_hosted {
+                        new_hosted.entry(global_id).or_insert(items);
+                    }
+                } else {
+                    let old_info = &old_detail.topic_queue_mapping_info;
+                    let new_info = &new_detail.topic_queue_mapping_info;
+                    if new_info.epoch < old_info.epoch {
+                        return Err(Box::new(BrokerError::IllegalArgumentError(format!(
+                            "Can't accept data with small epoch {} < {}",
+                            new_info.epoch, old_info.epoch
+                        ))));
+                    }
+                    if new_info.scope != old_info.scope {
+                        return Err(Box::new(BrokerError::IllegalArgumentError(format!(
+                            "Can't accept data with unmatched scope {:?} != {:?}",
+                            new_info.scope, old_info.scope
+                        ))));
+                    }
+                    let epoch_equal = new_info.epoch == old_info.epoch;
+                    for (global_id, old_items) in old_hosted {
+                        match new_hosted.get(&global_id) {
+                            None => {
+                                new_hosted.insert(global_id, old_items);
+                            }
+                            Some(new_items) => {
+                                TopicQueueMappingUtils::make_sure_logic_queue_mapping_item_immutable(
+                                    &old_items,
+                                    new_items,
+                                    epoch_equal,
+                                    is_clean,
+                                )
+                                .map_err(|e| {
+                                    Box::new(BrokerError::IllegalArgumentError(e.to_string()))
+                                })?;
+                            }
+                        }
+                    }
+                }
+            }
+            info!("update topic queue mapping of {}: {:?}", topic, new_detail);
+            table.insert(topic, new_detail);
+        }
+        self.data_version.lock().next_version();
+        if flush {
+            self.persist();
+        }
+        Ok(())
+    }
+
     pub fn delete(&self, topic: &CheetahString) {
         let old = self.topic_queue_mapping_table.lock().remove(topic);
         match old {
@@ -218,7 +299,7 @@ impl TopicQueueMappingManager {
 //Fully implemented will be removed
 impl ConfigManager for TopicQueueMappingManager {
     fn config_file_path(&self) -> String {
-        get_topic_queue_mapping_path(self.broker_config.store_path_root_dir.as_str())
+        get_topic_queue_mapping_path(self.broker_config.get().store_path_root_dir.as_str())
     }
 
     fn encode_pretty(&self, pretty_format: bool) -> String {
@@ -260,22 +341,27 @@ mod tests {
     use std::sync::Arc;
 
     use rocketmq_common::common::broker::broker_config::BrokerConfig;
+    use rocketmq_remoting::protocol::static_topic::logic_queue_mapping_item::LogicQueueMappingItem;
+    use rocketmq_remoting::protocol::static_topic::topic_queue_info::TopicQueueMappingInfo;
 
     use super::*;
 
     #[test]
     fn new_creates_default_manager() {
-        let broker_config = Arc::new(BrokerConfig::default());
+        let broker_config = SharedBrokerConfig::default();
         let manager = TopicQueueMappingManager::new(broker_config.clone());
 
-        assert_eq!(Arc::ptr_eq(&manager.broker_config, &broker_config), true);
+        assert!(Arc::ptr_eq(
+            &manager.broker_config.get(),
+            &broker_config.get()
+        ));
         assert_eq!(manager.data_version.lock().get_state_version(), 0);
         assert_eq!(manager.topic_queue_mapping_table.lock().len(), 0);
     }
 
     #[test]
     fn get_topic_queue_mapping_returns_none_for_non_existent_topic() {
-        let broker_config = Arc::new(BrokerConfig::default());
+        let broker_config = SharedBrokerConfig::default();
         let manager = TopicQueueMappingManager::new(broker_config);
 
         assert!(manager
@@ -285,7 +371,7 @@ mod tests {
 
     #[test]
     fn get_topic_queue_mapping_returns_mapping_for_existing_topic() {
-        let broker_config = Arc::new(BrokerConfig::default());
+        let broker_config = SharedBrokerConfig::default();
         let manager = TopicQueueMappingManager::new(broker_config);
         let detail = TopicQueueMappingDetail::default();
         manager.topic_queue_mapping_table.lock().insert(
@@ -298,7 +384,7 @@ mod tests {
 
     #[test]
     fn delete_removes_existing_topic() {
-        let broker_config = Arc::new(BrokerConfig::default());
+        let broker_config = SharedBrokerConfig::default();
         let manager = TopicQueueMappingManager::new(broker_config);
         let detail = TopicQueueMappingDetail::default();
         manager
@@ -310,4 +396,89 @@ mod tests {
 
         assert!(manager.get_topic_queue_mapping("existing_topic").is_none());
     }
+
+    fn mapping_detail(
+        epoch: i64,
+        items: Vec<(i32, LogicQueueMappingItem)>,
+    ) -> TopicQueueMappingDetail {
+        let mut hosted_queues: HashMap<i32, Vec<LogicQueueMappingItem>> = HashMap::new();
+        for (global_id, item) in items {
+            hosted_queues.entry(global_id).or_default().push(item);
+        }
+        TopicQueueMappingDetail {
+            topic_queue_mapping_info: TopicQueueMappingInfo::new(
+                "static_topic".into(),
+                2,
+                BrokerConfig::default().broker_name,
+                epoch,
+            ),
+            hosted_queues: Some(hosted_queues),
+        }
+    }
+
+    fn mapping_item(gen: i32, queue_id: i32) -> LogicQueueMappingItem {
+        LogicQueueMappingItem {
+            gen,
+            queue_id,
+            bname: Some(BrokerConfig::default().broker_name),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn update_topic_queue_mapping_checks_epoch_and_keeps_old_queues() {
+        let manager = TopicQueueMappingManager::new(SharedBrokerConfig::default());
+        let counter = manager.data_version.lock().get_counter();
+        manager
+            .update_topic_queue_mapping(
+                mapping_detail(1, vec![(0, mapping_item(0, 0)), (1, mapping_item(0, 1))]),
+                false,
+                false,
+                false,
+            )
+            .unwrap();
+
+        assert!(manager
+            .update_topic_queue_mapping(
+                mapping_detail(0, vec![(0, mapping_item(0, 0))]),
+                false,
+                false,
+                false,
+            )
+            .is_err());
+        assert!(manager
+            .update_topic_queue_mapping(
+                mapping_detail(2, vec![(0, mapping_item(0, 3))]),
+                false,
+                false,
+                false,
+            )
+            .is_err());
+
+        manager
+            .update_topic_queue_mapping(
+                mapping_detail(2, vec![(0, mapping_item(0, 0)), (0, mapping_item(1, 2))]),
+                false,
+                false,
+                false,
+            )
+            .unwrap();
+        let detail = manager.get_topic_queue_mapping("static_topic").unwrap();
+        let hosted_queues = detail.hosted_queues.unwrap();
+        assert_eq!(hosted_queues[&0].len(), 2);
+        assert_eq!(hosted_queues[&1], vec![mapping_item(0, 1)]);
+        assert_eq!(manager.data_version.lock().get_counter(), counter + 2);
+    }
+
+    #[test]
+    fn update_topic_queue_mapping_rejects_other_broker() {
+        let manager = TopicQueueMappingManager::new(SharedBrokerConfig::default());
+        let mut detail = mapping_detail(1, vec![(0, mapping_item(0, 0))]);
+        detail.topic_queue_mapping_info.bname = Some("other_broker".into());
+
+        assert!(manager
+            .update_topic_queue_mapping(detail, true, false, false)
+            .is_err());
+        assert!(manager.get_topic_queue_mapping("static_topic").is_none());
+    }
 }
@@ -19,10 +19,10 @@ use rocketmq_common::common::message::message_ext::MessageExt;
 use rocketmq_remoting::code::response_code::ResponseCode;
 
 #[derive(Debug, Clone)]
-pub(crate) struct OperationResult {
-    pub(crate) prepare_message: Option<MessageExt>,
-    pub(crate) response_remark: Option<String>,
-    pub(crate) response_code: ResponseCode,
+pub struct OperationResult {
+    pub prepare_message: Option<MessageExt>,
+    pub response_remark: Option<String>,
+    pub response_code: ResponseCode,
 }
 
 impl Default for OperationResult {
@@ -69,10 +69,23 @@ impl<MS: MessageStore> DefaultTransactionalMessageCheckListener<MS> {
     }
 }
 
+impl<MS> Clone for DefaultTransactionalMessageCheckListener<MS> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            broker_runtime_inner: self.broker_runtime_inner.clone(),
+        }
+    }
+}
+
 impl<MS> TransactionalMessageCheckListener for DefaultTransactionalMessageCheckListener<MS>
 where
     MS: MessageStore,
 {
+    async fn resolve_half_msg(&mut self, msg_ext: MessageExt) {
+        self.inner.resolve_half_msg(msg_ext);
+    }
+
     async fn resolve_discard_msg(&mut self, msg_ext: MessageExt) {
         error!(
             "MsgExt:{} has been checked too many times, so discard it by moving it to system \
@@ -118,7 +131,6 @@ where
     }
 }
 
-#[derive(Clone)]
 struct TransactionalMessageCheckListenerInner<MS> {
     //broker_config: Arc<BrokerConfig>,
     //producer_manager: Arc<ProducerManager>,
@@ -126,6 +138,15 @@ struct TransactionalMessageCheckListenerInner<MS> {
     broker_runtime_inner: ArcMut<BrokerRuntimeInner<MS>>,
 }
 
+impl<MS> Clone for TransactionalMessageCheckListenerInner<MS> {
+    fn clone(&self) -> Self {
+        Self {
+            broker_client: self.broker_client.clone(),
+            broker_runtime_inner: self.broker_runtime_inner.clone(),
+        }
+    }
+}
+
 impl<MS: MessageStore> TransactionalMessageCheckListenerInner<MS> {
     pub fn new(
         /* broker_config: Arc<BrokerConfig>,
@@ -201,15 +222,13 @@ impl<MS: MessageStore> TransactionalMessageCheckListenerInner<MS> {
         Ok(())
     }
 
-    pub fn resolve_half_msg(&self, _msg_ext: MessageExt) -> crate::Result<()> {
-        /*let this = self.clone();
+    pub fn resolve_half_msg(&self, msg_ext: MessageExt) {
+        let this = self.clone();
         tokio::spawn(async move {
             if let Err(e) = this.send_check_message(msg_ext).await {
-                warn!("Send check m_msg_extfailed: {:?}", e);
+                warn!("Send check message failed: {:?}", e);
             }
         });
-        Ok(())*/
-        unimplemented!("resolve_half_msg")
     }
 }
 
@@ -18,24 +18,36 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 
+use cheetah_string::CheetahString;
+use rocketmq_client_rust::consumer::pull_result::PullResult;
+use rocketmq_client_rust::consumer::pull_status::PullStatus;
 use rocketmq_common::common::message::message_ext::MessageExt;
 use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+use rocketmq_common::common::message::message_queue::MessageQueue;
 use rocketmq_common::common::message::message_single::Message;
+use rocketmq_common::common::message::MessageConst;
+use rocketmq_common::common::message::MessageTrait;
+use rocketmq_common::MessageAccessor::MessageAccessor;
 use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_remoting::code::response_code::ResponseCode;
 use rocketmq_remoting::protocol::header::end_transaction_request_header::EndTransactionRequestHeader;
 use rocketmq_store::base::message_result::PutMessageResult;
+use rocketmq_store::base::message_status_enum::PutMessageStatus;
 use rocketmq_store::log_file::MessageStore;
 use tokio::sync::Mutex;
+use tracing::debug;
 use tracing::error;
+use tracing::info;
 use tracing::warn;
 
 use crate::transaction::operation_result::OperationResult;
+use crate::transaction::queue::get_result::GetResult;
 use crate::transaction::queue::message_queue_op_context::MessageQueueOpContext;
 use crate::transaction::queue::transactional_message_bridge::TransactionalMessageBridge;
 use crate::transaction::queue::transactional_message_util::TransactionalMessageUtil;
 use crate::transaction::queue::transactional_op_batch_service::TransactionalOpBatchService;
 use crate::transaction::transaction_metrics::TransactionMetrics;
+use crate::transaction::transactional_message_check_listener::TransactionalMessageCheckListener;
 use crate::transaction::transactional_message_service::TransactionalMessageService;
 
 const PULL_MSG_RETRY_NUMBER: i32 = 1;
@@ -45,9 +57,12 @@ const MAX_RETRY_COUNT_WHEN_HALF_NULL: i32 = 1;
 const OP_MSG_PULL_NUMS: i32 = 32;
 const SLEEP_WHILE_NO_OP: i32 = 1000;
 
+/// Commit and rollback records of each half queue, waiting to be written into the op topic.
+type DeleteContext = Arc<Mutex<HashMap<i32, MessageQueueOpContext>>>;
+
 pub struct DefaultTransactionalMessageService<MS> {
     transactional_message_bridge: TransactionalMessageBridge<MS>,
-    delete_context: Arc<Mutex<HashMap<i32, MessageQueueOpContext>>>,
+    delete_context: DeleteContext,
     transactional_op_batch_service: TransactionalOpBatchService,
     transaction_metrics: TransactionMetrics,
 }
@@ -85,68 +100,340 @@ where
         }
     }
 
+    fn op_msg_max_size(&self) -> usize {
+        self.transactional_message_bridge
+            .broker_runtime_inner
+            .broker_config()
+            .transaction_op_msg_max_size as usize
+    }
+
     pub async fn get_op_message(
         &self,
         queue_id: i32,
         more_data: Option<String>,
     ) -> Option<Message> {
-        let topic = TransactionalMessageUtil::build_op_topic();
         let delete_context = self.delete_context.lock().await;
         let mq_context = delete_context.get(&queue_id)?;
+        build_op_message(mq_context, more_data, self.op_msg_max_size()).await
+    }
 
-        let more_data_length = if let Some(ref data) = more_data {
-            data.len()
-        } else {
-            0
-        };
-        let mut length = more_data_length;
-        let max_size = self
+    pub fn shutdown(&mut self) {
+        self.transactional_op_batch_service.shutdown();
+    }
+
+    /// Reads the op messages of `op_queue` from `pull_offset_of_op` and records in `remove_map`
+    /// the half offsets they committed or rolled back, keyed to the offset of the op message.
+    /// Op messages only referring to half messages below `mini_offset` go to `done_op_offset`.
+    async fn fill_op_remove_map(
+        &self,
+        remove_map: &mut HashMap<i64, i64>,
+        op_queue: &MessageQueue,
+        pull_offset_of_op: i64,
+        mini_offset: i64,
+        done_op_offset: &mut Vec<i64>,
+    ) -> Option<PullResult> {
+        let pull_result = self
             .transactional_message_bridge
-            .broker_runtime_inner
-            .broker_config()
-            .transaction_op_msg_max_size as usize;
-        if length < max_size {
-            let sz = mq_context.get_total_size() as usize;
-            if sz > max_size || length + sz > max_size {
-                length = max_size + 100;
-            } else {
-                length += sz;
+            .get_op_message(op_queue.get_queue_id(), pull_offset_of_op, OP_MSG_PULL_NUMS)
+            .await?;
+        match pull_result.pull_status() {
+            PullStatus::OffsetIllegal | PullStatus::NoMatchedMsg => {
+                warn!(
+                    "The miss op offset={} in queue={} is illegal, pullResult={}",
+                    pull_offset_of_op, op_queue, pull_result
+                );
+                self.transactional_message_bridge
+                    .update_consume_offset(op_queue, pull_result.next_begin_offset() as i64);
+                return Some(pull_result);
+            }
+            PullStatus::NoNewMsg => return Some(pull_result),
+            PullStatus::Found => {}
+        }
+        let Some(op_msg) = pull_result.msg_found_list() else {
+            return Some(pull_result);
+        };
+        for op_message_ext in op_msg {
+            let Some(body) = op_message_ext.get_body() else {
+                error!(
+                    "op message body is null. queueId={}, offset={}",
+                    op_message_ext.queue_id, op_message_ext.queue_offset
+                );
+                done_op_offset.push(op_message_ext.queue_offset);
+                continue;
+            };
+            if op_message_ext.get_tags().unwrap_or_default() != TransactionalMessageUtil::REMOVE_TAG
+            {
+                error!(
+                    "Found a illegal tag in opMessageExt. msgId={}, queueId={}, offset={}",
+                    op_message_ext.msg_id, op_message_ext.queue_id, op_message_ext.queue_offset
+                );
+                done_op_offset.push(op_message_ext.queue_offset);
+                continue;
+            }
+            let mut all_done = true;
+            for offset in String::from_utf8_lossy(body)
+                .split(TransactionalMessageUtil::OFFSET_SEPARATOR)
+                .filter_map(|offset| offset.trim().parse::<i64>().ok())
+            {
+                if offset >= mini_offset {
+                    remove_map.insert(offset, op_message_ext.queue_offset);
+                    all_done = false;
+                }
+            }
+            if all_done {
+                done_op_offset.push(op_message_ext.queue_offset);
             }
         }
+        Some(pull_result)
+    }
 
-        let mut sb = String::with_capacity(length);
+    /// Tells whether `msg_ext` is older than the retention of the commit log.
+    fn need_skip(&self, msg_ext: &MessageExt) -> bool {
+        let value_of_current_minus_born = get_current_millis() as i64 - msg_ext.born_timestamp;
+        let file_reserved_time = self
+            .transactional_message_bridge
+            .broker_runtime_inner
+            .message_store_config()
+            .file_reserved_time as i64;
+        if file_reserved_time > 0 && value_of_current_minus_born > file_reserved_time * 3600 * 1000
+        {
+            info!(
+                "Half message exceed file reserved time ,so skip it.messageId {},bornTime {}",
+                msg_ext.msg_id, msg_ext.born_timestamp
+            );
+            return true;
+        }
+        false
+    }
 
-        if let Some(data) = more_data {
-            sb.push_str(&data);
+    /// Appends `msg_ext` again to the half topic, so that the scan can move past it. Its offsets
+    /// are updated to the new copy.
+    async fn put_back_half_msg_queue(&self, msg_ext: &mut MessageExt, offset: i64) -> bool {
+        let message_inner = TransactionalMessageBridge::<MS>::renew_half_message_inner(msg_ext);
+        let put_message_result = self
+            .transactional_message_bridge
+            .put_message_return_result(message_inner)
+            .await;
+        if put_message_result.put_message_status() != PutMessageStatus::PutOk {
+            error!(
+                "PutBackToHalfQueueReturnResult write failed, topic: {}, queueId: {}, msgId: {}",
+                msg_ext.get_topic(),
+                msg_ext.queue_id,
+                msg_ext.msg_id
+            );
+            return false;
         }
+        if let Some(append_message_result) = put_message_result.append_message_result() {
+            msg_ext.queue_offset = append_message_result.logics_offset;
+            msg_ext.commit_log_offset = append_message_result.wrote_offset;
+            msg_ext.store_timestamp = append_message_result.store_timestamp;
+            if let Some(msg_id) = append_message_result.get_message_id() {
+                msg_ext.msg_id = CheetahString::from_string(msg_id);
+            }
+            debug!(
+                "Send check message, the offset={} restored in queueOffset={} commitLogOffset={} \
+                 newMsgId={} realMsgId={:?} topic={}",
+                offset,
+                msg_ext.queue_offset,
+                msg_ext.commit_log_offset,
+                msg_ext.msg_id,
+                msg_ext.get_user_property(&CheetahString::from_static_str(
+                    MessageConst::PROPERTY_UNIQ_CLIENT_MESSAGE_ID_KEYIDX
+                )),
+                msg_ext.get_topic()
+            );
+        }
+        true
+    }
 
-        while !mq_context.context_queue().is_empty().await {
-            if sb.len() >= max_size {
-                break;
+    /// Handles a half message that is still immune from checks: it is done when the half message
+    /// it was renewed from is already resolved, otherwise it is renewed once more.
+    async fn check_prepare_queue_offset(
+        &self,
+        remove_map: &mut HashMap<i64, i64>,
+        done_op_offset: &mut Vec<i64>,
+        msg_ext: &MessageExt,
+    ) -> bool {
+        let prepare_queue_offset = msg_ext.get_user_property(&CheetahString::from_static_str(
+            MessageConst::PROPERTY_TRANSACTION_PREPARED_QUEUE_OFFSET,
+        ));
+        if let Some(prepare_queue_offset) = prepare_queue_offset {
+            let prepare_queue_offset = prepare_queue_offset.parse::<i64>().unwrap_or(-1);
+            if prepare_queue_offset == -1 {
+                return false;
             }
-            {
-                if let Some(data) = mq_context.context_queue().try_poll().await {
-                    sb.push_str(&data);
-                }
+            if let Some(tmp_op_offset) = remove_map.remove(&prepare_queue_offset) {
+                done_op_offset.push(tmp_op_offset);
+                return true;
             }
         }
+        let message_inner =
+            TransactionalMessageBridge::<MS>::renew_immunity_half_message_inner(msg_ext);
+        self.transactional_message_bridge
+            .put_message_return_result(message_inner)
+            .await
+            .put_message_status()
+            == PutMessageStatus::PutOk
+    }
+
+    async fn get_half_msg(&self, queue_id: i32, offset: i64) -> Option<GetResult> {
+        let pull_result = self
+            .transactional_message_bridge
+            .get_half_message(queue_id, offset, PULL_MSG_RETRY_NUMBER)
+            .await?;
+        let msg = pull_result
+            .msg_found_list()
+            .as_ref()
+            .and_then(|msg_found_list| msg_found_list.first())
+            .map(|msg| msg.as_ref().clone())
+            .unwrap_or_default();
+        Some(GetResult { msg, pull_result })
+    }
+}
 
-        if sb.is_empty() {
-            return None;
+/// Tells whether `msg_ext` was checked `transaction_check_max` times, and counts the coming check
+/// otherwise.
+fn need_discard(msg_ext: &mut MessageExt, transaction_check_max: i32) -> bool {
+    let check_times_key =
+        CheetahString::from_static_str(MessageConst::PROPERTY_TRANSACTION_CHECK_TIMES);
+    let mut check_time = 1;
+    if let Some(check_times) = msg_ext.get_property(&check_times_key) {
+        check_time = check_times.parse::<i32>().unwrap_or_default();
+        if check_time >= transaction_check_max {
+            return true;
         }
+        check_time += 1;
+    }
+    MessageAccessor::put_property(
+        msg_ext,
+        check_times_key,
+        CheetahString::from_string(check_time.to_string()),
+    );
+    false
+}
 
-        Some(Message::with_tags(
-            topic,
-            TransactionalMessageUtil::REMOVE_TAG,
-            sb.as_bytes(),
-        ))
+/// Offset the op queue can be committed to: all op messages below it are done.
+fn calculate_op_offset(done_offset: &mut [i64], old_offset: i64) -> i64 {
+    done_offset.sort_unstable();
+    let mut new_offset = old_offset;
+    for offset in done_offset.iter() {
+        if *offset == new_offset {
+            new_offset += 1;
+        } else if *offset > new_offset {
+            break;
+        }
     }
+    new_offset
+}
 
-    pub fn shutdown(&mut self) {
-        warn!("DefaultTransactionalMessageService shutdown unimplemented, need to implement");
+impl<MS> DefaultTransactionalMessageService<MS>
+where
+    MS: MessageStore + Send + Sync + 'static,
+{
+    /// Starts writing the buffered commit and rollback records into the op topic.
+    pub fn start(&self) {
+        let bridge = self.transactional_message_bridge.clone();
+        let delete_context = Arc::clone(&self.delete_context);
+        let interval = bridge
+            .broker_runtime_inner
+            .broker_config()
+            .transaction_op_batch_interval;
+        self.transactional_op_batch_service
+            .start(interval, move || {
+                let bridge = bridge.clone();
+                let delete_context = Arc::clone(&delete_context);
+                async move { batch_send_op_message(&bridge, &delete_context).await }
+            });
     }
 }
 
+/// Drains the records buffered in `mq_context` into one op message of at most `max_size` bytes.
+async fn build_op_message(
+    mq_context: &MessageQueueOpContext,
+    more_data: Option<String>,
+    max_size: usize,
+) -> Option<Message> {
+    let more_data_length = more_data.as_ref().map_or(0, String::len);
+    let mut length = more_data_length;
+    if length < max_size {
+        let sz = mq_context.get_total_size() as usize;
+        if sz > max_size || length + sz > max_size {
+            length = max_size + 100;
+        } else {
+            length += sz;
+        }
+    }
+
+    let mut sb = String::with_capacity(length);
+    if let Some(data) = more_data {
+        sb.push_str(&data);
+    }
+    while !mq_context.context_queue().is_empty().await {
+        if sb.len() >= max_size {
+            break;
+        }
+        if let Some(data) = mq_context.context_queue().try_poll().await {
+            mq_context.total_size_add_and_get(-(data.len() as i32));
+            sb.push_str(&data);
+        }
+    }
+
+    if sb.is_empty() {
+        return None;
+    }
+
+    Some(Message::with_tags(
+        TransactionalMessageUtil::build_op_topic(),
+        TransactionalMessageUtil::REMOVE_TAG,
+        sb.as_bytes(),
+    ))
+}
+
+/// Writes the queues whose buffer is full or which were not written for
+/// `transaction_op_batch_interval`, and returns the timestamp of the next write.
+async fn batch_send_op_message<MS: MessageStore>(
+    bridge: &TransactionalMessageBridge<MS>,
+    delete_context: &DeleteContext,
+) -> u64 {
+    let start_time = get_current_millis();
+    let (max_size, interval) = {
+        let broker_config = bridge.broker_runtime_inner.broker_config();
+        (
+            broker_config.transaction_op_msg_max_size,
+            broker_config.transaction_op_batch_interval,
+        )
+    };
+    let mut first_timestamp = start_time;
+    let mut send_map = Vec::new();
+    {
+        let delete_context = delete_context.lock().await;
+        for (queue_id, mq_context) in delete_context.iter() {
+            let last_write_timestamp = mq_context.get_last_write_timestamp().await;
+            if mq_context.get_total_size() >= max_size
+                || start_time.saturating_sub(last_write_timestamp) >= interval
+            {
+                if let Some(op_message) =
+                    build_op_message(mq_context, None, max_size as usize).await
+                {
+                    send_map.push((*queue_id, op_message));
+                    mq_context.set_last_write_timestamp(start_time).await;
+                }
+            } else {
+                first_timestamp = first_timestamp.min(last_write_timestamp);
+            }
+        }
+    }
+    for (queue_id, op_message) in send_map {
+        if !bridge.write_op(queue_id, op_message).await {
+            error!(
+                "Transaction batch op message write failed. queueId is {}",
+                queue_id
+            );
+        }
+    }
+    first_timestamp + interval
+}
+
 impl<MS> TransactionalMessageService for DefaultTransactionalMessageService<MS>
 where
     MS: MessageStore + Send + Sync + 'static,
@@ -168,36 +455,32 @@ where
 
     async fn delete_prepare_message(&mut self, message_ext: &MessageExt) -> bool {
         let queue_id = message_ext.queue_id;
-        let mut delete_context = self.delete_context.lock().await;
-        let mq_context = delete_context
-            .entry(queue_id)
-            .or_insert(MessageQueueOpContext::new(get_current_millis(), 20000));
+        let max_size = self.op_msg_max_size();
         let data = format!(
             "{}{}",
             message_ext.queue_offset,
             TransactionalMessageUtil::OFFSET_SEPARATOR
         );
-        let len = data.len();
-        let res = mq_context
-            .context_queue()
-            .offer(data.clone(), Duration::from_millis(100))
-            .await;
-        if res {
-            let total_size = mq_context.total_size_add_and_get(len as i32);
-            if total_size
-                > self
-                    .transactional_message_bridge
-                    .broker_runtime_inner
-                    .broker_config()
-                    .transaction_op_msg_max_size
-            {
-                self.transactional_op_batch_service.wakeup();
+        let msg = {
+            let mut delete_context = self.delete_context.lock().await;
+            let mq_context = delete_context
+                .entry(queue_id)
+                .or_insert_with(|| MessageQueueOpContext::new(get_current_millis(), 20000));
+            let len = data.len();
+            let res = mq_context
+                .context_queue()
+                .offer(data.clone(), Duration::from_millis(100))
+                .await;
+            if res {
+                let total_size = mq_context.total_size_add_and_get(len as i32);
+                if total_size > max_size as i32 {
+                    self.transactional_op_batch_service.wakeup();
+                }
+                return true;
             }
-            return true;
-        } else {
             self.transactional_op_batch_service.wakeup();
-        }
-        let msg = self.get_op_message(queue_id, Some(data)).await;
+            build_op_message(mq_context, Some(data), max_size).await
+        };
         if self
             .transactional_message_bridge
             .write_op(queue_id, msg.expect("message is none"))
@@ -215,20 +498,257 @@ where
     }
 
     #[inline]
-    fn commit_message(&mut self, request_header: &EndTransactionRequestHeader) -> OperationResult {
+    async fn commit_message(
+        &mut self,
+        request_header: &EndTransactionRequestHeader,
+    ) -> OperationResult {
         self.get_half_message_by_offset(request_header.commit_log_offset as i64)
     }
 
     #[inline]
-    fn rollback_message(
+    async fn rollback_message(
         &mut self,
         request_header: &EndTransactionRequestHeader,
     ) -> OperationResult {
         self.get_half_message_by_offset(request_header.commit_log_offset as i64)
     }
 
-    fn check(&self, _transaction_timeout: u64, _transaction_check_max: i32) {
-        todo!()
+    async fn check<L>(
+        &mut self,
+        transaction_timeout: u64,
+        transaction_check_max: i32,
+        listener: &mut L,
+    ) where
+        L: TransactionalMessageCheckListener + Send,
+    {
+        let topic = CheetahString::from_static_str(TransactionalMessageUtil::build_half_topic());
+        let msg_queues = self
+            .transactional_message_bridge
+            .fetch_message_queues(&topic);
+        if msg_queues.is_empty() {
+            warn!("The queue of topic is empty :{}", topic);
+            return;
+        }
+        debug!("Check topic={}, queues={:?}", topic, msg_queues);
+        for message_queue in msg_queues {
+            let start_time = get_current_millis();
+            let op_queue = MessageQueue::from_parts(
+                TransactionalMessageUtil::build_op_topic(),
+                message_queue.get_broker_name().clone(),
+                message_queue.get_queue_id(),
+            );
+            let half_offset = self
+                .transactional_message_bridge
+                .fetch_consume_offset(&message_queue);
+            let op_offset = self
+                .transactional_message_bridge
+                .fetch_consume_offset(&op_queue);
+            info!(
+                "Before check, the queue={} msgOffset={} opOffset={}",
+                message_queue, half_offset, op_offset
+            );
+            if half_offset < 0 || op_offset < 0 {
+                error!(
+                    "MessageQueue: {} illegal offset read: {}, op offset: {},skip this queue",
+                    message_queue, half_offset, op_offset
+                );
+                continue;
+            }
+
+            let mut done_op_offset = Vec::new();
+            let mut remove_map = HashMap::new();
+            let Some(mut pull_result) = self
+                .fill_op_remove_map(
+                    &mut remove_map,
+                    &op_queue,
+                    op_offset,
+                    half_offset,
+                    &mut done_op_offset,
+                )
+                .await
+            else {
+                error!(
+                    "The queue={} check msgOffset={} with opOffset={} failed, pullResult is null",
+                    message_queue, half_offset, op_offset
+                );
+                continue;
+            };
+
+            let mut get_message_null_count = 1;
+            let mut new_offset = half_offset;
+            let mut i = half_offset;
+            let mut next_op_offset = pull_result.next_begin_offset() as i64;
+            let mut escape_fail_cnt = 0;
+            loop {
+                if get_current_millis() - start_time > MAX_PROCESS_TIME_LIMIT as u64 {
+                    info!(
+                        "Queue={} process time reach max={}",
+                        message_queue, MAX_PROCESS_TIME_LIMIT
+                    );
+                    break;
+                }
+                if let Some(removed_op_offset) = remove_map.remove(&i) {
+                    debug!(
+                        "Half offset {} has been committed/rolled back",
+                        removed_op_offset
+                    );
+                    done_op_offset.push(removed_op_offset);
+                } else {
+                    let Some(get_result) = self.get_half_msg(message_queue.get_queue_id(), i).await
+                    else {
+                        break;
+                    };
+                    let mut msg_ext = get_result.msg;
+                    if get_result
+                        .pull_result
+                        .msg_found_list()
+                        .as_ref()
+                        .and_then(|msg_found_list| msg_found_list.first())
+                        .is_none()
+                    {
+                        if get_message_null_count > MAX_RETRY_COUNT_WHEN_HALF_NULL {
+                            break;
+                        }
+                        get_message_null_count += 1;
+                        if *get_result.pull_result.pull_status() == PullStatus::NoNewMsg {
+                            debug!(
+                                "No new msg, the miss offset={} in={}, continue check={}",
+                                i, message_queue, get_message_null_count
+                            );
+                            break;
+                        }
+                        info!(
+                            "Illegal offset, the miss offset={} in={}, continue check={}",
+                            i, message_queue, get_message_null_count
+                        );
+                        i = get_result.pull_result.next_begin_offset() as i64;
+                        new_offset = i;
+                        continue;
+                    }
+
+                    if need_discard(&mut msg_ext, transaction_check_max) || self.need_skip(&msg_ext)
+                    {
+                        listener.resolve_discard_msg(msg_ext).await;
+                        new_offset = i + 1;
+                        i += 1;
+                        continue;
+                    }
+                    if msg_ext.store_timestamp >= start_time as i64 {
+                        debug!(
+                            "Fresh stored. the miss offset={}, check it later, store={}",
+                            i, msg_ext.store_timestamp
+                        );
+                        break;
+                    }
+
+                    let value_of_current_minus_born =
+                        get_current_millis() as i64 - msg_ext.born_timestamp;
+                    let mut check_immunity_time = transaction_timeout as i64;
+                    let check_immunity_time_str =
+                        msg_ext.get_user_property(&CheetahString::from_static_str(
+                            MessageConst::PROPERTY_CHECK_IMMUNITY_TIME_IN_SECONDS,
+                        ));
+                    if let Some(check_immunity_time_str) = check_immunity_time_str {
+                        check_immunity_time = TransactionalMessageUtil::get_immunity_time(
+                            &check_immunity_time_str,
+                            transaction_timeout,
+                        ) as i64;
+                        if value_of_current_minus_born < check_immunity_time
+                            && self
+                                .check_prepare_queue_offset(
+                                    &mut remove_map,
+                                    &mut done_op_offset,
+                                    &msg_ext,
+                                )
+                                .await
+                        {
+                            new_offset = i + 1;
+                            i += 1;
+                            continue;
+                        }
+                    } else if 0 <= value_of_current_minus_born
+                        && value_of_current_minus_born < check_immunity_time
+                    {
+                        debug!(
+                            "New arrived, the miss offset={}, check it later checkImmunity={}, \
+                             born={}",
+                            i, check_immunity_time, msg_ext.born_timestamp
+                        );
+                        break;
+                    }
+
+                    let op_msg = pull_result.msg_found_list().as_ref();
+                    let is_need_check = match op_msg.and_then(|op_msg| op_msg.last()) {
+                        None => value_of_current_minus_born > check_immunity_time,
+                        Some(last_op_msg) => {
+                            last_op_msg.born_timestamp - start_time as i64
+                                > transaction_timeout as i64
+                        }
+                    } || value_of_current_minus_born <= -1;
+
+                    if is_need_check {
+                        if !self.put_back_half_msg_queue(&mut msg_ext, i).await {
+                            escape_fail_cnt += 1;
+                            if escape_fail_cnt > MAX_RETRY_TIMES_FOR_ESCAPE {
+                                break;
+                            }
+                            continue;
+                        }
+                        listener.resolve_half_msg(msg_ext).await;
+                    } else {
+                        next_op_offset = if pull_result.next_begin_offset() as i64 > next_op_offset
+                        {
+                            pull_result.next_begin_offset() as i64
+                        } else {
+                            next_op_offset
+                        };
+                        let Some(next_pull_result) = self
+                            .fill_op_remove_map(
+                                &mut remove_map,
+                                &op_queue,
+                                next_op_offset,
+                                half_offset,
+                                &mut done_op_offset,
+                            )
+                            .await
+                        else {
+                            break;
+                        };
+                        if *next_pull_result.pull_status() != PullStatus::Found {
+                            tokio::time::sleep(Duration::from_millis(SLEEP_WHILE_NO_OP as u64))
+                                .await;
+                        }
+                        next_op_offset = next_pull_result.next_begin_offset() as i64;
+                        pull_result = next_pull_result;
+                        debug!(
+                            "The miss offset: {} in messageQueue: {} need to get more opMsg, \
+                             result is {}",
+                            i, message_queue, pull_result
+                        );
+                        continue;
+                    }
+                }
+                new_offset = i + 1;
+                i += 1;
+            }
+            if new_offset != half_offset {
+                self.transactional_message_bridge
+                    .update_consume_offset(&message_queue, new_offset);
+            }
+            let new_op_offset = calculate_op_offset(&mut done_op_offset, op_offset);
+            if new_op_offset != op_offset {
+                self.transactional_message_bridge
+                    .update_consume_offset(&op_queue, new_op_offset);
+            }
+            info!(
+                "After check, {} opOffset={} opOffsetDiff={} msgOffset={} msgOffsetDiff={}",
+                message_queue,
+                new_op_offset,
+                new_op_offset - op_offset,
+                new_offset,
+                new_offset - half_offset
+            );
+        }
     }
 
     fn open(&self) -> bool {
@@ -240,10 +760,72 @@ where
     }
 
     fn get_transaction_metrics(&self) -> &TransactionMetrics {
-        unimplemented!("get_transaction_metrics")
+        &self.transaction_metrics
     }
 
-    fn set_transaction_metrics(&mut self, _transaction_metrics: TransactionMetrics) {
-        unimplemented!("set_transaction_metrics")
+    fn set_transaction_metrics(&mut self, transaction_metrics: TransactionMetrics) {
+        self.transaction_metrics = transaction_metrics;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn op_offset_advances_over_contiguous_done_offsets() {
+        assert_eq!(calculate_op_offset(&mut [], 5), 5);
+        assert_eq!(calculate_op_offset(&mut [7, 5, 6, 9], 5), 8);
+        assert_eq!(calculate_op_offset(&mut [3, 4, 6], 5), 5);
+    }
+
+    #[test]
+    fn discards_after_max_check_times() {
+        let mut msg_ext = MessageExt::default();
+        assert!(!need_discard(&mut msg_ext, 2));
+        assert!(!need_discard(&mut msg_ext, 2));
+        assert!(need_discard(&mut msg_ext, 2));
+    }
+
+    #[tokio::test]
+    async fn builds_op_message_from_buffered_offsets() {
+        let mq_context = MessageQueueOpContext::new(get_current_millis(), 16);
+        for offset in ["1,", "2,"] {
+            assert!(
+                mq_context
+                    .context_queue()
+                    .offer(offset.to_string(), Duration::from_millis(10))
+                    .await
+            );
+            mq_context.total_size_add_and_get(offset.len() as i32);
+        }
+        let message = build_op_message(&mq_context, Some("0,".to_string()), 4096)
+            .await
+            .unwrap();
+        assert_eq!(
+            message.get_topic().as_str(),
+            TransactionalMessageUtil::build_op_topic()
+        );
+        assert_eq!(
+            message.get_tags().unwrap().as_str(),
+            TransactionalMessageUtil::REMOVE_TAG
+        );
+        assert_eq!(message.get_body().unwrap().as_ref(), b"0,1,2,");
+        assert_eq!(mq_context.get_total_size(), 0);
+        assert!(build_op_message(&mq_context, None, 4096).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn limits_op_message_size() {
+        let mq_context = MessageQueueOpContext::new(get_current_millis(), 16);
+        for offset in ["10,", "11,", "12,"] {
+            mq_context
+                .context_queue()
+                .offer(offset.to_string(), Duration::from_millis(10))
+                .await;
+        }
+        let message = build_op_message(&mq_context, None, 6).await.unwrap();
+        assert_eq!(message.get_body().unwrap().as_ref(), b"10,11,");
+        assert!(!mq_context.context_queue().is_empty().await);
     }
 }
@@ -61,6 +61,16 @@ pub struct TransactionalMessageBridge<MS> {
     pub(crate) broker_runtime_inner: ArcMut<BrokerRuntimeInner<MS>>,
 }
 
+impl<MS> Clone for TransactionalMessageBridge<MS> {
+    fn clone(&self) -> Self {
+        Self {
+            op_queue_map: Arc::clone(&self.op_queue_map),
+            store_host: self.store_host,
+            broker_runtime_inner: self.broker_runtime_inner.clone(),
+        }
+    }
+}
+
 impl<MS> TransactionalMessageBridge<MS>
 where
     MS: MessageStore,
@@ -140,21 +150,50 @@ where
             );
     }
 
+    /// Reads half messages from the local store. A slave acting as master which has not
+    /// replicated the message at `offset` yet pulls it from the master of its group instead.
     pub async fn get_half_message(
         &self,
         queue_id: i32,
         offset: i64,
         nums: i32,
     ) -> Option<PullResult> {
-        self.get_message(
-            &CheetahString::from_static_str(TransactionalMessageUtil::build_consumer_group()),
-            &CheetahString::from_static_str(TransactionalMessageUtil::build_half_topic()),
-            queue_id,
-            offset,
-            nums,
-            None,
-        )
-        .await
+        let half_topic =
+            CheetahString::from_static_str(TransactionalMessageUtil::build_half_topic());
+        let pull_result = self
+            .get_message(
+                &CheetahString::from_static_str(TransactionalMessageUtil::build_consumer_group()),
+                &half_topic,
+                queue_id,
+                offset,
+                nums,
+                None,
+            )
+            .await;
+        if pull_result
+            .as_ref()
+            .is_some_and(|pull_result| *pull_result.pull_status() == PullStatus::Found)
+        {
+            return pull_result;
+        }
+        let (message, _, _) = self
+            .broker_runtime_inner
+            .escape_bridge()
+            .get_message_from_master_async(&half_topic, offset, queue_id)
+            .await;
+        let Some(message) = message else {
+            return pull_result;
+        };
+        let (min_offset, max_offset) = pull_result.as_ref().map_or((0, 0), |pull_result| {
+            (pull_result.min_offset(), pull_result.max_offset())
+        });
+        Some(PullResult::new(
+            PullStatus::Found,
+            offset as u64 + 1,
+            min_offset,
+            max_offset,
+            Some(vec![ArcMut::new(message)]),
+        ))
     }
 
     pub async fn get_op_message(
@@ -14,41 +14,65 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::future::Future;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
 use rocketmq_common::TimeUtils::get_current_millis;
 use tokio::sync::Notify;
 use tracing::info;
 
+/// Wakes the writer of the transaction op topic, either periodically or when a queue has buffered
+/// enough commit and rollback records.
 #[derive(Default, Clone)]
 pub struct TransactionalOpBatchService {
     notify: Arc<Notify>,
+    stopped: Arc<AtomicBool>,
 }
 
 impl TransactionalOpBatchService {
     pub fn new() -> Self {
         TransactionalOpBatchService {
             notify: Arc::new(Notify::new()),
+            stopped: Arc::new(AtomicBool::new(false)),
         }
     }
 
     pub fn wakeup(&self) {
-        self.notify.notify_waiters();
+        self.notify.notify_one();
     }
 
-    pub fn run(&self, transaction_op_batch_interval: u64) {
+    /// Runs `batch_send_op_message` until shutdown. It returns the timestamp of the next write,
+    /// the service sleeps until then unless woken up earlier.
+    pub fn start<F, Fut>(&self, transaction_op_batch_interval: u64, batch_send_op_message: F)
+    where
+        F: Fn() -> Fut + Send + 'static,
+        Fut: Future<Output = u64> + Send,
+    {
         let this = self.clone();
         tokio::spawn(async move {
             info!("TransactionalOpBatchService started");
-            let wakeup_timestamp = get_current_millis() + transaction_op_batch_interval;
-            loop {
-                let mut interval = (wakeup_timestamp as i64) - get_current_millis() as i64;
-                if interval <= 0 {
-                    interval = 0;
-                    this.wakeup();
+            let mut wakeup_timestamp = get_current_millis() + transaction_op_batch_interval;
+            while !this.stopped.load(Ordering::Acquire) {
+                let interval = wakeup_timestamp.saturating_sub(get_current_millis());
+                if interval > 0 {
+                    tokio::select! {
+                        _ = this.notify.notified() => {}
+                        _ = tokio::time::sleep(tokio::time::Duration::from_millis(interval)) => {}
+                    }
                 }
-                tokio::time::sleep(tokio::time::Duration::from_millis(interval as u64)).await;
+                if this.stopped.load(Ordering::Acquire) {
+                    break;
+                }
+                wakeup_timestamp = batch_send_op_message().await;
             }
+            info!("TransactionalOpBatchService end");
         });
     }
+
+    pub fn shutdown(&self) {
+        self.stopped.store(true, Ordering::Release);
+        self.notify.notify_one();
+    }
 }
@@ -18,8 +18,19 @@
 use rocketmq_common::common::message::message_ext::MessageExt;
 
 /// Trait defining the listener for transactional message checks.
-/// This trait provides a method for resolving discarded messages.
-pub trait TransactionalMessageCheckListener {
+/// This trait provides methods for checking back half messages and resolving discarded messages.
+#[trait_variant::make(TransactionalMessageCheckListener: Send)]
+pub trait TransactionalMessageCheckListenerLocal {
+    /// Asks a producer of the group of `msg_ext` for the state of its local transaction.
+    ///
+    /// The request is sent in the background, the producer answers with an end transaction
+    /// request.
+    ///
+    /// # Arguments
+    ///
+    /// * `msg_ext` - The half message to check back
+    async fn resolve_half_msg(&mut self, msg_ext: MessageExt);
+
     /// Attempts to resolve a discarded message, typically called when a transaction
     /// message needs cleanup or final disposition.
     ///
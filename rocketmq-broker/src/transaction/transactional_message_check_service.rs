@@ -15,13 +15,86 @@
  * limitations under the License.
  */
 
-use tracing::warn;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
 
-#[derive(Clone)]
-pub struct TransactionalMessageCheckService;
+use rocketmq_common::common::broker::broker_role::BrokerRole;
+use rocketmq_common::TimeUtils::get_current_millis;
+use rocketmq_rust::ArcMut;
+use rocketmq_store::log_file::MessageStore;
+use tokio::sync::Notify;
+use tracing::info;
+
+use crate::broker_runtime::BrokerRuntimeInner;
+use crate::transaction::queue::default_transactional_message_check_listener::DefaultTransactionalMessageCheckListener;
+use crate::transaction::queue::default_transactional_message_service::DefaultTransactionalMessageService;
+use crate::transaction::transactional_message_service::TransactionalMessageService;
+
+/// Scans the half messages every `transaction_check_interval` and checks back the ones whose
+/// transaction is still unknown with their producer.
+#[derive(Clone, Default)]
+pub struct TransactionalMessageCheckService {
+    stopped: Arc<AtomicBool>,
+    notify: Arc<Notify>,
+}
 
 impl TransactionalMessageCheckService {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn start<MS: MessageStore>(
+        &self,
+        mut transactional_message_service: ArcMut<DefaultTransactionalMessageService<MS>>,
+        mut listener: DefaultTransactionalMessageCheckListener<MS>,
+        broker_runtime_inner: ArcMut<BrokerRuntimeInner<MS>>,
+    ) {
+        let this = self.clone();
+        tokio::spawn(async move {
+            info!("TransactionalMessageCheckService started");
+            while !this.stopped.load(Ordering::Acquire) {
+                let check_interval = broker_runtime_inner
+                    .broker_config()
+                    .transaction_check_interval;
+                tokio::select! {
+                    _ = tokio::time::sleep(Duration::from_millis(check_interval)) => {}
+                    _ = this.notify.notified() => {}
+                }
+                if this.stopped.load(Ordering::Acquire) {
+                    break;
+                }
+                // Only the master answers end transaction requests, so only it checks back.
+                if broker_runtime_inner.message_store_config().broker_role == BrokerRole::Slave {
+                    continue;
+                }
+                let (timeout, check_max) = {
+                    let broker_config = broker_runtime_inner.broker_config();
+                    (
+                        broker_config.transaction_timeout,
+                        broker_config.transaction_check_max,
+                    )
+                };
+                info!(
+                    "Begin to check prepare message, begin time:{}",
+                    get_current_millis()
+                );
+                let begin = get_current_millis();
+                transactional_message_service
+                    .check(timeout, check_max, &mut listener)
+                    .await;
+                info!(
+                    "End to check prepare message, consumed time:{}",
+                    get_current_millis() - begin
+                );
+            }
+            info!("TransactionalMessageCheckService end");
+        });
+    }
+
     pub fn shutdown(&mut self) {
-        warn!("TransactionalMessageCheckService shutdown not implemented");
+        self.stopped.store(true, Ordering::Release);
+        self.notify.notify_one();
     }
 }
@@ -21,6 +21,7 @@ use rocketmq_store::base::message_result::PutMessageResult;
 
 use crate::transaction::operation_result::OperationResult;
 use crate::transaction::transaction_metrics::TransactionMetrics;
+use crate::transaction::transactional_message_check_listener::TransactionalMessageCheckListener;
 
 /// Trait defining the local transactional message service.
 /// This trait provides methods for preparing, committing, rolling back, and checking transactional
@@ -72,7 +73,10 @@ pub trait TransactionalMessageServiceLocal: Sync + 'static {
     /// # Returns
     ///
     /// An `OperationResult` indicating the result of the commit operation.
-    fn commit_message(&mut self, request_header: &EndTransactionRequestHeader) -> OperationResult;
+    async fn commit_message(
+        &mut self,
+        request_header: &EndTransactionRequestHeader,
+    ) -> OperationResult;
 
     /// Rolls back a transactional message.
     ///
@@ -83,21 +87,29 @@ pub trait TransactionalMessageServiceLocal: Sync + 'static {
     /// # Returns
     ///
     /// An `OperationResult` indicating the result of the rollback operation.
-    fn rollback_message(&mut self, request_header: &EndTransactionRequestHeader)
-        -> OperationResult;
+    async fn rollback_message(
+        &mut self,
+        request_header: &EndTransactionRequestHeader,
+    ) -> OperationResult;
 
     /// Checks the state of transactional messages.
     ///
+    /// Half messages that are neither committed nor rolled back after `transaction_timeout` are
+    /// checked back with their producer through `listener`, or discarded once they were checked
+    /// `transaction_check_max` times.
+    ///
     /// # Arguments
     ///
     /// * `transaction_timeout` - The timeout for the transaction.
     /// * `transaction_check_max` - The maximum number of transaction checks.
-    fn check(
-        &self,
+    /// * `listener` - The listener checking back or discarding the half messages.
+    async fn check<L>(
+        &mut self,
         transaction_timeout: u64,
         transaction_check_max: i32,
-        // listener: AbstractTransactionalMessageCheckListener,
-    );
+        listener: &mut L,
+    ) where
+        L: TransactionalMessageCheckListener + Send;
 
     /// Opens the transactional message service.
     ///
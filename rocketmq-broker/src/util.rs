@@ -16,3 +16,6 @@
  */
 
 pub(crate) mod hook_utils;
+pub(crate) mod ordering_checker;
+pub(crate) mod process_metrics;
+pub(crate) mod response_compression;
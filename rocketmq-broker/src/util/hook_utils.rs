@@ -125,6 +125,18 @@ impl HookUtils {
                 PutMessageStatus::MessageIllegal,
             ));
         }
+        let body_length = msg.body().map_or(0, |body| body.len());
+        if body_length > message_store_config.max_message_size.max(0) as usize {
+            warn!(
+                "putMessage message topic[{}] body length {} exceeds the max message size {}",
+                msg.topic(),
+                body_length,
+                message_store_config.max_message_size
+            );
+            return Some(PutMessageResult::new_default(
+                PutMessageStatus::MessageIllegal,
+            ));
+        }
         if message_store.is_os_page_cache_busy() {
             return Some(PutMessageResult::new_default(
                 PutMessageStatus::OsPageCacheBusy,
@@ -134,6 +146,22 @@ impl HookUtils {
         None
     }
 
+    /// Rejects a message whose properties do not fit the `i16` length they are stored with.
+    pub fn check_properties_size(msg: &MessageExtBrokerInner) -> Option<PutMessageResult> {
+        let properties_length = msg.properties_string.len();
+        if properties_length > i16::MAX as usize {
+            warn!(
+                "putMessage message topic[{}] properties length too long {}",
+                msg.topic(),
+                properties_length
+            );
+            return Some(PutMessageResult::new_default(
+                PutMessageStatus::PropertiesSizeExceeded,
+            ));
+        }
+        None
+    }
+
     pub fn check_inner_batch(
         topic_config_table: &Arc<parking_lot::Mutex<HashMap<CheetahString, TopicConfig>>>,
         msg: &MessageExt,
@@ -418,4 +446,24 @@ mod tests {
             PutMessageStatus::MessageIllegal
         );
     }
+
+    #[test]
+    fn check_properties_size_rejects_properties_longer_than_i16() {
+        let msg = MessageExtBrokerInner {
+            properties_string: CheetahString::from_string("a".repeat(i16::MAX as usize)),
+            ..Default::default()
+        };
+        assert!(HookUtils::check_properties_size(&msg).is_none());
+
+        let msg = MessageExtBrokerInner {
+            properties_string: CheetahString::from_string("a".repeat(i16::MAX as usize + 1)),
+            ..Default::default()
+        };
+        assert_eq!(
+            HookUtils::check_properties_size(&msg)
+                .unwrap()
+                .put_message_status(),
+            PutMessageStatus::PropertiesSizeExceeded
+        );
+    }
 }
@@ -0,0 +1,178 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use cheetah_string::CheetahString;
+use dashmap::DashMap;
+use rocketmq_store::base::commit_log_dispatcher::CommitLogDispatcher;
+use rocketmq_store::base::dispatch_request::DispatchRequest;
+use rocketmq_store::base::get_message_result::GetMessageResult;
+use tracing::warn;
+
+/// Debug aid enabled by `enableOrderingCheck`, checks that queue offsets only move forward.
+///
+/// Added as a commit log dispatcher it checks that every queue is dispatched in offset order, the
+/// pull and pop paths hand it what they read so that the offsets delivered from one queue are
+/// checked as well. Violations are only logged and counted, nothing is rejected.
+#[derive(Clone, Default)]
+pub(crate) struct OrderingChecker {
+    /// Last consume queue offset dispatched per topic and queue.
+    dispatched: Arc<DashMap<(CheetahString, i32), i64>>,
+    violations: Arc<AtomicU64>,
+}
+
+impl OrderingChecker {
+    /// Checks the messages read for `group` from `queue_id` of `topic` starting at
+    /// `request_offset`: they must be in strictly increasing offset order, none before the
+    /// requested offset, and the next offset to read must lie past them.
+    pub(crate) fn check_delivery(
+        &self,
+        path: &str,
+        group: &str,
+        topic: &str,
+        queue_id: i32,
+        request_offset: i64,
+        result: &GetMessageResult,
+    ) -> bool {
+        let mut previous = None;
+        for &offset in result.message_queue_offset() {
+            let offset = offset as i64;
+            let violation = match previous {
+                None if offset < request_offset => Some(request_offset),
+                Some(previous) if offset <= previous => Some(previous),
+                _ => None,
+            };
+            if let Some(bound) = violation {
+                self.report(format_args!(
+                    "{} of group {} delivered offset {} after {} from {}-{}, requested from {}, \
+                     next begin offset {}",
+                    path,
+                    group,
+                    offset,
+                    bound,
+                    topic,
+                    queue_id,
+                    request_offset,
+                    result.next_begin_offset()
+                ));
+                return false;
+            }
+            previous = Some(offset);
+        }
+        if let Some(last) = previous {
+            if result.next_begin_offset() <= last {
+                self.report(format_args!(
+                    "{} of group {} returned next begin offset {} not past the delivered offset \
+                     {} of {}-{}",
+                    path,
+                    group,
+                    result.next_begin_offset(),
+                    last,
+                    topic,
+                    queue_id
+                ));
+                return false;
+            }
+        }
+        true
+    }
+
+    fn check_dispatch(&self, dispatch_request: &DispatchRequest) -> bool {
+        let offset = dispatch_request.consume_queue_offset;
+        let previous = self.dispatched.insert(
+            (dispatch_request.topic.clone(), dispatch_request.queue_id),
+            offset,
+        );
+        match previous {
+            Some(previous) if offset <= previous => {
+                self.report(format_args!(
+                    "dispatched offset {} after {} to {}-{}, commit log offset {}, size {}",
+                    offset,
+                    previous,
+                    dispatch_request.topic,
+                    dispatch_request.queue_id,
+                    dispatch_request.commit_log_offset,
+                    dispatch_request.msg_size
+                ));
+                false
+            }
+            _ => true,
+        }
+    }
+
+    fn report(&self, violation: std::fmt::Arguments<'_>) {
+        let count = self.violations.fetch_add(1, Ordering::Relaxed) + 1;
+        warn!("ordering violation #{}: {}", count, violation);
+    }
+}
+
+impl CommitLogDispatcher for OrderingChecker {
+    fn dispatch(&self, dispatch_request: &mut DispatchRequest) {
+        if dispatch_request.success {
+            self.check_dispatch(dispatch_request);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dispatch_request(queue_id: i32, consume_queue_offset: i64) -> DispatchRequest {
+        DispatchRequest {
+            topic: CheetahString::from_static_str("topic"),
+            queue_id,
+            consume_queue_offset,
+            success: true,
+            ..Default::default()
+        }
+    }
+
+    fn get_message_result(offsets: &[u64], next_begin_offset: i64) -> GetMessageResult {
+        let mut result = GetMessageResult::new();
+        result.set_message_queue_offset(offsets.to_vec());
+        result.set_next_begin_offset(next_begin_offset);
+        result
+    }
+
+    #[test]
+    fn reports_dispatch_going_backwards_per_queue() {
+        let checker = OrderingChecker::default();
+        assert!(checker.check_dispatch(&dispatch_request(0, 0)));
+        assert!(checker.check_dispatch(&dispatch_request(0, 1)));
+        assert!(checker.check_dispatch(&dispatch_request(1, 0)));
+        assert!(!checker.check_dispatch(&dispatch_request(0, 1)));
+        assert_eq!(checker.violations.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn reports_delivery_out_of_order() {
+        let checker = OrderingChecker::default();
+        let ok = get_message_result(&[5, 6, 7], 8);
+        assert!(checker.check_delivery("pull", "group", "topic", 0, 5, &ok));
+
+        let before_request = get_message_result(&[4, 5], 6);
+        assert!(!checker.check_delivery("pull", "group", "topic", 0, 5, &before_request));
+        let unordered = get_message_result(&[5, 7, 6], 8);
+        assert!(!checker.check_delivery("pop", "group", "topic", 0, 5, &unordered));
+        let stale_next = get_message_result(&[5, 6], 6);
+        assert!(!checker.check_delivery("pop", "group", "topic", 0, 5, &stale_next));
+        assert_eq!(checker.violations.load(Ordering::Relaxed), 3);
+    }
+}
@@ -0,0 +1,203 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::time::Duration;
+use std::time::Instant;
+
+use parking_lot::Mutex;
+use rocketmq_store::log_file::mapped_file::default_mapped_file_impl::DefaultMappedFile;
+use sysinfo::ProcessesToUpdate;
+use sysinfo::System;
+use tokio::runtime::RuntimeMetrics;
+
+/// Busy time of the tokio workers at the previous report, the utilization is measured between
+/// two reports.
+static LAST_WORKER_BUSY: Mutex<Option<WorkerBusySnapshot>> = Mutex::new(None);
+
+/// Total busy time of every tokio worker at one instant.
+struct WorkerBusySnapshot {
+    at: Instant,
+    busy: Vec<Duration>,
+}
+
+impl WorkerBusySnapshot {
+    fn take(metrics: &RuntimeMetrics) -> Self {
+        WorkerBusySnapshot {
+            at: Instant::now(),
+            busy: (0..metrics.num_workers())
+                .map(|worker| metrics.worker_total_busy_duration(worker))
+                .collect(),
+        }
+    }
+}
+
+/// Process level metrics of the broker, the counterpart of the JVM section of the Java broker
+/// runtime info.
+pub(crate) fn build_process_metrics(runtime_info: &mut HashMap<String, String>) {
+    let (rss, virtual_memory) = process_memory();
+    runtime_info.insert("processResidentMemory".to_string(), rss.to_string());
+    runtime_info.insert(
+        "processVirtualMemory".to_string(),
+        virtual_memory.to_string(),
+    );
+    runtime_info.insert(
+        "processOpenFileDescriptors".to_string(),
+        open_file_descriptors().to_string(),
+    );
+    runtime_info.insert("processThreads".to_string(), threads().to_string());
+    runtime_info.insert(
+        "processMappedBytes".to_string(),
+        DefaultMappedFile::get_total_mapped_virtual_memory().to_string(),
+    );
+    runtime_info.insert(
+        "processMappedFiles".to_string(),
+        DefaultMappedFile::get_total_mapped_files().to_string(),
+    );
+
+    let Ok(handle) = tokio::runtime::Handle::try_current() else {
+        return;
+    };
+    let metrics = handle.metrics();
+    let workers = metrics.num_workers();
+    runtime_info.insert("tokioWorkerThreads".to_string(), workers.to_string());
+    runtime_info.insert(
+        "tokioAliveTasks".to_string(),
+        metrics.num_alive_tasks().to_string(),
+    );
+    runtime_info.insert(
+        "tokioGlobalQueueDepth".to_string(),
+        metrics.global_queue_depth().to_string(),
+    );
+    let current = WorkerBusySnapshot::take(&metrics);
+    let mut last = LAST_WORKER_BUSY.lock();
+    let utilization = last
+        .as_ref()
+        .map_or(-1.0, |previous| worker_utilization(previous, &current));
+    *last = Some(current);
+    runtime_info.insert(
+        "tokioWorkerUtilization".to_string(),
+        format!("{:.4}", utilization),
+    );
+}
+
+/// The part of the time between two snapshots the workers spent busy, `-1` when the snapshots
+/// can not be compared.
+fn worker_utilization(previous: &WorkerBusySnapshot, current: &WorkerBusySnapshot) -> f64 {
+    let elapsed = current.at.saturating_duration_since(previous.at);
+    if previous.busy.len() != current.busy.len() || current.busy.is_empty() || elapsed.is_zero() {
+        return -1.0;
+    }
+    let busy: Duration = previous
+        .busy
+        .iter()
+        .zip(&current.busy)
+        .map(|(before, after)| after.saturating_sub(*before))
+        .sum();
+    let utilization = busy.as_secs_f64() / (elapsed.as_secs_f64() * current.busy.len() as f64);
+    utilization.min(1.0)
+}
+
+/// Resident and virtual memory of the broker process in bytes, `-1` when unknown.
+fn process_memory() -> (i64, i64) {
+    let Ok(pid) = sysinfo::get_current_pid() else {
+        return (-1, -1);
+    };
+    let mut system = System::new();
+    system.refresh_processes(ProcessesToUpdate::Some(&[pid]), false);
+    match system.process(pid) {
+        Some(process) => (process.memory() as i64, process.virtual_memory() as i64),
+        None => (-1, -1),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn open_file_descriptors() -> i64 {
+    std::fs::read_dir("/proc/self/fd")
+        .map(|entries| entries.count() as i64)
+        .unwrap_or(-1)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn open_file_descriptors() -> i64 {
+    -1
+}
+
+#[cfg(target_os = "linux")]
+fn threads() -> i64 {
+    std::fs::read_dir("/proc/self/task")
+        .map(|entries| entries.count() as i64)
+        .unwrap_or(-1)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn threads() -> i64 {
+    -1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn includes_process_section() {
+        let mut runtime_info = HashMap::new();
+        build_process_metrics(&mut runtime_info);
+        assert!(runtime_info.contains_key("processResidentMemory"));
+        assert!(runtime_info.contains_key("processMappedBytes"));
+        assert!(runtime_info.contains_key("tokioWorkerThreads"));
+        assert!(runtime_info.contains_key("tokioWorkerUtilization"));
+    }
+
+    #[test]
+    fn worker_utilization_is_the_busy_part_of_the_interval() {
+        let at = Instant::now();
+        let previous = WorkerBusySnapshot {
+            at,
+            busy: vec![Duration::from_millis(100), Duration::from_millis(200)],
+        };
+        let current = WorkerBusySnapshot {
+            at: at + Duration::from_secs(1),
+            busy: vec![Duration::from_millis(600), Duration::from_millis(1200)],
+        };
+        assert!((worker_utilization(&previous, &current) - 0.75).abs() < 1e-9);
+
+        let resized = WorkerBusySnapshot {
+            at: at + Duration::from_secs(1),
+            busy: vec![Duration::from_millis(600)],
+        };
+        assert_eq!(worker_utilization(&previous, &resized), -1.0);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn busy_workers_are_measured_between_two_snapshots() {
+        let metrics = tokio::runtime::Handle::current().metrics();
+        let previous = WorkerBusySnapshot::take(&metrics);
+        tokio::spawn(async {
+            let start = Instant::now();
+            while start.elapsed() < Duration::from_millis(50) {
+                std::hint::spin_loop();
+            }
+        })
+        .await
+        .unwrap();
+        // the busy time of a worker is published when it parks
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let current = WorkerBusySnapshot::take(&metrics);
+        let utilization = worker_utilization(&previous, &current);
+        assert!(utilization > 0.0 && utilization <= 1.0, "{}", utilization);
+    }
+}
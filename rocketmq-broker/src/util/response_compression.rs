@@ -0,0 +1,98 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use bytes::Bytes;
+use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::common::compression::compression_type::CompressionType;
+
+/// Compresses the message block of a pull or pop response when the broker enables it and the
+/// client accepts it.
+///
+/// Returns the body to send and the value of the compression type the client decompresses it
+/// with, `None` when the body is sent as is. Small blocks and blocks that do not shrink are never
+/// compressed.
+pub(crate) fn compress_response_body(
+    broker_config: &BrokerConfig,
+    accept_compression: Option<bool>,
+    body: Bytes,
+) -> (Bytes, Option<i32>) {
+    if !broker_config.pull_response_compression_enable
+        || !accept_compression.unwrap_or(false)
+        || body.len() < broker_config.pull_response_compression_min_bytes
+    {
+        return (body, None);
+    }
+    let Some(compression_type) =
+        parse_compression_type(broker_config.pull_response_compression_type.as_str())
+    else {
+        return (body, None);
+    };
+    let compressed = compression_type.compression(&body);
+    if compressed.len() >= body.len() {
+        return (body, None);
+    }
+    (compressed, Some(compression_type.get_value()))
+}
+
+fn parse_compression_type(name: &str) -> Option<CompressionType> {
+    match name.trim().to_uppercase().as_str() {
+        "LZ4" => Some(CompressionType::LZ4),
+        "ZSTD" => Some(CompressionType::Zstd),
+        "ZLIB" => Some(CompressionType::Zlib),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> BrokerConfig {
+        BrokerConfig {
+            pull_response_compression_enable: true,
+            pull_response_compression_min_bytes: 16,
+            ..BrokerConfig::default()
+        }
+    }
+
+    #[test]
+    fn compresses_when_accepted() {
+        let body = Bytes::from(vec![b'a'; 1024]);
+        let (compressed, compression_type) =
+            compress_response_body(&config(), Some(true), body.clone());
+        assert_eq!(compression_type, Some(CompressionType::Zstd.get_value()));
+        assert!(compressed.len() < body.len());
+        assert_eq!(CompressionType::Zstd.decompression(&compressed), body);
+    }
+
+    #[test]
+    fn keeps_body_when_not_accepted_or_small() {
+        let body = Bytes::from(vec![b'a'; 1024]);
+        assert_eq!(
+            compress_response_body(&config(), None, body.clone()).1,
+            None
+        );
+        assert_eq!(
+            compress_response_body(&BrokerConfig::default(), Some(true), body).1,
+            None
+        );
+        let small = Bytes::from_static(b"abc");
+        assert_eq!(
+            compress_response_body(&config(), Some(true), small.clone()),
+            (small, None)
+        );
+    }
+}
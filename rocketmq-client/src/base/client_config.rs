@@ -40,6 +40,7 @@ pub const DECODE_DECOMPRESS_BODY: &str = "com.rocketmq.decompress.body";
 pub const SEND_LATENCY_ENABLE: &str = "com.rocketmq.sendLatencyEnable";
 pub const START_DETECTOR_ENABLE: &str = "com.rocketmq.startDetectorEnable";
 pub const HEART_BEAT_V2: &str = "com.rocketmq.heartbeat.v2";
+pub const TRACE_CONTEXT_PROPAGATION_ENABLE: &str = "com.rocketmq.traceContextPropagation";
 
 #[derive(Clone)]
 pub struct ClientConfig {
@@ -64,6 +65,9 @@ pub struct ClientConfig {
     pub use_tls: bool,
     pub socks_proxy_config: CheetahString,
     pub mq_client_api_timeout: u64,
+    /// Topics queried from the name server in one route request at most, `0` or `1` queries
+    /// every topic on its own.
+    pub route_query_batch_size: usize,
     pub detect_timeout: u32,
     pub detect_interval: u32,
     pub language: LanguageCode,
@@ -73,6 +77,10 @@ pub struct ClientConfig {
     pub enable_heartbeat_channel_event_listener: bool,
     pub enable_trace: bool,
     pub trace_topic: Option<CheetahString>,
+    /// Whether to inject and extract the W3C `traceparent` message property.
+    pub enable_trace_context_propagation: bool,
+    /// Whether brokers may compress the message block of pull and pop responses.
+    pub accept_compressed_pull_body: bool,
 }
 
 impl Default for ClientConfig {
@@ -122,6 +130,7 @@ impl ClientConfig {
                 .unwrap_or_else(|_| "{}".to_string())
                 .into(),
             mq_client_api_timeout: Duration::from_secs(3).as_millis() as u64,
+            route_query_batch_size: 64,
             detect_timeout: 200,
             detect_interval: Duration::from_secs(2).as_millis() as u32,
             language: LanguageCode::RUST,
@@ -135,6 +144,11 @@ impl ClientConfig {
             enable_heartbeat_channel_event_listener: true,
             enable_trace: false,
             trace_topic: None,
+            enable_trace_context_propagation: env::var(TRACE_CONTEXT_PROPAGATION_ENABLE)
+                .unwrap_or_else(|_| "true".to_string())
+                .parse::<bool>()
+                .unwrap_or(true),
+            accept_compressed_pull_body: false,
         }
     }
 }
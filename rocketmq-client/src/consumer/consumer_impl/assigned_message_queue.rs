@@ -0,0 +1,245 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use parking_lot::RwLock;
+use rocketmq_common::common::message::message_queue::MessageQueue;
+
+use crate::consumer::consumer_impl::process_queue::ProcessQueue;
+
+/// The queues assigned to a lite pull consumer, with the positions it pulls and consumes them
+/// from.
+#[derive(Default)]
+pub(crate) struct AssignedMessageQueue {
+    assigned_message_queue_state: RwLock<HashMap<MessageQueue, MessageQueueState>>,
+}
+
+struct MessageQueueState {
+    process_queue: ProcessQueue,
+    paused: bool,
+    pull_offset: i64,
+    consume_offset: i64,
+    seek_offset: i64,
+}
+
+impl MessageQueueState {
+    fn new() -> Self {
+        Self {
+            process_queue: ProcessQueue::new(),
+            paused: false,
+            pull_offset: -1,
+            consume_offset: -1,
+            seek_offset: -1,
+        }
+    }
+}
+
+impl AssignedMessageQueue {
+    pub(crate) fn message_queues(&self) -> HashSet<MessageQueue> {
+        self.assigned_message_queue_state
+            .read()
+            .keys()
+            .cloned()
+            .collect()
+    }
+
+    pub(crate) fn contains(&self, message_queue: &MessageQueue) -> bool {
+        self.assigned_message_queue_state
+            .read()
+            .contains_key(message_queue)
+    }
+
+    /// Replaces the assignment, returning the queues that were added. The process queues of the
+    /// removed ones are dropped.
+    pub(crate) fn update_assigned_message_queue(
+        &self,
+        assigned: &HashSet<MessageQueue>,
+    ) -> Vec<MessageQueue> {
+        let mut state = self.assigned_message_queue_state.write();
+        state.retain(|message_queue, queue_state| {
+            let keep = assigned.contains(message_queue);
+            if !keep {
+                queue_state.process_queue.set_dropped(true);
+            }
+            keep
+        });
+        let mut added = Vec::new();
+        for message_queue in assigned {
+            if !state.contains_key(message_queue) {
+                state.insert(message_queue.clone(), MessageQueueState::new());
+                added.push(message_queue.clone());
+            }
+        }
+        added
+    }
+
+    pub(crate) fn pause(&self, message_queues: &[MessageQueue]) {
+        self.set_paused(message_queues, true);
+    }
+
+    pub(crate) fn resume(&self, message_queues: &[MessageQueue]) {
+        self.set_paused(message_queues, false);
+    }
+
+    fn set_paused(&self, message_queues: &[MessageQueue], paused: bool) {
+        let mut state = self.assigned_message_queue_state.write();
+        for message_queue in message_queues {
+            if let Some(queue_state) = state.get_mut(message_queue) {
+                queue_state.paused = paused;
+            }
+        }
+    }
+
+    pub(crate) fn is_paused(&self, message_queue: &MessageQueue) -> bool {
+        self.assigned_message_queue_state
+            .read()
+            .get(message_queue)
+            .is_some_and(|queue_state| queue_state.paused)
+    }
+
+    pub(crate) fn process_queue(&self, message_queue: &MessageQueue) -> Option<ProcessQueue> {
+        self.assigned_message_queue_state
+            .read()
+            .get(message_queue)
+            .map(|queue_state| queue_state.process_queue.clone())
+    }
+
+    /// Returns the process queue to pull `message_queue` into and the offset to pull from, which
+    /// is the pending seek offset when there is one. The offset is -1 when it is not known yet.
+    pub(crate) fn next_pull(&self, message_queue: &MessageQueue) -> Option<(ProcessQueue, i64)> {
+        let mut state = self.assigned_message_queue_state.write();
+        let queue_state = state.get_mut(message_queue)?;
+        if queue_state.seek_offset != -1 {
+            queue_state.pull_offset = queue_state.seek_offset;
+            queue_state.seek_offset = -1;
+        }
+        Some((queue_state.process_queue.clone(), queue_state.pull_offset))
+    }
+
+    /// Records where the next pull of `message_queue` starts, unless `process_queue` was
+    /// replaced by a seek in the meantime.
+    pub(crate) fn update_pull_offset(
+        &self,
+        message_queue: &MessageQueue,
+        offset: i64,
+        process_queue: &ProcessQueue,
+    ) {
+        if process_queue.is_dropped() {
+            return;
+        }
+        if let Some(queue_state) = self
+            .assigned_message_queue_state
+            .write()
+            .get_mut(message_queue)
+        {
+            queue_state.pull_offset = offset;
+        }
+    }
+
+    pub(crate) fn update_consume_offset(&self, message_queue: &MessageQueue, offset: i64) {
+        if let Some(queue_state) = self
+            .assigned_message_queue_state
+            .write()
+            .get_mut(message_queue)
+        {
+            queue_state.consume_offset = offset;
+        }
+    }
+
+    pub(crate) fn consume_offset(&self, message_queue: &MessageQueue) -> i64 {
+        self.assigned_message_queue_state
+            .read()
+            .get(message_queue)
+            .map_or(-1, |queue_state| queue_state.consume_offset)
+    }
+
+    pub(crate) fn seek_offset(&self, message_queue: &MessageQueue) -> i64 {
+        self.assigned_message_queue_state
+            .read()
+            .get(message_queue)
+            .map_or(-1, |queue_state| queue_state.seek_offset)
+    }
+
+    /// Moves `message_queue` to `offset`. The process queue holding the messages pulled so far is
+    /// dropped and replaced, so that a pull still in flight can not put messages from before the
+    /// new position back.
+    pub(crate) fn seek(&self, message_queue: &MessageQueue, offset: i64) -> bool {
+        let mut state = self.assigned_message_queue_state.write();
+        let Some(queue_state) = state.get_mut(message_queue) else {
+            return false;
+        };
+        queue_state.process_queue.set_dropped(true);
+        queue_state.process_queue = ProcessQueue::new();
+        queue_state.seek_offset = offset;
+        queue_state.consume_offset = offset;
+        true
+    }
+
+    /// Drops the process queues of all the assigned queues.
+    pub(crate) fn clear(&self) {
+        let mut state = self.assigned_message_queue_state.write();
+        for queue_state in state.values() {
+            queue_state.process_queue.set_dropped(true);
+        }
+        state.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seek_replaces_the_process_queue_and_is_pulled_from_once() {
+        let assigned = AssignedMessageQueue::default();
+        let message_queue = MessageQueue::from_parts("topic", "broker-a", 0);
+        assigned.update_assigned_message_queue(&HashSet::from([message_queue.clone()]));
+        let (process_queue, offset) = assigned.next_pull(&message_queue).unwrap();
+        assert_eq!(offset, -1);
+        assigned.update_pull_offset(&message_queue, 10, &process_queue);
+
+        assert!(assigned.seek(&message_queue, 3));
+        assert!(process_queue.is_dropped());
+        assert_eq!(assigned.seek_offset(&message_queue), 3);
+        assert_eq!(assigned.consume_offset(&message_queue), 3);
+
+        // a pull that was in flight during the seek does not move the position
+        assigned.update_pull_offset(&message_queue, 20, &process_queue);
+        let (new_process_queue, offset) = assigned.next_pull(&message_queue).unwrap();
+        assert_eq!(offset, 3);
+        assert!(!new_process_queue.is_dropped());
+        assert_eq!(assigned.seek_offset(&message_queue), -1);
+    }
+
+    #[test]
+    fn removed_queues_are_dropped() {
+        let assigned = AssignedMessageQueue::default();
+        let queue_0 = MessageQueue::from_parts("topic", "broker-a", 0);
+        let queue_1 = MessageQueue::from_parts("topic", "broker-a", 1);
+        let added = assigned
+            .update_assigned_message_queue(&HashSet::from([queue_0.clone(), queue_1.clone()]));
+        assert_eq!(added.len(), 2);
+        let process_queue = assigned.process_queue(&queue_0).unwrap();
+
+        let added = assigned.update_assigned_message_queue(&HashSet::from([queue_1.clone()]));
+        assert!(added.is_empty());
+        assert!(process_queue.is_dropped());
+        assert!(!assigned.contains(&queue_0));
+        assert!(!assigned.seek(&queue_0, 0));
+    }
+}
@@ -45,6 +45,7 @@ use crate::consumer::listener::consume_concurrently_status::ConsumeConcurrentlyS
 use crate::consumer::listener::consume_return_type::ConsumeReturnType;
 use crate::consumer::listener::message_listener_concurrently::ArcBoxMessageListenerConcurrently;
 use crate::hook::consume_message_context::ConsumeMessageContext;
+use crate::trace::trace_parent::TraceParent;
 
 pub struct ConsumeMessageConcurrentlyService {
     pub(crate) default_mqpush_consumer_impl: Option<ArcMut<DefaultMQPushConsumerImpl>>,
@@ -355,6 +356,8 @@ impl ConsumeRequest {
             );
             return;
         }
+        self.process_queue
+            .set_last_consume_timestamp(get_current_millis());
         let context = ConsumeConcurrentlyContext {
             message_queue: self.message_queue.clone(),
             delay_level_when_next_consume: 0,
@@ -408,11 +411,18 @@ impl ConsumeRequest {
                 .iter()
                 .map(|msg| msg.as_ref())
                 .collect::<Vec<&MessageExt>>();
-            match self.message_listener.consume_message(&vec, &context) {
-                Ok(value) => {
+            let consume_result = TraceParent::consume_scope(
+                vec.first().copied(),
+                default_mqpush_consumer_impl
+                    .client_config
+                    .enable_trace_context_propagation,
+                || self.message_listener.consume_message(&vec, &context).ok(),
+            );
+            match consume_result {
+                Some(value) => {
                     status = Some(value);
                 }
-                Err(_) => {
+                None => {
                     has_exception = true;
                 }
             }
@@ -28,6 +28,7 @@ use rocketmq_common::common::message::MessageConst;
 use rocketmq_common::common::message::MessageTrait;
 use rocketmq_common::common::mix_all;
 use rocketmq_common::MessageAccessor::MessageAccessor;
+use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_remoting::protocol::body::cm_result::CMResult;
 use rocketmq_remoting::protocol::body::consume_message_directly_result::ConsumeMessageDirectlyResult;
 use rocketmq_remoting::protocol::heartbeat::message_model::MessageModel;
@@ -52,6 +53,7 @@ use crate::consumer::message_queue_lock::MessageQueueLock;
 use crate::consumer::mq_consumer_inner::MQConsumerInnerLocal;
 use crate::hook::consume_message_context::ConsumeMessageContext;
 use crate::producer::mq_producer::MQProducer;
+use crate::trace::trace_parent::TraceParent;
 
 static MAX_TIME_CONSUME_CONTINUOUSLY: Lazy<u64> = Lazy::new(|| {
     std::env::var("rocketmq.client.maxTimeConsumeContinuously")
@@ -494,6 +496,8 @@ impl ConsumeRequest {
             );
             return;
         }
+        self.process_queue
+            .set_last_consume_timestamp(get_current_millis());
 
         let mut consume_message_orderly_service_inner = consume_message_orderly_service.clone();
         let lock = consume_message_orderly_service_inner
@@ -613,14 +617,23 @@ impl ConsumeRequest {
                     .map(|msg| msg.as_ref())
                     .collect::<Vec<&MessageExt>>();
 
-                match consume_message_orderly_service_inner
-                    .message_listener
-                    .consume_message(&vec, &mut context)
-                {
-                    Ok(value) => {
+                let consume_result = TraceParent::consume_scope(
+                    vec.first().copied(),
+                    consume_message_orderly_service_inner
+                        .client_config
+                        .enable_trace_context_propagation,
+                    || {
+                        consume_message_orderly_service_inner
+                            .message_listener
+                            .consume_message(&vec, &mut context)
+                            .ok()
+                    },
+                );
+                match consume_result {
+                    Some(value) => {
                         status = Some(value);
                     }
-                    Err(_) => {
+                    None => {
                         has_exception = true;
                     }
                 }
@@ -0,0 +1,705 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use cheetah_string::CheetahString;
+use rocketmq_common::common::base::service_state::ServiceState;
+use rocketmq_common::common::filter::expression_type::ExpressionType;
+use rocketmq_common::common::message::message_ext::MessageExt;
+use rocketmq_common::common::message::message_queue::MessageQueue;
+use rocketmq_common::common::sys_flag::pull_sys_flag::PullSysFlag;
+use rocketmq_common::common::FAQUrl;
+use rocketmq_common::TimeUtils::get_current_millis;
+use rocketmq_remoting::protocol::filter::filter_api::FilterAPI;
+use rocketmq_remoting::protocol::heartbeat::message_model::MessageModel;
+use rocketmq_remoting::protocol::heartbeat::subscription_data::SubscriptionData;
+use rocketmq_remoting::runtime::RPCHook;
+use rocketmq_rust::ArcMut;
+use rocketmq_rust::WeakArcMut;
+use tokio::sync::Mutex;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tracing::info;
+use tracing::warn;
+
+use crate::base::client_config::ClientConfig;
+use crate::consumer::consumer_impl::assigned_message_queue::AssignedMessageQueue;
+use crate::consumer::consumer_impl::process_queue::ProcessQueue;
+use crate::consumer::consumer_impl::pull_api_wrapper::PullAPIWrapper;
+use crate::consumer::consumer_impl::pull_request_ext::PullResultExt;
+use crate::consumer::default_lite_pull_consumer::LitePullConsumerConfig;
+use crate::consumer::pull_callback::PullCallback;
+use crate::consumer::pull_status::PullStatus;
+use crate::consumer::store::local_file_offset_store::LocalFileOffsetStore;
+use crate::consumer::store::offset_store::OffsetStore;
+use crate::consumer::store::read_offset_type::ReadOffsetType;
+use crate::consumer::store::remote_broker_offset_store::RemoteBrokerOffsetStore;
+use crate::factory::mq_client_instance::MQClientInstance;
+use crate::implementation::communication_mode::CommunicationMode;
+use crate::implementation::mq_client_manager::MQClientManager;
+use crate::mq_client_err;
+use crate::Result;
+
+const PULL_TIME_DELAY_MILLS_WHEN_PAUSE: u64 = 1000;
+const PULL_TIME_DELAY_MILLS_WHEN_FLOW_CONTROL: u64 = 50;
+
+/// Messages pulled from a queue, waiting in the cache to be polled.
+struct ConsumeRequest {
+    message_exts: Vec<ArcMut<MessageExt>>,
+    message_queue: MessageQueue,
+    process_queue: ProcessQueue,
+}
+
+/// The callback handed to the pull API, which is only called back by asynchronous pulls. The
+/// lite pull consumer pulls synchronously and gets the result returned instead.
+struct SyncPullCallback;
+
+impl PullCallback for SyncPullCallback {
+    async fn on_success(&mut self, _pull_result: PullResultExt) {}
+
+    fn on_exception(&mut self, _e: Box<dyn std::error::Error + Send>) {}
+}
+
+pub(crate) struct DefaultLitePullConsumerImpl {
+    client_config: ClientConfig,
+    consumer_config: ArcMut<LitePullConsumerConfig>,
+    rpc_hook: Option<Arc<Box<dyn RPCHook>>>,
+    service_state: ServiceState,
+    client_instance: Option<ArcMut<MQClientInstance>>,
+    pull_api_wrapper: Option<ArcMut<PullAPIWrapper>>,
+    offset_store: Option<ArcMut<OffsetStore>>,
+    assigned_message_queue: AssignedMessageQueue,
+    topic_to_sub_expression: parking_lot::RwLock<HashMap<CheetahString, CheetahString>>,
+    task_table: parking_lot::Mutex<HashMap<MessageQueue, JoinHandle<()>>>,
+    // Guards the pulled messages together with the process queues they are put in, a seek takes
+    // it to swap the process queue of a queue and drop the messages of the old one
+    consume_request_cache: Mutex<VecDeque<ConsumeRequest>>,
+    consume_request_notify: Notify,
+    auto_commit: AtomicBool,
+    next_auto_commit_deadline: AtomicU64,
+    default_lite_pull_consumer_impl: Option<WeakArcMut<DefaultLitePullConsumerImpl>>,
+}
+
+impl DefaultLitePullConsumerImpl {
+    pub(crate) fn new(
+        client_config: ClientConfig,
+        consumer_config: ArcMut<LitePullConsumerConfig>,
+        rpc_hook: Option<Arc<Box<dyn RPCHook>>>,
+    ) -> Self {
+        let auto_commit = AtomicBool::new(consumer_config.auto_commit);
+        Self {
+            client_config,
+            consumer_config,
+            rpc_hook,
+            service_state: ServiceState::CreateJust,
+            client_instance: None,
+            pull_api_wrapper: None,
+            offset_store: None,
+            assigned_message_queue: AssignedMessageQueue::default(),
+            topic_to_sub_expression: Default::default(),
+            task_table: Default::default(),
+            consume_request_cache: Mutex::new(VecDeque::new()),
+            consume_request_notify: Notify::new(),
+            auto_commit,
+            next_auto_commit_deadline: AtomicU64::new(0),
+            default_lite_pull_consumer_impl: None,
+        }
+    }
+
+    pub(crate) fn set_default_lite_pull_consumer_impl(
+        &mut self,
+        default_lite_pull_consumer_impl: WeakArcMut<DefaultLitePullConsumerImpl>,
+    ) {
+        self.default_lite_pull_consumer_impl = Some(default_lite_pull_consumer_impl);
+    }
+}
+
+impl DefaultLitePullConsumerImpl {
+    pub(crate) async fn start(&mut self) -> Result<()> {
+        match self.service_state {
+            ServiceState::CreateJust => {
+                self.service_state = ServiceState::StartFailed;
+                if self.consumer_config.consumer_group.is_empty() {
+                    return mq_client_err!("consumerGroup is empty");
+                }
+                if self.consumer_config.message_model == MessageModel::Clustering {
+                    self.client_config.change_instance_name_to_pid();
+                }
+                let mut client_instance = MQClientManager::get_instance()
+                    .get_or_create_mq_client_instance(
+                        self.client_config.clone(),
+                        self.rpc_hook.clone(),
+                    );
+                self.pull_api_wrapper = Some(ArcMut::new(PullAPIWrapper::new(
+                    client_instance.clone(),
+                    self.consumer_config.consumer_group.clone(),
+                    self.consumer_config.unit_mode,
+                )));
+                let offset_store = match self.consumer_config.message_model {
+                    MessageModel::Broadcasting => {
+                        OffsetStore::new_with_local(LocalFileOffsetStore::new(
+                            client_instance.clone(),
+                            self.consumer_config.consumer_group.clone(),
+                        ))
+                    }
+                    MessageModel::Clustering => {
+                        OffsetStore::new_with_remote(RemoteBrokerOffsetStore::new(
+                            client_instance.clone(),
+                            self.consumer_config.consumer_group.clone(),
+                        ))
+                    }
+                };
+                offset_store.load().await?;
+                self.offset_store = Some(ArcMut::new(offset_store));
+                let cloned = client_instance.clone();
+                client_instance.start(cloned).await?;
+                self.client_instance = Some(client_instance);
+                self.next_auto_commit_deadline.store(
+                    get_current_millis() + self.consumer_config.auto_commit_interval_millis,
+                    Ordering::Release,
+                );
+                self.service_state = ServiceState::Running;
+                info!(
+                    "the lite pull consumer [{}] start OK",
+                    self.consumer_config.consumer_group
+                );
+                for message_queue in self.assigned_message_queue.message_queues() {
+                    self.start_pull_task(message_queue);
+                }
+                Ok(())
+            }
+            ServiceState::Running => {
+                mq_client_err!("The LitePullConsumer service state is Running")
+            }
+            ServiceState::ShutdownAlready => {
+                mq_client_err!("The LitePullConsumer service state is ShutdownAlready")
+            }
+            ServiceState::StartFailed => mq_client_err!(format!(
+                "The LitePullConsumer service state not OK, maybe started once,{:?},{}",
+                self.service_state,
+                FAQUrl::suggest_todo(FAQUrl::CLIENT_SERVICE_NOT_OK)
+            )),
+        }
+    }
+
+    pub(crate) async fn shutdown(&mut self) {
+        match self.service_state {
+            ServiceState::CreateJust => {
+                warn!(
+                    "the lite pull consumer [{}] do not start, so do nothing",
+                    self.consumer_config.consumer_group
+                );
+            }
+            ServiceState::Running => {
+                self.service_state = ServiceState::ShutdownAlready;
+                for (_, task) in self.task_table.lock().drain() {
+                    task.abort();
+                }
+                if self.is_auto_commit() {
+                    self.commit_all(true).await;
+                }
+                self.assigned_message_queue.clear();
+                self.consume_request_cache.lock().await.clear();
+                if let Some(client_instance) = self.client_instance.as_mut() {
+                    client_instance.shutdown().await;
+                }
+                info!(
+                    "the lite pull consumer [{}] shutdown OK",
+                    self.consumer_config.consumer_group
+                );
+            }
+            ServiceState::ShutdownAlready | ServiceState::StartFailed => {}
+        }
+    }
+
+    pub(crate) fn is_running(&self) -> bool {
+        self.service_state == ServiceState::Running
+    }
+
+    fn make_sure_state_ok(&self) -> Result<()> {
+        if self.service_state != ServiceState::Running {
+            return mq_client_err!(format!(
+                "The consumer service state not OK, {:?} {}",
+                self.service_state,
+                FAQUrl::suggest_todo(FAQUrl::CLIENT_SERVICE_NOT_OK)
+            ));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn assignment(&self) -> HashSet<MessageQueue> {
+        self.assigned_message_queue.message_queues()
+    }
+
+    pub(crate) async fn assign(&self, message_queues: Vec<MessageQueue>) {
+        if message_queues.is_empty() {
+            warn!("Message queues can not be null or empty.");
+            return;
+        }
+        let message_queues = message_queues.into_iter().collect::<HashSet<_>>();
+        let added = self
+            .assigned_message_queue
+            .update_assigned_message_queue(&message_queues);
+        self.task_table.lock().retain(|message_queue, task| {
+            let keep = message_queues.contains(message_queue);
+            if !keep {
+                task.abort();
+            }
+            keep
+        });
+        self.consume_request_cache
+            .lock()
+            .await
+            .retain(|request| message_queues.contains(&request.message_queue));
+        if self.is_running() {
+            for message_queue in added {
+                self.start_pull_task(message_queue);
+            }
+        }
+    }
+
+    pub(crate) fn set_sub_expression_for_assign(&self, topic: &str, sub_expression: &str) {
+        self.topic_to_sub_expression.write().insert(
+            CheetahString::from_slice(topic),
+            CheetahString::from_slice(sub_expression),
+        );
+    }
+
+    pub(crate) fn pause(&self, message_queues: &[MessageQueue]) {
+        self.assigned_message_queue.pause(message_queues);
+    }
+
+    pub(crate) fn resume(&self, message_queues: &[MessageQueue]) {
+        self.assigned_message_queue.resume(message_queues);
+    }
+
+    pub(crate) fn is_auto_commit(&self) -> bool {
+        self.auto_commit.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn set_auto_commit(&self, auto_commit: bool) {
+        self.auto_commit.store(auto_commit, Ordering::Release);
+    }
+
+    /// Starts pulling `message_queue`, in place of the task that pulled it so far.
+    fn start_pull_task(&self, message_queue: MessageQueue) {
+        let Some(this) = self
+            .default_lite_pull_consumer_impl
+            .as_ref()
+            .and_then(|this| this.upgrade())
+        else {
+            return;
+        };
+        let task = tokio::spawn({
+            let message_queue = message_queue.clone();
+            async move { this.pull_message_queue(message_queue).await }
+        });
+        if let Some(previous) = self.task_table.lock().insert(message_queue, task) {
+            previous.abort();
+        }
+    }
+
+    async fn pull_message_queue(&self, message_queue: MessageQueue) {
+        let pull_time_delay_millis_when_exception =
+            self.client_config.pull_time_delay_millis_when_exception as u64;
+        while self.is_running() {
+            if self.assigned_message_queue.is_paused(&message_queue) {
+                tokio::time::sleep(Duration::from_millis(PULL_TIME_DELAY_MILLS_WHEN_PAUSE)).await;
+                continue;
+            }
+            let Some((process_queue, mut offset)) =
+                self.assigned_message_queue.next_pull(&message_queue)
+            else {
+                break;
+            };
+            if process_queue.msg_count() > self.consumer_config.pull_threshold_for_queue as u64 {
+                tokio::time::sleep(Duration::from_millis(
+                    PULL_TIME_DELAY_MILLS_WHEN_FLOW_CONTROL,
+                ))
+                .await;
+                continue;
+            }
+            if offset < 0 {
+                match self.fetch_consume_offset(&message_queue).await {
+                    Ok(consume_offset) => offset = consume_offset,
+                    Err(e) => {
+                        warn!(
+                            "Failed to fetch consume offset, message queue: {}, {}",
+                            message_queue, e
+                        );
+                        tokio::time::sleep(Duration::from_millis(
+                            pull_time_delay_millis_when_exception,
+                        ))
+                        .await;
+                        continue;
+                    }
+                }
+            }
+            let pull_result = match self.pull(&message_queue, offset).await {
+                Ok(pull_result) => pull_result,
+                Err(e) => {
+                    warn!(
+                        "An error occurred in pull message process, message queue: {}, {}",
+                        message_queue, e
+                    );
+                    tokio::time::sleep(Duration::from_millis(
+                        pull_time_delay_millis_when_exception,
+                    ))
+                    .await;
+                    continue;
+                }
+            };
+            let mut consume_request_cache = self.consume_request_cache.lock().await;
+            // a seek replaced the process queue while pulling, the messages are from before it
+            if process_queue.is_dropped() {
+                continue;
+            }
+            if pull_result.pull_result.pull_status == PullStatus::Found {
+                let message_exts = pull_result.pull_result.msg_found_list.unwrap_or_default();
+                if !message_exts.is_empty() {
+                    process_queue.put_message(message_exts.clone()).await;
+                    consume_request_cache.push_back(ConsumeRequest {
+                        message_exts,
+                        message_queue: message_queue.clone(),
+                        process_queue: process_queue.clone(),
+                    });
+                    self.consume_request_notify.notify_one();
+                }
+            }
+            self.assigned_message_queue.update_pull_offset(
+                &message_queue,
+                pull_result.pull_result.next_begin_offset as i64,
+                &process_queue,
+            );
+        }
+    }
+
+    async fn pull(&self, message_queue: &MessageQueue, offset: i64) -> Result<PullResultExt> {
+        let sub_expression = self
+            .topic_to_sub_expression
+            .read()
+            .get(message_queue.get_topic())
+            .cloned()
+            .unwrap_or_else(|| CheetahString::from_static_str(SubscriptionData::SUB_ALL));
+        let subscription_data =
+            match FilterAPI::build_subscription_data(message_queue.get_topic_cs(), &sub_expression)
+            {
+                Ok(subscription_data) => subscription_data,
+                Err(e) => return mq_client_err!(format!("parse subscription error: {}", e)),
+            };
+        let sys_flag = PullSysFlag::build_sys_flag_with_lite_pull(false, true, true, false, true);
+        let mut pull_api_wrapper = self
+            .pull_api_wrapper
+            .clone()
+            .expect("pull_api_wrapper is None");
+        let pull_result = pull_api_wrapper
+            .pull_kernel_impl(
+                message_queue,
+                subscription_data.sub_string.clone(),
+                CheetahString::from_static_str(ExpressionType::TAG),
+                0,
+                offset,
+                self.consumer_config.pull_batch_size as i32,
+                i32::MAX,
+                sys_flag as i32,
+                0,
+                self.consumer_config.broker_suspend_max_time_millis,
+                self.consumer_config.consumer_timeout_millis_when_suspend,
+                CommunicationMode::Sync,
+                SyncPullCallback,
+            )
+            .await?;
+        let Some(mut pull_result) = pull_result else {
+            return mq_client_err!("The pull result is empty");
+        };
+        pull_api_wrapper.process_pull_result(message_queue, &mut pull_result, &subscription_data);
+        Ok(pull_result)
+    }
+
+    /// Returns the committed offset of `message_queue`, or its max offset when nothing was
+    /// committed yet.
+    async fn fetch_consume_offset(&self, message_queue: &MessageQueue) -> Result<i64> {
+        let offset = self
+            .offset_store
+            .as_ref()
+            .expect("offset_store is None")
+            .read_offset(message_queue, ReadOffsetType::ReadFromStore)
+            .await;
+        match offset {
+            offset if offset >= 0 => Ok(offset),
+            -1 => self.max_offset(message_queue).await,
+            _ => mq_client_err!(format!(
+                "Failed to query consume offset from offset store, message queue: {}",
+                message_queue
+            )),
+        }
+    }
+
+    pub(crate) async fn poll(&self, timeout: u64) -> Vec<MessageExt> {
+        if !self.is_running() {
+            warn!("The consumer not running, poll returns nothing");
+            return Vec::new();
+        }
+        if self.is_auto_commit() {
+            self.maybe_auto_commit().await;
+        }
+        let deadline = Instant::now() + Duration::from_millis(timeout);
+        loop {
+            let notified = self.consume_request_notify.notified();
+            {
+                let mut consume_request_cache = self.consume_request_cache.lock().await;
+                while let Some(request) = consume_request_cache.pop_front() {
+                    if request.process_queue.is_dropped() {
+                        continue;
+                    }
+                    let offset = request
+                        .process_queue
+                        .remove_message(&request.message_exts)
+                        .await;
+                    if offset >= 0 {
+                        self.assigned_message_queue
+                            .update_consume_offset(&request.message_queue, offset);
+                    }
+                    return request
+                        .message_exts
+                        .iter()
+                        .map(|message_ext| message_ext.as_ref().clone())
+                        .collect();
+                }
+            }
+            if tokio::time::timeout_at(deadline, notified).await.is_err() {
+                return Vec::new();
+            }
+        }
+    }
+
+    async fn maybe_auto_commit(&self) {
+        let now = get_current_millis();
+        let deadline = self.next_auto_commit_deadline.load(Ordering::Acquire);
+        if now >= deadline
+            && self
+                .next_auto_commit_deadline
+                .compare_exchange(
+                    deadline,
+                    now + self.consumer_config.auto_commit_interval_millis,
+                    Ordering::AcqRel,
+                    Ordering::Acquire,
+                )
+                .is_ok()
+        {
+            self.commit_all(true).await;
+        }
+    }
+
+    /// Commits the consume offsets of all the assigned queues.
+    pub(crate) async fn commit_all(&self, persist: bool) {
+        let message_queues = self.assigned_message_queue.message_queues();
+        self.commit_with_set(message_queues, persist).await;
+    }
+
+    pub(crate) async fn commit_with_set(
+        &self,
+        message_queues: HashSet<MessageQueue>,
+        persist: bool,
+    ) {
+        let Some(mut offset_store) = self.offset_store.clone() else {
+            return;
+        };
+        for message_queue in &message_queues {
+            let consume_offset = self.assigned_message_queue.consume_offset(message_queue);
+            if consume_offset != -1 {
+                offset_store
+                    .update_offset(message_queue, consume_offset, false)
+                    .await;
+            }
+        }
+        if persist {
+            offset_store.persist_all(&message_queues).await;
+        }
+    }
+
+    pub(crate) async fn commit_with_map(
+        &self,
+        offset_map: HashMap<MessageQueue, i64>,
+        persist: bool,
+    ) {
+        let Some(mut offset_store) = self.offset_store.clone() else {
+            return;
+        };
+        let mut message_queues = HashSet::with_capacity(offset_map.len());
+        for (message_queue, offset) in offset_map {
+            if !self.assigned_message_queue.contains(&message_queue) {
+                warn!(
+                    "The message queue is not in assigned list, may be rebalancing, message \
+                     queue: {}",
+                    message_queue
+                );
+                continue;
+            }
+            offset_store
+                .update_offset(&message_queue, offset, false)
+                .await;
+            self.assigned_message_queue
+                .update_consume_offset(&message_queue, offset);
+            message_queues.insert(message_queue);
+        }
+        if persist {
+            offset_store.persist_all(&message_queues).await;
+        }
+    }
+
+    pub(crate) async fn committed(&self, message_queue: &MessageQueue) -> Result<i64> {
+        self.make_sure_state_ok()?;
+        let offset = self
+            .offset_store
+            .as_ref()
+            .expect("offset_store is None")
+            .read_offset(message_queue, ReadOffsetType::ReadFromStore)
+            .await;
+        if offset == -2 {
+            return mq_client_err!("Fetch consume offset from broker exception");
+        }
+        Ok(offset)
+    }
+
+    /// Moves `message_queue` to `offset`. The messages pulled from the queue but not polled yet
+    /// are dropped, and so are the ones of a pull still in flight, and pulling starts over from
+    /// `offset`.
+    pub(crate) async fn seek(&self, message_queue: &MessageQueue, offset: i64) -> Result<()> {
+        self.make_sure_state_ok()?;
+        if !self.assigned_message_queue.contains(message_queue) {
+            return mq_client_err!(format!(
+                "The message queue is not in assigned list, may be rebalancing, message queue: {}",
+                message_queue
+            ));
+        }
+        let min_offset = self.min_offset(message_queue).await?;
+        let max_offset = self.max_offset(message_queue).await?;
+        if offset < min_offset || offset > max_offset {
+            return mq_client_err!(format!(
+                "Seek offset illegal, seek offset = {}, min offset = {}, max offset = {}",
+                offset, min_offset, max_offset
+            ));
+        }
+        {
+            let mut consume_request_cache = self.consume_request_cache.lock().await;
+            if !self.assigned_message_queue.seek(message_queue, offset) {
+                return mq_client_err!(format!(
+                    "The message queue is not in assigned list, may be rebalancing, message \
+                     queue: {}",
+                    message_queue
+                ));
+            }
+            consume_request_cache.retain(|request| request.message_queue != *message_queue);
+        }
+        // do not wait for a long polling pull of the old position to return
+        self.start_pull_task(message_queue.clone());
+        Ok(())
+    }
+
+    pub(crate) async fn seek_to_begin(&self, message_queue: &MessageQueue) -> Result<()> {
+        self.make_sure_state_ok()?;
+        let begin = self.min_offset(message_queue).await?;
+        self.seek(message_queue, begin).await
+    }
+
+    pub(crate) async fn seek_to_end(&self, message_queue: &MessageQueue) -> Result<()> {
+        self.make_sure_state_ok()?;
+        let end = self.max_offset(message_queue).await?;
+        self.seek(message_queue, end).await
+    }
+
+    pub(crate) async fn seek_to_timestamp(
+        &self,
+        message_queue: &MessageQueue,
+        timestamp: u64,
+    ) -> Result<()> {
+        let offset = self.offset_for_timestamp(message_queue, timestamp).await?;
+        self.seek(message_queue, offset).await
+    }
+
+    pub(crate) async fn offset_for_timestamp(
+        &self,
+        message_queue: &MessageQueue,
+        timestamp: u64,
+    ) -> Result<i64> {
+        self.make_sure_state_ok()?;
+        self.client_instance
+            .as_ref()
+            .expect("client_instance is None")
+            .mq_admin_impl
+            .clone()
+            .search_offset(message_queue, timestamp)
+            .await
+    }
+
+    pub(crate) async fn fetch_message_queues(&self, topic: &str) -> Result<Vec<MessageQueue>> {
+        self.make_sure_state_ok()?;
+        let message_queues = self
+            .client_instance
+            .as_ref()
+            .expect("client_instance is None")
+            .mq_admin_impl
+            .clone()
+            .fetch_subscribe_message_queues(topic)
+            .await?;
+        Ok(message_queues.into_iter().collect())
+    }
+
+    async fn min_offset(&self, message_queue: &MessageQueue) -> Result<i64> {
+        self.client_instance
+            .as_ref()
+            .expect("client_instance is None")
+            .mq_admin_impl
+            .clone()
+            .min_offset(message_queue)
+            .await
+    }
+
+    async fn max_offset(&self, message_queue: &MessageQueue) -> Result<i64> {
+        self.client_instance
+            .as_ref()
+            .expect("client_instance is None")
+            .mq_admin_impl
+            .clone()
+            .max_offset(message_queue)
+            .await
+    }
+
+    pub(crate) async fn update_name_server_address(&mut self, name_server_address: &str) {
+        match self.client_instance.as_ref() {
+            Some(client_instance) => {
+                client_instance
+                    .get_mq_client_api_impl()
+                    .update_name_server_address_list(name_server_address)
+                    .await;
+            }
+            None => {
+                self.client_config.namesrv_addr =
+                    Some(CheetahString::from_slice(name_server_address));
+            }
+        }
+    }
+}
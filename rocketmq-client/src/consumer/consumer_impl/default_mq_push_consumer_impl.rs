@@ -15,6 +15,7 @@
  * limitations under the License.
  */
 
+use std::collections::HashMap;
 use std::collections::HashSet;
 use std::error::Error;
 use std::sync::atomic::AtomicBool;
@@ -40,6 +41,8 @@ use rocketmq_common::MessageAccessor::MessageAccessor;
 use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_remoting::protocol::body::consume_message_directly_result::ConsumeMessageDirectlyResult;
 use rocketmq_remoting::protocol::body::consumer_running_info::ConsumerRunningInfo;
+use rocketmq_remoting::protocol::body::process_queue_info::ProcessQueueInfo;
+use rocketmq_remoting::protocol::body::stuck_queue_info::StuckQueueInfo;
 use rocketmq_remoting::protocol::filter::filter_api::FilterAPI;
 use rocketmq_remoting::protocol::header::ack_message_request_header::AckMessageRequestHeader;
 use rocketmq_remoting::protocol::header::change_invisible_time_request_header::ChangeInvisibleTimeRequestHeader;
@@ -759,6 +762,35 @@ impl DefaultMQPushConsumerImpl {
         }
     }
 
+    /// Logs and returns the process queues without pull or consume progress for more than
+    /// `stuck_queue_alarm_minutes`.
+    pub(crate) async fn detect_stuck_process_queues(
+        &self,
+    ) -> HashMap<MessageQueue, StuckQueueInfo> {
+        let mut stuck_queues = HashMap::new();
+        let alarm_minutes = self.consumer_config.stuck_queue_alarm_minutes;
+        if alarm_minutes == 0 || *self.service_state != ServiceState::Running {
+            return stuck_queues;
+        }
+        let threshold_millis = alarm_minutes * 60 * 1000;
+        let process_queue_table = self
+            .rebalance_impl
+            .rebalance_impl_inner
+            .process_queue_table
+            .read()
+            .await;
+        for (mq, process_queue) in process_queue_table.iter() {
+            if let Some(stuck) = process_queue.detect_stuck(threshold_millis).await {
+                warn!(
+                    "process queue is stuck, group={}, mq={}, {}",
+                    self.consumer_config.consumer_group, mq, stuck
+                );
+                stuck_queues.insert(mq.clone(), stuck);
+            }
+        }
+        stuck_queues
+    }
+
     pub(crate) async fn pop_message(&mut self, pop_request: PopRequest) {
         let process_queue = pop_request.get_pop_process_queue();
         if process_queue.is_dropped() {
@@ -1106,6 +1138,9 @@ impl DefaultMQPushConsumerImpl {
                 begin_timestamp.elapsed().as_millis(),
                 e
             );
+            pull_request
+                .process_queue
+                .set_last_pull_error(e.to_string());
             self.execute_pull_request_later(
                 pull_request,
                 self.pull_time_delay_mills_when_exception,
@@ -1662,7 +1697,57 @@ impl MQConsumerInner for DefaultMQPushConsumerImpl {
         self.consumer_config.unit_mode
     }
 
-    fn consumer_running_info(&self) -> ConsumerRunningInfo {
-        todo!()
+    async fn consumer_running_info(&self) -> ConsumerRunningInfo {
+        let mut info = ConsumerRunningInfo::default();
+        info.properties.insert(
+            ConsumerRunningInfo::PROP_CONSUME_ORDERLY.into(),
+            self.consume_orderly.to_string().into(),
+        );
+        info.properties.insert(
+            ConsumerRunningInfo::PROP_THREADPOOL_CORE_SIZE.into(),
+            self.consumer_config.consume_thread_min.to_string().into(),
+        );
+        info.properties.insert(
+            ConsumerRunningInfo::PROP_CONSUME_TYPE.into(),
+            self.consume_type().get_type_cn().into(),
+        );
+        if let Some(namesrv_addr) = self.client_config.namesrv_addr.as_ref() {
+            info.properties.insert(
+                ConsumerRunningInfo::PROP_NAMESERVER_ADDR.into(),
+                namesrv_addr.clone(),
+            );
+        }
+        info.subscription_set = self
+            .rebalance_impl
+            .rebalance_impl_inner
+            .subscription_inner
+            .read()
+            .await
+            .values()
+            .cloned()
+            .collect();
+
+        let process_queue_table = self
+            .rebalance_impl
+            .rebalance_impl_inner
+            .process_queue_table
+            .read()
+            .await
+            .clone();
+        for (mq, process_queue) in process_queue_table {
+            let mut process_queue_info = ProcessQueueInfo::default();
+            if let Some(offset_store) = self.offset_store.as_ref() {
+                process_queue_info.commit_offset = offset_store
+                    .read_offset(&mq, ReadOffsetType::MemoryFirstThenStore)
+                    .await
+                    .max(0) as u64;
+            }
+            process_queue
+                .fill_process_queue_info(&mut process_queue_info)
+                .await;
+            info.mq_table.insert(mq, process_queue_info);
+        }
+        info.stuck_queue_table = self.detect_stuck_process_queues().await;
+        info
     }
 }
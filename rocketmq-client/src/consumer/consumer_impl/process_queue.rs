@@ -23,12 +23,15 @@ use std::time::Instant;
 
 use cheetah_string::CheetahString;
 use once_cell::sync::Lazy;
+use parking_lot::Mutex;
 use rocketmq_common::common::message::message_ext::MessageExt;
 use rocketmq_common::common::message::MessageConst;
 use rocketmq_common::common::message::MessageTrait;
 use rocketmq_common::MessageAccessor::MessageAccessor;
 use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_remoting::protocol::body::process_queue_info::ProcessQueueInfo;
+use rocketmq_remoting::protocol::body::stuck_queue_info::StuckQueueInfo;
+use rocketmq_remoting::protocol::body::stuck_queue_info::StuckReason;
 use rocketmq_rust::ArcMut;
 use rocketmq_rust::RocketMQTokioRwLock;
 use tokio::sync::RwLock;
@@ -64,6 +67,8 @@ pub(crate) struct ProcessQueue {
     pub(crate) dropped: Arc<AtomicBool>,
     pub(crate) last_pull_timestamp: Arc<AtomicU64>,
     pub(crate) last_consume_timestamp: Arc<AtomicU64>,
+    pub(crate) last_pull_success_timestamp: Arc<AtomicU64>,
+    pub(crate) last_pull_error: Arc<Mutex<Option<CheetahString>>>,
     pub(crate) locked: Arc<AtomicBool>,
     pub(crate) last_lock_timestamp: Arc<AtomicU64>,
     pub(crate) consuming: Arc<AtomicBool>,
@@ -86,6 +91,8 @@ impl ProcessQueue {
             dropped: Arc::new(AtomicBool::new(false)),
             last_pull_timestamp: Arc::new(AtomicU64::new(get_current_millis())),
             last_consume_timestamp: Arc::new(AtomicU64::new(get_current_millis())),
+            last_pull_success_timestamp: Arc::new(AtomicU64::new(get_current_millis())),
+            last_pull_error: Arc::new(Mutex::new(None)),
             locked: Arc::new(AtomicBool::new(false)),
             last_lock_timestamp: Arc::new(AtomicU64::new(get_current_millis())),
             consuming: Arc::new(AtomicBool::new(false)),
@@ -260,13 +267,13 @@ impl ProcessQueue {
                     Ordering::AcqRel,
                 );
             }
-            self.msg_count.fetch_sub(removed_cnt, Ordering::AcqRel);
-            if self.msg_count.load(Ordering::Acquire) == 0 {
-                self.msg_size.store(0, Ordering::Release);
-            }
-            if !msg_tree_map.is_empty() {
-                result = *msg_tree_map.first_key_value().unwrap().0;
-            }
+        }
+        self.msg_count.fetch_sub(removed_cnt, Ordering::AcqRel);
+        if self.msg_count.load(Ordering::Acquire) == 0 {
+            self.msg_size.store(0, Ordering::Release);
+        }
+        if !msg_tree_map.is_empty() {
+            result = *msg_tree_map.first_key_value().unwrap().0;
         }
         result
     }
@@ -346,8 +353,85 @@ impl ProcessQueue {
         drop(lock);
     }
 
-    pub(crate) fn fill_process_queue_info(&self, info: ProcessQueueInfo) {
-        unimplemented!("fill_process_queue_info")
+    pub(crate) async fn fill_process_queue_info(&self, info: &mut ProcessQueueInfo) {
+        {
+            let msg_tree_map = self.msg_tree_map.read().await;
+            if let Some((first, _)) = msg_tree_map.first_key_value() {
+                info.cached_msg_min_offset = *first as u64;
+            }
+            if let Some((last, _)) = msg_tree_map.last_key_value() {
+                info.cached_msg_max_offset = *last as u64;
+            }
+            info.cached_msg_count = msg_tree_map.len() as u32;
+        }
+        info.cached_msg_size_in_mib = (self.msg_size() / (1024 * 1024)) as u32;
+        {
+            let consuming_msg_orderly_tree_map = self.consuming_msg_orderly_tree_map.read().await;
+            if let Some((first, _)) = consuming_msg_orderly_tree_map.first_key_value() {
+                info.transaction_msg_min_offset = *first as u64;
+            }
+            if let Some((last, _)) = consuming_msg_orderly_tree_map.last_key_value() {
+                info.transaction_msg_max_offset = *last as u64;
+            }
+            info.transaction_msg_count = consuming_msg_orderly_tree_map.len() as u32;
+        }
+        info.locked = self.is_locked();
+        info.try_unlock_times = self.try_unlock_times.load(Ordering::Acquire) as u64;
+        info.last_lock_timestamp = self.get_last_lock_timestamp();
+        info.droped = self.is_dropped();
+        info.last_pull_timestamp = self.last_pull_timestamp.load(Ordering::Acquire);
+        info.last_consume_timestamp = self.last_consume_timestamp.load(Ordering::Acquire);
+    }
+
+    /// Returns the diagnostics of the queue when no pull got a response or no cached message was
+    /// consumed for more than `threshold_millis`.
+    pub(crate) async fn detect_stuck(&self, threshold_millis: u64) -> Option<StuckQueueInfo> {
+        if self.is_dropped() {
+            return None;
+        }
+        let now = get_current_millis();
+        let cached_msg_count = self.msg_count();
+        let consume_idle = now.saturating_sub(self.last_consume_timestamp.load(Ordering::Acquire));
+        let pull_idle =
+            now.saturating_sub(self.last_pull_success_timestamp.load(Ordering::Acquire));
+        let (reason, stuck_millis) = if cached_msg_count > 0 && consume_idle > threshold_millis {
+            (StuckReason::Consume, consume_idle)
+        } else if pull_idle > threshold_millis {
+            (StuckReason::Pull, pull_idle)
+        } else {
+            return None;
+        };
+        let (cached_msg_min_offset, cached_msg_max_offset) = {
+            let msg_tree_map = self.msg_tree_map.read().await;
+            (
+                msg_tree_map.first_key_value().map_or(-1, |(k, _)| *k),
+                msg_tree_map.last_key_value().map_or(-1, |(k, _)| *k),
+            )
+        };
+        Some(StuckQueueInfo {
+            reason,
+            stuck_millis,
+            cached_msg_min_offset,
+            cached_msg_max_offset,
+            cached_msg_count,
+            last_pull_error: self.last_pull_error.lock().clone(),
+        })
+    }
+
+    pub(crate) fn set_last_consume_timestamp(&self, last_consume_timestamp: u64) {
+        self.last_consume_timestamp
+            .store(last_consume_timestamp, Ordering::Release);
+    }
+
+    /// Records that a pull of the queue got a response from the broker.
+    pub(crate) fn mark_pull_success(&self) {
+        self.last_pull_success_timestamp
+            .store(get_current_millis(), Ordering::Release);
+        self.last_pull_error.lock().take();
+    }
+
+    pub(crate) fn set_last_pull_error(&self, error: String) {
+        *self.last_pull_error.lock() = Some(CheetahString::from_string(error));
     }
 
     pub(crate) fn set_last_pull_timestamp(&self, last_pull_timestamp: u64) {
@@ -372,3 +456,49 @@ impl ProcessQueue {
         self.locked.load(std::sync::atomic::Ordering::Acquire)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn detects_queue_without_consume_progress() {
+        let process_queue = ProcessQueue::new();
+        assert!(process_queue.detect_stuck(60_000).await.is_none());
+
+        let mut message = MessageExt {
+            queue_offset: 7,
+            ..Default::default()
+        };
+        message.set_body(bytes::Bytes::from_static(b"body"));
+        process_queue.put_message(vec![ArcMut::new(message)]).await;
+        process_queue.set_last_consume_timestamp(get_current_millis() - 120_000);
+        process_queue.set_last_pull_error("broker unreachable".to_string());
+
+        let stuck = process_queue.detect_stuck(60_000).await.unwrap();
+        assert_eq!(stuck.reason, StuckReason::Consume);
+        assert_eq!(stuck.cached_msg_min_offset, 7);
+        assert_eq!(stuck.cached_msg_count, 1);
+        assert_eq!(
+            stuck.last_pull_error.unwrap().as_str(),
+            "broker unreachable"
+        );
+
+        process_queue.set_dropped(true);
+        assert!(process_queue.detect_stuck(60_000).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn detects_queue_without_pull_response() {
+        let process_queue = ProcessQueue::new();
+        process_queue
+            .last_pull_success_timestamp
+            .store(get_current_millis() - 120_000, Ordering::Release);
+        let stuck = process_queue.detect_stuck(60_000).await.unwrap();
+        assert_eq!(stuck.reason, StuckReason::Pull);
+        assert!(stuck.stuck_millis >= 120_000);
+
+        process_queue.mark_pull_success();
+        assert!(process_queue.detect_stuck(60_000).await.is_none());
+    }
+}
@@ -318,6 +318,11 @@ impl PullAPIWrapper {
                 max_msg_bytes: Some(max_size_in_bytes),
                 request_source: None,
                 proxy_forward_client_id: None,
+                accept_compression: self
+                    .client_instance
+                    .client_config
+                    .accept_compressed_pull_body
+                    .then_some(true),
                 expression_type: Some(CheetahString::from_string(expression_type.to_string())),
                 topic_request: Some(TopicRequestHeader {
                     lo: None,
@@ -420,6 +425,11 @@ impl PullAPIWrapper {
                 exp_type: Some(expression_type),
                 exp: Some(expression),
                 order: Some(order),
+                accept_compression: self
+                    .client_instance
+                    .client_config
+                    .accept_compressed_pull_body
+                    .then_some(true),
                 topic_request_header: Some(TopicRequestHeader {
                     lo: None,
                     rpc: Some(RpcRequestHeader {
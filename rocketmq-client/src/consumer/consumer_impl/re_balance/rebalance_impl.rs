@@ -229,11 +229,13 @@ where
 
     async fn truncate_message_queue_not_my_topic(&self) {
         let sub_table = self.subscription_inner.read().await;
+        let mut unsubscribed_topics = HashSet::new();
 
         let mut process_queue_table = self.process_queue_table.write().await;
         process_queue_table.retain(|mq, pq| {
             if !sub_table.contains_key(mq.get_topic()) {
                 pq.set_dropped(true);
+                unsubscribed_topics.insert(mq.get_topic_cs().clone());
                 info!(
                     "doRebalance, {}, truncateMessageQueueNotMyTopic remove unnecessary mq, {}",
                     self.consumer_group.as_ref().unwrap(),
@@ -248,6 +250,7 @@ where
         pop_process_queue_table.retain(|mq, pq| {
             if !sub_table.contains_key(mq.get_topic()) {
                 pq.set_dropped(true);
+                unsubscribed_topics.insert(mq.get_topic_cs().clone());
                 info!(
                     "doRebalance, {}, truncateMessageQueueNotMyTopic remove unnecessary pop mq, {}",
                     self.consumer_group.as_ref().unwrap(),
@@ -263,6 +266,23 @@ where
         topic_client_rebalance.retain(|topic, _| sub_table.contains_key(topic));
         let mut topic_broker_rebalance = self.topic_broker_rebalance.write().await;
         topic_broker_rebalance.retain(|topic, _| sub_table.contains_key(topic));
+        drop(topic_broker_rebalance);
+        drop(topic_client_rebalance);
+        drop(pop_process_queue_table);
+        drop(process_queue_table);
+        drop(sub_table);
+
+        // the queues of an unsubscribed topic are revoked as well
+        if unsubscribed_topics.is_empty() {
+            return;
+        }
+        if let Some(mut sub_rebalance_impl) = self.sub_rebalance_impl.as_ref().unwrap().upgrade() {
+            for topic in unsubscribed_topics {
+                sub_rebalance_impl
+                    .message_queue_changed(&topic, &HashSet::new(), &HashSet::new())
+                    .await;
+            }
+        }
     }
 
     /// Retrieves the rebalance result from the broker for a given topic.
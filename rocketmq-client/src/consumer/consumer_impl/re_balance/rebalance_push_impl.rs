@@ -49,6 +49,7 @@ use crate::consumer::consumer_impl::pull_request::PullRequest;
 use crate::consumer::consumer_impl::re_balance::rebalance_impl::RebalanceImpl;
 use crate::consumer::consumer_impl::re_balance::Rebalance;
 use crate::consumer::default_mq_push_consumer::ConsumerConfig;
+use crate::consumer::message_queue_listener;
 use crate::consumer::store::read_offset_type::ReadOffsetType;
 use crate::factory::mq_client_instance::MQClientInstance;
 use crate::mq_client_err;
@@ -66,6 +67,8 @@ pub struct RebalancePushImpl {
     pub(crate) consumer_config: ArcMut<ConsumerConfig>,
     pub(crate) rebalance_impl_inner: RebalanceImpl<RebalancePushImpl>,
     pub(crate) default_mqpush_consumer_impl: Option<ArcMut<DefaultMQPushConsumerImpl>>,
+    /// Queues last reported to the message queue listener, per topic.
+    assigned_queue_table: HashMap<CheetahString, HashSet<MessageQueue>>,
 }
 
 impl RebalancePushImpl {
@@ -75,11 +78,38 @@ impl RebalancePushImpl {
             consumer_config,
             rebalance_impl_inner: RebalanceImpl::new(None, None, None, None),
             default_mqpush_consumer_impl: None,
+            assigned_queue_table: HashMap::new(),
         }
     }
 }
 
 impl RebalancePushImpl {
+    /// Reports the queues of `topic` now assigned to this consumer to the message queue listener,
+    /// with the ones assigned and revoked since the last report.
+    fn notify_message_queue_listener(
+        &mut self,
+        topic: &str,
+        mq_all: &HashSet<MessageQueue>,
+        mq_divided: &HashSet<MessageQueue>,
+    ) {
+        let previous = if mq_divided.is_empty() {
+            self.assigned_queue_table.remove(topic)
+        } else {
+            self.assigned_queue_table
+                .insert(CheetahString::from_slice(topic), mq_divided.clone())
+        }
+        .unwrap_or_default();
+        if let Some(ref message_queue_listener) = self.consumer_config.message_queue_listener {
+            message_queue_listener::notify_assignment_changed(
+                message_queue_listener.as_ref().as_ref(),
+                topic,
+                mq_all,
+                &previous,
+                mq_divided,
+            );
+        }
+    }
+
     pub fn get_subscription_inner(&self) -> Arc<RwLock<HashMap<CheetahString, SubscriptionData>>> {
         self.rebalance_impl_inner.subscription_inner.clone()
     }
@@ -198,6 +228,7 @@ impl Rebalance for RebalancePushImpl {
                 self.consumer_config.pull_threshold_size_for_topic = new_val;
             }
         }
+        drop(process_queue_table);
 
         //notify broker
         let _ = self
@@ -208,9 +239,7 @@ impl Rebalance for RebalancePushImpl {
             .mut_from_ref()
             .send_heartbeat_to_all_broker_with_lock_v2(true)
             .await;
-        if let Some(ref message_queue_listener) = self.consumer_config.message_queue_listener {
-            message_queue_listener.message_queue_changed(topic, mq_all, mq_divided);
-        }
+        self.notify_message_queue_listener(topic, mq_all, mq_divided);
     }
 
     async fn remove_unnecessary_message_queue(
@@ -518,3 +547,86 @@ impl Rebalance for RebalancePushImpl {
         unimplemented!()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::consumer::message_queue_listener::MessageQueueListener;
+
+    type Events = Arc<Mutex<Vec<(&'static str, Vec<i32>)>>>;
+
+    #[derive(Default)]
+    struct RecordingListener {
+        events: Events,
+    }
+
+    impl RecordingListener {
+        fn record(&self, event: &'static str, queues: &HashSet<MessageQueue>) {
+            let mut ids = queues
+                .iter()
+                .map(|mq| mq.get_queue_id())
+                .collect::<Vec<_>>();
+            ids.sort();
+            self.events.lock().unwrap().push((event, ids));
+        }
+    }
+
+    impl MessageQueueListener for RecordingListener {
+        fn message_queue_changed(
+            &self,
+            _topic: &str,
+            _mq_all: &HashSet<MessageQueue>,
+            mq_assigned: &HashSet<MessageQueue>,
+        ) {
+            self.record("changed", mq_assigned);
+        }
+
+        fn message_queues_assigned(&self, _topic: &str, assigned: &HashSet<MessageQueue>) {
+            self.record("assigned", assigned);
+        }
+
+        fn message_queues_revoked(&self, _topic: &str, revoked: &HashSet<MessageQueue>) {
+            self.record("revoked", revoked);
+        }
+    }
+
+    fn queues(ids: &[i32]) -> HashSet<MessageQueue> {
+        ids.iter()
+            .map(|id| MessageQueue::from_parts("topic", "broker", *id))
+            .collect()
+    }
+
+    #[test]
+    fn listener_is_told_about_assigned_and_revoked_queues() {
+        let listener = RecordingListener::default();
+        let events = listener.events.clone();
+        let consumer_config = ConsumerConfig {
+            message_queue_listener: Some(Arc::new(Box::new(listener))),
+            ..Default::default()
+        };
+        let mut rebalance =
+            RebalancePushImpl::new(ClientConfig::default(), ArcMut::new(consumer_config));
+        let all = queues(&[0, 1, 2, 3]);
+
+        rebalance.notify_message_queue_listener("topic", &all, &queues(&[0, 1]));
+        rebalance.notify_message_queue_listener("topic", &all, &queues(&[1, 2]));
+        // unsubscribed
+        rebalance.notify_message_queue_listener("topic", &HashSet::new(), &HashSet::new());
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                ("assigned", vec![0, 1]),
+                ("changed", vec![0, 1]),
+                ("revoked", vec![0]),
+                ("assigned", vec![2]),
+                ("changed", vec![1, 2]),
+                ("revoked", vec![1, 2]),
+                ("changed", vec![]),
+            ]
+        );
+        assert!(rebalance.assigned_queue_table.is_empty());
+    }
+}
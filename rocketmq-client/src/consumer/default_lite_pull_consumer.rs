@@ -0,0 +1,551 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use cheetah_string::CheetahString;
+use rocketmq_common::common::message::message_ext::MessageExt;
+use rocketmq_common::common::message::message_queue::MessageQueue;
+use rocketmq_remoting::protocol::heartbeat::message_model::MessageModel;
+use rocketmq_remoting::runtime::RPCHook;
+use rocketmq_rust::ArcMut;
+use tracing::warn;
+
+use crate::base::client_config::ClientConfig;
+use crate::consumer::consumer_impl::default_lite_pull_consumer_impl::DefaultLitePullConsumerImpl;
+use crate::consumer::lite_pull_consumer::LitePullConsumer;
+use crate::consumer::message_queue_listener::MessageQueueListener;
+use crate::consumer::message_selector::MessageSelector;
+use crate::consumer::topic_message_queue_change_listener::TopicMessageQueueChangeListener;
+use crate::mq_client_err;
+use crate::Result;
+
+#[derive(Clone)]
+pub struct LitePullConsumerConfig {
+    pub(crate) consumer_group: CheetahString,
+    pub(crate) message_model: MessageModel,
+    pub(crate) unit_mode: bool,
+    pub(crate) auto_commit: bool,
+    pub(crate) auto_commit_interval_millis: u64,
+    pub(crate) pull_batch_size: u32,
+    pub(crate) pull_threshold_for_queue: u32,
+    pub(crate) poll_timeout_millis: u64,
+    pub(crate) broker_suspend_max_time_millis: u64,
+    pub(crate) consumer_timeout_millis_when_suspend: u64,
+}
+
+impl LitePullConsumerConfig {
+    pub fn consumer_group(&self) -> &CheetahString {
+        &self.consumer_group
+    }
+
+    pub fn message_model(&self) -> MessageModel {
+        self.message_model
+    }
+
+    pub fn auto_commit_interval_millis(&self) -> u64 {
+        self.auto_commit_interval_millis
+    }
+
+    pub fn pull_batch_size(&self) -> u32 {
+        self.pull_batch_size
+    }
+
+    pub fn pull_threshold_for_queue(&self) -> u32 {
+        self.pull_threshold_for_queue
+    }
+
+    pub fn poll_timeout_millis(&self) -> u64 {
+        self.poll_timeout_millis
+    }
+
+    pub fn set_consumer_group(&mut self, consumer_group: impl Into<CheetahString>) {
+        self.consumer_group = consumer_group.into();
+    }
+
+    pub fn set_message_model(&mut self, message_model: MessageModel) {
+        self.message_model = message_model;
+    }
+
+    pub fn set_auto_commit(&mut self, auto_commit: bool) {
+        self.auto_commit = auto_commit;
+    }
+
+    pub fn set_auto_commit_interval_millis(&mut self, auto_commit_interval_millis: u64) {
+        self.auto_commit_interval_millis = auto_commit_interval_millis;
+    }
+
+    pub fn set_pull_batch_size(&mut self, pull_batch_size: u32) {
+        self.pull_batch_size = pull_batch_size;
+    }
+
+    pub fn set_pull_threshold_for_queue(&mut self, pull_threshold_for_queue: u32) {
+        self.pull_threshold_for_queue = pull_threshold_for_queue;
+    }
+
+    pub fn set_poll_timeout_millis(&mut self, poll_timeout_millis: u64) {
+        self.poll_timeout_millis = poll_timeout_millis;
+    }
+}
+
+impl Default for LitePullConsumerConfig {
+    fn default() -> Self {
+        LitePullConsumerConfig {
+            consumer_group: CheetahString::new(),
+            message_model: MessageModel::Clustering,
+            unit_mode: false,
+            auto_commit: true,
+            auto_commit_interval_millis: 5 * 1000,
+            pull_batch_size: 10,
+            pull_threshold_for_queue: 1000,
+            poll_timeout_millis: 5 * 1000,
+            broker_suspend_max_time_millis: 20 * 1000,
+            consumer_timeout_millis_when_suspend: 30 * 1000,
+        }
+    }
+}
+
+/// A consumer the application polls messages from, out of the message queues it assigned to it.
+///
+/// Subscribing to topics and having the queues rebalanced among the consumers of the group is
+/// not supported yet, the queues are chosen with [`assign`](LitePullConsumer::assign).
+pub struct DefaultLitePullConsumer {
+    consumer_config: ArcMut<LitePullConsumerConfig>,
+    default_lite_pull_consumer_impl: ArcMut<DefaultLitePullConsumerImpl>,
+}
+
+impl DefaultLitePullConsumer {
+    pub fn new(client_config: ClientConfig, consumer_config: LitePullConsumerConfig) -> Self {
+        Self::new_with_rpc_hook(client_config, consumer_config, None)
+    }
+
+    pub fn new_with_rpc_hook(
+        client_config: ClientConfig,
+        consumer_config: LitePullConsumerConfig,
+        rpc_hook: Option<Arc<Box<dyn RPCHook>>>,
+    ) -> Self {
+        let consumer_config = ArcMut::new(consumer_config);
+        let mut default_lite_pull_consumer_impl = ArcMut::new(DefaultLitePullConsumerImpl::new(
+            client_config,
+            consumer_config.clone(),
+            rpc_hook,
+        ));
+        let wrapper = ArcMut::downgrade(&default_lite_pull_consumer_impl);
+        default_lite_pull_consumer_impl.set_default_lite_pull_consumer_impl(wrapper);
+        DefaultLitePullConsumer {
+            consumer_config,
+            default_lite_pull_consumer_impl,
+        }
+    }
+
+    pub fn consumer_config(&self) -> &LitePullConsumerConfig {
+        &self.consumer_config
+    }
+}
+
+impl LitePullConsumer for DefaultLitePullConsumer {
+    async fn start(&self) -> Result<()> {
+        self.default_lite_pull_consumer_impl
+            .mut_from_ref()
+            .start()
+            .await
+    }
+
+    async fn shutdown(&self) {
+        self.default_lite_pull_consumer_impl
+            .mut_from_ref()
+            .shutdown()
+            .await
+    }
+
+    async fn is_running(&self) -> bool {
+        self.default_lite_pull_consumer_impl.is_running()
+    }
+
+    async fn subscribe(&self, topic: &str) -> Result<()> {
+        self.subscribe_with_selector(topic, None).await
+    }
+
+    async fn subscribe_with_expression(&self, topic: &str, _sub_expression: &str) -> Result<()> {
+        self.subscribe_with_selector(topic, None).await
+    }
+
+    async fn subscribe_with_listener<MQL>(
+        &self,
+        topic: &str,
+        _sub_expression: &str,
+        _listener: MQL,
+    ) -> Result<()>
+    where
+        MQL: MessageQueueListener,
+    {
+        self.subscribe_with_selector(topic, None).await
+    }
+
+    async fn subscribe_with_selector(
+        &self,
+        topic: &str,
+        _selector: Option<MessageSelector>,
+    ) -> Result<()> {
+        mq_client_err!(format!(
+            "Subscribe is not supported by the lite pull consumer yet, assign the message queues \
+             of topic {} instead",
+            topic
+        ))
+    }
+
+    async fn unsubscribe(&self, topic: &str) {
+        warn!(
+            "Subscribe is not supported by the lite pull consumer yet, nothing to unsubscribe \
+             from topic {}",
+            topic
+        );
+    }
+
+    async fn assignment(&self) -> Result<HashSet<MessageQueue>> {
+        Ok(self.default_lite_pull_consumer_impl.assignment())
+    }
+
+    async fn assign(&self, message_queues: Vec<MessageQueue>) {
+        self.default_lite_pull_consumer_impl
+            .assign(message_queues)
+            .await
+    }
+
+    async fn set_sub_expression_for_assign(&self, topic: &str, sub_expression: &str) {
+        self.default_lite_pull_consumer_impl
+            .set_sub_expression_for_assign(topic, sub_expression)
+    }
+
+    async fn poll(&self) -> Vec<MessageExt> {
+        self.poll_with_timeout(self.consumer_config.poll_timeout_millis)
+            .await
+    }
+
+    async fn poll_with_timeout(&self, timeout: u64) -> Vec<MessageExt> {
+        self.default_lite_pull_consumer_impl.poll(timeout).await
+    }
+
+    async fn seek(&self, message_queue: &MessageQueue, offset: i64) -> Result<()> {
+        self.default_lite_pull_consumer_impl
+            .seek(message_queue, offset)
+            .await
+    }
+
+    async fn pause(&self, message_queues: Vec<MessageQueue>) {
+        self.default_lite_pull_consumer_impl.pause(&message_queues)
+    }
+
+    async fn resume(&self, message_queues: Vec<MessageQueue>) {
+        self.default_lite_pull_consumer_impl.resume(&message_queues)
+    }
+
+    async fn is_auto_commit(&self) -> bool {
+        self.default_lite_pull_consumer_impl.is_auto_commit()
+    }
+
+    async fn set_auto_commit(&self, auto_commit: bool) {
+        self.default_lite_pull_consumer_impl
+            .set_auto_commit(auto_commit)
+    }
+
+    async fn fetch_message_queues(&self, topic: &str) -> Result<Vec<MessageQueue>> {
+        self.default_lite_pull_consumer_impl
+            .fetch_message_queues(topic)
+            .await
+    }
+
+    async fn offset_for_timestamp(
+        &self,
+        message_queue: &MessageQueue,
+        timestamp: u64,
+    ) -> Result<i64> {
+        self.default_lite_pull_consumer_impl
+            .offset_for_timestamp(message_queue, timestamp)
+            .await
+    }
+
+    async fn commit_sync(&self) {
+        self.default_lite_pull_consumer_impl.commit_all(true).await
+    }
+
+    async fn commit_sync_with_map(&self, offset_map: HashMap<MessageQueue, i64>, persist: bool) {
+        self.default_lite_pull_consumer_impl
+            .commit_with_map(offset_map, persist)
+            .await
+    }
+
+    async fn commit(&self) {
+        self.default_lite_pull_consumer_impl.commit_all(true).await
+    }
+
+    async fn commit_with_map(&self, offset_map: HashMap<MessageQueue, i64>, persist: bool) {
+        self.default_lite_pull_consumer_impl
+            .commit_with_map(offset_map, persist)
+            .await
+    }
+
+    async fn commit_with_set(&self, message_queues: HashSet<MessageQueue>, persist: bool) {
+        self.default_lite_pull_consumer_impl
+            .commit_with_set(message_queues, persist)
+            .await
+    }
+
+    async fn committed(&self, message_queue: &MessageQueue) -> Result<i64> {
+        self.default_lite_pull_consumer_impl
+            .committed(message_queue)
+            .await
+    }
+
+    async fn register_topic_message_queue_change_listener<TL>(
+        &self,
+        topic: &str,
+        _listener: TL,
+    ) -> Result<()>
+    where
+        TL: TopicMessageQueueChangeListener,
+    {
+        self.subscribe_with_selector(topic, None).await
+    }
+
+    async fn update_name_server_address(&self, name_server_address: &str) {
+        self.default_lite_pull_consumer_impl
+            .mut_from_ref()
+            .update_name_server_address(name_server_address)
+            .await
+    }
+
+    async fn seek_to_begin(&self, message_queue: &MessageQueue) -> Result<()> {
+        self.default_lite_pull_consumer_impl
+            .seek_to_begin(message_queue)
+            .await
+    }
+
+    async fn seek_to_end(&self, message_queue: &MessageQueue) -> Result<()> {
+        self.default_lite_pull_consumer_impl
+            .seek_to_end(message_queue)
+            .await
+    }
+
+    async fn seek_to_timestamp(&self, message_queue: &MessageQueue, timestamp: u64) -> Result<()> {
+        self.default_lite_pull_consumer_impl
+            .seek_to_timestamp(message_queue, timestamp)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bytes::BytesMut;
+    use rocketmq_common::common::message::message_decoder;
+    use rocketmq_common::common::message::message_single::Message;
+    use rocketmq_remoting::code::request_code::RequestCode;
+    use rocketmq_remoting::code::response_code::ResponseCode;
+    use rocketmq_remoting::net::channel::Channel;
+    use rocketmq_remoting::protocol::header::get_max_offset_response_header::GetMaxOffsetResponseHeader;
+    use rocketmq_remoting::protocol::header::get_min_offset_response_header::GetMinOffsetResponseHeader;
+    use rocketmq_remoting::protocol::header::pull_message_request_header::PullMessageRequestHeader;
+    use rocketmq_remoting::protocol::header::pull_message_response_header::PullMessageResponseHeader;
+    use rocketmq_remoting::protocol::header::query_consumer_offset_response_header::QueryConsumerOffsetResponseHeader;
+    use rocketmq_remoting::protocol::header::search_offset_request_header::SearchOffsetRequestHeader;
+    use rocketmq_remoting::protocol::header::search_offset_response_header::SearchOffsetResponseHeader;
+    use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+    use rocketmq_remoting::protocol::route::route_data_view::BrokerData;
+    use rocketmq_remoting::protocol::route::route_data_view::QueueData;
+    use rocketmq_remoting::protocol::route::topic_route_data::TopicRouteData;
+    use rocketmq_remoting::protocol::RemotingSerializable;
+    use rocketmq_remoting::remoting_server::server::run;
+    use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
+    use rocketmq_remoting::runtime::processor::RequestProcessor;
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    const TOPIC: &str = "lite_pull_topic";
+    const BROKER_NAME: &str = "lite_pull_broker";
+    const STORED_MESSAGES: i64 = 10;
+
+    fn store_timestamp(offset: i64) -> i64 {
+        1_000_000 + offset * 1000
+    }
+
+    /// Answers as both the name server and the broker of a topic with a single queue holding
+    /// `STORED_MESSAGES` messages, stored one second apart.
+    #[derive(Clone)]
+    struct FakeBroker {
+        address: CheetahString,
+    }
+
+    impl FakeBroker {
+        fn route(&self) -> TopicRouteData {
+            let mut route = TopicRouteData::new();
+            route
+                .queue_datas
+                .push(QueueData::new(BROKER_NAME.into(), 1, 1, 6, 0));
+            route.broker_datas.push(BrokerData::new(
+                "lite_pull_cluster".into(),
+                BROKER_NAME.into(),
+                HashMap::from([(0, self.address.clone())]),
+                None,
+            ));
+            route
+        }
+
+        async fn pull(request: &RemotingCommand) -> RemotingCommand {
+            let request_header = request
+                .decode_command_custom_header::<PullMessageRequestHeader>()
+                .unwrap();
+            let offset = request_header.queue_offset;
+            if offset >= STORED_MESSAGES {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                return RemotingCommand::create_response_command_with_code(
+                    ResponseCode::PullNotFound,
+                )
+                .set_command_custom_header(PullMessageResponseHeader {
+                    next_begin_offset: offset,
+                    max_offset: STORED_MESSAGES,
+                    ..Default::default()
+                });
+            }
+            let end = STORED_MESSAGES.min(offset + request_header.max_msg_nums as i64);
+            let mut body = BytesMut::new();
+            for queue_offset in offset..end {
+                let message_ext = MessageExt {
+                    message: Message::new(TOPIC, format!("message {}", queue_offset).as_bytes()),
+                    queue_offset,
+                    store_timestamp: store_timestamp(queue_offset),
+                    ..Default::default()
+                };
+                body.extend_from_slice(&message_decoder::encode(&message_ext, false).unwrap());
+            }
+            RemotingCommand::create_response_command()
+                .set_command_custom_header(PullMessageResponseHeader {
+                    next_begin_offset: end,
+                    max_offset: STORED_MESSAGES,
+                    ..Default::default()
+                })
+                .set_body(body.freeze())
+        }
+    }
+
+    impl RequestProcessor for FakeBroker {
+        async fn process_request(
+            &mut self,
+            _channel: Channel,
+            _ctx: ConnectionHandlerContext,
+            request: RemotingCommand,
+        ) -> rocketmq_remoting::Result<Option<RemotingCommand>> {
+            let response = match RequestCode::from(request.code()) {
+                RequestCode::GetRouteinfoByTopic => RemotingCommand::create_response_command()
+                    .set_body(self.route().encode().unwrap()),
+                RequestCode::QueryConsumerOffset => RemotingCommand::create_response_command()
+                    .set_command_custom_header(QueryConsumerOffsetResponseHeader {
+                        offset: Some(0),
+                    }),
+                RequestCode::GetMinOffset => RemotingCommand::create_response_command()
+                    .set_command_custom_header(GetMinOffsetResponseHeader { offset: 0 }),
+                RequestCode::GetMaxOffset => RemotingCommand::create_response_command()
+                    .set_command_custom_header(GetMaxOffsetResponseHeader {
+                        offset: STORED_MESSAGES,
+                    }),
+                RequestCode::SearchOffsetByTimestamp => {
+                    let request_header = request
+                        .decode_command_custom_header::<SearchOffsetRequestHeader>()
+                        .unwrap();
+                    let offset = (0..STORED_MESSAGES)
+                        .find(|offset| store_timestamp(*offset) >= request_header.timestamp)
+                        .unwrap_or(STORED_MESSAGES);
+                    RemotingCommand::create_response_command()
+                        .set_command_custom_header(SearchOffsetResponseHeader { offset })
+                }
+                RequestCode::PullMessage | RequestCode::LitePullMessage => {
+                    Self::pull(&request).await
+                }
+                _ => RemotingCommand::create_response_command(),
+            };
+            Ok(Some(response))
+        }
+    }
+
+    fn queue_offsets(messages: &[MessageExt]) -> Vec<i64> {
+        messages
+            .iter()
+            .map(|message_ext| message_ext.queue_offset)
+            .collect()
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn seek_to_timestamp_drops_the_fetched_messages_and_pulls_from_the_searched_offset() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = CheetahString::from_string(listener.local_addr().unwrap().to_string());
+        let (stop_tx, stop_rx) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(run(
+            listener,
+            stop_rx,
+            FakeBroker {
+                address: address.clone(),
+            },
+            None,
+            vec![],
+        ));
+
+        let client_config = ClientConfig {
+            namesrv_addr: Some(address),
+            instance_name: "lite_pull_seek_to_timestamp".into(),
+            ..Default::default()
+        };
+        let mut consumer_config = LitePullConsumerConfig::default();
+        consumer_config.set_consumer_group("lite_pull_group");
+        consumer_config.set_auto_commit(false);
+        consumer_config.set_pull_batch_size(2);
+        let consumer = DefaultLitePullConsumer::new(client_config, consumer_config);
+        let message_queue = MessageQueue::from_parts(TOPIC, BROKER_NAME, 0);
+        consumer.assign(vec![message_queue.clone()]).await;
+        consumer.start().await.unwrap();
+
+        assert_eq!(
+            queue_offsets(&consumer.poll_with_timeout(5000).await),
+            [0, 1]
+        );
+        // let every message be fetched into the cache
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        consumer
+            .seek_to_timestamp(&message_queue, store_timestamp(7) as u64 - 500)
+            .await
+            .unwrap();
+        assert_eq!(
+            queue_offsets(&consumer.poll_with_timeout(5000).await),
+            [7, 8]
+        );
+        assert_eq!(queue_offsets(&consumer.poll_with_timeout(5000).await), [9]);
+        assert_eq!(
+            consumer
+                .offset_for_timestamp(&message_queue, store_timestamp(3) as u64)
+                .await
+                .unwrap(),
+            3
+        );
+
+        consumer.shutdown().await;
+        let _ = stop_tx.send(());
+        server.await.unwrap();
+    }
+}
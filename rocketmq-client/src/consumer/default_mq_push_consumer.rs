@@ -90,6 +90,9 @@ pub struct ConsumerConfig {
     pub(crate) await_termination_millis_when_shutdown: u64,
     pub(crate) trace_dispatcher: Option<Arc<Box<dyn TraceDispatcher + Send + Sync>>>,
     pub(crate) client_rebalance: bool,
+    /// Minutes without pull or consume progress after which a process queue is reported as
+    /// stuck, `0` disables the detection.
+    pub(crate) stuck_queue_alarm_minutes: u64,
     pub(crate) rpc_hook: Option<Arc<Box<dyn RPCHook>>>,
 }
 
@@ -122,9 +125,9 @@ impl ConsumerConfig {
         &self.message_listener
     }*/
 
-    /*    pub fn message_queue_listener(&self) -> &Option<Arc<Box<dyn MessageQueueListener>>> {
-        &self.message_queue_listener
-    }*/
+    pub fn message_queue_listener(&self) -> Option<&Arc<Box<dyn MessageQueueListener>>> {
+        self.message_queue_listener.as_ref()
+    }
 
     pub fn consume_thread_min(&self) -> u32 {
         self.consume_thread_min
@@ -218,6 +221,10 @@ impl ConsumerConfig {
         self.client_rebalance
     }
 
+    pub fn stuck_queue_alarm_minutes(&self) -> u64 {
+        self.stuck_queue_alarm_minutes
+    }
+
     pub fn rpc_hook(&self) -> &Option<Arc<Box<dyn RPCHook>>> {
         &self.rpc_hook
     }
@@ -374,6 +381,10 @@ impl ConsumerConfig {
         self.client_rebalance = client_rebalance;
     }
 
+    pub fn set_stuck_queue_alarm_minutes(&mut self, stuck_queue_alarm_minutes: u64) {
+        self.stuck_queue_alarm_minutes = stuck_queue_alarm_minutes;
+    }
+
     pub fn set_rpc_hook(&mut self, rpc_hook: Option<Arc<Box<dyn RPCHook>>>) {
         self.rpc_hook = rpc_hook;
     }
@@ -420,6 +431,7 @@ impl Default for ConsumerConfig {
             await_termination_millis_when_shutdown: 0,
             trace_dispatcher: None,
             client_rebalance: true,
+            stuck_queue_alarm_minutes: 10,
             rpc_hook: None,
         }
     }
@@ -69,6 +69,9 @@ pub trait LitePullConsumerLocal: Sync {
 
     /// Subscribes to a topic with a subscription expression and a message queue listener.
     ///
+    /// The listener is told about the queues assigned to and revoked from this consumer after
+    /// every rebalance of the topic.
+    ///
     /// # Arguments
     ///
     /// * `topic` - The name of the topic to subscribe to.
@@ -302,4 +305,20 @@ pub trait LitePullConsumerLocal: Sync {
     ///
     /// * `Result<()>` - An empty result indicating success or failure.
     async fn seek_to_end(&self, message_queue: &MessageQueue) -> Result<()>;
+
+    /// Seeks a message queue to the first message stored at or after a timestamp.
+    ///
+    /// The offset is resolved by the broker's search by timestamp, then applied with
+    /// [`seek`](LitePullConsumerLocal::seek), which drops the messages already fetched for the
+    /// queue so that none from before the new position is returned by a later poll.
+    ///
+    /// # Arguments
+    ///
+    /// * `message_queue` - The message queue to seek.
+    /// * `timestamp` - The timestamp in milliseconds to seek to.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - An empty result indicating success or failure.
+    async fn seek_to_timestamp(&self, message_queue: &MessageQueue, timestamp: u64) -> Result<()>;
 }
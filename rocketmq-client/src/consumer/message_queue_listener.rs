@@ -18,11 +18,72 @@ use std::collections::HashSet;
 
 use rocketmq_common::common::message::message_queue::MessageQueue;
 
+/// Notified by the consumer whenever a rebalance changes the queues it is assigned, so that
+/// applications can set up or release per-queue state.
 pub trait MessageQueueListener: Send + Sync {
+    /// Called with the complete assignment of `topic` after every change.
     fn message_queue_changed(
         &self,
-        topic: &str,
-        mq_all: &HashSet<MessageQueue>,
-        mq_assigned: &HashSet<MessageQueue>,
-    );
+        _topic: &str,
+        _mq_all: &HashSet<MessageQueue>,
+        _mq_assigned: &HashSet<MessageQueue>,
+    ) {
+    }
+
+    /// Called with the queues of `topic` newly assigned to this consumer.
+    fn message_queues_assigned(&self, _topic: &str, _assigned: &HashSet<MessageQueue>) {}
+
+    /// Called with the queues of `topic` taken away from this consumer. Consumption of them has
+    /// already stopped.
+    fn message_queues_revoked(&self, _topic: &str, _revoked: &HashSet<MessageQueue>) {}
+}
+
+/// Splits the change from `previous` to `current` into the assigned and the revoked queues.
+pub(crate) fn diff_assignment(
+    previous: &HashSet<MessageQueue>,
+    current: &HashSet<MessageQueue>,
+) -> (HashSet<MessageQueue>, HashSet<MessageQueue>) {
+    let assigned = current.difference(previous).cloned().collect();
+    let revoked = previous.difference(current).cloned().collect();
+    (assigned, revoked)
+}
+
+/// Reports a changed assignment of `topic` to `listener`.
+pub(crate) fn notify_assignment_changed(
+    listener: &dyn MessageQueueListener,
+    topic: &str,
+    mq_all: &HashSet<MessageQueue>,
+    previous: &HashSet<MessageQueue>,
+    current: &HashSet<MessageQueue>,
+) {
+    let (assigned, revoked) = diff_assignment(previous, current);
+    if !revoked.is_empty() {
+        listener.message_queues_revoked(topic, &revoked);
+    }
+    if !assigned.is_empty() {
+        listener.message_queues_assigned(topic, &assigned);
+    }
+    listener.message_queue_changed(topic, mq_all, current);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queues(ids: &[i32]) -> HashSet<MessageQueue> {
+        ids.iter()
+            .map(|id| MessageQueue::from_parts("topic", "broker", *id))
+            .collect()
+    }
+
+    #[test]
+    fn diff_assignment_splits_added_and_removed_queues() {
+        let (assigned, revoked) = diff_assignment(&queues(&[0, 1, 2]), &queues(&[1, 2, 3]));
+        assert_eq!(assigned, queues(&[3]));
+        assert_eq!(revoked, queues(&[0]));
+
+        let (assigned, revoked) = diff_assignment(&queues(&[0]), &queues(&[0]));
+        assert!(assigned.is_empty());
+        assert!(revoked.is_empty());
+    }
 }
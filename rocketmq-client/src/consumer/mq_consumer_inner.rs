@@ -87,7 +87,7 @@ pub trait MQConsumerInnerLocal: MQConsumerInnerAny + Sync + 'static {
     fn is_unit_mode(&self) -> bool;
 
     /// Returns the running information of the consumer.
-    fn consumer_running_info(&self) -> ConsumerRunningInfo;
+    async fn consumer_running_info(&self) -> ConsumerRunningInfo;
 }
 
 pub trait MQConsumerInnerAny: std::any::Any {
@@ -133,6 +133,12 @@ impl MQConsumerInnerImpl {
             .consume_message_directly(msg, broker_name)
             .await
     }
+
+    pub(crate) async fn detect_stuck_process_queues(&self) {
+        self.default_mqpush_consumer_impl
+            .detect_stuck_process_queues()
+            .await;
+    }
 }
 
 impl MQConsumerInner for MQConsumerInnerImpl {
@@ -205,7 +211,7 @@ impl MQConsumerInner for MQConsumerInnerImpl {
     }
 
     #[inline]
-    fn consumer_running_info(&self) -> ConsumerRunningInfo {
-        MQConsumerInner::consumer_running_info(self.default_mqpush_consumer_impl.as_ref())
+    async fn consumer_running_info(&self) -> ConsumerRunningInfo {
+        MQConsumerInner::consumer_running_info(self.default_mqpush_consumer_impl.as_ref()).await
     }
 }
@@ -0,0 +1,255 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+
+use cheetah_string::CheetahString;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use rocketmq_common::common::message::message_client_id_setter::MessageClientIDSetter;
+use rocketmq_common::common::message::message_ext::MessageExt;
+use rocketmq_common::common::message::message_queue::MessageQueue;
+use rocketmq_common::utils::file_utils;
+use tracing::error;
+use tracing::warn;
+
+static PROCESSED_JOURNAL_DIR: Lazy<PathBuf> = Lazy::new(|| {
+    #[cfg(target_os = "windows")]
+    let home = std::env::var("USERPROFILE")
+        .map_or(PathBuf::from("C:\\tmp\\.rocketmq_journal"), |home| {
+            PathBuf::from(home).join(".rocketmq_journal")
+        });
+
+    #[cfg(not(target_os = "windows"))]
+    let home = std::env::var("HOME").map_or(PathBuf::from("/tmp/.rocketmq_journal"), |home| {
+        PathBuf::from(home).join(".rocketmq_journal")
+    });
+
+    std::env::var("rocketmq.client.processedJournalDir").map_or(home, PathBuf::from)
+});
+
+/// Unique keys of the last processed messages of one queue, oldest first.
+#[derive(Default)]
+struct QueueJournal {
+    keys: VecDeque<CheetahString>,
+    index: HashSet<CheetahString>,
+    dirty: bool,
+}
+
+impl QueueJournal {
+    fn insert(&mut self, key: CheetahString, capacity: usize) -> bool {
+        if self.index.contains(&key) {
+            return false;
+        }
+        while self.keys.len() >= capacity {
+            match self.keys.pop_front() {
+                Some(oldest) => {
+                    self.index.remove(&oldest);
+                }
+                None => break,
+            }
+        }
+        self.index.insert(key.clone());
+        self.keys.push_back(key);
+        self.dirty = true;
+        true
+    }
+}
+
+/// Journal of the messages an at-least-once consumer has processed, to skip redeliveries.
+///
+/// The unique keys of the last `capacity` messages of every queue are kept in a ring buffer and
+/// persisted per queue, so duplicates are also detected after a restart or after a queue comes
+/// back to this consumer. Messages without a unique key are identified by their message id.
+///
+/// ```ignore
+/// if journal.is_processed(context.get_message_queue(), msg) {
+///     continue;
+/// }
+/// handle(msg)?;
+/// journal.mark_processed(context.get_message_queue(), msg);
+/// ```
+pub struct ProcessedMessageJournal {
+    store_dir: PathBuf,
+    capacity: usize,
+    journal_table: Mutex<HashMap<MessageQueue, QueueJournal>>,
+}
+
+impl ProcessedMessageJournal {
+    /// Creates a journal of `group` under `~/.rocketmq_journal`, which can be changed with the
+    /// `rocketmq.client.processedJournalDir` environment variable.
+    pub fn new(group: &str, capacity: usize) -> Self {
+        Self::with_store_dir(PROCESSED_JOURNAL_DIR.join(group), capacity)
+    }
+
+    pub fn with_store_dir(store_dir: impl Into<PathBuf>, capacity: usize) -> Self {
+        Self {
+            store_dir: store_dir.into(),
+            capacity: capacity.max(1),
+            journal_table: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn message_key(msg: &MessageExt) -> CheetahString {
+        MessageClientIDSetter::get_uniq_id(msg).unwrap_or_else(|| msg.msg_id().clone())
+    }
+
+    fn journal_path(&self, mq: &MessageQueue) -> String {
+        self.store_dir
+            .join(mq.get_topic())
+            .join(format!(
+                "{}-{}.json",
+                mq.get_broker_name(),
+                mq.get_queue_id()
+            ))
+            .to_string_lossy()
+            .to_string()
+    }
+
+    fn read_journal(&self, mq: &MessageQueue) -> QueueJournal {
+        let path = self.journal_path(mq);
+        let content = file_utils::file_to_string(&path).unwrap_or_default();
+        let mut journal = QueueJournal::default();
+        if content.is_empty() {
+            return journal;
+        }
+        match serde_json::from_str::<Vec<CheetahString>>(&content) {
+            Ok(keys) => {
+                for key in keys {
+                    journal.insert(key, self.capacity);
+                }
+                journal.dirty = false;
+            }
+            Err(e) => warn!("read processed message journal {} failed: {}", path, e),
+        }
+        journal
+    }
+
+    /// Whether `msg` of `mq` was marked as processed before.
+    pub fn is_processed(&self, mq: &MessageQueue, msg: &MessageExt) -> bool {
+        let key = Self::message_key(msg);
+        let mut journal_table = self.journal_table.lock();
+        journal_table
+            .entry(mq.clone())
+            .or_insert_with(|| self.read_journal(mq))
+            .index
+            .contains(&key)
+    }
+
+    /// Records `msg` of `mq` as processed, returns `false` when it was recorded before.
+    pub fn mark_processed(&self, mq: &MessageQueue, msg: &MessageExt) -> bool {
+        let key = Self::message_key(msg);
+        let mut journal_table = self.journal_table.lock();
+        journal_table
+            .entry(mq.clone())
+            .or_insert_with(|| self.read_journal(mq))
+            .insert(key, self.capacity)
+    }
+
+    /// Writes the journal of `mq` to disk if it changed since the last write.
+    pub fn persist(&self, mq: &MessageQueue) {
+        let mut journal_table = self.journal_table.lock();
+        if let Some(journal) = journal_table.get_mut(mq) {
+            self.persist_journal(mq, journal);
+        }
+    }
+
+    pub fn persist_all(&self) {
+        let mut journal_table = self.journal_table.lock();
+        for (mq, journal) in journal_table.iter_mut() {
+            self.persist_journal(mq, journal);
+        }
+    }
+
+    fn persist_journal(&self, mq: &MessageQueue, journal: &mut QueueJournal) {
+        if !journal.dirty {
+            return;
+        }
+        let path = self.journal_path(mq);
+        let result = serde_json::to_string(&journal.keys)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+            .and_then(|content| file_utils::string_to_file(&content, &path));
+        match result {
+            Ok(_) => journal.dirty = false,
+            Err(e) => error!("persist processed message journal {} failed: {}", path, e),
+        }
+    }
+
+    /// Persists and releases the journal of a queue no longer consumed here, typically when the
+    /// message queue listener reports it as revoked.
+    pub fn remove(&self, mq: &MessageQueue) {
+        let mut journal_table = self.journal_table.lock();
+        if let Some(mut journal) = journal_table.remove(mq) {
+            self.persist_journal(mq, &mut journal);
+        }
+    }
+}
+
+impl Drop for ProcessedMessageJournal {
+    fn drop(&mut self) {
+        self.persist_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(msg_id: &str) -> MessageExt {
+        MessageExt {
+            msg_id: CheetahString::from_slice(msg_id),
+            ..MessageExt::default()
+        }
+    }
+
+    #[test]
+    fn evicts_oldest_keys_beyond_capacity() {
+        let mut journal = QueueJournal::default();
+        assert!(journal.insert("a".into(), 2));
+        assert!(!journal.insert("a".into(), 2));
+        assert!(journal.insert("b".into(), 2));
+        assert!(journal.insert("c".into(), 2));
+        assert!(!journal.index.contains(&CheetahString::from_static_str("a")));
+        assert_eq!(
+            journal
+                .keys
+                .iter()
+                .map(|key| key.as_str())
+                .collect::<Vec<_>>(),
+            ["b", "c"]
+        );
+    }
+
+    #[test]
+    fn detects_duplicates_across_restarts() {
+        let store_dir =
+            std::env::temp_dir().join(format!("processed_message_journal_{}", std::process::id()));
+        let mq = MessageQueue::from_parts("topic", "broker", 0);
+        let journal = ProcessedMessageJournal::with_store_dir(&store_dir, 16);
+        assert!(!journal.is_processed(&mq, &message("id1")));
+        assert!(journal.mark_processed(&mq, &message("id1")));
+        assert!(!journal.mark_processed(&mq, &message("id1")));
+        drop(journal);
+
+        let journal = ProcessedMessageJournal::with_store_dir(&store_dir, 16);
+        assert!(journal.is_processed(&mq, &message("id1")));
+        assert!(!journal.is_processed(&mq, &message("id2")));
+        let _ = std::fs::remove_dir_all(&store_dir);
+    }
+}
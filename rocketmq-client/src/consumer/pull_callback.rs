@@ -60,6 +60,7 @@ impl PullCallback for DefaultPullCallback {
         let message_queue_inner = self.message_queue_inner.take().unwrap();
         let subscription_data = self.subscription_data.take().unwrap();
         let mut pull_request = self.pull_request.take().unwrap();
+        pull_request.process_queue.mark_pull_success();
 
         push_consumer_impl
             .pull_api_wrapper
@@ -177,6 +178,9 @@ impl PullCallback for DefaultPullCallback {
     fn on_exception(&mut self, err: Box<dyn std::error::Error + Send>) {
         let message_queue_inner = self.message_queue_inner.take().unwrap();
         let pull_request = self.pull_request.take().unwrap();
+        pull_request
+            .process_queue
+            .set_last_pull_error(err.to_string());
         let topic = message_queue_inner.get_topic();
         if !topic.starts_with(mix_all::RETRY_GROUP_TOPIC_PREFIX) {
             if let Some(er) = err.downcast_ref::<MQClientError>() {
@@ -17,6 +17,7 @@
 use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::sync::atomic::AtomicBool;
 use std::sync::atomic::AtomicI64;
 use std::sync::Arc;
 use std::thread;
@@ -33,7 +34,9 @@ use rocketmq_common::common::message::message_queue_assignment::MessageQueueAssi
 use rocketmq_common::common::mix_all;
 use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_remoting::base::connection_net_event::ConnectionNetEvent;
+use rocketmq_remoting::code::response_code::RemotingSysResponseCode;
 use rocketmq_remoting::protocol::body::consume_message_directly_result::ConsumeMessageDirectlyResult;
+use rocketmq_remoting::protocol::body::consumer_running_info::ConsumerRunningInfo;
 use rocketmq_remoting::protocol::heartbeat::consumer_data::ConsumerData;
 use rocketmq_remoting::protocol::heartbeat::heartbeat_data::HeartbeatData;
 use rocketmq_remoting::protocol::heartbeat::message_model::MessageModel;
@@ -62,6 +65,7 @@ use crate::implementation::client_remoting_processor::ClientRemotingProcessor;
 use crate::implementation::find_broker_result::FindBrokerResult;
 use crate::implementation::mq_admin_impl::MQAdminImpl;
 use crate::implementation::mq_client_api_impl::MQClientAPIImpl;
+use crate::implementation::mq_client_manager::MQClientManager;
 use crate::mq_client_err;
 use crate::producer::default_mq_producer::DefaultMQProducer;
 use crate::producer::default_mq_producer::ProducerConfig;
@@ -103,6 +107,11 @@ pub struct MQClientInstance {
     >,
     lock_namesrv: Arc<RocketMQTokioMutex<()>>,
     lock_heartbeat: Arc<RocketMQTokioMutex<()>>,
+    /// Set by heartbeat requests arriving while another heartbeat is sent, which sends once more
+    /// instead of one heartbeat per request.
+    heartbeat_pending: Arc<AtomicBool>,
+    /// Set once the name server rejects batched route queries.
+    route_batch_unsupported: Arc<AtomicBool>,
 
     service_state: ServiceState,
     pub(crate) pull_message_service: ArcMut<PullMessageService>,
@@ -207,6 +216,8 @@ impl MQClientInstance {
             topic_end_points_table: Arc::new(Default::default()),
             lock_namesrv: Default::default(),
             lock_heartbeat: Default::default(),
+            heartbeat_pending: Arc::new(AtomicBool::new(false)),
+            route_batch_unsupported: Arc::new(AtomicBool::new(false)),
             service_state: ServiceState::CreateJust,
             pull_message_service: ArcMut::new(PullMessageService::new()),
             rebalance_service: RebalanceService::new(),
@@ -341,7 +352,44 @@ impl MQClientInstance {
         Ok(())
     }
 
-    pub async fn shutdown(&mut self) {}
+    pub async fn shutdown(&mut self) {
+        // The factory is shared, keep it alive while other clients still use it
+        if !self.consumer_table.read().await.is_empty() {
+            return;
+        }
+        if !self.admin_ext_table.read().await.is_empty() {
+            return;
+        }
+        // The inner producer used for sending back messages is always registered
+        if self.producer_table.read().await.len() > 1 {
+            return;
+        }
+        match self.service_state {
+            ServiceState::Running => {
+                Box::pin(
+                    self.default_producer
+                        .default_mqproducer_impl
+                        .as_mut()
+                        .unwrap()
+                        .shutdown_with_factory(false),
+                )
+                .await;
+                self.service_state = ServiceState::ShutdownAlready;
+                self.pull_message_service.shutdown();
+                self.rebalance_service.shutdown();
+                if let Some(mq_client_api_impl) = self.mq_client_api_impl.as_mut() {
+                    mq_client_api_impl.shutdown();
+                }
+                MQClientManager::get_instance()
+                    .remove_client_factory(&self.client_id)
+                    .await;
+                info!("the client factory [{}] shutdown OK", self.client_id);
+            }
+            ServiceState::CreateJust
+            | ServiceState::ShutdownAlready
+            | ServiceState::StartFailed => {}
+        }
+    }
 
     pub async fn register_producer(&mut self, group: &str, producer: MQProducerInnerImpl) -> bool {
         if group.is_empty() {
@@ -413,6 +461,21 @@ impl MQClientInstance {
             }
         });
 
+        // Detect stuck process queues
+        let client_instance = this.clone();
+        self.instance_runtime.get_handle().spawn(async move {
+            info!("ScheduledTask detectStuckProcessQueues started");
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            loop {
+                let current_execution_time = tokio::time::Instant::now();
+                client_instance.detect_all_stuck_process_queues().await;
+                let next_execution_time = current_execution_time + Duration::from_secs(60);
+                let delay =
+                    next_execution_time.saturating_duration_since(tokio::time::Instant::now());
+                tokio::time::sleep(delay).await;
+            }
+        });
+
         // Persist all consumer offset
         let mut client_instance = this;
         let persist_consumer_offset_interval =
@@ -451,12 +514,71 @@ impl MQClientInstance {
             }
         }
 
+        let topic_list = topic_list.into_iter().collect::<Vec<_>>();
+        let batch_size = self.client_config.route_query_batch_size;
+        if batch_size > 1
+            && !self
+                .route_batch_unsupported
+                .load(std::sync::atomic::Ordering::Relaxed)
+        {
+            for topics in topic_list.chunks(batch_size) {
+                self.update_topic_route_info_from_name_server_batch(topics)
+                    .await;
+            }
+            return;
+        }
         for topic in topic_list.iter() {
             self.update_topic_route_info_from_name_server_topic(topic)
                 .await;
         }
     }
 
+    /// Queries the routes of `topics` in one request, topics are queried one by one when the
+    /// name server does not answer the batch.
+    async fn update_topic_route_info_from_name_server_batch(&mut self, topics: &[CheetahString]) {
+        let lock_namesrv = self.lock_namesrv.clone();
+        let lock = lock_namesrv.lock().await;
+        let result = self
+            .mq_client_api_impl
+            .as_mut()
+            .unwrap()
+            .get_topic_route_info_list_from_name_server(
+                topics,
+                self.client_config.mq_client_api_timeout,
+            )
+            .await;
+        match result {
+            Ok(mut topic_route_data_table) => {
+                for topic in topics {
+                    let topic_route_data = topic_route_data_table.remove(topic);
+                    self.apply_topic_route_data(topic, topic_route_data).await;
+                }
+                drop(lock);
+            }
+            Err(e) => {
+                drop(lock);
+                if let MQClientErr(ref err) = e {
+                    if err.response_code()
+                        == RemotingSysResponseCode::RequestCodeNotSupported as i32
+                    {
+                        self.route_batch_unsupported
+                            .store(true, std::sync::atomic::Ordering::Relaxed);
+                    }
+                }
+                warn!(
+                    "get route info of {} topics from name server failed, query them one by one, \
+                     err: {}",
+                    topics.len(),
+                    e
+                );
+                for topic in topics {
+                    self.update_topic_route_info_from_name_server_topic(topic)
+                        .await;
+                }
+            }
+        }
+    }
+
     #[inline]
     pub async fn update_topic_route_info_from_name_server_topic(
         &mut self,
@@ -523,7 +645,8 @@ impl MQClientInstance {
         is_default: bool,
         producer_config: Option<&Arc<ProducerConfig>>,
     ) -> bool {
-        let lock = self.lock_namesrv.lock().await;
+        let lock_namesrv = self.lock_namesrv.clone();
+        let lock = lock_namesrv.lock().await;
         let topic_route_data = if is_default && producer_config.is_some() {
             let mut result = self
                 .mq_client_api_impl
@@ -556,6 +679,18 @@ impl MQClientInstance {
                 .await
                 .unwrap_or(None)
         };
+        let changed = self.apply_topic_route_data(topic, topic_route_data).await;
+        drop(lock);
+        changed
+    }
+
+    /// Saves the route of `topic` and updates the publish and subscribe info of the producers and
+    /// consumers if it changed.
+    async fn apply_topic_route_data(
+        &mut self,
+        topic: &CheetahString,
+        topic_route_data: Option<TopicRouteData>,
+    ) -> bool {
         if let Some(mut topic_route_data) = topic_route_data {
             let mut topic_route_table = self.topic_route_table.write().await;
             let old = topic_route_table.get(topic);
@@ -629,7 +764,6 @@ impl MQClientInstance {
             );
         }
 
-        drop(lock);
         false
     }
 
@@ -663,6 +797,13 @@ impl MQClientInstance {
         }
     }
 
+    pub async fn detect_all_stuck_process_queues(&self) {
+        let consumer_table = self.consumer_table.read().await;
+        for value in consumer_table.values() {
+            value.detect_stuck_process_queues().await;
+        }
+    }
+
     pub async fn clean_offline_broker(&mut self) {
         let lock = self
             .lock_namesrv
@@ -697,16 +838,37 @@ impl MQClientInstance {
             }
         }
     }
+
+    /// Sends a heartbeat to all brokers. Requests arriving while a heartbeat is sent are merged
+    /// into one more heartbeat sent by the holder of the lock.
     pub async fn send_heartbeat_to_all_broker_with_lock(&mut self) -> bool {
+        self.heartbeat_pending
+            .store(true, std::sync::atomic::Ordering::Release);
         if let Some(lock) = self.lock_heartbeat.try_lock().await {
-            if self.client_config.use_heartbeat_v2 {
-                self.send_heartbeat_to_all_broker_v2(false).await
-            } else {
-                self.send_heartbeat_to_all_broker().await
+            let mut result;
+            loop {
+                self.heartbeat_pending
+                    .store(false, std::sync::atomic::Ordering::Release);
+                result = if self.client_config.use_heartbeat_v2 {
+                    self.send_heartbeat_to_all_broker_v2(false).await
+                } else {
+                    self.send_heartbeat_to_all_broker().await
+                };
+                if !self
+                    .heartbeat_pending
+                    .load(std::sync::atomic::Ordering::Acquire)
+                {
+                    break;
+                }
             }
+            drop(lock);
+            result
         } else {
-            warn!("lock heartBeat, but failed. [{}]", self.client_id);
-            false
+            info!(
+                "heartbeat is being sent, merge into it. [{}]",
+                self.client_id
+            );
+            true
         }
     }
 
@@ -1226,6 +1388,15 @@ impl MQClientInstance {
 
         None
     }
+
+    pub async fn consumer_running_info(
+        &self,
+        consumer_group: &CheetahString,
+    ) -> Option<ConsumerRunningInfo> {
+        let consumer_table = self.consumer_table.read().await;
+        let consumer = consumer_table.get(consumer_group)?;
+        Some(consumer.consumer_running_info().await)
+    }
 }
 
 pub fn topic_route_data2topic_publish_info(
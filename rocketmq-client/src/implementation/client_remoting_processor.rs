@@ -31,6 +31,7 @@ use rocketmq_remoting::code::response_code::ResponseCode;
 use rocketmq_remoting::net::channel::Channel;
 use rocketmq_remoting::protocol::header::check_transaction_state_request_header::CheckTransactionStateRequestHeader;
 use rocketmq_remoting::protocol::header::consume_message_directly_result_request_header::ConsumeMessageDirectlyResultRequestHeader;
+use rocketmq_remoting::protocol::header::get_consumer_running_info_request_header::GetConsumerRunningInfoRequestHeader;
 use rocketmq_remoting::protocol::header::notify_consumer_ids_changed_request_header::NotifyConsumerIdsChangedRequestHeader;
 use rocketmq_remoting::protocol::header::reply_message_request_header::ReplyMessageRequestHeader;
 use rocketmq_remoting::protocol::namespace_util::NamespaceUtil;
@@ -79,7 +80,7 @@ impl RequestProcessor for ClientRemotingProcessor {
                 unimplemented!("GetConsumerStatusFromClient")
             }
             RequestCode::GetConsumerRunningInfo => {
-                unimplemented!("GetConsumerRunningInfo")
+                self.get_consumer_running_info(channel, ctx, request).await
             }
             RequestCode::ConsumeMessageDirectly => {
                 self.consume_message_directly(channel, ctx, request).await
@@ -313,4 +314,34 @@ impl ClientRemotingProcessor {
             ))
         }
     }
+
+    async fn get_consumer_running_info(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        request: RemotingCommand,
+    ) -> Result<Option<RemotingCommand>> {
+        let request_header =
+            request.decode_command_custom_header::<GetConsumerRunningInfoRequestHeader>()?;
+        let consumer_running_info = self
+            .client_instance
+            .consumer_running_info(&request_header.consumer_group)
+            .await;
+        if let Some(consumer_running_info) = consumer_running_info {
+            let body = consumer_running_info
+                .encode()
+                .map_err(|_| RemotingCommandError("encode result failed".to_string()))?;
+            Ok(Some(
+                RemotingCommand::create_response_command().set_body(body),
+            ))
+        } else {
+            Ok(Some(
+                RemotingCommand::create_response_command_with_code(ResponseCode::SystemError)
+                    .set_remark(format!(
+                        "The Consumer Group <{}> not exist in this consumer",
+                        request_header.consumer_group
+                    )),
+            ))
+        }
+    }
 }
@@ -14,6 +14,10 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::collections::HashSet;
+
+use cheetah_string::CheetahString;
+use rocketmq_common::common::boundary_type::BoundaryType;
 use rocketmq_common::common::message::message_queue::MessageQueue;
 use rocketmq_remoting::protocol::namespace_util::NamespaceUtil;
 use rocketmq_rust::ArcMut;
@@ -93,7 +97,88 @@ impl MQAdminImpl {
         ))
     }
 
+    pub async fn fetch_subscribe_message_queues(
+        &mut self,
+        topic: &str,
+    ) -> Result<HashSet<MessageQueue>> {
+        let topic_route_data = self
+            .client
+            .as_mut()
+            .expect("client is None")
+            .mq_client_api_impl
+            .as_mut()
+            .expect("mq_client_api_impl is None")
+            .get_topic_route_info_from_name_server_detail(topic, self.timeout_millis, true)
+            .await?;
+        if let Some(topic_route_data) = topic_route_data {
+            let message_queues =
+                mq_client_instance::topic_route_data2topic_subscribe_info(topic, &topic_route_data);
+            if !message_queues.is_empty() {
+                return Ok(message_queues);
+            }
+        }
+        mq_client_err!(format!(
+            "Can not find Message Queue for this topic, {}",
+            topic
+        ))
+    }
+
     pub async fn max_offset(&mut self, mq: &MessageQueue) -> Result<i64> {
+        let broker_addr = self.find_broker_addr(mq).await?;
+        self.client
+            .as_mut()
+            .expect("client is None")
+            .mq_client_api_impl
+            .as_mut()
+            .expect("mq_client_api_impl is None")
+            .get_max_offset(&broker_addr, mq, self.timeout_millis)
+            .await
+    }
+
+    pub async fn min_offset(&mut self, mq: &MessageQueue) -> Result<i64> {
+        let broker_addr = self.find_broker_addr(mq).await?;
+        self.client
+            .as_mut()
+            .expect("client is None")
+            .mq_client_api_impl
+            .as_mut()
+            .expect("mq_client_api_impl is None")
+            .get_min_offset(&broker_addr, mq, self.timeout_millis)
+            .await
+    }
+
+    /// Returns the offset of the first message of `mq` stored at or after `timestamp`.
+    pub async fn search_offset(&mut self, mq: &MessageQueue, timestamp: u64) -> Result<i64> {
+        self.search_offset_with_boundary(mq, timestamp, BoundaryType::Lower)
+            .await
+    }
+
+    pub async fn search_offset_with_boundary(
+        &mut self,
+        mq: &MessageQueue,
+        timestamp: u64,
+        boundary_type: BoundaryType,
+    ) -> Result<i64> {
+        let broker_addr = self.find_broker_addr(mq).await?;
+        self.client
+            .as_mut()
+            .expect("client is None")
+            .mq_client_api_impl
+            .as_mut()
+            .expect("mq_client_api_impl is None")
+            .search_offset(
+                &broker_addr,
+                mq,
+                timestamp,
+                boundary_type,
+                self.timeout_millis,
+            )
+            .await
+    }
+
+    /// Finds the master of the broker holding `mq`, refreshing the route of its topic once when
+    /// the broker is unknown.
+    async fn find_broker_addr(&mut self, mq: &MessageQueue) -> Result<CheetahString> {
         let client = self.client.as_mut().expect("client is None");
         let broker_name = client.get_broker_name_from_message_queue(mq).await;
         let mut broker_addr = client
@@ -108,19 +193,9 @@ impl MQAdminImpl {
                 .find_broker_address_in_publish(broker_name.as_ref())
                 .await;
         }
-        if let Some(ref broker_addr) = broker_addr {
-            let offset = client
-                .mq_client_api_impl
-                .as_mut()
-                .expect("mq_client_api_impl is None")
-                .get_max_offset(broker_addr, mq, self.timeout_millis)
-                .await?;
-            return Ok(offset);
+        match broker_addr {
+            Some(broker_addr) => Ok(broker_addr),
+            None => mq_client_err!(format!("The broker[{}] not exist", mq.get_broker_name())),
         }
-
-        unimplemented!("max_offset")
-    }
-    pub async fn search_offset(&mut self, mq: &MessageQueue, timestamp: u64) -> Result<i64> {
-        unimplemented!("max_offset")
     }
 }
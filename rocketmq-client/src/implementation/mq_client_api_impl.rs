@@ -20,8 +20,11 @@ use std::sync::atomic::AtomicU32;
 use std::sync::Arc;
 use std::time::Instant;
 
+use bytes::Bytes;
 use cheetah_string::CheetahString;
 use lazy_static::lazy_static;
+use rocketmq_common::common::boundary_type::BoundaryType;
+use rocketmq_common::common::compression::compression_type::CompressionType;
 use rocketmq_common::common::message::message_batch::MessageBatch;
 use rocketmq_common::common::message::message_client_id_setter::MessageClientIDSetter;
 use rocketmq_common::common::message::message_enum::MessageRequestMode;
@@ -42,6 +45,7 @@ use rocketmq_remoting::clients::rocketmq_default_impl::RocketmqDefaultClient;
 use rocketmq_remoting::clients::RemotingClient;
 use rocketmq_remoting::code::request_code::RequestCode;
 use rocketmq_remoting::code::response_code::ResponseCode;
+use rocketmq_remoting::protocol::admin::topic_stats_table::TopicStatsTable;
 use rocketmq_remoting::protocol::body::batch_ack_message_request_body::BatchAckMessageRequestBody;
 use rocketmq_remoting::protocol::body::check_client_request_body::CheckClientRequestBody;
 use rocketmq_remoting::protocol::body::get_consumer_listby_group_response_body::GetConsumerListByGroupResponseBody;
@@ -50,17 +54,23 @@ use rocketmq_remoting::protocol::body::query_assignment_response_body::QueryAssi
 use rocketmq_remoting::protocol::body::request::lock_batch_request_body::LockBatchRequestBody;
 use rocketmq_remoting::protocol::body::response::lock_batch_response_body::LockBatchResponseBody;
 use rocketmq_remoting::protocol::body::set_message_request_mode_request_body::SetMessageRequestModeRequestBody;
+use rocketmq_remoting::protocol::body::topic::topic_list::TopicList;
+use rocketmq_remoting::protocol::body::topic::topic_route_data_batch::TopicRouteDataBatch;
 use rocketmq_remoting::protocol::body::unlock_batch_request_body::UnlockBatchRequestBody;
 use rocketmq_remoting::protocol::header::ack_message_request_header::AckMessageRequestHeader;
 use rocketmq_remoting::protocol::header::change_invisible_time_request_header::ChangeInvisibleTimeRequestHeader;
 use rocketmq_remoting::protocol::header::change_invisible_time_response_header::ChangeInvisibleTimeResponseHeader;
 use rocketmq_remoting::protocol::header::client_request_header::GetRouteInfoRequestHeader;
+use rocketmq_remoting::protocol::header::clone_group_offset_request_header::CloneGroupOffsetRequestHeader;
 use rocketmq_remoting::protocol::header::consumer_send_msg_back_request_header::ConsumerSendMsgBackRequestHeader;
 use rocketmq_remoting::protocol::header::end_transaction_request_header::EndTransactionRequestHeader;
 use rocketmq_remoting::protocol::header::extra_info_util::ExtraInfoUtil;
 use rocketmq_remoting::protocol::header::get_consumer_listby_group_request_header::GetConsumerListByGroupRequestHeader;
 use rocketmq_remoting::protocol::header::get_max_offset_request_header::GetMaxOffsetRequestHeader;
 use rocketmq_remoting::protocol::header::get_max_offset_response_header::GetMaxOffsetResponseHeader;
+use rocketmq_remoting::protocol::header::get_min_offset_request_header::GetMinOffsetRequestHeader;
+use rocketmq_remoting::protocol::header::get_min_offset_response_header::GetMinOffsetResponseHeader;
+use rocketmq_remoting::protocol::header::get_topic_stats_info_request_header::GetTopicStatsInfoRequestHeader;
 use rocketmq_remoting::protocol::header::heartbeat_request_header::HeartbeatRequestHeader;
 use rocketmq_remoting::protocol::header::lock_batch_mq_request_header::LockBatchMqRequestHeader;
 use rocketmq_remoting::protocol::header::message_operation_header::send_message_request_header::SendMessageRequestHeader;
@@ -72,6 +82,8 @@ use rocketmq_remoting::protocol::header::pull_message_request_header::PullMessag
 use rocketmq_remoting::protocol::header::pull_message_response_header::PullMessageResponseHeader;
 use rocketmq_remoting::protocol::header::query_consumer_offset_request_header::QueryConsumerOffsetRequestHeader;
 use rocketmq_remoting::protocol::header::query_consumer_offset_response_header::QueryConsumerOffsetResponseHeader;
+use rocketmq_remoting::protocol::header::search_offset_request_header::SearchOffsetRequestHeader;
+use rocketmq_remoting::protocol::header::search_offset_response_header::SearchOffsetResponseHeader;
 use rocketmq_remoting::protocol::header::unlock_batch_mq_request_header::UnlockBatchMqRequestHeader;
 use rocketmq_remoting::protocol::header::unregister_client_request_header::UnregisterClientRequestHeader;
 use rocketmq_remoting::protocol::header::update_consumer_offset_header::UpdateConsumerOffsetRequestHeader;
@@ -172,6 +184,10 @@ impl MQClientAPIImpl {
         self.remoting_client.start(client).await;
     }
 
+    pub fn shutdown(&mut self) {
+        self.remoting_client.shutdown();
+    }
+
     pub async fn fetch_name_server_addr(&mut self) -> Option<String> {
         let addrs = self.top_addressing.fetch_ns_addr();
         if addrs.is_some() && !addrs.as_ref().unwrap().is_empty() {
@@ -285,6 +301,37 @@ impl MQClientAPIImpl {
         }
     }
 
+    /// Routes of `topics` in one request, topics without route are missing from the result.
+    pub async fn get_topic_route_info_list_from_name_server(
+        &self,
+        topics: &[CheetahString],
+        timeout_millis: u64,
+    ) -> Result<HashMap<CheetahString, TopicRouteData>> {
+        let body = TopicList {
+            topic_list: topics.to_vec(),
+            broker_addr: None,
+        };
+        let request =
+            RemotingCommand::create_remoting_command(RequestCode::GetRouteinfoByTopicList)
+                .set_body(body.encode().expect("encode TopicList failed"));
+        let response = self
+            .remoting_client
+            .invoke_async(None, request, timeout_millis)
+            .await?;
+        if ResponseCode::from(response.code()) != ResponseCode::Success {
+            return mq_client_err!(
+                response.code(),
+                response.remark().cloned().unwrap_or_default().to_string()
+            );
+        }
+        match response.body() {
+            Some(body) => Ok(TopicRouteDataBatch::decode(body)
+                .map(|batch| batch.topic_route_data_table)
+                .unwrap_or_default()),
+            None => Ok(HashMap::new()),
+        }
+    }
+
     pub fn get_name_server_address_list(&self) -> &[CheetahString] {
         self.remoting_client.get_name_server_address_list()
     }
@@ -587,18 +634,17 @@ impl MQClientAPIImpl {
             uniq_msg_id = Some(CheetahString::from_string(sb));
         }
 
-        let region_id = response
-            .ext_fields()
-            .unwrap()
-            .get(MessageConst::PROPERTY_MSG_REGION)
+        let ext_fields = response.ext_fields();
+        let region_id = ext_fields
+            .and_then(|ext_fields| ext_fields.get(MessageConst::PROPERTY_MSG_REGION))
+            .filter(|region_id| !region_id.is_empty())
             .map_or(mix_all::DEFAULT_TRACE_REGION_ID.to_string(), |s| {
                 s.to_string()
             });
-        let trace_on = response
-            .ext_fields()
-            .unwrap()
-            .get(MessageConst::PROPERTY_TRACE_SWITCH)
-            .is_some_and(|s| s.parse().unwrap_or(false));
+        // trace stays on unless the broker switches it off
+        let trace_on = ext_fields
+            .and_then(|ext_fields| ext_fields.get(MessageConst::PROPERTY_TRACE_SWITCH))
+            .map_or(true, |trace_switch| trace_switch.as_str() != "false");
         let send_result = SendResult {
             send_status,
             msg_id: uniq_msg_id,
@@ -608,6 +654,8 @@ impl MQClientAPIImpl {
             transaction_id: response_header.transaction_id().map(|s| s.to_string()),
             region_id: Some(region_id),
             trace_on,
+            store_rt: response_header.store_rt(),
+            page_cache_rt: response_header.page_cache_rt(),
             ..Default::default()
         };
 
@@ -1006,7 +1054,10 @@ impl MQClientAPIImpl {
                 msg_found_list: Some(vec![]),
             },
             suggest_which_broker_id: response_header.suggest_which_broker_id,
-            message_binary: response.take_body(),
+            message_binary: decompress_response_body(
+                response.take_body(),
+                response_header.body_compression_type,
+            ),
             offset_delta: response_header.offset_delta,
         };
         Ok(pull_result)
@@ -1208,6 +1259,60 @@ impl MQClientAPIImpl {
         Ok(())
     }
 
+    /// Asks the broker at `addr` for the offset of `message_queue` at `timestamp`.
+    pub async fn search_offset(
+        &mut self,
+        addr: &str,
+        message_queue: &MessageQueue,
+        timestamp: u64,
+        boundary_type: BoundaryType,
+        timeout_millis: u64,
+    ) -> Result<i64> {
+        let request_header = SearchOffsetRequestHeader {
+            topic: CheetahString::from_slice(message_queue.get_topic()),
+            queue_id: message_queue.get_queue_id(),
+            timestamp: timestamp as i64,
+            boundary_type: Some(CheetahString::from_string(
+                boundary_type.get_name().to_uppercase(),
+            )),
+            topic_request_header: Some(TopicRequestHeader {
+                rpc_request_header: Some(RpcRequestHeader {
+                    broker_name: Some(CheetahString::from_slice(message_queue.get_broker_name())),
+                    ..Default::default()
+                }),
+                lo: None,
+            }),
+        };
+
+        let request = RemotingCommand::create_request_command(
+            RequestCode::SearchOffsetByTimestamp,
+            request_header,
+        );
+
+        let response = self
+            .remoting_client
+            .invoke_async(
+                Some(&mix_all::broker_vip_channel(
+                    self.client_config.vip_channel_enabled,
+                    addr,
+                )),
+                request,
+                timeout_millis,
+            )
+            .await?;
+        if ResponseCode::from(response.code()) == ResponseCode::Success {
+            let response_header = response
+                .decode_command_custom_header::<SearchOffsetResponseHeader>()
+                .expect("decode error");
+            return Ok(response_header.offset);
+        }
+        client_broker_err!(
+            response.code(),
+            response.remark().map_or("".to_string(), |s| s.to_string()),
+            addr.to_string()
+        )
+    }
+
     pub async fn get_max_offset(
         &mut self,
         addr: &str,
@@ -1254,6 +1359,128 @@ impl MQClientAPIImpl {
         )
     }
 
+    pub async fn get_min_offset(
+        &mut self,
+        addr: &str,
+        message_queue: &MessageQueue,
+        timeout_millis: u64,
+    ) -> Result<i64> {
+        let request_header = GetMinOffsetRequestHeader {
+            topic: CheetahString::from_slice(message_queue.get_topic()),
+            queue_id: message_queue.get_queue_id(),
+            topic_request_header: Some(TopicRequestHeader {
+                rpc_request_header: Some(RpcRequestHeader {
+                    broker_name: Some(CheetahString::from_slice(message_queue.get_broker_name())),
+                    ..Default::default()
+                }),
+                lo: None,
+            }),
+        };
+
+        let request =
+            RemotingCommand::create_request_command(RequestCode::GetMinOffset, request_header);
+
+        let response = self
+            .remoting_client
+            .invoke_async(
+                Some(&mix_all::broker_vip_channel(
+                    self.client_config.vip_channel_enabled,
+                    addr,
+                )),
+                request,
+                timeout_millis,
+            )
+            .await?;
+        if ResponseCode::from(response.code()) == ResponseCode::Success {
+            let response_header = response
+                .decode_command_custom_header::<GetMinOffsetResponseHeader>()
+                .expect("decode error");
+            return Ok(response_header.offset);
+        }
+        client_broker_err!(
+            response.code(),
+            response.remark().map_or("".to_string(), |s| s.to_string()),
+            addr.to_string()
+        )
+    }
+
+    pub async fn clone_group_offset(
+        &mut self,
+        addr: &CheetahString,
+        src_group: &CheetahString,
+        dest_group: &CheetahString,
+        topic: &CheetahString,
+        is_offline: bool,
+        timeout_millis: u64,
+    ) -> Result<()> {
+        let request_header = CloneGroupOffsetRequestHeader {
+            src_group: src_group.clone(),
+            dest_group: dest_group.clone(),
+            topic: Some(topic.clone()),
+            offline: is_offline,
+            rpc_request_header: None,
+        };
+        let request =
+            RemotingCommand::create_request_command(RequestCode::CloneGroupOffset, request_header);
+        let response = self
+            .remoting_client
+            .invoke_async(
+                Some(&mix_all::broker_vip_channel(
+                    self.client_config.vip_channel_enabled,
+                    addr,
+                )),
+                request,
+                timeout_millis,
+            )
+            .await?;
+        if ResponseCode::from(response.code()) == ResponseCode::Success {
+            return Ok(());
+        }
+        client_broker_err!(
+            response.code(),
+            response.remark().map_or("".to_string(), |s| s.to_string()),
+            addr.to_string()
+        )
+    }
+
+    pub async fn get_topic_stats_info(
+        &mut self,
+        addr: &CheetahString,
+        topic: &CheetahString,
+        timeout_millis: u64,
+    ) -> Result<TopicStatsTable> {
+        let request_header = GetTopicStatsInfoRequestHeader {
+            topic: topic.clone(),
+            topic_request_header: None,
+        };
+        let request =
+            RemotingCommand::create_request_command(RequestCode::GetTopicStatsInfo, request_header);
+        let response = self
+            .remoting_client
+            .invoke_async(
+                Some(&mix_all::broker_vip_channel(
+                    self.client_config.vip_channel_enabled,
+                    addr,
+                )),
+                request,
+                timeout_millis,
+            )
+            .await?;
+        if ResponseCode::from(response.code()) == ResponseCode::Success {
+            if let Some(body) = response.body() {
+                if let Ok(topic_stats_table) = TopicStatsTable::decode(body.as_ref()) {
+                    return Ok(topic_stats_table);
+                }
+            }
+            return Ok(TopicStatsTable::new());
+        }
+        client_broker_err!(
+            response.code(),
+            response.remark().map_or("".to_string(), |s| s.to_string()),
+            addr.to_string()
+        )
+    }
+
     pub async fn set_message_request_mode(
         &mut self,
         broker_addr: &CheetahString,
@@ -1453,8 +1680,15 @@ impl MQClientAPIImpl {
         let response_code = ResponseCode::from(response.code());
         let (pop_status, msg_found_list) = match response_code {
             ResponseCode::Success => {
+                let body_compression_type = response
+                    .decode_command_custom_header::<PopMessageResponseHeader>()
+                    .ok()
+                    .and_then(|header| header.body_compression_type);
+                let mut body =
+                    decompress_response_body(response.take_body(), body_compression_type)
+                        .unwrap_or_default();
                 let messages = MessageDecoder::decodes_batch(
-                    response.get_body_mut().unwrap(),
+                    &mut body,
                     self.client_config.decode_read_body,
                     self.client_config.decode_decompress_body,
                 );
@@ -1812,3 +2046,20 @@ fn build_queue_offset_sorted_map(
     }
     sort_map
 }
+
+/// Restores a message block the broker compressed with the type of `bodyCompressionType`.
+fn decompress_response_body(
+    body: Option<Bytes>,
+    body_compression_type: Option<i32>,
+) -> Option<Bytes> {
+    match body_compression_type {
+        None => body,
+        Some(value @ 1..=3) => {
+            body.map(|body| CompressionType::find_by_value(value).decompression(&body))
+        }
+        Some(value) => {
+            warn!("unknown compression type {} of a pull response body", value);
+            body
+        }
+    }
+}
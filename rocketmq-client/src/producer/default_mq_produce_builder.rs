@@ -23,6 +23,7 @@ use rocketmq_common::common::compression::compressor::Compressor;
 use rocketmq_remoting::runtime::RPCHook;
 
 use crate::base::client_config::ClientConfig;
+use crate::consumer::topic_message_queue_change_listener::TopicMessageQueueChangeListener;
 use crate::producer::default_mq_producer::DefaultMQProducer;
 use crate::producer::produce_accumulator::ProduceAccumulator;
 use crate::producer::producer_impl::default_mq_producer_impl::DefaultMQProducerImpl;
@@ -53,6 +54,8 @@ pub struct DefaultMQProducerBuilder {
     compress_level: Option<i32>,
     compress_type: Option<CompressionType>,
     compressor: Option<Arc<Box<dyn Compressor + Send + Sync>>>,
+    shutdown_await_millis: Option<u64>,
+    topic_message_queue_change_listener: Option<Arc<dyn TopicMessageQueueChangeListener>>,
 }
 
 impl DefaultMQProducerBuilder {
@@ -81,6 +84,8 @@ impl DefaultMQProducerBuilder {
             compress_level: None,
             compress_type: None,
             compressor: None,
+            shutdown_await_millis: None,
+            topic_message_queue_change_listener: None,
         }
     }
 
@@ -242,6 +247,12 @@ impl DefaultMQProducerBuilder {
         self
     }
 
+    #[inline]
+    pub fn shutdown_await_millis(mut self, shutdown_await_millis: u64) -> Self {
+        self.shutdown_await_millis = Some(shutdown_await_millis);
+        self
+    }
+
     #[inline]
     pub fn compress_type(mut self, compress_type: CompressionType) -> Self {
         self.compress_type = Some(compress_type);
@@ -254,6 +265,15 @@ impl DefaultMQProducerBuilder {
         self
     }
 
+    #[inline]
+    pub fn topic_message_queue_change_listener(
+        mut self,
+        listener: impl TopicMessageQueueChangeListener + 'static,
+    ) -> Self {
+        self.topic_message_queue_change_listener = Some(Arc::new(listener));
+        self
+    }
+
     pub fn build(self) -> DefaultMQProducer {
         let mut mq_producer = DefaultMQProducer::default();
         if let Some(client_config) = self.client_config {
@@ -324,6 +344,9 @@ impl DefaultMQProducerBuilder {
         if let Some(compressor) = self.compressor {
             mq_producer.set_compressor(Some(compressor));
         }
+        if let Some(shutdown_await_millis) = self.shutdown_await_millis {
+            mq_producer.set_shutdown_await_millis(shutdown_await_millis);
+        }
 
         if let Some(default_mqproducer_impl) = self.default_mqproducer_impl {
             mq_producer.set_default_mqproducer_impl(default_mqproducer_impl);
@@ -335,6 +358,9 @@ impl DefaultMQProducerBuilder {
             );
             mq_producer.set_default_mqproducer_impl(producer_impl);
         }
+        if let Some(listener) = self.topic_message_queue_change_listener {
+            mq_producer.set_topic_message_queue_change_listener(listener);
+        }
 
         mq_producer
     }
@@ -36,9 +36,11 @@ use rocketmq_remoting::code::response_code::ResponseCode;
 use rocketmq_remoting::runtime::RPCHook;
 use rocketmq_rust::ArcMut;
 use tracing::error;
+use tracing::warn;
 
 use crate::base::client_config::ClientConfig;
 use crate::base::validators::Validators;
+use crate::consumer::topic_message_queue_change_listener::TopicMessageQueueChangeListener;
 use crate::mq_client_err;
 use crate::producer::default_mq_produce_builder::DefaultMQProducerBuilder;
 use crate::producer::mq_producer::MQProducer;
@@ -103,6 +105,10 @@ pub struct ProducerConfig {
     compress_level: i32,
     compress_type: CompressionType,
     compressor: Option<Arc<Box<dyn Compressor + Send + Sync>>>,
+    /// Maximum time to wait for pending asynchronous sends when shutting down.
+    shutdown_await_millis: u64,
+    /// Listener told about the new queues of a topic every time its route changes.
+    topic_message_queue_change_listener: Option<Arc<dyn TopicMessageQueueChangeListener>>,
 }
 
 impl ProducerConfig {
@@ -190,6 +196,16 @@ impl ProducerConfig {
     pub fn compressor(&self) -> &Option<Arc<Box<dyn Compressor + Send + Sync>>> {
         &self.compressor
     }
+
+    pub fn shutdown_await_millis(&self) -> u64 {
+        self.shutdown_await_millis
+    }
+
+    pub fn topic_message_queue_change_listener(
+        &self,
+    ) -> &Option<Arc<dyn TopicMessageQueueChangeListener>> {
+        &self.topic_message_queue_change_listener
+    }
 }
 
 impl Default for ProducerConfig {
@@ -238,6 +254,8 @@ impl Default for ProducerConfig {
             compressor: Some(Arc::new(CompressorFactory::get_compressor(
                 compression_type,
             ))),
+            shutdown_await_millis: 3000,
+            topic_message_queue_change_listener: None,
         }
     }
 }
@@ -346,11 +364,21 @@ impl DefaultMQProducer {
         &self.producer_config.compressor
     }
 
+    pub fn shutdown_await_millis(&self) -> u64 {
+        self.producer_config.shutdown_await_millis
+    }
+
     pub fn set_client_config(&mut self, client_config: ClientConfig) {
         self.client_config = client_config;
     }
 
-    pub fn set_default_mqproducer_impl(&mut self, default_mqproducer_impl: DefaultMQProducerImpl) {
+    pub fn set_default_mqproducer_impl(
+        &mut self,
+        mut default_mqproducer_impl: DefaultMQProducerImpl,
+    ) {
+        if let Some(listener) = &self.producer_config.topic_message_queue_change_listener {
+            default_mqproducer_impl.set_topic_message_queue_change_listener(listener.clone());
+        }
         let wrapper = ArcMut::new(default_mqproducer_impl);
         self.default_mqproducer_impl = Some(wrapper.clone());
         self.default_mqproducer_impl
@@ -464,10 +492,28 @@ impl DefaultMQProducer {
         self.producer_config.compressor = compressor;
     }
 
+    pub fn set_shutdown_await_millis(&mut self, shutdown_await_millis: u64) {
+        self.producer_config.shutdown_await_millis = shutdown_await_millis;
+    }
+
     pub fn producer_config(&self) -> &ProducerConfig {
         &self.producer_config
     }
 
+    /// Sets the listener told about the new queues of a topic every time brokers are added to or
+    /// removed from its route, e.g. to rebuild the hashing ring of a custom queue selector.
+    ///
+    /// The listener is called from the route update task and should return quickly.
+    pub fn set_topic_message_queue_change_listener(
+        &mut self,
+        listener: Arc<dyn TopicMessageQueueChangeListener>,
+    ) {
+        if let Some(ref mut default_mqproducer_impl) = self.default_mqproducer_impl {
+            default_mqproducer_impl.set_topic_message_queue_change_listener(listener.clone());
+        }
+        self.producer_config.topic_message_queue_change_listener = Some(listener);
+    }
+
     #[inline]
     pub fn set_send_latency_fault_enable(&mut self, send_latency_fault_enable: bool) {
         if let Some(ref mut default_mqproducer_impl) = self.default_mqproducer_impl {
@@ -661,11 +707,21 @@ impl MQProducer for DefaultMQProducer {
     }
 
     async fn shutdown(&mut self) {
+        if let Some(ref mut default_mqproducer_impl) = self.default_mqproducer_impl {
+            default_mqproducer_impl.shutdown().await;
+        }
+
         if let Some(ref mut produce_accumulator) = self.producer_config.produce_accumulator {
             produce_accumulator.shutdown();
         }
 
         if let Some(ref trace_dispatcher) = self.producer_config.trace_dispatcher {
+            if let Err(e) = trace_dispatcher.flush() {
+                warn!(
+                    "flush trace data failed when shutting down the producer: {}",
+                    e
+                );
+            }
             trace_dispatcher.shutdown();
         }
     }
@@ -1257,3 +1313,58 @@ impl MQProducer for DefaultMQProducer {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use parking_lot::Mutex;
+
+    use super::*;
+    use crate::producer::producer_impl::mq_producer_inner::MQProducerInner;
+    use crate::producer::producer_impl::topic_publish_info::TopicPublishInfo;
+
+    #[derive(Default)]
+    struct RecordingListener {
+        changes: Mutex<Vec<(String, HashSet<MessageQueue>)>>,
+    }
+
+    impl TopicMessageQueueChangeListener for RecordingListener {
+        fn on_changed(&self, topic: &str, message_queues: HashSet<MessageQueue>) {
+            self.changes
+                .lock()
+                .push((topic.to_string(), message_queues));
+        }
+    }
+
+    fn publish_info(queue_ids: &[i32]) -> Option<TopicPublishInfo> {
+        let mut info = TopicPublishInfo::new();
+        info.message_queue_list = queue_ids
+            .iter()
+            .map(|queue_id| MessageQueue::from_parts("TopicA", "broker-a", *queue_id))
+            .collect();
+        Some(info)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn listener_set_before_the_producer_impl_is_told_about_queue_changes() {
+        let listener = Arc::new(RecordingListener::default());
+        let mut producer = DefaultMQProducer::default();
+        producer.set_topic_message_queue_change_listener(listener.clone());
+        producer.set_default_mqproducer_impl(DefaultMQProducerImpl::new(
+            ClientConfig::default(),
+            ProducerConfig::default(),
+            None,
+        ));
+        let producer_impl = producer.default_mqproducer_impl.as_mut().unwrap();
+
+        producer_impl.update_topic_publish_info("TopicA".into(), publish_info(&[0, 1]));
+        producer_impl.update_topic_publish_info("TopicA".into(), publish_info(&[1, 0]));
+        producer_impl.update_topic_publish_info("TopicA".into(), publish_info(&[0, 1, 2]));
+
+        let changes = listener.changes.lock();
+        let queue_nums = changes
+            .iter()
+            .map(|(topic, message_queues)| (topic.as_str(), message_queues.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(queue_nums, [("TopicA", 2), ("TopicA", 3)]);
+    }
+}
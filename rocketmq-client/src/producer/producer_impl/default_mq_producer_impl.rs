@@ -18,6 +18,8 @@ use std::any::Any;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::future::Future;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
@@ -53,8 +55,10 @@ use rocketmq_remoting::runtime::RPCHook;
 use rocketmq_runtime::RocketMQRuntime;
 use rocketmq_rust::ArcMut;
 use tokio::runtime::Handle;
+use tokio::sync::Notify;
 use tokio::sync::RwLock;
 use tokio::sync::Semaphore;
+use tracing::info;
 use tracing::warn;
 
 use crate::base::client_config::ClientConfig;
@@ -65,6 +69,7 @@ use crate::client_error::MQClientError::RemotingTooMuchRequestError;
 use crate::client_error::MQClientError::RequestTimeoutError;
 use crate::client_error::RequestTimeoutErr;
 use crate::common::client_error_code::ClientErrorCode;
+use crate::consumer::topic_message_queue_change_listener::TopicMessageQueueChangeListener;
 use crate::factory::mq_client_instance::MQClientInstance;
 use crate::hook::check_forbidden_context::CheckForbiddenContext;
 use crate::hook::check_forbidden_hook::CheckForbiddenHook;
@@ -92,6 +97,7 @@ use crate::producer::send_result::SendResult;
 use crate::producer::send_status::SendStatus;
 use crate::producer::transaction_listener::TransactionListener;
 use crate::producer::transaction_send_result::TransactionSendResult;
+use crate::trace::trace_parent::TraceParent;
 use crate::Result;
 
 pub struct DefaultMQProducerImpl {
@@ -107,11 +113,15 @@ pub struct DefaultMQProducerImpl {
     mq_fault_strategy: ArcMut<MQFaultStrategy>,
     semaphore_async_send_num: Arc<Semaphore>,
     semaphore_async_send_size: Arc<Semaphore>,
+    /// Number of async sends spawned but not completed yet, waited on during shutdown.
+    pending_async_sends: Arc<AtomicUsize>,
+    async_sends_drained: Arc<Notify>,
     async_sender_runtime: Option<Arc<RocketMQRuntime>>,
     default_async_sender_runtime: Option<Arc<RocketMQRuntime>>,
     default_mqproducer_impl_inner: Option<ArcMut<DefaultMQProducerImpl>>,
     transaction_listener: Option<Arc<Box<dyn TransactionListener>>>,
     check_runtime: Option<Arc<RocketMQRuntime>>,
+    topic_message_queue_change_listener: Option<Arc<dyn TopicMessageQueueChangeListener>>,
 }
 
 #[allow(unused_must_use)]
@@ -143,6 +153,8 @@ impl DefaultMQProducerImpl {
             mq_fault_strategy: ArcMut::new(MQFaultStrategy::new(&client_config)),
             semaphore_async_send_num: Arc::new(semaphore_async_send_num),
             semaphore_async_send_size: Arc::new(semaphore_async_send_size),
+            pending_async_sends: Arc::new(AtomicUsize::new(0)),
+            async_sends_drained: Arc::new(Notify::new()),
             async_sender_runtime: None,
             default_async_sender_runtime: Some(Arc::new(RocketMQRuntime::new_multi(
                 num_cpus::get(),
@@ -151,6 +163,7 @@ impl DefaultMQProducerImpl {
             default_mqproducer_impl_inner: None,
             transaction_listener: None,
             check_runtime: None,
+            topic_message_queue_change_listener: None,
         }
     }
 
@@ -577,7 +590,7 @@ impl DefaultMQProducerImpl {
             }
             let result = tokio::time::timeout(
                 Duration::from_millis(timeout - cost_time),
-                self.semaphore_async_send_num.acquire(),
+                self.semaphore_async_send_num.clone().acquire_owned(),
             )
             .await;
             let acquire_value_num = match result {
@@ -618,7 +631,9 @@ impl DefaultMQProducerImpl {
             }
             let result = tokio::time::timeout(
                 Duration::from_millis(timeout - cost_time),
-                self.semaphore_async_send_size.acquire_many(msg_len as u32),
+                self.semaphore_async_send_size
+                    .clone()
+                    .acquire_many_owned(msg_len as u32),
             )
             .await;
             let acquire_value_size = match result {
@@ -649,11 +664,38 @@ impl DefaultMQProducerImpl {
             (None, None)
         };
 
-        self.get_async_sender_executor().get_handle().spawn(f);
-        drop((acquire_value_num, acquire_value_size));
+        let pending_async_sends = self.pending_async_sends.clone();
+        let async_sends_drained = self.async_sends_drained.clone();
+        pending_async_sends.fetch_add(1, Ordering::AcqRel);
+        self.get_async_sender_executor()
+            .get_handle()
+            .spawn(async move {
+                f.await;
+                // Release the back pressure permits only once the send has completed.
+                drop((acquire_value_num, acquire_value_size));
+                if pending_async_sends.fetch_sub(1, Ordering::AcqRel) == 1 {
+                    async_sends_drained.notify_waiters();
+                }
+            });
         Ok(())
     }
 
+    /// Waits until every spawned async send has completed, or `timeout` elapses.
+    ///
+    /// Returns `true` if no async send is pending anymore.
+    pub async fn wait_for_pending_async_sends(&self, timeout: Duration) -> bool {
+        let wait_drained = async {
+            loop {
+                let drained = self.async_sends_drained.notified();
+                if self.pending_async_sends.load(Ordering::Acquire) == 0 {
+                    return;
+                }
+                drained.await;
+            }
+        };
+        tokio::time::timeout(timeout, wait_drained).await.is_ok()
+    }
+
     #[inline]
     pub fn get_async_sender_executor(&self) -> &Arc<RocketMQRuntime> {
         if let Some(ref async_sender_runtime) = self.async_sender_runtime {
@@ -975,6 +1017,9 @@ impl DefaultMQProducerImpl {
         let batch = msg.as_any().downcast_ref::<MessageBatch>().is_some();
         if !batch {
             MessageClientIDSetter::set_uniq_id(msg);
+            if self.client_config.enable_trace_context_propagation {
+                TraceParent::inject(msg);
+            }
         }
         let mut topic_with_namespace = false;
         if self.client_config.get_namespace().is_some() {
@@ -2026,6 +2071,15 @@ impl DefaultMQProducerImpl {
         self.default_mqproducer_impl_inner = Some(default_mqproducer_impl_inner);
     }
 
+    /// Sets the listener told about the new queues of a topic every time brokers are added to or
+    /// removed from its route.
+    pub fn set_topic_message_queue_change_listener(
+        &mut self,
+        listener: Arc<dyn TopicMessageQueueChangeListener>,
+    ) {
+        self.topic_message_queue_change_listener = Some(listener);
+    }
+
     pub fn set_transaction_listener(
         &mut self,
         transaction_listener: Arc<Box<dyn TransactionListener>>,
@@ -2157,15 +2211,39 @@ impl MQProducerInner for DefaultMQProducerImpl {
         if topic.is_empty() || info.is_none() {
             return;
         }
+        let info = info.unwrap();
+        let message_queues = self.topic_message_queue_change_listener.as_ref().map(|_| {
+            info.message_queue_list
+                .iter()
+                .cloned()
+                .collect::<HashSet<_>>()
+        });
         let handle = Handle::current();
         let topic_publish_info_table = self.topic_publish_info_table.clone();
-        let _ = thread::spawn(move || {
+        let topic_clone = topic.clone();
+        let old_message_queues = thread::spawn(move || {
             handle.block_on(async move {
                 let mut write_guard = topic_publish_info_table.write().await;
-                write_guard.insert(topic, info.unwrap());
+                write_guard
+                    .insert(topic_clone, info)
+                    .map(|old| old.message_queue_list)
             })
         })
-        .join();
+        .join()
+        .ok()
+        .flatten();
+        if let (Some(listener), Some(message_queues)) = (
+            self.topic_message_queue_change_listener.as_ref(),
+            message_queues,
+        ) {
+            let old_message_queues = old_message_queues
+                .unwrap_or_default()
+                .into_iter()
+                .collect::<HashSet<_>>();
+            if old_message_queues != message_queues {
+                listener.on_changed(topic.as_str(), message_queues);
+            }
+        }
     }
 
     fn is_unit_mode(&self) -> bool {
@@ -2254,6 +2332,45 @@ impl DefaultMQProducerImpl {
         Ok(())
     }
 
+    pub async fn shutdown(&mut self) {
+        self.shutdown_with_factory(true).await
+    }
+
+    pub async fn shutdown_with_factory(&mut self, shutdown_factory: bool) {
+        match self.service_state {
+            ServiceState::Running => {
+                let await_millis = self.producer_config.shutdown_await_millis();
+                if !self
+                    .wait_for_pending_async_sends(Duration::from_millis(await_millis))
+                    .await
+                {
+                    warn!(
+                        "the producer [{}] shutdown with {} async sends still pending after {}ms",
+                        self.producer_config.producer_group(),
+                        self.pending_async_sends.load(Ordering::Acquire),
+                        await_millis
+                    );
+                }
+                if let Some(client_instance) = self.client_instance.as_mut() {
+                    client_instance
+                        .unregister_producer(self.producer_config.producer_group().clone())
+                        .await;
+                    if shutdown_factory {
+                        Box::pin(client_instance.shutdown()).await;
+                    }
+                }
+                self.service_state = ServiceState::ShutdownAlready;
+                info!(
+                    "the producer [{}] shutdown OK",
+                    self.producer_config.producer_group()
+                );
+            }
+            ServiceState::CreateJust
+            | ServiceState::ShutdownAlready
+            | ServiceState::StartFailed => {}
+        }
+    }
+
     pub fn register_end_transaction_hook(&mut self, hook: impl EndTransactionHook) {
         todo!()
     }
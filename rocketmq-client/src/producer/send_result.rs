@@ -32,6 +32,10 @@ pub struct SendResult {
     pub region_id: Option<String>,
     pub trace_on: bool,
     pub raw_resp_body: Option<Vec<u8>>,
+    /// Milliseconds the broker spent storing the message, `None` for brokers not reporting it.
+    pub store_rt: Option<i64>,
+    /// Milliseconds of the store time spent appending the message to the page cache.
+    pub page_cache_rt: Option<i64>,
 }
 
 impl Default for SendResult {
@@ -46,6 +50,8 @@ impl Default for SendResult {
             region_id: None,
             trace_on: true,
             raw_resp_body: None,
+            store_rt: None,
+            page_cache_rt: None,
         }
     }
 }
@@ -68,6 +74,8 @@ impl SendResult {
             region_id: None,
             trace_on: true,
             raw_resp_body: None,
+            store_rt: None,
+            page_cache_rt: None,
         }
     }
 
@@ -90,6 +98,8 @@ impl SendResult {
             region_id,
             trace_on: true,
             raw_resp_body: None,
+            store_rt: None,
+            page_cache_rt: None,
         }
     }
 
@@ -147,6 +157,16 @@ impl SendResult {
     pub fn get_raw_resp_body(&self) -> Option<&[u8]> {
         self.raw_resp_body.as_deref()
     }
+
+    #[inline]
+    pub fn store_rt(&self) -> Option<i64> {
+        self.store_rt
+    }
+
+    #[inline]
+    pub fn page_cache_rt(&self) -> Option<i64> {
+        self.page_cache_rt
+    }
 }
 
 impl std::fmt::Display for SendResult {
@@ -155,12 +175,14 @@ impl std::fmt::Display for SendResult {
         write!(
             f,
             "SendResult [sendStatus={:?}, msgId={:?}, offsetMsgId={:?}, messageQueue={:?}, \
-             queueOffset={}]",
+             queueOffset={}, regionId={:?}, storeRT={:?}]",
             self.send_status,
             self.msg_id,
             self.offset_msg_id,
             self.message_queue,
             self.queue_offset,
+            self.region_id,
+            self.store_rt,
         )
     }
 }
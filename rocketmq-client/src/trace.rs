@@ -20,5 +20,6 @@ pub mod trace_bean;
 pub mod trace_constants;
 pub mod trace_context;
 pub mod trace_dispatcher;
+pub mod trace_parent;
 pub mod trace_type;
 pub mod trace_view;
@@ -0,0 +1,250 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! W3C Trace Context (`traceparent`) propagation through message properties.
+//!
+//! The producer injects the `traceparent` of the current scope, or a new root when there is none,
+//! into each outgoing message. The push consumer extracts it again and runs the listener inside
+//! a child scope, so spans recorded on both sides of the message hop share one trace id.
+
+use std::fmt;
+use std::fmt::Display;
+use std::future::Future;
+
+use cheetah_string::CheetahString;
+use rand::random;
+use rocketmq_common::common::message::MessageTrait;
+
+/// Message property carrying the W3C `traceparent` value.
+pub const TRACE_PARENT_PROPERTY: &str = "traceparent";
+
+const VERSION: u8 = 0x00;
+const FLAG_SAMPLED: u8 = 0x01;
+
+tokio::task_local! {
+    static CURRENT_TRACE_PARENT: TraceParent;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TraceParent {
+    trace_id: u128,
+    parent_id: u64,
+    flags: u8,
+}
+
+impl TraceParent {
+    /// Starts a new trace with random, non-zero trace and parent ids.
+    pub fn new_root(sampled: bool) -> Self {
+        TraceParent {
+            trace_id: non_zero(random::<u128>),
+            parent_id: non_zero(random::<u64>),
+            flags: if sampled { FLAG_SAMPLED } else { 0 },
+        }
+    }
+
+    /// Returns a context in the same trace with a new parent (span) id.
+    pub fn child(&self) -> Self {
+        TraceParent {
+            parent_id: non_zero(random::<u64>),
+            ..*self
+        }
+    }
+
+    pub fn trace_id(&self) -> u128 {
+        self.trace_id
+    }
+
+    pub fn parent_id(&self) -> u64 {
+        self.parent_id
+    }
+
+    pub fn is_sampled(&self) -> bool {
+        self.flags & FLAG_SAMPLED != 0
+    }
+
+    /// Parses a `traceparent` value, e.g.
+    /// `00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01`.
+    ///
+    /// Unknown future versions are accepted as long as the first four fields are well formed,
+    /// as required by the specification.
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut parts = value.trim().split('-');
+        let version = parse_hex_field(parts.next()?, 2)?;
+        let trace_id = parse_hex_field(parts.next()?, 32)?;
+        let parent_id = parse_hex_field(parts.next()?, 16)?;
+        let flags = parse_hex_field(parts.next()?, 2)?;
+        if version == 0xff || (version == VERSION as u128 && parts.next().is_some()) {
+            return None;
+        }
+        if trace_id == 0 || parent_id == 0 {
+            return None;
+        }
+        Some(TraceParent {
+            trace_id,
+            parent_id: parent_id as u64,
+            flags: flags as u8,
+        })
+    }
+
+    /// Returns the context of the current task scope, if any.
+    pub fn current() -> Option<Self> {
+        CURRENT_TRACE_PARENT.try_with(|current| *current).ok()
+    }
+
+    /// Runs `f` with `self` as the current context.
+    pub async fn scope<F: Future>(self, f: F) -> F::Output {
+        CURRENT_TRACE_PARENT.scope(self, f).await
+    }
+
+    /// Runs the synchronous `f` with `self` as the current context.
+    pub fn sync_scope<R>(self, f: impl FnOnce() -> R) -> R {
+        CURRENT_TRACE_PARENT.sync_scope(self, f)
+    }
+
+    /// Reads the `traceparent` property of a message.
+    pub fn extract<M: MessageTrait + ?Sized>(msg: &M) -> Option<Self> {
+        msg.get_property(&CheetahString::from_static_str(TRACE_PARENT_PROPERTY))
+            .and_then(|value| TraceParent::parse(value.as_str()))
+    }
+
+    /// Sets the `traceparent` property of a message unless the application already did.
+    ///
+    /// The injected value is a child of the current scope, or a new sampled root.
+    pub fn inject<M: MessageTrait + ?Sized>(msg: &mut M) {
+        let key = CheetahString::from_static_str(TRACE_PARENT_PROPERTY);
+        if msg.get_property(&key).is_some() {
+            return;
+        }
+        let trace_parent = TraceParent::current()
+            .map(|current| current.child())
+            .unwrap_or_else(|| TraceParent::new_root(true));
+        msg.put_property(key, CheetahString::from_string(trace_parent.to_string()));
+    }
+
+    /// Runs a consume callback inside a child of the context carried by `msg`, if any.
+    pub fn consume_scope<M, R>(msg: Option<&M>, enabled: bool, f: impl FnOnce() -> R) -> R
+    where
+        M: MessageTrait + ?Sized,
+    {
+        match msg.filter(|_| enabled).and_then(TraceParent::extract) {
+            Some(trace_parent) => trace_parent.child().sync_scope(f),
+            None => f(),
+        }
+    }
+}
+
+impl Display for TraceParent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:02x}-{:032x}-{:016x}-{:02x}",
+            VERSION, self.trace_id, self.parent_id, self.flags
+        )
+    }
+}
+
+fn non_zero<T: PartialEq + Default>(mut gen: impl FnMut() -> T) -> T {
+    loop {
+        let value = gen();
+        if value != T::default() {
+            return value;
+        }
+    }
+}
+
+fn parse_hex_field(field: &str, len: usize) -> Option<u128> {
+    if field.len() != len
+        || !field
+            .bytes()
+            .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+    {
+        return None;
+    }
+    u128::from_str_radix(field, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use rocketmq_common::common::message::message_single::Message;
+
+    use super::*;
+
+    const SAMPLE: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    #[test]
+    fn parse_and_format_round_trip() {
+        let trace_parent = TraceParent::parse(SAMPLE).unwrap();
+        assert_eq!(trace_parent.trace_id(), 0x4bf92f3577b34da6a3ce929d0e0e4736);
+        assert_eq!(trace_parent.parent_id(), 0x00f067aa0ba902b7);
+        assert!(trace_parent.is_sampled());
+        assert_eq!(trace_parent.to_string(), SAMPLE);
+    }
+
+    #[test]
+    fn parse_rejects_invalid_values() {
+        assert!(TraceParent::parse("").is_none());
+        assert!(
+            TraceParent::parse("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7").is_none()
+        );
+        assert!(
+            TraceParent::parse("00-00000000000000000000000000000000-00f067aa0ba902b7-01").is_none()
+        );
+        assert!(
+            TraceParent::parse("00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01").is_none()
+        );
+        assert!(
+            TraceParent::parse("00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01").is_none()
+        );
+        assert!(
+            TraceParent::parse("ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01").is_none()
+        );
+        assert!(TraceParent::parse(&format!("{}-extra", SAMPLE)).is_none());
+    }
+
+    #[test]
+    fn child_keeps_trace_id() {
+        let root = TraceParent::new_root(false);
+        let child = root.child();
+        assert_eq!(root.trace_id(), child.trace_id());
+        assert!(!child.is_sampled());
+    }
+
+    #[test]
+    fn inject_uses_current_scope_and_keeps_existing_value() {
+        let root = TraceParent::parse(SAMPLE).unwrap();
+        let mut msg = Message::default();
+        root.sync_scope(|| TraceParent::inject(&mut msg));
+        let injected = TraceParent::extract(&msg).unwrap();
+        assert_eq!(injected.trace_id(), root.trace_id());
+        assert_ne!(injected.parent_id(), root.parent_id());
+
+        TraceParent::inject(&mut msg);
+        assert_eq!(TraceParent::extract(&msg), Some(injected));
+    }
+
+    #[test]
+    fn consume_scope_exposes_message_context() {
+        let mut msg = Message::default();
+        msg.put_property(
+            CheetahString::from_static_str(TRACE_PARENT_PROPERTY),
+            CheetahString::from_static_str(SAMPLE),
+        );
+        let current = TraceParent::consume_scope(Some(&msg), true, TraceParent::current).unwrap();
+        assert_eq!(current.trace_id(), 0x4bf92f3577b34da6a3ce929d0e0e4736);
+        assert!(TraceParent::consume_scope(Some(&msg), false, TraceParent::current).is_none());
+        assert!(TraceParent::current().is_none());
+    }
+}
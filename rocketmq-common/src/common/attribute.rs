@@ -19,9 +19,12 @@ pub mod attribute_parser;
 pub mod attribute_util;
 pub mod cleanup_policy;
 pub mod cq_type;
+pub mod long_range_attribute;
 pub mod topic_attributes;
 pub mod topic_message_type;
 
+use std::sync::Arc;
+
 /// `AttributeTrait` defines a common interface for attributes.
 ///
 /// This trait specifies the operations that can be performed on an attribute object.
@@ -46,7 +49,24 @@ pub trait AttributeTrait {
     ///
     /// # Arguments
     /// * `value` - A string slice representing the value to be verified.
-    fn verify(&self, value: &str);
+    ///
+    /// # Returns
+    /// `Ok(())` if the value is valid, otherwise the reason it is not.
+    fn verify(&self, value: &str) -> Result<(), String>;
+}
+
+impl<T: AttributeTrait + ?Sized> AttributeTrait for Arc<T> {
+    fn name(&self) -> String {
+        (**self).name()
+    }
+
+    fn changeable(&self) -> bool {
+        (**self).changeable()
+    }
+
+    fn verify(&self, value: &str) -> Result<(), String> {
+        (**self).verify(value)
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -35,8 +35,12 @@ impl AttributeTrait for EnumAttribute {
         self.attribute.changeable
     }
 
-    fn verify(&self, _value: &str) {
-        todo!()
+    fn verify(&self, value: &str) -> Result<(), String> {
+        if self.universe.contains(value) {
+            Ok(())
+        } else {
+            Err(format!("value is not in set: {:?}", self.universe))
+        }
     }
 }
 
@@ -52,8 +56,4 @@ impl EnumAttribute {
     pub fn get_universe(&self) -> &HashSet<String> {
         &self.universe
     }
-
-    pub fn verify(&self, value: &str) -> bool {
-        !self.universe.contains(value)
-    }
 }
@@ -17,19 +17,27 @@
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::string::ToString;
-use std::sync::Arc;
 
 use cheetah_string::CheetahString;
 use tracing::info;
 
 use crate::common::attribute::AttributeTrait;
 
+/// Applies the attribute modifications of a create or update topic request to the
+/// `current_attributes` of the topic.
+///
+/// `new_attributes` keys are prefixed with `+` to add or update an attribute, and with `-` to
+/// delete one. Creating a topic only accepts additions, and attributes that are not changeable
+/// can only be set when the topic is created.
+///
+/// # Returns
+/// The attributes of the topic after the modifications, or the reason they were rejected.
 pub fn alter_current_attributes<A: AttributeTrait>(
     create: bool,
     all: HashMap<CheetahString, A>,
     current_attributes: HashMap<CheetahString, CheetahString>,
     new_attributes: HashMap<CheetahString, CheetahString>,
-) -> HashMap<CheetahString, CheetahString> {
+) -> Result<HashMap<CheetahString, CheetahString>, String> {
     let mut init = HashMap::new();
     let mut add = HashMap::new();
     let mut update = HashMap::new();
@@ -38,17 +46,17 @@ pub fn alter_current_attributes<A: AttributeTrait>(
 
     for (key, value) in new_attributes {
         let real_key = real_key(key.as_str());
-        validate(&real_key);
-        duplication_check(&mut keys, &real_key);
+        validate(&real_key)?;
+        duplication_check(&mut keys, &real_key)?;
 
         if create {
             if key.starts_with('+') {
                 init.insert(real_key.clone().into(), value);
             } else {
-                panic!(
+                return Err(format!(
                     "only add attribute is supported while creating topic. key: {}",
                     real_key
-                );
+                ));
             }
         } else if key.starts_with('+') {
             if !current_attributes.contains_key(real_key.as_str()) {
@@ -58,41 +66,44 @@ pub fn alter_current_attributes<A: AttributeTrait>(
             }
         } else if key.starts_with('-') {
             if !current_attributes.contains_key(real_key.as_str()) {
-                panic!("attempt to delete a nonexistent key: {}", real_key);
+                return Err(format!("attempt to delete a nonexistent key: {}", real_key));
             }
             delete.insert(real_key.clone().into(), value);
         } else {
-            panic!("wrong format key: {}", real_key);
+            return Err(format!("wrong format key: {}", real_key));
         }
     }
 
-    validate_alter(&all, &init, true, false);
-    validate_alter(&all, &add, false, false);
-    validate_alter(&all, &update, false, false);
-    validate_alter(&all, &delete, false, true);
+    validate_alter(&all, &init, true, false)?;
+    validate_alter(&all, &add, false, false)?;
+    validate_alter(&all, &update, false, false)?;
+    validate_alter(&all, &delete, false, true)?;
 
     info!("add: {:?}, update: {:?}, delete: {:?}", add, update, delete);
 
-    let mut final_attributes = current_attributes.clone();
+    let mut final_attributes = current_attributes;
     final_attributes.extend(init);
     final_attributes.extend(add);
+    final_attributes.extend(update);
     for key in delete.keys() {
         final_attributes.remove(key);
     }
 
-    final_attributes
+    Ok(final_attributes)
 }
 
-fn duplication_check(keys: &mut HashSet<CheetahString>, key: &String) {
+fn duplication_check(keys: &mut HashSet<CheetahString>, key: &String) -> Result<(), String> {
     if !keys.insert(key.into()) {
-        panic!("alter duplication key. key: {}", key);
+        return Err(format!("alter duplication key. key: {}", key));
     }
+    Ok(())
 }
 
-fn validate(kv_attribute: &str) {
+fn validate(kv_attribute: &str) -> Result<(), String> {
     if kv_attribute.is_empty() || kv_attribute.contains('+') || kv_attribute.contains('-') {
-        panic!("kv string format wrong.");
+        return Err(String::from("kv string format wrong."));
     }
+    Ok(())
 }
 
 fn validate_alter<A: AttributeTrait>(
@@ -100,23 +111,121 @@ fn validate_alter<A: AttributeTrait>(
     alter: &HashMap<CheetahString, CheetahString>,
     init: bool,
     delete: bool,
-) {
+) -> Result<(), String> {
     for (key, value) in alter {
-        let attribute = all.get(key);
-        if attribute.is_none() {
-            panic!("unsupported key: {}", key);
-        }
-        let attribute = attribute.unwrap();
+        let Some(attribute) = all.get(key) else {
+            return Err(format!("unsupported key: {}", key));
+        };
         if !init && !attribute.changeable() {
-            panic!("attempt to update an unchangeable attribute. key: {}", key);
+            return Err(format!(
+                "attempt to update an unchangeable attribute. key: {}",
+                key
+            ));
         }
 
         if !delete {
-            attribute.verify(value);
+            attribute
+                .verify(value)
+                .map_err(|e| format!("invalid value of attribute {}: {}", key, e))?;
         }
     }
+    Ok(())
 }
 
 fn real_key(key: &str) -> String {
     key.chars().skip(1).collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::TopicAttributes::ALL;
+
+    fn all() -> HashMap<CheetahString, Arc<dyn AttributeTrait + Send + Sync>> {
+        ALL.iter()
+            .map(|(key, attribute)| (key.as_str().into(), attribute.clone()))
+            .collect()
+    }
+
+    fn attributes(pairs: &[(&str, &str)]) -> HashMap<CheetahString, CheetahString> {
+        pairs
+            .iter()
+            .map(|(key, value)| (CheetahString::from(*key), CheetahString::from(*value)))
+            .collect()
+    }
+
+    #[test]
+    fn create_only_accepts_additions() {
+        let created = alter_current_attributes(
+            true,
+            all(),
+            HashMap::new(),
+            attributes(&[("+message.type", "FIFO"), ("+reserve.time", "72")]),
+        )
+        .unwrap();
+        assert_eq!(
+            created,
+            attributes(&[("message.type", "FIFO"), ("reserve.time", "72")])
+        );
+
+        assert!(alter_current_attributes(
+            true,
+            all(),
+            HashMap::new(),
+            attributes(&[("-message.type", "")]),
+        )
+        .is_err());
+        assert!(alter_current_attributes(
+            true,
+            all(),
+            HashMap::new(),
+            attributes(&[("+message.type", "UNKNOWN")]),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn update_adds_updates_and_deletes() {
+        let current = attributes(&[("message.type", "NORMAL"), ("queue.type", "SimpleCQ")]);
+        let updated = alter_current_attributes(
+            false,
+            all(),
+            current.clone(),
+            attributes(&[("+message.type", "DELAY"), ("+reserve.time", "24")]),
+        )
+        .unwrap();
+        assert_eq!(
+            updated,
+            attributes(&[
+                ("message.type", "DELAY"),
+                ("queue.type", "SimpleCQ"),
+                ("reserve.time", "24")
+            ])
+        );
+
+        let deleted =
+            alter_current_attributes(false, all(), updated, attributes(&[("-reserve.time", "")]))
+                .unwrap();
+        assert_eq!(
+            deleted,
+            attributes(&[("message.type", "DELAY"), ("queue.type", "SimpleCQ")])
+        );
+
+        assert!(alter_current_attributes(
+            false,
+            all(),
+            current.clone(),
+            attributes(&[("+queue.type", "BatchCQ")]),
+        )
+        .is_err());
+        assert!(alter_current_attributes(
+            false,
+            all(),
+            current,
+            attributes(&[("+reserve.time", "-2")]),
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,85 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::common::attribute::Attribute;
+use crate::common::attribute::AttributeTrait;
+
+/// An attribute whose value is an integer within `[min, max]`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LongRangeAttribute {
+    pub(crate) attribute: Attribute,
+    pub(crate) min: i64,
+    pub(crate) max: i64,
+    pub(crate) default_value: i64,
+}
+
+impl AttributeTrait for LongRangeAttribute {
+    fn name(&self) -> String {
+        self.attribute.name.clone()
+    }
+
+    fn changeable(&self) -> bool {
+        self.attribute.changeable
+    }
+
+    fn verify(&self, value: &str) -> Result<(), String> {
+        match value.parse::<i64>() {
+            Ok(value) if (self.min..=self.max).contains(&value) => Ok(()),
+            _ => Err(format!("value is not in range({}, {})", self.min, self.max)),
+        }
+    }
+}
+
+impl LongRangeAttribute {
+    pub fn get_name(&self) -> &str {
+        self.attribute.name.as_str()
+    }
+
+    pub fn get_default_value(&self) -> i64 {
+        self.default_value
+    }
+
+    pub fn get_min(&self) -> i64 {
+        self.min
+    }
+
+    pub fn get_max(&self) -> i64 {
+        self.max
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verify_checks_the_range() {
+        let attribute = LongRangeAttribute {
+            attribute: Attribute {
+                name: String::from("reserve.time"),
+                changeable: true,
+            },
+            min: -1,
+            max: 100,
+            default_value: -1,
+        };
+        assert!(attribute.verify("-1").is_ok());
+        assert!(attribute.verify("100").is_ok());
+        assert!(attribute.verify("101").is_err());
+        assert!(attribute.verify("-2").is_err());
+        assert!(attribute.verify("one").is_err());
+    }
+}
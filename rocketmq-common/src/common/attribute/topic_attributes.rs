@@ -1,10 +1,13 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use lazy_static::lazy_static;
 
 use crate::common::attribute::attribute_enum::EnumAttribute;
+use crate::common::attribute::long_range_attribute::LongRangeAttribute;
 use crate::common::attribute::topic_message_type::TopicMessageType;
 use crate::common::attribute::Attribute;
+use crate::common::attribute::AttributeTrait;
 use crate::hashset;
 
 lazy_static! {
@@ -32,19 +35,34 @@ lazy_static! {
         universe: hashset! {String::from("BatchCQ"), String::from("SimpleCQ")},
         default_value: String::from("SimpleCQ"),
     };
-    pub static ref ALL: HashMap<String, EnumAttribute> = {
-        let mut map = HashMap::<String, EnumAttribute>::new();
+    /// Hours the messages of the topic are kept, -1 to keep them as long as the broker keeps
+    /// its files.
+    pub static ref TOPIC_RESERVE_TIME_ATTRIBUTE: LongRangeAttribute = LongRangeAttribute {
+        attribute: Attribute {
+            name: String::from("reserve.time"),
+            changeable: true,
+        },
+        min: -1,
+        max: i64::MAX,
+        default_value: -1,
+    };
+    pub static ref ALL: HashMap<String, Arc<dyn AttributeTrait + Send + Sync>> = {
+        let mut map = HashMap::<String, Arc<dyn AttributeTrait + Send + Sync>>::new();
         map.insert(
             QUEUE_TYPE_ATTRIBUTE.get_name().to_string(),
-            QUEUE_TYPE_ATTRIBUTE.clone(),
+            Arc::new(QUEUE_TYPE_ATTRIBUTE.clone()),
         );
         map.insert(
             CLEANUP_POLICY_ATTRIBUTE.get_name().to_string(),
-            CLEANUP_POLICY_ATTRIBUTE.clone(),
+            Arc::new(CLEANUP_POLICY_ATTRIBUTE.clone()),
         );
         map.insert(
             TOPIC_MESSAGE_TYPE_ATTRIBUTE.get_name().to_string(),
-            TOPIC_MESSAGE_TYPE_ATTRIBUTE.clone(),
+            Arc::new(TOPIC_MESSAGE_TYPE_ATTRIBUTE.clone()),
+        );
+        map.insert(
+            TOPIC_RESERVE_TIME_ATTRIBUTE.get_name().to_string(),
+            Arc::new(TOPIC_RESERVE_TIME_ATTRIBUTE.clone()),
         );
         map
     };
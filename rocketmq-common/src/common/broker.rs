@@ -17,3 +17,4 @@
 
 pub mod broker_config;
 pub mod broker_role;
+pub mod shared_broker_config;
@@ -30,6 +30,8 @@ use crate::common::mix_all;
 use crate::common::mix_all::NAMESRV_ADDR_PROPERTY;
 use crate::common::server::config::ServerConfig;
 use crate::common::topic::TopicValidator;
+use crate::utils::config_value_utils::find_config_value;
+use crate::utils::config_value_utils::set_config_value;
 
 const DEFAULT_CLUSTER_NAME: &str = "DefaultCluster";
 
@@ -120,6 +122,10 @@ pub struct BrokerConfig {
     pub listen_port: u32,
     pub trace_topic_enable: bool,
     pub msg_trace_topic_name: CheetahString,
+    /// Writes produce and consume traces of every message into `msg_trace_topic_name` from the
+    /// broker side, for clients that do not trace themselves.
+    pub broker_trace_enable: bool,
+    pub broker_trace_queue_size: usize,
     pub enable_controller_mode: bool,
     pub broker_name: CheetahString,
     pub region_id: CheetahString,
@@ -144,6 +150,13 @@ pub struct BrokerConfig {
     pub reject_transaction_message: bool,
     pub enable_detail_stat: bool,
     pub flush_consumer_offset_interval: u64,
+    /// Periodic persist intervals in ms of the broker managers, 0 persists only on change and
+    /// on shutdown
+    pub flush_topic_config_interval: u64,
+    pub flush_subscription_group_interval: u64,
+    pub flush_consumer_filter_interval: u64,
+    pub flush_consumer_order_info_interval: u64,
+    pub flush_message_request_mode_interval: u64,
     pub force_register: bool,
     pub register_name_server_period: u64,
     pub skip_pre_online: bool,
@@ -157,24 +170,60 @@ pub struct BrokerConfig {
     pub filter_support_retry: bool,
     pub use_server_side_reset_offset: bool,
     pub slave_read_enable: bool,
+    /// A slave sends consumers back to the master once its consume queues lag more than this
+    /// many bytes behind the replicated commit log.
+    pub slave_read_max_dispatch_behind_bytes: i64,
     pub commercial_base_count: i32,
     pub reject_pull_consumer_enable: bool,
+    /// Push `NOTIFY_CONSUMER_IDS_CHANGED` to the clients of a consumer group whose members
+    /// changed, at most once per `notify_consumer_ids_changed_min_interval_millis` per group
+    pub notify_consumer_ids_changed_enable: bool,
+    pub notify_consumer_ids_changed_min_interval_millis: u64,
+    /// Perm of the `%DLQ%` topics created on the first dead letter of a group, write only by
+    /// default so that dead letters are kept but not consumed until the perm is changed.
+    pub dlq_topic_perm: u32,
+    /// Queue nums of the `%DLQ%` topics created on the first dead letter of a group, capped at
+    /// 16.
+    pub dlq_queue_nums: i32,
+    /// Also puts a copy of every dead letter to `audit_dlq_topic`, which gathers the dead
+    /// letters of all groups of the cluster.
+    pub enable_audit_dlq: bool,
+    pub audit_dlq_topic: CheetahString,
     pub consumer_offset_update_version_step: i64,
     pub enable_broadcast_offset_store: bool,
     pub transfer_msg_by_heap: bool,
+    /// Compresses the message block of pull and pop responses for clients that accept it.
+    pub pull_response_compression_enable: bool,
+    /// `LZ4`, `ZSTD` or `ZLIB`.
+    pub pull_response_compression_type: CheetahString,
+    /// Message blocks smaller than this are sent uncompressed.
+    pub pull_response_compression_min_bytes: usize,
     pub short_polling_time_mills: u64,
     pub long_polling_enable: bool,
+    /// Calculates the bloom filter bit map of SQL92 subscriptions into the consume queue ext
+    /// when messages are dispatched, so pulls can skip messages without reading the commit log.
+    pub enable_calc_filter_bit_map: bool,
     pub max_error_rate_of_bloom_filter: i32,
     pub expect_consumer_num_use_filter: i32,
     pub bit_map_length_consume_queue_ext: i32,
     pub validate_system_topic_when_update_topic: bool,
     pub enable_mixed_message_type: bool,
+    /// Rejects sent messages whose type (normal, FIFO, delay or transaction) does not match the
+    /// `message.type` attribute of their topic, unless the topic is `MIXED`.
+    pub enable_topic_message_type_check: bool,
     pub auto_delete_unused_stats: bool,
     pub forward_timeout: u64,
     pub store_reply_message_enable: bool,
     pub lock_in_strict_mode: bool,
     pub transaction_timeout: u64,
     pub transaction_op_msg_max_size: i32,
+    /// Interval in milliseconds at which buffered commit and rollback records are written into
+    /// the transaction op topic.
+    pub transaction_op_batch_interval: u64,
+    /// Times a half message is checked back with its producer before it is discarded.
+    pub transaction_check_max: i32,
+    /// Interval in milliseconds between two scans of the half messages.
+    pub transaction_check_interval: u64,
     pub default_message_request_mode: MessageRequestMode,
     pub default_pop_share_queue_num: i32,
     pub load_balance_poll_name_server_interval: u64,
@@ -196,6 +245,7 @@ pub struct BrokerConfig {
     pub revive_max_slow: u64,
     pub revive_scan_time: u64,
     pub enable_skip_long_awaiting_ack: bool,
+    pub revive_ack_wait_ms: u64,
     pub skip_when_ck_re_put_reach_max_times: bool,
     pub compressed_register: bool,
     pub broker_not_active_timeout_millis: i64,
@@ -205,11 +255,89 @@ pub struct BrokerConfig {
     pub pop_polling_size: usize,
     pub enable_pop_message_threshold: bool,
     pub pop_inflight_message_threshold: i64,
+    pub adaptive_polling_enable: bool,
+    pub adaptive_polling_min_suspend_millis: u64,
+    pub adaptive_polling_hold_threshold: usize,
+    pub adaptive_polling_hold_limit: usize,
+    pub pop_queue_lock_wait_millis: u64,
+    pub enable_pop_group_inflight_limit: bool,
+    pub pop_group_inflight_message_limit: i64,
+    /// Read the queues of a POP request spanning the whole topic concurrently
+    pub enable_pop_readahead: bool,
+    /// Bytes the concurrent reads of one POP request may hold at once
+    pub pop_readahead_max_bytes: i64,
+    /// Keys separated by `;` which can not be changed through the update broker config request
+    pub config_black_list: CheetahString,
+    /// File that runtime config updates are persisted to, empty means the default location
+    pub broker_config_path: CheetahString,
+    pub enable_overload_shedding: bool,
+    pub overload_max_in_flight_requests: usize,
+    /// Under overload pull/pop requests are shed before send requests instead of after them
+    pub overload_shed_consume_before_send: bool,
+    pub enable_offset_rollback_protection: bool,
+    /// How far a client commit may move a queue offset backwards before it is rejected
+    pub offset_rollback_protect_window: i64,
+    /// Respond `SYSTEM_BUSY` to requests that waited too long for a processing slot
+    pub broker_fast_failure_enable: bool,
+    pub wait_time_mills_in_send_queue: u64,
+    pub wait_time_mills_in_pull_queue: u64,
+    pub wait_time_mills_in_lite_pull_queue: u64,
+    pub wait_time_mills_in_heartbeat_queue: u64,
+    pub wait_time_mills_in_transaction_queue: u64,
+    pub wait_time_mills_in_ack_queue: u64,
+    /// Number of requests of each kind processed concurrently, the others wait in its queue
+    pub send_message_thread_pool_nums: usize,
+    pub pull_message_thread_pool_nums: usize,
+    pub lite_pull_message_thread_pool_nums: usize,
+    pub heartbeat_thread_pool_nums: usize,
+    pub end_transaction_thread_pool_nums: usize,
+    pub ack_message_thread_pool_nums: usize,
+    pub pop_message_thread_pool_nums: usize,
+    pub query_message_thread_pool_nums: usize,
+    pub admin_broker_thread_pool_nums: usize,
+    pub client_manage_thread_pool_nums: usize,
+    pub consumer_manage_thread_pool_nums: usize,
+    /// Number of requests of each kind allowed to wait, the others are rejected with `SYSTEM_BUSY`
+    pub send_thread_pool_queue_capacity: usize,
+    pub pull_thread_pool_queue_capacity: usize,
+    pub lite_pull_thread_pool_queue_capacity: usize,
+    pub pop_thread_pool_queue_capacity: usize,
+    pub ack_thread_pool_queue_capacity: usize,
+    pub query_thread_pool_queue_capacity: usize,
+    pub admin_broker_thread_pool_queue_capacity: usize,
+    pub client_manager_thread_pool_queue_capacity: usize,
+    pub consumer_manager_thread_pool_queue_capacity: usize,
+    pub heartbeat_thread_pool_queue_capacity: usize,
+    pub end_transaction_thread_pool_queue_capacity: usize,
+    /// Bytes of cold (not page cached) commit log a consumer group may read per cold data check
+    /// interval before its pulls of cold data are flow controlled
+    pub cg_cold_read_threshold: i64,
+    /// Bytes of cold commit log all groups may read per check interval before any group is
+    /// flow controlled, below it the disk has room for cold reads
+    pub global_cold_read_threshold: i64,
+    /// How long a flow controlled pull of cold data is held before it is served anyway
+    pub cold_hold_timeout_millis: u64,
+    /// Write the slowest requests of every minute to `slow.log`
+    pub slow_request_log_enable: bool,
+    /// Requests faster than this are never written to the slow log
+    pub slow_request_log_threshold_millis: u64,
+    /// Max number of requests written to the slow log per minute
+    pub slow_request_log_sample_count: usize,
+    /// Slow log file, empty means `~/logs/rocketmqlogs/slow.log`
+    pub slow_request_log_path: CheetahString,
+    /// Debug aid, log every queue offset going backwards on dispatch or delivery
+    pub enable_ordering_check: bool,
+    /// Watch the toml config file the broker was started from and apply edits of the fields
+    /// that do not need a restart
+    pub enable_config_file_watch: bool,
+    pub config_file_watch_interval_millis: u64,
 }
 
 impl Default for BrokerConfig {
     fn default() -> Self {
         let broker_identity = BrokerIdentity::new();
+        let processor_number = num_cpus::get();
+        let send_message_thread_pool_nums = processor_number.min(4);
         let local_ip = local_ip_address::local_ip().unwrap();
         let broker_ip1 = local_ip.to_string().into();
         let broker_ip2 = Some(local_ip.to_string().into());
@@ -227,6 +355,8 @@ impl Default for BrokerConfig {
             msg_trace_topic_name: CheetahString::from_static_str(
                 TopicValidator::RMQ_SYS_TRACE_TOPIC,
             ),
+            broker_trace_enable: false,
+            broker_trace_queue_size: 20480,
             enable_controller_mode: false,
             broker_name: default_broker_name().into(),
             region_id: CheetahString::from_static_str(mix_all::DEFAULT_TRACE_REGION_ID),
@@ -256,6 +386,11 @@ impl Default for BrokerConfig {
             reject_transaction_message: false,
             enable_detail_stat: true,
             flush_consumer_offset_interval: 1000 * 5,
+            flush_topic_config_interval: 1000 * 60,
+            flush_subscription_group_interval: 1000 * 60,
+            flush_consumer_filter_interval: 1000 * 10,
+            flush_consumer_order_info_interval: 1000 * 10,
+            flush_message_request_mode_interval: 1000 * 60,
             force_register: true,
             register_name_server_period: 1000 * 30,
             skip_pre_online: false,
@@ -269,24 +404,39 @@ impl Default for BrokerConfig {
             filter_support_retry: false,
             use_server_side_reset_offset: true,
             slave_read_enable: false,
+            slave_read_max_dispatch_behind_bytes: 64 * 1024 * 1024,
             commercial_base_count: 1,
             reject_pull_consumer_enable: false,
+            notify_consumer_ids_changed_enable: true,
+            notify_consumer_ids_changed_min_interval_millis: 1000,
+            dlq_topic_perm: PermName::PERM_WRITE,
+            dlq_queue_nums: 1,
+            enable_audit_dlq: false,
+            audit_dlq_topic: CheetahString::from_static_str("rmq_sys_AUDIT_DLQ"),
             consumer_offset_update_version_step: 500,
             enable_broadcast_offset_store: true,
             transfer_msg_by_heap: true,
+            pull_response_compression_enable: false,
+            pull_response_compression_type: CheetahString::from_static_str("ZSTD"),
+            pull_response_compression_min_bytes: 4 * 1024,
             short_polling_time_mills: 1000,
             long_polling_enable: true,
+            enable_calc_filter_bit_map: false,
             max_error_rate_of_bloom_filter: 20,
             expect_consumer_num_use_filter: 32,
             bit_map_length_consume_queue_ext: 64,
             forward_timeout: 3 * 1000,
             validate_system_topic_when_update_topic: true,
             enable_mixed_message_type: false,
+            enable_topic_message_type_check: false,
             auto_delete_unused_stats: false,
             store_reply_message_enable: true,
             lock_in_strict_mode: false,
             transaction_timeout: 6_000,
             transaction_op_msg_max_size: 4096,
+            transaction_op_batch_interval: 3000,
+            transaction_check_max: 15,
+            transaction_check_interval: 30_000,
             default_message_request_mode: MessageRequestMode::Pull,
             default_pop_share_queue_num: -1,
             load_balance_poll_name_server_interval: 30_000,
@@ -306,6 +456,7 @@ impl Default for BrokerConfig {
             revive_max_slow: 3,
             revive_scan_time: 10_000,
             enable_skip_long_awaiting_ack: false,
+            revive_ack_wait_ms: 3 * 60 * 1000,
             skip_when_ck_re_put_reach_max_times: false,
             compressed_register: false,
             broker_not_active_timeout_millis: 10_000,
@@ -315,6 +466,62 @@ impl Default for BrokerConfig {
             pop_polling_size: 1024,
             enable_pop_message_threshold: false,
             pop_inflight_message_threshold: 10000,
+            adaptive_polling_enable: false,
+            adaptive_polling_min_suspend_millis: 1000,
+            adaptive_polling_hold_threshold: 10_000,
+            adaptive_polling_hold_limit: 100_000,
+            pop_queue_lock_wait_millis: 0,
+            enable_pop_group_inflight_limit: false,
+            pop_group_inflight_message_limit: 100_000,
+            enable_pop_readahead: false,
+            pop_readahead_max_bytes: 4 * 1024 * 1024,
+            config_black_list: CheetahString::from_static_str("configBlackList;brokerConfigPath"),
+            broker_config_path: CheetahString::empty(),
+            enable_overload_shedding: false,
+            overload_max_in_flight_requests: 10_000,
+            overload_shed_consume_before_send: false,
+            enable_offset_rollback_protection: false,
+            offset_rollback_protect_window: 0,
+            broker_fast_failure_enable: true,
+            wait_time_mills_in_send_queue: 200,
+            wait_time_mills_in_pull_queue: 5_000,
+            wait_time_mills_in_lite_pull_queue: 5_000,
+            wait_time_mills_in_heartbeat_queue: 31_000,
+            wait_time_mills_in_transaction_queue: 3_000,
+            wait_time_mills_in_ack_queue: 3_000,
+            send_message_thread_pool_nums,
+            pull_message_thread_pool_nums: 16 + processor_number * 2,
+            lite_pull_message_thread_pool_nums: 16 + processor_number * 2,
+            heartbeat_thread_pool_nums: processor_number.min(32),
+            end_transaction_thread_pool_nums: (8 + processor_number * 2)
+                .max(send_message_thread_pool_nums * 4),
+            ack_message_thread_pool_nums: 3,
+            pop_message_thread_pool_nums: 16 + processor_number * 2,
+            query_message_thread_pool_nums: 8 + processor_number,
+            admin_broker_thread_pool_nums: 16,
+            client_manage_thread_pool_nums: 32,
+            consumer_manage_thread_pool_nums: 32,
+            send_thread_pool_queue_capacity: 10_000,
+            pull_thread_pool_queue_capacity: 100_000,
+            lite_pull_thread_pool_queue_capacity: 100_000,
+            pop_thread_pool_queue_capacity: 100_000,
+            ack_thread_pool_queue_capacity: 100_000,
+            query_thread_pool_queue_capacity: 20_000,
+            admin_broker_thread_pool_queue_capacity: 10_000,
+            client_manager_thread_pool_queue_capacity: 1_000_000,
+            consumer_manager_thread_pool_queue_capacity: 1_000_000,
+            heartbeat_thread_pool_queue_capacity: 50_000,
+            end_transaction_thread_pool_queue_capacity: 100_000,
+            cg_cold_read_threshold: 3 * 1024 * 1024,
+            global_cold_read_threshold: 100 * 1024 * 1024,
+            cold_hold_timeout_millis: 3000,
+            slow_request_log_enable: false,
+            slow_request_log_threshold_millis: 1000,
+            slow_request_log_sample_count: 10,
+            slow_request_log_path: CheetahString::empty(),
+            enable_ordering_check: false,
+            enable_config_file_watch: false,
+            config_file_watch_interval_millis: 5000,
         }
     }
 }
@@ -410,6 +617,14 @@ impl BrokerConfig {
             "traceTopicEnable".into(),
             self.trace_topic_enable.to_string().into(),
         );
+        properties.insert(
+            "brokerTraceEnable".into(),
+            self.broker_trace_enable.to_string().into(),
+        );
+        properties.insert(
+            "brokerTraceQueueSize".into(),
+            self.broker_trace_queue_size.to_string().into(),
+        );
         properties.insert(
             "msgTraceTopicName".into(),
             self.msg_trace_topic_name.clone(),
@@ -498,6 +713,26 @@ impl BrokerConfig {
             "flushConsumerOffsetInterval".into(),
             self.flush_consumer_offset_interval.to_string().into(),
         );
+        properties.insert(
+            "flushTopicConfigInterval".into(),
+            self.flush_topic_config_interval.to_string().into(),
+        );
+        properties.insert(
+            "flushSubscriptionGroupInterval".into(),
+            self.flush_subscription_group_interval.to_string().into(),
+        );
+        properties.insert(
+            "flushConsumerFilterInterval".into(),
+            self.flush_consumer_filter_interval.to_string().into(),
+        );
+        properties.insert(
+            "flushConsumerOrderInfoInterval".into(),
+            self.flush_consumer_order_info_interval.to_string().into(),
+        );
+        properties.insert(
+            "flushMessageRequestModeInterval".into(),
+            self.flush_message_request_mode_interval.to_string().into(),
+        );
         properties.insert(
             "forceRegister".into(),
             self.force_register.to_string().into(),
@@ -550,6 +785,10 @@ impl BrokerConfig {
             "slaveReadEnable".into(),
             self.slave_read_enable.to_string().into(),
         );
+        properties.insert(
+            "slaveReadMaxDispatchBehindBytes".into(),
+            self.slave_read_max_dispatch_behind_bytes.to_string().into(),
+        );
         properties.insert(
             "commercialBaseCount".into(),
             self.commercial_base_count.to_string().into(),
@@ -558,6 +797,29 @@ impl BrokerConfig {
             "rejectPullConsumerEnable".into(),
             self.reject_pull_consumer_enable.to_string().into(),
         );
+        properties.insert(
+            "notifyConsumerIdsChangedEnable".into(),
+            self.notify_consumer_ids_changed_enable.to_string().into(),
+        );
+        properties.insert(
+            "notifyConsumerIdsChangedMinIntervalMillis".into(),
+            self.notify_consumer_ids_changed_min_interval_millis
+                .to_string()
+                .into(),
+        );
+        properties.insert(
+            "dlqTopicPerm".into(),
+            self.dlq_topic_perm.to_string().into(),
+        );
+        properties.insert(
+            "dlqQueueNums".into(),
+            self.dlq_queue_nums.to_string().into(),
+        );
+        properties.insert(
+            "enableAuditDlq".into(),
+            self.enable_audit_dlq.to_string().into(),
+        );
+        properties.insert("auditDlqTopic".into(), self.audit_dlq_topic.clone());
         properties.insert(
             "consumerOffsetUpdateVersionStep".into(),
             self.consumer_offset_update_version_step.to_string().into(),
@@ -570,6 +832,18 @@ impl BrokerConfig {
             "transferMsgByHeap".into(),
             self.transfer_msg_by_heap.to_string().into(),
         );
+        properties.insert(
+            "pullResponseCompressionEnable".into(),
+            self.pull_response_compression_enable.to_string().into(),
+        );
+        properties.insert(
+            "pullResponseCompressionType".into(),
+            self.pull_response_compression_type.clone(),
+        );
+        properties.insert(
+            "pullResponseCompressionMinBytes".into(),
+            self.pull_response_compression_min_bytes.to_string().into(),
+        );
         properties.insert(
             "shortPollingTimeMills".into(),
             self.short_polling_time_mills.to_string().into(),
@@ -578,6 +852,10 @@ impl BrokerConfig {
             "longPollingEnable".into(),
             self.long_polling_enable.to_string().into(),
         );
+        properties.insert(
+            "enableCalcFilterBitMap".into(),
+            self.enable_calc_filter_bit_map.to_string().into(),
+        );
         properties.insert(
             "maxErrorRateOfBloomFilter".into(),
             self.max_error_rate_of_bloom_filter.to_string().into(),
@@ -600,6 +878,10 @@ impl BrokerConfig {
             "enableMixedMessageType".into(),
             self.enable_mixed_message_type.to_string().into(),
         );
+        properties.insert(
+            "enableTopicMessageTypeCheck".into(),
+            self.enable_topic_message_type_check.to_string().into(),
+        );
         properties.insert(
             "autoDeleteUnusedStats".into(),
             self.auto_delete_unused_stats.to_string().into(),
@@ -608,10 +890,321 @@ impl BrokerConfig {
             "forwardTimeout".into(),
             self.forward_timeout.to_string().into(),
         );
+        properties.insert(
+            "adaptivePollingEnable".into(),
+            self.adaptive_polling_enable.to_string().into(),
+        );
+        properties.insert(
+            "adaptivePollingMinSuspendMillis".into(),
+            self.adaptive_polling_min_suspend_millis.to_string().into(),
+        );
+        properties.insert(
+            "adaptivePollingHoldThreshold".into(),
+            self.adaptive_polling_hold_threshold.to_string().into(),
+        );
+        properties.insert(
+            "adaptivePollingHoldLimit".into(),
+            self.adaptive_polling_hold_limit.to_string().into(),
+        );
+        properties.insert(
+            "popQueueLockWaitMillis".into(),
+            self.pop_queue_lock_wait_millis.to_string().into(),
+        );
+        properties.insert(
+            "enablePopGroupInflightLimit".into(),
+            self.enable_pop_group_inflight_limit.to_string().into(),
+        );
+        properties.insert(
+            "popGroupInflightMessageLimit".into(),
+            self.pop_group_inflight_message_limit.to_string().into(),
+        );
+        properties.insert(
+            "enablePopReadahead".into(),
+            self.enable_pop_readahead.to_string().into(),
+        );
+        properties.insert(
+            "popReadaheadMaxBytes".into(),
+            self.pop_readahead_max_bytes.to_string().into(),
+        );
+        properties.insert("configBlackList".into(), self.config_black_list.clone());
+        properties.insert("brokerConfigPath".into(), self.broker_config_path.clone());
+        properties.insert(
+            "enableOverloadShedding".into(),
+            self.enable_overload_shedding.to_string().into(),
+        );
+        properties.insert(
+            "overloadMaxInFlightRequests".into(),
+            self.overload_max_in_flight_requests.to_string().into(),
+        );
+        properties.insert(
+            "overloadShedConsumeBeforeSend".into(),
+            self.overload_shed_consume_before_send.to_string().into(),
+        );
+        properties.insert(
+            "enableOffsetRollbackProtection".into(),
+            self.enable_offset_rollback_protection.to_string().into(),
+        );
+        properties.insert(
+            "offsetRollbackProtectWindow".into(),
+            self.offset_rollback_protect_window.to_string().into(),
+        );
+        properties.insert(
+            "brokerFastFailureEnable".into(),
+            self.broker_fast_failure_enable.to_string().into(),
+        );
+        properties.insert(
+            "waitTimeMillsInSendQueue".into(),
+            self.wait_time_mills_in_send_queue.to_string().into(),
+        );
+        properties.insert(
+            "waitTimeMillsInPullQueue".into(),
+            self.wait_time_mills_in_pull_queue.to_string().into(),
+        );
+        properties.insert(
+            "waitTimeMillsInLitePullQueue".into(),
+            self.wait_time_mills_in_lite_pull_queue.to_string().into(),
+        );
+        properties.insert(
+            "waitTimeMillsInHeartbeatQueue".into(),
+            self.wait_time_mills_in_heartbeat_queue.to_string().into(),
+        );
+        properties.insert(
+            "waitTimeMillsInTransactionQueue".into(),
+            self.wait_time_mills_in_transaction_queue.to_string().into(),
+        );
+        properties.insert(
+            "waitTimeMillsInAckQueue".into(),
+            self.wait_time_mills_in_ack_queue.to_string().into(),
+        );
+        properties.insert(
+            "sendMessageThreadPoolNums".into(),
+            self.send_message_thread_pool_nums.to_string().into(),
+        );
+        properties.insert(
+            "pullMessageThreadPoolNums".into(),
+            self.pull_message_thread_pool_nums.to_string().into(),
+        );
+        properties.insert(
+            "litePullMessageThreadPoolNums".into(),
+            self.lite_pull_message_thread_pool_nums.to_string().into(),
+        );
+        properties.insert(
+            "heartbeatThreadPoolNums".into(),
+            self.heartbeat_thread_pool_nums.to_string().into(),
+        );
+        properties.insert(
+            "endTransactionThreadPoolNums".into(),
+            self.end_transaction_thread_pool_nums.to_string().into(),
+        );
+        properties.insert(
+            "ackMessageThreadPoolNums".into(),
+            self.ack_message_thread_pool_nums.to_string().into(),
+        );
+        properties.insert(
+            "popMessageThreadPoolNums".into(),
+            self.pop_message_thread_pool_nums.to_string().into(),
+        );
+        properties.insert(
+            "queryMessageThreadPoolNums".into(),
+            self.query_message_thread_pool_nums.to_string().into(),
+        );
+        properties.insert(
+            "adminBrokerThreadPoolNums".into(),
+            self.admin_broker_thread_pool_nums.to_string().into(),
+        );
+        properties.insert(
+            "clientManageThreadPoolNums".into(),
+            self.client_manage_thread_pool_nums.to_string().into(),
+        );
+        properties.insert(
+            "consumerManageThreadPoolNums".into(),
+            self.consumer_manage_thread_pool_nums.to_string().into(),
+        );
+        properties.insert(
+            "sendThreadPoolQueueCapacity".into(),
+            self.send_thread_pool_queue_capacity.to_string().into(),
+        );
+        properties.insert(
+            "pullThreadPoolQueueCapacity".into(),
+            self.pull_thread_pool_queue_capacity.to_string().into(),
+        );
+        properties.insert(
+            "litePullThreadPoolQueueCapacity".into(),
+            self.lite_pull_thread_pool_queue_capacity.to_string().into(),
+        );
+        properties.insert(
+            "popThreadPoolQueueCapacity".into(),
+            self.pop_thread_pool_queue_capacity.to_string().into(),
+        );
+        properties.insert(
+            "ackThreadPoolQueueCapacity".into(),
+            self.ack_thread_pool_queue_capacity.to_string().into(),
+        );
+        properties.insert(
+            "queryThreadPoolQueueCapacity".into(),
+            self.query_thread_pool_queue_capacity.to_string().into(),
+        );
+        properties.insert(
+            "adminBrokerThreadPoolQueueCapacity".into(),
+            self.admin_broker_thread_pool_queue_capacity
+                .to_string()
+                .into(),
+        );
+        properties.insert(
+            "clientManagerThreadPoolQueueCapacity".into(),
+            self.client_manager_thread_pool_queue_capacity
+                .to_string()
+                .into(),
+        );
+        properties.insert(
+            "consumerManagerThreadPoolQueueCapacity".into(),
+            self.consumer_manager_thread_pool_queue_capacity
+                .to_string()
+                .into(),
+        );
+        properties.insert(
+            "heartbeatThreadPoolQueueCapacity".into(),
+            self.heartbeat_thread_pool_queue_capacity.to_string().into(),
+        );
+        properties.insert(
+            "endTransactionThreadPoolQueueCapacity".into(),
+            self.end_transaction_thread_pool_queue_capacity
+                .to_string()
+                .into(),
+        );
+        properties.insert(
+            "transactionOpBatchInterval".into(),
+            self.transaction_op_batch_interval.to_string().into(),
+        );
+        properties.insert(
+            "transactionCheckMax".into(),
+            self.transaction_check_max.to_string().into(),
+        );
+        properties.insert(
+            "transactionCheckInterval".into(),
+            self.transaction_check_interval.to_string().into(),
+        );
+        properties.insert(
+            "cgColdReadThreshold".into(),
+            self.cg_cold_read_threshold.to_string().into(),
+        );
+        properties.insert(
+            "globalColdReadThreshold".into(),
+            self.global_cold_read_threshold.to_string().into(),
+        );
+        properties.insert(
+            "coldHoldTimeoutMillis".into(),
+            self.cold_hold_timeout_millis.to_string().into(),
+        );
+        properties.insert(
+            "slowRequestLogEnable".into(),
+            self.slow_request_log_enable.to_string().into(),
+        );
+        properties.insert(
+            "slowRequestLogThresholdMillis".into(),
+            self.slow_request_log_threshold_millis.to_string().into(),
+        );
+        properties.insert(
+            "slowRequestLogSampleCount".into(),
+            self.slow_request_log_sample_count.to_string().into(),
+        );
+        properties.insert(
+            "slowRequestLogPath".into(),
+            self.slow_request_log_path.clone(),
+        );
+        properties.insert(
+            "enableOrderingCheck".into(),
+            self.enable_ordering_check.to_string().into(),
+        );
+        properties.insert(
+            "enableConfigFileWatch".into(),
+            self.enable_config_file_watch.to_string().into(),
+        );
+        properties.insert(
+            "configFileWatchIntervalMillis".into(),
+            self.config_file_watch_interval_millis.to_string().into(),
+        );
         properties
     }
+
+    pub fn get_config_blacklist(&self) -> Vec<CheetahString> {
+        self.config_black_list
+            .split(';')
+            .map(str::trim)
+            .filter(|key| !key.is_empty())
+            .map(CheetahString::from)
+            .collect()
+    }
+
+    /// Whether a change of `key` only takes effect once the broker is restarted, because the value
+    /// was consumed while starting the broker (listeners, identity, store layout, thread pools).
+    pub fn requires_restart(key: &str) -> bool {
+        RESTART_REQUIRED_KEYS.contains(&key)
+    }
+
+    /// Applies `properties` (camelCase keys as returned by [`BrokerConfig::get_properties`]).
+    ///
+    /// Either every property is applied or, if a key is unknown or a value can not be parsed,
+    /// none is and the offending key is reported.
+    pub fn update(
+        &mut self,
+        properties: &HashMap<CheetahString, CheetahString>,
+    ) -> Result<(), String> {
+        let mut value = serde_json::to_value(&*self).map_err(|e| e.to_string())?;
+        for (key, raw) in properties {
+            if !set_config_value(&mut value, key.as_str(), raw.as_str())? {
+                return Err(format!("unknown broker config key '{}'", key));
+            }
+        }
+        *self =
+            serde_json::from_value(value).map_err(|e| format!("invalid broker config: {}", e))?;
+        Ok(())
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        serde_json::to_value(self)
+            .map(|value| find_config_value(&value, key).is_some())
+            .unwrap_or(false)
+    }
 }
 
+const RESTART_REQUIRED_KEYS: &[&str] = &[
+    "brokerName",
+    "brokerClusterName",
+    "brokerId",
+    "isBrokerContainer",
+    "isInBrokerContainer",
+    "bindAddress",
+    "brokerIp1",
+    "brokerIp2",
+    "listenPort",
+    "storePathRootDir",
+    "enableControllerMode",
+    "timerWheelEnable",
+    "reviveQueueNum",
+    "recoverConcurrently",
+    "duplicationEnable",
+    "enableSlaveActingMaster",
+    "sendMessageThreadPoolNums",
+    "pullMessageThreadPoolNums",
+    "litePullMessageThreadPoolNums",
+    "heartbeatThreadPoolNums",
+    "endTransactionThreadPoolNums",
+    "ackMessageThreadPoolNums",
+    "popMessageThreadPoolNums",
+    "queryMessageThreadPoolNums",
+    "adminBrokerThreadPoolNums",
+    "clientManageThreadPoolNums",
+    "consumerManageThreadPoolNums",
+    "brokerTraceEnable",
+    "brokerTraceQueueSize",
+    "enableOrderingCheck",
+    "enableConfigFileWatch",
+    "notifyConsumerIdsChangedEnable",
+    "notifyConsumerIdsChangedMinIntervalMillis",
+    "configFileWatchIntervalMillis",
+];
+
 pub fn default_broker_name() -> String {
     LOCAL_HOST_NAME
         .clone()
@@ -637,3 +1230,62 @@ impl Default for TopicQueueConfig {
 pub struct TimerWheelConfig {
     pub timer_wheel_enable: bool,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn properties(pairs: &[(&str, &str)]) -> HashMap<CheetahString, CheetahString> {
+        pairs
+            .iter()
+            .map(|(key, value)| (CheetahString::from(*key), CheetahString::from(*value)))
+            .collect()
+    }
+
+    #[test]
+    fn update_applies_top_level_and_nested_keys() {
+        let mut config = BrokerConfig::default();
+        config
+            .update(&properties(&[
+                ("popPollingSize", "2048"),
+                ("autoCreateTopicEnable", "false"),
+                ("defaultTopicQueueNums", "16"),
+                ("msgTraceTopicName", "TRACE_TOPIC"),
+            ]))
+            .unwrap();
+        assert_eq!(config.pop_polling_size, 2048);
+        assert!(!config.auto_create_topic_enable);
+        assert_eq!(config.topic_queue_config.default_topic_queue_nums, 16);
+        assert_eq!(config.msg_trace_topic_name, "TRACE_TOPIC");
+    }
+
+    #[test]
+    fn update_is_all_or_nothing() {
+        let mut config = BrokerConfig::default();
+        let before = config.pop_polling_size;
+        assert!(config
+            .update(&properties(&[
+                ("popPollingSize", "1"),
+                ("autoCreateTopicEnable", "maybe"),
+            ]))
+            .is_err());
+        assert_eq!(config.pop_polling_size, before);
+        assert!(config.update(&properties(&[("noSuchKey", "1")])).is_err());
+    }
+
+    #[test]
+    fn blacklist_and_restart_keys() {
+        let config = BrokerConfig::default();
+        assert_eq!(
+            config.get_config_blacklist(),
+            vec![
+                CheetahString::from("configBlackList"),
+                CheetahString::from("brokerConfigPath")
+            ]
+        );
+        assert!(BrokerConfig::requires_restart("listenPort"));
+        assert!(!BrokerConfig::requires_restart("popPollingSize"));
+        assert!(config.contains_key("defaultTopicQueueNums"));
+        assert!(!config.contains_key("noSuchKey"));
+    }
+}
@@ -0,0 +1,100 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::sync::Arc;
+
+use parking_lot::RwLock;
+
+use crate::common::broker::broker_config::BrokerConfig;
+
+/// Broker config shared by the components of one broker.
+///
+/// Readers take a snapshot with [`SharedBrokerConfig::get`], an update is applied to a copy and
+/// then published to every holder of the handle, so a reader never sees it half applied.
+#[derive(Clone, Default)]
+pub struct SharedBrokerConfig {
+    inner: Arc<RwLock<Arc<BrokerConfig>>>,
+}
+
+impl SharedBrokerConfig {
+    pub fn new(broker_config: BrokerConfig) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(Arc::new(broker_config))),
+        }
+    }
+
+    /// The current config.
+    #[inline]
+    pub fn get(&self) -> Arc<BrokerConfig> {
+        self.inner.read().clone()
+    }
+
+    /// Applies `update` to a copy of the current config and publishes the copy unless `update`
+    /// fails. Concurrent updates are applied one after the other.
+    pub fn update<T, E>(
+        &self,
+        update: impl FnOnce(&mut BrokerConfig) -> Result<T, E>,
+    ) -> Result<T, E> {
+        let mut current = self.inner.write();
+        let mut broker_config = BrokerConfig::clone(&current);
+        let result = update(&mut broker_config)?;
+        *current = Arc::new(broker_config);
+        Ok(result)
+    }
+
+    /// [`SharedBrokerConfig::update`] for a change that can not fail.
+    pub fn modify<T>(&self, modify: impl FnOnce(&mut BrokerConfig) -> T) -> T {
+        let mut current = self.inner.write();
+        let mut broker_config = BrokerConfig::clone(&current);
+        let result = modify(&mut broker_config);
+        *current = Arc::new(broker_config);
+        result
+    }
+}
+
+impl From<BrokerConfig> for SharedBrokerConfig {
+    fn from(broker_config: BrokerConfig) -> Self {
+        Self::new(broker_config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn updates_are_seen_by_every_holder() {
+        let shared = SharedBrokerConfig::new(BrokerConfig::default());
+        let other = shared.clone();
+        let before = shared.get();
+
+        other.modify(|broker_config| broker_config.flush_consumer_offset_interval = 1000);
+        assert_eq!(shared.get().flush_consumer_offset_interval, 1000);
+        // a snapshot taken earlier is left alone
+        assert_ne!(before.flush_consumer_offset_interval, 1000);
+    }
+
+    #[test]
+    fn failed_update_is_not_published() {
+        let shared = SharedBrokerConfig::new(BrokerConfig::default());
+        let result: Result<(), &str> = shared.update(|broker_config| {
+            broker_config.flush_consumer_offset_interval = 1000;
+            Err("rejected")
+        });
+        assert_eq!(result, Err("rejected"));
+        assert_ne!(shared.get().flush_consumer_offset_interval, 1000);
+    }
+}
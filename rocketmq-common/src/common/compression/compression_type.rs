@@ -41,6 +41,14 @@ impl CompressionType {
         }
     }
 
+    pub fn get_value(&self) -> i32 {
+        match self {
+            Self::LZ4 => 1,
+            Self::Zstd => 2,
+            Self::Zlib => 3,
+        }
+    }
+
     pub fn get_compression_flag(&self) -> i32 {
         match self {
             Self::LZ4 => MessageSysFlag::COMPRESSION_LZ4_TYPE,
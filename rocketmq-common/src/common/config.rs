@@ -26,6 +26,7 @@ use super::TopicFilterType;
 use crate::common::attribute::topic_message_type::TopicMessageType;
 use crate::common::constant::PermName;
 use crate::TopicAttributes::TOPIC_MESSAGE_TYPE_ATTRIBUTE;
+use crate::TopicAttributes::TOPIC_RESERVE_TIME_ATTRIBUTE;
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 #[serde(rename_all = "camelCase")]
@@ -89,6 +90,14 @@ impl TopicConfig {
         TopicMessageType::Normal
     }
 
+    /// Hours the messages of the topic are kept, -1 when the topic keeps the broker default.
+    pub fn get_reserve_time(&self) -> i64 {
+        self.attributes
+            .get(TOPIC_RESERVE_TIME_ATTRIBUTE.get_name())
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(TOPIC_RESERVE_TIME_ATTRIBUTE.get_default_value())
+    }
+
     pub fn new(topic_name: impl Into<CheetahString>) -> Self {
         TopicConfig {
             topic_name: Some(topic_name.into()),
@@ -299,4 +308,15 @@ mod tests {
         );
         assert_eq!(config.get_topic_message_type(), TopicMessageType::Normal);
     }
+
+    #[test]
+    fn get_reserve_time_from_attributes() {
+        let mut config = TopicConfig::default();
+        assert_eq!(config.get_reserve_time(), -1);
+        config.attributes.insert(
+            CheetahString::from(TOPIC_RESERVE_TIME_ATTRIBUTE.get_name()),
+            CheetahString::from("72"),
+        );
+        assert_eq!(config.get_reserve_time(), 72);
+    }
 }
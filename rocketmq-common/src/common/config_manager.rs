@@ -25,6 +25,14 @@ use crate::FileUtils;
 
 // Define the trait ConfigManager
 pub trait ConfigManager {
+    /// Loads the configuration, from the JSON file by default.
+    ///
+    /// Managers keeping their configuration elsewhere override it and fall back to
+    /// [`ConfigManager::load_json`].
+    fn load(&self) -> bool {
+        self.load_json()
+    }
+
     /// Loads the configuration from a file.
     ///
     /// This method attempts to load the configuration from a file whose path is returned by
@@ -35,7 +43,7 @@ pub trait ConfigManager {
     /// # Returns
     /// * `true` if the configuration is successfully loaded and decoded.
     /// * `false` if the configuration loading fails.
-    fn load(&self) -> bool {
+    fn load_json(&self) -> bool {
         let file_name = self.config_file_path();
         let result = FileUtils::file_to_string(file_name.as_str());
         match result {
@@ -94,12 +102,17 @@ pub trait ConfigManager {
         self.persist()
     }
 
-    /// Persists the configuration.
+    /// Persists the configuration, to the JSON file by default.
+    fn persist(&self) {
+        self.persist_json()
+    }
+
+    /// Persists the configuration to the JSON file.
     ///
     /// This method persists the configuration to a file whose path is returned by
     /// `config_file_path`. If the encoded configuration is not empty, it writes the
     /// configuration to the file.
-    fn persist(&self) {
+    fn persist_json(&self) {
         let json = self.encode_pretty(true);
         if !json.is_empty() {
             let file_name = self.config_file_path();
@@ -117,6 +117,56 @@ pub fn str_to_message_properties(
     map
 }
 
+/// Reads the properties of a message stored in the commit log without decoding the rest of it,
+/// `None` when the message has no properties or `buffer` is truncated.
+pub fn decode_properties(buffer: &[u8]) -> Option<HashMap<CheetahString, CheetahString>> {
+    let read_i32 = |index: usize| {
+        buffer
+            .get(index..index + 4)
+            .map(|bytes| i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    };
+    let sys_flag = read_i32(SYSFLAG_POSITION)?;
+    let version =
+        MessageVersion::value_of_magic_code(read_i32(MESSAGE_MAGIC_CODE_POSITION)?).ok()?;
+    let born_host_length = if sys_flag & MessageSysFlag::BORNHOST_V6_FLAG == 0 {
+        8
+    } else {
+        20
+    };
+    let store_host_length = if sys_flag & MessageSysFlag::STOREHOSTADDRESS_V6_FLAG == 0 {
+        8
+    } else {
+        20
+    };
+    let body_size_position = SYSFLAG_POSITION
+        + 4 // SYSFLAG
+        + 8 // BORNTIMESTAMP
+        + born_host_length
+        + 8 // STORETIMESTAMP
+        + store_host_length
+        + 4 // RECONSUMETIMES
+        + 8; // Prepared Transaction Offset
+    let body_size = read_i32(body_size_position)?.max(0) as usize;
+    let topic_length_position = body_size_position + 4 + body_size;
+    if topic_length_position + version.get_topic_length_size() > buffer.len() {
+        return None;
+    }
+    let topic_length = version.get_topic_length_at_index(buffer, topic_length_position);
+    let properties_position =
+        topic_length_position + version.get_topic_length_size() + topic_length;
+    let properties_length = buffer
+        .get(properties_position..properties_position + 2)
+        .map(|bytes| i16::from_be_bytes([bytes[0], bytes[1]]))?;
+    if properties_length <= 0 {
+        return None;
+    }
+    let properties = buffer
+        .get(properties_position + 2..properties_position + 2 + properties_length as usize)?;
+    Some(str_to_message_properties(Some(&String::from_utf8_lossy(
+        properties,
+    ))))
+}
+
 pub fn message_properties_to_string(
     properties: &HashMap<CheetahString, CheetahString>,
 ) -> CheetahString {
@@ -312,18 +362,7 @@ pub fn decode(
             }
             msg_ext.message.body = Some(body_bytes);
         } else {
-            let _ = byte_buffer.split_to(
-                BORN_TIMESTAMP_POSITION
-                    + born_host_ip_length
-                    + 4
-                    + 8
-                    + store_host_ip_length
-                    + 4
-                    + 4
-                    + 8
-                    + 4
-                    + body_len as usize,
-            );
+            byte_buffer.advance(body_len as usize);
         }
     }
 
@@ -675,7 +714,7 @@ pub fn encode(message_ext: &MessageExt, need_compress: bool) -> Result<Bytes> {
     }
 
     // 16 TOPIC
-    byte_buffer.put_i16(topic_len as i16);
+    byte_buffer.put_u8(topic_len as u8);
     byte_buffer.put_slice(topics);
 
     // 17 properties
@@ -782,7 +821,7 @@ pub fn encode_uniquely(message_ext: &MessageExt, need_compress: bool) -> Result<
     }
 
     // 14 TOPIC
-    byte_buffer.put_i16(topic_len as i16);
+    byte_buffer.put_u8(topic_len as u8);
     byte_buffer.put_slice(topics);
 
     // 15 properties
@@ -853,6 +892,41 @@ mod tests {
         assert_eq!(message_id.offset, 860316681131967304);
     }
 
+    #[test]
+    fn decode_properties_of_stored_message() {
+        let properties = format!("a{}1{}", NAME_VALUE_SEPARATOR, PROPERTY_SEPARATOR);
+        let mut bytes = BytesMut::new();
+        bytes.put_i32(0);
+        bytes.put_i32(MESSAGE_MAGIC_CODE_V2);
+        bytes.put_bytes(0, SYSFLAG_POSITION - 8);
+        bytes.put_i32(0);
+        bytes.put_bytes(0, 8 + 8 + 8 + 8 + 4 + 8);
+        bytes.put_i32(4);
+        bytes.put_slice(b"body");
+        bytes.put_i16(5);
+        bytes.put_slice(b"topic");
+        bytes.put_i16(properties.len() as i16);
+        bytes.put_slice(properties.as_bytes());
+
+        let decoded = decode_properties(&bytes).unwrap();
+        assert_eq!(decoded.get("a").map(|v| v.as_str()), Some("1"));
+        assert!(decode_properties(&bytes[..bytes.len() - 1]).is_none());
+    }
+
+    #[test]
+    fn decode_without_body_keeps_topic_and_properties() {
+        let mut message_ext = MessageExt::default();
+        message_ext.set_topic(CheetahString::from_static_str("topic"));
+        message_ext.set_body(Bytes::from("Hello, World!"));
+        message_ext.set_keys(CheetahString::from_static_str("key"));
+        let mut bytes = encode(&message_ext, false).unwrap();
+
+        let decoded = decode(&mut bytes, false, false, false, false, false).unwrap();
+        assert!(decoded.get_body().is_none());
+        assert_eq!(decoded.get_topic().as_str(), "topic");
+        assert_eq!(decoded.get_keys().unwrap().as_str(), "key");
+    }
+
     #[test]
     fn encode_with_compression() {
         let mut message_ext = MessageExt::default();
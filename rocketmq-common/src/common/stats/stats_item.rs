@@ -31,8 +31,8 @@ use crate::common::stats::call_snapshot::CallSnapshot;
 use crate::common::stats::stats_snapshot::StatsSnapshot;
 
 pub struct StatsItem {
-    value: AtomicU64,
-    times: AtomicU64,
+    value: Arc<AtomicU64>,
+    times: Arc<AtomicU64>,
     cs_list_minute: Arc<Mutex<LinkedList<CallSnapshot>>>,
     cs_list_hour: Arc<Mutex<LinkedList<CallSnapshot>>>,
     cs_list_day: Arc<Mutex<LinkedList<CallSnapshot>>>,
@@ -43,8 +43,8 @@ pub struct StatsItem {
 impl StatsItem {
     pub fn new(stats_name: &str, stats_key: &str) -> Self {
         StatsItem {
-            value: AtomicU64::new(0),
-            times: AtomicU64::new(0),
+            value: Arc::new(AtomicU64::new(0)),
+            times: Arc::new(AtomicU64::new(0)),
             cs_list_minute: Arc::new(Mutex::new(LinkedList::new())),
             cs_list_hour: Arc::new(Mutex::new(LinkedList::new())),
             cs_list_day: Arc::new(Mutex::new(LinkedList::new())),
@@ -53,15 +53,41 @@ impl StatsItem {
         }
     }
 
+    pub fn add_value(&self, inc_value: u64, inc_times: u64) {
+        self.value.fetch_add(inc_value, Ordering::Relaxed);
+        self.times.fetch_add(inc_times, Ordering::Relaxed);
+    }
+
+    pub fn get_value(&self) -> u64 {
+        self.value.load(Ordering::Relaxed)
+    }
+
+    pub fn get_times(&self) -> u64 {
+        self.times.load(Ordering::Relaxed)
+    }
+
+    pub fn get_stats_name(&self) -> &str {
+        &self.stats_name
+    }
+
+    pub fn get_stats_key(&self) -> &str {
+        &self.stats_key
+    }
+
     pub fn compute_stats_data(cs_list: Arc<Mutex<LinkedList<CallSnapshot>>>) -> StatsSnapshot {
         let mut stats_snapshot = StatsSnapshot::new();
         let cs_list = cs_list.lock();
         if !cs_list.is_empty() {
             let first = cs_list.front().unwrap();
             let last = cs_list.back().unwrap();
-            let sum = last.get_value() - first.get_value();
-            let tps = (sum as f64 * 1000.0) / (last.get_timestamp() - first.get_timestamp()) as f64;
-            let times_diff = last.get_times() - first.get_times();
+            let sum = last.get_value().saturating_sub(first.get_value());
+            let time_diff = last.get_timestamp().saturating_sub(first.get_timestamp());
+            let tps = if time_diff > 0 {
+                (sum as f64 * 1000.0) / time_diff as f64
+            } else {
+                0.0
+            };
+            let times_diff = last.get_times().saturating_sub(first.get_times());
             let avgpt = if times_diff > 0 {
                 sum as f64 / times_diff as f64
             } else {
@@ -97,19 +123,22 @@ impl StatsItem {
         let stats_name = self.stats_name.clone();
         let stats_key = self.stats_key.clone();
 
+        let (value, times) = (Arc::clone(&self.value), Arc::clone(&self.times));
         thread::spawn(move || loop {
             thread::sleep(Duration::from_secs(10));
-            Self::sampling_in_seconds(cs_list_minute.clone());
+            Self::sampling(&cs_list_minute, &value, &times, 10 * 1000, 7);
         });
 
+        let (value, times) = (Arc::clone(&self.value), Arc::clone(&self.times));
         thread::spawn(move || loop {
             thread::sleep(Duration::from_secs(600));
-            Self::sampling_in_minutes(cs_list_hour.clone());
+            Self::sampling(&cs_list_hour, &value, &times, 10 * 60 * 1000, 7);
         });
 
+        let (value, times) = (Arc::clone(&self.value), Arc::clone(&self.times));
         thread::spawn(move || loop {
             thread::sleep(Duration::from_secs(3600));
-            Self::sampling_in_hour(cs_list_day.clone());
+            Self::sampling(&cs_list_day, &value, &times, 60 * 60 * 1000, 25);
         });
 
         let stats_name_clone = stats_name.clone();
@@ -156,80 +185,54 @@ impl StatsItem {
         });
     }
 
-    pub fn sampling_in_seconds(cs_list: Arc<Mutex<LinkedList<CallSnapshot>>>) {
-        let mut cs_list = cs_list.lock();
-        if cs_list.is_empty() {
-            cs_list.push_back(CallSnapshot::new(
-                SystemTime::now()
-                    .duration_since(SystemTime::UNIX_EPOCH)
-                    .unwrap()
-                    .as_millis() as u64
-                    - 10 * 1000,
-                0,
-                0,
-            ));
-        }
-        cs_list.push_back(CallSnapshot::new(
-            SystemTime::now()
-                .duration_since(SystemTime::UNIX_EPOCH)
-                .unwrap()
-                .as_millis() as u64,
-            0,
-            0,
-        ));
-        if cs_list.len() > 7 {
-            cs_list.pop_front();
-        }
+    /// Records the current value into the one minute window, called every ten seconds.
+    pub fn sampling_in_seconds(&self) {
+        Self::sampling(&self.cs_list_minute, &self.value, &self.times, 10 * 1000, 7);
     }
 
-    pub fn sampling_in_minutes(cs_list: Arc<Mutex<LinkedList<CallSnapshot>>>) {
-        let mut cs_list = cs_list.lock();
-        if cs_list.is_empty() {
-            cs_list.push_back(CallSnapshot::new(
-                SystemTime::now()
-                    .duration_since(SystemTime::UNIX_EPOCH)
-                    .unwrap()
-                    .as_millis() as u64
-                    - 10 * 60 * 1000,
-                0,
-                0,
-            ));
-        }
-        cs_list.push_back(CallSnapshot::new(
-            SystemTime::now()
-                .duration_since(SystemTime::UNIX_EPOCH)
-                .unwrap()
-                .as_millis() as u64,
-            0,
-            0,
-        ));
-        if cs_list.len() > 7 {
-            cs_list.pop_front();
-        }
+    /// Records the current value into the one hour window, called every ten minutes.
+    pub fn sampling_in_minutes(&self) {
+        Self::sampling(
+            &self.cs_list_hour,
+            &self.value,
+            &self.times,
+            10 * 60 * 1000,
+            7,
+        );
+    }
+
+    /// Records the current value into the one day window, called every hour.
+    pub fn sampling_in_hour(&self) {
+        Self::sampling(
+            &self.cs_list_day,
+            &self.value,
+            &self.times,
+            60 * 60 * 1000,
+            25,
+        );
     }
 
-    pub fn sampling_in_hour(cs_list: Arc<Mutex<LinkedList<CallSnapshot>>>) {
+    fn sampling(
+        cs_list: &Mutex<LinkedList<CallSnapshot>>,
+        value: &AtomicU64,
+        times: &AtomicU64,
+        interval_millis: u64,
+        max_len: usize,
+    ) {
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
         let mut cs_list = cs_list.lock();
         if cs_list.is_empty() {
-            cs_list.push_back(CallSnapshot::new(
-                SystemTime::now()
-                    .duration_since(SystemTime::UNIX_EPOCH)
-                    .unwrap()
-                    .as_millis() as u64
-                    - 60 * 60 * 1000,
-                0,
-                0,
-            ));
+            cs_list.push_back(CallSnapshot::new(now.saturating_sub(interval_millis), 0, 0));
         }
         cs_list.push_back(CallSnapshot::new(
-            SystemTime::now()
-                .duration_since(SystemTime::UNIX_EPOCH)
-                .unwrap()
-                .as_millis() as u64,
-            0,
-            0,
+            now,
+            times.load(Ordering::Relaxed),
+            value.load(Ordering::Relaxed),
         ));
-        if cs_list.len() > 25 {
+        if cs_list.len() > max_len {
             cs_list.pop_front();
         }
     }
@@ -355,6 +358,18 @@ mod tests {
         assert_eq!(snapshot.get_avgpt(), 10.0);
     }
 
+    #[test]
+    fn sampling_records_added_values() {
+        let stats_item = StatsItem::new("TestName", "TestKey");
+        stats_item.add_value(300, 3);
+        stats_item.sampling_in_seconds();
+        let snapshot = stats_item.get_stats_data_in_minute();
+        assert_eq!(snapshot.get_sum(), 300);
+        assert_eq!(snapshot.get_times(), 3);
+        assert_eq!(snapshot.get_avgpt(), 100.0);
+        assert!(snapshot.get_tps() > 0.0);
+    }
+
     #[test]
     fn get_stats_data_in_minute_returns_correct_snapshot() {
         let stats_item = StatsItem::new("TestName", "TestKey");
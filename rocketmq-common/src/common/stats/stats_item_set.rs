@@ -14,17 +14,153 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::sync::Arc;
+
+use dashmap::DashMap;
+
+use crate::common::stats::stats_item::StatsItem;
 use crate::common::stats::stats_snapshot::StatsSnapshot;
 
+/// Group of [`StatsItem`]s sharing a stats name, keyed by e.g. topic or `topic@group`.
+///
+/// Items are created on first use. The owner is expected to call the `sampling_*` methods on the
+/// same schedule as [`StatsItem`] (every ten seconds, ten minutes and hour).
 #[derive(Debug)]
-pub struct StatsItemSet {}
+pub struct StatsItemSet {
+    stats_item_table: DashMap<String, Arc<StatsItem>>,
+    stats_name: String,
+}
 
 impl StatsItemSet {
-    pub fn new(_stats_name: String) -> Self {
-        StatsItemSet {}
+    pub fn new(stats_name: String) -> Self {
+        StatsItemSet {
+            stats_item_table: DashMap::new(),
+            stats_name,
+        }
+    }
+
+    pub fn get_stats_name(&self) -> &str {
+        &self.stats_name
+    }
+
+    pub fn add_value(&self, stats_key: &str, inc_value: u64, inc_times: u64) {
+        self.get_and_create_stats_item(stats_key)
+            .add_value(inc_value, inc_times);
+    }
+
+    pub fn get_and_create_stats_item(&self, stats_key: &str) -> Arc<StatsItem> {
+        if let Some(item) = self.stats_item_table.get(stats_key) {
+            return Arc::clone(item.value());
+        }
+        Arc::clone(
+            self.stats_item_table
+                .entry(stats_key.to_string())
+                .or_insert_with(|| Arc::new(StatsItem::new(&self.stats_name, stats_key)))
+                .value(),
+        )
+    }
+
+    pub fn get_stats_item(&self, stats_key: &str) -> Option<Arc<StatsItem>> {
+        self.stats_item_table
+            .get(stats_key)
+            .map(|item| Arc::clone(item.value()))
+    }
+
+    pub fn del_value(&self, stats_key: &str) {
+        self.stats_item_table.remove(stats_key);
+    }
+
+    /// Removes every item whose key starts with `prefix`, e.g. all groups of a deleted topic.
+    pub fn del_value_by_prefix_key(&self, prefix: &str) {
+        self.stats_item_table
+            .retain(|stats_key, _| !stats_key.starts_with(prefix));
+    }
+
+    /// Removes every item whose key ends with `suffix`, e.g. all topics of a deleted group.
+    pub fn del_value_by_suffix_key(&self, suffix: &str) {
+        self.stats_item_table
+            .retain(|stats_key, _| !stats_key.ends_with(suffix));
     }
 
     pub fn get_stats_data_in_minute(&self, stats_key: &str) -> StatsSnapshot {
-        StatsSnapshot::new()
+        match self.get_stats_item(stats_key) {
+            Some(item) => item.get_stats_data_in_minute(),
+            None => StatsSnapshot::new(),
+        }
+    }
+
+    pub fn get_stats_data_in_hour(&self, stats_key: &str) -> StatsSnapshot {
+        match self.get_stats_item(stats_key) {
+            Some(item) => item.get_stats_data_in_hour(),
+            None => StatsSnapshot::new(),
+        }
+    }
+
+    pub fn get_stats_data_in_day(&self, stats_key: &str) -> StatsSnapshot {
+        match self.get_stats_item(stats_key) {
+            Some(item) => item.get_stats_data_in_day(),
+            None => StatsSnapshot::new(),
+        }
+    }
+
+    pub fn sampling_in_seconds(&self) {
+        for item in self.stats_item_table.iter() {
+            item.value().sampling_in_seconds();
+        }
+    }
+
+    pub fn sampling_in_minutes(&self) {
+        for item in self.stats_item_table.iter() {
+            item.value().sampling_in_minutes();
+        }
+    }
+
+    pub fn sampling_in_hour(&self) {
+        for item in self.stats_item_table.iter() {
+            item.value().sampling_in_hour();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_value_creates_item_once() {
+        let set = StatsItemSet::new("TOPIC_PUT_NUMS".to_string());
+        set.add_value("TopicTest", 2, 1);
+        set.add_value("TopicTest", 3, 1);
+        let item = set.get_stats_item("TopicTest").unwrap();
+        assert_eq!(item.get_value(), 5);
+        assert_eq!(item.get_times(), 2);
+        assert_eq!(item.get_stats_name(), "TOPIC_PUT_NUMS");
+        assert!(set.get_stats_item("Other").is_none());
+    }
+
+    #[test]
+    fn sampling_feeds_rolling_windows() {
+        let set = StatsItemSet::new("GROUP_GET_NUMS".to_string());
+        set.add_value("TopicTest@GroupA", 10, 2);
+        set.sampling_in_seconds();
+        set.sampling_in_minutes();
+        assert_eq!(
+            set.get_stats_data_in_minute("TopicTest@GroupA").get_sum(),
+            10
+        );
+        assert_eq!(set.get_stats_data_in_hour("TopicTest@GroupA").get_sum(), 10);
+        assert_eq!(set.get_stats_data_in_day("TopicTest@GroupA").get_sum(), 0);
+    }
+
+    #[test]
+    fn del_value_by_prefix_key_removes_matching_items() {
+        let set = StatsItemSet::new("GROUP_GET_NUMS".to_string());
+        set.add_value("TopicA@GroupA", 1, 1);
+        set.add_value("TopicA@GroupB", 1, 1);
+        set.add_value("TopicB@GroupA", 1, 1);
+        set.del_value_by_prefix_key("TopicA@");
+        assert!(set.get_stats_item("TopicA@GroupA").is_none());
+        assert!(set.get_stats_item("TopicA@GroupB").is_none());
+        assert!(set.get_stats_item("TopicB@GroupA").is_some());
     }
 }
@@ -21,6 +21,8 @@ use std::sync::Mutex;
 use cheetah_string::CheetahString;
 use lazy_static::lazy_static;
 
+use crate::common::pop_ack_constants::PopAckConstants;
+
 pub const TOPIC_MAX_LENGTH: usize = 127;
 
 lazy_static! {
@@ -135,9 +137,10 @@ impl TopicValidator {
         system_topics.contains(topic) || topic.starts_with(TopicValidator::SYSTEM_TOPIC_PREFIX)
     }
 
+    /// Topics only the broker itself writes to, such as the schedule, half and revive topics.
     pub fn is_not_allowed_send_topic(topic: &str) -> bool {
         let not_allowed_topics = NOT_ALLOWED_SEND_TOPIC_SET.lock().unwrap();
-        not_allowed_topics.contains(topic)
+        not_allowed_topics.contains(topic) || PopAckConstants::is_start_with_revive_prefix(topic)
     }
 
     pub fn add_system_topic(system_topic: &'static str) {
@@ -235,6 +238,13 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn is_not_allowed_send_topic_with_revive_topic() {
+        assert!(TopicValidator::is_not_allowed_send_topic(
+            &PopAckConstants::build_cluster_revive_topic("DefaultCluster")
+        ));
+    }
+
     #[test]
     fn is_not_allowed_send_topic_with_allowed_topic() {
         assert!(!TopicValidator::is_not_allowed_send_topic("allowed_topic"));
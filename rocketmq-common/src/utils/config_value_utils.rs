@@ -0,0 +1,106 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+/// Looks the property `key` up in a config serialized to json.
+///
+/// Fields of nested config structs are exposed as top level properties.
+pub fn find_config_value<'a>(
+    value: &'a serde_json::Value,
+    key: &str,
+) -> Option<&'a serde_json::Value> {
+    let object = value.as_object()?;
+    object.get(key).or_else(|| {
+        object
+            .values()
+            .filter(|nested| nested.is_object())
+            .find_map(|nested| nested.as_object().and_then(|nested| nested.get(key)))
+    })
+}
+
+/// Sets the property `key` of a config serialized to json from its string form, parsed as the
+/// type of the current value. A key of both the config and a nested config struct, like the
+/// broker name, is set in both.
+///
+/// Returns `false` when the config has no property `key`.
+pub fn set_config_value(
+    value: &mut serde_json::Value,
+    key: &str,
+    raw: &str,
+) -> Result<bool, String> {
+    let Some(object) = value.as_object_mut() else {
+        return Ok(false);
+    };
+    let mut slots = Vec::new();
+    for (name, field) in object.iter_mut() {
+        if name == key {
+            slots.push(field);
+        } else if let Some(slot) = field.as_object_mut().and_then(|nested| nested.get_mut(key)) {
+            slots.push(slot);
+        }
+    }
+    if slots.is_empty() {
+        return Ok(false);
+    }
+    for slot in slots {
+        *slot = parse_config_value(slot, key, raw)?;
+    }
+    Ok(true)
+}
+
+fn parse_config_value(
+    current: &serde_json::Value,
+    key: &str,
+    raw: &str,
+) -> Result<serde_json::Value, String> {
+    let invalid = || format!("invalid value '{}' for config key '{}'", raw, key);
+    Ok(match current {
+        serde_json::Value::Bool(_) => serde_json::Value::Bool(raw.parse().map_err(|_| invalid())?),
+        serde_json::Value::Number(number) => {
+            if number.is_u64() {
+                raw.parse::<u64>().map_err(|_| invalid())?.into()
+            } else if number.is_i64() {
+                raw.parse::<i64>().map_err(|_| invalid())?.into()
+            } else {
+                raw.parse::<f64>().map_err(|_| invalid())?.into()
+            }
+        }
+        _ => serde_json::Value::String(raw.to_string()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn sets_top_level_and_nested_keys() {
+        let mut value = json!({
+            "brokerName": "a",
+            "listenPort": 10911,
+            "brokerIdentity": { "brokerName": "a", "brokerId": 0 },
+        });
+        assert!(set_config_value(&mut value, "brokerName", "b").unwrap());
+        assert!(set_config_value(&mut value, "brokerId", "1").unwrap());
+        assert_eq!(value["brokerName"], "b");
+        assert_eq!(value["brokerIdentity"]["brokerName"], "b");
+        assert_eq!(find_config_value(&value, "brokerId"), Some(&json!(1)));
+        assert!(!set_config_value(&mut value, "noSuchKey", "1").unwrap());
+        assert!(set_config_value(&mut value, "listenPort", "port").is_err());
+    }
+}
@@ -15,9 +15,12 @@
  * limitations under the License.
  */
 
+use std::collections::HashMap;
 use std::fmt::Debug;
+use std::path::Path;
 use std::path::PathBuf;
 
+use cheetah_string::CheetahString;
 use config::Config;
 use serde::Deserialize;
 
@@ -36,3 +39,72 @@ where
     //info!("parse config: {:?}", config_file);
     Ok(config_file)
 }
+
+/// Reads the properties set in a toml config file, keyed as in the file.
+///
+/// The fields of a nested table are read as top level properties, and every value is taken in
+/// its string form, as expected by the `update` of the broker and store configs. Unlike
+/// [`parse_config_file`] a file that can not be read or parsed is an error.
+pub fn parse_config_properties(
+    config_file: &Path,
+) -> anyhow::Result<HashMap<CheetahString, CheetahString>> {
+    let table = std::fs::read_to_string(config_file)?.parse::<toml::Table>()?;
+    let mut properties = HashMap::new();
+    for (key, value) in table {
+        match value {
+            toml::Value::Table(nested) => {
+                for (key, value) in nested {
+                    insert_property(&mut properties, key, value);
+                }
+            }
+            value => insert_property(&mut properties, key, value),
+        }
+    }
+    Ok(properties)
+}
+
+fn insert_property(
+    properties: &mut HashMap<CheetahString, CheetahString>,
+    key: String,
+    value: toml::Value,
+) {
+    let value = match value {
+        toml::Value::String(value) => value,
+        toml::Value::Integer(value) => value.to_string(),
+        toml::Value::Float(value) => value.to_string(),
+        toml::Value::Boolean(value) => value.to_string(),
+        _ => return,
+    };
+    properties.insert(key.into(), value.into());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_properties_of_a_toml_file() {
+        let path =
+            std::env::temp_dir().join(format!("config-properties-{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            "listenPort = 20911\nbrokerName = \"broker-a\"\nenablePopReadahead = \
+             true\n\n[brokerIdentity]\nbrokerId = 1\n",
+        )
+        .unwrap();
+        let properties = parse_config_properties(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let expected = [
+            ("listenPort", "20911"),
+            ("brokerName", "broker-a"),
+            ("enablePopReadahead", "true"),
+            ("brokerId", "1"),
+        ]
+        .into_iter()
+        .map(|(key, value)| (CheetahString::from(key), CheetahString::from(value)))
+        .collect::<HashMap<_, _>>();
+        assert_eq!(properties, expected);
+        assert!(parse_config_properties(&path).is_err());
+    }
+}
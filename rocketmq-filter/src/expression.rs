@@ -15,6 +15,7 @@
  * limitations under the License.
  */
 pub mod evaluation_context;
+pub mod sql_expression;
 
 use std::error::Error;
 
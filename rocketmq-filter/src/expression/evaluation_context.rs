@@ -14,13 +14,23 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
-use std::any::Any;
 use std::collections::HashMap;
 
+/// Values that expressions are evaluated against, the properties of a message for the broker.
 pub trait EvaluationContext {
     /// Get value by name from context
-    fn get(&self, name: &str) -> Option<&dyn Any>;
+    fn get(&self, name: &str) -> Option<&str>;
 
     /// Context variables
-    fn key_values(&self) -> HashMap<String, Box<dyn Any>>;
+    fn key_values(&self) -> HashMap<String, String>;
+}
+
+impl EvaluationContext for HashMap<String, String> {
+    fn get(&self, name: &str) -> Option<&str> {
+        HashMap::get(self, name).map(String::as_str)
+    }
+
+    fn key_values(&self) -> HashMap<String, String> {
+        self.clone()
+    }
 }
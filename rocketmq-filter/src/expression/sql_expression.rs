@@ -0,0 +1,224 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::any::Any;
+use std::cmp::Ordering;
+use std::error::Error;
+
+use crate::expression::evaluation_context::EvaluationContext;
+use crate::expression::Expression;
+
+/// Value of a SQL92 expression.
+///
+/// Message properties are always strings, they are converted when compared with a constant of
+/// another type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Long(i64),
+    Double(f64),
+    String(String),
+}
+
+impl Value {
+    fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Long(value) => Some(*value as f64),
+            Value::Double(value) => Some(*value),
+            Value::String(value) => value.trim().parse().ok(),
+            _ => None,
+        }
+    }
+
+    fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(value) => Some(*value),
+            Value::String(value) if value.eq_ignore_ascii_case("true") => Some(true),
+            Value::String(value) if value.eq_ignore_ascii_case("false") => Some(false),
+            _ => None,
+        }
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        matches!(self, Value::Long(_) | Value::Double(_))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComparisonOperator {
+    Equal,
+    NotEqual,
+    GreaterThan,
+    GreaterThanOrEqual,
+    LessThan,
+    LessThanOrEqual,
+}
+
+/// Compiled SQL92 filter expression.
+///
+/// Evaluation follows the three-valued logic of SQL: a comparison involving a missing property is
+/// unknown, and a message only matches when the whole expression is `TRUE`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SqlExpression {
+    Constant(Value),
+    Property(String),
+    Not(Box<SqlExpression>),
+    And(Box<SqlExpression>, Box<SqlExpression>),
+    Or(Box<SqlExpression>, Box<SqlExpression>),
+    Comparison(ComparisonOperator, Box<SqlExpression>, Box<SqlExpression>),
+    IsNull {
+        expression: Box<SqlExpression>,
+        negated: bool,
+    },
+    In {
+        expression: Box<SqlExpression>,
+        list: Vec<String>,
+        negated: bool,
+    },
+    Between {
+        expression: Box<SqlExpression>,
+        low: Box<SqlExpression>,
+        high: Box<SqlExpression>,
+        negated: bool,
+    },
+}
+
+impl SqlExpression {
+    /// Whether the expression evaluates to `TRUE` for `context`.
+    pub fn matches(&self, context: &dyn EvaluationContext) -> bool {
+        self.eval(context) == Value::Bool(true)
+    }
+
+    pub fn eval(&self, context: &dyn EvaluationContext) -> Value {
+        match self {
+            SqlExpression::Constant(value) => value.clone(),
+            SqlExpression::Property(name) => context
+                .get(name)
+                .map_or(Value::Null, |value| Value::String(value.to_string())),
+            SqlExpression::Not(expression) => match expression.eval(context).as_bool() {
+                Some(value) => Value::Bool(!value),
+                None => Value::Null,
+            },
+            SqlExpression::And(left, right) => {
+                let left = left.eval(context).as_bool();
+                if left == Some(false) {
+                    return Value::Bool(false);
+                }
+                match (left, right.eval(context).as_bool()) {
+                    (_, Some(false)) => Value::Bool(false),
+                    (Some(true), Some(true)) => Value::Bool(true),
+                    _ => Value::Null,
+                }
+            }
+            SqlExpression::Or(left, right) => {
+                let left = left.eval(context).as_bool();
+                if left == Some(true) {
+                    return Value::Bool(true);
+                }
+                match (left, right.eval(context).as_bool()) {
+                    (_, Some(true)) => Value::Bool(true),
+                    (Some(false), Some(false)) => Value::Bool(false),
+                    _ => Value::Null,
+                }
+            }
+            SqlExpression::Comparison(operator, left, right) => {
+                compare(*operator, &left.eval(context), &right.eval(context))
+            }
+            SqlExpression::IsNull {
+                expression,
+                negated,
+            } => Value::Bool((expression.eval(context) == Value::Null) != *negated),
+            SqlExpression::In {
+                expression,
+                list,
+                negated,
+            } => match expression.eval(context) {
+                Value::String(value) => Value::Bool(list.contains(&value) != *negated),
+                _ => Value::Null,
+            },
+            SqlExpression::Between {
+                expression,
+                low,
+                high,
+                negated,
+            } => {
+                let value = expression.eval(context);
+                let ge_low = compare(
+                    ComparisonOperator::GreaterThanOrEqual,
+                    &value,
+                    &low.eval(context),
+                );
+                let le_high = compare(
+                    ComparisonOperator::LessThanOrEqual,
+                    &value,
+                    &high.eval(context),
+                );
+                match (ge_low, le_high) {
+                    (Value::Bool(ge_low), Value::Bool(le_high)) => {
+                        Value::Bool((ge_low && le_high) != *negated)
+                    }
+                    _ => Value::Null,
+                }
+            }
+        }
+    }
+}
+
+fn compare(operator: ComparisonOperator, left: &Value, right: &Value) -> Value {
+    if *left == Value::Null || *right == Value::Null {
+        return Value::Null;
+    }
+    let ordering = match (left, right) {
+        (Value::String(left), Value::String(right)) => match operator {
+            ComparisonOperator::Equal | ComparisonOperator::NotEqual => Some(left.cmp(right)),
+            // strings are only compared for equality
+            _ => None,
+        },
+        (Value::Long(left), Value::Long(right)) => Some(left.cmp(right)),
+        (Value::Bool(_), _) | (_, Value::Bool(_)) => match operator {
+            ComparisonOperator::Equal | ComparisonOperator::NotEqual => {
+                match (left.as_bool(), right.as_bool()) {
+                    (Some(left), Some(right)) => Some(left.cmp(&right)),
+                    _ => None,
+                }
+            }
+            _ => None,
+        },
+        _ => match (left.as_f64(), right.as_f64()) {
+            (Some(left), Some(right)) => left.partial_cmp(&right),
+            _ => None,
+        },
+    };
+    let Some(ordering) = ordering else {
+        return Value::Bool(false);
+    };
+    Value::Bool(match operator {
+        ComparisonOperator::Equal => ordering == Ordering::Equal,
+        ComparisonOperator::NotEqual => ordering != Ordering::Equal,
+        ComparisonOperator::GreaterThan => ordering == Ordering::Greater,
+        ComparisonOperator::GreaterThanOrEqual => ordering != Ordering::Less,
+        ComparisonOperator::LessThan => ordering == Ordering::Less,
+        ComparisonOperator::LessThanOrEqual => ordering != Ordering::Greater,
+    })
+}
+
+impl Expression for SqlExpression {
+    /// Evaluates to a `bool`, unknown results do not match.
+    fn evaluate(&self, context: &dyn EvaluationContext) -> Result<Box<dyn Any>, Box<dyn Error>> {
+        Ok(Box::new(self.matches(context)))
+    }
+}
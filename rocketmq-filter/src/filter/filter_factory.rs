@@ -0,0 +1,100 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::OnceLock;
+
+use crate::error::FilterError;
+use crate::expression::Expression;
+use crate::filter::filter_spi::FilterSpi;
+use crate::filter::sql_filter::SqlFilter;
+
+/// Filters by the type of expression they compile, [`SqlFilter`] is registered by default.
+pub struct FilterFactory {
+    filter_spi_table: HashMap<String, Arc<dyn FilterSpi>>,
+}
+
+impl Default for FilterFactory {
+    fn default() -> Self {
+        let mut filter_factory = FilterFactory {
+            filter_spi_table: HashMap::new(),
+        };
+        filter_factory.register(Arc::new(SqlFilter));
+        filter_factory
+    }
+}
+
+impl FilterFactory {
+    /// Factory with the filters shipped with this crate.
+    pub fn instance() -> &'static FilterFactory {
+        static INSTANCE: OnceLock<FilterFactory> = OnceLock::new();
+        INSTANCE.get_or_init(FilterFactory::default)
+    }
+
+    /// Registers `filter_spi`, replacing the filter of the same type if there is one.
+    pub fn register(&mut self, filter_spi: Arc<dyn FilterSpi>) {
+        self.filter_spi_table
+            .insert(filter_spi.of_type().to_string(), filter_spi);
+    }
+
+    pub fn unregister(&mut self, type_: &str) -> Option<Arc<dyn FilterSpi>> {
+        self.filter_spi_table.remove(type_)
+    }
+
+    pub fn get(&self, type_: &str) -> Option<&Arc<dyn FilterSpi>> {
+        self.filter_spi_table.get(type_)
+    }
+
+    pub fn registered_types(&self) -> Vec<&str> {
+        self.filter_spi_table.keys().map(String::as_str).collect()
+    }
+
+    /// Compiles `expr` with the filter of `type_`.
+    pub fn compile(
+        &self,
+        type_: &str,
+        expr: &str,
+    ) -> Result<Box<dyn Expression + Send + Sync>, FilterError> {
+        match self.get(type_) {
+            Some(filter_spi) => filter_spi.compile(expr),
+            None => Err(FilterError::UnsupportedType(type_.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::filter::sql_filter::SQL92;
+
+    #[test]
+    fn compiles_registered_types_only() {
+        let filter_factory = FilterFactory::instance();
+        let expression = filter_factory.compile(SQL92, "a > 1").unwrap();
+        let context = HashMap::from([("a".to_string(), "2".to_string())]);
+        let result = expression.evaluate(&context).unwrap();
+        assert_eq!(result.downcast_ref::<bool>(), Some(&true));
+
+        assert!(filter_factory.compile(SQL92, "a >").is_err());
+        assert_eq!(
+            filter_factory.compile("TAG", "*").err(),
+            Some(FilterError::UnsupportedType("TAG".to_string()))
+        );
+    }
+}
@@ -15,5 +15,8 @@
  * limitations under the License.
  */
 
+pub mod error;
 pub mod expression;
+pub mod filter;
+pub mod parser;
 pub mod utils;
@@ -0,0 +1,251 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::error::FilterError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Identifier(String),
+    String(String),
+    Long(i64),
+    Double(f64),
+    And,
+    Or,
+    Not,
+    Is,
+    Null,
+    In,
+    Between,
+    True,
+    False,
+    Equal,
+    NotEqual,
+    GreaterThan,
+    GreaterThanOrEqual,
+    LessThan,
+    LessThanOrEqual,
+    LeftParen,
+    RightParen,
+    Comma,
+    Minus,
+}
+
+/// Splits a SQL92 expression into tokens, each paired with its byte position in `expression`.
+///
+/// Keywords are case insensitive, string literals are quoted with `'` and a quote inside of them
+/// is written twice.
+pub fn tokenize(expression: &str) -> Result<Vec<(usize, Token)>, FilterError> {
+    let bytes = expression.as_bytes();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+    while index < bytes.len() {
+        let start = index;
+        let c = bytes[index];
+        let token = match c {
+            b' ' | b'\t' | b'\r' | b'\n' => {
+                index += 1;
+                continue;
+            }
+            b'(' => {
+                index += 1;
+                Token::LeftParen
+            }
+            b')' => {
+                index += 1;
+                Token::RightParen
+            }
+            b',' => {
+                index += 1;
+                Token::Comma
+            }
+            b'-' => {
+                index += 1;
+                Token::Minus
+            }
+            b'=' => {
+                index += 1;
+                Token::Equal
+            }
+            b'!' if bytes.get(index + 1) == Some(&b'=') => {
+                index += 2;
+                Token::NotEqual
+            }
+            b'<' => match bytes.get(index + 1) {
+                Some(b'>') => {
+                    index += 2;
+                    Token::NotEqual
+                }
+                Some(b'=') => {
+                    index += 2;
+                    Token::LessThanOrEqual
+                }
+                _ => {
+                    index += 1;
+                    Token::LessThan
+                }
+            },
+            b'>' => match bytes.get(index + 1) {
+                Some(b'=') => {
+                    index += 2;
+                    Token::GreaterThanOrEqual
+                }
+                _ => {
+                    index += 1;
+                    Token::GreaterThan
+                }
+            },
+            b'\'' => {
+                let mut value = String::new();
+                index += 1;
+                loop {
+                    let Some(offset) = expression[index..].find('\'') else {
+                        return Err(error(start, "unterminated string literal"));
+                    };
+                    value.push_str(&expression[index..index + offset]);
+                    index += offset + 1;
+                    if bytes.get(index) == Some(&b'\'') {
+                        value.push('\'');
+                        index += 1;
+                    } else {
+                        break;
+                    }
+                }
+                Token::String(value)
+            }
+            b'0'..=b'9' | b'.' => {
+                let mut is_double = false;
+                while index < bytes.len() {
+                    match bytes[index] {
+                        b'0'..=b'9' => index += 1,
+                        b'.' | b'e' | b'E' => {
+                            is_double = true;
+                            index += 1;
+                            if matches!(bytes[index - 1], b'e' | b'E')
+                                && matches!(bytes.get(index), Some(b'+') | Some(b'-'))
+                            {
+                                index += 1;
+                            }
+                        }
+                        _ => break,
+                    }
+                }
+                let literal = &expression[start..index];
+                if is_double {
+                    Token::Double(
+                        literal
+                            .parse()
+                            .map_err(|_| error(start, "invalid number literal"))?,
+                    )
+                } else {
+                    Token::Long(
+                        literal
+                            .parse()
+                            .map_err(|_| error(start, "invalid number literal"))?,
+                    )
+                }
+            }
+            c if c.is_ascii_alphabetic() || c == b'_' || c == b'$' => {
+                while index < bytes.len()
+                    && (bytes[index].is_ascii_alphanumeric()
+                        || matches!(bytes[index], b'_' | b'$' | b'.'))
+                {
+                    index += 1;
+                }
+                let word = &expression[start..index];
+                match word.to_ascii_uppercase().as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    "IS" => Token::Is,
+                    "NULL" => Token::Null,
+                    "IN" => Token::In,
+                    "BETWEEN" => Token::Between,
+                    "TRUE" => Token::True,
+                    "FALSE" => Token::False,
+                    _ => Token::Identifier(word.to_string()),
+                }
+            }
+            _ => {
+                return Err(error(
+                    start,
+                    &format!(
+                        "unexpected character '{}'",
+                        expression[start..].chars().next().unwrap()
+                    ),
+                ))
+            }
+        };
+        tokens.push((start, token));
+    }
+    Ok(tokens)
+}
+
+fn error(position: usize, message: &str) -> FilterError {
+    FilterError::ParseError {
+        position,
+        message: message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(expression: &str) -> Vec<Token> {
+        tokenize(expression)
+            .unwrap()
+            .into_iter()
+            .map(|(_, token)| token)
+            .collect()
+    }
+
+    #[test]
+    fn tokenizes_operators_and_literals() {
+        assert_eq!(
+            tokens("a.b >= 1.5 and c <> 'it''s'"),
+            vec![
+                Token::Identifier("a.b".to_string()),
+                Token::GreaterThanOrEqual,
+                Token::Double(1.5),
+                Token::And,
+                Token::Identifier("c".to_string()),
+                Token::NotEqual,
+                Token::String("it's".to_string()),
+            ]
+        );
+        assert_eq!(
+            tokens("x IS NOT null"),
+            vec![
+                Token::Identifier("x".to_string()),
+                Token::Is,
+                Token::Not,
+                Token::Null,
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_position_of_errors() {
+        assert_eq!(
+            tokenize("a = 'x"),
+            Err(FilterError::ParseError {
+                position: 4,
+                message: "unterminated string literal".to_string(),
+            })
+        );
+        assert!(tokenize("a # 1").is_err());
+    }
+}
@@ -0,0 +1,338 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::error::FilterError;
+use crate::expression::sql_expression::ComparisonOperator;
+use crate::expression::sql_expression::SqlExpression;
+use crate::expression::sql_expression::Value;
+use crate::parser::lexer::tokenize;
+use crate::parser::lexer::Token;
+
+/// Recursive descent parser of the SQL92 subset accepted by message selectors.
+///
+/// Operators by increasing precedence: `OR`, `AND`, `NOT`, `=`/`<>`/`IS [NOT] NULL` and finally
+/// `>`/`>=`/`<`/`<=`/`[NOT] IN`/`[NOT] BETWEEN`.
+pub struct SelectorParser {
+    tokens: Vec<(usize, Token)>,
+    index: usize,
+    end: usize,
+}
+
+impl SelectorParser {
+    pub fn parse(expression: &str) -> Result<SqlExpression, FilterError> {
+        let mut parser = SelectorParser {
+            tokens: tokenize(expression)?,
+            index: 0,
+            end: expression.len(),
+        };
+        let position = parser.position();
+        let result = parser.parse_or()?;
+        if parser.index < parser.tokens.len() {
+            return Err(parser.error("unexpected token"));
+        }
+        check_boolean(&result, position)?;
+        Ok(result)
+    }
+
+    fn parse_or(&mut self) -> Result<SqlExpression, FilterError> {
+        let mut left = self.parse_and()?;
+        while self.next_if(&Token::Or) {
+            let position = self.position();
+            let right = self.parse_and()?;
+            check_boolean(&left, position)?;
+            check_boolean(&right, position)?;
+            left = SqlExpression::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<SqlExpression, FilterError> {
+        let mut left = self.parse_not()?;
+        while self.next_if(&Token::And) {
+            let position = self.position();
+            let right = self.parse_not()?;
+            check_boolean(&left, position)?;
+            check_boolean(&right, position)?;
+            left = SqlExpression::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_not(&mut self) -> Result<SqlExpression, FilterError> {
+        let position = self.position();
+        if self.next_if(&Token::Not) {
+            let expression = self.parse_not()?;
+            check_boolean(&expression, position)?;
+            return Ok(SqlExpression::Not(Box::new(expression)));
+        }
+        self.parse_equality()
+    }
+
+    fn parse_equality(&mut self) -> Result<SqlExpression, FilterError> {
+        let mut left = self.parse_comparison()?;
+        loop {
+            let operator = match self.peek() {
+                Some(Token::Equal) => ComparisonOperator::Equal,
+                Some(Token::NotEqual) => ComparisonOperator::NotEqual,
+                Some(Token::Is) => {
+                    self.index += 1;
+                    let negated = self.next_if(&Token::Not);
+                    self.expect(&Token::Null)?;
+                    left = SqlExpression::IsNull {
+                        expression: Box::new(left),
+                        negated,
+                    };
+                    continue;
+                }
+                _ => return Ok(left),
+            };
+            self.index += 1;
+            let right = self.parse_comparison()?;
+            left = SqlExpression::Comparison(operator, Box::new(left), Box::new(right));
+        }
+    }
+
+    fn parse_comparison(&mut self) -> Result<SqlExpression, FilterError> {
+        let mut left = self.parse_unary()?;
+        loop {
+            let operator = match self.peek() {
+                Some(Token::GreaterThan) => ComparisonOperator::GreaterThan,
+                Some(Token::GreaterThanOrEqual) => ComparisonOperator::GreaterThanOrEqual,
+                Some(Token::LessThan) => ComparisonOperator::LessThan,
+                Some(Token::LessThanOrEqual) => ComparisonOperator::LessThanOrEqual,
+                Some(Token::Not) | Some(Token::In) | Some(Token::Between) => {
+                    let negated = self.next_if(&Token::Not);
+                    left = if self.next_if(&Token::In) {
+                        self.parse_in(left, negated)?
+                    } else if self.next_if(&Token::Between) {
+                        self.parse_between(left, negated)?
+                    } else {
+                        return Err(self.error("expected IN or BETWEEN"));
+                    };
+                    continue;
+                }
+                _ => return Ok(left),
+            };
+            self.index += 1;
+            let position = self.position();
+            let right = self.parse_unary()?;
+            check_numeric(&left, position)?;
+            check_numeric(&right, position)?;
+            left = SqlExpression::Comparison(operator, Box::new(left), Box::new(right));
+        }
+    }
+
+    fn parse_in(
+        &mut self,
+        expression: SqlExpression,
+        negated: bool,
+    ) -> Result<SqlExpression, FilterError> {
+        self.expect(&Token::LeftParen)?;
+        let mut list = Vec::new();
+        loop {
+            match self.next() {
+                Some(Token::String(value)) => list.push(value),
+                _ => return Err(self.previous_error("expected a string literal")),
+            }
+            if !self.next_if(&Token::Comma) {
+                break;
+            }
+        }
+        self.expect(&Token::RightParen)?;
+        Ok(SqlExpression::In {
+            expression: Box::new(expression),
+            list,
+            negated,
+        })
+    }
+
+    fn parse_between(
+        &mut self,
+        expression: SqlExpression,
+        negated: bool,
+    ) -> Result<SqlExpression, FilterError> {
+        let position = self.position();
+        let low = self.parse_unary()?;
+        self.expect(&Token::And)?;
+        let high = self.parse_unary()?;
+        check_numeric(&expression, position)?;
+        check_numeric(&low, position)?;
+        check_numeric(&high, position)?;
+        Ok(SqlExpression::Between {
+            expression: Box::new(expression),
+            low: Box::new(low),
+            high: Box::new(high),
+            negated,
+        })
+    }
+
+    fn parse_unary(&mut self) -> Result<SqlExpression, FilterError> {
+        if self.next_if(&Token::Minus) {
+            return match self.next() {
+                Some(Token::Long(value)) => Ok(SqlExpression::Constant(Value::Long(-value))),
+                Some(Token::Double(value)) => Ok(SqlExpression::Constant(Value::Double(-value))),
+                _ => Err(self.previous_error("expected a number literal")),
+            };
+        }
+        match self.next() {
+            Some(Token::LeftParen) => {
+                let expression = self.parse_or()?;
+                self.expect(&Token::RightParen)?;
+                Ok(expression)
+            }
+            Some(Token::Identifier(name)) => Ok(SqlExpression::Property(name)),
+            Some(Token::String(value)) => Ok(SqlExpression::Constant(Value::String(value))),
+            Some(Token::Long(value)) => Ok(SqlExpression::Constant(Value::Long(value))),
+            Some(Token::Double(value)) => Ok(SqlExpression::Constant(Value::Double(value))),
+            Some(Token::True) => Ok(SqlExpression::Constant(Value::Bool(true))),
+            Some(Token::False) => Ok(SqlExpression::Constant(Value::Bool(false))),
+            Some(Token::Null) => Ok(SqlExpression::Constant(Value::Null)),
+            _ => Err(self.previous_error("expected an operand")),
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index).map(|(_, token)| token)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.index).map(|(_, token)| token.clone());
+        self.index += 1;
+        token
+    }
+
+    fn next_if(&mut self, expected: &Token) -> bool {
+        if self.peek() == Some(expected) {
+            self.index += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, expected: &Token) -> Result<(), FilterError> {
+        if self.next_if(expected) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected {:?}", expected)))
+        }
+    }
+
+    fn position(&self) -> usize {
+        self.tokens
+            .get(self.index)
+            .map_or(self.end, |(position, _)| *position)
+    }
+
+    fn error(&self, message: &str) -> FilterError {
+        FilterError::ParseError {
+            position: self.position(),
+            message: message.to_string(),
+        }
+    }
+
+    /// Error at the token just consumed by [`Self::next`].
+    fn previous_error(&mut self, message: &str) -> FilterError {
+        self.index -= 1;
+        self.error(message)
+    }
+}
+
+/// Only properties and boolean valued expressions can be combined by `AND`, `OR` and `NOT`.
+fn check_boolean(expression: &SqlExpression, position: usize) -> Result<(), FilterError> {
+    match expression {
+        SqlExpression::Constant(Value::Bool(_)) => Ok(()),
+        SqlExpression::Constant(_) => Err(FilterError::ParseError {
+            position,
+            message: "expression will not result in a boolean value".to_string(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Constants ordered by `>`, `<` or `BETWEEN` must be numbers.
+fn check_numeric(expression: &SqlExpression, position: usize) -> Result<(), FilterError> {
+    match expression {
+        SqlExpression::Constant(value) if !value.is_numeric() => Err(FilterError::ParseError {
+            position,
+            message: format!("value {:?} cannot be compared", value),
+        }),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn matches(expression: &str, properties: &[(&str, &str)]) -> bool {
+        let context: HashMap<String, String> = properties
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        SelectorParser::parse(expression).unwrap().matches(&context)
+    }
+
+    #[test]
+    fn evaluates_comparisons() {
+        let properties = [("a", "3"), ("b", "abc"), ("c", "1.5")];
+        assert!(matches("a = 3", &properties));
+        assert!(matches("a > 2 AND a <= 3", &properties));
+        assert!(matches("c < 2", &properties));
+        assert!(matches("b = 'abc'", &properties));
+        assert!(matches("b <> 'x' and -1 < a", &properties));
+        assert!(!matches("b > 1", &properties));
+    }
+
+    #[test]
+    fn evaluates_between_in_and_null_checks() {
+        let properties = [("a", "3"), ("b", "abc")];
+        assert!(matches("a BETWEEN 1 AND 3", &properties));
+        assert!(matches("a not between 4 and 5", &properties));
+        assert!(matches("b IN ('x', 'abc')", &properties));
+        assert!(!matches("b NOT IN ('abc')", &properties));
+        assert!(matches("missing IS NULL AND b IS NOT NULL", &properties));
+    }
+
+    #[test]
+    fn missing_properties_never_match() {
+        assert!(!matches("a = 1", &[]));
+        assert!(!matches("a <> 1", &[]));
+        assert!(!matches("NOT (a > 1)", &[]));
+        assert!(matches("a > 1 OR TRUE", &[]));
+    }
+
+    #[test]
+    fn precedence_of_logical_operators() {
+        let properties = [("a", "1")];
+        assert!(matches("a = 2 AND a = 3 OR a = 1", &properties));
+        assert!(!matches("a = 2 AND (a = 3 OR a = 1)", &properties));
+        assert!(matches("NOT a = 2", &properties));
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        assert!(SelectorParser::parse("").is_err());
+        assert!(SelectorParser::parse("a = ").is_err());
+        assert!(SelectorParser::parse("a > 'x'").is_err());
+        assert!(SelectorParser::parse("a IN (1)").is_err());
+        assert!(SelectorParser::parse("(a = 1").is_err());
+        assert!(SelectorParser::parse("a = 1 b").is_err());
+        assert!(SelectorParser::parse("1 AND a = 1").is_err());
+    }
+}
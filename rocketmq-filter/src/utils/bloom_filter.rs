@@ -48,8 +48,7 @@ impl BloomFilter {
         }
 
         let error_rate = f as f64 / 100.0;
-        let k =
-            (0.5f64.log2() * (n as f64 * error_rate.ln()).abs() / error_rate.ln()).ceil() as i32;
+        let k = (error_rate.ln() / 0.5f64.ln()).ceil() as i32;
 
         if k < 1 {
             return Err(
@@ -88,4 +87,144 @@ impl BloomFilter {
             None => false,
         }
     }
+
+    /// Positions of the `k` bits of `s`, derived from one murmur3 hash by double hashing.
+    pub fn calc_bit_positions(&self, s: &str) -> Vec<i32> {
+        let (hash64, _) = murmur3_x64_128(s.as_bytes());
+        let hash1 = hash64 as i32;
+        let hash2 = (hash64 >> 32) as i32;
+        (1..=self.k)
+            .map(|i| {
+                let mut combined_hash = hash1.wrapping_add(i.wrapping_mul(hash2));
+                if combined_hash < 0 {
+                    combined_hash = !combined_hash;
+                }
+                combined_hash % self.m
+            })
+            .collect()
+    }
+
+    pub fn generate(&self, s: &str) -> BloomFilterData {
+        BloomFilterData::new(self.calc_bit_positions(s), self.m as u32)
+    }
+
+    /// Sets the bits of `filter_data` in `bits`, returns `false` when the data was generated by
+    /// another bloom filter or `bits` is too short.
+    pub fn hash_to(&self, filter_data: &BloomFilterData, bits: &mut [u8]) -> bool {
+        if !self.is_valid(Some(filter_data)) || bits.len() * 8 < self.m as usize {
+            return false;
+        }
+        for &pos in filter_data.bit_pos() {
+            bits[pos as usize / 8] |= 1 << (pos % 8);
+        }
+        true
+    }
+
+    /// Whether all bits of `filter_data` are set in `bits`.
+    pub fn is_hit(&self, filter_data: &BloomFilterData, bits: &[u8]) -> bool {
+        if !self.is_valid(Some(filter_data)) || bits.len() * 8 < self.m as usize {
+            return false;
+        }
+        filter_data
+            .bit_pos()
+            .iter()
+            .all(|&pos| bits[pos as usize / 8] & (1 << (pos % 8)) != 0)
+    }
+}
+
+/// The 128 bit x64 variant of murmur3 with seed 0, as used by the Java broker.
+fn murmur3_x64_128(data: &[u8]) -> (u64, u64) {
+    const C1: u64 = 0x87c3_7b91_1142_53d5;
+    const C2: u64 = 0x4cf5_ad43_2745_937f;
+
+    fn mix_k1(k1: u64) -> u64 {
+        k1.wrapping_mul(C1).rotate_left(31).wrapping_mul(C2)
+    }
+
+    fn mix_k2(k2: u64) -> u64 {
+        k2.wrapping_mul(C2).rotate_left(33).wrapping_mul(C1)
+    }
+
+    fn fmix(mut k: u64) -> u64 {
+        k ^= k >> 33;
+        k = k.wrapping_mul(0xff51_afd7_ed55_8ccd);
+        k ^= k >> 33;
+        k = k.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+        k ^ (k >> 33)
+    }
+
+    fn read_u64(bytes: &[u8]) -> u64 {
+        bytes
+            .iter()
+            .rev()
+            .fold(0u64, |acc, &byte| (acc << 8) | byte as u64)
+    }
+
+    let (mut h1, mut h2) = (0u64, 0u64);
+    let mut chunks = data.chunks_exact(16);
+    for chunk in &mut chunks {
+        h1 ^= mix_k1(read_u64(&chunk[..8]));
+        h1 = h1
+            .rotate_left(27)
+            .wrapping_add(h2)
+            .wrapping_mul(5)
+            .wrapping_add(0x52dc_e729);
+        h2 ^= mix_k2(read_u64(&chunk[8..]));
+        h2 = h2
+            .rotate_left(31)
+            .wrapping_add(h1)
+            .wrapping_mul(5)
+            .wrapping_add(0x3849_5ab5);
+    }
+    let tail = chunks.remainder();
+    if tail.len() > 8 {
+        h2 ^= mix_k2(read_u64(&tail[8..]));
+    }
+    if !tail.is_empty() {
+        h1 ^= mix_k1(read_u64(&tail[..tail.len().min(8)]));
+    }
+
+    h1 ^= data.len() as u64;
+    h2 ^= data.len() as u64;
+    h1 = h1.wrapping_add(h2);
+    h2 = h2.wrapping_add(h1);
+    h1 = fmix(h1);
+    h2 = fmix(h2);
+    h1 = h1.wrapping_add(h2);
+    h2 = h2.wrapping_add(h1);
+    (h1, h2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn murmur3_matches_reference() {
+        assert_eq!(
+            murmur3_x64_128(b"hello"),
+            (0xcbd8_a7b3_41bd_9b02, 0x5b1e_906a_48ae_1d19)
+        );
+    }
+
+    #[test]
+    fn hits_only_hashed_data() {
+        let bloom_filter = BloomFilter::new(20, 64).unwrap();
+        assert_eq!(bloom_filter.k(), 3);
+        let mut bits = vec![0u8; bloom_filter.m() as usize / 8];
+        let hashed = bloom_filter.generate("group#topic");
+        assert!(bloom_filter.is_valid(Some(&hashed)));
+        assert!(hashed
+            .bit_pos()
+            .iter()
+            .all(|&pos| (0..bloom_filter.m()).contains(&pos)));
+        assert!(!bloom_filter.is_hit(&hashed, &bits));
+        assert!(bloom_filter.hash_to(&hashed, &mut bits));
+        assert!(bloom_filter.is_hit(&hashed, &bits));
+
+        let other = BloomFilter::new(10, 64).unwrap().generate("group#topic");
+        assert!(!bloom_filter.hash_to(&other, &mut bits));
+        assert!(!bloom_filter.is_hit(&other, &bits));
+        assert!(!bloom_filter.is_hit(&hashed, &bits[1..]));
+    }
 }
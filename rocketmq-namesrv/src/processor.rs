@@ -48,10 +48,9 @@ impl RequestProcessor for NameServerRequestProcessor {
         let request_code = RequestCode::from(request.code());
         info!("Name server Received request code: {:?}", request_code);
         let result = match request_code {
-            RequestCode::GetRouteinfoByTopic => {
-                self.client_request_processor
-                    .process_request(channel, ctx, request_code, request)
-            }
+            RequestCode::GetRouteinfoByTopic | RequestCode::GetRouteinfoByTopicList => self
+                .client_request_processor
+                .process_request(channel, ctx, request_code, request),
             _ => {
                 self.default_request_processor
                     .process_request(channel, ctx, request_code, request)
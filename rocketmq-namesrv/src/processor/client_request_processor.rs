@@ -26,8 +26,12 @@ use rocketmq_remoting::code::request_code::RequestCode;
 use rocketmq_remoting::code::response_code::RemotingSysResponseCode;
 use rocketmq_remoting::code::response_code::ResponseCode;
 use rocketmq_remoting::net::channel::Channel;
+use rocketmq_remoting::protocol::body::topic::topic_list::TopicList;
+use rocketmq_remoting::protocol::body::topic::topic_route_data_batch::TopicRouteDataBatch;
 use rocketmq_remoting::protocol::header::client_request_header::GetRouteInfoRequestHeader;
 use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+use rocketmq_remoting::protocol::route::topic_route_data::TopicRouteData;
+use rocketmq_remoting::protocol::RemotingDeserializable;
 use rocketmq_remoting::protocol::RemotingSerializable;
 use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
 use rocketmq_rust::ArcMut;
@@ -65,36 +69,10 @@ impl ClientRequestProcessor {
                     "decode GetRouteInfoRequestHeader fail".to_string(),
                 )
             })?;
-        let namesrv_ready = self.need_check_namesrv_ready.load(Ordering::Relaxed)
-            && TimeUtils::get_current_millis() - self.startup_time_millis
-                >= Duration::from_secs(
-                    self.name_server_runtime_inner
-                        .name_server_config()
-                        .wait_seconds_for_service as u64,
-                )
-                .as_millis() as u64;
-        if self
-            .name_server_runtime_inner
-            .name_server_config()
-            .need_wait_for_service
-            && !namesrv_ready
-        {
-            warn!(
-                "name remoting_server not ready. request code {} ",
-                request.code()
-            );
-            return Ok(Some(
-                RemotingCommand::create_response_command_with_code(
-                    RemotingSysResponseCode::SystemError,
-                )
-                .set_remark("name remoting_server not ready"),
-            ));
+        if let Some(response) = self.check_namesrv_ready(&request) {
+            return Ok(Some(response));
         }
-        match self
-            .name_server_runtime_inner
-            .route_info_manager()
-            .pickup_topic_route_data(request_header.topic.as_ref())
-        {
+        match self.pickup_topic_route_data(&request_header.topic) {
             None => Ok(Some(
                 RemotingCommand::create_response_command_with_code(ResponseCode::TopicNotExist)
                     .set_remark(format!(
@@ -103,26 +81,7 @@ impl ClientRequestProcessor {
                         FAQUrl::suggest_todo(FAQUrl::APPLY_TOPIC_URL)
                     )),
             )),
-            Some(mut topic_route_data) => {
-                if self.need_check_namesrv_ready.load(Ordering::Acquire) {
-                    self.need_check_namesrv_ready
-                        .store(false, Ordering::Release);
-                }
-                if self
-                    .name_server_runtime_inner
-                    .name_server_config()
-                    .order_message_enable
-                {
-                    //get kv config
-                    let order_topic_config = self
-                        .name_server_runtime_inner
-                        .kvconfig_manager()
-                        .get_kvconfig(
-                            &CheetahString::from_static_str(NAMESPACE_ORDER_TOPIC_CONFIG),
-                            &request_header.topic,
-                        );
-                    topic_route_data.order_topic_conf = order_topic_config;
-                };
+            Some(topic_route_data) => {
                 /*let standard_json_only = request_header.accept_standard_json_only.unwrap_or(false);
                 let content = if request.version() >= RocketMqVersion::into(RocketMqVersion::V494)
                     || standard_json_only
@@ -142,6 +101,104 @@ impl ClientRequestProcessor {
             }
         }
     }
+
+    /// Answers the routes of every topic of the list in one response, so clients subscribing
+    /// to many topics do not query them one by one.
+    fn get_route_info_by_topic_list(
+        &self,
+        request: RemotingCommand,
+    ) -> crate::Result<Option<RemotingCommand>> {
+        let Some(topic_list) = request
+            .body()
+            .as_ref()
+            .and_then(|body| TopicList::decode(body).ok())
+        else {
+            return Ok(Some(
+                RemotingCommand::create_response_command_with_code(
+                    RemotingSysResponseCode::SystemError,
+                )
+                .set_remark("decode TopicList fail"),
+            ));
+        };
+        if let Some(response) = self.check_namesrv_ready(&request) {
+            return Ok(Some(response));
+        }
+        let topic_route_data_table = topic_list
+            .topic_list
+            .into_iter()
+            .filter_map(|topic| {
+                let topic_route_data = self.pickup_topic_route_data(&topic)?;
+                Some((topic, topic_route_data))
+            })
+            .collect();
+        let content = TopicRouteDataBatch {
+            topic_route_data_table,
+        }
+        .encode()
+        .map_err(|_| MQNamesrvError("encode TopicRouteDataBatch failed".to_string()))?;
+        Ok(Some(
+            RemotingCommand::create_response_command_with_code(ResponseCode::Success)
+                .set_body(content),
+        ))
+    }
+
+    /// The not ready response while the name server waits for the brokers to register after
+    /// startup.
+    fn check_namesrv_ready(&self, request: &RemotingCommand) -> Option<RemotingCommand> {
+        let namesrv_ready = self.need_check_namesrv_ready.load(Ordering::Relaxed)
+            && TimeUtils::get_current_millis() - self.startup_time_millis
+                >= Duration::from_secs(
+                    self.name_server_runtime_inner
+                        .name_server_config()
+                        .wait_seconds_for_service as u64,
+                )
+                .as_millis() as u64;
+        if self
+            .name_server_runtime_inner
+            .name_server_config()
+            .need_wait_for_service
+            && !namesrv_ready
+        {
+            warn!(
+                "name remoting_server not ready. request code {} ",
+                request.code()
+            );
+            return Some(
+                RemotingCommand::create_response_command_with_code(
+                    RemotingSysResponseCode::SystemError,
+                )
+                .set_remark("name remoting_server not ready"),
+            );
+        }
+        None
+    }
+
+    fn pickup_topic_route_data(&self, topic: &CheetahString) -> Option<TopicRouteData> {
+        let mut topic_route_data = self
+            .name_server_runtime_inner
+            .route_info_manager()
+            .pickup_topic_route_data(topic)?;
+        if self.need_check_namesrv_ready.load(Ordering::Acquire) {
+            self.need_check_namesrv_ready
+                .store(false, Ordering::Release);
+        }
+        if self
+            .name_server_runtime_inner
+            .name_server_config()
+            .order_message_enable
+        {
+            //get kv config
+            let order_topic_config = self
+                .name_server_runtime_inner
+                .kvconfig_manager()
+                .get_kvconfig(
+                    &CheetahString::from_static_str(NAMESPACE_ORDER_TOPIC_CONFIG),
+                    topic,
+                );
+            topic_route_data.order_topic_conf = order_topic_config;
+        };
+        Some(topic_route_data)
+    }
 }
 
 impl ClientRequestProcessor {
@@ -149,9 +206,12 @@ impl ClientRequestProcessor {
         &mut self,
         _channel: Channel,
         _ctx: ConnectionHandlerContext,
-        _request_code: RequestCode,
+        request_code: RequestCode,
         request: RemotingCommand,
     ) -> crate::Result<Option<RemotingCommand>> {
-        self.get_route_info_by_topic(request)
+        match request_code {
+            RequestCode::GetRouteinfoByTopicList => self.get_route_info_by_topic_list(request),
+            _ => self.get_route_info_by_topic(request),
+        }
     }
 }
@@ -0,0 +1,30 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+#![no_main]
+
+use bytes::BytesMut;
+use libfuzzer_sys::fuzz_target;
+use rocketmq_remoting::codec::remoting_command_codec::RemotingCommandCodec;
+use tokio_util::codec::Decoder;
+
+// Feeds arbitrary bytes to the codec the way a connection does, decoding frames until it asks
+// for more data or fails. Any panic here is a bug reachable by whoever can reach the port.
+fuzz_target!(|data: &[u8]| {
+    let mut codec = RemotingCommandCodec::new();
+    let mut src = BytesMut::from(data);
+    while let Ok(Some(_)) = codec.decode(&mut src) {}
+});
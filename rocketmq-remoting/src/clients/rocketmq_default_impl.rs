@@ -36,6 +36,7 @@ use tracing::warn;
 use crate::base::connection_net_event::ConnectionNetEvent;
 use crate::clients::Client;
 use crate::clients::RemotingClient;
+use crate::net::dns_resolver::DnsResolver;
 use crate::protocol::remoting_command::RemotingCommand;
 use crate::remoting::RemotingService;
 use crate::remoting_error::RemotingError;
@@ -60,6 +61,7 @@ pub struct RocketmqDefaultClient<PR = DefaultRemotingRequestProcessor> {
     client_runtime: Option<RocketMQRuntime>,
     processor: PR,
     tx: Option<tokio::sync::broadcast::Sender<ConnectionNetEvent>>,
+    dns_resolver: Arc<DnsResolver>,
 }
 impl<PR: RequestProcessor + Sync + Clone + 'static> RocketmqDefaultClient<PR> {
     pub fn new(tokio_client_config: Arc<TokioClientConfig>, processor: PR) -> Self {
@@ -71,6 +73,9 @@ impl<PR: RequestProcessor + Sync + Clone + 'static> RocketmqDefaultClient<PR> {
         processor: PR,
         tx: Option<tokio::sync::broadcast::Sender<ConnectionNetEvent>>,
     ) -> Self {
+        let dns_resolver = Arc::new(DnsResolver::new(Duration::from_millis(
+            tokio_client_config.dns_cache_ttl_millis,
+        )));
         Self {
             tokio_client_config,
             connection_tables: Arc::new(Mutex::new(Default::default())),
@@ -81,6 +86,7 @@ impl<PR: RequestProcessor + Sync + Clone + 'static> RocketmqDefaultClient<PR> {
             client_runtime: Some(RocketMQRuntime::new_multi(10, "client-thread")),
             processor,
             tx,
+            dns_resolver,
         }
     }
 }
@@ -180,10 +186,14 @@ impl<PR: RequestProcessor + Sync + Clone + 'static> RocketmqDefaultClient<PR> {
             let _ = connection_tables.remove(addr.as_str());
         }
 
-        let addr_inner = addr.to_string();
-
         match time::timeout(duration, async {
-            Client::connect(addr_inner, self.processor.clone(), self.tx.as_ref()).await
+            let socket_addrs = self.dns_resolver.resolve(addr.as_str()).await?;
+            Client::connect(
+                socket_addrs.as_slice(),
+                self.processor.clone(),
+                self.tx.as_ref(),
+            )
+            .await
         })
         .await
         {
@@ -194,12 +204,14 @@ impl<PR: RequestProcessor + Sync + Clone + 'static> RocketmqDefaultClient<PR> {
                     connection_tables.insert(addr.clone(), client.clone());
                     Some(client)
                 }
-                Err(_) => {
-                    error!("getAndCreateClient connect to {} failed", addr);
+                Err(err) => {
+                    self.dns_resolver.evict(addr.as_str());
+                    error!("getAndCreateClient connect to {} failed, {}", addr, err);
                     None
                 }
             },
             Err(_) => {
+                self.dns_resolver.evict(addr.as_str());
                 error!("getAndCreateClient connect to {} failed", addr);
                 None
             }
@@ -71,6 +71,8 @@ pub enum RequestCode {
     UnregisterBroker = 104,
     GetRouteinfoByTopic = 105,
     GetBrokerClusterInfo = 106,
+    /// Routes of many topics in one request, the body is a `TopicList`.
+    GetRouteinfoByTopicList = 107,
     UpdateAndCreateSubscriptionGroup = 200,
     GetAllSubscriptionGroupConfig = 201,
     GetTopicStatsInfo = 202,
@@ -141,6 +143,7 @@ pub enum RequestCode {
     ExchangeBrokerHaInfo = 906,
     GetBrokerHaStatus = 907,
     ResetMasterFlushOffset = 908,
+    NotifyBrokerRoleChanged = 1008,
     GetAllProducerInfo = 328,
     DeleteExpiredCommitlog = 329,
 
@@ -214,6 +217,7 @@ impl From<i32> for RequestCode {
             104 => RequestCode::UnregisterBroker,
             105 => RequestCode::GetRouteinfoByTopic,
             106 => RequestCode::GetBrokerClusterInfo,
+            107 => RequestCode::GetRouteinfoByTopicList,
             200 => RequestCode::UpdateAndCreateSubscriptionGroup,
             201 => RequestCode::GetAllSubscriptionGroupConfig,
             202 => RequestCode::GetTopicStatsInfo,
@@ -284,6 +288,7 @@ impl From<i32> for RequestCode {
             906 => RequestCode::ExchangeBrokerHaInfo,
             907 => RequestCode::GetBrokerHaStatus,
             908 => RequestCode::ResetMasterFlushOffset,
+            1008 => RequestCode::NotifyBrokerRoleChanged,
             328 => RequestCode::GetAllProducerInfo,
             329 => RequestCode::DeleteExpiredCommitlog,
             2001 => RequestCode::UpdateColdDataFlowCtrConfig,
@@ -14,6 +14,9 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::fmt::Display;
+use std::str::FromStr;
+
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub enum RemotingSysResponseCode {
     Success = 0,
@@ -87,6 +90,7 @@ pub enum ResponseCode {
     BrokerNotExist = 211,
     BrokerDispatchNotComplete = 212,
     BroadcastConsumption = 213,
+    NoBuffer = 214,
     FlowControl = 215,
     NotLeaderForQueue = 501,
     IllegalOperation = 604,
@@ -110,6 +114,10 @@ pub enum ResponseCode {
     ControllerElectMasterFailed = 2012,
     ControllerAlterSyncStateSetFailed = 2013,
     ControllerBrokerIdInvalid = 2014,
+    ControllerJraftInternalError = 2015,
+    ControllerBrokerLiveInfoNotExists = 2016,
+    UserNotExist = 3001,
+    PolicyNotExist = 3002,
 }
 
 impl From<ResponseCode> for i32 {
@@ -159,6 +167,7 @@ impl From<i32> for ResponseCode {
             211 => ResponseCode::BrokerNotExist,
             212 => ResponseCode::BrokerDispatchNotComplete,
             213 => ResponseCode::BroadcastConsumption,
+            214 => ResponseCode::NoBuffer,
             215 => ResponseCode::FlowControl,
             501 => ResponseCode::NotLeaderForQueue,
             604 => ResponseCode::IllegalOperation,
@@ -182,11 +191,226 @@ impl From<i32> for ResponseCode {
             2012 => ResponseCode::ControllerElectMasterFailed,
             2013 => ResponseCode::ControllerAlterSyncStateSetFailed,
             2014 => ResponseCode::ControllerBrokerIdInvalid,
+            2015 => ResponseCode::ControllerJraftInternalError,
+            2016 => ResponseCode::ControllerBrokerLiveInfoNotExists,
+            3001 => ResponseCode::UserNotExist,
+            3002 => ResponseCode::PolicyNotExist,
             _ => ResponseCode::SystemError,
         }
     }
 }
 
+impl ResponseCode {
+    /// Returns the constant name used by the Java broker for this code, e.g. `POLLING_FULL`.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            ResponseCode::Success => "SUCCESS",
+            ResponseCode::SystemError => "SYSTEM_ERROR",
+            ResponseCode::SystemBusy => "SYSTEM_BUSY",
+            ResponseCode::RequestCodeNotSupported => "REQUEST_CODE_NOT_SUPPORTED",
+            ResponseCode::TransactionFailed => "TRANSACTION_FAILED",
+            ResponseCode::FlushDiskTimeout => "FLUSH_DISK_TIMEOUT",
+            ResponseCode::SlaveNotAvailable => "SLAVE_NOT_AVAILABLE",
+            ResponseCode::FlushSlaveTimeout => "FLUSH_SLAVE_TIMEOUT",
+            ResponseCode::MessageIllegal => "MESSAGE_ILLEGAL",
+            ResponseCode::ServiceNotAvailable => "SERVICE_NOT_AVAILABLE",
+            ResponseCode::VersionNotSupported => "VERSION_NOT_SUPPORTED",
+            ResponseCode::NoPermission => "NO_PERMISSION",
+            ResponseCode::TopicNotExist => "TOPIC_NOT_EXIST",
+            ResponseCode::TopicExistAlready => "TOPIC_EXIST_ALREADY",
+            ResponseCode::PullNotFound => "PULL_NOT_FOUND",
+            ResponseCode::PullRetryImmediately => "PULL_RETRY_IMMEDIATELY",
+            ResponseCode::PullOffsetMoved => "PULL_OFFSET_MOVED",
+            ResponseCode::QueryNotFound => "QUERY_NOT_FOUND",
+            ResponseCode::SubscriptionParseFailed => "SUBSCRIPTION_PARSE_FAILED",
+            ResponseCode::SubscriptionNotExist => "SUBSCRIPTION_NOT_EXIST",
+            ResponseCode::SubscriptionNotLatest => "SUBSCRIPTION_NOT_LATEST",
+            ResponseCode::SubscriptionGroupNotExist => "SUBSCRIPTION_GROUP_NOT_EXIST",
+            ResponseCode::FilterDataNotExist => "FILTER_DATA_NOT_EXIST",
+            ResponseCode::FilterDataNotLatest => "FILTER_DATA_NOT_LATEST",
+            ResponseCode::TransactionShouldCommit => "TRANSACTION_SHOULD_COMMIT",
+            ResponseCode::TransactionShouldRollback => "TRANSACTION_SHOULD_ROLLBACK",
+            ResponseCode::TransactionStateUnknow => "TRANSACTION_STATE_UNKNOW",
+            ResponseCode::TransactionStateGroupWrong => "TRANSACTION_STATE_GROUP_WRONG",
+            ResponseCode::NoBuyerId => "NO_BUYER_ID",
+            ResponseCode::NotInCurrentUnit => "NOT_IN_CURRENT_UNIT",
+            ResponseCode::ConsumerNotOnline => "CONSUMER_NOT_ONLINE",
+            ResponseCode::ConsumeMsgTimeout => "CONSUME_MSG_TIMEOUT",
+            ResponseCode::NoMessage => "NO_MESSAGE",
+            ResponseCode::PollingFull => "POLLING_FULL",
+            ResponseCode::PollingTimeout => "POLLING_TIMEOUT",
+            ResponseCode::BrokerNotExist => "BROKER_NOT_EXIST",
+            ResponseCode::BrokerDispatchNotComplete => "BROKER_DISPATCH_NOT_COMPLETE",
+            ResponseCode::BroadcastConsumption => "BROADCAST_CONSUMPTION",
+            ResponseCode::NoBuffer => "NO_BUFFER",
+            ResponseCode::FlowControl => "FLOW_CONTROL",
+            ResponseCode::NotLeaderForQueue => "NOT_LEADER_FOR_QUEUE",
+            ResponseCode::IllegalOperation => "ILLEGAL_OPERATION",
+            ResponseCode::RpcUnknown => "RPC_UNKNOWN",
+            ResponseCode::RpcAddrIsNull => "RPC_ADDR_IS_NULL",
+            ResponseCode::RpcSendToChannelFailed => "RPC_SEND_TO_CHANNEL_FAILED",
+            ResponseCode::RpcTimeOut => "RPC_TIME_OUT",
+            ResponseCode::GoAway => "GO_AWAY",
+            ResponseCode::ControllerFencedMasterEpoch => "CONTROLLER_FENCED_MASTER_EPOCH",
+            ResponseCode::ControllerFencedSyncStateSetEpoch => {
+                "CONTROLLER_FENCED_SYNC_STATE_SET_EPOCH"
+            }
+            ResponseCode::ControllerInvalidMaster => "CONTROLLER_INVALID_MASTER",
+            ResponseCode::ControllerInvalidReplicas => "CONTROLLER_INVALID_REPLICAS",
+            ResponseCode::ControllerMasterNotAvailable => "CONTROLLER_MASTER_NOT_AVAILABLE",
+            ResponseCode::ControllerInvalidRequest => "CONTROLLER_INVALID_REQUEST",
+            ResponseCode::ControllerBrokerNotAlive => "CONTROLLER_BROKER_NOT_ALIVE",
+            ResponseCode::ControllerNotLeader => "CONTROLLER_NOT_LEADER",
+            ResponseCode::ControllerBrokerMetadataNotExist => {
+                "CONTROLLER_BROKER_METADATA_NOT_EXIST"
+            }
+            ResponseCode::ControllerInvalidCleanBrokerMetadata => {
+                "CONTROLLER_INVALID_CLEAN_BROKER_METADATA"
+            }
+            ResponseCode::ControllerBrokerNeedToBeRegistered => {
+                "CONTROLLER_BROKER_NEED_TO_BE_REGISTERED"
+            }
+            ResponseCode::ControllerMasterStillExist => "CONTROLLER_MASTER_STILL_EXIST",
+            ResponseCode::ControllerElectMasterFailed => "CONTROLLER_ELECT_MASTER_FAILED",
+            ResponseCode::ControllerAlterSyncStateSetFailed => {
+                "CONTROLLER_ALTER_SYNC_STATE_SET_FAILED"
+            }
+            ResponseCode::ControllerBrokerIdInvalid => "CONTROLLER_BROKER_ID_INVALID",
+            ResponseCode::ControllerJraftInternalError => "CONTROLLER_JRAFT_INTERNAL_ERROR",
+            ResponseCode::ControllerBrokerLiveInfoNotExists => {
+                "CONTROLLER_BROKER_LIVE_INFO_NOT_EXISTS"
+            }
+            ResponseCode::UserNotExist => "USER_NOT_EXIST",
+            ResponseCode::PolicyNotExist => "POLICY_NOT_EXIST",
+        }
+    }
+
+    /// Whether a client may retry the same request later, possibly against another broker,
+    /// instead of surfacing the failure to the application.
+    pub const fn is_retriable(&self) -> bool {
+        matches!(
+            self,
+            ResponseCode::SystemBusy
+                | ResponseCode::FlushDiskTimeout
+                | ResponseCode::SlaveNotAvailable
+                | ResponseCode::FlushSlaveTimeout
+                | ResponseCode::ServiceNotAvailable
+                | ResponseCode::PollingFull
+                | ResponseCode::PollingTimeout
+                | ResponseCode::NoBuffer
+                | ResponseCode::FlowControl
+                | ResponseCode::GoAway
+        )
+    }
+
+    /// Builds a remark prefixed with the code name, e.g. `[POLLING_FULL] too many requests`,
+    /// so clients can match on the reason without parsing free-form text.
+    pub fn remark(&self, detail: impl Display) -> String {
+        format!("[{}] {}", self.as_str(), detail)
+    }
+
+    /// Recovers the code from a remark built by [`ResponseCode::remark`].
+    pub fn from_remark(remark: &str) -> Option<ResponseCode> {
+        let rest = remark.strip_prefix('[')?;
+        let end = rest.find(']')?;
+        rest[..end].parse().ok()
+    }
+}
+
+impl Display for ResponseCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ResponseCode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "SUCCESS" => Ok(ResponseCode::Success),
+            "SYSTEM_ERROR" => Ok(ResponseCode::SystemError),
+            "SYSTEM_BUSY" => Ok(ResponseCode::SystemBusy),
+            "REQUEST_CODE_NOT_SUPPORTED" => Ok(ResponseCode::RequestCodeNotSupported),
+            "TRANSACTION_FAILED" => Ok(ResponseCode::TransactionFailed),
+            "FLUSH_DISK_TIMEOUT" => Ok(ResponseCode::FlushDiskTimeout),
+            "SLAVE_NOT_AVAILABLE" => Ok(ResponseCode::SlaveNotAvailable),
+            "FLUSH_SLAVE_TIMEOUT" => Ok(ResponseCode::FlushSlaveTimeout),
+            "MESSAGE_ILLEGAL" => Ok(ResponseCode::MessageIllegal),
+            "SERVICE_NOT_AVAILABLE" => Ok(ResponseCode::ServiceNotAvailable),
+            "VERSION_NOT_SUPPORTED" => Ok(ResponseCode::VersionNotSupported),
+            "NO_PERMISSION" => Ok(ResponseCode::NoPermission),
+            "TOPIC_NOT_EXIST" => Ok(ResponseCode::TopicNotExist),
+            "TOPIC_EXIST_ALREADY" => Ok(ResponseCode::TopicExistAlready),
+            "PULL_NOT_FOUND" => Ok(ResponseCode::PullNotFound),
+            "PULL_RETRY_IMMEDIATELY" => Ok(ResponseCode::PullRetryImmediately),
+            "PULL_OFFSET_MOVED" => Ok(ResponseCode::PullOffsetMoved),
+            "QUERY_NOT_FOUND" => Ok(ResponseCode::QueryNotFound),
+            "SUBSCRIPTION_PARSE_FAILED" => Ok(ResponseCode::SubscriptionParseFailed),
+            "SUBSCRIPTION_NOT_EXIST" => Ok(ResponseCode::SubscriptionNotExist),
+            "SUBSCRIPTION_NOT_LATEST" => Ok(ResponseCode::SubscriptionNotLatest),
+            "SUBSCRIPTION_GROUP_NOT_EXIST" => Ok(ResponseCode::SubscriptionGroupNotExist),
+            "FILTER_DATA_NOT_EXIST" => Ok(ResponseCode::FilterDataNotExist),
+            "FILTER_DATA_NOT_LATEST" => Ok(ResponseCode::FilterDataNotLatest),
+            "TRANSACTION_SHOULD_COMMIT" => Ok(ResponseCode::TransactionShouldCommit),
+            "TRANSACTION_SHOULD_ROLLBACK" => Ok(ResponseCode::TransactionShouldRollback),
+            "TRANSACTION_STATE_UNKNOW" => Ok(ResponseCode::TransactionStateUnknow),
+            "TRANSACTION_STATE_GROUP_WRONG" => Ok(ResponseCode::TransactionStateGroupWrong),
+            "NO_BUYER_ID" => Ok(ResponseCode::NoBuyerId),
+            "NOT_IN_CURRENT_UNIT" => Ok(ResponseCode::NotInCurrentUnit),
+            "CONSUMER_NOT_ONLINE" => Ok(ResponseCode::ConsumerNotOnline),
+            "CONSUME_MSG_TIMEOUT" => Ok(ResponseCode::ConsumeMsgTimeout),
+            "NO_MESSAGE" => Ok(ResponseCode::NoMessage),
+            "POLLING_FULL" => Ok(ResponseCode::PollingFull),
+            "POLLING_TIMEOUT" => Ok(ResponseCode::PollingTimeout),
+            "BROKER_NOT_EXIST" => Ok(ResponseCode::BrokerNotExist),
+            "BROKER_DISPATCH_NOT_COMPLETE" => Ok(ResponseCode::BrokerDispatchNotComplete),
+            "BROADCAST_CONSUMPTION" => Ok(ResponseCode::BroadcastConsumption),
+            "NO_BUFFER" => Ok(ResponseCode::NoBuffer),
+            "FLOW_CONTROL" => Ok(ResponseCode::FlowControl),
+            "NOT_LEADER_FOR_QUEUE" => Ok(ResponseCode::NotLeaderForQueue),
+            "ILLEGAL_OPERATION" => Ok(ResponseCode::IllegalOperation),
+            "RPC_UNKNOWN" => Ok(ResponseCode::RpcUnknown),
+            "RPC_ADDR_IS_NULL" => Ok(ResponseCode::RpcAddrIsNull),
+            "RPC_SEND_TO_CHANNEL_FAILED" => Ok(ResponseCode::RpcSendToChannelFailed),
+            "RPC_TIME_OUT" => Ok(ResponseCode::RpcTimeOut),
+            "GO_AWAY" => Ok(ResponseCode::GoAway),
+            "CONTROLLER_FENCED_MASTER_EPOCH" => Ok(ResponseCode::ControllerFencedMasterEpoch),
+            "CONTROLLER_FENCED_SYNC_STATE_SET_EPOCH" => {
+                Ok(ResponseCode::ControllerFencedSyncStateSetEpoch)
+            }
+            "CONTROLLER_INVALID_MASTER" => Ok(ResponseCode::ControllerInvalidMaster),
+            "CONTROLLER_INVALID_REPLICAS" => Ok(ResponseCode::ControllerInvalidReplicas),
+            "CONTROLLER_MASTER_NOT_AVAILABLE" => Ok(ResponseCode::ControllerMasterNotAvailable),
+            "CONTROLLER_INVALID_REQUEST" => Ok(ResponseCode::ControllerInvalidRequest),
+            "CONTROLLER_BROKER_NOT_ALIVE" => Ok(ResponseCode::ControllerBrokerNotAlive),
+            "CONTROLLER_NOT_LEADER" => Ok(ResponseCode::ControllerNotLeader),
+            "CONTROLLER_BROKER_METADATA_NOT_EXIST" => {
+                Ok(ResponseCode::ControllerBrokerMetadataNotExist)
+            }
+            "CONTROLLER_INVALID_CLEAN_BROKER_METADATA" => {
+                Ok(ResponseCode::ControllerInvalidCleanBrokerMetadata)
+            }
+            "CONTROLLER_BROKER_NEED_TO_BE_REGISTERED" => {
+                Ok(ResponseCode::ControllerBrokerNeedToBeRegistered)
+            }
+            "CONTROLLER_MASTER_STILL_EXIST" => Ok(ResponseCode::ControllerMasterStillExist),
+            "CONTROLLER_ELECT_MASTER_FAILED" => Ok(ResponseCode::ControllerElectMasterFailed),
+            "CONTROLLER_ALTER_SYNC_STATE_SET_FAILED" => {
+                Ok(ResponseCode::ControllerAlterSyncStateSetFailed)
+            }
+            "CONTROLLER_BROKER_ID_INVALID" => Ok(ResponseCode::ControllerBrokerIdInvalid),
+            "CONTROLLER_JRAFT_INTERNAL_ERROR" => Ok(ResponseCode::ControllerJraftInternalError),
+            "CONTROLLER_BROKER_LIVE_INFO_NOT_EXISTS" => {
+                Ok(ResponseCode::ControllerBrokerLiveInfoNotExists)
+            }
+            "USER_NOT_EXIST" => Ok(ResponseCode::UserNotExist),
+            "POLICY_NOT_EXIST" => Ok(ResponseCode::PolicyNotExist),
+            _ => Err(format!("unknown response code: {}", s)),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -280,6 +504,7 @@ mod tests {
             ResponseCode::BrokerDispatchNotComplete
         );
         assert_eq!(ResponseCode::from(213), ResponseCode::BroadcastConsumption);
+        assert_eq!(ResponseCode::from(214), ResponseCode::NoBuffer);
         assert_eq!(ResponseCode::from(215), ResponseCode::FlowControl);
         assert_eq!(ResponseCode::from(501), ResponseCode::NotLeaderForQueue);
         assert_eq!(ResponseCode::from(604), ResponseCode::IllegalOperation);
@@ -348,6 +573,48 @@ mod tests {
             ResponseCode::from(2014),
             ResponseCode::ControllerBrokerIdInvalid
         );
+        assert_eq!(
+            ResponseCode::from(2015),
+            ResponseCode::ControllerJraftInternalError
+        );
+        assert_eq!(
+            ResponseCode::from(2016),
+            ResponseCode::ControllerBrokerLiveInfoNotExists
+        );
+        assert_eq!(ResponseCode::from(3001), ResponseCode::UserNotExist);
+        assert_eq!(ResponseCode::from(3002), ResponseCode::PolicyNotExist);
         assert_eq!(ResponseCode::from(9999), ResponseCode::SystemError); // Edge case
     }
+
+    #[test]
+    fn response_code_name_round_trip() {
+        assert_eq!(ResponseCode::PollingFull.as_str(), "POLLING_FULL");
+        assert_eq!(ResponseCode::RpcTimeOut.as_str(), "RPC_TIME_OUT");
+        assert_eq!(ResponseCode::NoBuffer.to_string(), "NO_BUFFER");
+        assert_eq!(
+            "POLLING_TIMEOUT".parse::<ResponseCode>(),
+            Ok(ResponseCode::PollingTimeout)
+        );
+        assert!("NOT_A_CODE".parse::<ResponseCode>().is_err());
+    }
+
+    #[test]
+    fn response_code_remark_is_machine_readable() {
+        let remark = ResponseCode::PollingFull.remark("the broker is busy");
+        assert_eq!(remark, "[POLLING_FULL] the broker is busy");
+        assert_eq!(
+            ResponseCode::from_remark(&remark),
+            Some(ResponseCode::PollingFull)
+        );
+        assert_eq!(ResponseCode::from_remark("the broker is busy"), None);
+        assert_eq!(ResponseCode::from_remark("[UNKNOWN] x"), None);
+    }
+
+    #[test]
+    fn response_code_retriable() {
+        assert!(ResponseCode::PollingFull.is_retriable());
+        assert!(ResponseCode::FlowControl.is_retriable());
+        assert!(!ResponseCode::NoPermission.is_retriable());
+        assert!(!ResponseCode::Success.is_retriable());
+    }
 }
@@ -15,6 +15,9 @@
  * limitations under the License.
  */
 
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
 use bytes::BufMut;
 use bytes::BytesMut;
 use tokio_util::codec::BytesCodec;
@@ -24,6 +27,16 @@ use tokio_util::codec::Encoder;
 use crate::protocol::remoting_command::RemotingCommand;
 use crate::remoting_error::RemotingError;
 
+/// Frames that failed to decode since the process started, every one of them closed its
+/// connection.
+static MALFORMED_FRAME_COUNT: AtomicU64 = AtomicU64::new(0);
+
+/// Number of malformed frames received on any connection, a rising count usually means the
+/// port is reached by something that does not speak the RocketMQ protocol.
+pub fn malformed_frame_count() -> u64 {
+    MALFORMED_FRAME_COUNT.load(Ordering::Relaxed)
+}
+
 /// Encodes a `RemotingCommand` into a `BytesMut` buffer.
 ///
 /// This method takes a `RemotingCommand` and a mutable reference to a `BytesMut` buffer as
@@ -88,9 +101,14 @@ impl Decoder for RemotingCommandCodec {
     ///
     /// # Errors
     ///
-    /// This function will return an error if the decoding process fails.
+    /// This function will return an error if the decoding process fails, e.g. when the frame
+    /// or header length is out of range or the header is malformed. The stream can not be
+    /// resynchronized after that, so callers are expected to close the connection.
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        RemotingCommand::decode(src)
+        RemotingCommand::decode(src).map_err(|e| {
+            MALFORMED_FRAME_COUNT.fetch_add(1, Ordering::Relaxed);
+            e
+        })
     }
 }
 
@@ -180,10 +198,48 @@ mod tests {
     #[tokio::test]
     async fn decode_handles_sufficient_data() {
         let mut decoder = RemotingCommandCodec::new();
-        let mut src = BytesMut::from(&[0, 0, 0, 1, 0, 0, 0, 0][..]);
+        let mut src = BytesMut::from(&[0, 0, 0, 8, 0, 0, 0, 0][..]);
         assert!(matches!(decoder.decode(&mut src), Ok(None)));
     }
 
+    #[tokio::test]
+    async fn decode_rejects_malformed_frames() {
+        let mut decoder = RemotingCommandCodec::new();
+        let before = malformed_frame_count();
+        let frames: [&[u8]; 5] = [
+            // shorter than the header length field
+            &[0, 0, 0, 1, 0, 0, 0, 0],
+            // negative frame length
+            &[0xFF, 0xFF, 0xFF, 0xFF, 0, 0, 0, 0],
+            // beyond the frame limit
+            &[0x7F, 0xFF, 0xFF, 0xFF],
+            // header longer than the frame
+            &[0, 0, 0, 6, 0, 0, 0, 9, 0, 0],
+            // unknown serialize type
+            &[0, 0, 0, 4, 7, 0, 0, 0],
+        ];
+        for frame in frames {
+            let mut src = BytesMut::from(frame);
+            assert!(decoder.decode(&mut src).is_err(), "{:?}", frame);
+        }
+        assert!(malformed_frame_count() >= before + frames.len() as u64);
+    }
+
+    #[tokio::test]
+    async fn decode_round_trips_encoded_command() {
+        let mut codec = RemotingCommandCodec::new();
+        let mut dst = BytesMut::new();
+        let command = RemotingCommand::create_remoting_command(1)
+            .set_opaque(7)
+            .set_body(Bytes::from("body"))
+            .set_remark_option(Some("remark".to_string()));
+        codec.encode(command, &mut dst).unwrap();
+        let decoded = codec.decode(&mut dst).unwrap().unwrap();
+        assert_eq!(decoded.opaque(), 7);
+        assert_eq!(decoded.get_body(), Some(&Bytes::from("body")));
+        assert!(dst.is_empty());
+    }
+
     #[tokio::test]
     async fn encode_handles_empty_body() {
         let mut encoder = RemotingCommandCodec::new();
@@ -148,4 +148,11 @@ impl Connection {
         self.writer.send(self.buf.clone()).await?;
         Ok(())
     }
+
+    /// Closes the write half of the connection, the peer sees the end of the stream.
+    pub async fn close(&mut self) -> Result<(), RemotingError> {
+        self.ok = false;
+        self.writer.close().await?;
+        Ok(())
+    }
 }
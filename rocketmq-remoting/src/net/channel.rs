@@ -241,6 +241,14 @@ impl Channel {
         Ok(())
     }
 
+    /// Queues a response on the writer of the channel, behind the commands queued before it.
+    pub async fn send_response(&self, response: RemotingCommand) -> Result<()> {
+        if let Err(err) = self.tx.send((response, None, None)).await {
+            return Err(ChannelSendRequestFailed(err.to_string()));
+        }
+        Ok(())
+    }
+
     pub async fn send(&mut self, request: RemotingCommand) -> Result<()> {
         let request = request.mark_oneway_rpc();
         if let Err(err) = self.tx.send((request, None, None)).await {
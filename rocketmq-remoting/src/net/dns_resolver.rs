@@ -0,0 +1,150 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+use std::time::Instant;
+
+use parking_lot::Mutex;
+use rand::seq::SliceRandom;
+use tracing::warn;
+
+struct CachedAddrs {
+    addrs: Vec<SocketAddr>,
+    expire_at: Instant,
+}
+
+/// Resolves `host:port` addresses of name servers and brokers without blocking the reactor.
+///
+/// Resolved addresses are cached for `ttl`, and returned in random order so that clients of a
+/// host name backed by several records spread over them. An address that fails to resolve falls
+/// back to its expired addresses, if any.
+pub struct DnsResolver {
+    ttl: Duration,
+    cache: Mutex<HashMap<String, CachedAddrs>>,
+}
+
+impl DnsResolver {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub async fn resolve(&self, addr: &str) -> io::Result<Vec<SocketAddr>> {
+        if let Ok(socket_addr) = addr.parse::<SocketAddr>() {
+            return Ok(vec![socket_addr]);
+        }
+        let now = Instant::now();
+        let cached = {
+            let cache = self.cache.lock();
+            match cache.get(addr) {
+                Some(cached) if cached.expire_at > now => {
+                    return Ok(shuffled(&cached.addrs));
+                }
+                Some(cached) => Some(cached.addrs.clone()),
+                None => None,
+            }
+        };
+        let result = tokio::net::lookup_host(addr).await;
+        match result.map(|addrs| addrs.collect::<Vec<_>>()) {
+            Ok(addrs) if !addrs.is_empty() => {
+                if !self.ttl.is_zero() {
+                    self.cache.lock().insert(
+                        addr.to_string(),
+                        CachedAddrs {
+                            addrs: addrs.clone(),
+                            expire_at: now + self.ttl,
+                        },
+                    );
+                }
+                Ok(shuffled(&addrs))
+            }
+            result => {
+                let err = match result {
+                    Err(err) => err,
+                    Ok(_) => io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("no address found for {}", addr),
+                    ),
+                };
+                match cached {
+                    Some(addrs) => {
+                        warn!(
+                            "resolve {} failed, use the expired addresses: {}",
+                            addr, err
+                        );
+                        Ok(shuffled(&addrs))
+                    }
+                    None => Err(err),
+                }
+            }
+        }
+    }
+
+    /// Forgets the addresses of `addr`, so the next connection resolves it again.
+    pub fn evict(&self, addr: &str) {
+        self.cache.lock().remove(addr);
+    }
+}
+
+fn shuffled(addrs: &[SocketAddr]) -> Vec<SocketAddr> {
+    let mut addrs = addrs.to_vec();
+    addrs.shuffle(&mut rand::thread_rng());
+    addrs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn resolves_socket_addresses_directly() {
+        let resolver = DnsResolver::new(Duration::from_secs(30));
+        let addrs = resolver.resolve("127.0.0.1:9876").await.unwrap();
+        assert_eq!(addrs, vec!["127.0.0.1:9876".parse().unwrap()]);
+        assert!(resolver.cache.lock().is_empty());
+    }
+
+    #[tokio::test]
+    async fn caches_resolved_host_names() {
+        let resolver = DnsResolver::new(Duration::from_secs(30));
+        let addrs = resolver.resolve("localhost:9876").await.unwrap();
+        assert!(addrs.iter().all(|addr| addr.port() == 9876));
+        assert!(resolver.cache.lock().contains_key("localhost:9876"));
+
+        resolver.evict("localhost:9876");
+        assert!(resolver.cache.lock().is_empty());
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_expired_addresses() {
+        let resolver = DnsResolver::new(Duration::from_secs(30));
+        let addr: SocketAddr = "10.0.0.1:9876".parse().unwrap();
+        resolver.cache.lock().insert(
+            "unknown.invalid:9876".to_string(),
+            CachedAddrs {
+                addrs: vec![addr],
+                expire_at: Instant::now(),
+            },
+        );
+        let addrs = resolver.resolve("unknown.invalid:9876").await.unwrap();
+        assert_eq!(addrs, vec![addr]);
+    }
+}
@@ -42,13 +42,20 @@ pub mod kv_table;
 pub mod pop_process_queue_info;
 pub mod process_queue_info;
 pub mod producer_connection;
+pub mod producer_info;
+pub mod producer_table_info;
 pub mod query_assignment_request_body;
 pub mod query_assignment_response_body;
 pub mod query_consume_queue_response_body;
+pub mod query_consume_time_span_body;
+pub mod query_correction_offset_body;
 pub mod queue_time_span;
 pub mod request;
+pub mod reset_offset_body;
 pub mod response;
 pub mod set_message_request_mode_request_body;
+pub mod stuck_queue_info;
+pub mod sync_state_set;
 pub mod topic;
 pub mod topic_info_wrapper;
 pub mod unlock_batch_request_body;
@@ -14,5 +14,70 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
-#[derive(Clone)]
-pub struct ConsumerRunningInfo {}
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use cheetah_string::CheetahString;
+use rocketmq_common::common::message::message_queue::MessageQueue;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json_any_key::*;
+
+use crate::protocol::body::process_queue_info::ProcessQueueInfo;
+use crate::protocol::body::stuck_queue_info::StuckQueueInfo;
+use crate::protocol::heartbeat::subscription_data::SubscriptionData;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsumerRunningInfo {
+    pub properties: HashMap<CheetahString, CheetahString>,
+    pub subscription_set: HashSet<SubscriptionData>,
+    #[serde(with = "any_key_map")]
+    pub mq_table: HashMap<MessageQueue, ProcessQueueInfo>,
+    /// Queues whose pull or consume has not progressed for the configured time.
+    #[serde(with = "any_key_map")]
+    pub stuck_queue_table: HashMap<MessageQueue, StuckQueueInfo>,
+    pub jstack: Option<CheetahString>,
+}
+
+impl ConsumerRunningInfo {
+    pub const PROP_NAMESERVER_ADDR: &'static str = "PROP_NAMESERVER_ADDR";
+    pub const PROP_THREADPOOL_CORE_SIZE: &'static str = "PROP_THREADPOOL_CORE_SIZE";
+    pub const PROP_CONSUME_ORDERLY: &'static str = "PROP_CONSUMEORDERLY";
+    pub const PROP_CONSUME_TYPE: &'static str = "PROP_CONSUME_TYPE";
+    pub const PROP_CLIENT_VERSION: &'static str = "PROP_CLIENT_VERSION";
+    pub const PROP_CONSUMER_START_TIMESTAMP: &'static str = "PROP_CONSUMER_START_TIMESTAMP";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::body::stuck_queue_info::StuckReason;
+
+    #[test]
+    fn consumer_running_info_round_trip() {
+        let mq = MessageQueue::from_parts("topic", "broker-a", 1);
+        let mut info = ConsumerRunningInfo::default();
+        info.mq_table
+            .insert(mq.clone(), ProcessQueueInfo::default());
+        info.stuck_queue_table.insert(
+            mq.clone(),
+            StuckQueueInfo {
+                reason: StuckReason::Consume,
+                stuck_millis: 600_000,
+                cached_msg_min_offset: 10,
+                cached_msg_max_offset: 20,
+                cached_msg_count: 11,
+                last_pull_error: None,
+            },
+        );
+        let json = serde_json::to_string(&info).unwrap();
+        assert!(json.contains("\"CONSUME\""));
+        let decoded: ConsumerRunningInfo = serde_json::from_str(&json).unwrap();
+        assert!(decoded.mq_table.contains_key(&mq));
+        assert_eq!(
+            decoded.stuck_queue_table.get(&mq).unwrap().cached_msg_count,
+            11
+        );
+    }
+}
@@ -0,0 +1,50 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use cheetah_string::CheetahString;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::protocol::LanguageCode;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ProducerInfo {
+    pub client_id: CheetahString,
+    #[serde(rename = "remoteIP")]
+    pub remote_ip: CheetahString,
+    pub language: LanguageCode,
+    pub version: i32,
+    pub last_update_timestamp: u64,
+}
+
+impl ProducerInfo {
+    pub fn new(
+        client_id: CheetahString,
+        remote_ip: CheetahString,
+        language: LanguageCode,
+        version: i32,
+        last_update_timestamp: u64,
+    ) -> Self {
+        Self {
+            client_id,
+            remote_ip,
+            language,
+            version,
+            last_update_timestamp,
+        }
+    }
+}
@@ -0,0 +1,61 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+
+use cheetah_string::CheetahString;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::protocol::body::producer_info::ProducerInfo;
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ProducerTableInfo {
+    pub data: HashMap<CheetahString, Vec<ProducerInfo>>,
+}
+
+impl ProducerTableInfo {
+    pub fn new(data: HashMap<CheetahString, Vec<ProducerInfo>>) -> Self {
+        Self { data }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::LanguageCode;
+
+    #[test]
+    fn serialize_producer_table_info() {
+        let mut data = HashMap::new();
+        data.insert(
+            CheetahString::from_static_str("group"),
+            vec![ProducerInfo::new(
+                CheetahString::from_static_str("client"),
+                CheetahString::from_static_str("127.0.0.1:1234"),
+                LanguageCode::JAVA,
+                1,
+                2,
+            )],
+        );
+        let json = serde_json::to_string(&ProducerTableInfo::new(data)).unwrap();
+        assert!(json.contains("\"data\":{\"group\":[{"));
+        assert!(json.contains("\"clientId\":\"client\""));
+        assert!(json.contains("\"remoteIP\":\"127.0.0.1:1234\""));
+        assert!(json.contains("\"lastUpdateTimestamp\":2"));
+    }
+}
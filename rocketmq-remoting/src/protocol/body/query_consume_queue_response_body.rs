@@ -24,11 +24,11 @@ use crate::protocol::heartbeat::subscription_data::SubscriptionData;
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct QueryConsumeQueueResponseBody {
-    subscription_data: SubscriptionData,
-    filter_data: CheetahString,
-    queue_data: Vec<ConsumeQueueData>,
-    max_queue_index: i64,
-    min_queue_index: i64,
+    pub subscription_data: SubscriptionData,
+    pub filter_data: CheetahString,
+    pub queue_data: Vec<ConsumeQueueData>,
+    pub max_queue_index: i64,
+    pub min_queue_index: i64,
 }
 
 #[cfg(test)]
@@ -0,0 +1,68 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::fmt::Display;
+
+use cheetah_string::CheetahString;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Why a process queue of a push consumer is considered stuck.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum StuckReason {
+    /// No pull of the queue got a response from the broker.
+    Pull,
+    /// Cached messages of the queue are not being consumed.
+    Consume,
+}
+
+impl Display for StuckReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StuckReason::Pull => write!(f, "PULL"),
+            StuckReason::Consume => write!(f, "CONSUME"),
+        }
+    }
+}
+
+/// Diagnostics of a process queue whose pull or consume has not progressed for a while.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StuckQueueInfo {
+    pub reason: StuckReason,
+    pub stuck_millis: u64,
+    pub cached_msg_min_offset: i64,
+    pub cached_msg_max_offset: i64,
+    pub cached_msg_count: u64,
+    pub last_pull_error: Option<CheetahString>,
+}
+
+impl Display for StuckQueueInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "StuckQueueInfo [reason: {}, stuck_millis: {}, cached_msg_min_offset: {}, \
+             cached_msg_max_offset: {}, cached_msg_count: {}, last_pull_error: {}]",
+            self.reason,
+            self.stuck_millis,
+            self.cached_msg_min_offset,
+            self.cached_msg_max_offset,
+            self.cached_msg_count,
+            self.last_pull_error.as_deref().unwrap_or("")
+        )
+    }
+}
@@ -16,3 +16,4 @@
  */
 
 pub mod topic_list;
+pub mod topic_route_data_batch;
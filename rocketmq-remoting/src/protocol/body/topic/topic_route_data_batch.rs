@@ -0,0 +1,55 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+
+use cheetah_string::CheetahString;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::protocol::route::topic_route_data::TopicRouteData;
+
+/// Response body of `GetRouteinfoByTopicList`, topics without route are left out.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TopicRouteDataBatch {
+    pub topic_route_data_table: HashMap<CheetahString, TopicRouteData>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::RemotingDeserializable;
+    use crate::protocol::RemotingSerializable;
+
+    #[test]
+    fn encode_and_decode() {
+        let batch = TopicRouteDataBatch {
+            topic_route_data_table: HashMap::from([(
+                "topic".into(),
+                TopicRouteData {
+                    order_topic_conf: Some("broker-a:4".into()),
+                    ..Default::default()
+                },
+            )]),
+        };
+        let decoded = TopicRouteDataBatch::decode(&batch.encode().unwrap()).unwrap();
+        assert_eq!(
+            decoded.topic_route_data_table["topic"].order_topic_conf,
+            Some("broker-a:4".into())
+        );
+    }
+}
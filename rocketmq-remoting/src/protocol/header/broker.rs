@@ -1 +1,3 @@
+pub mod add_broker_request_header;
 pub mod broker_heartbeat_request_header;
+pub mod remove_broker_request_header;
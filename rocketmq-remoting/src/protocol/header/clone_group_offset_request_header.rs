@@ -0,0 +1,81 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use cheetah_string::CheetahString;
+use rocketmq_macros::RequestHeaderCodec;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::rpc::rpc_request_header::RpcRequestHeader;
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default, RequestHeaderCodec)]
+#[serde(rename_all = "camelCase")]
+pub struct CloneGroupOffsetRequestHeader {
+    #[required]
+    pub src_group: CheetahString,
+
+    #[required]
+    pub dest_group: CheetahString,
+
+    pub topic: Option<CheetahString>,
+
+    pub offline: bool,
+
+    #[serde(flatten)]
+    pub rpc_request_header: Option<RpcRequestHeader>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use cheetah_string::CheetahString;
+
+    use super::*;
+    use crate::protocol::command_custom_header::FromMap;
+
+    #[test]
+    fn clone_group_offset_request_header_from_map() {
+        let mut map = HashMap::new();
+        map.insert(
+            CheetahString::from_static_str("srcGroup"),
+            CheetahString::from_static_str("group_a"),
+        );
+        map.insert(
+            CheetahString::from_static_str("destGroup"),
+            CheetahString::from_static_str("group_b"),
+        );
+        map.insert(
+            CheetahString::from_static_str("offline"),
+            CheetahString::from_static_str("true"),
+        );
+        let header = <CloneGroupOffsetRequestHeader as FromMap>::from(&map).unwrap();
+        assert_eq!(header.src_group, "group_a");
+        assert_eq!(header.dest_group, "group_b");
+        assert!(header.topic.is_none());
+        assert!(header.offline);
+    }
+
+    #[test]
+    fn clone_group_offset_request_header_missing_dest_group() {
+        let mut map = HashMap::new();
+        map.insert(
+            CheetahString::from_static_str("srcGroup"),
+            CheetahString::from_static_str("group_a"),
+        );
+        assert!(<CloneGroupOffsetRequestHeader as FromMap>::from(&map).is_err());
+    }
+}
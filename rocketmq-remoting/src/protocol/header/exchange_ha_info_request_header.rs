@@ -0,0 +1,33 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use cheetah_string::CheetahString;
+use rocketmq_macros::RequestHeaderCodec;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Header of the request exchanging the HA addresses of a broker group. Without a master HA
+/// address the receiver answers with its own in an [`ExchangeHAInfoResponseHeader`], otherwise it
+/// replicates from the given master.
+///
+/// [`ExchangeHAInfoResponseHeader`]: crate::protocol::header::exchange_ha_info_response_header::ExchangeHAInfoResponseHeader
+#[derive(Debug, Clone, Serialize, Deserialize, Default, RequestHeaderCodec)]
+#[serde(rename_all = "camelCase")]
+pub struct ExchangeHAInfoRequestHeader {
+    pub master_ha_address: Option<CheetahString>,
+    pub master_flush_offset: Option<i64>,
+    pub master_address: Option<CheetahString>,
+}
@@ -31,6 +31,10 @@ pub struct SendMessageResponseHeader {
     queue_offset: i64,
     transaction_id: Option<CheetahString>,
     batch_uniq_id: Option<CheetahString>,
+    /// Milliseconds the broker spent storing the message, including flush and replication.
+    store_rt: Option<i64>,
+    /// Milliseconds the broker spent appending the message to the page cache.
+    page_cache_rt: Option<i64>,
 }
 
 impl SendMessageResponseHeader {
@@ -47,6 +51,8 @@ impl SendMessageResponseHeader {
             queue_offset,
             transaction_id,
             batch_uniq_id,
+            store_rt: None,
+            page_cache_rt: None,
         }
     }
 
@@ -70,6 +76,14 @@ impl SendMessageResponseHeader {
         self.batch_uniq_id.as_deref()
     }
 
+    pub fn store_rt(&self) -> Option<i64> {
+        self.store_rt
+    }
+
+    pub fn page_cache_rt(&self) -> Option<i64> {
+        self.page_cache_rt
+    }
+
     pub fn set_msg_id(&mut self, msg_id: impl Into<CheetahString>) {
         self.msg_id = msg_id.into();
     }
@@ -89,6 +103,14 @@ impl SendMessageResponseHeader {
     pub fn set_batch_uniq_id(&mut self, batch_uniq_id: Option<CheetahString>) {
         self.batch_uniq_id = batch_uniq_id;
     }
+
+    pub fn set_store_rt(&mut self, store_rt: Option<i64>) {
+        self.store_rt = store_rt;
+    }
+
+    pub fn set_page_cache_rt(&mut self, page_cache_rt: Option<i64>) {
+        self.page_cache_rt = page_cache_rt;
+    }
 }
 
 impl FastCodesHeader for SendMessageResponseHeader {
@@ -99,13 +121,19 @@ impl FastCodesHeader for SendMessageResponseHeader {
         Self::write_if_not_null(
             out,
             "transactionId",
-            self.transaction_id.clone().as_deref().unwrap(),
+            self.transaction_id.as_deref().unwrap_or_default(),
         );
         Self::write_if_not_null(
             out,
             "batchUniqId",
-            self.batch_uniq_id.clone().as_deref().unwrap(),
+            self.batch_uniq_id.as_deref().unwrap_or_default(),
         );
+        if let Some(store_rt) = self.store_rt {
+            Self::write_if_not_null(out, "storeRt", store_rt.to_string().as_str());
+        }
+        if let Some(page_cache_rt) = self.page_cache_rt {
+            Self::write_if_not_null(out, "pageCacheRt", page_cache_rt.to_string().as_str());
+        }
     }
 
     fn decode_fast(&mut self, fields: &HashMap<CheetahString, CheetahString>) {
@@ -128,5 +156,13 @@ impl FastCodesHeader for SendMessageResponseHeader {
         if let Some(str) = fields.get(&CheetahString::from_slice("batchUniqId")) {
             self.batch_uniq_id = Some(str.clone());
         }
+
+        if let Some(str) = fields.get(&CheetahString::from_slice("storeRt")) {
+            self.store_rt = str.parse::<i64>().ok();
+        }
+
+        if let Some(str) = fields.get(&CheetahString::from_slice("pageCacheRt")) {
+            self.page_cache_rt = str.parse::<i64>().ok();
+        }
     }
 }
@@ -0,0 +1,66 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use cheetah_string::CheetahString;
+use rocketmq_macros::RequestHeaderCodec;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Header of the request the controller sends to the brokers of a group once it elected a
+/// master, the body carries the [`SyncStateSet`] of the group.
+///
+/// [`SyncStateSet`]: crate::protocol::body::sync_state_set::SyncStateSet
+#[derive(Debug, Clone, Serialize, Deserialize, Default, RequestHeaderCodec)]
+#[serde(rename_all = "camelCase")]
+pub struct NotifyBrokerRoleChangedRequestHeader {
+    pub master_address: Option<CheetahString>,
+
+    #[required]
+    pub master_epoch: i32,
+
+    #[required]
+    pub sync_state_set_epoch: i32,
+
+    #[required]
+    pub master_broker_id: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::command_custom_header::CommandCustomHeader;
+    use crate::protocol::command_custom_header::FromMap;
+
+    #[test]
+    fn notify_broker_role_changed_request_header_round_trips_through_map() {
+        let header = NotifyBrokerRoleChangedRequestHeader {
+            master_address: Some(CheetahString::from("127.0.0.1:10912")),
+            master_epoch: 3,
+            sync_state_set_epoch: 5,
+            master_broker_id: 2,
+        };
+        let map = header.to_map().unwrap();
+        assert_eq!(
+            map.get(&CheetahString::from_static_str("masterEpoch")),
+            Some(&CheetahString::from("3"))
+        );
+        let decoded = <NotifyBrokerRoleChangedRequestHeader as FromMap>::from(&map).unwrap();
+        assert_eq!(decoded.master_address, header.master_address);
+        assert_eq!(decoded.master_epoch, 3);
+        assert_eq!(decoded.sync_state_set_epoch, 5);
+        assert_eq!(decoded.master_broker_id, 2);
+    }
+}
@@ -47,6 +47,8 @@ pub struct PopMessageRequestHeader {
     pub exp: Option<CheetahString>,
     pub order: Option<bool>,
     pub attempt_id: Option<CheetahString>,
+    /// Whether the client can decompress a message block compressed by the broker.
+    pub accept_compression: Option<bool>,
 
     #[serde(flatten)]
     pub topic_request_header: Option<TopicRequestHeader>,
@@ -73,6 +75,7 @@ impl Default for PopMessageRequestHeader {
             exp: None,
             order: Some(false),
             attempt_id: None,
+            accept_compression: None,
             topic_request_header: None,
         }
     }
@@ -140,6 +143,7 @@ mod tests {
             exp: Some(CheetahString::from("exp1")),
             order: Some(true),
             attempt_id: Some(CheetahString::from("attempt1")),
+            accept_compression: None,
             topic_request_header: None,
         };
         assert_eq!(
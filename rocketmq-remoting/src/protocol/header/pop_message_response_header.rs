@@ -47,6 +47,13 @@ pub struct PopMessageResponseHeader {
 
     #[serde(rename = "orderCountInfo", skip_serializing_if = "Option::is_none")]
     pub order_count_info: Option<CheetahString>,
+
+    /// Compression type of the message block, absent when the body is not compressed.
+    #[serde(
+        rename = "bodyCompressionType",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub body_compression_type: Option<i32>,
 }
 
 impl Display for PopMessageResponseHeader {
@@ -80,6 +87,7 @@ mod tests {
             start_offset_info: Some("start_offset".into()),
             msg_offset_info: Some("msg_offset".into()),
             order_count_info: Some("order_count".into()),
+            body_compression_type: None,
         };
         let expected = "PopMessageResponseHeader [pop_time=123456789, invisible_time=987654321, \
                         revive_qid=42, rest_num=10, start_offset_info=Some(\"start_offset\"), \
@@ -98,6 +106,7 @@ mod tests {
             start_offset_info: None,
             msg_offset_info: None,
             order_count_info: None,
+            body_compression_type: None,
         };
         let expected = "PopMessageResponseHeader [pop_time=123456789, invisible_time=987654321, \
                         revive_qid=42, rest_num=10, start_offset_info=None, msg_offset_info=None, \
@@ -115,6 +124,7 @@ mod tests {
             start_offset_info: Some("start_offset".into()),
             msg_offset_info: Some("msg_offset".into()),
             order_count_info: Some("order_count".into()),
+            body_compression_type: None,
         };
         let json = serde_json::to_string(&header).unwrap();
         let expected = r#"{"popTime":123456789,"invisibleTime":987654321,"reviveQid":42,"restNum":10,"startOffsetInfo":"start_offset","msgOffsetInfo":"msg_offset","orderCountInfo":"order_count"}"#;
@@ -54,11 +54,14 @@ pub struct PullMessageRequestHeader {
     pub max_msg_bytes: Option<i32>,
     pub request_source: Option<i32>,
     pub proxy_forward_client_id: Option<CheetahString>,
+    /// Whether the client can decompress a message block compressed by the broker.
+    pub accept_compression: Option<bool>,
     #[serde(flatten)]
     pub topic_request: Option<TopicRequestHeader>,
 }
 
 impl PullMessageRequestHeader {
+    const ACCEPT_COMPRESSION: &'static str = "acceptCompression";
     const COMMIT_OFFSET: &'static str = "commitOffset";
     const CONSUMER_GROUP: &'static str = "consumerGroup";
     const EXPRESSION_TYPE: &'static str = "expressionType";
@@ -151,6 +154,12 @@ impl CommandCustomHeader for PullMessageRequestHeader {
                 value.clone(),
             );
         }
+        if let Some(value) = self.accept_compression {
+            map.insert(
+                CheetahString::from_static_str(Self::ACCEPT_COMPRESSION),
+                CheetahString::from_string(value.to_string()),
+            );
+        }
 
         if let Some(ref rpc) = self.topic_request {
             if let Some(rpc_map) = rpc.to_map() {
@@ -209,6 +218,9 @@ impl CommandCustomHeader for PullMessageRequestHeader {
         if let Some(ref value) = self.proxy_forward_client_id {
             self.write_if_not_null(out, Self::PROXY_FORWARD_CLIENT_ID, value.as_str());
         }
+        if let Some(value) = self.accept_compression {
+            self.write_if_not_null(out, Self::ACCEPT_COMPRESSION, value.to_string().as_str());
+        }
 
         // Assuming "lo", "ns", "nsd", "bname", "oway" are other fields in the struct
         if let Some(ref value) = self.topic_request {
@@ -311,6 +323,10 @@ impl CommandCustomHeader for PullMessageRequestHeader {
             ))
             .cloned();
 
+        self.accept_compression = fields
+            .get(&CheetahString::from_static_str(Self::ACCEPT_COMPRESSION))
+            .and_then(|value| value.parse::<bool>().ok());
+
         self.topic_request = Some(TopicRequestHeader {
             rpc: Some(RpcRequestHeader::default()),
             ..TopicRequestHeader::default()
@@ -437,6 +453,9 @@ impl FromMap for PullMessageRequestHeader {
                     Self::PROXY_FORWARD_CLIENT_ID,
                 ))
                 .cloned(),
+            accept_compression: map
+                .get(&CheetahString::from_static_str(Self::ACCEPT_COMPRESSION))
+                .and_then(|value| value.parse::<bool>().ok()),
             topic_request: Some(<TopicRequestHeader as FromMap>::from(map)?),
         })
     }
@@ -575,6 +594,7 @@ mod tests {
             max_msg_bytes: Some(1024),
             request_source: Some(1),
             proxy_forward_client_id: Some(CheetahString::from_static_str("test_client_id")),
+            accept_compression: Some(true),
             topic_request: None,
         };
         let map = header.to_map().unwrap();
@@ -645,6 +665,11 @@ mod tests {
                 .unwrap(),
             "test_client_id"
         );
+        assert_eq!(
+            map.get(&CheetahString::from_static_str("acceptCompression"))
+                .unwrap(),
+            "true"
+        );
     }
 
     #[test]
@@ -38,6 +38,8 @@ pub struct PullMessageResponseHeader {
     pub topic_sys_flag: Option<i32>,
     pub group_sys_flag: Option<i32>,
     pub forbidden_type: Option<i32>,
+    /// Compression type of the message block, absent when the body is not compressed.
+    pub body_compression_type: Option<i32>,
 }
 
 impl PullMessageResponseHeader {
@@ -49,6 +51,7 @@ impl PullMessageResponseHeader {
     pub const TOPIC_SYS_FLAG: &'static str = "topicSysFlag";
     pub const GROUP_SYS_FLAG: &'static str = "groupSysFlag";
     pub const FORBIDDEN_TYPE: &'static str = "forbiddenType";
+    pub const BODY_COMPRESSION_TYPE: &'static str = "bodyCompressionType";
 }
 
 impl CommandCustomHeader for PullMessageResponseHeader {
@@ -99,6 +102,12 @@ impl CommandCustomHeader for PullMessageResponseHeader {
                 CheetahString::from_string(value.to_string()),
             );
         }
+        if let Some(value) = self.body_compression_type {
+            map.insert(
+                CheetahString::from_static_str(Self::BODY_COMPRESSION_TYPE),
+                CheetahString::from_string(value.to_string()),
+            );
+        }
         Some(map)
     }
 
@@ -131,6 +140,9 @@ impl CommandCustomHeader for PullMessageResponseHeader {
         if let Some(value) = self.forbidden_type {
             self.write_if_not_null(out, Self::FORBIDDEN_TYPE, value.to_string().as_str());
         }
+        if let Some(value) = self.body_compression_type {
+            self.write_if_not_null(out, Self::BODY_COMPRESSION_TYPE, value.to_string().as_str());
+        }
     }
 
     fn decode_fast(&mut self, fields: &HashMap<CheetahString, CheetahString>) -> crate::Result<()> {
@@ -183,6 +195,10 @@ impl CommandCustomHeader for PullMessageResponseHeader {
             .get(&CheetahString::from_static_str(Self::FORBIDDEN_TYPE))
             .and_then(|v| v.parse().ok());
 
+        self.body_compression_type = fields
+            .get(&CheetahString::from_static_str(Self::BODY_COMPRESSION_TYPE))
+            .and_then(|v| v.parse().ok());
+
         Ok(())
     }
 
@@ -221,6 +237,9 @@ impl FromMap for PullMessageResponseHeader {
         let forbidden_type = map.get(&CheetahString::from_static_str(
             PullMessageResponseHeader::FORBIDDEN_TYPE,
         ));
+        let body_compression_type = map.get(&CheetahString::from_static_str(
+            PullMessageResponseHeader::BODY_COMPRESSION_TYPE,
+        ));
 
         Ok(PullMessageResponseHeader {
             suggest_which_broker_id: suggest_which_broker_id.and_then(|v| v.parse().ok()).ok_or(
@@ -243,6 +262,7 @@ impl FromMap for PullMessageResponseHeader {
             topic_sys_flag: topic_sys_flag.and_then(|v| v.parse().ok()),
             group_sys_flag: group_sys_flag.and_then(|v| v.parse().ok()),
             forbidden_type: forbidden_type.and_then(|v| v.parse().ok()),
+            body_compression_type: body_compression_type.and_then(|v| v.parse().ok()),
         })
     }
 }
@@ -266,6 +286,7 @@ mod tests {
             topic_sys_flag: Some(161718),
             group_sys_flag: Some(192021),
             forbidden_type: Some(222324),
+            body_compression_type: None,
         };
         let map = header.to_map().unwrap();
         assert_eq!(
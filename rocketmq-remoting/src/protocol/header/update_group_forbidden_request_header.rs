@@ -0,0 +1,83 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use cheetah_string::CheetahString;
+use rocketmq_macros::RequestHeaderCodec;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::rpc::topic_request_header::TopicRequestHeader;
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default, RequestHeaderCodec)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateGroupForbiddenRequestHeader {
+    #[required]
+    pub group: CheetahString,
+
+    #[required]
+    pub topic: CheetahString,
+
+    pub readable: Option<bool>,
+
+    #[serde(flatten)]
+    pub topic_request_header: Option<TopicRequestHeader>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use cheetah_string::CheetahString;
+
+    use super::*;
+    use crate::protocol::command_custom_header::FromMap;
+
+    #[test]
+    fn update_group_forbidden_request_header_from_map() {
+        let mut map = HashMap::new();
+        map.insert(
+            CheetahString::from_static_str("group"),
+            CheetahString::from_static_str("test_group"),
+        );
+        map.insert(
+            CheetahString::from_static_str("topic"),
+            CheetahString::from_static_str("test_topic"),
+        );
+        map.insert(
+            CheetahString::from_static_str("readable"),
+            CheetahString::from_static_str("false"),
+        );
+        let header = <UpdateGroupForbiddenRequestHeader as FromMap>::from(&map).unwrap();
+        assert_eq!(header.group, "test_group");
+        assert_eq!(header.topic, "test_topic");
+        assert_eq!(header.readable, Some(false));
+    }
+
+    #[test]
+    fn update_group_forbidden_request_header_without_readable() {
+        let mut map = HashMap::new();
+        map.insert(
+            CheetahString::from_static_str("group"),
+            CheetahString::from_static_str("test_group"),
+        );
+        map.insert(
+            CheetahString::from_static_str("topic"),
+            CheetahString::from_static_str("test_topic"),
+        );
+        let header = <UpdateGroupForbiddenRequestHeader as FromMap>::from(&map).unwrap();
+        assert!(header.readable.is_none());
+    }
+}
@@ -32,7 +32,7 @@ pub enum ConsumeType {
 }
 
 impl ConsumeType {
-    fn get_type_cn(&self) -> &'static str {
+    pub fn get_type_cn(&self) -> &'static str {
         match self {
             ConsumeType::ConsumeActively => "PULL",
             ConsumeType::ConsumePassively => "PUSH",
@@ -40,6 +40,7 @@ use tracing::error;
 use super::RemotingCommandType;
 use super::SerializeType;
 use crate::code::response_code::RemotingSysResponseCode;
+use crate::code::response_code::ResponseCode;
 use crate::protocol::command_custom_header::CommandCustomHeader;
 use crate::protocol::command_custom_header::FromMap;
 use crate::protocol::LanguageCode;
@@ -49,6 +50,9 @@ use crate::rocketmq_serializable::RocketMQSerializable;
 pub const SERIALIZE_TYPE_PROPERTY: &str = "rocketmq.serialize.type";
 pub const SERIALIZE_TYPE_ENV: &str = "ROCKETMQ_SERIALIZE_TYPE";
 pub const REMOTING_VERSION_KEY: &str = "rocketmq.remoting.version";
+/// Max length of a frame after its length field, the same 16 MB limit as
+/// `com.rocketmq.remoting.frameMaxLength` of the Java broker.
+pub const MAX_FRAME_LENGTH: usize = 16 * 1024 * 1024;
 
 lazy_static! {
     static ref requestId: Arc<AtomicI32> = Arc::new(AtomicI32::new(0));
@@ -216,6 +220,12 @@ impl RemotingCommand {
             .mark_response_type()
     }
 
+    /// Creates an error response whose remark carries the code name as a machine-readable
+    /// prefix, see [`ResponseCode::remark`].
+    pub fn create_error_response(code: ResponseCode, detail: impl fmt::Display) -> Self {
+        Self::create_response_command_with_code_remark(code, code.remark(detail))
+    }
+
     pub fn create_response_command() -> Self {
         Self::default()
             .set_code(RemotingSysResponseCode::Success)
@@ -490,19 +500,32 @@ impl RemotingCommand {
             return Ok(None);
         }
         //Read the total size as a big-endian i32 from the first 4 bytes.
-        let total_size = i32::from_be_bytes([src[0], src[1], src[2], src[3]]) as usize;
+        let total_size = i32::from_be_bytes([src[0], src[1], src[2], src[3]]);
+        // No peer sends frames beyond the limit, so anything else is garbage and the connection
+        // can not be resynchronized.
+        if total_size < 0 || total_size as usize > MAX_FRAME_LENGTH {
+            return Err(RemotingError::RemotingCommandDecoderError(format!(
+                "Frame length {} is out of range [4, {}]",
+                total_size, MAX_FRAME_LENGTH
+            )));
+        }
+        let total_size = total_size as usize;
 
         if read_to < total_size + 4 {
             // Wait for more data when the available data is less than the total size.
             return Ok(None);
         }
+        // A complete frame holds at least the header length.
+        if total_size < 4 {
+            return Err(RemotingError::RemotingCommandDecoderError(format!(
+                "Frame length {} is out of range [4, {}]",
+                total_size, MAX_FRAME_LENGTH
+            )));
+        }
         // Split the BytesMut to get the command data including the total size.
         let mut cmd_data = src.split_to(total_size + 4);
         // Discard the first i32 (total size).
         cmd_data.advance(4);
-        if cmd_data.remaining() < 4 {
-            return Ok(None);
-        }
         // Read the header length as a big-endian i32.
         let ori_header_length = cmd_data.get_i32();
         let header_length = parse_header_length(ori_header_length);
@@ -838,4 +861,18 @@ mod tests {
         println!("i={}", RemotingCommand::default().opaque);
         println!("i={}", RemotingCommand::default().opaque);
     }
+
+    #[test]
+    fn test_create_error_response() {
+        let response =
+            RemotingCommand::create_error_response(ResponseCode::PollingFull, "queue is full");
+        assert_eq!(response.code(), ResponseCode::PollingFull as i32);
+        assert!(response.is_response_type());
+        let remark = response.remark().unwrap();
+        assert_eq!(remark.as_str(), "[POLLING_FULL] queue is full");
+        assert_eq!(
+            ResponseCode::from_remark(remark.as_str()),
+            Some(ResponseCode::PollingFull)
+        );
+    }
 }
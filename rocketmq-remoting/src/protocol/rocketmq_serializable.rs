@@ -53,8 +53,10 @@ impl RocketMQSerializable {
         limit: usize,
     ) -> Result<Option<CheetahString>> {
         let len = if use_short_length {
+            Self::ensure_remaining(buf, 2)?;
             buf.get_u16() as usize
         } else {
+            Self::ensure_remaining(buf, 4)?;
             buf.get_u32() as usize
         };
 
@@ -65,6 +67,7 @@ impl RocketMQSerializable {
         if len > limit {
             return Err(RemotingError::DecodingError(len, limit));
         }
+        Self::ensure_remaining(buf, len)?;
 
         let bytes = buf.split_to(len).freeze(); // Convert BytesMut to Bytes
                                                 /*str::from_utf8(&bytes)
@@ -210,9 +213,19 @@ impl RocketMQSerializable {
         header_buffer: &mut BytesMut,
         header_len: usize,
     ) -> Result<RemotingCommand> {
+        // code, language, version, opaque and flag
+        Self::ensure_remaining(header_buffer, 13)?;
+        let code = header_buffer.get_i16();
+        let language_code = header_buffer.get_u8();
+        let language = LanguageCode::value_of(language_code).ok_or_else(|| {
+            RemotingError::RemotingCommandDecoderError(format!(
+                "Unknown language code {}",
+                language_code
+            ))
+        })?;
         let cmd = RemotingCommand::default()
-            .set_code(header_buffer.get_i16())
-            .set_language(LanguageCode::value_of(header_buffer.get_u8()).unwrap())
+            .set_code(code)
+            .set_language(language)
             .set_version(header_buffer.get_i16() as i32)
             .set_opaque(header_buffer.get_i32())
             .set_flag(header_buffer.get_i32());
@@ -220,9 +233,10 @@ impl RocketMQSerializable {
         let remark = Self::read_str(header_buffer, false, header_len)?;
 
         // HashMap<String, String> extFields
-        let ext_fields_length = header_buffer.get_i32() as usize;
+        Self::ensure_remaining(header_buffer, 4)?;
+        let ext_fields_length = header_buffer.get_u32() as usize;
         let ext = if ext_fields_length > 0 {
-            if ext_fields_length > header_len {
+            if ext_fields_length > header_len.min(header_buffer.remaining()) {
                 return Err(RemotingError::DecodingError(ext_fields_length, header_len));
             }
             Self::map_deserialize(header_buffer, ext_fields_length)?
@@ -238,16 +252,26 @@ impl RocketMQSerializable {
         len: usize,
     ) -> Result<HashMap<CheetahString, CheetahString>> {
         let mut map = HashMap::new();
-        let end_index = buffer.len() - len;
+        let Some(end_index) = buffer.len().checked_sub(len) else {
+            return Err(RemotingError::DecodingError(len, buffer.len()));
+        };
 
         while buffer.remaining() > end_index {
-            let key = Self::read_str(buffer, true, len)?.unwrap();
-            let value = Self::read_str(buffer, false, len)?.unwrap();
+            let key = Self::read_str(buffer, true, len)?.unwrap_or_default();
+            let value = Self::read_str(buffer, false, len)?.unwrap_or_default();
             map.insert(key, value);
         }
 
         Ok(map)
     }
+
+    /// Fails instead of panicking when a malformed header ends before the field being read.
+    fn ensure_remaining(buf: &BytesMut, len: usize) -> Result<()> {
+        if buf.remaining() < len {
+            return Err(RemotingError::DecodingError(len, buf.remaining()));
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -328,4 +352,29 @@ mod tests {
             [("key".into(), "value".into())].iter().cloned().collect()
         );
     }
+
+    #[test]
+    fn read_str_truncated() {
+        let mut buf = BytesMut::from(&[0, 0, 0, 4, 116, 101][..]);
+        assert!(RocketMQSerializable::read_str(&mut buf, false, 10).is_err());
+        let mut buf = BytesMut::from(&[0][..]);
+        assert!(RocketMQSerializable::read_str(&mut buf, true, 10).is_err());
+    }
+
+    #[test]
+    fn protocol_decode_rejects_malformed_header() {
+        // unknown language code
+        let mut buf = BytesMut::from(&[0, 1, 200, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0][..]);
+        assert!(RocketMQSerializable::rocket_mq_protocol_decode(&mut buf, 13).is_err());
+        // ends before the flag
+        let mut buf = BytesMut::from(&[0, 1, 0, 0, 0][..]);
+        assert!(RocketMQSerializable::rocket_mq_protocol_decode(&mut buf, 5).is_err());
+        // extFields longer than what is left of the header
+        let mut buf = BytesMut::from(
+            &[
+                0, 1, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 9, 0, 1,
+            ][..],
+        );
+        assert!(RocketMQSerializable::rocket_mq_protocol_decode(&mut buf, 23).is_err());
+    }
 }
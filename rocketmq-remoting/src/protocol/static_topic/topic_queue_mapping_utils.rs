@@ -17,6 +17,7 @@
 use rocketmq_common::common::mix_all;
 
 use crate::protocol::static_topic::logic_queue_mapping_item::LogicQueueMappingItem;
+use crate::remoting_error::RemotingError;
 
 pub struct TopicQueueMappingUtils;
 
@@ -80,6 +81,67 @@ impl TopicQueueMappingUtils {
         None
     }
 
+    /// Checks that `new_items` only changes what may change in the items of a logic queue: the
+    /// generations both lists hold must map to the same physical queue, and with an equal epoch
+    /// the leader must stay the same.
+    pub fn make_sure_logic_queue_mapping_item_immutable(
+        old_items: &[LogicQueueMappingItem],
+        new_items: &[LogicQueueMappingItem],
+        epoch_equal: bool,
+        is_clean: bool,
+    ) -> crate::Result<()> {
+        if old_items.is_empty() {
+            return Ok(());
+        }
+        if !is_clean && new_items.is_empty() {
+            return Err(RemotingError::IllegalArgument(
+                "Can't accept empty items when the old items are not empty".to_string(),
+            ));
+        }
+        let (mut i_old, mut i_new) = (0, 0);
+        while i_old < old_items.len() && i_new < new_items.len() {
+            let old_item = &old_items[i_old];
+            let new_item = &new_items[i_new];
+            if new_item.gen < old_item.gen {
+                // the earliest items may have been deleted concurrently
+                i_new += 1;
+            } else if old_item.gen < new_item.gen {
+                // the queue is mapped back to a broker which held it before, or the earliest
+                // item has been cleaned
+                i_old += 1;
+            } else {
+                if old_item.bname != new_item.bname
+                    || old_item.queue_id != new_item.queue_id
+                    || old_item.start_offset != new_item.start_offset
+                    || (old_item.logic_offset != -1
+                        && old_item.logic_offset != new_item.logic_offset)
+                {
+                    return Err(RemotingError::IllegalArgument(format!(
+                        "The mapping item of gen {} is changed from {:?} to {:?}",
+                        old_item.gen, old_item, new_item
+                    )));
+                }
+                i_old += 1;
+                i_new += 1;
+            }
+        }
+        if epoch_equal {
+            if let (Some(old_leader), Some(new_leader)) = (old_items.last(), new_items.last()) {
+                if old_leader.gen != new_leader.gen
+                    || old_leader.bname != new_leader.bname
+                    || old_leader.queue_id != new_leader.queue_id
+                    || old_leader.start_offset != new_leader.start_offset
+                {
+                    return Err(RemotingError::IllegalArgument(format!(
+                        "The leader item is changed from {:?} to {:?} with the same epoch",
+                        old_leader, new_leader
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
     pub fn get_mock_broker_name(scope: &str) -> String {
         assert!(!scope.is_empty(), "Scope cannot be null");
 
@@ -17,8 +17,11 @@
 use std::fmt;
 
 use cheetah_string::CheetahString;
+use serde::Deserialize;
+use serde::Serialize;
 
-#[derive(Debug, Eq)]
+#[derive(Debug, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct GroupForbidden {
     topic: CheetahString,
     group: CheetahString,
@@ -193,4 +196,21 @@ mod tests {
 
         assert_eq!(hash1, hash2);
     }
+
+    #[test]
+    fn group_forbidden_round_trips_through_camel_case_json() {
+        let group_forbidden = GroupForbidden::new(
+            CheetahString::from("topic"),
+            CheetahString::from("group"),
+            Some(false),
+        );
+        let json = serde_json::to_string(&group_forbidden).unwrap();
+        assert_eq!(
+            json,
+            r#"{"topic":"topic","group":"group","readable":false}"#
+        );
+        let decoded: GroupForbidden = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, group_forbidden);
+        assert_eq!(decoded.readable(), Some(false));
+    }
 }
@@ -49,9 +49,6 @@ use crate::Result;
 /// Default limit the max number of connections.
 const DEFAULT_MAX_CONNECTIONS: usize = 1000;
 
-/// Shorthand for the transmit half of the message channel.
-type Tx = mpsc::UnboundedSender<RemotingCommand>;
-
 /// Shorthand for the receive half of the message channel.
 type Rx = mpsc::UnboundedReceiver<RemotingCommand>;
 
@@ -64,6 +61,9 @@ pub struct ConnectionHandler<RP> {
     conn_disconnect_notify: Option<broadcast::Sender<SocketAddr>>,
     rpc_hooks: Arc<Vec<Box<dyn RPCHook>>>,
     response_table: ArcMut<HashMap<i32, ResponseFuture>>,
+    /// Responses the processor writes later through the context, see
+    /// [`ConnectionHandlerContextWrapper::write`].
+    response_rx: Rx,
 }
 
 impl<RP> Drop for ConnectionHandler<RP> {
@@ -113,6 +113,12 @@ impl<RP: RequestProcessor + Sync + 'static> ConnectionHandler<RP> {
             //Get the next frame from the connection.
             let frame = tokio::select! {
                 res = self.connection_handler_context.channel.connection.receive_command() => res,
+                Some(response) = self.response_rx.recv() => {
+                    match self.handle_written_response(response).await {
+                        HandleErrorResult::ReturnMethod => return Ok(()),
+                        _ => continue,
+                    }
+                }
                 _ = self.shutdown.recv() =>{
                     //If a shutdown signal is received, return from `handle`.
                     self.channel.connection_mut().ok = false;
@@ -121,7 +127,18 @@ impl<RP: RequestProcessor + Sync + 'static> ConnectionHandler<RP> {
             };
 
             let mut cmd = match frame {
-                Some(frame) => frame?,
+                Some(Ok(cmd)) => cmd,
+                Some(Err(error)) => {
+                    //A frame that can not be decoded leaves the stream unreadable, close the
+                    // connection instead of guessing where the next frame starts.
+                    warn!(
+                        "close the connection of {}, malformed frame: {}",
+                        self.connection_handler_context.channel.remote_address(),
+                        error
+                    );
+                    self.channel.connection_mut().ok = false;
+                    return Ok(());
+                }
                 None => {
                     //If the frame is None, it means the connection is closed.
                     return Ok(());
@@ -168,7 +185,7 @@ impl<RP: RequestProcessor + Sync + 'static> ConnectionHandler<RP> {
                 }
             };
 
-            let exception = match self.do_before_rpc_hooks(&self.channel, response.as_mut()) {
+            let exception = match self.do_after_rpc_hooks(&self.channel, response.as_mut()) {
                 Ok(_) => None,
                 Err(error) => Some(error),
             };
@@ -182,24 +199,46 @@ impl<RP: RequestProcessor + Sync + 'static> ConnectionHandler<RP> {
                 continue;
             }
             let response = response.unwrap();
-            tokio::select! {
-                result =self.connection_handler_context.channel.connection.send_command(response.set_opaque(opaque)) => match result{
-                    Ok(_) =>{},
-                    Err(err) => {
-                        match err {
-                            RemotingError::Io(io_error) => {
-                                error!("connection disconnect: {}", io_error);
-                                return Ok(())
-                            }
-                            _ => { error!("send response failed: {}", err);}
-                        }
-                    },
-                },
+            if !self.write_response(response.set_opaque(opaque)).await {
+                return Ok(());
             }
         }
         Ok(())
     }
 
+    /// Runs the after hooks on a response the processor wrote through the context, it already
+    /// carries the opaque of its request.
+    async fn handle_written_response(
+        &mut self,
+        mut response: RemotingCommand,
+    ) -> HandleErrorResult {
+        let opaque = response.opaque();
+        let exception = self
+            .do_after_rpc_hooks(&self.channel, Some(&mut response))
+            .err();
+        match self.handle_error(false, opaque, exception).await {
+            HandleErrorResult::GoHead => {}
+            result => return result,
+        }
+        if self.write_response(response).await {
+            HandleErrorResult::Continue
+        } else {
+            HandleErrorResult::ReturnMethod
+        }
+    }
+
+    /// Queues a response on the writer of the channel, which writes the commands of the
+    /// connection one after the other. Returns `false` once the connection is gone.
+    async fn write_response(&self, response: RemotingCommand) -> bool {
+        match self.channel.send_response(response).await {
+            Ok(_) => true,
+            Err(err) => {
+                error!("send response failed: {}", err);
+                false
+            }
+        }
+    }
+
     async fn handle_error(
         &mut self,
         oneway_rpc: bool,
@@ -207,50 +246,25 @@ impl<RP: RequestProcessor + Sync + 'static> ConnectionHandler<RP> {
         exception: Option<RemotingError>,
     ) -> HandleErrorResult {
         if let Some(exception_inner) = exception {
-            match exception_inner {
+            let response = match exception_inner {
                 RemotingError::AbortProcessError(code, message) => {
                     if oneway_rpc {
                         return HandleErrorResult::Continue;
                     }
-                    let response =
-                        RemotingCommand::create_response_command_with_code_remark(code, message);
-                    tokio::select! {
-                        result =self.connection_handler_context.channel.connection.send_command(response.set_opaque(opaque)) => match result{
-                            Ok(_) =>{},
-                            Err(err) => {
-                                match err {
-                                    RemotingError::Io(io_error) => {
-                                        error!("send response failed: {}", io_error);
-                                        return HandleErrorResult::ReturnMethod;
-                                    }
-                                    _ => { error!("send response failed: {}", err);}
-                                }
-                            },
-                        },
-                    }
+                    RemotingCommand::create_response_command_with_code_remark(code, message)
                 }
                 _ => {
-                    if !oneway_rpc {
-                        let response = RemotingCommand::create_response_command_with_code_remark(
-                            ResponseCode::SystemError,
-                            exception_inner.to_string(),
-                        );
-                        tokio::select! {
-                            result =self.connection_handler_context.channel.connection.send_command(response.set_opaque(opaque)) => match result{
-                                Ok(_) =>{},
-                                Err(err) => {
-                                    match err {
-                                        RemotingError::Io(io_error) => {
-                                            error!("send response failed: {}", io_error);
-                                            return HandleErrorResult::ReturnMethod;
-                                        }
-                                        _ => { error!("send response failed: {}", err);}
-                                    }
-                                },
-                            },
-                        }
+                    if oneway_rpc {
+                        return HandleErrorResult::Continue;
                     }
+                    RemotingCommand::create_response_command_with_code_remark(
+                        ResponseCode::SystemError,
+                        exception_inner.to_string(),
+                    )
                 }
+            };
+            if !self.write_response(response.set_opaque(opaque)).await {
+                return HandleErrorResult::ReturnMethod;
             }
             HandleErrorResult::Continue
         } else {
@@ -311,6 +325,7 @@ impl<RP: RequestProcessor + Sync + 'static + Clone> ConnectionListener<RP> {
             socket.set_nodelay(true).expect("set nodelay failed");
 
             let response_table = ArcMut::new(HashMap::with_capacity(128));
+            let (response_tx, response_rx) = mpsc::unbounded_channel();
             let channel = Channel::new(
                 socket.local_addr()?,
                 remote_addr,
@@ -324,6 +339,7 @@ impl<RP: RequestProcessor + Sync + 'static + Clone> ConnectionListener<RP> {
                 connection_handler_context: ArcMut::new(ConnectionHandlerContextWrapper {
                     // connection: Connection::new(socket),
                     channel: channel.clone(),
+                    response_tx: Some(response_tx),
                 }),
                 channel,
                 shutdown: Shutdown::new(self.notify_shutdown.subscribe()),
@@ -331,6 +347,7 @@ impl<RP: RequestProcessor + Sync + 'static + Clone> ConnectionListener<RP> {
                 conn_disconnect_notify: self.conn_disconnect_notify.clone(),
                 rpc_hooks: self.rpc_hooks.clone(),
                 response_table,
+                response_rx,
             };
 
             tokio::spawn(async move {
@@ -500,3 +517,109 @@ impl Shutdown {
         self.is_shutdown = true;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+
+    use bytes::Bytes;
+
+    use super::*;
+
+    const REQUESTS: i32 = 64;
+    const BODY_SIZE: usize = 64 * 1024;
+
+    /// Answers every request from a task of its own, like the broker does from its pools.
+    #[derive(Clone)]
+    struct DeferredProcessor;
+
+    impl RequestProcessor for DeferredProcessor {
+        async fn process_request(
+            &mut self,
+            _channel: Channel,
+            ctx: ConnectionHandlerContext,
+            request: RemotingCommand,
+        ) -> Result<Option<RemotingCommand>> {
+            let opaque = request.opaque();
+            tokio::spawn(async move {
+                // answer out of order
+                time::sleep(Duration::from_millis((REQUESTS - opaque) as u64 % 7)).await;
+                let body = Bytes::from(vec![opaque as u8; BODY_SIZE]);
+                let mut ctx = ctx;
+                ctx.write(
+                    RemotingCommand::create_response_command()
+                        .set_body(body)
+                        .set_opaque(opaque),
+                )
+                .await;
+            });
+            Ok(None)
+        }
+    }
+
+    struct CountingHook(Arc<AtomicUsize>);
+
+    impl RPCHook for CountingHook {
+        fn do_before_request(
+            &self,
+            _remote_addr: SocketAddr,
+            _request: &mut RemotingCommand,
+        ) -> Result<()> {
+            Ok(())
+        }
+
+        fn do_after_response(
+            &self,
+            _remote_addr: SocketAddr,
+            _response: &mut RemotingCommand,
+        ) -> Result<()> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn responses_written_concurrently_arrive_whole() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let after_responses = Arc::new(AtomicUsize::new(0));
+        let (stop_tx, stop_rx) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(run(
+            listener,
+            stop_rx,
+            DeferredProcessor,
+            None,
+            vec![Box::new(CountingHook(after_responses.clone()))],
+        ));
+
+        let mut connection = Connection::new(TcpStream::connect(address).await.unwrap());
+        for opaque in 0..REQUESTS {
+            connection
+                .send_command(RemotingCommand::create_remoting_command(0).set_opaque(opaque))
+                .await
+                .unwrap();
+        }
+
+        let mut answered = Vec::new();
+        for _ in 0..REQUESTS {
+            let response = time::timeout(Duration::from_secs(10), connection.receive_command())
+                .await
+                .expect("a response is missing")
+                .unwrap()
+                .unwrap();
+            let opaque = response.opaque();
+            let body = response.get_body().unwrap();
+            assert_eq!(body.len(), BODY_SIZE);
+            assert!(body.iter().all(|byte| *byte == opaque as u8));
+            answered.push(opaque);
+        }
+        answered.sort_unstable();
+        assert_eq!(answered, (0..REQUESTS).collect::<Vec<_>>());
+        assert_eq!(after_responses.load(Ordering::SeqCst), REQUESTS as usize);
+
+        drop(connection);
+        let _ = stop_tx.send(());
+        server.await.unwrap();
+    }
+}
@@ -46,6 +46,8 @@ pub struct TokioClientConfig {
     pub max_reconnect_interval_time_seconds: i64,
     pub enable_reconnect_for_go_away: bool,
     pub enable_transparent_retry: bool,
+    /// How long resolved addresses of a host name are reused, `0` resolves on every connect.
+    pub dns_cache_ttl_millis: u64,
 }
 
 impl Default for TokioClientConfig {
@@ -71,6 +73,7 @@ impl Default for TokioClientConfig {
             max_reconnect_interval_time_seconds: 60,
             enable_reconnect_for_go_away: true,
             enable_transparent_retry: true,
+            dns_cache_ttl_millis: 30_000,
         }
     }
 }
@@ -15,19 +15,26 @@
  * limitations under the License.
  */
 
+use std::hash::Hash;
+use std::hash::Hasher;
+
 use rocketmq_rust::ArcMut;
+use tokio::sync::mpsc;
 use tracing::error;
 
 use crate::connection::Connection;
 use crate::net::channel::Channel;
 use crate::protocol::remoting_command::RemotingCommand;
+use crate::remoting_error::RemotingError;
 
 pub type ConnectionHandlerContext = ArcMut<ConnectionHandlerContextWrapper>;
 
-#[derive(Hash, Eq, PartialEq)]
 pub struct ConnectionHandlerContextWrapper {
     // pub(crate) connection: Connection,
     pub(crate) channel: Channel,
+    /// Hands the responses written from outside the connection handler back to it, so that they
+    /// pass the rpc hooks like the responses returned by the processor.
+    pub(crate) response_tx: Option<mpsc::UnboundedSender<RemotingCommand>>,
 }
 
 impl ConnectionHandlerContextWrapper {
@@ -36,6 +43,7 @@ impl ConnectionHandlerContextWrapper {
         Self {
             //connection,
             channel,
+            response_tx: None,
         }
     }
 
@@ -43,12 +51,17 @@ impl ConnectionHandlerContextWrapper {
         self.channel.connection_ref()
     }
 
+    /// Writes a response, it is queued behind the commands written to the channel before, so
+    /// that responses written from several tasks at once do not interleave on the connection.
     pub async fn write(&mut self, cmd: RemotingCommand) {
-        match self.channel.connection_mut().send_command(cmd).await {
-            Ok(_) => {}
-            Err(error) => {
-                error!("send response failed: {}", error);
-            }
+        let result = match self.response_tx.as_ref() {
+            Some(response_tx) => response_tx
+                .send(cmd)
+                .map_err(|error| RemotingError::ChannelSendRequestFailed(error.to_string())),
+            None => self.channel.send_response(cmd).await,
+        };
+        if let Err(error) = result {
+            error!("send response failed: {}", error);
         }
     }
 
@@ -61,6 +74,20 @@ impl ConnectionHandlerContextWrapper {
     }
 }
 
+impl PartialEq for ConnectionHandlerContextWrapper {
+    fn eq(&self, other: &Self) -> bool {
+        self.channel == other.channel
+    }
+}
+
+impl Eq for ConnectionHandlerContextWrapper {}
+
+impl Hash for ConnectionHandlerContextWrapper {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.channel.hash(state);
+    }
+}
+
 impl AsRef<ConnectionHandlerContextWrapper> for ConnectionHandlerContextWrapper {
     fn as_ref(&self) -> &ConnectionHandlerContextWrapper {
         self
@@ -0,0 +1,105 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Compares committing, flushing and reading a mapped file through its mapping and through
+//! io_uring, run with `cargo bench --features io_uring --bench mapped_file_io` and point
+//! `TMPDIR` to the device to measure.
+
+use cheetah_string::CheetahString;
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::BatchSize;
+use criterion::Criterion;
+use criterion::Throughput;
+use rocketmq_store::base::transient_store_pool::TransientStorePool;
+use rocketmq_store::config::message_store_config::MessageStoreConfig;
+use rocketmq_store::log_file::mapped_file::default_mapped_file_impl::DefaultMappedFile;
+use rocketmq_store::log_file::mapped_file::uring_file;
+use rocketmq_store::log_file::mapped_file::MappedFile;
+use tempfile::TempDir;
+
+const FILE_SIZE: usize = 16 * 1024 * 1024;
+const MESSAGE_SIZE: usize = 1024;
+/// Messages appended between two commits and flushes.
+const MESSAGES_PER_FLUSH: usize = 64;
+
+fn new_mapped_file(dir: &TempDir, transient_store_pool: &TransientStorePool) -> DefaultMappedFile {
+    let file_name = dir.path().join(format!("{:020}", 0));
+    DefaultMappedFile::new_with_transient_store_pool(
+        CheetahString::from(file_name.to_str().unwrap()),
+        FILE_SIZE as u64,
+        transient_store_pool.clone(),
+    )
+}
+
+fn append_commit_and_flush(mapped_file: &DefaultMappedFile) {
+    let message = [7u8; MESSAGE_SIZE];
+    for _ in 0..FILE_SIZE / (MESSAGE_SIZE * MESSAGES_PER_FLUSH) {
+        for _ in 0..MESSAGES_PER_FLUSH {
+            mapped_file.append_message_bytes(&message);
+        }
+        mapped_file.commit(0);
+        mapped_file.flush(0);
+    }
+}
+
+fn read_all(mapped_file: &DefaultMappedFile) {
+    for pos in (0..FILE_SIZE).step_by(MESSAGE_SIZE * MESSAGES_PER_FLUSH) {
+        criterion::black_box(mapped_file.get_data(pos, MESSAGE_SIZE * MESSAGES_PER_FLUSH));
+    }
+}
+
+fn bench_io(c: &mut Criterion, name: &str, enable_io_uring: bool) {
+    uring_file::init(&MessageStoreConfig {
+        enable_io_uring,
+        ..MessageStoreConfig::default()
+    });
+    if enable_io_uring && !uring_file::is_enabled() {
+        eprintln!("io_uring is not available, skip the {} benchmarks", name);
+        return;
+    }
+    let dir = tempfile::tempdir().unwrap();
+    let transient_store_pool = TransientStorePool::new(1, FILE_SIZE);
+    transient_store_pool.init();
+
+    let mut group = c.benchmark_group(name);
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(FILE_SIZE as u64));
+    group.bench_function("append_commit_flush", |b| {
+        b.iter_batched(
+            || new_mapped_file(&dir, &transient_store_pool),
+            |mapped_file| {
+                append_commit_and_flush(&mapped_file);
+                mapped_file.destroy(0);
+            },
+            BatchSize::PerIteration,
+        )
+    });
+    let mapped_file = new_mapped_file(&dir, &transient_store_pool);
+    append_commit_and_flush(&mapped_file);
+    group.bench_function("read", |b| b.iter(|| read_all(&mapped_file)));
+    group.finish();
+    mapped_file.destroy(0);
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    bench_io(c, "mmap", false);
+    bench_io(c, "io_uring", true);
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -17,19 +17,23 @@
 
 use memmap2::MmapMut;
 
+pub mod adaptive_tuning;
 pub mod allocate_mapped_file_service;
 pub mod append_message_callback;
 pub mod commit_log_dispatcher;
 pub mod compaction_append_msg_callback;
-pub(crate) mod dispatch_request;
+pub mod dispatch_request;
+pub mod dispatch_subscription;
 pub mod flush_manager;
 pub mod get_message_result;
 pub mod message_arriving_listener;
+pub mod message_iterator;
 pub mod message_result;
 pub mod message_status_enum;
 pub mod message_store;
 pub mod put_message_context;
 pub mod query_message_result;
+pub mod retention_guard;
 pub mod select_result;
 pub mod store_checkpoint;
 pub mod store_enum;
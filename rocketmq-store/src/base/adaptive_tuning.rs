@@ -0,0 +1,397 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicI32;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use tracing::info;
+
+use crate::config::message_store_config::MessageStoreConfig;
+
+/// Upper bounds in ms of the put latency buckets, the last bucket takes everything slower.
+const LATENCY_BUCKETS_MILLIS: [u64; 11] = [0, 1, 2, 5, 10, 20, 50, 100, 200, 500, 1000];
+
+/// Flush, commit and reput parameters in effect, read by the store services on every round.
+///
+/// They start out as configured. The flush intervals are changed by [`StoreTuning::update`],
+/// the others only by the adaptive tuning, if enabled.
+pub struct StoreTuning {
+    flush_least_pages: AtomicI32,
+    commit_interval_millis: AtomicU64,
+    reput_batch_messages: AtomicUsize,
+    flush_interval_commit_log_millis: AtomicU64,
+    flush_interval_consume_queue_millis: AtomicU64,
+    put_latency: [AtomicU64; LATENCY_BUCKETS_MILLIS.len() + 1],
+}
+
+impl StoreTuning {
+    pub fn new(message_store_config: &MessageStoreConfig) -> Self {
+        let reput_batch_messages = if message_store_config.adaptive_tuning_enable {
+            message_store_config.adaptive_reput_batch_min.max(1)
+        } else {
+            usize::MAX
+        };
+        Self {
+            flush_least_pages: AtomicI32::new(message_store_config.flush_commit_log_least_pages),
+            commit_interval_millis: AtomicU64::new(message_store_config.commit_interval_commit_log),
+            reput_batch_messages: AtomicUsize::new(reput_batch_messages),
+            flush_interval_commit_log_millis: AtomicU64::new(
+                message_store_config.flush_interval_commit_log.max(0) as u64,
+            ),
+            flush_interval_consume_queue_millis: AtomicU64::new(
+                message_store_config.flush_interval_consume_queue as u64,
+            ),
+            put_latency: Default::default(),
+        }
+    }
+
+    /// Applies the store property `key` if it can change at runtime, returns `false` if it can
+    /// not.
+    pub fn update(&self, key: &str, value: &str) -> Result<bool, String> {
+        let target = match key {
+            "flushIntervalCommitLog" => &self.flush_interval_commit_log_millis,
+            "flushIntervalConsumeQueue" => &self.flush_interval_consume_queue_millis,
+            _ => return Ok(false),
+        };
+        let millis = value
+            .parse::<u64>()
+            .map_err(|_| format!("invalid value '{}' for config key '{}'", value, key))?;
+        target.store(millis, Ordering::Relaxed);
+        Ok(true)
+    }
+
+    #[inline]
+    pub fn flush_interval_commit_log_millis(&self) -> u64 {
+        self.flush_interval_commit_log_millis
+            .load(Ordering::Relaxed)
+    }
+
+    #[inline]
+    pub fn flush_interval_consume_queue_millis(&self) -> u64 {
+        self.flush_interval_consume_queue_millis
+            .load(Ordering::Relaxed)
+    }
+
+    #[inline]
+    pub fn flush_least_pages(&self) -> i32 {
+        self.flush_least_pages.load(Ordering::Relaxed)
+    }
+
+    #[inline]
+    pub fn commit_interval_millis(&self) -> u64 {
+        self.commit_interval_millis.load(Ordering::Relaxed)
+    }
+
+    /// Messages dispatched per reput round before the reput yields.
+    #[inline]
+    pub fn reput_batch_messages(&self) -> usize {
+        self.reput_batch_messages.load(Ordering::Relaxed)
+    }
+
+    pub fn record_put(&self, elapsed_millis: u64) {
+        let bucket = LATENCY_BUCKETS_MILLIS
+            .iter()
+            .position(|&bound| elapsed_millis <= bound)
+            .unwrap_or(LATENCY_BUCKETS_MILLIS.len());
+        self.put_latency[bucket].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Puts recorded since the last call, and their latency.
+    fn take_window(&self, elapsed: Duration) -> PutWindow {
+        let counts = self
+            .put_latency
+            .iter()
+            .map(|bucket| bucket.swap(0, Ordering::Relaxed))
+            .collect::<Vec<_>>();
+        PutWindow::from_buckets(&counts, elapsed)
+    }
+
+    fn values(&self) -> TuningValues {
+        TuningValues {
+            flush_least_pages: self.flush_least_pages(),
+            commit_interval_millis: self.commit_interval_millis(),
+            reput_batch_messages: self.reput_batch_messages(),
+        }
+    }
+
+    fn apply(&self, values: TuningValues) {
+        self.flush_least_pages
+            .store(values.flush_least_pages, Ordering::Relaxed);
+        self.commit_interval_millis
+            .store(values.commit_interval_millis, Ordering::Relaxed);
+        self.reput_batch_messages
+            .store(values.reput_batch_messages, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct PutWindow {
+    tps: u64,
+    p99_millis: u64,
+}
+
+impl PutWindow {
+    fn from_buckets(counts: &[u64], elapsed: Duration) -> Self {
+        let total = counts.iter().sum::<u64>();
+        let tps = if elapsed.is_zero() {
+            0
+        } else {
+            (total as f64 / elapsed.as_secs_f64()) as u64
+        };
+        let mut p99_millis = 0;
+        let threshold = total - total / 100;
+        let mut seen = 0;
+        for (bucket, count) in counts.iter().enumerate() {
+            seen += count;
+            if total > 0 && seen >= threshold {
+                p99_millis = LATENCY_BUCKETS_MILLIS
+                    .get(bucket)
+                    .copied()
+                    .unwrap_or(u64::MAX);
+                break;
+            }
+        }
+        Self { tps, p99_millis }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TuningValues {
+    flush_least_pages: i32,
+    commit_interval_millis: u64,
+    reput_batch_messages: usize,
+}
+
+/// Moves the [`StoreTuning`] values within their bounds, one step per tuning interval.
+///
+/// Under a high put rate flushes and commits are batched more and the reput dispatches more
+/// per round. Once the p99 put latency goes over its target, flushes and commits are made
+/// smaller first, since large flushes stall the puts behind them. With a low put rate
+/// everything goes back towards the configured values.
+struct AdaptiveTuner {
+    latency_target_millis: u64,
+    high_tps: u64,
+    min: TuningValues,
+    max: TuningValues,
+}
+
+impl AdaptiveTuner {
+    fn new(message_store_config: &MessageStoreConfig) -> Self {
+        let min = TuningValues {
+            flush_least_pages: message_store_config.flush_commit_log_least_pages,
+            commit_interval_millis: message_store_config.commit_interval_commit_log,
+            reput_batch_messages: message_store_config.adaptive_reput_batch_min.max(1),
+        };
+        let max = TuningValues {
+            flush_least_pages: message_store_config
+                .adaptive_flush_least_pages_max
+                .max(min.flush_least_pages),
+            commit_interval_millis: message_store_config
+                .adaptive_commit_interval_max_millis
+                .max(min.commit_interval_millis),
+            reput_batch_messages: message_store_config
+                .adaptive_reput_batch_max
+                .max(min.reput_batch_messages),
+        };
+        Self {
+            latency_target_millis: message_store_config.adaptive_put_latency_target_millis,
+            high_tps: message_store_config.adaptive_high_put_tps,
+            min,
+            max,
+        }
+    }
+
+    fn next(&self, current: TuningValues, window: PutWindow) -> TuningValues {
+        let high = window.tps >= self.high_tps;
+        let low = window.tps < self.high_tps / 2;
+        let mut next = current;
+        if window.p99_millis > self.latency_target_millis {
+            next.flush_least_pages = step_down(
+                current.flush_least_pages as u64,
+                self.min.flush_least_pages as u64,
+            ) as i32;
+            next.commit_interval_millis = step_down(
+                current.commit_interval_millis,
+                self.min.commit_interval_millis,
+            );
+        } else if high {
+            next.flush_least_pages = step_up(
+                current.flush_least_pages as u64,
+                self.max.flush_least_pages as u64,
+            ) as i32;
+            next.commit_interval_millis = step_up(
+                current.commit_interval_millis,
+                self.max.commit_interval_millis,
+            );
+        } else if low {
+            next.flush_least_pages = step_down(
+                current.flush_least_pages as u64,
+                self.min.flush_least_pages as u64,
+            ) as i32;
+            next.commit_interval_millis = step_down(
+                current.commit_interval_millis,
+                self.min.commit_interval_millis,
+            );
+        }
+        if high {
+            next.reput_batch_messages = step_up(
+                current.reput_batch_messages as u64,
+                self.max.reput_batch_messages as u64,
+            ) as usize;
+        } else if low {
+            next.reput_batch_messages = step_down(
+                current.reput_batch_messages as u64,
+                self.min.reput_batch_messages as u64,
+            ) as usize;
+        }
+        next
+    }
+}
+
+fn step_up(value: u64, max: u64) -> u64 {
+    value.saturating_mul(2).max(value + 1).min(max)
+}
+
+fn step_down(value: u64, min: u64) -> u64 {
+    (value / 2).max(min)
+}
+
+/// Runs the adaptive tuning of `store_tuning` until `shutdown` is set.
+pub(crate) fn start_adaptive_tuning(
+    message_store_config: &MessageStoreConfig,
+    store_tuning: Arc<StoreTuning>,
+    shutdown: Arc<AtomicBool>,
+) {
+    let tuner = AdaptiveTuner::new(message_store_config);
+    let period = Duration::from_millis(message_store_config.adaptive_tuning_interval_millis.max(1));
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        interval.tick().await;
+        let mut window_start = Instant::now();
+        while !shutdown.load(Ordering::Acquire) {
+            interval.tick().await;
+            let window = store_tuning.take_window(window_start.elapsed());
+            window_start = Instant::now();
+            let current = store_tuning.values();
+            let next = tuner.next(current, window);
+            if next != current {
+                info!(
+                    "adaptive tuning at {} puts/s, p99 put {} ms: {:?} -> {:?}",
+                    window.tps, window.p99_millis, current, next
+                );
+                store_tuning.apply(next);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> MessageStoreConfig {
+        MessageStoreConfig {
+            flush_commit_log_least_pages: 4,
+            commit_interval_commit_log: 200,
+            adaptive_tuning_enable: true,
+            adaptive_put_latency_target_millis: 10,
+            adaptive_high_put_tps: 1000,
+            adaptive_flush_least_pages_max: 16,
+            adaptive_commit_interval_max_millis: 800,
+            adaptive_reput_batch_min: 100,
+            adaptive_reput_batch_max: 400,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn p99_is_taken_from_the_latency_buckets() {
+        let tuning = StoreTuning::new(&config());
+        for _ in 0..98 {
+            tuning.record_put(1);
+        }
+        tuning.record_put(40);
+        tuning.record_put(40);
+        let window = tuning.take_window(Duration::from_secs(1));
+        assert_eq!(
+            window,
+            PutWindow {
+                tps: 100,
+                p99_millis: 50
+            }
+        );
+        assert_eq!(tuning.take_window(Duration::from_secs(1)).tps, 0);
+    }
+
+    #[test]
+    fn tunes_within_bounds() {
+        let config = config();
+        let tuner = AdaptiveTuner::new(&config);
+        let mut values = StoreTuning::new(&config).values();
+        let busy = PutWindow {
+            tps: 5000,
+            p99_millis: 2,
+        };
+        for _ in 0..10 {
+            values = tuner.next(values, busy);
+        }
+        assert_eq!(values, tuner.max);
+
+        let slow = PutWindow {
+            tps: 5000,
+            p99_millis: 100,
+        };
+        values = tuner.next(values, slow);
+        assert_eq!(values.flush_least_pages, 8);
+        assert_eq!(values.commit_interval_millis, 400);
+        assert_eq!(values.reput_batch_messages, 400);
+
+        let idle = PutWindow {
+            tps: 10,
+            p99_millis: 0,
+        };
+        for _ in 0..10 {
+            values = tuner.next(values, idle);
+        }
+        assert_eq!(values, tuner.min);
+    }
+
+    #[test]
+    fn flush_intervals_change_at_runtime() {
+        let tuning = StoreTuning::new(&MessageStoreConfig::default());
+        assert_eq!(tuning.flush_interval_commit_log_millis(), 500);
+        assert_eq!(tuning.update("flushIntervalCommitLog", "200"), Ok(true));
+        assert_eq!(tuning.update("flushIntervalConsumeQueue", "3000"), Ok(true));
+        assert_eq!(tuning.flush_interval_commit_log_millis(), 200);
+        assert_eq!(tuning.flush_interval_consume_queue_millis(), 3000);
+
+        assert!(tuning.update("flushIntervalCommitLog", "-1").is_err());
+        assert_eq!(tuning.flush_interval_commit_log_millis(), 200);
+        assert_eq!(tuning.update("mappedFileSizeCommitLog", "1024"), Ok(false));
+    }
+
+    #[test]
+    fn reput_is_unbounded_without_tuning() {
+        let tuning = StoreTuning::new(&MessageStoreConfig::default());
+        assert_eq!(tuning.reput_batch_messages(), usize::MAX);
+    }
+}
@@ -14,100 +14,344 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+
 use std::collections::HashMap;
 use std::fmt::Display;
 use std::fmt::Formatter;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU8;
+use std::sync::atomic::Ordering;
 use std::sync::mpsc;
 use std::sync::mpsc::Receiver;
+use std::sync::mpsc::RecvTimeoutError;
 use std::sync::mpsc::Sender;
 use std::sync::Arc;
+use std::time::Duration;
 
+use cheetah_string::CheetahString;
+use parking_lot::Condvar;
 use parking_lot::Mutex;
+use tokio::runtime::Handle;
+use tokio::runtime::RuntimeFlavor;
+use tracing::info;
+use tracing::warn;
 
+use crate::base::transient_store_pool::TransientStorePool;
+use crate::config::message_store_config::MessageStoreConfig;
 use crate::log_file::mapped_file::default_mapped_file_impl::DefaultMappedFile;
+use crate::log_file::mapped_file::MappedFile;
+
+/// How long a put waits for the file it rolls to, when the allocating thread is creating it.
+const WAIT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often the allocating thread checks whether the service is shut down.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
 
+/// Creates the commit log files ahead of the puts on a thread of its own.
+///
+/// A put rolling to the next file asks for that file and the one after it. The next file is
+/// usually created by then, and the one after it is created, and warmed up with
+/// `warm_mapped_file_enable`, while the puts fill the next file. When the allocating thread has
+/// not started on the next file yet, the put creates it itself rather than wait behind the
+/// requests queued before it.
+#[derive(Clone)]
 pub struct AllocateMappedFileService {
-    tx: Sender<AllocateRequest>,
-    rx: Arc<Mutex<Receiver<AllocateRequest>>>,
-    request_table: Arc<parking_lot::Mutex<HashMap<String, AllocateRequest>>>,
+    allocator: Allocator,
+    tx: Sender<Arc<AllocateRequest>>,
+    rx: Arc<Mutex<Option<Receiver<Arc<AllocateRequest>>>>>,
+}
+
+/// The part of the service the allocating thread holds. It does not hold the sender of the
+/// requests, so the thread ends once the service is dropped.
+#[derive(Clone)]
+struct Allocator {
+    message_store_config: Arc<MessageStoreConfig>,
+    transient_store_pool: Option<TransientStorePool>,
+    request_table: Arc<Mutex<HashMap<String, Arc<AllocateRequest>>>>,
+    stopped: Arc<AtomicBool>,
 }
 
 impl AllocateMappedFileService {
-    pub fn new() -> Self {
+    pub fn new(
+        message_store_config: Arc<MessageStoreConfig>,
+        transient_store_pool: Option<TransientStorePool>,
+    ) -> Self {
         let (tx, rx) = mpsc::channel();
         Self {
+            allocator: Allocator {
+                message_store_config,
+                transient_store_pool,
+                request_table: Arc::new(Mutex::new(HashMap::new())),
+                stopped: Arc::new(AtomicBool::new(false)),
+            },
             tx,
-            rx: Arc::new(parking_lot::Mutex::new(rx)),
-            request_table: Arc::new(Default::default()),
+            rx: Arc::new(Mutex::new(Some(rx))),
         }
     }
-}
 
-impl Default for AllocateMappedFileService {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
-impl AllocateMappedFileService {
+    /// Returns the file at `next_file_path`, and has the one at `next_next_file_path` created in
+    /// the background. `None` when the allocating thread does not finish the file it is creating
+    /// within five seconds, or the transient store pool has no buffer for it with
+    /// `fast_fail_if_no_buffer_in_store_pool`.
     pub fn put_request_and_return_mapped_file(
         &self,
         next_file_path: String,
         next_next_file_path: String,
-        file_size: i32,
-    ) -> DefaultMappedFile {
-        unimplemented!()
+        file_size: u64,
+    ) -> Option<DefaultMappedFile> {
+        let allocator = &self.allocator;
+        let mut can_submit_requests = 2;
+        if let Some(transient_store_pool) = allocator.transient_store_pool.as_ref() {
+            if allocator
+                .message_store_config
+                .fast_fail_if_no_buffer_in_store_pool
+            {
+                can_submit_requests = transient_store_pool.available_buffer_nums() as i32
+                    - allocator.pending_requests() as i32;
+            }
+        }
+
+        if !self.submit(&next_file_path, file_size, &mut can_submit_requests) {
+            warn!(
+                "[NOTIFYME]TransientStorePool is not enough, so create mapped file error, \
+                 RequestQueueSize : {}, StorePoolSize: {}",
+                allocator.pending_requests(),
+                allocator.available_buffer_nums()
+            );
+            return None;
+        }
+        if !self.submit(&next_next_file_path, file_size, &mut can_submit_requests) {
+            warn!(
+                "[NOTIFYME]TransientStorePool is not enough, so skip preallocate mapped file, \
+                 RequestQueueSize : {}, StorePoolSize: {}",
+                allocator.pending_requests(),
+                allocator.available_buffer_nums()
+            );
+        }
+
+        let Some(request) = allocator.request_table.lock().get(&next_file_path).cloned() else {
+            warn!("find preallocate mmap failed, this never happen");
+            return None;
+        };
+        if request.claim() {
+            allocator.request_table.lock().remove(&next_file_path);
+            // not warmed up, that would only delay the put further
+            return Some(allocator.create_mapped_file(&request));
+        }
+        match block_in_place(|| request.wait_for_mapped_file(WAIT_TIMEOUT)) {
+            Some(mapped_file) => {
+                allocator.request_table.lock().remove(&next_file_path);
+                Some(mapped_file)
+            }
+            None => {
+                warn!("create mmap timeout {} {}", request.file_path, file_size);
+                None
+            }
+        }
     }
-}
 
-/*impl ThreadService for AllocateMappedFileService {
-    fn start(&self) {
-        let service_name = self.get_service_name();
-        let builder = std::thread::Builder::new().name(service_name);
-        let rx = self.rx.clone();
-        let request_table = self.request_table.clone();
-        let _ = builder
-            .spawn(move || loop {
-                match rx.lock().recv() {
-                    Ok(ref req) => {
-                        let guard = request_table.lock();
-                        let expected_request = guard.get(&req.file_path);
-                        if expected_request.is_none() {
-                            warn!(
-                                "this mmap request expired, maybe cause timeout {} {}",
-                                req.file_path, req.file_size,
-                            );
-                            continue;
-                        }
-                        if expected_request.unwrap() != req {
-                            warn!(
-                                "this mmap request expired, maybe cause timeout {} {}",
-                                req.file_path, req.file_size,
-                            );
-                            continue;
-                        }
-                        if req.mapped_file.is_none() {}
-                    }
-                    Err(err) => {
-                        error!("{}", err)
+    /// Queues the request for `file_path` unless it is queued already, `false` when it may not
+    /// be queued.
+    fn submit(&self, file_path: &str, file_size: u64, can_submit_requests: &mut i32) -> bool {
+        let mut request_table = self.allocator.request_table.lock();
+        if request_table.contains_key(file_path) {
+            return true;
+        }
+        if *can_submit_requests <= 0 {
+            return false;
+        }
+        let request = Arc::new(AllocateRequest::new(file_path.to_string(), file_size));
+        request_table.insert(file_path.to_string(), request.clone());
+        *can_submit_requests -= 1;
+        self.tx.send(request).is_ok()
+    }
+
+    pub fn start(&self) {
+        let Some(rx) = self.rx.lock().take() else {
+            return;
+        };
+        let allocator = self.allocator.clone();
+        let _ = std::thread::Builder::new()
+            .name("AllocateMappedFileService".to_string())
+            .spawn(move || {
+                info!("AllocateMappedFileService service started");
+                while !allocator.stopped.load(Ordering::Acquire) {
+                    match rx.recv_timeout(POLL_INTERVAL) {
+                        Ok(request) => allocator.mmap_operation(&request),
+                        Err(RecvTimeoutError::Timeout) => {}
+                        Err(RecvTimeoutError::Disconnected) => break,
                     }
                 }
-            })
-            .unwrap()
-            .join();
+                info!("AllocateMappedFileService service end");
+            });
+    }
+
+    /// Stops the allocation and deletes the files created ahead that no put took.
+    pub fn shutdown(&self) {
+        self.allocator.stopped.store(true, Ordering::Release);
+        let requests = self
+            .allocator
+            .request_table
+            .lock()
+            .drain()
+            .map(|(_, request)| request)
+            .collect::<Vec<_>>();
+        for request in requests {
+            if let Some(mapped_file) = request.take_mapped_file() {
+                info!(
+                    "delete pre allocated mapped file, {}",
+                    mapped_file.get_file_name()
+                );
+                mapped_file.destroy(1000);
+            }
+        }
     }
+}
 
-    fn shutdown(&self) {}
+impl Allocator {
+    fn pending_requests(&self) -> usize {
+        self.request_table
+            .lock()
+            .values()
+            .filter(|request| request.is_pending())
+            .count()
+    }
 
-    fn get_service_name(&self) -> String {
-        "AllocateMappedFileService".to_string()
+    fn available_buffer_nums(&self) -> usize {
+        self.transient_store_pool
+            .as_ref()
+            .map_or(0, TransientStorePool::available_buffer_nums)
     }
-}*/
+
+    /// Creates the file of `request`, unless a put claimed it or the request was dropped
+    /// meanwhile.
+    fn mmap_operation(&self, request: &Arc<AllocateRequest>) {
+        if !request.start_creating() {
+            return;
+        }
+        let expected_request = self.request_table.lock().get(&request.file_path).cloned();
+        if !expected_request.is_some_and(|expected_request| Arc::ptr_eq(&expected_request, request))
+        {
+            warn!(
+                "this mmap request expired, maybe cause timeout {} {}",
+                request.file_path, request.file_size
+            );
+            request.complete(None);
+            return;
+        }
+
+        let mapped_file = self.create_mapped_file(request);
+        // only the commit log files are warmed up
+        if self.message_store_config.warm_mapped_file_enable
+            && request.file_size >= self.message_store_config.mapped_file_size_commit_log as u64
+        {
+            mapped_file.warm_mapped_file(
+                self.message_store_config.flush_disk_type,
+                self.message_store_config
+                    .flush_least_pages_when_warm_mapped_file,
+            );
+        }
+        // the service shut down while the file was created
+        if !self.request_table.lock().contains_key(&request.file_path) {
+            mapped_file.destroy(1000);
+            request.complete(None);
+            return;
+        }
+        request.complete(Some(mapped_file));
+    }
+
+    fn create_mapped_file(&self, request: &AllocateRequest) -> DefaultMappedFile {
+        let file_name = CheetahString::from_string(request.file_path.clone());
+        match self.transient_store_pool.as_ref() {
+            Some(transient_store_pool) => DefaultMappedFile::new_with_transient_store_pool(
+                file_name,
+                request.file_size,
+                transient_store_pool.clone(),
+            ),
+            None => DefaultMappedFile::new(file_name, request.file_size),
+        }
+    }
+}
+
+/// Runs the blocking `wait`, moving the other tasks of the tokio worker thread it is called from
+/// to the other workers meanwhile.
+fn block_in_place<T>(wait: impl FnOnce() -> T) -> T {
+    match Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+            tokio::task::block_in_place(wait)
+        }
+        _ => wait(),
+    }
+}
+
+/// The request waits for the allocating thread.
+const PENDING: u8 = 0;
+/// The allocating thread creates the file.
+const CREATING: u8 = 1;
+/// The allocating thread is done with the request.
+const DONE: u8 = 2;
+/// A put creates the file itself, the allocating thread skips the request.
+const CLAIMED: u8 = 3;
 
 struct AllocateRequest {
     file_path: String,
-    file_size: i32,
-    mapped_file: Option<DefaultMappedFile>,
+    file_size: u64,
+    /// The created file, once it is created, until a put takes it.
+    mapped_file: Mutex<Option<DefaultMappedFile>>,
+    state: AtomicU8,
+    created: Condvar,
+}
+
+impl AllocateRequest {
+    fn new(file_path: String, file_size: u64) -> Self {
+        Self {
+            file_path,
+            file_size,
+            mapped_file: Mutex::new(None),
+            state: AtomicU8::new(PENDING),
+            created: Condvar::new(),
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.state.load(Ordering::Acquire) == DONE
+    }
+
+    fn is_pending(&self) -> bool {
+        matches!(self.state.load(Ordering::Acquire), PENDING | CREATING)
+    }
+
+    fn start_creating(&self) -> bool {
+        self.state
+            .compare_exchange(PENDING, CREATING, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+    }
+
+    fn claim(&self) -> bool {
+        self.state
+            .compare_exchange(PENDING, CLAIMED, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+    }
+
+    fn complete(&self, mapped_file: Option<DefaultMappedFile>) {
+        let mut created = self.mapped_file.lock();
+        *created = mapped_file;
+        self.state.store(DONE, Ordering::Release);
+        self.created.notify_all();
+    }
+
+    fn wait_for_mapped_file(&self, timeout: Duration) -> Option<DefaultMappedFile> {
+        let mut mapped_file = self.mapped_file.lock();
+        if !self.is_done() {
+            self.created
+                .wait_while_for(&mut mapped_file, |_| !self.is_done(), timeout);
+        }
+        mapped_file.take()
+    }
+
+    fn take_mapped_file(&self) -> Option<DefaultMappedFile> {
+        self.mapped_file.lock().take()
+    }
 }
 
 impl Display for AllocateRequest {
@@ -120,10 +364,149 @@ impl Display for AllocateRequest {
     }
 }
 
-impl PartialEq<Self> for AllocateRequest {
-    fn eq(&self, other: &Self) -> bool {
-        self.file_path == other.file_path && self.file_size == other.file_size
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    fn file_path(dir: &Path, offset: u64) -> String {
+        dir.join(format!("{:020}", offset))
+            .to_string_lossy()
+            .to_string()
     }
-}
 
-impl Eq for AllocateRequest {}
+    fn wait_until(mut condition: impl FnMut() -> bool) -> bool {
+        for _ in 0..100 {
+            if condition() {
+                return true;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        condition()
+    }
+
+    #[test]
+    fn creates_the_file_after_next_ahead_of_the_puts() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = AllocateMappedFileService::new(Arc::new(MessageStoreConfig::default()), None);
+        service.start();
+
+        let mapped_file = service
+            .put_request_and_return_mapped_file(
+                file_path(dir.path(), 0),
+                file_path(dir.path(), 4096),
+                4096,
+            )
+            .unwrap();
+        assert_eq!(mapped_file.get_file_from_offset(), 0);
+        let pre_allocated = service
+            .allocator
+            .request_table
+            .lock()
+            .get(&file_path(dir.path(), 4096))
+            .cloned()
+            .unwrap();
+        assert!(wait_until(|| pre_allocated.is_done()));
+
+        let mapped_file = service
+            .put_request_and_return_mapped_file(
+                file_path(dir.path(), 4096),
+                file_path(dir.path(), 8192),
+                4096,
+            )
+            .unwrap();
+        assert_eq!(mapped_file.get_file_from_offset(), 4096);
+        service.shutdown();
+    }
+
+    #[test]
+    fn creates_the_file_on_the_put_when_the_allocation_has_not_started() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = AllocateMappedFileService::new(Arc::new(MessageStoreConfig::default()), None);
+
+        // no allocating thread yet, nothing would create the file the put waits for
+        let started = std::time::Instant::now();
+        let mapped_file = service
+            .put_request_and_return_mapped_file(
+                file_path(dir.path(), 0),
+                file_path(dir.path(), 4096),
+                4096,
+            )
+            .unwrap();
+        assert!(started.elapsed() < WAIT_TIMEOUT);
+        assert_eq!(mapped_file.get_file_from_offset(), 0);
+        assert!(!service
+            .allocator
+            .request_table
+            .lock()
+            .contains_key(&file_path(dir.path(), 0)));
+
+        // the allocating thread skips the claimed request and creates the next file
+        service.start();
+        let pre_allocated = service
+            .allocator
+            .request_table
+            .lock()
+            .get(&file_path(dir.path(), 4096))
+            .cloned()
+            .unwrap();
+        assert!(wait_until(|| pre_allocated.is_done()));
+        service.shutdown();
+        assert!(Path::new(&file_path(dir.path(), 0)).exists());
+        assert!(!Path::new(&file_path(dir.path(), 4096)).exists());
+    }
+
+    #[test]
+    fn shutdown_deletes_the_files_no_put_took() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = AllocateMappedFileService::new(Arc::new(MessageStoreConfig::default()), None);
+        service.start();
+
+        service
+            .put_request_and_return_mapped_file(
+                file_path(dir.path(), 0),
+                file_path(dir.path(), 4096),
+                4096,
+            )
+            .unwrap();
+        let pre_allocated = service
+            .allocator
+            .request_table
+            .lock()
+            .get(&file_path(dir.path(), 4096))
+            .cloned()
+            .unwrap();
+        assert!(wait_until(|| pre_allocated.is_done()));
+        assert!(Path::new(&file_path(dir.path(), 4096)).exists());
+
+        service.shutdown();
+        assert!(Path::new(&file_path(dir.path(), 0)).exists());
+        assert!(!Path::new(&file_path(dir.path(), 4096)).exists());
+    }
+
+    #[test]
+    fn fails_fast_without_a_buffer_for_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let message_store_config = MessageStoreConfig {
+            fast_fail_if_no_buffer_in_store_pool: true,
+            ..MessageStoreConfig::default()
+        };
+        let transient_store_pool = TransientStorePool::new(0, 4096);
+        let service = AllocateMappedFileService::new(
+            Arc::new(message_store_config),
+            Some(transient_store_pool),
+        );
+        service.start();
+
+        assert!(service
+            .put_request_and_return_mapped_file(
+                file_path(dir.path(), 0),
+                file_path(dir.path(), 4096),
+                4096,
+            )
+            .is_none());
+        assert!(service.allocator.request_table.lock().is_empty());
+        service.shutdown();
+    }
+}
@@ -36,7 +36,6 @@ use crate::base::message_status_enum::AppendMessageStatus;
 use crate::base::put_message_context::PutMessageContext;
 use crate::config::message_store_config::MessageStoreConfig;
 use crate::log_file::commit_log::get_message_num;
-use crate::log_file::commit_log::CommitLog;
 use crate::log_file::commit_log::BLANK_MAGIC_CODE;
 use crate::log_file::commit_log::CRC32_RESERVED_LEN;
 use crate::log_file::mapped_file::MappedFile;
@@ -117,11 +116,6 @@ impl AppendMessageCallback for DefaultAppendMessageCallback {
         put_message_context: &PutMessageContext,
     ) -> AppendMessageResult {
         let mut pre_encode_buffer = msg_inner.encoded_buff.take().unwrap(); // Assuming get_encoded_buff returns Option<ByteBuffer>
-        let is_multi_dispatch_msg = self.message_store_config.enable_multi_dispatch
-            && CommitLog::is_multi_dispatch_msg(msg_inner);
-        if is_multi_dispatch_msg {
-            unimplemented!("Multi dispatch message is not supported yet");
-        }
 
         let msg_len = i32::from_be_bytes(pre_encode_buffer[0..4].try_into().unwrap());
         //physic offset
@@ -151,7 +145,14 @@ impl AppendMessageCallback for DefaultAppendMessageCallback {
             bytes.put_i32(max_blank);
             bytes.put_i32(BLANK_MAGIC_CODE);
             let instant = Instant::now();
-            mapped_file.write_bytes_segment(bytes.as_ref(), wrote_offset as usize, 0, bytes.len());
+            mapped_file.write_bytes_segment(
+                bytes.as_ref(),
+                mapped_file.get_wrote_position() as usize,
+                0,
+                bytes.len(),
+            );
+            // appended again to the next file
+            msg_inner.encoded_buff = Some(pre_encode_buffer);
             return AppendMessageResult {
                 status: AppendMessageStatus::EndOfFile,
                 wrote_offset,
@@ -270,10 +271,12 @@ impl AppendMessageCallback for DefaultAppendMessageCallback {
                 bytes.put_i32(BLANK_MAGIC_CODE);
                 mapped_file.write_bytes_segment(
                     bytes.as_ref(),
-                    wrote_offset as usize,
+                    mapped_file.get_wrote_position() as usize,
                     0,
                     bytes.len(),
                 );
+                // appended again to the next file
+                msg_batch.encoded_buff = Some(messages_byte_buffer);
                 return AppendMessageResult {
                     status: AppendMessageStatus::EndOfFile,
                     wrote_offset,
@@ -0,0 +1,199 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashSet;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use cheetah_string::CheetahString;
+use parking_lot::RwLock;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
+use tracing::info;
+use tracing::warn;
+
+use crate::base::commit_log_dispatcher::CommitLogDispatcher;
+use crate::base::dispatch_request::DispatchRequest;
+
+struct Subscription {
+    name: CheetahString,
+    /// Topics followed by the subscription, all topics if empty.
+    topics: HashSet<CheetahString>,
+    sender: mpsc::Sender<Arc<DispatchRequest>>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl Subscription {
+    fn is_interested(&self, topic: &CheetahString) -> bool {
+        self.topics.is_empty() || self.topics.contains(topic)
+    }
+}
+
+/// Lets in-process services follow the dispatch stream of the store, e.g. to trace, bridge or
+/// measure the messages of some topics, without pulling them through the remoting stack.
+///
+/// It is added as the last commit log dispatcher, so the consume queue and index of a message
+/// are built by the time its subscribers see it. Slow subscribers never hold up the reput: a
+/// request that does not fit into the queue of a subscriber is dropped for that subscriber.
+#[derive(Clone, Default)]
+pub struct DispatchSubscriptionManager {
+    subscriptions: Arc<RwLock<Vec<Subscription>>>,
+}
+
+impl DispatchSubscriptionManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Follows the dispatch requests of `topics`, or of every topic if `topics` is empty.
+    /// The subscription ends when the returned subscriber is dropped.
+    pub fn subscribe(
+        &self,
+        name: impl Into<CheetahString>,
+        topics: HashSet<CheetahString>,
+        capacity: usize,
+    ) -> DispatchSubscriber {
+        let (sender, receiver) = mpsc::channel(capacity.max(1));
+        let dropped = Arc::new(AtomicU64::new(0));
+        let name = name.into();
+        info!("dispatch subscription {} follows topics {:?}", name, topics);
+        self.subscriptions.write().push(Subscription {
+            name,
+            topics,
+            sender,
+            dropped: Arc::clone(&dropped),
+        });
+        DispatchSubscriber { receiver, dropped }
+    }
+
+    pub fn subscription_count(&self) -> usize {
+        self.subscriptions.read().len()
+    }
+}
+
+impl CommitLogDispatcher for DispatchSubscriptionManager {
+    fn dispatch(&self, dispatch_request: &mut DispatchRequest) {
+        let mut closed = false;
+        {
+            let subscriptions = self.subscriptions.read();
+            if subscriptions.is_empty() {
+                return;
+            }
+            let mut shared = None;
+            for subscription in subscriptions.iter() {
+                if !subscription.is_interested(&dispatch_request.topic) {
+                    continue;
+                }
+                let request = shared
+                    .get_or_insert_with(|| Arc::new(dispatch_request.clone()))
+                    .clone();
+                match subscription.sender.try_send(request) {
+                    Ok(_) => {}
+                    Err(TrySendError::Full(_)) => {
+                        let dropped = subscription.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+                        if dropped % 1000 == 1 {
+                            warn!(
+                                "dispatch subscription {} is full, {} requests dropped",
+                                subscription.name, dropped
+                            );
+                        }
+                    }
+                    Err(TrySendError::Closed(_)) => closed = true,
+                }
+            }
+        }
+        if closed {
+            self.subscriptions.write().retain(|subscription| {
+                let open = !subscription.sender.is_closed();
+                if !open {
+                    info!("dispatch subscription {} closed", subscription.name);
+                }
+                open
+            });
+        }
+    }
+}
+
+/// Receiving end of a dispatch subscription.
+pub struct DispatchSubscriber {
+    receiver: mpsc::Receiver<Arc<DispatchRequest>>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl DispatchSubscriber {
+    /// Waits for the next dispatch request, `None` once the store is gone.
+    pub async fn recv(&mut self) -> Option<Arc<DispatchRequest>> {
+        self.receiver.recv().await
+    }
+
+    pub fn try_recv(&mut self) -> Option<Arc<DispatchRequest>> {
+        self.receiver.try_recv().ok()
+    }
+
+    /// Requests dropped because the subscriber did not keep up.
+    pub fn dropped_count(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dispatch(manager: &DispatchSubscriptionManager, topic: &'static str) {
+        let mut dispatch_request = DispatchRequest {
+            topic: CheetahString::from_static_str(topic),
+            ..Default::default()
+        };
+        manager.dispatch(&mut dispatch_request);
+    }
+
+    #[test]
+    fn delivers_the_followed_topics_only() {
+        let manager = DispatchSubscriptionManager::new();
+        let mut all = manager.subscribe("all", HashSet::new(), 8);
+        let mut some = manager.subscribe("some", HashSet::from(["a".into()]), 8);
+        dispatch(&manager, "a");
+        dispatch(&manager, "b");
+
+        assert_eq!(all.try_recv().unwrap().topic, "a");
+        assert_eq!(all.try_recv().unwrap().topic, "b");
+        assert_eq!(some.try_recv().unwrap().topic, "a");
+        assert!(some.try_recv().is_none());
+    }
+
+    #[test]
+    fn drops_requests_for_slow_subscribers() {
+        let manager = DispatchSubscriptionManager::new();
+        let mut subscriber = manager.subscribe("slow", HashSet::new(), 1);
+        dispatch(&manager, "a");
+        dispatch(&manager, "a");
+        assert_eq!(subscriber.dropped_count(), 1);
+        assert!(subscriber.try_recv().is_some());
+        assert!(subscriber.try_recv().is_none());
+    }
+
+    #[test]
+    fn removes_dropped_subscribers() {
+        let manager = DispatchSubscriptionManager::new();
+        let subscriber = manager.subscribe("gone", HashSet::new(), 1);
+        assert_eq!(manager.subscription_count(), 1);
+        drop(subscriber);
+        dispatch(&manager, "a");
+        assert_eq!(manager.subscription_count(), 0);
+    }
+}
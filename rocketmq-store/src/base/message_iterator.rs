@@ -0,0 +1,225 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use cheetah_string::CheetahString;
+use rocketmq_common::common::message::message_decoder;
+use rocketmq_common::common::message::message_ext::MessageExt;
+
+use crate::base::message_status_enum::GetMessageStatus;
+use crate::filter::MessageFilter;
+use crate::log_file::MessageStore;
+
+/// Messages read from the store at once by default.
+const DEFAULT_BATCH_SIZE: i32 = 32;
+
+/// Cursor over the messages of one queue, starting at a queue offset.
+///
+/// The iterator reads the queue batch by batch, skips messages rejected by the filter and moves
+/// over offsets the store reports as illegal, so callers only deal with decoded messages and
+/// [`next_offset`](Self::next_offset), the offset to resume from.
+pub struct MessageIterator<'a, MS> {
+    message_store: &'a MS,
+    group: CheetahString,
+    topic: CheetahString,
+    queue_id: i32,
+    offset: i64,
+    batch_size: i32,
+    message_filter: Option<Arc<Box<dyn MessageFilter>>>,
+    de_compress_body: bool,
+    buffered: VecDeque<MessageExt>,
+}
+
+impl<'a, MS: MessageStore> MessageIterator<'a, MS> {
+    pub fn new(
+        message_store: &'a MS,
+        group: CheetahString,
+        topic: CheetahString,
+        queue_id: i32,
+        offset: i64,
+    ) -> Self {
+        Self {
+            message_store,
+            group,
+            topic,
+            queue_id,
+            offset,
+            batch_size: DEFAULT_BATCH_SIZE,
+            message_filter: None,
+            de_compress_body: false,
+            buffered: VecDeque::new(),
+        }
+    }
+
+    pub fn with_batch_size(mut self, batch_size: i32) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    pub fn with_filter(mut self, message_filter: Arc<Box<dyn MessageFilter>>) -> Self {
+        self.message_filter = Some(message_filter);
+        self
+    }
+
+    pub fn with_de_compress_body(mut self, de_compress_body: bool) -> Self {
+        self.de_compress_body = de_compress_body;
+        self
+    }
+
+    #[inline]
+    pub fn topic(&self) -> &CheetahString {
+        &self.topic
+    }
+
+    #[inline]
+    pub fn queue_id(&self) -> i32 {
+        self.queue_id
+    }
+
+    /// Queue offset of the first message not returned yet.
+    pub fn next_offset(&self) -> i64 {
+        self.buffered
+            .front()
+            .map_or(self.offset, |msg_ext| msg_ext.queue_offset)
+    }
+
+    /// Returns the next message, or `None` once the end of the queue is reached.
+    pub async fn next(&mut self) -> Option<MessageExt> {
+        if self.buffered.is_empty() {
+            self.fetch().await;
+        }
+        self.buffered.pop_front()
+    }
+
+    /// Returns the messages of the current batch, reading a new one from the store if the
+    /// current batch is used up. An empty result means the end of the queue is reached.
+    pub async fn next_batch(&mut self) -> Vec<MessageExt> {
+        if self.buffered.is_empty() {
+            self.fetch().await;
+        }
+        self.buffered.drain(..).collect()
+    }
+
+    async fn fetch(&mut self) {
+        loop {
+            let Some(get_message_result) = self
+                .message_store
+                .get_message(
+                    &self.group,
+                    &self.topic,
+                    self.queue_id,
+                    self.offset,
+                    self.batch_size,
+                    self.message_filter.clone(),
+                )
+                .await
+            else {
+                return;
+            };
+            let status = get_message_result.status();
+            let next_begin_offset = get_message_result.next_begin_offset();
+            if status == Some(GetMessageStatus::Found) {
+                let de_compress_body = self.de_compress_body;
+                self.buffered.extend(
+                    get_message_result
+                        .message_mapped_list()
+                        .iter()
+                        .filter_map(|buffer| buffer.get_bytes())
+                        .filter_map(|mut bytes| {
+                            message_decoder::decode(
+                                &mut bytes,
+                                true,
+                                de_compress_body,
+                                false,
+                                false,
+                                false,
+                            )
+                        }),
+                );
+            }
+            // Release the mapped buffers before reading on.
+            drop(get_message_result);
+            match advance(status, self.offset, next_begin_offset) {
+                Some(offset) => self.offset = offset,
+                None => return,
+            }
+            if !self.buffered.is_empty() {
+                return;
+            }
+        }
+    }
+}
+
+/// Offset to read from after a read at `offset` ended with `status`, or `None` when the end of
+/// the queue is reached.
+fn advance(status: Option<GetMessageStatus>, offset: i64, next_begin_offset: i64) -> Option<i64> {
+    match status? {
+        GetMessageStatus::Found
+        | GetMessageStatus::NoMatchedMessage
+        | GetMessageStatus::MessageWasRemoving
+        | GetMessageStatus::OffsetFoundNull
+        | GetMessageStatus::OffsetTooSmall
+        | GetMessageStatus::OffsetOverflowBadly
+        | GetMessageStatus::OffsetReset
+            if next_begin_offset != offset =>
+        {
+            Some(next_begin_offset)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moves_over_skipped_and_illegal_offsets() {
+        assert_eq!(advance(Some(GetMessageStatus::Found), 0, 32), Some(32));
+        assert_eq!(
+            advance(Some(GetMessageStatus::NoMatchedMessage), 0, 32),
+            Some(32)
+        );
+        assert_eq!(
+            advance(Some(GetMessageStatus::OffsetTooSmall), 0, 100),
+            Some(100)
+        );
+        assert_eq!(
+            advance(Some(GetMessageStatus::OffsetOverflowBadly), 200, 100),
+            Some(100)
+        );
+    }
+
+    #[test]
+    fn stops_at_the_end_of_the_queue() {
+        assert_eq!(advance(None, 0, 0), None);
+        assert_eq!(
+            advance(Some(GetMessageStatus::OffsetOverflowOne), 8, 8),
+            None
+        );
+        assert_eq!(
+            advance(Some(GetMessageStatus::NoMessageInQueue), 0, 0),
+            None
+        );
+        assert_eq!(
+            advance(Some(GetMessageStatus::NoMatchedLogicQueue), 0, 0),
+            None
+        );
+        assert_eq!(advance(Some(GetMessageStatus::OffsetReset), 8, 8), None);
+    }
+}
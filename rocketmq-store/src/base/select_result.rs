@@ -51,8 +51,11 @@ impl Default for SelectMappedBufferResult {
 }
 
 impl SelectMappedBufferResult {
-    /// Returns the buffer.
+    /// Returns the buffer, `bytes` when the data is not read from a mapped file.
     pub fn get_buffer(&self) -> &[u8] {
+        if let Some(bytes) = self.bytes.as_ref() {
+            return bytes.as_ref();
+        }
         self.mapped_file.as_ref().unwrap().get_mapped_file()
             [self.start_offset as usize..(self.start_offset + self.size as u64) as usize]
             .as_ref()
@@ -65,12 +68,28 @@ impl SelectMappedBufferResult {
     }
 
     pub fn get_bytes(&self) -> Option<Bytes> {
+        if self.bytes.is_some() {
+            return self.bytes.clone();
+        }
         if self.size <= 0 || self.mapped_file.is_none() {
             return None;
         }
         Some(BytesMut::from(self.get_buffer()).freeze())
     }
 
+    /// The `size` bytes at `pos` of the buffer, read from `bytes` or from the mapped file.
+    pub fn get_data(&self, pos: usize, size: usize) -> Option<Bytes> {
+        if pos + size > self.size.max(0) as usize {
+            return None;
+        }
+        if let Some(bytes) = self.bytes.as_ref() {
+            return Some(bytes.slice(pos..pos + size));
+        }
+        let mapped_file = self.mapped_file.as_ref()?;
+        let file_pos = (self.start_offset - mapped_file.get_file_from_offset()) as usize + pos;
+        mapped_file.get_data(file_pos, size)
+    }
+
     pub fn is_in_mem(&self) -> bool {
         match self.mapped_file.as_ref() {
             None => true,
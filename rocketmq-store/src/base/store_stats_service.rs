@@ -138,6 +138,38 @@ impl StoreStatsService {
         &self.put_message_failed_times
     }
 
+    /// Counts `times` messages of `size` bytes put to `topic`.
+    pub fn add_single_put_message_topic(&self, topic: &str, times: usize, size: usize) {
+        Self::add_topic_total(&self.put_message_topic_times_total, topic, times);
+        Self::add_topic_total(&self.put_message_topic_size_total, topic, size);
+    }
+
+    pub fn get_single_put_message_topic_times_total(&self, topic: &str) -> usize {
+        self.put_message_topic_times_total
+            .read()
+            .get(topic)
+            .map_or(0, |total| total.load(Ordering::Relaxed))
+    }
+
+    pub fn get_single_put_message_topic_size_total(&self, topic: &str) -> usize {
+        self.put_message_topic_size_total
+            .read()
+            .get(topic)
+            .map_or(0, |total| total.load(Ordering::Relaxed))
+    }
+
+    fn add_topic_total(totals: &RwLock<HashMap<String, AtomicUsize>>, topic: &str, value: usize) {
+        if let Some(total) = totals.read().get(topic) {
+            total.fetch_add(value, Ordering::Relaxed);
+            return;
+        }
+        totals
+            .write()
+            .entry(topic.to_string())
+            .or_default()
+            .fetch_add(value, Ordering::Relaxed);
+    }
+
     #[inline]
     fn reset_put_message_time_buckets(&mut self) {
         let mut next_buckets: BTreeMap<u64, AtomicUsize> = BTreeMap::new();
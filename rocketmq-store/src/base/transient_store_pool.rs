@@ -19,12 +19,19 @@ use std::collections::VecDeque;
 use std::sync::Arc;
 use std::sync::Mutex;
 
+use memmap2::MmapMut;
+use tracing::info;
 use tracing::warn;
 
+/// A pool of write buffers for the commit log files.
+///
+/// Messages are appended to the buffer a file borrowed instead of its mapping, and committed to
+/// the file by the commit real time service. The buffers are anonymous mappings locked into
+/// memory, so that appends do not fault on the page cache of a busy disk.
 pub struct TransientStorePool {
     pool_size: usize,
     file_size: usize,
-    available_buffers: Arc<Mutex<VecDeque<Vec<u8>>>>,
+    available_buffers: Arc<Mutex<VecDeque<MmapMut>>>,
     is_real_commit: Arc<Mutex<bool>>,
 }
 
@@ -40,12 +47,31 @@ impl TransientStorePool {
         }
     }
 
+    /// Allocates and locks the buffers of the pool.
     pub fn init(&self) {
         let mut available_buffers = self.available_buffers.lock().unwrap();
         for _ in 0..self.pool_size {
-            let buffer = vec![0u8; self.file_size];
+            let buffer = match MmapMut::map_anon(self.file_size) {
+                Ok(buffer) => buffer,
+                Err(e) => {
+                    warn!("allocate transient store buffer failed: {}", e);
+                    break;
+                }
+            };
+            #[cfg(unix)]
+            if let Err(e) = buffer.lock() {
+                warn!(
+                    "lock transient store buffer failed, raise `ulimit -l`: {}",
+                    e
+                );
+            }
             available_buffers.push_back(buffer);
         }
+        info!(
+            "TransientStorePool init {} buffers of {} bytes",
+            available_buffers.len(),
+            self.file_size
+        );
     }
 
     pub fn destroy(&self) {
@@ -53,12 +79,12 @@ impl TransientStorePool {
         available_buffers.clear();
     }
 
-    pub fn return_buffer(&self, buffer: Vec<u8>) {
+    pub fn return_buffer(&self, buffer: MmapMut) {
         let mut available_buffers = self.available_buffers.lock().unwrap();
         available_buffers.push_front(buffer);
     }
 
-    pub fn borrow_buffer(&self) -> Option<Vec<u8>> {
+    pub fn borrow_buffer(&self) -> Option<MmapMut> {
         let mut available_buffers = self.available_buffers.lock().unwrap();
         let buffer = available_buffers.pop_front();
         if available_buffers.len() < self.pool_size / 10 * 4 {
@@ -96,3 +122,24 @@ impl Clone for TransientStorePool {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn borrowed_buffers_are_returned_to_the_pool() {
+        let pool = TransientStorePool::new(2, 4096);
+        pool.init();
+        assert_eq!(pool.available_buffer_nums(), 2);
+        let first = pool.borrow_buffer().unwrap();
+        let second = pool.borrow_buffer().unwrap();
+        assert_eq!(first.len(), 4096);
+        assert!(pool.borrow_buffer().is_none());
+        pool.return_buffer(first);
+        pool.return_buffer(second);
+        assert_eq!(pool.available_buffer_nums(), 2);
+        pool.destroy();
+        assert_eq!(pool.available_buffer_nums(), 0);
+    }
+}
@@ -18,3 +18,4 @@
 pub mod flush_disk_type;
 pub mod message_store_config;
 pub(crate) mod store_path_config_helper;
+pub mod store_timestamp_mode;
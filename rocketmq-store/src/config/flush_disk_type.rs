@@ -18,6 +18,8 @@ use std::fmt;
 
 use serde::Deserialize;
 use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
 
 #[allow(dead_code)]
 #[derive(Debug, Copy, Clone, Default, PartialEq)]
@@ -37,6 +39,15 @@ impl FlushDiskType {
     }
 }
 
+impl Serialize for FlushDiskType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.get_flush_disk_type())
+    }
+}
+
 impl<'de> Deserialize<'de> for FlushDiskType {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
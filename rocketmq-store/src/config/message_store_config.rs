@@ -21,17 +21,21 @@ use std::path::PathBuf;
 use cheetah_string::CheetahString;
 use lazy_static::lazy_static;
 use rocketmq_common::common::broker::broker_role::BrokerRole;
+use rocketmq_common::utils::config_value_utils::find_config_value;
+use rocketmq_common::utils::config_value_utils::set_config_value;
 use serde::Deserialize;
+use serde::Serialize;
 
 use crate::base::store_enum::StoreType;
 use crate::config::flush_disk_type::FlushDiskType;
+use crate::config::store_timestamp_mode::StoreTimestampMode;
 use crate::queue::single_consume_queue::CQ_STORE_UNIT_SIZE;
 
 lazy_static! {
     static ref USER_HOME: PathBuf = dirs::home_dir().unwrap();
 }
 
-#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct MessageStoreConfig {
     pub store_path_root_dir: CheetahString,
@@ -93,8 +97,16 @@ pub struct MessageStoreConfig {
     pub put_msg_index_hight_water: usize,
     pub max_message_size: i32,
     pub check_crc_on_recover: bool,
+    /// Verifies the CRC of the messages read by the consumers, a message failing it is not
+    /// returned
+    pub check_crc_on_read: bool,
+    /// Verifies the CRC of the messages dispatched to the consume queues and indexes, a message
+    /// failing it is stepped over
+    pub check_crc_on_dispatch: bool,
     pub flush_commit_log_least_pages: i32,
     pub commit_commit_log_least_pages: i32,
+    /// Pages touched between two flushes while a new commit log file is warmed up with
+    /// `SYNC_FLUSH`
     pub flush_least_pages_when_warm_mapped_file: usize,
     pub flush_consume_queue_least_pages: usize,
     pub flush_commit_log_thorough_interval: i32,
@@ -124,6 +136,7 @@ pub struct MessageStoreConfig {
     pub message_delay_level: String,
     pub flush_delay_offset_interval: usize,
     pub clean_file_forcibly_enable: bool,
+    /// Touch every page of a new commit log file and lock it in memory as it is created
     pub warm_mapped_file_enable: bool,
     pub offset_check_in_slave: bool,
     pub debug_lock_enable: bool,
@@ -206,6 +219,73 @@ pub struct MessageStoreConfig {
     pub enable_rocksdb_log: bool,
     pub topic_queue_lock_num: usize,
     pub max_filter_message_size: i32,
+    /// Max number of commit log reads of cold (not page cached) data running at the same time
+    /// on the blocking pool, `0` reads cold data on the calling task
+    pub cold_read_io_pool_size: usize,
+    /// Persist the write position of every consume queue every `flush_interval_consume_queue`
+    /// ms, so recovery can resume scanning a queue from there instead of from its last files
+    pub enable_queue_offset_snapshot: bool,
+    /// Adjust the flush and commit batching and the reput batch size to the observed put rate
+    /// and put latency, within the bounds below
+    pub adaptive_tuning_enable: bool,
+    pub adaptive_tuning_interval_millis: u64,
+    /// p99 put latency above which flushes and commits are made smaller again
+    pub adaptive_put_latency_target_millis: u64,
+    /// Put rate from which flushes, commits and reput batches grow
+    pub adaptive_high_put_tps: u64,
+    /// Upper bound of the tuned `flush_commit_log_least_pages`, the configured value is the
+    /// lower bound
+    pub adaptive_flush_least_pages_max: i32,
+    /// Upper bound of the tuned `commit_interval_commit_log`, the configured value is the lower
+    /// bound
+    pub adaptive_commit_interval_max_millis: u64,
+    /// Bounds of the number of messages dispatched per reput round, unbounded without tuning
+    pub adaptive_reput_batch_min: usize,
+    pub adaptive_reput_batch_max: usize,
+    /// Upload the sealed commit log and consume queue files to `tiered_backend_provider` and
+    /// serve the messages no longer kept locally from there
+    pub tiered_storage_enable: bool,
+    /// `posix` keeps the tier in the local directory `tiered_store_file_path`, `s3` in the
+    /// bucket below under the key prefix `tiered_store_file_path`
+    pub tiered_backend_provider: CheetahString,
+    pub tiered_store_file_path: CheetahString,
+    pub object_store_endpoint: CheetahString,
+    pub object_store_region: CheetahString,
+    pub object_store_bucket: CheetahString,
+    pub object_store_access_key: CheetahString,
+    pub object_store_secret_key: CheetahString,
+    pub tiered_upload_interval_millis: u64,
+    /// Hours an uploaded file is kept in the tier, local files older than that are not
+    /// uploaded anymore
+    pub tiered_store_file_reserved_time: u64,
+    /// Bytes of the tier kept in memory for cold reads, in segments of
+    /// `tiered_read_cache_segment_size` bytes, `0` reads every message from the tier
+    pub tiered_read_cache_size: usize,
+    pub tiered_read_cache_segment_size: usize,
+    /// Segments fetched in the background after a read that missed the cache, so that a
+    /// sequential replay finds the data it reads next in the cache
+    pub tiered_read_ahead_segments: usize,
+    /// Commit the write buffers, flush and read the store files through io_uring instead of
+    /// the mappings, needs the `io_uring` feature and Linux, falls back to the mappings
+    /// otherwise
+    pub enable_io_uring: bool,
+    /// Submission queue entries of the io_uring of each store thread
+    pub io_uring_entries: u32,
+    /// Time the messages are stored with, ignored with `duplication_enable` where a message
+    /// keeps the store timestamp it comes with
+    pub store_timestamp_mode: StoreTimestampMode,
+    /// `ip:port` the messages are stored with instead of the address of the broker, such as the
+    /// address clients of another data center reach it at
+    pub store_host_override: Option<CheetahString>,
+    /// Recompress the sealed commit log files older than `commit_log_compression_after_hours`
+    /// into `store_path_root_dir/compressedcommitlog`, the messages in them are decompressed on
+    /// read
+    pub commit_log_compression_enable: bool,
+    /// `ZSTD`, `LZ4` or `ZLIB`
+    pub commit_log_compression_type: CheetahString,
+    pub commit_log_compression_after_hours: u64,
+    /// Bytes compressed together, a read decompresses the blocks it covers
+    pub commit_log_compression_block_size: usize,
 }
 
 impl Default for MessageStoreConfig {
@@ -242,7 +322,7 @@ impl Default for MessageStoreConfig {
             timer_enable_disruptor: false,
             timer_enable_check_metrics: false,
             timer_intercept_delay_level: false,
-            timer_max_delay_sec: 0,
+            timer_max_delay_sec: 3600 * 24 * 3,
             timer_wheel_enable: false,
             disappear_time_after_start: -1,
             timer_stop_enqueue: false,
@@ -250,7 +330,7 @@ impl Default for MessageStoreConfig {
             timer_skip_unknown_error: false,
             timer_warm_enable: false,
             timer_stop_dequeue: false,
-            timer_congest_num_each_slot: 0,
+            timer_congest_num_each_slot: i32::MAX as usize,
             timer_metric_small_threshold: 0,
             timer_progress_log_interval_ms: 0,
             store_type: Default::default(),
@@ -262,8 +342,8 @@ impl Default for MessageStoreConfig {
             flush_interval_commit_log: 500,
             commit_interval_commit_log: 200,
             max_recovery_commit_log_files: 0,
-            disk_space_warning_level_ratio: 0,
-            disk_space_clean_forcibly_ratio: 0,
+            disk_space_warning_level_ratio: 90,
+            disk_space_clean_forcibly_ratio: 85,
             use_reentrant_lock_when_put_message: false,
             flush_commit_log_timed: true,
             flush_interval_consume_queue: 1000,
@@ -274,14 +354,16 @@ impl Default for MessageStoreConfig {
             redelete_hanged_file_interval: 1000 * 120,
             delete_when: "04".to_string(),
             disk_max_used_space_ratio: 75,
-            file_reserved_time: 0,
-            delete_file_batch_max: 0,
+            file_reserved_time: 72,
+            delete_file_batch_max: 10,
             put_msg_index_hight_water: 0,
             max_message_size: 1024 * 1024 * 4,
-            check_crc_on_recover: false,
-            flush_commit_log_least_pages: 0,
+            check_crc_on_recover: true,
+            check_crc_on_read: false,
+            check_crc_on_dispatch: false,
+            flush_commit_log_least_pages: 4,
             commit_commit_log_least_pages: 4,
-            flush_least_pages_when_warm_mapped_file: 0,
+            flush_least_pages_when_warm_mapped_file: 1024 / 4 * 16,
             flush_consume_queue_least_pages: 0,
             flush_commit_log_thorough_interval: 1000 * 10,
             commit_commit_log_thorough_interval: 200,
@@ -296,20 +378,21 @@ impl Default for MessageStoreConfig {
             max_index_num: 5000000 * 4,
             max_msgs_num_batch: 64,
             message_index_safe: false,
-            ha_listen_port: 0,
-            ha_send_heartbeat_interval: 0,
-            ha_housekeeping_interval: 0,
-            ha_transfer_batch_size: 0,
+            ha_listen_port: 10912,
+            ha_send_heartbeat_interval: 1000 * 5,
+            ha_housekeeping_interval: 1000 * 20,
+            ha_transfer_batch_size: 1024 * 32,
             ha_master_address: None,
-            ha_max_gap_not_in_sync: 0,
+            ha_max_gap_not_in_sync: 1024 * 1024 * 256,
             broker_role: Default::default(),
             flush_disk_type: FlushDiskType::SyncFlush,
             sync_flush_timeout: 1000 * 5,
             put_message_timeout: 0,
             slave_timeout: 0,
-            message_delay_level: "".to_string(),
-            flush_delay_offset_interval: 0,
-            clean_file_forcibly_enable: false,
+            message_delay_level: "1s 5s 10s 30s 1m 2m 3m 4m 5m 6m 7m 8m 9m 10m 20m 30m 1h 2h"
+                .to_string(),
+            flush_delay_offset_interval: 1000 * 10,
+            clean_file_forcibly_enable: true,
             warm_mapped_file_enable: false,
             offset_check_in_slave: false,
             debug_lock_enable: false,
@@ -329,7 +412,7 @@ impl Default for MessageStoreConfig {
             enable_schedule_message_stats: false,
             enable_lmq: false,
             enable_multi_dispatch: false,
-            max_lmq_consume_queue_num: 0,
+            max_lmq_consume_queue_num: 20000,
             enable_schedule_async_deliver: false,
             schedule_async_deliver_max_pending_limit: 0,
             schedule_async_deliver_max_resend_num2_blocked: 0,
@@ -367,7 +450,7 @@ impl Default for MessageStoreConfig {
             enable_auto_in_sync_replicas: false,
             ha_flow_control_enable: false,
             max_ha_transfer_byte_in_second: 0,
-            ha_max_time_slave_not_catchup: 0,
+            ha_max_time_slave_not_catchup: 1000 * 15,
             sync_master_flush_offset_when_startup: false,
             max_checksum_range: 0,
             replicas_per_disk_partition: 0,
@@ -392,6 +475,37 @@ impl Default for MessageStoreConfig {
             enable_rocksdb_log: false,
             topic_queue_lock_num: 32,
             max_filter_message_size: 16000,
+            cold_read_io_pool_size: 16,
+            enable_queue_offset_snapshot: true,
+            adaptive_tuning_enable: false,
+            adaptive_tuning_interval_millis: 10_000,
+            adaptive_put_latency_target_millis: 10,
+            adaptive_high_put_tps: 20_000,
+            adaptive_flush_least_pages_max: 16,
+            adaptive_commit_interval_max_millis: 1000,
+            adaptive_reput_batch_min: 1024,
+            adaptive_reput_batch_max: 32 * 1024,
+            tiered_storage_enable: false,
+            tiered_backend_provider: CheetahString::from_static_str("posix"),
+            tiered_store_file_path: CheetahString::empty(),
+            object_store_endpoint: CheetahString::empty(),
+            object_store_region: CheetahString::empty(),
+            object_store_bucket: CheetahString::empty(),
+            object_store_access_key: CheetahString::empty(),
+            object_store_secret_key: CheetahString::empty(),
+            tiered_upload_interval_millis: 10 * 1000,
+            tiered_store_file_reserved_time: 72,
+            tiered_read_cache_size: 256 * 1024 * 1024,
+            tiered_read_cache_segment_size: 4 * 1024 * 1024,
+            tiered_read_ahead_segments: 2,
+            enable_io_uring: false,
+            io_uring_entries: 256,
+            store_timestamp_mode: StoreTimestampMode::LockTime,
+            store_host_override: None,
+            commit_log_compression_enable: false,
+            commit_log_compression_type: CheetahString::from_static_str("ZSTD"),
+            commit_log_compression_after_hours: 72,
+            commit_log_compression_block_size: 1024 * 1024,
         }
     }
 }
@@ -407,6 +521,34 @@ impl MessageStoreConfig {
         self.store_path_commit_log.clone().unwrap().to_string()
     }
 
+    /// Directory of the compressed sealed commit log files.
+    pub fn get_store_path_compressed_commit_log(&self) -> String {
+        PathBuf::from(self.store_path_root_dir.to_string())
+            .join("compressedcommitlog")
+            .to_string_lossy()
+            .to_string()
+    }
+
+    /// Base directory of the DLedger commit log, every member keeps its files in a
+    /// `dledger-<selfId>` directory in it.
+    pub fn get_store_path_dledger_commit_log(&self) -> String {
+        match self.store_path_dledger_commit_log.as_ref() {
+            Some(path) if !path.is_empty() => path.to_string(),
+            _ => PathBuf::from(self.store_path_root_dir.to_string())
+                .join("dledger")
+                .to_string_lossy()
+                .to_string(),
+        }
+    }
+
+    /// The transient store pool only works with `ASYNC_FLUSH`, the write buffers are committed
+    /// asynchronously, and not with the DLedger commit log.
+    pub fn is_transient_store_pool_enable(&self) -> bool {
+        self.transient_store_pool_enable
+            && self.flush_disk_type == FlushDiskType::AsyncFlush
+            && !self.enable_dledger_commit_log
+    }
+
     pub fn is_enable_rocksdb_store(&self) -> bool {
         self.store_type == StoreType::RocksDB
     }
@@ -670,6 +812,14 @@ impl MessageStoreConfig {
             "checkCrcOnRecover".to_string(),
             self.check_crc_on_recover.to_string(),
         );
+        properties.insert(
+            "checkCrcOnRead".to_string(),
+            self.check_crc_on_read.to_string(),
+        );
+        properties.insert(
+            "checkCrcOnDispatch".to_string(),
+            self.check_crc_on_dispatch.to_string(),
+        );
         properties.insert(
             "flushCommitLogLeastPages".to_string(),
             self.flush_commit_log_least_pages.to_string(),
@@ -1102,9 +1252,210 @@ impl MessageStoreConfig {
             "maxFilterMessageSize".into(),
             self.max_filter_message_size.to_string(),
         );
+        properties.insert(
+            "coldReadIoPoolSize".into(),
+            self.cold_read_io_pool_size.to_string(),
+        );
+        properties.insert(
+            "enableQueueOffsetSnapshot".into(),
+            self.enable_queue_offset_snapshot.to_string(),
+        );
+        properties.insert(
+            "adaptiveTuningEnable".into(),
+            self.adaptive_tuning_enable.to_string(),
+        );
+        properties.insert(
+            "adaptiveTuningIntervalMillis".into(),
+            self.adaptive_tuning_interval_millis.to_string(),
+        );
+        properties.insert(
+            "adaptivePutLatencyTargetMillis".into(),
+            self.adaptive_put_latency_target_millis.to_string(),
+        );
+        properties.insert(
+            "adaptiveHighPutTps".into(),
+            self.adaptive_high_put_tps.to_string(),
+        );
+        properties.insert(
+            "adaptiveFlushLeastPagesMax".into(),
+            self.adaptive_flush_least_pages_max.to_string(),
+        );
+        properties.insert(
+            "adaptiveCommitIntervalMaxMillis".into(),
+            self.adaptive_commit_interval_max_millis.to_string(),
+        );
+        properties.insert(
+            "adaptiveReputBatchMin".into(),
+            self.adaptive_reput_batch_min.to_string(),
+        );
+        properties.insert(
+            "adaptiveReputBatchMax".into(),
+            self.adaptive_reput_batch_max.to_string(),
+        );
+        properties.insert(
+            "tieredStorageEnable".into(),
+            self.tiered_storage_enable.to_string(),
+        );
+        properties.insert(
+            "tieredBackendProvider".into(),
+            self.tiered_backend_provider.to_string(),
+        );
+        properties.insert(
+            "tieredStoreFilePath".into(),
+            self.tiered_store_file_path.to_string(),
+        );
+        properties.insert(
+            "objectStoreEndpoint".into(),
+            self.object_store_endpoint.to_string(),
+        );
+        properties.insert(
+            "objectStoreRegion".into(),
+            self.object_store_region.to_string(),
+        );
+        properties.insert(
+            "objectStoreBucket".into(),
+            self.object_store_bucket.to_string(),
+        );
+        properties.insert(
+            "objectStoreAccessKey".into(),
+            self.object_store_access_key.to_string(),
+        );
+        properties.insert(
+            "tieredUploadIntervalMillis".into(),
+            self.tiered_upload_interval_millis.to_string(),
+        );
+        properties.insert(
+            "tieredStoreFileReservedTime".into(),
+            self.tiered_store_file_reserved_time.to_string(),
+        );
+        properties.insert(
+            "tieredReadCacheSize".into(),
+            self.tiered_read_cache_size.to_string(),
+        );
+        properties.insert(
+            "tieredReadCacheSegmentSize".into(),
+            self.tiered_read_cache_segment_size.to_string(),
+        );
+        properties.insert(
+            "tieredReadAheadSegments".into(),
+            self.tiered_read_ahead_segments.to_string(),
+        );
+        properties.insert("enableIoUring".into(), self.enable_io_uring.to_string());
+        properties.insert("ioUringEntries".into(), self.io_uring_entries.to_string());
+        properties.insert(
+            "storeTimestampMode".into(),
+            self.store_timestamp_mode
+                .get_store_timestamp_mode()
+                .to_string(),
+        );
+        properties.insert(
+            "storeHostOverride".into(),
+            self.store_host_override
+                .clone()
+                .unwrap_or_default()
+                .to_string(),
+        );
+        properties.insert(
+            "commitLogCompressionEnable".into(),
+            self.commit_log_compression_enable.to_string(),
+        );
+        properties.insert(
+            "commitLogCompressionType".into(),
+            self.commit_log_compression_type.to_string(),
+        );
+        properties.insert(
+            "commitLogCompressionAfterHours".into(),
+            self.commit_log_compression_after_hours.to_string(),
+        );
+        properties.insert(
+            "commitLogCompressionBlockSize".into(),
+            self.commit_log_compression_block_size.to_string(),
+        );
         properties
             .into_iter()
             .map(|(k, v)| (k.into(), v.into()))
             .collect::<HashMap<CheetahString, CheetahString>>()
     }
+
+    /// Applies `properties` (camelCase keys as returned by
+    /// [`MessageStoreConfig::get_properties`]).
+    ///
+    /// Either every property is applied or, if a key is unknown or a value can not be parsed,
+    /// none is and the offending key is reported.
+    pub fn update(
+        &mut self,
+        properties: &HashMap<CheetahString, CheetahString>,
+    ) -> Result<(), String> {
+        let mut value = serde_json::to_value(&*self).map_err(|e| e.to_string())?;
+        for (key, raw) in properties {
+            if !set_config_value(&mut value, key.as_str(), raw.as_str())? {
+                return Err(format!("unknown message store config key '{}'", key));
+            }
+        }
+        *self = serde_json::from_value(value)
+            .map_err(|e| format!("invalid message store config: {}", e))?;
+        Ok(())
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        serde_json::to_value(self)
+            .map(|value| find_config_value(&value, key).is_some())
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn properties(pairs: &[(&str, &str)]) -> HashMap<CheetahString, CheetahString> {
+        pairs
+            .iter()
+            .map(|(key, value)| (CheetahString::from(*key), CheetahString::from(*value)))
+            .collect()
+    }
+
+    #[test]
+    fn update_applies_the_properties() {
+        let mut config = MessageStoreConfig::default();
+        config
+            .update(&properties(&[
+                ("storePathRootDir", "/tmp/store-a"),
+                ("storePathCommitLog", "/tmp/store-a/log"),
+                ("brokerRole", "SLAVE"),
+                ("flushDiskType", "SYNC_FLUSH"),
+                ("haListenPort", "10922"),
+                ("duplicationEnable", "true"),
+            ]))
+            .unwrap();
+        assert_eq!(config.store_path_root_dir, "/tmp/store-a");
+        assert_eq!(config.get_store_path_commit_log(), "/tmp/store-a/log");
+        assert_eq!(config.broker_role, BrokerRole::Slave);
+        assert_eq!(config.flush_disk_type, FlushDiskType::SyncFlush);
+        assert_eq!(config.ha_listen_port, 10922);
+        assert!(config.duplication_enable);
+        assert!(config.contains_key("storePathRootDir"));
+        assert!(!config.contains_key("brokerName"));
+    }
+
+    #[test]
+    fn update_rejects_unknown_keys_and_invalid_values() {
+        let mut config = MessageStoreConfig::default();
+        let err = config
+            .update(&properties(&[
+                ("storePathRootDir", "/tmp/store-a"),
+                ("brokerName", "broker-a"),
+            ]))
+            .unwrap_err();
+        assert!(err.contains("brokerName"));
+        assert_eq!(config, MessageStoreConfig::default());
+
+        assert!(config
+            .update(&properties(&[("haListenPort", "port")]))
+            .is_err());
+        assert!(config
+            .update(&properties(&[("flushDiskType", "NO_FLUSH")]))
+            .is_err());
+        assert_eq!(config, MessageStoreConfig::default());
+    }
 }
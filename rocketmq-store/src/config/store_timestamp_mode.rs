@@ -0,0 +1,93 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::fmt;
+
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+
+/// Which time a message is stored with.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub enum StoreTimestampMode {
+    /// The time the put lock of the commit log is taken, the store timestamps grow along the
+    /// commit log.
+    #[default]
+    LockTime,
+
+    /// The time the message arrived at the store, before it waits for the put lock, so the
+    /// time spent waiting is not counted in the store timestamp.
+    ArrivalTime,
+
+    /// The born timestamp set by the client, for replicating messages between data centers
+    /// whose broker clocks differ. It is bounded by the lock time and the store timestamp of
+    /// the message before, so a client clock running ahead or behind does not break the order.
+    BornTime,
+}
+
+impl StoreTimestampMode {
+    pub fn get_store_timestamp_mode(&self) -> &'static str {
+        match self {
+            StoreTimestampMode::LockTime => "LOCK_TIME",
+            StoreTimestampMode::ArrivalTime => "ARRIVAL_TIME",
+            StoreTimestampMode::BornTime => "BORN_TIME",
+        }
+    }
+}
+
+impl Serialize for StoreTimestampMode {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.get_store_timestamp_mode())
+    }
+}
+
+impl<'de> Deserialize<'de> for StoreTimestampMode {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct StoreTimestampModeVisitor;
+
+        impl serde::de::Visitor<'_> for StoreTimestampModeVisitor {
+            type Value = StoreTimestampMode;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a string representing StoreTimestampMode")
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                match value {
+                    "LOCK_TIME" => Ok(StoreTimestampMode::LockTime),
+                    "ARRIVAL_TIME" => Ok(StoreTimestampMode::ArrivalTime),
+                    "BORN_TIME" => Ok(StoreTimestampMode::BornTime),
+                    _ => Err(serde::de::Error::unknown_variant(
+                        value,
+                        &["LOCK_TIME", "ARRIVAL_TIME", "BORN_TIME"],
+                    )),
+                }
+            }
+        }
+
+        deserializer.deserialize_str(StoreTimestampModeVisitor)
+    }
+}
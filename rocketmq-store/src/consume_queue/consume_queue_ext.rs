@@ -15,10 +15,19 @@
  * limitations under the License.
  */
 
-const MIN_EXT_UNIT_SIZE: i16 = 2  // size, 32k max
+use bytes::Buf;
+use bytes::BufMut;
+
+pub(crate) const MIN_EXT_UNIT_SIZE: i16 = 2  // size, 32k max
  + 8 * 2 // msg time + tagCode
   + 2; // bitMapSize
-const MAX_EXT_UNIT_SIZE: i16 = i16::MAX;
+pub(crate) const MAX_EXT_UNIT_SIZE: i16 = i16::MAX;
+
+/// Whether the tags code of a consume queue unit is an address in the extend file.
+#[inline]
+pub fn is_ext_addr(tags_code: i64) -> bool {
+    tags_code < i32::MIN as i64
+}
 
 #[derive(Clone, Default)]
 pub struct CqExtUnit {
@@ -61,4 +70,66 @@ impl CqExtUnit {
     pub fn filter_bit_map(&self) -> &Option<Vec<u8>> {
         &self.filter_bit_map
     }
+
+    /// Bytes of the unit once written: size, tags code, store time, bit map size and bit map.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(self.size as usize);
+        buf.put_i16(self.size);
+        buf.put_i64(self.tags_code);
+        buf.put_i64(self.msg_store_time);
+        buf.put_i16(self.bit_map_size);
+        if let Some(filter_bit_map) = self.filter_bit_map.as_ref() {
+            buf.put_slice(filter_bit_map);
+        }
+        buf
+    }
+
+    /// Reads the unit at the start of `buf`, `None` when nothing was written there.
+    pub fn decode(mut buf: &[u8]) -> Option<Self> {
+        if buf.remaining() < MIN_EXT_UNIT_SIZE as usize {
+            return None;
+        }
+        let size = buf.get_i16();
+        if size < MIN_EXT_UNIT_SIZE {
+            return None;
+        }
+        let tags_code = buf.get_i64();
+        let msg_store_time = buf.get_i64();
+        let bit_map_size = buf.get_i16();
+        let filter_bit_map = if bit_map_size > 0 {
+            if buf.remaining() < bit_map_size as usize {
+                return None;
+            }
+            Some(buf[..bit_map_size as usize].to_vec())
+        } else {
+            None
+        };
+        Some(Self {
+            size,
+            tags_code,
+            msg_store_time,
+            bit_map_size: bit_map_size.max(0),
+            filter_bit_map,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_and_decode() {
+        let unit = CqExtUnit::new(7, 1000, Some(vec![1, 2, 3]));
+        let bytes = unit.encode();
+        assert_eq!(bytes.len(), unit.size() as usize);
+        let decoded = CqExtUnit::decode(&bytes).unwrap();
+        assert_eq!(decoded.tags_code(), 7);
+        assert_eq!(decoded.msg_store_time(), 1000);
+        assert_eq!(decoded.filter_bit_map().as_deref(), Some(&[1u8, 2, 3][..]));
+
+        let decoded = CqExtUnit::decode(&CqExtUnit::new(7, 1000, None).encode()).unwrap();
+        assert!(decoded.filter_bit_map().is_none());
+        assert!(CqExtUnit::decode(&[0u8; 32]).is_none());
+    }
 }
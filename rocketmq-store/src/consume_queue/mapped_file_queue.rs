@@ -21,16 +21,21 @@ use std::path::PathBuf;
 use std::sync::atomic::AtomicU64;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
 
 use cheetah_string::CheetahString;
 use parking_lot::RwLock;
+use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_common::UtilAll::offset_to_file_name;
 use tracing::info;
 use tracing::warn;
 
+use crate::base::allocate_mapped_file_service::AllocateMappedFileService;
+use crate::base::transient_store_pool::TransientStorePool;
+use crate::consume_queue::multi_store_paths::MultiStorePaths;
 use crate::log_file::mapped_file::default_mapped_file_impl::DefaultMappedFile;
 use crate::log_file::mapped_file::MappedFile;
-use crate::services::allocate_mapped_file_service::AllocateMappedFileService;
 
 #[derive(Default, Clone)]
 pub struct MappedFileQueue {
@@ -50,6 +55,12 @@ pub struct MappedFileQueue {
     pub(crate) committed_where: Arc<AtomicU64>,
 
     pub(crate) store_timestamp: Arc<AtomicU64>,
+
+    pub(crate) transient_store_pool: Option<TransientStorePool>,
+
+    pub(crate) fast_fail_if_no_buffer_in_store_pool: bool,
+
+    pub(crate) multi_store_paths: Option<MultiStorePaths>,
 }
 
 impl MappedFileQueue {
@@ -67,6 +78,40 @@ impl MappedFileQueue {
             flushed_where: Arc::new(AtomicU64::new(0)),
             committed_where: Arc::new(AtomicU64::new(0)),
             store_timestamp: Arc::new(AtomicU64::new(0)),
+            transient_store_pool: None,
+            fast_fail_if_no_buffer_in_store_pool: false,
+            multi_store_paths: None,
+        }
+    }
+
+    /// Files created from now on append to buffers of `transient_store_pool`. When the pool is
+    /// exhausted a file is written through its mapping, or not created at all with
+    /// `fast_fail_if_no_buffer_in_store_pool`.
+    #[inline]
+    pub fn set_transient_store_pool(
+        &mut self,
+        transient_store_pool: TransientStorePool,
+        fast_fail_if_no_buffer_in_store_pool: bool,
+    ) {
+        self.transient_store_pool = Some(transient_store_pool);
+        self.fast_fail_if_no_buffer_in_store_pool = fast_fail_if_no_buffer_in_store_pool;
+    }
+
+    /// Spreads the files over the directories of `multi_store_paths` instead of `store_path`.
+    #[inline]
+    pub fn set_multi_store_paths(&mut self, multi_store_paths: MultiStorePaths) {
+        self.multi_store_paths = Some(multi_store_paths);
+    }
+
+    #[inline]
+    pub fn get_multi_store_paths(&self) -> Option<&MultiStorePaths> {
+        self.multi_store_paths.as_ref()
+    }
+
+    fn store_dirs(&self) -> Vec<String> {
+        match self.multi_store_paths.as_ref() {
+            Some(multi_store_paths) => multi_store_paths.all_paths().cloned().collect(),
+            None => vec![self.store_path.clone()],
         }
     }
 }
@@ -75,15 +120,16 @@ impl MappedFileQueue {
     #[inline]
     pub fn load(&mut self) -> bool {
         //list dir files
-        let dir = Path::new(&self.store_path);
-        if let Ok(ls) = fs::read_dir(dir) {
-            let files: Vec<_> = ls
-                .filter_map(Result::ok)
-                .map(|entry| entry.path())
-                .collect();
-            return self.do_load(files);
+        let mut files = Vec::new();
+        for dir in self.store_dirs() {
+            if let Ok(ls) = fs::read_dir(Path::new(&dir)) {
+                files.extend(ls.filter_map(Result::ok).map(|entry| entry.path()));
+            }
         }
-        true
+        if files.is_empty() {
+            return true;
+        }
+        self.do_load(files)
     }
 
     #[inline]
@@ -209,9 +255,23 @@ impl MappedFileQueue {
 
     #[inline]
     pub fn try_create_mapped_file(&mut self, create_offset: u64) -> Option<Arc<DefaultMappedFile>> {
-        let next_file_path =
-            PathBuf::from(self.store_path.clone()).join(offset_to_file_name(create_offset));
-        let next_next_file_path = PathBuf::from(self.store_path.clone())
+        let store_path = match self.multi_store_paths.as_ref() {
+            Some(multi_store_paths) => {
+                let Some(store_path) = multi_store_paths.select_store_path(self.mapped_file_size)
+                else {
+                    warn!(
+                        "no store path of {:?} can take the mapped file at {}",
+                        multi_store_paths.store_paths(),
+                        create_offset
+                    );
+                    return None;
+                };
+                store_path
+            }
+            None => self.store_path.clone(),
+        };
+        let next_file_path = PathBuf::from(&store_path).join(offset_to_file_name(create_offset));
+        let next_next_file_path = PathBuf::from(&store_path)
             .join(offset_to_file_name(create_offset + self.mapped_file_size));
         self.do_create_mapped_file(next_file_path, next_next_file_path)
     }
@@ -220,16 +280,36 @@ impl MappedFileQueue {
     fn do_create_mapped_file(
         &mut self,
         next_file_path: PathBuf,
-        _next_next_file_path: PathBuf,
+        next_next_file_path: PathBuf,
     ) -> Option<Arc<DefaultMappedFile>> {
+        let file_name = CheetahString::from_string(next_file_path.to_string_lossy().to_string());
         let mut mapped_file = match self.allocate_mapped_file_service {
-            None => DefaultMappedFile::new(
-                CheetahString::from_string(next_file_path.to_string_lossy().to_string()),
-                self.mapped_file_size,
-            ),
-            Some(ref _value) => {
-                unimplemented!()
-            }
+            None => match self.transient_store_pool {
+                Some(ref transient_store_pool) => {
+                    if self.fast_fail_if_no_buffer_in_store_pool
+                        && transient_store_pool.available_buffer_nums() == 0
+                    {
+                        warn!(
+                            "[NOTIFYME]TransientStorePool is not enough, so create mapped file \
+                             error, file: {}",
+                            file_name
+                        );
+                        return None;
+                    }
+                    DefaultMappedFile::new_with_transient_store_pool(
+                        file_name,
+                        self.mapped_file_size,
+                        transient_store_pool.clone(),
+                    )
+                }
+                None => DefaultMappedFile::new(file_name, self.mapped_file_size),
+            },
+            Some(ref allocate_mapped_file_service) => allocate_mapped_file_service
+                .put_request_and_return_mapped_file(
+                    file_name.to_string(),
+                    next_next_file_path.to_string_lossy().to_string(),
+                    self.mapped_file_size,
+                )?,
         };
 
         if self.mapped_files.read().is_empty() {
@@ -280,6 +360,11 @@ impl MappedFileQueue {
                 }
             }
         }
+        if !will_remove_files.is_empty() {
+            self.mapped_files
+                .write()
+                .retain(|mf| !will_remove_files.contains(mf));
+        }
     }
 
     #[inline]
@@ -304,6 +389,149 @@ impl MappedFileQueue {
         }
     }
 
+    /// Deletes the files, except the last one, whose last unit of `unit_size` bytes points before
+    /// `offset` of the commit log, and returns how many were deleted.
+    pub fn delete_expired_file_by_offset(&self, offset: i64, unit_size: i32) -> i32 {
+        let mapped_files = self.mapped_files.read().clone();
+        let mut deleted = Vec::new();
+        for mapped_file in mapped_files
+            .iter()
+            .take(mapped_files.len().saturating_sub(1))
+        {
+            let last_unit_pos = (self.mapped_file_size as i64 - unit_size as i64) as usize;
+            let expired = mapped_file
+                .get_bytes(last_unit_pos, 8)
+                .is_some_and(|bytes| i64::from_be_bytes(bytes[..].try_into().unwrap()) < offset);
+            if !expired {
+                break;
+            }
+            if !mapped_file.destroy(1000 * 60) {
+                warn!(
+                    "this being used mapped file {} can not be deleted",
+                    mapped_file.get_file_name()
+                );
+                break;
+            }
+            info!(
+                "physic min offset {}, destroy the expired mapped file {}",
+                offset,
+                mapped_file.get_file_name()
+            );
+            deleted.push(mapped_file.clone());
+        }
+        if !deleted.is_empty() {
+            self.mapped_files
+                .write()
+                .retain(|mapped_file| !deleted.iter().any(|d| Arc::ptr_eq(d, mapped_file)));
+        }
+        deleted.len() as i32
+    }
+
+    /// Deletes, oldest first, the files except the last one past the millis `expire_timestamp`
+    /// gives for them, or all of them with `clean_immediately`, and returns how many were
+    /// deleted.
+    ///
+    /// Stops at the first file `can_delete` keeps or that is still held after
+    /// `interval_forcibly` millis, and after `delete_file_batch_max` files. Waits
+    /// `delete_files_interval` millis between two deletions so the disk is not flooded.
+    pub fn delete_expired_file_by_time(
+        &self,
+        expire_timestamp: &dyn Fn(&DefaultMappedFile) -> i64,
+        delete_files_interval: u64,
+        interval_forcibly: u64,
+        clean_immediately: bool,
+        delete_file_batch_max: usize,
+        can_delete: &dyn Fn(&str) -> bool,
+    ) -> i32 {
+        let mapped_files = self.mapped_files.read().clone();
+        let candidates = mapped_files.len().saturating_sub(1);
+        let mut deleted = Vec::new();
+        for (index, mapped_file) in mapped_files.iter().take(candidates).enumerate() {
+            if (get_current_millis() as i64) < expire_timestamp(mapped_file) && !clean_immediately {
+                break;
+            }
+            if !can_delete(mapped_file.get_file_name().as_str()) {
+                info!(
+                    "the expired mapped file {} is kept by the retention guard",
+                    mapped_file.get_file_name()
+                );
+                break;
+            }
+            if !mapped_file.destroy(interval_forcibly) {
+                warn!(
+                    "this being used mapped file {} can not be deleted",
+                    mapped_file.get_file_name()
+                );
+                break;
+            }
+            deleted.push(mapped_file.clone());
+            if deleted.len() >= delete_file_batch_max.max(1) {
+                break;
+            }
+            if delete_files_interval > 0 && index + 1 < candidates {
+                thread::sleep(Duration::from_millis(delete_files_interval));
+            }
+        }
+        if !deleted.is_empty() {
+            self.mapped_files
+                .write()
+                .retain(|mapped_file| !deleted.iter().any(|d| Arc::ptr_eq(d, mapped_file)));
+        }
+        deleted.len() as i32
+    }
+
+    /// Destroys `mapped_file` and takes it out of the queue, returns whether it is deleted. A file
+    /// still held after `interval_forcibly` millis stays in the queue, shut down.
+    pub fn delete_mapped_file(
+        &self,
+        mapped_file: &Arc<DefaultMappedFile>,
+        interval_forcibly: u64,
+    ) -> bool {
+        if !mapped_file.destroy(interval_forcibly) {
+            return false;
+        }
+        self.mapped_files
+            .write()
+            .retain(|file| !Arc::ptr_eq(file, mapped_file));
+        true
+    }
+
+    /// Destroys again the first file if an earlier deletion shut it down but could not delete
+    /// it while it was held, returns whether it is deleted now.
+    pub fn retry_delete_first_file(&self, interval_forcibly: u64) -> bool {
+        let Some(mapped_file) = self.get_first_mapped_file() else {
+            return false;
+        };
+        if mapped_file.is_available() {
+            return false;
+        }
+        warn!(
+            "the mapped file {} was destroyed once, but still alive",
+            mapped_file.get_file_name()
+        );
+        let result = mapped_file.destroy(interval_forcibly);
+        if result {
+            info!(
+                "the mapped file {} is deleted on retry",
+                mapped_file.get_file_name()
+            );
+            self.mapped_files
+                .write()
+                .retain(|file| !Arc::ptr_eq(file, &mapped_file));
+        } else {
+            warn!(
+                "the mapped file {} can not be deleted on retry",
+                mapped_file.get_file_name()
+            );
+        }
+        result
+    }
+
+    /// The size of all files of the queue.
+    pub fn get_total_file_size(&self) -> i64 {
+        self.mapped_file_size as i64 * self.get_mapped_files_size() as i64
+    }
+
     #[inline]
     pub(crate) fn delete_expired_file(&mut self, files: Vec<Arc<DefaultMappedFile>>) {
         let mut files = files;
@@ -321,9 +549,11 @@ impl MappedFileQueue {
         }
         self.mapped_files.write().clear();
         self.set_flushed_where(0);
-        let path = PathBuf::from(&self.store_path);
-        if path.is_dir() {
-            let _ = fs::remove_dir_all(path);
+        for dir in self.store_dirs() {
+            let path = PathBuf::from(dir);
+            if path.is_dir() {
+                let _ = fs::remove_dir_all(path);
+            }
         }
     }
 
@@ -503,4 +733,44 @@ mod tests {
         assert!(queue.load());
         assert_eq!(queue.mapped_files.read().len(), 1);
     }
+
+    #[test]
+    fn delete_expired_file_by_time_keeps_the_last_file_and_honors_the_batch_max() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut queue =
+            MappedFileQueue::new(temp_dir.path().to_string_lossy().into_owned(), 1024, None);
+        for offset in [0, 1024, 2048, 3072] {
+            queue.try_create_mapped_file(offset).unwrap();
+        }
+
+        // nothing expired yet
+        let expire_in_a_minute = |mapped_file: &DefaultMappedFile| {
+            mapped_file.get_last_modified_timestamp() as i64 + 60 * 1000
+        };
+        assert_eq!(
+            queue.delete_expired_file_by_time(&expire_in_a_minute, 0, 0, false, 10, &|_| true),
+            0
+        );
+        // a guarded file stops the deletion
+        assert_eq!(
+            queue.delete_expired_file_by_time(&|_| 0, 0, 0, true, 10, &|_| false),
+            0
+        );
+        assert_eq!(
+            queue.delete_expired_file_by_time(&|_| 0, 0, 0, true, 2, &|_| true),
+            2
+        );
+        assert_eq!(
+            queue.delete_expired_file_by_time(&|_| 0, 0, 0, true, 10, &|_| true),
+            1
+        );
+        let offsets: Vec<u64> = queue
+            .get_mapped_files()
+            .read()
+            .iter()
+            .map(|mapped_file| mapped_file.get_file_from_offset())
+            .collect();
+        assert_eq!(offsets, vec![3072]);
+        assert!(!queue.retry_delete_first_file(0));
+    }
 }
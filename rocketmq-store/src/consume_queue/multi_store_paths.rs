@@ -0,0 +1,197 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashSet;
+use std::fs;
+use std::sync::Arc;
+
+use parking_lot::RwLock;
+use rocketmq_common::common::mix_all::MULTI_PATH_SPLITTER;
+
+use crate::config::message_store_config::MessageStoreConfig;
+use crate::utils::store_util::StoreUtil;
+
+/// The directories the commit log spreads its files over, when `storePathCommitLog` lists
+/// several paths split by [`MULTI_PATH_SPLITTER`].
+///
+/// A new file goes to the writable directory with the most free space among those that are not
+/// full, or among all of them once every one is full. The files in
+/// `readOnlyCommitLogStorePaths` are loaded and read, but no file is created there.
+#[derive(Clone, Default)]
+pub struct MultiStorePaths {
+    store_paths: Vec<String>,
+    read_only_paths: Vec<String>,
+    full_store_paths: Arc<RwLock<HashSet<String>>>,
+}
+
+fn split_paths(paths: &str) -> Vec<String> {
+    paths
+        .split(MULTI_PATH_SPLITTER.as_str())
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+fn is_writable(path: &str) -> bool {
+    fs::create_dir_all(path).is_ok()
+        && fs::metadata(path).is_ok_and(|metadata| !metadata.permissions().readonly())
+}
+
+impl MultiStorePaths {
+    pub fn new(store_paths: &str, read_only_paths: Option<&str>) -> Self {
+        let read_only_paths = read_only_paths.map(split_paths).unwrap_or_default();
+        let store_paths = split_paths(store_paths)
+            .into_iter()
+            .filter(|path| !read_only_paths.contains(path))
+            .collect();
+        Self {
+            store_paths,
+            read_only_paths,
+            full_store_paths: Arc::new(RwLock::new(HashSet::new())),
+        }
+    }
+
+    /// `Some` when the commit log of `message_store_config` spans several directories.
+    pub fn from_config(message_store_config: &MessageStoreConfig) -> Option<Self> {
+        if message_store_config.enable_dledger_commit_log {
+            return None;
+        }
+        let paths = Self::new(
+            &message_store_config.get_store_path_commit_log(),
+            message_store_config
+                .read_only_commit_log_store_paths
+                .as_deref(),
+        );
+        (paths.store_paths.len() > 1 || !paths.read_only_paths.is_empty()).then_some(paths)
+    }
+
+    pub fn store_paths(&self) -> &[String] {
+        &self.store_paths
+    }
+
+    pub fn read_only_paths(&self) -> &[String] {
+        &self.read_only_paths
+    }
+
+    /// Every directory holding files, the read-only ones included.
+    pub fn all_paths(&self) -> impl Iterator<Item = &String> {
+        self.store_paths.iter().chain(self.read_only_paths.iter())
+    }
+
+    pub fn get_full_store_paths(&self) -> HashSet<String> {
+        self.full_store_paths.read().clone()
+    }
+
+    pub fn set_full_store_paths(&self, full_store_paths: HashSet<String>) {
+        *self.full_store_paths.write() = full_store_paths;
+    }
+
+    /// Measures the used ratio of the disk of every store path, marks those at or above
+    /// `full_ratio` as full and returns the ratios, `-1` for a path that can not be measured.
+    pub fn refresh_disk_ratios(&self, full_ratio: f64) -> Vec<(String, f64)> {
+        let ratios: Vec<(String, f64)> = self
+            .store_paths
+            .iter()
+            .map(|path| (path.clone(), StoreUtil::get_disk_used_ratio(path)))
+            .collect();
+        self.set_full_store_paths(
+            ratios
+                .iter()
+                .filter(|(_, ratio)| *ratio >= full_ratio)
+                .map(|(path, _)| path.clone())
+                .collect(),
+        );
+        ratios
+    }
+
+    /// The directory to create a file of `file_size` bytes in, `None` when there is no
+    /// writable one with enough space left.
+    pub fn select_store_path(&self, file_size: u64) -> Option<String> {
+        let candidates: Vec<(&String, Option<u64>)> = self
+            .store_paths
+            .iter()
+            .filter(|path| is_writable(path))
+            .map(|path| {
+                (
+                    path,
+                    StoreUtil::get_disk_space(path).map(|(available, _)| available),
+                )
+            })
+            .filter(|(_, available)| available.map_or(true, |available| available >= file_size))
+            .collect();
+        let full_store_paths = self.full_store_paths.read();
+        let most_free = |skip_full: bool| {
+            candidates
+                .iter()
+                .filter(|(path, _)| !skip_full || !full_store_paths.contains(*path))
+                .max_by_key(|(_, available)| available.unwrap_or(0))
+                .map(|(path, _)| path.to_string())
+        };
+        most_free(true).or_else(|| most_free(false))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consume_queue::mapped_file_queue::MappedFileQueue;
+    use crate::log_file::mapped_file::MappedFile;
+
+    #[test]
+    fn creates_files_in_the_directories_not_full() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = |name: &str| temp_dir.path().join(name).to_string_lossy().to_string();
+        let store_paths = MultiStorePaths::new(
+            &format!("{},{},{}", dir("a"), dir("b"), dir("c")),
+            Some(&dir("c")),
+        );
+        assert_eq!(store_paths.store_paths(), &[dir("a"), dir("b")]);
+        assert_eq!(store_paths.read_only_paths(), &[dir("c")]);
+
+        let new_queue = || {
+            let mut queue = MappedFileQueue::new(String::new(), 1024, None);
+            queue.set_multi_store_paths(store_paths.clone());
+            queue
+        };
+        let mut queue = new_queue();
+        store_paths.set_full_store_paths(HashSet::from([dir("a")]));
+        let first = queue.try_create_mapped_file(0).unwrap();
+        let second = queue.try_create_mapped_file(1024).unwrap();
+        store_paths.set_full_store_paths(HashSet::from([dir("b")]));
+        let third = queue.try_create_mapped_file(2048).unwrap();
+        assert!(first.get_file_name().starts_with(dir("b").as_str()));
+        assert!(second.get_file_name().starts_with(dir("b").as_str()));
+        assert!(third.get_file_name().starts_with(dir("a").as_str()));
+
+        // a file left in a read-only directory is still loaded
+        fs::create_dir_all(dir("c")).unwrap();
+        fs::rename(
+            third.get_file_name().as_str(),
+            std::path::Path::new(&dir("c")).join("00000000000000002048"),
+        )
+        .unwrap();
+        let mut reloaded = new_queue();
+        assert!(reloaded.load());
+        let offsets: Vec<u64> = reloaded
+            .get_mapped_files()
+            .read()
+            .iter()
+            .map(|mapped_file| mapped_file.get_file_from_offset())
+            .collect();
+        assert_eq!(offsets, vec![0, 1024, 2048]);
+    }
+}
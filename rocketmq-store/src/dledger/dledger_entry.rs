@@ -0,0 +1,250 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::path::PathBuf;
+
+use tracing::warn;
+
+/// Magic code leading every record of the entry index.
+pub const ENTRY_MAGIC: i32 = 0xCED7_2001_u32 as i32;
+
+/// Size of one record of the entry index, magic, size, term, pos and index.
+pub const ENTRY_INDEX_UNIT_SIZE: usize = 4 + 4 + 8 + 8 + 8;
+
+/// One entry of the replicated log.
+///
+/// The body of an entry is not copied into the index, it is the `size` bytes the leader appended
+/// to its commit log at `pos`. A message stored at commit log offset `o` is therefore covered by
+/// the entry whose range `[pos, pos + size)` contains `o`, and every member holding the entry
+/// holds the message at the same offset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DLedgerEntry {
+    pub index: i64,
+    pub term: i64,
+    pub pos: i64,
+    pub size: i32,
+}
+
+impl DLedgerEntry {
+    /// Commit log offset right after the body of the entry.
+    pub fn end_pos(&self) -> i64 {
+        self.pos + self.size as i64
+    }
+
+    pub fn encode_index(&self) -> [u8; ENTRY_INDEX_UNIT_SIZE] {
+        let mut record = [0u8; ENTRY_INDEX_UNIT_SIZE];
+        record[..4].copy_from_slice(&ENTRY_MAGIC.to_be_bytes());
+        record[4..8].copy_from_slice(&self.size.to_be_bytes());
+        record[8..16].copy_from_slice(&self.term.to_be_bytes());
+        record[16..24].copy_from_slice(&self.pos.to_be_bytes());
+        record[24..32].copy_from_slice(&self.index.to_be_bytes());
+        record
+    }
+
+    /// `None` when the record is not a complete entry, the tail of an index cut off by a crash.
+    pub fn decode_index(record: &[u8]) -> Option<Self> {
+        if record.len() < ENTRY_INDEX_UNIT_SIZE
+            || i32::from_be_bytes(record[..4].try_into().unwrap()) != ENTRY_MAGIC
+        {
+            return None;
+        }
+        Some(Self {
+            size: i32::from_be_bytes(record[4..8].try_into().unwrap()),
+            term: i64::from_be_bytes(record[8..16].try_into().unwrap()),
+            pos: i64::from_be_bytes(record[16..24].try_into().unwrap()),
+            index: i64::from_be_bytes(record[24..32].try_into().unwrap()),
+        })
+    }
+}
+
+/// The entries of a member, kept in memory and in an index file of fixed size records, the
+/// record of entry `i` at `i * ENTRY_INDEX_UNIT_SIZE`.
+pub(crate) struct DLedgerEntryStore {
+    path: PathBuf,
+    file: Option<File>,
+    entries: Vec<DLedgerEntry>,
+}
+
+impl DLedgerEntryStore {
+    pub(crate) fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            file: None,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Loads the index, dropping a tail that is no complete entry.
+    pub(crate) fn load(&mut self) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&self.path)?;
+        let mut content = Vec::new();
+        file.read_to_end(&mut content)?;
+        self.entries.clear();
+        for record in content.chunks(ENTRY_INDEX_UNIT_SIZE) {
+            match DLedgerEntry::decode_index(record) {
+                Some(entry) if entry.index == self.entries.len() as i64 => self.entries.push(entry),
+                _ => {
+                    warn!(
+                        "dledger entry index {} is broken after entry {}, the rest is dropped",
+                        self.path.display(),
+                        self.entries.len()
+                    );
+                    break;
+                }
+            }
+        }
+        file.set_len((self.entries.len() * ENTRY_INDEX_UNIT_SIZE) as u64)?;
+        self.file = Some(file);
+        Ok(())
+    }
+
+    pub(crate) fn last(&self) -> Option<&DLedgerEntry> {
+        self.entries.last()
+    }
+
+    /// Index of the last entry, -1 without entries.
+    pub(crate) fn last_index(&self) -> i64 {
+        self.entries.len() as i64 - 1
+    }
+
+    /// Term of the last entry, 0 without entries.
+    pub(crate) fn last_term(&self) -> i64 {
+        self.last().map_or(0, |entry| entry.term)
+    }
+
+    pub(crate) fn get(&self, index: i64) -> Option<&DLedgerEntry> {
+        usize::try_from(index)
+            .ok()
+            .and_then(|index| self.entries.get(index))
+    }
+
+    /// Up to `max` entries from `from` on.
+    pub(crate) fn range(&self, from: i64, max: usize) -> &[DLedgerEntry] {
+        let from = (from.max(0) as usize).min(self.entries.len());
+        let to = (from + max).min(self.entries.len());
+        &self.entries[from..to]
+    }
+
+    /// Index of the entry covering commit log offset `offset`.
+    pub(crate) fn index_of_offset(&self, offset: i64) -> Option<i64> {
+        let index = self
+            .entries
+            .partition_point(|entry| entry.end_pos() <= offset);
+        self.entries
+            .get(index)
+            .filter(|entry| entry.pos <= offset)
+            .map(|entry| entry.index)
+    }
+
+    pub(crate) fn append(&mut self, entry: DLedgerEntry) -> io::Result<()> {
+        if entry.index != self.entries.len() as i64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "entry {} does not follow the last entry {}",
+                    entry.index,
+                    self.last_index()
+                ),
+            ));
+        }
+        if let Some(file) = self.file.as_mut() {
+            file.write_all(&entry.encode_index())?;
+        }
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Drops the entries from `index` on.
+    pub(crate) fn truncate(&mut self, index: i64) -> io::Result<()> {
+        let index = (index.max(0) as usize).min(self.entries.len());
+        if let Some(file) = self.file.as_mut() {
+            file.set_len((index * ENTRY_INDEX_UNIT_SIZE) as u64)?;
+        }
+        self.entries.truncate(index);
+        Ok(())
+    }
+
+    /// Drops the entries whose body is not complete below commit log offset `max_offset`.
+    pub(crate) fn truncate_by_offset(&mut self, max_offset: i64) -> io::Result<()> {
+        let index = self
+            .entries
+            .partition_point(|entry| entry.end_pos() <= max_offset);
+        self.truncate(index as i64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(index: i64, term: i64, pos: i64, size: i32) -> DLedgerEntry {
+        DLedgerEntry {
+            index,
+            term,
+            pos,
+            size,
+        }
+    }
+
+    #[test]
+    fn entries_map_offsets_and_survive_a_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("index");
+        let mut store = DLedgerEntryStore::new(&path);
+        store.load().unwrap();
+        assert_eq!(store.last_index(), -1);
+        assert_eq!(store.last_term(), 0);
+
+        store.append(entry(0, 1, 0, 100)).unwrap();
+        store.append(entry(1, 1, 100, 0)).unwrap();
+        store.append(entry(2, 2, 100, 50)).unwrap();
+        assert!(store.append(entry(4, 2, 150, 50)).is_err());
+        assert_eq!(store.index_of_offset(99), Some(0));
+        assert_eq!(store.index_of_offset(100), Some(2));
+        assert_eq!(store.index_of_offset(150), None);
+        assert_eq!(store.range(1, 10).len(), 2);
+
+        // a torn record at the end is dropped on load
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(&[1, 2, 3])
+            .unwrap();
+        let mut store = DLedgerEntryStore::new(&path);
+        store.load().unwrap();
+        assert_eq!(store.last(), Some(&entry(2, 2, 100, 50)));
+
+        store.truncate_by_offset(149).unwrap();
+        assert_eq!(store.last_index(), 1);
+        let mut store = DLedgerEntryStore::new(&path);
+        store.load().unwrap();
+        assert_eq!(store.last_index(), 1);
+        assert_eq!(store.get(0), Some(&entry(0, 1, 0, 100)));
+    }
+}
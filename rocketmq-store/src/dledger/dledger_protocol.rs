@@ -0,0 +1,282 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::io;
+
+use bytes::Buf;
+use bytes::BufMut;
+use bytes::Bytes;
+use bytes::BytesMut;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+
+use crate::dledger::dledger_entry::DLedgerEntry;
+
+/// Frames larger than this are taken for a broken stream.
+const MAX_FRAME_SIZE: usize = 256 * 1024 * 1024;
+
+const VOTE_REQUEST: u8 = 1;
+const APPEND_REQUEST: u8 = 2;
+
+/// The requests the members of a group exchange, a candidate asks for votes and the leader
+/// appends entries, without any entry it is the heartbeat of the leader.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum DLedgerRequest {
+    Vote {
+        term: i64,
+        candidate_id: String,
+        last_index: i64,
+        last_term: i64,
+    },
+    Append {
+        term: i64,
+        leader_id: String,
+        prev_index: i64,
+        prev_term: i64,
+        commit_index: i64,
+        /// Entries from `prev_index + 1` on, with their bodies.
+        entries: Vec<(DLedgerEntry, Bytes)>,
+    },
+}
+
+/// `success` is whether the vote was granted or the entries were appended, `last_index` is the
+/// last entry of the responding member, where the leader goes on after a rejected append.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct DLedgerResponse {
+    pub(crate) term: i64,
+    pub(crate) success: bool,
+    pub(crate) last_index: i64,
+}
+
+fn put_string(buf: &mut BytesMut, value: &str) {
+    buf.put_u16(value.len() as u16);
+    buf.put_slice(value.as_bytes());
+}
+
+fn get_string(buf: &mut &[u8]) -> Option<String> {
+    if buf.remaining() < 2 {
+        return None;
+    }
+    let len = buf.get_u16() as usize;
+    if buf.remaining() < len {
+        return None;
+    }
+    let value = String::from_utf8(buf[..len].to_vec()).ok()?;
+    buf.advance(len);
+    Some(value)
+}
+
+fn get_i64s<const N: usize>(buf: &mut &[u8]) -> Option<[i64; N]> {
+    if buf.remaining() < 8 * N {
+        return None;
+    }
+    Some(std::array::from_fn(|_| buf.get_i64()))
+}
+
+impl DLedgerRequest {
+    pub(crate) fn encode(&self) -> Bytes {
+        let mut buf = BytesMut::new();
+        match self {
+            DLedgerRequest::Vote {
+                term,
+                candidate_id,
+                last_index,
+                last_term,
+            } => {
+                buf.put_u8(VOTE_REQUEST);
+                buf.put_i64(*term);
+                buf.put_i64(*last_index);
+                buf.put_i64(*last_term);
+                put_string(&mut buf, candidate_id);
+            }
+            DLedgerRequest::Append {
+                term,
+                leader_id,
+                prev_index,
+                prev_term,
+                commit_index,
+                entries,
+            } => {
+                buf.put_u8(APPEND_REQUEST);
+                buf.put_i64(*term);
+                buf.put_i64(*prev_index);
+                buf.put_i64(*prev_term);
+                buf.put_i64(*commit_index);
+                put_string(&mut buf, leader_id);
+                buf.put_i32(entries.len() as i32);
+                for (entry, body) in entries {
+                    buf.put_i64(entry.term);
+                    buf.put_i64(entry.pos);
+                    buf.put_i32(body.len() as i32);
+                    buf.put_slice(body);
+                }
+            }
+        }
+        buf.freeze()
+    }
+
+    pub(crate) fn decode(mut buf: &[u8]) -> Option<Self> {
+        if buf.remaining() < 1 {
+            return None;
+        }
+        match buf.get_u8() {
+            VOTE_REQUEST => {
+                let [term, last_index, last_term] = get_i64s(&mut buf)?;
+                let candidate_id = get_string(&mut buf)?;
+                Some(DLedgerRequest::Vote {
+                    term,
+                    candidate_id,
+                    last_index,
+                    last_term,
+                })
+            }
+            APPEND_REQUEST => {
+                let [term, prev_index, prev_term, commit_index] = get_i64s(&mut buf)?;
+                let leader_id = get_string(&mut buf)?;
+                if buf.remaining() < 4 {
+                    return None;
+                }
+                let count = buf.get_i32().max(0) as usize;
+                let mut entries = Vec::with_capacity(count.min(1024));
+                for i in 0..count {
+                    let [term, pos] = get_i64s(&mut buf)?;
+                    if buf.remaining() < 4 {
+                        return None;
+                    }
+                    let size = buf.get_i32();
+                    if size < 0 || buf.remaining() < size as usize {
+                        return None;
+                    }
+                    let body = Bytes::copy_from_slice(&buf[..size as usize]);
+                    buf.advance(size as usize);
+                    let entry = DLedgerEntry {
+                        index: prev_index + 1 + i as i64,
+                        term,
+                        pos,
+                        size,
+                    };
+                    entries.push((entry, body));
+                }
+                Some(DLedgerRequest::Append {
+                    term,
+                    leader_id,
+                    prev_index,
+                    prev_term,
+                    commit_index,
+                    entries,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+impl DLedgerResponse {
+    pub(crate) const SIZE: usize = 8 + 1 + 8;
+
+    pub(crate) fn encode(&self) -> Bytes {
+        let mut buf = BytesMut::with_capacity(Self::SIZE);
+        buf.put_i64(self.term);
+        buf.put_u8(self.success as u8);
+        buf.put_i64(self.last_index);
+        buf.freeze()
+    }
+
+    pub(crate) fn decode(mut buf: &[u8]) -> Option<Self> {
+        if buf.remaining() < Self::SIZE {
+            return None;
+        }
+        Some(Self {
+            term: buf.get_i64(),
+            success: buf.get_u8() != 0,
+            last_index: buf.get_i64(),
+        })
+    }
+}
+
+pub(crate) async fn write_frame(stream: &mut TcpStream, frame: &[u8]) -> io::Result<()> {
+    stream.write_u32(frame.len() as u32).await?;
+    stream.write_all(frame).await?;
+    stream.flush().await
+}
+
+pub(crate) async fn read_frame(stream: &mut TcpStream) -> io::Result<Vec<u8>> {
+    let len = stream.read_u32().await? as usize;
+    if len > MAX_FRAME_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("frame of {} bytes exceeds the limit", len),
+        ));
+    }
+    let mut frame = vec![0u8; len];
+    stream.read_exact(&mut frame).await?;
+    Ok(frame)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_round_trip() {
+        let vote = DLedgerRequest::Vote {
+            term: 3,
+            candidate_id: String::from("n1"),
+            last_index: 10,
+            last_term: 2,
+        };
+        assert_eq!(DLedgerRequest::decode(&vote.encode()), Some(vote));
+
+        let append = DLedgerRequest::Append {
+            term: 3,
+            leader_id: String::from("n0"),
+            prev_index: 4,
+            prev_term: 2,
+            commit_index: 4,
+            entries: vec![
+                (
+                    DLedgerEntry {
+                        index: 5,
+                        term: 3,
+                        pos: 1000,
+                        size: 3,
+                    },
+                    Bytes::from_static(b"abc"),
+                ),
+                (
+                    DLedgerEntry {
+                        index: 6,
+                        term: 3,
+                        pos: 1003,
+                        size: 0,
+                    },
+                    Bytes::new(),
+                ),
+            ],
+        };
+        let encoded = append.encode();
+        assert_eq!(DLedgerRequest::decode(&encoded), Some(append));
+        assert_eq!(DLedgerRequest::decode(&encoded[..encoded.len() - 1]), None);
+
+        let response = DLedgerResponse {
+            term: 3,
+            success: true,
+            last_index: 6,
+        };
+        assert_eq!(DLedgerResponse::decode(&response.encode()), Some(response));
+    }
+}
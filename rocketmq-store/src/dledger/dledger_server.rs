@@ -0,0 +1,899 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicI64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use bytes::Bytes;
+use rocketmq_common::FileUtils::file_to_string;
+use rocketmq_common::FileUtils::string_to_file;
+use tokio::net::TcpListener;
+use tokio::net::TcpStream;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tokio::task::JoinSet;
+use tokio::time::Instant;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
+
+use crate::base::message_status_enum::PutMessageStatus;
+use crate::config::message_store_config::MessageStoreConfig;
+use crate::dledger::dledger_entry::DLedgerEntry;
+use crate::dledger::dledger_entry::DLedgerEntryStore;
+use crate::dledger::dledger_protocol::read_frame;
+use crate::dledger::dledger_protocol::write_frame;
+use crate::dledger::dledger_protocol::DLedgerRequest;
+use crate::dledger::dledger_protocol::DLedgerResponse;
+use crate::dledger::member_state::DLedgerPeers;
+use crate::dledger::member_state::MemberRole;
+use crate::dledger::member_state::MemberState;
+use crate::log_file::commit_log::CommitLog;
+
+/// How often the leader sends entries or a heartbeat to every follower.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_millis(2000);
+
+/// Heartbeats a follower misses before it starts an election.
+const MAX_HEARTBEAT_LEAK: u32 = 3;
+
+/// Bounds of the random part of the election timeout, so that the members do not all start
+/// their elections at once.
+const MIN_VOTE_INTERVAL_MILLIS: u64 = 300;
+const MAX_VOTE_INTERVAL_MILLIS: u64 = 1000;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_millis(3000);
+
+const STATE_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Most entries the leader sends in one append.
+const MAX_PUSH_ENTRIES: usize = 1024;
+
+const COMMITTED_INDEX_KEY: &str = "committedIndex";
+
+/// Told about every role change of the member, the broker registers as master while its
+/// member leads and as slave otherwise.
+pub trait DLedgerRoleChangeHandler: Send + Sync {
+    fn handle(&self, term: i64, role: MemberRole);
+}
+
+/// Directory of the member in `storePathDledgerCommitLog`, the commit log goes to its `data`
+/// directory.
+pub fn get_store_path_dledger_member(message_store_config: &MessageStoreConfig) -> PathBuf {
+    PathBuf::from(message_store_config.get_store_path_dledger_commit_log()).join(format!(
+        "dledger-{}",
+        message_store_config
+            .dledger_self_id
+            .as_deref()
+            .unwrap_or_default()
+    ))
+}
+
+pub fn get_store_path_dledger_data(message_store_config: &MessageStoreConfig) -> String {
+    get_store_path_dledger_member(message_store_config)
+        .join("data")
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn election_timeout() -> Duration {
+    let jitter = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.subsec_nanos() as u64)
+        .unwrap_or_default()
+        % (MAX_VOTE_INTERVAL_MILLIS - MIN_VOTE_INTERVAL_MILLIS);
+    HEARTBEAT_INTERVAL * MAX_HEARTBEAT_LEAK
+        + Duration::from_millis(MIN_VOTE_INTERVAL_MILLIS + jitter)
+}
+
+/// A member of a DLedger group, replicating the commit log by raft.
+///
+/// The members elect a leader, only the leader takes puts. The range every put appends to the
+/// commit log of the leader becomes an entry, see [`DLedgerEntry`], that the leader sends with
+/// its body to the followers. An entry is committed once a majority of the group holds it, the
+/// put waits for that and the commit log is dispatched up to the committed position only, so a
+/// message a producer saw stored survives the loss of any minority of the group.
+pub struct DLedgerServer {
+    peers: DLedgerPeers,
+    message_store_config: Arc<MessageStoreConfig>,
+    commit_log: tokio::sync::Mutex<CommitLog>,
+    state: parking_lot::Mutex<MemberState>,
+    entry_store: parking_lot::Mutex<DLedgerEntryStore>,
+    committed_index: AtomicI64,
+    committed_pos: AtomicI64,
+    /// `checkpoint` of the member, the committed index as of the last state check. A restarted
+    /// member takes the entries past it as uncommitted, the leader commits them again.
+    checkpoint_path: String,
+    persisted_committed_index: AtomicI64,
+    /// Last entry every follower is known to hold, while this member leads.
+    match_index: parking_lot::Mutex<HashMap<String, i64>>,
+    last_leader_contact: parking_lot::Mutex<Instant>,
+    commit_notify: Notify,
+    append_notify: Notify,
+    role_change_handler: parking_lot::RwLock<Option<Arc<dyn DLedgerRoleChangeHandler>>>,
+    tasks: parking_lot::Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl DLedgerServer {
+    pub fn new(
+        commit_log: CommitLog,
+        message_store_config: Arc<MessageStoreConfig>,
+        peers: DLedgerPeers,
+    ) -> Self {
+        let member_dir = get_store_path_dledger_member(&message_store_config);
+        Self {
+            peers,
+            state: parking_lot::Mutex::new(MemberState::new(
+                member_dir.join("currterm").to_string_lossy(),
+            )),
+            entry_store: parking_lot::Mutex::new(DLedgerEntryStore::new(member_dir.join("index"))),
+            message_store_config,
+            commit_log: tokio::sync::Mutex::new(commit_log),
+            committed_index: AtomicI64::new(-1),
+            committed_pos: AtomicI64::new(0),
+            checkpoint_path: member_dir.join("checkpoint").to_string_lossy().into_owned(),
+            persisted_committed_index: AtomicI64::new(-1),
+            match_index: parking_lot::Mutex::new(HashMap::new()),
+            last_leader_contact: parking_lot::Mutex::new(Instant::now()),
+            commit_notify: Notify::new(),
+            append_notify: Notify::new(),
+            role_change_handler: parking_lot::RwLock::new(None),
+            tasks: parking_lot::Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn set_role_change_handler(&self, handler: Arc<dyn DLedgerRoleChangeHandler>) {
+        *self.role_change_handler.write() = Some(handler);
+    }
+
+    pub fn peers(&self) -> &DLedgerPeers {
+        &self.peers
+    }
+
+    pub fn role(&self) -> MemberRole {
+        self.state.lock().role()
+    }
+
+    pub fn is_leader(&self) -> bool {
+        self.state.lock().is_leader()
+    }
+
+    pub fn term(&self) -> i64 {
+        self.state.lock().term()
+    }
+
+    pub fn leader_id(&self) -> Option<String> {
+        self.state.lock().leader_id().map(str::to_string)
+    }
+
+    pub fn get_last_index(&self) -> i64 {
+        self.entry_store.lock().last_index()
+    }
+
+    pub fn get_committed_index(&self) -> i64 {
+        self.committed_index.load(Ordering::Acquire)
+    }
+
+    /// Commit log offset up to which the entries are committed.
+    pub fn get_committed_pos(&self) -> i64 {
+        self.committed_pos.load(Ordering::Acquire)
+    }
+
+    /// Loads the entries once the commit log is recovered, and lines both up.
+    ///
+    /// The entries up to the committed index of the checkpoint are committed, the commit log is
+    /// dispatched up to the end of the last of them.
+    pub async fn load(&self) -> io::Result<()> {
+        let term = self.term();
+        let mut commit_log = self.commit_log.lock().await;
+        let mut entry_store = self.entry_store.lock();
+        entry_store.load()?;
+        let max_offset = commit_log.get_max_offset();
+        entry_store.truncate_by_offset(max_offset)?;
+        if let Some(end_pos) = entry_store
+            .last()
+            .map(DLedgerEntry::end_pos)
+            .filter(|end_pos| *end_pos < max_offset)
+        {
+            warn!(
+                "commit log ends at {} past the last dledger entry, truncate it to {}",
+                max_offset, end_pos
+            );
+            commit_log.truncate_dirty_files(end_pos);
+        }
+        let committed_index = self.load_committed_index().min(entry_store.last_index());
+        let committed_pos = match entry_store.get(committed_index) {
+            Some(entry) => entry.end_pos(),
+            // nothing committed yet, the commit log holds what was written before the entries
+            None => entry_store
+                .get(0)
+                .map_or(commit_log.get_max_offset(), |entry| entry.pos),
+        };
+        self.committed_index
+            .store(committed_index, Ordering::Release);
+        self.persisted_committed_index
+            .store(committed_index, Ordering::Release);
+        self.committed_pos.store(committed_pos, Ordering::Release);
+        info!(
+            "dledger member {} of group {} loaded {} entries, {} committed, in term {}",
+            self.peers.self_id(),
+            self.peers.group(),
+            entry_store.last_index() + 1,
+            committed_index + 1,
+            term
+        );
+        Ok(())
+    }
+
+    fn load_committed_index(&self) -> i64 {
+        let content = file_to_string(&self.checkpoint_path)
+            .ok()
+            .filter(|content| !content.is_empty())
+            .or_else(|| file_to_string(&format!("{}.bak", self.checkpoint_path)).ok());
+        content
+            .iter()
+            .flat_map(|content| content.lines())
+            .find_map(|line| match line.split_once('=') {
+                Some((COMMITTED_INDEX_KEY, index)) => index.trim().parse().ok(),
+                _ => None,
+            })
+            .unwrap_or(-1)
+    }
+
+    /// Writes the committed index to the checkpoint when it moved since the last time.
+    fn persist_committed_index(&self) {
+        let committed_index = self.get_committed_index();
+        if self.persisted_committed_index.load(Ordering::Acquire) == committed_index {
+            return;
+        }
+        let content = format!("{}={}\n", COMMITTED_INDEX_KEY, committed_index);
+        match string_to_file(&content, &self.checkpoint_path) {
+            Ok(()) => self
+                .persisted_committed_index
+                .store(committed_index, Ordering::Release),
+            Err(e) => error!(
+                "persist dledger checkpoint to {} failed: {}",
+                self.checkpoint_path, e
+            ),
+        }
+    }
+
+    pub fn start(self: &Arc<Self>) -> io::Result<()> {
+        let listener = std::net::TcpListener::bind(("0.0.0.0", self.peers.self_port()))?;
+        listener.set_nonblocking(true)?;
+        let listener = TcpListener::from_std(listener)?;
+        info!(
+            "dledger member {} of group {} listen on port {}",
+            self.peers.self_id(),
+            self.peers.group(),
+            self.peers.self_port()
+        );
+        *self.last_leader_contact.lock() = Instant::now();
+        let mut tasks = self.tasks.lock();
+        tasks.push(tokio::spawn(self.clone().accept_members(listener)));
+        tasks.push(tokio::spawn(self.clone().maintain_state()));
+        for (peer_id, addr) in self.peers.remote_peers() {
+            tasks.push(tokio::spawn(
+                self.clone()
+                    .replicate(peer_id.to_string(), addr.to_string()),
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn shutdown(&self) {
+        for task in self.tasks.lock().drain(..) {
+            task.abort();
+        }
+        self.persist_committed_index();
+    }
+
+    /// Makes the commit log range `[from, to)` a put appended entries of the current term, one
+    /// per commit log file the range touches. Called in the put lock, so that the entries
+    /// follow the commit log order.
+    ///
+    /// Fails when the member is no leader any more.
+    pub(crate) fn append_as_leader(&self, from: i64, to: i64) -> bool {
+        {
+            let state = self.state.lock();
+            if !state.is_leader() {
+                return false;
+            }
+            let file_size = self.message_store_config.mapped_file_size_commit_log as i64;
+            let mut entry_store = self.entry_store.lock();
+            let mut pos = from;
+            loop {
+                // the body of an entry never spans two commit log files
+                let end_pos = to.min(pos - pos % file_size + file_size);
+                let entry = DLedgerEntry {
+                    index: entry_store.last_index() + 1,
+                    term: state.term(),
+                    pos,
+                    size: (end_pos - pos) as i32,
+                };
+                if let Err(e) = entry_store.append(entry) {
+                    error!("append dledger entry at {} failed: {}", pos, e);
+                    return false;
+                }
+                pos = end_pos;
+                if pos >= to {
+                    break;
+                }
+            }
+        }
+        self.append_notify.notify_waiters();
+        // a group of one commits right away
+        self.update_commit_index();
+        true
+    }
+
+    /// Waits until the commit log is committed up to `next_offset`.
+    pub(crate) async fn wait_for_commit(
+        &self,
+        next_offset: i64,
+        timeout: Duration,
+    ) -> PutMessageStatus {
+        let deadline = Instant::now() + timeout;
+        loop {
+            // created before the check, so that a commit in between is not missed
+            let committed = self.commit_notify.notified();
+            if self.get_committed_pos() >= next_offset {
+                return PutMessageStatus::PutOk;
+            }
+            if tokio::time::timeout_at(deadline, committed).await.is_err() {
+                return PutMessageStatus::FlushSlaveTimeout;
+            }
+        }
+    }
+
+    fn on_role_change(&self, term: i64, role: MemberRole) {
+        info!(
+            "dledger member {} of group {} changes to {} in term {}",
+            self.peers.self_id(),
+            self.peers.group(),
+            role,
+            term
+        );
+        let handler = self.role_change_handler.read().clone();
+        if let Some(handler) = handler {
+            handler.handle(term, role);
+        }
+    }
+
+    /// Steps down when a member answered from a later term.
+    fn observe_term(&self, term: i64) {
+        let stepped_down = {
+            let mut state = self.state.lock();
+            let role = state.role();
+            state.observe_term(term) && role != MemberRole::Follower
+        };
+        if stepped_down {
+            self.on_role_change(term, MemberRole::Follower);
+        }
+    }
+
+    fn advance_commit(&self, index: i64) {
+        let Some(end_pos) = self
+            .entry_store
+            .lock()
+            .get(index)
+            .map(DLedgerEntry::end_pos)
+        else {
+            return;
+        };
+        if self.committed_index.fetch_max(index, Ordering::AcqRel) < index {
+            self.committed_pos.fetch_max(end_pos, Ordering::AcqRel);
+            self.commit_notify.notify_waiters();
+            // the followers learn the new commit index right away
+            self.append_notify.notify_waiters();
+        }
+    }
+
+    /// Commits the last entry a majority holds, once it is one of the current term.
+    fn update_commit_index(&self) {
+        let state = self.state.lock();
+        if !state.is_leader() {
+            return;
+        }
+        let mut matched: Vec<i64> = {
+            let match_index = self.match_index.lock();
+            self.peers
+                .remote_peers()
+                .map(|(peer_id, _)| match_index.get(peer_id).copied().unwrap_or(-1))
+                .collect()
+        };
+        matched.push(self.entry_store.lock().last_index());
+        matched.sort_unstable();
+        let quorum_index = matched[matched.len() - 1 - self.peers.len() / 2];
+        let of_current_term = self
+            .entry_store
+            .lock()
+            .get(quorum_index)
+            .is_some_and(|entry| entry.term == state.term());
+        drop(state);
+        if of_current_term {
+            self.advance_commit(quorum_index);
+        }
+    }
+
+    async fn maintain_state(self: Arc<Self>) {
+        let mut timeout = election_timeout();
+        loop {
+            tokio::time::sleep(STATE_CHECK_INTERVAL).await;
+            self.persist_committed_index();
+            if self.is_leader() || self.last_leader_contact.lock().elapsed() < timeout {
+                continue;
+            }
+            self.elect().await;
+            *self.last_leader_contact.lock() = Instant::now();
+            timeout = election_timeout();
+        }
+    }
+
+    async fn elect(&self) {
+        let (term, last_index, last_term, was_candidate) = {
+            let mut state = self.state.lock();
+            let was_candidate = state.role() == MemberRole::Candidate;
+            let Some(term) = state.next_term(self.peers.self_id()) else {
+                warn!(
+                    "dledger member {} cannot persist its vote, no election",
+                    self.peers.self_id()
+                );
+                return;
+            };
+            let entry_store = self.entry_store.lock();
+            (
+                term,
+                entry_store.last_index(),
+                entry_store.last_term(),
+                was_candidate,
+            )
+        };
+        if !was_candidate {
+            self.on_role_change(term, MemberRole::Candidate);
+        }
+        let request = DLedgerRequest::Vote {
+            term,
+            candidate_id: self.peers.self_id().to_string(),
+            last_index,
+            last_term,
+        }
+        .encode();
+        let mut votes = JoinSet::new();
+        for (_, addr) in self.peers.remote_peers() {
+            let addr = addr.to_string();
+            let request = request.clone();
+            votes.spawn(async move { call(&mut None, &addr, &request).await });
+        }
+        let mut granted = 1;
+        while !self.peers.is_quorum(granted) {
+            let Some(result) = votes.join_next().await else {
+                break;
+            };
+            let Ok(Ok(response)) = result else {
+                continue;
+            };
+            if response.term > term {
+                self.observe_term(response.term);
+                return;
+            }
+            if response.success {
+                granted += 1;
+            }
+        }
+        if !self.peers.is_quorum(granted) {
+            info!(
+                "dledger member {} got {} of {} votes in term {}",
+                self.peers.self_id(),
+                granted,
+                self.peers.len(),
+                term
+            );
+            return;
+        }
+        {
+            let mut state = self.state.lock();
+            if state.term() != term || state.role() != MemberRole::Candidate {
+                return;
+            }
+            state.change_to_leader(self.peers.self_id());
+        }
+        self.match_index.lock().clear();
+        self.append_no_op().await;
+        self.on_role_change(term, MemberRole::Leader);
+    }
+
+    /// Appends the empty entry a new leader starts its term with, committing it commits the
+    /// entries of the earlier terms as well.
+    async fn append_no_op(&self) {
+        let offset = {
+            let mut commit_log = self.commit_log.lock().await;
+            // the bytes of a put that lost leadership before becoming an entry are cut off, so
+            // the commit log of every member ends with its last entry
+            let last_end_pos = self.entry_store.lock().last().map(DLedgerEntry::end_pos);
+            if let Some(last_end_pos) =
+                last_end_pos.filter(|end_pos| *end_pos < commit_log.get_max_offset())
+            {
+                commit_log.truncate_dirty_files(last_end_pos);
+            }
+            commit_log.get_max_offset()
+        };
+        self.append_as_leader(offset, offset);
+    }
+
+    async fn accept_members(self: Arc<Self>, listener: TcpListener) {
+        // the connections are aborted together with the accept task
+        let mut connections = JoinSet::new();
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => {
+                        let _ = stream.set_nodelay(true);
+                        connections.spawn(self.clone().serve(stream));
+                    }
+                    Err(e) => error!("dledger accept member failed: {}", e),
+                },
+                Some(_) = connections.join_next(), if !connections.is_empty() => {}
+            }
+        }
+    }
+
+    async fn serve(self: Arc<Self>, mut stream: TcpStream) {
+        loop {
+            let frame = match read_frame(&mut stream).await {
+                Ok(frame) => frame,
+                Err(_) => return,
+            };
+            let response = match DLedgerRequest::decode(&frame) {
+                Some(DLedgerRequest::Vote {
+                    term,
+                    candidate_id,
+                    last_index,
+                    last_term,
+                }) => self.handle_vote(term, &candidate_id, last_index, last_term),
+                Some(DLedgerRequest::Append {
+                    term,
+                    leader_id,
+                    prev_index,
+                    prev_term,
+                    commit_index,
+                    entries,
+                }) => {
+                    self.handle_append(
+                        term,
+                        &leader_id,
+                        prev_index,
+                        prev_term,
+                        commit_index,
+                        entries,
+                    )
+                    .await
+                }
+                None => {
+                    warn!("dledger member got a malformed request, close the connection");
+                    return;
+                }
+            };
+            if write_frame(&mut stream, &response.encode()).await.is_err() {
+                return;
+            }
+        }
+    }
+
+    fn handle_vote(
+        &self,
+        term: i64,
+        candidate_id: &str,
+        last_index: i64,
+        last_term: i64,
+    ) -> DLedgerResponse {
+        let (response, stepped_down) = {
+            let mut state = self.state.lock();
+            let role = state.role();
+            state.observe_term(term);
+            let entry_store = self.entry_store.lock();
+            // a vote only goes to a candidate whose log holds at least the entries of this one
+            let up_to_date =
+                (last_term, last_index) >= (entry_store.last_term(), entry_store.last_index());
+            let granted = term == state.term() && up_to_date && state.vote(candidate_id);
+            if granted {
+                *self.last_leader_contact.lock() = Instant::now();
+            }
+            let response = DLedgerResponse {
+                term: state.term(),
+                success: granted,
+                last_index: entry_store.last_index(),
+            };
+            (
+                response,
+                role != MemberRole::Follower && state.role() == MemberRole::Follower,
+            )
+        };
+        if stepped_down {
+            self.on_role_change(response.term, MemberRole::Follower);
+        }
+        response
+    }
+
+    async fn handle_append(
+        &self,
+        term: i64,
+        leader_id: &str,
+        prev_index: i64,
+        prev_term: i64,
+        commit_index: i64,
+        entries: Vec<(DLedgerEntry, Bytes)>,
+    ) -> DLedgerResponse {
+        let role = {
+            let mut state = self.state.lock();
+            if term < state.term() {
+                return DLedgerResponse {
+                    term: state.term(),
+                    success: false,
+                    last_index: self.entry_store.lock().last_index(),
+                };
+            }
+            let role = state.role();
+            state.observe_term(term);
+            if state.change_to_follower(leader_id) {
+                info!(
+                    "dledger member {} follows leader {} in term {}",
+                    self.peers.self_id(),
+                    leader_id,
+                    term
+                );
+            }
+            *self.last_leader_contact.lock() = Instant::now();
+            role
+        };
+        if role != MemberRole::Follower {
+            self.on_role_change(term, MemberRole::Follower);
+        }
+
+        let entry_count = entries.len() as i64;
+        let mut commit_log = self.commit_log.lock().await;
+        let success = self
+            .append_entries(&mut commit_log, prev_index, prev_term, entries)
+            .await;
+        drop(commit_log);
+        if success {
+            self.advance_commit(commit_index.min(prev_index + entry_count));
+        }
+        let last_index = self.entry_store.lock().last_index();
+        DLedgerResponse {
+            term,
+            success,
+            // a rejected leader goes on from before the entry that did not match
+            last_index: if success {
+                last_index
+            } else {
+                last_index.min(prev_index - 1)
+            },
+        }
+    }
+
+    /// Appends the entries of the leader from `prev_index + 1` on, replacing the entries that
+    /// conflict with them together with their commit log range.
+    async fn append_entries(
+        &self,
+        commit_log: &mut CommitLog,
+        prev_index: i64,
+        prev_term: i64,
+        entries: Vec<(DLedgerEntry, Bytes)>,
+    ) -> bool {
+        if prev_index >= 0
+            && self
+                .entry_store
+                .lock()
+                .get(prev_index)
+                .map(|entry| entry.term)
+                != Some(prev_term)
+        {
+            return false;
+        }
+        for (entry, body) in entries {
+            let existing = self.entry_store.lock().get(entry.index).copied();
+            match existing {
+                Some(existing) if existing.term == entry.term => continue,
+                Some(_) if entry.index <= self.get_committed_index() => {
+                    error!(
+                        "dledger entry {} of the leader conflicts with a committed entry",
+                        entry.index
+                    );
+                    return false;
+                }
+                Some(_) => {
+                    warn!(
+                        "dledger entries from {} conflict with the leader, truncate them",
+                        entry.index
+                    );
+                    if let Err(e) = self.entry_store.lock().truncate(entry.index) {
+                        error!(
+                            "truncate dledger entries from {} failed: {}",
+                            entry.index, e
+                        );
+                        return false;
+                    }
+                }
+                None => {}
+            }
+            let max_offset = commit_log.get_max_offset();
+            if max_offset > entry.pos && entry.pos >= self.get_committed_pos() {
+                commit_log.truncate_dirty_files(entry.pos);
+            }
+            if commit_log.get_max_offset() != entry.pos {
+                error!(
+                    "commit log ends at {}, the dledger entry {} of the leader starts at {}",
+                    commit_log.get_max_offset(),
+                    entry.index,
+                    entry.pos
+                );
+                return false;
+            }
+            if !body.is_empty() && !commit_log.append_data(entry.pos, &body).await {
+                return false;
+            }
+            if let Err(e) = self.entry_store.lock().append(entry) {
+                error!("append dledger entry {} failed: {}", entry.index, e);
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Sends the entries from the next one the follower lacks on, or a heartbeat, while this
+    /// member leads.
+    async fn replicate(self: Arc<Self>, peer_id: String, addr: String) {
+        let mut stream = None;
+        let mut replicating_term = -1;
+        let mut next_index = 0;
+        loop {
+            // created before the checks, so that an append in between is not missed
+            let appended = self.append_notify.notified();
+            let (is_leader, term) = {
+                let state = self.state.lock();
+                (state.is_leader(), state.term())
+            };
+            if !is_leader {
+                stream = None;
+                let _ = tokio::time::timeout(STATE_CHECK_INTERVAL, appended).await;
+                continue;
+            }
+            if term != replicating_term {
+                replicating_term = term;
+                next_index = self.get_last_index() + 1;
+                self.match_index.lock().remove(&peer_id);
+            }
+            let (request, prev_index, entry_count) = self.build_append(term, next_index).await;
+            let response = match call(&mut stream, &addr, &request).await {
+                Ok(response) => response,
+                Err(e) => {
+                    warn!(
+                        "dledger leader {} failed to reach member {}: {}",
+                        self.peers.self_id(),
+                        peer_id,
+                        e
+                    );
+                    stream = None;
+                    tokio::time::sleep(HEARTBEAT_INTERVAL).await;
+                    continue;
+                }
+            };
+            if response.term > term {
+                self.observe_term(response.term);
+                continue;
+            }
+            if !response.success {
+                let retry_from = (response.last_index + 1).min(next_index - 1).max(0);
+                if retry_from == next_index {
+                    // the follower cannot take even the first entry, retry with the heartbeat
+                    tokio::time::sleep(HEARTBEAT_INTERVAL).await;
+                }
+                next_index = retry_from;
+                continue;
+            }
+            let matched = prev_index + entry_count;
+            self.match_index.lock().insert(peer_id.clone(), matched);
+            next_index = matched + 1;
+            self.update_commit_index();
+            if next_index > self.get_last_index() {
+                let _ = tokio::time::timeout(HEARTBEAT_INTERVAL, appended).await;
+            }
+        }
+    }
+
+    /// The append from `next_index` on, bounded by `ha_transfer_batch_size` bytes, with the
+    /// index of the entry before it and the number of entries it carries.
+    async fn build_append(&self, term: i64, next_index: i64) -> (Bytes, i64, i64) {
+        let batch_size = self.message_store_config.ha_transfer_batch_size.max(1);
+        let prev_index = next_index - 1;
+        let (prev_term, entries) = {
+            let entry_store = self.entry_store.lock();
+            let prev_term = entry_store.get(prev_index).map_or(0, |entry| entry.term);
+            let mut size = 0;
+            let entries: Vec<DLedgerEntry> = entry_store
+                .range(next_index, MAX_PUSH_ENTRIES)
+                .iter()
+                .take_while(|entry| {
+                    let take = size == 0 || size + entry.size as usize <= batch_size;
+                    size += entry.size as usize;
+                    take
+                })
+                .copied()
+                .collect();
+            (prev_term, entries)
+        };
+        let mut with_bodies = Vec::with_capacity(entries.len());
+        {
+            let commit_log = self.commit_log.lock().await;
+            for entry in entries {
+                match read_body(&commit_log, &entry) {
+                    Some(body) => with_bodies.push((entry, body)),
+                    None => break,
+                }
+            }
+        }
+        let entry_count = with_bodies.len() as i64;
+        let request = DLedgerRequest::Append {
+            term,
+            leader_id: self.peers.self_id().to_string(),
+            prev_index,
+            prev_term,
+            commit_index: self.get_committed_index(),
+            entries: with_bodies,
+        };
+        (request.encode(), prev_index, entry_count)
+    }
+}
+
+fn read_body(commit_log: &CommitLog, entry: &DLedgerEntry) -> Option<Bytes> {
+    if entry.size == 0 {
+        return Some(Bytes::new());
+    }
+    commit_log
+        .get_data(entry.pos)?
+        .get_data(0, entry.size as usize)
+        .filter(|body| body.len() == entry.size as usize)
+}
+
+/// Sends `request` to the member at `addr` on `stream`, connecting it first if needed.
+async fn call(
+    stream: &mut Option<TcpStream>,
+    addr: &str,
+    request: &[u8],
+) -> io::Result<DLedgerResponse> {
+    let exchange = async {
+        if stream.is_none() {
+            let connected = TcpStream::connect(addr).await?;
+            connected.set_nodelay(true)?;
+            *stream = Some(connected);
+        }
+        let connected = stream.as_mut().unwrap();
+        write_frame(connected, request).await?;
+        let frame = read_frame(connected).await?;
+        DLedgerResponse::decode(&frame)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed response"))
+    };
+    tokio::time::timeout(REQUEST_TIMEOUT, exchange)
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "no response in time"))?
+}
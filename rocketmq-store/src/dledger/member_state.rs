@@ -0,0 +1,331 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fmt::Formatter;
+
+use rocketmq_common::FileUtils::file_to_string;
+use rocketmq_common::FileUtils::string_to_file;
+use tracing::error;
+
+/// The role of a member of a DLedger group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemberRole {
+    Leader,
+    Candidate,
+    Follower,
+}
+
+impl Display for MemberRole {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MemberRole::Leader => write!(f, "LEADER"),
+            MemberRole::Candidate => write!(f, "CANDIDATE"),
+            MemberRole::Follower => write!(f, "FOLLOWER"),
+        }
+    }
+}
+
+/// The members of a DLedger group, parsed from `dledgerPeers` in the form
+/// `n0-127.0.0.1:40911;n1-127.0.0.1:40912;n2-127.0.0.1:40913`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DLedgerPeers {
+    group: String,
+    self_id: String,
+    peers: BTreeMap<String, String>,
+}
+
+impl DLedgerPeers {
+    pub fn parse(group: &str, peers: &str, self_id: &str) -> Result<Self, String> {
+        let mut parsed = BTreeMap::new();
+        for peer in peers
+            .split(';')
+            .map(str::trim)
+            .filter(|peer| !peer.is_empty())
+        {
+            let Some((id, addr)) = peer.split_once('-') else {
+                return Err(format!("peer {} is not in the form id-host:port", peer));
+            };
+            if addr
+                .rsplit_once(':')
+                .map_or(true, |(_, port)| port.parse::<u16>().is_err())
+            {
+                return Err(format!("peer {} has no valid port", peer));
+            }
+            if parsed.insert(id.to_string(), addr.to_string()).is_some() {
+                return Err(format!("peer id {} is configured twice", id));
+            }
+        }
+        if !parsed.contains_key(self_id) {
+            return Err(format!(
+                "self id {} is not one of the peers {}",
+                self_id, peers
+            ));
+        }
+        Ok(Self {
+            group: group.to_string(),
+            self_id: self_id.to_string(),
+            peers: parsed,
+        })
+    }
+
+    pub fn group(&self) -> &str {
+        &self.group
+    }
+
+    pub fn self_id(&self) -> &str {
+        &self.self_id
+    }
+
+    pub fn self_addr(&self) -> &str {
+        &self.peers[&self.self_id]
+    }
+
+    /// Port the member listens on for the other members.
+    pub fn self_port(&self) -> u16 {
+        self.self_addr()
+            .rsplit_once(':')
+            .and_then(|(_, port)| port.parse().ok())
+            .unwrap_or_default()
+    }
+
+    /// The other members with their addresses.
+    pub fn remote_peers(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.peers
+            .iter()
+            .filter(|(id, _)| **id != self.self_id)
+            .map(|(id, addr)| (id.as_str(), addr.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.peers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.peers.is_empty()
+    }
+
+    /// Whether `count` members, this one included, are a majority of the group.
+    pub fn is_quorum(&self, count: usize) -> bool {
+        count > self.peers.len() / 2
+    }
+
+    /// Broker id the member registers with while it is a follower, `n1` registers as 2, as the
+    /// leader registers as the master.
+    pub fn slave_broker_id(&self) -> u64 {
+        self.self_id
+            .trim_start_matches(|c: char| !c.is_ascii_digit())
+            .parse::<u64>()
+            .map(|id| id + 1)
+            .unwrap_or(1)
+    }
+}
+
+/// The term and vote of a member, persisted to `currterm` so that a restarted member neither
+/// goes back in term nor votes twice in one term.
+pub(crate) struct MemberState {
+    path: String,
+    role: MemberRole,
+    term: i64,
+    voted_for: Option<String>,
+    leader_id: Option<String>,
+}
+
+impl MemberState {
+    const TERM_KEY: &'static str = "currTerm";
+    const VOTE_KEY: &'static str = "voteLeader";
+
+    pub(crate) fn new(path: impl Into<String>) -> Self {
+        let path = path.into();
+        let mut state = Self {
+            path,
+            role: MemberRole::Follower,
+            term: 0,
+            voted_for: None,
+            leader_id: None,
+        };
+        let content = file_to_string(&state.path)
+            .ok()
+            .filter(|content| !content.is_empty())
+            .or_else(|| file_to_string(&format!("{}.bak", state.path)).ok());
+        for line in content.iter().flat_map(|content| content.lines()) {
+            match line.split_once('=') {
+                Some((Self::TERM_KEY, term)) => state.term = term.trim().parse().unwrap_or(0),
+                Some((Self::VOTE_KEY, vote)) if !vote.trim().is_empty() => {
+                    state.voted_for = Some(vote.trim().to_string())
+                }
+                _ => {}
+            }
+        }
+        state
+    }
+
+    pub(crate) fn role(&self) -> MemberRole {
+        self.role
+    }
+
+    pub(crate) fn term(&self) -> i64 {
+        self.term
+    }
+
+    pub(crate) fn leader_id(&self) -> Option<&str> {
+        self.leader_id.as_deref()
+    }
+
+    pub(crate) fn is_leader(&self) -> bool {
+        self.role == MemberRole::Leader
+    }
+
+    /// Starts an election in the next term and votes for `self_id`, `None` when the vote cannot
+    /// be persisted.
+    pub(crate) fn next_term(&mut self, self_id: &str) -> Option<i64> {
+        self.term += 1;
+        if !self.persist_vote(self_id) {
+            self.term -= 1;
+            return None;
+        }
+        self.role = MemberRole::Candidate;
+        self.leader_id = None;
+        Some(self.term)
+    }
+
+    /// Steps down to follower when a member is seen in a later term.
+    pub(crate) fn observe_term(&mut self, term: i64) -> bool {
+        if term <= self.term {
+            return false;
+        }
+        self.term = term;
+        self.role = MemberRole::Follower;
+        self.voted_for = None;
+        self.leader_id = None;
+        self.persist();
+        true
+    }
+
+    /// Grants the vote of the current term unless it went to another candidate or cannot be
+    /// persisted.
+    pub(crate) fn vote(&mut self, candidate_id: &str) -> bool {
+        match self.voted_for.as_deref() {
+            Some(voted_for) => voted_for == candidate_id,
+            None => self.persist_vote(candidate_id),
+        }
+    }
+
+    /// Votes for `candidate_id` in the current term, the vote is taken back when it cannot be
+    /// persisted, as a restarted member would not know of it.
+    fn persist_vote(&mut self, candidate_id: &str) -> bool {
+        let voted_for = self.voted_for.replace(candidate_id.to_string());
+        if self.persist() {
+            return true;
+        }
+        self.voted_for = voted_for;
+        false
+    }
+
+    pub(crate) fn change_to_leader(&mut self, self_id: &str) {
+        self.role = MemberRole::Leader;
+        self.leader_id = Some(self_id.to_string());
+    }
+
+    /// Returns whether the leader changed.
+    pub(crate) fn change_to_follower(&mut self, leader_id: &str) -> bool {
+        let changed = self.leader_id.as_deref() != Some(leader_id);
+        self.role = MemberRole::Follower;
+        self.leader_id = Some(leader_id.to_string());
+        changed
+    }
+
+    fn persist(&self) -> bool {
+        let content = format!(
+            "{}={}\n{}={}\n",
+            Self::TERM_KEY,
+            self.term,
+            Self::VOTE_KEY,
+            self.voted_for.as_deref().unwrap_or_default()
+        );
+        match string_to_file(&content, &self.path) {
+            Ok(()) => true,
+            Err(e) => {
+                error!("persist dledger term to {} failed: {}", self.path, e);
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_peers() {
+        let peers = DLedgerPeers::parse(
+            "RaftNode00",
+            "n0-127.0.0.1:40911;n1-127.0.0.1:40912;n2-127.0.0.1:40913",
+            "n1",
+        )
+        .unwrap();
+        assert_eq!(peers.len(), 3);
+        assert_eq!(peers.self_addr(), "127.0.0.1:40912");
+        assert_eq!(peers.self_port(), 40912);
+        assert_eq!(peers.slave_broker_id(), 2);
+        assert_eq!(
+            peers.remote_peers().map(|(id, _)| id).collect::<Vec<_>>(),
+            ["n0", "n2"]
+        );
+        assert!(!peers.is_quorum(1));
+        assert!(peers.is_quorum(2));
+
+        assert!(DLedgerPeers::parse("g", "n0-127.0.0.1:40911", "n1").is_err());
+        assert!(DLedgerPeers::parse("g", "n0-127.0.0.1", "n0").is_err());
+        assert!(DLedgerPeers::parse("g", "n0-a:1;n0-b:2", "n0").is_err());
+    }
+
+    #[test]
+    fn term_and_vote_survive_a_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("currterm").to_string_lossy().into_owned();
+        let mut state = MemberState::new(&path);
+        assert_eq!(state.term(), 0);
+        assert_eq!(state.next_term("n0"), Some(1));
+        assert!(!state.vote("n1"));
+        assert!(state.observe_term(3));
+        assert!(state.vote("n1"));
+        assert!(!state.vote("n2"));
+
+        let mut state = MemberState::new(&path);
+        assert_eq!(state.term(), 3);
+        assert_eq!(state.role(), MemberRole::Follower);
+        assert!(!state.vote("n2"));
+        assert!(state.vote("n1"));
+    }
+
+    #[test]
+    fn no_vote_is_granted_when_it_cannot_be_persisted() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("file");
+        std::fs::write(&file, "").unwrap();
+        // the parent of the term file is a file, so it cannot be written
+        let mut state = MemberState::new(file.join("currterm").to_string_lossy());
+        assert!(state.observe_term(2));
+        assert!(!state.vote("n1"));
+        assert!(!state.vote("n2"));
+        assert_eq!(state.next_term("n0"), None);
+        assert_eq!(state.term(), 2);
+        assert_eq!(state.role(), MemberRole::Follower);
+    }
+}
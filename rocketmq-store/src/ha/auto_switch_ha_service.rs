@@ -0,0 +1,285 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashSet;
+use std::io;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use cheetah_string::CheetahString;
+use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::TimeUtils::get_current_millis;
+use tokio::task::JoinHandle;
+use tracing::info;
+use tracing::warn;
+
+use crate::config::message_store_config::MessageStoreConfig;
+use crate::ha::default_ha_service::DefaultHAService;
+use crate::ha::epoch_file_cache::EpochEntry;
+use crate::ha::epoch_file_cache::EpochFileCache;
+use crate::log_file::commit_log::CommitLog;
+use crate::store_path_config_helper::get_store_path_epoch_file;
+
+/// How often the sync state set is checked for slaves that caught up or fell behind.
+const SYNC_STATE_SET_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The HA service of a broker in controller mode, whose role is switched by the controller.
+///
+/// On every switch the commit log is truncated to the confirm offset, as only the confirmed
+/// part is known to be held by the whole sync state set, and the new epoch is recorded in the
+/// epoch file. As master it tracks the sync state set, the slaves that hold the commit log up
+/// to the confirm offset, which the next master is elected from.
+pub struct AutoSwitchHAService {
+    ha_service: Arc<DefaultHAService>,
+    commit_log: parking_lot::Mutex<CommitLog>,
+    message_store_config: Arc<MessageStoreConfig>,
+    epoch_cache: EpochFileCache,
+    local_broker_id: AtomicU64,
+    task: parking_lot::Mutex<Option<JoinHandle<()>>>,
+}
+
+impl AutoSwitchHAService {
+    pub fn new(
+        ha_service: Arc<DefaultHAService>,
+        commit_log: CommitLog,
+        message_store_config: Arc<MessageStoreConfig>,
+        broker_config: &BrokerConfig,
+    ) -> Self {
+        let epoch_file = message_store_config
+            .store_path_epoch_file
+            .as_ref()
+            .filter(|path| !path.is_empty())
+            .map(|path| path.to_string())
+            .unwrap_or_else(|| {
+                get_store_path_epoch_file(message_store_config.store_path_root_dir.as_str())
+            });
+        let sync_state_set = HashSet::from([broker_config.broker_identity.broker_id]);
+        ha_service
+            .group_transfer_service()
+            .set_sync_state_set(sync_state_set);
+        Self {
+            ha_service,
+            commit_log: parking_lot::Mutex::new(commit_log),
+            message_store_config,
+            epoch_cache: EpochFileCache::new(epoch_file),
+            local_broker_id: AtomicU64::new(broker_config.broker_identity.broker_id),
+            task: parking_lot::Mutex::new(None),
+        }
+    }
+
+    pub fn start(self: &Arc<Self>) -> io::Result<()> {
+        self.ha_service.start()?;
+        let this = self.clone();
+        *self.task.lock() = Some(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(SYNC_STATE_SET_CHECK_INTERVAL);
+            loop {
+                interval.tick().await;
+                if this.is_master() {
+                    this.update_sync_state_set();
+                }
+            }
+        }));
+        Ok(())
+    }
+
+    pub fn shutdown(&self) {
+        if let Some(task) = self.task.lock().take() {
+            task.abort();
+        }
+        self.ha_service.shutdown();
+    }
+
+    pub fn ha_service(&self) -> &Arc<DefaultHAService> {
+        &self.ha_service
+    }
+
+    pub fn is_master(&self) -> bool {
+        self.ha_service.group_transfer_service().is_master()
+    }
+
+    pub fn local_broker_id(&self) -> u64 {
+        self.local_broker_id.load(Ordering::Acquire)
+    }
+
+    /// Becomes the master of `master_epoch`, rejected when the broker already saw a later epoch.
+    pub async fn change_to_master(&self, master_epoch: i32) -> bool {
+        let last_epoch = self.epoch_cache.last_epoch();
+        if master_epoch < last_epoch {
+            warn!(
+                "change to master rejected, epoch {} is older than the last epoch {}",
+                master_epoch, last_epoch
+            );
+            return false;
+        }
+        self.ha_service.clear_ha_master_address();
+        let max_offset = self.truncate_to_confirm_offset().await;
+        if master_epoch > last_epoch && !self.epoch_cache.append_entry(master_epoch, max_offset) {
+            return false;
+        }
+        let group_transfer_service = self.ha_service.group_transfer_service();
+        group_transfer_service.set_sync_state_set(HashSet::from([self.local_broker_id()]));
+        group_transfer_service.set_master(true);
+        info!(
+            "change to master, epoch {}, max offset {}",
+            master_epoch, max_offset
+        );
+        true
+    }
+
+    /// Becomes a slave of the master of `new_master_epoch`, with the broker id the controller
+    /// assigned, if any.
+    ///
+    /// Replicates from `new_master_ha_addr` if given, otherwise the slave waits for the HA
+    /// address of the new master, the address of the old master is dropped either way.
+    pub async fn change_to_slave(
+        &self,
+        new_master_ha_addr: Option<&CheetahString>,
+        new_master_epoch: i32,
+        slave_id: Option<u64>,
+    ) -> bool {
+        let last_epoch = self.epoch_cache.last_epoch();
+        if new_master_epoch < last_epoch {
+            warn!(
+                "change to slave rejected, epoch {} is older than the last epoch {}",
+                new_master_epoch, last_epoch
+            );
+            return false;
+        }
+        // truncated while still master, as a master computes its confirm offset from the acks
+        let max_offset = self.truncate_to_confirm_offset().await;
+        self.ha_service.group_transfer_service().set_master(false);
+        if let Some(slave_id) = slave_id {
+            self.local_broker_id.store(slave_id, Ordering::Release);
+            self.ha_service.set_local_broker_id(slave_id);
+        }
+        if new_master_epoch > last_epoch {
+            self.epoch_cache.append_entry(new_master_epoch, max_offset);
+        }
+        match new_master_ha_addr {
+            Some(new_master_ha_addr) => {
+                self.ha_service.update_ha_master_address(new_master_ha_addr)
+            }
+            None => self.ha_service.clear_ha_master_address(),
+        }
+        info!(
+            "change to slave of {:?}, epoch {}, max offset {}",
+            new_master_ha_addr, new_master_epoch, max_offset
+        );
+        true
+    }
+
+    /// Truncates the unconfirmed part of the commit log, returns the max offset after it.
+    ///
+    /// Holds the put message lock, so no put or replicated data is appended meanwhile.
+    async fn truncate_to_confirm_offset(&self) -> i64 {
+        let put_message_lock = self.commit_log.lock().put_message_lock().clone();
+        let _put_message_lock = put_message_lock.lock().await;
+        let mut commit_log = self.commit_log.lock();
+        let confirm_offset = commit_log.get_confirm_offset();
+        if confirm_offset >= 0 {
+            commit_log.truncate_dirty_files(confirm_offset);
+        }
+        let max_offset = commit_log.get_max_offset();
+        commit_log.set_confirm_offset(confirm_offset.clamp(0, max_offset));
+        self.epoch_cache.truncate_suffix_by_offset(max_offset);
+        max_offset
+    }
+
+    pub fn get_sync_state_set(&self) -> HashSet<u64> {
+        self.ha_service
+            .group_transfer_service()
+            .sync_state_set()
+            .unwrap_or_default()
+    }
+
+    /// Takes the sync state set the controller agreed to.
+    pub fn set_sync_state_set(&self, sync_state_set: HashSet<u64>) {
+        self.ha_service
+            .group_transfer_service()
+            .set_sync_state_set(sync_state_set);
+    }
+
+    pub fn in_sync_replicas_nums(&self) -> usize {
+        self.ha_service
+            .group_transfer_service()
+            .in_sync_replicas_nums()
+    }
+
+    /// The sync state set without the slaves that were disconnected or did not catch up within
+    /// `ha_max_time_slave_not_catchup`.
+    pub fn maybe_shrink_sync_state_set(&self) -> HashSet<u64> {
+        let now = get_current_millis();
+        let max_time_not_catchup = self.message_store_config.ha_max_time_slave_not_catchup as u64;
+        let local_broker_id = self.local_broker_id();
+        let sync_state_set = self.get_sync_state_set();
+        let caught_up = self
+            .ha_service
+            .group_transfer_service()
+            .slaves()
+            .into_iter()
+            .filter(|slave| now.saturating_sub(slave.last_caught_up_millis) <= max_time_not_catchup)
+            .filter_map(|slave| slave.broker_id)
+            .collect::<HashSet<_>>();
+        sync_state_set
+            .into_iter()
+            .filter(|broker_id| *broker_id == local_broker_id || caught_up.contains(broker_id))
+            .collect()
+    }
+
+    /// Whether the slave joins the sync state set, once it holds everything the master
+    /// confirmed.
+    pub fn maybe_expand_sync_state_set(&self, slave_broker_id: u64, slave_max_offset: i64) -> bool {
+        let sync_state_set = self.get_sync_state_set();
+        if sync_state_set.contains(&slave_broker_id) {
+            return false;
+        }
+        let confirm_offset = self.commit_log.lock().get_confirm_offset();
+        slave_max_offset >= confirm_offset
+    }
+
+    fn update_sync_state_set(&self) {
+        let mut sync_state_set = self.maybe_shrink_sync_state_set();
+        for slave in self.ha_service.group_transfer_service().slaves() {
+            if let Some(broker_id) = slave.broker_id {
+                if self.maybe_expand_sync_state_set(broker_id, slave.ack_offset) {
+                    sync_state_set.insert(broker_id);
+                }
+            }
+        }
+        if sync_state_set != self.get_sync_state_set() {
+            info!("sync state set changes to {:?}", sync_state_set);
+            self.set_sync_state_set(sync_state_set);
+        }
+    }
+
+    pub fn get_last_epoch(&self) -> i32 {
+        self.epoch_cache.last_epoch()
+    }
+
+    /// The epochs of the broker, the last one ending at the max commit log offset.
+    pub fn get_epoch_entries(&self) -> Vec<EpochEntry> {
+        let max_offset = self.commit_log.lock().get_max_offset();
+        self.epoch_cache.set_last_epoch_entry_end_offset(max_offset);
+        self.epoch_cache.get_all_entries()
+    }
+
+    /// Drops the epochs of the commit log files that were deleted.
+    pub fn truncate_epoch_file_prefix(&self, min_phy_offset: i64) {
+        self.epoch_cache.truncate_prefix_by_offset(min_phy_offset);
+    }
+}
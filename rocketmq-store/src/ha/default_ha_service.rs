@@ -0,0 +1,176 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::io;
+use std::sync::Arc;
+
+use cheetah_string::CheetahString;
+use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::common::broker::broker_role::BrokerRole;
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+use tokio::task::JoinSet;
+use tracing::error;
+use tracing::info;
+
+use crate::config::message_store_config::MessageStoreConfig;
+use crate::ha::group_transfer_service::GroupTransferService;
+use crate::ha::ha_client::HAClient;
+use crate::ha::ha_connection::HAConnection;
+use crate::log_file::commit_log::CommitLog;
+
+/// Replicates the commit log from a master to its slaves.
+///
+/// A master accepts the slaves on `ha_listen_port` and serves each of them on its own
+/// [`HAConnection`], a slave replicates from the master address set in `ha_master_address` or
+/// learnt from the name server through its [`HAClient`]. A slave which may act as master accepts
+/// too, so that a returning master can catch up with it.
+pub struct DefaultHAService {
+    message_store_config: Arc<MessageStoreConfig>,
+    auto_switch: bool,
+    slave_acting_master: bool,
+    commit_log: CommitLog,
+    group_transfer_service: Arc<GroupTransferService>,
+    ha_client: Arc<HAClient>,
+    tasks: parking_lot::Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl DefaultHAService {
+    pub fn new(
+        mut commit_log: CommitLog,
+        message_store_config: Arc<MessageStoreConfig>,
+        broker_config: &BrokerConfig,
+    ) -> Self {
+        let auto_switch = broker_config.enable_controller_mode;
+        let group_transfer_service = Arc::new(GroupTransferService::default());
+        commit_log.set_group_transfer_service(group_transfer_service.clone());
+        Self {
+            ha_client: Arc::new(HAClient::new(
+                commit_log.clone(),
+                message_store_config.clone(),
+                auto_switch,
+                broker_config.broker_identity.broker_id,
+            )),
+            message_store_config,
+            auto_switch,
+            slave_acting_master: broker_config.enable_slave_acting_master,
+            commit_log,
+            group_transfer_service,
+            tasks: parking_lot::Mutex::new(Vec::new()),
+        }
+    }
+
+    pub(crate) fn group_transfer_service(&self) -> &Arc<GroupTransferService> {
+        &self.group_transfer_service
+    }
+
+    pub fn start(&self) -> io::Result<()> {
+        let mut tasks = self.tasks.lock();
+        // in controller mode the role changes at runtime, so slaves are always accepted
+        if self.auto_switch
+            || self.slave_acting_master
+            || self.message_store_config.broker_role != BrokerRole::Slave
+        {
+            let listener = std::net::TcpListener::bind((
+                "0.0.0.0",
+                self.message_store_config.ha_listen_port as u16,
+            ))?;
+            listener.set_nonblocking(true)?;
+            let listener = TcpListener::from_std(listener)?;
+            info!(
+                "HA service listen on port {}",
+                self.message_store_config.ha_listen_port
+            );
+            tasks.push(tokio::spawn(accept_slaves(
+                listener,
+                self.commit_log.clone(),
+                self.message_store_config.clone(),
+                self.group_transfer_service.clone(),
+                self.auto_switch,
+            )));
+        }
+        tasks.push(tokio::spawn(self.ha_client.clone().run()));
+        Ok(())
+    }
+
+    pub fn shutdown(&self) {
+        for task in self.tasks.lock().drain(..) {
+            task.abort();
+        }
+    }
+
+    /// Replicates from `new_addr` from now on, an empty address stops the replication.
+    pub fn update_ha_master_address(&self, new_addr: &CheetahString) {
+        if new_addr.is_empty() {
+            self.ha_client.clear_master_address();
+        } else {
+            self.ha_client.update_master_address(new_addr);
+        }
+    }
+
+    pub fn get_ha_master_address(&self) -> Option<CheetahString> {
+        self.ha_client.master_address()
+    }
+
+    pub(crate) fn clear_ha_master_address(&self) {
+        self.ha_client.clear_master_address();
+    }
+
+    /// The broker id the slave sends on connect in controller mode.
+    pub(crate) fn set_local_broker_id(&self, broker_id: u64) {
+        self.ha_client.set_broker_id(broker_id);
+    }
+
+    /// Number of the slaves connected to this master.
+    pub fn get_connection_count(&self) -> usize {
+        self.group_transfer_service.connection_count()
+    }
+
+    /// The largest commit log offset any slave acked.
+    pub fn get_push_to_slave_max_offset(&self) -> i64 {
+        self.group_transfer_service.push_slave_max_offset()
+    }
+}
+
+async fn accept_slaves(
+    listener: TcpListener,
+    commit_log: CommitLog,
+    message_store_config: Arc<MessageStoreConfig>,
+    group_transfer_service: Arc<GroupTransferService>,
+    auto_switch: bool,
+) {
+    // the connections are aborted together with the accept task
+    let mut connections = JoinSet::new();
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, addr)) => {
+                    let _ = stream.set_nodelay(true);
+                    let connection = HAConnection::new(
+                        addr,
+                        commit_log.clone(),
+                        message_store_config.clone(),
+                        group_transfer_service.clone(),
+                        auto_switch,
+                    );
+                    connections.spawn(connection.serve(stream));
+                }
+                Err(e) => error!("HA service accept slave failed: {}", e),
+            },
+            Some(_) = connections.join_next(), if !connections.is_empty() => {}
+        }
+    }
+}
@@ -0,0 +1,249 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::BTreeMap;
+
+use rocketmq_common::FileUtils::file_to_string;
+use rocketmq_common::FileUtils::string_to_file;
+use tracing::error;
+use tracing::warn;
+
+/// The commit log range written while one master epoch lasted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EpochEntry {
+    pub epoch: i32,
+    pub start_offset: i64,
+    /// Start offset of the next epoch, `i64::MAX` for the last one until it is set.
+    pub end_offset: i64,
+}
+
+impl EpochEntry {
+    pub fn new(epoch: i32, start_offset: i64) -> Self {
+        Self {
+            epoch,
+            start_offset,
+            end_offset: i64::MAX,
+        }
+    }
+}
+
+/// The epochs of a broker in controller mode, persisted as one `epoch-startOffset` line per
+/// epoch.
+///
+/// Comparing the epochs of two replicas tells up to which offset their commit logs agree, the
+/// part past it is truncated when the role changes.
+pub struct EpochFileCache {
+    path: String,
+    entries: parking_lot::RwLock<BTreeMap<i32, EpochEntry>>,
+}
+
+impl EpochFileCache {
+    pub fn new(path: impl Into<String>) -> Self {
+        let path = path.into();
+        let entries = load_entries(&path)
+            .or_else(|| load_entries(&format!("{}.bak", path)))
+            .unwrap_or_default();
+        Self {
+            path,
+            entries: parking_lot::RwLock::new(entries),
+        }
+    }
+
+    /// Starts `epoch` at `start_offset`, it has to be after the last epoch in both respects.
+    pub fn append_entry(&self, epoch: i32, start_offset: i64) -> bool {
+        let mut entries = self.entries.write();
+        if let Some((_, last)) = entries.iter_mut().next_back() {
+            if last.epoch >= epoch || last.start_offset > start_offset {
+                warn!(
+                    "epoch {} at {} does not follow the last epoch {} at {}",
+                    epoch, start_offset, last.epoch, last.start_offset
+                );
+                return false;
+            }
+            last.end_offset = start_offset;
+        }
+        entries.insert(epoch, EpochEntry::new(epoch, start_offset));
+        self.persist(&entries);
+        true
+    }
+
+    pub fn last_entry(&self) -> Option<EpochEntry> {
+        self.entries.read().values().next_back().copied()
+    }
+
+    pub fn last_epoch(&self) -> i32 {
+        self.last_entry().map(|entry| entry.epoch).unwrap_or(0)
+    }
+
+    pub fn get_entry(&self, epoch: i32) -> Option<EpochEntry> {
+        self.entries.read().get(&epoch).copied()
+    }
+
+    pub fn get_all_entries(&self) -> Vec<EpochEntry> {
+        self.entries.read().values().copied().collect()
+    }
+
+    /// Ends the last epoch at `end_offset`, the max commit log offset of the broker.
+    pub fn set_last_epoch_entry_end_offset(&self, end_offset: i64) {
+        if let Some(last) = self.entries.write().values_mut().next_back() {
+            last.end_offset = end_offset;
+        }
+    }
+
+    /// The offset up to which the commit log agrees with the one of the replica that wrote
+    /// `other`, -1 when they share no epoch.
+    pub fn find_consistent_point(&self, other: &[EpochEntry]) -> i64 {
+        let entries = self.entries.read();
+        for local in entries.values().rev() {
+            let matched = other.iter().find(|entry| {
+                entry.epoch == local.epoch && entry.start_offset == local.start_offset
+            });
+            if let Some(matched) = matched {
+                return local.end_offset.min(matched.end_offset);
+            }
+        }
+        -1
+    }
+
+    /// Drops the epochs starting at or after `offset`, the last one kept then ends at `offset`.
+    pub fn truncate_suffix_by_offset(&self, offset: i64) {
+        let mut entries = self.entries.write();
+        entries.retain(|_, entry| entry.start_offset < offset);
+        if let Some(last) = entries.values_mut().next_back() {
+            last.end_offset = offset;
+        }
+        self.persist(&entries);
+    }
+
+    /// Drops the epochs that ended at or before `offset`, the commit log before it was deleted.
+    pub fn truncate_prefix_by_offset(&self, offset: i64) {
+        let mut entries = self.entries.write();
+        entries.retain(|_, entry| entry.end_offset > offset);
+        if let Some(first) = entries.values_mut().next() {
+            first.start_offset = first.start_offset.max(offset);
+        }
+        self.persist(&entries);
+    }
+
+    fn persist(&self, entries: &BTreeMap<i32, EpochEntry>) {
+        let content = entries
+            .values()
+            .map(|entry| format!("{}-{}\n", entry.epoch, entry.start_offset))
+            .collect::<String>();
+        if let Err(e) = string_to_file(&content, &self.path) {
+            error!("persist epoch file {} failed: {}", self.path, e);
+        }
+    }
+}
+
+fn load_entries(path: &str) -> Option<BTreeMap<i32, EpochEntry>> {
+    let content = file_to_string(path).ok()?;
+    let mut entries = BTreeMap::new();
+    for line in content.lines().filter(|line| !line.trim().is_empty()) {
+        let Some((epoch, start_offset)) = line.trim().split_once('-') else {
+            warn!("invalid epoch entry {} in {}", line, path);
+            return None;
+        };
+        let (Ok(epoch), Ok(start_offset)) = (epoch.parse::<i32>(), start_offset.parse::<i64>())
+        else {
+            warn!("invalid epoch entry {} in {}", line, path);
+            return None;
+        };
+        entries.insert(epoch, EpochEntry::new(epoch, start_offset));
+    }
+    // every epoch ends where the next one starts
+    let starts = entries
+        .values()
+        .map(|entry| entry.start_offset)
+        .skip(1)
+        .collect::<Vec<_>>();
+    for (entry, end_offset) in entries.values_mut().zip(starts) {
+        entry.end_offset = end_offset;
+    }
+    Some(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn epoch_file(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("epoch-{}-{}", name, std::process::id()))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn appends_and_reloads_epochs() {
+        let path = epoch_file("reload");
+        let cache = EpochFileCache::new(path.clone());
+        assert!(cache.append_entry(1, 0));
+        assert!(cache.append_entry(2, 100));
+        assert!(!cache.append_entry(2, 200));
+        assert!(!cache.append_entry(3, 50));
+        assert!(cache.append_entry(4, 300));
+
+        let reloaded = EpochFileCache::new(path.clone());
+        assert_eq!(reloaded.get_entry(2).unwrap().end_offset, 300);
+        assert_eq!(reloaded.last_epoch(), 4);
+
+        reloaded.truncate_suffix_by_offset(150);
+        assert_eq!(
+            reloaded.last_entry().unwrap(),
+            EpochEntry {
+                epoch: 2,
+                start_offset: 100,
+                end_offset: 150,
+            }
+        );
+        reloaded.truncate_prefix_by_offset(120);
+        assert_eq!(reloaded.get_all_entries().len(), 1);
+        assert_eq!(reloaded.get_entry(2).unwrap().start_offset, 120);
+
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(format!("{}.bak", path));
+    }
+
+    #[test]
+    fn finds_the_consistent_point() {
+        let path = epoch_file("consistent");
+        let cache = EpochFileCache::new(path.clone());
+        cache.append_entry(1, 0);
+        cache.append_entry(2, 100);
+        cache.set_last_epoch_entry_end_offset(180);
+
+        let master = [
+            EpochEntry {
+                epoch: 1,
+                start_offset: 0,
+                end_offset: 100,
+            },
+            EpochEntry {
+                epoch: 2,
+                start_offset: 100,
+                end_offset: 150,
+            },
+            EpochEntry::new(3, 150),
+        ];
+        assert_eq!(cache.find_consistent_point(&master), 150);
+        assert_eq!(cache.find_consistent_point(&master[..1]), 100);
+        assert_eq!(cache.find_consistent_point(&[EpochEntry::new(5, 0)]), -1);
+
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(format!("{}.bak", path));
+    }
+}
@@ -0,0 +1,275 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicI64;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use rocketmq_common::TimeUtils::get_current_millis;
+use tokio::sync::Notify;
+use tracing::warn;
+
+use crate::base::message_status_enum::PutMessageStatus;
+
+/// A slave connected to the master.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SlaveState {
+    /// Sent on connect in controller mode only.
+    pub(crate) broker_id: Option<u64>,
+    pub(crate) ack_offset: i64,
+    /// When the slave last acked everything the master had.
+    pub(crate) last_caught_up_millis: u64,
+}
+
+/// Tracks how far the slaves acked the commit log of the master, so that a put on a
+/// `SYNC_MASTER` can wait until enough replicas hold the message.
+///
+/// In controller mode only the slaves in the sync state set count, and the confirm offset of
+/// the master is the least offset they all acked.
+#[derive(Default)]
+pub(crate) struct GroupTransferService {
+    slaves: parking_lot::Mutex<HashMap<SocketAddr, SlaveState>>,
+    push_slave_max_offset: AtomicI64,
+    ack_notify: Notify,
+    data_notify: Notify,
+    sync_state_set: parking_lot::RwLock<Option<HashSet<u64>>>,
+    master: AtomicBool,
+}
+
+impl GroupTransferService {
+    pub(crate) fn add_slave(&self, addr: SocketAddr) {
+        self.slaves.lock().insert(
+            addr,
+            SlaveState {
+                broker_id: None,
+                ack_offset: -1,
+                last_caught_up_millis: get_current_millis(),
+            },
+        );
+    }
+
+    pub(crate) fn set_slave_broker_id(&self, addr: &SocketAddr, broker_id: u64) {
+        if let Some(slave) = self.slaves.lock().get_mut(addr) {
+            slave.broker_id = Some(broker_id);
+        }
+    }
+
+    pub(crate) fn remove_slave(&self, addr: &SocketAddr) {
+        self.slaves.lock().remove(addr);
+    }
+
+    pub(crate) fn connection_count(&self) -> usize {
+        self.slaves.lock().len()
+    }
+
+    pub(crate) fn slaves(&self) -> Vec<SlaveState> {
+        self.slaves.lock().values().copied().collect()
+    }
+
+    pub(crate) fn is_master(&self) -> bool {
+        self.master.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn set_master(&self, master: bool) {
+        self.master.store(master, Ordering::Release);
+    }
+
+    /// `None` outside of controller mode.
+    pub(crate) fn sync_state_set(&self) -> Option<HashSet<u64>> {
+        self.sync_state_set.read().clone()
+    }
+
+    pub(crate) fn set_sync_state_set(&self, sync_state_set: HashSet<u64>) {
+        *self.sync_state_set.write() = Some(sync_state_set);
+        self.ack_notify.notify_waiters();
+    }
+
+    /// Replicas in sync, the master included.
+    pub(crate) fn in_sync_replicas_nums(&self) -> usize {
+        match self.sync_state_set.read().as_ref() {
+            Some(sync_state_set) => sync_state_set.len(),
+            None => self.connection_count() + 1,
+        }
+    }
+
+    /// The least offset the master and the connected slaves of the sync state set all hold.
+    pub(crate) fn compute_confirm_offset(&self, master_max_offset: i64) -> i64 {
+        let sync_state_set = self.sync_state_set.read();
+        self.slaves
+            .lock()
+            .values()
+            // a slave that did not report yet is still in its handshake
+            .filter(|slave| slave.ack_offset >= 0 && is_in(&sync_state_set, slave.broker_id))
+            .map(|slave| slave.ack_offset)
+            .fold(master_max_offset, i64::min)
+    }
+
+    /// The largest offset any slave acked.
+    pub(crate) fn push_slave_max_offset(&self) -> i64 {
+        self.push_slave_max_offset.load(Ordering::Acquire)
+    }
+
+    /// Records the offset a slave acked and wakes the puts waiting for it.
+    pub(crate) fn notify_slave_ack(&self, addr: SocketAddr, offset: i64, master_max_offset: i64) {
+        if let Some(slave) = self.slaves.lock().get_mut(&addr) {
+            slave.ack_offset = offset;
+            if offset >= master_max_offset {
+                slave.last_caught_up_millis = get_current_millis();
+            }
+        }
+        self.push_slave_max_offset
+            .fetch_max(offset, Ordering::AcqRel);
+        self.ack_notify.notify_waiters();
+    }
+
+    /// Woken whenever new data was put, the connections wait on it to push the data right away.
+    pub(crate) fn data_notify(&self) -> &Notify {
+        &self.data_notify
+    }
+
+    /// Replicas, the master included, that hold the commit log up to `next_offset`.
+    pub(crate) fn ack_nums(&self, next_offset: i64) -> u32 {
+        let sync_state_set = self.sync_state_set.read();
+        let acked = self
+            .slaves
+            .lock()
+            .values()
+            .filter(|slave| slave.ack_offset >= next_offset)
+            .filter(|slave| is_in(&sync_state_set, slave.broker_id))
+            .count();
+        acked as u32 + 1
+    }
+
+    /// Whether a slave is connected that is less than `max_gap` bytes behind `master_put_where`.
+    pub(crate) fn is_slave_ok(&self, master_put_where: i64, max_gap: i64) -> bool {
+        self.connection_count() > 0 && master_put_where - self.push_slave_max_offset() < max_gap
+    }
+
+    fn is_transfer_ok(&self, next_offset: i64, need_ack_nums: u32) -> bool {
+        if need_ack_nums <= 1 {
+            self.push_slave_max_offset() >= next_offset
+        } else {
+            self.ack_nums(next_offset) >= need_ack_nums
+        }
+    }
+
+    /// Waits until `need_ack_nums` replicas, the master included, hold the commit log up to
+    /// `next_offset`. With `need_ack_nums` of one or less a single slave has to catch up.
+    ///
+    /// Returns [`PutMessageStatus::FlushSlaveTimeout`] when they did not within `timeout`.
+    pub(crate) async fn wait_for_transfer(
+        &self,
+        next_offset: i64,
+        need_ack_nums: u32,
+        timeout: Duration,
+    ) -> PutMessageStatus {
+        self.data_notify.notify_waiters();
+        let transferred = async {
+            loop {
+                // created before the check, so that an ack in between is not missed
+                let notified = self.ack_notify.notified();
+                if self.is_transfer_ok(next_offset, need_ack_nums) {
+                    return;
+                }
+                notified.await;
+            }
+        };
+        match tokio::time::timeout(timeout, transferred).await {
+            Ok(()) => PutMessageStatus::PutOk,
+            Err(_) => {
+                warn!(
+                    "transfer message to slave timeout, offset: {}, ackNums: {}",
+                    next_offset, need_ack_nums
+                );
+                PutMessageStatus::FlushSlaveTimeout
+            }
+        }
+    }
+}
+
+/// Every slave is in when there is no sync state set.
+fn is_in(sync_state_set: &Option<HashSet<u64>>, broker_id: Option<u64>) -> bool {
+    match sync_state_set {
+        Some(sync_state_set) => broker_id.is_some_and(|id| sync_state_set.contains(&id)),
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn waits_for_the_slaves_to_ack() {
+        let service = Arc::new(GroupTransferService::default());
+        let first: SocketAddr = "127.0.0.1:40001".parse().unwrap();
+        let second: SocketAddr = "127.0.0.1:40002".parse().unwrap();
+        service.add_slave(first);
+        service.add_slave(second);
+        assert!(service.is_slave_ok(100, 1024));
+        assert!(!service.is_slave_ok(2048, 1024));
+        assert_eq!(
+            service
+                .wait_for_transfer(100, 1, Duration::from_millis(10))
+                .await,
+            PutMessageStatus::FlushSlaveTimeout
+        );
+
+        let acking = service.clone();
+        tokio::spawn(async move {
+            acking.notify_slave_ack(first, 100, 120);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            acking.notify_slave_ack(second, 120, 120);
+        });
+        assert_eq!(
+            service
+                .wait_for_transfer(100, 3, Duration::from_secs(5))
+                .await,
+            PutMessageStatus::PutOk
+        );
+        assert_eq!(service.ack_nums(110), 2);
+        assert_eq!(service.push_slave_max_offset(), 120);
+
+        service.remove_slave(&second);
+        assert_eq!(service.connection_count(), 1);
+    }
+
+    #[test]
+    fn counts_the_sync_state_set_only() {
+        let service = GroupTransferService::default();
+        let first: SocketAddr = "127.0.0.1:40001".parse().unwrap();
+        let second: SocketAddr = "127.0.0.1:40002".parse().unwrap();
+        service.add_slave(first);
+        service.add_slave(second);
+        service.set_slave_broker_id(&first, 1);
+        service.set_slave_broker_id(&second, 2);
+        service.notify_slave_ack(first, 80, 100);
+        service.notify_slave_ack(second, 100, 100);
+        assert_eq!(service.compute_confirm_offset(100), 80);
+        assert_eq!(service.in_sync_replicas_nums(), 3);
+
+        service.set_sync_state_set(HashSet::from([0, 2]));
+        assert_eq!(service.compute_confirm_offset(100), 100);
+        assert_eq!(service.ack_nums(80), 2);
+        assert_eq!(service.in_sync_replicas_nums(), 2);
+    }
+}
@@ -0,0 +1,215 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::io;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use cheetah_string::CheetahString;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::net::tcp::OwnedReadHalf;
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::net::TcpStream;
+use tokio::sync::Notify;
+use tracing::info;
+use tracing::warn;
+
+use crate::config::message_store_config::MessageStoreConfig;
+use crate::ha::ha_connection::decode_transfer_header;
+use crate::ha::ha_connection::AUTO_SWITCH_TRANSFER_HEADER_SIZE;
+use crate::ha::ha_connection::TRANSFER_HEADER_SIZE;
+use crate::log_file::commit_log::CommitLog;
+
+/// How long the slave waits before connecting again when it has no master or lost it.
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The client of a slave that replicates the commit log of its master.
+///
+/// It reports its max commit log offset on connect, after every chunk it applied and at least
+/// every `ha_send_heartbeat_interval`, and appends the chunks the master pushes.
+///
+/// In controller mode it sends its broker id first, and takes the confirm offset of the master
+/// up to what it holds itself.
+pub(crate) struct HAClient {
+    master_address: parking_lot::RwLock<Option<CheetahString>>,
+    commit_log: tokio::sync::Mutex<CommitLog>,
+    message_store_config: Arc<MessageStoreConfig>,
+    applied: Notify,
+    auto_switch: bool,
+    broker_id: AtomicU64,
+}
+
+impl HAClient {
+    pub(crate) fn new(
+        commit_log: CommitLog,
+        message_store_config: Arc<MessageStoreConfig>,
+        auto_switch: bool,
+        broker_id: u64,
+    ) -> Self {
+        let master_address = message_store_config
+            .ha_master_address
+            .as_deref()
+            .filter(|address| !address.is_empty())
+            .map(CheetahString::from);
+        Self {
+            master_address: parking_lot::RwLock::new(master_address),
+            commit_log: tokio::sync::Mutex::new(commit_log),
+            message_store_config,
+            applied: Notify::new(),
+            auto_switch,
+            broker_id: AtomicU64::new(broker_id),
+        }
+    }
+
+    pub(crate) fn master_address(&self) -> Option<CheetahString> {
+        self.master_address.read().clone()
+    }
+
+    /// Takes effect with the next connect.
+    pub(crate) fn update_master_address(&self, new_address: &CheetahString) {
+        let mut master_address = self.master_address.write();
+        if master_address.as_ref() != Some(new_address) {
+            info!(
+                "update master address, OLD: {:?} NEW: {}",
+                *master_address, new_address
+            );
+            *master_address = Some(new_address.clone());
+        }
+    }
+
+    /// Stops replicating, for a slave that became master.
+    pub(crate) fn clear_master_address(&self) {
+        if let Some(old) = self.master_address.write().take() {
+            info!("clear master address {}", old);
+        }
+    }
+
+    pub(crate) fn set_broker_id(&self, broker_id: u64) {
+        self.broker_id.store(broker_id, Ordering::Release);
+    }
+
+    pub(crate) async fn run(self: Arc<Self>) {
+        loop {
+            let Some(master_address) = self.master_address() else {
+                tokio::time::sleep(RECONNECT_INTERVAL).await;
+                continue;
+            };
+            match TcpStream::connect(master_address.as_str()).await {
+                Ok(stream) => {
+                    info!("HAClient connected to master {}", master_address);
+                    if let Err(e) = self.replicate(stream, &master_address).await {
+                        warn!(
+                            "HAClient connection to master {} closed: {}",
+                            master_address, e
+                        );
+                    }
+                }
+                Err(e) => warn!(
+                    "HAClient connect to master {} failed: {}",
+                    master_address, e
+                ),
+            }
+            tokio::time::sleep(RECONNECT_INTERVAL).await;
+        }
+    }
+
+    async fn max_offset(&self) -> i64 {
+        self.commit_log.lock().await.get_max_offset()
+    }
+
+    async fn replicate(&self, stream: TcpStream, master_address: &CheetahString) -> io::Result<()> {
+        let (reader, writer) = stream.into_split();
+        tokio::select! {
+            result = self.apply_data(reader) => result,
+            result = self.report_offsets(writer) => result,
+            _ = self.wait_master_changed(master_address) => Ok(()),
+        }
+    }
+
+    async fn wait_master_changed(&self, master_address: &CheetahString) {
+        while self.master_address().as_ref() == Some(master_address) {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    }
+
+    async fn report_offsets(&self, mut writer: OwnedWriteHalf) -> io::Result<()> {
+        let heartbeat =
+            Duration::from_millis(self.message_store_config.ha_send_heartbeat_interval as u64);
+        if self.auto_switch {
+            writer
+                .write_i64(self.broker_id.load(Ordering::Acquire) as i64)
+                .await?;
+        }
+        loop {
+            let notified = self.applied.notified();
+            writer.write_i64(self.max_offset().await).await?;
+            let _ = tokio::time::timeout(heartbeat, notified).await;
+        }
+    }
+
+    async fn apply_data(&self, mut reader: OwnedReadHalf) -> io::Result<()> {
+        let housekeeping =
+            Duration::from_millis(self.message_store_config.ha_housekeeping_interval as u64);
+        let mut header = if self.auto_switch {
+            vec![0u8; AUTO_SWITCH_TRANSFER_HEADER_SIZE]
+        } else {
+            vec![0u8; TRANSFER_HEADER_SIZE]
+        };
+        loop {
+            tokio::time::timeout(housekeeping, reader.read_exact(&mut header))
+                .await
+                .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "master sent nothing"))??;
+            let (phy_offset, body_size, confirm_offset) = decode_transfer_header(&header);
+            let mut body = vec![0u8; body_size.max(0) as usize];
+            reader.read_exact(&mut body).await?;
+
+            let mut commit_log = self.commit_log.lock().await;
+            if body.is_empty() {
+                update_confirm_offset(&mut commit_log, confirm_offset);
+                continue;
+            }
+            let slave_offset = commit_log.get_max_offset();
+            if slave_offset != 0 && slave_offset != phy_offset {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "master pushed offset {} not equal the max phy offset {} in slave",
+                        phy_offset, slave_offset
+                    ),
+                ));
+            }
+            if !commit_log.append_data(phy_offset, &body).await {
+                return Err(io::Error::other(format!(
+                    "append the data pushed at offset {} failed",
+                    phy_offset
+                )));
+            }
+            update_confirm_offset(&mut commit_log, confirm_offset);
+            drop(commit_log);
+            self.applied.notify_waiters();
+        }
+    }
+}
+
+fn update_confirm_offset(commit_log: &mut CommitLog, confirm_offset: Option<i64>) {
+    if let Some(confirm_offset) = confirm_offset {
+        let confirm_offset = confirm_offset.min(commit_log.get_max_offset());
+        commit_log.set_confirm_offset(confirm_offset);
+    }
+}
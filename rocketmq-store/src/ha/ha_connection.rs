@@ -0,0 +1,231 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::net::tcp::OwnedReadHalf;
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::net::TcpStream;
+use tokio::sync::oneshot;
+use tokio::time::Instant;
+use tracing::info;
+use tracing::warn;
+
+use crate::config::message_store_config::MessageStoreConfig;
+use crate::ha::group_transfer_service::GroupTransferService;
+use crate::log_file::commit_log::CommitLog;
+
+/// Header of a frame the master pushes, the commit log offset of the body followed by its size.
+pub(crate) const TRANSFER_HEADER_SIZE: usize = 8 + 4;
+
+/// In controller mode the header also carries the confirm offset of the master.
+pub(crate) const AUTO_SWITCH_TRANSFER_HEADER_SIZE: usize = TRANSFER_HEADER_SIZE + 8;
+
+/// How long the master waits for new data before checking again.
+const WAIT_DATA_INTERVAL: Duration = Duration::from_millis(100);
+
+pub(crate) fn encode_transfer_header(
+    phy_offset: i64,
+    body_size: i32,
+    confirm_offset: Option<i64>,
+) -> Vec<u8> {
+    let mut header = Vec::with_capacity(AUTO_SWITCH_TRANSFER_HEADER_SIZE);
+    header.extend_from_slice(&phy_offset.to_be_bytes());
+    header.extend_from_slice(&body_size.to_be_bytes());
+    if let Some(confirm_offset) = confirm_offset {
+        header.extend_from_slice(&confirm_offset.to_be_bytes());
+    }
+    header
+}
+
+/// Decodes a header of [`TRANSFER_HEADER_SIZE`] or [`AUTO_SWITCH_TRANSFER_HEADER_SIZE`] bytes.
+pub(crate) fn decode_transfer_header(header: &[u8]) -> (i64, i32, Option<i64>) {
+    let phy_offset = i64::from_be_bytes(header[..8].try_into().unwrap());
+    let body_size = i32::from_be_bytes(header[8..12].try_into().unwrap());
+    let confirm_offset = (header.len() == AUTO_SWITCH_TRANSFER_HEADER_SIZE)
+        .then(|| i64::from_be_bytes(header[12..].try_into().unwrap()));
+    (phy_offset, body_size, confirm_offset)
+}
+
+/// The connection of the master to one slave.
+///
+/// The read half takes the offsets the slave reports, the write half pushes the commit log from
+/// the first reported offset on, and a heartbeat frame without body when there is nothing to
+/// push. The connection is closed when either half fails.
+///
+/// In controller mode the slave sends its broker id before the first offset, and every frame
+/// carries the confirm offset of the master.
+pub(crate) struct HAConnection {
+    addr: SocketAddr,
+    commit_log: CommitLog,
+    message_store_config: Arc<MessageStoreConfig>,
+    group_transfer_service: Arc<GroupTransferService>,
+    auto_switch: bool,
+}
+
+impl HAConnection {
+    pub(crate) fn new(
+        addr: SocketAddr,
+        commit_log: CommitLog,
+        message_store_config: Arc<MessageStoreConfig>,
+        group_transfer_service: Arc<GroupTransferService>,
+        auto_switch: bool,
+    ) -> Self {
+        Self {
+            addr,
+            commit_log,
+            message_store_config,
+            group_transfer_service,
+            auto_switch,
+        }
+    }
+
+    pub(crate) async fn serve(self, stream: TcpStream) {
+        info!("HA connection of slave {} established", self.addr);
+        self.group_transfer_service.add_slave(self.addr);
+        let (reader, writer) = stream.into_split();
+        let (first_report_tx, first_report_rx) = oneshot::channel();
+        let result = tokio::select! {
+            result = self.read_reports(reader, first_report_tx) => result,
+            result = self.transfer_data(writer, first_report_rx) => result,
+        };
+        self.group_transfer_service.remove_slave(&self.addr);
+        if let Err(e) = result {
+            warn!("HA connection of slave {} closed: {}", self.addr, e);
+        }
+    }
+
+    async fn read_reports(
+        &self,
+        mut reader: OwnedReadHalf,
+        first_report: oneshot::Sender<i64>,
+    ) -> io::Result<()> {
+        let housekeeping =
+            Duration::from_millis(self.message_store_config.ha_housekeeping_interval as u64);
+        if self.auto_switch {
+            let broker_id = tokio::time::timeout(housekeeping, reader.read_i64())
+                .await
+                .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "no handshake in time"))??;
+            info!(
+                "slave {} handshakes with broker id {}",
+                self.addr, broker_id
+            );
+            self.group_transfer_service
+                .set_slave_broker_id(&self.addr, broker_id as u64);
+        }
+        let mut first_report = Some(first_report);
+        loop {
+            let offset = tokio::time::timeout(housekeeping, reader.read_i64())
+                .await
+                .map_err(|_| {
+                    io::Error::new(io::ErrorKind::TimedOut, "no offset reported in time")
+                })??;
+            if let Some(first_report) = first_report.take() {
+                info!("slave {} requests offset {}", self.addr, offset);
+                let _ = first_report.send(offset);
+            }
+            self.group_transfer_service.notify_slave_ack(
+                self.addr,
+                offset,
+                self.commit_log.get_max_offset(),
+            );
+        }
+    }
+
+    fn confirm_offset(&self) -> Option<i64> {
+        self.auto_switch
+            .then(|| self.commit_log.get_confirm_offset())
+    }
+
+    async fn transfer_data(
+        &self,
+        mut writer: OwnedWriteHalf,
+        first_report: oneshot::Receiver<i64>,
+    ) -> io::Result<()> {
+        let slave_request_offset = first_report.await.map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "slave closed before reporting",
+            )
+        })?;
+        // a new slave starts from the last commit log file of the master
+        let mut next_transfer_from = if slave_request_offset == 0 {
+            let master_offset = self.commit_log.get_max_offset();
+            let file_size = self.message_store_config.mapped_file_size_commit_log as i64;
+            master_offset - master_offset % file_size
+        } else {
+            slave_request_offset
+        };
+        let batch_size = self.message_store_config.ha_transfer_batch_size.max(1);
+        let heartbeat =
+            Duration::from_millis(self.message_store_config.ha_send_heartbeat_interval as u64);
+        let mut last_write = Instant::now();
+        loop {
+            // created before the read, so that data put in between is not missed
+            let data_notified = self.group_transfer_service.data_notify().notified();
+            let data = self
+                .commit_log
+                .get_data(next_transfer_from)
+                .and_then(|result| result.get_data(0, (result.size as usize).min(batch_size)));
+            match data {
+                Some(body) if !body.is_empty() => {
+                    let header = encode_transfer_header(
+                        next_transfer_from,
+                        body.len() as i32,
+                        self.confirm_offset(),
+                    );
+                    writer.write_all(&header).await?;
+                    writer.write_all(&body).await?;
+                    next_transfer_from += body.len() as i64;
+                    last_write = Instant::now();
+                }
+                _ => {
+                    if last_write.elapsed() >= heartbeat {
+                        let header =
+                            encode_transfer_header(next_transfer_from, 0, self.confirm_offset());
+                        writer.write_all(&header).await?;
+                        last_write = Instant::now();
+                    }
+                    let _ = tokio::time::timeout(WAIT_DATA_INTERVAL, data_notified).await;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transfer_header_round_trip() {
+        let header = encode_transfer_header(1024 * 1024 * 1024 + 7, 32768, None);
+        assert_eq!(header.len(), TRANSFER_HEADER_SIZE);
+        assert_eq!(
+            decode_transfer_header(&header),
+            (1024 * 1024 * 1024 + 7, 32768, None)
+        );
+
+        let header = encode_transfer_header(300, 0, Some(200));
+        assert_eq!(header.len(), AUTO_SWITCH_TRANSFER_HEADER_SIZE);
+        assert_eq!(decode_transfer_header(&header), (300, 0, Some(200)));
+    }
+}
@@ -15,6 +15,7 @@
  * limitations under the License.
  */
 use rocketmq_common::common::message::message_ext::MessageExt;
+use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
 
 use crate::base::message_result::PutMessageResult;
 
@@ -34,6 +35,17 @@ pub trait PutMessageHook {
     ///
     /// The result of putting the message
     fn execute_before_put_message(&self, msg: &MessageExt) -> Option<PutMessageResult>;
+
+    /// Execute before putting a message, with the message open to transformation, e.g. to
+    /// redirect it to a system topic.
+    ///
+    /// Delegates to [`PutMessageHook::execute_before_put_message`] by default.
+    fn execute_before_put_message_mut(
+        &self,
+        msg: &mut MessageExtBrokerInner,
+    ) -> Option<PutMessageResult> {
+        self.execute_before_put_message(&msg.message_ext_inner)
+    }
 }
 
 /// Alias for `Arc<dyn PutMessageHook>`.
@@ -18,7 +18,8 @@
 use std::sync::Arc;
 
 use bytes::Buf;
-use bytes::Bytes;
+use bytes::BufMut;
+use bytes::BytesMut;
 use cheetah_string::CheetahString;
 use rocketmq_common::common::hasher::string_hasher::JavaStringHasher;
 use tracing::info;
@@ -123,7 +124,14 @@ impl IndexFile {
         let begin_time = std::time::Instant::now();
         if self.mapped_file.hold() {
             self.index_header.update_byte_buffer();
-            self.mapped_file.flush(0);
+            // units are put at their slots, not appended, so the whole mapping is forced
+            if let Err(e) = self.mapped_file.get_mapped_file().flush() {
+                warn!(
+                    "flush index file {} failed, {}",
+                    self.mapped_file.get_file_name(),
+                    e
+                );
+            }
             self.mapped_file.release();
             info!(
                 "flush index file elapsed time(ms) {}",
@@ -142,83 +150,68 @@ impl IndexFile {
         self.mapped_file.destroy(interval_forcibly)
     }
 
+    /// Reads the slot of a key hash, the position of the last unit put with that hash.
     #[inline]
-    pub fn put_key(&self, key: &str, phy_offset: i64, store_timestamp: i64) -> bool {
-        if self.index_header.get_index_count() < self.index_num as i32 {
-            let key_hash = self.index_key_hash_method(key);
-            let slot_pos = key_hash as usize % self.hash_slot_num;
-            let abs_slot_pos = INDEX_HEADER_SIZE + slot_pos * HASH_SLOT_SIZE;
-
-            let mut buffer = self
-                .mapped_file
-                .get_data(abs_slot_pos, abs_slot_pos + 4)
-                .unwrap();
-            let mut slot_value = buffer.get_i32();
-            if slot_value <= INVALID_INDEX || slot_value > self.index_header.get_index_count() {
-                slot_value = INVALID_INDEX;
-            }
-
-            let mut time_diff = store_timestamp - self.index_header.get_begin_timestamp();
-            time_diff /= 1000;
-            if self.index_header.get_begin_timestamp() <= 0 {
-                time_diff = 0;
-            } else if time_diff > i32::MAX as i64 {
-                time_diff = i32::MAX as i64;
-            } else if time_diff < 0 {
-                time_diff = 0;
-            }
+    fn read_slot(&self, abs_slot_pos: usize) -> i32 {
+        self.mapped_file
+            .get_bytes(abs_slot_pos, HASH_SLOT_SIZE)
+            .map_or(INVALID_INDEX, |mut slot| slot.get_i32())
+    }
 
-            let abs_index_pos = INDEX_HEADER_SIZE
-                + self.hash_slot_num * HASH_SLOT_SIZE
-                + self.index_header.get_index_count() as usize * INDEX_SIZE;
+    #[inline]
+    fn abs_index_pos(&self, index: i32) -> usize {
+        INDEX_HEADER_SIZE + self.hash_slot_num * HASH_SLOT_SIZE + index as usize * INDEX_SIZE
+    }
 
-            self.mapped_file.append_message_offset_length(
-                &Bytes::copy_from_slice(&key_hash.to_be_bytes()),
-                abs_index_pos,
-                4,
-            );
-            self.mapped_file.append_message_offset_length(
-                &Bytes::copy_from_slice(&phy_offset.to_be_bytes()),
-                abs_index_pos,
-                8,
-            );
-            self.mapped_file.append_message_offset_length(
-                &Bytes::copy_from_slice(&(time_diff as i32).to_be_bytes()),
-                abs_index_pos,
-                4,
-            );
-            self.mapped_file.append_message_offset_length(
-                &Bytes::copy_from_slice(&slot_value.to_be_bytes()),
-                abs_index_pos,
-                4,
-            );
-            self.mapped_file.append_message_offset_length(
-                &Bytes::copy_from_slice(&self.index_header.get_index_count().to_be_bytes()),
-                abs_slot_pos,
-                4,
+    /// Puts the message at `phy_offset` under `key`, linking the unit to the previous one with
+    /// the same hash slot. Returns `false` when the file is full.
+    #[inline]
+    pub fn put_key(&self, key: &str, phy_offset: i64, store_timestamp: i64) -> bool {
+        let index_count = self.index_header.get_index_count();
+        if index_count >= self.index_num as i32 {
+            warn!(
+                "Over index file capacity: index count = {}; index max num = {}",
+                index_count, self.index_num
             );
+            return false;
+        }
+        let key_hash = self.index_key_hash_method(key);
+        let slot_pos = key_hash as usize % self.hash_slot_num;
+        let abs_slot_pos = INDEX_HEADER_SIZE + slot_pos * HASH_SLOT_SIZE;
 
-            if self.index_header.get_index_count() <= 1 {
-                self.index_header.set_begin_phy_offset(phy_offset);
-                self.index_header.set_begin_timestamp(store_timestamp);
-            }
-
-            if slot_value == INVALID_INDEX {
-                self.index_header.inc_hash_slot_count();
-            }
-            self.index_header.inc_index_count();
-            self.index_header.set_end_phy_offset(phy_offset);
-            self.index_header.set_end_timestamp(store_timestamp);
+        let mut slot_value = self.read_slot(abs_slot_pos);
+        if slot_value <= INVALID_INDEX || slot_value > index_count {
+            slot_value = INVALID_INDEX;
+        }
 
-            true
+        let begin_timestamp = self.index_header.get_begin_timestamp();
+        let time_diff = if begin_timestamp <= 0 {
+            0
         } else {
-            warn!(
-                "Over index file capacity: index count = {}; index max num = {}",
-                self.index_header.get_index_count(),
-                self.index_num
-            );
-            false
+            ((store_timestamp - begin_timestamp) / 1000).clamp(0, i32::MAX as i64)
+        };
+
+        let mut unit = BytesMut::with_capacity(INDEX_SIZE);
+        unit.put_i32(key_hash);
+        unit.put_i64(phy_offset);
+        unit.put_i32(time_diff as i32);
+        unit.put_i32(slot_value);
+        self.mapped_file
+            .put_slice(&unit, self.abs_index_pos(index_count));
+        self.mapped_file
+            .put_slice(&index_count.to_be_bytes(), abs_slot_pos);
+
+        if index_count <= 1 {
+            self.index_header.set_begin_phy_offset(phy_offset);
+            self.index_header.set_begin_timestamp(store_timestamp);
+        }
+        if slot_value == INVALID_INDEX {
+            self.index_header.inc_hash_slot_count();
         }
+        self.index_header.inc_index_count();
+        self.index_header.set_end_phy_offset(phy_offset);
+        self.index_header.set_end_timestamp(store_timestamp);
+        true
     }
 
     #[inline]
@@ -256,6 +249,7 @@ impl IndexFile {
             || end >= begin_timestamp && end <= end_timestamp
     }
 
+    /// Collects the offsets of the messages put under `key` in `[begin, end]`, newest first.
     #[inline]
     pub fn select_phy_offset(
         &self,
@@ -268,58 +262,85 @@ impl IndexFile {
         if !self.mapped_file.hold() {
             return;
         }
-
+        let index_count = self.index_header.get_index_count();
         let key_hash = self.index_key_hash_method(key);
         let slot_pos = key_hash as usize % self.hash_slot_num;
-        let abs_slot_pos = INDEX_HEADER_SIZE + slot_pos * HASH_SLOT_SIZE;
-
-        let mut buffer = self
-            .mapped_file
-            .get_data(abs_slot_pos, abs_slot_pos + 4)
-            .unwrap();
-        let slot_value = buffer.get_i32();
-        if slot_value <= INVALID_INDEX
-            || slot_value > self.index_header.get_index_count()
-            || self.index_header.get_index_count() <= 1
-        {
-            return;
-        }
-
-        let mut next_index_to_read = slot_value;
-        while phy_offsets.len() < max_num {
-            let abs_index_pos = INDEX_HEADER_SIZE
-                + self.hash_slot_num * HASH_SLOT_SIZE
-                + next_index_to_read as usize * INDEX_SIZE;
-
-            let key_hash_read = buffer.slice(abs_index_pos..abs_index_pos + 4).get_i32();
-            let phy_offset_read = buffer
-                .slice(abs_index_pos + 4..abs_index_pos + 12)
-                .get_i64();
-            let time_diff = buffer
-                .slice(abs_index_pos + 12..abs_index_pos + 16)
-                .get_i32();
-            let prev_index_read = buffer
-                .slice(abs_index_pos + 16..abs_index_pos + 20)
-                .get_i32();
-
-            if time_diff < 0 {
-                break;
-            }
-
-            let time_read = self.index_header.get_begin_timestamp() + time_diff as i64 * 1000;
-            if key_hash == key_hash_read && (time_read >= begin && time_read <= end) {
-                phy_offsets.push(phy_offset_read);
-            }
-
-            if prev_index_read <= INVALID_INDEX
-                || prev_index_read > self.index_header.get_index_count()
-                || prev_index_read == next_index_to_read
-                || time_read < begin
-            {
-                break;
+        let slot_value = self.read_slot(INDEX_HEADER_SIZE + slot_pos * HASH_SLOT_SIZE);
+        if slot_value > INVALID_INDEX && slot_value <= index_count && index_count > 1 {
+            let mut next_index_to_read = slot_value;
+            while phy_offsets.len() < max_num {
+                let Some(mut unit) = self
+                    .mapped_file
+                    .get_bytes(self.abs_index_pos(next_index_to_read), INDEX_SIZE)
+                else {
+                    break;
+                };
+                let key_hash_read = unit.get_i32();
+                let phy_offset_read = unit.get_i64();
+                let time_diff = unit.get_i32();
+                let prev_index_read = unit.get_i32();
+                if time_diff < 0 {
+                    break;
+                }
+
+                let time_read = self.index_header.get_begin_timestamp() + time_diff as i64 * 1000;
+                if key_hash == key_hash_read && time_read >= begin && time_read <= end {
+                    phy_offsets.push(phy_offset_read);
+                }
+
+                if prev_index_read <= INVALID_INDEX
+                    || prev_index_read > index_count
+                    || prev_index_read == next_index_to_read
+                    || time_read < begin
+                {
+                    break;
+                }
+                next_index_to_read = prev_index_read;
             }
+        }
+        self.mapped_file.release();
+    }
+}
 
-            next_index_to_read = prev_index_read;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn put_and_select_survive_reload() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let file_name = temp_dir.path().join("20240101000000000");
+        let file_name = file_name.to_str().unwrap();
+
+        let index_file = IndexFile::new(file_name, 4, 8, 0, 0);
+        assert!(index_file.put_key("topic#a", 100, 1_000_000));
+        assert!(index_file.put_key("topic#b", 200, 1_002_000));
+        assert!(index_file.put_key("topic#a", 300, 1_004_000));
+
+        let mut phy_offsets = Vec::new();
+        index_file.select_phy_offset(&mut phy_offsets, "topic#a", 32, 0, i64::MAX);
+        assert_eq!(phy_offsets, vec![300, 100]);
+
+        phy_offsets.clear();
+        index_file.select_phy_offset(&mut phy_offsets, "topic#a", 32, 1_003_000, i64::MAX);
+        assert_eq!(phy_offsets, vec![300]);
+        index_file.flush();
+        drop(index_file);
+
+        let reloaded = IndexFile::new(file_name, 4, 8, 0, 0);
+        reloaded.load();
+        assert_eq!(reloaded.get_begin_timestamp(), 1_000_000);
+        assert_eq!(reloaded.get_end_timestamp(), 1_004_000);
+        assert_eq!(reloaded.get_end_phy_offset(), 300);
+        phy_offsets.clear();
+        reloaded.select_phy_offset(&mut phy_offsets, "topic#b", 32, 0, i64::MAX);
+        assert_eq!(phy_offsets, vec![200]);
+
+        // the index count starts at one, so 7 units fill a file of 8
+        for offset in 0..4 {
+            assert!(reloaded.put_key("topic#c", 400 + offset, 1_006_000));
         }
+        assert!(reloaded.is_write_full());
+        assert!(!reloaded.put_key("topic#c", 500, 1_008_000));
     }
 }
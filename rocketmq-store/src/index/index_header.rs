@@ -14,14 +14,12 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
-use std::mem;
 use std::sync::atomic::AtomicI32;
 use std::sync::atomic::AtomicI64;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
 use bytes::Buf;
-use bytes::Bytes;
 
 use crate::log_file::mapped_file::default_mapped_file_impl::DefaultMappedFile;
 use crate::log_file::mapped_file::MappedFile;
@@ -114,10 +112,9 @@ impl IndexHeader {
     pub fn set_begin_timestamp(&self, begin_timestamp: i64) {
         self.begin_timestamp
             .store(begin_timestamp, Ordering::SeqCst);
-        self.mapped_file.append_message_offset_length(
-            &Bytes::copy_from_slice(&self.begin_timestamp.load(Ordering::SeqCst).to_be_bytes()),
+        self.mapped_file.put_slice(
+            &self.begin_timestamp.load(Ordering::SeqCst).to_be_bytes(),
             BEGIN_TIMESTAMP_INDEX,
-            mem::size_of::<i64>(),
         );
     }
 
@@ -129,10 +126,9 @@ impl IndexHeader {
     #[inline]
     pub fn set_end_timestamp(&self, end_timestamp: i64) {
         self.end_timestamp.store(end_timestamp, Ordering::SeqCst);
-        self.mapped_file.append_message_offset_length(
-            &Bytes::copy_from_slice(&self.end_timestamp.load(Ordering::SeqCst).to_be_bytes()),
+        self.mapped_file.put_slice(
+            &self.end_timestamp.load(Ordering::SeqCst).to_be_bytes(),
             END_TIMESTAMP_INDEX,
-            mem::size_of::<i64>(),
         );
     }
 
@@ -145,10 +141,9 @@ impl IndexHeader {
     pub fn set_begin_phy_offset(&self, begin_phy_offset: i64) {
         self.begin_phy_offset
             .store(begin_phy_offset, Ordering::SeqCst);
-        self.mapped_file.append_message_offset_length(
-            &Bytes::copy_from_slice(&self.begin_phy_offset.load(Ordering::SeqCst).to_be_bytes()),
+        self.mapped_file.put_slice(
+            &self.begin_phy_offset.load(Ordering::SeqCst).to_be_bytes(),
             BEGIN_PHY_OFFSET_INDEX,
-            mem::size_of::<i64>(),
         );
     }
 
@@ -160,10 +155,9 @@ impl IndexHeader {
     #[inline]
     pub fn set_end_phy_offset(&self, end_phy_offset: i64) {
         self.end_phy_offset.store(end_phy_offset, Ordering::SeqCst);
-        self.mapped_file.append_message_offset_length(
-            &Bytes::copy_from_slice(&self.end_phy_offset.load(Ordering::SeqCst).to_be_bytes()),
+        self.mapped_file.put_slice(
+            &self.end_phy_offset.load(Ordering::SeqCst).to_be_bytes(),
             END_PHY_OFFSET_INDEX,
-            mem::size_of::<i64>(),
         );
     }
 
@@ -175,10 +169,9 @@ impl IndexHeader {
     #[inline]
     pub fn inc_hash_slot_count(&self) {
         self.hash_slot_count.fetch_add(1, Ordering::SeqCst);
-        self.mapped_file.append_message_offset_length(
-            &Bytes::copy_from_slice(&self.hash_slot_count.load(Ordering::SeqCst).to_be_bytes()),
+        self.mapped_file.put_slice(
+            &self.hash_slot_count.load(Ordering::SeqCst).to_be_bytes(),
             HASH_SLOT_COUNT_INDEX,
-            mem::size_of::<i32>(),
         );
     }
 
@@ -190,10 +183,9 @@ impl IndexHeader {
     #[inline]
     pub fn inc_index_count(&self) {
         self.index_count.fetch_add(1, Ordering::SeqCst);
-        self.mapped_file.append_message_offset_length(
-            &Bytes::copy_from_slice(&self.index_count.load(Ordering::SeqCst).to_be_bytes()),
+        self.mapped_file.put_slice(
+            &self.index_count.load(Ordering::SeqCst).to_be_bytes(),
             INDEX_COUNT_INDEX,
-            mem::size_of::<i32>(),
         );
     }
 }
@@ -108,22 +108,31 @@ impl IndexService {
         }
     }
 
+    /// Deletes the index files whose messages all precede `offset`, the min offset of the
+    /// commit log, keeping the last one that is still written.
     #[inline]
     pub fn delete_expired_file(&self, offset: u64) {
-        let mut index_file_list_lock = self.index_file_list.write();
-        if index_file_list_lock.is_empty() {
-            return;
-        }
-        let mut files = Vec::new();
-        for index_file in index_file_list_lock.iter() {
-            if (index_file.get_end_phy_offset() as u64) < offset {
-                files.push(index_file.clone());
-            } else {
-                break;
+        let mut index_file_list = self.index_file_list.write();
+        let expired = index_file_list
+            .iter()
+            .take(index_file_list.len().saturating_sub(1))
+            .take_while(|index_file| (index_file.get_end_phy_offset() as u64) < offset)
+            .count();
+        for index_file in index_file_list.drain(..expired) {
+            if !index_file.destroy(3000) {
+                warn!(
+                    "destroy expired index file {} failed",
+                    index_file.get_file_name()
+                );
             }
         }
-        if !files.is_empty() {
-            index_file_list_lock.retain(|index_file| !files.contains(index_file));
+    }
+
+    /// Flushes every index file, on store shutdown.
+    #[inline]
+    pub fn shutdown(&self) {
+        for index_file in self.index_file_list.read().iter() {
+            index_file.shutdown();
         }
     }
 
@@ -150,37 +159,19 @@ impl IndexService {
         let mut index_last_update_phyoffset = 0;
         let max_num = max_num.min(self.message_store_config.max_msgs_num_batch as i32);
 
+        // from the newest file back to the first one older than `begin`
         let index_file_list = self.index_file_list.read();
-        let mut index = 0;
-        if !index_file_list.is_empty() {
-            for f in index_file_list.iter() {
-                let last_file = index == index_file_list.len() - 1;
-                if last_file {
-                    // Assuming IndexFile has methods to get end timestamp and phy offset
-                    index_last_update_timestamp = f.get_end_timestamp();
-                    index_last_update_phyoffset = f.get_end_phy_offset();
-                }
-
-                // Assuming IndexFile has a method to check if the file's timestamps match the query
-                if f.is_time_matched(begin, end) {
-                    // Assuming IndexFile has a method to select physical offsets based on the query
-                    f.select_phy_offset(
-                        &mut phy_offsets,
-                        build_key(topic, key).as_str(),
-                        max_num as usize,
-                        begin,
-                        end,
-                    );
-                }
-
-                if f.get_begin_timestamp() < begin {
-                    break;
-                }
-
-                if phy_offsets.len() as i32 >= max_num {
-                    break;
-                }
-                index += 1;
+        if let Some(last_file) = index_file_list.last() {
+            index_last_update_timestamp = last_file.get_end_timestamp();
+            index_last_update_phyoffset = last_file.get_end_phy_offset();
+        }
+        let key = build_key(topic, key);
+        for f in index_file_list.iter().rev() {
+            if f.is_time_matched(begin, end) {
+                f.select_phy_offset(&mut phy_offsets, key.as_str(), max_num as usize, begin, end);
+            }
+            if f.get_begin_timestamp() < begin || phy_offsets.len() as i32 >= max_num {
+                break;
             }
         }
         QueryOffsetResult::new(
@@ -211,39 +202,23 @@ impl IndexService {
                     _ => (),
                 }
 
-                let mut index_file_new = None;
-                if let Some(ref uniq_key) = dispatch_request.uniq_key {
-                    index_file_new = self.put_key(
-                        index_file_inner,
-                        dispatch_request,
-                        build_key(topic, uniq_key.as_str()).as_str(),
-                    );
-                    if index_file_new.is_none() {
-                        error!(
-                            "putKey error commitlog {} uniqkey {}",
-                            dispatch_request.commit_log_offset, uniq_key
-                        );
-                        return;
-                    }
-                }
-
-                if !keys.is_empty() {
-                    let keyset = keys.split(MessageConst::KEY_SEPARATOR);
-                    for key in keyset {
-                        if !key.is_empty() {
-                            let index_file = self.put_key(
-                                index_file_new.take().unwrap(),
-                                dispatch_request,
-                                build_key(topic, key).as_str(),
+                let mut index_file = index_file_inner;
+                let uniq_key = dispatch_request.uniq_key.as_ref();
+                let keys = uniq_key
+                    .map(|uniq_key| uniq_key.as_str())
+                    .into_iter()
+                    .chain(keys.split(MessageConst::KEY_SEPARATOR))
+                    .filter(|key| !key.is_empty());
+                for key in keys {
+                    match self.put_key(index_file, dispatch_request, build_key(topic, key).as_str())
+                    {
+                        Some(next) => index_file = next,
+                        None => {
+                            error!(
+                                "putKey error commitlog {} key {}",
+                                dispatch_request.commit_log_offset, key
                             );
-                            if index_file.is_none() {
-                                error!(
-                                    "putKey error commitlog {} uniqkey {}",
-                                    dispatch_request.commit_log_offset,
-                                    dispatch_request.uniq_key.as_ref().unwrap()
-                                );
-                                return;
-                            }
+                            return;
                         }
                     }
                 }
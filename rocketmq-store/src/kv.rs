@@ -15,5 +15,6 @@
  * limitations under the License.
  */
 
+pub(crate) mod compaction_log;
 pub(crate) mod compaction_service;
 pub(crate) mod compaction_store;
@@ -0,0 +1,458 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use bytes::Buf;
+use cheetah_string::CheetahString;
+use rocketmq_common::common::message::message_decoder;
+use rocketmq_common::common::message::MessageTrait;
+use tracing::info;
+use tracing::warn;
+
+use crate::base::get_message_result::GetMessageResult;
+use crate::base::message_status_enum::GetMessageStatus;
+use crate::base::select_result::SelectMappedBufferResult;
+use crate::log_file::mapped_file::default_mapped_file_impl::DefaultMappedFile;
+use crate::log_file::mapped_file::MappedFile;
+
+/// Directory a segment is rewritten in before it replaces the original.
+const COMPACTING_DIR: &str = "compacting";
+
+/// A message of the log.
+#[derive(Clone)]
+struct CompactionUnit {
+    queue_offset: i64,
+    pos: i32,
+    size: i32,
+    key: Option<CheetahString>,
+}
+
+/// A file of messages in commit log format, named by the queue offset of its first message.
+struct Segment {
+    mapped_file: Arc<DefaultMappedFile>,
+    units: Vec<CompactionUnit>,
+}
+
+impl Segment {
+    fn path(dir: &Path, first_queue_offset: i64) -> PathBuf {
+        dir.join(format!("{:020}", first_queue_offset))
+    }
+
+    fn create(path: &Path, file_size: usize) -> Self {
+        Self {
+            mapped_file: Arc::new(DefaultMappedFile::new(
+                CheetahString::from(path.to_string_lossy().as_ref()),
+                file_size as u64,
+            )),
+            units: Vec::new(),
+        }
+    }
+
+    /// Opens a segment and reads back its messages up to the first one that does not decode.
+    fn open(path: &Path, file_size: usize) -> Self {
+        let mut segment = Self::create(path, file_size);
+        let mut pos = 0;
+        while let Some(size) = segment
+            .mapped_file
+            .get_bytes(pos, 4)
+            .map(|mut size| size.get_i32())
+            .filter(|size| *size > 0)
+        {
+            let Some(mut message) = segment.mapped_file.get_bytes(pos, size as usize) else {
+                break;
+            };
+            let Some(message) =
+                message_decoder::decode(&mut message, false, false, false, false, false)
+            else {
+                break;
+            };
+            segment.units.push(CompactionUnit {
+                queue_offset: message.queue_offset(),
+                pos: pos as i32,
+                size,
+                key: message.get_keys(),
+            });
+            pos += size as usize;
+        }
+        let wrote = pos as i32;
+        segment.mapped_file.set_wrote_position(wrote);
+        segment.mapped_file.set_committed_position(wrote);
+        segment.mapped_file.set_flushed_position(wrote);
+        segment
+    }
+
+    fn file_name(&self) -> String {
+        PathBuf::from(self.mapped_file.get_file_name().as_str())
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    /// Index of the first unit at or after `queue_offset`.
+    fn position_of(&self, queue_offset: i64) -> usize {
+        self.units
+            .partition_point(|unit| unit.queue_offset < queue_offset)
+    }
+}
+
+/// The compacted messages of one queue of a compaction topic.
+///
+/// Messages are appended to the last segment, the segments before it are sealed and are the
+/// ones compaction rewrites, keeping for every key only the message with the highest queue
+/// offset of the whole log. Queue offsets are kept, so a compacted queue has holes and a pull
+/// from a removed offset gets the next message that is still there.
+pub(crate) struct CompactionLog {
+    topic: CheetahString,
+    queue_id: i32,
+    dir: PathBuf,
+    segment_size: usize,
+    segments: parking_lot::RwLock<Vec<Segment>>,
+}
+
+impl CompactionLog {
+    pub(crate) fn new(
+        store_path: &str,
+        topic: CheetahString,
+        queue_id: i32,
+        segment_size: usize,
+    ) -> Self {
+        let dir = PathBuf::from(store_path)
+            .join(topic.as_str())
+            .join(queue_id.to_string());
+        Self {
+            topic,
+            queue_id,
+            dir,
+            segment_size,
+            segments: Default::default(),
+        }
+    }
+
+    /// Opens the segments on disk, dropping what an interrupted compaction left.
+    pub(crate) fn load(&self) -> io::Result<()> {
+        let compacting = self.dir.join(COMPACTING_DIR);
+        if compacting.exists() {
+            fs::remove_dir_all(&compacting)?;
+        }
+        let mut files = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let is_segment = entry
+                .file_name()
+                .to_str()
+                .is_some_and(|name| name.parse::<u64>().is_ok());
+            if is_segment && entry.file_type()?.is_file() {
+                files.push((entry.path(), entry.metadata()?.len() as usize));
+            }
+        }
+        files.sort();
+        let mut segments = self.segments.write();
+        for (path, file_size) in files {
+            segments.push(Segment::open(&path, file_size));
+        }
+        info!(
+            "load compaction log of {}:{}, {} segments",
+            self.topic,
+            self.queue_id,
+            segments.len()
+        );
+        Ok(())
+    }
+
+    fn max_offset_of(segments: &[Segment]) -> i64 {
+        segments
+            .iter()
+            .rev()
+            .find_map(|segment| segment.units.last())
+            .map_or(0, |unit| unit.queue_offset + 1)
+    }
+
+    fn min_offset_of(segments: &[Segment]) -> i64 {
+        segments
+            .iter()
+            .find_map(|segment| segment.units.first())
+            .map_or(0, |unit| unit.queue_offset)
+    }
+
+    pub(crate) fn get_max_offset(&self) -> i64 {
+        Self::max_offset_of(&self.segments.read())
+    }
+
+    pub(crate) fn get_min_offset(&self) -> i64 {
+        Self::min_offset_of(&self.segments.read())
+    }
+
+    /// Appends the message at `queue_offset`, ignoring the ones already in the log, so that
+    /// replaying the commit log after a restart does not duplicate them.
+    pub(crate) fn put_message(&self, queue_offset: i64, data: &[u8], key: Option<CheetahString>) {
+        if data.len() > self.segment_size {
+            warn!(
+                "message of {} bytes at {}:{}:{} exceeds the compaction segment size",
+                data.len(),
+                self.topic,
+                self.queue_id,
+                queue_offset
+            );
+            return;
+        }
+        let mut segments = self.segments.write();
+        if queue_offset < Self::max_offset_of(&segments) {
+            return;
+        }
+        let full = segments.last().map_or(true, |segment| {
+            segment.mapped_file.get_wrote_position() as usize + data.len()
+                > segment.mapped_file.get_file_size() as usize
+        });
+        if full {
+            if let Some(segment) = segments.last() {
+                segment.mapped_file.flush(0);
+            }
+            let path = Segment::path(&self.dir, queue_offset);
+            segments.push(Segment::create(&path, self.segment_size));
+        }
+        let segment = segments.last_mut().unwrap();
+        let pos = segment.mapped_file.get_wrote_position();
+        if segment
+            .mapped_file
+            .append_message_offset_length(data, 0, data.len())
+        {
+            segment.units.push(CompactionUnit {
+                queue_offset,
+                pos,
+                size: data.len() as i32,
+                key,
+            });
+        }
+    }
+
+    pub(crate) fn get_message(
+        &self,
+        offset: i64,
+        max_msg_nums: i32,
+        max_total_msg_size: i32,
+    ) -> GetMessageResult {
+        let segments = self.segments.read();
+        let min_offset = Self::min_offset_of(&segments);
+        let max_offset = Self::max_offset_of(&segments);
+        let mut result = GetMessageResult::new();
+        result.set_min_offset(min_offset);
+        result.set_max_offset(max_offset);
+        let (status, next_begin_offset) = if max_offset == 0 {
+            (GetMessageStatus::NoMessageInQueue, 0)
+        } else if offset < min_offset {
+            (GetMessageStatus::OffsetTooSmall, min_offset)
+        } else if offset == max_offset {
+            (GetMessageStatus::OffsetOverflowOne, offset)
+        } else if offset > max_offset {
+            (GetMessageStatus::OffsetOverflowBadly, max_offset)
+        } else {
+            let mut next_begin_offset = offset;
+            'segments: for segment in segments.iter() {
+                for unit in &segment.units[segment.position_of(next_begin_offset)..] {
+                    if result.message_count() >= max_msg_nums
+                        || (result.message_count() > 0
+                            && result.buffer_total_size() + unit.size > max_total_msg_size)
+                    {
+                        break 'segments;
+                    }
+                    if !segment.mapped_file.hold() {
+                        break 'segments;
+                    }
+                    result.add_message(
+                        SelectMappedBufferResult {
+                            start_offset: unit.pos as u64,
+                            size: unit.size,
+                            mapped_file: Some(segment.mapped_file.clone()),
+                            is_in_cache: true,
+                            ..Default::default()
+                        },
+                        unit.queue_offset as u64,
+                        1,
+                    );
+                    next_begin_offset = unit.queue_offset + 1;
+                }
+            }
+            let status = if result.message_count() > 0 {
+                GetMessageStatus::Found
+            } else {
+                GetMessageStatus::NoMatchedMessage
+            };
+            (status, next_begin_offset)
+        };
+        result.set_status(Some(status));
+        result.set_next_begin_offset(next_begin_offset);
+        result
+    }
+
+    /// Rewrites the sealed segments that have messages superseded by a later one with the same
+    /// key, or without key, returns how many messages were removed.
+    pub(crate) fn compact(&self) -> io::Result<usize> {
+        // latest queue offset of every key, the offset map
+        let mut offset_map = HashMap::new();
+        let sealed: Vec<(Arc<DefaultMappedFile>, String, Vec<CompactionUnit>)> = {
+            let segments = self.segments.read();
+            for unit in segments.iter().flat_map(|segment| segment.units.iter()) {
+                if let Some(key) = unit.key.as_ref() {
+                    offset_map.insert(key.clone(), unit.queue_offset);
+                }
+            }
+            segments
+                .iter()
+                .take(segments.len().saturating_sub(1))
+                .map(|segment| {
+                    (
+                        segment.mapped_file.clone(),
+                        segment.file_name(),
+                        segment.units.clone(),
+                    )
+                })
+                .collect()
+        };
+
+        let mut removed = 0;
+        for (mapped_file, file_name, units) in sealed {
+            let kept: Vec<&CompactionUnit> = units
+                .iter()
+                .filter(|unit| {
+                    unit.key
+                        .as_ref()
+                        .is_some_and(|key| offset_map.get(key) == Some(&unit.queue_offset))
+                })
+                .collect();
+            if kept.len() == units.len() {
+                continue;
+            }
+            let path = self.dir.join(&file_name);
+            let replacement = if kept.is_empty() {
+                fs::remove_file(&path)?;
+                None
+            } else {
+                let compacting = self.dir.join(COMPACTING_DIR);
+                fs::create_dir_all(&compacting)?;
+                let compacting_path = compacting.join(&file_name);
+                let file_size: i32 = kept.iter().map(|unit| unit.size).sum();
+                {
+                    let compacted = Segment::create(&compacting_path, file_size as usize);
+                    for unit in &kept {
+                        let data = mapped_file
+                            .get_bytes(unit.pos as usize, unit.size as usize)
+                            .ok_or_else(|| io::Error::other("read compaction segment failed"))?;
+                        compacted
+                            .mapped_file
+                            .append_message_offset_length(&data, 0, data.len());
+                    }
+                    compacted.mapped_file.get_mapped_file().flush()?;
+                }
+                // readers of the original keep its mapping until they release it
+                fs::rename(&compacting_path, &path)?;
+                Some(Segment::open(&path, file_size as usize))
+            };
+            removed += units.len() - kept.len();
+
+            let mut segments = self.segments.write();
+            if let Some(index) = segments
+                .iter()
+                .position(|segment| Arc::ptr_eq(&segment.mapped_file, &mapped_file))
+            {
+                match replacement {
+                    Some(replacement) => segments[index] = replacement,
+                    None => {
+                        segments.remove(index);
+                    }
+                }
+            }
+        }
+        if removed > 0 {
+            info!(
+                "compact {}:{}, {} messages removed",
+                self.topic, self.queue_id, removed
+            );
+        }
+        Ok(removed)
+    }
+
+    pub(crate) fn flush(&self) {
+        if let Some(segment) = self.segments.read().last() {
+            segment.mapped_file.flush(0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use rocketmq_common::common::message::message_ext::MessageExt;
+
+    use super::*;
+
+    fn encode(queue_offset: i64, key: &str) -> Vec<u8> {
+        let mut message = MessageExt::default();
+        message.set_topic(CheetahString::from_static_str("kv-topic"));
+        message.set_body(Bytes::from_static(b"value"));
+        message.set_keys(CheetahString::from(key));
+        message.queue_offset = queue_offset;
+        message.store_size = message_decoder::encode(&message, false).unwrap().len() as i32;
+        message_decoder::encode(&message, false).unwrap().to_vec()
+    }
+
+    fn queue_offsets(compaction_log: &CompactionLog, offset: i64) -> Vec<u64> {
+        compaction_log
+            .get_message(offset, 32, i32::MAX)
+            .message_queue_offset()
+            .clone()
+    }
+
+    #[test]
+    fn compact_keeps_the_latest_message_per_key() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store_path = temp_dir.path().to_str().unwrap();
+        let keys = ["a", "b", "a", "c", "b", "a"];
+        // two messages per segment
+        let segment_size = encode(0, "a").len() * 2;
+        let compaction_log = CompactionLog::new(store_path, "kv-topic".into(), 0, segment_size);
+        for (queue_offset, key) in keys.iter().enumerate() {
+            let data = encode(queue_offset as i64, key);
+            compaction_log.put_message(queue_offset as i64, &data, Some((*key).into()));
+        }
+        // a replayed message is ignored
+        compaction_log.put_message(5, &encode(5, "a"), Some("a".into()));
+        assert_eq!(queue_offsets(&compaction_log, 0), vec![0, 1, 2, 3, 4, 5]);
+
+        // the last segment with 4 and 5 is not compacted
+        assert_eq!(compaction_log.compact().unwrap(), 3);
+        assert_eq!(queue_offsets(&compaction_log, 1), Vec::<u64>::new());
+        assert_eq!(queue_offsets(&compaction_log, 3), vec![3, 4, 5]);
+        assert_eq!(queue_offsets(&compaction_log, 4), vec![4, 5]);
+        assert_eq!(compaction_log.get_min_offset(), 3);
+        assert_eq!(compaction_log.get_max_offset(), 6);
+        let result = compaction_log.get_message(0, 32, i32::MAX);
+        assert_eq!(result.status(), Some(GetMessageStatus::OffsetTooSmall));
+        assert_eq!(result.next_begin_offset(), 3);
+        compaction_log.flush();
+        drop(compaction_log);
+
+        let reloaded = CompactionLog::new(store_path, "kv-topic".into(), 0, segment_size);
+        reloaded.load().unwrap();
+        assert_eq!(queue_offsets(&reloaded, 3), vec![3, 4, 5]);
+        assert_eq!(reloaded.get_message(3, 1, i32::MAX).next_begin_offset(), 4);
+    }
+}
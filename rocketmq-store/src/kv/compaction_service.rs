@@ -14,15 +14,134 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
-#[derive(Default, Clone)]
-pub struct CompactionService {}
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use cheetah_string::CheetahString;
+use rocketmq_common::common::attribute::cleanup_policy::CleanupPolicy;
+use rocketmq_common::common::config::TopicConfig;
+use rocketmq_common::CleanupPolicyUtils::get_delete_policy;
+
+use crate::base::commit_log_dispatcher::CommitLogDispatcher;
+use crate::base::dispatch_request::DispatchRequest;
+use crate::config::message_store_config::MessageStoreConfig;
+use crate::kv::compaction_store::CompactionStore;
+use crate::log_file::commit_log::CommitLog;
+use crate::log_file::mapped_file::MappedFile;
+
+/// Runs the compaction of the [`CompactionStore`] every `compaction_schedule_internal` millis,
+/// and flushes it as often as the consume queues.
+#[derive(Clone)]
+pub struct CompactionService {
+    compaction_store: Arc<CompactionStore>,
+    message_store_config: Arc<MessageStoreConfig>,
+}
 
 impl CompactionService {
-    pub fn load(&mut self, exit_ok: bool) -> bool {
-        println!(
-            "[unimplemented]load compaction service, exit ok: {}",
-            exit_ok
+    pub fn new(
+        compaction_store: Arc<CompactionStore>,
+        message_store_config: Arc<MessageStoreConfig>,
+    ) -> Self {
+        Self {
+            compaction_store,
+            message_store_config,
+        }
+    }
+
+    pub fn load(&mut self, _exit_ok: bool) -> bool {
+        self.compaction_store.load()
+    }
+
+    pub fn start(&self) {
+        let compaction_store = self.compaction_store.clone();
+        let compaction_interval = Duration::from_millis(
+            self.message_store_config
+                .compaction_schedule_internal
+                .max(1) as u64,
+        );
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(compaction_interval);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                let compaction_store = compaction_store.clone();
+                let _ = tokio::task::spawn_blocking(move || compaction_store.compact()).await;
+            }
+        });
+
+        let compaction_store = self.compaction_store.clone();
+        let flush_interval = Duration::from_millis(
+            self.message_store_config
+                .flush_interval_consume_queue
+                .max(1) as u64,
+        );
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(flush_interval);
+            loop {
+                interval.tick().await;
+                compaction_store.flush();
+            }
+        });
+    }
+
+    pub fn shutdown(&self) {
+        self.compaction_store.flush();
+    }
+}
+
+/// Copies the messages of the compaction topics from the commit log to the
+/// [`CompactionStore`], keyed by their keys.
+pub struct CommitLogDispatcherCompaction {
+    compaction_store: Arc<CompactionStore>,
+    commit_log: CommitLog,
+    topic_config_table: Arc<parking_lot::Mutex<HashMap<CheetahString, TopicConfig>>>,
+}
+
+impl CommitLogDispatcherCompaction {
+    pub fn new(
+        compaction_store: Arc<CompactionStore>,
+        commit_log: CommitLog,
+        topic_config_table: Arc<parking_lot::Mutex<HashMap<CheetahString, TopicConfig>>>,
+    ) -> Self {
+        Self {
+            compaction_store,
+            commit_log,
+            topic_config_table,
+        }
+    }
+}
+
+impl CommitLogDispatcher for CommitLogDispatcherCompaction {
+    fn dispatch(&self, dispatch_request: &mut DispatchRequest) {
+        let policy = get_delete_policy(
+            self.topic_config_table
+                .lock()
+                .get(dispatch_request.topic.as_str()),
         );
-        true
+        if policy != CleanupPolicy::COMPACTION {
+            return;
+        }
+        let Some(mut result) = self.commit_log.get_message(
+            dispatch_request.commit_log_offset,
+            dispatch_request.msg_size,
+        ) else {
+            return;
+        };
+        let data = result.mapped_file.as_ref().and_then(|mapped_file| {
+            let pos = result.start_offset - mapped_file.get_file_from_offset();
+            mapped_file.get_bytes(pos as usize, result.size as usize)
+        });
+        result.release();
+        if let Some(data) = data {
+            let key = (!dispatch_request.keys.is_empty()).then(|| dispatch_request.keys.clone());
+            self.compaction_store.put_message(
+                &dispatch_request.topic,
+                dispatch_request.queue_id,
+                dispatch_request.consume_queue_offset,
+                &data,
+                key,
+            );
+        }
     }
 }
@@ -14,29 +14,156 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Arc;
+
 use cheetah_string::CheetahString;
+use tracing::error;
+use tracing::info;
 
 use crate::base::get_message_result::GetMessageResult;
+use crate::base::message_status_enum::GetMessageStatus;
+use crate::config::message_store_config::MessageStoreConfig;
+use crate::kv::compaction_log::CompactionLog;
+use crate::store_path_config_helper::get_store_path_compaction_log;
+
+type CompactionLogTable = HashMap<(CheetahString, i32), Arc<CompactionLog>>;
 
-pub struct CompactionStore;
+/// The compaction logs of the queues of the compaction topics, see [`CompactionLog`].
+pub struct CompactionStore {
+    store_path: String,
+    segment_size: usize,
+    compaction_log_table: parking_lot::RwLock<CompactionLogTable>,
+}
 
 impl CompactionStore {
-    pub fn new() -> Self {
-        CompactionStore
+    pub fn new(message_store_config: &MessageStoreConfig) -> Self {
+        Self {
+            store_path: get_store_path_compaction_log(
+                message_store_config.store_path_root_dir.as_str(),
+            ),
+            segment_size: message_store_config.compaction_mapped_file_size,
+            compaction_log_table: Default::default(),
+        }
+    }
+
+    /// Loads the logs found under `<store>/compaction/compactionLog/<topic>/<queueId>`.
+    pub fn load(&self) -> bool {
+        let Ok(topic_dirs) = fs::read_dir(&self.store_path) else {
+            return true;
+        };
+        let mut compaction_log_table = self.compaction_log_table.write();
+        for topic_dir in topic_dirs.filter_map(Result::ok) {
+            let topic = CheetahString::from(topic_dir.file_name().to_string_lossy().as_ref());
+            let Ok(queue_dirs) = fs::read_dir(topic_dir.path()) else {
+                continue;
+            };
+            for queue_dir in queue_dirs.filter_map(Result::ok) {
+                let Ok(queue_id) = queue_dir.file_name().to_string_lossy().parse::<i32>() else {
+                    continue;
+                };
+                let compaction_log = CompactionLog::new(
+                    self.store_path.as_str(),
+                    topic.clone(),
+                    queue_id,
+                    self.segment_size,
+                );
+                if let Err(e) = compaction_log.load() {
+                    error!(
+                        "load compaction log of {}:{} failed, {}",
+                        topic, queue_id, e
+                    );
+                    return false;
+                }
+                compaction_log_table.insert((topic.clone(), queue_id), Arc::new(compaction_log));
+            }
+        }
+        info!("load {} compaction logs", compaction_log_table.len());
+        true
+    }
+
+    fn find_compaction_log(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+    ) -> Option<Arc<CompactionLog>> {
+        self.compaction_log_table
+            .read()
+            .get(&(topic.clone(), queue_id))
+            .cloned()
+    }
+
+    fn find_or_create_compaction_log(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+    ) -> Arc<CompactionLog> {
+        if let Some(compaction_log) = self.find_compaction_log(topic, queue_id) {
+            return compaction_log;
+        }
+        self.compaction_log_table
+            .write()
+            .entry((topic.clone(), queue_id))
+            .or_insert_with(|| {
+                Arc::new(CompactionLog::new(
+                    self.store_path.as_str(),
+                    topic.clone(),
+                    queue_id,
+                    self.segment_size,
+                ))
+            })
+            .clone()
+    }
+
+    pub fn put_message(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        queue_offset: i64,
+        data: &[u8],
+        key: Option<CheetahString>,
+    ) {
+        self.find_or_create_compaction_log(topic, queue_id)
+            .put_message(queue_offset, data, key);
+    }
+
+    /// Compacts every log, see [`CompactionLog::compact`].
+    pub fn compact(&self) {
+        let compaction_logs: Vec<_> = self.compaction_log_table.read().values().cloned().collect();
+        for compaction_log in compaction_logs {
+            if let Err(e) = compaction_log.compact() {
+                error!("compaction failed, {}", e);
+            }
+        }
+    }
+
+    pub fn flush(&self) {
+        for compaction_log in self.compaction_log_table.read().values() {
+            compaction_log.flush();
+        }
     }
 }
 
-#[allow(unused_variables)]
 impl CompactionStore {
     pub fn get_message(
         &self,
-        group: &CheetahString,
+        _group: &CheetahString,
         topic: &CheetahString,
         queue_id: i32,
         offset: i64,
         max_msg_nums: i32,
         max_total_msg_size: i32,
     ) -> Option<GetMessageResult> {
-        None
+        match self.find_compaction_log(topic, queue_id) {
+            Some(compaction_log) => {
+                Some(compaction_log.get_message(offset, max_msg_nums, max_total_msg_size))
+            }
+            None => {
+                let mut result = GetMessageResult::new();
+                result.set_status(Some(GetMessageStatus::NoMatchedLogicQueue));
+                Some(result)
+            }
+        }
     }
 }
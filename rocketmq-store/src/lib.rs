@@ -21,7 +21,9 @@
 pub mod base;
 pub mod config;
 pub mod consume_queue;
+pub mod dledger;
 pub mod filter;
+pub mod ha;
 pub mod hook;
 mod index;
 mod kv;
@@ -30,9 +32,9 @@ pub(crate) mod message_encoder;
 pub mod message_store;
 pub mod pop;
 mod queue;
-pub(crate) mod services;
 pub mod stats;
 pub mod store;
 pub mod store_path_config_helper;
+pub mod tiered;
 pub mod timer;
 pub mod utils;
@@ -20,18 +20,23 @@ use std::sync::Arc;
 
 use cheetah_string::CheetahString;
 use parking_lot::RwLock;
+use rocketmq_common::common::boundary_type::BoundaryType;
 use rocketmq_common::common::message::message_batch::MessageExtBatch;
 use rocketmq_common::common::message::message_ext::MessageExt;
 use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
 use rocketmq_common::TimeUtils::get_current_millis;
 
+use crate::base::commit_log_dispatcher::CommitLogDispatcher;
 use crate::base::dispatch_request::DispatchRequest;
 use crate::base::get_message_result::GetMessageResult;
+use crate::base::message_arriving_listener::MessageArrivingListener;
 use crate::base::message_result::PutMessageResult;
 use crate::base::query_message_result::QueryMessageResult;
 use crate::base::select_result::SelectMappedBufferResult;
 use crate::config::message_store_config::MessageStoreConfig;
+use crate::dledger::dledger_server::DLedgerRoleChangeHandler;
 use crate::filter::MessageFilter;
+use crate::ha::auto_switch_ha_service::AutoSwitchHAService;
 use crate::hook::put_message_hook::BoxedPutMessageHook;
 use crate::queue::ArcConsumeQueue;
 use crate::stats::broker_stats_manager::BrokerStatsManager;
@@ -40,8 +45,10 @@ use crate::timer::timer_message_store::TimerMessageStore;
 
 pub(crate) mod cold_data_check_service;
 pub mod commit_log;
+pub mod compressed_commit_log;
 pub mod flush_manager_impl;
 pub mod mapped_file;
+pub(crate) mod topic_retention;
 
 pub const MAX_PULL_MSG_SIZE: i32 = 128 * 1024 * 1024;
 
@@ -172,6 +179,22 @@ pub trait RocketMQMessageStore: Sync + 'static {
     /// * `put_message_hook` - The hook to set.
     fn set_put_message_hook(&self, put_message_hook: BoxedPutMessageHook);
 
+    /// Add a commit log dispatcher which runs before the built-in ones, so it can enrich the
+    /// dispatch request, e.g. with the filter bit map saved in the consume queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `dispatcher` - The dispatcher to add.
+    fn add_first_dispatcher(&self, dispatcher: Box<dyn CommitLogDispatcher>);
+
+    /// Add a commit log dispatcher which runs after the built-in ones, so the consume queue and
+    /// index of a message are in place when it is called.
+    ///
+    /// # Arguments
+    ///
+    /// * `dispatcher` - The dispatcher to add.
+    fn add_dispatcher(&self, dispatcher: Box<dyn CommitLogDispatcher>);
+
     /// Get the broker statistics manager.
     ///
     /// # Returns
@@ -206,6 +229,56 @@ pub trait RocketMQMessageStore: Sync + 'static {
     /// The maximum offset in the queue.
     fn get_max_offset_in_queue(&self, topic: &CheetahString, queue_id: i32) -> i64;
 
+    /// Look up the consume queue offset of the first message stored at or after the given
+    /// timestamp, same as [`BoundaryType::Lower`].
+    ///
+    /// # Arguments
+    ///
+    /// * `topic` - The topic name.
+    /// * `queue_id` - The queue identifier.
+    /// * `timestamp` - The store timestamp in milliseconds.
+    ///
+    /// # Returns
+    ///
+    /// The matching offset, or the offset of the last message if every message is older.
+    fn get_offset_in_queue_by_time(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        timestamp: i64,
+    ) -> i64 {
+        self.get_offset_in_queue_by_time_with_boundary(
+            topic,
+            queue_id,
+            timestamp,
+            BoundaryType::Lower,
+        )
+    }
+
+    /// Look up the consume queue offset of a message by its store timestamp.
+    ///
+    /// With [`BoundaryType::Lower`] this is the first message stored at or after `timestamp`,
+    /// with [`BoundaryType::Upper`] the last message stored at or before it. When no message is
+    /// on the requested side, the message closest to `timestamp` is returned instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `topic` - The topic name.
+    /// * `queue_id` - The queue identifier.
+    /// * `timestamp` - The store timestamp in milliseconds.
+    /// * `boundary_type` - Which side of `timestamp` to look on.
+    ///
+    /// # Returns
+    ///
+    /// The matching offset, or the minimum offset in the queue if it is empty.
+    fn get_offset_in_queue_by_time_with_boundary(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        timestamp: i64,
+        boundary_type: BoundaryType,
+    ) -> i64;
+
     /// Get the maximum committed offset in the queue.
     ///
     /// # Arguments
@@ -298,6 +371,30 @@ pub trait RocketMQMessageStore: Sync + 'static {
         batch_size: i32,
     ) -> bool;
 
+    /// Check if the message at a consume offset lies in the cold area of the commit log, i.e.
+    /// is expected to be read from disk instead of page cache.
+    ///
+    /// Always `false` when cold data flow control is disabled or `group` is a system group that
+    /// is never limited.
+    ///
+    /// # Arguments
+    ///
+    /// * `group` - The consumer group reading the message.
+    /// * `topic` - The topic name.
+    /// * `queue_id` - The queue identifier.
+    /// * `consume_offset` - The consume offset.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the message is cold; `false` otherwise.
+    fn is_msg_in_cold_area(
+        &self,
+        group: &CheetahString,
+        topic: &CheetahString,
+        queue_id: i32,
+        consume_offset: i64,
+    ) -> bool;
+
     /// Notify that a message has arrived if necessary.
     ///
     /// # Arguments
@@ -305,6 +402,18 @@ pub trait RocketMQMessageStore: Sync + 'static {
     /// * `dispatch_request` - The dispatch request.
     fn notify_message_arrive_if_necessary(&self, dispatch_request: &mut DispatchRequest);
 
+    /// Set the listener told about messages arriving in a consume queue, used by long polling.
+    ///
+    /// # Arguments
+    ///
+    /// * `message_arriving_listener` - The listener, `None` to remove it.
+    fn set_message_arriving_listener(
+        &mut self,
+        message_arriving_listener: Option<
+            Arc<Box<dyn MessageArrivingListener + Sync + Send + 'static>>,
+        >,
+    );
+
     /// Find the consume queue for a topic and queue identifier.
     ///
     /// # Arguments
@@ -425,6 +534,30 @@ pub trait RocketMQMessageStore: Sync + 'static {
         consume_queue_offset: i64,
     ) -> i64;
 
+    /// Gets the store time of the earliest message still kept in the queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `topic` - The message topic.
+    /// * `queue_id` - The queue ID.
+    ///
+    /// # Returns
+    ///
+    /// The store timestamp of the message at the min offset, or `-1` if the queue is empty.
+    fn get_earliest_message_time_in_queue(&self, topic: &CheetahString, queue_id: i32) -> i64;
+
+    /// Gets the store time of the latest message in the queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `topic` - The message topic.
+    /// * `queue_id` - The queue ID.
+    ///
+    /// # Returns
+    ///
+    /// The store timestamp of the message before the max offset, or `-1` if the queue is empty.
+    fn get_latest_message_time_in_queue(&self, topic: &CheetahString, queue_id: i32) -> i64;
+
     /// Message store runtime information, which should generally contains various statistical
     /// information.
     ///
@@ -469,4 +602,24 @@ pub trait RocketMQMessageStore: Sync + 'static {
     fn remain_how_many_data_to_flush(&self) -> i64;
 
     fn get_message_store_config(&self) -> &MessageStoreConfig;
+
+    /// Set the number of alive replicas of the broker group, this broker included.
+    fn set_alive_replica_num_in_group(&mut self, alive_replica_nums: i32);
+
+    /// Get the number of alive replicas of the broker group, this broker included.
+    fn get_alive_replica_num_in_group(&self) -> i32;
+
+    /// Set the address of the master a slave replicates the commit log from, an empty address
+    /// stops the replication.
+    fn update_ha_master_address(&self, new_addr: &CheetahString);
+
+    /// Set the handler told about the role changes of the DLedger member of the store.
+    fn set_dledger_role_change_handler(&self, handler: Arc<dyn DLedgerRoleChangeHandler>);
+
+    /// The HA service whose role the controller switches, `None` outside of controller mode.
+    fn get_auto_switch_ha_service(&self) -> Option<Arc<AutoSwitchHAService>>;
+
+    /// Apply the store property `key` to the running store, `Ok(false)` if it only takes effect
+    /// after a restart.
+    fn update_runtime_config(&self, key: &str, value: &str) -> Result<bool, String>;
 }
@@ -14,11 +14,48 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
-#[derive(Default)]
-pub struct ColdDataCheckService;
+use crate::config::message_store_config::MessageStoreConfig;
+use crate::utils::store_util::TOTAL_PHYSICAL_MEMORY_SIZE;
+
+/// Tells whether commit log data is expected to be served from page cache.
+///
+/// The page cache holds the most recently written part of the commit log, so data further behind
+/// the max offset than `access_message_in_memory_max_ratio` percent of the physical memory is
+/// taken as cold, a read of it goes to the disk.
+pub struct ColdDataCheckService {
+    hot_window: i64,
+}
 
 impl ColdDataCheckService {
-    pub fn is_data_in_page_cache(&self) -> bool {
-        true
+    pub fn new(message_store_config: &MessageStoreConfig) -> Self {
+        Self::with_memory(
+            *TOTAL_PHYSICAL_MEMORY_SIZE,
+            message_store_config.access_message_in_memory_max_ratio,
+        )
+    }
+
+    fn with_memory(physical_memory: u64, in_memory_max_ratio: usize) -> Self {
+        Self {
+            hot_window: (physical_memory as f64 * (in_memory_max_ratio as f64 / 100.0)) as i64,
+        }
+    }
+
+    /// Whether the data at commit log `offset` is in page cache while the commit log ends at
+    /// `max_offset`.
+    pub fn is_data_in_page_cache(&self, offset: i64, max_offset: i64) -> bool {
+        max_offset - offset <= self.hot_window
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_behind_the_hot_window_is_cold() {
+        let service = ColdDataCheckService::with_memory(1000, 40);
+        assert!(service.is_data_in_page_cache(600, 1000));
+        assert!(service.is_data_in_page_cache(1000, 1000));
+        assert!(!service.is_data_in_page_cache(599, 1000));
     }
 }
@@ -18,17 +18,23 @@
 #![allow(clippy::missing_const_for_thread_local)]
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::mem;
+use std::net::SocketAddr;
+use std::sync::atomic::AtomicI64;
 use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
 
 use bytes::Buf;
 use bytes::Bytes;
 use bytes::BytesMut;
 use cheetah_string::CheetahString;
 use rocketmq_common::common::attribute::cq_type::CQType;
-use rocketmq_common::common::broker::broker_config::BrokerConfig;
 use rocketmq_common::common::broker::broker_role::BrokerRole;
+use rocketmq_common::common::broker::shared_broker_config::SharedBrokerConfig;
 use rocketmq_common::common::config::TopicConfig;
 use rocketmq_common::common::message::message_batch::MessageExtBatch;
 use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
@@ -44,15 +50,18 @@ use rocketmq_common::CRC32Utils::crc32;
 use rocketmq_common::MessageDecoder::string_to_message_properties;
 use rocketmq_common::MessageDecoder::MESSAGE_MAGIC_CODE_POSITION;
 use rocketmq_common::MessageDecoder::MESSAGE_MAGIC_CODE_V2;
+use rocketmq_common::MessageDecoder::MESSAGE_PHYSIC_OFFSET_POSITION;
 use rocketmq_common::MessageDecoder::SYSFLAG_POSITION;
-use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_common::UtilAll::time_millis_to_human_string;
 use rocketmq_rust::ArcMut;
+use tokio::runtime::Handle;
 use tokio::time::Instant;
 use tracing::error;
 use tracing::info;
 use tracing::warn;
 
+use crate::base::adaptive_tuning::StoreTuning;
+use crate::base::allocate_mapped_file_service::AllocateMappedFileService;
 use crate::base::append_message_callback::DefaultAppendMessageCallback;
 use crate::base::commit_log_dispatcher::CommitLogDispatcher;
 use crate::base::dispatch_request::DispatchRequest;
@@ -66,10 +75,19 @@ use crate::base::select_result::SelectMappedBufferResult;
 use crate::base::store_checkpoint::StoreCheckpoint;
 use crate::base::swappable::Swappable;
 use crate::base::topic_queue_lock::TopicQueueLock;
+use crate::base::transient_store_pool::TransientStorePool;
 use crate::config::message_store_config::MessageStoreConfig;
+use crate::config::store_timestamp_mode::StoreTimestampMode;
 use crate::consume_queue::mapped_file_queue::MappedFileQueue;
+use crate::consume_queue::multi_store_paths::MultiStorePaths;
+use crate::dledger::dledger_server::get_store_path_dledger_data;
+use crate::dledger::dledger_server::DLedgerServer;
+use crate::ha::group_transfer_service::GroupTransferService;
 use crate::log_file::cold_data_check_service::ColdDataCheckService;
+use crate::log_file::compressed_commit_log::parse_compression_type;
+use crate::log_file::compressed_commit_log::CompressedCommitLog;
 use crate::log_file::flush_manager_impl::defalut_flush_manager::DefaultFlushManager;
+use crate::log_file::flush_manager_impl::defalut_flush_manager::GroupCommitService;
 use crate::log_file::mapped_file::default_mapped_file_impl::DefaultMappedFile;
 use crate::log_file::mapped_file::MappedFile;
 use crate::message_encoder::message_ext_encoder::MessageExtEncoder;
@@ -185,11 +203,11 @@ pub fn get_message_num(
 pub struct CommitLog {
     mapped_file_queue: MappedFileQueue,
     message_store_config: Arc<MessageStoreConfig>,
-    broker_config: Arc<BrokerConfig>,
+    broker_config: SharedBrokerConfig,
     enabled_append_prop_crc: bool,
     //local_file_message_store: Option<Weak<Mutex<LocalFileMessageStore>>>,
     dispatcher: CommitLogDispatcherDefault,
-    confirm_offset: i64,
+    confirm_offset: Arc<AtomicI64>,
     store_checkpoint: Arc<StoreCheckpoint>,
     append_message_callback: Arc<DefaultAppendMessageCallback>,
     put_message_lock: Arc<tokio::sync::Mutex<()>>,
@@ -197,33 +215,80 @@ pub struct CommitLog {
     topic_config_table: Arc<parking_lot::Mutex<HashMap<CheetahString, TopicConfig>>>,
     consume_queue_store: ConsumeQueueStore,
     flush_manager: Arc<tokio::sync::Mutex<DefaultFlushManager>>,
+    group_commit_service: Option<GroupCommitService>,
     //flush_manager: Arc<parking_lot::Mutex<DefaultFlushManager>>,
     begin_time_in_lock: Arc<AtomicU64>,
     cold_data_check_service: Arc<ColdDataCheckService>,
+    group_transfer_service: Option<Arc<GroupTransferService>>,
+    dledger_server: Option<Arc<DLedgerServer>>,
+    store_host_override: Option<SocketAddr>,
+    compressed_commit_log: Arc<CompressedCommitLog>,
+    /// Store timestamp of the last message put, taken in the put lock.
+    last_store_timestamp: Arc<AtomicI64>,
 }
 
 impl CommitLog {
     pub fn new(
         message_store_config: Arc<MessageStoreConfig>,
-        broker_config: Arc<BrokerConfig>,
+        broker_config: SharedBrokerConfig,
         dispatcher: &CommitLogDispatcherDefault,
         store_checkpoint: Arc<StoreCheckpoint>,
         topic_config_table: Arc<parking_lot::Mutex<HashMap<CheetahString, TopicConfig>>>,
         consume_queue_store: ConsumeQueueStore,
+        store_tuning: Arc<StoreTuning>,
+        transient_store_pool: Option<TransientStorePool>,
+        allocate_mapped_file_service: Option<AllocateMappedFileService>,
     ) -> Self {
         let enabled_append_prop_crc = message_store_config.enabled_append_prop_crc;
-        let store_path = message_store_config.get_store_path_commit_log();
+        let store_path = if message_store_config.enable_dledger_commit_log {
+            get_store_path_dledger_data(&message_store_config)
+        } else {
+            message_store_config.get_store_path_commit_log()
+        };
         let mapped_file_size = message_store_config.mapped_file_size_commit_log;
-        let mapped_file_queue = MappedFileQueue::new(store_path, mapped_file_size as u64, None);
+        let mut mapped_file_queue = MappedFileQueue::new(
+            store_path,
+            mapped_file_size as u64,
+            allocate_mapped_file_service,
+        );
+        if let Some(multi_store_paths) = MultiStorePaths::from_config(&message_store_config) {
+            mapped_file_queue.set_multi_store_paths(multi_store_paths);
+        }
+        if let Some(transient_store_pool) = transient_store_pool {
+            mapped_file_queue.set_transient_store_pool(
+                transient_store_pool,
+                message_store_config.fast_fail_if_no_buffer_in_store_pool,
+            );
+        }
+        let cold_data_check_service = Arc::new(ColdDataCheckService::new(&message_store_config));
+        let flush_manager = DefaultFlushManager::new(
+            message_store_config.clone(),
+            mapped_file_queue.clone(),
+            store_checkpoint.clone(),
+            store_tuning,
+        );
+        let group_commit_service = flush_manager.group_commit_service().cloned();
+        let store_host_override = message_store_config.store_host_override.as_ref().and_then(
+            |store_host| match store_host.parse::<SocketAddr>() {
+                Ok(store_host) => Some(store_host),
+                Err(e) => {
+                    warn!(
+                        "illegal storeHostOverride {}, the messages keep their store host, {}",
+                        store_host, e
+                    );
+                    None
+                }
+            },
+        );
         Self {
-            mapped_file_queue: mapped_file_queue.clone(),
+            mapped_file_queue,
             message_store_config: message_store_config.clone(),
             broker_config,
             enabled_append_prop_crc,
             //local_file_message_store: None,
             dispatcher: dispatcher.clone(),
-            confirm_offset: -1,
-            store_checkpoint: store_checkpoint.clone(),
+            confirm_offset: Arc::new(AtomicI64::new(-1)),
+            store_checkpoint,
             append_message_callback: Arc::new(DefaultAppendMessageCallback::new(
                 message_store_config.clone(),
                 topic_config_table.clone(),
@@ -234,13 +299,17 @@ impl CommitLog {
             )),
             topic_config_table,
             consume_queue_store,
-            flush_manager: Arc::new(tokio::sync::Mutex::new(DefaultFlushManager::new(
-                message_store_config,
-                mapped_file_queue,
-                store_checkpoint,
-            ))),
+            flush_manager: Arc::new(tokio::sync::Mutex::new(flush_manager)),
+            group_commit_service,
             begin_time_in_lock: Arc::new(AtomicU64::new(0)),
-            cold_data_check_service: Arc::new(Default::default()),
+            cold_data_check_service,
+            group_transfer_service: None,
+            dledger_server: None,
+            store_host_override,
+            compressed_commit_log: Arc::new(CompressedCommitLog::new(
+                message_store_config.get_store_path_compressed_commit_log(),
+            )),
+            last_store_timestamp: Arc::new(AtomicI64::new(0)),
         }
     }
 }
@@ -248,8 +317,18 @@ impl CommitLog {
 #[allow(unused_variables)]
 impl CommitLog {
     pub fn load(&mut self) -> bool {
-        let result = self.mapped_file_queue.load();
+        let mut result = self.mapped_file_queue.load();
         self.mapped_file_queue.check_self();
+        if result {
+            let file_offsets = self
+                .mapped_file_queue
+                .get_mapped_files()
+                .read()
+                .iter()
+                .map(|mapped_file| mapped_file.get_file_from_offset() as i64)
+                .collect::<HashSet<_>>();
+            result = self.compressed_commit_log.load(&file_offsets);
+        }
         info!("load commit log {}", if result { "OK" } else { "Failed" });
         result
     }
@@ -266,11 +345,38 @@ impl CommitLog {
         });
     }
 
-    pub fn shutdown(&mut self) {}
+    /// Returns once the flush manager committed and flushed everything appended so far.
+    pub fn shutdown(&mut self) {
+        let handle = Handle::current();
+        let flush_manager = self.flush_manager.clone();
+        let _ = thread::spawn(move || {
+            handle.block_on(async move {
+                flush_manager.lock().await.shutdown();
+            });
+        })
+        .join();
+    }
+
+    /// Puts on a `SYNC_MASTER` wait on `group_transfer_service` for the slaves to ack them.
+    pub(crate) fn set_group_transfer_service(
+        &mut self,
+        group_transfer_service: Arc<GroupTransferService>,
+    ) {
+        self.group_transfer_service = Some(group_transfer_service);
+    }
+
+    /// With the DLedger commit log only the leader of the group takes puts, and a put is acked
+    /// once a majority of the group holds it.
+    pub(crate) fn set_dledger_server(&mut self, dledger_server: Arc<DLedgerServer>) {
+        self.dledger_server = Some(dledger_server);
+    }
 
     pub fn destroy(&mut self) {}
 
     pub fn get_message(&self, offset: i64, size: i32) -> Option<SelectMappedBufferResult> {
+        if self.compressed_commit_log.contains(offset) {
+            return self.compressed_commit_log.get_message(offset, size);
+        }
         let mapped_file_size = self.message_store_config.mapped_file_size_commit_log;
         let mapped_file = self
             .mapped_file_queue
@@ -283,24 +389,58 @@ impl CommitLog {
                     mmap_file.select_mapped_buffer(pos as i32, size);
                 if let Some(ref mut result) = select_mapped_buffer_result {
                     result.mapped_file = Some(mmap_file);
-                    result.is_in_cache = self.cold_data_check_service.is_data_in_page_cache();
+                    result.is_in_cache = self.is_data_in_page_cache(offset);
                 }
                 select_mapped_buffer_result
             }
         }
     }
 
+    /// Whether the data at `offset` is expected to be served from page cache, see
+    /// [`ColdDataCheckService`].
+    pub fn is_data_in_page_cache(&self, offset: i64) -> bool {
+        self.cold_data_check_service
+            .is_data_in_page_cache(offset, self.get_max_offset())
+    }
+
+    pub fn pickup_store_timestamp(&self, offset: i64, size: i32) -> i64 {
+        if offset >= self.get_min_offset() && offset + size as i64 <= self.get_max_offset() {
+            if let Some(mut result) = self.get_message(offset, size) {
+                let mut store_timestamp = read_store_timestamp(result.get_buffer());
+                let needed = result
+                    .get_buffer()
+                    .get(SYSFLAG_POSITION..SYSFLAG_POSITION + 4)
+                    .map(|mut sys_flag| store_timestamp_end(sys_flag.get_i32()));
+                result.release();
+                // a V6 born host moves the store timestamp past the caller's size
+                if let (None, Some(needed)) = (store_timestamp, needed) {
+                    if offset + needed as i64 <= self.get_max_offset() {
+                        if let Some(mut result) = self.get_message(offset, needed as i32) {
+                            store_timestamp = read_store_timestamp(result.get_buffer());
+                            result.release();
+                        }
+                    }
+                }
+                return store_timestamp.unwrap_or(-1);
+            }
+        }
+        -1
+    }
+
     pub fn set_confirm_offset(&mut self, phy_offset: i64) {
-        self.confirm_offset = phy_offset;
+        self.confirm_offset.store(phy_offset, Ordering::Release);
         self.store_checkpoint
             .set_confirm_phy_offset(phy_offset as u64);
     }
 
     pub async fn put_messages(&mut self, mut msg_batch: MessageExtBatch) -> PutMessageResult {
+        if !self.is_dledger_leader_or_none() {
+            return PutMessageResult::new_default(PutMessageStatus::ServiceNotAvailable);
+        }
         msg_batch
             .message_ext_broker_inner
             .message_ext_inner
-            .store_timestamp = get_current_millis() as i64;
+            .store_timestamp = self.store_timestamp_on_arrival(&msg_batch.message_ext_broker_inner);
         let tran_type =
             MessageSysFlag::get_transaction_value(msg_batch.message_ext_broker_inner.sys_flag());
         if MessageSysFlag::TRANSACTION_NOT_TYPE != tran_type {
@@ -316,16 +456,7 @@ impl CommitLog {
             return PutMessageResult::new_default(PutMessageStatus::MessageIllegal);
         }
 
-        //setting ip type:IPV4 OR IPV6, default is ipv4
-        let born_host = msg_batch.message_ext_broker_inner.born_host();
-        if born_host.is_ipv6() {
-            msg_batch.message_ext_broker_inner.with_born_host_v6_flag();
-        }
-
-        let store_host = msg_batch.message_ext_broker_inner.store_host();
-        if store_host.is_ipv6() {
-            msg_batch.message_ext_broker_inner.with_store_host_v6_flag();
-        }
+        self.prepare_hosts(&mut msg_batch.message_ext_broker_inner);
 
         let mut _unlock_mapped_file = None;
         let mut mapped_file = self.mapped_file_queue.get_last_mapped_file();
@@ -334,11 +465,14 @@ impl CommitLog {
         } else {
             0
         };
-        let need_ack_nums = self.message_store_config.in_sync_replicas;
+        let mut need_ack_nums = self.message_store_config.in_sync_replicas;
         let need_handle_ha = self.need_handle_ha(&msg_batch.message_ext_broker_inner);
-        if need_handle_ha && self.broker_config.enable_controller_mode {
-            unimplemented!("controller mode not support HA")
-        } else if need_handle_ha && self.broker_config.enable_slave_acting_master {
+        if need_handle_ha && self.broker_config.get().enable_controller_mode {
+            match self.controller_need_ack_nums(need_ack_nums) {
+                Ok(ack_nums) => need_ack_nums = ack_nums,
+                Err(status) => return PutMessageResult::new_default(status),
+            }
+        } else if need_handle_ha && self.broker_config.get().enable_slave_acting_master {
             unimplemented!("slave acting master not support HA")
         }
         msg_batch.message_ext_broker_inner.version = MessageVersion::V1;
@@ -367,6 +501,7 @@ impl CommitLog {
         self.assign_offset(&mut msg_batch.message_ext_broker_inner);
 
         let lock = self.put_message_lock.lock().await;
+        let dledger_from = self.dledger_server.is_some().then(|| self.get_max_offset());
         self.begin_time_in_lock.store(
             time_utils::get_current_millis(),
             std::sync::atomic::Ordering::Release,
@@ -376,7 +511,10 @@ impl CommitLog {
         msg_batch
             .message_ext_broker_inner
             .message_ext_inner
-            .store_timestamp = time_utils::get_current_millis() as i64;
+            .store_timestamp = self.store_timestamp_in_lock(
+            msg_batch.message_ext_broker_inner.store_timestamp(),
+            time_utils::get_current_millis() as i64,
+        );
 
         if mapped_file.is_none() || mapped_file.as_ref().unwrap().is_full() {
             mapped_file = self
@@ -453,6 +591,7 @@ impl CommitLog {
                 PutMessageResult::new_append_result(PutMessageStatus::UnknownError, Some(result))
             }
         };
+        let put_message_result = self.append_dledger_entries(put_message_result, dledger_from);
         let elapsed_time_in_lock = start_time.elapsed().as_millis() as u64;
         drop(lock);
         self.begin_time_in_lock
@@ -486,9 +625,12 @@ impl CommitLog {
     }
 
     pub async fn put_message(&mut self, mut msg: MessageExtBrokerInner) -> PutMessageResult {
+        if !self.is_dledger_leader_or_none() {
+            return PutMessageResult::new_default(PutMessageStatus::ServiceNotAvailable);
+        }
         // Set the storage time
         if !self.message_store_config.duplication_enable {
-            msg.message_ext_inner.store_timestamp = time_utils::get_current_millis() as i64;
+            msg.message_ext_inner.store_timestamp = self.store_timestamp_on_arrival(&msg);
         }
         // Set the message body CRC (consider the most appropriate setting on the client)
         msg.message_ext_inner.body_crc = crc32(
@@ -513,16 +655,7 @@ impl CommitLog {
             msg.with_version(MessageVersion::V2);
         }
 
-        //setting ip type:IPV4 OR IPV6, default is ipv4
-        let born_host = msg.born_host();
-        if born_host.is_ipv6() {
-            msg.with_born_host_v6_flag();
-        }
-
-        let store_host = msg.store_host();
-        if store_host.is_ipv6() {
-            msg.with_store_host_v6_flag();
-        }
+        self.prepare_hosts(&mut msg);
 
         let topic_queue_key = generate_key(&msg);
 
@@ -536,11 +669,14 @@ impl CommitLog {
         } else {
             0
         };
-        let need_ack_nums = self.message_store_config.in_sync_replicas;
+        let mut need_ack_nums = self.message_store_config.in_sync_replicas;
         let need_handle_ha = self.need_handle_ha(&msg);
-        if need_handle_ha && self.broker_config.enable_controller_mode {
-            unimplemented!("controller mode not support HA")
-        } else if need_handle_ha && self.broker_config.enable_slave_acting_master {
+        if need_handle_ha && self.broker_config.get().enable_controller_mode {
+            match self.controller_need_ack_nums(need_ack_nums) {
+                Ok(ack_nums) => need_ack_nums = ack_nums,
+                Err(status) => return PutMessageResult::new_default(status),
+            }
+        } else if need_handle_ha && self.broker_config.get().enable_slave_acting_master {
             unimplemented!("slave acting master not support HA")
         }
 
@@ -564,13 +700,15 @@ impl CommitLog {
         msg.encoded_buff = Some(encoded_buff);
         let put_message_context = PutMessageContext::new(topic_queue_key);
         let lock = self.put_message_lock.lock().await;
+        let dledger_from = self.dledger_server.is_some().then(|| self.get_max_offset());
         let begin_lock_timestamp = time_utils::get_current_millis();
         self.begin_time_in_lock
             .store(begin_lock_timestamp, std::sync::atomic::Ordering::Release);
         let start_time = Instant::now();
         // Here settings are stored timestamp, in order to ensure an orderly global
         if !self.message_store_config.duplication_enable {
-            msg.message_ext_inner.store_timestamp = begin_lock_timestamp as i64;
+            msg.message_ext_inner.store_timestamp =
+                self.store_timestamp_in_lock(msg.store_timestamp(), begin_lock_timestamp as i64);
         }
 
         if mapped_file.is_none() || mapped_file.as_ref().unwrap().is_full() {
@@ -647,6 +785,7 @@ impl CommitLog {
                 PutMessageResult::new_append_result(PutMessageStatus::UnknownError, Some(result))
             }
         };
+        let put_message_result = self.append_dledger_entries(put_message_result, dledger_from);
         let elapsed_time_in_lock = start_time.elapsed().as_millis() as u64;
         drop(lock);
         self.begin_time_in_lock
@@ -672,6 +811,86 @@ impl CommitLog {
         }
     }
 
+    fn is_dledger_leader_or_none(&self) -> bool {
+        self.dledger_server
+            .as_ref()
+            .map_or(true, |dledger_server| dledger_server.is_leader())
+    }
+
+    /// Makes the range a put appended from `from` on entries of the DLedger leader, the put
+    /// fails when the member lost leadership meanwhile.
+    fn append_dledger_entries(
+        &self,
+        put_message_result: PutMessageResult,
+        from: Option<i64>,
+    ) -> PutMessageResult {
+        let (Some(dledger_server), Some(from)) = (self.dledger_server.as_ref(), from) else {
+            return put_message_result;
+        };
+        if put_message_result.put_message_status() != PutMessageStatus::PutOk {
+            return put_message_result;
+        }
+        let Some(result) = put_message_result.append_message_result() else {
+            return put_message_result;
+        };
+        if dledger_server.append_as_leader(from, result.wrote_offset + result.wrote_bytes as i64) {
+            return put_message_result;
+        }
+        PutMessageResult::new_append_result(
+            PutMessageStatus::ServiceNotAvailable,
+            Some(result.clone()),
+        )
+    }
+
+    /// The store timestamp of a message arriving now, settled in the put lock by
+    /// [`Self::store_timestamp_in_lock`]. A message without a born timestamp arrives now with
+    /// `BornTime`.
+    fn store_timestamp_on_arrival(&self, msg: &MessageExtBrokerInner) -> i64 {
+        match self.message_store_config.store_timestamp_mode {
+            StoreTimestampMode::BornTime if msg.born_timestamp() > 0 => msg.born_timestamp(),
+            _ => time_utils::get_current_millis() as i64,
+        }
+    }
+
+    /// The store timestamp of a message that arrived with `arrival_timestamp`, taken in the put
+    /// lock at `lock_timestamp`. A born timestamp is bounded by the lock time and the store
+    /// timestamp of the message before, so that the store timestamps grow along the commit log
+    /// and the checkpoint does not follow a client clock running ahead.
+    fn store_timestamp_in_lock(&self, arrival_timestamp: i64, lock_timestamp: i64) -> i64 {
+        let store_timestamp = match self.message_store_config.store_timestamp_mode {
+            StoreTimestampMode::LockTime => lock_timestamp,
+            StoreTimestampMode::ArrivalTime => arrival_timestamp,
+            StoreTimestampMode::BornTime => arrival_timestamp
+                .min(lock_timestamp)
+                .max(self.last_store_timestamp.load(Ordering::Acquire)),
+        };
+        self.last_store_timestamp
+            .store(store_timestamp, Ordering::Release);
+        store_timestamp
+    }
+
+    /// Overrides the store host with `store_host_override`, then sets the IPv6 flags after the
+    /// born and store hosts actually stored, a re-put message may carry the flags of another
+    /// broker.
+    fn prepare_hosts(&self, msg: &mut MessageExtBrokerInner) {
+        if let Some(store_host) = self.store_host_override {
+            msg.message_ext_inner.set_store_host(store_host);
+        }
+        let mut sys_flag = msg.sys_flag();
+        //setting ip type:IPV4 OR IPV6, default is ipv4
+        if msg.born_host().is_ipv6() {
+            sys_flag |= MessageSysFlag::BORNHOST_V6_FLAG;
+        } else {
+            sys_flag &= !MessageSysFlag::BORNHOST_V6_FLAG;
+        }
+        if msg.store_host().is_ipv6() {
+            sys_flag |= MessageSysFlag::STOREHOSTADDRESS_V6_FLAG;
+        } else {
+            sys_flag &= !MessageSysFlag::STOREHOSTADDRESS_V6_FLAG;
+        }
+        msg.message_ext_inner.set_sys_flag(sys_flag);
+    }
+
     fn increase_offset(&self, msg: &MessageExtBrokerInner, message_num: i16) {
         let tran_type = MessageSysFlag::get_transaction_value(msg.sys_flag());
         if MessageSysFlag::TRANSACTION_NOT_TYPE == tran_type
@@ -743,13 +962,36 @@ impl CommitLog {
         put_message_result: &AppendMessageResult,
         need_ack_nums: u32,
     ) -> PutMessageStatus {
-        if need_ack_nums <= 1 {
+        let next_offset = put_message_result.wrote_offset + put_message_result.wrote_bytes as i64;
+        if let Some(dledger_server) = self.dledger_server.as_ref() {
+            return dledger_server
+                .wait_for_commit(
+                    next_offset,
+                    Duration::from_millis(self.message_store_config.sync_flush_timeout),
+                )
+                .await;
+        }
+        let Some(group_transfer_service) = self.group_transfer_service.as_ref() else {
             return PutMessageStatus::PutOk;
+        };
+        if self.broker_config.get().enable_controller_mode {
+            // the sync state set was checked by the put already
+            if need_ack_nums <= 1 {
+                return PutMessageStatus::PutOk;
+            }
+        } else if !group_transfer_service.is_slave_ok(
+            next_offset,
+            self.message_store_config.ha_max_gap_not_in_sync as i64,
+        ) {
+            return PutMessageStatus::SlaveNotAvailable;
         }
-
-        //HA service to do unimplemented
-
-        PutMessageStatus::PutOk
+        group_transfer_service
+            .wait_for_transfer(
+                next_offset,
+                need_ack_nums,
+                Duration::from_millis(self.message_store_config.sync_flush_timeout),
+            )
+            .await
     }
 
     async fn handle_disk_flush(
@@ -757,6 +999,17 @@ impl CommitLog {
         put_message_result: &AppendMessageResult,
         msg: &MessageExtBrokerInner,
     ) -> PutMessageStatus {
+        if let Some(group_commit_service) = self.group_commit_service.as_ref() {
+            if msg.is_wait_store_msg_ok() {
+                // waits without the flush manager, so that concurrent puts are flushed as a group
+                return group_commit_service
+                    .put_request(
+                        put_message_result.wrote_offset + put_message_result.wrote_bytes as i64,
+                        self.message_store_config.sync_flush_timeout,
+                    )
+                    .await;
+            }
+        }
         self.flush_manager
             .lock()
             .await
@@ -764,7 +1017,27 @@ impl CommitLog {
             .await
     }
 
+    /// In controller mode a put needs `min_in_sync_replicas` replicas in sync, and has to be
+    /// acked by all of them with `all_ack_in_sync_state_set`.
+    fn controller_need_ack_nums(&self, need_ack_nums: u32) -> Result<u32, PutMessageStatus> {
+        let Some(group_transfer_service) = self.group_transfer_service.as_ref() else {
+            return Ok(need_ack_nums);
+        };
+        let in_sync_replicas_nums = group_transfer_service.in_sync_replicas_nums();
+        if in_sync_replicas_nums < self.message_store_config.min_in_sync_replicas {
+            return Err(PutMessageStatus::InSyncReplicasNotEnough);
+        }
+        if self.message_store_config.all_ack_in_sync_state_set {
+            return Ok(in_sync_replicas_nums as u32);
+        }
+        Ok(need_ack_nums)
+    }
+
     fn need_handle_ha(&self, msg_inner: &MessageExtBrokerInner) -> bool {
+        if self.dledger_server.is_some() {
+            // every put on the DLedger commit log waits for the majority of the group
+            return true;
+        }
         if !msg_inner.is_wait_store_msg_ok() {
             /*
              No need to sync messages that special config to extra broker slaves.
@@ -775,6 +1048,13 @@ impl CommitLog {
         if self.message_store_config.duplication_enable {
             return false;
         }
+        if self.broker_config.get().enable_controller_mode {
+            // the role is assigned by the controller at runtime
+            return self
+                .group_transfer_service
+                .as_ref()
+                .is_some_and(|service| service.is_master());
+        }
         if BrokerRole::SyncMaster != self.message_store_config.broker_role {
             // No need to check ha in async or slave broker
             return false;
@@ -785,7 +1065,7 @@ impl CommitLog {
 
     fn on_commit_log_dispatch(
         &mut self,
-        request: &DispatchRequest,
+        request: &mut DispatchRequest,
         do_dispatch: bool,
         is_recover: bool,
         is_file_end: bool,
@@ -795,11 +1075,13 @@ impl CommitLog {
         }
     }
 
+    /// Whether the message is dispatched into the queues listed in `INNER_MULTI_DISPATCH`
+    /// besides its own, which never holds for a retry topic.
     pub fn is_multi_dispatch_msg(msg_inner: &MessageExtBrokerInner) -> bool {
         msg_inner
             .property(MessageConst::PROPERTY_INNER_MULTI_DISPATCH)
             .is_some_and(|s| !s.is_empty())
-            && msg_inner
+            && !msg_inner
                 .topic()
                 .starts_with(mix_all::RETRY_GROUP_TOPIC_PREFIX)
     }
@@ -839,7 +1121,7 @@ impl CommitLog {
                     break;
                 }
                 let mut msg_bytes = msg.unwrap();
-                let dispatch_request = check_message_and_return_size(
+                let mut dispatch_request = check_message_and_return_size(
                     &mut msg_bytes,
                     check_crc_on_recover,
                     check_dup_info,
@@ -850,12 +1132,12 @@ impl CommitLog {
                 if dispatch_request.success && dispatch_request.msg_size > 0 {
                     last_valid_msg_phy_offset = process_offset + mapped_file_offset;
                     mapped_file_offset += dispatch_request.msg_size as u64;
-                    self.on_commit_log_dispatch(&dispatch_request, do_dispatch, true, false);
+                    self.on_commit_log_dispatch(&mut dispatch_request, do_dispatch, true, false);
                 } else if dispatch_request.success && dispatch_request.msg_size == 0 {
                     // Come the end of the file, switch to the next file Since the
                     // return 0 representatives met last hole,
                     // this can not be included in truncate offset
-                    self.on_commit_log_dispatch(&dispatch_request, do_dispatch, true, true);
+                    self.on_commit_log_dispatch(&mut dispatch_request, do_dispatch, true, true);
                     index += 1;
                     if index >= mapped_files_inner.len() {
                         info!(
@@ -882,8 +1164,24 @@ impl CommitLog {
                 }
             }
             process_offset += mapped_file_offset;
-            if broker_config.enable_controller_mode {
-                unimplemented!();
+            if broker_config.get().enable_controller_mode {
+                let confirm_offset = self.get_confirm_offset();
+                let min_phy_offset = self.get_min_offset();
+                if confirm_offset < min_phy_offset {
+                    error!(
+                        "confirmOffset {} is less than minPhyOffset {}, correct confirmOffset to \
+                         minPhyOffset",
+                        confirm_offset, min_phy_offset
+                    );
+                    self.set_confirm_offset(min_phy_offset);
+                } else if confirm_offset > process_offset as i64 {
+                    error!(
+                        "confirmOffset {} is larger than processOffset {}, correct confirmOffset \
+                         to processOffset",
+                        confirm_offset, process_offset
+                    );
+                    self.set_confirm_offset(process_offset as i64);
+                }
             } else {
                 self.set_confirm_offset(last_valid_msg_phy_offset as i64);
             }
@@ -938,14 +1236,48 @@ impl CommitLog {
 
     //Fetch and compute the newest confirmOffset.
     pub fn get_confirm_offset(&self) -> i64 {
-        if self.broker_config.enable_controller_mode {
-            unimplemented!()
-        } else if self.broker_config.duplication_enable {
-            return self.confirm_offset;
+        if let Some(dledger_server) = self.dledger_server.as_ref() {
+            return dledger_server.get_committed_pos();
+        }
+        if self.broker_config.get().enable_controller_mode {
+            // a master confirms what the sync state set acked, a slave what its master confirmed
+            if let Some(group_transfer_service) = self
+                .group_transfer_service
+                .as_ref()
+                .filter(|service| service.is_master())
+            {
+                return group_transfer_service.compute_confirm_offset(self.get_max_offset());
+            }
+            let confirm_offset = self.confirm_offset.load(Ordering::Acquire);
+            return if confirm_offset < 0 {
+                self.store_checkpoint.confirm_phy_offset() as i64
+            } else {
+                confirm_offset
+            };
+        } else if self.broker_config.get().duplication_enable {
+            return self.confirm_offset.load(Ordering::Acquire);
         }
         self.get_max_offset()
     }
 
+    /// Cuts the commit log and the consume queues off at `offset`, the part past it was never
+    /// confirmed.
+    pub fn truncate_dirty_files(&mut self, offset: i64) {
+        if offset >= self.get_max_offset() {
+            return;
+        }
+        warn!(
+            "truncate commit log from {} to {}",
+            self.get_max_offset(),
+            offset
+        );
+        self.mapped_file_queue.truncate_dirty_files(offset);
+        self.consume_queue_store.truncate_dirty(offset);
+        if self.get_confirm_offset() > offset {
+            self.set_confirm_offset(offset);
+        }
+    }
+
     pub async fn recover_abnormally(
         &mut self,
         max_phy_offset_of_consume_queue: i64,
@@ -967,6 +1299,7 @@ impl CommitLog {
                     &self.message_store_config,
                     mapped_file,
                     &self.store_checkpoint,
+                    max_phy_offset_of_consume_queue,
                 ) {
                     break;
                 }
@@ -993,7 +1326,7 @@ impl CommitLog {
                     break;
                 }
                 let mut msg_bytes = msg.unwrap();
-                let dispatch_request = check_message_and_return_size(
+                let mut dispatch_request = check_message_and_return_size(
                     &mut msg_bytes,
                     check_crc_on_recover,
                     check_dup_info,
@@ -1006,13 +1339,13 @@ impl CommitLog {
                     mapped_file_offset += dispatch_request.msg_size as u64;
 
                     if self.message_store_config.duplication_enable
-                        || self.broker_config.enable_controller_mode
+                        || self.broker_config.get().enable_controller_mode
                     {
                         if dispatch_request.commit_log_offset + size as i64
                             <= self.get_confirm_offset()
                         {
                             self.on_commit_log_dispatch(
-                                &dispatch_request,
+                                &mut dispatch_request,
                                 do_dispatch,
                                 true,
                                 false,
@@ -1021,13 +1354,18 @@ impl CommitLog {
                                 dispatch_request.commit_log_offset as u64 + size as u64;
                         }
                     } else {
-                        self.on_commit_log_dispatch(&dispatch_request, do_dispatch, true, false);
+                        self.on_commit_log_dispatch(
+                            &mut dispatch_request,
+                            do_dispatch,
+                            true,
+                            false,
+                        );
                     }
                 } else if dispatch_request.success && dispatch_request.msg_size == 0 {
                     // Come the end of the file, switch to the next file Since the
                     // return 0 representatives met last hole,
                     // this can not be included in truncate offset
-                    self.on_commit_log_dispatch(&dispatch_request, do_dispatch, true, true);
+                    self.on_commit_log_dispatch(&mut dispatch_request, do_dispatch, true, true);
                     index += 1;
                     if index >= mapped_files_inner.len() {
                         info!(
@@ -1058,12 +1396,8 @@ impl CommitLog {
             // this.getMessageStore().finishCommitLogDispatch();
 
             process_offset += mapped_file_offset;
-            if broker_config.enable_controller_mode {
-                println!(
-                    "TODO: finishCommitLogDispatch:{}",
-                    last_confirm_valid_msg_phy_offset
-                );
-                unimplemented!();
+            if broker_config.get().enable_controller_mode {
+                self.set_confirm_offset(last_confirm_valid_msg_phy_offset as i64);
             } else {
                 self.set_confirm_offset(last_valid_msg_phy_offset as i64);
             }
@@ -1099,7 +1433,39 @@ impl CommitLog {
         self.mapped_file_queue.get_max_offset()
     }
 
+    /// Taken by every append to the commit log.
+    pub fn put_message_lock(&self) -> &Arc<tokio::sync::Mutex<()>> {
+        &self.put_message_lock
+    }
+
+    /// Appends the commit log data a slave received from its master at `start_offset`.
+    ///
+    /// The data never spans two files, as the master pushes the files one by one.
+    pub async fn append_data(&mut self, start_offset: i64, data: &[u8]) -> bool {
+        let _lock = self.put_message_lock.lock().await;
+        let Some(mapped_file) = self
+            .mapped_file_queue
+            .get_last_mapped_file_mut_start_offset(start_offset as u64, true)
+        else {
+            error!(
+                "create mapped file error while append data at offset {}",
+                start_offset
+            );
+            return false;
+        };
+        mapped_file.append_message_bytes(data)
+    }
+
+    /// The directories the files are spread over, `None` when they are all in one.
+    pub fn get_multi_store_paths(&self) -> Option<&MultiStorePaths> {
+        self.mapped_file_queue.get_multi_store_paths()
+    }
+
     pub fn get_min_offset(&self) -> i64 {
+        // the compressed files hold the oldest part of the commit log
+        if let Some(min_offset) = self.compressed_commit_log.get_min_offset() {
+            return min_offset;
+        }
         match self.mapped_file_queue.get_first_mapped_file() {
             None => -1,
             Some(mapped_file) => {
@@ -1121,11 +1487,18 @@ impl CommitLog {
         self.get_data_with_option(offset, offset == 0)
     }
 
+    /// The data from `offset` to the end of its file. Data in a compressed file is decompressed,
+    /// up to `max_message_size` bytes of it, so that it holds at least one whole message.
     pub fn get_data_with_option(
         &self,
         offset: i64,
         return_first_on_not_found: bool,
     ) -> Option<SelectMappedBufferResult> {
+        if self.compressed_commit_log.contains(offset) {
+            return self
+                .compressed_commit_log
+                .get_data(offset, self.message_store_config.max_message_size);
+        }
         let mapped_file_size = self.message_store_config.mapped_file_size_commit_log as i64;
         let mapped_file = self
             .mapped_file_queue
@@ -1146,6 +1519,119 @@ impl CommitLog {
         self.mapped_file_queue.check_self();
     }
 
+    /// Deletes the files past the millis `expire_timestamp` gives for them, see
+    /// [`MappedFileQueue::delete_expired_file_by_time`].
+    ///
+    /// `expire_timestamp` takes the first offset and the last modification millis of a file. The
+    /// compressed files go first, the other files only once they are all deleted.
+    pub fn delete_expired_file(
+        &self,
+        expire_timestamp: &dyn Fn(i64, i64) -> i64,
+        delete_files_interval: u64,
+        interval_forcibly: u64,
+        clean_immediately: bool,
+        delete_file_batch_max: usize,
+        can_delete: &dyn Fn(&str) -> bool,
+    ) -> i32 {
+        let deleted = self.compressed_commit_log.delete_expired_files(
+            expire_timestamp,
+            clean_immediately,
+            delete_file_batch_max,
+            can_delete,
+        );
+        if deleted >= delete_file_batch_max.max(1)
+            || self.compressed_commit_log.get_min_offset().is_some()
+        {
+            return deleted as i32;
+        }
+        deleted as i32
+            + self.mapped_file_queue.delete_expired_file_by_time(
+                &|mapped_file: &DefaultMappedFile| {
+                    expire_timestamp(
+                        mapped_file.get_file_from_offset() as i64,
+                        mapped_file.get_last_modified_timestamp() as i64,
+                    )
+                },
+                delete_files_interval,
+                interval_forcibly,
+                clean_immediately,
+                delete_file_batch_max.max(1) - deleted,
+                can_delete,
+            )
+    }
+
+    /// Compresses, oldest first, the sealed files last modified more than
+    /// `commit_log_compression_after_hours` ago that end before `max_offset`, and deletes them
+    /// once compressed, see [`CompressedCommitLog`]. Stops at the first file `can_compress`
+    /// refuses, so the compressed files stay ahead of the others. Returns how many files were
+    /// compressed.
+    pub fn compress_sealed_files(
+        &self,
+        max_offset: i64,
+        can_compress: &dyn Fn(&str) -> bool,
+    ) -> usize {
+        let message_store_config = &self.message_store_config;
+        let Some(compression_type) =
+            parse_compression_type(message_store_config.commit_log_compression_type.as_str())
+        else {
+            warn!(
+                "unknown commitLogCompressionType {}, the commit log is not compressed",
+                message_store_config.commit_log_compression_type
+            );
+            return 0;
+        };
+        let compress_before = time_utils::get_current_millis().saturating_sub(
+            message_store_config.commit_log_compression_after_hours * 60 * 60 * 1000,
+        );
+        let mapped_files = self.mapped_file_queue.get_mapped_files().read().clone();
+        let mut compressed = 0;
+        for mapped_file in mapped_files
+            .iter()
+            .take(mapped_files.len().saturating_sub(1))
+        {
+            let file_from_offset = mapped_file.get_file_from_offset() as i64;
+            if !self.compressed_commit_log.is_compressed(file_from_offset) {
+                if !mapped_file.is_full()
+                    || file_from_offset + mapped_file.get_file_size() as i64 > max_offset
+                    || mapped_file.get_last_modified_timestamp() > compress_before
+                    || !can_compress(mapped_file.get_file_name().as_str())
+                {
+                    break;
+                }
+                if let Err(e) = self.compressed_commit_log.compress(
+                    mapped_file,
+                    compression_type,
+                    message_store_config.commit_log_compression_block_size,
+                ) {
+                    warn!(
+                        "compress commit log file {} failed: {}",
+                        mapped_file.get_file_name(),
+                        e
+                    );
+                    break;
+                }
+                compressed += 1;
+            }
+            // the reads of the file go to the compressed file from now on
+            if !self.mapped_file_queue.delete_mapped_file(
+                mapped_file,
+                message_store_config.destroy_mapped_file_interval_forcibly as u64,
+            ) {
+                warn!(
+                    "the compressed commit log file {} is still used, delete it later",
+                    mapped_file.get_file_name()
+                );
+                break;
+            }
+        }
+        compressed
+    }
+
+    pub fn retry_delete_first_file(&self, interval_forcibly: u64) -> bool {
+        self.mapped_file_queue
+            .retry_delete_first_file(interval_forcibly)
+    }
+
     pub fn lock_time_mills(&self) -> i64 {
         let begin = self
             .begin_time_in_lock
@@ -1170,6 +1656,32 @@ impl CommitLog {
     }
 }
 
+/// Returns the offset just past the store timestamp of a message with the given sys flag.
+fn store_timestamp_end(sys_flag: i32) -> usize {
+    let born_host_length = if sys_flag & MessageSysFlag::BORNHOST_V6_FLAG == 0 {
+        8
+    } else {
+        20
+    };
+    4 + 4 + 4 + 4 + 4 + 8 + 8 + 4 + 8 + born_host_length + 8
+}
+
+/// Reads the store timestamp of the message at the head of `buffer`, if the buffer holds it.
+fn read_store_timestamp(buffer: &[u8]) -> Option<i64> {
+    let mut sys_flag = buffer.get(SYSFLAG_POSITION..SYSFLAG_POSITION + 4)?;
+    let end = store_timestamp_end(sys_flag.get_i32());
+    buffer
+        .get(end - 8..end)
+        .map(|mut timestamp| timestamp.get_i64())
+}
+
+/// Decodes the message at the head of `bytes` for dispatching, `msg_size` is `0` at the blank
+/// end of a file.
+///
+/// A message cut short by a torn write, or failing the CRC check with `check_crc` or the
+/// `DUP_INFO` check with `check_dup_info`, is not `success`, with the `msg_size` it claims when
+/// that is known so the callers can step over it. The body is only read with `read_body`, its
+/// CRC is only checked then.
 pub fn check_message_and_return_size(
     bytes: &mut Bytes,
     check_crc: bool,
@@ -1177,6 +1689,15 @@ pub fn check_message_and_return_size(
     read_body: bool,
     message_store_config: &Arc<MessageStoreConfig>,
 ) -> DispatchRequest {
+    let broken = |msg_size: i32| DispatchRequest {
+        msg_size,
+        success: false,
+        ..Default::default()
+    };
+    if bytes.remaining() < 8 {
+        return broken(-1);
+    }
+    let message = bytes.clone();
     let total_size = bytes.get_i32();
     let magic_code = bytes.get_i32();
     if magic_code == MESSAGE_MAGIC_CODE || magic_code == MESSAGE_MAGIC_CODE_V2 {
@@ -1191,19 +1712,33 @@ pub fn check_message_and_return_size(
             "found a illegal magic code 0x{}",
             format!("{:X}", magic_code),
         );
-        return DispatchRequest {
-            msg_size: -1,
-            success: false,
-            ..Default::default()
-        };
+        return broken(-1);
     }
     let message_version = MessageVersion::value_of_magic_code(magic_code).unwrap();
+    // the fixed fields in front of the sys flag
+    if total_size < 0 || message.len() < total_size as usize || bytes.remaining() < 32 {
+        warn!(
+            "found a half message, totalSize={}, readable={}",
+            total_size,
+            message.len()
+        );
+        return broken(-1);
+    }
     let body_crc = bytes.get_i32();
     let queue_id = bytes.get_i32();
     let flag = bytes.get_i32();
     let queue_offset = bytes.get_i64();
     let physic_offset = bytes.get_i64();
     let sys_flag = bytes.get_i32();
+    // the fixed fields behind the sys flag, the topic and properties lengths included
+    let fixed_size = MessageExtEncoder::cal_msg_length(message_version, sys_flag, 0, 0, 0);
+    if total_size < fixed_size {
+        warn!(
+            "found a half message, totalSize={}, fixed fields {}",
+            total_size, fixed_size
+        );
+        return broken(-1);
+    }
     let born_time_stamp = bytes.get_i64();
 
     let born_host = if sys_flag & MessageSysFlag::BORNHOST_V6_FLAG == 0 {
@@ -1222,18 +1757,24 @@ pub fn check_message_and_return_size(
     let reconsume_times = bytes.get_i32();
     let prepared_transaction_offset = bytes.get_i64();
     let body_len = bytes.get_i32();
+    // the body, topic and properties are bounded by the size of the message
+    let mut variable_size = (total_size - fixed_size) as usize;
+    if body_len < 0 || body_len as usize > variable_size {
+        warn!(
+            "found a half message, totalSize={}, bodyLen={}",
+            total_size, body_len
+        );
+        return broken(-1);
+    }
+    variable_size -= body_len as usize;
     if body_len > 0 {
         if read_body {
             let body = bytes.copy_to_bytes(body_len as usize);
             if check_crc && !message_store_config.force_verify_prop_crc {
                 let crc = crc32(body.as_ref());
                 if crc != body_crc as u32 {
-                    warn!("CRC check failed. bodyCRC={}, currentCRC={}", crc, body_crc);
-                    return DispatchRequest {
-                        msg_size: -1,
-                        success: false,
-                        ..Default::default()
-                    };
+                    warn!("CRC check failed. bodyCRC={}, currentCRC={}", body_crc, crc);
+                    return broken(total_size);
                 }
             }
         } else {
@@ -1241,13 +1782,28 @@ pub fn check_message_and_return_size(
         }
     }
     let topic_len = message_version.get_topic_length(bytes);
+    if topic_len > variable_size {
+        warn!(
+            "found a half message, totalSize={}, topicLen={}",
+            total_size, topic_len
+        );
+        return broken(-1);
+    }
+    variable_size -= topic_len;
     let topic_bytes = bytes.copy_to_bytes(topic_len);
     let topic =
         CheetahString::from_string(String::from_utf8_lossy(topic_bytes.as_ref()).to_string());
     let properties_length = bytes.get_i16();
+    if properties_length < 0 || properties_length as usize > variable_size {
+        warn!(
+            "found a half message, totalSize={}, propertiesLength={}",
+            total_size, properties_length
+        );
+        return broken(-1);
+    }
     let (tags_code, keys, uniq_key, properties_map) = if properties_length > 0 {
         let properties = bytes.copy_to_bytes(properties_length as usize);
-        let properties_content = String::from_utf8_lossy(topic_bytes.as_ref()).to_string();
+        let properties_content = String::from_utf8_lossy(properties.as_ref()).to_string();
         //need to optimize
         let properties_map =
             string_to_message_properties(Some(&CheetahString::from_string(properties_content)));
@@ -1259,21 +1815,13 @@ pub fn check_message_and_return_size(
             let dup_info = properties_map.get(MessageConst::DUP_INFO).cloned();
             if dup_info.is_none() {
                 warn!("DupInfo in properties check failed. dupInfo=null");
-                return DispatchRequest {
-                    msg_size: -1,
-                    success: false,
-                    ..Default::default()
-                };
+                return broken(-1);
             } else {
                 let content = dup_info.unwrap();
                 let vec = content.split('_').collect::<Vec<&str>>();
                 if vec.len() != 2 {
                     warn!("DupInfo in properties check failed. dupInfo={}", content);
-                    return DispatchRequest {
-                        msg_size: -1,
-                        success: false,
-                        ..Default::default()
-                    };
+                    return broken(-1);
                 }
             }
         }
@@ -1292,9 +1840,26 @@ pub fn check_message_and_return_size(
         (0, CheetahString::new(), None, HashMap::new())
     };
 
-    if check_crc && !message_store_config.force_verify_prop_crc {
-        let _expected_crc = -1i32;
-        if !properties_map.is_empty() {}
+    if check_crc && message_store_config.force_verify_prop_crc {
+        // the CRC property covers the message in front of it, its digits are the lowest first
+        let expected_crc = properties_map.get(MessageConst::PROPERTY_CRC32).map(|crc| {
+            crc.chars().rev().fold(0u64, |crc, digit| {
+                crc * 10 + digit.to_digit(10).unwrap_or(0) as u64
+            })
+        });
+        let check_size = total_size - CRC32_RESERVED_LEN;
+        let Some(expected_crc) = expected_crc.filter(|_| check_size >= 0) else {
+            warn!("the message has no CRC property to check");
+            return broken(total_size);
+        };
+        let crc = crc32(&message[..check_size as usize]);
+        if crc as u64 != expected_crc {
+            warn!(
+                "CRC check failed. propertyCRC={}, currentCRC={}",
+                expected_crc, crc
+            );
+            return broken(total_size);
+        }
     }
 
     let read_length = MessageExtEncoder::cal_msg_length(
@@ -1358,10 +1923,13 @@ fn set_batch_size_if_needed(
     }
 }
 
+/// Whether the abnormal recovery may start from `mapped_file`, the messages in front of its
+/// first one being dispatched as the checkpoint or the RocksDB consume queues tell.
 fn is_mapped_file_matched_recover(
     message_store_config: &Arc<MessageStoreConfig>,
     mapped_file: &DefaultMappedFile,
     store_checkpoint: &StoreCheckpoint,
+    max_phy_offset_of_consume_queue: i64,
 ) -> bool {
     let magic_code = mapped_file
         .get_bytes(MESSAGE_MAGIC_CODE_POSITION, mem::size_of::<i32>())
@@ -1373,7 +1941,19 @@ fn is_mapped_file_matched_recover(
         return false;
     }
     if message_store_config.is_enable_rocksdb_store() {
-        unimplemented!()
+        // the RocksDB consume queues are not covered by the checkpoint, they tell how far the
+        // dispatching went themselves
+        let phy_offset = mapped_file
+            .get_bytes(MESSAGE_PHYSIC_OFFSET_POSITION, mem::size_of::<i64>())
+            .unwrap_or(Bytes::from([0u8; mem::size_of::<i64>()].as_ref()))
+            .get_i64();
+        if phy_offset <= max_phy_offset_of_consume_queue {
+            info!(
+                "find check. beginPhyOffset: {}, maxPhyOffsetInConsumeQueue: {}",
+                phy_offset, max_phy_offset_of_consume_queue
+            );
+            return true;
+        }
     } else {
         let sys_flag = mapped_file
             .get_bytes(SYSFLAG_POSITION, mem::size_of::<i32>())
@@ -1427,3 +2007,122 @@ impl Swappable for CommitLog {
         todo!()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rocketmq_common::common::message::MessageTrait;
+    use rocketmq_common::MessageDecoder::MESSAGE_STORE_TIMESTAMP_POSITION;
+
+    use super::*;
+
+    const TOPIC: &str = "TopicTest";
+
+    fn encode_message(message_store_config: &Arc<MessageStoreConfig>) -> BytesMut {
+        let body = Bytes::from_static(b"hello rocketmq");
+        let mut msg = MessageExtBrokerInner::default();
+        msg.set_topic(CheetahString::from_static_str(TOPIC));
+        msg.message_ext_inner.set_body_crc(crc32(body.as_ref()));
+        msg.set_body(body);
+        let mut encoder = MessageExtEncoder::new(Arc::clone(message_store_config));
+        assert!(encoder.encode(&msg).is_none());
+        BytesMut::from(encoder.get_encoder_buffer().as_ref())
+    }
+
+    #[test]
+    fn check_message_and_return_size_steps_over_a_message_failing_the_crc() {
+        let message_store_config = Arc::new(MessageStoreConfig::default());
+        let mut message = encode_message(&message_store_config);
+        let total_size = message.len() as i32;
+
+        let request = check_message_and_return_size(
+            &mut message.clone().freeze(),
+            true,
+            false,
+            true,
+            &message_store_config,
+        );
+        assert!(request.success);
+        assert_eq!(request.msg_size, total_size);
+        assert_eq!(request.topic.as_str(), TOPIC);
+
+        // the last byte of the body, in front of the topic and the properties length
+        let body_end = message.len() - 2 - TOPIC.len() - 1;
+        message[body_end - 1] ^= 0xFF;
+        let request = check_message_and_return_size(
+            &mut message.clone().freeze(),
+            true,
+            false,
+            true,
+            &message_store_config,
+        );
+        assert!(!request.success);
+        assert_eq!(request.msg_size, total_size);
+
+        // the CRC is not verified without reading the body
+        let request = check_message_and_return_size(
+            &mut message.freeze(),
+            true,
+            false,
+            false,
+            &message_store_config,
+        );
+        assert!(request.success);
+    }
+
+    #[test]
+    fn read_store_timestamp_follows_a_v6_born_host() {
+        let message_store_config = Arc::new(MessageStoreConfig::default());
+        let mut msg = MessageExtBrokerInner::default();
+        msg.set_topic(CheetahString::from_static_str(TOPIC));
+        msg.set_body(Bytes::from_static(b"hello rocketmq"));
+        msg.message_ext_inner
+            .set_born_host("[::1]:10911".parse().unwrap());
+        msg.with_born_host_v6_flag();
+        msg.message_ext_inner.set_store_timestamp(1_700_000_000_123);
+        let mut encoder = MessageExtEncoder::new(message_store_config);
+        assert!(encoder.encode(&msg).is_none());
+        let message = encoder.get_encoder_buffer();
+
+        assert_eq!(
+            store_timestamp_end(msg.sys_flag()),
+            MESSAGE_STORE_TIMESTAMP_POSITION + 20
+        );
+        assert_eq!(read_store_timestamp(&message), Some(1_700_000_000_123));
+        // the size used for an IPv4 born host stops short of the timestamp
+        assert_eq!(
+            read_store_timestamp(&message[..MESSAGE_STORE_TIMESTAMP_POSITION + 8]),
+            None
+        );
+        assert_eq!(read_store_timestamp(&message[..8]), None);
+    }
+
+    #[test]
+    fn check_message_and_return_size_rejects_a_torn_message() {
+        let message_store_config = Arc::new(MessageStoreConfig::default());
+        let message = encode_message(&message_store_config).freeze();
+
+        for torn_size in [4, 12, message.len() - 20, message.len() - 1] {
+            let request = check_message_and_return_size(
+                &mut message.slice(..torn_size),
+                true,
+                false,
+                true,
+                &message_store_config,
+            );
+            assert!(!request.success);
+            assert_eq!(request.msg_size, -1);
+        }
+
+        // a header written over a zeroed tail
+        let mut torn = BytesMut::from(&message[..8]);
+        torn.resize(message.len(), 0);
+        let request = check_message_and_return_size(
+            &mut torn.freeze(),
+            true,
+            false,
+            true,
+            &message_store_config,
+        );
+        assert!(!request.success);
+    }
+}
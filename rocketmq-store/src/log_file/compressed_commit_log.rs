@@ -0,0 +1,507 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use bytes::Buf;
+use bytes::BufMut;
+use bytes::Bytes;
+use bytes::BytesMut;
+use parking_lot::Mutex;
+use parking_lot::RwLock;
+use rocketmq_common::common::compression::compression_type::CompressionType;
+use rocketmq_common::CRC32Utils::crc32;
+use rocketmq_common::TimeUtils::get_current_millis;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
+
+use crate::base::select_result::SelectMappedBufferResult;
+use crate::log_file::mapped_file::default_mapped_file_impl::DefaultMappedFile;
+use crate::log_file::mapped_file::MappedFile;
+
+const MAGIC_CODE: u32 = 0xC0C0_C0DE;
+/// Magic code, compression type, last modification of the commit log file, bytes of commit log
+/// data, block size and block count.
+const HEADER_SIZE: usize = 4 + 4 + 8 + 8 + 4 + 4;
+/// File position, size and CRC of a block.
+const BLOCK_INDEX_SIZE: usize = 8 + 4 + 4;
+/// Decompressed blocks kept for the reads that follow.
+const BLOCK_CACHE_SIZE: usize = 16;
+
+pub(crate) fn parse_compression_type(name: &str) -> Option<CompressionType> {
+    match name.trim().to_uppercase().as_str() {
+        "LZ4" => Some(CompressionType::LZ4),
+        "ZSTD" => Some(CompressionType::Zstd),
+        "ZLIB" => Some(CompressionType::Zlib),
+        _ => None,
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+struct Block {
+    position: u64,
+    size: u32,
+    crc: u32,
+}
+
+/// A sealed commit log file compressed in blocks.
+///
+/// A block that does not get smaller compressed is stored as is, so its size is the one of the
+/// commit log data it holds.
+struct CompressedFile {
+    file_from_offset: i64,
+    path: PathBuf,
+    file: Mutex<File>,
+    compression_type: CompressionType,
+    last_modified: i64,
+    data_size: i64,
+    block_size: usize,
+    blocks: Vec<Block>,
+}
+
+impl CompressedFile {
+    fn open(path: PathBuf, file_from_offset: i64) -> io::Result<Self> {
+        let mut file = File::open(&path)?;
+        let mut header = [0u8; HEADER_SIZE];
+        file.read_exact(&mut header)?;
+        let mut header = &header[..];
+        if header.get_u32() != MAGIC_CODE {
+            return Err(invalid_data(format!(
+                "{} is not a compressed commit log file",
+                path.display()
+            )));
+        }
+        let compression_type = match header.get_i32() {
+            1 => CompressionType::LZ4,
+            2 => CompressionType::Zstd,
+            3 => CompressionType::Zlib,
+            value => {
+                return Err(invalid_data(format!(
+                    "unknown compression type {} of {}",
+                    value,
+                    path.display()
+                )))
+            }
+        };
+        let last_modified = header.get_i64();
+        let data_size = header.get_i64();
+        let block_size = header.get_u32() as usize;
+        let block_count = header.get_u32() as usize;
+        if block_size == 0 || (block_count * block_size) < data_size as usize {
+            return Err(invalid_data(format!(
+                "the blocks of {} do not hold its {} bytes",
+                path.display(),
+                data_size
+            )));
+        }
+        let mut index = vec![0u8; block_count * BLOCK_INDEX_SIZE];
+        file.read_exact(&mut index)?;
+        let mut index = &index[..];
+        let blocks = (0..block_count)
+            .map(|_| Block {
+                position: index.get_u64(),
+                size: index.get_u32(),
+                crc: index.get_u32(),
+            })
+            .collect();
+        Ok(Self {
+            file_from_offset,
+            path,
+            file: Mutex::new(file),
+            compression_type,
+            last_modified,
+            data_size,
+            block_size,
+            blocks,
+        })
+    }
+
+    fn end_offset(&self) -> i64 {
+        self.file_from_offset + self.data_size
+    }
+
+    fn read_block(&self, index: usize) -> io::Result<Bytes> {
+        let block = self.blocks.get(index).ok_or_else(|| {
+            invalid_data(format!("{} has no block {}", self.path.display(), index))
+        })?;
+        let mut data = vec![0u8; block.size as usize];
+        {
+            let mut file = self.file.lock();
+            file.seek(SeekFrom::Start(block.position))?;
+            file.read_exact(&mut data)?;
+        }
+        if crc32(&data) != block.crc {
+            return Err(invalid_data(format!(
+                "the CRC of block {} of {} does not match",
+                index,
+                self.path.display()
+            )));
+        }
+        let raw_size = (self.data_size as usize - index * self.block_size).min(self.block_size);
+        if data.len() == raw_size {
+            return Ok(Bytes::from(data));
+        }
+        Ok(self.compression_type.decompression(&data))
+    }
+}
+
+/// The sealed commit log files recompressed to save disk space on brokers keeping the messages
+/// long.
+///
+/// The compressed files hold the oldest part of the commit log, a read of it decompresses the
+/// blocks it covers.
+pub struct CompressedCommitLog {
+    store_path: PathBuf,
+    files: RwLock<BTreeMap<i64, Arc<CompressedFile>>>,
+    /// The blocks read last, by file and index, the least recently read first.
+    block_cache: Mutex<VecDeque<((i64, usize), Bytes)>>,
+}
+
+impl CompressedCommitLog {
+    pub fn new(store_path: impl Into<PathBuf>) -> Self {
+        Self {
+            store_path: store_path.into(),
+            files: RwLock::new(BTreeMap::new()),
+            block_cache: Mutex::new(VecDeque::with_capacity(BLOCK_CACHE_SIZE)),
+        }
+    }
+
+    /// Loads the compressed files, a compressed file of a commit log file that is still there,
+    /// because the broker stopped before deleting it, is deleted.
+    pub fn load(&self, commit_log_file_offsets: &HashSet<i64>) -> bool {
+        let Ok(entries) = fs::read_dir(&self.store_path) else {
+            return true;
+        };
+        let mut files = self.files.write();
+        for path in entries.filter_map(Result::ok).map(|entry| entry.path()) {
+            let Some(file_from_offset) = path
+                .file_name()
+                .and_then(|file_name| file_name.to_str())
+                .and_then(|file_name| file_name.parse::<i64>().ok())
+            else {
+                // left by a compression the broker stopped in the middle of
+                if path.extension().is_some_and(|extension| extension == "tmp") {
+                    let _ = fs::remove_file(&path);
+                }
+                continue;
+            };
+            if commit_log_file_offsets.contains(&file_from_offset) {
+                warn!(
+                    "the commit log file of {} is still there, delete it",
+                    path.display()
+                );
+                let _ = fs::remove_file(&path);
+                continue;
+            }
+            match CompressedFile::open(path.clone(), file_from_offset) {
+                Ok(file) => {
+                    info!("load {} OK", path.display());
+                    files.insert(file_from_offset, Arc::new(file));
+                }
+                Err(e) => {
+                    error!(
+                        "load compressed commit log file {} failed: {}",
+                        path.display(),
+                        e
+                    );
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// The offset the first compressed file starts at.
+    pub fn get_min_offset(&self) -> Option<i64> {
+        self.files.read().keys().next().copied()
+    }
+
+    pub fn is_compressed(&self, file_from_offset: i64) -> bool {
+        self.files.read().contains_key(&file_from_offset)
+    }
+
+    /// Whether the commit log data at `offset` is in a compressed file.
+    pub fn contains(&self, offset: i64) -> bool {
+        self.find_file(offset).is_some()
+    }
+
+    fn find_file(&self, offset: i64) -> Option<Arc<CompressedFile>> {
+        self.files
+            .read()
+            .range(..=offset)
+            .next_back()
+            .map(|(_, file)| file)
+            .filter(|file| offset < file.end_offset())
+            .cloned()
+    }
+
+    /// The `size` bytes of the commit log at `offset`, decompressed.
+    pub fn get_message(&self, offset: i64, size: i32) -> Option<SelectMappedBufferResult> {
+        let file = self.find_file(offset)?;
+        if size <= 0 || offset + size as i64 > file.end_offset() {
+            return None;
+        }
+        let mut message = BytesMut::with_capacity(size as usize);
+        let mut pos = (offset - file.file_from_offset) as usize;
+        let end = pos + size as usize;
+        while pos < end {
+            let index = pos / file.block_size;
+            let block = match self.get_block(&file, index) {
+                Ok(block) => block,
+                Err(e) => {
+                    error!("read compressed commit log at {} failed: {}", offset, e);
+                    return None;
+                }
+            };
+            let block_pos = pos - index * file.block_size;
+            let len = (end - pos).min(block.len().saturating_sub(block_pos));
+            if len == 0 {
+                error!(
+                    "block {} of {} is shorter than its data",
+                    index,
+                    file.path.display()
+                );
+                return None;
+            }
+            message.put_slice(&block[block_pos..block_pos + len]);
+            pos += len;
+        }
+        Some(SelectMappedBufferResult {
+            start_offset: offset as u64,
+            bytes: Some(message.freeze()),
+            size,
+            mapped_file: None,
+            is_in_cache: false,
+        })
+    }
+
+    /// The commit log data from `offset` to the end of its compressed file, at most `max_size`
+    /// bytes of it, decompressed.
+    pub fn get_data(&self, offset: i64, max_size: i32) -> Option<SelectMappedBufferResult> {
+        let file = self.find_file(offset)?;
+        let size = (file.end_offset() - offset).min(max_size as i64);
+        self.get_message(offset, size as i32)
+    }
+
+    fn get_block(&self, file: &CompressedFile, index: usize) -> io::Result<Bytes> {
+        let key = (file.file_from_offset, index);
+        {
+            let mut block_cache = self.block_cache.lock();
+            if let Some(position) = block_cache.iter().position(|(cached, _)| *cached == key) {
+                let entry = block_cache.remove(position).unwrap();
+                let block = entry.1.clone();
+                block_cache.push_back(entry);
+                return Ok(block);
+            }
+        }
+        let block = file.read_block(index)?;
+        let mut block_cache = self.block_cache.lock();
+        if block_cache.len() >= BLOCK_CACHE_SIZE {
+            block_cache.pop_front();
+        }
+        block_cache.push_back((key, block.clone()));
+        Ok(block)
+    }
+
+    /// Compresses the sealed `mapped_file` in blocks of `block_size` bytes, the caller deletes
+    /// it once this returns.
+    pub fn compress(
+        &self,
+        mapped_file: &DefaultMappedFile,
+        compression_type: CompressionType,
+        block_size: usize,
+    ) -> io::Result<()> {
+        let block_size = block_size.max(4096);
+        let file_from_offset = mapped_file.get_file_from_offset() as i64;
+        let last_modified = mapped_file.get_last_modified_timestamp() as i64;
+        if !mapped_file.hold() {
+            return Err(io::Error::other(format!(
+                "{} is not available",
+                mapped_file.get_file_name()
+            )));
+        }
+        let data_size = mapped_file.get_wrote_position() as usize;
+        let blocks = mapped_file.get_mapped_file()[..data_size]
+            .chunks(block_size)
+            .map(|chunk| {
+                let compressed = compression_type.compression(chunk);
+                if compressed.len() < chunk.len() {
+                    compressed
+                } else {
+                    Bytes::copy_from_slice(chunk)
+                }
+            })
+            .collect::<Vec<_>>();
+        mapped_file.release();
+
+        let index_size = blocks.len() * BLOCK_INDEX_SIZE;
+        let mut head = BytesMut::with_capacity(HEADER_SIZE + index_size);
+        head.put_u32(MAGIC_CODE);
+        head.put_i32(compression_type.get_value());
+        head.put_i64(last_modified);
+        head.put_i64(data_size as i64);
+        head.put_u32(block_size as u32);
+        head.put_u32(blocks.len() as u32);
+        let mut position = (HEADER_SIZE + index_size) as u64;
+        for block in &blocks {
+            head.put_u64(position);
+            head.put_u32(block.len() as u32);
+            head.put_u32(crc32(block));
+            position += block.len() as u64;
+        }
+
+        fs::create_dir_all(&self.store_path)?;
+        let path = self.store_path.join(format!("{:020}", file_from_offset));
+        let tmp_path = path.with_extension("tmp");
+        if let Err(e) = write_file(&tmp_path, &head, &blocks) {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(e);
+        }
+        fs::rename(&tmp_path, &path)?;
+        let file = CompressedFile::open(path, file_from_offset)?;
+        info!(
+            "compressed commit log file {}, {} bytes to {} bytes",
+            mapped_file.get_file_name(),
+            data_size,
+            position
+        );
+        self.files.write().insert(file_from_offset, Arc::new(file));
+        Ok(())
+    }
+
+    /// Deletes, oldest first, the compressed files past the millis `expire_timestamp` gives for
+    /// their first offset and last modification, or all of them with `clean_immediately`, up to
+    /// `delete_file_batch_max` files, and returns how many were deleted.
+    pub fn delete_expired_files(
+        &self,
+        expire_timestamp: &dyn Fn(i64, i64) -> i64,
+        clean_immediately: bool,
+        delete_file_batch_max: usize,
+        can_delete: &dyn Fn(&str) -> bool,
+    ) -> usize {
+        let files = self.files.read().values().cloned().collect::<Vec<_>>();
+        let mut deleted = 0;
+        for file in files {
+            if deleted >= delete_file_batch_max.max(1) {
+                break;
+            }
+            if (get_current_millis() as i64)
+                < expire_timestamp(file.file_from_offset, file.last_modified)
+                && !clean_immediately
+            {
+                break;
+            }
+            if !can_delete(file.path.to_string_lossy().as_ref()) {
+                break;
+            }
+            self.files.write().remove(&file.file_from_offset);
+            self.block_cache
+                .lock()
+                .retain(|((file_from_offset, _), _)| *file_from_offset != file.file_from_offset);
+            match fs::remove_file(&file.path) {
+                Ok(()) => info!("delete file success: {}", file.path.display()),
+                Err(e) => warn!("delete file {} failed: {}", file.path.display(), e),
+            }
+            deleted += 1;
+        }
+        deleted
+    }
+}
+
+fn write_file(path: &Path, head: &[u8], blocks: &[Bytes]) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(head)?;
+    for block in blocks {
+        file.write_all(block)?;
+    }
+    file.sync_all()
+}
+
+#[cfg(test)]
+mod tests {
+    use cheetah_string::CheetahString;
+
+    use super::*;
+
+    #[test]
+    fn reads_messages_across_compressed_blocks() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = dir.path().join("commitlog").join(format!("{:020}", 65536));
+        let mapped_file =
+            DefaultMappedFile::new(CheetahString::from(file_name.to_str().unwrap()), 65536);
+        let data = (0..20000u32)
+            .map(|i| (i % 251) as u8)
+            .chain(vec![7u8; 20000])
+            .collect::<Vec<_>>();
+        assert!(mapped_file.append_message_bytes(&data));
+
+        let compressed_commit_log = CompressedCommitLog::new(dir.path().join("compressed"));
+        compressed_commit_log
+            .compress(&mapped_file, CompressionType::Zstd, 4096)
+            .unwrap();
+        assert_eq!(compressed_commit_log.get_min_offset(), Some(65536));
+        assert!(compressed_commit_log.contains(65536 + 39999));
+        assert!(!compressed_commit_log.contains(65536 + 40000));
+        let message = compressed_commit_log
+            .get_message(65536 + 4000, 9000)
+            .unwrap();
+        assert_eq!(message.get_buffer(), &data[4000..13000]);
+        assert!(compressed_commit_log
+            .get_message(65536 + 39000, 2000)
+            .is_none());
+        // the data ends with the file
+        let rest = compressed_commit_log.get_data(65536 + 39000, 2000).unwrap();
+        assert_eq!(rest.get_buffer(), &data[39000..40000]);
+        let rest = compressed_commit_log.get_data(65536, 500).unwrap();
+        assert_eq!(rest.get_data(100, 400).unwrap().as_ref(), &data[100..500]);
+        assert!(rest.get_data(100, 401).is_none());
+
+        let reloaded = CompressedCommitLog::new(dir.path().join("compressed"));
+        assert!(reloaded.load(&HashSet::new()));
+        let message = reloaded.get_message(65536 + 30000, 100).unwrap();
+        assert_eq!(message.get_buffer(), &data[30000..30100]);
+        assert_eq!(
+            reloaded.delete_expired_files(&|_, last_modified| last_modified, false, 10, &|_| true),
+            1
+        );
+        assert_eq!(reloaded.get_min_offset(), None);
+
+        // a compressed file whose commit log file is still there is dropped
+        compressed_commit_log
+            .compress(&mapped_file, CompressionType::LZ4, 4096)
+            .unwrap();
+        let reloaded = CompressedCommitLog::new(dir.path().join("compressed"));
+        assert!(reloaded.load(&HashSet::from([65536])));
+        assert!(!reloaded.contains(65536));
+    }
+}
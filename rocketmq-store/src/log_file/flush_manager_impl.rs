@@ -15,4 +15,5 @@
  * limitations under the License.
  */
 pub mod defalut_flush_manager;
+pub(crate) mod flush_disk_watcher;
 pub mod group_commit_request;
@@ -14,15 +14,21 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::sync::Weak;
+use std::time::Duration;
+use std::time::Instant;
 
 use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
 use rocketmq_common::TimeUtils::get_current_millis;
 use tokio::sync::Mutex;
 use tokio::sync::Notify;
 use tokio::time;
+use tracing::info;
 
+use crate::base::adaptive_tuning::StoreTuning;
 use crate::base::flush_manager::FlushManager;
 use crate::base::message_result::AppendMessageResult;
 use crate::base::message_status_enum::PutMessageStatus;
@@ -30,14 +36,28 @@ use crate::base::store_checkpoint::StoreCheckpoint;
 use crate::config::flush_disk_type::FlushDiskType;
 use crate::config::message_store_config::MessageStoreConfig;
 use crate::consume_queue::mapped_file_queue::MappedFileQueue;
+use crate::log_file::flush_manager_impl::flush_disk_watcher::FlushDiskWatcher;
 use crate::log_file::flush_manager_impl::group_commit_request::GroupCommitRequest;
 
+/// How long the group commit service waits for a wakeup before it flushes anyway.
+const GROUP_COMMIT_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Every how many thorough flushes the flush real time service logs how far it falls behind.
+const PRINT_FLUSH_PROGRESS_TIMES: u64 = 10;
+
+/// Flushes and commits taking longer than this are logged.
+const SLOW_FLUSH_THRESHOLD: Duration = Duration::from_millis(500);
+
+/// Attempts to flush, or commit, everything on shutdown.
+const FLUSH_RETRY_TIMES_OVER: usize = 10;
+
 pub struct DefaultFlushManager {
     group_commit_service: Option<GroupCommitService>,
     flush_real_time_service: Option<FlushRealTimeService>,
     commit_real_time_service: Option<CommitRealTimeService>,
     message_store_config: Arc<MessageStoreConfig>,
     mapped_file_queue: Option<MappedFileQueue>,
+    store_checkpoint: Arc<StoreCheckpoint>,
 }
 
 impl DefaultFlushManager {
@@ -45,15 +65,15 @@ impl DefaultFlushManager {
         message_store_config: Arc<MessageStoreConfig>,
         mapped_file_queue: MappedFileQueue,
         store_checkpoint: Arc<StoreCheckpoint>,
+        store_tuning: Arc<StoreTuning>,
     ) -> Self {
         let (group_commit_service, flush_real_time_service) =
             match message_store_config.flush_disk_type {
                 FlushDiskType::SyncFlush => (
-                    Some(GroupCommitService {
-                        store_checkpoint: store_checkpoint.clone(),
-                        rx_out: None,
-                        tx_in: None,
-                    }),
+                    Some(GroupCommitService::new(
+                        mapped_file_queue.clone(),
+                        store_checkpoint.clone(),
+                    )),
                     None,
                 ),
                 FlushDiskType::AsyncFlush => (
@@ -61,16 +81,19 @@ impl DefaultFlushManager {
                     Some(FlushRealTimeService {
                         message_store_config: message_store_config.clone(),
                         store_checkpoint: store_checkpoint.clone(),
+                        store_tuning: store_tuning.clone(),
                         notified: Arc::new(Notify::new()),
+                        stopped: Arc::new(AtomicBool::new(false)),
                     }),
                 ),
             };
 
-        let commit_real_time_service = if message_store_config.transient_store_pool_enable {
+        let commit_real_time_service = if message_store_config.is_transient_store_pool_enable() {
             Some(CommitRealTimeService {
                 message_store_config: message_store_config.clone(),
-                store_checkpoint,
+                store_tuning,
                 notified: Arc::new(Default::default()),
+                stopped: Arc::new(AtomicBool::new(false)),
                 flush_manager: None,
             })
         } else {
@@ -83,6 +106,7 @@ impl DefaultFlushManager {
             message_store_config,
             commit_real_time_service,
             mapped_file_queue: Some(mapped_file_queue),
+            store_checkpoint,
         }
     }
 }
@@ -92,6 +116,12 @@ impl DefaultFlushManager {
         self.commit_real_time_service.as_ref()
     }
 
+    /// The service `SYNC_FLUSH` puts wait on, it is shared so that they can wait without
+    /// holding the flush manager.
+    pub(crate) fn group_commit_service(&self) -> Option<&GroupCommitService> {
+        self.group_commit_service.as_ref()
+    }
+
     pub(crate) fn commit_real_time_service_mut(&mut self) -> Option<&mut CommitRealTimeService> {
         self.commit_real_time_service.as_mut()
     }
@@ -99,34 +129,49 @@ impl DefaultFlushManager {
 
 impl FlushManager for DefaultFlushManager {
     fn start(&mut self) {
-        if let Some(ref mut group_commit_service) = self.group_commit_service {
-            group_commit_service.start(self.mapped_file_queue.clone().unwrap());
+        if let Some(ref group_commit_service) = self.group_commit_service {
+            group_commit_service.start();
         }
         if let Some(ref mut flush_real_time_service) = self.flush_real_time_service {
             flush_real_time_service.start(self.mapped_file_queue.clone().unwrap());
         }
 
-        if self.message_store_config.transient_store_pool_enable {
+        if self.message_store_config.is_transient_store_pool_enable() {
             if let Some(ref mut commit_real_time_service) = self.commit_real_time_service {
                 commit_real_time_service.start(self.mapped_file_queue.clone().unwrap());
             }
         }
     }
 
+    /// Stops the services, then commits the write buffers and flushes the commit log before it
+    /// returns, so that nothing appended is lost once the store deletes its abort file.
     fn shutdown(&mut self) {
-        if let Some(ref mut group_commit_service) = self.group_commit_service {
-            group_commit_service.shutdown();
+        if let Some(ref mut commit_real_time_service) = self.commit_real_time_service {
+            commit_real_time_service.shutdown();
         }
         if let Some(ref mut flush_real_time_service) = self.flush_real_time_service {
             flush_real_time_service.shutdown();
         }
-        if let Some(ref mut commit_real_time_service) = self.commit_real_time_service {
-            commit_real_time_service.shutdown();
+        if let Some(ref mapped_file_queue) = self.mapped_file_queue {
+            // the write buffers are committed before the commit log is flushed
+            if self.commit_real_time_service.is_some() {
+                retry_on_shutdown("commit", || mapped_file_queue.commit(0));
+            }
+            retry_on_shutdown("flush", || mapped_file_queue.flush(0));
+            let store_timestamp = mapped_file_queue.get_store_timestamp();
+            if store_timestamp > 0 {
+                self.store_checkpoint
+                    .set_physic_msg_timestamp(store_timestamp);
+            }
+        }
+        // the requests still waiting are flushed by now, the last round completes them
+        if let Some(ref group_commit_service) = self.group_commit_service {
+            group_commit_service.shutdown();
         }
     }
 
     fn wake_up_flush(&mut self) {
-        if let Some(ref mut group_commit_service) = self.group_commit_service {
+        if let Some(ref group_commit_service) = self.group_commit_service {
             group_commit_service.wakeup();
         }
         if let Some(ref mut flush_real_time_service) = self.flush_real_time_service {
@@ -148,33 +193,21 @@ impl FlushManager for DefaultFlushManager {
         match self.message_store_config.flush_disk_type {
             FlushDiskType::SyncFlush => {
                 if message_ext.is_wait_store_msg_ok() {
-                    let commit_request = GroupCommitRequest::new(
-                        result.wrote_offset + result.wrote_bytes as i64,
-                        self.message_store_config.sync_flush_timeout,
-                    );
-
-                    time::timeout(
-                        time::Duration::from_millis(self.message_store_config.sync_flush_timeout),
-                        self.group_commit_service
-                            .as_mut()
-                            .unwrap()
-                            .put_request(commit_request),
-                    )
-                    .await
-                    .map_or(PutMessageStatus::FlushDiskTimeout, |request| {
-                        request.map_or(PutMessageStatus::FlushDiskTimeout, |request| {
-                            request
-                                .flush_ok
-                                .unwrap_or(PutMessageStatus::FlushDiskTimeout)
-                        })
-                    })
+                    self.group_commit_service
+                        .as_ref()
+                        .unwrap()
+                        .put_request(
+                            result.wrote_offset + result.wrote_bytes as i64,
+                            self.message_store_config.sync_flush_timeout,
+                        )
+                        .await
                 } else {
-                    self.group_commit_service.as_mut().unwrap().wakeup();
+                    self.group_commit_service.as_ref().unwrap().wakeup();
                     PutMessageStatus::PutOk
                 }
             }
             FlushDiskType::AsyncFlush => {
-                if self.message_store_config.transient_store_pool_enable {
+                if self.message_store_config.is_transient_store_pool_enable() {
                     self.commit_real_time_service.as_mut().unwrap().wakeup();
                 } else {
                     self.flush_real_time_service.as_mut().unwrap().wakeup();
@@ -185,147 +218,251 @@ impl FlushManager for DefaultFlushManager {
     }
 }
 
-struct GroupCommitService {
+/// Runs `action` until it reports that nothing is left, at most `FLUSH_RETRY_TIMES_OVER` times.
+fn retry_on_shutdown(action_name: &str, mut action: impl FnMut() -> bool) {
+    let mut result = false;
+    for _ in 0..FLUSH_RETRY_TIMES_OVER {
+        if result {
+            break;
+        }
+        result = action();
+        info!(
+            "{} commit log on shutdown, {}",
+            action_name,
+            if result { "OK" } else { "Not OK" }
+        );
+    }
+}
+
+/// Flushes the commit log for the puts of a `SYNC_FLUSH` store that wait for the flush.
+///
+/// Puts queue their requests on `requests_write`, the service swaps the queue out as one group,
+/// flushes the commit log once for all of them and completes every request of the group.
+#[derive(Clone)]
+pub(crate) struct GroupCommitService {
+    mapped_file_queue: MappedFileQueue,
     store_checkpoint: Arc<StoreCheckpoint>,
-    rx_out: Option<tokio::sync::mpsc::Receiver<GroupCommitRequest>>,
-    tx_in: Option<tokio::sync::mpsc::Sender<GroupCommitRequest>>,
+    requests_write: Arc<parking_lot::Mutex<Vec<Arc<GroupCommitRequest>>>>,
+    flush_disk_watcher: FlushDiskWatcher,
+    notified: Arc<Notify>,
+    stopped: Arc<AtomicBool>,
 }
 
 impl GroupCommitService {
-    pub async fn put_request(&mut self, request: GroupCommitRequest) -> Option<GroupCommitRequest> {
-        if let Some(ref tx_in) = self.tx_in {
-            let _ = tx_in.send(request).await;
-        }
-        match self.rx_out {
-            None => None,
-            Some(ref mut rx_out) => rx_out.recv().await,
+    fn new(mapped_file_queue: MappedFileQueue, store_checkpoint: Arc<StoreCheckpoint>) -> Self {
+        Self {
+            mapped_file_queue,
+            store_checkpoint,
+            requests_write: Arc::new(parking_lot::Mutex::new(Vec::new())),
+            flush_disk_watcher: FlushDiskWatcher::new(),
+            notified: Arc::new(Notify::new()),
+            stopped: Arc::new(AtomicBool::new(false)),
         }
     }
 
-    fn start(&mut self, mapped_file_queue: MappedFileQueue) {
-        let (tx_in, mut rx_in) = tokio::sync::mpsc::channel::<GroupCommitRequest>(1024);
-        self.tx_in = Some(tx_in);
-        let (tx_out, rx_out) = tokio::sync::mpsc::channel::<GroupCommitRequest>(1024);
-        self.rx_out = Some(rx_out);
+    /// Waits for the commit log to be flushed up to `next_offset`, `FlushDiskTimeout` when it
+    /// is not within `timeout_millis`.
+    pub(crate) async fn put_request(
+        &self,
+        next_offset: i64,
+        timeout_millis: u64,
+    ) -> PutMessageStatus {
+        let (request, flush_ok) = GroupCommitRequest::new(next_offset, timeout_millis);
+        self.flush_disk_watcher.add(request.clone());
+        self.requests_write.lock().push(request);
+        self.wakeup();
+        flush_ok.await.unwrap_or(PutMessageStatus::FlushDiskTimeout)
+    }
+
+    fn start(&self) {
+        self.flush_disk_watcher.start();
+        let service = self.clone();
         tokio::spawn(async move {
             loop {
-                match rx_in.recv().await {
-                    None => {}
-                    Some(mut request) => {
-                        let mut flush_ok =
-                            mapped_file_queue.get_flushed_where() >= request.next_offset;
-                        for i in 0..1000 {
-                            if flush_ok {
-                                break;
-                            }
-                            mapped_file_queue.flush(0);
-                            flush_ok = mapped_file_queue.get_flushed_where() >= request.next_offset;
-                            if flush_ok {
-                                break;
-                            }
-                            time::sleep(time::Duration::from_millis(1)).await;
-                        }
-                        request.flush_ok = Some(if flush_ok {
-                            PutMessageStatus::PutOk
-                        } else {
-                            PutMessageStatus::FlushDiskTimeout
-                        });
-                        let _ = tx_out.send(request).await;
+                let stopped = service.stopped.load(Ordering::Acquire);
+                if !stopped {
+                    tokio::select! {
+                        _ = service.notified.notified() => {}
+                        _ = time::sleep(GROUP_COMMIT_INTERVAL) => {}
                     }
                 }
+                let requests_read = std::mem::take(&mut *service.requests_write.lock());
+                service.do_commit(requests_read).await;
+                if stopped {
+                    break;
+                }
             }
         });
     }
 
-    pub fn wakeup(&mut self) {}
+    async fn do_commit(&self, requests_read: Vec<Arc<GroupCommitRequest>>) {
+        if requests_read.is_empty() {
+            // puts that do not wait for the flush only wake the service up
+            self.mapped_file_queue.flush(0);
+        }
+        for request in requests_read {
+            let mut flush_ok = self.mapped_file_queue.get_flushed_where() >= request.next_offset;
+            for _ in 0..1000 {
+                if flush_ok || request.is_done() {
+                    break;
+                }
+                self.mapped_file_queue.flush(0);
+                flush_ok = self.mapped_file_queue.get_flushed_where() >= request.next_offset;
+                if !flush_ok {
+                    time::sleep(time::Duration::from_millis(1)).await;
+                }
+            }
+            request.wakeup_customer(if flush_ok {
+                PutMessageStatus::PutOk
+            } else {
+                PutMessageStatus::FlushDiskTimeout
+            });
+        }
+        let store_timestamp = self.mapped_file_queue.get_store_timestamp();
+        if store_timestamp > 0 {
+            self.store_checkpoint
+                .set_physic_msg_timestamp(store_timestamp);
+        }
+    }
+
+    pub fn wakeup(&self) {
+        self.notified.notify_one();
+    }
 
-    pub fn shutdown(&mut self) {}
+    /// Flushes the requests queued so far once more before the service stops.
+    pub fn shutdown(&self) {
+        self.stopped.store(true, Ordering::Release);
+        self.wakeup();
+        self.flush_disk_watcher.shutdown();
+    }
 }
 
+/// Flushes the commit log of an `ASYNC_FLUSH` store every `flush_interval_commit_log` ms, as
+/// currently set in the [`StoreTuning`].
+///
+/// A round only flushes once `flush_commit_log_least_pages` dirty pages piled up, except for
+/// one round every `flush_commit_log_thorough_interval` ms that flushes whatever is dirty. With
+/// `flush_commit_log_timed` the rounds run at a fixed rate, otherwise a put wakes the service up
+/// early.
 struct FlushRealTimeService {
     message_store_config: Arc<MessageStoreConfig>,
     store_checkpoint: Arc<StoreCheckpoint>,
+    store_tuning: Arc<StoreTuning>,
     notified: Arc<Notify>,
+    stopped: Arc<AtomicBool>,
+}
+
+/// Tells the flush rounds that have to flush thoroughly, and the ones of them that log the
+/// flush progress.
+#[derive(Default)]
+struct ThoroughFlushSchedule {
+    last_flush_timestamp: u64,
+    thorough_flush_times: u64,
+}
+
+impl ThoroughFlushSchedule {
+    /// Returns the least pages to flush in the round starting at `now`, and whether the round
+    /// logs how far the flush falls behind.
+    fn least_pages(&mut self, now: u64, least_pages: i32, thorough_interval: u64) -> (i32, bool) {
+        if now < self.last_flush_timestamp + thorough_interval {
+            return (least_pages, false);
+        }
+        self.last_flush_timestamp = now;
+        let print_flush_progress = self.thorough_flush_times % PRINT_FLUSH_PROGRESS_TIMES == 0;
+        self.thorough_flush_times += 1;
+        (0, print_flush_progress)
+    }
 }
 
 impl FlushRealTimeService {
     fn start(&mut self, mapped_file_queue: MappedFileQueue) {
         let message_store_config = self.message_store_config.clone();
         let store_checkpoint = self.store_checkpoint.clone();
+        let store_tuning = self.store_tuning.clone();
         let notified = self.notified.clone();
+        let stopped = self.stopped.clone();
         tokio::spawn(async move {
-            let mut last_flush_timestamp = 0;
-            loop {
-                let flush_commit_log_timed = message_store_config.flush_commit_log_timed;
-                let interval = message_store_config.flush_interval_commit_log;
-                let mut flush_physic_queue_least_pages =
-                    message_store_config.flush_commit_log_least_pages;
-                let flush_physic_queue_thorough_interval =
-                    message_store_config.flush_commit_log_thorough_interval;
-                //let mut print_flush_progress = false;
-
-                let current_time_millis = get_current_millis();
-                if current_time_millis
-                    >= last_flush_timestamp + flush_physic_queue_thorough_interval as u64
-                {
-                    last_flush_timestamp = current_time_millis;
-                    flush_physic_queue_least_pages = 0;
-                }
-                if flush_commit_log_timed {
-                    time::sleep(time::Duration::from_millis(interval as u64)).await;
+            let mut schedule = ThoroughFlushSchedule::default();
+            while !stopped.load(Ordering::Acquire) {
+                let interval =
+                    time::Duration::from_millis(store_tuning.flush_interval_commit_log_millis());
+                let (flush_physic_queue_least_pages, print_flush_progress) = schedule.least_pages(
+                    get_current_millis(),
+                    store_tuning.flush_least_pages(),
+                    message_store_config
+                        .flush_commit_log_thorough_interval
+                        .max(0) as u64,
+                );
+                if message_store_config.flush_commit_log_timed {
+                    time::sleep(interval).await;
                 } else {
                     tokio::select! {
                         _ = notified.notified() => {}
-                        _ = tokio::time::sleep(std::time::Duration::from_millis(interval as u64)) => {}
+                        _ = time::sleep(interval) => {}
                     }
                 }
+                if print_flush_progress {
+                    info!(
+                        "how much disk fall behind memory, {}",
+                        mapped_file_queue.remain_how_many_data_to_flush()
+                    );
+                }
 
+                let begin = Instant::now();
                 mapped_file_queue.flush(flush_physic_queue_least_pages);
                 let store_timestamp = mapped_file_queue.get_store_timestamp();
                 if store_timestamp > 0 {
                     store_checkpoint.set_physic_msg_timestamp(store_timestamp);
                 }
+                let past = begin.elapsed();
+                if past > SLOW_FLUSH_THRESHOLD {
+                    info!("Flush data to disk costs {} ms", past.as_millis());
+                }
             }
         });
     }
 
     pub fn wakeup(&mut self) {
         if !self.message_store_config.flush_commit_log_timed {
-            let notified = self.notified.clone();
-            tokio::spawn(async move { notified.notified().await });
+            self.notified.notify_one();
         }
     }
 
-    pub fn shutdown(&mut self) {}
+    pub fn shutdown(&mut self) {
+        self.stopped.store(true, Ordering::Release);
+        self.notified.notify_one();
+    }
 }
 
+/// Commits what was appended to the write buffers of the transient store pool to the commit
+/// log files, and wakes the flush up once something was committed.
 pub(crate) struct CommitRealTimeService {
     message_store_config: Arc<MessageStoreConfig>,
-    store_checkpoint: Arc<StoreCheckpoint>,
+    store_tuning: Arc<StoreTuning>,
     notified: Arc<Notify>,
+    stopped: Arc<AtomicBool>,
     flush_manager: Option<Weak<Mutex<DefaultFlushManager>>>,
 }
 
 impl CommitRealTimeService {
     pub fn wakeup(&mut self) {
-        let notified = self.notified.clone();
-        tokio::spawn(async move { notified.notified().await });
+        self.notified.notify_one();
     }
 
     fn start(&mut self, mapped_file_queue: MappedFileQueue) {
         let message_store_config = self.message_store_config.clone();
-        let store_checkpoint = self.store_checkpoint.clone();
+        let store_tuning = self.store_tuning.clone();
         let notified = self.notified.clone();
+        let stopped = self.stopped.clone();
         let flush_manager = self.flush_manager.clone();
         tokio::spawn(async move {
             let mut last_commit_timestamp = 0;
-            loop {
-                let interval = message_store_config.commit_interval_commit_log;
+            while !stopped.load(Ordering::Acquire) {
+                let interval = store_tuning.commit_interval_millis();
                 let mut commit_data_least_pages =
                     message_store_config.commit_commit_log_least_pages;
                 let commit_data_thorough_interval =
                     message_store_config.commit_commit_log_thorough_interval;
-                //let mut print_flush_progress = false;
 
                 let begin = get_current_millis();
                 if begin >= last_commit_timestamp + commit_data_thorough_interval {
@@ -333,25 +470,54 @@ impl CommitRealTimeService {
                     commit_data_least_pages = 0;
                 }
 
+                // false means some data was committed
                 let result = mapped_file_queue.commit(commit_data_least_pages);
+                let end = get_current_millis();
                 if !result {
-                    last_commit_timestamp = get_current_millis();
-                    if let Some(flush_manager) = flush_manager.as_ref().unwrap().upgrade() {
+                    last_commit_timestamp = end;
+                    if let Some(flush_manager) = flush_manager.as_ref().and_then(Weak::upgrade) {
                         flush_manager.lock().await.wake_up_flush();
                     }
                 }
+                if end - begin > SLOW_FLUSH_THRESHOLD.as_millis() as u64 {
+                    info!("Commit data to file costs {} ms", end - begin);
+                }
 
                 tokio::select! {
                     _ = notified.notified() => {}
-                    _ = tokio::time::sleep(std::time::Duration::from_millis(interval)) => {}
+                    _ = time::sleep(time::Duration::from_millis(interval)) => {}
                 }
             }
         });
     }
 
-    pub fn shutdown(&mut self) {}
+    pub fn shutdown(&mut self) {
+        self.stopped.store(true, Ordering::Release);
+        self.notified.notify_one();
+    }
 
     pub fn set_flush_manager(&mut self, flush_manager: Option<Weak<Mutex<DefaultFlushManager>>>) {
         self.flush_manager = flush_manager;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flushes_thoroughly_once_per_interval() {
+        let mut schedule = ThoroughFlushSchedule::default();
+        assert_eq!(schedule.least_pages(10_000, 4, 10_000), (0, true));
+        assert_eq!(schedule.least_pages(15_000, 4, 10_000), (4, false));
+        assert_eq!(schedule.least_pages(19_999, 4, 10_000), (4, false));
+        assert_eq!(schedule.least_pages(20_000, 4, 10_000), (0, false));
+        for round in 3..=PRINT_FLUSH_PROGRESS_TIMES {
+            assert_eq!(schedule.least_pages(round * 10_000, 4, 10_000), (0, false));
+        }
+        assert_eq!(
+            schedule.least_pages((PRINT_FLUSH_PROGRESS_TIMES + 1) * 10_000, 4, 10_000),
+            (0, true)
+        );
+    }
+}
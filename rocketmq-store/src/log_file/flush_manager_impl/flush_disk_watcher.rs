@@ -0,0 +1,110 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use rocketmq_common::TimeUtils::get_current_nano;
+use tokio::sync::mpsc;
+use tokio::sync::Notify;
+
+use crate::base::message_status_enum::PutMessageStatus;
+use crate::log_file::flush_manager_impl::group_commit_request::GroupCommitRequest;
+
+/// Longest the watcher sleeps before it checks the request it is watching again.
+const MAX_CHECK_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Completes the group commit requests the flush has not completed in time with
+/// `FlushDiskTimeout`.
+///
+/// Requests are watched in the order they were added, they all share the same
+/// `sync_flush_timeout` so a request never expires before the ones added ahead of it.
+#[derive(Clone)]
+pub(crate) struct FlushDiskWatcher {
+    tx: mpsc::UnboundedSender<Arc<GroupCommitRequest>>,
+    rx: Arc<Mutex<Option<mpsc::UnboundedReceiver<Arc<GroupCommitRequest>>>>>,
+    shutdown: Arc<Notify>,
+}
+
+impl FlushDiskWatcher {
+    pub(crate) fn new() -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        Self {
+            tx,
+            rx: Arc::new(Mutex::new(Some(rx))),
+            shutdown: Arc::new(Notify::new()),
+        }
+    }
+
+    pub(crate) fn add(&self, request: Arc<GroupCommitRequest>) {
+        let _ = self.tx.send(request);
+    }
+
+    pub(crate) fn start(&self) {
+        let Some(mut rx) = self.rx.lock().take() else {
+            return;
+        };
+        let shutdown = self.shutdown.clone();
+        tokio::spawn(async move {
+            loop {
+                let request = tokio::select! {
+                    request = rx.recv() => match request {
+                        Some(request) => request,
+                        None => return,
+                    },
+                    _ = shutdown.notified() => return,
+                };
+                while !request.is_done() {
+                    if request.is_expired() {
+                        request.wakeup_customer(PutMessageStatus::FlushDiskTimeout);
+                        break;
+                    }
+                    let remaining =
+                        Duration::from_nanos(request.dead_line.saturating_sub(get_current_nano()));
+                    tokio::time::sleep(remaining.min(MAX_CHECK_INTERVAL)).await;
+                }
+            }
+        });
+    }
+
+    pub(crate) fn shutdown(&self) {
+        self.shutdown.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn times_out_requests_not_flushed_in_time() {
+        let watcher = FlushDiskWatcher::new();
+        watcher.start();
+        let (expired, expired_flush_ok) = GroupCommitRequest::new(100, 20);
+        let (flushed, flushed_flush_ok) = GroupCommitRequest::new(200, 20);
+        watcher.add(expired);
+        watcher.add(flushed.clone());
+        flushed.wakeup_customer(PutMessageStatus::PutOk);
+
+        assert_eq!(
+            expired_flush_ok.await.unwrap(),
+            PutMessageStatus::FlushDiskTimeout
+        );
+        assert_eq!(flushed_flush_ok.await.unwrap(), PutMessageStatus::PutOk);
+        watcher.shutdown();
+    }
+}
@@ -15,37 +15,70 @@
  * limitations under the License.
  */
 use std::sync::atomic::AtomicI32;
+use std::sync::Arc;
 
+use parking_lot::Mutex;
 use rocketmq_common::TimeUtils::get_current_nano;
+use tokio::sync::oneshot;
 
 use crate::base::message_status_enum::PutMessageStatus;
 
+/// A put waiting for the commit log to be flushed up to `next_offset`.
+///
+/// The request is completed once, either by the group commit service when the flush is done or
+/// by the flush disk watcher when `dead_line` has passed.
 #[derive(Debug)]
 pub(crate) struct GroupCommitRequest {
     pub(crate) next_offset: i64,
-    pub(crate) flush_ok: Option<PutMessageStatus>,
     pub(crate) ack_nums: AtomicI32,
     pub(crate) dead_line: u64,
+    flush_ok: Mutex<Option<oneshot::Sender<PutMessageStatus>>>,
 }
 
-impl Default for GroupCommitRequest {
-    fn default() -> Self {
-        Self {
-            next_offset: 0,
-            flush_ok: None,
+impl GroupCommitRequest {
+    /// Returns the request and the receiver its waiter awaits the flush status on.
+    pub(crate) fn new(
+        next_offset: i64,
+        timeout_millis: u64,
+    ) -> (Arc<Self>, oneshot::Receiver<PutMessageStatus>) {
+        let (tx, rx) = oneshot::channel();
+        let request = Self {
+            next_offset,
             ack_nums: AtomicI32::new(1),
-            dead_line: 0,
+            dead_line: get_current_nano() + timeout_millis * 1_000_000,
+            flush_ok: Mutex::new(Some(tx)),
+        };
+        (Arc::new(request), rx)
+    }
+
+    /// Completes the request with `status`, a request already completed is left as is.
+    pub(crate) fn wakeup_customer(&self, status: PutMessageStatus) {
+        if let Some(tx) = self.flush_ok.lock().take() {
+            let _ = tx.send(status);
         }
     }
+
+    pub(crate) fn is_done(&self) -> bool {
+        self.flush_ok.lock().is_none()
+    }
+
+    pub(crate) fn is_expired(&self) -> bool {
+        get_current_nano() >= self.dead_line
+    }
 }
 
-impl GroupCommitRequest {
-    pub(crate) fn new(next_offset: i64, timeout_millis: u64) -> Self {
-        let dead_line = get_current_nano() + timeout_millis * 1_000_000;
-        Self {
-            next_offset,
-            dead_line,
-            ..Self::default()
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn first_completion_wins() {
+        let (request, flush_ok) = GroupCommitRequest::new(1024, 5_000);
+        assert!(!request.is_done());
+        assert!(!request.is_expired());
+        request.wakeup_customer(PutMessageStatus::FlushDiskTimeout);
+        request.wakeup_customer(PutMessageStatus::PutOk);
+        assert!(request.is_done());
+        assert_eq!(flush_ok.await.unwrap(), PutMessageStatus::FlushDiskTimeout);
     }
 }
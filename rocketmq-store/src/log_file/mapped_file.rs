@@ -33,6 +33,7 @@ use crate::config::flush_disk_type::FlushDiskType;
 pub mod default_mapped_file_impl;
 pub(crate) mod reference_resource;
 mod reference_resource_impl;
+pub mod uring_file;
 
 pub trait MappedFile {
     /// Returns the file name of the mapped file.
@@ -388,7 +389,7 @@ pub trait MappedFile {
     /// Returns the timestamp of the last modification to the store.
     ///
     /// # Returns
-    /// A `i64` representing the timestamp of the last modification.
+    /// The millis since the epoch of the last modification of the file, `0` when unknown.
     fn get_last_modified_timestamp(&self) -> u64;
 
     /// Retrieves data from the store starting at the specified position and of the specified size.
@@ -499,14 +500,13 @@ pub trait MappedFile {
     /// space if necessary.
     fn munlock(&self);
 
-    /// Warms up the mapped file by accessing a specified number of pages.
+    /// Warms up the mapped file by writing to every page of it, then locks it in memory.
     ///
-    /// This method is used to improve the performance of accessing the mapped file by pre-loading a
-    /// specified number of pages into memory.
+    /// This method is used to avoid the page faults of the first writes to a new file.
     ///
     /// # Arguments
     /// * `flush_disk_type` - The strategy used for flushing data to disk.
-    /// * `pages` - The number of pages to access for warming up the file.
+    /// * `pages` - The number of pages touched between two flushes with `SYNC_FLUSH`.
     fn warm_mapped_file(&self, flush_disk_type: FlushDiskType, pages: usize);
 
     /// Attempts to swap the current mapped file with a new one.
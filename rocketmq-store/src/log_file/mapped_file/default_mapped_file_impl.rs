@@ -26,10 +26,14 @@ use std::sync::atomic::AtomicI32;
 use std::sync::atomic::AtomicI64;
 use std::sync::atomic::AtomicU64;
 use std::sync::atomic::Ordering;
+use std::time::Instant;
+use std::time::UNIX_EPOCH;
 
 use bytes::Bytes;
 use bytes::BytesMut;
 use cheetah_string::CheetahString;
+#[cfg(unix)]
+use memmap2::Advice;
 use memmap2::MmapMut;
 use rocketmq_common::common::message::message_batch::MessageExtBatch;
 use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
@@ -51,6 +55,7 @@ use crate::base::transient_store_pool::TransientStorePool;
 use crate::config::flush_disk_type::FlushDiskType;
 use crate::log_file::mapped_file::reference_resource::ReferenceResource;
 use crate::log_file::mapped_file::reference_resource_impl::ReferenceResourceImpl;
+use crate::log_file::mapped_file::uring_file;
 use crate::log_file::mapped_file::MappedFile;
 
 pub const OS_PAGE_SIZE: u64 = 1024 * 4;
@@ -62,6 +67,9 @@ pub struct DefaultMappedFile {
     reference_resource: ReferenceResourceImpl,
     file: File,
     mmapped_file: SyncUnsafeCellWrapper<MmapMut>,
+    /// Buffer messages are appended to before they are committed to `mmapped_file`, borrowed
+    /// from `transient_store_pool` and returned to it once the whole file is committed.
+    write_buffer: SyncUnsafeCellWrapper<Option<MmapMut>>,
     transient_store_pool: Option<TransientStorePool>,
     file_name: CheetahString,
     file_from_offset: u64,
@@ -123,10 +131,13 @@ impl DefaultMappedFile {
         file.set_len(file_size).unwrap();
 
         let mmap = unsafe { MmapMut::map_mut(&file).unwrap() };
+        TOTAL_MAPPED_VIRTUAL_MEMORY.fetch_add(file_size as i64, Ordering::Relaxed);
+        TOTAL_MAPPED_FILES.fetch_add(1, Ordering::Relaxed);
         Self {
             reference_resource: ReferenceResourceImpl::new(),
             file,
             mmapped_file: SyncUnsafeCellWrapper::new(mmap),
+            write_buffer: SyncUnsafeCellWrapper::new(None),
             file_name,
             file_from_offset,
             mapped_byte_buffer: None,
@@ -145,6 +156,17 @@ impl DefaultMappedFile {
         }
     }
 
+    /// Bytes currently mapped by all mapped files of the process.
+    #[inline]
+    pub fn get_total_mapped_virtual_memory() -> i64 {
+        TOTAL_MAPPED_VIRTUAL_MEMORY.load(Ordering::Relaxed)
+    }
+
+    #[inline]
+    pub fn get_total_mapped_files() -> i32 {
+        TOTAL_MAPPED_FILES.load(Ordering::Relaxed)
+    }
+
     #[inline]
     fn get_file_from_offset(file_name: &CheetahString) -> u64 {
         let file_from_offset = PathBuf::from(file_name.as_str())
@@ -172,14 +194,24 @@ impl DefaultMappedFile {
         file
     }
 
+    /// Creates a file that appends to a buffer of `transient_store_pool`, or to its mapping
+    /// like [`DefaultMappedFile::new`] when the pool has no buffer left.
     #[inline]
     pub fn new_with_transient_store_pool(
         file_name: CheetahString,
         file_size: u64,
         transient_store_pool: TransientStorePool,
     ) -> Self {
+        let Some(write_buffer) = transient_store_pool.borrow_buffer() else {
+            warn!(
+                "TransientStorePool is exhausted, {} is written through its mapping",
+                file_name
+            );
+            return Self::new(file_name, file_size);
+        };
         let file_from_offset = Self::get_file_from_offset(&file_name);
         let path_buf = PathBuf::from(file_name.as_str());
+        ensure_dir_ok(path_buf.parent().unwrap().to_str().unwrap());
         let file = OpenOptions::new()
             .read(true)
             .write(true)
@@ -190,6 +222,8 @@ impl DefaultMappedFile {
         file.set_len(file_size).unwrap();
 
         let mmap = unsafe { MmapMut::map_mut(&file).unwrap() };
+        TOTAL_MAPPED_VIRTUAL_MEMORY.fetch_add(file_size as i64, Ordering::Relaxed);
+        TOTAL_MAPPED_FILES.fetch_add(1, Ordering::Relaxed);
         Self {
             reference_resource: ReferenceResourceImpl::new(),
             file,
@@ -209,6 +243,7 @@ impl DefaultMappedFile {
             transient_store_pool: Some(transient_store_pool),
             stop_timestamp: 0,
             mmapped_file: SyncUnsafeCellWrapper::new(mmap),
+            write_buffer: SyncUnsafeCellWrapper::new(Some(write_buffer)),
         }
     }
 }
@@ -346,7 +381,7 @@ impl MappedFile for DefaultMappedFile {
 
         if current_pos + length <= self.file_size as usize {
             let mut mapped_file =
-                &mut self.get_mapped_file_mut()[current_pos..current_pos + length];
+                &mut self.get_write_buffer_mut()[current_pos..current_pos + length];
             if let Some(data_slice) = data.get(offset..offset + length) {
                 if mapped_file.write_all(data_slice).is_ok() {
                     self.wrote_position
@@ -368,7 +403,7 @@ impl MappedFile for DefaultMappedFile {
 
         if current_pos + length <= self.file_size as usize {
             let mut mapped_file =
-                &mut self.get_mapped_file_mut()[current_pos..current_pos + length];
+                &mut self.get_write_buffer_mut()[current_pos..current_pos + length];
 
             if let Some(data_slice) = data.get(offset..offset + length) {
                 if mapped_file.write_all(data_slice).is_ok() {
@@ -394,7 +429,7 @@ impl MappedFile for DefaultMappedFile {
 
         if current_pos + length <= self.file_size as usize {
             let mut mapped_file =
-                &mut self.get_mapped_file_mut()[current_pos..current_pos + length];
+                &mut self.get_write_buffer_mut()[current_pos..current_pos + length];
 
             if let Some(data_slice) = data.get(offset..offset + length) {
                 if mapped_file.write_all(data_slice).is_ok() {
@@ -412,7 +447,7 @@ impl MappedFile for DefaultMappedFile {
     #[inline]
     fn write_bytes_segment(&self, data: &[u8], start: usize, offset: usize, length: usize) -> bool {
         if start + length <= self.file_size as usize {
-            let mut mapped_file = &mut self.get_mapped_file_mut()[start..start + length];
+            let mut mapped_file = &mut self.get_write_buffer_mut()[start..start + length];
             if data.len() == length {
                 if mapped_file.write_all(data).is_ok() {
                     return true;
@@ -437,7 +472,7 @@ impl MappedFile for DefaultMappedFile {
         let length = data.len();
         let end_index = index + length;
         if length > 0 && end_index <= self.file_size as usize {
-            let mut mapped_file = &mut self.get_mapped_file_mut()[index..end_index];
+            let mut mapped_file = &mut self.get_write_buffer_mut()[index..end_index];
             if mapped_file.write_all(data).is_ok() {
                 return true;
             } else {
@@ -459,20 +494,20 @@ impl MappedFile for DefaultMappedFile {
                 let value = self.get_read_position();
                 self.mapped_byte_buffer_access_count_since_last_swap
                     .fetch_add(1, Ordering::AcqRel);
-                if self.transient_store_pool.is_none() {
-                    if let Err(e) = self.mmapped_file.flush() {
-                        error!("Error occurred when force data to disk: {:?}", e);
-                    } else {
-                        self.last_flush_time
-                            .store(get_current_millis(), Ordering::Relaxed);
-                    }
-                    MappedFile::release(self);
+                // committed data of a file with a write buffer is in the mapping as well, and
+                // a data sync of the file also writes back the pages dirtied through it
+                let flushed = if uring_file::is_enabled() {
+                    uring_file::sync_data(&self.file)
+                } else {
+                    self.mmapped_file.flush()
+                };
+                if let Err(e) = flushed {
+                    error!("Error occurred when force data to disk: {:?}", e);
                 } else {
-                    unimplemented!(
-                        // need to implement
-                        "flush transient store pool"
-                    );
+                    self.last_flush_time
+                        .store(get_current_millis(), Ordering::Relaxed);
                 }
+                MappedFile::release(self);
                 self.flushed_position.store(value, Ordering::Release);
             } else {
                 warn!(
@@ -488,10 +523,32 @@ impl MappedFile for DefaultMappedFile {
 
     #[inline]
     fn commit(&self, commit_least_pages: i32) -> i32 {
-        unimplemented!(
-            // need to implement
-            "commit"
-        )
+        if self.write_buffer.as_ref().is_none() {
+            // appended to the mapping, everything wrote counts as committed
+            return self.get_wrote_position();
+        }
+        if self
+            .transient_store_pool
+            .as_ref()
+            .is_some_and(|pool| !pool.is_real_commit())
+        {
+            self.committed_position
+                .store(self.get_wrote_position(), Ordering::Release);
+        } else if self.is_able_to_commit(commit_least_pages) {
+            if MappedFile::hold(self) {
+                self.commit0();
+                MappedFile::release(self);
+            } else {
+                warn!(
+                    "in commit, hold failed, commit offset = {}",
+                    self.committed_position.load(Ordering::Relaxed)
+                );
+            }
+        }
+        if self.get_committed_position() as u64 == self.file_size {
+            self.return_write_buffer();
+        }
+        self.get_committed_position()
     }
 
     #[inline]
@@ -548,12 +605,10 @@ impl MappedFile for DefaultMappedFile {
     fn get_last_modified_timestamp(&self) -> u64 {
         self.file
             .metadata()
-            .unwrap()
-            .modified()
-            .unwrap()
-            .elapsed()
-            .unwrap()
-            .as_millis() as u64
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |modified| modified.as_millis() as u64)
     }
 
     #[inline]
@@ -562,7 +617,11 @@ impl MappedFile for DefaultMappedFile {
         let read_end_position = pos + size;
         if read_end_position <= read_position as usize {
             if MappedFile::hold(self) {
-                let buffer = BytesMut::from(&self.mmapped_file.as_ref()[pos..read_end_position]);
+                let buffer = if uring_file::is_enabled() {
+                    self.read_through_io_uring(pos, size)
+                } else {
+                    BytesMut::from(&self.mmapped_file.as_ref()[pos..read_end_position])
+                };
                 MappedFile::release(self);
                 Some(buffer.freeze())
             } else {
@@ -585,6 +644,7 @@ impl MappedFile for DefaultMappedFile {
     #[inline]
     fn destroy(&self, interval_forcibly: u64) -> bool {
         MappedFile::shutdown(self, interval_forcibly);
+        self.return_write_buffer();
         if self.is_cleanup_over() {
             if let Err(e) = fs::remove_file(self.file_name.as_str()) {
                 error!("delete file failed: {:?}", e);
@@ -646,14 +706,12 @@ impl MappedFile for DefaultMappedFile {
         self.wrote_position.store(wrote_position, Ordering::SeqCst)
     }
 
+    /// Readers see the committed data only on a file with a write buffer.
     #[inline]
     fn get_read_position(&self) -> i32 {
         match self.transient_store_pool {
             None => self.wrote_position.load(Ordering::Acquire),
-            Some(_) => {
-                //need to optimize
-                self.wrote_position.load(Ordering::Acquire)
-            }
+            Some(_) => self.committed_position.load(Ordering::Acquire),
         }
     }
 
@@ -665,22 +723,91 @@ impl MappedFile for DefaultMappedFile {
 
     #[inline]
     fn get_committed_position(&self) -> i32 {
-        self.committed_position.load(Ordering::Relaxed)
+        self.committed_position.load(Ordering::Acquire)
     }
 
+    /// Locks the mapping in memory and advises the kernel it is needed soon, a failure is
+    /// only logged, mlock is bounded by `ulimit -l`.
     #[inline]
     fn mlock(&self) {
-        todo!()
+        #[cfg(unix)]
+        {
+            let begin = Instant::now();
+            let mapped_file = self.get_mapped_file();
+            let ret = mapped_file.lock();
+            info!(
+                "mlock {} {} ret = {:?} time consuming = {}ms",
+                self.file_name,
+                self.file_size,
+                ret,
+                begin.elapsed().as_millis()
+            );
+            let ret = mapped_file.advise(Advice::WillNeed);
+            info!(
+                "madvise {} {} ret = {:?} time consuming = {}ms",
+                self.file_name,
+                self.file_size,
+                ret,
+                begin.elapsed().as_millis()
+            );
+        }
     }
 
     #[inline]
     fn munlock(&self) {
-        todo!()
+        #[cfg(unix)]
+        {
+            let begin = Instant::now();
+            let ret = self.get_mapped_file().unlock();
+            info!(
+                "munlock {} {} ret = {:?} time consuming = {}ms",
+                self.file_name,
+                self.file_size,
+                ret,
+                begin.elapsed().as_millis()
+            );
+        }
     }
 
-    #[inline]
+    /// Touches every page of the mapping so that appending to the file does not fault them in,
+    /// then locks it in memory. A `SYNC_FLUSH` store flushes every `pages` pages on the way,
+    /// so that the warm-up does not leave the whole file to write back at once.
     fn warm_mapped_file(&self, flush_disk_type: FlushDiskType, pages: usize) {
-        todo!()
+        self.mapped_byte_buffer_access_count_since_last_swap
+            .fetch_add(1, Ordering::AcqRel);
+        let begin = Instant::now();
+        let mapped_file = self.get_mapped_file_mut();
+        let page_size = OS_PAGE_SIZE as usize;
+        let mut flush = 0;
+        for i in (0..self.file_size as usize).step_by(page_size) {
+            mapped_file[i] = 0;
+            // force flush when flush disk type is sync
+            if flush_disk_type == FlushDiskType::SyncFlush
+                && i / page_size - flush / page_size >= pages
+            {
+                if let Err(e) = mapped_file.flush_range(flush, i - flush) {
+                    error!("flush {} while warming it up failed, {}", self.file_name, e);
+                }
+                flush = i;
+            }
+        }
+        // force flush when prepare load finished
+        if flush_disk_type == FlushDiskType::SyncFlush {
+            info!(
+                "mapped file warm-up done, force to disk, mappedFile={}, costTime={}ms",
+                self.file_name,
+                begin.elapsed().as_millis()
+            );
+            if let Err(e) = mapped_file.flush() {
+                error!("flush {} after warming it up failed, {}", self.file_name, e);
+            }
+        }
+        info!(
+            "mapped file warm-up done. mappedFile={}, costTime={}ms",
+            self.file_name,
+            begin.elapsed().as_millis()
+        );
+        self.mlock();
     }
 
     #[inline]
@@ -849,6 +976,80 @@ impl DefaultMappedFile {
         self.mmapped_file.as_ref()
     }
 
+    /// The write buffer while the file has one, its mapping otherwise.
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    fn get_write_buffer_mut(&self) -> &mut [u8] {
+        match self.write_buffer.mut_from_ref() {
+            Some(write_buffer) => write_buffer.as_mut(),
+            None => self.get_mapped_file_mut().as_mut(),
+        }
+    }
+
+    #[inline]
+    fn is_able_to_commit(&self, commit_least_pages: i32) -> bool {
+        if self.is_full() {
+            return true;
+        }
+        let commit = self.committed_position.load(Ordering::Relaxed);
+        let write = self.wrote_position.load(Ordering::Acquire);
+        if commit_least_pages > 0 {
+            return (write / OS_PAGE_SIZE as i32) - (commit / OS_PAGE_SIZE as i32)
+                >= commit_least_pages;
+        }
+        write > commit
+    }
+
+    /// Copies what was wrote since the last commit from the write buffer to the mapping.
+    fn commit0(&self) {
+        let write_pos = self.wrote_position.load(Ordering::Acquire) as usize;
+        let last_committed_position = self.committed_position.load(Ordering::Relaxed) as usize;
+        if write_pos <= last_committed_position {
+            return;
+        }
+        if let Some(write_buffer) = self.write_buffer.as_ref() {
+            let committed = &write_buffer[last_committed_position..write_pos];
+            let wrote = uring_file::is_enabled()
+                && uring_file::write_all_at(&self.file, committed, last_committed_position as u64)
+                    .map_err(|e| {
+                        warn!(
+                            "commit {} through io_uring failed, copy it to the mapping, {}",
+                            self.file_name, e
+                        )
+                    })
+                    .is_ok();
+            if !wrote {
+                self.get_mapped_file_mut()[last_committed_position..write_pos]
+                    .copy_from_slice(committed);
+            }
+        }
+        self.committed_position
+            .store(write_pos as i32, Ordering::Release);
+    }
+
+    /// Reads `[pos, pos + size)` from the file through io_uring, from the mapping when that
+    /// fails.
+    fn read_through_io_uring(&self, pos: usize, size: usize) -> BytesMut {
+        let mut buffer = BytesMut::zeroed(size);
+        if let Err(e) = uring_file::read_exact_at(&self.file, &mut buffer, pos as u64) {
+            warn!(
+                "read {} through io_uring failed, read the mapping, {}",
+                self.file_name, e
+            );
+            buffer.copy_from_slice(&self.mmapped_file.as_ref()[pos..pos + size]);
+        }
+        buffer
+    }
+
+    /// Hands the write buffer back to the pool, only once nothing is appended to it anymore.
+    fn return_write_buffer(&self) {
+        if let Some(write_buffer) = self.write_buffer.mut_from_ref().take() {
+            if let Some(transient_store_pool) = self.transient_store_pool.as_ref() {
+                transient_store_pool.return_buffer(write_buffer);
+            }
+        }
+    }
+
     #[inline]
     fn is_able_to_flush(&self, flush_least_pages: i32) -> bool {
         if self.is_full() {
@@ -915,3 +1116,67 @@ impl ReferenceResource for DefaultMappedFile {
         self.reference_resource.is_cleanup_over()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file_name(dir: &Path, offset: u64) -> CheetahString {
+        CheetahString::from_string(
+            dir.join(format!("{:020}", offset))
+                .to_string_lossy()
+                .to_string(),
+        )
+    }
+
+    #[test]
+    fn write_buffer_is_read_once_committed() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_size = OS_PAGE_SIZE * 4;
+        let pool = TransientStorePool::new(1, file_size as usize);
+        pool.init();
+        let mapped_file = DefaultMappedFile::new_with_transient_store_pool(
+            file_name(dir.path(), 0),
+            file_size,
+            pool.clone(),
+        );
+        assert_eq!(pool.available_buffer_nums(), 0);
+
+        // the pool is exhausted, the next file appends to its mapping
+        let fallback = DefaultMappedFile::new_with_transient_store_pool(
+            file_name(dir.path(), file_size),
+            file_size,
+            pool.clone(),
+        );
+        assert!(fallback.append_message_bytes(b"direct"));
+        assert_eq!(fallback.get_read_position(), 6);
+        assert_eq!(fallback.commit(0), 6);
+
+        assert!(mapped_file.append_message_bytes(b"hello"));
+        assert_eq!(mapped_file.get_wrote_position(), 5);
+        assert_eq!(mapped_file.get_read_position(), 0);
+        assert_eq!(mapped_file.get_mapped_file()[..5], [0u8; 5]);
+        assert_eq!(mapped_file.commit(1), 0);
+        assert_eq!(mapped_file.commit(0), 5);
+        assert_eq!(mapped_file.get_read_position(), 5);
+        assert_eq!(&mapped_file.get_mapped_file()[..5], b"hello");
+
+        mapped_file.set_wrote_position(file_size as i32);
+        assert_eq!(mapped_file.commit(0), file_size as i32);
+        assert_eq!(pool.available_buffer_nums(), 1);
+    }
+
+    #[test]
+    fn warm_mapped_file_keeps_the_file_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_size = OS_PAGE_SIZE * 8;
+        let mapped_file = DefaultMappedFile::new(file_name(dir.path(), 0), file_size);
+
+        mapped_file.warm_mapped_file(FlushDiskType::SyncFlush, 2);
+        assert!(mapped_file.get_mapped_file().iter().all(|byte| *byte == 0));
+        assert_eq!(mapped_file.get_wrote_position(), 0);
+        assert!(mapped_file.append_message_bytes(b"hello"));
+        assert_eq!(mapped_file.flush(0), 5);
+        assert_eq!(mapped_file.get_data(0, 5).unwrap().as_ref(), b"hello");
+    }
+}
@@ -0,0 +1,234 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! File IO of the mapped files through io_uring.
+//!
+//! Every thread submitting IO gets its own ring, created on its first IO. Without the
+//! `io_uring` feature or off Linux, [`is_enabled`] is always `false` and the mapped files
+//! read, commit and flush through their mappings.
+
+pub use imp::*;
+
+#[cfg(all(feature = "io_uring", target_os = "linux"))]
+mod imp {
+    use std::cell::RefCell;
+    use std::fs::File;
+    use std::io;
+    use std::os::fd::AsRawFd;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::atomic::AtomicU32;
+    use std::sync::atomic::Ordering;
+
+    use io_uring::opcode;
+    use io_uring::squeue;
+    use io_uring::types;
+    use io_uring::IoUring;
+    use tracing::info;
+    use tracing::warn;
+
+    use crate::config::message_store_config::MessageStoreConfig;
+
+    /// Bytes read or wrote by a single submission.
+    const MAX_IO_SIZE: usize = 1024 * 1024;
+
+    static ENABLED: AtomicBool = AtomicBool::new(false);
+    static ENTRIES: AtomicU32 = AtomicU32::new(256);
+
+    thread_local! {
+        static RING: RefCell<Option<IoUring>> = const { RefCell::new(None) };
+    }
+
+    /// Enables io_uring when `enable_io_uring` is set and the kernel lets this process set up
+    /// a ring.
+    pub fn init(message_store_config: &MessageStoreConfig) {
+        if !message_store_config.enable_io_uring {
+            ENABLED.store(false, Ordering::Release);
+            return;
+        }
+        let entries = message_store_config.io_uring_entries.max(1);
+        match IoUring::new(entries) {
+            Ok(_) => {
+                ENTRIES.store(entries, Ordering::Release);
+                ENABLED.store(true, Ordering::Release);
+                info!(
+                    "the store files are accessed through io_uring, entries: {}",
+                    entries
+                );
+            }
+            Err(e) => {
+                ENABLED.store(false, Ordering::Release);
+                warn!(
+                    "setting up io_uring failed, the store files are accessed through their \
+                     mappings, {}",
+                    e
+                );
+            }
+        }
+    }
+
+    #[inline]
+    pub fn is_enabled() -> bool {
+        ENABLED.load(Ordering::Acquire)
+    }
+
+    /// Writes all of `buf` to `file` at `offset`.
+    pub fn write_all_at(file: &File, mut buf: &[u8], mut offset: u64) -> io::Result<()> {
+        while !buf.is_empty() {
+            let len = buf.len().min(MAX_IO_SIZE);
+            let entry = opcode::Write::new(types::Fd(file.as_raw_fd()), buf.as_ptr(), len as u32)
+                .offset(offset)
+                .build();
+            // SAFETY: `buf` outlives the submission, it is waited for below
+            let wrote = unsafe { submit_and_wait(&entry)? } as usize;
+            if wrote == 0 {
+                return Err(io::ErrorKind::WriteZero.into());
+            }
+            buf = &buf[wrote..];
+            offset += wrote as u64;
+        }
+        Ok(())
+    }
+
+    /// Fills `buf` with the bytes of `file` from `offset`.
+    pub fn read_exact_at(file: &File, mut buf: &mut [u8], mut offset: u64) -> io::Result<()> {
+        while !buf.is_empty() {
+            let len = buf.len().min(MAX_IO_SIZE);
+            let entry =
+                opcode::Read::new(types::Fd(file.as_raw_fd()), buf.as_mut_ptr(), len as u32)
+                    .offset(offset)
+                    .build();
+            // SAFETY: `buf` outlives the submission, it is waited for below
+            let read = unsafe { submit_and_wait(&entry)? } as usize;
+            if read == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            buf = &mut buf[read..];
+            offset += read as u64;
+        }
+        Ok(())
+    }
+
+    /// Writes the dirty pages of `file` to the device, the ones dirtied through a shared
+    /// mapping of it included.
+    pub fn sync_data(file: &File) -> io::Result<()> {
+        let entry = opcode::Fsync::new(types::Fd(file.as_raw_fd()))
+            .flags(types::FsyncFlags::DATASYNC)
+            .build();
+        // SAFETY: a fsync does not reference any buffer
+        unsafe { submit_and_wait(&entry) }.map(|_| ())
+    }
+
+    /// Submits `entry` to the ring of the current thread and waits for its completion.
+    ///
+    /// # Safety
+    ///
+    /// The buffers referenced by `entry` must stay valid until this returns.
+    unsafe fn submit_and_wait(entry: &squeue::Entry) -> io::Result<i32> {
+        RING.with(|ring| {
+            let mut ring = ring.borrow_mut();
+            if ring.is_none() {
+                *ring = Some(IoUring::new(ENTRIES.load(Ordering::Acquire))?);
+            }
+            let ring = ring.as_mut().unwrap();
+            ring.submission()
+                .push(entry)
+                .map_err(|_| io::Error::other("io_uring submission queue is full"))?;
+            ring.submit_and_wait(1)?;
+            let cqe = ring
+                .completion()
+                .next()
+                .ok_or_else(|| io::Error::other("io_uring completed nothing"))?;
+            if cqe.result() < 0 {
+                Err(io::Error::from_raw_os_error(-cqe.result()))
+            } else {
+                Ok(cqe.result())
+            }
+        })
+    }
+}
+
+#[cfg(not(all(feature = "io_uring", target_os = "linux")))]
+mod imp {
+    use std::fs::File;
+    use std::io;
+
+    use tracing::warn;
+
+    use crate::config::message_store_config::MessageStoreConfig;
+
+    pub fn init(message_store_config: &MessageStoreConfig) {
+        if message_store_config.enable_io_uring {
+            warn!(
+                "enableIoUring requires the io_uring feature on Linux, the store files are \
+                 accessed through their mappings"
+            );
+        }
+    }
+
+    #[inline]
+    pub fn is_enabled() -> bool {
+        false
+    }
+
+    pub fn write_all_at(file: &File, buf: &[u8], offset: u64) -> io::Result<()> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    pub fn read_exact_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<()> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    pub fn sync_data(file: &File) -> io::Result<()> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+}
+
+#[cfg(all(test, feature = "io_uring", target_os = "linux"))]
+mod tests {
+    use std::fs::OpenOptions;
+
+    use super::*;
+    use crate::config::message_store_config::MessageStoreConfig;
+
+    #[test]
+    fn writes_reads_and_syncs_through_io_uring() {
+        init(&MessageStoreConfig {
+            enable_io_uring: true,
+            ..MessageStoreConfig::default()
+        });
+        if !is_enabled() {
+            // io_uring may be forbidden in the sandbox running the tests
+            return;
+        }
+        let temp_dir = tempfile::tempdir().unwrap();
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(temp_dir.path().join("00000000000000000000"))
+            .unwrap();
+        file.set_len(8192).unwrap();
+
+        write_all_at(&file, b"rocketmq", 4096).unwrap();
+        sync_data(&file).unwrap();
+        let mut buf = [0u8; 8];
+        read_exact_at(&file, &mut buf, 4096).unwrap();
+        assert_eq!(&buf, b"rocketmq");
+        assert!(read_exact_at(&file, &mut [0u8; 16], 8190).is_err());
+    }
+}
@@ -0,0 +1,157 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use cheetah_string::CheetahString;
+use parking_lot::RwLock;
+
+use crate::base::commit_log_dispatcher::CommitLogDispatcher;
+use crate::base::dispatch_request::DispatchRequest;
+
+/// The newest store timestamp of every topic with messages in a commit log file.
+#[derive(Default)]
+struct FileTopics {
+    /// Whether the file was dispatched from its first message, so no topic of it is missing.
+    complete: bool,
+    newest_store_timestamps: HashMap<CheetahString, i64>,
+}
+
+/// Tracks the topics of the commit log files, so a file expires by the `reserve.time` of the
+/// topics in it instead of `fileReservedTime`.
+///
+/// A file is only known once dispatched from its first message, the files dispatched before the
+/// broker started keep the broker retention.
+pub struct TopicRetention {
+    mapped_file_size: i64,
+    files: RwLock<BTreeMap<i64, FileTopics>>,
+}
+
+impl TopicRetention {
+    pub fn new(mapped_file_size: i64) -> Self {
+        Self {
+            mapped_file_size: mapped_file_size.max(1),
+            files: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn record(&self, commit_log_offset: i64, topic: &CheetahString, store_timestamp: i64) {
+        let file_from_offset = commit_log_offset - commit_log_offset % self.mapped_file_size;
+        let mut files = self.files.write();
+        let file_topics = files.entry(file_from_offset).or_insert_with(|| FileTopics {
+            complete: commit_log_offset == file_from_offset,
+            ..FileTopics::default()
+        });
+        let newest = file_topics
+            .newest_store_timestamps
+            .entry(topic.clone())
+            .or_insert(store_timestamp);
+        *newest = (*newest).max(store_timestamp);
+    }
+
+    /// The millis after which no message of the file at `file_from_offset` is to be kept,
+    /// `None` when the topics of the file are not all known.
+    ///
+    /// `reserve_time` gives the hours the messages of a topic are kept, `-1` for the topics kept
+    /// `default_reserved_time` millis.
+    pub fn expire_timestamp(
+        &self,
+        file_from_offset: i64,
+        default_reserved_time: i64,
+        reserve_time: impl Fn(&CheetahString) -> i64,
+    ) -> Option<i64> {
+        let files = self.files.read();
+        let file_topics = files.get(&file_from_offset).filter(|file| file.complete)?;
+        file_topics
+            .newest_store_timestamps
+            .iter()
+            .map(|(topic, newest)| match reserve_time(topic) {
+                hours if hours >= 0 => newest.saturating_add(hours.saturating_mul(60 * 60 * 1000)),
+                _ => newest.saturating_add(default_reserved_time),
+            })
+            .max()
+    }
+
+    /// Forgets the files before `min_offset`, no longer in the commit log.
+    pub fn forget_files_before(&self, min_offset: i64) {
+        let mut files = self.files.write();
+        *files = files.split_off(&min_offset);
+    }
+}
+
+/// Records the topics of the dispatched messages in the [`TopicRetention`].
+pub struct CommitLogDispatcherTopicRetention {
+    topic_retention: Arc<TopicRetention>,
+}
+
+impl CommitLogDispatcherTopicRetention {
+    pub fn new(topic_retention: Arc<TopicRetention>) -> Self {
+        Self { topic_retention }
+    }
+}
+
+impl CommitLogDispatcher for CommitLogDispatcherTopicRetention {
+    fn dispatch(&self, dispatch_request: &mut DispatchRequest) {
+        self.topic_retention.record(
+            dispatch_request.commit_log_offset,
+            &dispatch_request.topic,
+            dispatch_request.store_timestamp,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: i64 = 60 * 60 * 1000;
+
+    #[test]
+    fn a_file_expires_with_the_longest_kept_topic() {
+        let topic_retention = TopicRetention::new(1024);
+        let short = CheetahString::from_static_str("short");
+        let long = CheetahString::from_static_str("long");
+        let reserve_time = |topic: &CheetahString| if topic.as_str() == "short" { 1 } else { -1 };
+
+        topic_retention.record(0, &short, 1000);
+        topic_retention.record(100, &short, 2000);
+        assert_eq!(
+            topic_retention.expire_timestamp(0, 72 * HOUR, reserve_time),
+            Some(2000 + HOUR)
+        );
+        topic_retention.record(200, &long, 1500);
+        assert_eq!(
+            topic_retention.expire_timestamp(0, 72 * HOUR, reserve_time),
+            Some(1500 + 72 * HOUR)
+        );
+
+        // dispatched from the middle of the file, some topics may be missing
+        topic_retention.record(1024 + 100, &short, 3000);
+        assert_eq!(
+            topic_retention.expire_timestamp(1024, 72 * HOUR, reserve_time),
+            None
+        );
+
+        topic_retention.forget_files_before(1024);
+        assert_eq!(
+            topic_retention.expire_timestamp(0, 72 * HOUR, reserve_time),
+            None
+        );
+    }
+}
@@ -15,5 +15,7 @@
  * limitations under the License.
  */
 
+pub mod boxed_message_store;
 #[cfg(feature = "local_file_store")]
 pub mod default_message_store;
+pub mod message_store_factory;
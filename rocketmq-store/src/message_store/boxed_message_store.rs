@@ -0,0 +1,919 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::any::Any;
+use std::collections::HashMap;
+use std::error::Error;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use cheetah_string::CheetahString;
+use parking_lot::RwLock;
+use rocketmq_common::common::boundary_type::BoundaryType;
+use rocketmq_common::common::message::message_batch::MessageExtBatch;
+use rocketmq_common::common::message::message_ext::MessageExt;
+use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+use rocketmq_rust::ArcMut;
+
+use crate::base::commit_log_dispatcher::CommitLogDispatcher;
+use crate::base::dispatch_request::DispatchRequest;
+use crate::base::get_message_result::GetMessageResult;
+use crate::base::message_arriving_listener::MessageArrivingListener;
+use crate::base::message_result::PutMessageResult;
+use crate::base::query_message_result::QueryMessageResult;
+use crate::base::select_result::SelectMappedBufferResult;
+use crate::config::message_store_config::MessageStoreConfig;
+use crate::dledger::dledger_server::DLedgerRoleChangeHandler;
+use crate::filter::MessageFilter;
+use crate::ha::auto_switch_ha_service::AutoSwitchHAService;
+use crate::hook::put_message_hook::BoxedPutMessageHook;
+use crate::log_file::MessageStore;
+use crate::queue::ArcConsumeQueue;
+use crate::stats::broker_stats_manager::BrokerStatsManager;
+use crate::store::running_flags::RunningFlags;
+use crate::timer::timer_message_store::TimerMessageStore;
+
+type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Object safe form of [`MessageStore`], the async methods return boxed futures.
+///
+/// It is implemented for every store held in an [`ArcMut`], the way the broker holds its store,
+/// and is only meant to sit behind a [`BoxedMessageStore`].
+pub trait DynMessageStore: Send + Sync + 'static {
+    fn as_any(&self) -> &dyn Any;
+
+    fn load(&mut self) -> BoxFuture<'_, bool>;
+
+    fn start(&mut self) -> Result<(), Box<dyn Error>>;
+
+    fn shutdown(&mut self);
+
+    fn set_confirm_offset(&mut self, phy_offset: i64);
+
+    fn get_max_phy_offset(&self) -> i64;
+
+    fn set_broker_init_max_offset(&mut self, broker_init_max_offset: i64);
+
+    fn now(&self) -> u64;
+
+    fn get_state_machine_version(&self) -> i64;
+
+    fn put_message(&mut self, msg: MessageExtBrokerInner) -> BoxFuture<'_, PutMessageResult>;
+
+    fn put_messages(&mut self, msg_batch: MessageExtBatch) -> BoxFuture<'_, PutMessageResult>;
+
+    fn truncate_files(&mut self, offset_to_truncate: i64) -> bool;
+
+    fn is_os_page_cache_busy(&self) -> bool;
+
+    fn get_running_flags(&self) -> &RunningFlags;
+
+    fn is_shutdown(&self) -> bool;
+
+    fn get_put_message_hook_list(&self) -> Arc<RwLock<Vec<BoxedPutMessageHook>>>;
+
+    fn set_put_message_hook(&self, put_message_hook: BoxedPutMessageHook);
+
+    fn add_first_dispatcher(&self, dispatcher: Box<dyn CommitLogDispatcher>);
+
+    fn add_dispatcher(&self, dispatcher: Box<dyn CommitLogDispatcher>);
+
+    fn get_broker_stats_manager(&self) -> Option<Arc<BrokerStatsManager>>;
+
+    fn dispatch_behind_bytes(&self) -> i64;
+
+    fn get_min_offset_in_queue(&self, topic: &CheetahString, queue_id: i32) -> i64;
+
+    fn get_max_offset_in_queue(&self, topic: &CheetahString, queue_id: i32) -> i64;
+
+    fn get_offset_in_queue_by_time_with_boundary(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        timestamp: i64,
+        boundary_type: BoundaryType,
+    ) -> i64;
+
+    fn get_max_offset_in_queue_committed(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        committed: bool,
+    ) -> i64;
+
+    fn get_message<'a>(
+        &'a self,
+        group: &'a CheetahString,
+        topic: &'a CheetahString,
+        queue_id: i32,
+        offset: i64,
+        max_msg_nums: i32,
+        message_filter: Option<Arc<Box<dyn MessageFilter>>>,
+    ) -> BoxFuture<'a, Option<GetMessageResult>>;
+
+    #[allow(clippy::too_many_arguments)]
+    fn get_message_with_total_size<'a>(
+        &'a self,
+        group: &'a CheetahString,
+        topic: &'a CheetahString,
+        queue_id: i32,
+        offset: i64,
+        max_msg_nums: i32,
+        max_total_msg_size: i32,
+        message_filter: Option<Arc<Box<dyn MessageFilter>>>,
+    ) -> BoxFuture<'a, Option<GetMessageResult>>;
+
+    fn check_in_mem_by_consume_offset(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        consume_offset: i64,
+        batch_size: i32,
+    ) -> bool;
+
+    fn is_msg_in_cold_area(
+        &self,
+        group: &CheetahString,
+        topic: &CheetahString,
+        queue_id: i32,
+        consume_offset: i64,
+    ) -> bool;
+
+    fn notify_message_arrive_if_necessary(&self, dispatch_request: &mut DispatchRequest);
+
+    fn set_message_arriving_listener(
+        &mut self,
+        message_arriving_listener: Option<
+            Arc<Box<dyn MessageArrivingListener + Sync + Send + 'static>>,
+        >,
+    );
+
+    fn find_consume_queue(&self, topic: &CheetahString, queue_id: i32) -> Option<ArcConsumeQueue>;
+
+    fn delete_topics(&mut self, delete_topics: Vec<&CheetahString>) -> i32;
+
+    fn query_message<'a>(
+        &'a self,
+        topic: &'a CheetahString,
+        key: &'a CheetahString,
+        max_num: i32,
+        begin_timestamp: i64,
+        end_timestamp: i64,
+    ) -> BoxFuture<'a, Option<QueryMessageResult>>;
+
+    fn select_one_message_by_offset(
+        &self,
+        commit_log_offset: i64,
+    ) -> BoxFuture<'_, Option<SelectMappedBufferResult>>;
+
+    fn select_one_message_by_offset_with_size(
+        &self,
+        commit_log_offset: i64,
+        size: i32,
+    ) -> BoxFuture<'_, Option<SelectMappedBufferResult>>;
+
+    fn look_message_by_offset(&self, commit_log_offset: i64) -> Option<MessageExt>;
+
+    fn look_message_by_offset_with_size(
+        &self,
+        commit_log_offset: i64,
+        size: i32,
+    ) -> Option<MessageExt>;
+
+    fn get_message_store_timestamp(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        consume_queue_offset: i64,
+    ) -> i64;
+
+    fn get_earliest_message_time_in_queue(&self, topic: &CheetahString, queue_id: i32) -> i64;
+
+    fn get_latest_message_time_in_queue(&self, topic: &CheetahString, queue_id: i32) -> i64;
+
+    fn get_runtime_info(&self) -> HashMap<String, String>;
+
+    fn lock_time_mills(&self) -> i64;
+
+    fn get_earliest_message_time(&self) -> i64;
+
+    fn get_timer_message_store(&self) -> Arc<TimerMessageStore>;
+
+    fn set_timer_message_store(&mut self, timer_message_store: Arc<TimerMessageStore>);
+
+    fn remain_transient_store_buffer_nums(&self) -> i32;
+
+    fn remain_how_many_data_to_commit(&self) -> i64;
+
+    fn remain_how_many_data_to_flush(&self) -> i64;
+
+    fn get_message_store_config(&self) -> &MessageStoreConfig;
+
+    fn set_alive_replica_num_in_group(&mut self, alive_replica_nums: i32);
+
+    fn get_alive_replica_num_in_group(&self) -> i32;
+
+    fn update_ha_master_address(&self, new_addr: &CheetahString);
+
+    fn set_dledger_role_change_handler(&self, handler: Arc<dyn DLedgerRoleChangeHandler>);
+
+    fn get_auto_switch_ha_service(&self) -> Option<Arc<AutoSwitchHAService>>;
+
+    fn update_runtime_config(&self, key: &str, value: &str) -> Result<bool, String>;
+}
+
+impl<MS: MessageStore> DynMessageStore for ArcMut<MS> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn load(&mut self) -> BoxFuture<'_, bool> {
+        Box::pin(MessageStore::load(&mut **self))
+    }
+
+    fn start(&mut self) -> Result<(), Box<dyn Error>> {
+        MessageStore::start(&mut **self)
+    }
+
+    fn shutdown(&mut self) {
+        MessageStore::shutdown(&mut **self)
+    }
+
+    fn set_confirm_offset(&mut self, phy_offset: i64) {
+        MessageStore::set_confirm_offset(&mut **self, phy_offset)
+    }
+
+    fn get_max_phy_offset(&self) -> i64 {
+        MessageStore::get_max_phy_offset(&**self)
+    }
+
+    fn set_broker_init_max_offset(&mut self, broker_init_max_offset: i64) {
+        MessageStore::set_broker_init_max_offset(&mut **self, broker_init_max_offset)
+    }
+
+    fn now(&self) -> u64 {
+        MessageStore::now(&**self)
+    }
+
+    fn get_state_machine_version(&self) -> i64 {
+        MessageStore::get_state_machine_version(&**self)
+    }
+
+    fn put_message(&mut self, msg: MessageExtBrokerInner) -> BoxFuture<'_, PutMessageResult> {
+        Box::pin(MessageStore::put_message(&mut **self, msg))
+    }
+
+    fn put_messages(&mut self, msg_batch: MessageExtBatch) -> BoxFuture<'_, PutMessageResult> {
+        Box::pin(MessageStore::put_messages(&mut **self, msg_batch))
+    }
+
+    fn truncate_files(&mut self, offset_to_truncate: i64) -> bool {
+        MessageStore::truncate_files(&mut **self, offset_to_truncate)
+    }
+
+    fn is_os_page_cache_busy(&self) -> bool {
+        MessageStore::is_os_page_cache_busy(&**self)
+    }
+
+    fn get_running_flags(&self) -> &RunningFlags {
+        MessageStore::get_running_flags(&**self)
+    }
+
+    fn is_shutdown(&self) -> bool {
+        MessageStore::is_shutdown(&**self)
+    }
+
+    fn get_put_message_hook_list(&self) -> Arc<RwLock<Vec<BoxedPutMessageHook>>> {
+        MessageStore::get_put_message_hook_list(&**self)
+    }
+
+    fn set_put_message_hook(&self, put_message_hook: BoxedPutMessageHook) {
+        MessageStore::set_put_message_hook(&**self, put_message_hook)
+    }
+
+    fn add_first_dispatcher(&self, dispatcher: Box<dyn CommitLogDispatcher>) {
+        MessageStore::add_first_dispatcher(&**self, dispatcher)
+    }
+
+    fn add_dispatcher(&self, dispatcher: Box<dyn CommitLogDispatcher>) {
+        MessageStore::add_dispatcher(&**self, dispatcher)
+    }
+
+    fn get_broker_stats_manager(&self) -> Option<Arc<BrokerStatsManager>> {
+        MessageStore::get_broker_stats_manager(&**self)
+    }
+
+    fn dispatch_behind_bytes(&self) -> i64 {
+        MessageStore::dispatch_behind_bytes(&**self)
+    }
+
+    fn get_min_offset_in_queue(&self, topic: &CheetahString, queue_id: i32) -> i64 {
+        MessageStore::get_min_offset_in_queue(&**self, topic, queue_id)
+    }
+
+    fn get_max_offset_in_queue(&self, topic: &CheetahString, queue_id: i32) -> i64 {
+        MessageStore::get_max_offset_in_queue(&**self, topic, queue_id)
+    }
+
+    fn get_offset_in_queue_by_time_with_boundary(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        timestamp: i64,
+        boundary_type: BoundaryType,
+    ) -> i64 {
+        MessageStore::get_offset_in_queue_by_time_with_boundary(
+            &**self,
+            topic,
+            queue_id,
+            timestamp,
+            boundary_type,
+        )
+    }
+
+    fn get_max_offset_in_queue_committed(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        committed: bool,
+    ) -> i64 {
+        MessageStore::get_max_offset_in_queue_committed(&**self, topic, queue_id, committed)
+    }
+
+    fn get_message<'a>(
+        &'a self,
+        group: &'a CheetahString,
+        topic: &'a CheetahString,
+        queue_id: i32,
+        offset: i64,
+        max_msg_nums: i32,
+        message_filter: Option<Arc<Box<dyn MessageFilter>>>,
+    ) -> BoxFuture<'a, Option<GetMessageResult>> {
+        Box::pin(MessageStore::get_message(
+            &**self,
+            group,
+            topic,
+            queue_id,
+            offset,
+            max_msg_nums,
+            message_filter,
+        ))
+    }
+
+    fn get_message_with_total_size<'a>(
+        &'a self,
+        group: &'a CheetahString,
+        topic: &'a CheetahString,
+        queue_id: i32,
+        offset: i64,
+        max_msg_nums: i32,
+        max_total_msg_size: i32,
+        message_filter: Option<Arc<Box<dyn MessageFilter>>>,
+    ) -> BoxFuture<'a, Option<GetMessageResult>> {
+        Box::pin(MessageStore::get_message_with_total_size(
+            &**self,
+            group,
+            topic,
+            queue_id,
+            offset,
+            max_msg_nums,
+            max_total_msg_size,
+            message_filter,
+        ))
+    }
+
+    fn check_in_mem_by_consume_offset(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        consume_offset: i64,
+        batch_size: i32,
+    ) -> bool {
+        MessageStore::check_in_mem_by_consume_offset(
+            &**self,
+            topic,
+            queue_id,
+            consume_offset,
+            batch_size,
+        )
+    }
+
+    fn is_msg_in_cold_area(
+        &self,
+        group: &CheetahString,
+        topic: &CheetahString,
+        queue_id: i32,
+        consume_offset: i64,
+    ) -> bool {
+        MessageStore::is_msg_in_cold_area(&**self, group, topic, queue_id, consume_offset)
+    }
+
+    fn notify_message_arrive_if_necessary(&self, dispatch_request: &mut DispatchRequest) {
+        MessageStore::notify_message_arrive_if_necessary(&**self, dispatch_request)
+    }
+
+    fn set_message_arriving_listener(
+        &mut self,
+        message_arriving_listener: Option<
+            Arc<Box<dyn MessageArrivingListener + Sync + Send + 'static>>,
+        >,
+    ) {
+        MessageStore::set_message_arriving_listener(&mut **self, message_arriving_listener)
+    }
+
+    fn find_consume_queue(&self, topic: &CheetahString, queue_id: i32) -> Option<ArcConsumeQueue> {
+        MessageStore::find_consume_queue(&**self, topic, queue_id)
+    }
+
+    fn delete_topics(&mut self, delete_topics: Vec<&CheetahString>) -> i32 {
+        MessageStore::delete_topics(&mut **self, delete_topics)
+    }
+
+    fn query_message<'a>(
+        &'a self,
+        topic: &'a CheetahString,
+        key: &'a CheetahString,
+        max_num: i32,
+        begin_timestamp: i64,
+        end_timestamp: i64,
+    ) -> BoxFuture<'a, Option<QueryMessageResult>> {
+        Box::pin(MessageStore::query_message(
+            &**self,
+            topic,
+            key,
+            max_num,
+            begin_timestamp,
+            end_timestamp,
+        ))
+    }
+
+    fn select_one_message_by_offset(
+        &self,
+        commit_log_offset: i64,
+    ) -> BoxFuture<'_, Option<SelectMappedBufferResult>> {
+        Box::pin(MessageStore::select_one_message_by_offset(
+            &**self,
+            commit_log_offset,
+        ))
+    }
+
+    fn select_one_message_by_offset_with_size(
+        &self,
+        commit_log_offset: i64,
+        size: i32,
+    ) -> BoxFuture<'_, Option<SelectMappedBufferResult>> {
+        Box::pin(MessageStore::select_one_message_by_offset_with_size(
+            &**self,
+            commit_log_offset,
+            size,
+        ))
+    }
+
+    fn look_message_by_offset(&self, commit_log_offset: i64) -> Option<MessageExt> {
+        MessageStore::look_message_by_offset(&**self, commit_log_offset)
+    }
+
+    fn look_message_by_offset_with_size(
+        &self,
+        commit_log_offset: i64,
+        size: i32,
+    ) -> Option<MessageExt> {
+        MessageStore::look_message_by_offset_with_size(&**self, commit_log_offset, size)
+    }
+
+    fn get_message_store_timestamp(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        consume_queue_offset: i64,
+    ) -> i64 {
+        MessageStore::get_message_store_timestamp(&**self, topic, queue_id, consume_queue_offset)
+    }
+
+    fn get_earliest_message_time_in_queue(&self, topic: &CheetahString, queue_id: i32) -> i64 {
+        MessageStore::get_earliest_message_time_in_queue(&**self, topic, queue_id)
+    }
+
+    fn get_latest_message_time_in_queue(&self, topic: &CheetahString, queue_id: i32) -> i64 {
+        MessageStore::get_latest_message_time_in_queue(&**self, topic, queue_id)
+    }
+
+    fn get_runtime_info(&self) -> HashMap<String, String> {
+        MessageStore::get_runtime_info(&**self)
+    }
+
+    fn lock_time_mills(&self) -> i64 {
+        MessageStore::lock_time_mills(&**self)
+    }
+
+    fn get_earliest_message_time(&self) -> i64 {
+        MessageStore::get_earliest_message_time(&**self)
+    }
+
+    fn get_timer_message_store(&self) -> Arc<TimerMessageStore> {
+        MessageStore::get_timer_message_store(&**self)
+    }
+
+    fn set_timer_message_store(&mut self, timer_message_store: Arc<TimerMessageStore>) {
+        MessageStore::set_timer_message_store(&mut **self, timer_message_store)
+    }
+
+    fn remain_transient_store_buffer_nums(&self) -> i32 {
+        MessageStore::remain_transient_store_buffer_nums(&**self)
+    }
+
+    fn remain_how_many_data_to_commit(&self) -> i64 {
+        MessageStore::remain_how_many_data_to_commit(&**self)
+    }
+
+    fn remain_how_many_data_to_flush(&self) -> i64 {
+        MessageStore::remain_how_many_data_to_flush(&**self)
+    }
+
+    fn get_message_store_config(&self) -> &MessageStoreConfig {
+        MessageStore::get_message_store_config(&**self)
+    }
+
+    fn set_alive_replica_num_in_group(&mut self, alive_replica_nums: i32) {
+        MessageStore::set_alive_replica_num_in_group(&mut **self, alive_replica_nums)
+    }
+
+    fn get_alive_replica_num_in_group(&self) -> i32 {
+        MessageStore::get_alive_replica_num_in_group(&**self)
+    }
+
+    fn update_ha_master_address(&self, new_addr: &CheetahString) {
+        MessageStore::update_ha_master_address(&**self, new_addr)
+    }
+
+    fn set_dledger_role_change_handler(&self, handler: Arc<dyn DLedgerRoleChangeHandler>) {
+        MessageStore::set_dledger_role_change_handler(&**self, handler)
+    }
+
+    fn get_auto_switch_ha_service(&self) -> Option<Arc<AutoSwitchHAService>> {
+        MessageStore::get_auto_switch_ha_service(&**self)
+    }
+
+    fn update_runtime_config(&self, key: &str, value: &str) -> Result<bool, String> {
+        MessageStore::update_runtime_config(&**self, key, value)
+    }
+}
+
+/// A message store whose implementation is picked at runtime.
+///
+/// The broker is generic over its [`MessageStore`]; running it with a `BoxedMessageStore` lets
+/// one binary serve every store kind registered in the
+/// [`MessageStoreFactory`](crate::message_store::message_store_factory::MessageStoreFactory).
+pub struct BoxedMessageStore {
+    inner: Box<dyn DynMessageStore>,
+}
+
+impl BoxedMessageStore {
+    pub fn new<MS: MessageStore>(message_store: ArcMut<MS>) -> Self {
+        Self {
+            inner: Box::new(message_store),
+        }
+    }
+
+    /// The concrete store behind this one, `None` when it is not an `MS`.
+    pub fn downcast_ref<MS: MessageStore>(&self) -> Option<&ArcMut<MS>> {
+        self.inner.as_any().downcast_ref::<ArcMut<MS>>()
+    }
+}
+
+impl MessageStore for BoxedMessageStore {
+    async fn load(&mut self) -> bool {
+        self.inner.load().await
+    }
+
+    fn start(&mut self) -> Result<(), Box<dyn Error>> {
+        self.inner.start()
+    }
+
+    fn shutdown(&mut self) {
+        self.inner.shutdown()
+    }
+
+    fn set_confirm_offset(&mut self, phy_offset: i64) {
+        self.inner.set_confirm_offset(phy_offset)
+    }
+
+    fn get_max_phy_offset(&self) -> i64 {
+        self.inner.get_max_phy_offset()
+    }
+
+    fn set_broker_init_max_offset(&mut self, broker_init_max_offset: i64) {
+        self.inner
+            .set_broker_init_max_offset(broker_init_max_offset)
+    }
+
+    fn now(&self) -> u64 {
+        self.inner.now()
+    }
+
+    fn get_state_machine_version(&self) -> i64 {
+        self.inner.get_state_machine_version()
+    }
+
+    async fn put_message(&mut self, msg: MessageExtBrokerInner) -> PutMessageResult {
+        self.inner.put_message(msg).await
+    }
+
+    async fn put_messages(&mut self, msg_batch: MessageExtBatch) -> PutMessageResult {
+        self.inner.put_messages(msg_batch).await
+    }
+
+    fn truncate_files(&mut self, offset_to_truncate: i64) -> bool {
+        self.inner.truncate_files(offset_to_truncate)
+    }
+
+    fn is_os_page_cache_busy(&self) -> bool {
+        self.inner.is_os_page_cache_busy()
+    }
+
+    fn get_running_flags(&self) -> &RunningFlags {
+        self.inner.get_running_flags()
+    }
+
+    fn is_shutdown(&self) -> bool {
+        self.inner.is_shutdown()
+    }
+
+    fn get_put_message_hook_list(&self) -> Arc<RwLock<Vec<BoxedPutMessageHook>>> {
+        self.inner.get_put_message_hook_list()
+    }
+
+    fn set_put_message_hook(&self, put_message_hook: BoxedPutMessageHook) {
+        self.inner.set_put_message_hook(put_message_hook)
+    }
+
+    fn add_first_dispatcher(&self, dispatcher: Box<dyn CommitLogDispatcher>) {
+        self.inner.add_first_dispatcher(dispatcher)
+    }
+
+    fn add_dispatcher(&self, dispatcher: Box<dyn CommitLogDispatcher>) {
+        self.inner.add_dispatcher(dispatcher)
+    }
+
+    fn get_broker_stats_manager(&self) -> Option<Arc<BrokerStatsManager>> {
+        self.inner.get_broker_stats_manager()
+    }
+
+    fn dispatch_behind_bytes(&self) -> i64 {
+        self.inner.dispatch_behind_bytes()
+    }
+
+    fn get_min_offset_in_queue(&self, topic: &CheetahString, queue_id: i32) -> i64 {
+        self.inner.get_min_offset_in_queue(topic, queue_id)
+    }
+
+    fn get_max_offset_in_queue(&self, topic: &CheetahString, queue_id: i32) -> i64 {
+        self.inner.get_max_offset_in_queue(topic, queue_id)
+    }
+
+    fn get_offset_in_queue_by_time_with_boundary(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        timestamp: i64,
+        boundary_type: BoundaryType,
+    ) -> i64 {
+        self.inner.get_offset_in_queue_by_time_with_boundary(
+            topic,
+            queue_id,
+            timestamp,
+            boundary_type,
+        )
+    }
+
+    fn get_max_offset_in_queue_committed(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        committed: bool,
+    ) -> i64 {
+        self.inner
+            .get_max_offset_in_queue_committed(topic, queue_id, committed)
+    }
+
+    async fn get_message(
+        &self,
+        group: &CheetahString,
+        topic: &CheetahString,
+        queue_id: i32,
+        offset: i64,
+        max_msg_nums: i32,
+        message_filter: Option<Arc<Box<dyn MessageFilter>>>,
+    ) -> Option<GetMessageResult> {
+        self.inner
+            .get_message(group, topic, queue_id, offset, max_msg_nums, message_filter)
+            .await
+    }
+
+    async fn get_message_with_total_size(
+        &self,
+        group: &CheetahString,
+        topic: &CheetahString,
+        queue_id: i32,
+        offset: i64,
+        max_msg_nums: i32,
+        max_total_msg_size: i32,
+        message_filter: Option<Arc<Box<dyn MessageFilter>>>,
+    ) -> Option<GetMessageResult> {
+        self.inner
+            .get_message_with_total_size(
+                group,
+                topic,
+                queue_id,
+                offset,
+                max_msg_nums,
+                max_total_msg_size,
+                message_filter,
+            )
+            .await
+    }
+
+    fn check_in_mem_by_consume_offset(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        consume_offset: i64,
+        batch_size: i32,
+    ) -> bool {
+        self.inner
+            .check_in_mem_by_consume_offset(topic, queue_id, consume_offset, batch_size)
+    }
+
+    fn is_msg_in_cold_area(
+        &self,
+        group: &CheetahString,
+        topic: &CheetahString,
+        queue_id: i32,
+        consume_offset: i64,
+    ) -> bool {
+        self.inner
+            .is_msg_in_cold_area(group, topic, queue_id, consume_offset)
+    }
+
+    fn notify_message_arrive_if_necessary(&self, dispatch_request: &mut DispatchRequest) {
+        self.inner
+            .notify_message_arrive_if_necessary(dispatch_request)
+    }
+
+    fn set_message_arriving_listener(
+        &mut self,
+        message_arriving_listener: Option<
+            Arc<Box<dyn MessageArrivingListener + Sync + Send + 'static>>,
+        >,
+    ) {
+        self.inner
+            .set_message_arriving_listener(message_arriving_listener)
+    }
+
+    fn find_consume_queue(&self, topic: &CheetahString, queue_id: i32) -> Option<ArcConsumeQueue> {
+        self.inner.find_consume_queue(topic, queue_id)
+    }
+
+    fn delete_topics(&mut self, delete_topics: Vec<&CheetahString>) -> i32 {
+        self.inner.delete_topics(delete_topics)
+    }
+
+    async fn query_message(
+        &self,
+        topic: &CheetahString,
+        key: &CheetahString,
+        max_num: i32,
+        begin_timestamp: i64,
+        end_timestamp: i64,
+    ) -> Option<QueryMessageResult> {
+        self.inner
+            .query_message(topic, key, max_num, begin_timestamp, end_timestamp)
+            .await
+    }
+
+    async fn select_one_message_by_offset(
+        &self,
+        commit_log_offset: i64,
+    ) -> Option<SelectMappedBufferResult> {
+        self.inner
+            .select_one_message_by_offset(commit_log_offset)
+            .await
+    }
+
+    async fn select_one_message_by_offset_with_size(
+        &self,
+        commit_log_offset: i64,
+        size: i32,
+    ) -> Option<SelectMappedBufferResult> {
+        self.inner
+            .select_one_message_by_offset_with_size(commit_log_offset, size)
+            .await
+    }
+
+    fn look_message_by_offset(&self, commit_log_offset: i64) -> Option<MessageExt> {
+        self.inner.look_message_by_offset(commit_log_offset)
+    }
+
+    fn look_message_by_offset_with_size(
+        &self,
+        commit_log_offset: i64,
+        size: i32,
+    ) -> Option<MessageExt> {
+        self.inner
+            .look_message_by_offset_with_size(commit_log_offset, size)
+    }
+
+    fn get_message_store_timestamp(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        consume_queue_offset: i64,
+    ) -> i64 {
+        self.inner
+            .get_message_store_timestamp(topic, queue_id, consume_queue_offset)
+    }
+
+    fn get_earliest_message_time_in_queue(&self, topic: &CheetahString, queue_id: i32) -> i64 {
+        self.inner
+            .get_earliest_message_time_in_queue(topic, queue_id)
+    }
+
+    fn get_latest_message_time_in_queue(&self, topic: &CheetahString, queue_id: i32) -> i64 {
+        self.inner.get_latest_message_time_in_queue(topic, queue_id)
+    }
+
+    fn get_runtime_info(&self) -> HashMap<String, String> {
+        self.inner.get_runtime_info()
+    }
+
+    fn lock_time_mills(&self) -> i64 {
+        self.inner.lock_time_mills()
+    }
+
+    fn get_earliest_message_time(&self) -> i64 {
+        self.inner.get_earliest_message_time()
+    }
+
+    fn get_timer_message_store(&self) -> Arc<TimerMessageStore> {
+        self.inner.get_timer_message_store()
+    }
+
+    fn set_timer_message_store(&mut self, timer_message_store: Arc<TimerMessageStore>) {
+        self.inner.set_timer_message_store(timer_message_store)
+    }
+
+    fn remain_transient_store_buffer_nums(&self) -> i32 {
+        self.inner.remain_transient_store_buffer_nums()
+    }
+
+    fn remain_how_many_data_to_commit(&self) -> i64 {
+        self.inner.remain_how_many_data_to_commit()
+    }
+
+    fn remain_how_many_data_to_flush(&self) -> i64 {
+        self.inner.remain_how_many_data_to_flush()
+    }
+
+    fn get_message_store_config(&self) -> &MessageStoreConfig {
+        self.inner.get_message_store_config()
+    }
+
+    fn set_alive_replica_num_in_group(&mut self, alive_replica_nums: i32) {
+        self.inner
+            .set_alive_replica_num_in_group(alive_replica_nums)
+    }
+
+    fn get_alive_replica_num_in_group(&self) -> i32 {
+        self.inner.get_alive_replica_num_in_group()
+    }
+
+    fn update_ha_master_address(&self, new_addr: &CheetahString) {
+        self.inner.update_ha_master_address(new_addr)
+    }
+
+    fn set_dledger_role_change_handler(&self, handler: Arc<dyn DLedgerRoleChangeHandler>) {
+        self.inner.set_dledger_role_change_handler(handler)
+    }
+
+    fn get_auto_switch_ha_service(&self) -> Option<Arc<AutoSwitchHAService>> {
+        self.inner.get_auto_switch_ha_service()
+    }
+
+    fn update_runtime_config(&self, key: &str, value: &str) -> Result<bool, String> {
+        self.inner.update_runtime_config(key, value)
+    }
+}
@@ -21,6 +21,7 @@ use std::error::Error;
 use std::fs;
 use std::path::PathBuf;
 use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicI32;
 use std::sync::atomic::AtomicI64;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
@@ -31,12 +32,14 @@ use std::time::Instant;
 use bytes::Buf;
 use cheetah_string::CheetahString;
 use rocketmq_common::common::attribute::cleanup_policy::CleanupPolicy;
+use rocketmq_common::common::boundary_type::BoundaryType;
 use rocketmq_common::common::broker::broker_role::BrokerRole;
 use rocketmq_common::common::message::message_batch::MessageExtBatch;
 use rocketmq_common::common::message::message_ext::MessageExt;
 use rocketmq_common::common::mix_all::is_lmq;
 use rocketmq_common::common::mix_all::is_sys_consumer_group_for_no_cold_read_limit;
 use rocketmq_common::common::mix_all::MULTI_DISPATCH_QUEUE_SPLITTER;
+use rocketmq_common::common::mix_all::MULTI_PATH_SPLITTER;
 use rocketmq_common::common::mix_all::RETRY_GROUP_TOPIC_PREFIX;
 use rocketmq_common::utils::util_all;
 use rocketmq_common::CleanupPolicyUtils::get_delete_policy;
@@ -44,6 +47,7 @@ use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_common::{
     common::{
         broker::broker_config::BrokerConfig,
+        broker::shared_broker_config::SharedBrokerConfig,
         config::TopicConfig,
         message::{message_ext_broker_inner::MessageExtBrokerInner, MessageConst},
         sys_flag::message_sys_flag::MessageSysFlag,
@@ -57,10 +61,13 @@ use rocketmq_common::{
 use rocketmq_rust::ArcMut;
 use tokio::runtime::Handle;
 use tokio::sync::mpsc::Sender;
+use tokio::sync::Semaphore;
 use tracing::error;
 use tracing::info;
 use tracing::warn;
 
+use crate::base::adaptive_tuning::start_adaptive_tuning;
+use crate::base::adaptive_tuning::StoreTuning;
 use crate::base::allocate_mapped_file_service::AllocateMappedFileService;
 use crate::base::commit_log_dispatcher::CommitLogDispatcher;
 use crate::base::dispatch_request::DispatchRequest;
@@ -70,6 +77,7 @@ use crate::base::message_result::PutMessageResult;
 use crate::base::message_status_enum::GetMessageStatus;
 use crate::base::message_status_enum::PutMessageStatus;
 use crate::base::query_message_result::QueryMessageResult;
+use crate::base::retention_guard::RetentionGuard;
 use crate::base::select_result::SelectMappedBufferResult;
 use crate::base::store_checkpoint::StoreCheckpoint;
 use crate::base::store_stats_service::StoreStatsService;
@@ -77,19 +85,29 @@ use crate::base::transient_store_pool::TransientStorePool;
 use crate::config::message_store_config::MessageStoreConfig;
 use crate::config::store_path_config_helper::get_store_path_batch_consume_queue;
 use crate::config::store_path_config_helper::get_store_path_consume_queue_ext;
+use crate::dledger::dledger_server::get_store_path_dledger_data;
+use crate::dledger::dledger_server::DLedgerRoleChangeHandler;
+use crate::dledger::dledger_server::DLedgerServer;
+use crate::dledger::member_state::DLedgerPeers;
 use crate::filter::MessageFilter;
+use crate::ha::auto_switch_ha_service::AutoSwitchHAService;
+use crate::ha::default_ha_service::DefaultHAService;
 use crate::hook::put_message_hook::BoxedPutMessageHook;
 use crate::index::index_dispatch::CommitLogDispatcherBuildIndex;
 use crate::index::index_service::IndexService;
+use crate::kv::compaction_service::CommitLogDispatcherCompaction;
 use crate::kv::compaction_service::CompactionService;
 use crate::kv::compaction_store::CompactionStore;
 use crate::log_file::commit_log;
 use crate::log_file::commit_log::CommitLog;
-use crate::log_file::mapped_file::MappedFile;
+use crate::log_file::mapped_file::uring_file;
+use crate::log_file::topic_retention::CommitLogDispatcherTopicRetention;
+use crate::log_file::topic_retention::TopicRetention;
 use crate::log_file::MessageStore;
 use crate::log_file::MAX_PULL_MSG_SIZE;
 use crate::queue::build_consume_queue::CommitLogDispatcherBuildConsumeQueue;
 use crate::queue::local_file_consume_queue_store::ConsumeQueueStore;
+use crate::queue::queue_offset_snapshot::QueueOffsetSnapshot;
 use crate::queue::ArcConsumeQueue;
 use crate::queue::ConsumeQueueStoreTrait;
 use crate::stats::broker_stats_manager::BrokerStatsManager;
@@ -98,12 +116,13 @@ use crate::store_path_config_helper::get_abort_file;
 use crate::store_path_config_helper::get_store_checkpoint;
 use crate::store_path_config_helper::get_store_path_consume_queue;
 use crate::timer::timer_message_store::TimerMessageStore;
+use crate::utils::store_util::StoreUtil;
 use crate::utils::store_util::TOTAL_PHYSICAL_MEMORY_SIZE;
 
 ///Using local files to store message data, which is also the default method.
 pub struct DefaultMessageStore {
     message_store_config: Arc<MessageStoreConfig>,
-    broker_config: Arc<BrokerConfig>,
+    broker_config: SharedBrokerConfig,
     put_message_hook_list: Arc<parking_lot::RwLock<Vec<BoxedPutMessageHook>>>,
     topic_config_table: Arc<parking_lot::Mutex<HashMap<CheetahString, TopicConfig>>>,
     //message_store_runtime: Option<RocketMQRuntime>,
@@ -133,16 +152,26 @@ pub struct DefaultMessageStore {
     timer_message_store: Arc<TimerMessageStore>,
     transient_store_pool: TransientStorePool,
     message_store_arc: Option<ArcMut<DefaultMessageStore>>,
+    cold_read_permits: Arc<Semaphore>,
+    store_tuning: Arc<StoreTuning>,
+    alive_replica_num_in_group: Arc<AtomicI32>,
+    ha_service: Option<Arc<DefaultHAService>>,
+    auto_switch_ha_service: Option<Arc<AutoSwitchHAService>>,
+    dledger_server: Option<Arc<DLedgerServer>>,
+    /// Term in which the DLedger leader recovered the queue offsets it assigns.
+    dledger_offsets_term: Arc<parking_lot::Mutex<i64>>,
+    retention_guard: Arc<parking_lot::RwLock<Option<Arc<dyn RetentionGuard>>>>,
 }
 
 impl DefaultMessageStore {
     pub fn new(
         message_store_config: Arc<MessageStoreConfig>,
-        broker_config: Arc<BrokerConfig>,
+        broker_config: SharedBrokerConfig,
         topic_config_table: Arc<parking_lot::Mutex<HashMap<CheetahString, TopicConfig>>>,
         broker_stats_manager: Option<Arc<BrokerStatsManager>>,
         notify_message_arrive_in_batch: bool,
     ) -> Self {
+        uring_file::init(&message_store_config);
         let running_flags = Arc::new(RunningFlags::new());
         let store_checkpoint = Arc::new(
             StoreCheckpoint::new(get_store_checkpoint(
@@ -166,27 +195,128 @@ impl DefaultMessageStore {
             CommitLogDispatcherBuildConsumeQueue::new(consume_queue_store.clone());
 
         let dispatcher = CommitLogDispatcherDefault {
-            dispatcher_vec: Arc::new(vec![Box::new(build_consume_queue), Box::new(build_index)]),
+            dispatcher_vec: Arc::new(parking_lot::RwLock::new(vec![
+                Box::new(build_consume_queue),
+                Box::new(build_index),
+            ])),
         };
 
-        let commit_log = CommitLog::new(
+        let store_tuning = Arc::new(StoreTuning::new(&message_store_config));
+        let transient_store_pool = TransientStorePool::new(
+            message_store_config.transient_store_pool_size,
+            message_store_config.mapped_file_size_commit_log,
+        );
+        let transient_store_pool_enable =
+            Self::transient_store_pool_enable(&message_store_config, &broker_config.get());
+        if transient_store_pool_enable {
+            transient_store_pool.init();
+        }
+        let allocate_mapped_file_service = AllocateMappedFileService::new(
+            message_store_config.clone(),
+            transient_store_pool_enable.then(|| transient_store_pool.clone()),
+        );
+        let mut commit_log = CommitLog::new(
             message_store_config.clone(),
             broker_config.clone(),
             &dispatcher,
             store_checkpoint.clone(),
             topic_config_table.clone(),
             consume_queue_store.clone(),
+            store_tuning.clone(),
+            transient_store_pool_enable.then(|| transient_store_pool.clone()),
+            Some(allocate_mapped_file_service.clone()),
         );
+        let ha_service = (!message_store_config.enable_dledger_commit_log
+            && !message_store_config.duplication_enable)
+            .then(|| {
+                let ha_service = Arc::new(DefaultHAService::new(
+                    commit_log.clone(),
+                    message_store_config.clone(),
+                    &broker_config.get(),
+                ));
+                commit_log.set_group_transfer_service(ha_service.group_transfer_service().clone());
+                ha_service
+            });
+        let dledger_server = message_store_config.enable_dledger_commit_log.then(|| {
+            let peers = DLedgerPeers::parse(
+                message_store_config
+                    .dledger_group
+                    .as_deref()
+                    .unwrap_or_default(),
+                message_store_config
+                    .dledger_peers
+                    .as_deref()
+                    .unwrap_or_default(),
+                message_store_config
+                    .dledger_self_id
+                    .as_deref()
+                    .unwrap_or_default(),
+            )
+            .unwrap_or_else(|e| panic!("illegal dledger config: {}", e));
+            let dledger_server = Arc::new(DLedgerServer::new(
+                commit_log.clone(),
+                message_store_config.clone(),
+                peers,
+            ));
+            commit_log.set_dledger_server(dledger_server.clone());
+            dledger_server
+        });
+        let auto_switch_ha_service = ha_service
+            .as_ref()
+            .filter(|_| broker_config.get().enable_controller_mode)
+            .map(|ha_service| {
+                Arc::new(AutoSwitchHAService::new(
+                    ha_service.clone(),
+                    commit_log.clone(),
+                    message_store_config.clone(),
+                    &broker_config.get(),
+                ))
+            });
 
         ensure_dir_ok(message_store_config.store_path_root_dir.as_str());
-        ensure_dir_ok(Self::get_store_path_physic(&message_store_config).as_str());
+        for store_path_physic in Self::get_store_path_physic(&message_store_config)
+            .split(MULTI_PATH_SPLITTER.as_str())
+            .filter(|path| !path.trim().is_empty())
+        {
+            ensure_dir_ok(store_path_physic.trim());
+        }
         ensure_dir_ok(Self::get_store_path_logic(&message_store_config).as_str());
 
-        let identity = broker_config.broker_identity.clone();
-        let transient_store_pool = TransientStorePool::new(
-            message_store_config.transient_store_pool_size,
-            message_store_config.mapped_file_size_commit_log,
-        );
+        let compaction_store = Arc::new(CompactionStore::new(&message_store_config));
+        if message_store_config.enable_compaction {
+            dispatcher
+                .dispatcher_vec
+                .write()
+                .push(Box::new(CommitLogDispatcherCompaction::new(
+                    compaction_store.clone(),
+                    commit_log.clone(),
+                    topic_config_table.clone(),
+                )));
+        }
+        let topic_retention = Arc::new(TopicRetention::new(
+            message_store_config.mapped_file_size_commit_log as i64,
+        ));
+        dispatcher
+            .dispatcher_vec
+            .write()
+            .push(Box::new(CommitLogDispatcherTopicRetention::new(
+                topic_retention.clone(),
+            )));
+        let clean_consume_queue_service = Arc::new(CleanConsumeQueueService::new(
+            commit_log.clone(),
+            consume_queue_store.clone(),
+            index_service.clone(),
+        ));
+        let retention_guard = Arc::new(parking_lot::RwLock::new(None));
+        let clean_commit_log_service = Arc::new(CleanCommitLogService::new(
+            message_store_config.clone(),
+            commit_log.clone(),
+            topic_config_table.clone(),
+            topic_retention,
+            running_flags.clone(),
+            retention_guard.clone(),
+        ));
+        let identity = broker_config.get().broker_identity.clone();
         Self {
             message_store_config: message_store_config.clone(),
             broker_config,
@@ -194,11 +324,14 @@ impl DefaultMessageStore {
             topic_config_table,
             // message_store_runtime: Some(RocketMQRuntime::new_multi(10, "message-store-thread")),
             commit_log,
-            compaction_service: Default::default(),
+            compaction_service: CompactionService::new(
+                compaction_store.clone(),
+                message_store_config.clone(),
+            ),
             store_checkpoint: Some(store_checkpoint),
             master_flushed_offset: Arc::new(AtomicI64::new(-1)),
             index_service,
-            allocate_mapped_file_service: Arc::new(AllocateMappedFileService::new()),
+            allocate_mapped_file_service: Arc::new(allocate_mapped_file_service),
             consume_queue_store,
             dispatcher,
             broker_init_max_offset: Arc::new(AtomicI64::new(-1)),
@@ -208,28 +341,36 @@ impl DefaultMessageStore {
             reput_message_service: ReputMessageService {
                 tx: None,
                 reput_from_offset: None,
-                message_store_config,
+                message_store_config: message_store_config.clone(),
                 inner: None,
             },
-            clean_commit_log_service: Arc::new(CleanCommitLogService {}),
+            clean_commit_log_service,
             correct_logic_offset_service: Arc::new(CorrectLogicOffsetService {}),
-            clean_consume_queue_service: Arc::new(CleanConsumeQueueService {}),
+            clean_consume_queue_service,
             broker_stats_manager,
             message_arriving_listener: None,
             notify_message_arrive_in_batch,
             store_stats_service: Arc::new(StoreStatsService::new(Some(identity))),
-            compaction_store: Arc::new(CompactionStore),
+            compaction_store,
             timer_message_store: Arc::new(TimerMessageStore::new_empty()),
             transient_store_pool,
             message_store_arc: None,
+            cold_read_permits: Arc::new(Semaphore::new(
+                message_store_config.cold_read_io_pool_size.max(1),
+            )),
+            store_tuning,
+            alive_replica_num_in_group: Arc::new(AtomicI32::new(1)),
+            ha_service,
+            auto_switch_ha_service,
+            dledger_server,
+            dledger_offsets_term: Arc::new(parking_lot::Mutex::new(-1)),
+            retention_guard,
         }
     }
 
     pub fn get_store_path_physic(message_store_config: &Arc<MessageStoreConfig>) -> String {
         match message_store_config.enable_dledger_commit_log {
-            true => {
-                unimplemented!("dledger commit log is not supported yet")
-            }
+            true => get_store_path_dledger_data(message_store_config),
             false => message_store_config.get_store_path_commit_log(),
         }
     }
@@ -243,253 +384,724 @@ impl DefaultMessageStore {
     }
 
     pub fn is_transient_store_pool_enable(&self) -> bool {
-        self.message_store_config.transient_store_pool_enable
-            && (self.broker_config.enable_controller_mode
-                || self.message_store_config().broker_role != BrokerRole::Slave)
-    }
-
-    pub fn set_message_store_arc(
-        &mut self,
-        message_store_arc: Option<ArcMut<DefaultMessageStore>>,
-    ) {
-        self.message_store_arc = message_store_arc;
-    }
-}
-
-impl Drop for DefaultMessageStore {
-    fn drop(&mut self) {
-        // if let Some(runtime) = self.message_store_runtime.take() {
-        //     runtime.shutdown();
-        // }
-    }
-}
-
-impl DefaultMessageStore {
-    #[inline]
-    pub fn get_topic_config(&self, topic: &str) -> Option<TopicConfig> {
-        if self.topic_config_table.lock().is_empty() {
-            return None;
-        }
-        self.topic_config_table.lock().get(topic).cloned()
+        Self::transient_store_pool_enable(&self.message_store_config, &self.broker_config.get())
     }
 
-    fn is_temp_file_exist(&self) -> bool {
-        let file_name = get_abort_file(self.message_store_config.store_path_root_dir.as_str());
-        fs::metadata(file_name).is_ok()
+    /// A slave only appends what the master pushes, unless the controller may switch it to a
+    /// master.
+    fn transient_store_pool_enable(
+        message_store_config: &MessageStoreConfig,
+        broker_config: &BrokerConfig,
+    ) -> bool {
+        message_store_config.is_transient_store_pool_enable()
+            && (broker_config.enable_controller_mode
+                || message_store_config.broker_role != BrokerRole::Slave)
     }
 
-    fn create_temp_file(&self) {
-        let file_name = get_abort_file(self.message_store_config.store_path_root_dir.as_str());
-        let pid = std::process::id();
-        match fs::File::create(file_name.as_str()) {
-            Ok(_) => {}
-            Err(e) => {
-                error!("create temp file error: {}", e);
-            }
+    /// Whether the member can assign queue offsets. A follower only dispatches what it
+    /// replicates, so a new leader takes puts once the commit log it holds is dispatched and
+    /// recovers the queue offsets from the consume queues then.
+    fn prepare_dledger_leader(&mut self) -> bool {
+        let Some(dledger_server) = self.dledger_server.clone() else {
+            return true;
+        };
+        let offsets_term = self.dledger_offsets_term.clone();
+        let mut offsets_term = offsets_term.lock();
+        let term = dledger_server.term();
+        // the commit log rejects the puts of a member that does not lead
+        if *offsets_term == term || !dledger_server.is_leader() {
+            return true;
         }
-        let _ = string_to_file(pid.to_string().as_str(), file_name.as_str());
-    }
-
-    async fn recover(&mut self, last_exit_ok: bool) {
-        let recover_concurrently = self.is_recover_concurrently();
-        info!(
-            "message store recover mode: {}",
-            if recover_concurrently {
-                "concurrent"
-            } else {
-                "normal"
-            },
-        );
-        let recover_consume_queue_start = Instant::now();
-        self.recover_consume_queue().await;
-        let max_phy_offset_of_consume_queue = self
-            .consume_queue_store
-            .get_max_phy_offset_in_consume_queue();
-        let recover_consume_queue = Instant::now()
-            .saturating_duration_since(recover_consume_queue_start)
-            .as_millis();
-
-        let recover_commit_log_start = Instant::now();
-        if last_exit_ok {
-            self.recover_normally(max_phy_offset_of_consume_queue).await;
-        } else {
-            self.recover_abnormally(max_phy_offset_of_consume_queue)
-                .await;
+        let reput_from_offset = self
+            .reput_message_service
+            .reput_from_offset
+            .as_ref()
+            .map_or(0, |offset| offset.load(Ordering::Acquire));
+        if reput_from_offset < self.commit_log.get_max_offset() {
+            return false;
         }
-        let recover_commit_log = Instant::now()
-            .saturating_duration_since(recover_commit_log_start)
-            .as_millis();
-
-        let recover_topic_queue_table_start = Instant::now();
         self.recover_topic_queue_table();
-        let recover_topic_queue_table = Instant::now()
-            .saturating_duration_since(recover_topic_queue_table_start)
-            .as_millis();
+        *offsets_term = term;
         info!(
-            "message store recover total cost: {} ms, recoverConsumeQueue: {} ms, \
-             recoverCommitLog: {} ms, recoverOffsetTable: {} ms",
-            recover_consume_queue + recover_commit_log + recover_topic_queue_table,
-            recover_consume_queue,
-            recover_commit_log,
-            recover_topic_queue_table
+            "dledger leader recovered the queue offsets in term {}",
+            term
         );
+        true
     }
 
-    pub fn recover_topic_queue_table(&mut self) {
-        let min_phy_offset = self.commit_log.get_min_offset();
-        self.consume_queue_store
-            .recover_offset_table(min_phy_offset);
-    }
-
-    pub async fn recover_normally(&mut self, max_phy_offset_of_consume_queue: i64) {
-        self.commit_log
-            .recover_normally(
-                max_phy_offset_of_consume_queue,
-                self.message_store_arc.clone().unwrap(),
-            )
-            .await;
-    }
-
-    pub async fn recover_abnormally(&mut self, max_phy_offset_of_consume_queue: i64) {
-        self.commit_log
-            .recover_abnormally(
-                max_phy_offset_of_consume_queue,
-                self.message_store_arc.clone().unwrap(),
-            )
-            .await;
-    }
-
-    fn is_recover_concurrently(&self) -> bool {
-        self.broker_config.recover_concurrently
-            & self.message_store_config.is_enable_rocksdb_store()
-    }
-
-    async fn recover_consume_queue(&mut self) {
-        if self.is_recover_concurrently() {
-            self.consume_queue_store.recover_concurrently();
-        } else {
-            self.consume_queue_store.recover();
-        }
+    /// The DLedger member of a broker with `enableDLegerCommitLog`.
+    pub fn get_dledger_server(&self) -> Option<&Arc<DLedgerServer>> {
+        self.dledger_server.as_ref()
     }
 
-    pub fn on_commit_log_dispatch(
+    pub fn set_message_store_arc(
         &mut self,
-        dispatch_request: &DispatchRequest,
-        do_dispatch: bool,
-        is_recover: bool,
-        _is_file_end: bool,
+        message_store_arc: Option<ArcMut<DefaultMessageStore>>,
     ) {
-        if do_dispatch && !is_recover {
-            self.do_dispatch(dispatch_request);
-        }
-    }
-
-    pub fn do_dispatch(&mut self, dispatch_request: &DispatchRequest) {
-        self.dispatcher.dispatch(dispatch_request)
+        self.message_store_arc = message_store_arc;
     }
 
-    pub fn truncate_dirty_logic_files(&mut self, phy_offset: i64) {
-        self.consume_queue_store.truncate_dirty(phy_offset);
+    /// Lets `retention_guard` keep expired commit log and consume queue files, e.g. until they
+    /// are uploaded to the tiered storage.
+    pub fn set_retention_guard(&self, retention_guard: Arc<dyn RetentionGuard>) {
+        *self.retention_guard.write() = Some(retention_guard);
     }
 
-    pub fn consume_queue_store_mut(&mut self) -> &mut ConsumeQueueStore {
-        &mut self.consume_queue_store
+    /// Whether the clean services may delete the local file at `file_path`.
+    pub fn can_delete_file(&self, file_path: &str) -> bool {
+        self.retention_guard
+            .read()
+            .as_ref()
+            .map_or(true, |retention_guard| {
+                retention_guard.can_delete(file_path)
+            })
     }
 
-    fn delete_file(&mut self, file_name: String) {
-        match fs::remove_file(PathBuf::from(file_name.as_str())) {
-            Ok(_) => {
-                info!("delete OK, file:{}", file_name);
-            }
-            Err(err) => {
-                error!("delete error, file:{}, {:?}", file_name, err);
-            }
+    /// Whether the message at `offset` is expected to be read from disk instead of page cache.
+    fn is_cold_read(&self, topic: &CheetahString, queue_id: i32, offset: i64) -> bool {
+        let Some(consume_queue) = self.find_consume_queue(topic, queue_id) else {
+            return false;
+        };
+        match consume_queue.get(offset) {
+            Some(cq_unit) => !estimate_in_mem_by_commit_offset(
+                cq_unit.pos,
+                self.commit_log.get_max_offset(),
+                &self.message_store_config,
+            ),
+            None => false,
         }
     }
 
-    fn add_schedule_task(&self) {
-        // clean files  Periodically
-        let clean_commit_log_service_arc = self.clean_commit_log_service.clone();
-        let clean_resource_interval = self.message_store_config.clean_resource_interval as u64;
-        tokio::spawn(async move {
-            let mut interval = tokio::time::interval(Duration::from_millis(1000 * 60));
-            interval.tick().await;
-            let mut interval =
-                tokio::time::interval(Duration::from_millis(clean_resource_interval));
-            loop {
-                clean_commit_log_service_arc.run();
-                interval.tick().await;
-            }
-        });
-
-        let message_store = self.message_store_arc.clone().unwrap();
-        tokio::spawn(async move {
-            let mut interval = tokio::time::interval(Duration::from_secs(60));
-            interval.tick().await;
-            let mut interval = tokio::time::interval(Duration::from_secs(10 * 60));
-            loop {
-                message_store.check_self();
-                interval.tick().await;
-            }
-        });
-
-        // store check point flush
-        let store_checkpoint_arc = self.store_checkpoint.clone().unwrap();
-        tokio::spawn(async move {
-            let mut interval = tokio::time::interval(Duration::from_secs(1));
-            interval.tick().await;
-            let mut interval = tokio::time::interval(Duration::from_secs(1));
-            loop {
-                let _ = store_checkpoint_arc.flush();
-                interval.tick().await;
-            }
-        });
-
-        let correct_logic_offset_service_arc = self.correct_logic_offset_service.clone();
-        let clean_consume_queue_service_arc = self.clean_consume_queue_service.clone();
-        tokio::spawn(async move {
-            let mut interval = tokio::time::interval(Duration::from_millis(1000 * 60));
-            interval.tick().await;
-            let mut interval =
-                tokio::time::interval(Duration::from_millis(clean_resource_interval));
-            loop {
-                correct_logic_offset_service_arc.run();
-                clean_consume_queue_service_arc.run();
-                interval.tick().await;
-            }
-        });
-    }
-
-    fn check_self(&self) {
-        self.commit_log.check_self();
-        self.consume_queue_store.check_self();
-    }
-
-    pub fn next_offset_correction(&self, old_offset: i64, new_offset: i64) -> i64 {
-        let mut next_offset = old_offset;
-        if self.message_store_config.broker_role != BrokerRole::Slave
-            || self.message_store_config.offset_check_in_slave
-        {
-            next_offset = new_offset;
+    #[allow(clippy::too_many_arguments)]
+    fn get_message_blocking(
+        &self,
+        group: &CheetahString,
+        topic: &CheetahString,
+        queue_id: i32,
+        offset: i64,
+        max_msg_nums: i32,
+        max_total_msg_size: i32,
+        message_filter: Option<Arc<Box<dyn MessageFilter>>>,
+    ) -> Option<GetMessageResult> {
+        if self.shutdown.load(Ordering::Relaxed) {
+            warn!("message store has shutdown, so getMessage is forbidden");
+            return None;
         }
-        next_offset
-    }
 
-    fn check_in_mem_by_commit_offset(&self, offset_py: i64, size: i32) -> bool {
-        let message = self.commit_log.get_message(offset_py, size);
-        match message {
-            None => false,
-            Some(msg) => msg.is_in_mem(),
+        if !self.running_flags.is_readable() {
+            warn!(
+                "message store is not readable, so getMessage is forbidden {}",
+                self.running_flags.get_flag_bits()
+            );
+            return None;
         }
-    }
-
-    pub fn set_message_arriving_listener(
-        &mut self,
-        message_arriving_listener: Option<
-            Arc<Box<dyn MessageArrivingListener + Sync + Send + 'static>>,
-        >,
+        let topic_config = self.get_topic_config(topic);
+        let policy = get_delete_policy(topic_config.as_ref());
+        if policy == CleanupPolicy::COMPACTION && self.message_store_config.enable_compaction {
+            return self.compaction_store.get_message(
+                group,
+                topic,
+                queue_id,
+                offset,
+                max_msg_nums,
+                max_total_msg_size,
+            );
+        }
+        let begin_time = Instant::now();
+
+        let mut status;
+
+        let mut next_begin_offset = offset;
+        let mut min_offset = 0;
+        let mut max_offset = 0;
+        let mut get_result = Some(GetMessageResult::new());
+        let max_offset_py = self.commit_log.get_max_offset();
+        let consume_queue = self.find_consume_queue(topic, queue_id);
+        if let Some(consume_queue) = consume_queue {
+            min_offset = consume_queue.get_min_offset_in_queue();
+            max_offset = consume_queue.get_max_offset_in_queue();
+            if max_offset == 0 {
+                status = GetMessageStatus::NoMessageInQueue;
+                next_begin_offset = self.next_offset_correction(offset, 0);
+            } else if offset < min_offset {
+                status = GetMessageStatus::OffsetTooSmall;
+                next_begin_offset = self.next_offset_correction(offset, min_offset);
+            } else if offset == max_offset {
+                status = GetMessageStatus::OffsetOverflowOne;
+                next_begin_offset = self.next_offset_correction(offset, offset);
+            } else if offset > max_offset {
+                status = GetMessageStatus::OffsetOverflowBadly;
+                next_begin_offset = self.next_offset_correction(offset, max_offset);
+            } else {
+                let max_filter_message_size = self
+                    .message_store_config
+                    .max_filter_message_size
+                    .max(max_msg_nums * consume_queue.get_unit_size());
+                let disk_fall_recorded = self.message_store_config.disk_fall_recorded;
+                let mut max_pull_size = max_total_msg_size.max(100);
+                if max_pull_size > MAX_PULL_MSG_SIZE {
+                    warn!(
+                        "The max pull size is too large maxPullSize={} topic={} queueId={}",
+                        max_pull_size, topic, queue_id
+                    );
+                    max_pull_size = MAX_PULL_MSG_SIZE;
+                }
+                status = GetMessageStatus::NoMatchedMessage;
+                let mut max_phy_offset_pulling = 0;
+                let mut cq_file_num = 0;
+                while get_result.as_ref().unwrap().buffer_total_size() <= 0
+                    && next_begin_offset < max_offset
+                    && cq_file_num
+                        < self
+                            .message_store_config
+                            .travel_cq_file_num_when_get_message
+                {
+                    cq_file_num += 1;
+                    let buffer_consume_queue =
+                        consume_queue.iterate_from_inner(next_begin_offset, max_msg_nums);
+                    if buffer_consume_queue.is_none() {
+                        status = GetMessageStatus::OffsetFoundNull;
+                        next_begin_offset = self.next_offset_correction(
+                            next_begin_offset,
+                            self.consume_queue_store
+                                .roll_next_file(&**consume_queue, next_begin_offset),
+                        );
+                        warn!(
+                            "consumer request topic: {}, offset: {}, minOffset: {}, maxOffset: \
+                             {}, but access logic queue failed. Correct nextBeginOffset to {}",
+                            topic, offset, min_offset, max_offset, next_begin_offset
+                        );
+                        break;
+                    }
+                    let mut next_phy_file_start_offset = i64::MIN;
+                    let mut buffer_consume_queue = buffer_consume_queue.unwrap();
+                    loop {
+                        if next_begin_offset >= max_offset {
+                            break;
+                        }
+                        if let Some(cq_unit) = buffer_consume_queue.next() {
+                            let offset_py = cq_unit.pos;
+                            let size_py = cq_unit.size;
+                            let is_in_mem = estimate_in_mem_by_commit_offset(
+                                offset_py,
+                                max_offset_py,
+                                &self.message_store_config,
+                            );
+                            if (cq_unit.queue_offset - offset)
+                                * consume_queue.get_unit_size() as i64
+                                > max_filter_message_size as i64
+                            {
+                                break;
+                            }
+                            let get_result_ref = get_result.as_mut().unwrap();
+                            if is_the_batch_full(
+                                size_py,
+                                cq_unit.batch_num as i32,
+                                max_msg_nums,
+                                max_pull_size as i64,
+                                get_result_ref.buffer_total_size(),
+                                get_result_ref.message_count(),
+                                is_in_mem,
+                                &self.message_store_config,
+                            ) {
+                                break;
+                            }
+                            if get_result_ref.buffer_total_size() >= max_pull_size {
+                                break;
+                            }
+                            max_phy_offset_pulling = offset_py;
+                            next_begin_offset = cq_unit.queue_offset + cq_unit.batch_num as i64;
+                            if next_phy_file_start_offset != i64::MIN
+                                && offset_py < next_phy_file_start_offset
+                            {
+                                continue;
+                            }
+
+                            if let Some(filter) = message_filter.as_ref() {
+                                if !filter.is_matched_by_consume_queue(
+                                    cq_unit.get_valid_tags_code_as_long(),
+                                    cq_unit.cq_ext_unit.as_ref(),
+                                ) {
+                                    if get_result_ref.buffer_total_size() == 0 {
+                                        status = GetMessageStatus::NoMatchedMessage;
+                                    }
+                                    continue;
+                                }
+                            }
+
+                            let select_result = self.commit_log.get_message(offset_py, size_py);
+                            if select_result.is_none() {
+                                if get_result_ref.buffer_total_size() == 0 {
+                                    status = GetMessageStatus::MessageWasRemoving;
+                                }
+                                next_phy_file_start_offset =
+                                    self.commit_log.roll_next_file(offset_py);
+                                continue;
+                            }
+                            if self.message_store_config.cold_data_flow_control_enable
+                                && !is_sys_consumer_group_for_no_cold_read_limit(group)
+                                && !select_result.as_ref().unwrap().is_in_cache
+                            {
+                                get_result_ref.set_cold_data_sum(
+                                    get_result_ref.cold_data_sum() + size_py as i64,
+                                );
+                            }
+
+                            if self.message_store_config.check_crc_on_read {
+                                let mut bytes = select_result
+                                    .as_ref()
+                                    .unwrap()
+                                    .get_bytes()
+                                    .unwrap_or_default();
+                                if !commit_log::check_message_and_return_size(
+                                    &mut bytes,
+                                    true,
+                                    false,
+                                    true,
+                                    &self.message_store_config,
+                                )
+                                .success
+                                {
+                                    error!(
+                                        "the message at commit log offset {} failed the CRC \
+                                         check, topic: {}, queue: {}",
+                                        offset_py, topic, queue_id
+                                    );
+                                    drop(select_result);
+                                    continue;
+                                }
+                            }
+
+                            if message_filter.is_some()
+                                && !message_filter
+                                    .as_ref()
+                                    .as_ref()
+                                    .unwrap()
+                                    .is_matched_by_commit_log(
+                                        Some(select_result.as_ref().unwrap().get_buffer()),
+                                        None,
+                                    )
+                            {
+                                if get_result_ref.buffer_total_size() == 0 {
+                                    status = GetMessageStatus::NoMatchedMessage;
+                                }
+                                drop(select_result);
+                                continue;
+                            }
+                            self.store_stats_service
+                                .get_message_transferred_msg_count()
+                                .fetch_add(cq_unit.batch_num as usize, Ordering::Relaxed);
+                            get_result.as_mut().unwrap().add_message(
+                                select_result.unwrap(),
+                                cq_unit.queue_offset as u64,
+                                cq_unit.batch_num as i32,
+                            );
+                            status = GetMessageStatus::Found;
+                            next_phy_file_start_offset = i64::MIN;
+                        }
+                    }
+                }
+                if disk_fall_recorded {
+                    let fall_behind = max_offset_py - max_phy_offset_pulling;
+                    self.broker_stats_manager
+                        .as_ref()
+                        .unwrap()
+                        .record_disk_fall_behind_size(group, topic, queue_id, fall_behind);
+                }
+                let diff = max_offset_py - max_phy_offset_pulling;
+                let memory = ((*TOTAL_PHYSICAL_MEMORY_SIZE as f64)
+                    * (self.message_store_config.access_message_in_memory_max_ratio as f64 / 100.0))
+                    as i64;
+                get_result
+                    .as_mut()
+                    .unwrap()
+                    .set_suggest_pulling_from_slave(diff > memory);
+            }
+        } else {
+            status = GetMessageStatus::NoMatchedLogicQueue;
+            next_begin_offset = self.next_offset_correction(offset, 0);
+        }
+
+        if GetMessageStatus::Found == status {
+            self.store_stats_service
+                .get_message_times_total_found()
+                .fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.store_stats_service
+                .get_message_times_total_miss()
+                .fetch_add(1, Ordering::Relaxed);
+        }
+        let elapsed_time = begin_time.elapsed().as_millis() as u64;
+        if get_result.is_none() {
+            get_result = Some(GetMessageResult::new_result_size(0));
+        }
+        let result = get_result.as_mut().unwrap();
+        result.set_status(Some(status));
+        result.set_next_begin_offset(next_begin_offset);
+        result.set_max_offset(max_offset);
+        result.set_min_offset(min_offset);
+        /*        println!(
+            "------------------------------------------------{} {} {} {} {}",
+            result, next_begin_offset, max_offset, min_offset, elapsed_time
+        );*/
+        get_result
+    }
+}
+
+impl Drop for DefaultMessageStore {
+    fn drop(&mut self) {
+        // if let Some(runtime) = self.message_store_runtime.take() {
+        //     runtime.shutdown();
+        // }
+    }
+}
+
+impl DefaultMessageStore {
+    #[inline]
+    pub fn get_topic_config(&self, topic: &str) -> Option<TopicConfig> {
+        if self.topic_config_table.lock().is_empty() {
+            return None;
+        }
+        self.topic_config_table.lock().get(topic).cloned()
+    }
+
+    fn is_temp_file_exist(&self) -> bool {
+        let file_name = get_abort_file(self.message_store_config.store_path_root_dir.as_str());
+        fs::metadata(file_name).is_ok()
+    }
+
+    fn create_temp_file(&self) {
+        let file_name = get_abort_file(self.message_store_config.store_path_root_dir.as_str());
+        let pid = std::process::id();
+        match fs::File::create(file_name.as_str()) {
+            Ok(_) => {}
+            Err(e) => {
+                error!("create temp file error: {}", e);
+            }
+        }
+        let _ = string_to_file(pid.to_string().as_str(), file_name.as_str());
+    }
+
+    async fn recover(&mut self, last_exit_ok: bool) {
+        let recover_concurrently = self.is_recover_concurrently();
+        info!(
+            "message store recover mode: {}",
+            if recover_concurrently {
+                "concurrent"
+            } else {
+                "normal"
+            },
+        );
+        let recover_consume_queue_start = Instant::now();
+        self.recover_consume_queue().await;
+        let max_phy_offset_of_consume_queue = self
+            .consume_queue_store
+            .get_max_phy_offset_in_consume_queue();
+        let recover_consume_queue = Instant::now()
+            .saturating_duration_since(recover_consume_queue_start)
+            .as_millis();
+
+        let recover_commit_log_start = Instant::now();
+        if last_exit_ok {
+            self.recover_normally(max_phy_offset_of_consume_queue).await;
+        } else {
+            self.recover_abnormally(max_phy_offset_of_consume_queue)
+                .await;
+        }
+        let recover_commit_log = Instant::now()
+            .saturating_duration_since(recover_commit_log_start)
+            .as_millis();
+
+        let recover_topic_queue_table_start = Instant::now();
+        self.recover_topic_queue_table();
+        let recover_topic_queue_table = Instant::now()
+            .saturating_duration_since(recover_topic_queue_table_start)
+            .as_millis();
+        info!(
+            "message store recover total cost: {} ms, recoverConsumeQueue: {} ms, \
+             recoverCommitLog: {} ms, recoverOffsetTable: {} ms",
+            recover_consume_queue + recover_commit_log + recover_topic_queue_table,
+            recover_consume_queue,
+            recover_commit_log,
+            recover_topic_queue_table
+        );
+    }
+
+    pub fn recover_topic_queue_table(&mut self) {
+        let min_phy_offset = self.commit_log.get_min_offset();
+        self.consume_queue_store
+            .recover_offset_table(min_phy_offset);
+        if self.message_store_config.duplication_enable
+            || self.broker_config.get().enable_controller_mode
+        {
+            self.compensate_for_ha();
+        }
+    }
+
+    /// Moves the queue offsets past the messages behind the confirm offset, they are not
+    /// dispatched to the consume queues until they are confirmed but their queue offsets are
+    /// taken.
+    fn compensate_for_ha(&mut self) {
+        let mut topic_queue_table = self.consume_queue_store.get_topic_queue_table();
+        let mut start_read_offset = self.commit_log.get_confirm_offset().max(0);
+        let max_offset = self.commit_log.get_max_offset();
+        info!(
+            "correct unsubmitted offset, start read offset = {}",
+            start_read_offset
+        );
+        while start_read_offset < max_offset {
+            let Some(mut bytes) = self
+                .commit_log
+                .get_message(start_read_offset, 4)
+                .map(|result| result.get_buffer().get_i32())
+                .and_then(|size| self.commit_log.get_message(start_read_offset, size))
+                .and_then(|result| result.get_bytes())
+            else {
+                break;
+            };
+            let dispatch_request = commit_log::check_message_and_return_size(
+                &mut bytes,
+                true,
+                self.message_store_config.duplication_enable,
+                true,
+                &self.message_store_config,
+            );
+            if !dispatch_request.success {
+                break;
+            }
+            if dispatch_request.msg_size == 0 {
+                start_read_offset = self.commit_log.roll_next_file(start_read_offset);
+                continue;
+            }
+            let key = CheetahString::from_string(format!(
+                "{}-{}",
+                dispatch_request.topic, dispatch_request.queue_id
+            ));
+            topic_queue_table.insert(key.clone(), dispatch_request.consume_queue_offset + 1);
+            start_read_offset += dispatch_request.msg_size as i64;
+            info!(
+                "correcting. key: {}, start read offset: {}",
+                key, start_read_offset
+            );
+        }
+        self.consume_queue_store
+            .set_topic_queue_table(topic_queue_table);
+    }
+
+    pub async fn recover_normally(&mut self, max_phy_offset_of_consume_queue: i64) {
+        self.commit_log
+            .recover_normally(
+                max_phy_offset_of_consume_queue,
+                self.message_store_arc.clone().unwrap(),
+            )
+            .await;
+    }
+
+    pub async fn recover_abnormally(&mut self, max_phy_offset_of_consume_queue: i64) {
+        self.commit_log
+            .recover_abnormally(
+                max_phy_offset_of_consume_queue,
+                self.message_store_arc.clone().unwrap(),
+            )
+            .await;
+    }
+
+    fn is_recover_concurrently(&self) -> bool {
+        self.broker_config.get().recover_concurrently
+            & self.message_store_config.is_enable_rocksdb_store()
+    }
+
+    async fn recover_consume_queue(&mut self) {
+        if self.is_recover_concurrently() {
+            self.consume_queue_store.recover_concurrently();
+        } else {
+            self.consume_queue_store.recover();
+        }
+    }
+
+    pub fn on_commit_log_dispatch(
+        &mut self,
+        dispatch_request: &mut DispatchRequest,
+        do_dispatch: bool,
+        is_recover: bool,
+        _is_file_end: bool,
     ) {
-        self.message_arriving_listener = message_arriving_listener;
+        if do_dispatch && !is_recover {
+            self.do_dispatch(dispatch_request);
+        }
+    }
+
+    pub fn do_dispatch(&mut self, dispatch_request: &mut DispatchRequest) {
+        self.dispatcher.dispatch(dispatch_request)
+    }
+
+    pub fn truncate_dirty_logic_files(&mut self, phy_offset: i64) {
+        self.consume_queue_store.truncate_dirty(phy_offset);
+    }
+
+    pub fn consume_queue_store_mut(&mut self) -> &mut ConsumeQueueStore {
+        &mut self.consume_queue_store
+    }
+
+    fn delete_file(&mut self, file_name: String) {
+        match fs::remove_file(PathBuf::from(file_name.as_str())) {
+            Ok(_) => {
+                info!("delete OK, file:{}", file_name);
+            }
+            Err(err) => {
+                error!("delete error, file:{}, {:?}", file_name, err);
+            }
+        }
+    }
+
+    fn add_schedule_task(&self) {
+        let clean_resource_interval = self.message_store_config.clean_resource_interval as u64;
+        let message_store = self.message_store_arc.clone().unwrap();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(60));
+            interval.tick().await;
+            let mut interval = tokio::time::interval(Duration::from_secs(10 * 60));
+            loop {
+                message_store.check_self();
+                interval.tick().await;
+            }
+        });
+
+        // store check point flush
+        let store_checkpoint_arc = self.store_checkpoint.clone().unwrap();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(1));
+            interval.tick().await;
+            let mut interval = tokio::time::interval(Duration::from_secs(1));
+            loop {
+                let _ = store_checkpoint_arc.flush();
+                interval.tick().await;
+            }
+        });
+
+        let correct_logic_offset_service_arc = self.correct_logic_offset_service.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_millis(1000 * 60));
+            interval.tick().await;
+            let mut interval =
+                tokio::time::interval(Duration::from_millis(clean_resource_interval));
+            loop {
+                correct_logic_offset_service_arc.run();
+                interval.tick().await;
+            }
+        });
+    }
+
+    /// Deletes the expired commit log files, then the consume queue and index files of the
+    /// messages they held, every `cleanResourceInterval` millis.
+    fn start_clean_service(&self) {
+        let clean_commit_log_service = self.clean_commit_log_service.clone();
+        let clean_consume_queue_service = self.clean_consume_queue_service.clone();
+        let shutdown = self.shutdown.clone();
+        let clean_interval =
+            Duration::from_millis(self.message_store_config.clean_resource_interval.max(1) as u64);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(clean_interval);
+            interval.tick().await;
+            while !shutdown.load(Ordering::Acquire) {
+                interval.tick().await;
+                let clean_commit_log_service = clean_commit_log_service.clone();
+                let clean_consume_queue_service = clean_consume_queue_service.clone();
+                // the deletions wait between files and on files still held
+                let _ = tokio::task::spawn_blocking(move || {
+                    clean_commit_log_service.run();
+                    clean_consume_queue_service.run();
+                })
+                .await;
+            }
+        });
+    }
+
+    /// Compresses the sealed commit log files that are old enough every `cleanResourceInterval`
+    /// millis, see [`CommitLog::compress_sealed_files`]. Only dispatched files are compressed,
+    /// and the ones the retention guard still keeps are left for it.
+    fn start_commit_log_compression_service(&self) {
+        let Some(reput_from_offset) = self.reput_message_service.reput_from_offset.clone() else {
+            return;
+        };
+        let commit_log = self.commit_log.clone();
+        let retention_guard = self.retention_guard.clone();
+        let shutdown = self.shutdown.clone();
+        let compression_interval =
+            Duration::from_millis(self.message_store_config.clean_resource_interval.max(1) as u64);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(compression_interval);
+            interval.tick().await;
+            while !shutdown.load(Ordering::Acquire) {
+                interval.tick().await;
+                let commit_log = commit_log.clone();
+                let retention_guard = retention_guard.clone();
+                let max_offset = reput_from_offset.load(Ordering::Acquire);
+                let _ = tokio::task::spawn_blocking(move || {
+                    let retention_guard = retention_guard.read().clone();
+                    let compressed = commit_log.compress_sealed_files(max_offset, &|file_path| {
+                        retention_guard.as_ref().map_or(true, |retention_guard| {
+                            retention_guard.can_delete(file_path)
+                        })
+                    });
+                    if compressed > 0 {
+                        info!("compressed {} commit log files", compressed);
+                    }
+                })
+                .await;
+            }
+        });
+    }
+
+    fn is_lmq_consume_queue_num_exceeded(&self) -> bool {
+        self.message_store_config.enable_lmq
+            && self.message_store_config.enable_multi_dispatch
+            && self.consume_queue_store.get_lmq_queue_num()
+                > self.message_store_config.max_lmq_consume_queue_num
+    }
+
+    /// Lets the next runs of the clean service delete expired commit log files whatever the
+    /// hour and the disk usage.
+    pub fn execute_delete_files_manually(&self) {
+        self.clean_commit_log_service
+            .execute_delete_files_manually();
+    }
+
+    fn start_queue_offset_snapshot(&self) {
+        let consume_queue_store = self.consume_queue_store.clone();
+        let shutdown = self.shutdown.clone();
+        let store_tuning = self.store_tuning.clone();
+        tokio::spawn(async move {
+            let mut snapshot = QueueOffsetSnapshot::default();
+            while !shutdown.load(Ordering::Acquire) {
+                let flush_interval = store_tuning.flush_interval_consume_queue_millis().max(1);
+                tokio::time::sleep(Duration::from_millis(flush_interval)).await;
+                consume_queue_store.persist_queue_offset_snapshot(&mut snapshot);
+            }
+        });
+    }
+
+    fn check_self(&self) {
+        self.commit_log.check_self();
+        self.consume_queue_store.check_self();
+    }
+
+    pub fn next_offset_correction(&self, old_offset: i64, new_offset: i64) -> i64 {
+        let mut next_offset = old_offset;
+        if self.message_store_config.broker_role != BrokerRole::Slave
+            || self.message_store_config.offset_check_in_slave
+        {
+            next_offset = new_offset;
+        }
+        next_offset
+    }
+
+    fn check_in_mem_by_commit_offset(&self, offset_py: i64, size: i32) -> bool {
+        let message = self.commit_log.get_message(offset_py, size);
+        match message {
+            None => false,
+            Some(msg) => msg.is_in_mem(),
+        }
     }
 }
 
@@ -503,6 +1115,79 @@ fn estimate_in_mem_by_commit_offset(
     (max_offset_py - offset_py) <= memory as i64
 }
 
+/// Binary search of the offsets in `[min_offset, max_offset)` by store timestamp, which is
+/// non-decreasing within a queue.
+///
+/// On an exact match the first (`Lower`) or last (`Upper`) message of that timestamp is picked,
+/// otherwise the closest message after (`Lower`) or before (`Upper`) it, falling back to the
+/// other side at either end of the queue. An unreadable message stops the search at
+/// `min_offset`.
+pub(crate) fn search_offset_by_time(
+    min_offset: i64,
+    max_offset: i64,
+    timestamp: i64,
+    boundary_type: BoundaryType,
+    store_timestamp: impl Fn(i64) -> i64,
+) -> i64 {
+    if min_offset >= max_offset {
+        return min_offset;
+    }
+    let mut low = min_offset;
+    let mut high = max_offset - 1;
+    let mut left_offset = -1;
+    let mut right_offset = -1;
+    let mut target_offset = -1;
+    while low <= high {
+        let mid = low + (high - low) / 2;
+        let store_time = store_timestamp(mid);
+        if store_time < 0 {
+            warn!("failed to read the store timestamp at offset {}", mid);
+            return min_offset;
+        }
+        match store_time.cmp(&timestamp) {
+            std::cmp::Ordering::Equal => {
+                target_offset = mid;
+                break;
+            }
+            std::cmp::Ordering::Greater => {
+                right_offset = mid;
+                high = mid - 1;
+            }
+            std::cmp::Ordering::Less => {
+                left_offset = mid;
+                low = mid + 1;
+            }
+        }
+    }
+    if target_offset != -1 {
+        // several messages may share the timestamp, walk to the requested end of them
+        let mut offset = target_offset;
+        match boundary_type {
+            BoundaryType::Lower => {
+                while offset > min_offset && store_timestamp(offset - 1) == timestamp {
+                    offset -= 1;
+                }
+            }
+            BoundaryType::Upper => {
+                while offset < max_offset - 1 && store_timestamp(offset + 1) == timestamp {
+                    offset += 1;
+                }
+            }
+        }
+        return offset;
+    }
+    if left_offset == -1 {
+        return right_offset;
+    }
+    if right_offset == -1 {
+        return left_offset;
+    }
+    match boundary_type {
+        BoundaryType::Lower => right_offset,
+        BoundaryType::Upper => left_offset,
+    }
+}
+
 fn is_the_batch_full(
     size_py: i32,
     unit_batch_num: i32,
@@ -565,6 +1250,14 @@ impl MessageStore for DefaultMessageStore {
         }
         // load Consume Queue-- init Consume log mapped file queue
         result &= self.consume_queue_store.load();
+        #[cfg(feature = "rocksdb")]
+        if result {
+            let commit_log = &self.commit_log;
+            self.consume_queue_store
+                .migrate_file_consume_queues(&|pos, size| {
+                    commit_log.pickup_store_timestamp(pos, size)
+                });
+        }
 
         if self.message_store_config.enable_compaction {
             result &= self.compaction_service.load(last_exit_ok);
@@ -585,7 +1278,13 @@ impl MessageStore for DefaultMessageStore {
             info!(
                 "message store recover end, and the max phy offset = {}",
                 self.get_max_phy_offset()
-            )
+            );
+            if let Some(dledger_server) = self.dledger_server.as_ref() {
+                if let Err(e) = dledger_server.load().await {
+                    error!("load dledger entries failed: {}", e);
+                    result = false;
+                }
+            }
         }
 
         let max_offset = self.get_max_phy_offset();
@@ -593,7 +1292,7 @@ impl MessageStore for DefaultMessageStore {
         info!("load over, and the max phy offset = {}", max_offset);
 
         if !result {
-            // self.allocate_mapped_file_service.shutdown();
+            self.allocate_mapped_file_service.shutdown();
         }
         result
     }
@@ -609,11 +1308,37 @@ impl MessageStore for DefaultMessageStore {
             self.dispatcher.clone(),
             self.notify_message_arrive_in_batch,
             self.message_store_arc.clone().unwrap(),
+            self.store_tuning.clone(),
         );
 
+        self.allocate_mapped_file_service.start();
         self.commit_log.start();
+        if let Some(auto_switch_ha_service) = self.auto_switch_ha_service.as_ref() {
+            auto_switch_ha_service.start()?;
+        } else if let Some(ha_service) = self.ha_service.as_ref() {
+            ha_service.start()?;
+        } else if let Some(dledger_server) = self.dledger_server.as_ref() {
+            dledger_server.start()?;
+        }
+        if self.message_store_config.adaptive_tuning_enable {
+            start_adaptive_tuning(
+                &self.message_store_config,
+                self.store_tuning.clone(),
+                self.shutdown.clone(),
+            );
+        }
 
         //self.add_schedule_task();
+        self.start_clean_service();
+        if self.message_store_config.commit_log_compression_enable {
+            self.start_commit_log_compression_service();
+        }
+        if self.message_store_config.enable_compaction {
+            self.compaction_service.start();
+        }
+        if self.message_store_config.enable_queue_offset_snapshot {
+            self.start_queue_offset_snapshot();
+        }
 
         Ok(())
     }
@@ -621,8 +1346,27 @@ impl MessageStore for DefaultMessageStore {
     fn shutdown(&mut self) {
         if !self.shutdown.load(Ordering::Acquire) {
             self.shutdown.store(true, Ordering::SeqCst);
+            if let Some(auto_switch_ha_service) = self.auto_switch_ha_service.as_ref() {
+                auto_switch_ha_service.shutdown();
+            } else if let Some(ha_service) = self.ha_service.as_ref() {
+                ha_service.shutdown();
+            } else if let Some(dledger_server) = self.dledger_server.as_ref() {
+                dledger_server.shutdown();
+            }
             self.reput_message_service.shutdown();
             self.commit_log.shutdown();
+            self.allocate_mapped_file_service.shutdown();
+            self.index_service.shutdown();
+            if self.message_store_config.enable_compaction {
+                self.compaction_service.shutdown();
+            }
+            if self.is_transient_store_pool_enable() {
+                self.transient_store_pool.destroy();
+            }
+            if self.message_store_config.enable_queue_offset_snapshot {
+                self.consume_queue_store
+                    .persist_queue_offset_snapshot(&mut QueueOffsetSnapshot::default());
+            }
 
             if self.running_flags.is_writeable() {
                 //delete abort file
@@ -650,9 +1394,9 @@ impl MessageStore for DefaultMessageStore {
         self.state_machine_version.load(Ordering::Relaxed)
     }
 
-    async fn put_message(&mut self, msg: MessageExtBrokerInner) -> PutMessageResult {
+    async fn put_message(&mut self, mut msg: MessageExtBrokerInner) -> PutMessageResult {
         for hook in self.put_message_hook_list.read().iter() {
-            if let Some(result) = hook.execute_before_put_message(&msg.message_ext_inner) {
+            if let Some(result) = hook.execute_before_put_message_mut(&mut msg) {
                 return result;
             }
         }
@@ -677,10 +1421,27 @@ impl MessageStore for DefaultMessageStore {
                 return PutMessageResult::new_default(PutMessageStatus::MessageIllegal);
             }
         }
+
+        if msg
+            .property(MessageConst::PROPERTY_INNER_MULTI_DISPATCH)
+            .is_some_and(|queues| !queues.is_empty())
+            && self.is_lmq_consume_queue_num_exceeded()
+        {
+            warn!(
+                "the number of light message queues exceeded {}, topic: {}",
+                self.message_store_config.max_lmq_consume_queue_num,
+                msg.topic()
+            );
+            return PutMessageResult::new_default(PutMessageStatus::LmqConsumeQueueNumExceeded);
+        }
+        if !self.prepare_dledger_leader() {
+            return PutMessageResult::new_default(PutMessageStatus::ServiceNotAvailable);
+        }
         let begin_time = Instant::now();
         //put message to commit log
         let result = self.commit_log.put_message(msg).await;
         let elapsed_time = begin_time.elapsed().as_millis();
+        self.store_tuning.record_put(elapsed_time as u64);
         if elapsed_time > 500 {
             warn!(
                 "DefaultMessageStore#putMessage: CommitLog#putMessage cost {}ms",
@@ -706,10 +1467,14 @@ impl MessageStore for DefaultMessageStore {
             }
         }
 
+        if !self.prepare_dledger_leader() {
+            return PutMessageResult::new_default(PutMessageStatus::ServiceNotAvailable);
+        }
         let begin_time = Instant::now();
         //put message to commit log
         let result = self.commit_log.put_messages(msg_batch).await;
         let elapsed_time = begin_time.elapsed().as_millis();
+        self.store_tuning.record_put(elapsed_time as u64);
         if elapsed_time > 500 {
             warn!("not in lock eclipse time(ms) {}ms", elapsed_time,);
         }
@@ -738,8 +1503,7 @@ impl MessageStore for DefaultMessageStore {
     }
 
     fn is_shutdown(&self) -> bool {
-        // todo!()
-        false
+        self.shutdown.load(Ordering::Acquire)
     }
 
     fn get_put_message_hook_list(&self) -> Arc<parking_lot::RwLock<Vec<BoxedPutMessageHook>>> {
@@ -750,6 +1514,14 @@ impl MessageStore for DefaultMessageStore {
         self.put_message_hook_list.write().push(put_message_hook);
     }
 
+    fn add_first_dispatcher(&self, dispatcher: Box<dyn CommitLogDispatcher>) {
+        self.dispatcher.dispatcher_vec.write().insert(0, dispatcher);
+    }
+
+    fn add_dispatcher(&self, dispatcher: Box<dyn CommitLogDispatcher>) {
+        self.dispatcher.dispatcher_vec.write().push(dispatcher);
+    }
+
     fn get_broker_stats_manager(&self) -> Option<Arc<BrokerStatsManager>> {
         self.broker_stats_manager.clone()
     }
@@ -767,6 +1539,22 @@ impl MessageStore for DefaultMessageStore {
         self.get_max_offset_in_queue_committed(topic, queue_id, true)
     }
 
+    fn get_offset_in_queue_by_time_with_boundary(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        timestamp: i64,
+        boundary_type: BoundaryType,
+    ) -> i64 {
+        search_offset_by_time(
+            self.get_min_offset_in_queue(topic, queue_id).max(0),
+            self.get_max_offset_in_queue(topic, queue_id),
+            timestamp,
+            boundary_type,
+            |offset| self.get_message_store_timestamp(topic, queue_id, offset),
+        )
+    }
+
     fn get_max_offset_in_queue_committed(
         &self,
         topic: &CheetahString,
@@ -817,244 +1605,49 @@ impl MessageStore for DefaultMessageStore {
         max_total_msg_size: i32,
         message_filter: Option<Arc<Box<dyn MessageFilter>>>,
     ) -> Option<GetMessageResult> {
-        if self.shutdown.load(Ordering::Relaxed) {
-            warn!("message store has shutdown, so getMessage is forbidden");
-            return None;
-        }
-
-        if !self.running_flags.is_readable() {
-            warn!(
-                "message store is not readable, so getMessage is forbidden {}",
-                self.running_flags.get_flag_bits()
-            );
-            return None;
-        }
-        let topic_config = self.get_topic_config(topic);
-        let policy = get_delete_policy(topic_config.as_ref());
-        if policy == CleanupPolicy::COMPACTION && self.message_store_config.enable_compaction {
-            //not implemented will be implemented in the future
-            return self.compaction_store.get_message(
-                group,
-                topic,
-                queue_id,
-                offset,
-                max_msg_nums,
-                max_total_msg_size,
-            );
-        }
-        let begin_time = Instant::now();
-
-        let mut status = GetMessageStatus::NoMessageInQueue;
-
-        let mut next_begin_offset = offset;
-        let mut min_offset = 0;
-        let mut max_offset = 0;
-        let mut get_result = Some(GetMessageResult::new());
-        let max_offset_py = self.commit_log.get_max_offset();
-        let consume_queue = self.find_consume_queue(topic, queue_id);
-        if let Some(consume_queue) = consume_queue {
-            min_offset = consume_queue.get_min_offset_in_queue();
-            max_offset = consume_queue.get_max_offset_in_queue();
-            if max_offset == 0 {
-                status = GetMessageStatus::NoMessageInQueue;
-                next_begin_offset = self.next_offset_correction(offset, 0);
-            } else if offset < min_offset {
-                status = GetMessageStatus::OffsetTooSmall;
-                next_begin_offset = self.next_offset_correction(offset, min_offset);
-            } else if offset == max_offset {
-                status = GetMessageStatus::OffsetOverflowOne;
-                next_begin_offset = self.next_offset_correction(offset, offset);
-            } else if offset > max_offset {
-                status = GetMessageStatus::OffsetOverflowBadly;
-                next_begin_offset = self.next_offset_correction(offset, max_offset);
-            } else {
-                let max_filter_message_size = self
-                    .message_store_config
-                    .max_filter_message_size
-                    .max(max_msg_nums * consume_queue.get_unit_size());
-                let disk_fall_recorded = self.message_store_config.disk_fall_recorded;
-                let mut max_pull_size = max_total_msg_size.max(100);
-                if max_pull_size > MAX_PULL_MSG_SIZE {
-                    warn!(
-                        "The max pull size is too large maxPullSize={} topic={} queueId={}",
-                        max_pull_size, topic, queue_id
-                    );
-                    max_pull_size = MAX_PULL_MSG_SIZE;
-                }
-                status = GetMessageStatus::NoMatchedMessage;
-                let mut max_phy_offset_pulling = 0;
-                let mut cq_file_num = 0;
-                while get_result.as_ref().unwrap().buffer_total_size() <= 0
-                    && next_begin_offset < max_offset
-                    && cq_file_num
-                        < self
-                            .message_store_config
-                            .travel_cq_file_num_when_get_message
-                {
-                    cq_file_num += 1;
-                    let buffer_consume_queue =
-                        consume_queue.iterate_from_inner(next_begin_offset, max_msg_nums);
-                    if buffer_consume_queue.is_none() {
-                        status = GetMessageStatus::OffsetFoundNull;
-                        next_begin_offset = self.next_offset_correction(
-                            next_begin_offset,
-                            self.consume_queue_store
-                                .roll_next_file(&**consume_queue, next_begin_offset),
-                        );
-                        warn!(
-                            "consumer request topic: {}, offset: {}, minOffset: {}, maxOffset: \
-                             {}, but access logic queue failed. Correct nextBeginOffset to {}",
-                            topic, offset, min_offset, max_offset, next_begin_offset
+        // page faults on cold commit log data would block the reactor thread, so such reads run
+        // on the blocking pool, bounded by cold_read_io_pool_size
+        if self.message_store_config.cold_read_io_pool_size > 0
+            && self.is_cold_read(topic, queue_id, offset)
+        {
+            if let Some(message_store) = self.message_store_arc.clone() {
+                let permit = self.cold_read_permits.clone().acquire_owned().await.ok()?;
+                let group = group.clone();
+                let blocking_topic = topic.clone();
+                let result = tokio::task::spawn_blocking(move || {
+                    let _permit = permit;
+                    message_store.get_message_blocking(
+                        &group,
+                        &blocking_topic,
+                        queue_id,
+                        offset,
+                        max_msg_nums,
+                        max_total_msg_size,
+                        message_filter,
+                    )
+                })
+                .await;
+                return match result {
+                    Ok(result) => result,
+                    Err(e) => {
+                        error!(
+                            "cold read of topic {} queue {} failed: {}",
+                            topic, queue_id, e
                         );
-                        break;
-                    }
-                    let mut next_phy_file_start_offset = i64::MIN;
-                    let mut buffer_consume_queue = buffer_consume_queue.unwrap();
-                    loop {
-                        if next_begin_offset >= max_offset {
-                            break;
-                        }
-                        if let Some(cq_unit) = buffer_consume_queue.next() {
-                            let offset_py = cq_unit.pos;
-                            let size_py = cq_unit.size;
-                            let is_in_mem = estimate_in_mem_by_commit_offset(
-                                offset_py,
-                                max_offset_py,
-                                &self.message_store_config,
-                            );
-                            if (cq_unit.queue_offset - offset)
-                                * consume_queue.get_unit_size() as i64
-                                > max_filter_message_size as i64
-                            {
-                                break;
-                            }
-                            let get_result_ref = get_result.as_mut().unwrap();
-                            if is_the_batch_full(
-                                size_py,
-                                cq_unit.batch_num as i32,
-                                max_msg_nums,
-                                max_pull_size as i64,
-                                get_result_ref.buffer_total_size(),
-                                get_result_ref.message_count(),
-                                is_in_mem,
-                                &self.message_store_config,
-                            ) {
-                                break;
-                            }
-                            if get_result_ref.buffer_total_size() >= max_pull_size {
-                                break;
-                            }
-                            max_phy_offset_pulling = offset_py;
-                            next_begin_offset = cq_unit.queue_offset + cq_unit.batch_num as i64;
-                            if next_phy_file_start_offset != i64::MIN
-                                && offset_py < next_phy_file_start_offset
-                            {
-                                continue;
-                            }
-
-                            if let Some(filter) = message_filter.as_ref() {
-                                if !filter.is_matched_by_consume_queue(
-                                    cq_unit.get_valid_tags_code_as_long(),
-                                    cq_unit.cq_ext_unit.as_ref(),
-                                ) {
-                                    if get_result_ref.buffer_total_size() == 0 {
-                                        status = GetMessageStatus::NoMatchedMessage;
-                                    }
-                                    continue;
-                                }
-                            }
-
-                            let select_result = self.commit_log.get_message(offset_py, size_py);
-                            if select_result.is_none() {
-                                if get_result_ref.buffer_total_size() == 0 {
-                                    status = GetMessageStatus::MessageWasRemoving;
-                                }
-                                next_phy_file_start_offset =
-                                    self.commit_log.roll_next_file(offset_py);
-                                continue;
-                            }
-                            if self.message_store_config.cold_data_flow_control_enable
-                                && !is_sys_consumer_group_for_no_cold_read_limit(group)
-                                && !select_result.as_ref().unwrap().is_in_cache
-                            {
-                                get_result_ref.set_cold_data_sum(
-                                    get_result_ref.cold_data_sum() + size_py as i64,
-                                );
-                            }
-
-                            if message_filter.is_some()
-                                && !message_filter
-                                    .as_ref()
-                                    .as_ref()
-                                    .unwrap()
-                                    .is_matched_by_commit_log(
-                                        Some(select_result.as_ref().unwrap().get_buffer()),
-                                        None,
-                                    )
-                            {
-                                if get_result_ref.buffer_total_size() == 0 {
-                                    status = GetMessageStatus::NoMatchedMessage;
-                                }
-                                drop(select_result);
-                                continue;
-                            }
-                            self.store_stats_service
-                                .get_message_transferred_msg_count()
-                                .fetch_add(cq_unit.batch_num as usize, Ordering::Relaxed);
-                            get_result.as_mut().unwrap().add_message(
-                                select_result.unwrap(),
-                                cq_unit.queue_offset as u64,
-                                cq_unit.batch_num as i32,
-                            );
-                            status = GetMessageStatus::Found;
-                            next_phy_file_start_offset = i64::MIN;
-                        }
+                        None
                     }
-                }
-                if disk_fall_recorded {
-                    let fall_behind = max_offset_py - max_phy_offset_pulling;
-                    self.broker_stats_manager
-                        .as_ref()
-                        .unwrap()
-                        .record_disk_fall_behind_size(group, topic, queue_id, fall_behind);
-                }
-                let diff = max_offset_py - max_phy_offset_pulling;
-                let memory = ((*TOTAL_PHYSICAL_MEMORY_SIZE as f64)
-                    * (self.message_store_config.access_message_in_memory_max_ratio as f64 / 100.0))
-                    as i64;
-                get_result
-                    .as_mut()
-                    .unwrap()
-                    .set_suggest_pulling_from_slave(diff > memory);
+                };
             }
-        } else {
-            status = GetMessageStatus::NoMatchedLogicQueue;
-            next_begin_offset = self.next_offset_correction(offset, 0);
-        }
-
-        if GetMessageStatus::Found == status {
-            self.store_stats_service
-                .get_message_times_total_found()
-                .fetch_add(1, Ordering::Relaxed);
-        } else {
-            self.store_stats_service
-                .get_message_times_total_miss()
-                .fetch_add(1, Ordering::Relaxed);
-        }
-        let elapsed_time = begin_time.elapsed().as_millis() as u64;
-        if get_result.is_none() {
-            get_result = Some(GetMessageResult::new_result_size(0));
         }
-        let result = get_result.as_mut().unwrap();
-        result.set_status(Some(status));
-        result.set_next_begin_offset(next_begin_offset);
-        result.set_max_offset(max_offset);
-        result.set_min_offset(min_offset);
-        /*        println!(
-            "------------------------------------------------{} {} {} {} {}",
-            result, next_begin_offset, max_offset, min_offset, elapsed_time
-        );*/
-        get_result
+        self.get_message_blocking(
+            group,
+            topic,
+            queue_id,
+            offset,
+            max_msg_nums,
+            max_total_msg_size,
+            message_filter,
+        )
     }
 
     fn check_in_mem_by_consume_offset(
@@ -1082,14 +1675,36 @@ impl MessageStore for DefaultMessageStore {
             let size = cq.size;
             return self.check_in_mem_by_commit_offset(start_offset_py, size);
         }
-        let last_cqitem = last_cqitem.as_ref().unwrap();
-        let end_offset_py = last_cqitem.pos;
-        let size = (end_offset_py - start_offset_py) + last_cqitem.size as i64;
-        self.check_in_mem_by_commit_offset(start_offset_py, size as i32)
+        let last_cqitem = last_cqitem.as_ref().unwrap();
+        let end_offset_py = last_cqitem.pos;
+        let size = (end_offset_py - start_offset_py) + last_cqitem.size as i64;
+        self.check_in_mem_by_commit_offset(start_offset_py, size as i32)
+    }
+
+    fn is_msg_in_cold_area(
+        &self,
+        group: &CheetahString,
+        topic: &CheetahString,
+        queue_id: i32,
+        consume_offset: i64,
+    ) -> bool {
+        if !self.message_store_config.cold_data_flow_control_enable
+            || is_sys_consumer_group_for_no_cold_read_limit(group)
+        {
+            return false;
+        }
+        let Some(consume_queue) = self.find_consume_queue(topic, queue_id) else {
+            return false;
+        };
+        match consume_queue.get(consume_offset) {
+            Some(cq_unit) => !self.commit_log.is_data_in_page_cache(cq_unit.pos),
+            None => false,
+        }
     }
 
     fn notify_message_arrive_if_necessary(&self, dispatch_request: &mut DispatchRequest) {
-        if self.broker_config.long_polling_enable && self.message_arriving_listener.is_some() {
+        if self.broker_config.get().long_polling_enable && self.message_arriving_listener.is_some()
+        {
             self.message_arriving_listener.as_ref().unwrap().arriving(
                 dispatch_request.topic.as_ref(),
                 dispatch_request.queue_id,
@@ -1104,6 +1719,15 @@ impl MessageStore for DefaultMessageStore {
         }
     }
 
+    fn set_message_arriving_listener(
+        &mut self,
+        message_arriving_listener: Option<
+            Arc<Box<dyn MessageArrivingListener + Sync + Send + 'static>>,
+        >,
+    ) {
+        self.message_arriving_listener = message_arriving_listener;
+    }
+
     fn find_consume_queue(&self, topic: &CheetahString, queue_id: i32) -> Option<ArcConsumeQueue> {
         Some(
             self.consume_queue_store
@@ -1132,7 +1756,7 @@ impl MessageStore for DefaultMessageStore {
             let consume_queue_table = self.consume_queue_store.get_consume_queue_table();
             consume_queue_table.lock().remove(topic);
 
-            if self.broker_config.auto_delete_unused_stats {
+            if self.broker_config.get().auto_delete_unused_stats {
                 self.broker_stats_manager
                     .as_ref()
                     .unwrap()
@@ -1191,7 +1815,9 @@ impl MessageStore for DefaultMessageStore {
                 if m == 0 {
                     last_query_msg_time = msg.as_ref().unwrap().store_timestamp;
                 }
-                let result = self.commit_log.get_data_with_option(offset, false);
+                // only the message, whether it is in a mapped file or a compressed one
+                let result =
+                    msg.and_then(|msg| self.commit_log.get_message(offset, msg.store_size));
                 if let Some(sbr) = result {
                     query_message_result.add_message(sbr);
                 }
@@ -1261,10 +1887,9 @@ impl MessageStore for DefaultMessageStore {
     ) -> i64 {
         let consume_queue = self.find_consume_queue(topic, queue_id);
         if let Some(consume_queue) = consume_queue {
-            if let Some((_, store_time)) =
-                consume_queue.get_cq_unit_and_store_time(consume_queue_offset)
-            {
-                store_time
+            if let Some(cq_unit) = consume_queue.get(consume_queue_offset) {
+                self.commit_log
+                    .pickup_store_timestamp(cq_unit.pos, cq_unit.size)
             } else {
                 -1
             }
@@ -1272,8 +1897,56 @@ impl MessageStore for DefaultMessageStore {
             -1
         }
     }
+
+    fn get_earliest_message_time_in_queue(&self, topic: &CheetahString, queue_id: i32) -> i64 {
+        let min_offset = self.get_min_offset_in_queue(topic, queue_id).max(0);
+        if min_offset >= self.get_max_offset_in_queue(topic, queue_id) {
+            return -1;
+        }
+        self.get_message_store_timestamp(topic, queue_id, min_offset)
+    }
+
+    fn get_latest_message_time_in_queue(&self, topic: &CheetahString, queue_id: i32) -> i64 {
+        let max_offset = self.get_max_offset_in_queue(topic, queue_id);
+        if max_offset <= self.get_min_offset_in_queue(topic, queue_id).max(0) {
+            return -1;
+        }
+        self.get_message_store_timestamp(topic, queue_id, max_offset - 1)
+    }
+
     fn get_runtime_info(&self) -> HashMap<String, String> {
-        self.store_stats_service.get_runtime_info()
+        let mut result = self.store_stats_service.get_runtime_info();
+        {
+            let mut min_physics_used_ratio = f64::MAX;
+            let commit_log_store_path = Self::get_store_path_physic(&self.message_store_config);
+            for cl_path in commit_log_store_path.split(MULTI_PATH_SPLITTER.as_str()) {
+                let physic_ratio = StoreUtil::get_disk_used_ratio(cl_path);
+                result.insert(
+                    format!("commitLogDiskRatio_{}", cl_path),
+                    physic_ratio.to_string(),
+                );
+                min_physics_used_ratio = min_physics_used_ratio.min(physic_ratio);
+            }
+            result.insert(
+                "commitLogDiskRatio".to_string(),
+                min_physics_used_ratio.to_string(),
+            );
+        }
+        {
+            let logic_ratio = StoreUtil::get_disk_used_ratio(
+                Self::get_store_path_logic(&self.message_store_config).as_str(),
+            );
+            result.insert("consumeQueueDiskRatio".to_string(), logic_ratio.to_string());
+        }
+        result.insert(
+            "commitLogMinOffset".to_string(),
+            self.commit_log.get_min_offset().to_string(),
+        );
+        result.insert(
+            "commitLogMaxOffset".to_string(),
+            self.commit_log.get_max_offset().to_string(),
+        );
+        result
     }
 
     fn lock_time_mills(&self) -> i64 {
@@ -1281,7 +1954,9 @@ impl MessageStore for DefaultMessageStore {
     }
 
     fn get_earliest_message_time(&self) -> i64 {
-        -1
+        let min_phy_offset = self.commit_log.get_min_offset();
+        let size = (MessageDecoder::MESSAGE_STORE_TIMESTAMP_POSITION + 8) as i32;
+        self.commit_log.pickup_store_timestamp(min_phy_offset, size)
     }
 
     fn get_timer_message_store(&self) -> Arc<TimerMessageStore> {
@@ -1310,20 +1985,49 @@ impl MessageStore for DefaultMessageStore {
     fn get_message_store_config(&self) -> &MessageStoreConfig {
         self.message_store_config.as_ref()
     }
+
+    fn set_alive_replica_num_in_group(&mut self, alive_replica_nums: i32) {
+        self.alive_replica_num_in_group
+            .store(alive_replica_nums, Ordering::Release);
+    }
+
+    fn get_alive_replica_num_in_group(&self) -> i32 {
+        self.alive_replica_num_in_group.load(Ordering::Acquire)
+    }
+
+    fn update_ha_master_address(&self, new_addr: &CheetahString) {
+        if let Some(ha_service) = self.ha_service.as_ref() {
+            ha_service.update_ha_master_address(new_addr);
+        }
+    }
+
+    fn set_dledger_role_change_handler(&self, handler: Arc<dyn DLedgerRoleChangeHandler>) {
+        if let Some(dledger_server) = self.dledger_server.as_ref() {
+            dledger_server.set_role_change_handler(handler);
+        }
+    }
+
+    fn get_auto_switch_ha_service(&self) -> Option<Arc<AutoSwitchHAService>> {
+        self.auto_switch_ha_service.clone()
+    }
+
+    fn update_runtime_config(&self, key: &str, value: &str) -> Result<bool, String> {
+        self.store_tuning.update(key, value)
+    }
 }
 
 #[derive(Clone)]
 pub struct CommitLogDispatcherDefault {
     /*build_index: CommitLogDispatcherBuildIndex,
     build_consume_queue: CommitLogDispatcherBuildConsumeQueue,*/
-    dispatcher_vec: Arc<Vec<Box<dyn CommitLogDispatcher>>>,
+    dispatcher_vec: Arc<parking_lot::RwLock<Vec<Box<dyn CommitLogDispatcher>>>>,
 }
 
 impl CommitLogDispatcher for CommitLogDispatcherDefault {
-    fn dispatch(&self, dispatch_request: &DispatchRequest) {
+    fn dispatch(&self, dispatch_request: &mut DispatchRequest) {
         /*self.build_index.dispatch(dispatch_request);
         self.build_consume_queue.dispatch(dispatch_request);*/
-        for dispatcher in self.dispatcher_vec.iter() {
+        for dispatcher in self.dispatcher_vec.read().iter() {
             dispatcher.dispatch(dispatch_request);
         }
     }
@@ -1360,7 +2064,7 @@ impl ReputMessageService {
             .unwrap()
             .split(MULTI_DISPATCH_QUEUE_SPLITTER)
             .collect();
-        let queue_offsets: Vec<&str> = multi_dispatch_queue
+        let queue_offsets: Vec<&str> = multi_queue_offset
             .unwrap()
             .split(MULTI_DISPATCH_QUEUE_SPLITTER)
             .collect();
@@ -1403,6 +2107,7 @@ impl ReputMessageService {
         dispatcher: CommitLogDispatcherDefault,
         notify_message_arrive_in_batch: bool,
         message_store: ArcMut<DefaultMessageStore>,
+        store_tuning: Arc<StoreTuning>,
     ) {
         let mut inner = ReputMessageServiceInner {
             reput_from_offset: self.reput_from_offset.clone().unwrap(),
@@ -1411,6 +2116,7 @@ impl ReputMessageService {
             dispatcher,
             notify_message_arrive_in_batch,
             message_store,
+            store_tuning,
         };
         self.inner = Some(inner.clone());
         let (tx, mut rx) = tokio::sync::mpsc::channel(1);
@@ -1474,6 +2180,7 @@ struct ReputMessageServiceInner {
     dispatcher: CommitLogDispatcherDefault,
     notify_message_arrive_in_batch: bool,
     message_store: ArcMut<DefaultMessageStore>,
+    store_tuning: Arc<StoreTuning>,
 }
 
 impl ReputMessageServiceInner {
@@ -1538,6 +2245,8 @@ impl ReputMessageServiceInner {
             self.reput_from_offset
                 .store(self.commit_log.get_min_offset(), Ordering::Release);
         }
+        let reput_batch_messages = self.store_tuning.reput_batch_messages();
+        let mut dispatched = 0;
         let mut do_next = true;
         while do_next && self.is_commit_log_available() {
             let result = self
@@ -1550,28 +2259,26 @@ impl ReputMessageServiceInner {
             self.reput_from_offset
                 .store(result.start_offset as i64, Ordering::SeqCst);
             let mut read_size = 0i32;
-            let mapped_file = result.mapped_file.as_ref().unwrap();
-            let start_pos = (result.start_offset % mapped_file.get_file_size()) as i32;
             loop {
-                let size = mapped_file.get_bytes((start_pos + read_size) as usize, 4);
+                // the data of a compressed file ends with the last whole message it holds
+                let size = result.get_data(read_size as usize, 4);
                 if size.is_none() {
                     do_next = false;
                     break;
                 }
-                let mut bytes = mapped_file.get_data(
-                    (start_pos + read_size) as usize,
-                    size.unwrap().get_i32() as usize,
-                );
+                let mut bytes =
+                    result.get_data(read_size as usize, size.unwrap().get_i32().max(0) as usize);
                 if bytes.is_none() {
                     do_next = false;
                     break;
                 }
 
+                let check_crc_on_dispatch = self.message_store_config.check_crc_on_dispatch;
                 let mut dispatch_request = commit_log::check_message_and_return_size(
                     bytes.as_mut().unwrap(),
+                    check_crc_on_dispatch,
                     false,
-                    false,
-                    false,
+                    check_crc_on_dispatch,
                     &self.message_store_config,
                 );
                 if self.reput_from_offset.load(Ordering::Acquire) + dispatch_request.msg_size as i64
@@ -1583,7 +2290,7 @@ impl ReputMessageServiceInner {
                 if dispatch_request.success {
                     match dispatch_request.msg_size.cmp(&0) {
                         std::cmp::Ordering::Greater => {
-                            self.dispatcher.dispatch(&dispatch_request);
+                            self.dispatcher.dispatch(&mut dispatch_request);
                             if !self.notify_message_arrive_in_batch {
                                 self.message_store
                                     .notify_message_arrive_if_necessary(&mut dispatch_request);
@@ -1591,10 +2298,23 @@ impl ReputMessageServiceInner {
                             self.reput_from_offset
                                 .fetch_add(dispatch_request.msg_size as i64, Ordering::AcqRel);
                             read_size += dispatch_request.msg_size;
+                            dispatched += 1;
+                            if dispatched >= reput_batch_messages {
+                                // yield, the next round goes on from reput_from_offset
+                                do_next = false;
+                            }
+                            // a slave counts the puts it replicated, its commit log is not
+                            // written through put_message
                             if !self.message_store_config.duplication_enable
                                 && self.message_store_config.broker_role == BrokerRole::Slave
                             {
-                                unimplemented!()
+                                self.message_store
+                                    .store_stats_service
+                                    .add_single_put_message_topic(
+                                        dispatch_request.topic.as_str(),
+                                        dispatch_request.batch_size as usize,
+                                        dispatch_request.msg_size as usize,
+                                    );
                             }
                         }
                         std::cmp::Ordering::Equal => {
@@ -1614,10 +2334,18 @@ impl ReputMessageServiceInner {
                     );
                     self.reput_from_offset
                         .fetch_add(dispatch_request.msg_size as i64, Ordering::SeqCst);
+                    read_size += dispatch_request.msg_size;
                 } else {
                     do_next = false;
+                    // the DLedger commit log is not left behind at a broken message, the rest
+                    // of the range is skipped
                     if self.message_store_config.enable_dledger_commit_log {
-                        unimplemented!()
+                        error!(
+                            "[BUG]dispatch message to consume queue error, COMMITLOG OFFSET: {}",
+                            self.reput_from_offset.load(Ordering::Relaxed)
+                        );
+                        self.reput_from_offset
+                            .fetch_add((result.size - read_size) as i64, Ordering::SeqCst);
                     }
                 }
 
@@ -1646,19 +2374,282 @@ impl ReputMessageServiceInner {
     }
 }
 
-struct CleanCommitLogService {}
+/// How many runs of the [`CleanCommitLogService`] delete files after
+/// [`DefaultMessageStore::execute_delete_files_manually`].
+const MAX_MANUAL_DELETE_FILE_TIMES: i32 = 20;
+
+/// How the used ratio of a disk compares to the retention watermarks, from the least to the
+/// most used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum DiskUsage {
+    Ok,
+    OverMaxUsed,
+    OverCleanForcibly,
+    OverWarning,
+}
+
+/// The used ratios of a disk at which the [`CleanCommitLogService`] deletes files early,
+/// bounded as in the Java broker.
+#[derive(Debug, Clone, Copy)]
+struct DiskWatermarks {
+    warning: f64,
+    clean_forcibly: f64,
+    max_used: f64,
+}
+
+impl DiskWatermarks {
+    fn new(message_store_config: &MessageStoreConfig) -> Self {
+        Self {
+            warning: (message_store_config.disk_space_warning_level_ratio as f64 / 100.0)
+                .clamp(0.35, 0.90),
+            clean_forcibly: (message_store_config.disk_space_clean_forcibly_ratio as f64 / 100.0)
+                .clamp(0.35, 0.85),
+            max_used: message_store_config.disk_max_used_space_ratio.clamp(10, 95) as f64 / 100.0,
+        }
+    }
+
+    /// A disk that can not be measured, with a negative ratio, counts as not full.
+    fn usage(&self, used_ratio: f64) -> DiskUsage {
+        if used_ratio > self.warning {
+            DiskUsage::OverWarning
+        } else if used_ratio > self.clean_forcibly {
+            DiskUsage::OverCleanForcibly
+        } else if used_ratio > self.max_used {
+            DiskUsage::OverMaxUsed
+        } else {
+            DiskUsage::Ok
+        }
+    }
+}
+
+/// Deletes the commit log files last modified more than `fileReservedTime` hours ago, at the
+/// `deleteWhen` hours or as soon as the commit log or consume queue disk is over
+/// `diskMaxUsedSpaceRatio`. A file whose topics are all known expires instead once the newest
+/// message of every topic in it is past the `reserve.time` of the topic, `fileReservedTime` for
+/// a topic without one, without waiting for the `deleteWhen` hours.
+///
+/// Over `diskSpaceCleanForciblyRatio` the files are deleted whatever their age with
+/// `cleanFileForciblyEnable`, and over `diskSpaceWarningLevelRatio` the store also refuses
+/// messages until the disk is back under it. A file still held by readers is only unmapped and
+/// deleted again every `redeleteHangedFileInterval` millis.
+struct CleanCommitLogService {
+    message_store_config: Arc<MessageStoreConfig>,
+    commit_log: CommitLog,
+    topic_config_table: Arc<parking_lot::Mutex<HashMap<CheetahString, TopicConfig>>>,
+    topic_retention: Arc<TopicRetention>,
+    running_flags: Arc<RunningFlags>,
+    retention_guard: Arc<parking_lot::RwLock<Option<Arc<dyn RetentionGuard>>>>,
+    manual_delete_file_several_times: AtomicI32,
+    last_redelete_timestamp: AtomicI64,
+}
 
 impl CleanCommitLogService {
+    fn new(
+        message_store_config: Arc<MessageStoreConfig>,
+        commit_log: CommitLog,
+        topic_config_table: Arc<parking_lot::Mutex<HashMap<CheetahString, TopicConfig>>>,
+        topic_retention: Arc<TopicRetention>,
+        running_flags: Arc<RunningFlags>,
+        retention_guard: Arc<parking_lot::RwLock<Option<Arc<dyn RetentionGuard>>>>,
+    ) -> Self {
+        Self {
+            message_store_config,
+            commit_log,
+            topic_config_table,
+            topic_retention,
+            running_flags,
+            retention_guard,
+            manual_delete_file_several_times: AtomicI32::new(0),
+            last_redelete_timestamp: AtomicI64::new(0),
+        }
+    }
+
+    fn execute_delete_files_manually(&self) {
+        self.manual_delete_file_several_times
+            .store(MAX_MANUAL_DELETE_FILE_TIMES, Ordering::Release);
+        info!("executeDeleteFilesManually was invoked");
+    }
+
     fn run(&self) {
-        info!("clean commit log service run unimplemented!")
+        self.delete_expired_files();
+        self.redelete_hanged_file();
+    }
+
+    fn delete_expired_files(&self) {
+        let message_store_config = &self.message_store_config;
+        let default_reserved_time = message_store_config.file_reserved_time as i64 * 60 * 60 * 1000;
+        let reserve_times: HashMap<CheetahString, i64> = self
+            .topic_config_table
+            .lock()
+            .iter()
+            .map(|(topic, topic_config)| (topic.clone(), topic_config.get_reserve_time()))
+            .collect();
+        let reserve_time = |topic: &CheetahString| reserve_times.get(topic).copied().unwrap_or(-1);
+        let expire_timestamp = |file_from_offset: i64, last_modified: i64| {
+            self.topic_retention
+                .expire_timestamp(file_from_offset, default_reserved_time, reserve_time)
+                .unwrap_or(last_modified + default_reserved_time)
+        };
+
+        let time_up = util_all::is_it_time_to_do(message_store_config.delete_when.as_str());
+        let disk_usage = self.check_disk_usage();
+        let manual_delete = self
+            .manual_delete_file_several_times
+            .load(Ordering::Acquire)
+            > 0;
+        // the topics with a short reserve.time do not wait for the deleteWhen hours
+        let min_offset = self.commit_log.get_min_offset();
+        let topics_expired = self.commit_log.roll_next_file(min_offset)
+            < self.commit_log.get_max_offset()
+            && self
+                .topic_retention
+                .expire_timestamp(min_offset, default_reserved_time, reserve_time)
+                .is_some_and(|expire_timestamp| get_current_millis() as i64 >= expire_timestamp);
+        if !time_up && !topics_expired && disk_usage == DiskUsage::Ok && !manual_delete {
+            return;
+        }
+        if manual_delete {
+            self.manual_delete_file_several_times
+                .fetch_sub(1, Ordering::AcqRel);
+        }
+        let clean_at_once = message_store_config.clean_file_forcibly_enable
+            && disk_usage >= DiskUsage::OverCleanForcibly;
+        info!(
+            "begin to delete before {} hours file. timeUp: {} topicsExpired: {} diskUsage: {:?} \
+             manualDeleteFileSeveralTimes: {} cleanAtOnce: {} deleteFileBatchMax: {}",
+            message_store_config.file_reserved_time,
+            time_up,
+            topics_expired,
+            disk_usage,
+            self.manual_delete_file_several_times
+                .load(Ordering::Acquire),
+            clean_at_once,
+            message_store_config.delete_file_batch_max
+        );
+        let retention_guard = self.retention_guard.read().clone();
+        let can_delete = |file_path: &str| {
+            retention_guard.as_ref().map_or(true, |retention_guard| {
+                retention_guard.can_delete(file_path)
+            })
+        };
+        let delete_count = self.commit_log.delete_expired_file(
+            &expire_timestamp,
+            message_store_config.delete_commit_log_files_interval as u64,
+            message_store_config.destroy_mapped_file_interval_forcibly as u64,
+            clean_at_once,
+            message_store_config.delete_file_batch_max,
+            &can_delete,
+        );
+        if delete_count > 0 {
+            info!("deleted {} expired commit log files", delete_count);
+            self.topic_retention
+                .forget_files_before(self.commit_log.get_min_offset());
+        } else if disk_usage > DiskUsage::Ok {
+            warn!("disk space will be full soon, but delete file failed.");
+        }
+    }
+
+    /// Measures the commit log and the consume queue disks, marking the store not writeable
+    /// while either is over the warning level.
+    fn check_disk_usage(&self) -> DiskUsage {
+        let watermarks = DiskWatermarks::new(&self.message_store_config);
+        let physic_ratio = match self.commit_log.get_multi_store_paths() {
+            // the commit log keeps growing as long as one of its paths has space left
+            Some(multi_store_paths) => multi_store_paths
+                .refresh_disk_ratios(watermarks.clean_forcibly)
+                .into_iter()
+                .map(|(_, ratio)| ratio)
+                .filter(|ratio| *ratio >= 0.0)
+                .min_by(f64::total_cmp)
+                .unwrap_or(-1.0),
+            None => StoreUtil::get_disk_used_ratio(
+                DefaultMessageStore::get_store_path_physic(&self.message_store_config).as_str(),
+            ),
+        };
+        let physic_usage = watermarks.usage(physic_ratio);
+        if physic_usage == DiskUsage::OverWarning {
+            if self.running_flags.get_and_make_disk_full() {
+                error!(
+                    "physic disk maybe full soon {}, so mark disk full",
+                    physic_ratio
+                );
+            }
+        } else if !self.running_flags.get_and_make_disk_ok() {
+            info!("physic disk space OK now {}, so mark disk ok", physic_ratio);
+        }
+
+        let logic_ratio = StoreUtil::get_disk_used_ratio(
+            DefaultMessageStore::get_store_path_logic(&self.message_store_config).as_str(),
+        );
+        let logic_usage = watermarks.usage(logic_ratio);
+        if logic_usage == DiskUsage::OverWarning {
+            if self.running_flags.get_and_make_logic_disk_full() {
+                error!(
+                    "logic disk maybe full soon {}, so mark disk full",
+                    logic_ratio
+                );
+            }
+        } else if !self.running_flags.get_and_make_logic_disk_ok() {
+            info!("logic disk space OK now {}, so mark disk ok", logic_ratio);
+        }
+        physic_usage.max(logic_usage)
+    }
+
+    fn redelete_hanged_file(&self) {
+        let now = get_current_millis() as i64;
+        let last_redelete_timestamp = self.last_redelete_timestamp.load(Ordering::Acquire);
+        if now - last_redelete_timestamp
+            > self.message_store_config.redelete_hanged_file_interval as i64
+        {
+            self.last_redelete_timestamp.store(now, Ordering::Release);
+            self.commit_log.retry_delete_first_file(
+                self.message_store_config
+                    .destroy_mapped_file_interval_forcibly as u64,
+            );
+        }
     }
 }
 
-struct CleanConsumeQueueService {}
+/// Deletes the consume queue files, with their extend files, and the index files of the
+/// messages the commit log no longer has, in lockstep with its retention.
+struct CleanConsumeQueueService {
+    commit_log: CommitLog,
+    consume_queue_store: ConsumeQueueStore,
+    index_service: IndexService,
+    last_physical_min_offset: AtomicI64,
+}
 
 impl CleanConsumeQueueService {
+    fn new(
+        commit_log: CommitLog,
+        consume_queue_store: ConsumeQueueStore,
+        index_service: IndexService,
+    ) -> Self {
+        Self {
+            commit_log,
+            consume_queue_store,
+            index_service,
+            last_physical_min_offset: AtomicI64::new(0),
+        }
+    }
+
     fn run(&self) {
-        println!("clean consume queue service run unimplemented!")
+        let min_offset = self.commit_log.get_min_offset();
+        if min_offset > self.last_physical_min_offset.load(Ordering::Acquire) {
+            self.last_physical_min_offset
+                .store(min_offset, Ordering::Release);
+            let consume_queue_table = self.consume_queue_store.get_consume_queue_table();
+            let consume_queues: Vec<ArcConsumeQueue> = consume_queue_table
+                .lock()
+                .values()
+                .flat_map(|queues| queues.values().cloned())
+                .collect();
+            for consume_queue in consume_queues {
+                self.consume_queue_store
+                    .delete_expired_file(&**consume_queue, min_offset);
+            }
+            self.index_service.delete_expired_file(min_offset as u64);
+        }
     }
 }
 
@@ -1669,3 +2660,712 @@ impl CorrectLogicOffsetService {
         println!("correct logic offset service run unimplemented!")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use bytes::Bytes;
+    use rocketmq_common::common::broker::broker_config::BrokerConfig;
+    use rocketmq_common::common::message::message_decoder::message_properties_to_string;
+    use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+    use rocketmq_common::common::message::MessageTrait;
+
+    use super::*;
+    use crate::config::flush_disk_type::FlushDiskType;
+    use crate::config::store_timestamp_mode::StoreTimestampMode;
+
+    const STORE_TIMES: [i64; 6] = [10, 20, 20, 20, 30, 40];
+
+    fn search(timestamp: i64, boundary_type: BoundaryType) -> i64 {
+        search_offset_by_time(
+            0,
+            STORE_TIMES.len() as i64,
+            timestamp,
+            boundary_type,
+            |offset| STORE_TIMES[offset as usize],
+        )
+    }
+
+    #[test]
+    fn disk_watermarks_are_bounded_and_ordered() {
+        let message_store_config = MessageStoreConfig {
+            disk_space_warning_level_ratio: 99,
+            disk_space_clean_forcibly_ratio: 80,
+            disk_max_used_space_ratio: 5,
+            ..MessageStoreConfig::default()
+        };
+        let watermarks = DiskWatermarks::new(&message_store_config);
+        assert_eq!(watermarks.usage(-1.0), DiskUsage::Ok);
+        assert_eq!(watermarks.usage(0.05), DiskUsage::Ok);
+        assert_eq!(watermarks.usage(0.5), DiskUsage::OverMaxUsed);
+        assert_eq!(watermarks.usage(0.85), DiskUsage::OverCleanForcibly);
+        assert_eq!(watermarks.usage(0.95), DiskUsage::OverWarning);
+    }
+
+    #[test]
+    fn search_offset_by_time_picks_the_requested_end_of_equal_timestamps() {
+        assert_eq!(search(20, BoundaryType::Lower), 1);
+        assert_eq!(search(20, BoundaryType::Upper), 3);
+    }
+
+    #[test]
+    fn search_offset_by_time_between_messages() {
+        assert_eq!(search(25, BoundaryType::Lower), 4);
+        assert_eq!(search(25, BoundaryType::Upper), 3);
+        assert_eq!(search(5, BoundaryType::Upper), 0);
+        assert_eq!(search(50, BoundaryType::Lower), 5);
+        assert_eq!(
+            search_offset_by_time(3, 3, 20, BoundaryType::Lower, |_| -1),
+            3
+        );
+    }
+
+    /// Builds a started store under `store_path_root_dir`.
+    pub(crate) async fn start_store(
+        message_store_config: MessageStoreConfig,
+    ) -> ArcMut<DefaultMessageStore> {
+        start_store_with_broker_config(message_store_config, BrokerConfig::default()).await
+    }
+
+    /// Builds a started store under `store_path_root_dir` of a broker with `broker_config`.
+    pub(crate) async fn start_store_with_broker_config(
+        message_store_config: MessageStoreConfig,
+        broker_config: BrokerConfig,
+    ) -> ArcMut<DefaultMessageStore> {
+        let message_store_config = MessageStoreConfig {
+            mapped_file_size_commit_log: 1024 * 1024,
+            mapped_file_size_consume_queue: 20 * 1024,
+            ..message_store_config
+        };
+        let broker_config = BrokerConfig {
+            store_path_root_dir: message_store_config.store_path_root_dir.clone(),
+            ..broker_config
+        };
+        let mut message_store = ArcMut::new(DefaultMessageStore::new(
+            Arc::new(message_store_config),
+            SharedBrokerConfig::new(broker_config),
+            Default::default(),
+            None,
+            false,
+        ));
+        let message_store_clone = message_store.clone();
+        message_store.set_message_store_arc(Some(message_store_clone));
+        assert!(message_store.load().await);
+        message_store.start().unwrap();
+        message_store
+    }
+
+    pub(crate) fn message(topic: &str, body: &'static [u8]) -> MessageExtBrokerInner {
+        let mut msg = MessageExtBrokerInner::default();
+        msg.set_topic(CheetahString::from_slice(topic));
+        msg.set_body(Bytes::from_static(body));
+        msg.message_ext_inner
+            .set_born_host("127.0.0.1:10911".parse().unwrap());
+        msg.message_ext_inner
+            .set_store_host("127.0.0.1:10911".parse().unwrap());
+        msg
+    }
+
+    /// Waits up to five seconds for `condition` to hold.
+    pub(crate) async fn wait_until(mut condition: impl FnMut() -> bool) -> bool {
+        for _ in 0..100 {
+            if condition() {
+                return true;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        condition()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn slave_dispatches_the_messages_replicated_from_its_master() {
+        let master_dir = tempfile::tempdir().unwrap();
+        let slave_dir = tempfile::tempdir().unwrap();
+        let ha_listen_port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port() as usize;
+        let mut master = start_store(MessageStoreConfig {
+            store_path_root_dir: master_dir.path().to_string_lossy().to_string().into(),
+            ha_listen_port,
+            ..MessageStoreConfig::default()
+        })
+        .await;
+        let mut slave = start_store(MessageStoreConfig {
+            store_path_root_dir: slave_dir.path().to_string_lossy().to_string().into(),
+            broker_role: BrokerRole::Slave,
+            ha_master_address: Some(format!("127.0.0.1:{}", ha_listen_port)),
+            ..MessageStoreConfig::default()
+        })
+        .await;
+
+        for body in [b"first".as_slice(), b"second".as_slice()] {
+            let result = master.put_message(message("ReplicatedTopic", body)).await;
+            assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+        }
+        let topic = CheetahString::from_static_str("ReplicatedTopic");
+        assert!(
+            wait_until(|| slave.get_max_offset_in_queue(&topic, 0) == 2).await,
+            "the slave dispatched {} messages",
+            slave.get_max_offset_in_queue(&topic, 0)
+        );
+        assert_eq!(slave.get_max_phy_offset(), master.get_max_phy_offset());
+        assert_eq!(
+            slave
+                .store_stats_service
+                .get_single_put_message_topic_times_total("ReplicatedTopic"),
+            2
+        );
+
+        slave.shutdown();
+        master.shutdown();
+    }
+
+    fn free_port() -> usize {
+        std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port() as usize
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn returning_master_catches_up_with_the_slave_acting_as_master() {
+        let master_dir = tempfile::tempdir().unwrap();
+        let slave_dir = tempfile::tempdir().unwrap();
+        let returning_dir = tempfile::tempdir().unwrap();
+        let master_port = free_port();
+        let slave_port = free_port();
+        let mut master = start_store(MessageStoreConfig {
+            store_path_root_dir: master_dir.path().to_string_lossy().to_string().into(),
+            ha_listen_port: master_port,
+            ..MessageStoreConfig::default()
+        })
+        .await;
+        let mut slave = start_store_with_broker_config(
+            MessageStoreConfig {
+                store_path_root_dir: slave_dir.path().to_string_lossy().to_string().into(),
+                broker_role: BrokerRole::Slave,
+                ha_listen_port: slave_port,
+                ha_master_address: Some(format!("127.0.0.1:{}", master_port)),
+                ..MessageStoreConfig::default()
+            },
+            BrokerConfig {
+                enable_slave_acting_master: true,
+                ..BrokerConfig::default()
+            },
+        )
+        .await;
+        for body in [b"first".as_slice(), b"second".as_slice()] {
+            let result = master.put_message(message("FailoverTopic", body)).await;
+            assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+        }
+        let max_phy_offset = master.get_max_phy_offset();
+        assert!(wait_until(|| slave.get_max_phy_offset() == max_phy_offset).await);
+        master.shutdown();
+
+        // the master comes back with an empty store
+        let mut returning = start_store(MessageStoreConfig {
+            store_path_root_dir: returning_dir.path().to_string_lossy().to_string().into(),
+            ha_listen_port: free_port(),
+            ..MessageStoreConfig::default()
+        })
+        .await;
+        returning.update_ha_master_address(&format!("127.0.0.1:{}", slave_port).into());
+        assert!(
+            wait_until(|| returning.get_max_phy_offset() == max_phy_offset).await,
+            "the returning master replicated up to {}",
+            returning.get_max_phy_offset()
+        );
+        returning.update_ha_master_address(&CheetahString::empty());
+        assert_eq!(
+            returning
+                .ha_service
+                .as_ref()
+                .unwrap()
+                .get_ha_master_address(),
+            None
+        );
+
+        returning.shutdown();
+        slave.shutdown();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn role_changes_truncate_the_unconfirmed_commit_log_under_the_put_lock() {
+        let store_dir = tempfile::tempdir().unwrap();
+        let mut broker_config = BrokerConfig {
+            enable_controller_mode: true,
+            ..BrokerConfig::default()
+        };
+        broker_config.broker_identity.broker_id = 2;
+        let mut message_store = start_store_with_broker_config(
+            MessageStoreConfig {
+                store_path_root_dir: store_dir.path().to_string_lossy().to_string().into(),
+                ..MessageStoreConfig::default()
+            },
+            broker_config,
+        )
+        .await;
+        let ha_service = message_store.get_auto_switch_ha_service().unwrap();
+        assert!(!ha_service.is_master());
+
+        assert!(ha_service.change_to_master(1).await);
+        assert!(ha_service.is_master());
+        let result = message_store
+            .put_message(message("RoleTopic", b"first"))
+            .await;
+        assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+        let confirmed = message_store.get_max_phy_offset();
+        let result = message_store
+            .put_message(message("RoleTopic", b"second"))
+            .await;
+        assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+        let max_phy_offset = message_store.get_max_phy_offset();
+
+        // the master confirmed everything the sync state set, itself only, holds
+        assert!(ha_service.change_to_slave(None, 2, None).await);
+        assert!(!ha_service.is_master());
+        assert_eq!(message_store.get_max_phy_offset(), max_phy_offset);
+
+        // the new master only confirmed the first message
+        message_store.set_confirm_offset(confirmed);
+        let put_message_lock = message_store.commit_log.put_message_lock().clone();
+        let put_message_guard = put_message_lock.lock().await;
+        let change = tokio::spawn({
+            let ha_service = ha_service.clone();
+            async move { ha_service.change_to_master(3).await }
+        });
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(!change.is_finished());
+        assert_eq!(message_store.get_max_phy_offset(), max_phy_offset);
+        drop(put_message_guard);
+        assert!(change.await.unwrap());
+        assert_eq!(message_store.get_max_phy_offset(), confirmed);
+        assert_eq!(
+            message_store.get_max_offset_in_queue(&CheetahString::from_static_str("RoleTopic"), 0),
+            1
+        );
+
+        assert!(!ha_service.change_to_master(2).await);
+        let epochs = ha_service
+            .get_epoch_entries()
+            .iter()
+            .map(|entry| (entry.epoch, entry.start_offset))
+            .collect::<Vec<_>>();
+        // epoch 2 started past the truncated offset
+        assert_eq!(epochs, vec![(1, 0), (3, confirmed)]);
+
+        message_store.shutdown();
+    }
+
+    /// Waits up to thirty seconds, a few election timeouts, for one of `members` to lead.
+    async fn wait_for_dledger_leader(members: &[ArcMut<DefaultMessageStore>]) -> Option<usize> {
+        for _ in 0..300 {
+            let leaders: Vec<usize> = (0..members.len())
+                .filter(|i| members[*i].get_dledger_server().unwrap().is_leader())
+                .collect();
+            if let [leader] = leaders.as_slice() {
+                return Some(*leader);
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        None
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn dledger_group_elects_a_leader_and_replicates_its_puts() {
+        let dirs: Vec<_> = (0..3).map(|_| tempfile::tempdir().unwrap()).collect();
+        let peers = (0..3)
+            .map(|i| format!("n{}-127.0.0.1:{}", i, free_port()))
+            .collect::<Vec<_>>()
+            .join(";");
+        let mut members = Vec::new();
+        for (i, dir) in dirs.iter().enumerate() {
+            members.push(
+                start_store(MessageStoreConfig {
+                    store_path_root_dir: dir.path().to_string_lossy().to_string().into(),
+                    enable_dledger_commit_log: true,
+                    dledger_group: Some("DLedgerGroup".to_string()),
+                    dledger_peers: Some(peers.clone()),
+                    dledger_self_id: Some(format!("n{}", i)),
+                    // the disk check of a nearly full test machine would stop the dispatch
+                    clean_resource_interval: 600_000,
+                    ..MessageStoreConfig::default()
+                })
+                .await,
+            );
+        }
+        let topic = CheetahString::from_static_str("DLedgerTopic");
+
+        let leader = wait_for_dledger_leader(&members).await.expect("no leader");
+        let term = members[leader].get_dledger_server().unwrap().term();
+        for (i, member) in members.iter().enumerate() {
+            if i != leader {
+                let result = member
+                    .clone()
+                    .put_message(message("DLedgerTopic", b"rejected"))
+                    .await;
+                assert_eq!(
+                    result.put_message_status(),
+                    PutMessageStatus::ServiceNotAvailable
+                );
+            }
+        }
+        for body in [b"first".as_slice(), b"second".as_slice()] {
+            let result = members[leader]
+                .put_message(message("DLedgerTopic", body))
+                .await;
+            assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+        }
+        let max_phy_offset = members[leader].get_max_phy_offset();
+        for member in &members {
+            assert!(
+                wait_until(|| member.get_max_phy_offset() == max_phy_offset
+                    && member.get_max_offset_in_queue(&topic, 0) == 2)
+                .await,
+                "a member holds the commit log up to {}",
+                member.get_max_phy_offset()
+            );
+        }
+
+        // the two members left elect a new leader in a later term, which takes puts
+        let mut old_leader = members.remove(leader);
+        old_leader.shutdown();
+        let leader = wait_for_dledger_leader(&members)
+            .await
+            .expect("no leader after the leader left");
+        assert!(members[leader].get_dledger_server().unwrap().term() > term);
+        // the new leader takes puts once it dispatched what the old one committed
+        let mut status = PutMessageStatus::ServiceNotAvailable;
+        for _ in 0..100 {
+            status = members[leader]
+                .put_message(message("DLedgerTopic", b"third"))
+                .await
+                .put_message_status();
+            if status != PutMessageStatus::ServiceNotAvailable {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        assert_eq!(status, PutMessageStatus::PutOk);
+        let max_phy_offset = members[leader].get_max_phy_offset();
+        for member in &members {
+            assert!(
+                wait_until(|| member.get_max_offset_in_queue(&topic, 0) == 3).await,
+                "a member dispatched {} messages",
+                member.get_max_offset_in_queue(&topic, 0)
+            );
+            assert_eq!(member.get_max_phy_offset(), max_phy_offset);
+        }
+
+        for mut member in members {
+            member.shutdown();
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn dledger_entries_past_the_committed_index_stay_uncommitted_after_a_restart() {
+        let dirs: Vec<_> = (0..3).map(|_| tempfile::tempdir().unwrap()).collect();
+        let peers = (0..3)
+            .map(|i| format!("n{}-127.0.0.1:{}", i, free_port()))
+            .collect::<Vec<_>>()
+            .join(";");
+        let config = |i: usize| MessageStoreConfig {
+            store_path_root_dir: dirs[i].path().to_string_lossy().to_string().into(),
+            enable_dledger_commit_log: true,
+            dledger_group: Some("DLedgerGroup".to_string()),
+            dledger_peers: Some(peers.clone()),
+            dledger_self_id: Some(format!("n{}", i)),
+            sync_flush_timeout: 500,
+            clean_resource_interval: 600_000,
+            ..MessageStoreConfig::default()
+        };
+        let mut members = Vec::new();
+        for i in 0..3 {
+            members.push(start_store(config(i)).await);
+        }
+        let topic = CheetahString::from_static_str("DLedgerTopic");
+
+        let leader = wait_for_dledger_leader(&members).await.expect("no leader");
+        let result = members[leader]
+            .put_message(message("DLedgerTopic", b"committed"))
+            .await;
+        assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+        let dledger_server = members[leader].get_dledger_server().unwrap().clone();
+        let committed_index = dledger_server.get_committed_index();
+        let committed_pos = dledger_server.get_committed_pos();
+
+        // without its followers the leader appends the entry but cannot commit it
+        for (i, member) in members.iter_mut().enumerate() {
+            if i != leader {
+                member.shutdown();
+            }
+        }
+        let result = members[leader]
+            .put_message(message("DLedgerTopic", b"uncommitted"))
+            .await;
+        assert_eq!(
+            result.put_message_status(),
+            PutMessageStatus::FlushSlaveTimeout
+        );
+        let last_index = dledger_server.get_last_index();
+        assert!(last_index > committed_index);
+        assert_eq!(dledger_server.get_committed_index(), committed_index);
+        members[leader].shutdown();
+        drop(dledger_server);
+
+        let mut restarted = start_store(config(leader)).await;
+        let dledger_server = restarted.get_dledger_server().unwrap();
+        assert_eq!(dledger_server.get_last_index(), last_index);
+        assert_eq!(dledger_server.get_committed_index(), committed_index);
+        assert_eq!(dledger_server.get_committed_pos(), committed_pos);
+        assert!(restarted.get_max_phy_offset() > committed_pos);
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert_eq!(restarted.get_max_offset_in_queue(&topic, 0), 1);
+        restarted.shutdown();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn born_time_store_timestamps_grow_along_the_commit_log() {
+        let store_dir = tempfile::tempdir().unwrap();
+        let mut message_store = start_store(MessageStoreConfig {
+            store_path_root_dir: store_dir.path().to_string_lossy().to_string().into(),
+            store_timestamp_mode: StoreTimestampMode::BornTime,
+            ..MessageStoreConfig::default()
+        })
+        .await;
+        let now = get_current_millis() as i64;
+        let put = |born_timestamp: i64| {
+            let mut msg = message("BornTimeTopic", b"body");
+            msg.message_ext_inner.set_born_timestamp(born_timestamp);
+            let mut message_store = message_store.clone();
+            async move {
+                let result = message_store.put_message(msg).await;
+                assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+                let result = result.append_message_result().unwrap();
+                let store_timestamp = message_store
+                    .commit_log
+                    .pickup_store_timestamp(result.wrote_offset, result.wrote_bytes);
+                assert_eq!(store_timestamp, result.store_timestamp);
+                store_timestamp
+            }
+        };
+
+        // a born timestamp is kept
+        assert_eq!(put(now - 60_000).await, now - 60_000);
+        // a client clock running ahead does not move the store timestamps ahead
+        let ahead = put(now + 3_600_000).await;
+        assert!(ahead >= now && ahead <= get_current_millis() as i64);
+        // nor does one running behind move them back
+        assert_eq!(put(now - 120_000).await, ahead);
+
+        message_store.shutdown();
+    }
+
+    static BODY: [u8; 4000] = [b'x'; 4000];
+
+    /// Fills the first commit log file with `count` messages of `topic` and more, until the
+    /// first file is sealed. The first message has `first_key`. Returns the size of a message.
+    async fn fill_first_file(
+        message_store: &mut ArcMut<DefaultMessageStore>,
+        topic: &str,
+        first_key: &str,
+    ) -> i64 {
+        let mut msg = message(topic, &BODY);
+        msg.set_keys(CheetahString::from_slice(first_key));
+        msg.properties_string = message_properties_to_string(msg.get_properties());
+        let result = message_store.put_message(msg).await;
+        let message_size = result.append_message_result().unwrap().wrote_bytes as i64;
+        while message_store.get_max_phy_offset() <= 1024 * 1024 {
+            let result = message_store.put_message(message(topic, &BODY)).await;
+            assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+        }
+        message_size
+    }
+
+    fn reput_from_offset(message_store: &DefaultMessageStore) -> i64 {
+        message_store
+            .reput_message_service
+            .reput_from_offset
+            .as_ref()
+            .unwrap()
+            .load(Ordering::Acquire)
+    }
+
+    /// Compresses the sealed first commit log file once it is dispatched.
+    async fn compress_first_file(message_store: &ArcMut<DefaultMessageStore>) {
+        let max_phy_offset = message_store.get_max_phy_offset();
+        assert!(wait_until(|| reput_from_offset(message_store) == max_phy_offset).await);
+        assert_eq!(
+            message_store
+                .commit_log
+                .compress_sealed_files(max_phy_offset, &|_| true),
+            1
+        );
+        assert_eq!(message_store.commit_log.get_min_offset(), 0);
+    }
+
+    fn compressing_store_config(store_dir: &tempfile::TempDir) -> MessageStoreConfig {
+        MessageStoreConfig {
+            store_path_root_dir: store_dir.path().to_string_lossy().to_string().into(),
+            commit_log_compression_after_hours: 0,
+            commit_log_compression_block_size: 64 * 1024,
+            ..MessageStoreConfig::default()
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn query_message_reads_the_compressed_commit_log() {
+        let store_dir = tempfile::tempdir().unwrap();
+        let mut message_store = start_store(compressing_store_config(&store_dir)).await;
+        let message_size = fill_first_file(&mut message_store, "CompressedTopic", "FirstKey").await;
+        compress_first_file(&message_store).await;
+
+        let result = message_store
+            .query_message(
+                &CheetahString::from_static_str("CompressedTopic"),
+                &CheetahString::from_static_str("FirstKey"),
+                32,
+                0,
+                i64::MAX,
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.buffer_total_size as i64, message_size);
+        let mut bytes = result.message_maped_list[0].get_bytes().unwrap();
+        let msg = MessageDecoder::decode(&mut bytes, true, true, false, false, false).unwrap();
+        assert_eq!(msg.get_keys().unwrap(), "FirstKey");
+        assert_eq!(msg.get_body().unwrap().as_ref(), BODY.as_slice());
+        message_store.shutdown();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn reput_lagging_behind_goes_on_in_the_compressed_commit_log() {
+        let store_dir = tempfile::tempdir().unwrap();
+        let mut message_store = start_store(compressing_store_config(&store_dir)).await;
+        let message_size = fill_first_file(&mut message_store, "CompressedTopic", "FirstKey").await;
+        compress_first_file(&message_store).await;
+
+        message_store
+            .reput_message_service
+            .reput_from_offset
+            .as_ref()
+            .unwrap()
+            .store(message_size, Ordering::SeqCst);
+        let max_phy_offset = message_store.get_max_phy_offset();
+        assert!(
+            wait_until(|| reput_from_offset(&message_store) == max_phy_offset).await,
+            "the reput stopped at {}",
+            reput_from_offset(&message_store)
+        );
+        message_store.shutdown();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn slave_catches_up_from_the_compressed_commit_log_of_its_master() {
+        let master_dir = tempfile::tempdir().unwrap();
+        let slave_dir = tempfile::tempdir().unwrap();
+        let ha_listen_port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port() as usize;
+        let mut master = start_store(MessageStoreConfig {
+            ha_listen_port,
+            ..compressing_store_config(&master_dir)
+        })
+        .await;
+        fill_first_file(&mut master, "CompressedTopic", "FirstKey").await;
+        compress_first_file(&master).await;
+
+        // the slave holds the first messages of the compressed file
+        let mut slave = start_store(MessageStoreConfig {
+            ha_listen_port: 0,
+            ..compressing_store_config(&slave_dir)
+        })
+        .await;
+        for _ in 0..3 {
+            let result = slave.put_message(message("CompressedTopic", &BODY)).await;
+            assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+        }
+        slave.shutdown();
+        let mut slave = start_store(MessageStoreConfig {
+            broker_role: BrokerRole::Slave,
+            ha_master_address: Some(format!("127.0.0.1:{}", ha_listen_port)),
+            ..compressing_store_config(&slave_dir)
+        })
+        .await;
+
+        let max_phy_offset = master.get_max_phy_offset();
+        assert!(
+            wait_until(|| slave.get_max_phy_offset() == max_phy_offset).await,
+            "the slave replicated up to {}",
+            slave.get_max_phy_offset()
+        );
+        slave.shutdown();
+        master.shutdown();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn commit_log_files_are_created_ahead_of_the_puts() {
+        let store_dir = tempfile::tempdir().unwrap();
+        let message_store_config = MessageStoreConfig {
+            store_path_root_dir: store_dir.path().to_string_lossy().to_string().into(),
+            warm_mapped_file_enable: true,
+            ..MessageStoreConfig::default()
+        };
+        let commit_log_dir = PathBuf::from(message_store_config.get_store_path_commit_log());
+        let mut message_store = start_store(message_store_config).await;
+        fill_first_file(&mut message_store, "WarmTopic", "FirstKey").await;
+
+        // the second file took the put, the third one is created in the background
+        let third_file = commit_log_dir.join(format!("{:020}", 2 * 1024 * 1024));
+        assert!(wait_until(|| third_file.exists()).await);
+        assert_eq!(message_store.commit_log.get_max_offset() / (1024 * 1024), 1);
+        message_store.shutdown();
+        assert!(commit_log_dir.join(format!("{:020}", 1024 * 1024)).exists());
+        assert!(!third_file.exists());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn shutdown_commits_the_write_buffers_before_deleting_the_abort_file() {
+        let store_dir = tempfile::tempdir().unwrap();
+        let message_store_config = MessageStoreConfig {
+            store_path_root_dir: store_dir.path().to_string_lossy().to_string().into(),
+            flush_disk_type: FlushDiskType::AsyncFlush,
+            transient_store_pool_enable: true,
+            transient_store_pool_size: 2,
+            // the commit real time service leaves the message in the write buffer
+            commit_commit_log_least_pages: i32::MAX,
+            commit_commit_log_thorough_interval: u64::MAX / 2,
+            ..MessageStoreConfig::default()
+        };
+        let mut message_store = start_store(message_store_config.clone()).await;
+        let result = message_store
+            .put_message(message("TransientTopic", b"body"))
+            .await;
+        assert_eq!(result.put_message_status(), PutMessageStatus::PutOk);
+        assert!(message_store.commit_log.remain_how_many_data_to_commit() > 0);
+        message_store.shutdown();
+        assert_eq!(message_store.commit_log.remain_how_many_data_to_commit(), 0);
+        assert_eq!(message_store.commit_log.remain_how_many_data_to_flush(), 0);
+        let max_phy_offset = message_store.get_max_phy_offset();
+        assert!(max_phy_offset > 0);
+        assert!(!Path::new(&get_abort_file(
+            message_store_config.store_path_root_dir.as_str()
+        ))
+        .exists());
+
+        let mut message_store = start_store(message_store_config).await;
+        assert_eq!(message_store.get_max_phy_offset(), max_phy_offset);
+        let selected = message_store.select_one_message_by_offset(0).await;
+        assert_eq!(
+            selected.map(|selected| selected.size as i64),
+            Some(max_phy_offset)
+        );
+        message_store.shutdown();
+    }
+}
@@ -0,0 +1,166 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use cheetah_string::CheetahString;
+use rocketmq_common::common::broker::shared_broker_config::SharedBrokerConfig;
+use rocketmq_common::common::config::TopicConfig;
+use rocketmq_rust::ArcMut;
+use tracing::error;
+
+use crate::base::store_enum::StoreType;
+use crate::config::message_store_config::MessageStoreConfig;
+use crate::message_store::boxed_message_store::BoxedMessageStore;
+use crate::stats::broker_stats_manager::BrokerStatsManager;
+use crate::tiered::provider::build_provider;
+use crate::tiered::tiered_message_store::TieredMessageStore;
+
+/// What a store builder gets from the broker.
+pub struct MessageStoreContext {
+    pub message_store_config: Arc<MessageStoreConfig>,
+    pub broker_config: SharedBrokerConfig,
+    pub topic_config_table: Arc<parking_lot::Mutex<HashMap<CheetahString, TopicConfig>>>,
+    pub broker_stats_manager: Option<Arc<BrokerStatsManager>>,
+}
+
+pub type MessageStoreBuilder = Box<dyn Fn(&MessageStoreContext) -> BoxedMessageStore + Send + Sync>;
+
+/// Builds the message store named by `storeType` in the store config.
+///
+/// The local file store is registered by default, and for `RocksDB` too with the `rocksdb`
+/// feature, other stores such as a DLedger commit log store are plugged in with
+/// [`MessageStoreFactory::register`].
+pub struct MessageStoreFactory {
+    builders: HashMap<StoreType, MessageStoreBuilder>,
+}
+
+impl Default for MessageStoreFactory {
+    fn default() -> Self {
+        #[allow(unused_mut)]
+        let mut factory = Self {
+            builders: HashMap::new(),
+        };
+        #[cfg(feature = "local_file_store")]
+        factory.register(StoreType::LocalFile, Box::new(build_local_file_store));
+        // the default store keeps its consume queues in RocksDB for this store type
+        #[cfg(feature = "rocksdb")]
+        factory.register(StoreType::RocksDB, Box::new(build_local_file_store));
+        factory
+    }
+}
+
+impl MessageStoreFactory {
+    /// Registers `builder` for `store_type`, replacing the one registered before.
+    pub fn register(&mut self, store_type: StoreType, builder: MessageStoreBuilder) {
+        self.builders.insert(store_type, builder);
+    }
+
+    pub fn contains(&self, store_type: StoreType) -> bool {
+        self.builders.contains_key(&store_type)
+    }
+
+    /// Builds the store for the configured store type, `None` when no builder is registered
+    /// for it.
+    ///
+    /// With `tiered_storage_enable` the store is wrapped in a [`TieredMessageStore`].
+    pub fn build(&self, context: &MessageStoreContext) -> Option<BoxedMessageStore> {
+        let message_store = self
+            .builders
+            .get(&context.message_store_config.store_type)
+            .map(|builder| builder(context))?;
+        if !context.message_store_config.tiered_storage_enable {
+            return Some(message_store);
+        }
+        match build_provider(&context.message_store_config) {
+            Ok(provider) => Some(BoxedMessageStore::new(ArcMut::new(
+                TieredMessageStore::new(message_store, provider),
+            ))),
+            Err(e) => {
+                error!("tiered storage disabled, build its provider failed: {}", e);
+                Some(message_store)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "local_file_store")]
+fn build_local_file_store(context: &MessageStoreContext) -> BoxedMessageStore {
+    use crate::log_file::MessageStore;
+    use crate::message_store::default_message_store::DefaultMessageStore;
+    use crate::timer::timer_message_store::TimerMessageStore;
+
+    let mut message_store = ArcMut::new(DefaultMessageStore::new(
+        context.message_store_config.clone(),
+        context.broker_config.clone(),
+        context.topic_config_table.clone(),
+        context.broker_stats_manager.clone(),
+        false,
+    ));
+    let message_store_clone = message_store.clone();
+    message_store.set_message_store_arc(Some(message_store_clone));
+    if context.message_store_config.is_timer_wheel_enable() {
+        match TimerMessageStore::new(Some(message_store.clone())) {
+            Ok(timer_message_store) => {
+                message_store.set_timer_message_store(Arc::new(timer_message_store))
+            }
+            Err(e) => error!(
+                "open the timer message store failed, timer messages are not delivered: {}",
+                e
+            ),
+        }
+    }
+    BoxedMessageStore::new(message_store)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(store_type: StoreType, store_path_root_dir: &str) -> MessageStoreContext {
+        let message_store_config = MessageStoreConfig {
+            store_type,
+            store_path_root_dir: store_path_root_dir.into(),
+            ..Default::default()
+        };
+        MessageStoreContext {
+            message_store_config: Arc::new(message_store_config),
+            broker_config: SharedBrokerConfig::default(),
+            topic_config_table: Arc::new(parking_lot::Mutex::new(HashMap::new())),
+            broker_stats_manager: None,
+        }
+    }
+
+    #[test]
+    fn builds_registered_store_types_only() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path().to_str().unwrap();
+        let factory = MessageStoreFactory::default();
+        assert!(factory.contains(StoreType::LocalFile));
+        assert_eq!(
+            factory.contains(StoreType::RocksDB),
+            cfg!(feature = "rocksdb")
+        );
+        #[cfg(not(feature = "rocksdb"))]
+        assert!(factory.build(&context(StoreType::RocksDB, dir)).is_none());
+
+        let store = factory.build(&context(StoreType::LocalFile, dir)).unwrap();
+        assert!(store
+            .downcast_ref::<crate::message_store::default_message_store::DefaultMessageStore>()
+            .is_some());
+    }
+}
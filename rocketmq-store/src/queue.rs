@@ -35,6 +35,11 @@ pub mod build_consume_queue;
 mod consume_queue_ext;
 pub mod local_file_consume_queue_store;
 mod queue_offset_operator;
+pub(crate) mod queue_offset_snapshot;
+#[cfg(feature = "rocksdb")]
+pub mod rocksdb_consume_queue;
+#[cfg(feature = "rocksdb")]
+pub mod rocksdb_consume_queue_table;
 pub mod single_consume_queue;
 
 pub type ArcConsumeQueue = ArcMut<Box<dyn ConsumeQueueTrait>>;
@@ -53,6 +58,14 @@ pub trait FileQueueLifeCycle: Swappable {
     /// Recovers the queue state from persistent storage.
     fn recover(&mut self);
 
+    /// Recovers the queue state, trusting the units before `offset_in_queue` to be written.
+    ///
+    /// Queues which cannot resume there fall back to a full [`recover`](Self::recover).
+    fn recover_from(&mut self, offset_in_queue: i64) {
+        let _ = offset_in_queue;
+        self.recover();
+    }
+
     /// Performs a self-check to ensure the queue's integrity.
     fn check_self(&self);
 
@@ -677,6 +690,13 @@ pub trait ConsumeQueueTrait: Send + Sync + FileQueueLifeCycle {
     /// The maximum offset in the queue as a 64-bit integer.
     fn get_max_offset_in_queue(&self) -> i64;
 
+    /// Retrieves the offset in the queue up to which the units are flushed to disk.
+    ///
+    /// # Returns
+    /// The flushed offset, `None` if the queue does not keep its units in flushed files of one
+    /// unit per offset.
+    fn get_flushed_offset_in_queue(&self) -> Option<i64>;
+
     /// Retrieves the total number of messages in the queue.
     ///
     /// # Returns
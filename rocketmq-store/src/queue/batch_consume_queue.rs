@@ -18,33 +18,140 @@
 use std::collections::BTreeMap;
 use std::path::PathBuf;
 use std::sync::atomic::AtomicI64;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
+use bytes::Buf;
+use bytes::BufMut;
+use bytes::Bytes;
+use bytes::BytesMut;
 use cheetah_string::CheetahString;
 use rocketmq_common::common::attribute::cq_type::CQType;
 use rocketmq_common::common::boundary_type::BoundaryType;
+use rocketmq_common::common::broker::broker_role::BrokerRole;
+use rocketmq_common::common::message::message_decoder;
 use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+use rocketmq_common::common::message::MessageConst;
+use rocketmq_common::common::message::MessageTrait;
+use rocketmq_common::common::sys_flag::message_sys_flag::MessageSysFlag;
+use tracing::error;
 use tracing::info;
+use tracing::warn;
 
 use crate::base::dispatch_request::DispatchRequest;
+use crate::base::store_checkpoint::StoreCheckpoint;
 use crate::base::swappable::Swappable;
 use crate::config::message_store_config::MessageStoreConfig;
 use crate::consume_queue::mapped_file_queue::MappedFileQueue;
 use crate::filter::MessageFilter;
 use crate::log_file::mapped_file::default_mapped_file_impl::DefaultMappedFile;
+use crate::log_file::mapped_file::MappedFile;
 use crate::queue::queue_offset_operator::QueueOffsetOperator;
 use crate::queue::ConsumeQueueTrait;
 use crate::queue::CqUnit;
 use crate::queue::FileQueueLifeCycle;
+use crate::store::running_flags::RunningFlags;
+
+pub const CQ_STORE_UNIT_SIZE: i32 = 46;
+const MSG_STORE_TIME_OFFSET_INDEX: usize = 20;
+
+/// One unit of a [`BatchConsumeQueue`], covering the `batch_num` messages from
+/// `msg_base_offset` on that were appended to the commit log as one entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct BatchUnit {
+    pos: i64,
+    size: i32,
+    tags_code: i64,
+    store_time: i64,
+    msg_base_offset: i64,
+    batch_num: i16,
+    compacted_offset: i32,
+}
+
+impl BatchUnit {
+    fn encode(&self) -> Bytes {
+        let mut bytes = BytesMut::with_capacity(CQ_STORE_UNIT_SIZE as usize);
+        bytes.put_i64(self.pos);
+        bytes.put_i32(self.size);
+        bytes.put_i64(self.tags_code);
+        bytes.put_i64(self.store_time);
+        bytes.put_i64(self.msg_base_offset);
+        bytes.put_i16(self.batch_num);
+        bytes.put_i32(self.compacted_offset);
+        // reserved
+        bytes.put_i32(0);
+        bytes.freeze()
+    }
+
+    fn decode(mut bytes: Bytes) -> Self {
+        Self {
+            pos: bytes.get_i64(),
+            size: bytes.get_i32(),
+            tags_code: bytes.get_i64(),
+            store_time: bytes.get_i64(),
+            msg_base_offset: bytes.get_i64(),
+            batch_num: bytes.get_i16(),
+            compacted_offset: bytes.get_i32(),
+        }
+    }
 
-const CQ_STORE_UNIT_SIZE: i32 = 46;
-const MSG_TAG_OFFSET_INDEX: i32 = 12;
-const MSG_STORE_TIME_OFFSET_INDEX: i32 = 20;
-const MSG_BASE_OFFSET_INDEX: i32 = 28;
-const MSG_BATCH_SIZE_INDEX: i32 = 36;
-const MSG_COMPACT_OFFSET_INDEX: i32 = 38;
-const MSG_COMPACT_OFFSET_LENGTH: i32 = 4;
-const INVALID_POS: i32 = -1;
+    fn is_valid(&self) -> bool {
+        self.pos >= 0 && self.size > 0 && self.batch_num > 0
+    }
+
+    fn next_offset(&self) -> i64 {
+        self.msg_base_offset + self.batch_num as i64
+    }
+
+    fn to_cq_unit(self) -> CqUnit {
+        CqUnit {
+            queue_offset: self.msg_base_offset,
+            size: self.size,
+            pos: self.pos,
+            batch_num: self.batch_num,
+            tags_code: self.tags_code,
+            compacted_offset: self.compacted_offset,
+            ..CqUnit::default()
+        }
+    }
+}
+
+/// The number of units written to `mapped_file`.
+fn unit_count(mapped_file: &DefaultMappedFile) -> i32 {
+    mapped_file.get_read_position() / CQ_STORE_UNIT_SIZE
+}
+
+fn read_unit(mapped_file: &DefaultMappedFile, index: i32) -> Option<BatchUnit> {
+    mapped_file
+        .get_bytes(
+            (index * CQ_STORE_UNIT_SIZE) as usize,
+            CQ_STORE_UNIT_SIZE as usize,
+        )
+        .map(BatchUnit::decode)
+}
+
+fn read_store_time(mapped_file: &DefaultMappedFile, index: i32) -> i64 {
+    mapped_file
+        .get_bytes(
+            (index * CQ_STORE_UNIT_SIZE) as usize + MSG_STORE_TIME_OFFSET_INDEX,
+            8,
+        )
+        .map_or(-1, |mut bytes| bytes.get_i64())
+}
+
+/// The index of the first of the `count` units whose `key` is not below `value`, `count` when
+/// there is none. The key must grow with the index.
+fn first_unit_at_or_after(count: i32, value: i64, key: impl Fn(i32) -> Option<i64>) -> i32 {
+    let (mut low, mut high) = (0, count);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        match key(mid) {
+            Some(key) if key >= value => high = mid,
+            _ => low = mid + 1,
+        }
+    }
+    low
+}
 
 ///
 /// BatchConsumeQueue's store unit. Format:
@@ -55,29 +162,36 @@ const INVALID_POS: i32 = -1;
 /// ├─────────────────────────┼───────────┼────────────┼──────────┤
 /// │       msgBaseOffset     │ batchSize │compOffset  │ reserved │
 /// │         (8 Bytes)       │ (2 Bytes) │ (4 Bytes)  │(4 Bytes) │
-///Store Unit
+/// └─────────────────────────┴───────────┴────────────┴──────────┘
 /// BatchConsumeQueue's store unit. Size:
 /// CommitLog Physical Offset(8) + Body Size(4) + Tag HashCode(8) + Store time(8) +
 /// msgBaseOffset(8) + batchSize(2) + compactedOffset(4) + reserved(4)= 46 Bytes
+///
+/// A batch appended to the commit log as one entry takes a single unit, while every message of
+/// it keeps its own queue offset within `[msgBaseOffset, msgBaseOffset + batchSize)`. A lookup by
+/// offset therefore returns the unit of the batch that contains it.
+///
+/// The first base offset of every file is cached, so that a lookup finds its file without
+/// reading the others.
 pub struct BatchConsumeQueue {
     message_store_config: Arc<MessageStoreConfig>,
     mapped_file_queue: MappedFileQueue,
-    //message_store: Arc<RwLock<dyn MessageStore>>,
     topic: CheetahString,
     queue_id: i32,
-    byte_buffer_item: Vec<u8>,
-    store_path: CheetahString,
-    mapped_file_size: usize,
+    mapped_file_size: i32,
     max_msg_phy_offset_in_commit_log: Arc<AtomicI64>,
     min_logic_offset: Arc<AtomicI64>,
+    // the offset after the last message of the queue
     max_offset_in_queue: Arc<AtomicI64>,
+    // -1 as long as the queue holds no unit
     min_offset_in_queue: Arc<AtomicI64>,
-    commit_log_size: i32,
-    offset_cache: Arc<parking_lot::RwLock<BTreeMap<i64, DefaultMappedFile>>>,
-    time_cache: Arc<parking_lot::RwLock<BTreeMap<i64, DefaultMappedFile>>>,
+    offset_cache: Arc<parking_lot::RwLock<BTreeMap<i64, Arc<DefaultMappedFile>>>>,
+    running_flags: Arc<RunningFlags>,
+    store_checkpoint: Arc<StoreCheckpoint>,
 }
 
 impl BatchConsumeQueue {
+    #[allow(clippy::too_many_arguments)]
     #[inline]
     pub fn new(
         topic: CheetahString,
@@ -86,52 +200,173 @@ impl BatchConsumeQueue {
         mapped_file_size: usize,
         subfolder: Option<CheetahString>,
         message_store_config: Arc<MessageStoreConfig>,
+        running_flags: Arc<RunningFlags>,
+        store_checkpoint: Arc<StoreCheckpoint>,
     ) -> Self {
-        let commit_log_size = message_store_config.mapped_file_size_commit_log;
-
-        let mapped_file_queue = if let Some(subfolder) = subfolder {
-            let queue_dir = PathBuf::from(store_path.as_str())
-                .join(topic.as_str())
-                .join(queue_id.to_string())
-                .join(subfolder.as_str());
-            MappedFileQueue::new(
-                queue_dir.to_string_lossy().to_string(),
-                mapped_file_size as u64,
-                None,
-            )
-        } else {
-            let queue_dir = PathBuf::from(store_path.as_str())
-                .join(topic.as_str())
-                .join(queue_id.to_string());
-            MappedFileQueue::new(
-                queue_dir.to_string_lossy().to_string(),
-                mapped_file_size as u64,
-                None,
-            )
-        };
-
-        let byte_buffer_item = vec![0u8; CQ_STORE_UNIT_SIZE as usize];
+        let mut queue_dir = PathBuf::from(store_path.as_str())
+            .join(topic.as_str())
+            .join(queue_id.to_string());
+        if let Some(subfolder) = subfolder {
+            queue_dir = queue_dir.join(subfolder.as_str());
+        }
+        let mapped_file_queue = MappedFileQueue::new(
+            queue_dir.to_string_lossy().to_string(),
+            mapped_file_size as u64,
+            None,
+        );
 
         BatchConsumeQueue {
             message_store_config,
             mapped_file_queue,
             topic,
             queue_id,
-            byte_buffer_item,
-            store_path,
-            mapped_file_size,
+            mapped_file_size: mapped_file_size as i32,
             max_msg_phy_offset_in_commit_log: Arc::new(AtomicI64::new(-1)),
             min_logic_offset: Arc::new(AtomicI64::new(0)),
             max_offset_in_queue: Arc::new(AtomicI64::new(0)),
             min_offset_in_queue: Arc::new(AtomicI64::new(-1)),
-            commit_log_size: commit_log_size as i32,
             offset_cache: Arc::new(parking_lot::RwLock::new(BTreeMap::new())),
-            time_cache: Arc::new(parking_lot::RwLock::new(BTreeMap::new())),
+            running_flags,
+            store_checkpoint,
         }
     }
+
+    fn mapped_files(&self) -> Vec<Arc<DefaultMappedFile>> {
+        self.mapped_file_queue.get_mapped_files().read().clone()
+    }
+
+    /// Appends the unit of a batch of `batch_num` messages from `msg_base_offset` on, stored at
+    /// `offset` of the commit log.
+    pub fn put_batch_message_position_info(
+        &mut self,
+        offset: i64,
+        size: i32,
+        tags_code: i64,
+        store_time: i64,
+        msg_base_offset: i64,
+        batch_num: i16,
+    ) -> bool {
+        if offset + size as i64 <= self.get_max_physic_offset() {
+            warn!(
+                "Maybe try to build consume queue repeatedly maxPhysicOffset={} phyOffset={}, \
+                 size={}",
+                self.get_max_physic_offset(),
+                offset,
+                size
+            );
+            return true;
+        }
+        let unit = BatchUnit {
+            pos: offset,
+            size,
+            tags_code,
+            store_time,
+            msg_base_offset,
+            batch_num,
+            compacted_offset: 0,
+        };
+        let max_offset_in_queue = self.get_max_offset_in_queue();
+        if self.min_offset_in_queue.load(Ordering::Acquire) >= 0
+            && msg_base_offset != max_offset_in_queue
+        {
+            warn!(
+                "[BUG]batch consume queue order maybe wrong, msgBaseOffset: {} maxOffsetInQueue: \
+                 {} Topic: {} QID: {}",
+                msg_base_offset, max_offset_in_queue, self.topic, self.queue_id
+            );
+        }
+
+        let expect_logic_offset = self.mapped_file_queue.get_max_offset();
+        let Some(mapped_file) = self
+            .mapped_file_queue
+            .get_last_mapped_file_mut_start_offset(expect_logic_offset as u64, true)
+        else {
+            return false;
+        };
+        let first_unit_of_file = mapped_file.get_wrote_position() == 0;
+        if !mapped_file.append_message_bytes(&unit.encode()) {
+            return false;
+        }
+        if first_unit_of_file {
+            self.offset_cache
+                .write()
+                .insert(msg_base_offset, mapped_file);
+        }
+        self.max_msg_phy_offset_in_commit_log
+            .store(offset + size as i64, Ordering::SeqCst);
+        self.max_offset_in_queue
+            .store(unit.next_offset(), Ordering::Release);
+        if self.min_offset_in_queue.load(Ordering::Acquire) < 0 {
+            self.min_offset_in_queue
+                .store(msg_base_offset, Ordering::Release);
+            self.min_logic_offset
+                .store(expect_logic_offset, Ordering::SeqCst);
+        }
+        true
+    }
+
+    /// Rebuilds the offset cache and the min and max offsets from the units in the files.
+    fn reload_offsets(&self) {
+        let mapped_files = self.mapped_files();
+        let mut offset_cache = self.offset_cache.write();
+        offset_cache.clear();
+        for mapped_file in mapped_files.iter() {
+            if unit_count(mapped_file) == 0 {
+                continue;
+            }
+            if let Some(first_unit) = read_unit(mapped_file, 0) {
+                offset_cache.insert(first_unit.msg_base_offset, mapped_file.clone());
+            }
+        }
+        let last_unit = mapped_files.iter().rev().find_map(|mapped_file| {
+            let count = unit_count(mapped_file);
+            if count > 0 {
+                read_unit(mapped_file, count - 1)
+            } else {
+                None
+            }
+        });
+        match (offset_cache.first_key_value(), last_unit) {
+            (Some((min_offset, mapped_file)), Some(last_unit)) => {
+                self.min_offset_in_queue
+                    .store(*min_offset, Ordering::Release);
+                self.min_logic_offset
+                    .store(mapped_file.get_file_from_offset() as i64, Ordering::SeqCst);
+                self.max_offset_in_queue
+                    .store(last_unit.next_offset(), Ordering::Release);
+                self.max_msg_phy_offset_in_commit_log
+                    .store(last_unit.pos + last_unit.size as i64, Ordering::SeqCst);
+            }
+            _ => {
+                self.min_offset_in_queue.store(-1, Ordering::Release);
+                self.max_offset_in_queue.store(0, Ordering::Release);
+            }
+        }
+    }
+
+    /// The mapped file and index of the unit of the batch that contains `offset`.
+    fn find_unit(&self, offset: i64) -> Option<(Arc<DefaultMappedFile>, i32)> {
+        if offset < self.get_min_offset_in_queue() || offset >= self.get_max_offset_in_queue() {
+            return None;
+        }
+        let mapped_file = self
+            .offset_cache
+            .read()
+            .range(..=offset)
+            .next_back()
+            .map(|(_, mapped_file)| mapped_file.clone())?;
+        let index = first_unit_at_or_after(unit_count(&mapped_file), offset + 1, |index| {
+            read_unit(&mapped_file, index).map(|unit| unit.msg_base_offset)
+        }) - 1;
+        (index >= 0).then_some((mapped_file, index))
+    }
+
+    fn get_unit(&self, offset: i64) -> Option<BatchUnit> {
+        let (mapped_file, index) = self.find_unit(offset)?;
+        read_unit(&mapped_file, index)
+    }
 }
 
-#[allow(unused_variables)]
 impl FileQueueLifeCycle for BatchConsumeQueue {
     #[inline]
     fn load(&mut self) -> bool {
@@ -146,49 +381,150 @@ impl FileQueueLifeCycle for BatchConsumeQueue {
         result
     }
 
-    #[inline]
     fn recover(&mut self) {
-        todo!()
+        let mapped_files = self.mapped_files();
+        if mapped_files.is_empty() {
+            return;
+        }
+        let units_per_file = self.mapped_file_size / CQ_STORE_UNIT_SIZE;
+        let mut index = mapped_files.len().saturating_sub(3);
+        let process_offset = loop {
+            let mapped_file = &mapped_files[index];
+            let mut mapped_file_offset = 0;
+            for unit_index in 0..units_per_file {
+                match read_unit(mapped_file, unit_index) {
+                    Some(unit) if unit.is_valid() => {
+                        mapped_file_offset = (unit_index + 1) * CQ_STORE_UNIT_SIZE;
+                    }
+                    _ => break,
+                }
+            }
+            if mapped_file_offset == self.mapped_file_size && index + 1 < mapped_files.len() {
+                index += 1;
+                info!(
+                    "recover next batch consume queue file, {}",
+                    mapped_files[index].get_file_name()
+                );
+                continue;
+            }
+            info!(
+                "recover batch consume queue file over, {} {}",
+                mapped_file.get_file_name(),
+                mapped_file_offset
+            );
+            break mapped_file.get_file_from_offset() as i64 + mapped_file_offset as i64;
+        };
+        self.mapped_file_queue.set_flushed_where(process_offset);
+        self.mapped_file_queue.set_committed_where(process_offset);
+        self.mapped_file_queue.truncate_dirty_files(process_offset);
+        self.reload_offsets();
     }
 
     #[inline]
     fn check_self(&self) {
-        todo!()
+        self.mapped_file_queue.check_self();
     }
 
     #[inline]
     fn flush(&self, flush_least_pages: i32) -> bool {
-        todo!()
+        self.mapped_file_queue.flush(flush_least_pages)
     }
 
-    #[inline]
     fn destroy(&mut self) {
-        todo!()
+        self.max_msg_phy_offset_in_commit_log
+            .store(-1, Ordering::SeqCst);
+        self.min_logic_offset.store(0, Ordering::SeqCst);
+        self.min_offset_in_queue.store(-1, Ordering::Release);
+        self.max_offset_in_queue.store(0, Ordering::Release);
+        self.offset_cache.write().clear();
+        self.mapped_file_queue.destroy();
     }
 
-    #[inline]
+    /// Drops the units of the messages at or after `max_commit_log_pos`, from the last file
+    /// backwards.
     fn truncate_dirty_logic_files(&mut self, max_commit_log_pos: i64) {
-        todo!()
+        while let Some(mapped_file) = self.mapped_file_queue.get_last_mapped_file() {
+            let keep =
+                first_unit_at_or_after(unit_count(&mapped_file), max_commit_log_pos, |index| {
+                    read_unit(&mapped_file, index)
+                        .filter(BatchUnit::is_valid)
+                        .map_or(Some(i64::MAX), |unit| Some(unit.pos))
+                });
+            if keep == 0 {
+                self.mapped_file_queue.delete_last_mapped_file();
+                continue;
+            }
+            let position = keep * CQ_STORE_UNIT_SIZE;
+            mapped_file.set_wrote_position(position);
+            mapped_file.set_committed_position(position);
+            mapped_file.set_flushed_position(position);
+            break;
+        }
+        self.reload_offsets();
     }
 
-    #[inline]
+    /// Deletes the files, except the last one, whose units all point before
+    /// `min_commit_log_pos`, and returns how many were deleted.
     fn delete_expired_file(&self, min_commit_log_pos: i64) -> i32 {
-        todo!()
+        let mapped_files = self.mapped_files();
+        let mut deleted = Vec::new();
+        for mapped_file in mapped_files
+            .iter()
+            .take(mapped_files.len().saturating_sub(1))
+        {
+            let count = unit_count(mapped_file);
+            let expired = count == 0
+                || read_unit(mapped_file, count - 1)
+                    .is_some_and(|last_unit| last_unit.pos < min_commit_log_pos);
+            if !expired
+                || !mapped_file.destroy(
+                    self.message_store_config
+                        .destroy_mapped_file_interval_forcibly as u64,
+                )
+            {
+                break;
+            }
+            deleted.push(mapped_file.clone());
+        }
+        if !deleted.is_empty() {
+            self.mapped_file_queue
+                .get_mapped_files()
+                .write()
+                .retain(|mapped_file| !deleted.iter().any(|d| Arc::ptr_eq(d, mapped_file)));
+            self.offset_cache
+                .write()
+                .retain(|_, mapped_file| !deleted.iter().any(|d| Arc::ptr_eq(d, mapped_file)));
+            info!(
+                "delete {} expired files of batch consume queue {}-{}",
+                deleted.len(),
+                self.topic,
+                self.queue_id
+            );
+        }
+        self.correct_min_offset(min_commit_log_pos);
+        deleted.len() as i32
     }
 
-    #[inline]
+    /// The first offset of the file after the one holding `next_begin_offset`, the max offset
+    /// when it is in the last file.
     fn roll_next_file(&self, next_begin_offset: i64) -> i64 {
-        todo!()
+        self.offset_cache
+            .read()
+            .range(next_begin_offset + 1..)
+            .next()
+            .map_or(self.get_max_offset_in_queue(), |(offset, _)| *offset)
     }
 
     #[inline]
     fn is_first_file_available(&self) -> bool {
-        todo!()
+        self.mapped_file_queue
+            .get_first_mapped_file()
+            .is_some_and(|mapped_file| mapped_file.is_available())
     }
 
     #[inline]
     fn is_first_file_exist(&self) -> bool {
-        todo!()
+        self.mapped_file_queue.get_first_mapped_file().is_some()
     }
 }
 
@@ -196,122 +532,234 @@ impl Swappable for BatchConsumeQueue {
     #[inline]
     fn swap_map(
         &self,
-        reserve_num: i32,
-        force_swap_interval_ms: i64,
-        normal_swap_interval_ms: i64,
+        _reserve_num: i32,
+        _force_swap_interval_ms: i64,
+        _normal_swap_interval_ms: i64,
     ) {
-        todo!()
     }
 
     #[inline]
-    fn clean_swapped_map(&self, force_clean_swap_interval_ms: i64) {
-        todo!()
-    }
+    fn clean_swapped_map(&self, _force_clean_swap_interval_ms: i64) {}
 }
 
 impl ConsumeQueueTrait for BatchConsumeQueue {
     #[inline]
     fn get_topic(&self) -> &CheetahString {
-        todo!()
+        &self.topic
     }
 
     #[inline]
     fn get_queue_id(&self) -> i32 {
-        todo!()
+        self.queue_id
     }
 
     #[inline]
     fn get(&self, index: i64) -> Option<CqUnit> {
-        todo!()
+        self.get_unit(index).map(BatchUnit::to_cq_unit)
     }
 
     #[inline]
     fn get_cq_unit_and_store_time(&self, index: i64) -> Option<(CqUnit, i64)> {
-        todo!()
+        self.get_unit(index)
+            .map(|unit| (unit.to_cq_unit(), unit.store_time))
     }
 
     #[inline]
     fn get_earliest_unit_and_store_time(&self) -> Option<(CqUnit, i64)> {
-        todo!()
+        self.get_cq_unit_and_store_time(self.get_min_offset_in_queue())
     }
 
     #[inline]
     fn get_earliest_unit(&self) -> CqUnit {
-        todo!()
+        self.get(self.get_min_offset_in_queue()).unwrap_or_default()
     }
 
     #[inline]
     fn get_latest_unit(&self) -> CqUnit {
-        todo!()
+        self.get(self.get_max_offset_in_queue() - 1)
+            .unwrap_or_default()
     }
 
     #[inline]
     fn get_last_offset(&self) -> i64 {
-        todo!()
+        self.get_unit(self.get_max_offset_in_queue() - 1)
+            .map_or(-1, |unit| unit.pos)
     }
 
     #[inline]
     fn get_min_offset_in_queue(&self) -> i64 {
-        todo!()
+        self.min_offset_in_queue.load(Ordering::Acquire).max(0)
     }
 
     #[inline]
     fn get_max_offset_in_queue(&self) -> i64 {
-        todo!()
+        self.max_offset_in_queue.load(Ordering::Acquire)
+    }
+
+    #[inline]
+    fn get_flushed_offset_in_queue(&self) -> Option<i64> {
+        None
     }
 
     #[inline]
     fn get_message_total_in_queue(&self) -> i64 {
-        todo!()
+        (self.get_max_offset_in_queue() - self.get_min_offset_in_queue()).max(0)
     }
 
     #[inline]
     fn get_offset_in_queue_by_time(&self, timestamp: i64) -> i64 {
-        todo!()
+        self.get_offset_in_queue_by_time_boundary(timestamp, BoundaryType::Lower)
     }
 
-    #[inline]
+    /// The base offset of the first batch stored at or after `timestamp` for the lower boundary,
+    /// and the last offset of the last batch stored at or before it for the upper one.
     fn get_offset_in_queue_by_time_boundary(
         &self,
         timestamp: i64,
         boundary_type: BoundaryType,
     ) -> i64 {
-        todo!()
+        let min_offset = self.get_min_offset_in_queue();
+        let max_offset = self.get_max_offset_in_queue();
+        let mapped_files = self.mapped_files();
+        match boundary_type {
+            BoundaryType::Lower => {
+                for mapped_file in mapped_files.iter() {
+                    let count = unit_count(mapped_file);
+                    let index = first_unit_at_or_after(count, timestamp, |index| {
+                        Some(read_store_time(mapped_file, index))
+                    });
+                    if index < count {
+                        return read_unit(mapped_file, index)
+                            .map_or(max_offset, |unit| unit.msg_base_offset.max(min_offset));
+                    }
+                }
+                max_offset
+            }
+            BoundaryType::Upper => {
+                for mapped_file in mapped_files.iter().rev() {
+                    let count = unit_count(mapped_file);
+                    let index = first_unit_at_or_after(count, timestamp + 1, |index| {
+                        Some(read_store_time(mapped_file, index))
+                    }) - 1;
+                    if index >= 0 {
+                        return read_unit(mapped_file, index)
+                            .map_or(min_offset, |unit| (unit.next_offset() - 1).max(min_offset));
+                    }
+                }
+                min_offset
+            }
+        }
     }
 
     #[inline]
     fn get_max_physic_offset(&self) -> i64 {
-        todo!()
+        self.max_msg_phy_offset_in_commit_log.load(Ordering::SeqCst)
     }
 
     #[inline]
     fn get_min_logic_offset(&self) -> i64 {
-        todo!()
+        self.min_logic_offset.load(Ordering::Relaxed)
     }
 
     #[inline]
     fn get_cq_type(&self) -> CQType {
-        todo!()
+        CQType::BatchCQ
     }
 
     #[inline]
     fn get_total_size(&self) -> i64 {
-        todo!()
+        self.mapped_files()
+            .iter()
+            .map(|mapped_file| mapped_file.get_file_size() as i64)
+            .sum()
     }
 
     #[inline]
     fn get_unit_size(&self) -> i32 {
-        todo!()
+        CQ_STORE_UNIT_SIZE
     }
 
-    #[inline]
+    /// Moves the min offset to the first batch stored at or after `min_commit_log_offset`, or
+    /// to the max offset when there is none.
     fn correct_min_offset(&self, min_commit_log_offset: i64) {
-        todo!()
+        for mapped_file in self.mapped_files().iter() {
+            let count = unit_count(mapped_file);
+            let index = first_unit_at_or_after(count, min_commit_log_offset, |index| {
+                read_unit(mapped_file, index).map(|unit| unit.pos)
+            });
+            if index == count {
+                continue;
+            }
+            let Some(unit) = read_unit(mapped_file, index) else {
+                return;
+            };
+            if unit.msg_base_offset > self.get_min_offset_in_queue() {
+                self.min_offset_in_queue
+                    .store(unit.msg_base_offset, Ordering::Release);
+                self.min_logic_offset.store(
+                    mapped_file.get_file_from_offset() as i64 + (index * CQ_STORE_UNIT_SIZE) as i64,
+                    Ordering::SeqCst,
+                );
+                info!(
+                    "BatchConsumeQueue[topic={}, queue-id={}], min-offset is corrected to {}",
+                    self.topic, self.queue_id, unit.msg_base_offset
+                );
+            }
+            return;
+        }
+        if self.min_offset_in_queue.load(Ordering::Acquire) >= 0 {
+            self.min_offset_in_queue
+                .store(self.get_max_offset_in_queue(), Ordering::Release);
+            info!(
+                "BatchConsumeQueue[topic={}, queue-id={}] contains no valid entries. Min-offset \
+                 is assigned as: {}.",
+                self.topic,
+                self.queue_id,
+                self.get_min_offset_in_queue()
+            );
+        }
     }
 
-    #[inline]
     fn put_message_position_info_wrapper(&mut self, request: &DispatchRequest) {
-        todo!()
+        // a message that is no inner batch takes a unit of its own
+        let msg_base_offset = if request.msg_base_offset >= 0 {
+            request.msg_base_offset
+        } else {
+            request.consume_queue_offset
+        };
+        let max_retries = 30i32;
+        let can_write = self.running_flags.is_cq_writeable();
+        let mut i = 0i32;
+        while i < max_retries && can_write {
+            if self.put_batch_message_position_info(
+                request.commit_log_offset,
+                request.msg_size,
+                request.tags_code,
+                request.store_timestamp,
+                msg_base_offset,
+                request.batch_size.max(1),
+            ) {
+                if self.message_store_config.broker_role == BrokerRole::Slave
+                    || self.message_store_config.enable_dledger_commit_log
+                {
+                    self.store_checkpoint
+                        .set_physic_msg_timestamp(request.store_timestamp as u64);
+                }
+                self.store_checkpoint
+                    .set_logics_msg_timestamp(request.store_timestamp as u64);
+                return;
+            }
+            warn!(
+                "[BUG]put commit log position info to {}:{} failed, retry {} times",
+                self.topic, self.queue_id, i
+            );
+            i += 1;
+        }
+        error!(
+            "[BUG]batch consume queue can not write, {} {}",
+            self.topic, self.queue_id
+        );
+        self.running_flags.make_logics_queue_error();
     }
 
     #[inline]
@@ -321,34 +769,201 @@ impl ConsumeQueueTrait for BatchConsumeQueue {
         msg: &MessageExtBrokerInner,
         message_num: i16,
     ) {
-        todo!()
+        queue_offset_assigner.increase_batch_queue_offset(
+            &CheetahString::from_string(format!("{}-{}", msg.topic(), msg.queue_id())),
+            message_num,
+        );
     }
 
-    #[inline]
+    /// Assigns the batch queue offset, an inner batch also carries it as `INNER_BASE` so that
+    /// the unit can be rebuilt from the commit log.
     fn assign_queue_offset(
         &self,
         queue_offset_operator: &QueueOffsetOperator,
         msg: &mut MessageExtBrokerInner,
     ) {
-        todo!()
+        let queue_offset = queue_offset_operator.get_batch_queue_offset(
+            &CheetahString::from_string(format!("{}-{}", msg.topic(), msg.queue_id())),
+        );
+        if MessageSysFlag::check(msg.sys_flag(), MessageSysFlag::INNER_BATCH_FLAG) {
+            msg.put_property(
+                CheetahString::from_static_str(MessageConst::PROPERTY_INNER_BASE),
+                CheetahString::from_string(queue_offset.to_string()),
+            );
+            msg.properties_string =
+                message_decoder::message_properties_to_string(msg.get_properties());
+        }
+        msg.message_ext_inner.queue_offset = queue_offset;
     }
 
-    #[inline]
+    /// Counts the messages in `[from, to)` of the batches matched by `filter`.
     fn estimate_message_count(&self, from: i64, to: i64, filter: &dyn MessageFilter) -> i64 {
-        todo!()
+        let from = from.max(self.get_min_offset_in_queue());
+        let to = to.min(self.get_max_offset_in_queue());
+        let mut count = 0;
+        let mut next = from;
+        while next < to {
+            let Some(units) = self.iterate_from(next) else {
+                break;
+            };
+            let before = next;
+            for unit in units {
+                if unit.queue_offset >= to {
+                    return count;
+                }
+                let end = (unit.queue_offset + unit.batch_num as i64).min(to);
+                if filter.is_matched_by_consume_queue(Some(unit.tags_code), None) {
+                    count += end - unit.queue_offset.max(from);
+                }
+                next = end;
+            }
+            if next == before {
+                break;
+            }
+        }
+        count
     }
 
-    #[inline]
+    /// Iterates the units of the file holding `start_index` from the batch that contains it on.
     fn iterate_from(&self, start_index: i64) -> Option<Box<dyn Iterator<Item = CqUnit>>> {
-        todo!()
+        let (mapped_file, index) = self.find_unit(start_index)?;
+        let end = unit_count(&mapped_file);
+        Some(Box::new(BatchConsumeQueueIterator {
+            mapped_file,
+            index,
+            end,
+        }))
     }
 
     #[inline]
     fn iterate_from_inner(
         &self,
         start_index: i64,
-        count: i32,
+        _count: i32,
     ) -> Option<Box<dyn Iterator<Item = CqUnit>>> {
-        todo!()
+        self.iterate_from(start_index)
+    }
+}
+
+struct BatchConsumeQueueIterator {
+    mapped_file: Arc<DefaultMappedFile>,
+    index: i32,
+    end: i32,
+}
+
+impl Iterator for BatchConsumeQueueIterator {
+    type Item = CqUnit;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.end {
+            return None;
+        }
+        let unit = read_unit(&self.mapped_file, self.index)?;
+        self.index += 1;
+        Some(unit.to_cq_unit())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(commit_log_offset: i64, msg_base_offset: i64, batch_size: i16) -> DispatchRequest {
+        DispatchRequest {
+            topic: CheetahString::from_static_str("topic"),
+            queue_id: 0,
+            commit_log_offset,
+            msg_size: 100,
+            store_timestamp: 1000 + msg_base_offset,
+            consume_queue_offset: msg_base_offset,
+            msg_base_offset,
+            batch_size,
+            ..DispatchRequest::default()
+        }
+    }
+
+    #[test]
+    fn batches_take_one_unit_and_expose_every_offset() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store_checkpoint =
+            Arc::new(StoreCheckpoint::new(temp_dir.path().join("checkpoint")).unwrap());
+        let new_queue = || {
+            // four units per file
+            BatchConsumeQueue::new(
+                CheetahString::from_static_str("topic"),
+                0,
+                CheetahString::from(temp_dir.path().to_str().unwrap()),
+                (CQ_STORE_UNIT_SIZE * 4) as usize,
+                None,
+                Arc::new(MessageStoreConfig::default()),
+                Arc::new(RunningFlags::new()),
+                store_checkpoint.clone(),
+            )
+        };
+        let mut queue = new_queue();
+        // ten batches of ten messages each
+        for batch in 0..10 {
+            queue.put_message_position_info_wrapper(&request(batch * 100, batch * 10, 10));
+        }
+        // dispatched again after a restart
+        queue.put_message_position_info_wrapper(&request(900, 90, 10));
+        assert_eq!(queue.get_min_offset_in_queue(), 0);
+        assert_eq!(queue.get_max_offset_in_queue(), 100);
+        assert_eq!(queue.get_max_physic_offset(), 1000);
+        assert_eq!(queue.mapped_file_queue.get_mapped_files_size(), 3);
+
+        let unit = queue.get(57).unwrap();
+        assert_eq!((unit.queue_offset, unit.batch_num, unit.pos), (50, 10, 500));
+        let units: Vec<_> = queue.iterate_from_inner(21, 32).unwrap().collect();
+        assert_eq!(
+            units
+                .iter()
+                .map(|unit| unit.queue_offset)
+                .collect::<Vec<_>>(),
+            vec![20, 30]
+        );
+        assert!(queue.get(100).is_none());
+        assert_eq!(queue.roll_next_file(35), 40);
+        assert_eq!(queue.get_offset_in_queue_by_time(1035), 40);
+        assert_eq!(
+            queue.get_offset_in_queue_by_time_boundary(1035, BoundaryType::Upper),
+            39
+        );
+
+        struct MatchAll;
+        impl MessageFilter for MatchAll {
+            fn is_matched_by_consume_queue(
+                &self,
+                _tags_code: Option<i64>,
+                _cq_ext_unit: Option<&crate::consume_queue::consume_queue_ext::CqExtUnit>,
+            ) -> bool {
+                true
+            }
+
+            fn is_matched_by_commit_log(
+                &self,
+                _msg_buffer: Option<&[u8]>,
+                _properties: Option<&std::collections::HashMap<CheetahString, CheetahString>>,
+            ) -> bool {
+                true
+            }
+        }
+        assert_eq!(queue.estimate_message_count(15, 75, &MatchAll), 60);
+
+        queue.truncate_dirty_logic_files(750);
+        assert_eq!(queue.get_max_offset_in_queue(), 80);
+        assert_eq!(queue.get_max_physic_offset(), 800);
+
+        assert_eq!(queue.delete_expired_file(450), 1);
+        assert_eq!(queue.get_min_offset_in_queue(), 50);
+        assert!(queue.get(45).is_none());
+        queue.flush(0);
+
+        let mut reloaded = new_queue();
+        assert!(reloaded.load());
+        reloaded.recover();
+        assert_eq!(reloaded.get_min_offset_in_queue(), 40);
+        assert_eq!(reloaded.get_max_offset_in_queue(), 80);
+        assert_eq!(reloaded.get(63).unwrap().pos, 600);
     }
 }
@@ -17,10 +17,15 @@
 use std::path::PathBuf;
 
 use cheetah_string::CheetahString;
+use tracing::error;
 use tracing::info;
+use tracing::warn;
 
 use crate::consume_queue::consume_queue_ext::CqExtUnit;
+use crate::consume_queue::consume_queue_ext::MAX_EXT_UNIT_SIZE;
+use crate::consume_queue::consume_queue_ext::MIN_EXT_UNIT_SIZE;
 use crate::consume_queue::mapped_file_queue::MappedFileQueue;
+use crate::log_file::mapped_file::MappedFile;
 
 const END_BLANK_DATA_LENGTH: usize = 4;
 
@@ -68,9 +73,65 @@ impl ConsumeQueueExt {
 }
 
 impl ConsumeQueueExt {
-    pub fn truncate_by_max_address(&self, max_address: i64) {}
+    /// Offset of the unit in the extend files, `address` is returned by [`Self::put`].
+    pub fn un_decorate(address: i64) -> i64 {
+        if Self::is_ext_addr(address) {
+            address.wrapping_sub(i64::MIN)
+        } else {
+            address
+        }
+    }
+
+    /// Address stored as tags code in the consume queue for the unit at `offset`.
+    pub fn decorate(offset: i64) -> i64 {
+        if Self::is_ext_addr(offset) {
+            offset
+        } else {
+            offset.wrapping_add(i64::MIN)
+        }
+    }
 
-    pub fn truncate_by_min_address(&self, min_address: i64) {}
+    pub fn truncate_by_max_address(&mut self, max_address: i64) {
+        if !Self::is_ext_addr(max_address) {
+            return;
+        }
+        info!(
+            "Truncate consume queue ext by max address {}, {}-{}",
+            max_address, self.topic, self.queue_id
+        );
+        let Some(cq_ext_unit) = self.get(max_address) else {
+            error!(
+                "Max address is illegal in consume queue extend! {}",
+                max_address
+            );
+            return;
+        };
+        let real_offset = Self::un_decorate(max_address);
+        self.mapped_file_queue
+            .truncate_dirty_files(real_offset + cq_ext_unit.size() as i64);
+    }
+
+    pub fn truncate_by_min_address(&self, min_address: i64) {
+        if !Self::is_ext_addr(min_address) {
+            return;
+        }
+        let real_offset = Self::un_decorate(min_address);
+        let mapped_files = self.mapped_file_queue.get_mapped_files();
+        mapped_files.write().retain(|mapped_file| {
+            let file_tail_offset =
+                mapped_file.get_file_from_offset() as i64 + self.mapped_file_size as i64;
+            if file_tail_offset < real_offset {
+                info!(
+                    "Delete consume queue extend file {} by min address {}",
+                    mapped_file.get_file_name(),
+                    min_address
+                );
+                !mapped_file.destroy(1000)
+            } else {
+                true
+            }
+        });
+    }
 
     pub fn load(&mut self) -> bool {
         let result = self.mapped_file_queue.load();
@@ -84,17 +145,158 @@ impl ConsumeQueueExt {
         result
     }
 
-    pub fn recover(&mut self) {}
+    /// Finds the end of the units written, the consume queue truncates the files afterwards.
+    pub fn recover(&mut self) {
+        let mapped_files = self.mapped_file_queue.get_mapped_files();
+        let Some(mut process_offset) = mapped_files
+            .read()
+            .first()
+            .map(|mapped_file| mapped_file.get_file_from_offset() as i64)
+        else {
+            return;
+        };
+        for mapped_file in mapped_files.read().iter() {
+            // the written part of a file ends with the first unit without size
+            let buffer = mapped_file.slice_byte_buffer();
+            let mut mapped_file_offset = 0usize;
+            while let Some(cq_ext_unit) =
+                buffer.get(mapped_file_offset..).and_then(CqExtUnit::decode)
+            {
+                mapped_file_offset += cq_ext_unit.size() as usize;
+            }
+            process_offset = mapped_file.get_file_from_offset() as i64 + mapped_file_offset as i64;
+        }
+        info!(
+            "All files of consume queue extend has been recovered over, {}-{} {}",
+            self.topic, self.queue_id, process_offset
+        );
+        self.mapped_file_queue.set_flushed_where(process_offset);
+        self.mapped_file_queue.set_committed_where(process_offset);
+        self.mapped_file_queue.truncate_dirty_files(process_offset);
+    }
+
+    /// Appends the unit, returns its address or `1` when it can not be saved.
+    pub fn put(&mut self, cq_ext_unit: CqExtUnit) -> i64 {
+        const RETRY_TIMES: usize = 3;
+        let size =
+            MIN_EXT_UNIT_SIZE as usize + cq_ext_unit.filter_bit_map().as_ref().map_or(0, Vec::len);
+        if size > MAX_EXT_UNIT_SIZE as usize {
+            error!(
+                "Size of cq ext unit is greater than {}, {}",
+                MAX_EXT_UNIT_SIZE, size
+            );
+            return 1;
+        }
+        if self.mapped_file_queue.get_max_offset() + size as i64 > MAX_REAL_OFFSET {
+            warn!(
+                "Capacity of ext is maximum!{}, {}",
+                self.mapped_file_queue.get_max_offset(),
+                size
+            );
+            return 1;
+        }
+        let data = cq_ext_unit.encode();
+        for _ in 0..RETRY_TIMES {
+            let Some(mapped_file) = self
+                .mapped_file_queue
+                .get_last_mapped_file_mut_start_offset(0, true)
+            else {
+                error!(
+                    "Create mapped file when save consume queue extend failed, {}-{}",
+                    self.topic, self.queue_id
+                );
+                continue;
+            };
+            let wrote_position = mapped_file.get_wrote_position();
+            let blank_size = self.mapped_file_size - wrote_position - END_BLANK_DATA_LENGTH as i32;
+            if size as i32 > blank_size {
+                // the rest of the file is too small, mark its end and go on with the next one
+                mapped_file.put_slice(&(-1i16).to_be_bytes(), wrote_position as usize);
+                mapped_file.set_wrote_position(self.mapped_file_size);
+                info!(
+                    "No enough space(need:{}, has:{}) of file {}, so fill to end",
+                    size,
+                    blank_size,
+                    mapped_file.get_file_name()
+                );
+                continue;
+            }
+            if mapped_file.append_message_bytes(&data) {
+                return Self::decorate(
+                    wrote_position as i64 + mapped_file.get_file_from_offset() as i64,
+                );
+            }
+        }
+        1
+    }
 
-    pub fn put(&self, cq_ext_unit: CqExtUnit) -> i64 {
-        unimplemented!()
+    pub fn flush(&self, flush_least_pages: i32) -> bool {
+        self.mapped_file_queue.flush(flush_least_pages)
     }
 
     pub fn destroy(&mut self) {
         self.mapped_file_queue.destroy();
     }
 
-    pub fn get(&self, address: i64, cq_ext_unit: &CqExtUnit) -> bool {
-        unimplemented!()
+    pub fn check_self(&self) {
+        self.mapped_file_queue.check_self();
+    }
+
+    pub fn get_total_size(&self) -> i64 {
+        self.mapped_file_queue.get_total_file_size()
+    }
+
+    /// Reads the unit saved at `address`.
+    pub fn get(&self, address: i64) -> Option<CqExtUnit> {
+        if !Self::is_ext_addr(address) {
+            return None;
+        }
+        let real_offset = Self::un_decorate(address);
+        let mapped_file = self
+            .mapped_file_queue
+            .find_mapped_file_by_offset(real_offset, real_offset == 0)?;
+        let pos = (real_offset % self.mapped_file_size as i64) as usize;
+        CqExtUnit::decode(&mapped_file.get_mapped_byte_buffer()[pos..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decorate_address() {
+        assert!(!ConsumeQueueExt::is_ext_addr(0));
+        let address = ConsumeQueueExt::decorate(100);
+        assert!(ConsumeQueueExt::is_ext_addr(address));
+        assert_eq!(ConsumeQueueExt::decorate(address), address);
+        assert_eq!(ConsumeQueueExt::un_decorate(address), 100);
+        assert_eq!(ConsumeQueueExt::un_decorate(100), 100);
+    }
+
+    #[test]
+    fn put_and_get() {
+        let dir = std::env::temp_dir().join(format!("cq_ext_{}", std::process::id()));
+        let mut ext = ConsumeQueueExt::new(
+            "topic".into(),
+            0,
+            dir.to_string_lossy().to_string().into(),
+            64,
+            64,
+        );
+        let first = ext.put(CqExtUnit::new(1, 10, Some(vec![0b101; 16])));
+        let second = ext.put(CqExtUnit::new(2, 20, Some(vec![0b110; 16])));
+        let third = ext.put(CqExtUnit::new(3, 30, None));
+        assert!(ConsumeQueueExt::is_ext_addr(first));
+        assert!(ConsumeQueueExt::is_ext_addr(third));
+        // the second unit does not fit into the rest of the first file
+        assert_eq!(ConsumeQueueExt::un_decorate(second), 64);
+
+        let unit = ext.get(second).unwrap();
+        assert_eq!(unit.tags_code(), 2);
+        assert_eq!(unit.filter_bit_map().as_deref(), Some(&[0b110u8; 16][..]));
+        assert_eq!(ext.get(third).unwrap().msg_store_time(), 30);
+        assert!(ext.get(1).is_none());
+        ext.destroy();
     }
 }
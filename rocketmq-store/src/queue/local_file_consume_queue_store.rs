@@ -21,30 +21,49 @@ use std::fs;
 use std::path::Path;
 use std::sync::Arc;
 
+use bytes::BufMut;
 use bytes::Bytes;
+use bytes::BytesMut;
 use cheetah_string::CheetahString;
 use rocketmq_common::common::attribute::cq_type::CQType;
-use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::common::broker::shared_broker_config::SharedBrokerConfig;
 use rocketmq_common::common::config::TopicConfig;
 use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+use rocketmq_common::common::message::MessageConst;
+use rocketmq_common::common::mix_all::is_lmq;
+use rocketmq_common::common::mix_all::MULTI_DISPATCH_QUEUE_SPLITTER;
+use rocketmq_common::common::mix_all::RETRY_GROUP_TOPIC_PREFIX;
 use rocketmq_common::utils::queue_type_utils::QueueTypeUtils;
 use rocketmq_rust::ArcMut;
+use tracing::error;
 use tracing::info;
+use tracing::warn;
 
 use crate::base::dispatch_request::DispatchRequest;
 use crate::base::store_checkpoint::StoreCheckpoint;
 use crate::config::message_store_config::MessageStoreConfig;
 use crate::queue::batch_consume_queue::BatchConsumeQueue;
 use crate::queue::queue_offset_operator::QueueOffsetOperator;
+use crate::queue::queue_offset_snapshot::QueueOffsetSnapshot;
+#[cfg(feature = "rocksdb")]
+use crate::queue::rocksdb_consume_queue::migrate_from_file_consume_queue;
+#[cfg(feature = "rocksdb")]
+use crate::queue::rocksdb_consume_queue::RocksDBConsumeQueue;
+#[cfg(feature = "rocksdb")]
+use crate::queue::rocksdb_consume_queue_table::RocksDBConsumeQueueTable;
 use crate::queue::single_consume_queue::ConsumeQueue;
+use crate::queue::single_consume_queue::CQ_STORE_UNIT_SIZE;
 use crate::queue::ArcConsumeQueue;
 use crate::queue::ConsumeQueueStoreTrait;
 use crate::queue::ConsumeQueueTable;
 use crate::queue::ConsumeQueueTrait;
 use crate::queue::CqUnit;
 use crate::store::running_flags::RunningFlags;
+use crate::store_path_config_helper::get_queue_offset_snapshot_path;
 use crate::store_path_config_helper::get_store_path_batch_consume_queue;
 use crate::store_path_config_helper::get_store_path_consume_queue;
+#[cfg(feature = "rocksdb")]
+use crate::store_path_config_helper::get_store_path_rocksdb_consume_queue;
 
 #[derive(Clone)]
 pub struct ConsumeQueueStore {
@@ -57,9 +76,12 @@ pub struct ConsumeQueueStore {
 struct Inner {
     // commit_log: Arc<Mutex<CommitLog>>,
     pub(crate) message_store_config: Arc<MessageStoreConfig>,
-    pub(crate) broker_config: Arc<BrokerConfig>,
+    pub(crate) broker_config: SharedBrokerConfig,
     pub(crate) queue_offset_operator: QueueOffsetOperator,
     pub(crate) consume_queue_table: Arc<ConsumeQueueTable>,
+    // all queues are kept in it when storeType is RocksDB
+    #[cfg(feature = "rocksdb")]
+    pub(crate) rocksdb_table: Option<RocksDBConsumeQueueTable>,
 }
 
 impl Inner {
@@ -76,11 +98,30 @@ impl ConsumeQueueStore {
     #[inline]
     pub fn new(
         message_store_config: Arc<MessageStoreConfig>,
-        broker_config: Arc<BrokerConfig>,
+        broker_config: SharedBrokerConfig,
         topic_config_table: Arc<parking_lot::Mutex<HashMap<CheetahString, TopicConfig>>>,
         running_flags: Arc<RunningFlags>,
         store_checkpoint: Arc<StoreCheckpoint>,
     ) -> Self {
+        #[cfg(feature = "rocksdb")]
+        let rocksdb_table = message_store_config.is_enable_rocksdb_store().then(|| {
+            let store_path = get_store_path_rocksdb_consume_queue(
+                message_store_config.store_path_root_dir.as_str(),
+            );
+            RocksDBConsumeQueueTable::open(&store_path).unwrap_or_else(|e| {
+                panic!(
+                    "open RocksDB consume queue store {} failed, {}",
+                    store_path, e
+                )
+            })
+        });
+        #[cfg(not(feature = "rocksdb"))]
+        if message_store_config.is_enable_rocksdb_store() {
+            warn!(
+                "storeType RocksDB requires the rocksdb feature, the consume queues are kept in \
+                 files"
+            );
+        }
         Self {
             inner: Arc::new(Inner {
                 //commit_log,
@@ -88,6 +129,8 @@ impl ConsumeQueueStore {
                 broker_config,
                 queue_offset_operator: QueueOffsetOperator::new(),
                 consume_queue_table: Arc::new(parking_lot::Mutex::new(HashMap::new())),
+                #[cfg(feature = "rocksdb")]
+                rocksdb_table,
             }),
             running_flags,
             store_checkpoint,
@@ -96,6 +139,113 @@ impl ConsumeQueueStore {
     }
 }
 
+impl ConsumeQueueStore {
+    /// The number of light message queues that have been assigned an offset.
+    pub fn get_lmq_queue_num(&self) -> usize {
+        self.inner.queue_offset_operator.get_lmq_queue_num()
+    }
+
+    fn check_multi_dispatch_queue(&self, request: &DispatchRequest) -> bool {
+        if !self.inner.message_store_config.enable_multi_dispatch
+            || request.topic.starts_with(RETRY_GROUP_TOPIC_PREFIX)
+        {
+            return false;
+        }
+        let Some(properties) = request.properties_map.as_ref() else {
+            return false;
+        };
+        properties
+            .get(MessageConst::PROPERTY_INNER_MULTI_DISPATCH)
+            .is_some_and(|queues| !queues.is_empty())
+            && properties
+                .get(MessageConst::PROPERTY_INNER_MULTI_QUEUE_OFFSET)
+                .is_some_and(|offsets| !offsets.is_empty())
+    }
+
+    /// Puts the position of a multi dispatch message into every queue of
+    /// `INNER_MULTI_DISPATCH` at the offset assigned to it in `INNER_MULTI_QUEUE_OFFSET`.
+    fn multi_dispatch_lmq_queue(&self, request: &DispatchRequest) {
+        let properties = request.properties_map.as_ref().unwrap();
+        let queues = properties[MessageConst::PROPERTY_INNER_MULTI_DISPATCH]
+            .split(MULTI_DISPATCH_QUEUE_SPLITTER)
+            .collect::<Vec<_>>();
+        let queue_offsets = properties[MessageConst::PROPERTY_INNER_MULTI_QUEUE_OFFSET]
+            .split(MULTI_DISPATCH_QUEUE_SPLITTER)
+            .collect::<Vec<_>>();
+        if queues.len() != queue_offsets.len() {
+            error!(
+                "[bug] queues.length!=queueOffsets.length, topic:{}, queues:{:?}, offsets:{:?}",
+                request.topic, queues, queue_offsets
+            );
+            return;
+        }
+        for (queue, queue_offset) in queues.into_iter().zip(queue_offsets) {
+            let Ok(queue_offset) = queue_offset.parse::<i64>() else {
+                error!(
+                    "[bug] illegal multi queue offset {} of queue {}, topic:{}",
+                    queue_offset, queue, request.topic
+                );
+                continue;
+            };
+            let queue_name = CheetahString::from_slice(queue);
+            let queue_id = if self.inner.message_store_config.enable_lmq && is_lmq(Some(queue)) {
+                0
+            } else {
+                request.queue_id
+            };
+            let lmq_request = DispatchRequest {
+                topic: queue_name.clone(),
+                queue_id,
+                consume_queue_offset: queue_offset,
+                properties_map: None,
+                ..request.clone()
+            };
+            let mut cq = self.find_or_create_consume_queue(&queue_name, queue_id);
+            self.put_message_position_info_wrapper_with_cq(&mut **cq.as_mut(), &lmq_request);
+        }
+    }
+
+    /// Flushes every consume queue and writes the offsets they are flushed up to to the queue
+    /// offset snapshot, so the next recovery can skip the units before them. Nothing is written
+    /// when no queue was flushed further since the previous snapshot.
+    pub(crate) fn persist_queue_offset_snapshot(&self, previous: &mut QueueOffsetSnapshot) {
+        let flush_least_pages = self
+            .inner
+            .message_store_config
+            .flush_consume_queue_least_pages as i32;
+        let consume_queues = self
+            .inner
+            .consume_queue_table
+            .lock()
+            .values()
+            .flat_map(|consume_queue_table| consume_queue_table.values().cloned())
+            .filter(|consume_queue| consume_queue.get_cq_type() == CQType::SimpleCQ)
+            .collect::<Vec<_>>();
+        let mut snapshot = QueueOffsetSnapshot::default();
+        for consume_queue in consume_queues {
+            consume_queue.flush(flush_least_pages);
+            if let Some(flushed_offset) = consume_queue.get_flushed_offset_in_queue() {
+                snapshot.insert(
+                    consume_queue.get_topic().as_str(),
+                    consume_queue.get_queue_id(),
+                    flushed_offset,
+                );
+            }
+        }
+        if snapshot == *previous {
+            return;
+        }
+        match snapshot.persist(&self.queue_offset_snapshot_path()) {
+            Ok(()) => *previous = snapshot,
+            Err(e) => warn!("persist queue offset snapshot failed, {}", e),
+        }
+    }
+
+    fn queue_offset_snapshot_path(&self) -> String {
+        get_queue_offset_snapshot_path(self.inner.message_store_config.store_path_root_dir.as_str())
+    }
+}
+
 #[allow(unused_variables)]
 impl ConsumeQueueStoreTrait for ConsumeQueueStore {
     #[inline]
@@ -105,6 +255,10 @@ impl ConsumeQueueStoreTrait for ConsumeQueueStore {
 
     #[inline]
     fn load(&mut self) -> bool {
+        #[cfg(feature = "rocksdb")]
+        if let Some(table) = self.inner.rocksdb_table.clone() {
+            return self.load_rocksdb_consume_queues(&table);
+        }
         self.load_consume_queues(
             CheetahString::from_string(get_store_path_consume_queue(
                 self.inner.message_store_config.store_path_root_dir.as_str(),
@@ -125,13 +279,21 @@ impl ConsumeQueueStoreTrait for ConsumeQueueStore {
 
     #[inline]
     fn recover(&mut self) {
+        let snapshot = if self.inner.message_store_config.enable_queue_offset_snapshot {
+            QueueOffsetSnapshot::load(&self.queue_offset_snapshot_path())
+        } else {
+            QueueOffsetSnapshot::default()
+        };
         let mut mutex = self.inner.consume_queue_table.lock().clone();
         for (_topic, consume_queue_table) in mutex.iter_mut() {
             for (_queue_id, consume_queue) in consume_queue_table.iter() {
                 let queue_id = consume_queue.get_queue_id();
                 let topic = consume_queue.get_topic();
                 let mut file_queue_life_cycle = self.get_life_cycle(topic, queue_id);
-                file_queue_life_cycle.recover();
+                match snapshot.flushed_offset(topic.as_str(), queue_id) {
+                    Some(flushed_offset) => file_queue_life_cycle.recover_from(flushed_offset),
+                    None => file_queue_life_cycle.recover(),
+                }
             }
         }
     }
@@ -168,7 +330,7 @@ impl ConsumeQueueStoreTrait for ConsumeQueueStore {
 
     #[inline]
     fn flush(&self, consume_queue: &dyn ConsumeQueueTrait, flush_least_pages: i32) -> bool {
-        todo!()
+        consume_queue.flush(flush_least_pages)
     }
 
     #[inline]
@@ -187,22 +349,22 @@ impl ConsumeQueueStoreTrait for ConsumeQueueStore {
         consume_queue: &dyn ConsumeQueueTrait,
         min_commit_log_pos: i64,
     ) -> i32 {
-        todo!()
+        consume_queue.delete_expired_file(min_commit_log_pos)
     }
 
     #[inline]
     fn is_first_file_available(&self, consume_queue: &dyn ConsumeQueueTrait) -> bool {
-        todo!()
+        consume_queue.is_first_file_available()
     }
 
     #[inline]
     fn is_first_file_exist(&self, consume_queue: &dyn ConsumeQueueTrait) -> bool {
-        todo!()
+        consume_queue.is_first_file_exist()
     }
 
     #[inline]
     fn roll_next_file(&self, consume_queue: &dyn ConsumeQueueTrait, offset: i64) -> i64 {
-        todo!()
+        consume_queue.roll_next_file(offset)
     }
 
     #[inline]
@@ -221,7 +383,9 @@ impl ConsumeQueueStoreTrait for ConsumeQueueStore {
     fn put_message_position_info_wrapper(&self, request: &DispatchRequest) {
         let mut cq = self.find_or_create_consume_queue(request.topic.as_ref(), request.queue_id);
         self.put_message_position_info_wrapper_with_cq(&mut **cq.as_mut(), request);
-        // println!("put_message_position_info_wrapper-----{}", request.topic)
+        if self.check_multi_dispatch_queue(request) {
+            self.multi_dispatch_lmq_queue(request);
+        }
     }
 
     #[inline]
@@ -242,7 +406,18 @@ impl ConsumeQueueStoreTrait for ConsumeQueueStore {
         start_index: i64,
         num: i32,
     ) -> Option<Vec<Bytes>> {
-        todo!()
+        let consume_queue = self.find_consume_queue(topic, queue_id)?;
+        let units = consume_queue
+            .iterate_from_inner(start_index, num)?
+            .map(|cq_unit| {
+                let mut unit = BytesMut::with_capacity(CQ_STORE_UNIT_SIZE as usize);
+                unit.put_i64(cq_unit.pos);
+                unit.put_i32(cq_unit.size);
+                unit.put_i64(cq_unit.tags_code);
+                unit.freeze()
+            })
+            .collect();
+        Some(units)
     }
 
     #[inline]
@@ -264,12 +439,14 @@ impl ConsumeQueueStoreTrait for ConsumeQueueStore {
 
     #[inline]
     fn increase_lmq_offset(&mut self, queue_key: &CheetahString, message_num: i16) {
-        todo!()
+        self.inner
+            .queue_offset_operator
+            .increase_lmq_offset(queue_key, message_num);
     }
 
     #[inline]
     fn get_lmq_queue_offset(&self, queue_key: &CheetahString) -> i64 {
-        todo!()
+        self.inner.queue_offset_operator.get_lmq_offset(queue_key)
     }
 
     #[inline]
@@ -284,7 +461,7 @@ impl ConsumeQueueStoreTrait for ConsumeQueueStore {
                     consume_queue.get_queue_id()
                 ));
                 let max_offset_in_queue = consume_queue.get_max_offset_in_queue();
-                if consume_queue.get_cq_type() == CQType::SimpleCQ {
+                if consume_queue.get_cq_type() != CQType::BatchCQ {
                     cq_offset_table.insert(key, max_offset_in_queue);
                 } else {
                     bcq_offset_table.insert(key, max_offset_in_queue);
@@ -292,11 +469,6 @@ impl ConsumeQueueStoreTrait for ConsumeQueueStore {
                 self.correct_min_offset(&***consume_queue, min_phy_offset)
             }
         }
-        if self.inner.message_store_config.duplication_enable
-            || self.inner.broker_config.enable_controller_mode
-        {
-            unimplemented!()
-        }
         self.set_topic_queue_table(cq_offset_table);
         self.set_batch_topic_queue_table(bcq_offset_table);
     }
@@ -318,7 +490,7 @@ impl ConsumeQueueStoreTrait for ConsumeQueueStore {
 
     #[inline]
     fn get_topic_queue_table(&self) -> HashMap<CheetahString, i64> {
-        todo!()
+        self.inner.queue_offset_operator.get_topic_queue_table()
     }
 
     #[inline]
@@ -364,37 +536,24 @@ impl ConsumeQueueStoreTrait for ConsumeQueueStore {
         }
 
         let consume_queue = topic_map.entry(queue_id).or_insert_with(|| {
-            let option = self.topic_config_table.lock().get(topic).cloned();
-            match QueueTypeUtils::get_cq_type(&option) {
-                CQType::SimpleCQ => ArcMut::new(Box::new(ConsumeQueue::new(
-                    topic.clone(),
-                    queue_id,
-                    CheetahString::from_string(get_store_path_consume_queue(
-                        self.inner.message_store_config.store_path_root_dir.as_str(),
-                    )),
-                    self.inner
-                        .message_store_config
-                        .get_mapped_file_size_consume_queue(),
-                    self.inner.message_store_config.clone(),
-                    self.running_flags.clone(),
-                    self.store_checkpoint.clone(),
-                ))),
-                CQType::BatchCQ => ArcMut::new(Box::new(BatchConsumeQueue::new(
-                    topic.clone(),
-                    queue_id,
-                    CheetahString::from_string(get_store_path_batch_consume_queue(
-                        self.inner.message_store_config.store_path_root_dir.as_str(),
-                    )),
-                    self.inner
-                        .message_store_config
-                        .mapper_file_size_batch_consume_queue,
-                    None,
-                    self.inner.message_store_config.clone(),
-                ))),
-                CQType::RocksDBCQ => {
-                    unimplemented!()
-                }
+            if let Some(consume_queue) = self.create_rocksdb_consume_queue(topic, queue_id) {
+                return ArcMut::new(consume_queue);
             }
+            let cq_type = self.get_cq_type(topic);
+            let store_path = match cq_type {
+                CQType::BatchCQ => get_store_path_batch_consume_queue(
+                    self.inner.message_store_config.store_path_root_dir.as_str(),
+                ),
+                _ => get_store_path_consume_queue(
+                    self.inner.message_store_config.store_path_root_dir.as_str(),
+                ),
+            };
+            ArcMut::new(self.create_consume_queue_by_type(
+                topic,
+                queue_id,
+                cq_type,
+                CheetahString::from_string(store_path),
+            ))
         });
         consume_queue.clone()
     }
@@ -404,7 +563,7 @@ impl ConsumeQueueStoreTrait for ConsumeQueueStore {
         &self,
         topic: &CheetahString,
     ) -> Option<HashMap<i32, ArcConsumeQueue>> {
-        todo!()
+        self.inner.consume_queue_table.lock().get(topic).cloned()
     }
 
     #[inline]
@@ -498,6 +657,107 @@ impl ConsumeQueueStore {
         true
     }
 
+    /// Loads the queues recorded in RocksDB instead of scanning the consume queue dirs.
+    #[cfg(feature = "rocksdb")]
+    fn load_rocksdb_consume_queues(&mut self, table: &RocksDBConsumeQueueTable) -> bool {
+        let queues = match table.queues() {
+            Ok(queues) => queues,
+            Err(e) => {
+                error!("load the queues from RocksDB failed, {}", e);
+                return false;
+            }
+        };
+        for (topic, queue_id) in queues {
+            let topic = CheetahString::from_string(topic);
+            let logic = self.create_consume_queue_by_type(
+                &topic,
+                queue_id,
+                CQType::RocksDBCQ,
+                CheetahString::empty(),
+            );
+            self.put_consume_queue(topic.clone(), queue_id, logic);
+            if !self.load_logic(&topic, queue_id) {
+                return false;
+            }
+        }
+        info!("load {:?} all over, OK", CQType::RocksDBCQ);
+        true
+    }
+
+    /// Copies the file-based consume queues into RocksDB on the first start after storeType is
+    /// switched to RocksDB, returns how many units were copied. Nothing is copied once RocksDB
+    /// holds any queue, and the files are left for a switch back.
+    ///
+    /// `store_timestamp` picks up the store time of the message at a commit log offset with a
+    /// size, the commit log has to be loaded before.
+    #[cfg(feature = "rocksdb")]
+    pub fn migrate_file_consume_queues(
+        &mut self,
+        store_timestamp: &dyn Fn(i64, i32) -> i64,
+    ) -> usize {
+        let Some(table) = self.inner.rocksdb_table.clone() else {
+            return 0;
+        };
+        if !self.inner.consume_queue_table.lock().is_empty() {
+            return 0;
+        }
+        let store_path = CheetahString::from_string(get_store_path_consume_queue(
+            self.inner.message_store_config.store_path_root_dir.as_str(),
+        ));
+        let Ok(topic_dirs) = fs::read_dir(store_path.as_str()) else {
+            return 0;
+        };
+        let mut migrated = 0;
+        for topic_dir in topic_dirs.filter_map(Result::ok) {
+            let topic =
+                CheetahString::from_string(topic_dir.file_name().to_string_lossy().into_owned());
+            let Ok(queue_dirs) = fs::read_dir(topic_dir.path()) else {
+                continue;
+            };
+            for queue_dir in queue_dirs.filter_map(Result::ok) {
+                let Ok(queue_id) = queue_dir.file_name().to_string_lossy().parse::<i32>() else {
+                    continue;
+                };
+                let mut file_queue = ConsumeQueue::new(
+                    topic.clone(),
+                    queue_id,
+                    store_path.clone(),
+                    self.inner
+                        .message_store_config
+                        .get_mapped_file_size_consume_queue(),
+                    self.inner.message_store_config.clone(),
+                    self.running_flags.clone(),
+                    self.store_checkpoint.clone(),
+                );
+                if !file_queue.load() {
+                    warn!(
+                        "skip migrating consume queue {}-{}, load failed",
+                        topic, queue_id
+                    );
+                    continue;
+                }
+                file_queue.recover();
+                match migrate_from_file_consume_queue(&file_queue, &table, store_timestamp) {
+                    Ok(units) => {
+                        info!(
+                            "migrated {} units of consume queue {}-{} to RocksDB",
+                            units, topic, queue_id
+                        );
+                        migrated += units;
+                    }
+                    Err(e) => error!(
+                        "migrate consume queue {}-{} to RocksDB failed, {}",
+                        topic, queue_id, e
+                    ),
+                }
+            }
+        }
+        if migrated > 0 && !self.load_rocksdb_consume_queues(&table) {
+            error!("load the migrated consume queues from RocksDB failed");
+        }
+        migrated
+    }
+
     #[inline]
     fn load_logic(&mut self, topic: &CheetahString, queue_id: i32) -> bool {
         let mut file_queue_life_cycle = self.get_life_cycle(topic, queue_id);
@@ -518,8 +778,7 @@ impl ConsumeQueueStore {
 
     #[inline]
     fn queue_type_should_be(&self, topic: &str, cq_type: CQType) {
-        let option = self.topic_config_table.lock().get(topic).cloned();
-        let act = QueueTypeUtils::get_cq_type(&option);
+        let act = self.get_cq_type(topic);
         if act != cq_type {
             panic!(
                 "The queue type of topic: {} should be {:?}, but is {:?}",
@@ -550,6 +809,23 @@ impl ConsumeQueueStore {
 }
 
 impl ConsumeQueueStore {
+    /// The queue type of the topic config. Without the RocksDB store a RocksDB queue type is
+    /// kept in the simple consume queue files.
+    fn get_cq_type(&self, topic: &str) -> CQType {
+        let option = self.topic_config_table.lock().get(topic).cloned();
+        match QueueTypeUtils::get_cq_type(&option) {
+            CQType::RocksDBCQ => {
+                warn!(
+                    "the queue type of topic {} is RocksDBCQ but storeType is not RocksDB, use \
+                     SimpleCQ",
+                    topic
+                );
+                CQType::SimpleCQ
+            }
+            cq_type => cq_type,
+        }
+    }
+
     #[inline]
     fn create_consume_queue_by_type(
         &self,
@@ -583,17 +859,192 @@ impl ConsumeQueueStore {
                         .mapper_file_size_batch_consume_queue,
                     None,
                     self.inner.message_store_config.clone(),
+                    self.running_flags.clone(),
+                    self.store_checkpoint.clone(),
                 );
                 Box::new(consume_queue)
             }
-            _ => {
-                unimplemented!()
-            }
+            CQType::RocksDBCQ => self
+                .create_rocksdb_consume_queue(topic, queue_id)
+                .unwrap_or_else(|| {
+                    panic!(
+                        "the RocksDB consume queue of topic {} requires storeType RocksDB",
+                        topic
+                    )
+                }),
         }
     }
 
+    /// A RocksDB consume queue when storeType is RocksDB, `None` otherwise.
+    #[cfg(feature = "rocksdb")]
+    fn create_rocksdb_consume_queue(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+    ) -> Option<Box<dyn ConsumeQueueTrait>> {
+        let table = self.inner.rocksdb_table.clone()?;
+        Some(Box::new(RocksDBConsumeQueue::new(
+            topic.clone(),
+            queue_id,
+            table,
+            self.inner.message_store_config.clone(),
+            self.running_flags.clone(),
+            self.store_checkpoint.clone(),
+        )))
+    }
+
+    #[cfg(not(feature = "rocksdb"))]
+    fn create_rocksdb_consume_queue(
+        &self,
+        _topic: &CheetahString,
+        _queue_id: i32,
+    ) -> Option<Box<dyn ConsumeQueueTrait>> {
+        None
+    }
+
+    fn find_consume_queue(&self, topic: &CheetahString, queue_id: i32) -> Option<ArcConsumeQueue> {
+        self.inner
+            .consume_queue_table
+            .lock()
+            .get(topic)?
+            .get(&queue_id)
+            .cloned()
+    }
+
     #[inline]
     fn get_life_cycle(&self, topic: &CheetahString, queue_id: i32) -> ArcConsumeQueue {
         self.find_or_create_consume_queue(topic, queue_id)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn consume_queue_store(
+        root_dir: &Path,
+        configure: impl FnOnce(&mut MessageStoreConfig),
+    ) -> ConsumeQueueStore {
+        let mut message_store_config = MessageStoreConfig {
+            store_path_root_dir: CheetahString::from(root_dir.to_str().unwrap()),
+            mapped_file_size_consume_queue: CQ_STORE_UNIT_SIZE as usize * 4,
+            ..MessageStoreConfig::default()
+        };
+        configure(&mut message_store_config);
+        ConsumeQueueStore::new(
+            Arc::new(message_store_config),
+            SharedBrokerConfig::default(),
+            Arc::new(parking_lot::Mutex::new(HashMap::new())),
+            Arc::new(RunningFlags::new()),
+            Arc::new(StoreCheckpoint::new(root_dir.join("checkpoint")).unwrap()),
+        )
+    }
+
+    fn dispatch(store: &ConsumeQueueStore, topic: &str, count: i64) {
+        for cq_offset in 0..count {
+            store.put_message_position_info_wrapper(&DispatchRequest {
+                topic: CheetahString::from(topic),
+                queue_id: 0,
+                commit_log_offset: cq_offset * 100,
+                msg_size: 100,
+                consume_queue_offset: cq_offset,
+                ..DispatchRequest::default()
+            });
+        }
+    }
+
+    #[test]
+    fn snapshot_holds_the_flushed_offsets() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = get_queue_offset_snapshot_path(temp_dir.path().to_str().unwrap());
+
+        // the files are smaller than a page, with a least page only the full first file of four
+        // units is flushed
+        let store = consume_queue_store(temp_dir.path(), |config| {
+            config.flush_consume_queue_least_pages = 1;
+        });
+        dispatch(&store, "topic", 6);
+        let mut previous = QueueOffsetSnapshot::default();
+        store.persist_queue_offset_snapshot(&mut previous);
+        assert_eq!(
+            QueueOffsetSnapshot::load(&path).flushed_offset("topic", 0),
+            Some(4)
+        );
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = get_queue_offset_snapshot_path(temp_dir.path().to_str().unwrap());
+        let store = consume_queue_store(temp_dir.path(), |_| {});
+        dispatch(&store, "topic", 6);
+        // a round flushes up to the end of one file, as the commit log flush does
+        store.persist_queue_offset_snapshot(&mut previous);
+        assert_eq!(previous.flushed_offset("topic", 0), Some(4));
+        store.persist_queue_offset_snapshot(&mut previous);
+        assert_eq!(previous.flushed_offset("topic", 0), Some(6));
+        assert_eq!(QueueOffsetSnapshot::load(&path), previous);
+        assert!(!Path::new(&format!("{}.tmp", path)).exists());
+    }
+
+    #[test]
+    fn recovers_from_the_snapshot() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = consume_queue_store(temp_dir.path(), |_| {});
+        dispatch(&store, "topic", 6);
+        let mut snapshot = QueueOffsetSnapshot::default();
+        store.persist_queue_offset_snapshot(&mut snapshot);
+        store.persist_queue_offset_snapshot(&mut snapshot);
+        assert_eq!(snapshot.flushed_offset("topic", 0), Some(6));
+        // written after the snapshot, found by the scan from the snapshot offset on
+        store.put_message_position_info_wrapper(&DispatchRequest {
+            topic: CheetahString::from("topic"),
+            queue_id: 0,
+            commit_log_offset: 600,
+            msg_size: 100,
+            consume_queue_offset: 6,
+            ..DispatchRequest::default()
+        });
+        let topic = CheetahString::from("topic");
+        store.flush(&**store.find_or_create_consume_queue(&topic, 0), 0);
+
+        let mut recovered = consume_queue_store(temp_dir.path(), |_| {});
+        assert!(recovered.load());
+        recovered.recover();
+        let consume_queue = recovered.find_or_create_consume_queue(&topic, 0);
+        assert_eq!(consume_queue.get_max_offset_in_queue(), 7);
+        assert_eq!(consume_queue.get_max_physic_offset(), 700);
+    }
+
+    #[test]
+    fn rocksdb_queue_type_without_rocksdb_store_uses_simple_consume_queue() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let topic = CheetahString::from("topic");
+        let topic_config = TopicConfig {
+            attributes: HashMap::from([(
+                CheetahString::from("queue.type"),
+                CheetahString::from("RocksDBCQ"),
+            )]),
+            ..TopicConfig::new(topic.clone())
+        };
+        let store = consume_queue_store(temp_dir.path(), |_| {});
+        store
+            .topic_config_table
+            .lock()
+            .insert(topic.clone(), topic_config.clone());
+        dispatch(&store, "topic", 3);
+        let consume_queue = store.find_or_create_consume_queue(&topic, 0);
+        assert_eq!(consume_queue.get_cq_type(), CQType::SimpleCQ);
+        assert_eq!(consume_queue.get_max_offset_in_queue(), 3);
+        store.flush(&**consume_queue, 0);
+
+        // the queue files are loaded again as simple consume queues after a restart
+        let mut reloaded = consume_queue_store(temp_dir.path(), |_| {});
+        reloaded
+            .topic_config_table
+            .lock()
+            .insert(topic.clone(), topic_config);
+        assert!(reloaded.load());
+        reloaded.recover();
+        let consume_queue = reloaded.find_or_create_consume_queue(&topic, 0);
+        assert_eq!(consume_queue.get_cq_type(), CQType::SimpleCQ);
+        assert_eq!(consume_queue.get_max_offset_in_queue(), 3);
+    }
+}
@@ -19,6 +19,7 @@ use std::collections::HashMap;
 use std::sync::Arc;
 
 use cheetah_string::CheetahString;
+use rocketmq_common::common::mix_all::is_lmq;
 use tracing::info;
 
 pub struct QueueOffsetOperator {
@@ -110,6 +111,11 @@ impl QueueOffsetOperator {
         *entry += message_num as i64;
     }
 
+    #[inline]
+    pub fn get_lmq_queue_num(&self) -> usize {
+        self.lmq_topic_queue_table.lock().len()
+    }
+
     #[inline]
     pub fn current_queue_offset(&self, topic_queue_key: &CheetahString) -> i64 {
         let topic_queue_table = self.topic_queue_table.lock();
@@ -135,6 +141,11 @@ impl QueueOffsetOperator {
         );
     }
 
+    #[inline]
+    pub fn get_topic_queue_table(&self) -> HashMap<CheetahString, i64> {
+        self.topic_queue_table.lock().clone()
+    }
+
     #[inline]
     pub fn set_topic_queue_table(&self, topic_queue_table: HashMap<CheetahString, i64>) {
         *self.topic_queue_table.lock() = topic_queue_table;
@@ -144,7 +155,7 @@ impl QueueOffsetOperator {
     pub fn set_lmq_topic_queue_table(&self, lmq_topic_queue_table: HashMap<CheetahString, i64>) {
         let mut table = HashMap::new();
         for (key, value) in lmq_topic_queue_table.iter() {
-            if key.contains("lmq") {
+            if is_lmq(Some(key.as_str())) {
                 table.insert(key.clone(), *value);
             }
         }
@@ -234,4 +245,17 @@ mod tests {
 
         assert_eq!(operator.get_queue_offset("new_key".into()), 10);
     }
+
+    #[test]
+    fn get_topic_queue_table_returns_the_increased_offsets() {
+        let operator = QueueOffsetOperator::new();
+        operator.increase_queue_offset("topic-0".into(), 3);
+        operator.increase_queue_offset("topic-1".into(), 1);
+
+        let table = operator.get_topic_queue_table();
+
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.get("topic-0"), Some(&3));
+        assert_eq!(table.get("topic-1"), Some(&1));
+    }
 }
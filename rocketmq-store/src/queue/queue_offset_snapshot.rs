@@ -0,0 +1,110 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::fs;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use rocketmq_common::FileUtils::file_to_string;
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::warn;
+
+/// Flushed offsets of the consume queues, persisted by the store from time to time.
+///
+/// Every unit a consume queue held before its snapshot offset was on disk when the snapshot was
+/// taken, so recovery only has to scan the queue from there on instead of from its last files.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct QueueOffsetSnapshot {
+    /// `topic-queueId` to the flushed offset in the queue.
+    offset_table: HashMap<String, i64>,
+}
+
+impl QueueOffsetSnapshot {
+    /// Reads the snapshot at `path`, an empty one when it is missing or unreadable.
+    pub(crate) fn load(path: &str) -> Self {
+        let content = match file_to_string(path) {
+            Ok(content) => content,
+            Err(e) => {
+                warn!("read queue offset snapshot {} failed, {}", path, e);
+                return Self::default();
+            }
+        };
+        if content.is_empty() {
+            return Self::default();
+        }
+        serde_json::from_str(&content).unwrap_or_else(|e| {
+            warn!("decode queue offset snapshot {} failed, {}", path, e);
+            Self::default()
+        })
+    }
+
+    /// Writes the snapshot to `path` and syncs it, replacing the previous one at once so that a
+    /// crash leaves either of them.
+    pub(crate) fn persist(&self, path: &str) -> std::io::Result<()> {
+        let content = serde_json::to_string(self)?;
+        if let Some(parent) = Path::new(path).parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp_path = format!("{}.tmp", path);
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, path)
+    }
+
+    pub(crate) fn flushed_offset(&self, topic: &str, queue_id: i32) -> Option<i64> {
+        self.offset_table.get(&Self::key(topic, queue_id)).copied()
+    }
+
+    pub(crate) fn insert(&mut self, topic: &str, queue_id: i32, flushed_offset: i64) {
+        self.offset_table
+            .insert(Self::key(topic, queue_id), flushed_offset);
+    }
+
+    fn key(topic: &str, queue_id: i32) -> String {
+        format!("{}-{}", topic, queue_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn persists_and_loads_offsets() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("queueOffsetSnapshot.json");
+        let path = path.to_str().unwrap();
+        assert_eq!(
+            QueueOffsetSnapshot::load(path),
+            QueueOffsetSnapshot::default()
+        );
+
+        let mut snapshot = QueueOffsetSnapshot::default();
+        snapshot.insert("topic", 0, 42);
+        snapshot.insert("topic-a", 1, 7);
+        snapshot.persist(path).unwrap();
+
+        let loaded = QueueOffsetSnapshot::load(path);
+        assert_eq!(loaded.flushed_offset("topic", 0), Some(42));
+        assert_eq!(loaded.flushed_offset("topic-a", 1), Some(7));
+        assert_eq!(loaded.flushed_offset("topic", 1), None);
+    }
+}
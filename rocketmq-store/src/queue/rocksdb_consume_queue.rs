@@ -0,0 +1,758 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::VecDeque;
+use std::sync::atomic::AtomicI64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use cheetah_string::CheetahString;
+use rocketmq_common::common::attribute::cq_type::CQType;
+use rocketmq_common::common::boundary_type::BoundaryType;
+use rocketmq_common::common::broker::broker_role::BrokerRole;
+use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
+
+use crate::base::dispatch_request::DispatchRequest;
+use crate::base::store_checkpoint::StoreCheckpoint;
+use crate::base::swappable::Swappable;
+use crate::config::message_store_config::MessageStoreConfig;
+use crate::filter::MessageFilter;
+use crate::message_store::default_message_store::search_offset_by_time;
+use crate::queue::queue_offset_operator::QueueOffsetOperator;
+use crate::queue::rocksdb_consume_queue_table::QueueOffset;
+use crate::queue::rocksdb_consume_queue_table::RocksDBConsumeQueueTable;
+use crate::queue::rocksdb_consume_queue_table::RocksDBCqUnit;
+use crate::queue::single_consume_queue::ConsumeQueue;
+use crate::queue::single_consume_queue::CQ_STORE_UNIT_SIZE;
+use crate::queue::ConsumeQueueTrait;
+use crate::queue::CqUnit;
+use crate::queue::FileQueueLifeCycle;
+use crate::store::running_flags::RunningFlags;
+
+/// How many units an iterator reads from RocksDB at once.
+const ITERATE_BATCH_NUMS: i32 = 32;
+
+/// How many units of a file-based queue are written to RocksDB in one batch on migration.
+const MIGRATE_BATCH_NUMS: usize = 1024;
+
+/// A consume queue whose units are kept in the RocksDB shared by the store instead of mapped
+/// files, used for all queues when `storeType` is `RocksDB`.
+///
+/// The min and max offset and the max physical offset are cached, they are loaded from the
+/// offset column family and updated with every write.
+#[derive(Clone)]
+pub struct RocksDBConsumeQueue {
+    message_store_config: Arc<MessageStoreConfig>,
+    table: RocksDBConsumeQueueTable,
+    topic: CheetahString,
+    queue_id: i32,
+    min_offset: Arc<AtomicI64>,
+    // the offset of the next unit
+    max_offset: Arc<AtomicI64>,
+    max_physic_offset: Arc<AtomicI64>,
+    running_flags: Arc<RunningFlags>,
+    store_checkpoint: Arc<StoreCheckpoint>,
+}
+
+impl RocksDBConsumeQueue {
+    pub fn new(
+        topic: CheetahString,
+        queue_id: i32,
+        table: RocksDBConsumeQueueTable,
+        message_store_config: Arc<MessageStoreConfig>,
+        running_flags: Arc<RunningFlags>,
+        store_checkpoint: Arc<StoreCheckpoint>,
+    ) -> Self {
+        Self {
+            message_store_config,
+            table,
+            topic,
+            queue_id,
+            min_offset: Arc::new(AtomicI64::new(0)),
+            max_offset: Arc::new(AtomicI64::new(0)),
+            max_physic_offset: Arc::new(AtomicI64::new(-1)),
+            running_flags,
+            store_checkpoint,
+        }
+    }
+
+    fn get_unit(&self, index: i64) -> Option<RocksDBCqUnit> {
+        match self.table.get(&self.topic, self.queue_id, index) {
+            Ok(unit) => unit,
+            Err(e) => {
+                error!(
+                    "get consume queue unit {}:{} at {} from RocksDB failed, {}",
+                    self.topic, self.queue_id, index, e
+                );
+                None
+            }
+        }
+    }
+
+    /// The first offset in `[min, max)` whose message is at or after `phy_offset`, the max
+    /// offset when there is none. The physical offsets grow with the queue offsets, so this is
+    /// a binary search.
+    fn first_offset_at_or_after(&self, phy_offset: i64) -> i64 {
+        let mut low = self.get_min_offset_in_queue();
+        let mut high = self.get_max_offset_in_queue();
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match self.get_unit(mid) {
+                Some(unit) if unit.phy_offset >= phy_offset => high = mid,
+                _ => low = mid + 1,
+            }
+        }
+        low
+    }
+
+    fn put_message_position_info(&self, request: &DispatchRequest, tags_code: i64) -> bool {
+        let cq_offset = request.consume_queue_offset;
+        let max_offset = self.get_max_offset_in_queue();
+        if cq_offset < max_offset {
+            warn!(
+                "Build consume queue repeatedly, cqOffset: {} maxOffset: {} Topic: {} QID: {}",
+                cq_offset, max_offset, self.topic, self.queue_id
+            );
+            return true;
+        }
+        if cq_offset != max_offset && max_offset != 0 {
+            warn!(
+                "[BUG]logic queue order maybe wrong, cqOffset: {} maxOffset: {} Topic: {} QID: {} \
+                 Diff: {}",
+                cq_offset,
+                max_offset,
+                self.topic,
+                self.queue_id,
+                cq_offset - max_offset
+            );
+        }
+        let unit = RocksDBCqUnit {
+            phy_offset: request.commit_log_offset,
+            size: request.msg_size,
+            tags_code,
+            store_timestamp: request.store_timestamp,
+        };
+        if let Err(e) = self
+            .table
+            .put_units(&self.topic, self.queue_id, &[(cq_offset, unit)])
+        {
+            warn!(
+                "put consume queue unit {}:{} at {} to RocksDB failed, {}",
+                self.topic, self.queue_id, cq_offset, e
+            );
+            return false;
+        }
+        // the first unit of a queue created after its messages were already assigned offsets
+        if max_offset == 0 && cq_offset != 0 {
+            self.set_min_offset(cq_offset, unit.phy_offset);
+        }
+        self.max_offset.store(cq_offset + 1, Ordering::Release);
+        self.max_physic_offset
+            .store(unit.phy_offset + unit.size as i64, Ordering::SeqCst);
+        true
+    }
+
+    fn set_min_offset(&self, cq_offset: i64, phy_offset: i64) {
+        let min_offset = QueueOffset {
+            phy_offset,
+            cq_offset,
+        };
+        if let Err(e) = self
+            .table
+            .set_min_offset(&self.topic, self.queue_id, min_offset)
+        {
+            warn!(
+                "set min offset of consume queue {}:{} failed, {}",
+                self.topic, self.queue_id, e
+            );
+        }
+        self.min_offset.store(cq_offset, Ordering::Release);
+    }
+
+    fn to_cq_unit(index: i64, unit: &RocksDBCqUnit) -> CqUnit {
+        CqUnit {
+            queue_offset: index,
+            size: unit.size,
+            pos: unit.phy_offset,
+            tags_code: unit.tags_code,
+            ..CqUnit::default()
+        }
+    }
+}
+
+impl FileQueueLifeCycle for RocksDBConsumeQueue {
+    fn load(&mut self) -> bool {
+        let (min_offset, max_offset) = match (
+            self.table.min_offset(&self.topic, self.queue_id),
+            self.table.max_offset(&self.topic, self.queue_id),
+        ) {
+            (Ok(min_offset), Ok(max_offset)) => (min_offset, max_offset),
+            (Err(e), _) | (_, Err(e)) => {
+                error!(
+                    "load consume queue {}-{} from RocksDB failed, {}",
+                    self.topic, self.queue_id, e
+                );
+                return false;
+            }
+        };
+        if let Some(max_offset) = max_offset {
+            self.max_offset
+                .store(max_offset.cq_offset + 1, Ordering::Release);
+            self.max_physic_offset
+                .store(max_offset.phy_offset, Ordering::SeqCst);
+        }
+        if let Some(min_offset) = min_offset {
+            self.min_offset
+                .store(min_offset.cq_offset, Ordering::Release);
+        }
+        info!(
+            "load consume queue {}-{} from RocksDB OK, min offset {}, max offset {}",
+            self.topic,
+            self.queue_id,
+            self.get_min_offset_in_queue(),
+            self.get_max_offset_in_queue()
+        );
+        true
+    }
+
+    fn recover(&mut self) {
+        // every write goes to RocksDB in one batch, what has been loaded is consistent
+    }
+
+    fn check_self(&self) {
+        info!(
+            "consume queue {}-{} in RocksDB, min offset {}, max offset {}, max physic offset {}",
+            self.topic,
+            self.queue_id,
+            self.get_min_offset_in_queue(),
+            self.get_max_offset_in_queue(),
+            self.get_max_physic_offset()
+        );
+    }
+
+    fn flush(&self, _flush_least_pages: i32) -> bool {
+        match self.table.flush() {
+            Ok(()) => true,
+            Err(e) => {
+                warn!("flush RocksDB consume queue failed, {}", e);
+                false
+            }
+        }
+    }
+
+    fn destroy(&mut self) {
+        if let Err(e) = self.table.destroy_queue(&self.topic, self.queue_id) {
+            warn!(
+                "destroy consume queue {}-{} in RocksDB failed, {}",
+                self.topic, self.queue_id, e
+            );
+        }
+        self.min_offset.store(0, Ordering::Release);
+        self.max_offset.store(0, Ordering::Release);
+        self.max_physic_offset.store(-1, Ordering::SeqCst);
+    }
+
+    fn truncate_dirty_logic_files(&mut self, max_commit_log_pos: i64) {
+        if self.get_max_physic_offset() <= max_commit_log_pos {
+            return;
+        }
+        let truncate_from = self.first_offset_at_or_after(max_commit_log_pos);
+        let last_unit = (truncate_from > self.get_min_offset_in_queue())
+            .then(|| self.get_unit(truncate_from - 1))
+            .flatten();
+        let max_offset = last_unit.map(|unit| QueueOffset {
+            phy_offset: unit.phy_offset + unit.size as i64,
+            cq_offset: truncate_from - 1,
+        });
+        if let Err(e) =
+            self.table
+                .truncate_from(&self.topic, self.queue_id, truncate_from, max_offset)
+        {
+            warn!(
+                "truncate consume queue {}-{} in RocksDB failed, {}",
+                self.topic, self.queue_id, e
+            );
+            return;
+        }
+        info!(
+            "truncate consume queue {}-{} from {} in RocksDB, max commit log pos {}",
+            self.topic, self.queue_id, truncate_from, max_commit_log_pos
+        );
+        self.max_offset.store(truncate_from, Ordering::Release);
+        self.max_physic_offset.store(
+            max_offset.map_or(-1, |max_offset| max_offset.phy_offset),
+            Ordering::SeqCst,
+        );
+    }
+
+    fn delete_expired_file(&self, min_commit_log_pos: i64) -> i32 {
+        self.correct_min_offset(min_commit_log_pos);
+        0
+    }
+
+    fn roll_next_file(&self, next_begin_offset: i64) -> i64 {
+        // the units of a queue are not split into files
+        next_begin_offset
+    }
+
+    fn is_first_file_available(&self) -> bool {
+        true
+    }
+
+    fn is_first_file_exist(&self) -> bool {
+        true
+    }
+}
+
+impl Swappable for RocksDBConsumeQueue {
+    fn swap_map(
+        &self,
+        _reserve_num: i32,
+        _force_swap_interval_ms: i64,
+        _normal_swap_interval_ms: i64,
+    ) {
+    }
+
+    fn clean_swapped_map(&self, _force_clean_swap_interval_ms: i64) {}
+}
+
+impl ConsumeQueueTrait for RocksDBConsumeQueue {
+    fn get_topic(&self) -> &CheetahString {
+        &self.topic
+    }
+
+    fn get_queue_id(&self) -> i32 {
+        self.queue_id
+    }
+
+    fn get(&self, index: i64) -> Option<CqUnit> {
+        self.get_unit(index)
+            .map(|unit| Self::to_cq_unit(index, &unit))
+    }
+
+    fn get_cq_unit_and_store_time(&self, index: i64) -> Option<(CqUnit, i64)> {
+        self.get_unit(index)
+            .map(|unit| (Self::to_cq_unit(index, &unit), unit.store_timestamp))
+    }
+
+    fn get_earliest_unit_and_store_time(&self) -> Option<(CqUnit, i64)> {
+        self.get_cq_unit_and_store_time(self.get_min_offset_in_queue())
+    }
+
+    fn get_earliest_unit(&self) -> CqUnit {
+        self.get(self.get_min_offset_in_queue()).unwrap_or_default()
+    }
+
+    fn get_latest_unit(&self) -> CqUnit {
+        self.get(self.get_max_offset_in_queue() - 1)
+            .unwrap_or_default()
+    }
+
+    fn get_last_offset(&self) -> i64 {
+        self.get_unit(self.get_max_offset_in_queue() - 1)
+            .map_or(-1, |unit| unit.phy_offset)
+    }
+
+    fn get_min_offset_in_queue(&self) -> i64 {
+        self.min_offset.load(Ordering::Acquire)
+    }
+
+    fn get_max_offset_in_queue(&self) -> i64 {
+        self.max_offset.load(Ordering::Acquire)
+    }
+
+    fn get_flushed_offset_in_queue(&self) -> Option<i64> {
+        None
+    }
+
+    fn get_message_total_in_queue(&self) -> i64 {
+        (self.get_max_offset_in_queue() - self.get_min_offset_in_queue()).max(0)
+    }
+
+    fn get_offset_in_queue_by_time(&self, timestamp: i64) -> i64 {
+        self.get_offset_in_queue_by_time_boundary(timestamp, BoundaryType::Lower)
+    }
+
+    fn get_offset_in_queue_by_time_boundary(
+        &self,
+        timestamp: i64,
+        boundary_type: BoundaryType,
+    ) -> i64 {
+        search_offset_by_time(
+            self.get_min_offset_in_queue(),
+            self.get_max_offset_in_queue(),
+            timestamp,
+            boundary_type,
+            |offset| {
+                self.get_unit(offset)
+                    .map_or(-1, |unit| unit.store_timestamp)
+            },
+        )
+    }
+
+    fn get_max_physic_offset(&self) -> i64 {
+        self.max_physic_offset.load(Ordering::SeqCst)
+    }
+
+    fn get_min_logic_offset(&self) -> i64 {
+        self.get_min_offset_in_queue()
+    }
+
+    fn get_cq_type(&self) -> CQType {
+        CQType::RocksDBCQ
+    }
+
+    fn get_total_size(&self) -> i64 {
+        self.get_message_total_in_queue() * self.get_unit_size() as i64
+    }
+
+    fn get_unit_size(&self) -> i32 {
+        CQ_STORE_UNIT_SIZE
+    }
+
+    fn correct_min_offset(&self, min_commit_log_offset: i64) {
+        let min_offset = self.get_min_offset_in_queue();
+        let new_min_offset = self.first_offset_at_or_after(min_commit_log_offset);
+        if new_min_offset <= min_offset {
+            return;
+        }
+        let min_phy_offset = self
+            .get_unit(new_min_offset)
+            .map_or(min_commit_log_offset, |unit| unit.phy_offset);
+        let min_offset = QueueOffset {
+            phy_offset: min_phy_offset,
+            cq_offset: new_min_offset,
+        };
+        if let Err(e) = self
+            .table
+            .delete_before(&self.topic, self.queue_id, min_offset)
+        {
+            warn!(
+                "delete expired units of consume queue {}-{} in RocksDB failed, {}",
+                self.topic, self.queue_id, e
+            );
+            return;
+        }
+        self.min_offset.store(new_min_offset, Ordering::Release);
+        info!(
+            "ConsumeQueue[topic={}, queue-id={}] in RocksDB, min-offset is corrected to {}",
+            self.topic, self.queue_id, new_min_offset
+        );
+    }
+
+    fn put_message_position_info_wrapper(&mut self, request: &DispatchRequest) {
+        let max_retries = 30i32;
+        let can_write = self.running_flags.is_cq_writeable();
+        let mut i = 0i32;
+        while i < max_retries && can_write {
+            if self.put_message_position_info(request, request.tags_code) {
+                if self.message_store_config.broker_role == BrokerRole::Slave
+                    || self.message_store_config.enable_dledger_commit_log
+                {
+                    self.store_checkpoint
+                        .set_physic_msg_timestamp(request.store_timestamp as u64);
+                }
+                self.store_checkpoint
+                    .set_logics_msg_timestamp(request.store_timestamp as u64);
+                return;
+            }
+            warn!(
+                "[BUG]put commit log position info to {}:{} failed, retry {} times",
+                self.topic, self.queue_id, i
+            );
+            i += 1;
+        }
+        error!(
+            "[BUG]consume queue can not write, {} {}",
+            self.topic, self.queue_id
+        );
+        self.running_flags.make_logics_queue_error();
+    }
+
+    fn increase_queue_offset(
+        &self,
+        queue_offset_assigner: &QueueOffsetOperator,
+        msg: &MessageExtBrokerInner,
+        message_num: i16,
+    ) {
+        queue_offset_assigner.increase_queue_offset(
+            CheetahString::from_string(format!("{}-{}", msg.topic(), msg.queue_id())),
+            message_num,
+        );
+    }
+
+    fn assign_queue_offset(
+        &self,
+        queue_offset_operator: &QueueOffsetOperator,
+        msg: &mut MessageExtBrokerInner,
+    ) {
+        let queue_offset = queue_offset_operator.get_queue_offset(CheetahString::from_string(
+            format!("{}-{}", msg.topic(), msg.queue_id()),
+        ));
+        msg.message_ext_inner.queue_offset = queue_offset;
+    }
+
+    fn estimate_message_count(&self, from: i64, to: i64, filter: &dyn MessageFilter) -> i64 {
+        let from = from.max(self.get_min_offset_in_queue());
+        let to = to.min(self.get_max_offset_in_queue());
+        if from >= to {
+            return 0;
+        }
+        match self.iterate_from_inner(from, (to - from).min(i32::MAX as i64) as i32) {
+            Some(iter) => iter
+                .filter(|unit| filter.is_matched_by_consume_queue(Some(unit.tags_code), None))
+                .count() as i64,
+            None => 0,
+        }
+    }
+
+    fn iterate_from(&self, start_index: i64) -> Option<Box<dyn Iterator<Item = CqUnit>>> {
+        let remaining = self.get_max_offset_in_queue() - start_index;
+        self.iterate_from_inner(start_index, remaining.min(i32::MAX as i64) as i32)
+    }
+
+    /// Reads the units of `[start_index, start_index + count)` in batches from RocksDB.
+    fn iterate_from_inner(
+        &self,
+        start_index: i64,
+        count: i32,
+    ) -> Option<Box<dyn Iterator<Item = CqUnit>>> {
+        if start_index < self.get_min_offset_in_queue()
+            || start_index >= self.get_max_offset_in_queue()
+            || count <= 0
+        {
+            return None;
+        }
+        let end = (start_index + count as i64).min(self.get_max_offset_in_queue());
+        Some(Box::new(RocksDBConsumeQueueIterator {
+            table: self.table.clone(),
+            topic: self.topic.clone(),
+            queue_id: self.queue_id,
+            next: start_index,
+            end,
+            buffer: VecDeque::new(),
+        }))
+    }
+}
+
+struct RocksDBConsumeQueueIterator {
+    table: RocksDBConsumeQueueTable,
+    topic: CheetahString,
+    queue_id: i32,
+    next: i64,
+    end: i64,
+    buffer: VecDeque<CqUnit>,
+}
+
+impl Iterator for RocksDBConsumeQueueIterator {
+    type Item = CqUnit;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buffer.is_empty() && self.next < self.end {
+            let num = (self.end - self.next).min(ITERATE_BATCH_NUMS as i64) as i32;
+            match self.table.range(&self.topic, self.queue_id, self.next, num) {
+                Ok(units) => {
+                    self.buffer.extend(
+                        units
+                            .iter()
+                            .map(|(index, unit)| RocksDBConsumeQueue::to_cq_unit(*index, unit)),
+                    );
+                    self.next += num as i64;
+                }
+                Err(e) => {
+                    error!(
+                        "range query consume queue {}:{} from {} failed, {}",
+                        self.topic, self.queue_id, self.next, e
+                    );
+                    self.next = self.end;
+                }
+            }
+        }
+        self.buffer.pop_front()
+    }
+}
+
+/// Copies the units of a file-based consume queue into `table`, returns how many were copied.
+///
+/// The file-based queue only keeps the tags code, the store time of every unit is picked up from
+/// the commit log by `store_timestamp`. The pre-blank units before the first message of a queue
+/// are skipped, the min offset is set to the first unit copied.
+pub fn migrate_from_file_consume_queue(
+    file_queue: &ConsumeQueue,
+    table: &RocksDBConsumeQueueTable,
+    store_timestamp: &dyn Fn(i64, i32) -> i64,
+) -> Result<usize, rocksdb::Error> {
+    let topic = file_queue.get_topic().as_str();
+    let queue_id = file_queue.get_queue_id();
+    let max_offset = file_queue.get_max_offset_in_queue();
+    let mut index = file_queue.get_min_offset_in_files();
+    let mut first_unit = None;
+    let mut migrated = 0;
+    let mut units = Vec::with_capacity(MIGRATE_BATCH_NUMS);
+    while index < max_offset {
+        // a file at a time, up to its read position
+        let Some(iter) = file_queue.iterate_from(index) else {
+            break;
+        };
+        let from = index;
+        for cq_unit in iter {
+            index = cq_unit.queue_offset + 1;
+            if cq_unit.size == i32::MAX {
+                continue;
+            }
+            let unit = RocksDBCqUnit {
+                phy_offset: cq_unit.pos,
+                size: cq_unit.size,
+                tags_code: cq_unit.tags_code,
+                store_timestamp: store_timestamp(cq_unit.pos, cq_unit.size),
+            };
+            first_unit.get_or_insert((cq_unit.queue_offset, unit.phy_offset));
+            units.push((cq_unit.queue_offset, unit));
+            if units.len() >= MIGRATE_BATCH_NUMS {
+                table.put_units(topic, queue_id, &units)?;
+                migrated += units.len();
+                units.clear();
+            }
+        }
+        if index == from {
+            break;
+        }
+    }
+    table.put_units(topic, queue_id, &units)?;
+    migrated += units.len();
+    if let Some((cq_offset, phy_offset)) = first_unit {
+        table.set_min_offset(
+            topic,
+            queue_id,
+            QueueOffset {
+                phy_offset,
+                cq_offset,
+            },
+        )?;
+    }
+    Ok(migrated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(cq_offset: i64) -> DispatchRequest {
+        DispatchRequest {
+            topic: CheetahString::from_static_str("topic"),
+            queue_id: 0,
+            commit_log_offset: cq_offset * 100,
+            msg_size: 100,
+            store_timestamp: 1000 + cq_offset * 10,
+            consume_queue_offset: cq_offset,
+            ..DispatchRequest::default()
+        }
+    }
+
+    #[test]
+    fn put_query_truncate_and_correct_min_offset() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let table = RocksDBConsumeQueueTable::open(temp_dir.path().to_str().unwrap()).unwrap();
+        let store_checkpoint = Arc::new(
+            StoreCheckpoint::new(temp_dir.path().join("checkpoint").to_str().unwrap()).unwrap(),
+        );
+        let new_queue = || {
+            RocksDBConsumeQueue::new(
+                CheetahString::from_static_str("topic"),
+                0,
+                table.clone(),
+                Arc::new(MessageStoreConfig::default()),
+                Arc::new(RunningFlags::new()),
+                store_checkpoint.clone(),
+            )
+        };
+        let mut queue = new_queue();
+        for cq_offset in 0..100 {
+            queue.put_message_position_info_wrapper(&request(cq_offset));
+        }
+        // dispatched again after a restart
+        queue.put_message_position_info_wrapper(&request(99));
+        assert_eq!(queue.get_max_offset_in_queue(), 100);
+        assert_eq!(queue.get_max_physic_offset(), 10000);
+        assert_eq!(queue.get(42).unwrap().pos, 4200);
+        assert_eq!(queue.get_offset_in_queue_by_time(1425), 43);
+
+        let units: Vec<_> = queue.iterate_from_inner(30, 40).unwrap().collect();
+        assert_eq!(units.len(), 40);
+        assert_eq!(units[39].queue_offset, 69);
+        assert!(queue.iterate_from_inner(100, 1).is_none());
+
+        queue.truncate_dirty_logic_files(9050);
+        assert_eq!(queue.get_max_offset_in_queue(), 91);
+        assert_eq!(queue.get_max_physic_offset(), 9100);
+
+        queue.correct_min_offset(2000);
+        assert_eq!(queue.get_min_offset_in_queue(), 20);
+        assert!(queue.get(19).is_none());
+
+        let mut reloaded = new_queue();
+        assert!(reloaded.load());
+        assert_eq!(reloaded.get_min_offset_in_queue(), 20);
+        assert_eq!(reloaded.get_max_offset_in_queue(), 91);
+        assert_eq!(reloaded.get_message_total_in_queue(), 71);
+    }
+
+    #[test]
+    fn migrates_units_of_file_consume_queue() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root_dir = temp_dir.path().to_str().unwrap();
+        let message_store_config = Arc::new(MessageStoreConfig::default());
+        let store_checkpoint =
+            Arc::new(StoreCheckpoint::new(temp_dir.path().join("checkpoint")).unwrap());
+        // two units per file
+        let mut file_queue = ConsumeQueue::new(
+            CheetahString::from_static_str("topic"),
+            1,
+            CheetahString::from(root_dir),
+            CQ_STORE_UNIT_SIZE * 2,
+            message_store_config.clone(),
+            Arc::new(RunningFlags::new()),
+            store_checkpoint.clone(),
+        );
+        for cq_offset in 0..5 {
+            assert!(file_queue.put_message_position_info(cq_offset * 100, 100, 7, cq_offset));
+        }
+
+        let table =
+            RocksDBConsumeQueueTable::open(temp_dir.path().join("rocksdb").to_str().unwrap())
+                .unwrap();
+        let migrated =
+            migrate_from_file_consume_queue(&file_queue, &table, &|pos, _| 1000 + pos).unwrap();
+        assert_eq!(migrated, 5);
+
+        let mut queue = RocksDBConsumeQueue::new(
+            CheetahString::from_static_str("topic"),
+            1,
+            table,
+            message_store_config,
+            Arc::new(RunningFlags::new()),
+            store_checkpoint,
+        );
+        assert!(queue.load());
+        assert_eq!(queue.get_max_offset_in_queue(), 5);
+        assert_eq!(queue.get_max_physic_offset(), 500);
+        let (unit, store_time) = queue.get_cq_unit_and_store_time(3).unwrap();
+        assert_eq!((unit.pos, unit.tags_code, store_time), (300, 7, 1300));
+    }
+}
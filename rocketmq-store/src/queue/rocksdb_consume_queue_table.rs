@@ -0,0 +1,437 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::fs;
+use std::sync::Arc;
+
+use rocksdb::ColumnFamily;
+use rocksdb::ColumnFamilyDescriptor;
+use rocksdb::Direction;
+use rocksdb::Error;
+use rocksdb::IteratorMode;
+use rocksdb::Options;
+use rocksdb::WriteBatch;
+use rocksdb::DB;
+use tracing::warn;
+
+/// Column family of the consume queue units.
+const DEFAULT_CF: &str = "default";
+
+/// Column family of the min and max offset of every queue.
+const OFFSET_CF: &str = "offset";
+
+const CTRL_1: u8 = 1;
+
+const MAX_OFFSET: &[u8] = b"max";
+const MIN_OFFSET: &[u8] = b"min";
+
+/// Size of a unit value: CommitLog Physical Offset(8) + Body Size(4) + Tag HashCode(8) + Msg
+/// Store Time(8).
+pub const ROCKSDB_CQ_UNIT_SIZE: usize = 8 + 4 + 8 + 8;
+
+/// Size of an offset value: CommitLog Physical Offset(8) + ConsumeQueue Offset(8).
+const OFFSET_VALUE_SIZE: usize = 8 + 8;
+
+/// A consume queue unit as it is kept in RocksDB.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RocksDBCqUnit {
+    pub phy_offset: i64,
+    pub size: i32,
+    pub tags_code: i64,
+    pub store_timestamp: i64,
+}
+
+impl RocksDBCqUnit {
+    fn encode(&self) -> [u8; ROCKSDB_CQ_UNIT_SIZE] {
+        let mut value = [0u8; ROCKSDB_CQ_UNIT_SIZE];
+        value[..8].copy_from_slice(&self.phy_offset.to_be_bytes());
+        value[8..12].copy_from_slice(&self.size.to_be_bytes());
+        value[12..20].copy_from_slice(&self.tags_code.to_be_bytes());
+        value[20..].copy_from_slice(&self.store_timestamp.to_be_bytes());
+        value
+    }
+
+    fn decode(value: &[u8]) -> Option<Self> {
+        if value.len() != ROCKSDB_CQ_UNIT_SIZE {
+            return None;
+        }
+        Some(Self {
+            phy_offset: i64::from_be_bytes(value[..8].try_into().unwrap()),
+            size: i32::from_be_bytes(value[8..12].try_into().unwrap()),
+            tags_code: i64::from_be_bytes(value[12..20].try_into().unwrap()),
+            store_timestamp: i64::from_be_bytes(value[20..].try_into().unwrap()),
+        })
+    }
+}
+
+/// The min or max entry of a queue in the offset column family.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueueOffset {
+    pub phy_offset: i64,
+    pub cq_offset: i64,
+}
+
+impl QueueOffset {
+    fn encode(&self) -> [u8; OFFSET_VALUE_SIZE] {
+        let mut value = [0u8; OFFSET_VALUE_SIZE];
+        value[..8].copy_from_slice(&self.phy_offset.to_be_bytes());
+        value[8..].copy_from_slice(&self.cq_offset.to_be_bytes());
+        value
+    }
+
+    fn decode(value: &[u8]) -> Option<Self> {
+        if value.len() != OFFSET_VALUE_SIZE {
+            return None;
+        }
+        Some(Self {
+            phy_offset: i64::from_be_bytes(value[..8].try_into().unwrap()),
+            cq_offset: i64::from_be_bytes(value[8..].try_into().unwrap()),
+        })
+    }
+}
+
+/// `[topic length(4)][topic][CTRL_1][queue id(4)][CTRL_1]`, the common prefix of all keys of a
+/// queue. Big endian numbers keep the units of a queue sorted by their offset.
+fn queue_prefix(topic: &str, queue_id: i32) -> Vec<u8> {
+    let mut key = Vec::with_capacity(4 + topic.len() + 1 + 4 + 1 + 8);
+    key.extend_from_slice(&(topic.len() as i32).to_be_bytes());
+    key.extend_from_slice(topic.as_bytes());
+    key.push(CTRL_1);
+    key.extend_from_slice(&queue_id.to_be_bytes());
+    key.push(CTRL_1);
+    key
+}
+
+fn unit_key(topic: &str, queue_id: i32, cq_offset: i64) -> Vec<u8> {
+    let mut key = queue_prefix(topic, queue_id);
+    key.extend_from_slice(&cq_offset.to_be_bytes());
+    key
+}
+
+fn offset_key(topic: &str, queue_id: i32, kind: &[u8]) -> Vec<u8> {
+    let mut key = queue_prefix(topic, queue_id);
+    key.extend_from_slice(kind);
+    key
+}
+
+/// Splits an offset key into its topic, queue id and kind.
+fn parse_offset_key(key: &[u8]) -> Option<(String, i32, &[u8])> {
+    let topic_len = i32::from_be_bytes(key.get(..4)?.try_into().ok()?) as usize;
+    let topic = std::str::from_utf8(key.get(4..4 + topic_len)?).ok()?;
+    let rest = key.get(4 + topic_len..)?;
+    if rest.len() < 6 || rest[0] != CTRL_1 || rest[5] != CTRL_1 {
+        return None;
+    }
+    let queue_id = i32::from_be_bytes(rest[1..5].try_into().unwrap());
+    Some((topic.to_string(), queue_id, &rest[6..]))
+}
+
+/// The RocksDB instance shared by all RocksDB consume queues of a store.
+///
+/// The units of a queue are keyed by topic, queue id and queue offset in the default column
+/// family, the min and max offset of every queue are kept in the `offset` column family, so that
+/// the queues are found again on load without scanning the units.
+#[derive(Clone)]
+pub struct RocksDBConsumeQueueTable {
+    db: Arc<DB>,
+}
+
+impl RocksDBConsumeQueueTable {
+    pub fn open(store_path: &str) -> Result<Self, Error> {
+        if let Err(e) = fs::create_dir_all(store_path) {
+            warn!(
+                "create RocksDB consume queue dir {} failed, {}",
+                store_path, e
+            );
+        }
+        let mut options = Options::default();
+        options.create_if_missing(true);
+        options.create_missing_column_families(true);
+        let db = DB::open_cf_descriptors(
+            &options,
+            store_path,
+            vec![
+                ColumnFamilyDescriptor::new(DEFAULT_CF, Options::default()),
+                ColumnFamilyDescriptor::new(OFFSET_CF, Options::default()),
+            ],
+        )?;
+        Ok(Self { db: Arc::new(db) })
+    }
+
+    fn unit_cf(&self) -> &ColumnFamily {
+        self.db
+            .cf_handle(DEFAULT_CF)
+            .expect("default column family")
+    }
+
+    fn offset_cf(&self) -> &ColumnFamily {
+        self.db.cf_handle(OFFSET_CF).expect("offset column family")
+    }
+
+    /// Writes `units` keyed by their queue offset, and moves the max offset of the queue to the
+    /// last of them, in one batch.
+    pub fn put_units(
+        &self,
+        topic: &str,
+        queue_id: i32,
+        units: &[(i64, RocksDBCqUnit)],
+    ) -> Result<(), Error> {
+        let Some((last_offset, last_unit)) = units.last() else {
+            return Ok(());
+        };
+        let mut batch = WriteBatch::default();
+        for (cq_offset, unit) in units {
+            batch.put_cf(
+                self.unit_cf(),
+                unit_key(topic, queue_id, *cq_offset),
+                unit.encode(),
+            );
+        }
+        let max_offset = QueueOffset {
+            phy_offset: last_unit.phy_offset + last_unit.size as i64,
+            cq_offset: *last_offset,
+        };
+        batch.put_cf(
+            self.offset_cf(),
+            offset_key(topic, queue_id, MAX_OFFSET),
+            max_offset.encode(),
+        );
+        self.db.write(batch)
+    }
+
+    pub fn get(
+        &self,
+        topic: &str,
+        queue_id: i32,
+        cq_offset: i64,
+    ) -> Result<Option<RocksDBCqUnit>, Error> {
+        Ok(self
+            .db
+            .get_cf(self.unit_cf(), unit_key(topic, queue_id, cq_offset))?
+            .and_then(|value| RocksDBCqUnit::decode(&value)))
+    }
+
+    /// The units of `[start, start + num)` that exist, in offset order.
+    pub fn range(
+        &self,
+        topic: &str,
+        queue_id: i32,
+        start: i64,
+        num: i32,
+    ) -> Result<Vec<(i64, RocksDBCqUnit)>, Error> {
+        let prefix = queue_prefix(topic, queue_id);
+        let from = unit_key(topic, queue_id, start);
+        let end = start.saturating_add(num as i64);
+        let mut units = Vec::with_capacity(num.clamp(0, 1024) as usize);
+        let iter = self.db.iterator_cf(
+            self.unit_cf(),
+            IteratorMode::From(&from, Direction::Forward),
+        );
+        for item in iter {
+            let (key, value) = item?;
+            if key.len() != prefix.len() + 8 || !key.starts_with(&prefix) {
+                break;
+            }
+            let cq_offset = i64::from_be_bytes(key[prefix.len()..].try_into().unwrap());
+            if cq_offset >= end {
+                break;
+            }
+            if let Some(unit) = RocksDBCqUnit::decode(&value) {
+                units.push((cq_offset, unit));
+            }
+        }
+        Ok(units)
+    }
+
+    pub fn max_offset(&self, topic: &str, queue_id: i32) -> Result<Option<QueueOffset>, Error> {
+        self.queue_offset(topic, queue_id, MAX_OFFSET)
+    }
+
+    pub fn min_offset(&self, topic: &str, queue_id: i32) -> Result<Option<QueueOffset>, Error> {
+        self.queue_offset(topic, queue_id, MIN_OFFSET)
+    }
+
+    fn queue_offset(
+        &self,
+        topic: &str,
+        queue_id: i32,
+        kind: &[u8],
+    ) -> Result<Option<QueueOffset>, Error> {
+        Ok(self
+            .db
+            .get_cf(self.offset_cf(), offset_key(topic, queue_id, kind))?
+            .and_then(|value| QueueOffset::decode(&value)))
+    }
+
+    /// Deletes the units before `min_offset` and records it as the min offset of the queue.
+    pub fn delete_before(
+        &self,
+        topic: &str,
+        queue_id: i32,
+        min_offset: QueueOffset,
+    ) -> Result<(), Error> {
+        let mut batch = WriteBatch::default();
+        batch.delete_range_cf(
+            self.unit_cf(),
+            unit_key(topic, queue_id, 0),
+            unit_key(topic, queue_id, min_offset.cq_offset),
+        );
+        batch.put_cf(
+            self.offset_cf(),
+            offset_key(topic, queue_id, MIN_OFFSET),
+            min_offset.encode(),
+        );
+        self.db.write(batch)
+    }
+
+    /// Records `min_offset` as the min offset of the queue without deleting any unit.
+    pub fn set_min_offset(
+        &self,
+        topic: &str,
+        queue_id: i32,
+        min_offset: QueueOffset,
+    ) -> Result<(), Error> {
+        self.db.put_cf(
+            self.offset_cf(),
+            offset_key(topic, queue_id, MIN_OFFSET),
+            min_offset.encode(),
+        )
+    }
+
+    /// Deletes the units from `from` on, the max offset of the queue moves to `max_offset`, or
+    /// is removed when no unit is left.
+    pub fn truncate_from(
+        &self,
+        topic: &str,
+        queue_id: i32,
+        from: i64,
+        max_offset: Option<QueueOffset>,
+    ) -> Result<(), Error> {
+        let mut batch = WriteBatch::default();
+        batch.delete_range_cf(
+            self.unit_cf(),
+            unit_key(topic, queue_id, from),
+            unit_key(topic, queue_id, i64::MAX),
+        );
+        let max_key = offset_key(topic, queue_id, MAX_OFFSET);
+        match max_offset {
+            Some(max_offset) => batch.put_cf(self.offset_cf(), max_key, max_offset.encode()),
+            None => batch.delete_cf(self.offset_cf(), max_key),
+        }
+        self.db.write(batch)
+    }
+
+    /// Deletes all units and offsets of the queue.
+    pub fn destroy_queue(&self, topic: &str, queue_id: i32) -> Result<(), Error> {
+        let mut batch = WriteBatch::default();
+        batch.delete_range_cf(
+            self.unit_cf(),
+            unit_key(topic, queue_id, 0),
+            unit_key(topic, queue_id, i64::MAX),
+        );
+        batch.delete_cf(self.offset_cf(), offset_key(topic, queue_id, MAX_OFFSET));
+        batch.delete_cf(self.offset_cf(), offset_key(topic, queue_id, MIN_OFFSET));
+        self.db.write(batch)
+    }
+
+    /// The topic and queue id of every queue holding units.
+    pub fn queues(&self) -> Result<Vec<(String, i32)>, Error> {
+        let mut queues = Vec::new();
+        for item in self.db.iterator_cf(self.offset_cf(), IteratorMode::Start) {
+            let (key, _) = item?;
+            match parse_offset_key(&key) {
+                Some((topic, queue_id, kind)) if kind == MAX_OFFSET => {
+                    queues.push((topic, queue_id))
+                }
+                _ => {}
+            }
+        }
+        Ok(queues)
+    }
+
+    /// Syncs the write ahead log, what has been put survives a crash of the broker.
+    pub fn flush(&self) -> Result<(), Error> {
+        self.db.flush_wal(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit(phy_offset: i64) -> RocksDBCqUnit {
+        RocksDBCqUnit {
+            phy_offset,
+            size: 100,
+            tags_code: 7,
+            store_timestamp: 1000 + phy_offset,
+        }
+    }
+
+    #[test]
+    fn keys_keep_queues_apart_and_offsets_sorted() {
+        assert!(unit_key("topic", 1, 255) < unit_key("topic", 1, 256));
+        assert!(!unit_key("topic1", 1, 0).starts_with(&queue_prefix("topic", 1)));
+        let key = offset_key("topic", 3, MAX_OFFSET);
+        assert_eq!(
+            parse_offset_key(&key),
+            Some(("topic".to_string(), 3, MAX_OFFSET))
+        );
+        assert_eq!(RocksDBCqUnit::decode(&unit(5).encode()), Some(unit(5)));
+    }
+
+    #[test]
+    fn range_truncate_and_delete_before() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let table = RocksDBConsumeQueueTable::open(temp_dir.path().to_str().unwrap()).unwrap();
+        let units: Vec<_> = (0..10).map(|i| (i, unit(i * 100))).collect();
+        table.put_units("topic", 0, &units).unwrap();
+        table.put_units("topic", 1, &units[..1]).unwrap();
+
+        assert_eq!(table.get("topic", 0, 3).unwrap(), Some(unit(300)));
+        let range = table.range("topic", 0, 8, 32).unwrap();
+        assert_eq!(range, vec![(8, unit(800)), (9, unit(900))]);
+        assert_eq!(
+            table.max_offset("topic", 0).unwrap(),
+            Some(QueueOffset {
+                phy_offset: 1000,
+                cq_offset: 9
+            })
+        );
+        assert_eq!(table.queues().unwrap().len(), 2);
+
+        let max_offset = QueueOffset {
+            phy_offset: 600,
+            cq_offset: 5,
+        };
+        table
+            .truncate_from("topic", 0, 6, Some(max_offset))
+            .unwrap();
+        assert!(table.get("topic", 0, 6).unwrap().is_none());
+        assert_eq!(table.max_offset("topic", 0).unwrap(), Some(max_offset));
+
+        let min_offset = QueueOffset {
+            phy_offset: 200,
+            cq_offset: 2,
+        };
+        table.delete_before("topic", 0, min_offset).unwrap();
+        assert_eq!(table.range("topic", 0, 0, 32).unwrap().len(), 4);
+        assert_eq!(table.min_offset("topic", 0).unwrap(), Some(min_offset));
+
+        table.destroy_queue("topic", 1).unwrap();
+        assert_eq!(table.queues().unwrap(), vec![("topic".to_string(), 0)]);
+    }
+}
@@ -27,7 +27,12 @@ use cheetah_string::CheetahString;
 use rocketmq_common::common::attribute::cq_type::CQType;
 use rocketmq_common::common::boundary_type::BoundaryType;
 use rocketmq_common::common::broker::broker_role::BrokerRole;
+use rocketmq_common::common::message::message_decoder;
 use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+use rocketmq_common::common::message::MessageConst;
+use rocketmq_common::common::message::MessageTrait;
+use rocketmq_common::common::mix_all::is_lmq;
+use rocketmq_common::common::mix_all::MULTI_DISPATCH_QUEUE_SPLITTER;
 use tracing::debug;
 use tracing::error;
 use tracing::info;
@@ -41,6 +46,7 @@ use crate::config::message_store_config::MessageStoreConfig;
 use crate::consume_queue::consume_queue_ext::CqExtUnit;
 use crate::consume_queue::mapped_file_queue::MappedFileQueue;
 use crate::filter::MessageFilter;
+use crate::log_file::commit_log::CommitLog;
 use crate::log_file::mapped_file::default_mapped_file_impl::DefaultMappedFile;
 use crate::log_file::mapped_file::MappedFile;
 use crate::queue::consume_queue_ext::ConsumeQueueExt;
@@ -66,6 +72,11 @@ pub const MSG_TAG_OFFSET_INDEX: i32 = 12;
 /// </pre>
 /// ConsumeQueue's store unit. Size: CommitLog Physical Offset(8) + Body Size(4) + Tag HashCode(8) =
 /// 20 Bytes
+struct MultiDispatchQueueKey {
+    key: CheetahString,
+    is_lmq: bool,
+}
+
 #[derive(Clone)]
 pub struct ConsumeQueue {
     message_store_config: Arc<MessageStoreConfig>,
@@ -210,12 +221,21 @@ impl ConsumeQueue {
         }
         if self.is_ext_read_enable() {
             self.consume_queue_ext
-                .as_ref()
+                .as_mut()
                 .unwrap()
                 .truncate_by_max_address(max_ext_addr);
         }
     }
 
+    /// The offset of the first unit kept in the files, the units before it have been deleted.
+    pub(crate) fn get_min_offset_in_files(&self) -> i64 {
+        self.mapped_file_queue
+            .get_first_mapped_file()
+            .map_or(0, |mapped_file| {
+                mapped_file.get_file_from_offset() as i64 / CQ_STORE_UNIT_SIZE as i64
+            })
+    }
+
     #[inline]
     pub fn is_ext_read_enable(&self) -> bool {
         self.consume_queue_ext.is_some()
@@ -226,7 +246,152 @@ impl ConsumeQueue {
         ConsumeQueueExt::is_ext_addr(tags_code)
     }
 
+    /// Scans the units from `first_unit` of the mapped file at `file_index` on, and truncates
+    /// the queue after the last intact one.
+    fn recover_from_unit(&mut self, file_index: usize, first_unit: i32) {
+        let binding = self.mapped_file_queue.get_mapped_files();
+        let mapped_files = binding.read();
+        let mut index = file_index;
+        let mapped_file_size_logics = self.mapped_file_size;
+        let Some(mut mapped_file) = mapped_files.get(index) else {
+            return;
+        };
+        let mut process_offset = mapped_file.get_file_from_offset();
+        let mut mapped_file_offset = (first_unit * CQ_STORE_UNIT_SIZE) as i64;
+        let mut first_unit = first_unit;
+        let mut max_ext_addr = 1i64;
+        loop {
+            for index in first_unit..(mapped_file_size_logics / CQ_STORE_UNIT_SIZE) {
+                let bytes_option = mapped_file.get_bytes(
+                    (index * CQ_STORE_UNIT_SIZE) as usize,
+                    CQ_STORE_UNIT_SIZE as usize,
+                );
+                if bytes_option.is_none() {
+                    break;
+                }
+                let mut byte_buffer = bytes_option.unwrap();
+                let offset = byte_buffer.get_i64();
+                let size = byte_buffer.get_i32();
+                let tags_code = byte_buffer.get_i64();
+                if offset >= 0 && size > 0 {
+                    mapped_file_offset =
+                        (index * CQ_STORE_UNIT_SIZE) as i64 + CQ_STORE_UNIT_SIZE as i64;
+                    self.set_max_physic_offset(offset + size as i64);
+                    if Self::is_ext_addr(tags_code) {
+                        max_ext_addr = tags_code;
+                    }
+                } else {
+                    info!(
+                        "recover current consume queue file over,  {}, {} {} {}",
+                        mapped_file.get_file_name(),
+                        offset,
+                        size,
+                        tags_code
+                    );
+                    break;
+                }
+            }
+            if mapped_file_offset == mapped_file_size_logics as i64 {
+                index += 1;
+                if index >= mapped_files.len() {
+                    info!(
+                        "recover last consume queue file over, last mapped file {}",
+                        mapped_file.get_file_name()
+                    );
+                    break;
+                } else {
+                    mapped_file = mapped_files.get(index).unwrap();
+                    process_offset = mapped_file.get_file_from_offset();
+                    mapped_file_offset = 0;
+                    first_unit = 0;
+                    info!(
+                        "recover next consume queue file, {}",
+                        mapped_file.get_file_name()
+                    );
+                }
+            } else {
+                info!(
+                    "recover current consume queue file over, {} {}",
+                    mapped_file.get_file_name(),
+                    process_offset + (mapped_file_offset as u64),
+                );
+                break;
+            }
+        }
+        process_offset += mapped_file_offset as u64;
+        self.mapped_file_queue
+            .set_flushed_where(process_offset as i64);
+        self.mapped_file_queue
+            .set_committed_where(process_offset as i64);
+        self.mapped_file_queue
+            .truncate_dirty_files(process_offset as i64);
+
+        if self.is_ext_read_enable() {
+            let consume_queue_ext = self.consume_queue_ext.as_mut().unwrap();
+            consume_queue_ext.recover();
+            info!("Truncate consume queue extend file by max {}", max_ext_addr);
+            consume_queue_ext.truncate_by_max_address(max_ext_addr);
+        }
+    }
+
+    /// Mapped file and unit to resume recovery at, and the max physic offset up to there, when
+    /// the units before `offset_in_queue` were already written. `None` when the files on disk do
+    /// not hold such units.
+    fn snapshot_recover_start(&self, offset_in_queue: i64) -> Option<(usize, i32, i64)> {
+        if offset_in_queue <= 0 || self.is_ext_read_enable() {
+            return None;
+        }
+        let position = offset_in_queue * CQ_STORE_UNIT_SIZE as i64;
+        let binding = self.mapped_file_queue.get_mapped_files();
+        let mapped_files = binding.read();
+        let file_index = mapped_files.iter().position(|mapped_file| {
+            let from = mapped_file.get_file_from_offset() as i64;
+            position > from && position <= from + self.mapped_file_size as i64
+        })?;
+        let mapped_file = mapped_files.get(file_index)?;
+        let end_in_file = position - mapped_file.get_file_from_offset() as i64;
+        let mut last_unit = mapped_file.get_bytes(
+            (end_in_file - CQ_STORE_UNIT_SIZE as i64) as usize,
+            CQ_STORE_UNIT_SIZE as usize,
+        )?;
+        let offset = last_unit.get_i64();
+        let size = last_unit.get_i32();
+        if offset < 0 || size <= 0 {
+            return None;
+        }
+        Some((
+            file_index,
+            (end_in_file / CQ_STORE_UNIT_SIZE as i64) as i32,
+            offset + size as i64,
+        ))
+    }
+
     #[inline]
+    fn is_need_handle_multi_dispatch(&self, msg: &MessageExtBrokerInner) -> bool {
+        self.message_store_config.enable_multi_dispatch && CommitLog::is_multi_dispatch_msg(msg)
+    }
+
+    /// The offset table keys of the queues in `INNER_MULTI_DISPATCH`, a light message queue
+    /// only has the queue 0.
+    fn multi_dispatch_queue_keys(&self, msg: &MessageExtBrokerInner) -> Vec<MultiDispatchQueueKey> {
+        let Some(multi_dispatch_queue) = msg.property(MessageConst::PROPERTY_INNER_MULTI_DISPATCH)
+        else {
+            return Vec::new();
+        };
+        multi_dispatch_queue
+            .as_str()
+            .split(MULTI_DISPATCH_QUEUE_SPLITTER)
+            .map(|queue| {
+                let is_lmq = self.message_store_config.enable_lmq && is_lmq(Some(queue));
+                let queue_id = if is_lmq { 0 } else { msg.queue_id() };
+                MultiDispatchQueueKey {
+                    key: CheetahString::from_string(format!("{}-{}", queue, queue_id)),
+                    is_lmq,
+                }
+            })
+            .collect()
+    }
+
     pub fn is_ext_write_enable(&self) -> bool {
         self.consume_queue_ext.is_some() && self.message_store_config.enable_consume_queue_ext
     }
@@ -367,103 +532,42 @@ impl FileQueueLifeCycle for ConsumeQueue {
 
     #[inline]
     fn recover(&mut self) {
-        let binding = self.mapped_file_queue.get_mapped_files();
-        let mapped_files = binding.read();
-        if mapped_files.is_empty() {
+        let mapped_files_len = self.mapped_file_queue.get_mapped_files().read().len();
+        if mapped_files_len == 0 {
             return;
         }
-        let mut index = (mapped_files.len()) as i32 - 3;
-        if index < 0 {
-            index = 0;
-        }
-        let mut index = index as usize;
-        let mapped_file_size_logics = self.mapped_file_size;
-        let mut mapped_file = mapped_files.get(index).unwrap();
-        let mut process_offset = mapped_file.get_file_from_offset();
-        let mut mapped_file_offset = 0i64;
-        let mut max_ext_addr = 1i64;
-        loop {
-            for index in 0..(mapped_file_size_logics / CQ_STORE_UNIT_SIZE) {
-                let bytes_option = mapped_file.get_bytes(
-                    (index * CQ_STORE_UNIT_SIZE) as usize,
-                    CQ_STORE_UNIT_SIZE as usize,
-                );
-                if bytes_option.is_none() {
-                    break;
-                }
-                let mut byte_buffer = bytes_option.unwrap();
-                let offset = byte_buffer.get_i64();
-                let size = byte_buffer.get_i32();
-                let tags_code = byte_buffer.get_i64();
-                if offset >= 0 && size > 0 {
-                    mapped_file_offset =
-                        (index * CQ_STORE_UNIT_SIZE) as i64 + CQ_STORE_UNIT_SIZE as i64;
-                    self.set_max_physic_offset(offset + size as i64);
-                    if Self::is_ext_addr(tags_code) {
-                        max_ext_addr = tags_code;
-                    }
-                    //println!("offset {}, size {}, tags_code {}", offset, size, tags_code);
-                } else {
-                    info!(
-                        "recover current consume queue file over,  {}, {} {} {}",
-                        mapped_file.get_file_name(),
-                        offset,
-                        size,
-                        tags_code
-                    );
-                    break;
-                }
-            }
-            if mapped_file_offset == mapped_file_size_logics as i64 {
-                index += 1;
-                if index >= mapped_files.len() {
-                    info!(
-                        "recover last consume queue file over, last mapped file {}",
-                        mapped_file.get_file_name()
-                    );
-                    break;
-                } else {
-                    mapped_file = mapped_files.get(index).unwrap();
-                    process_offset = mapped_file.get_file_from_offset();
-                    mapped_file_offset = 0;
-                    info!(
-                        "recover next consume queue file, {}",
-                        mapped_file.get_file_name()
-                    );
-                }
-            } else {
-                info!(
-                    "recover current consume queue file over, {} {}",
-                    mapped_file.get_file_name(),
-                    process_offset + (mapped_file_offset as u64),
-                );
-                break;
-            }
-        }
-        process_offset += mapped_file_offset as u64;
-        self.mapped_file_queue
-            .set_flushed_where(process_offset as i64);
-        self.mapped_file_queue
-            .set_committed_where(process_offset as i64);
-        self.mapped_file_queue
-            .truncate_dirty_files(process_offset as i64);
+        self.recover_from_unit(mapped_files_len.saturating_sub(3), 0);
+    }
 
-        if self.is_ext_read_enable() {
-            let consume_queue_ext = self.consume_queue_ext.as_mut().unwrap();
-            consume_queue_ext.recover();
-            info!("Truncate consume queue extend file by max {}", max_ext_addr);
-            consume_queue_ext.truncate_by_max_address(max_ext_addr);
+    fn recover_from(&mut self, offset_in_queue: i64) {
+        match self.snapshot_recover_start(offset_in_queue) {
+            Some((file_index, first_unit, max_physic_offset)) => {
+                self.set_max_physic_offset(max_physic_offset);
+                self.recover_from_unit(file_index, first_unit);
+            }
+            None => self.recover(),
         }
     }
 
     #[inline]
     fn check_self(&self) {
-        todo!()
+        self.mapped_file_queue.check_self();
+        if self.is_ext_read_enable() {
+            self.consume_queue_ext.as_ref().unwrap().check_self();
+        }
     }
 
     #[inline]
     fn flush(&self, flush_least_pages: i32) -> bool {
-        todo!()
+        let mut result = self.mapped_file_queue.flush(flush_least_pages);
+        if self.is_ext_read_enable() {
+            result &= self
+                .consume_queue_ext
+                .as_ref()
+                .unwrap()
+                .flush(flush_least_pages);
+        }
+        result
     }
 
     #[inline]
@@ -481,9 +585,15 @@ impl FileQueueLifeCycle for ConsumeQueue {
         self.truncate_dirty_logic_files_handler(max_commit_log_pos, true);
     }
 
+    /// Deletes the files whose units all point before `min_commit_log_pos`, the extend files
+    /// follow with the corrected min offset.
     #[inline]
     fn delete_expired_file(&self, min_commit_log_pos: i64) -> i32 {
-        todo!()
+        let count = self
+            .mapped_file_queue
+            .delete_expired_file_by_offset(min_commit_log_pos, CQ_STORE_UNIT_SIZE);
+        self.correct_min_offset(min_commit_log_pos);
+        count
     }
 
     #[inline]
@@ -493,12 +603,14 @@ impl FileQueueLifeCycle for ConsumeQueue {
 
     #[inline]
     fn is_first_file_available(&self) -> bool {
-        todo!()
+        self.mapped_file_queue
+            .get_first_mapped_file()
+            .is_some_and(|mapped_file| mapped_file.is_available())
     }
 
     #[inline]
     fn is_first_file_exist(&self) -> bool {
-        todo!()
+        self.mapped_file_queue.get_first_mapped_file().is_some()
     }
 }
 
@@ -533,10 +645,7 @@ impl ConsumeQueueTrait for ConsumeQueue {
 
     #[inline]
     fn get(&self, index: i64) -> Option<CqUnit> {
-        match self.iterate_from(index) {
-            None => None,
-            Some(value) => None,
-        }
+        self.iterate_from(index)?.next()
     }
 
     #[inline]
@@ -574,6 +683,11 @@ impl ConsumeQueueTrait for ConsumeQueue {
         self.mapped_file_queue.get_max_offset() / CQ_STORE_UNIT_SIZE as i64
     }
 
+    #[inline]
+    fn get_flushed_offset_in_queue(&self) -> Option<i64> {
+        Some(self.mapped_file_queue.get_flushed_where() / CQ_STORE_UNIT_SIZE as i64)
+    }
+
     #[inline]
     fn get_message_total_in_queue(&self) -> i64 {
         todo!()
@@ -610,7 +724,11 @@ impl ConsumeQueueTrait for ConsumeQueue {
 
     #[inline]
     fn get_total_size(&self) -> i64 {
-        todo!()
+        let mut total_size = self.mapped_file_queue.get_total_file_size();
+        if self.is_ext_read_enable() {
+            total_size += self.consume_queue_ext.as_ref().unwrap().get_total_size();
+        }
+        total_size
     }
 
     #[inline]
@@ -620,7 +738,8 @@ impl ConsumeQueueTrait for ConsumeQueue {
 
     #[inline]
     fn correct_min_offset(&self, min_commit_log_offset: i64) {
-        if min_commit_log_offset >= self.mapped_file_queue.get_max_offset() {
+        if self.min_logic_offset.load(Ordering::Acquire) >= self.mapped_file_queue.get_max_offset()
+        {
             info!(
                 "ConsumeQueue[Topic={}, queue-id={}] contains no valid entries",
                 self.topic, self.queue_id
@@ -630,27 +749,14 @@ impl ConsumeQueueTrait for ConsumeQueue {
         // Check whether the consume queue maps no valid data at all. This check may cost 1 IO
         // operation. The rationale is that consume queue always preserves the last file. In
         // case there are many deprecated topics, This check would save a lot of efforts.
-        let last_mapped_file = self.mapped_file_queue.get_last_mapped_file();
-        if last_mapped_file.is_none() {
+        let Some(last_mapped_file) = self.mapped_file_queue.get_last_mapped_file() else {
             return;
-        }
-        let last_mapped_file = last_mapped_file.unwrap();
+        };
         let max_readable_position = last_mapped_file.get_read_position();
-        let mut last_record = last_mapped_file.select_mapped_buffer(
-            max_readable_position - CQ_STORE_UNIT_SIZE,
-            CQ_STORE_UNIT_SIZE,
-        );
-        if let Some(ref mut result) = last_record {
-            result.mapped_file = Some(last_mapped_file.clone());
-        }
-        if let Some(last_record) = last_record {
-            let mut bytes = last_record
-                .mapped_file
-                .as_ref()
-                .unwrap()
-                .get_bytes(last_record.start_offset as usize, last_record.size as usize)
-                .unwrap();
-            let commit_log_offset = bytes.get_i64();
+        if max_readable_position >= CQ_STORE_UNIT_SIZE {
+            let commit_log_offset = last_mapped_file
+                .get_bytes((max_readable_position - CQ_STORE_UNIT_SIZE) as usize, 8)
+                .map_or(-1, |mut bytes| bytes.get_i64());
             if commit_log_offset < min_commit_log_offset {
                 self.min_logic_offset.store(
                     max_readable_position as i64 + last_mapped_file.get_file_from_offset() as i64,
@@ -669,47 +775,39 @@ impl ConsumeQueueTrait for ConsumeQueue {
 
         let mapped_file = self.mapped_file_queue.get_first_mapped_file();
         let mut min_ext_addr = 1i64;
-        if let Some(mapped_file) = mapped_file {
+        if let Some(mapped) = mapped_file {
             // Search from previous min logical offset. Typically, a consume queue file segment
             // contains 300,000 entries searching from previous position saves
             // significant amount of comparisons and IOs
             let mut intact = true; // Assume previous value is still valid
             let mut start = self.min_logic_offset.load(Ordering::Acquire)
-                - mapped_file.get_file_from_offset() as i64;
+                - mapped.get_file_from_offset() as i64;
             if start < 0 {
                 intact = false;
                 start = 0;
             }
-            if start > mapped_file.get_file_size() as i64 {
+            if start > mapped.get_file_size() as i64 {
                 error!(
                     "[Bug][InconsistentState] ConsumeQueue file {} should have been deleted",
-                    mapped_file.get_file_name()
+                    mapped.get_file_name()
                 );
                 return;
             }
-            let result = mapped_file.select_mapped_buffer_with_position(start as i32);
-            if result.is_none() {
-                warn!(
-                    "[Bug] Failed to scan consume queue entries from file on correcting min \
-                     offset: {}",
-                    mapped_file.get_file_name()
-                );
-                return;
-            }
-            let mut result = result.unwrap();
-            result.mapped_file = Some(mapped_file);
-            if result.size == 0 {
+            let size = mapped.get_read_position() - start as i32;
+            if size <= 0 {
                 debug!(
                     "ConsumeQueue[topic={}, queue-id={}] contains no valid entries",
                     self.topic, self.queue_id
                 );
                 return;
             }
-            let mapped = result.mapped_file.as_ref().unwrap();
-            let commit_log_offset = mapped
-                .get_bytes(result.start_offset as usize, 8)
-                .unwrap()
-                .get_i64();
+            // the physical offset of the unit at `position` of the scanned part of the file
+            let commit_log_offset_at = |position: i32| {
+                mapped
+                    .get_bytes((start + position as i64) as usize, 8)
+                    .map_or(-1, |mut bytes| bytes.get_i64())
+            };
+            let commit_log_offset = commit_log_offset_at(0);
             if intact && commit_log_offset >= min_commit_log_offset {
                 info!(
                     "Abort correction as previous min-offset points to {}, which is greater than \
@@ -719,13 +817,13 @@ impl ConsumeQueueTrait for ConsumeQueue {
                 return;
             }
             let mut low = 0;
-            let mut high = result.size - CQ_STORE_UNIT_SIZE;
+            let mut high = size - CQ_STORE_UNIT_SIZE;
             loop {
                 if high - low <= CQ_STORE_UNIT_SIZE {
                     break;
                 }
                 let mid = (low + high) / 2 / CQ_STORE_UNIT_SIZE * CQ_STORE_UNIT_SIZE;
-                let commit_log_offset = mapped.get_bytes(mid as usize, 8).unwrap().get_i64();
+                let commit_log_offset = commit_log_offset_at(mid);
 
                 match commit_log_offset.cmp(&min_commit_log_offset) {
                     std::cmp::Ordering::Greater => high = mid,
@@ -739,8 +837,10 @@ impl ConsumeQueueTrait for ConsumeQueue {
             }
             let mut i = low;
             while i <= high {
-                let offset_py = mapped.get_bytes(i as usize, 8).unwrap().get_i64();
-                let tags_code = mapped.get_bytes((i + 12) as usize, 8).unwrap().get_i64();
+                let offset_py = commit_log_offset_at(i);
+                let tags_code = mapped
+                    .get_bytes((start + (i + MSG_TAG_OFFSET_INDEX) as i64) as usize, 8)
+                    .map_or(0, |mut bytes| bytes.get_i64());
                 if offset_py >= min_commit_log_offset {
                     self.min_logic_offset.store(
                         mapped.get_file_from_offset() as i64 + i as i64 + start,
@@ -771,7 +871,7 @@ impl ConsumeQueueTrait for ConsumeQueue {
         while i < max_retries && can_write {
             let mut tags_code = request.tags_code;
             if self.is_ext_write_enable() {
-                let ext_addr = self.consume_queue_ext.as_ref().unwrap().put(CqExtUnit::new(
+                let ext_addr = self.consume_queue_ext.as_mut().unwrap().put(CqExtUnit::new(
                     tags_code,
                     request.store_timestamp,
                     request.bit_map.clone(),
@@ -793,16 +893,16 @@ impl ConsumeQueueTrait for ConsumeQueue {
                 tags_code,
                 request.consume_queue_offset,
             ) {
+                // the commit log of a slave or a DLedger member is checkpointed as it is
+                // dispatched
                 if self.message_store_config.broker_role == BrokerRole::Slave
                     || self.message_store_config.enable_dledger_commit_log
                 {
-                    unimplemented!("slave or dledger commit log not support")
+                    self.store_checkpoint
+                        .set_physic_msg_timestamp(request.store_timestamp as u64);
                 }
                 self.store_checkpoint
                     .set_logics_msg_timestamp(request.store_timestamp as u64);
-                //if (MultiDispatchUtils.checkMultiDispatchQueue(this.messageStore.
-                // getMessageStoreConfig(), request)) {
-                // multiDispatchLmqQueue(request, maxRetries);                 }
                 return;
             } else {
                 warn!(
@@ -830,8 +930,20 @@ impl ConsumeQueueTrait for ConsumeQueue {
             CheetahString::from_string(format!("{}-{}", msg.topic(), msg.queue_id())),
             message_num,
         );
+        if !self.is_need_handle_multi_dispatch(msg) {
+            return;
+        }
+        for queue_key in self.multi_dispatch_queue_keys(msg) {
+            if queue_key.is_lmq {
+                queue_offset_assigner.increase_lmq_offset(&queue_key.key, 1);
+            } else {
+                queue_offset_assigner.increase_queue_offset(queue_key.key, 1);
+            }
+        }
     }
 
+    /// Assigns the queue offset, a multi dispatch message also carries the offset it takes in
+    /// every queue of `INNER_MULTI_DISPATCH` as `INNER_MULTI_QUEUE_OFFSET`.
     #[inline]
     fn assign_queue_offset(
         &self,
@@ -842,6 +954,27 @@ impl ConsumeQueueTrait for ConsumeQueue {
             format!("{}-{}", msg.topic(), msg.queue_id()),
         ));
         msg.message_ext_inner.queue_offset = queue_offset;
+        if !self.is_need_handle_multi_dispatch(msg) {
+            return;
+        }
+        let queue_offsets = self
+            .multi_dispatch_queue_keys(msg)
+            .into_iter()
+            .map(|queue_key| {
+                let queue_offset = if queue_key.is_lmq {
+                    queue_offset_operator.get_lmq_offset(&queue_key.key)
+                } else {
+                    queue_offset_operator.get_queue_offset(queue_key.key)
+                };
+                queue_offset.to_string()
+            })
+            .collect::<Vec<_>>()
+            .join(MULTI_DISPATCH_QUEUE_SPLITTER);
+        msg.put_property(
+            CheetahString::from_static_str(MessageConst::PROPERTY_INNER_MULTI_QUEUE_OFFSET),
+            CheetahString::from_string(queue_offsets),
+        );
+        msg.properties_string = message_decoder::message_properties_to_string(msg.get_properties());
     }
 
     #[inline]
@@ -880,11 +1013,8 @@ struct ConsumeQueueIterator {
 }
 
 impl ConsumeQueueIterator {
-    fn get_ext(&self, offset: i64, cq_ext_unit: &CqExtUnit) -> bool {
-        match self.consume_queue_ext.as_ref() {
-            None => false,
-            Some(value) => value.get(offset, cq_ext_unit),
-        }
+    fn get_ext(&self, offset: i64) -> Option<CqExtUnit> {
+        self.consume_queue_ext.as_ref()?.get(offset)
     }
 }
 
@@ -898,12 +1028,15 @@ impl Iterator for ConsumeQueueIterator {
                 if self.counter * CQ_STORE_UNIT_SIZE >= value.size {
                     return None;
                 }
-                let mmp = value.mapped_file.as_ref().unwrap().get_mapped_file();
+                let mapped_file = value.mapped_file.as_ref().unwrap();
+                let mmp = mapped_file.get_mapped_file();
+                // the start offset counts from the first file of the queue
                 let start =
                     value.start_offset as usize + (self.counter * CQ_STORE_UNIT_SIZE) as usize;
+                let position = start - mapped_file.get_file_from_offset() as usize;
                 self.counter += 1;
-                let end = start + CQ_STORE_UNIT_SIZE as usize;
-                let mut bytes = Bytes::copy_from_slice(&mmp[start..end]);
+                let mut bytes =
+                    Bytes::copy_from_slice(&mmp[position..position + CQ_STORE_UNIT_SIZE as usize]);
                 let pos = bytes.get_i64();
                 let size = bytes.get_i32();
                 let tags_code = bytes.get_i64();
@@ -916,9 +1049,7 @@ impl Iterator for ConsumeQueueIterator {
                 };
 
                 if ConsumeQueueExt::is_ext_addr(cq_unit.tags_code) {
-                    let cq_ext_unit = CqExtUnit::default();
-                    let ext_ret = self.get_ext(cq_unit.tags_code, &cq_ext_unit);
-                    if ext_ret {
+                    if let Some(cq_ext_unit) = self.get_ext(cq_unit.tags_code) {
                         cq_unit.tags_code = cq_ext_unit.tags_code();
                         cq_unit.cq_ext_unit = Some(cq_ext_unit);
                     } else {
@@ -934,3 +1065,110 @@ impl Iterator for ConsumeQueueIterator {
         }
     }
 }
+
+impl Drop for ConsumeQueueIterator {
+    fn drop(&mut self) {
+        if let Some(mut smbr) = self.smbr.take() {
+            smbr.release();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_bit_maps_in_the_extend_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root_dir = temp_dir.path().to_str().unwrap();
+        // two units per file of the queue and of its extend files
+        let message_store_config = Arc::new(MessageStoreConfig {
+            store_path_root_dir: CheetahString::from(root_dir),
+            enable_consume_queue_ext: true,
+            mapped_file_size_consume_queue_ext: 64,
+            ..MessageStoreConfig::default()
+        });
+        let mut queue = ConsumeQueue::new(
+            CheetahString::from_static_str("topic"),
+            0,
+            CheetahString::from(temp_dir.path().join("consumequeue").to_str().unwrap()),
+            CQ_STORE_UNIT_SIZE * 2,
+            message_store_config,
+            Arc::new(RunningFlags::new()),
+            Arc::new(StoreCheckpoint::new(temp_dir.path().join("checkpoint")).unwrap()),
+        );
+        for cq_offset in 0..6 {
+            queue.put_message_position_info_wrapper(&DispatchRequest {
+                commit_log_offset: cq_offset * 100,
+                msg_size: 100,
+                tags_code: cq_offset,
+                store_timestamp: 1000 + cq_offset,
+                consume_queue_offset: cq_offset,
+                bit_map: Some(vec![cq_offset as u8; 8]),
+                ..DispatchRequest::default()
+            });
+        }
+        let unit = queue.get(3).unwrap();
+        assert_eq!(unit.tags_code, 3);
+        let cq_ext_unit = unit.cq_ext_unit.unwrap();
+        assert_eq!(cq_ext_unit.msg_store_time(), 1003);
+        assert_eq!(cq_ext_unit.filter_bit_map().as_deref(), Some(&[3u8; 8][..]));
+        assert_eq!(queue.get_total_size(), 3 * 40 + 3 * 64);
+
+        assert_eq!(queue.delete_expired_file(450), 2);
+        assert_eq!(queue.get_min_offset_in_queue(), 5);
+        assert_eq!(queue.get_total_size(), 40 + 64);
+        let unit = queue.get(5).unwrap();
+        assert_eq!(unit.tags_code, 5);
+        assert!(unit.cq_ext_unit.is_some());
+    }
+
+    #[test]
+    fn assigns_the_offsets_of_the_light_message_queues() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let message_store_config = Arc::new(MessageStoreConfig {
+            enable_multi_dispatch: true,
+            enable_lmq: true,
+            ..MessageStoreConfig::default()
+        });
+        let queue = ConsumeQueue::new(
+            CheetahString::from_static_str("topic"),
+            0,
+            CheetahString::from(temp_dir.path().join("consumequeue").to_str().unwrap()),
+            CQ_STORE_UNIT_SIZE * 2,
+            message_store_config,
+            Arc::new(RunningFlags::new()),
+            Arc::new(StoreCheckpoint::new(temp_dir.path().join("checkpoint")).unwrap()),
+        );
+        let operator = QueueOffsetOperator::new();
+        operator.increase_lmq_offset(&CheetahString::from_static_str("%LMQ%b-0"), 3);
+        let mut msg = MessageExtBrokerInner::default();
+        msg.set_topic(CheetahString::from_static_str("topic"));
+        msg.put_property(
+            CheetahString::from_static_str(MessageConst::PROPERTY_INNER_MULTI_DISPATCH),
+            CheetahString::from_static_str("%LMQ%a,%LMQ%b"),
+        );
+
+        queue.assign_queue_offset(&operator, &mut msg);
+        assert_eq!(
+            msg.property(MessageConst::PROPERTY_INNER_MULTI_QUEUE_OFFSET)
+                .as_deref(),
+            Some("0,3")
+        );
+        assert!(msg
+            .properties_string
+            .contains(MessageConst::PROPERTY_INNER_MULTI_QUEUE_OFFSET));
+
+        queue.increase_queue_offset(&operator, &msg, 1);
+        assert_eq!(operator.get_queue_offset("topic-0".into()), 1);
+        assert_eq!(
+            operator.get_lmq_offset(&CheetahString::from_static_str("%LMQ%a-0")),
+            1
+        );
+        assert_eq!(
+            operator.get_lmq_offset(&CheetahString::from_static_str("%LMQ%b-0")),
+            4
+        );
+    }
+}
@@ -1 +0,0 @@
-pub(crate) mod allocate_mapped_file_service;
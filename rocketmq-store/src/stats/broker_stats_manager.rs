@@ -16,9 +16,10 @@
  */
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 
 use cheetah_string::CheetahString;
-use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::common::broker::shared_broker_config::SharedBrokerConfig;
 use rocketmq_common::common::statistics::state_getter::StateGetter;
 use rocketmq_common::common::statistics::statistics_item::StatisticsItem;
 use rocketmq_common::common::statistics::statistics_item_formatter::StatisticsItemFormatter;
@@ -28,9 +29,13 @@ use rocketmq_common::common::statistics::statistics_item_state_getter::Statistic
 use rocketmq_common::common::statistics::statistics_kind_meta::StatisticsKindMeta;
 use rocketmq_common::common::statistics::statistics_manager::StatisticsManager;
 use rocketmq_common::common::stats::moment_stats_item_set::MomentStatsItemSet;
+use rocketmq_common::common::stats::stats_item::StatsItem;
 use rocketmq_common::common::stats::stats_item_set::StatsItemSet;
 use rocketmq_common::common::stats::Stats;
-use tracing::warn;
+use rocketmq_common::common::topic::TopicValidator;
+use tokio::task::JoinHandle;
+
+use crate::stats::stats_type::StatsType;
 
 pub struct BrokerStatsManager {
     stats_table: Arc<parking_lot::RwLock<HashMap<String, StatsItemSet>>>,
@@ -41,7 +46,8 @@ pub struct BrokerStatsManager {
     account_stat_manager: StatisticsManager,
     producer_state_getter: Option<Arc<dyn StateGetter>>,
     consumer_state_getter: Option<Arc<dyn StateGetter>>,
-    broker_config: Option<Arc<BrokerConfig>>,
+    broker_config: Option<SharedBrokerConfig>,
+    sampling_tasks: parking_lot::Mutex<Vec<JoinHandle<()>>>,
 }
 
 impl BrokerStatsManager {
@@ -98,16 +104,40 @@ impl BrokerStatsManager {
 }
 
 impl BrokerStatsManager {
-    #[inline]
+    /// Starts sampling every stats item set into its minute, hour and day windows.
     pub fn start(&self) {
-        //nothing to do
+        let mut sampling_tasks = self.sampling_tasks.lock();
+        if !sampling_tasks.is_empty() {
+            return;
+        }
+        sampling_tasks
+            .push(self.spawn_sampling(Duration::from_secs(10), |set| set.sampling_in_seconds()));
+        sampling_tasks.push(self.spawn_sampling(Duration::from_secs(10 * 60), |set| {
+            set.sampling_in_minutes()
+        }));
+        sampling_tasks
+            .push(self.spawn_sampling(Duration::from_secs(60 * 60), |set| set.sampling_in_hour()));
+    }
+
+    fn spawn_sampling(&self, period: Duration, sampling: fn(&StatsItemSet)) -> JoinHandle<()> {
+        let stats_table = Arc::clone(&self.stats_table);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+                for set in stats_table.read().values() {
+                    sampling(set);
+                }
+            }
+        })
     }
 
     #[inline]
-    pub fn new(broker_config: Arc<BrokerConfig>) -> Self {
+    pub fn new(broker_config: SharedBrokerConfig) -> Self {
         let stats_table = Arc::new(parking_lot::RwLock::new(HashMap::new()));
-        let enable_queue_stat = broker_config.enable_detail_stat;
+        let enable_queue_stat = broker_config.get().enable_detail_stat;
         let cluster_name = broker_config
+            .get()
             .broker_identity
             .broker_cluster_name
             .to_string();
@@ -121,6 +151,7 @@ impl BrokerStatsManager {
             producer_state_getter: None,
             consumer_state_getter: None,
             broker_config: Some(broker_config),
+            sampling_tasks: Default::default(),
         };
         broker_stats_manager.init();
         broker_stats_manager
@@ -128,7 +159,7 @@ impl BrokerStatsManager {
 
     #[inline]
     pub fn new_with_name(
-        broker_config: Arc<BrokerConfig>,
+        broker_config: SharedBrokerConfig,
         cluster_name: String,
         enable_queue_stat: bool,
     ) -> Self {
@@ -147,6 +178,7 @@ impl BrokerStatsManager {
             producer_state_getter: None,
             consumer_state_getter: None,
             broker_config: Some(broker_config),
+            sampling_tasks: Default::default(),
         };
         broker_stats_manager.init();
         broker_stats_manager
@@ -162,9 +194,7 @@ impl BrokerStatsManager {
             Stats::GROUP_GET_FALL_TIME.to_string(),
         )));
 
-        let enable_queue_stat = true; // replace with actual condition
-
-        if enable_queue_stat {
+        if self.enable_queue_stat {
             self.stats_table.write().insert(
                 Stats::QUEUE_PUT_NUMS.to_string(),
                 StatsItemSet::new(Stats::QUEUE_PUT_NUMS.to_string()),
@@ -479,14 +509,39 @@ impl BrokerStatsManager {
 
     #[inline]
     pub fn get_broker_puts_num_without_system_topic(&self) -> u64 {
-        0
+        self.get_stats_item(
+            Self::BROKER_PUT_NUMS_WITHOUT_SYSTEM_TOPIC,
+            &self.cluster_name,
+        )
+        .map_or(0, |item| item.get_value())
     }
 
     #[inline]
     pub fn get_broker_gets_num_without_system_topic(&self) -> u64 {
-        0
+        self.get_stats_item(
+            Self::BROKER_GET_NUMS_WITHOUT_SYSTEM_TOPIC,
+            &self.cluster_name,
+        )
+        .map_or(0, |item| item.get_value())
+    }
+
+    /// Returns the item of `stats_key` in the set named `stats_name`, e.g. `TOPIC_PUT_NUMS` and a
+    /// topic, or `GROUP_GET_NUMS` and `topic@group`.
+    pub fn get_stats_item(&self, stats_name: &str, stats_key: &str) -> Option<Arc<StatsItem>> {
+        self.stats_table
+            .read()
+            .get(stats_name)
+            .and_then(|set| set.get_stats_item(stats_key))
+    }
+
+    #[inline]
+    fn add_value(&self, stats_name: &str, stats_key: &str, inc_value: i32, inc_times: i32) {
+        if let Some(set) = self.stats_table.read().get(stats_name) {
+            set.add_value(stats_key, inc_value.max(0) as u64, inc_times.max(0) as u64);
+        }
     }
 
+    /// Records how many bytes the consumer of `group` lags behind on disk, the latest value wins.
     #[inline]
     pub fn record_disk_fall_behind_size(
         &self,
@@ -495,38 +550,245 @@ impl BrokerStatsManager {
         queue_id: i32,
         fall_behind: i64,
     ) {
+        if let Some(set) = self.moment_stats_item_set_fall_size.as_ref() {
+            let stats_key = format!("{}@{}@{}", queue_id, topic, group);
+            set.set_value(&stats_key, fall_behind.clamp(0, i32::MAX as i64) as i32);
+        }
+    }
+
+    /// Records how many milliseconds the consumer of `group` lags behind on disk, the latest
+    /// value wins.
+    #[inline]
+    pub fn record_disk_fall_behind_time(
+        &self,
+        group: &str,
+        topic: &str,
+        queue_id: i32,
+        fall_behind: i64,
+    ) {
+        if let Some(set) = self.moment_stats_item_set_fall_time.as_ref() {
+            let stats_key = format!("{}@{}@{}", queue_id, topic, group);
+            set.set_value(&stats_key, fall_behind.clamp(0, i32::MAX as i64) as i32);
+        }
+    }
+
+    #[inline]
+    pub fn inc_topic_put_nums(&self, topic: &str, num: i32, times: i32) {
+        self.add_value(Stats::TOPIC_PUT_NUMS, topic, num, times);
+    }
+
+    #[inline]
+    pub fn inc_topic_put_size(&self, topic: &str, size: i32) {
+        self.add_value(Stats::TOPIC_PUT_SIZE, topic, size, 1);
+    }
+
+    #[inline]
+    pub fn inc_group_get_nums(&self, group: &str, topic: &str, inc_value: i32) {
+        let stats_key = build_stats_key(Some(topic), Some(group));
+        self.add_value(Stats::GROUP_GET_NUMS, &stats_key, inc_value, 1);
     }
 
     #[inline]
-    pub fn inc_topic_put_nums(&self, topic: &str, num: i32, times: i32) {}
+    pub fn inc_group_get_size(&self, group: &str, topic: &str, inc_value: i32) {
+        let stats_key = build_stats_key(Some(topic), Some(group));
+        self.add_value(Stats::GROUP_GET_SIZE, &stats_key, inc_value, 1);
+    }
 
     #[inline]
-    pub fn inc_topic_put_size(&self, topic: &str, size: i32) {}
+    pub fn inc_group_ck_nums(&self, group: &str, topic: &str, inc_value: i32) {
+        let stats_key = build_stats_key(Some(topic), Some(group));
+        self.add_value(Self::GROUP_CK_NUMS, &stats_key, inc_value, 1);
+    }
 
     #[inline]
-    pub fn inc_group_get_nums(&self, group: &str, topic: &str, inc_value: i32) {}
+    pub fn inc_group_ack_nums(&self, group: &str, topic: &str, inc_value: i32) {
+        let stats_key = build_stats_key(Some(topic), Some(group));
+        self.add_value(Self::GROUP_ACK_NUMS, &stats_key, inc_value, 1);
+    }
+
+    #[inline]
+    pub fn inc_group_get_latency(&self, group: &str, topic: &str, queue_id: i32, inc_value: i32) {
+        let stats_key = format!("{}@{}@{}", queue_id, topic, group);
+        self.add_value(Stats::GROUP_GET_LATENCY, &stats_key, inc_value, 1);
+    }
+
+    #[inline]
+    pub fn inc_queue_get_nums(&self, group: &str, topic: &str, queue_id: i32, inc_value: i32) {
+        if self.enable_queue_stat {
+            let stats_key = build_stats_key(
+                Some(&build_stats_key(Some(topic), Some(&queue_id.to_string()))),
+                Some(group),
+            );
+            self.add_value(Stats::QUEUE_GET_NUMS, &stats_key, inc_value, 1);
+        }
+    }
+
+    #[inline]
+    pub fn inc_queue_get_size(&self, group: &str, topic: &str, queue_id: i32, inc_value: i32) {
+        if self.enable_queue_stat {
+            let stats_key = build_stats_key(
+                Some(&build_stats_key(Some(topic), Some(&queue_id.to_string()))),
+                Some(group),
+            );
+            self.add_value(Stats::QUEUE_GET_SIZE, &stats_key, inc_value, 1);
+        }
+    }
+
     #[inline]
-    pub fn inc_group_get_size(&self, group: &str, topic: &str, inc_value: i32) {}
+    pub fn inc_send_back_nums(&self, group: &str, topic: &str) {
+        let stats_key = build_stats_key(Some(topic), Some(group));
+        self.add_value(Stats::SNDBCK_PUT_NUMS, &stats_key, 1, 1);
+    }
 
+    /// Counts a message sent back by `group` that went to its dead letter queue.
     #[inline]
-    pub fn inc_group_ck_nums(&self, group: &str, topic: &str, inc_value: i32) {}
+    pub fn inc_dlq_put_nums(&self, group: &str, topic: &str) {
+        let stats_key = build_stats_key(Some(topic), Some(group));
+        self.add_value(Self::DLQ_PUT_NUMS, &stats_key, 1, 1);
+    }
 
+    /// Adds to the commercial set `key`, e.g. `COMMERCIAL_SEND_TIMES`, for the resources `owner`
+    /// is billed for.
     #[inline]
-    pub fn inc_group_ack_nums(&self, group: &str, topic: &str, inc_value: i32) {}
+    pub fn inc_commercial_value(
+        &self,
+        key: &str,
+        owner: &str,
+        group: &str,
+        topic: &str,
+        stats_type: StatsType,
+        inc_value: i32,
+    ) {
+        let stats_key = build_commercial_stats_key(owner, topic, group, stats_type.as_str());
+        self.add_value(key, &stats_key, inc_value, 1);
+    }
+
+    /// Adds to `SNDBCK2DLQ_TIMES`, keyed like the commercial sets.
     #[inline]
-    pub fn inc_broker_get_nums(&self, group: &str, inc_value: i32) {}
+    pub fn inc_dlq_stat_value(
+        &self,
+        owner: &str,
+        group: &str,
+        topic: &str,
+        stats_type: StatsType,
+        inc_value: i32,
+    ) {
+        let stats_key = build_commercial_stats_key(owner, topic, group, stats_type.as_str());
+        self.add_value(Self::SNDBCK2DLQ_TIMES, &stats_key, inc_value, 1);
+    }
+
+    /// Broker wide counters only track the value, keyed by the cluster name.
     #[inline]
-    pub fn inc_broker_put_nums(&self, group: &str, inc_value: i32) {}
+    pub fn inc_broker_get_nums(&self, topic: &str, inc_value: i32) {
+        self.add_value(Stats::BROKER_GET_NUMS, &self.cluster_name, inc_value, 0);
+        if !TopicValidator::is_system_topic(topic) {
+            self.add_value(
+                Self::BROKER_GET_NUMS_WITHOUT_SYSTEM_TOPIC,
+                &self.cluster_name,
+                inc_value,
+                0,
+            );
+        }
+    }
 
     #[inline]
-    pub fn on_topic_deleted(&self, topic: &CheetahString) {}
+    pub fn inc_broker_put_nums(&self, topic: &str, inc_value: i32) {
+        self.add_value(Stats::BROKER_PUT_NUMS, &self.cluster_name, inc_value, 0);
+        if !TopicValidator::is_system_topic(topic) {
+            self.add_value(
+                Self::BROKER_PUT_NUMS_WITHOUT_SYSTEM_TOPIC,
+                &self.cluster_name,
+                inc_value,
+                0,
+            );
+        }
+    }
+
+    pub fn on_topic_deleted(&self, topic: &CheetahString) {
+        let stats_table = self.stats_table.read();
+        for stats_name in [Stats::TOPIC_PUT_NUMS, Stats::TOPIC_PUT_SIZE] {
+            if let Some(set) = stats_table.get(stats_name) {
+                set.del_value(topic.as_str());
+            }
+        }
+        let prefix = build_stats_key(Some(topic.as_str()), None);
+        for stats_name in [
+            Stats::QUEUE_PUT_NUMS,
+            Stats::QUEUE_PUT_SIZE,
+            Stats::QUEUE_GET_NUMS,
+            Stats::QUEUE_GET_SIZE,
+            Stats::GROUP_GET_NUMS,
+            Stats::GROUP_GET_SIZE,
+            Stats::SNDBCK_PUT_NUMS,
+            Self::DLQ_PUT_NUMS,
+            Self::GROUP_ACK_NUMS,
+            Self::GROUP_CK_NUMS,
+        ] {
+            if let Some(set) = stats_table.get(stats_name) {
+                set.del_value_by_prefix_key(&prefix);
+            }
+        }
+        for set in [
+            &self.moment_stats_item_set_fall_size,
+            &self.moment_stats_item_set_fall_time,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            set.del_value_by_infix_key(topic.as_str(), "@");
+        }
+    }
+
+    pub fn on_group_deleted(&self, group: &CheetahString) {
+        let suffix = build_stats_key(None, Some(group.as_str()));
+        let stats_table = self.stats_table.read();
+        for stats_name in [
+            Stats::GROUP_GET_NUMS,
+            Stats::GROUP_GET_SIZE,
+            Stats::GROUP_GET_LATENCY,
+            Stats::QUEUE_GET_NUMS,
+            Stats::QUEUE_GET_SIZE,
+            Stats::SNDBCK_PUT_NUMS,
+            Self::DLQ_PUT_NUMS,
+            Self::GROUP_ACK_NUMS,
+            Self::GROUP_CK_NUMS,
+        ] {
+            if let Some(set) = stats_table.get(stats_name) {
+                set.del_value_by_suffix_key(&suffix);
+            }
+        }
+        for set in [
+            &self.moment_stats_item_set_fall_size,
+            &self.moment_stats_item_set_fall_time,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            set.del_value_by_suffix_key(group.as_str(), "@");
+        }
+    }
 
     #[inline]
-    pub fn inc_queue_put_nums(&self, topic: &str, queue_id: i32, num: i32, times: i32) {}
+    pub fn inc_queue_put_nums(&self, topic: &str, queue_id: i32, num: i32, times: i32) {
+        if self.enable_queue_stat {
+            let stats_key = build_stats_key(Some(topic), Some(&queue_id.to_string()));
+            self.add_value(Stats::QUEUE_PUT_NUMS, &stats_key, num, times);
+        }
+    }
+
     #[inline]
-    pub fn inc_queue_put_size(&self, topic: &str, queue_id: i32, size: i32) {}
+    pub fn inc_queue_put_size(&self, topic: &str, queue_id: i32, size: i32) {
+        if self.enable_queue_stat {
+            let stats_key = build_stats_key(Some(topic), Some(&queue_id.to_string()));
+            self.add_value(Stats::QUEUE_PUT_SIZE, &stats_key, size, 1);
+        }
+    }
+
     #[inline]
-    pub fn inc_topic_put_latency(&self, topic: &str, queue_id: i32, inc_value: i32) {}
+    pub fn inc_topic_put_latency(&self, topic: &str, queue_id: i32, inc_value: i32) {
+        let stats_key = format!("{}@{}", queue_id, topic);
+        self.add_value(Self::TOPIC_PUT_LATENCY, &stats_key, inc_value, 1);
+    }
 
     #[inline]
     pub fn tps_group_get_nums(&self, group: &str, topic: &str) -> f64 {
@@ -538,10 +800,14 @@ impl BrokerStatsManager {
     }
 
     #[inline]
-    pub fn inc_broker_ack_nums(&self, inc_value: i32) {}
+    pub fn inc_broker_ack_nums(&self, inc_value: i32) {
+        self.add_value(Self::BROKER_ACK_NUMS, &self.cluster_name, inc_value, 0);
+    }
 
     pub fn shutdown(&self) {
-        warn!("BrokerStatsManager shutdown unimplemented");
+        for task in self.sampling_tasks.lock().drain(..) {
+            task.abort();
+        }
     }
 }
 
@@ -564,7 +830,7 @@ pub fn create_statistics_kind_meta(
     item_names: Vec<&str>,
     formatter: &StatisticsItemFormatter,
     interval: u64,
-    broker_config: &Arc<BrokerConfig>,
+    broker_config: &SharedBrokerConfig,
 ) -> Arc<StatisticsKindMeta> {
     let printer = StatisticsItemPrinter::new(formatter);
     let scheduled_printer = StatisticsItemScheduledPrinter;
@@ -653,6 +919,72 @@ pub fn split_account_stat_key(account_stat_key: &str) -> Vec<&str> {
 mod tests {
     use super::*;
 
+    #[tokio::test]
+    async fn inc_values_are_visible_through_stats_items() {
+        let manager = BrokerStatsManager::new(SharedBrokerConfig::default());
+        manager.inc_topic_put_nums("TopicTest", 2, 1);
+        manager.inc_group_get_nums("GroupA", "TopicTest", 3);
+        manager.inc_broker_put_nums("TopicTest", 2);
+
+        let item = manager
+            .get_stats_item(Stats::TOPIC_PUT_NUMS, "TopicTest")
+            .unwrap();
+        assert_eq!(item.get_value(), 2);
+        let item = manager
+            .get_stats_item(Stats::GROUP_GET_NUMS, "TopicTest@GroupA")
+            .unwrap();
+        assert_eq!(item.get_value(), 3);
+        assert_eq!(manager.get_broker_puts_num_without_system_topic(), 2);
+        assert!(manager
+            .get_stats_item(Stats::SNDBCK_PUT_NUMS, "TopicTest@GroupA")
+            .is_none());
+
+        manager.on_topic_deleted(&CheetahString::from_static_str("TopicTest"));
+        assert!(manager
+            .get_stats_item(Stats::GROUP_GET_NUMS, "TopicTest@GroupA")
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn group_stats_are_dropped_with_the_group() {
+        let manager = BrokerStatsManager::new(SharedBrokerConfig::default());
+        manager.inc_group_get_latency("GroupA", "TopicTest", 1, 5);
+        manager.inc_dlq_put_nums("GroupA", "TopicTest");
+        manager.inc_commercial_value(
+            Stats::COMMERCIAL_SEND_TIMES,
+            "owner",
+            "GroupA",
+            "TopicTest",
+            StatsType::SendSuccess,
+            2,
+        );
+        assert_eq!(
+            manager
+                .get_stats_item(Stats::GROUP_GET_LATENCY, "1@TopicTest@GroupA")
+                .unwrap()
+                .get_value(),
+            5
+        );
+        assert_eq!(
+            manager
+                .get_stats_item(
+                    Stats::COMMERCIAL_SEND_TIMES,
+                    "owner@TopicTest@GroupA@SEND_SUCCESS"
+                )
+                .unwrap()
+                .get_value(),
+            2
+        );
+
+        manager.on_group_deleted(&CheetahString::from_static_str("GroupA"));
+        assert!(manager
+            .get_stats_item(Stats::GROUP_GET_LATENCY, "1@TopicTest@GroupA")
+            .is_none());
+        assert!(manager
+            .get_stats_item(BrokerStatsManager::DLQ_PUT_NUMS, "TopicTest@GroupA")
+            .is_none());
+    }
+
     #[test]
     fn build_commercial_stats_key_creates_correct_key() {
         let key = build_commercial_stats_key("owner1", "topic1", "group1", "type1");
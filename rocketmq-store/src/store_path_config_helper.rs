@@ -38,6 +38,22 @@ pub fn get_store_path_batch_consume_queue(root_dir: &str) -> String {
         .into_owned()
 }
 
+pub fn get_store_path_rocksdb_consume_queue(root_dir: &str) -> String {
+    PathBuf::from(root_dir)
+        .join("rocksdb")
+        .join("consumequeue")
+        .to_string_lossy()
+        .into_owned()
+}
+
+pub fn get_store_path_compaction_log(root_dir: &str) -> String {
+    PathBuf::from(root_dir)
+        .join("compaction")
+        .join("compactionLog")
+        .to_string_lossy()
+        .into_owned()
+}
+
 pub fn get_store_path_index(root_dir: &str) -> String {
     PathBuf::from(root_dir)
         .join("index")
@@ -52,6 +68,13 @@ pub fn get_store_checkpoint(root_dir: &str) -> String {
         .into_owned()
 }
 
+pub fn get_store_path_epoch_file(root_dir: &str) -> String {
+    PathBuf::from(root_dir)
+        .join("epochFileCheckpoint")
+        .to_string_lossy()
+        .into_owned()
+}
+
 pub fn get_abort_file(root_dir: &str) -> String {
     PathBuf::from(root_dir)
         .join("abort")
@@ -74,6 +97,36 @@ pub fn get_delay_offset_store_path(root_dir: &str) -> String {
         .into_owned()
 }
 
+pub fn get_queue_offset_snapshot_path(root_dir: &str) -> String {
+    PathBuf::from(root_dir)
+        .join("config")
+        .join("queueOffsetSnapshot.json")
+        .to_string_lossy()
+        .into_owned()
+}
+
+pub fn get_store_path_timer_log(root_dir: &str) -> String {
+    PathBuf::from(root_dir)
+        .join("timerlog")
+        .to_string_lossy()
+        .into_owned()
+}
+
+pub fn get_timer_wheel_path(root_dir: &str) -> String {
+    PathBuf::from(root_dir)
+        .join("timerwheel")
+        .to_string_lossy()
+        .into_owned()
+}
+
+pub fn get_timer_check_path(root_dir: &str) -> String {
+    PathBuf::from(root_dir)
+        .join("config")
+        .join("timercheck")
+        .to_string_lossy()
+        .into_owned()
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -140,5 +193,20 @@ mod tests {
                 .to_string_lossy()
                 .into_owned()
         );
+        assert_eq!(
+            get_store_path_timer_log(root_dir),
+            PathBuf::from(root_dir)
+                .join("timerlog")
+                .to_string_lossy()
+                .into_owned()
+        );
+        assert_eq!(
+            get_timer_check_path(root_dir),
+            PathBuf::from(root_dir)
+                .join("config")
+                .join("timercheck")
+                .to_string_lossy()
+                .into_owned()
+        );
     }
 }
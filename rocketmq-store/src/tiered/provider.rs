@@ -0,0 +1,76 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::future::Future;
+use std::io;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use bytes::Bytes;
+
+use crate::config::message_store_config::MessageStoreConfig;
+use crate::tiered::provider::posix_provider::PosixFileProvider;
+
+pub mod posix_provider;
+#[cfg(feature = "tiered_s3")]
+pub mod s3_provider;
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// The backend the tiered storage uploads the sealed store files to.
+///
+/// A file is addressed by its path relative to the store root, e.g.
+/// `commitlog/00000000000000000000` or `consumequeue/TopicTest/0/00000000000000000000`.
+pub trait TieredStorageProvider: Send + Sync + 'static {
+    /// Writes the whole file at `path`, replacing the one there.
+    fn write<'a>(&'a self, path: &'a str, data: Bytes) -> BoxFuture<'a, io::Result<()>>;
+
+    /// Reads `len` bytes from `position` of the file at `path`.
+    fn read<'a>(
+        &'a self,
+        path: &'a str,
+        position: u64,
+        len: usize,
+    ) -> BoxFuture<'a, io::Result<Bytes>>;
+
+    /// Deletes the file at `path`, deleting a missing file is not an error.
+    fn delete<'a>(&'a self, path: &'a str) -> BoxFuture<'a, io::Result<()>>;
+}
+
+/// Builds the provider named by `tiered_backend_provider`.
+pub fn build_provider(
+    message_store_config: &MessageStoreConfig,
+) -> io::Result<Arc<dyn TieredStorageProvider>> {
+    match message_store_config.tiered_backend_provider.as_str() {
+        "posix" => {
+            let dir = if message_store_config.tiered_store_file_path.is_empty() {
+                PathBuf::from(message_store_config.store_path_root_dir.as_str()).join("tiered")
+            } else {
+                PathBuf::from(message_store_config.tiered_store_file_path.as_str())
+            };
+            Ok(Arc::new(PosixFileProvider::new(dir)))
+        }
+        #[cfg(feature = "tiered_s3")]
+        "s3" => Ok(Arc::new(s3_provider::S3Provider::new(
+            message_store_config,
+        )?)),
+        provider => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("unsupported tiered backend provider {}", provider),
+        )),
+    }
+}
@@ -0,0 +1,76 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::io;
+use std::io::SeekFrom;
+use std::path::PathBuf;
+
+use bytes::Bytes;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncSeekExt;
+
+use crate::tiered::provider::BoxFuture;
+use crate::tiered::provider::TieredStorageProvider;
+
+/// Keeps the tier in a local directory, typically a mount of a larger and slower disk.
+pub struct PosixFileProvider {
+    dir: PathBuf,
+}
+
+impl PosixFileProvider {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+impl TieredStorageProvider for PosixFileProvider {
+    fn write<'a>(&'a self, path: &'a str, data: Bytes) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            let file = self.dir.join(path);
+            if let Some(parent) = file.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            // a reader never sees a partly written file
+            let tmp = file.with_extension("tmp");
+            tokio::fs::write(&tmp, &data).await?;
+            tokio::fs::rename(&tmp, &file).await
+        })
+    }
+
+    fn read<'a>(
+        &'a self,
+        path: &'a str,
+        position: u64,
+        len: usize,
+    ) -> BoxFuture<'a, io::Result<Bytes>> {
+        Box::pin(async move {
+            let mut file = tokio::fs::File::open(self.dir.join(path)).await?;
+            file.seek(SeekFrom::Start(position)).await?;
+            let mut data = vec![0u8; len];
+            file.read_exact(&mut data).await?;
+            Ok(Bytes::from(data))
+        })
+    }
+
+    fn delete<'a>(&'a self, path: &'a str) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            match tokio::fs::remove_file(self.dir.join(path)).await {
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+                result => result,
+            }
+        })
+    }
+}
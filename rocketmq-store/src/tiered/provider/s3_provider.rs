@@ -0,0 +1,109 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::io;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use object_store::aws::AmazonS3Builder;
+use object_store::path::Path;
+use object_store::ObjectStore;
+use object_store::PutPayload;
+
+use crate::config::message_store_config::MessageStoreConfig;
+use crate::tiered::provider::BoxFuture;
+use crate::tiered::provider::TieredStorageProvider;
+
+/// Keeps the tier in an S3 compatible object store, the files of a broker under the key
+/// prefix `tiered_store_file_path`.
+///
+/// The credentials not configured are taken from the `AWS_*` environment variables.
+pub struct S3Provider {
+    store: Arc<dyn ObjectStore>,
+    prefix: String,
+}
+
+impl S3Provider {
+    pub fn new(message_store_config: &MessageStoreConfig) -> io::Result<Self> {
+        let mut builder = AmazonS3Builder::from_env()
+            .with_bucket_name(message_store_config.object_store_bucket.as_str());
+        if !message_store_config.object_store_region.is_empty() {
+            builder = builder.with_region(message_store_config.object_store_region.as_str());
+        }
+        if !message_store_config.object_store_endpoint.is_empty() {
+            builder = builder
+                .with_endpoint(message_store_config.object_store_endpoint.as_str())
+                .with_allow_http(true);
+        }
+        if !message_store_config.object_store_access_key.is_empty() {
+            builder = builder
+                .with_access_key_id(message_store_config.object_store_access_key.as_str())
+                .with_secret_access_key(message_store_config.object_store_secret_key.as_str());
+        }
+        let store = builder.build().map_err(io::Error::other)?;
+        Ok(Self {
+            store: Arc::new(store),
+            prefix: message_store_config
+                .tiered_store_file_path
+                .trim_matches('/')
+                .to_string(),
+        })
+    }
+
+    fn object_path(&self, path: &str) -> Path {
+        if self.prefix.is_empty() {
+            Path::from(path)
+        } else {
+            Path::from(format!("{}/{}", self.prefix, path))
+        }
+    }
+}
+
+impl TieredStorageProvider for S3Provider {
+    fn write<'a>(&'a self, path: &'a str, data: Bytes) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            self.store
+                .put(&self.object_path(path), PutPayload::from(data))
+                .await
+                .map(|_| ())
+                .map_err(io::Error::other)
+        })
+    }
+
+    fn read<'a>(
+        &'a self,
+        path: &'a str,
+        position: u64,
+        len: usize,
+    ) -> BoxFuture<'a, io::Result<Bytes>> {
+        Box::pin(async move {
+            let start = position as usize;
+            self.store
+                .get_range(&self.object_path(path), start..start + len)
+                .await
+                .map_err(io::Error::other)
+        })
+    }
+
+    fn delete<'a>(&'a self, path: &'a str) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            match self.store.delete(&self.object_path(path)).await {
+                Ok(()) | Err(object_store::Error::NotFound { .. }) => Ok(()),
+                Err(e) => Err(io::Error::other(e)),
+            }
+        })
+    }
+}
@@ -0,0 +1,276 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::io;
+use std::sync::Arc;
+
+use bytes::Buf;
+use bytes::Bytes;
+use tracing::warn;
+
+use crate::base::get_message_result::GetMessageResult;
+use crate::base::message_status_enum::GetMessageStatus;
+use crate::base::select_result::SelectMappedBufferResult;
+use crate::config::message_store_config::MessageStoreConfig;
+use crate::filter::MessageFilter;
+use crate::queue::single_consume_queue::CQ_STORE_UNIT_SIZE;
+use crate::tiered::provider::TieredStorageProvider;
+use crate::tiered::tiered_metadata::commit_log_key;
+use crate::tiered::tiered_metadata::consume_queue_key;
+use crate::tiered::tiered_metadata::TieredMetadata;
+use crate::tiered::tiered_read_cache::TieredReadCache;
+
+/// Reads the messages of a queue from the files uploaded to the tier, the consume queue units
+/// first and then the messages they point to, through a [`TieredReadCache`] unless
+/// `tiered_read_cache_size` is `0`.
+pub(crate) struct TieredFetcher {
+    provider: Arc<dyn TieredStorageProvider>,
+    metadata: Arc<TieredMetadata>,
+    message_store_config: Arc<MessageStoreConfig>,
+    read_cache: Option<Arc<TieredReadCache>>,
+}
+
+impl TieredFetcher {
+    pub(crate) fn new(
+        provider: Arc<dyn TieredStorageProvider>,
+        metadata: Arc<TieredMetadata>,
+        message_store_config: Arc<MessageStoreConfig>,
+    ) -> Self {
+        let read_cache = (message_store_config.tiered_read_cache_size > 0).then(|| {
+            Arc::new(TieredReadCache::new(
+                provider.clone(),
+                &message_store_config,
+            ))
+        });
+        Self {
+            provider,
+            metadata,
+            message_store_config,
+            read_cache,
+        }
+    }
+
+    async fn read(&self, path: &str, position: u64, len: usize) -> io::Result<Bytes> {
+        match (self.read_cache.as_ref(), self.metadata.file_size(path)) {
+            (Some(read_cache), Some(file_size)) => {
+                read_cache.read(path, file_size, position, len).await
+            }
+            _ => self.provider.read(path, position, len).await,
+        }
+    }
+
+    /// The first queue offset kept in the tier.
+    pub(crate) fn get_min_offset_in_queue(&self, topic: &str, queue_id: i32) -> Option<i64> {
+        self.metadata
+            .min_consume_queue_file(topic, queue_id)
+            .map(|file_from_offset| file_from_offset as i64 / CQ_STORE_UNIT_SIZE as i64)
+    }
+
+    /// The messages from `offset` on that are in the tier, `None` when the tier does not have
+    /// the one at `offset`.
+    pub(crate) async fn get_message(
+        &self,
+        topic: &str,
+        queue_id: i32,
+        offset: i64,
+        max_msg_nums: i32,
+        max_total_msg_size: i32,
+        message_filter: Option<&dyn MessageFilter>,
+    ) -> Option<GetMessageResult> {
+        let cq_file_size = self
+            .message_store_config
+            .get_mapped_file_size_consume_queue() as u64;
+        let commit_log_file_size = self.message_store_config.mapped_file_size_commit_log as u64;
+        let unit_size = CQ_STORE_UNIT_SIZE as u64;
+        let mut result = GetMessageResult::new();
+        let mut next_offset = offset.max(0) as u64;
+        'files: while result.message_count() < max_msg_nums {
+            let position = next_offset * unit_size;
+            let cq_key = consume_queue_key(topic, queue_id, position - position % cq_file_size);
+            if !self.metadata.is_uploaded(&cq_key) {
+                break;
+            }
+            let units = ((max_msg_nums - result.message_count()) as u64)
+                .min((cq_file_size - position % cq_file_size) / unit_size);
+            let data = match self
+                .read(
+                    &cq_key,
+                    position % cq_file_size,
+                    (units * unit_size) as usize,
+                )
+                .await
+            {
+                Ok(data) => data,
+                Err(e) => {
+                    warn!("read {} from the tiered storage failed: {}", cq_key, e);
+                    break;
+                }
+            };
+            for mut unit in data.chunks_exact(unit_size as usize) {
+                let phy_offset = unit.get_i64() as u64;
+                let size = unit.get_i32();
+                let tags_code = unit.get_i64();
+                if size <= 0 {
+                    break 'files;
+                }
+                if result.message_count() > 0
+                    && result.buffer_total_size() + size > max_total_msg_size
+                {
+                    break 'files;
+                }
+                if message_filter.is_some_and(|filter| {
+                    !filter.is_matched_by_consume_queue(Some(tags_code), None)
+                }) {
+                    next_offset += 1;
+                    continue;
+                }
+                let commit_log_key = commit_log_key(phy_offset - phy_offset % commit_log_file_size);
+                let message = match self
+                    .read(
+                        &commit_log_key,
+                        phy_offset % commit_log_file_size,
+                        size as usize,
+                    )
+                    .await
+                {
+                    Ok(message) => message,
+                    Err(e) => {
+                        warn!(
+                            "read {} from the tiered storage failed: {}",
+                            commit_log_key, e
+                        );
+                        break 'files;
+                    }
+                };
+                if message_filter.is_some_and(|filter| {
+                    !filter.is_matched_by_commit_log(Some(message.as_ref()), None)
+                }) {
+                    next_offset += 1;
+                    continue;
+                }
+                result.add_message(
+                    SelectMappedBufferResult {
+                        start_offset: phy_offset,
+                        bytes: Some(message),
+                        size,
+                        mapped_file: None,
+                        is_in_cache: false,
+                    },
+                    next_offset,
+                    1,
+                );
+                next_offset += 1;
+            }
+        }
+        if next_offset == offset.max(0) as u64 {
+            return None;
+        }
+        result.set_status(Some(if result.message_count() > 0 {
+            GetMessageStatus::Found
+        } else {
+            GetMessageStatus::NoMatchedMessage
+        }));
+        result.set_next_begin_offset(next_offset as i64);
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::path::PathBuf;
+
+    use bytes::BufMut;
+    use bytes::BytesMut;
+    use rocketmq_common::UtilAll::offset_to_file_name;
+
+    use super::*;
+    use crate::base::retention_guard::RetentionGuard;
+    use crate::store_path_config_helper::get_store_path_consume_queue;
+    use crate::tiered::provider::posix_provider::PosixFileProvider;
+    use crate::tiered::tiered_upload_service::TieredUploadService;
+
+    const MESSAGE_SIZE: u64 = 100;
+
+    fn write_file(dir: &Path, file_from_offset: u64, data: &[u8]) -> String {
+        std::fs::create_dir_all(dir).unwrap();
+        let file = dir.join(offset_to_file_name(file_from_offset));
+        std::fs::write(&file, data).unwrap();
+        file.to_string_lossy().into_owned()
+    }
+
+    #[tokio::test]
+    async fn serves_uploaded_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root_dir = temp_dir.path().join("store");
+        let message_store_config = Arc::new(MessageStoreConfig {
+            store_path_root_dir: root_dir.to_string_lossy().to_string().into(),
+            mapped_file_size_commit_log: 4 * MESSAGE_SIZE as usize,
+            mapped_file_size_consume_queue: 4 * CQ_STORE_UNIT_SIZE as usize,
+            ..Default::default()
+        });
+
+        // four messages in the first commit log file, numbered by their first byte
+        let commit_log_dir = PathBuf::from(message_store_config.get_store_path_commit_log());
+        let mut commit_log = BytesMut::new();
+        let mut consume_queue = BytesMut::new();
+        for i in 0..4u64 {
+            commit_log.put_bytes(i as u8, MESSAGE_SIZE as usize);
+            consume_queue.put_i64((i * MESSAGE_SIZE) as i64);
+            consume_queue.put_i32(MESSAGE_SIZE as i32);
+            consume_queue.put_i64(0);
+        }
+        let sealed_commit_log = write_file(&commit_log_dir, 0, &commit_log);
+        let current_commit_log = write_file(&commit_log_dir, 4 * MESSAGE_SIZE, &[0; 4]);
+        let queue_dir = PathBuf::from(get_store_path_consume_queue(root_dir.to_str().unwrap()))
+            .join("TopicTest")
+            .join("0");
+        write_file(&queue_dir, 0, &consume_queue);
+        write_file(&queue_dir, 4 * CQ_STORE_UNIT_SIZE as u64, &[0; 20]);
+
+        let provider: Arc<dyn TieredStorageProvider> =
+            Arc::new(PosixFileProvider::new(temp_dir.path().join("tier")));
+        let metadata = Arc::new(TieredMetadata::new(&message_store_config));
+        let upload_service = TieredUploadService::new(
+            provider.clone(),
+            metadata.clone(),
+            message_store_config.clone(),
+        );
+        assert!(!upload_service.can_delete(&sealed_commit_log));
+        upload_service.upload_sealed_files().await;
+        assert!(upload_service.can_delete(&sealed_commit_log));
+        assert!(!upload_service.can_delete(&current_commit_log));
+
+        let fetcher = TieredFetcher::new(provider, metadata, message_store_config);
+        assert_eq!(fetcher.get_min_offset_in_queue("TopicTest", 0), Some(0));
+        let result = fetcher
+            .get_message("TopicTest", 0, 1, 2, i32::MAX, None)
+            .await
+            .unwrap();
+        assert_eq!(result.status(), Some(GetMessageStatus::Found));
+        assert_eq!(result.message_queue_offset(), &vec![1, 2]);
+        assert_eq!(result.next_begin_offset(), 3);
+        for (i, message) in result.message_mapped_list().iter().enumerate() {
+            assert_eq!(message.get_buffer(), &[i as u8 + 1; MESSAGE_SIZE as usize]);
+        }
+
+        // the last file is not sealed, so not uploaded
+        assert!(fetcher
+            .get_message("TopicTest", 0, 4, 2, i32::MAX, None)
+            .await
+            .is_none());
+    }
+}
@@ -0,0 +1,489 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::Arc;
+
+use cheetah_string::CheetahString;
+use parking_lot::RwLock;
+use rocketmq_common::common::boundary_type::BoundaryType;
+use rocketmq_common::common::message::message_batch::MessageExtBatch;
+use rocketmq_common::common::message::message_ext::MessageExt;
+use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+
+use crate::base::commit_log_dispatcher::CommitLogDispatcher;
+use crate::base::dispatch_request::DispatchRequest;
+use crate::base::get_message_result::GetMessageResult;
+use crate::base::message_arriving_listener::MessageArrivingListener;
+use crate::base::message_result::PutMessageResult;
+use crate::base::message_status_enum::GetMessageStatus;
+use crate::base::query_message_result::QueryMessageResult;
+use crate::base::select_result::SelectMappedBufferResult;
+use crate::config::message_store_config::MessageStoreConfig;
+use crate::dledger::dledger_server::DLedgerRoleChangeHandler;
+use crate::filter::MessageFilter;
+use crate::ha::auto_switch_ha_service::AutoSwitchHAService;
+use crate::hook::put_message_hook::BoxedPutMessageHook;
+use crate::log_file::MessageStore;
+use crate::log_file::MAX_PULL_MSG_SIZE;
+use crate::message_store::boxed_message_store::BoxedMessageStore;
+use crate::queue::ArcConsumeQueue;
+use crate::stats::broker_stats_manager::BrokerStatsManager;
+use crate::store::running_flags::RunningFlags;
+use crate::tiered::provider::TieredStorageProvider;
+use crate::tiered::tiered_fetcher::TieredFetcher;
+use crate::tiered::tiered_metadata::TieredMetadata;
+use crate::tiered::tiered_upload_service::TieredUploadService;
+use crate::timer::timer_message_store::TimerMessageStore;
+
+/// A message store with a tiered storage behind it.
+///
+/// The sealed commit log and consume queue files of the wrapped store are uploaded to the
+/// [`TieredStorageProvider`], and a pull from an offset the wrapped store no longer has is
+/// served from there. The wrapped local file store only deletes a file once it is uploaded.
+pub struct TieredMessageStore {
+    next: BoxedMessageStore,
+    metadata: Arc<TieredMetadata>,
+    fetcher: TieredFetcher,
+    upload_service: Arc<TieredUploadService>,
+}
+
+impl TieredMessageStore {
+    pub fn new(next: BoxedMessageStore, provider: Arc<dyn TieredStorageProvider>) -> Self {
+        let message_store_config = Arc::new(next.get_message_store_config().clone());
+        let metadata = Arc::new(TieredMetadata::new(&message_store_config));
+        let upload_service = Arc::new(TieredUploadService::new(
+            provider.clone(),
+            metadata.clone(),
+            message_store_config.clone(),
+        ));
+        #[cfg(feature = "local_file_store")]
+        if let Some(message_store) =
+            next.downcast_ref::<crate::message_store::default_message_store::DefaultMessageStore>()
+        {
+            message_store.set_retention_guard(upload_service.clone());
+        }
+        Self {
+            next,
+            fetcher: TieredFetcher::new(provider, metadata.clone(), message_store_config),
+            metadata,
+            upload_service,
+        }
+    }
+
+    /// The wrapped store.
+    pub fn next(&self) -> &BoxedMessageStore {
+        &self.next
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn fall_back_to_tier(
+        &self,
+        result: Option<GetMessageResult>,
+        topic: &CheetahString,
+        queue_id: i32,
+        offset: i64,
+        max_msg_nums: i32,
+        max_total_msg_size: i32,
+        message_filter: Option<Arc<Box<dyn MessageFilter>>>,
+    ) -> Option<GetMessageResult> {
+        let Some(local_result) = result.as_ref() else {
+            return result;
+        };
+        if local_result.status() != Some(GetMessageStatus::OffsetTooSmall) {
+            return result;
+        }
+        let max_offset = local_result.max_offset();
+        let mut tiered_result = match self
+            .fetcher
+            .get_message(
+                topic,
+                queue_id,
+                offset,
+                max_msg_nums,
+                max_total_msg_size,
+                message_filter
+                    .as_ref()
+                    .map(|filter| &***filter as &dyn MessageFilter),
+            )
+            .await
+        {
+            Some(tiered_result) => tiered_result,
+            None => return result,
+        };
+        tiered_result.set_min_offset(self.get_min_offset_in_queue(topic, queue_id));
+        tiered_result.set_max_offset(max_offset);
+        Some(tiered_result)
+    }
+}
+
+impl MessageStore for TieredMessageStore {
+    async fn load(&mut self) -> bool {
+        self.metadata.load() && self.next.load().await
+    }
+
+    fn start(&mut self) -> Result<(), Box<dyn Error>> {
+        self.next.start()?;
+        self.upload_service.clone().start();
+        Ok(())
+    }
+
+    fn shutdown(&mut self) {
+        self.next.shutdown()
+    }
+
+    fn set_confirm_offset(&mut self, phy_offset: i64) {
+        self.next.set_confirm_offset(phy_offset)
+    }
+
+    fn get_max_phy_offset(&self) -> i64 {
+        self.next.get_max_phy_offset()
+    }
+
+    fn set_broker_init_max_offset(&mut self, broker_init_max_offset: i64) {
+        self.next.set_broker_init_max_offset(broker_init_max_offset)
+    }
+
+    fn now(&self) -> u64 {
+        self.next.now()
+    }
+
+    fn get_state_machine_version(&self) -> i64 {
+        self.next.get_state_machine_version()
+    }
+
+    async fn put_message(&mut self, msg: MessageExtBrokerInner) -> PutMessageResult {
+        self.next.put_message(msg).await
+    }
+
+    async fn put_messages(&mut self, msg_batch: MessageExtBatch) -> PutMessageResult {
+        self.next.put_messages(msg_batch).await
+    }
+
+    fn truncate_files(&mut self, offset_to_truncate: i64) -> bool {
+        self.next.truncate_files(offset_to_truncate)
+    }
+
+    fn is_os_page_cache_busy(&self) -> bool {
+        self.next.is_os_page_cache_busy()
+    }
+
+    fn get_running_flags(&self) -> &RunningFlags {
+        self.next.get_running_flags()
+    }
+
+    fn is_shutdown(&self) -> bool {
+        self.next.is_shutdown()
+    }
+
+    fn get_put_message_hook_list(&self) -> Arc<RwLock<Vec<BoxedPutMessageHook>>> {
+        self.next.get_put_message_hook_list()
+    }
+
+    fn set_put_message_hook(&self, put_message_hook: BoxedPutMessageHook) {
+        self.next.set_put_message_hook(put_message_hook)
+    }
+
+    fn add_first_dispatcher(&self, dispatcher: Box<dyn CommitLogDispatcher>) {
+        self.next.add_first_dispatcher(dispatcher)
+    }
+
+    fn add_dispatcher(&self, dispatcher: Box<dyn CommitLogDispatcher>) {
+        self.next.add_dispatcher(dispatcher)
+    }
+
+    fn get_broker_stats_manager(&self) -> Option<Arc<BrokerStatsManager>> {
+        self.next.get_broker_stats_manager()
+    }
+
+    fn dispatch_behind_bytes(&self) -> i64 {
+        self.next.dispatch_behind_bytes()
+    }
+
+    fn get_min_offset_in_queue(&self, topic: &CheetahString, queue_id: i32) -> i64 {
+        let min_offset = self.next.get_min_offset_in_queue(topic, queue_id);
+        self.fetcher
+            .get_min_offset_in_queue(topic, queue_id)
+            .map_or(min_offset, |tiered_min_offset| {
+                min_offset.min(tiered_min_offset)
+            })
+    }
+
+    fn get_max_offset_in_queue(&self, topic: &CheetahString, queue_id: i32) -> i64 {
+        self.next.get_max_offset_in_queue(topic, queue_id)
+    }
+
+    fn get_offset_in_queue_by_time_with_boundary(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        timestamp: i64,
+        boundary_type: BoundaryType,
+    ) -> i64 {
+        self.next.get_offset_in_queue_by_time_with_boundary(
+            topic,
+            queue_id,
+            timestamp,
+            boundary_type,
+        )
+    }
+
+    fn get_max_offset_in_queue_committed(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        committed: bool,
+    ) -> i64 {
+        self.next
+            .get_max_offset_in_queue_committed(topic, queue_id, committed)
+    }
+
+    async fn get_message(
+        &self,
+        group: &CheetahString,
+        topic: &CheetahString,
+        queue_id: i32,
+        offset: i64,
+        max_msg_nums: i32,
+        message_filter: Option<Arc<Box<dyn MessageFilter>>>,
+    ) -> Option<GetMessageResult> {
+        let result = self
+            .next
+            .get_message(
+                group,
+                topic,
+                queue_id,
+                offset,
+                max_msg_nums,
+                message_filter.clone(),
+            )
+            .await;
+        self.fall_back_to_tier(
+            result,
+            topic,
+            queue_id,
+            offset,
+            max_msg_nums,
+            MAX_PULL_MSG_SIZE,
+            message_filter,
+        )
+        .await
+    }
+
+    async fn get_message_with_total_size(
+        &self,
+        group: &CheetahString,
+        topic: &CheetahString,
+        queue_id: i32,
+        offset: i64,
+        max_msg_nums: i32,
+        max_total_msg_size: i32,
+        message_filter: Option<Arc<Box<dyn MessageFilter>>>,
+    ) -> Option<GetMessageResult> {
+        let result = self
+            .next
+            .get_message_with_total_size(
+                group,
+                topic,
+                queue_id,
+                offset,
+                max_msg_nums,
+                max_total_msg_size,
+                message_filter.clone(),
+            )
+            .await;
+        self.fall_back_to_tier(
+            result,
+            topic,
+            queue_id,
+            offset,
+            max_msg_nums,
+            max_total_msg_size,
+            message_filter,
+        )
+        .await
+    }
+
+    fn check_in_mem_by_consume_offset(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        consume_offset: i64,
+        batch_size: i32,
+    ) -> bool {
+        self.next
+            .check_in_mem_by_consume_offset(topic, queue_id, consume_offset, batch_size)
+    }
+
+    fn is_msg_in_cold_area(
+        &self,
+        group: &CheetahString,
+        topic: &CheetahString,
+        queue_id: i32,
+        consume_offset: i64,
+    ) -> bool {
+        self.next
+            .is_msg_in_cold_area(group, topic, queue_id, consume_offset)
+    }
+
+    fn notify_message_arrive_if_necessary(&self, dispatch_request: &mut DispatchRequest) {
+        self.next
+            .notify_message_arrive_if_necessary(dispatch_request)
+    }
+
+    fn set_message_arriving_listener(
+        &mut self,
+        message_arriving_listener: Option<
+            Arc<Box<dyn MessageArrivingListener + Sync + Send + 'static>>,
+        >,
+    ) {
+        self.next
+            .set_message_arriving_listener(message_arriving_listener)
+    }
+
+    fn find_consume_queue(&self, topic: &CheetahString, queue_id: i32) -> Option<ArcConsumeQueue> {
+        self.next.find_consume_queue(topic, queue_id)
+    }
+
+    fn delete_topics(&mut self, delete_topics: Vec<&CheetahString>) -> i32 {
+        self.next.delete_topics(delete_topics)
+    }
+
+    async fn query_message(
+        &self,
+        topic: &CheetahString,
+        key: &CheetahString,
+        max_num: i32,
+        begin_timestamp: i64,
+        end_timestamp: i64,
+    ) -> Option<QueryMessageResult> {
+        self.next
+            .query_message(topic, key, max_num, begin_timestamp, end_timestamp)
+            .await
+    }
+
+    async fn select_one_message_by_offset(
+        &self,
+        commit_log_offset: i64,
+    ) -> Option<SelectMappedBufferResult> {
+        self.next
+            .select_one_message_by_offset(commit_log_offset)
+            .await
+    }
+
+    async fn select_one_message_by_offset_with_size(
+        &self,
+        commit_log_offset: i64,
+        size: i32,
+    ) -> Option<SelectMappedBufferResult> {
+        self.next
+            .select_one_message_by_offset_with_size(commit_log_offset, size)
+            .await
+    }
+
+    fn look_message_by_offset(&self, commit_log_offset: i64) -> Option<MessageExt> {
+        self.next.look_message_by_offset(commit_log_offset)
+    }
+
+    fn look_message_by_offset_with_size(
+        &self,
+        commit_log_offset: i64,
+        size: i32,
+    ) -> Option<MessageExt> {
+        self.next
+            .look_message_by_offset_with_size(commit_log_offset, size)
+    }
+
+    fn get_message_store_timestamp(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        consume_queue_offset: i64,
+    ) -> i64 {
+        self.next
+            .get_message_store_timestamp(topic, queue_id, consume_queue_offset)
+    }
+
+    fn get_earliest_message_time_in_queue(&self, topic: &CheetahString, queue_id: i32) -> i64 {
+        self.next
+            .get_earliest_message_time_in_queue(topic, queue_id)
+    }
+
+    fn get_latest_message_time_in_queue(&self, topic: &CheetahString, queue_id: i32) -> i64 {
+        self.next.get_latest_message_time_in_queue(topic, queue_id)
+    }
+
+    fn get_runtime_info(&self) -> HashMap<String, String> {
+        self.next.get_runtime_info()
+    }
+
+    fn lock_time_mills(&self) -> i64 {
+        self.next.lock_time_mills()
+    }
+
+    fn get_earliest_message_time(&self) -> i64 {
+        self.next.get_earliest_message_time()
+    }
+
+    fn get_timer_message_store(&self) -> Arc<TimerMessageStore> {
+        self.next.get_timer_message_store()
+    }
+
+    fn set_timer_message_store(&mut self, timer_message_store: Arc<TimerMessageStore>) {
+        self.next.set_timer_message_store(timer_message_store)
+    }
+
+    fn remain_transient_store_buffer_nums(&self) -> i32 {
+        self.next.remain_transient_store_buffer_nums()
+    }
+
+    fn remain_how_many_data_to_commit(&self) -> i64 {
+        self.next.remain_how_many_data_to_commit()
+    }
+
+    fn remain_how_many_data_to_flush(&self) -> i64 {
+        self.next.remain_how_many_data_to_flush()
+    }
+
+    fn get_message_store_config(&self) -> &MessageStoreConfig {
+        self.next.get_message_store_config()
+    }
+
+    fn set_alive_replica_num_in_group(&mut self, alive_replica_nums: i32) {
+        self.next.set_alive_replica_num_in_group(alive_replica_nums)
+    }
+
+    fn get_alive_replica_num_in_group(&self) -> i32 {
+        self.next.get_alive_replica_num_in_group()
+    }
+
+    fn update_ha_master_address(&self, new_addr: &CheetahString) {
+        self.next.update_ha_master_address(new_addr)
+    }
+
+    fn set_dledger_role_change_handler(&self, handler: Arc<dyn DLedgerRoleChangeHandler>) {
+        self.next.set_dledger_role_change_handler(handler)
+    }
+
+    fn get_auto_switch_ha_service(&self) -> Option<Arc<AutoSwitchHAService>> {
+        self.next.get_auto_switch_ha_service()
+    }
+
+    fn update_runtime_config(&self, key: &str, value: &str) -> Result<bool, String> {
+        self.next.update_runtime_config(key, value)
+    }
+}
@@ -0,0 +1,185 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+use parking_lot::RwLock;
+use rocketmq_common::TimeUtils::get_current_millis;
+use rocketmq_common::UtilAll::offset_to_file_name;
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::warn;
+
+use crate::config::message_store_config::MessageStoreConfig;
+use crate::store_path_config_helper::get_store_path_consume_queue;
+
+pub(crate) const COMMIT_LOG_DIR: &str = "commitlog";
+pub(crate) const CONSUME_QUEUE_DIR: &str = "consumequeue";
+
+pub(crate) fn commit_log_key(file_from_offset: u64) -> String {
+    format!(
+        "{}/{}",
+        COMMIT_LOG_DIR,
+        offset_to_file_name(file_from_offset)
+    )
+}
+
+pub(crate) fn consume_queue_key(topic: &str, queue_id: i32, file_from_offset: u64) -> String {
+    format!(
+        "{}/{}/{}/{}",
+        CONSUME_QUEUE_DIR,
+        topic,
+        queue_id,
+        offset_to_file_name(file_from_offset)
+    )
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TieredFileMetadata {
+    pub(crate) size: u64,
+    pub(crate) upload_timestamp: u64,
+}
+
+/// The files uploaded to the tiered storage, keyed by their path in the tier, kept in
+/// `tiered/metadata.json` under the store root.
+pub(crate) struct TieredMetadata {
+    path: PathBuf,
+    commit_log_dir: PathBuf,
+    consume_queue_dir: PathBuf,
+    files: RwLock<BTreeMap<String, TieredFileMetadata>>,
+}
+
+impl TieredMetadata {
+    pub(crate) fn new(message_store_config: &MessageStoreConfig) -> Self {
+        let root_dir = message_store_config.store_path_root_dir.as_str();
+        Self {
+            path: PathBuf::from(root_dir).join("tiered").join("metadata.json"),
+            commit_log_dir: PathBuf::from(message_store_config.get_store_path_commit_log()),
+            consume_queue_dir: PathBuf::from(get_store_path_consume_queue(root_dir)),
+            files: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    pub(crate) fn commit_log_dir(&self) -> &Path {
+        &self.commit_log_dir
+    }
+
+    pub(crate) fn consume_queue_dir(&self) -> &Path {
+        &self.consume_queue_dir
+    }
+
+    pub(crate) fn load(&self) -> bool {
+        let content = match std::fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return true,
+            Err(e) => {
+                warn!("read tiered metadata {} failed: {}", self.path.display(), e);
+                return false;
+            }
+        };
+        match serde_json::from_str(&content) {
+            Ok(files) => {
+                *self.files.write() = files;
+                true
+            }
+            Err(e) => {
+                warn!(
+                    "decode tiered metadata {} failed: {}",
+                    self.path.display(),
+                    e
+                );
+                false
+            }
+        }
+    }
+
+    pub(crate) fn persist(&self) -> io::Result<()> {
+        let content =
+            serde_json::to_string_pretty(&*self.files.read()).map_err(io::Error::other)?;
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, content)?;
+        std::fs::rename(&tmp, &self.path)
+    }
+
+    /// The path in the tier of the local commit log or consume queue file at `file`.
+    pub(crate) fn tier_key(&self, file: &Path) -> Option<String> {
+        let join = |dir: &str, relative: &Path| {
+            let mut key = String::from(dir);
+            for component in relative.components() {
+                key.push('/');
+                key.push_str(component.as_os_str().to_str()?);
+            }
+            Some(key)
+        };
+        if let Ok(relative) = file.strip_prefix(&self.commit_log_dir) {
+            join(COMMIT_LOG_DIR, relative)
+        } else if let Ok(relative) = file.strip_prefix(&self.consume_queue_dir) {
+            join(CONSUME_QUEUE_DIR, relative)
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn is_uploaded(&self, key: &str) -> bool {
+        self.files.read().contains_key(key)
+    }
+
+    pub(crate) fn record_upload(&self, key: String, size: u64) {
+        self.files.write().insert(
+            key,
+            TieredFileMetadata {
+                size,
+                upload_timestamp: get_current_millis(),
+            },
+        );
+    }
+
+    pub(crate) fn file_size(&self, key: &str) -> Option<u64> {
+        self.files.read().get(key).map(|metadata| metadata.size)
+    }
+
+    pub(crate) fn remove(&self, key: &str) {
+        self.files.write().remove(key);
+    }
+
+    /// The files uploaded before `timestamp`.
+    pub(crate) fn uploaded_before(&self, timestamp: u64) -> Vec<String> {
+        self.files
+            .read()
+            .iter()
+            .filter(|(_, metadata)| metadata.upload_timestamp < timestamp)
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    /// The start offset of the first consume queue file of the queue in the tier.
+    pub(crate) fn min_consume_queue_file(&self, topic: &str, queue_id: i32) -> Option<u64> {
+        let prefix = format!("{}/{}/{}/", CONSUME_QUEUE_DIR, topic, queue_id);
+        self.files
+            .read()
+            .range(prefix.clone()..)
+            .take_while(|(key, _)| key.starts_with(&prefix))
+            .filter_map(|(key, _)| key[prefix.len()..].parse::<u64>().ok())
+            .min()
+    }
+}
@@ -0,0 +1,285 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::io;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use bytes::BytesMut;
+use parking_lot::Mutex;
+use tracing::warn;
+
+use crate::config::message_store_config::MessageStoreConfig;
+use crate::tiered::provider::TieredStorageProvider;
+
+/// A file in the tier and the index of a segment in it.
+type SegmentKey = (String, u64);
+
+struct CachedSegment {
+    data: Bytes,
+    last_access: u64,
+}
+
+#[derive(Default)]
+struct Segments {
+    segments: HashMap<SegmentKey, CachedSegment>,
+    /// The cached segments by their last access, the least recently used first.
+    access_order: BTreeMap<u64, SegmentKey>,
+    size: usize,
+    access_counter: u64,
+    /// The segments read ahead right now.
+    loading: HashSet<SegmentKey>,
+}
+
+impl Segments {
+    fn touch(&mut self, segment_key: &SegmentKey) -> Option<Bytes> {
+        let segment = self.segments.get_mut(segment_key)?;
+        self.access_counter += 1;
+        self.access_order.remove(&segment.last_access);
+        segment.last_access = self.access_counter;
+        self.access_order
+            .insert(self.access_counter, segment_key.clone());
+        Some(segment.data.clone())
+    }
+}
+
+/// Caches the files of the tier in segments of `tiered_read_cache_segment_size` bytes, up to
+/// `tiered_read_cache_size` bytes, dropping the least recently used segment first.
+///
+/// A read that misses the cache fetches the segments it covers whole, and the
+/// `tiered_read_ahead_segments` segments after them in the background, so that a sequential
+/// replay issues one read to the tier per segment instead of one per message.
+pub(crate) struct TieredReadCache {
+    provider: Arc<dyn TieredStorageProvider>,
+    capacity: usize,
+    segment_size: u64,
+    read_ahead_segments: u64,
+    inner: Mutex<Segments>,
+}
+
+impl TieredReadCache {
+    pub(crate) fn new(
+        provider: Arc<dyn TieredStorageProvider>,
+        message_store_config: &MessageStoreConfig,
+    ) -> Self {
+        Self {
+            provider,
+            capacity: message_store_config.tiered_read_cache_size,
+            segment_size: message_store_config.tiered_read_cache_segment_size.max(1) as u64,
+            read_ahead_segments: message_store_config.tiered_read_ahead_segments as u64,
+            inner: Mutex::new(Segments::default()),
+        }
+    }
+
+    /// Reads `len` bytes from `position` of the file at `path`, which has `file_size` bytes.
+    pub(crate) async fn read(
+        self: &Arc<Self>,
+        path: &str,
+        file_size: u64,
+        position: u64,
+        len: usize,
+    ) -> io::Result<Bytes> {
+        let end = position + len as u64;
+        if end > file_size {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!(
+                    "read {}..{} of {} of {} bytes",
+                    position, end, path, file_size
+                ),
+            ));
+        }
+        if len == 0 {
+            return Ok(Bytes::new());
+        }
+        let first_segment = position / self.segment_size;
+        let last_segment = (end - 1) / self.segment_size;
+        let mut missed = false;
+        let mut segments = Vec::with_capacity((last_segment - first_segment + 1) as usize);
+        for index in first_segment..=last_segment {
+            let segment_key = (path.to_string(), index);
+            let cached = self.inner.lock().touch(&segment_key);
+            let segment = match cached {
+                Some(segment) => segment,
+                None => {
+                    missed = true;
+                    self.load(segment_key, file_size).await?
+                }
+            };
+            segments.push(segment);
+        }
+        if missed {
+            self.read_ahead(path, file_size, last_segment);
+        }
+
+        let offset = (position - first_segment * self.segment_size) as usize;
+        if segments.len() == 1 {
+            return Ok(segments[0].slice(offset..offset + len));
+        }
+        let mut data = BytesMut::with_capacity(len);
+        let mut offset = offset;
+        for segment in segments {
+            let take = (segment.len() - offset).min(len - data.len());
+            data.extend_from_slice(&segment[offset..offset + take]);
+            offset = 0;
+        }
+        Ok(data.freeze())
+    }
+
+    async fn load(&self, segment_key: SegmentKey, file_size: u64) -> io::Result<Bytes> {
+        let start = segment_key.1 * self.segment_size;
+        let len = self.segment_size.min(file_size - start) as usize;
+        match self.provider.read(&segment_key.0, start, len).await {
+            Ok(data) => {
+                self.insert(segment_key, data.clone());
+                Ok(data)
+            }
+            Err(e) => {
+                self.inner.lock().loading.remove(&segment_key);
+                Err(e)
+            }
+        }
+    }
+
+    fn insert(&self, segment_key: SegmentKey, data: Bytes) {
+        let mut inner = self.inner.lock();
+        inner.loading.remove(&segment_key);
+        if data.len() > self.capacity || inner.segments.contains_key(&segment_key) {
+            return;
+        }
+        while inner.size + data.len() > self.capacity {
+            let Some((_, evicted)) = inner.access_order.pop_first() else {
+                break;
+            };
+            if let Some(segment) = inner.segments.remove(&evicted) {
+                inner.size -= segment.data.len();
+            }
+        }
+        inner.access_counter += 1;
+        let last_access = inner.access_counter;
+        inner.size += data.len();
+        inner.access_order.insert(last_access, segment_key.clone());
+        inner
+            .segments
+            .insert(segment_key, CachedSegment { data, last_access });
+    }
+
+    fn read_ahead(self: &Arc<Self>, path: &str, file_size: u64, last_segment: u64) {
+        for index in last_segment + 1..=last_segment + self.read_ahead_segments {
+            if index * self.segment_size >= file_size {
+                break;
+            }
+            let segment_key = (path.to_string(), index);
+            {
+                let mut inner = self.inner.lock();
+                if inner.segments.contains_key(&segment_key)
+                    || !inner.loading.insert(segment_key.clone())
+                {
+                    continue;
+                }
+            }
+            let cache = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = cache.load(segment_key.clone(), file_size).await {
+                    warn!(
+                        "read ahead segment {} of {} failed: {}",
+                        segment_key.1, segment_key.0, e
+                    );
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::tiered::provider::BoxFuture;
+
+    #[derive(Default)]
+    struct CountingProvider {
+        files: HashMap<String, Bytes>,
+        reads: AtomicUsize,
+    }
+
+    impl TieredStorageProvider for CountingProvider {
+        fn write<'a>(&'a self, _path: &'a str, _data: Bytes) -> BoxFuture<'a, io::Result<()>> {
+            Box::pin(async { Ok(()) })
+        }
+
+        fn read<'a>(
+            &'a self,
+            path: &'a str,
+            position: u64,
+            len: usize,
+        ) -> BoxFuture<'a, io::Result<Bytes>> {
+            self.reads.fetch_add(1, Ordering::SeqCst);
+            let data = self.files[path].slice(position as usize..position as usize + len);
+            Box::pin(async move { Ok(data) })
+        }
+
+        fn delete<'a>(&'a self, _path: &'a str) -> BoxFuture<'a, io::Result<()>> {
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    #[tokio::test]
+    async fn reads_segments_once_and_ahead() {
+        let file = Bytes::from((0..400u32).map(|i| i as u8).collect::<Vec<_>>());
+        let provider = Arc::new(CountingProvider {
+            files: HashMap::from([("commitlog/0".to_string(), file.clone())]),
+            ..Default::default()
+        });
+        let cache = Arc::new(TieredReadCache::new(
+            provider.clone(),
+            &MessageStoreConfig {
+                tiered_read_cache_size: 200,
+                tiered_read_cache_segment_size: 100,
+                tiered_read_ahead_segments: 1,
+                ..Default::default()
+            },
+        ));
+        let reads = || provider.reads.load(Ordering::SeqCst);
+        let cache = &cache;
+        let read = move |position: u64, len: usize| cache.read("commitlog/0", 400, position, len);
+
+        assert_eq!(read(0, 50).await.unwrap(), file.slice(0..50));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        // the first segment and the one read ahead
+        assert_eq!(reads(), 2);
+
+        assert_eq!(read(50, 100).await.unwrap(), file.slice(50..150));
+        assert_eq!(reads(), 2);
+
+        // the third segment and the fourth read ahead drop the first two
+        assert_eq!(read(250, 10).await.unwrap(), file.slice(250..260));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(reads(), 4);
+        assert_eq!(read(390, 10).await.unwrap(), file.slice(390..400));
+        assert_eq!(reads(), 4);
+        assert_eq!(read(0, 10).await.unwrap(), file.slice(0..10));
+        assert_eq!(reads(), 5);
+
+        assert!(read(395, 10).await.is_err());
+    }
+}
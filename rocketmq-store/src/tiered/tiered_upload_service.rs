@@ -0,0 +1,204 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use bytes::Bytes;
+use rocketmq_common::TimeUtils::get_current_millis;
+use tracing::info;
+use tracing::warn;
+
+use crate::base::retention_guard::RetentionGuard;
+use crate::config::message_store_config::MessageStoreConfig;
+use crate::tiered::provider::TieredStorageProvider;
+use crate::tiered::tiered_metadata::TieredMetadata;
+
+/// The files of `dir` but its last one, which is still written. A directory that cannot be read
+/// has no sealed files.
+fn sealed_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let offset = entry.file_name().to_str()?.parse::<u64>().ok()?;
+            Some((offset, entry.path()))
+        })
+        .collect::<Vec<_>>();
+    files.sort_unstable_by_key(|(offset, _)| *offset);
+    files.pop();
+    files.into_iter().map(|(_, path)| path).collect()
+}
+
+fn sub_dirs(dir: &Path) -> Vec<PathBuf> {
+    std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .map(|entry| entry.path())
+                .filter(|path| path.is_dir())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Uploads the sealed commit log and consume queue files to the tier every
+/// `tiered_upload_interval_millis`, and deletes the files kept there longer than
+/// `tiered_store_file_reserved_time` hours.
+///
+/// As the [`RetentionGuard`] of the local store it keeps the files that are not uploaded yet.
+pub(crate) struct TieredUploadService {
+    provider: Arc<dyn TieredStorageProvider>,
+    metadata: Arc<TieredMetadata>,
+    message_store_config: Arc<MessageStoreConfig>,
+}
+
+impl TieredUploadService {
+    pub(crate) fn new(
+        provider: Arc<dyn TieredStorageProvider>,
+        metadata: Arc<TieredMetadata>,
+        message_store_config: Arc<MessageStoreConfig>,
+    ) -> Self {
+        Self {
+            provider,
+            metadata,
+            message_store_config,
+        }
+    }
+
+    pub(crate) fn start(self: Arc<Self>) {
+        let interval = Duration::from_millis(
+            self.message_store_config
+                .tiered_upload_interval_millis
+                .max(1),
+        );
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            loop {
+                interval.tick().await;
+                self.upload_sealed_files().await;
+                self.delete_expired_files().await;
+            }
+        });
+    }
+
+    fn reserved_millis(&self) -> u64 {
+        self.message_store_config.tiered_store_file_reserved_time * 60 * 60 * 1000
+    }
+
+    /// Whether the local file is recent enough to be kept in the tier.
+    fn within_reserved_time(&self, file: &Path) -> bool {
+        let reserved_millis = self.reserved_millis();
+        if reserved_millis == 0 {
+            return true;
+        }
+        std::fs::metadata(file)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .map_or(true, |age| (age.as_millis() as u64) < reserved_millis)
+    }
+
+    fn sealed_local_files(&self) -> Vec<PathBuf> {
+        let mut files = sealed_files(self.metadata.commit_log_dir());
+        for topic_dir in sub_dirs(self.metadata.consume_queue_dir()) {
+            for queue_dir in sub_dirs(&topic_dir) {
+                files.extend(sealed_files(&queue_dir));
+            }
+        }
+        files
+    }
+
+    pub(crate) async fn upload_sealed_files(&self) {
+        let mut uploaded = false;
+        for file in self.sealed_local_files() {
+            let Some(key) = self.metadata.tier_key(&file) else {
+                continue;
+            };
+            if self.metadata.is_uploaded(&key) || !self.within_reserved_time(&file) {
+                continue;
+            }
+            // the file may be deleted in the meantime
+            let data = match tokio::fs::read(&file).await {
+                Ok(data) => Bytes::from(data),
+                Err(e) => {
+                    warn!(
+                        "read {} for the tiered storage failed: {}",
+                        file.display(),
+                        e
+                    );
+                    continue;
+                }
+            };
+            let size = data.len() as u64;
+            if let Err(e) = self.provider.write(&key, data).await {
+                warn!("upload {} to the tiered storage failed: {}", key, e);
+                break;
+            }
+            info!("uploaded {} to the tiered storage, {} bytes", key, size);
+            self.metadata.record_upload(key, size);
+            uploaded = true;
+        }
+        if uploaded {
+            self.persist_metadata();
+        }
+    }
+
+    pub(crate) async fn delete_expired_files(&self) {
+        let reserved_millis = self.reserved_millis();
+        if reserved_millis == 0 {
+            return;
+        }
+        let expired = self
+            .metadata
+            .uploaded_before(get_current_millis().saturating_sub(reserved_millis));
+        if expired.is_empty() {
+            return;
+        }
+        for key in expired {
+            match self.provider.delete(&key).await {
+                Ok(()) => {
+                    info!("deleted expired {} from the tiered storage", key);
+                    self.metadata.remove(&key);
+                }
+                Err(e) => warn!("delete {} from the tiered storage failed: {}", key, e),
+            }
+        }
+        self.persist_metadata();
+    }
+
+    fn persist_metadata(&self) {
+        if let Err(e) = self.metadata.persist() {
+            warn!("persist tiered metadata failed: {}", e);
+        }
+    }
+}
+
+impl RetentionGuard for TieredUploadService {
+    fn can_delete(&self, file_path: &str) -> bool {
+        let file = Path::new(file_path);
+        match self.metadata.tier_key(file) {
+            // a file out of the reserved time is never uploaded
+            Some(key) => self.metadata.is_uploaded(&key) || !self.within_reserved_time(file),
+            None => true,
+        }
+    }
+}
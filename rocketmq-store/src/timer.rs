@@ -15,4 +15,8 @@
  * limitations under the License.
  */
 
+pub mod slot;
+pub mod timer_checkpoint;
+pub mod timer_log;
 pub mod timer_message_store;
+pub mod timer_wheel;
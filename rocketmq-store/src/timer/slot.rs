@@ -0,0 +1,75 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use bytes::Buf;
+use bytes::BufMut;
+
+/// One precision unit of the timer wheel.
+///
+/// The timer log units of the messages due in that unit form a list, linked backwards from
+/// `last_pos` to `first_pos` through the previous position stored in every unit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Slot {
+    pub time_ms: i64,
+    pub first_pos: i64,
+    pub last_pos: i64,
+    pub num: i32,
+    pub magic: i32,
+}
+
+impl Slot {
+    pub const SIZE: usize = 8 + 8 + 8 + 4 + 4;
+
+    pub fn new(time_ms: i64, first_pos: i64, last_pos: i64) -> Self {
+        Self::with_num(time_ms, first_pos, last_pos, 0, 0)
+    }
+
+    pub fn with_num(time_ms: i64, first_pos: i64, last_pos: i64, num: i32, magic: i32) -> Self {
+        Self {
+            time_ms,
+            first_pos,
+            last_pos,
+            num,
+            magic,
+        }
+    }
+
+    /// A slot nothing was put into.
+    pub fn empty() -> Self {
+        Self::new(-1, -1, -1)
+    }
+
+    pub fn encode(&self) -> [u8; Self::SIZE] {
+        let mut buffer = [0u8; Self::SIZE];
+        let mut writer = &mut buffer[..];
+        writer.put_i64(self.time_ms);
+        writer.put_i64(self.first_pos);
+        writer.put_i64(self.last_pos);
+        writer.put_i32(self.num);
+        writer.put_i32(self.magic);
+        buffer
+    }
+
+    pub fn decode(mut buffer: &[u8]) -> Self {
+        Self {
+            time_ms: buffer.get_i64(),
+            first_pos: buffer.get_i64(),
+            last_pos: buffer.get_i64(),
+            num: buffer.get_i32(),
+            magic: buffer.get_i32(),
+        }
+    }
+}
@@ -0,0 +1,165 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::fs::OpenOptions;
+use std::path::Path;
+use std::sync::atomic::AtomicI64;
+use std::sync::atomic::Ordering;
+
+use memmap2::MmapMut;
+use rocketmq_common::UtilAll::ensure_dir_ok;
+
+use crate::log_file::mapped_file::default_mapped_file_impl::OS_PAGE_SIZE;
+
+/// Progress of the timer message store, persisted every flush so a restart knows from where to
+/// recover the timer log and which timer messages are already in it.
+pub struct TimerCheckpoint {
+    mmap: parking_lot::Mutex<MmapMut>,
+    last_read_time_ms: AtomicI64,
+    last_timer_log_flush_pos: AtomicI64,
+    last_timer_queue_offset: AtomicI64,
+    master_timer_queue_offset: AtomicI64,
+}
+
+impl TimerCheckpoint {
+    pub fn new<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        ensure_dir_ok(path.as_ref().parent().unwrap().to_str().unwrap());
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path.as_ref())?;
+        file.set_len(OS_PAGE_SIZE)?;
+        let mmap = unsafe { MmapMut::map_mut(&file)? };
+        let read =
+            |index: usize| i64::from_be_bytes(mmap[index * 8..index * 8 + 8].try_into().unwrap());
+        let checkpoint = Self {
+            last_read_time_ms: AtomicI64::new(read(0)),
+            last_timer_log_flush_pos: AtomicI64::new(read(1)),
+            last_timer_queue_offset: AtomicI64::new(read(2)),
+            master_timer_queue_offset: AtomicI64::new(read(3)),
+            mmap: parking_lot::Mutex::new(mmap),
+        };
+        Ok(checkpoint)
+    }
+
+    pub fn flush(&self) -> std::io::Result<()> {
+        let mut mmap = self.mmap.lock();
+        let values = [
+            self.last_read_time_ms(),
+            self.last_timer_log_flush_pos(),
+            self.last_timer_queue_offset(),
+            self.master_timer_queue_offset(),
+        ];
+        for (index, value) in values.iter().enumerate() {
+            mmap[index * 8..index * 8 + 8].copy_from_slice(&value.to_be_bytes());
+        }
+        mmap.flush()
+    }
+
+    pub fn last_read_time_ms(&self) -> i64 {
+        self.last_read_time_ms.load(Ordering::Relaxed)
+    }
+
+    pub fn set_last_read_time_ms(&self, last_read_time_ms: i64) {
+        self.last_read_time_ms
+            .store(last_read_time_ms, Ordering::Relaxed);
+    }
+
+    pub fn last_timer_log_flush_pos(&self) -> i64 {
+        self.last_timer_log_flush_pos.load(Ordering::Relaxed)
+    }
+
+    pub fn set_last_timer_log_flush_pos(&self, last_timer_log_flush_pos: i64) {
+        self.last_timer_log_flush_pos
+            .store(last_timer_log_flush_pos, Ordering::Relaxed);
+    }
+
+    pub fn last_timer_queue_offset(&self) -> i64 {
+        self.last_timer_queue_offset.load(Ordering::Relaxed)
+    }
+
+    pub fn set_last_timer_queue_offset(&self, last_timer_queue_offset: i64) {
+        self.last_timer_queue_offset
+            .store(last_timer_queue_offset, Ordering::Relaxed);
+    }
+
+    pub fn master_timer_queue_offset(&self) -> i64 {
+        self.master_timer_queue_offset.load(Ordering::Relaxed)
+    }
+
+    pub fn set_master_timer_queue_offset(&self, master_timer_queue_offset: i64) {
+        self.master_timer_queue_offset
+            .store(master_timer_queue_offset, Ordering::Relaxed);
+    }
+
+    /// Encodes the progress in the layout of the checkpoint file, for brokers of the same group.
+    pub fn encode(&self) -> Vec<u8> {
+        [
+            self.last_read_time_ms(),
+            self.last_timer_log_flush_pos(),
+            self.last_timer_queue_offset(),
+            self.master_timer_queue_offset(),
+        ]
+        .iter()
+        .flat_map(|value| value.to_be_bytes())
+        .collect()
+    }
+
+    /// Takes over the dequeue progress from the [`encode`](Self::encode)d checkpoint of the
+    /// broker that acted as master meanwhile, so that timer messages it delivered are not
+    /// delivered again. Returns `false` if `encoded` is not a checkpoint.
+    pub fn apply_master_progress(&self, encoded: &[u8]) -> bool {
+        if encoded.len() < 32 {
+            return false;
+        }
+        let read = |index: usize| {
+            i64::from_be_bytes(encoded[index * 8..index * 8 + 8].try_into().unwrap())
+        };
+        self.set_last_read_time_ms(read(0));
+        self.set_master_timer_queue_offset(read(3));
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applies_the_progress_of_the_acting_master() {
+        let dir = std::env::temp_dir().join(format!(
+            "timer_checkpoint_test_{}",
+            rocketmq_common::TimeUtils::get_current_nano()
+        ));
+        let master = TimerCheckpoint::new(dir.join("master")).unwrap();
+        master.set_last_read_time_ms(100);
+        master.set_last_timer_log_flush_pos(200);
+        master.set_master_timer_queue_offset(300);
+        let encoded = master.encode();
+        assert_eq!(encoded.len(), 32);
+
+        let local = TimerCheckpoint::new(dir.join("local")).unwrap();
+        local.set_last_timer_log_flush_pos(10);
+        assert!(local.apply_master_progress(&encoded));
+        assert_eq!(local.last_read_time_ms(), 100);
+        assert_eq!(local.master_timer_queue_offset(), 300);
+        assert_eq!(local.last_timer_log_flush_pos(), 10);
+        assert!(!local.apply_master_progress(&encoded[..8]));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
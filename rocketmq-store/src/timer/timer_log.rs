@@ -0,0 +1,210 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use bytes::Buf;
+use bytes::BufMut;
+use tracing::error;
+
+use crate::consume_queue::mapped_file_queue::MappedFileQueue;
+use crate::log_file::commit_log;
+use crate::log_file::mapped_file::MappedFile;
+
+/// Size of one unit of the timer log.
+pub const UNIT_SIZE: i32 = 4 // size
+    + 8 // prev pos
+    + 4 // magic
+    + 8 // curr write time
+    + 4 // delayed time
+    + 8 // offset of the message in the commit log
+    + 4 // size of the message in the commit log
+    + 4 // hash code of the real topic
+    + 8; // reserved
+/// The room a file keeps for the blank marking its end.
+pub const MIN_BLANK_LEN: i32 = 4 + 8 + 4;
+pub const BLANK_MAGIC_CODE: i32 = commit_log::BLANK_MAGIC_CODE;
+
+/// One timer message in the timer log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimerLogUnit {
+    /// Position of the previous unit of the same slot, `-1` for the first one.
+    pub prev_pos: i64,
+    pub magic: i32,
+    /// Write time of the timer wheel when the unit was appended.
+    pub enqueue_time: i64,
+    /// Time the unit is due, stored as an offset to `enqueue_time`.
+    pub delayed_time: i64,
+    pub offset_py: i64,
+    pub size_py: i32,
+    pub topic_hash: i32,
+}
+
+impl TimerLogUnit {
+    pub fn encode(&self) -> [u8; UNIT_SIZE as usize] {
+        let mut buffer = [0u8; UNIT_SIZE as usize];
+        let mut writer = &mut buffer[..];
+        writer.put_i32(UNIT_SIZE);
+        writer.put_i64(self.prev_pos);
+        writer.put_i32(self.magic);
+        writer.put_i64(self.enqueue_time);
+        writer.put_i32((self.delayed_time - self.enqueue_time) as i32);
+        writer.put_i64(self.offset_py);
+        writer.put_i32(self.size_py);
+        writer.put_i32(self.topic_hash);
+        writer.put_i64(0);
+        buffer
+    }
+
+    /// Decodes a unit, `None` when `buffer` does not start with one.
+    pub fn decode(mut buffer: &[u8]) -> Option<Self> {
+        if buffer.len() < UNIT_SIZE as usize || buffer.get_i32() != UNIT_SIZE {
+            return None;
+        }
+        let prev_pos = buffer.get_i64();
+        let magic = buffer.get_i32();
+        let enqueue_time = buffer.get_i64();
+        let delayed_time = enqueue_time + buffer.get_i32() as i64;
+        Some(Self {
+            prev_pos,
+            magic,
+            enqueue_time,
+            delayed_time,
+            offset_py: buffer.get_i64(),
+            size_py: buffer.get_i32(),
+            topic_hash: buffer.get_i32(),
+        })
+    }
+}
+
+/// Append only log of the timer messages, in the order they were put into the timer wheel.
+pub struct TimerLog {
+    mapped_file_queue: MappedFileQueue,
+    file_size: i32,
+}
+
+impl TimerLog {
+    pub fn new(store_path: String, file_size: usize) -> Self {
+        Self {
+            mapped_file_queue: MappedFileQueue::new(store_path, file_size as u64, None),
+            file_size: file_size as i32,
+        }
+    }
+
+    pub fn load(&mut self) -> bool {
+        self.mapped_file_queue.load()
+    }
+
+    /// Appends `data` and returns its position, `-1` on failure.
+    ///
+    /// Data never spans two files: the rest of a file too short for it is marked blank.
+    pub fn append(&mut self, data: &[u8]) -> i64 {
+        let Some(mut mapped_file) = self
+            .mapped_file_queue
+            .get_last_mapped_file_mut_start_offset(0, true)
+        else {
+            error!("Create mapped file1 error for timer log");
+            return -1;
+        };
+        let wrote_position = mapped_file.get_wrote_position();
+        if data.len() as i32 + MIN_BLANK_LEN > self.file_size - wrote_position {
+            let mut blank = Vec::with_capacity(MIN_BLANK_LEN as usize);
+            blank.put_i32(self.file_size - wrote_position);
+            blank.put_i64(0);
+            blank.put_i32(BLANK_MAGIC_CODE);
+            if !mapped_file.append_message_bytes(&blank) {
+                error!("Append blank error for timer log");
+                return -1;
+            }
+            mapped_file.set_wrote_position(self.file_size);
+            mapped_file = match self
+                .mapped_file_queue
+                .get_last_mapped_file_mut_start_offset(0, true)
+            {
+                Some(mapped_file) => mapped_file,
+                None => {
+                    error!("Create mapped file2 error for timer log");
+                    return -1;
+                }
+            };
+        }
+        let position =
+            mapped_file.get_file_from_offset() as i64 + mapped_file.get_wrote_position() as i64;
+        if !mapped_file.append_message_bytes(data) {
+            error!("Append error for timer log");
+            return -1;
+        }
+        position
+    }
+
+    pub fn read_unit(&self, position: i64) -> Option<TimerLogUnit> {
+        let mapped_file = self
+            .mapped_file_queue
+            .find_mapped_file_by_offset(position, false)?;
+        let bytes = mapped_file.get_bytes(
+            (position % self.file_size as i64) as usize,
+            UNIT_SIZE as usize,
+        )?;
+        TimerLogUnit::decode(&bytes)
+    }
+
+    pub fn file_size(&self) -> i32 {
+        self.file_size
+    }
+
+    pub fn mapped_file_queue(&self) -> &MappedFileQueue {
+        &self.mapped_file_queue
+    }
+
+    pub fn mapped_file_queue_mut(&mut self) -> &mut MappedFileQueue {
+        &mut self.mapped_file_queue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit(prev_pos: i64) -> TimerLogUnit {
+        TimerLogUnit {
+            prev_pos,
+            magic: 1,
+            enqueue_time: 10_000,
+            delayed_time: 15_000,
+            offset_py: 128,
+            size_py: 256,
+            topic_hash: 7,
+        }
+    }
+
+    #[test]
+    fn units_never_span_two_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_size = (UNIT_SIZE * 2 + MIN_BLANK_LEN) as usize;
+        let mut timer_log = TimerLog::new(dir.path().to_string_lossy().into_owned(), file_size);
+        assert_eq!(timer_log.append(&unit(-1).encode()), 0);
+        assert_eq!(timer_log.append(&unit(0).encode()), UNIT_SIZE as i64);
+        // the third unit does not fit beside the blank, it opens the next file
+        assert_eq!(
+            timer_log.append(&unit(UNIT_SIZE as i64).encode()),
+            file_size as i64
+        );
+        assert_eq!(timer_log.read_unit(UNIT_SIZE as i64), Some(unit(0)));
+        assert_eq!(
+            timer_log.read_unit(file_size as i64),
+            Some(unit(UNIT_SIZE as i64))
+        );
+        assert_eq!(timer_log.read_unit(UNIT_SIZE as i64 * 2), None);
+    }
+}
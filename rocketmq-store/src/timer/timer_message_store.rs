@@ -14,16 +14,51 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::io;
+use std::sync::atomic::AtomicBool;
 use std::sync::atomic::AtomicI64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
 
 use cheetah_string::CheetahString;
+use parking_lot::Mutex;
+use parking_lot::RwLock;
+use rocketmq_common::common::broker::broker_role::BrokerRole;
+use rocketmq_common::common::message::message_client_id_setter::MessageClientIDSetter;
+use rocketmq_common::common::message::message_decoder;
+use rocketmq_common::common::message::message_ext::MessageExt;
+use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
 use rocketmq_common::common::message::MessageConst;
+use rocketmq_common::common::message::MessageTrait;
 use rocketmq_common::common::system_clock::SystemClock;
+use rocketmq_common::MessageAccessor::MessageAccessor;
+use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_rust::ArcMut;
+use tokio::sync::Notify;
+use tracing::error;
+use tracing::info;
 use tracing::warn;
 
+use crate::base::message_status_enum::GetMessageStatus;
+use crate::base::message_status_enum::PutMessageStatus;
+use crate::config::message_store_config::MessageStoreConfig;
+use crate::log_file::mapped_file::MappedFile;
 use crate::log_file::MessageStore;
 use crate::message_store::default_message_store::DefaultMessageStore;
+use crate::store_path_config_helper::get_store_path_timer_log;
+use crate::store_path_config_helper::get_timer_check_path;
+use crate::store_path_config_helper::get_timer_wheel_path;
+use crate::timer::timer_checkpoint::TimerCheckpoint;
+use crate::timer::timer_log::TimerLog;
+use crate::timer::timer_log::TimerLogUnit;
+use crate::timer::timer_log::BLANK_MAGIC_CODE;
+use crate::timer::timer_log::MIN_BLANK_LEN;
+use crate::timer::timer_log::UNIT_SIZE;
+use crate::timer::timer_wheel::TimerWheel;
 
 pub const TIMER_TOPIC: &str = concat!("rmq_sys_", "wheel_timer");
 pub const TIMER_OUT_MS: &str = MessageConst::PROPERTY_TIMER_OUT_MS;
@@ -46,39 +81,534 @@ pub const MAGIC_DEFAULT: i32 = 1;
 pub const MAGIC_ROLL: i32 = 1 << 1;
 pub const MAGIC_DELETE: i32 = 1 << 2;
 
+/// Timer messages read from the timer topic at once.
+const ENQUEUE_BATCH_SIZE: i32 = 32;
+
+/// Delivers the messages sent with `TIMER_DELIVER_MS`, `TIMER_DELAY_SEC` or `TIMER_DELAY_MS` at
+/// their time.
+///
+/// Such messages are parked in the timer topic when stored. The enqueue task appends every one of
+/// them to the timer log and links it into the slot of the timer wheel for its time. The dequeue
+/// task walks the wheel one slot after the other and puts the messages of every due slot back to
+/// their real topic. Messages due beyond the roll window are put back to the timer topic instead,
+/// to be rolled into a later round of the wheel, and a message carrying `TIMER_DEL_UNIQKEY`
+/// cancels the message of that key due in the same slot.
+///
+/// Clones share their state.
+#[derive(Clone)]
 pub struct TimerMessageStore {
-    pub curr_read_time_ms: AtomicI64,
-    pub curr_queue_offset: AtomicI64,
+    pub curr_read_time_ms: Arc<AtomicI64>,
+    pub curr_queue_offset: Arc<AtomicI64>,
     pub default_message_store: Option<ArcMut<DefaultMessageStore>>,
+    state: Option<Arc<TimerState>>,
 }
 
-impl Clone for TimerMessageStore {
-    fn clone(&self) -> Self {
-        Self {
-            curr_read_time_ms: AtomicI64::new(
-                self.curr_read_time_ms
-                    .load(std::sync::atomic::Ordering::Relaxed),
-            ),
-            curr_queue_offset: AtomicI64::new(
-                self.curr_queue_offset
-                    .load(std::sync::atomic::Ordering::Relaxed),
-            ),
-            default_message_store: self.default_message_store.clone(),
-        }
-    }
+struct TimerState {
+    message_store_config: Arc<MessageStoreConfig>,
+    timer_log: Mutex<TimerLog>,
+    timer_wheel: RwLock<TimerWheel>,
+    timer_checkpoint: TimerCheckpoint,
+    slots_total: i32,
+    precision_ms: i64,
+    curr_write_time_ms: AtomicI64,
+    last_enqueue_but_expired_time: AtomicI64,
+    last_enqueue_but_expired_store_time: AtomicI64,
+    enqueue_tps: TpsCounter,
+    dequeue_tps: TpsCounter,
+    running: AtomicBool,
+    shutdown: Notify,
 }
 
 impl TimerMessageStore {
     pub fn load(&mut self) -> bool {
+        let Some(state) = self.state.clone() else {
+            return true;
+        };
+        if !state.timer_log.lock().load() {
+            return false;
+        }
+        self.recover(&state);
+        info!(
+            "TimerMessageStore loaded, currReadTimeMs: {}, currQueueOffset: {}",
+            self.curr_read_time_ms.load(Ordering::Relaxed),
+            self.curr_queue_offset.load(Ordering::Relaxed)
+        );
         true
     }
 
+    /// Rebuilds the wheel from the timer log units not flushed with it, and finds the timer
+    /// messages to enqueue and the slot to dequeue next.
+    fn recover(&self, state: &TimerState) {
+        let mut timer_wheel = state.timer_wheel.write();
+        let mut timer_log = state.timer_log.lock();
+        let mut begin_offset = state.timer_checkpoint.last_timer_log_flush_pos();
+        if let Some(last_file) = timer_log.mapped_file_queue().get_last_mapped_file() {
+            begin_offset -= last_file.get_file_size() as i64;
+        }
+        let (process_offset, last_unit) =
+            recover_and_revise(&timer_log, &mut timer_wheel, begin_offset.max(0));
+        let mapped_file_queue = timer_log.mapped_file_queue_mut();
+        mapped_file_queue.set_flushed_where(process_offset);
+        mapped_file_queue.set_committed_where(process_offset);
+        mapped_file_queue.truncate_dirty_files(process_offset);
+        drop(timer_log);
+        drop(timer_wheel);
+
+        let topic = CheetahString::from_static_str(TIMER_TOPIC);
+        let mut queue_offset = state.timer_checkpoint.last_timer_queue_offset();
+        if let Some(store) = self.default_message_store.as_ref() {
+            if let Some(msg_ext) = last_unit.and_then(|unit| {
+                store.look_message_by_offset_with_size(unit.offset_py, unit.size_py)
+            }) {
+                if msg_ext.topic().as_str() == TIMER_TOPIC {
+                    queue_offset = msg_ext.queue_offset + 1;
+                }
+            }
+            queue_offset = queue_offset
+                .min(store.get_max_offset_in_queue(&topic, 0))
+                .max(store.get_min_offset_in_queue(&topic, 0));
+        }
+        self.curr_queue_offset
+            .store(queue_offset, Ordering::Relaxed);
+
+        let now = format_time_ms(get_current_millis() as i64, state.precision_ms);
+        let min_read_time_ms =
+            now - (state.slots_total - TIMER_BLANK_SLOTS) as i64 * state.precision_ms;
+        self.curr_read_time_ms.store(
+            state
+                .timer_checkpoint
+                .last_read_time_ms()
+                .max(min_read_time_ms),
+            Ordering::Relaxed,
+        );
+        state.curr_write_time_ms.store(now, Ordering::Relaxed);
+    }
+
     pub fn start(&mut self) {
-        warn!("TimerMessageStore start unimplemented, do nothing");
+        let Some(state) = self.state.clone() else {
+            return;
+        };
+        if state.running.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let idle = Duration::from_millis((100 * state.precision_ms / 1000).max(1) as u64);
+
+        let this = self.clone();
+        let enqueue_state = state.clone();
+        tokio::spawn(async move {
+            while enqueue_state.running.load(Ordering::Acquire) {
+                if this.enqueue().await {
+                    continue;
+                }
+                tokio::select! {
+                    _ = tokio::time::sleep(idle) => {}
+                    _ = enqueue_state.shutdown.notified() => {}
+                }
+            }
+            info!("TimerEnqueueService end");
+        });
+
+        let this = self.clone();
+        let dequeue_state = state.clone();
+        tokio::spawn(async move {
+            while dequeue_state.running.load(Ordering::Acquire) {
+                if this.dequeue().await != -1 {
+                    continue;
+                }
+                tokio::select! {
+                    _ = tokio::time::sleep(idle) => {}
+                    _ = dequeue_state.shutdown.notified() => {}
+                }
+            }
+            info!("TimerDequeueService end");
+        });
+
+        let this = self.clone();
+        let interval =
+            Duration::from_millis(state.message_store_config.timer_flush_interval_ms.max(1) as u64);
+        tokio::spawn(async move {
+            while state.running.load(Ordering::Acquire) {
+                tokio::select! {
+                    _ = tokio::time::sleep(interval) => {}
+                    _ = state.shutdown.notified() => {}
+                }
+                this.flush();
+            }
+            info!("TimerFlushService end");
+        });
+        info!("TimerMessageStore started");
     }
 
-    pub fn is_reject(&self, _deliver_ms: u64) -> bool {
-        false
+    /// Moves the timer messages stored after the last run into the wheel.
+    ///
+    /// Returns whether messages were found, so that the caller runs it again right away.
+    async fn enqueue(&self) -> bool {
+        let (Some(state), Some(store)) = (self.state.as_ref(), self.default_message_store.as_ref())
+        else {
+            return false;
+        };
+        self.maybe_move_write_time(state);
+        if state.message_store_config.timer_stop_enqueue {
+            return false;
+        }
+        let topic = CheetahString::from_static_str(TIMER_TOPIC);
+        let offset = self.curr_queue_offset.load(Ordering::Relaxed);
+        let Some(get_message_result) = store
+            .get_message(&topic, &topic, 0, offset, ENQUEUE_BATCH_SIZE, None)
+            .await
+        else {
+            return false;
+        };
+        match get_message_result.status() {
+            Some(GetMessageStatus::Found) => {}
+            Some(
+                GetMessageStatus::OffsetTooSmall
+                | GetMessageStatus::OffsetOverflowBadly
+                | GetMessageStatus::OffsetFoundNull
+                | GetMessageStatus::OffsetReset,
+            ) => {
+                let next_begin_offset = get_message_result.next_begin_offset();
+                if next_begin_offset != offset {
+                    warn!(
+                        "TimerMessageStore, offset {} of the timer queue is illegal, correct it \
+                         to {}",
+                        offset, next_begin_offset
+                    );
+                    self.curr_queue_offset
+                        .store(next_begin_offset, Ordering::Relaxed);
+                }
+                return false;
+            }
+            _ => return false,
+        }
+        let next_begin_offset = get_message_result.next_begin_offset();
+        let messages = get_message_result
+            .message_mapped_list()
+            .iter()
+            .filter_map(|buffer| buffer.get_bytes())
+            .filter_map(|mut bytes| {
+                message_decoder::decode(&mut bytes, true, false, false, false, false)
+            })
+            .collect::<Vec<_>>();
+        drop(get_message_result);
+
+        for msg_ext in &messages {
+            if !state.running.load(Ordering::Acquire) {
+                return false;
+            }
+            self.maybe_move_write_time(state);
+            let Some(delayed_time) =
+                property(msg_ext, TIMER_OUT_MS).and_then(|time| time.parse::<i64>().ok())
+            else {
+                warn!(
+                    "Timer message without {}, skip it, msgId: {}",
+                    TIMER_OUT_MS, msg_ext.msg_id
+                );
+                self.curr_queue_offset
+                    .store(msg_ext.queue_offset + 1, Ordering::Relaxed);
+                continue;
+            };
+            let now = get_current_millis() as i64;
+            state
+                .last_enqueue_but_expired_time
+                .store(now, Ordering::Relaxed);
+            state
+                .last_enqueue_but_expired_store_time
+                .store(msg_ext.store_timestamp, Ordering::Relaxed);
+            if should_running_dequeue(&state.message_store_config)
+                && delayed_time < state.curr_write_time_ms.load(Ordering::Relaxed)
+            {
+                // already due, there is no slot left to put it into
+                if !self
+                    .put_until_done(state, convert_message(msg_ext, now, false), false)
+                    .await
+                {
+                    return false;
+                }
+            } else if !self.do_enqueue(
+                state,
+                msg_ext.commit_log_offset,
+                msg_ext.store_size,
+                delayed_time,
+                msg_ext,
+            ) && !state.message_store_config.timer_skip_unknown_error
+            {
+                return false;
+            }
+            self.curr_queue_offset
+                .store(msg_ext.queue_offset + 1, Ordering::Relaxed);
+        }
+        if self.curr_queue_offset.load(Ordering::Relaxed) < next_begin_offset {
+            self.curr_queue_offset
+                .store(next_begin_offset, Ordering::Relaxed);
+        }
+        !messages.is_empty()
+    }
+
+    /// Appends a timer message to the timer log and links it into the slot it is due in, or
+    /// into the last slot of the roll window when it is due later.
+    fn do_enqueue(
+        &self,
+        state: &TimerState,
+        offset_py: i64,
+        size_py: i32,
+        delayed_time: i64,
+        msg_ext: &MessageExt,
+    ) -> bool {
+        let curr_write_time_ms = state.curr_write_time_ms.load(Ordering::Relaxed);
+        let roll_window_ms =
+            state.message_store_config.timer_roll_window_slot as i64 * state.precision_ms;
+        let mut delayed_time = delayed_time;
+        let mut magic = MAGIC_DEFAULT;
+        if delayed_time - curr_write_time_ms >= roll_window_ms {
+            magic |= MAGIC_ROLL;
+            // give the message enough time to be rolled before it is due
+            delayed_time =
+                if delayed_time - curr_write_time_ms - roll_window_ms < roll_window_ms / 3 {
+                    curr_write_time_ms + roll_window_ms / 2
+                } else {
+                    curr_write_time_ms + roll_window_ms
+                };
+        }
+        let is_delete = property(msg_ext, TIMER_DELETE_UNIQUE_KEY).is_some();
+        if is_delete {
+            magic |= MAGIC_DELETE;
+        }
+        let real_topic = property(msg_ext, MessageConst::PROPERTY_REAL_TOPIC).unwrap_or_default();
+
+        let mut timer_wheel = state.timer_wheel.write();
+        let slot = timer_wheel.get_slot(delayed_time);
+        let unit = TimerLogUnit {
+            prev_pos: slot.last_pos,
+            magic,
+            enqueue_time: curr_write_time_ms,
+            delayed_time,
+            offset_py,
+            size_py,
+            topic_hash: java_hash_code(real_topic.as_str()),
+        };
+        let position = state.timer_log.lock().append(&unit.encode());
+        if position == -1 {
+            return false;
+        }
+        timer_wheel.put_slot(
+            delayed_time,
+            if slot.first_pos == -1 {
+                position
+            } else {
+                slot.first_pos
+            },
+            position,
+            if is_delete {
+                slot.num - 1
+            } else {
+                slot.num + 1
+            },
+            slot.magic,
+        );
+        state.enqueue_tps.inc();
+        true
+    }
+
+    /// Delivers the messages of the slot to read next.
+    ///
+    /// Returns `-1` when there is nothing to read yet, `0` for an empty slot and `1` otherwise.
+    async fn dequeue(&self) -> i32 {
+        let (Some(state), Some(store)) = (self.state.as_ref(), self.default_message_store.as_ref())
+        else {
+            return -1;
+        };
+        if state.message_store_config.timer_stop_dequeue
+            || !should_running_dequeue(&state.message_store_config)
+        {
+            return -1;
+        }
+        let curr_read_time_ms = self.curr_read_time_ms.load(Ordering::Relaxed);
+        if curr_read_time_ms >= state.curr_write_time_ms.load(Ordering::Relaxed) {
+            return -1;
+        }
+        let slot = state.timer_wheel.read().get_slot(curr_read_time_ms);
+        if slot.time_ms == -1 {
+            self.move_read_time(state);
+            return 0;
+        }
+
+        let mut delete_units = Vec::new();
+        let mut normal_units = VecDeque::new();
+        {
+            let timer_log = state.timer_log.lock();
+            let mut position = slot.last_pos;
+            while position != -1 {
+                let Some(unit) = timer_log.read_unit(position) else {
+                    error!(
+                        "Timer log unit at {} of slot {} is broken",
+                        position, curr_read_time_ms
+                    );
+                    break;
+                };
+                if need_delete(unit.magic) && !need_roll(unit.magic) {
+                    delete_units.push(unit);
+                } else {
+                    normal_units.push_front(unit);
+                }
+                position = unit.prev_pos;
+            }
+        }
+        if delete_units.is_empty() && normal_units.is_empty() {
+            warn!("Timer message get the null slot [{}]", curr_read_time_ms);
+        }
+
+        let delete_keys = delete_units
+            .iter()
+            .filter_map(|unit| store.look_message_by_offset_with_size(unit.offset_py, unit.size_py))
+            .filter_map(|msg_ext| property(&msg_ext, TIMER_DELETE_UNIQUE_KEY))
+            .collect::<HashSet<_>>();
+        for unit in normal_units {
+            let Some(msg_ext) =
+                store.look_message_by_offset_with_size(unit.offset_py, unit.size_py)
+            else {
+                warn!(
+                    "Timer message of slot {} is missing in the commit log, offset: {}",
+                    curr_read_time_ms, unit.offset_py
+                );
+                continue;
+            };
+            if !delete_keys.is_empty() {
+                if let Some(uniq_key) = MessageClientIDSetter::get_uniq_id(&msg_ext) {
+                    if delete_keys.contains(&build_delete_key(&real_topic(&msg_ext), &uniq_key)) {
+                        continue;
+                    }
+                }
+            }
+            let roll = need_roll(unit.magic);
+            if !self
+                .put_until_done(
+                    state,
+                    convert_message(&msg_ext, unit.enqueue_time, roll),
+                    roll,
+                )
+                .await
+            {
+                // read the slot again once running
+                return -1;
+            }
+        }
+        self.move_read_time(state);
+        1
+    }
+
+    /// Puts a due message back, retrying as long as the store asks for it.
+    ///
+    /// Returns `false` when the store stopped before the message was put.
+    async fn put_until_done(
+        &self,
+        state: &TimerState,
+        msg: MessageExtBrokerInner,
+        roll: bool,
+    ) -> bool {
+        let retry_interval = Duration::from_millis((500 * state.precision_ms / 1000) as u64);
+        while self.do_put(copy_message(&msg), roll).await == PUT_NEED_RETRY {
+            if !state.running.load(Ordering::Acquire)
+                || !should_running_dequeue(&state.message_store_config)
+            {
+                return false;
+            }
+            tokio::time::sleep(retry_interval).await;
+        }
+        state.dequeue_tps.inc();
+        true
+    }
+
+    async fn do_put(&self, msg: MessageExtBrokerInner, roll: bool) -> i32 {
+        if !roll && msg.property(TIMER_DELETE_UNIQUE_KEY).is_some() {
+            warn!(
+                "Trying do put delete timer msg, msgId: {}, roll: {}",
+                msg.message_ext_inner.msg_id, roll
+            );
+            return PUT_NO_RETRY;
+        }
+        let Some(mut store) = self.default_message_store.clone() else {
+            return PUT_NO_RETRY;
+        };
+        for _ in 0..3 {
+            match store
+                .put_message(copy_message(&msg))
+                .await
+                .put_message_status()
+            {
+                PutMessageStatus::PutOk => return PUT_OK,
+                PutMessageStatus::ServiceNotAvailable => return PUT_NEED_RETRY,
+                PutMessageStatus::MessageIllegal | PutMessageStatus::PropertiesSizeExceeded => {
+                    return PUT_NO_RETRY
+                }
+                status => {
+                    warn!(
+                        "Put timer message failed, status: {:?}, msgId: {}",
+                        status, msg.message_ext_inner.msg_id
+                    );
+                }
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        PUT_NO_RETRY
+    }
+
+    fn maybe_move_write_time(&self, state: &TimerState) {
+        state.curr_write_time_ms.fetch_max(
+            format_time_ms(get_current_millis() as i64, state.precision_ms),
+            Ordering::Relaxed,
+        );
+    }
+
+    fn move_read_time(&self, state: &TimerState) {
+        self.curr_read_time_ms
+            .fetch_add(state.precision_ms, Ordering::Relaxed);
+    }
+
+    /// Flushes the timer log and the wheel, then the checkpoint pointing into them.
+    fn flush(&self) {
+        let Some(state) = self.state.as_ref() else {
+            return;
+        };
+        let checkpoint = &state.timer_checkpoint;
+        let timer_log = state.timer_log.lock();
+        checkpoint.set_last_timer_log_flush_pos(timer_log.mapped_file_queue().get_flushed_where());
+        checkpoint.set_last_read_time_ms(self.curr_read_time_ms.load(Ordering::Relaxed));
+        let curr_queue_offset = self.curr_queue_offset.load(Ordering::Relaxed);
+        if should_running_dequeue(&state.message_store_config) {
+            checkpoint.set_master_timer_queue_offset(curr_queue_offset);
+        }
+        checkpoint.set_last_timer_queue_offset(
+            curr_queue_offset.min(checkpoint.master_timer_queue_offset()),
+        );
+        timer_log.mapped_file_queue().flush(0);
+        drop(timer_log);
+        if let Err(e) = state.timer_wheel.read().flush() {
+            error!("flush timer wheel failed: {}", e);
+        }
+        if let Err(e) = checkpoint.flush() {
+            error!("flush timer checkpoint failed: {}", e);
+        }
+    }
+
+    /// Whether a message due at `deliver_ms` is rejected because its slot is congested.
+    ///
+    /// Messages are accepted up to `timer_congest_num_each_slot` per slot, rejected from twice
+    /// that on, and rejected at random with a growing probability in between.
+    pub fn is_reject(&self, deliver_ms: u64) -> bool {
+        let Some(state) = self.state.as_ref() else {
+            return false;
+        };
+        let limit = state.message_store_config.timer_congest_num_each_slot as i64;
+        let congest_num = state.timer_wheel.read().get_num(deliver_ms as i64) as i64;
+        if congest_num <= limit {
+            return false;
+        }
+        if congest_num >= limit * 2 {
+            return true;
+        }
+        let random = (SystemClock::now() % 1000) as i64;
+        random < 1000 * (congest_num - limit) / limit.max(1)
     }
 
     pub fn get_dequeue_behind(&self) -> i64 {
@@ -86,6 +616,9 @@ impl TimerMessageStore {
     }
 
     pub fn get_dequeue_behind_millis(&self) -> i64 {
+        if self.state.is_none() {
+            return 0;
+        }
         (SystemClock::now() as i64)
             - self
                 .curr_read_time_ms
@@ -93,7 +626,17 @@ impl TimerMessageStore {
     }
 
     pub fn get_enqueue_behind_millis(&self) -> i64 {
-        unimplemented!("getEnqueueBehindMillis")
+        let Some(state) = self.state.as_ref() else {
+            return 0;
+        };
+        let now = get_current_millis() as i64;
+        if now - state.last_enqueue_but_expired_time.load(Ordering::Relaxed) < 2000 {
+            return now
+                - state
+                    .last_enqueue_but_expired_store_time
+                    .load(Ordering::Relaxed);
+        }
+        0
     }
 
     pub fn get_enqueue_behind(&self) -> i64 {
@@ -101,14 +644,14 @@ impl TimerMessageStore {
     }
 
     pub fn get_enqueue_behind_messages(&self) -> i64 {
+        let Some(store) = self.default_message_store.as_ref() else {
+            return 0;
+        };
         let temp_queue_offset = self
             .curr_queue_offset
             .load(std::sync::atomic::Ordering::Relaxed);
-        let consume_queue = self
-            .default_message_store
-            .as_ref()
-            .unwrap()
-            .find_consume_queue(&CheetahString::from_static_str(TIMER_TOPIC), 0);
+        let consume_queue =
+            store.find_consume_queue(&CheetahString::from_static_str(TIMER_TOPIC), 0);
         let max_offset_in_queue = match consume_queue {
             Some(queue) => queue.get_max_offset_in_queue(),
             None => 0,
@@ -117,41 +660,431 @@ impl TimerMessageStore {
     }
 
     pub fn get_all_congest_num(&self) -> i64 {
-        0
+        let Some(state) = self.state.as_ref() else {
+            return 0;
+        };
+        state
+            .timer_wheel
+            .read()
+            .get_all_num(self.curr_read_time_ms.load(Ordering::Relaxed))
     }
 
     pub fn get_enqueue_tps(&self) -> f32 {
-        0.0
+        self.state
+            .as_ref()
+            .map_or(0.0, |state| state.enqueue_tps.tps())
     }
 
     pub fn get_dequeue_tps(&self) -> f32 {
-        0.0
+        self.state
+            .as_ref()
+            .map_or(0.0, |state| state.dequeue_tps.tps())
     }
 
-    pub fn new(default_message_store: Option<ArcMut<DefaultMessageStore>>) -> Self {
-        Self {
-            curr_read_time_ms: AtomicI64::new(0),
-            curr_queue_offset: AtomicI64::new(0),
+    /// Opens the timer wheel and checkpoint of `default_message_store`.
+    pub fn new(default_message_store: Option<ArcMut<DefaultMessageStore>>) -> io::Result<Self> {
+        let state = match default_message_store.as_ref() {
+            Some(store) => Some(Arc::new(TimerState::new(store.message_store_config())?)),
+            None => None,
+        };
+        Ok(Self {
+            curr_read_time_ms: Arc::new(AtomicI64::new(0)),
+            curr_queue_offset: Arc::new(AtomicI64::new(0)),
             default_message_store,
-        }
+            state,
+        })
     }
 
     pub fn new_empty() -> Self {
         Self {
-            curr_read_time_ms: AtomicI64::new(0),
-            curr_queue_offset: AtomicI64::new(0),
+            curr_read_time_ms: Arc::new(AtomicI64::new(0)),
+            curr_queue_offset: Arc::new(AtomicI64::new(0)),
             default_message_store: None,
+            state: None,
         }
     }
 
+    pub fn timer_checkpoint(&self) -> Option<&TimerCheckpoint> {
+        self.state.as_ref().map(|state| &state.timer_checkpoint)
+    }
+
     pub fn set_default_message_store(
         &mut self,
         default_message_store: Option<ArcMut<DefaultMessageStore>>,
-    ) {
+    ) -> io::Result<()> {
+        if self.state.is_none() {
+            if let Some(store) = default_message_store.as_ref() {
+                self.state = Some(Arc::new(TimerState::new(store.message_store_config())?));
+            }
+        }
         self.default_message_store = default_message_store;
+        Ok(())
     }
 
     pub fn shutdown(&mut self) {
-        warn!("TimerMessageStore shutdown unimplemented, do nothing");
+        let Some(state) = self.state.clone() else {
+            return;
+        };
+        if state.running.swap(false, Ordering::AcqRel) {
+            state.shutdown.notify_waiters();
+            self.flush();
+            info!("TimerMessageStore shutdown");
+        }
+    }
+}
+
+impl TimerState {
+    fn new(message_store_config: Arc<MessageStoreConfig>) -> io::Result<Self> {
+        let root_dir = message_store_config.store_path_root_dir.as_str();
+        let precision_ms = message_store_config.timer_precision_ms.max(1);
+        let slots_total =
+            (TIMER_WHEEL_TTL_DAY as u64 * DAY_SECS as u64 * 1000 / precision_ms) as i32;
+        let timer_wheel =
+            TimerWheel::new(get_timer_wheel_path(root_dir), slots_total, precision_ms)?;
+        let timer_checkpoint = TimerCheckpoint::new(get_timer_check_path(root_dir))?;
+        let timer_log = TimerLog::new(
+            get_store_path_timer_log(root_dir),
+            message_store_config.mapped_file_size_timer_log,
+        );
+        Ok(Self {
+            timer_log: Mutex::new(timer_log),
+            timer_wheel: RwLock::new(timer_wheel),
+            timer_checkpoint,
+            slots_total,
+            precision_ms: precision_ms as i64,
+            curr_write_time_ms: AtomicI64::new(0),
+            last_enqueue_but_expired_time: AtomicI64::new(0),
+            last_enqueue_but_expired_store_time: AtomicI64::new(0),
+            enqueue_tps: TpsCounter::default(),
+            dequeue_tps: TpsCounter::default(),
+            running: AtomicBool::new(false),
+            shutdown: Notify::new(),
+            message_store_config,
+        })
+    }
+}
+
+/// Copies `msg` for another put, without the buffer it was encoded into by the last one.
+fn copy_message(msg: &MessageExtBrokerInner) -> MessageExtBrokerInner {
+    MessageExtBrokerInner {
+        message_ext_inner: msg.message_ext_inner.clone(),
+        properties_string: msg.properties_string.clone(),
+        tags_code: msg.tags_code,
+        encoded_buff: None,
+        encode_completed: false,
+        version: msg.version,
+    }
+}
+
+/// Walks the timer log from the file holding `begin_offset` and links every unit the wheel
+/// misses into its slot.
+///
+/// Returns the end of the valid units and the last of them.
+fn recover_and_revise(
+    timer_log: &TimerLog,
+    timer_wheel: &mut TimerWheel,
+    begin_offset: i64,
+) -> (i64, Option<TimerLogUnit>) {
+    let mapped_files = timer_log
+        .mapped_file_queue()
+        .get_mapped_files()
+        .read()
+        .clone();
+    if mapped_files.is_empty() {
+        return (0, None);
+    }
+    let index = mapped_files
+        .iter()
+        .rposition(|mapped_file| begin_offset >= mapped_file.get_file_from_offset() as i64)
+        .unwrap_or(0);
+    let file_size = timer_log.file_size() as usize;
+    let mut process_offset = mapped_files[index].get_file_from_offset() as i64;
+    let mut last_unit = None;
+    for mapped_file in &mapped_files[index..] {
+        let file_from_offset = mapped_file.get_file_from_offset() as i64;
+        let data = mapped_file.get_mapped_byte_buffer();
+        let mut position = 0;
+        while position + MIN_BLANK_LEN as usize <= file_size {
+            let size = i32::from_be_bytes(data[position..position + 4].try_into().unwrap());
+            let magic = i32::from_be_bytes(data[position + 12..position + 16].try_into().unwrap());
+            if magic == BLANK_MAGIC_CODE && size > 0 {
+                position += size as usize;
+                continue;
+            }
+            let Some(unit) = data
+                .get(position..position + UNIT_SIZE as usize)
+                .and_then(TimerLogUnit::decode)
+                .filter(|unit| is_magic_ok(unit.magic))
+            else {
+                break;
+            };
+            let unit_offset = file_from_offset + position as i64;
+            let slot = timer_wheel.get_slot(unit.delayed_time);
+            if unit_offset > slot.last_pos {
+                let delete = need_delete(unit.magic) && !need_roll(unit.magic);
+                timer_wheel.put_slot(
+                    unit.delayed_time,
+                    if slot.first_pos == -1 {
+                        unit_offset
+                    } else {
+                        slot.first_pos
+                    },
+                    unit_offset,
+                    if delete { slot.num - 1 } else { slot.num + 1 },
+                    slot.magic,
+                );
+            }
+            last_unit = Some(unit);
+            position += UNIT_SIZE as usize;
+        }
+        process_offset = file_from_offset + position as i64;
+        if position < file_size {
+            break;
+        }
+    }
+    info!("Recover timer log to offset {}", process_offset);
+    (process_offset, last_unit)
+}
+
+/// Builds the message put back to the store for a due timer log unit: the message for its real
+/// topic, or the message for the timer topic again when it is rolled.
+pub fn convert_message(
+    msg_ext: &MessageExt,
+    enqueue_time: i64,
+    need_roll: bool,
+) -> MessageExtBrokerInner {
+    let mut msg_ext = msg_ext.clone();
+    if enqueue_time != -1 {
+        MessageAccessor::put_property(
+            &mut msg_ext,
+            CheetahString::from_static_str(TIMER_ENQUEUE_MS),
+            CheetahString::from_string(enqueue_time.to_string()),
+        );
+    }
+    if need_roll {
+        let roll_times = property(&msg_ext, TIMER_ROLL_TIMES)
+            .and_then(|roll_times| roll_times.parse::<i32>().ok())
+            .unwrap_or(0);
+        MessageAccessor::put_property(
+            &mut msg_ext,
+            CheetahString::from_static_str(TIMER_ROLL_TIMES),
+            CheetahString::from_string((roll_times + 1).to_string()),
+        );
+    }
+    MessageAccessor::put_property(
+        &mut msg_ext,
+        CheetahString::from_static_str(TIMER_DEQUEUE_MS),
+        CheetahString::from_string(get_current_millis().to_string()),
+    );
+    let mut msg_inner = MessageExtBrokerInner {
+        tags_code: MessageExtBrokerInner::tags_string_to_tags_code(
+            msg_ext.get_tags().unwrap_or_default().as_str(),
+        ),
+        message_ext_inner: msg_ext,
+        ..MessageExtBrokerInner::default()
+    };
+    msg_inner.set_wait_store_msg_ok(false);
+    if !need_roll {
+        if let Some(real_topic) = msg_inner.property(MessageConst::PROPERTY_REAL_TOPIC) {
+            msg_inner.set_topic(real_topic);
+        }
+        if let Some(real_queue_id) = msg_inner
+            .property(MessageConst::PROPERTY_REAL_QUEUE_ID)
+            .and_then(|queue_id| queue_id.parse::<i32>().ok())
+        {
+            msg_inner.message_ext_inner.queue_id = real_queue_id;
+        }
+        // the message is due, keep it from being parked in the timer topic once more
+        for name in [
+            MessageConst::PROPERTY_REAL_TOPIC,
+            MessageConst::PROPERTY_REAL_QUEUE_ID,
+            MessageConst::PROPERTY_TIMER_DELIVER_MS,
+            MessageConst::PROPERTY_TIMER_DELAY_SEC,
+            MessageConst::PROPERTY_TIMER_DELAY_MS,
+        ] {
+            MessageAccessor::clear_property(&mut msg_inner, name);
+        }
+    }
+    msg_inner.properties_string =
+        message_decoder::message_properties_to_string(msg_inner.get_properties());
+    msg_inner
+}
+
+/// Key cancelling the timer message of `uniq_key` sent to `real_topic`, the value of
+/// `TIMER_DEL_UNIQKEY`.
+pub fn build_delete_key(real_topic: &str, uniq_key: &str) -> CheetahString {
+    CheetahString::from_string(format!("{}+{}", real_topic, uniq_key))
+}
+
+fn real_topic(msg_ext: &MessageExt) -> CheetahString {
+    property(msg_ext, MessageConst::PROPERTY_REAL_TOPIC).unwrap_or_else(|| msg_ext.topic().clone())
+}
+
+fn property(msg_ext: &MessageExt, name: &'static str) -> Option<CheetahString> {
+    msg_ext.get_property(&CheetahString::from_static_str(name))
+}
+
+fn format_time_ms(time_ms: i64, precision_ms: i64) -> i64 {
+    time_ms / precision_ms * precision_ms
+}
+
+fn should_running_dequeue(message_store_config: &MessageStoreConfig) -> bool {
+    message_store_config.broker_role != BrokerRole::Slave
+}
+
+fn need_roll(magic: i32) -> bool {
+    magic & MAGIC_ROLL != 0
+}
+
+fn need_delete(magic: i32) -> bool {
+    magic & MAGIC_DELETE != 0
+}
+
+fn is_magic_ok(magic: i32) -> bool {
+    magic & MAGIC_DEFAULT != 0 && magic & !(MAGIC_DEFAULT | MAGIC_ROLL | MAGIC_DELETE) == 0
+}
+
+/// The `String.hashCode` of Java, kept in the timer log for the metrics per topic.
+fn java_hash_code(value: &str) -> i32 {
+    value
+        .encode_utf16()
+        .fold(0i32, |hash, c| hash.wrapping_mul(31).wrapping_add(c as i32))
+}
+
+/// Events per second since the previous read of the rate.
+struct TpsCounter {
+    count: AtomicI64,
+    last: Mutex<(Instant, i64)>,
+}
+
+impl Default for TpsCounter {
+    fn default() -> Self {
+        Self {
+            count: AtomicI64::new(0),
+            last: Mutex::new((Instant::now(), 0)),
+        }
+    }
+}
+
+impl TpsCounter {
+    fn inc(&self) {
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn tps(&self) -> f32 {
+        let count = self.count.load(Ordering::Relaxed);
+        let now = Instant::now();
+        let mut last = self.last.lock();
+        let elapsed = now.duration_since(last.0).as_secs_f32();
+        let tps = if elapsed > 0.0 {
+            (count - last.1) as f32 / elapsed
+        } else {
+            0.0
+        };
+        *last = (now, count);
+        tps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::timer::slot::Slot;
+
+    fn timer_message(real_topic: &str) -> MessageExt {
+        let mut msg_ext = MessageExt::default();
+        msg_ext.set_topic(CheetahString::from_static_str(TIMER_TOPIC));
+        let mut properties = HashMap::new();
+        properties.insert(
+            CheetahString::from_static_str(MessageConst::PROPERTY_REAL_TOPIC),
+            CheetahString::from_slice(real_topic),
+        );
+        properties.insert(
+            CheetahString::from_static_str(MessageConst::PROPERTY_REAL_QUEUE_ID),
+            CheetahString::from_static_str("3"),
+        );
+        properties.insert(
+            CheetahString::from_static_str(MessageConst::PROPERTY_TIMER_DELAY_SEC),
+            CheetahString::from_static_str("10"),
+        );
+        MessageAccessor::set_properties(&mut msg_ext, properties);
+        msg_ext
+    }
+
+    #[test]
+    fn due_message_goes_back_to_its_real_topic() {
+        let msg_inner = convert_message(&timer_message("topic"), 1000, false);
+        assert_eq!(msg_inner.topic().as_str(), "topic");
+        assert_eq!(msg_inner.message_ext_inner.queue_id, 3);
+        assert_eq!(
+            msg_inner.property(TIMER_ENQUEUE_MS).unwrap().as_str(),
+            "1000"
+        );
+        assert!(msg_inner
+            .property(MessageConst::PROPERTY_TIMER_DELAY_SEC)
+            .is_none());
+        assert!(msg_inner
+            .property(MessageConst::PROPERTY_REAL_TOPIC)
+            .is_none());
+    }
+
+    #[test]
+    fn rolled_message_stays_in_the_timer_topic() {
+        let msg_inner = convert_message(&timer_message("topic"), 1000, true);
+        let msg_inner = convert_message(&msg_inner.message_ext_inner, 2000, true);
+        assert_eq!(msg_inner.topic().as_str(), TIMER_TOPIC);
+        assert_eq!(msg_inner.property(TIMER_ROLL_TIMES).unwrap().as_str(), "2");
+        assert_eq!(
+            msg_inner
+                .property(MessageConst::PROPERTY_REAL_TOPIC)
+                .unwrap()
+                .as_str(),
+            "topic"
+        );
+    }
+
+    #[test]
+    fn recovery_links_units_missing_in_the_wheel() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_string_lossy().into_owned();
+        let mut timer_log = TimerLog::new(get_store_path_timer_log(&root), 1024);
+        let mut timer_wheel = TimerWheel::new(get_timer_wheel_path(&root), 60, 1000).unwrap();
+        let first = TimerLogUnit {
+            prev_pos: -1,
+            magic: MAGIC_DEFAULT,
+            enqueue_time: 10_000,
+            delayed_time: 20_000,
+            offset_py: 0,
+            size_py: 100,
+            topic_hash: java_hash_code("topic"),
+        };
+        let second = TimerLogUnit {
+            prev_pos: 0,
+            magic: MAGIC_DEFAULT | MAGIC_DELETE,
+            offset_py: 100,
+            ..first
+        };
+        timer_log.append(&first.encode());
+        timer_wheel.put_slot(20_000, 0, 0, 1, 0);
+        timer_log.append(&second.encode());
+
+        let (process_offset, last_unit) = recover_and_revise(&timer_log, &mut timer_wheel, 0);
+        assert_eq!(process_offset, 2 * UNIT_SIZE as i64);
+        assert_eq!(last_unit, Some(second));
+        assert_eq!(
+            timer_wheel.get_slot(20_000),
+            Slot::with_num(20_000, 0, UNIT_SIZE as i64, 0, 0)
+        );
+    }
+
+    #[test]
+    fn delete_key_and_magic() {
+        assert_eq!(build_delete_key("topic", "id").as_str(), "topic+id");
+        assert!(is_magic_ok(MAGIC_DEFAULT | MAGIC_ROLL | MAGIC_DELETE));
+        assert!(!is_magic_ok(0));
+        assert!(!is_magic_ok(BLANK_MAGIC_CODE));
+        assert_eq!(java_hash_code("topic"), 110546223);
     }
 }
@@ -0,0 +1,128 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::fs::OpenOptions;
+use std::io;
+use std::path::Path;
+
+use memmap2::MmapMut;
+use rocketmq_common::UtilAll::ensure_dir_ok;
+
+use crate::timer::slot::Slot;
+
+/// The timer wheel: one [`Slot`] per precision unit, for twice `slots_total` units.
+///
+/// The wheel lives in a memory mapped file, so the slots survive a restart; the slot of a time is
+/// only valid while it holds that time, an older round of the wheel reads as an empty slot.
+pub struct TimerWheel {
+    mmap: MmapMut,
+    slots_total: i64,
+    precision_ms: i64,
+}
+
+impl TimerWheel {
+    pub fn new<P: AsRef<Path>>(path: P, slots_total: i32, precision_ms: u64) -> io::Result<Self> {
+        ensure_dir_ok(path.as_ref().parent().unwrap().to_str().unwrap());
+        let wheel_length = slots_total as u64 * 2 * Slot::SIZE as u64;
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path.as_ref())?;
+        let file_length = file.metadata()?.len();
+        if file_length != 0 && file_length != wheel_length {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "timer wheel length {} does not match the expected {}",
+                    file_length, wheel_length
+                ),
+            ));
+        }
+        file.set_len(wheel_length)?;
+        let mmap = unsafe { MmapMut::map_mut(&file)? };
+        Ok(Self {
+            mmap,
+            slots_total: slots_total as i64,
+            precision_ms: precision_ms as i64,
+        })
+    }
+
+    fn slot_index(&self, time_ms: i64) -> usize {
+        ((time_ms / self.precision_ms) % (self.slots_total * 2)) as usize
+    }
+
+    pub fn get_slot(&self, time_ms: i64) -> Slot {
+        let start = self.slot_index(time_ms) * Slot::SIZE;
+        let slot = Slot::decode(&self.mmap[start..start + Slot::SIZE]);
+        if slot.time_ms != time_ms / self.precision_ms * self.precision_ms {
+            return Slot::empty();
+        }
+        slot
+    }
+
+    pub fn put_slot(&mut self, time_ms: i64, first_pos: i64, last_pos: i64, num: i32, magic: i32) {
+        let slot = Slot::with_num(
+            time_ms / self.precision_ms * self.precision_ms,
+            first_pos,
+            last_pos,
+            num,
+            magic,
+        );
+        let start = self.slot_index(time_ms) * Slot::SIZE;
+        self.mmap[start..start + Slot::SIZE].copy_from_slice(&slot.encode());
+    }
+
+    /// Messages due in the slot of `time_ms`.
+    pub fn get_num(&self, time_ms: i64) -> i32 {
+        self.get_slot(time_ms).num
+    }
+
+    /// Messages due in the whole wheel, starting from the slot of `time_start_ms`.
+    pub fn get_all_num(&self, time_start_ms: i64) -> i64 {
+        (0..self.slots_total * 2)
+            .map(|index| self.get_num(time_start_ms + index * self.precision_ms) as i64)
+            .sum()
+    }
+
+    pub fn flush(&self) -> io::Result<()> {
+        self.mmap.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slots_survive_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("timerwheel");
+        let mut wheel = TimerWheel::new(&path, 8, 1000).unwrap();
+        wheel.put_slot(5_500, 0, 52, 2, 0);
+        assert_eq!(wheel.get_slot(5_000), Slot::with_num(5_000, 0, 52, 2, 0));
+        wheel.flush().unwrap();
+        drop(wheel);
+
+        let wheel = TimerWheel::new(&path, 8, 1000).unwrap();
+        assert_eq!(wheel.get_num(5_999), 2);
+        assert_eq!(wheel.get_all_num(0), 2);
+        // the same index one round later belongs to another time
+        assert_eq!(wheel.get_slot(21_000), Slot::empty());
+        assert!(TimerWheel::new(&path, 16, 1000).is_err());
+    }
+}
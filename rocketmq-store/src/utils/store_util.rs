@@ -14,7 +14,10 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::fs;
+
 use once_cell::sync::Lazy;
+use sysinfo::Disks;
 use sysinfo::System;
 
 pub struct StoreUtil;
@@ -26,7 +29,42 @@ impl StoreUtil {
     pub fn get_total_physical_memory_size() -> u64 {
         let mut sys = System::new_all();
         sys.refresh_all();
-        let physical_total = sys.total_memory();
-        physical_total * 1024 // Convert from kilobytes to bytes
+        // sysinfo already reports bytes
+        sys.total_memory()
+    }
+
+    /// The available and the total bytes of the disk `path` is on, `None` when the path does
+    /// not exist.
+    pub fn get_disk_space(path: &str) -> Option<(u64, u64)> {
+        let path = fs::canonicalize(path).ok()?;
+        let disks = Disks::new_with_refreshed_list();
+        disks
+            .iter()
+            .filter(|disk| path.starts_with(disk.mount_point()))
+            .max_by_key(|disk| disk.mount_point().as_os_str().len())
+            .map(|disk| (disk.available_space(), disk.total_space()))
+    }
+
+    /// The used part of the disk `path` is on, `-1` when it can not be measured.
+    pub fn get_disk_used_ratio(path: &str) -> f64 {
+        match Self::get_disk_space(path) {
+            Some((available, total)) if total > 0 => 1.0 - available as f64 / total as f64,
+            _ => -1.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn total_physical_memory_size_is_in_bytes() {
+        let mut sys = System::new_all();
+        sys.refresh_memory();
+        assert_eq!(
+            StoreUtil::get_total_physical_memory_size(),
+            sys.total_memory()
+        );
     }
 }
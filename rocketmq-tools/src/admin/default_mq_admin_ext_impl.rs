@@ -203,7 +203,36 @@ impl MQAdminExt for DefaultMQAdminExtImpl {
         topic: CheetahString,
         broker_addr: Option<CheetahString>,
     ) -> crate::Result<TopicStatsTable> {
-        todo!()
+        let mut mq_client_api = self
+            .client_instance
+            .as_ref()
+            .unwrap()
+            .get_mq_client_api_impl();
+        if let Some(broker_addr) = broker_addr {
+            return mq_client_api
+                .get_topic_stats_info(&broker_addr, &topic, self.timeout_millis)
+                .await
+                .map_err(crate::tools_error::ToolsError::MQClientError);
+        }
+        let topic_route_data = mq_client_api
+            .get_topic_route_info_from_name_server(&topic, self.timeout_millis)
+            .await
+            .map_err(crate::tools_error::ToolsError::MQClientError)?;
+        let mut offset_table = HashMap::new();
+        if let Some(topic_route_data) = topic_route_data {
+            for broker_data in &topic_route_data.broker_datas {
+                if let Some(addr) = broker_data.select_broker_addr() {
+                    let topic_stats_table = mq_client_api
+                        .get_topic_stats_info(&addr, &topic, self.timeout_millis)
+                        .await
+                        .map_err(crate::tools_error::ToolsError::MQClientError)?;
+                    offset_table.extend(topic_stats_table.get_offset_table());
+                }
+            }
+        }
+        let mut topic_stats_table = TopicStatsTable::new();
+        topic_stats_table.set_offset_table(offset_table);
+        Ok(topic_stats_table)
     }
 
     async fn examine_topic_stats_concurrent(
@@ -484,7 +513,33 @@ impl MQAdminExt for DefaultMQAdminExtImpl {
         topic: CheetahString,
         is_offline: bool,
     ) -> crate::Result<()> {
-        todo!()
+        let mut mq_client_api = self
+            .client_instance
+            .as_ref()
+            .unwrap()
+            .get_mq_client_api_impl();
+        let topic_route_data = mq_client_api
+            .get_topic_route_info_from_name_server(&topic, self.timeout_millis)
+            .await
+            .map_err(crate::tools_error::ToolsError::MQClientError)?;
+        if let Some(topic_route_data) = topic_route_data {
+            for broker_data in &topic_route_data.broker_datas {
+                if let Some(addr) = broker_data.select_broker_addr() {
+                    mq_client_api
+                        .clone_group_offset(
+                            &addr,
+                            &src_group,
+                            &dest_group,
+                            &topic,
+                            is_offline,
+                            self.timeout_millis,
+                        )
+                        .await
+                        .map_err(crate::tools_error::ToolsError::MQClientError)?;
+                }
+            }
+        }
+        Ok(())
     }
 
     async fn get_cluster_list(&self, topic: String) -> crate::Result<HashSet<CheetahString>> {